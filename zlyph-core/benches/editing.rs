@@ -0,0 +1,163 @@
+//! Timings of the engine's everyday work on documents of 1 MB and 10 MB,
+//! and 100 MB too with `ZLYPH_BENCH_HUGE=1` set
+//!
+//! `cargo bench -p zlyph-core -- --save-baseline main` records a baseline
+//! and `cargo bench -p zlyph-core -- --baseline main` compares against it,
+//! reporting anything more than 5% slower as a regression. The budgets in
+//! `tests/perf_budget_tests.rs` hold the 1 MB case to fixed limits in
+//! every test run.
+
+use criterion::{criterion_group, criterion_main, BatchSize, BenchmarkId, Criterion, Throughput};
+use std::hint::black_box;
+use std::time::Duration;
+use zlyph_core::config::Config;
+use zlyph_core::{search, EditorAction, EditorEngine};
+
+const MB: usize = 1024 * 1024;
+
+fn sizes() -> Vec<usize> {
+    let mut sizes = vec![MB, 10 * MB];
+    if std::env::var_os("ZLYPH_BENCH_HUGE").is_some() {
+        sizes.push(100 * MB);
+    }
+    sizes
+}
+
+/// Prose-like lines adding up to about `bytes`, with "needle" on every
+/// thousandth line for searches to find
+fn document(bytes: usize) -> String {
+    let mut text = String::with_capacity(bytes + 100);
+    let mut line = 0;
+    while text.len() < bytes {
+        if line > 0 {
+            text.push('\n');
+        }
+        if line % 1000 == 0 {
+            text.push_str("a needle in the haystack, ");
+        }
+        text.push_str(&format!(
+            "line {line}: the quick brown fox jumps over the lazy dog {}",
+            line % 97
+        ));
+        line += 1;
+    }
+    text
+}
+
+/// An engine in ordinary editing mode whatever the size, with the cursor
+/// in the middle of the document
+fn engine(text: &str) -> EditorEngine {
+    let mut config = Config::default();
+    config.large_file.threshold_mb = u64::MAX;
+    let mut engine = EditorEngine::builder().config(config).text(text).build();
+    let row = engine.state().lines.len() / 2;
+    engine.handle_action(EditorAction::SetCursorPosition { row, column: 5 });
+    engine
+}
+
+/// Run `action` on a fresh engine each time, so the undo history doesn't
+/// grow from one iteration to the next
+fn bench_action(c: &mut Criterion, name: &str, action: impl Fn() -> EditorAction) {
+    let mut group = c.benchmark_group(name);
+    group.sample_size(10);
+    for size in sizes() {
+        let text = document(size);
+        group.throughput(Throughput::Bytes(size as u64));
+        group.bench_with_input(
+            BenchmarkId::from_parameter(label(size)),
+            &text,
+            |b, text| {
+                b.iter_batched(
+                    || engine(text),
+                    |mut engine| {
+                        engine.handle_action(action());
+                        engine
+                    },
+                    BatchSize::PerIteration,
+                )
+            },
+        );
+    }
+    group.finish();
+}
+
+fn label(size: usize) -> String {
+    format!("{}MB", size / MB)
+}
+
+fn insert_at_cursor(c: &mut Criterion) {
+    let mut group = c.benchmark_group("insert_at_cursor");
+    for size in sizes() {
+        let mut engine = engine(&document(size));
+        // Typing within one undo step, as a burst of keystrokes is
+        group.bench_function(label(size), |b| {
+            b.iter(|| engine.handle_action(EditorAction::TypeCharacter(black_box('x'))))
+        });
+    }
+    group.finish();
+}
+
+/// Frontends paste by typing the clipboard's text as one string
+fn bulk_paste(c: &mut Criterion) {
+    let pasted = document(64 * 1024);
+    bench_action(c, "bulk_paste", || EditorAction::TypeString(pasted.clone()));
+}
+
+fn line_operations(c: &mut Criterion) {
+    bench_action(c, "newline", || EditorAction::Newline);
+    bench_action(c, "delete_line", || EditorAction::DeleteLine);
+    bench_action(c, "move_line_down", || EditorAction::MoveLineDown);
+}
+
+fn undo(c: &mut Criterion) {
+    let mut group = c.benchmark_group("undo");
+    group.sample_size(10);
+    for size in sizes() {
+        let text = document(size);
+        group.bench_with_input(
+            BenchmarkId::from_parameter(label(size)),
+            &text,
+            |b, text| {
+                b.iter_batched(
+                    || {
+                        let mut engine = engine(text);
+                        engine.handle_action(EditorAction::DeleteLine);
+                        engine
+                    },
+                    |mut engine| {
+                        engine.handle_action(EditorAction::Undo);
+                        engine
+                    },
+                    BatchSize::PerIteration,
+                )
+            },
+        );
+    }
+    group.finish();
+}
+
+fn find_all(c: &mut Criterion) {
+    let mut group = c.benchmark_group("search");
+    group.sample_size(10);
+    for size in sizes() {
+        let lines: Vec<String> = document(size).lines().map(String::from).collect();
+        group.throughput(Throughput::Bytes(size as u64));
+        group.bench_function(label(size), |b| {
+            b.iter(|| search::find_all(&lines, black_box("needle")))
+        });
+    }
+    group.finish();
+}
+
+fn config() -> Criterion {
+    Criterion::default()
+        .noise_threshold(0.05)
+        .warm_up_time(Duration::from_secs(1))
+}
+
+criterion_group! {
+    name = benches;
+    config = config();
+    targets = insert_at_cursor, bulk_paste, line_operations, undo, find_all
+}
+criterion_main!(benches);
@@ -0,0 +1,32 @@
+//! Drive the engine without any UI: build a buffer, edit it with actions
+//! and read back the text, cursor and selection.
+
+use zlyph_core::{BufferPosition, EditorAction, EditorEngine};
+
+fn main() {
+    let mut engine = EditorEngine::builder()
+        .text("# Groceries\n- eggs\n- milk")
+        .build();
+
+    // Add an item at the end; Newline continues the list with "- "
+    engine.handle_action(EditorAction::SelectToBottom);
+    engine.handle_action(EditorAction::MoveRight);
+    engine.handle_action(EditorAction::Newline);
+    engine.handle_action(EditorAction::TypeString("bread".into()));
+
+    // Move "milk" above "eggs"
+    engine.handle_action(EditorAction::SetCursorPosition { row: 2, column: 0 });
+    engine.handle_action(EditorAction::MoveLineUp);
+
+    // Select the heading
+    engine.handle_action(EditorAction::SetCursorPosition { row: 0, column: 2 });
+    engine.handle_action(EditorAction::SelectToLineEnd);
+
+    println!("{}", engine.text());
+    println!("---");
+    let BufferPosition { row, column } = engine.state().cursor;
+    println!("cursor: line {}, column {}", row + 1, column + 1);
+    if let Some(selected) = engine.selected_text() {
+        println!("selected: {selected:?}");
+    }
+}
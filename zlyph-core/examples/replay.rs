@@ -0,0 +1,41 @@
+//! Apply a JSON list of actions, such as `["SelectAll", {"TypeString":
+//! "hi"}]`, to text read from stdin, and print the result.
+//!
+//! ```sh
+//! echo '["MoveToEndOfLine", {"TypeString": "!"}]' > actions.json
+//! echo hello | cargo run -p zlyph-core --example replay -- actions.json
+//! ```
+
+use std::io::{self, Read};
+use std::process::ExitCode;
+use zlyph_core::{EditorAction, EditorEngine};
+
+fn main() -> ExitCode {
+    let Some(path) = std::env::args().nth(1) else {
+        eprintln!("usage: replay ACTIONS.json < input");
+        return ExitCode::FAILURE;
+    };
+    let actions: Vec<EditorAction> = match std::fs::read_to_string(&path)
+        .map_err(|e| e.to_string())
+        .and_then(|json| serde_json::from_str(&json).map_err(|e| e.to_string()))
+    {
+        Ok(actions) => actions,
+        Err(e) => {
+            eprintln!("{path}: {e}");
+            return ExitCode::FAILURE;
+        }
+    };
+
+    let mut input = String::new();
+    if let Err(e) = io::stdin().read_to_string(&mut input) {
+        eprintln!("stdin: {e}");
+        return ExitCode::FAILURE;
+    }
+
+    let mut engine = EditorEngine::builder().text(input).build();
+    for action in actions {
+        engine.handle_action(action);
+    }
+    println!("{}", engine.text());
+    ExitCode::SUCCESS
+}
@@ -0,0 +1,174 @@
+//! Spoken feedback for screen reader users: short announcements of where
+//! the cursor went, what is selected and what the editor is showing
+//!
+//! Neither frontend has an accessibility tree a screen reader can walk, so
+//! announcements are spoken by a command such as `say` on macOS or `spd-say`
+//! (speech-dispatcher, which Orca speaks through) on Linux, and shown in the
+//! status line where a terminal screen reader picks them up. A new
+//! announcement cuts off the one still being spoken, as screen readers do.
+
+use crate::config::AccessibilityConfig;
+use crate::state::{BufferPosition, EditorState};
+use std::process::{Child, Command, Stdio};
+
+/// What was last announced about, to tell what changed
+#[derive(Debug, Clone, PartialEq)]
+struct Snapshot {
+    cursor: BufferPosition,
+    anchor: Option<BufferPosition>,
+    line: String,
+    status: Option<String>,
+}
+
+#[derive(Default)]
+pub struct Announcer {
+    config: AccessibilityConfig,
+    last: Option<Snapshot>,
+    /// The latest announcement, for the status line
+    latest: Option<String>,
+    speech: Option<Child>,
+}
+
+impl Announcer {
+    pub fn new(config: AccessibilityConfig) -> Self {
+        Self {
+            config,
+            ..Default::default()
+        }
+    }
+
+    pub fn latest(&self) -> Option<&str> {
+        self.latest.as_deref().filter(|_| self.config.enabled)
+    }
+
+    /// Announce what changed since the last call, given the buffer and the
+    /// frontend's status, such as the panel that is open. The first call
+    /// only takes note of where things are.
+    pub fn update(&mut self, state: &EditorState, status: Option<&str>) -> Option<String> {
+        if !self.config.enabled {
+            return None;
+        }
+        let cursor = state.cursor;
+        let snapshot = Snapshot {
+            cursor,
+            anchor: state.selection_anchor.filter(|&anchor| anchor != cursor),
+            line: state.lines[cursor.row].clone(),
+            status: status.map(str::to_string),
+        };
+        let last = self.last.replace(snapshot.clone())?;
+        let text = describe_change(&last, &snapshot, state)?;
+        self.speak(&text);
+        self.latest = Some(text.clone());
+        Some(text)
+    }
+
+    fn speak(&mut self, text: &str) {
+        if let Some(mut speech) = self.speech.take() {
+            let _ = speech.kill();
+            let _ = speech.wait();
+        }
+        let program =
+            self.config
+                .speak_command
+                .as_deref()
+                .unwrap_or(if cfg!(target_os = "macos") {
+                    "say"
+                } else {
+                    "spd-say"
+                });
+        self.speech = Command::new(program)
+            .args(&self.config.speak_args)
+            .arg(text)
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn()
+            .ok();
+    }
+}
+
+fn describe_change(last: &Snapshot, now: &Snapshot, state: &EditorState) -> Option<String> {
+    if now.status != last.status {
+        if let Some(status) = &now.status {
+            return Some(status.clone());
+        }
+    }
+    if now.anchor != last.anchor || (now.anchor.is_some() && now.cursor != last.cursor) {
+        return Some(match now.anchor {
+            Some(anchor) => {
+                let count = selected_chars(state, anchor, now.cursor);
+                format!("{} {} selected", count, plural(count, "character"))
+            }
+            None => "Selection cleared".to_string(),
+        });
+    }
+    if now.cursor.row != last.cursor.row {
+        return Some(describe_line(now.cursor.row, &now.line));
+    }
+    if now.cursor.column == last.cursor.column {
+        return None;
+    }
+    if now.line != last.line {
+        // Echo the character just typed
+        let typed = now.cursor.column > last.cursor.column && now.line.len() > last.line.len();
+        return typed
+            .then(|| now.line[..now.cursor.column].chars().next_back())
+            .flatten()
+            .map(describe_char);
+    }
+    Some(
+        now.line[now.cursor.column..]
+            .chars()
+            .next()
+            .map_or_else(|| "End of line".to_string(), describe_char),
+    )
+}
+
+/// "Line 4, " and the line's text, as read out on moving to it
+pub fn describe_line(row: usize, line: &str) -> String {
+    let text = if line.trim().is_empty() {
+        "blank"
+    } else {
+        line
+    };
+    format!("Line {}, {}", row + 1, text)
+}
+
+/// A character as spoken, naming the ones a voice would skip
+pub fn describe_char(c: char) -> String {
+    match c {
+        ' ' => "space".to_string(),
+        '\t' => "tab".to_string(),
+        '\u{00A0}' => "no-break space".to_string(),
+        _ => c.to_string(),
+    }
+}
+
+/// Characters between two positions, counting each line break as one
+fn selected_chars(state: &EditorState, a: BufferPosition, b: BufferPosition) -> usize {
+    let (start, end) = if (a.row, a.column) <= (b.row, b.column) {
+        (a, b)
+    } else {
+        (b, a)
+    };
+    if start.row == end.row {
+        return state.lines[start.row][start.column..end.column]
+            .chars()
+            .count();
+    }
+    let first = state.lines[start.row][start.column..].chars().count();
+    let middle: usize = state.lines[start.row + 1..end.row]
+        .iter()
+        .map(|line| line.chars().count())
+        .sum();
+    let last = state.lines[end.row][..end.column].chars().count();
+    first + middle + last + (end.row - start.row)
+}
+
+fn plural(count: usize, word: &str) -> String {
+    if count == 1 {
+        word.to_string()
+    } else {
+        format!("{}s", word)
+    }
+}
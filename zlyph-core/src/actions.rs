@@ -1,6 +1,12 @@
 //! Platform-agnostic editor actions
 
-#[derive(Debug, Clone, PartialEq)]
+use serde::{Deserialize, Serialize};
+
+/// Everything a frontend can ask the engine to do, passed to
+/// [`EditorEngine::handle_action`](crate::EditorEngine::handle_action).
+/// New actions are added in minor releases, so matches need a `_` arm.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[non_exhaustive]
 pub enum EditorAction {
     // Text manipulation
     TypeCharacter(char),
@@ -8,7 +14,12 @@ pub enum EditorAction {
     Backspace,
     Delete,
     Newline,
+    /// Insert text from the clipboard; several lines are re-indented to
+    /// the line they go into
     Paste(String),
+    /// Insert text from the clipboard without its indent or invisible
+    /// characters
+    PastePlain(String),
 
     // Cursor movement
     MoveLeft,
@@ -19,6 +30,17 @@ pub enum EditorAction {
     MoveToEndOfLine,
     MoveWordLeft,
     MoveWordRight,
+    /// Move to the start of the next sentence, or of this one and then the
+    /// one before; see [`crate::sentences`]
+    MoveToNextSentence,
+    MoveToPrevSentence,
+    /// Move to the next occurrence on the line of the character typed
+    /// next, or just before it; see [`crate::jump`]
+    JumpToChar,
+    JumpBeforeChar,
+    /// Label each place in view of the two characters typed next, and
+    /// move to the one whose label is typed after them
+    Leap,
 
     // Selection
     SelectLeft,
@@ -28,6 +50,22 @@ pub enum EditorAction {
     SelectWordLeft,
     SelectWordRight,
     SelectAll,
+    SelectLine,
+    SelectSentence,
+    SelectParagraph,
+    SelectToTop,
+    SelectToBottom,
+    SelectToLineStart,
+    SelectToLineEnd,
+    ExpandSelection,
+    ShrinkSelection,
+    /// Select the syntax node around the selection
+    SelectParent,
+    /// Select the next item at the selection's level, such as the next
+    /// argument or statement
+    SelectNextSibling,
+    /// Select the Markdown section at the cursor, subsections included
+    SelectSection,
 
     // Editing operations
     Undo,
@@ -41,8 +79,136 @@ pub enum EditorAction {
     DeleteWordRight,
     MoveLineUp,
     MoveLineDown,
+    /// Swap the Markdown section at the cursor, subsections and all, with
+    /// the one above or below it at the same level
+    MoveSectionUp,
+    MoveSectionDown,
+    /// Raise or lower the heading of the section at the cursor a level,
+    /// with the headings of its subsections
+    PromoteSection,
+    DemoteSection,
     Tab,
     Outdent,
+    /// Insert the current date, time, or both, in the configured formats
+    InsertDate,
+    InsertTime,
+    InsertTimestamp,
+    /// Insert the character with a code point given in hex, as in `1F680`
+    /// or `U+1F680`
+    InsertUnicodeCodepoint(String),
+    /// Insert text an input method has finished composing, as one undo step
+    InsertComposed(String),
+
+    // Search; see [`crate::search`]
+    /// Select the next match of the search, or of the selected text while
+    /// no search is active, wrapping around the buffer
+    NextMatch,
+    PrevMatch,
+    /// Put a cursor at every match, each selecting it; see
+    /// [`crate::multi_cursor`]
+    SelectAllMatches,
+
+    // Completion
+    TriggerCompletion,
+    /// Dismiss transient UI such as the completion popup
+    Cancel,
+
+    // Language intelligence (answered by an external provider such as a language server)
+    Hover,
+    GoToDefinition,
+
+    // Links
+    /// Open the link, URL or file path at the cursor; answered by the
+    /// frontend, which can open browsers and files
+    OpenLinkUnderCursor,
+
+    // Footnotes
+    /// Refer to a new numbered footnote at the cursor and start its
+    /// definition at the end of the document
+    InsertFootnote,
+    /// Number the footnotes in the order they're referred to
+    RenumberFootnotes,
+    /// Go from a footnote's reference to its definition, or back
+    GoToFootnote,
+
+    // Citations
+    /// List the references cited with `@key` at the cursor, from the
+    /// configured bibliography
+    InsertBibliography,
+
+    // Diagnostics
+    NextDiagnostic,
+    PrevDiagnostic,
+    ToggleDiagnosticsPanel,
+    /// Offer the replacements suggested by the diagnostic under the cursor
+    QuickFix,
+    /// Replace the text of the diagnostic under the cursor with one of its
+    /// suggestions, as picked from the context menu
+    ApplySuggestion(String),
+    /// Remove invisible characters and make unusual spaces ordinary ones
+    StripInvisibles,
+    /// Replace the selected arithmetic with its result, or write the result
+    /// after it; see [`crate::calc`]
+    EvaluateSelection,
+    /// Pad the selected lines, or the paragraph at the cursor, so that the
+    /// delimiter lines up in each; see [`crate::align`]
+    AlignOn(String),
+    // Structured text in the selection, or the whole buffer; see [`crate::pretty`]
+    /// Lay out JSON one value to a line
+    FormatJson,
+    FormatToml,
+    FormatYaml,
+    /// Take out the whitespace between JSON tokens
+    MinifyJson,
+    /// Put the keys of each object, table or mapping in order, in whichever
+    /// of JSON, TOML and YAML the text is written
+    SortKeys,
+    /// Encode, decode, dump or hash the selection; see [`crate::transform`]
+    TransformSelection(crate::transform::Transform),
+    // Numbers; see [`crate::numbers`]
+    /// Add to the number at or after the cursor, or the first number on
+    /// each selected line
+    IncrementNumber(i64),
+    DecrementNumber(i64),
+    /// Add the amount to the first number, twice it to the second and so
+    /// on, down the selected lines or the cursors, so a column of zeros
+    /// becomes 1, 2, 3
+    NumberSequence(i64),
+
+    // Markdown tables; see [`crate::tables`]
+    /// Line up the columns of the table at the cursor
+    FormatTable,
+    /// Add an empty row below the cursor's, or at the top of the body
+    /// from the header
+    InsertTableRow,
+    DeleteTableRow,
+    /// Add an empty column after the cursor's
+    InsertTableColumn,
+    DeleteTableColumn,
+
+    // Bookmarks
+    /// Bookmark the cursor's line, or remove its bookmark
+    ToggleBookmark,
+    NextBookmark,
+    PrevBookmark,
+
+    // Frontmatter
+    /// Set a field of the note's frontmatter, starting the block if there
+    /// is none; see [`crate::frontmatter`]
+    SetFrontmatterField {
+        key: String,
+        value: crate::frontmatter::Value,
+    },
+    RemoveFrontmatterField(String),
+
+    // Writing goals
+    /// Start a writing session from the configured goal, or end the current one
+    ToggleWritingSession,
+
+    // Dictation
+    /// Start typing what the configured dictation command hears, or stop;
+    /// see [`crate::dictation`]
+    ToggleDictation,
 
     // View operations
     IncreaseFontSize,
@@ -53,7 +219,91 @@ pub enum EditorAction {
     Quit,
 
     // Mouse-driven cursor positioning
-    SetCursorPosition { row: usize, column: usize },
-    StartSelection { row: usize, column: usize },
-    ExtendSelection { row: usize, column: usize },
+    SetCursorPosition {
+        row: usize,
+        column: usize,
+    },
+    StartSelection {
+        row: usize,
+        column: usize,
+    },
+    ExtendSelection {
+        row: usize,
+        column: usize,
+    },
+
+    // Drag and drop of the selection
+    /// Move the selected text to a position, as one undo step
+    MoveSelectionTo {
+        row: usize,
+        column: usize,
+    },
+    /// Copy the selected text to a position, as one undo step
+    CopySelectionTo {
+        row: usize,
+        column: usize,
+    },
+}
+
+impl EditorAction {
+    /// Whether the action can change the text, rather than only moving the
+    /// cursor, selecting or showing something
+    pub fn edits_text(&self) -> bool {
+        matches!(
+            self,
+            Self::TypeCharacter(_)
+                | Self::TypeString(_)
+                | Self::Backspace
+                | Self::Delete
+                | Self::Newline
+                | Self::Paste(_)
+                | Self::PastePlain(_)
+                | Self::Undo
+                | Self::Redo
+                | Self::Cut
+                | Self::DeleteLine
+                | Self::DeleteToBeginningOfLine
+                | Self::DeleteToEndOfLine
+                | Self::DeleteWordLeft
+                | Self::DeleteWordRight
+                | Self::MoveLineUp
+                | Self::MoveLineDown
+                | Self::MoveSectionUp
+                | Self::MoveSectionDown
+                | Self::PromoteSection
+                | Self::DemoteSection
+                | Self::InsertFootnote
+                | Self::RenumberFootnotes
+                | Self::InsertBibliography
+                | Self::Tab
+                | Self::Outdent
+                | Self::InsertDate
+                | Self::InsertTime
+                | Self::InsertTimestamp
+                | Self::InsertUnicodeCodepoint(_)
+                | Self::InsertComposed(_)
+                | Self::ApplySuggestion(_)
+                | Self::StripInvisibles
+                | Self::EvaluateSelection
+                | Self::AlignOn(_)
+                | Self::FormatJson
+                | Self::FormatToml
+                | Self::FormatYaml
+                | Self::MinifyJson
+                | Self::SortKeys
+                | Self::TransformSelection(_)
+                | Self::IncrementNumber(_)
+                | Self::DecrementNumber(_)
+                | Self::NumberSequence(_)
+                | Self::FormatTable
+                | Self::InsertTableRow
+                | Self::DeleteTableRow
+                | Self::InsertTableColumn
+                | Self::DeleteTableColumn
+                | Self::SetFrontmatterField { .. }
+                | Self::RemoveFrontmatterField(_)
+                | Self::MoveSelectionTo { .. }
+                | Self::CopySelectionTo { .. }
+        )
+    }
 }
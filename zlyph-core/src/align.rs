@@ -0,0 +1,109 @@
+//! Lining lines up on a delimiter, as in a block of assignments or a
+//! comma-separated list
+//!
+//! `=` and `:` separate a key from its value, so only their first
+//! occurrence in a line is aligned, not counting an `=` that's part of an
+//! operator such as `+=`, `==` or `=>`, or a `:` in `::`; any other
+//! delimiter, such as `,` or `|`, separates columns and every occurrence
+//! is. `,` and `:` stay against
+//! the text before them and the padding goes after, as they are written in
+//! prose and YAML; other delimiters get a space on either side.
+
+use unicode_width::UnicodeWidthStr;
+
+/// `lines` with padding added so that the occurrences of `delimiter` line
+/// up; lines without it are left alone
+pub fn align(lines: &[String], delimiter: &str) -> Vec<String> {
+    if delimiter.is_empty() {
+        return lines.to_vec();
+    }
+    let first_only = matches!(delimiter, "=" | ":");
+    let fields: Vec<Option<Vec<&str>>> = lines
+        .iter()
+        .map(|line| {
+            if first_only {
+                let at = key_separator(line, delimiter)?;
+                Some(vec![&line[..at], &line[at + delimiter.len()..]])
+            } else {
+                line.contains(delimiter)
+                    .then(|| line.split(delimiter).collect())
+            }
+        })
+        .collect();
+
+    // Each column's width, not counting the last, which isn't padded
+    let mut widths: Vec<usize> = Vec::new();
+    for row in fields.iter().flatten() {
+        for (column, field) in row[..row.len() - 1].iter().enumerate() {
+            let width = tidy(field, column).width();
+            match widths.get_mut(column) {
+                Some(max) => *max = (*max).max(width),
+                None => widths.push(width),
+            }
+        }
+    }
+
+    let hugs = matches!(delimiter, "," | ":");
+    lines
+        .iter()
+        .zip(&fields)
+        .map(|(line, row)| {
+            let Some(row) = row else {
+                return line.clone();
+            };
+            let last = row.len() - 1;
+            let mut aligned = String::new();
+            for (column, field) in row.iter().enumerate() {
+                let field = tidy(field, column);
+                if column == last {
+                    aligned.push_str(field);
+                    break;
+                }
+                let padding = " ".repeat(widths[column] - field.width());
+                if hugs {
+                    aligned.push_str(field);
+                    aligned.push_str(delimiter);
+                    aligned.push_str(&padding);
+                } else {
+                    aligned.push_str(field);
+                    aligned.push_str(&padding);
+                    // Nothing before a leading `|` to keep apart from it
+                    if widths[column] > 0 {
+                        aligned.push(' ');
+                    }
+                    aligned.push_str(delimiter);
+                }
+                if !row[column + 1].trim().is_empty() {
+                    aligned.push(' ');
+                }
+            }
+            aligned.trim_end().to_string()
+        })
+        .collect()
+}
+
+/// Where `delimiter` first separates a key from its value in `line`
+fn key_separator(line: &str, delimiter: &str) -> Option<usize> {
+    line.match_indices(delimiter).map(|(at, _)| at).find(|&at| {
+        let before = line[..at].chars().next_back();
+        let after = line[at + delimiter.len()..].chars().next();
+        match delimiter {
+            "=" => {
+                !before.is_some_and(|c| "+-*/%&|^<>!=:".contains(c))
+                    && !matches!(after, Some('=' | '>'))
+            }
+            ":" => before != Some(':') && after != Some(':'),
+            _ => true,
+        }
+    })
+}
+
+/// A field without the spaces around it, keeping the indentation of the
+/// first
+fn tidy(field: &str, column: usize) -> &str {
+    if column == 0 {
+        field.trim_end()
+    } else {
+        field.trim()
+    }
+}
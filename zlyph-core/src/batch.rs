@@ -0,0 +1,60 @@
+//! Non-interactive editing: replaying a script of steps against a buffer
+//!
+//! A script is a JSON array. Each step is either an [`EditorAction`] as the
+//! JSON-RPC server takes it, e.g. `"MoveDown"` or `{"TypeString": "hi"}`, or
+//! an ex command such as `{"ex": "%s/foo/bar/g"}`.
+
+use crate::ex::Command;
+use crate::{EditorAction, EditorEngine};
+use serde::Deserialize;
+use std::io;
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(untagged)]
+pub enum Step {
+    Ex { ex: String },
+    Action(EditorAction),
+}
+
+pub fn parse_script(script: &str) -> io::Result<Vec<Step>> {
+    serde_json::from_str(script).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+}
+
+/// Apply the steps in order, stopping at the first that fails; the error
+/// names the failing step
+pub fn run(engine: &mut EditorEngine, steps: &[Step]) -> io::Result<()> {
+    for (i, step) in steps.iter().enumerate() {
+        match step {
+            Step::Action(action) => engine.handle_action(action.clone()),
+            // Shell commands are waited for, so the next step sees their output
+            Step::Ex { ex } => Command::parse(ex)
+                .and_then(|command| command.execute(engine))
+                .and_then(|()| engine.wait_for_shell_command())
+                .map_err(|e| io::Error::new(e.kind(), format!("step {} ({}): {}", i + 1, ex, e)))?,
+        }
+    }
+    Ok(())
+}
+
+/// Run the steps over `content`, keeping its line endings and final newline
+pub fn apply_to_text(content: &str, steps: &[Step]) -> io::Result<String> {
+    let mut engine = EditorEngine::new();
+    engine.load_text(content);
+    run(&mut engine, steps)?;
+    Ok(join_like(&engine.state().lines, content))
+}
+
+/// Join `lines` with the line endings of `original`, ending in a newline if
+/// it did
+pub fn join_like(lines: &[String], original: &str) -> String {
+    let line_ending = if original.contains("\r\n") {
+        "\r\n"
+    } else {
+        "\n"
+    };
+    let mut text = lines.join(line_ending);
+    if original.ends_with('\n') {
+        text.push_str(line_ending);
+    }
+    text
+}
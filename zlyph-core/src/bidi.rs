@@ -0,0 +1,180 @@
+//! Bidirectional text: the order right-to-left scripts such as Arabic and
+//! Hebrew are shown in, and where columns of such lines are drawn
+//!
+//! Buffers keep text in logical order, the order it is typed in. A line
+//! with right-to-left text is shown in visual order, with each run of
+//! right-to-left characters reversed and its brackets mirrored, following
+//! the Unicode Bidirectional Algorithm with the direction of the line taken
+//! from its first strong character. Lines with no right-to-left text, the
+//! usual case, skip all of this.
+
+use std::ops::Range;
+use unicode_bidi::{bidi_class, BidiClass, ParagraphBidiInfo};
+
+/// Whether `line` has any right-to-left text to reorder
+pub fn has_rtl(line: &str) -> bool {
+    !line.is_ascii()
+        && line.chars().any(|c| {
+            matches!(
+                bidi_class(c),
+                BidiClass::R
+                    | BidiClass::AL
+                    | BidiClass::AN
+                    | BidiClass::RLE
+                    | BidiClass::RLO
+                    | BidiClass::RLI
+            )
+        })
+}
+
+/// The character a bracket becomes when shown right to left
+fn mirrored(c: char) -> char {
+    match c {
+        '(' => ')',
+        ')' => '(',
+        '[' => ']',
+        ']' => '[',
+        '{' => '}',
+        '}' => '{',
+        '<' => '>',
+        '>' => '<',
+        '«' => '»',
+        '»' => '«',
+        '‹' => '›',
+        '›' => '‹',
+        _ => c,
+    }
+}
+
+/// One character of a line, where it is in the line and where it is shown
+#[derive(Debug, Clone, Copy)]
+struct Glyph {
+    logical: usize,
+    len: usize,
+    display: usize,
+    display_len: usize,
+    rtl: bool,
+}
+
+/// A line laid out in visual order. Columns are byte offsets into the line
+/// and display indices byte offsets into [`Layout::text`], both at
+/// character boundaries.
+#[derive(Debug, Clone)]
+pub struct Layout {
+    /// The line as shown, left to right
+    pub text: String,
+    /// In display order
+    glyphs: Vec<Glyph>,
+    line_len: usize,
+}
+
+impl Layout {
+    pub fn new(line: &str) -> Self {
+        let mut text = String::with_capacity(line.len());
+        let mut glyphs = Vec::new();
+        if !line.is_empty() {
+            let info = ParagraphBidiInfo::new(line, None);
+            let (levels, runs) = info.visual_runs(0..line.len());
+            for run in runs {
+                let rtl = levels[run.start].is_rtl();
+                let chars: Vec<(usize, char)> = line[run.clone()].char_indices().collect();
+                let ordered: Box<dyn Iterator<Item = &(usize, char)>> = if rtl {
+                    Box::new(chars.iter().rev())
+                } else {
+                    Box::new(chars.iter())
+                };
+                for &(offset, c) in ordered {
+                    let shown = if rtl { mirrored(c) } else { c };
+                    glyphs.push(Glyph {
+                        logical: run.start + offset,
+                        len: c.len_utf8(),
+                        display: text.len(),
+                        display_len: shown.len_utf8(),
+                        rtl,
+                    });
+                    text.push(shown);
+                }
+            }
+        }
+        Self {
+            text,
+            glyphs,
+            line_len: line.len(),
+        }
+    }
+
+    /// Where the caret before the character at `column` is drawn: the left
+    /// edge of a left-to-right character and the right edge of a
+    /// right-to-left one. At the end of the line it follows the last
+    /// character typed.
+    pub fn display_index(&self, column: usize) -> usize {
+        let glyph = match self.glyphs.iter().find(|glyph| glyph.logical == column) {
+            Some(glyph) => glyph,
+            None => match self.glyphs.iter().max_by_key(|glyph| glyph.logical) {
+                Some(last) if column >= self.line_len => {
+                    return if last.rtl {
+                        last.display
+                    } else {
+                        last.display + last.display_len
+                    };
+                }
+                _ => return self.text.len().min(column),
+            },
+        };
+        if glyph.rtl {
+            glyph.display + glyph.display_len
+        } else {
+            glyph.display
+        }
+    }
+
+    /// The column a caret drawn at `display` stands for, e.g. after a click
+    pub fn column(&self, display: usize) -> usize {
+        match self.glyphs.iter().find(|glyph| glyph.display >= display) {
+            Some(glyph) if glyph.rtl => glyph.logical + glyph.len,
+            Some(glyph) => glyph.logical,
+            None => match self.glyphs.last() {
+                Some(last) if last.rtl => last.logical,
+                Some(last) => last.logical + last.len,
+                None => 0,
+            },
+        }
+    }
+
+    /// Where the text of the columns in `range` is shown, left to right;
+    /// more than one range when it crosses a change of direction
+    pub fn display_ranges(&self, range: Range<usize>) -> Vec<Range<usize>> {
+        let mut ranges: Vec<Range<usize>> = Vec::new();
+        for glyph in &self.glyphs {
+            if glyph.logical < range.start || glyph.logical >= range.end {
+                continue;
+            }
+            let shown = glyph.display..glyph.display + glyph.display_len;
+            match ranges.last_mut() {
+                Some(last) if last.end == shown.start => last.end = shown.end,
+                _ => ranges.push(shown),
+            }
+        }
+        ranges
+    }
+
+    /// The column one character to the left or right of `column` on screen,
+    /// or None at that end of the line
+    pub fn move_visually(&self, column: usize, left: bool) -> Option<usize> {
+        let mut display = self.display_index(column);
+        loop {
+            display = if left {
+                let previous = self.text[..display].chars().next_back()?;
+                display - previous.len_utf8()
+            } else {
+                let next = self.text[display..].chars().next()?;
+                display + next.len_utf8()
+            };
+            // Where runs meet, two carets on screen stand for one column
+            let moved = self.column(display);
+            if moved != column {
+                return Some(moved);
+            }
+        }
+    }
+}
@@ -0,0 +1,89 @@
+//! Bookmarked lines to jump between
+//!
+//! A bookmark is a gutter decoration at the start of its line, published
+//! under [`SOURCE`], so it moves with the edits around it and goes when
+//! its line is deleted. Frontends keep each file's bookmarked rows in the
+//! workspace state and hand them back with
+//! [`EditorEngine::set_bookmarks`] when the file is opened again.
+//!
+//! [`EditorEngine::set_bookmarks`]: crate::EditorEngine::set_bookmarks
+
+use crate::decorations::{Decoration, Tone};
+
+/// Decoration source bookmarks are published under
+pub const SOURCE: &str = "bookmarks";
+
+/// Gutter symbol beside a bookmarked line
+pub const SYMBOL: &str = "◆";
+
+/// The gutter marker for a bookmark at byte `offset`
+pub(crate) fn marker(offset: usize) -> Decoration {
+    Decoration::gutter(offset, SYMBOL).with_tone(Tone::Accent)
+}
+
+/// The bookmarked row after `row` in sorted `rows`, or before it when not
+/// `forward`, wrapping around at the ends
+pub fn next(rows: &[usize], row: usize, forward: bool) -> Option<usize> {
+    if forward {
+        rows.iter()
+            .copied()
+            .find(|&r| r > row)
+            .or(rows.first().copied())
+    } else {
+        rows.iter()
+            .rev()
+            .copied()
+            .find(|&r| r < row)
+            .or(rows.last().copied())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Bookmark {
+    pub row: usize,
+    /// The line's text, trimmed
+    pub text: String,
+}
+
+/// The list of a buffer's bookmarks, for frontends to show and jump from
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BookmarksPanel {
+    /// Index into [`BookmarksPanel::entries`]
+    pub selected: usize,
+    entries: Vec<Bookmark>,
+}
+
+impl BookmarksPanel {
+    /// List the bookmarked `rows` of `lines`, selecting the first at or
+    /// after `cursor_row`
+    pub fn new(lines: &[String], rows: &[usize], cursor_row: usize) -> Self {
+        let entries: Vec<Bookmark> = rows
+            .iter()
+            .filter_map(|&row| {
+                let text = lines.get(row)?.trim().to_string();
+                Some(Bookmark { row, text })
+            })
+            .collect();
+        let selected = entries
+            .iter()
+            .position(|entry| entry.row >= cursor_row)
+            .unwrap_or(entries.len().saturating_sub(1));
+        Self { selected, entries }
+    }
+
+    pub fn entries(&self) -> &[Bookmark] {
+        &self.entries
+    }
+
+    pub fn select_previous(&mut self) {
+        self.selected = self.selected.saturating_sub(1);
+    }
+
+    pub fn select_next(&mut self) {
+        self.selected = (self.selected + 1).min(self.entries.len().saturating_sub(1));
+    }
+
+    pub fn selected_entry(&self) -> Option<&Bookmark> {
+        self.entries.get(self.selected)
+    }
+}
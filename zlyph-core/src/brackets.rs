@@ -0,0 +1,174 @@
+//! Rainbow brackets: each matching pair of `()`, `[]` and `{}` colored by
+//! how deeply it is nested, and the text between a pair given a background
+//! that deepens with each level
+//!
+//! Pairs are matched across lines. A bracket inside a double-quoted string
+//! or a backtick code span closed on its line, or after a backslash, isn't
+//! counted, and neither is one without a partner, so a stray bracket
+//! doesn't shift the colors of the rest of the file.
+
+use crate::highlight::{LineHighlights, Style};
+use crate::BufferPosition;
+use std::ops::Range;
+
+/// Each line's bracket and scope runs, in order and not overlapping; only
+/// [`Style::bracket`] and [`Style::scope`] are set
+pub fn highlight(lines: &[String]) -> Vec<Vec<(Range<usize>, Style)>> {
+    let mut brackets: Vec<(BufferPosition, bool)> = pairs(lines)
+        .into_iter()
+        .flat_map(|(open, close)| [(open, true), (close, false)])
+        .collect();
+    brackets.sort_unstable();
+
+    let mut result = vec![Vec::new(); lines.len()];
+    let mut brackets = brackets.into_iter().peekable();
+    let mut depth: u8 = 0;
+    for (row, line) in lines.iter().enumerate() {
+        let spans = &mut result[row];
+        let mut at = 0;
+        while let Some((position, opens)) = brackets.next_if(|(at, _)| at.row == row) {
+            let column = position.column;
+            if column > at && depth > 0 {
+                spans.push((at..column, scope(depth)));
+            }
+            let bracket = if opens {
+                depth = depth.saturating_add(1);
+                depth - 1
+            } else {
+                depth = depth.saturating_sub(1);
+                depth
+            };
+            let style = Style {
+                bracket: Some(bracket),
+                ..scope(depth.min(bracket))
+            };
+            spans.push((column..column + 1, style));
+            at = column + 1;
+        }
+        if line.len() > at && depth > 0 {
+            spans.push((at..line.len(), scope(depth)));
+        }
+    }
+    result
+}
+
+/// Where each matched pair opens and closes, in the order they open
+pub fn pairs(lines: &[String]) -> Vec<(BufferPosition, BufferPosition)> {
+    let mut pairs = Vec::new();
+    let mut open: Vec<(BufferPosition, u8)> = Vec::new();
+    for (row, line) in lines.iter().enumerate() {
+        for (column, c) in outside_strings(line).filter(|(_, c)| b"()[]{}".contains(c)) {
+            let at = BufferPosition::new(row, column);
+            if closing(c).is_some() {
+                open.push((at, c));
+            } else if let Some(&(start, opener)) = open.last() {
+                // A closer that doesn't match the last opener is a stray
+                if closing(opener) == Some(c) {
+                    open.pop();
+                    pairs.push((start, at));
+                }
+            }
+        }
+    }
+    pairs.sort_unstable();
+    pairs
+}
+
+/// `lines`' bracket pairs and scopes laid over the highlights already
+/// found for them, keeping each run's own style
+pub fn apply(lines: &[String], highlights: &mut Vec<LineHighlights>) {
+    highlights.resize_with(lines.len(), LineHighlights::default);
+    for (line, layer) in highlights.iter_mut().zip(highlight(lines)) {
+        if !layer.is_empty() {
+            line.spans = overlay(&line.spans, &layer);
+        }
+    }
+}
+
+fn scope(depth: u8) -> Style {
+    Style {
+        scope: depth,
+        ..Style::default()
+    }
+}
+
+/// The bracket that closes `c`, if it opens a pair
+fn closing(c: u8) -> Option<u8> {
+    match c {
+        b'(' => Some(b')'),
+        b'[' => Some(b']'),
+        b'{' => Some(b'}'),
+        _ => None,
+    }
+}
+
+/// The byte column of each ASCII character in `line` outside strings and
+/// code spans and not escaped, with the character
+pub(crate) fn outside_strings(line: &str) -> impl Iterator<Item = (usize, u8)> + '_ {
+    let bytes = line.as_bytes();
+    let mut quote = None;
+    let mut at = 0;
+    std::iter::from_fn(move || {
+        while at < bytes.len() {
+            let column = at;
+            let byte = bytes[at];
+            at += 1;
+            match byte {
+                b'\\' => at += 1,
+                b'"' | b'`' if quote == Some(byte) => quote = None,
+                b'"' | b'`' if quote.is_none() && bytes[at..].contains(&byte) => quote = Some(byte),
+                b'"' | b'`' => {}
+                _ if quote.is_none() && byte.is_ascii() => return Some((column, byte)),
+                _ => {}
+            }
+        }
+        None
+    })
+}
+
+/// `spans` split where `layer`'s runs start and end, each piece taking the
+/// bracket and scope of the layer run it's in
+fn overlay(
+    spans: &[(Range<usize>, Style)],
+    layer: &[(Range<usize>, Style)],
+) -> Vec<(Range<usize>, Style)> {
+    let mut edges: Vec<usize> = spans
+        .iter()
+        .chain(layer)
+        .flat_map(|(range, _)| [range.start, range.end])
+        .collect();
+    edges.sort_unstable();
+    edges.dedup();
+
+    // Both are in order, so each edge's run is looked for from the last one
+    let (mut next_span, mut next_layer) = (0, 0);
+    let mut result: Vec<(Range<usize>, Style)> = Vec::new();
+    for edge in edges.windows(2) {
+        let (start, end) = (edge[0], edge[1]);
+        let base = covering(spans, &mut next_span, start);
+        let over = covering(layer, &mut next_layer, start);
+        if base.is_none() && over.is_none() {
+            continue;
+        }
+        let mut style = base.unwrap_or_default();
+        if let Some(over) = over {
+            style.bracket = over.bracket;
+            style.scope = over.scope;
+        }
+        match result.last_mut() {
+            Some((range, last)) if range.end == start && *last == style => range.end = end,
+            _ => result.push((start..end, style)),
+        }
+    }
+    result
+}
+
+/// The style of the run in `runs` covering `at`, looking from `next` on
+fn covering(runs: &[(Range<usize>, Style)], next: &mut usize, at: usize) -> Option<Style> {
+    while runs.get(*next).is_some_and(|(range, _)| range.end <= at) {
+        *next += 1;
+    }
+    runs.get(*next)
+        .filter(|(range, _)| range.start <= at)
+        .map(|(_, style)| *style)
+}
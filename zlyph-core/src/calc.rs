@@ -0,0 +1,496 @@
+//! Arithmetic for the scratchpad: `+ - * / ^`, parentheses and percentages,
+//! quantities in units of length, mass, time and data, and dates
+//!
+//! `3 ft + 2 in`, `90 min in h` and `1.5 GiB to MB` convert between units,
+//! `2026-03-01 + 2 weeks` moves a date, and `2026-12-25 - today` counts the
+//! days between two. Results keep the unit of the left-hand side.
+
+use chrono::{Duration, NaiveDate};
+use std::fmt;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Dimension {
+    Length,
+    Mass,
+    Time,
+    Data,
+}
+
+/// A unit of measure, such as `km` or `h`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Unit {
+    pub name: &'static str,
+    dimension: Dimension,
+    /// Size in metres, grams, seconds or bytes
+    factor: f64,
+}
+
+const fn unit(name: &'static str, dimension: Dimension, factor: f64) -> Unit {
+    Unit {
+        name,
+        dimension,
+        factor,
+    }
+}
+
+const DAY: Unit = unit("day", Dimension::Time, 86_400.0);
+
+/// Every unit with the names it is written as, case aside
+const UNITS: &[(&[&str], Unit)] = &[
+    (
+        &[
+            "mm",
+            "millimeter",
+            "millimeters",
+            "millimetre",
+            "millimetres",
+        ],
+        unit("mm", Dimension::Length, 0.001),
+    ),
+    (
+        &[
+            "cm",
+            "centimeter",
+            "centimeters",
+            "centimetre",
+            "centimetres",
+        ],
+        unit("cm", Dimension::Length, 0.01),
+    ),
+    (
+        &["m", "meter", "meters", "metre", "metres"],
+        unit("m", Dimension::Length, 1.0),
+    ),
+    (
+        &["km", "kilometer", "kilometers", "kilometre", "kilometres"],
+        unit("km", Dimension::Length, 1000.0),
+    ),
+    (
+        &["in", "inch", "inches"],
+        unit("in", Dimension::Length, 0.0254),
+    ),
+    (
+        &["ft", "foot", "feet"],
+        unit("ft", Dimension::Length, 0.3048),
+    ),
+    (
+        &["yd", "yard", "yards"],
+        unit("yd", Dimension::Length, 0.9144),
+    ),
+    (
+        &["mi", "mile", "miles"],
+        unit("mi", Dimension::Length, 1609.344),
+    ),
+    (
+        &["mg", "milligram", "milligrams"],
+        unit("mg", Dimension::Mass, 0.001),
+    ),
+    (&["g", "gram", "grams"], unit("g", Dimension::Mass, 1.0)),
+    (
+        &["kg", "kilogram", "kilograms"],
+        unit("kg", Dimension::Mass, 1000.0),
+    ),
+    (
+        &["lb", "lbs", "pound", "pounds"],
+        unit("lb", Dimension::Mass, 453.592_37),
+    ),
+    (
+        &["oz", "ounce", "ounces"],
+        unit("oz", Dimension::Mass, 28.349_523_125),
+    ),
+    (
+        &["ms", "millisecond", "milliseconds"],
+        unit("ms", Dimension::Time, 0.001),
+    ),
+    (
+        &["s", "sec", "secs", "second", "seconds"],
+        unit("s", Dimension::Time, 1.0),
+    ),
+    (
+        &["min", "mins", "minute", "minutes"],
+        unit("min", Dimension::Time, 60.0),
+    ),
+    (
+        &["h", "hr", "hrs", "hour", "hours"],
+        unit("h", Dimension::Time, 3600.0),
+    ),
+    (&["d", "day", "days"], DAY),
+    (
+        &["w", "wk", "week", "weeks"],
+        unit("week", Dimension::Time, 604_800.0),
+    ),
+    (&["b", "byte", "bytes"], unit("B", Dimension::Data, 1.0)),
+    (&["kb"], unit("KB", Dimension::Data, 1e3)),
+    (&["mb"], unit("MB", Dimension::Data, 1e6)),
+    (&["gb"], unit("GB", Dimension::Data, 1e9)),
+    (&["tb"], unit("TB", Dimension::Data, 1e12)),
+    (&["kib"], unit("KiB", Dimension::Data, 1024.0)),
+    (&["mib"], unit("MiB", Dimension::Data, 1_048_576.0)),
+    (&["gib"], unit("GiB", Dimension::Data, 1_073_741_824.0)),
+];
+
+fn find_unit(word: &str) -> Option<Unit> {
+    let word = word.to_ascii_lowercase();
+    UNITS
+        .iter()
+        .find(|(names, _)| names.contains(&word.as_str()))
+        .map(|&(_, unit)| unit)
+}
+
+/// The result of an expression
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Value {
+    Number(f64, Option<Unit>),
+    Date(NaiveDate),
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            Value::Date(date) => write!(f, "{}", date.format("%Y-%m-%d")),
+            Value::Number(value, None) => f.write_str(&format_number(value)),
+            Value::Number(value, Some(unit)) => {
+                // Days and weeks are words, so they take a plural
+                let plural = matches!(unit.name, "day" | "week") && value.abs() != 1.0;
+                write!(
+                    f,
+                    "{} {}{}",
+                    format_number(value),
+                    unit.name,
+                    if plural { "s" } else { "" }
+                )
+            }
+        }
+    }
+}
+
+/// Up to ten decimal places, without trailing zeros
+fn format_number(value: f64) -> String {
+    let text = format!("{:.10}", value);
+    let text = text.trim_end_matches('0').trim_end_matches('.');
+    if text == "-0" {
+        "0".to_string()
+    } else {
+        text.to_string()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Number(f64),
+    Date(NaiveDate),
+    Word(String),
+    Symbol(char),
+}
+
+fn tokenize(expression: &str) -> Result<Vec<Token>, String> {
+    let chars: Vec<char> = expression.chars().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        if c.is_whitespace() {
+            i += 1;
+        } else if c.is_ascii_digit()
+            || (c == '.' && chars.get(i + 1).is_some_and(char::is_ascii_digit))
+        {
+            let date: String = chars[i..chars.len().min(i + 10)].iter().collect();
+            if let Some(parsed) = date
+                .get(4..5)
+                .filter(|dash| *dash == "-")
+                .and_then(|_| NaiveDate::parse_from_str(&date, "%Y-%m-%d").ok())
+            {
+                tokens.push(Token::Date(parsed));
+                i += 10;
+                continue;
+            }
+            let mut number = String::new();
+            while let Some(&c) = chars.get(i) {
+                // Thousands separators are dropped: `1,000` and `1_000`
+                let separator = (c == ',' || c == '_')
+                    && chars.len() >= i + 4
+                    && chars[i + 1..i + 4].iter().all(char::is_ascii_digit)
+                    && !chars.get(i + 4).is_some_and(char::is_ascii_digit);
+                if c.is_ascii_digit() || c == '.' {
+                    number.push(c);
+                } else if !separator {
+                    break;
+                }
+                i += 1;
+            }
+            let value = number
+                .parse()
+                .map_err(|_| format!("'{}' is not a number", number))?;
+            tokens.push(Token::Number(value));
+        } else if c.is_alphabetic() {
+            let start = i;
+            while chars.get(i).is_some_and(|c| c.is_alphabetic()) {
+                i += 1;
+            }
+            tokens.push(Token::Word(chars[start..i].iter().collect()));
+        } else {
+            let symbol = match c {
+                '×' => '*',
+                '÷' => '/',
+                '−' => '-',
+                '+' | '-' | '*' | '/' | '^' | '%' | '(' | ')' => c,
+                _ => return Err(format!("unexpected '{}'", c)),
+            };
+            tokens.push(Token::Symbol(symbol));
+            i += 1;
+        }
+    }
+    Ok(tokens)
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    position: usize,
+    today: NaiveDate,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.position)
+    }
+
+    fn eat_symbol(&mut self, symbols: &[char]) -> Option<char> {
+        match self.peek() {
+            Some(&Token::Symbol(c)) if symbols.contains(&c) => {
+                self.position += 1;
+                Some(c)
+            }
+            _ => None,
+        }
+    }
+
+    /// The unit named next, unless it is `in` starting a conversion
+    fn unit_here(&self) -> Option<Unit> {
+        let Some(Token::Word(word)) = self.peek() else {
+            return None;
+        };
+        let unit = find_unit(word)?;
+        let converts = word.eq_ignore_ascii_case("in")
+            && matches!(self.tokens.get(self.position + 1), Some(Token::Word(next)) if find_unit(next).is_some());
+        (!converts).then_some(unit)
+    }
+
+    fn expression(&mut self) -> Result<Value, String> {
+        let value = self.sum()?;
+        if let Some(Token::Word(word)) = self.peek() {
+            if ["in", "to", "as"]
+                .iter()
+                .any(|k| word.eq_ignore_ascii_case(k))
+            {
+                self.position += 1;
+                let target = match self.peek() {
+                    Some(Token::Word(word)) => {
+                        find_unit(word).ok_or_else(|| format!("unknown unit '{}'", word))?
+                    }
+                    _ => return Err("expected a unit to convert to".to_string()),
+                };
+                self.position += 1;
+                return convert(value, target);
+            }
+        }
+        match self.peek() {
+            None => Ok(value),
+            Some(Token::Word(word)) => Err(format!("unknown unit '{}'", word)),
+            Some(_) => Err("unexpected text after the expression".to_string()),
+        }
+    }
+
+    fn sum(&mut self) -> Result<Value, String> {
+        let mut value = self.product()?;
+        while let Some(op) = self.eat_symbol(&['+', '-']) {
+            let right = self.product()?;
+            value = add(value, right, op == '-')?;
+        }
+        Ok(value)
+    }
+
+    fn product(&mut self) -> Result<Value, String> {
+        let mut value = self.unary()?;
+        while let Some(op) = self.eat_symbol(&['*', '/']) {
+            let right = self.unary()?;
+            value = if op == '*' {
+                multiply(value, right)?
+            } else {
+                divide(value, right)?
+            };
+        }
+        Ok(value)
+    }
+
+    fn unary(&mut self) -> Result<Value, String> {
+        match self.eat_symbol(&['-', '+']) {
+            Some('-') => match self.unary()? {
+                Value::Number(value, unit) => Ok(Value::Number(-value, unit)),
+                Value::Date(_) => Err("can't negate a date".to_string()),
+            },
+            Some(_) => self.unary(),
+            None => self.power(),
+        }
+    }
+
+    fn power(&mut self) -> Result<Value, String> {
+        let base = self.postfix()?;
+        if self.eat_symbol(&['^']).is_none() {
+            return Ok(base);
+        }
+        match (base, self.unary()?) {
+            (Value::Number(base, None), Value::Number(exponent, None)) => {
+                Ok(Value::Number(base.powf(exponent), None))
+            }
+            _ => Err("only plain numbers can be raised to a power".to_string()),
+        }
+    }
+
+    /// A primary followed by a percent sign or a unit
+    fn postfix(&mut self) -> Result<Value, String> {
+        let mut value = self.primary()?;
+        if self.eat_symbol(&['%']).is_some() {
+            value = divide(value, Value::Number(100.0, None))?;
+        }
+        if let Some(unit) = self.unit_here() {
+            self.position += 1;
+            value = match value {
+                Value::Number(value, None) => Value::Number(value, Some(unit)),
+                _ => return Err(format!("'{}' must follow a plain number", unit.name)),
+            };
+        }
+        Ok(value)
+    }
+
+    fn primary(&mut self) -> Result<Value, String> {
+        let token = self.peek().cloned();
+        self.position += 1;
+        match token {
+            Some(Token::Number(value)) => Ok(Value::Number(value, None)),
+            Some(Token::Date(date)) => Ok(Value::Date(date)),
+            Some(Token::Word(word)) if word.eq_ignore_ascii_case("today") => {
+                Ok(Value::Date(self.today))
+            }
+            Some(Token::Symbol('(')) => {
+                let value = self.sum()?;
+                self.eat_symbol(&[')'])
+                    .ok_or_else(|| "missing ')'".to_string())?;
+                Ok(value)
+            }
+            Some(Token::Word(word)) => Err(format!("unknown word '{}'", word)),
+            Some(Token::Symbol(c)) => Err(format!("unexpected '{}'", c)),
+            None => Err("the expression ends too soon".to_string()),
+        }
+    }
+}
+
+/// Bring `value` into `unit` of the same dimension
+fn rescale(value: f64, from: Unit, to: Unit) -> Result<f64, String> {
+    if from.dimension != to.dimension {
+        return Err(format!("can't convert {} to {}", from.name, to.name));
+    }
+    Ok(value * from.factor / to.factor)
+}
+
+fn convert(value: Value, target: Unit) -> Result<Value, String> {
+    match value {
+        Value::Number(value, None) => Ok(Value::Number(value, Some(target))),
+        Value::Number(value, Some(unit)) => {
+            Ok(Value::Number(rescale(value, unit, target)?, Some(target)))
+        }
+        Value::Date(_) => Err("can't convert a date".to_string()),
+    }
+}
+
+/// A number of days, from a plain number or a time
+fn whole_days(value: f64, unit: Option<Unit>) -> Result<i64, String> {
+    let days = match unit {
+        None => value,
+        Some(unit) => rescale(value, unit, DAY)?,
+    };
+    if days.fract().abs() > 1e-9 {
+        return Err("dates move by whole days".to_string());
+    }
+    Ok(days.round() as i64)
+}
+
+fn shift_date(date: NaiveDate, days: i64) -> Result<Value, String> {
+    Duration::try_days(days)
+        .and_then(|days| date.checked_add_signed(days))
+        .map(Value::Date)
+        .ok_or_else(|| "the date is out of range".to_string())
+}
+
+fn add(left: Value, right: Value, subtract: bool) -> Result<Value, String> {
+    let sign = if subtract { -1.0 } else { 1.0 };
+    match (left, right) {
+        (Value::Number(a, unit), Value::Number(b, other)) => {
+            let b = match (unit, other) {
+                (Some(unit), Some(other)) => rescale(b, other, unit)?,
+                _ => b,
+            };
+            Ok(Value::Number(a + sign * b, unit.or(other)))
+        }
+        (Value::Date(date), Value::Number(value, unit)) => {
+            let days = whole_days(value, unit)?;
+            shift_date(date, if subtract { -days } else { days })
+        }
+        (Value::Number(value, unit), Value::Date(date)) if !subtract => {
+            shift_date(date, whole_days(value, unit)?)
+        }
+        (Value::Date(a), Value::Date(b)) if subtract => {
+            Ok(Value::Number((a - b).num_days() as f64, Some(DAY)))
+        }
+        _ => Err(format!(
+            "can't {} those",
+            if subtract { "subtract" } else { "add" }
+        )),
+    }
+}
+
+fn multiply(left: Value, right: Value) -> Result<Value, String> {
+    match (left, right) {
+        (Value::Number(_, Some(_)), Value::Number(_, Some(_))) => {
+            Err("can't multiply two quantities with units".to_string())
+        }
+        (Value::Number(a, unit), Value::Number(b, other)) => {
+            Ok(Value::Number(a * b, unit.or(other)))
+        }
+        _ => Err("can't multiply a date".to_string()),
+    }
+}
+
+fn divide(left: Value, right: Value) -> Result<Value, String> {
+    let (Value::Number(a, unit), Value::Number(b, other)) = (left, right) else {
+        return Err("can't divide a date".to_string());
+    };
+    if b == 0.0 {
+        return Err("division by zero".to_string());
+    }
+    match (unit, other) {
+        // A ratio of two quantities is a plain number
+        (Some(unit), Some(other)) => Ok(Value::Number(a / rescale(b, other, unit)?, None)),
+        (unit, None) => Ok(Value::Number(a / b, unit)),
+        (None, Some(other)) => Err(format!("can't divide by {}", other.name)),
+    }
+}
+
+/// Evaluate `expression`, taking `today` as the date `today` stands for
+pub fn evaluate(expression: &str, today: NaiveDate) -> Result<Value, String> {
+    let tokens = tokenize(expression)?;
+    if tokens.is_empty() {
+        return Err("nothing to evaluate".to_string());
+    }
+    let mut parser = Parser {
+        tokens,
+        position: 0,
+        today,
+    };
+    let value = parser.expression()?;
+    if let Value::Number(number, _) = value {
+        if !number.is_finite() {
+            return Err("the result is too large".to_string());
+        }
+    }
+    Ok(value)
+}
@@ -0,0 +1,73 @@
+//! Structured notice of what the engine changed, for code that keeps its
+//! own copy of the text or cursor, such as a renderer's line cache, a
+//! language server client or a sync layer
+//!
+//! [`EditorEngine::subscribe`](crate::EditorEngine::subscribe) returns a
+//! channel that gets a [`ChangeEvent`] for every edit, cursor move,
+//! selection change, load and save made through the engine's methods, and
+//! progress of loads and saves run in the background.
+//! Changes made directly through `state_mut` aren't reported.
+
+use crate::{BufferPosition, BufferRange};
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[non_exhaustive]
+pub enum ChangeEvent {
+    /// The text in `range`, as positioned before the edit, was replaced by
+    /// `text`; an insertion has an empty range and a deletion empty text
+    Replaced {
+        range: BufferRange,
+        text: String,
+    },
+    /// The text changed somewhere in a large file, where working out the
+    /// span would cost more than the edit; see [`crate::large_file`]
+    Edited,
+    CursorMoved {
+        cursor: BufferPosition,
+    },
+    /// The selection now spans `selection`, or nothing is selected
+    SelectionChanged {
+        selection: Option<BufferRange>,
+    },
+    /// The whole buffer was replaced, from `path` or from a string, with
+    /// the cursor at the start and nothing selected
+    Loaded {
+        path: Option<PathBuf>,
+    },
+    Saved {
+        path: PathBuf,
+    },
+    /// `done` of `total` bytes of a background load have been read; see
+    /// [`crate::file_io`]
+    Loading {
+        path: PathBuf,
+        done: u64,
+        total: u64,
+    },
+    /// `done` of `total` bytes of a background save have been written
+    Saving {
+        path: PathBuf,
+        done: u64,
+        total: u64,
+    },
+    /// A background load or save was cancelled, leaving the buffer or file
+    /// as it was
+    Cancelled {
+        path: PathBuf,
+    },
+    /// A background load or save failed
+    Failed {
+        path: PathBuf,
+        message: String,
+    },
+}
+
+/// Position of a byte offset within `text`, whose lines end in `\n`
+pub(crate) fn position_in(text: &str, offset: usize) -> BufferPosition {
+    let before = &text[..offset];
+    let row = before.matches('\n').count();
+    let column = offset - before.rfind('\n').map_or(0, |i| i + 1);
+    BufferPosition::new(row, column)
+}
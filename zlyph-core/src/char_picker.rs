@@ -0,0 +1,194 @@
+//! Finding characters to insert by name: emoji by their name or `:shortcode:`
+//! and any other named Unicode character, or a code point typed in hex
+//!
+//! Characters whose names only number them, such as CJK ideographs and
+//! Hangul syllables, are left out of name searches; a code point still
+//! inserts them.
+
+use std::sync::OnceLock;
+
+/// How many matches a search returns
+const MAX_MATCHES: usize = 100;
+
+/// A character or emoji the picker offers
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Symbol {
+    pub text: String,
+    /// Lowercase name, e.g. "rocket" or "greek small letter alpha"
+    pub name: String,
+    /// GitHub-style shortcode of an emoji, without the colons
+    pub shortcode: Option<String>,
+}
+
+/// Every emoji, then every other named character
+pub fn symbols() -> &'static [Symbol] {
+    static SYMBOLS: OnceLock<Vec<Symbol>> = OnceLock::new();
+    SYMBOLS.get_or_init(|| {
+        let mut symbols: Vec<Symbol> = emojis::iter()
+            .map(|emoji| Symbol {
+                text: emoji.as_str().to_string(),
+                name: emoji.name().to_lowercase(),
+                shortcode: emoji.shortcode().map(String::from),
+            })
+            .collect();
+        let emoji_texts: std::collections::HashSet<String> =
+            symbols.iter().map(|symbol| symbol.text.clone()).collect();
+        for c in (' '..=char::MAX).filter(|c| !c.is_control()) {
+            let Some(name) = unicode_names2::name(c) else {
+                continue;
+            };
+            let name = name.to_string().to_lowercase();
+            let numbered = name.ends_with(&format!("{:x}", c as u32));
+            if !numbered && !emoji_texts.contains(c.encode_utf8(&mut [0; 4])) {
+                symbols.push(Symbol {
+                    text: c.to_string(),
+                    name,
+                    shortcode: None,
+                });
+            }
+        }
+        symbols
+    })
+}
+
+/// The character a code point typed in hex stands for, as in `1F680`,
+/// `U+1F680` or `0x1f680`
+pub fn parse_codepoint(input: &str) -> Option<char> {
+    let input = input.trim();
+    let hex = ["U+", "u+", "0x", "0X"]
+        .iter()
+        .find_map(|prefix| input.strip_prefix(prefix))
+        .unwrap_or(input);
+    if hex.is_empty() || hex.len() > 6 {
+        return None;
+    }
+    char::from_u32(u32::from_str_radix(hex, 16).ok()?)
+}
+
+/// Symbols matching `query`, best first. A query starting with `:` matches
+/// emoji shortcodes; otherwise every word of it must appear in the name.
+pub fn search(query: &str) -> Vec<&'static Symbol> {
+    let query = query.trim().to_lowercase();
+    if query.is_empty() {
+        return symbols()
+            .iter()
+            .filter(|symbol| symbol.shortcode.is_some())
+            .take(MAX_MATCHES)
+            .collect();
+    }
+
+    if let Some(code) = query.strip_prefix(':') {
+        let code = code.trim_end_matches(':');
+        let mut matches: Vec<&Symbol> = symbols()
+            .iter()
+            .filter(|symbol| {
+                symbol
+                    .shortcode
+                    .as_deref()
+                    .is_some_and(|shortcode| shortcode.starts_with(code))
+            })
+            .collect();
+        matches.sort_by_key(|symbol| symbol.shortcode.as_ref().map_or(0, String::len));
+        matches.truncate(MAX_MATCHES);
+        return matches;
+    }
+
+    let words: Vec<&str> = query.split_whitespace().collect();
+    let mut matches: Vec<(usize, &Symbol)> = symbols()
+        .iter()
+        .enumerate()
+        .filter(|(_, symbol)| words.iter().all(|word| symbol.name.contains(word)))
+        .collect();
+    // Exact names, then names starting with the query, then emoji, then
+    // shorter names
+    matches.sort_by_key(|(order, symbol)| {
+        (
+            symbol.name != query,
+            !symbol.name.starts_with(&query),
+            symbol.shortcode.is_none(),
+            symbol.name.len(),
+            *order,
+        )
+    });
+    matches
+        .into_iter()
+        .take(MAX_MATCHES)
+        .map(|(_, symbol)| symbol)
+        .collect()
+}
+
+/// The picker both frontends show: the query typed and its matches, led by
+/// the character it names if it is a code point written as `U+…` or `0x…`
+#[derive(Debug, Clone, Default)]
+pub struct CharPicker {
+    pub query: String,
+    /// Index into [`CharPicker::matches`]
+    pub selected: usize,
+    matches: Vec<Symbol>,
+    /// Whether the first match is the code point typed
+    codepoint: bool,
+}
+
+impl CharPicker {
+    pub fn new(query: &str) -> Self {
+        let mut picker = Self::default();
+        picker.type_text(query);
+        picker
+    }
+
+    pub fn type_text(&mut self, text: &str) {
+        self.query.push_str(text);
+        self.update();
+    }
+
+    pub fn backspace(&mut self) {
+        self.query.pop();
+        self.update();
+    }
+
+    fn update(&mut self) {
+        let prefixed = ["u+", "0x"].iter().any(|prefix| {
+            self.query
+                .get(..2)
+                .is_some_and(|start| start.eq_ignore_ascii_case(prefix))
+        });
+        let codepoint = parse_codepoint(&self.query)
+            .filter(|_| prefixed)
+            .map(|c| Symbol {
+                text: c.to_string(),
+                name: unicode_names2::name(c).map_or_else(
+                    || format!("u+{:04x}", c as u32),
+                    |name| name.to_string().to_lowercase(),
+                ),
+                shortcode: None,
+            });
+        self.codepoint = codepoint.is_some();
+        self.matches = codepoint
+            .into_iter()
+            .chain(search(&self.query).into_iter().cloned())
+            .collect();
+        self.selected = 0;
+    }
+
+    pub fn matches(&self) -> &[Symbol] {
+        &self.matches
+    }
+
+    pub fn select_previous(&mut self) {
+        self.selected = self.selected.saturating_sub(1);
+    }
+
+    pub fn select_next(&mut self) {
+        self.selected = (self.selected + 1).min(self.matches.len().saturating_sub(1));
+    }
+
+    /// Whether the selected match is the code point typed rather than one
+    /// found by name
+    pub fn codepoint_selected(&self) -> bool {
+        self.codepoint && self.selected == 0
+    }
+
+    pub fn selected_symbol(&self) -> Option<&Symbol> {
+        self.matches.get(self.selected)
+    }
+}
@@ -0,0 +1,365 @@
+//! Citations: `@key` in Markdown refers to a reference in the configured
+//! bibliography, a BibTeX (`.bib`) or CSL-JSON (`.json`) file
+//!
+//! Keys are written as Pandoc writes them, bare or in brackets as in
+//! `[@smith2020, p. 4]`. An `@` straight after a letter or digit, as in an
+//! email address, doesn't start one. Only the fields a short reference
+//! needs are read: authors, title, year and where it appeared. BibTeX's
+//! `@string` macros and LaTeX accents are left as written.
+
+use crate::markdown;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::fs;
+use std::ops::Range;
+use std::path::Path;
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Reference {
+    pub key: String,
+    /// Family names, in order
+    pub authors: Vec<String>,
+    pub title: String,
+    pub year: Option<String>,
+    /// The journal, book or publisher it appeared in
+    pub source: Option<String>,
+}
+
+impl Reference {
+    /// One line for a hover or a bibliography, as in
+    /// `Smith and Jones (2020). A title. A journal.`
+    pub fn summary(&self) -> String {
+        let authors = match self.authors.as_slice() {
+            [] => None,
+            [only] => Some(only.clone()),
+            [first, second] => Some(format!("{} and {}", first, second)),
+            [first, ..] => Some(format!("{} et al.", first)),
+        };
+        let head = match (authors, &self.year) {
+            (Some(authors), Some(year)) => Some(format!("{} ({})", authors, year)),
+            (authors, year) => authors.or(year.clone()),
+        };
+        [head, Some(self.title.clone()), self.source.clone()]
+            .into_iter()
+            .flatten()
+            .filter(|part| !part.is_empty())
+            .map(|part| {
+                if part.ends_with(['.', '?', '!']) {
+                    part
+                } else {
+                    part + "."
+                }
+            })
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+}
+
+/// The references in the bibliography at `path`, read as CSL-JSON if it
+/// ends in `.json` and as BibTeX otherwise
+pub fn load(path: &Path) -> Vec<Reference> {
+    let Ok(text) = fs::read_to_string(path) else {
+        return Vec::new();
+    };
+    if path
+        .extension()
+        .is_some_and(|extension| extension == "json")
+    {
+        parse_csl_json(&text)
+    } else {
+        parse_bibtex(&text)
+    }
+}
+
+pub fn parse_bibtex(text: &str) -> Vec<Reference> {
+    let mut references = Vec::new();
+    let mut from = 0;
+    while let Some(start) = text[from..].find('@').map(|at| from + at) {
+        let Some(open) = text[start..].find(['{', '(']).map(|at| start + at) else {
+            break;
+        };
+        let kind = text[start + 1..open].trim().to_ascii_lowercase();
+        // An `@` outside an entry, as in an address in a comment
+        if kind.is_empty() || !kind.chars().all(|c| c.is_ascii_alphabetic()) {
+            from = start + 1;
+            continue;
+        }
+        let close = body_end(text, open);
+        from = close;
+        if !matches!(kind.as_str(), "comment" | "string" | "preamble") {
+            references.extend(bibtex_entry(&text[open + 1..close]));
+        }
+    }
+    references
+}
+
+/// Where the entry body opened by the `{` or `(` at `open` closes
+fn body_end(text: &str, open: usize) -> usize {
+    let bytes = text.as_bytes();
+    let close = if bytes[open] == b'(' { b')' } else { b'}' };
+    let mut depth = 0;
+    for (at, &byte) in bytes.iter().enumerate().skip(open + 1) {
+        match byte {
+            b'{' => depth += 1,
+            b'}' if depth > 0 => depth -= 1,
+            _ if byte == close && depth == 0 => return at,
+            _ => {}
+        }
+    }
+    text.len()
+}
+
+/// An entry from its body, `key, field = value, ...`
+fn bibtex_entry(body: &str) -> Option<Reference> {
+    let (key, mut rest) = body.split_once(',').unwrap_or((body, ""));
+    let key = key.trim();
+    if key.is_empty() {
+        return None;
+    }
+    let mut fields = HashMap::new();
+    while let Some((name, after)) = rest.split_once('=') {
+        let (value, after) = bibtex_value(after);
+        fields.insert(name.trim().to_ascii_lowercase(), value);
+        rest = after;
+    }
+    let field = |name: &str| fields.get(name).map(|value| clean(value));
+    let year = field("year").or_else(|| {
+        field("date").map(|date| date.split('-').next().unwrap_or_default().to_string())
+    });
+    Some(Reference {
+        key: key.to_string(),
+        authors: fields
+            .get("author")
+            .or_else(|| fields.get("editor"))
+            .map(|names| {
+                split_names(names)
+                    .iter()
+                    .map(|name| family_name(name))
+                    .collect()
+            })
+            .unwrap_or_default(),
+        title: field("title").unwrap_or_default(),
+        year,
+        source: field("journal")
+            .or_else(|| field("journaltitle"))
+            .or_else(|| field("booktitle"))
+            .or_else(|| field("publisher")),
+    })
+}
+
+/// A field's value, its braces and quotes taken off but those inside kept,
+/// and the text after the comma ending it
+fn bibtex_value(text: &str) -> (String, &str) {
+    let bytes = text.as_bytes();
+    let mut value = String::new();
+    let mut at = 0;
+    while at < bytes.len() {
+        match bytes[at] {
+            b',' => return (value, &text[at + 1..]),
+            b'{' | b'"' => {
+                let end = delimited_end(text, at);
+                value.push_str(&text[at + 1..end]);
+                at = end + 1;
+            }
+            byte if byte == b'#' || byte.is_ascii_whitespace() => at += 1,
+            _ => {
+                let end = text[at..]
+                    .find(|c: char| c == ',' || c == '#' || c.is_whitespace())
+                    .map_or(text.len(), |len| at + len);
+                value.push_str(&text[at..end]);
+                at = end;
+            }
+        }
+    }
+    (value, "")
+}
+
+/// Where the braced or quoted value opened at `open` ends
+fn delimited_end(text: &str, open: usize) -> usize {
+    let quoted = text.as_bytes()[open] == b'"';
+    let mut depth = 0;
+    for (at, &byte) in text.as_bytes().iter().enumerate().skip(open + 1) {
+        match byte {
+            b'{' => depth += 1,
+            b'}' if depth > 0 => depth -= 1,
+            b'}' if !quoted => return at,
+            b'"' if quoted && depth == 0 => return at,
+            _ => {}
+        }
+    }
+    text.len()
+}
+
+/// The names in an author field, split at `and`s outside braces, so
+/// `{Barnes and Noble}` stays one name
+fn split_names(names: &str) -> Vec<&str> {
+    let mut split = Vec::new();
+    let mut depth = 0;
+    let mut start = 0;
+    for (at, c) in names.char_indices() {
+        match c {
+            '{' => depth += 1,
+            '}' => depth -= 1,
+            _ if depth == 0 && at >= start && c.is_whitespace() => {
+                let rest = names[at..].trim_start();
+                if let Some(after) = rest.strip_prefix("and") {
+                    if after.starts_with(char::is_whitespace) {
+                        split.push(&names[start..at]);
+                        start = names.len() - after.len();
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+    split.push(&names[start..]);
+    split
+        .into_iter()
+        .filter(|name| !name.trim().is_empty())
+        .collect()
+}
+
+/// `Smith` from `Smith, Jane` or `Jane Smith`, and a braced name whole
+fn family_name(name: &str) -> String {
+    let name = name.trim();
+    if name.starts_with('{') && name.ends_with('}') {
+        return clean(name);
+    }
+    match name.split_once(',') {
+        Some((family, _)) => clean(family),
+        None => clean(name.rsplit(char::is_whitespace).next().unwrap_or(name)),
+    }
+}
+
+/// `value` without braces, its space collapsed
+fn clean(value: &str) -> String {
+    value
+        .replace(['{', '}'], "")
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+pub fn parse_csl_json(text: &str) -> Vec<Reference> {
+    let Ok(Value::Array(items)) = serde_json::from_str::<Value>(text) else {
+        return Vec::new();
+    };
+    items.iter().filter_map(csl_item).collect()
+}
+
+fn csl_item(item: &Value) -> Option<Reference> {
+    let key = match &item["id"] {
+        Value::String(id) => id.clone(),
+        Value::Number(id) => id.to_string(),
+        _ => return None,
+    };
+    let text = |name: &str| item[name].as_str().map(clean);
+    let names = item["author"].as_array().or(item["editor"].as_array());
+    let authors = names
+        .into_iter()
+        .flatten()
+        .filter_map(|name| name["family"].as_str().or(name["literal"].as_str()))
+        .map(clean)
+        .collect();
+    let issued = &item["issued"];
+    let year = match &issued["date-parts"][0][0] {
+        Value::Number(year) => Some(year.to_string()),
+        Value::String(year) => Some(year.clone()),
+        _ => issued["raw"]
+            .as_str()
+            .or(issued["literal"].as_str())
+            .map(String::from),
+    };
+    Some(Reference {
+        key,
+        authors,
+        title: text("title").unwrap_or_default(),
+        year,
+        source: text("container-title").or_else(|| text("publisher")),
+    })
+}
+
+/// Each citation in `line` as the range of its key, after the `@`
+pub fn citations(line: &str) -> Vec<Range<usize>> {
+    line.match_indices('@')
+        .filter(|&(at, _)| starts_citation(line, at))
+        .filter_map(|(at, _)| {
+            let len = key_len(&line[at + 1..]);
+            (len > 0).then_some(at + 1..at + 1 + len)
+        })
+        .collect()
+}
+
+/// Whether the `@` at `at` can start a citation: it's at the start of the
+/// line or after something other than a letter or digit
+pub(crate) fn starts_citation(line: &str, at: usize) -> bool {
+    !line[..at]
+        .chars()
+        .next_back()
+        .is_some_and(|c| c.is_alphanumeric() || c == '_')
+}
+
+/// The length of the citation key at the start of `text`; punctuation
+/// inside a key is part of it, but not at its end, so `@smith2020.` cites
+/// `smith2020`
+pub(crate) fn key_len(text: &str) -> usize {
+    if !text.starts_with(|c: char| c.is_alphanumeric() || c == '_') {
+        return 0;
+    }
+    let run = text.find(|c: char| !is_key_char(c)).unwrap_or(text.len());
+    text[..run]
+        .trim_end_matches(|c: char| !(c.is_alphanumeric() || c == '_'))
+        .len()
+}
+
+fn is_key_char(c: char) -> bool {
+    c.is_alphanumeric() || "_:.#$%&-+?<>~/".contains(c)
+}
+
+/// Where the key of a citation being typed before `column` starts, for
+/// completing it
+pub fn open_citation_start(line: &str, column: usize) -> Option<usize> {
+    let before = line.get(..column)?;
+    let at = before.rfind('@')?;
+    let typed = &before[at + 1..];
+    (starts_citation(line, at) && typed.chars().all(is_key_char)).then_some(at + 1)
+}
+
+/// The key of the citation at `column`, the `@` included
+pub fn key_at(line: &str, column: usize) -> Option<&str> {
+    citations(line)
+        .into_iter()
+        .find(|key| key.start - 1 <= column && column <= key.end)
+        .map(|key| &line[key])
+}
+
+/// The keys cited in `lines` outside fenced code, each once, in the order
+/// they're first cited
+pub fn cited_keys(lines: &[String]) -> Vec<String> {
+    let code = markdown::code_rows(lines);
+    let mut keys: Vec<String> = Vec::new();
+    for (line, _) in lines.iter().zip(code).filter(|&(_, code)| !code) {
+        for key in citations(line) {
+            if !keys.iter().any(|cited| *cited == line[key.clone()]) {
+                keys.push(line[key].to_string());
+            }
+        }
+    }
+    keys
+}
+
+/// A Markdown list of the references cited in `lines`, ordered by their
+/// authors, and the cited keys missing from `references`
+pub fn bibliography(lines: &[String], references: &[Reference]) -> (Vec<String>, Vec<String>) {
+    let mut listed = Vec::new();
+    let mut missing = Vec::new();
+    for key in cited_keys(lines) {
+        match references.iter().find(|reference| reference.key == key) {
+            Some(reference) => listed.push(reference.summary()),
+            None => missing.push(key),
+        }
+    }
+    listed.sort_by_key(|summary| summary.to_lowercase());
+    let entries = listed.into_iter().map(|summary| format!("- {}", summary));
+    (entries.collect(), missing)
+}
@@ -0,0 +1,221 @@
+//! The selection drawn as a picture for sharing: highlighted in the theme's
+//! colors inside a rounded window, with padding around it
+//!
+//! The picture is an SVG, which is also rasterized to PNG with the system's
+//! fonts. Characters are placed as in a monospaced font, so the text should
+//! be set in one.
+
+use crate::config::{CodeImageConfig, Config, FontConfig};
+use crate::export::{self, Palette};
+use crate::BufferRange;
+use resvg::{tiny_skia, usvg};
+use std::fmt::Write as _;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// Width of a character as a share of the font size, as in most
+/// monospaced fonts
+const ADVANCE: f32 = 0.6;
+/// Space between the window's edge and the text
+const INSET: f32 = 20.0;
+/// Height of the title bar with its three buttons
+const TITLE_BAR: f32 = 36.0;
+
+/// What to draw besides the text
+#[derive(Debug, Clone, PartialEq)]
+pub struct ImageOptions {
+    /// Shown in the title bar, usually the file's name
+    pub title: String,
+    pub font_family: String,
+    pub font_size: f32,
+    /// Multiple of the font size
+    pub line_height: f32,
+    /// Space around the window, filled with `backdrop`
+    pub padding: f32,
+    pub backdrop: String,
+    pub window_chrome: bool,
+}
+
+impl ImageOptions {
+    /// Options for a picture of the file at `path` as configured
+    pub fn new(path: Option<&Path>, config: &CodeImageConfig, font: &FontConfig) -> Self {
+        let families: Vec<&str> = std::iter::once(&font.family)
+            .chain(&font.fallbacks)
+            .map(String::as_str)
+            .collect();
+        Self {
+            title: path
+                .and_then(Path::file_name)
+                .map(|name| name.to_string_lossy().into_owned())
+                .unwrap_or_default(),
+            font_family: format!("{}, monospace", families.join(", ")),
+            font_size: config.font_size,
+            line_height: font.line_height,
+            padding: config.padding,
+            backdrop: config.backdrop.clone(),
+            window_chrome: config.window_chrome,
+        }
+    }
+}
+
+/// Where a file's picture is written unless asked otherwise: beside it, as
+/// `main.png` for `main.rs`
+pub fn image_path(path: &Path) -> PathBuf {
+    path.with_extension("png")
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// `lines`, or only `range` of them, drawn as an SVG picture
+pub fn svg(
+    lines: &[String],
+    path: Option<&Path>,
+    range: Option<BufferRange>,
+    palette: &Palette,
+    options: &ImageOptions,
+) -> String {
+    let printed = export::layout(lines, path, range, usize::MAX, true);
+    // Leave out the blank lines a selection starts or ends with
+    let first = printed.iter().position(|runs| !runs.is_empty());
+    let last = printed.iter().rposition(|runs| !runs.is_empty());
+    let printed = match (first, last) {
+        (Some(first), Some(last)) => &printed[first..=last],
+        _ => &printed[..0],
+    };
+
+    let size = options.font_size.max(4.0);
+    let line_height = size * options.line_height.max(1.0);
+    let columns = printed
+        .iter()
+        .filter_map(|runs| runs.last())
+        .map(|run| run.column + run.text.chars().count())
+        .max()
+        .unwrap_or(0)
+        .max(20);
+    let title_bar = if options.window_chrome {
+        TITLE_BAR
+    } else {
+        0.0
+    };
+    let window_width = columns as f32 * size * ADVANCE + 2.0 * INSET;
+    let window_height = printed.len().max(1) as f32 * line_height + 2.0 * INSET + title_bar;
+    let (x, y) = (options.padding, options.padding);
+    let width = window_width + 2.0 * options.padding;
+    let height = window_height + 2.0 * options.padding;
+
+    let mut svg = format!(
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{width}\" height=\"{height}\" \
+         viewBox=\"0 0 {width} {height}\">\n\
+         <filter id=\"shadow\" x=\"-20%\" y=\"-20%\" width=\"140%\" height=\"140%\">\
+         <feDropShadow dx=\"0\" dy=\"8\" stdDeviation=\"12\" flood-opacity=\"0.35\"/></filter>\n\
+         <rect width=\"{width}\" height=\"{height}\" fill=\"{}\"/>\n\
+         <rect x=\"{x}\" y=\"{y}\" width=\"{window_width}\" height=\"{window_height}\" \
+         rx=\"10\" fill=\"{}\" filter=\"url(#shadow)\"/>\n",
+        escape(&options.backdrop),
+        palette.background,
+    );
+    if options.window_chrome {
+        for (index, color) in ["#ff5f56", "#ffbd2e", "#27c93f"].iter().enumerate() {
+            let _ = writeln!(
+                svg,
+                "<circle cx=\"{}\" cy=\"{}\" r=\"6\" fill=\"{}\"/>",
+                x + INSET + index as f32 * 20.0,
+                y + TITLE_BAR / 2.0 + 2.0,
+                color
+            );
+        }
+        let _ = writeln!(
+            svg,
+            "<text x=\"{}\" y=\"{}\" text-anchor=\"middle\" font-family=\"{}\" \
+             font-size=\"{}\" fill=\"{}\">{}</text>",
+            x + window_width / 2.0,
+            y + TITLE_BAR / 2.0 + size * 0.35 + 2.0,
+            escape(&options.font_family),
+            size * 0.85,
+            palette.muted,
+            escape(&options.title)
+        );
+    }
+
+    let _ = writeln!(
+        svg,
+        "<g font-family=\"{}\" font-size=\"{}\" xml:space=\"preserve\">",
+        escape(&options.font_family),
+        size
+    );
+    for (index, runs) in printed.iter().enumerate() {
+        let baseline = y + title_bar + INSET + index as f32 * line_height + line_height * 0.75;
+        for run in runs {
+            let mut attributes = format!("fill=\"{}\"", export::color(run.style, palette));
+            if run.style.bold || run.style.heading.is_some() {
+                attributes.push_str(" font-weight=\"bold\"");
+            }
+            if run.style.italic || run.style.quote {
+                attributes.push_str(" font-style=\"italic\"");
+            }
+            if run.style.strikethrough {
+                attributes.push_str(" text-decoration=\"line-through\"");
+            } else if run.style.link && !run.style.markup {
+                attributes.push_str(" text-decoration=\"underline\"");
+            }
+            let _ = writeln!(
+                svg,
+                "<text x=\"{}\" y=\"{}\" {}>{}</text>",
+                x + INSET + run.column as f32 * size * ADVANCE,
+                baseline,
+                attributes,
+                escape(&run.text)
+            );
+        }
+    }
+    svg.push_str("</g>\n</svg>\n");
+    svg
+}
+
+/// An SVG picture rasterized at `scale` times its size, with the system's
+/// fonts
+pub fn png(svg: &str, scale: f32) -> io::Result<Vec<u8>> {
+    let mut options = usvg::Options::default();
+    options.fontdb_mut().load_system_fonts();
+    let tree = usvg::Tree::from_str(svg, &options).map_err(io::Error::other)?;
+    let size = tree
+        .size()
+        .to_int_size()
+        .scale_by(scale)
+        .ok_or_else(|| io::Error::other("the picture is empty"))?;
+    let mut pixmap = tiny_skia::Pixmap::new(size.width(), size.height())
+        .ok_or_else(|| io::Error::other("the picture is too big"))?;
+    resvg::render(
+        &tree,
+        tiny_skia::Transform::from_scale(scale, scale),
+        &mut pixmap.as_mut(),
+    );
+    pixmap.encode_png().map_err(io::Error::other)
+}
+
+/// Draw `lines`, or only `range` of them, as configured to `target`: an
+/// SVG if its name ends in `.svg`, else a PNG
+pub fn write(
+    target: &Path,
+    lines: &[String],
+    path: Option<&Path>,
+    range: Option<BufferRange>,
+    palette: &Palette,
+    config: &Config,
+) -> io::Result<()> {
+    let options = ImageOptions::new(path, &config.code_image, &config.font);
+    let picture = svg(lines, path, range, palette, &options);
+    let is_svg = target
+        .extension()
+        .is_some_and(|extension| extension.eq_ignore_ascii_case("svg"));
+    if is_svg {
+        std::fs::write(target, picture)
+    } else {
+        std::fs::write(target, png(&picture, config.code_image.scale)?)
+    }
+}
@@ -0,0 +1,126 @@
+//! Word completion from buffer contents and an optional dictionary
+
+use crate::text_objects::is_word_char;
+use crate::BufferPosition;
+use std::collections::HashSet;
+
+/// An open completion popup
+#[derive(Debug, Clone, PartialEq)]
+pub struct Completion {
+    /// Start of the partial word being completed
+    pub start: BufferPosition,
+    pub prefix: String,
+    pub items: Vec<String>,
+    pub selected: usize,
+}
+
+impl Completion {
+    pub fn selected_item(&self) -> Option<&str> {
+        self.items.get(self.selected).map(|s| s.as_str())
+    }
+
+    pub fn select_offset(&mut self, step: isize) {
+        if self.items.is_empty() {
+            return;
+        }
+        let len = self.items.len() as isize;
+        self.selected = (self.selected as isize + step).rem_euclid(len) as usize;
+    }
+}
+
+/// Start column of the word ending at `column`
+pub fn word_start(line: &str, column: usize) -> usize {
+    line[..column]
+        .char_indices()
+        .rev()
+        .take_while(|(_, c)| is_word_char(*c))
+        .last()
+        .map_or(column, |(i, _)| i)
+}
+
+/// Unique words in the buffer, skipping the one being typed at `exclude`
+pub fn buffer_words(lines: &[String], exclude: BufferPosition) -> Vec<String> {
+    let mut seen = HashSet::new();
+    let mut words = Vec::new();
+
+    for (row, line) in lines.iter().enumerate() {
+        let mut start = None;
+        for (i, c) in line
+            .char_indices()
+            .chain(std::iter::once((line.len(), ' ')))
+        {
+            match (start, is_word_char(c)) {
+                (None, true) => start = Some(i),
+                (Some(s), false) => {
+                    let is_current =
+                        row == exclude.row && s <= exclude.column && exclude.column <= i;
+                    let word = &line[s..i];
+                    if !is_current && seen.insert(word) {
+                        words.push(word.to_string());
+                    }
+                    start = None;
+                }
+                _ => {}
+            }
+        }
+    }
+
+    words
+}
+
+/// Score how well `candidate` matches `query`; higher is better, None means no match.
+///
+/// Exact prefixes rank above case-insensitive prefixes, which rank above
+/// scattered subsequence matches. Shorter candidates win ties.
+pub fn fuzzy_score(candidate: &str, query: &str) -> Option<i64> {
+    if query.is_empty() {
+        return Some(-(candidate.len() as i64));
+    }
+    if candidate == query {
+        return None;
+    }
+
+    let length_penalty = candidate.len() as i64;
+    if candidate.starts_with(query) {
+        return Some(3000 - length_penalty);
+    }
+
+    let candidate_lower = candidate.to_lowercase();
+    let query_lower = query.to_lowercase();
+    if candidate_lower.starts_with(&query_lower) {
+        return Some(2000 - length_penalty);
+    }
+
+    let mut score = 1000 - length_penalty;
+    let mut last_match: Option<usize> = None;
+    let mut chars = candidate_lower.char_indices();
+    for q in query_lower.chars() {
+        let (i, _) = chars.find(|(_, c)| *c == q)?;
+        if let Some(last) = last_match {
+            score -= (i - last - 1) as i64 * 10;
+        }
+        last_match = Some(i);
+    }
+    Some(score)
+}
+
+/// Rank candidate words against `query`, best first
+pub fn rank<'a>(
+    candidates: impl IntoIterator<Item = &'a str>,
+    query: &str,
+    limit: usize,
+) -> Vec<String> {
+    let mut scored: Vec<(i64, &str)> = candidates
+        .into_iter()
+        .filter_map(|c| fuzzy_score(c, query).map(|score| (score, c)))
+        .collect();
+    scored.sort_by(|a, b| b.0.cmp(&a.0).then_with(|| a.1.cmp(b.1)));
+
+    let mut seen = HashSet::new();
+    scored
+        .into_iter()
+        .filter(|(_, c)| seen.insert(*c))
+        .take(limit)
+        .map(|(_, c)| c.to_string())
+        .collect()
+}
@@ -0,0 +1,62 @@
+//! Input method composition: text an input method is still building, such
+//! as Pinyin being turned into Chinese characters, shown at the cursor but
+//! not yet part of the buffer
+//!
+//! Input methods count in UTF-16 code units where the buffer counts bytes;
+//! the conversions here go between the two within a line.
+
+use std::ops::Range;
+
+/// Text being composed and the part of it the input method has selected,
+/// as byte offsets into the text
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Composition {
+    pub text: String,
+    pub selected: Range<usize>,
+}
+
+impl Composition {
+    /// `text` with the UTF-16 range `selected` of it selected, or the caret
+    /// at its end when none is given
+    pub fn new(text: &str, selected: Option<Range<usize>>) -> Self {
+        let selected = match selected {
+            Some(range) => to_byte(text, range.start)..to_byte(text, range.end),
+            None => text.len()..text.len(),
+        };
+        Self {
+            text: text.to_string(),
+            selected,
+        }
+    }
+
+    /// `line` as the input method sees it, with the composition at `column`
+    pub fn apply(&self, line: &str, column: usize) -> String {
+        let column = column.min(line.len());
+        let mut text = String::with_capacity(line.len() + self.text.len());
+        text.push_str(&line[..column]);
+        text.push_str(&self.text);
+        text.push_str(&line[column..]);
+        text
+    }
+}
+
+/// Byte offset in `text` of the UTF-16 offset `utf16`, moved back to the
+/// start of a character it falls inside and to the end of the text past it
+pub fn to_byte(text: &str, utf16: usize) -> usize {
+    let mut units = 0;
+    for (byte, c) in text.char_indices() {
+        units += c.len_utf16();
+        if units > utf16 {
+            return byte;
+        }
+    }
+    text.len()
+}
+
+/// UTF-16 offset in `text` of the byte offset `byte`
+pub fn to_utf16(text: &str, byte: usize) -> usize {
+    text.char_indices()
+        .take_while(|&(start, _)| start < byte)
+        .map(|(_, c)| c.len_utf16())
+        .sum()
+}
@@ -0,0 +1,684 @@
+//! User configuration loaded from `~/.config/zlyph/config.toml`
+
+use crate::hooks::HookDefinition;
+use crate::snippets::SnippetDefinition;
+use crate::task_runner::TaskDefinition;
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct Config {
+    /// User-defined snippets expanded with Tab after their trigger word
+    pub snippets: Vec<SnippetDefinition>,
+    /// Commands run on editor events, e.g. a formatter after saving
+    pub hooks: Vec<HookDefinition>,
+    /// Build and lint commands whose errors are listed to jump to
+    pub tasks: Vec<TaskDefinition>,
+    pub completion: CompletionConfig,
+    pub lsp: LspConfig,
+    pub languagetool: LanguageToolConfig,
+    pub goal: GoalConfig,
+    pub view: ViewConfig,
+    pub window: WindowConfig,
+    pub font: FontConfig,
+    pub cursor: CursorConfig,
+    pub scroll: ScrollConfig,
+    pub history: HistoryConfig,
+    pub remote: RemoteConfig,
+    pub encryption: EncryptionConfig,
+    pub notes: NotesConfig,
+    pub journal: JournalConfig,
+    pub citations: CitationsConfig,
+    pub datetime: DateTimeConfig,
+    pub typography: TypographyConfig,
+    pub accessibility: AccessibilityConfig,
+    pub context_menu: ContextMenuConfig,
+    pub large_file: LargeFileConfig,
+    pub format: FormatConfig,
+    pub export: ExportConfig,
+    pub print: PrintConfig,
+    pub code_image: CodeImageConfig,
+    pub terminal: TerminalConfig,
+    pub dictation: DictationConfig,
+    /// Words replaced when followed by a space or Tab, as in
+    /// `";sig" = "Best,\nDoug"`
+    pub abbreviations: BTreeMap<String, String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct CompletionConfig {
+    /// Open the popup automatically while typing
+    pub enabled: bool,
+    /// Characters of a word to type before the popup appears
+    pub min_prefix: usize,
+    pub max_items: usize,
+    /// Optional word list (one word per line) offered alongside buffer words
+    pub dictionary: Option<PathBuf>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct LspConfig {
+    pub servers: Vec<LspServerConfig>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct LspServerConfig {
+    /// Language identifier sent to the server, e.g. "rust"
+    pub language_id: String,
+    /// File extensions (without the dot) handled by this server
+    pub extensions: Vec<String>,
+    pub command: String,
+    #[serde(default)]
+    pub args: Vec<String>,
+}
+
+impl LspConfig {
+    /// Server configured for the extension of `path`, if any
+    pub fn server_for(&self, path: &Path) -> Option<&LspServerConfig> {
+        let extension = path.extension()?.to_str()?;
+        self.servers
+            .iter()
+            .find(|server| server.extensions.iter().any(|e| e == extension))
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct LanguageToolConfig {
+    pub enabled: bool,
+    /// Base URL of a LanguageTool server, e.g. one started with `languagetool-server`
+    pub url: String,
+    /// Language code such as "en-US", or "auto" to detect it
+    pub language: String,
+    /// Idle time after the last edit before paragraphs are checked
+    pub debounce_ms: u64,
+}
+
+impl Default for LanguageToolConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            url: "http://localhost:8081".to_string(),
+            language: "auto".to_string(),
+            debounce_ms: 1000,
+        }
+    }
+}
+
+/// Display preferences; the GUI writes these back when they are toggled
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct ViewConfig {
+    /// Distraction-free writing: centered cursor line, dimmed surroundings, no chrome
+    pub focus_mode: bool,
+    /// How much of the text around the cursor focus mode leaves undimmed
+    pub focus_scope: FocusScope,
+    /// Miniature of the whole buffer along the right edge of the GUI
+    pub minimap: bool,
+    /// Whether the GUI remembers zoom for each file or once for all of them
+    pub zoom: ZoomScope,
+    /// The file and the sections or definitions the cursor is in, above the
+    /// text in the GUI and in the status row of the TUI
+    pub breadcrumbs: bool,
+    /// Vertical lines at each indent level, the one around the cursor brighter
+    pub indent_guides: bool,
+    /// Line up tab-separated columns across adjacent lines (GUI)
+    pub elastic_tabstops: bool,
+    /// Color bracket pairs by nesting depth and shade the text inside them
+    pub rainbow_brackets: bool,
+}
+
+impl Default for ViewConfig {
+    fn default() -> Self {
+        Self {
+            focus_mode: false,
+            focus_scope: FocusScope::default(),
+            minimap: false,
+            zoom: ZoomScope::default(),
+            breadcrumbs: true,
+            indent_guides: true,
+            elastic_tabstops: false,
+            rainbow_brackets: false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ZoomScope {
+    #[default]
+    Global,
+    Buffer,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FocusScope {
+    #[default]
+    Paragraph,
+    Sentence,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CursorStyle {
+    Bar,
+    Block,
+    Underline,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct CursorConfig {
+    /// Unset keeps each frontend's usual shape: a bar in the GUI, a block in the TUI
+    pub style: Option<CursorStyle>,
+    pub blink: bool,
+    pub blink_interval_ms: u64,
+    /// Leave a short fading trail when the cursor jumps along a line (GUI)
+    pub smear: bool,
+    /// Move left and right through right-to-left text in the order it is
+    /// shown rather than the order it was typed
+    pub visual_movement: bool,
+}
+
+impl Default for CursorConfig {
+    fn default() -> Self {
+        Self {
+            style: None,
+            blink: true,
+            blink_interval_ms: 530,
+            smear: false,
+            visual_movement: false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct ScrollConfig {
+    /// Animate scrolling in the GUI instead of jumping
+    pub smooth: bool,
+    /// Lines kept between the cursor and the top or bottom edge
+    pub margin: usize,
+}
+
+impl Default for ScrollConfig {
+    fn default() -> Self {
+        Self {
+            smooth: true,
+            margin: 2,
+        }
+    }
+}
+
+/// Local history of edited files; see [`crate::history`]
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct HistoryConfig {
+    pub enabled: bool,
+    /// Least time between snapshots taken while editing
+    pub interval_secs: u64,
+    /// Snapshots older than this are removed
+    pub max_age_days: u64,
+    /// Most space one file's snapshots take before the oldest are removed
+    pub max_size_kb: u64,
+}
+
+impl Default for HistoryConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            interval_secs: 300,
+            max_age_days: 30,
+            max_size_kb: 4096,
+        }
+    }
+}
+
+/// Editing files on other machines; see [`crate::vfs`]
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct RemoteConfig {
+    /// Command run to reach a host, given ssh's options and arguments
+    pub ssh_command: String,
+    /// How long to wait for a host before using the cached copy
+    pub connect_timeout_secs: u64,
+}
+
+impl Default for RemoteConfig {
+    fn default() -> Self {
+        Self {
+            ssh_command: "ssh".to_string(),
+            connect_timeout_secs: 10,
+        }
+    }
+}
+
+/// Encrypting the default notes file; see [`crate::encryption`]
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct EncryptionConfig {
+    pub enabled: bool,
+    /// Keep the passphrase in the OS keychain instead of asking each start
+    pub keychain: bool,
+}
+
+/// Notes linked with `[[wiki links]]`; see [`crate::notes`]
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct NotesConfig {
+    /// Where linked notes are found and created; the edited file's folder
+    /// when unset
+    pub directory: Option<PathBuf>,
+    /// Show a note's frontmatter as one summary line until the cursor
+    /// goes into it; see [`crate::frontmatter`]
+    pub collapse_frontmatter: bool,
+}
+
+/// `@key` citations; see [`crate::citations`]
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct CitationsConfig {
+    /// A BibTeX (`.bib`) or CSL-JSON (`.json`) file the keys are completed
+    /// from and looked up in
+    pub bibliography: Option<PathBuf>,
+}
+
+/// Daily notes; see [`crate::journal`]
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct JournalConfig {
+    /// Where daily notes are kept; the notes directory, else
+    /// `~/.config/zlyph/journal`, when unset
+    pub directory: Option<PathBuf>,
+    /// Template a new daily note starts as: a path, or the name of one in
+    /// the templates directory
+    pub template: Option<PathBuf>,
+    /// strftime format of the date heading a new daily note
+    pub heading_format: String,
+}
+
+impl Default for JournalConfig {
+    fn default() -> Self {
+        Self {
+            directory: None,
+            template: None,
+            heading_format: "%A, %B %-d, %Y".to_string(),
+        }
+    }
+}
+
+/// strftime formats of the dates and times the insert actions type
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct DateTimeConfig {
+    pub date_format: String,
+    pub time_format: String,
+    pub timestamp_format: String,
+}
+
+impl Default for DateTimeConfig {
+    fn default() -> Self {
+        Self {
+            date_format: "%Y-%m-%d".to_string(),
+            time_format: "%H:%M".to_string(),
+            timestamp_format: "%Y-%m-%d %H:%M".to_string(),
+        }
+    }
+}
+
+/// Curly quotes, dashes and ellipses typed in notes; see [`crate::typography`]
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct TypographyConfig {
+    pub enabled: bool,
+}
+
+/// Spoken announcements for screen reader users; see [`crate::accessibility`]
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct AccessibilityConfig {
+    pub enabled: bool,
+    /// Command that speaks its last argument; "say" on macOS and "spd-say"
+    /// elsewhere when unset
+    pub speak_command: Option<String>,
+    /// Arguments put before the announcement, e.g. ["-r", "300"]
+    pub speak_args: Vec<String>,
+}
+
+/// The GUI's right-click menu; see [`crate::context_menu`]
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct ContextMenuConfig {
+    /// Address searched for the selection, with `{}` standing for it
+    pub search_url: String,
+}
+
+impl Default for ContextMenuConfig {
+    fn default() -> Self {
+        Self {
+            search_url: "https://duckduckgo.com/?q={}".to_string(),
+        }
+    }
+}
+
+/// When a file counts as large; see [`crate::large_file`]
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct LargeFileConfig {
+    /// Files at least this many megabytes are opened in large-file mode
+    pub threshold_mb: u64,
+}
+
+impl Default for LargeFileConfig {
+    fn default() -> Self {
+        Self { threshold_mb: 20 }
+    }
+}
+
+impl LargeFileConfig {
+    pub fn threshold_bytes(&self) -> u64 {
+        self.threshold_mb.saturating_mul(1024 * 1024)
+    }
+}
+
+/// How structured text is laid out; see [`crate::pretty`]
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct FormatConfig {
+    /// Spaces for each level of nesting
+    pub indent: usize,
+}
+
+impl Default for FormatConfig {
+    fn default() -> Self {
+        Self { indent: 2 }
+    }
+}
+
+/// How the buffer is written out as HTML; see [`crate::export`]
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct ExportConfig {
+    pub line_numbers: bool,
+    /// Style each span itself rather than with a stylesheet
+    pub inline_css: bool,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Paper {
+    #[default]
+    A4,
+    Letter,
+}
+
+/// How the buffer is laid out on pages; see [`crate::pdf`]
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct PrintConfig {
+    pub paper: Paper,
+    pub margin_mm: f32,
+    /// In points
+    pub font_size: f32,
+    /// Print the file's name and the page number at the top of each page
+    pub header: bool,
+    pub highlight: bool,
+}
+
+impl Default for PrintConfig {
+    fn default() -> Self {
+        Self {
+            paper: Paper::A4,
+            margin_mm: 15.0,
+            font_size: 10.0,
+            header: true,
+            highlight: true,
+        }
+    }
+}
+
+/// How a picture of the selection is drawn; see [`crate::code_image`]
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct CodeImageConfig {
+    /// In pixels, as are `padding` and the window's size
+    pub font_size: f32,
+    /// Space around the window
+    pub padding: f32,
+    /// Color of the space around the window, as in `#abb8c3`, or
+    /// `transparent`
+    pub backdrop: String,
+    /// Draw a title bar with the file's name and three buttons
+    pub window_chrome: bool,
+    /// Pixels per point of a PNG, 2 for a sharp picture on high-density
+    /// screens
+    pub scale: f32,
+}
+
+impl Default for CodeImageConfig {
+    fn default() -> Self {
+        Self {
+            font_size: 14.0,
+            padding: 48.0,
+            backdrop: "#abb8c3".to_string(),
+            window_chrome: true,
+            scale: 2.0,
+        }
+    }
+}
+
+/// Speech typed at the cursor; see [`crate::dictation`]
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct DictationConfig {
+    /// Command that listens to the microphone and prints each phrase it
+    /// recognizes on a line, run by the system's shell
+    pub command: String,
+}
+
+impl Default for DictationConfig {
+    fn default() -> Self {
+        Self {
+            command: "whisper-stream".to_string(),
+        }
+    }
+}
+
+/// The terminal panel below the editor; see [`crate::terminal`]
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct TerminalConfig {
+    /// Program run in the panel; unset runs `$SHELL`, or the system's
+    /// default shell
+    pub shell: Option<String>,
+    pub args: Vec<String>,
+    /// Rows of the panel
+    pub height: u16,
+    /// Lines kept after they scroll off the top, for capturing output
+    pub scrollback: usize,
+}
+
+impl Default for TerminalConfig {
+    fn default() -> Self {
+        Self {
+            shell: None,
+            args: Vec::new(),
+            height: 12,
+            scrollback: 5000,
+        }
+    }
+}
+
+/// GUI window appearance, adjustable at runtime and written back when changed
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct WindowConfig {
+    /// Opacity of the editor background, from 0.0 (clear) to 1.0 (solid)
+    pub opacity: f32,
+    /// Blur whatever shows through a translucent background
+    pub blur: bool,
+    pub titlebar: bool,
+}
+
+impl Default for WindowConfig {
+    fn default() -> Self {
+        Self {
+            opacity: 0.75,
+            blur: true,
+            titlebar: true,
+        }
+    }
+}
+
+/// Text appearance in the GUI, applied again whenever the config file changes
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default)]
+pub struct FontConfig {
+    pub family: String,
+    /// Families used, in order, when `family` is not installed, and for
+    /// characters it has no glyphs for
+    pub fallbacks: Vec<String>,
+    /// From 100 (thin) to 900 (black); 400 is regular and 700 bold
+    pub weight: f32,
+    /// Height of a line as a multiple of the font size
+    pub line_height: f32,
+    /// Join character sequences such as `->` into one glyph, in fonts that
+    /// have them
+    pub ligatures: bool,
+}
+
+impl Default for FontConfig {
+    fn default() -> Self {
+        Self {
+            family: "Monaco".to_string(),
+            fallbacks: vec!["Menlo".to_string(), "DejaVu Sans Mono".to_string()],
+            weight: 400.0,
+            line_height: 1.5,
+            ligatures: true,
+        }
+    }
+}
+
+impl FontConfig {
+    /// The family to use: the first of `family` and its fallbacks that is
+    /// among the `installed` ones, matched regardless of case
+    pub fn pick_family<'a>(&'a self, installed: &[String]) -> Option<&'a str> {
+        std::iter::once(&self.family)
+            .chain(&self.fallbacks)
+            .find(|family| {
+                installed
+                    .iter()
+                    .any(|name| name.eq_ignore_ascii_case(family))
+            })
+            .map(String::as_str)
+    }
+}
+
+/// Target for a writing session; with neither limit set a session just counts words
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(default)]
+pub struct GoalConfig {
+    /// Words to add during the session
+    pub words: Option<usize>,
+    /// Length of a timed session
+    pub minutes: Option<u64>,
+    /// Start a session as soon as the editor opens
+    pub start_on_launch: bool,
+}
+
+impl Default for CompletionConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            min_prefix: 2,
+            max_items: 10,
+            dictionary: None,
+        }
+    }
+}
+
+impl Config {
+    /// Parse configuration from TOML text
+    pub fn from_toml(content: &str) -> io::Result<Self> {
+        toml::from_str(content).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+    }
+
+    /// Load configuration from a file; a missing file yields the defaults
+    pub fn load_from_file<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        match fs::read_to_string(path) {
+            Ok(content) => Self::from_toml(&content),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Load configuration from the default location
+    pub fn load() -> io::Result<Self> {
+        Self::load_from_file(Self::default_path())
+    }
+
+    /// Set `key` in `[section]` of the config file at `path`, keeping the rest
+    /// of the file, comments included, as it was
+    pub fn store_value_in_file<P: AsRef<Path>>(
+        path: P,
+        section: &str,
+        key: &str,
+        value: impl Into<toml_edit::Value>,
+    ) -> io::Result<()> {
+        let path = path.as_ref();
+        let content = match fs::read_to_string(path) {
+            Ok(content) => content,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => String::new(),
+            Err(e) => return Err(e),
+        };
+        let mut document: toml_edit::DocumentMut = content
+            .parse()
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+
+        let table = document
+            .entry(section)
+            .or_insert(toml_edit::table())
+            .as_table_like_mut()
+            .ok_or_else(|| {
+                io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("[{}] is not a table", section),
+                )
+            })?;
+        table.insert(key, toml_edit::value(value));
+
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(path, document.to_string())
+    }
+
+    /// Set `key` in `[section]` of the default config file
+    pub fn store_value(
+        section: &str,
+        key: &str,
+        value: impl Into<toml_edit::Value>,
+    ) -> io::Result<()> {
+        Self::store_value_in_file(Self::default_path(), section, key, value)
+    }
+
+    /// Get default config file path
+    pub fn default_path() -> PathBuf {
+        config_dir().join("config.toml")
+    }
+}
+
+/// Directory holding zlyph's config and default notes file
+pub fn config_dir() -> PathBuf {
+    let home = std::env::var("HOME")
+        .or_else(|_| std::env::var("USERPROFILE"))
+        .unwrap_or_else(|_| ".".to_string());
+    PathBuf::from(home).join(".config").join("zlyph")
+}
@@ -0,0 +1,134 @@
+//! The right-click menu: the suggestions of the diagnostic under the cursor,
+//! such as a misspelled word's corrections, then the editing items and a web
+//! search for the selection
+//!
+//! Items that do nothing here, like Cut with nothing selected, stay in the
+//! menu but cannot be chosen, as in native menus.
+
+use crate::diagnostics::{self, Diagnostic};
+use crate::state::EditorState;
+
+/// Suggestions offered at most, before the editing items
+const MAX_SUGGESTIONS: usize = 5;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum MenuItem {
+    /// Replace the diagnostic's text with this
+    Suggestion(String),
+    Cut,
+    Copy,
+    Paste,
+    SelectAll,
+    SearchWeb,
+}
+
+impl MenuItem {
+    pub fn label(&self) -> String {
+        match self {
+            MenuItem::Suggestion(text) => text.clone(),
+            MenuItem::Cut => "Cut".to_string(),
+            MenuItem::Copy => "Copy".to_string(),
+            MenuItem::Paste => "Paste".to_string(),
+            MenuItem::SelectAll => "Select All".to_string(),
+            MenuItem::SearchWeb => "Search the Web for Selection".to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ContextMenu {
+    pub items: Vec<MenuItem>,
+    /// Item highlighted for Enter, once the arrow keys have moved
+    pub selected: Option<usize>,
+    has_selection: bool,
+}
+
+impl ContextMenu {
+    /// The menu at the cursor, which the frontend has moved to where the
+    /// click was unless it fell inside the selection
+    pub fn new(state: &EditorState, diagnostics: &[Diagnostic]) -> Self {
+        let mut items: Vec<MenuItem> = diagnostics::fixable_at(diagnostics, state.cursor)
+            .map(|diagnostic| {
+                diagnostic
+                    .replacements
+                    .iter()
+                    .take(MAX_SUGGESTIONS)
+                    .cloned()
+                    .map(MenuItem::Suggestion)
+                    .collect()
+            })
+            .unwrap_or_default();
+        items.extend([
+            MenuItem::Cut,
+            MenuItem::Copy,
+            MenuItem::Paste,
+            MenuItem::SelectAll,
+            MenuItem::SearchWeb,
+        ]);
+        Self {
+            items,
+            selected: None,
+            has_selection: state
+                .selection_anchor
+                .is_some_and(|anchor| anchor != state.cursor),
+        }
+    }
+
+    pub fn is_enabled(&self, item: &MenuItem) -> bool {
+        match item {
+            MenuItem::Cut | MenuItem::Copy | MenuItem::SearchWeb => self.has_selection,
+            _ => true,
+        }
+    }
+
+    /// How many suggestions lead the menu, to set them apart from the rest
+    pub fn suggestions(&self) -> usize {
+        self.items
+            .iter()
+            .take_while(|item| matches!(item, MenuItem::Suggestion(_)))
+            .count()
+    }
+
+    pub fn select_next(&mut self) {
+        self.step(1);
+    }
+
+    pub fn select_previous(&mut self) {
+        self.step(self.items.len() - 1);
+    }
+
+    pub fn selected_item(&self) -> Option<&MenuItem> {
+        self.selected.map(|index| &self.items[index])
+    }
+
+    /// Move the highlight by `offset` around the menu, over disabled items
+    fn step(&mut self, offset: usize) {
+        let len = self.items.len();
+        let mut index = self
+            .selected
+            .unwrap_or(if offset == 1 { len - 1 } else { 0 });
+        for _ in 0..len {
+            index = (index + offset) % len;
+            if self.is_enabled(&self.items[index]) {
+                self.selected = Some(index);
+                return;
+            }
+        }
+    }
+}
+
+/// The address searched for `query`: `template` with `{}` replaced by the
+/// percent-encoded query
+pub fn search_url(template: &str, query: &str) -> String {
+    let mut encoded = String::new();
+    for byte in query.trim().bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => {
+                encoded.push(byte as char)
+            }
+            b' ' => encoded.push('+'),
+            _ => encoded.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    template.replace("{}", &encoded)
+}
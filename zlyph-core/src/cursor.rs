@@ -0,0 +1,26 @@
+//! Cursor blinking shared by the frontends
+
+use crate::config::CursorConfig;
+use std::time::Duration;
+
+/// Whether a blinking cursor is drawn `since_input` after the last keystroke or
+/// cursor move. The cursor stays solid for the first interval, so it never
+/// disappears while typing.
+pub fn is_visible(config: &CursorConfig, since_input: Duration) -> bool {
+    if !config.blink || config.blink_interval_ms == 0 {
+        return true;
+    }
+    let phase = since_input.as_millis() / config.blink_interval_ms as u128;
+    phase.is_multiple_of(2)
+}
+
+/// How long after `since_input` a blinking cursor next shows or hides, so
+/// a frontend can sleep until then; `None` when it doesn't blink
+pub fn next_blink(config: &CursorConfig, since_input: Duration) -> Option<Duration> {
+    if !config.blink || config.blink_interval_ms == 0 {
+        return None;
+    }
+    let interval = config.blink_interval_ms as u128;
+    let left = interval - since_input.as_millis() % interval;
+    Some(Duration::from_millis(left as u64))
+}
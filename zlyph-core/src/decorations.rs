@@ -0,0 +1,213 @@
+//! Decorations: virtual text, gutter icons and highlighted ranges that
+//! features and plugins lay over the text without changing it
+//!
+//! Like diagnostics, each producer publishes its decorations under its own
+//! source name with [`EditorEngine::set_decorations`], replacing whatever
+//! it published before. Ranges are byte offsets into the text and move with
+//! the edits around them, so a producer only has to publish again when what
+//! it shows changes. A decoration whose text is deleted goes with it.
+//!
+//! [`EditorEngine::set_decorations`]: crate::EditorEngine::set_decorations
+
+use serde::{Deserialize, Serialize};
+use std::ops::Range;
+
+/// The color a decoration is drawn in, which each frontend maps onto its
+/// own theme
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Tone {
+    #[default]
+    Muted,
+    Accent,
+    Info,
+    Warning,
+    Error,
+}
+
+/// Where virtual text goes on its line
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Placement {
+    /// Before the character at the start of the range, pushing the rest of
+    /// the line along, like a type hint
+    #[default]
+    Inline,
+    /// After the end of the range's first line, like blame or a diagnostic's
+    /// message
+    EndOfLine,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Kind {
+    /// Text shown that is not part of the buffer; the cursor steps over it
+    VirtualText {
+        text: String,
+        #[serde(default)]
+        placement: Placement,
+    },
+    /// A symbol, one character wide, in the gutter beside the range's
+    /// first line
+    Gutter(String),
+    /// The range's text drawn over a tint
+    Highlight,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Decoration {
+    /// Byte offsets into the text; virtual text and gutter icons may use an
+    /// empty range to mark a point
+    pub range: Range<usize>,
+    pub kind: Kind,
+    #[serde(default)]
+    pub tone: Tone,
+    /// Producer that published the decoration, e.g. "blame"
+    #[serde(default)]
+    pub source: String,
+}
+
+impl Decoration {
+    pub fn virtual_text(at: usize, text: impl Into<String>, placement: Placement) -> Self {
+        Self {
+            range: at..at,
+            kind: Kind::VirtualText {
+                text: text.into(),
+                placement,
+            },
+            tone: Tone::Muted,
+            source: String::new(),
+        }
+    }
+
+    pub fn gutter(at: usize, symbol: impl Into<String>) -> Self {
+        Self {
+            range: at..at,
+            kind: Kind::Gutter(symbol.into()),
+            tone: Tone::Muted,
+            source: String::new(),
+        }
+    }
+
+    pub fn highlight(range: Range<usize>) -> Self {
+        Self {
+            range,
+            kind: Kind::Highlight,
+            tone: Tone::Muted,
+            source: String::new(),
+        }
+    }
+
+    pub fn with_tone(mut self, tone: Tone) -> Self {
+        self.tone = tone;
+        self
+    }
+}
+
+/// A decoration as it falls on one line, for frontends to draw
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LineDecoration<'a> {
+    /// Virtual text before byte `column` of the line
+    Inline {
+        column: usize,
+        text: &'a str,
+        tone: Tone,
+    },
+    /// Virtual text after the line's end
+    EndOfLine { text: &'a str, tone: Tone },
+    /// A tinted byte range of the line
+    Highlight { columns: Range<usize>, tone: Tone },
+}
+
+/// What decorations show on the line from byte `start` to `end` of the
+/// text: inline text by column, then highlights, then end-of-line text in
+/// the order published
+pub(crate) fn on_line(
+    decorations: &[Decoration],
+    start: usize,
+    end: usize,
+) -> Vec<LineDecoration<'_>> {
+    let mut inline = Vec::new();
+    let mut highlights = Vec::new();
+    let mut end_of_line = Vec::new();
+    for decoration in decorations {
+        let range = &decoration.range;
+        let tone = decoration.tone;
+        match &decoration.kind {
+            Kind::VirtualText { text, placement } if (start..=end).contains(&range.start) => {
+                match placement {
+                    Placement::Inline => inline.push(LineDecoration::Inline {
+                        column: range.start - start,
+                        text,
+                        tone,
+                    }),
+                    Placement::EndOfLine => {
+                        end_of_line.push(LineDecoration::EndOfLine { text, tone })
+                    }
+                }
+            }
+            Kind::Highlight if range.start < end && start < range.end => {
+                highlights.push(LineDecoration::Highlight {
+                    columns: range.start.max(start) - start..range.end.min(end) - start,
+                    tone,
+                })
+            }
+            _ => {}
+        }
+    }
+    inline.sort_by_key(|decoration| match decoration {
+        LineDecoration::Inline { column, .. } => *column,
+        _ => 0,
+    });
+    inline.extend(highlights);
+    inline.extend(end_of_line);
+    inline
+}
+
+/// The gutter icon for the line from byte `start` to `end`: the first one
+/// published, with its tone
+pub(crate) fn gutter(decorations: &[Decoration], start: usize, end: usize) -> Option<(&str, Tone)> {
+    decorations
+        .iter()
+        .find_map(|decoration| match &decoration.kind {
+            Kind::Gutter(symbol) if (start..=end).contains(&decoration.range.start) => {
+                Some((symbol.as_str(), decoration.tone))
+            }
+            _ => None,
+        })
+}
+
+/// Move `decorations` to where they are after `start..old_end` was replaced
+/// by text ending at `new_end`. A range keeps out text inserted at its
+/// edges and is dropped once its text is all deleted; a point moves ahead
+/// of text inserted at it and is dropped when the text around it goes.
+pub(crate) fn shift(
+    decorations: &mut Vec<Decoration>,
+    start: usize,
+    old_end: usize,
+    new_end: usize,
+) {
+    let map = |offset: usize, inclusive: bool| {
+        if offset < start || (inclusive && offset == start) {
+            offset
+        } else if offset >= old_end {
+            offset - old_end + new_end
+        } else {
+            new_end
+        }
+    };
+    decorations.retain_mut(|decoration| {
+        let Range {
+            start: from,
+            end: to,
+        } = decoration.range;
+        if from == to {
+            if start < from && from < old_end {
+                return false;
+            }
+            let at = map(from, false);
+            decoration.range = at..at;
+            true
+        } else {
+            decoration.range = map(from, false)..map(to, true);
+            !decoration.range.is_empty()
+        }
+    });
+}
@@ -0,0 +1,207 @@
+//! Go to definition and find references without a language server
+//!
+//! Definitions come from a ctags file (`tags` or `.tags`, as `ctags -R`
+//! writes it) at the workspace root when there is one, and otherwise from
+//! the outline [`symbols::outline`] finds, run over every file in the
+//! workspace. References are the name's whole-word occurrences. Both are
+//! searched by [`ProjectSearch`](crate::project_search::ProjectSearch), so
+//! frontends list them in the find-in-files panel.
+
+use crate::project_search::{content_hash, FileMatches, ProjectMatch};
+use crate::symbols::{self, SymbolKind};
+use crate::text_objects::is_word_char;
+use crate::BufferPosition;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Names of the ctags file looked for at the workspace root
+pub const TAG_FILES: [&str; 2] = ["tags", ".tags"];
+
+/// The ctags file at `root`, if there is one
+pub fn tags_file(root: &Path) -> Option<PathBuf> {
+    TAG_FILES
+        .iter()
+        .map(|name| root.join(name))
+        .find(|path| path.is_file())
+}
+
+/// Where `name` is defined in `lines`, the text of the file at `path`, as
+/// the outline sees it
+pub fn find_definitions(
+    path: &Path,
+    lines: &[String],
+    name: &str,
+) -> Vec<(BufferPosition, BufferPosition)> {
+    symbols::outline(lines, Some(path))
+        .into_iter()
+        .filter(|symbol| symbol.kind != SymbolKind::Heading && symbol.name == name)
+        .map(|symbol| {
+            (
+                BufferPosition::new(symbol.row, symbol.column),
+                BufferPosition::new(symbol.row, symbol.column + name.len()),
+            )
+        })
+        .collect()
+}
+
+/// Every occurrence of `name` in `lines` as a whole word, other than in a
+/// ctags file
+pub fn find_references(
+    path: &Path,
+    lines: &[String],
+    name: &str,
+) -> Vec<(BufferPosition, BufferPosition)> {
+    let tags = path
+        .file_name()
+        .is_some_and(|file| TAG_FILES.iter().any(|tags| file == *tags));
+    if name.is_empty() || tags {
+        return Vec::new();
+    }
+    let mut found = Vec::new();
+    for (row, line) in lines.iter().enumerate() {
+        for (column, _) in line.match_indices(name) {
+            let end = column + name.len();
+            let before = line[..column].chars().next_back();
+            let after = line[end..].chars().next();
+            if !before.is_some_and(is_word_char) && !after.is_some_and(is_word_char) {
+                found.push((
+                    BufferPosition::new(row, column),
+                    BufferPosition::new(row, end),
+                ));
+            }
+        }
+    }
+    found
+}
+
+/// Where a ctags entry says its name is defined
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Address {
+    /// Row, counted from zero
+    Line(usize),
+    /// The whole text of the line, or text in it when `exact` is false
+    Pattern { text: String, exact: bool },
+}
+
+/// One entry of a ctags file
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Tag {
+    pub name: String,
+    pub path: PathBuf,
+    pub address: Address,
+}
+
+/// The entries of a ctags file, with paths taken from `dir`, the file's
+/// directory; `!_TAG_` headers and lines not in the format are skipped
+pub fn parse_tags(content: &str, dir: &Path) -> Vec<Tag> {
+    content
+        .lines()
+        .filter(|line| !line.starts_with("!_TAG_"))
+        .filter_map(|line| {
+            let mut fields = line.splitn(3, '\t');
+            let name = fields.next()?;
+            let file = fields.next()?;
+            let rest = fields.next()?;
+            // Extension fields such as the kind follow `;"`
+            let address = rest.split_once(";\"").map_or(rest, |(address, _)| address);
+            Some(Tag {
+                name: name.to_string(),
+                path: dir.join(file),
+                address: parse_address(address.trim_end())?,
+            })
+        })
+        .collect()
+}
+
+fn parse_address(address: &str) -> Option<Address> {
+    if let Ok(line) = address.parse::<usize>() {
+        return Some(Address::Line(line.saturating_sub(1)));
+    }
+    let delimiter = address.chars().next().filter(|c| matches!(c, '/' | '?'))?;
+    let pattern = address[1..].strip_suffix(delimiter)?;
+    let (pattern, start) = match pattern.strip_prefix('^') {
+        Some(pattern) => (pattern, true),
+        None => (pattern, false),
+    };
+    let (pattern, end) = match pattern.strip_suffix('$') {
+        Some(pattern) if !pattern.ends_with('\\') => (pattern, true),
+        _ => (pattern, false),
+    };
+    let mut text = String::with_capacity(pattern.len());
+    let mut chars = pattern.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => text.extend(chars.next()),
+            c => text.push(c),
+        }
+    }
+    Some(Address::Pattern {
+        text,
+        exact: start && end,
+    })
+}
+
+impl Address {
+    /// The row of `lines` the address points at
+    pub fn resolve(&self, lines: &[String]) -> Option<usize> {
+        match self {
+            Address::Line(row) => (*row < lines.len()).then_some(*row),
+            Address::Pattern { text, exact: true } => lines.iter().position(|line| line == text),
+            Address::Pattern { text, .. } => lines.iter().position(|line| line.contains(text)),
+        }
+    }
+}
+
+/// The definitions of `name` the ctags file at `tags` lists, file by file
+pub fn tag_definitions(tags: &Path, name: &str) -> Vec<FileMatches> {
+    let Ok(content) = fs::read_to_string(tags) else {
+        return Vec::new();
+    };
+    let dir = tags.parent().unwrap_or(Path::new("."));
+    let mut by_file: BTreeMap<PathBuf, Vec<Address>> = BTreeMap::new();
+    for tag in parse_tags(&content, dir) {
+        if tag.name == name {
+            by_file.entry(tag.path).or_default().push(tag.address);
+        }
+    }
+
+    let mut found = Vec::new();
+    for (path, addresses) in by_file {
+        let Ok(content) = fs::read_to_string(&path) else {
+            continue;
+        };
+        let lines: Vec<String> = content.lines().map(String::from).collect();
+        let mut rows: Vec<usize> = addresses
+            .iter()
+            .filter_map(|address| address.resolve(&lines))
+            .collect();
+        rows.sort_unstable();
+        rows.dedup();
+        let matches: Vec<ProjectMatch> = rows
+            .into_iter()
+            .map(|row| {
+                let line = &lines[row];
+                // The name on the line, or the line's start if it isn't there
+                let (start, end) = find_references(&path, std::slice::from_ref(line), name)
+                    .first()
+                    .map_or((0, 0), |(start, end)| (start.column, end.column));
+                ProjectMatch {
+                    start: BufferPosition::new(row, start),
+                    end: BufferPosition::new(row, end),
+                    line: line.clone(),
+                }
+            })
+            .collect();
+        if matches.is_empty() {
+            continue;
+        }
+        found.push(FileMatches {
+            modified: fs::metadata(&path).and_then(|m| m.modified()).ok(),
+            content_hash: content_hash(&content),
+            path,
+            matches,
+        });
+    }
+    found
+}
@@ -0,0 +1,96 @@
+//! Diagnostics (errors, warnings, hints) attached to buffer ranges
+//!
+//! Any producer — a language server, a linter, a spellchecker — publishes its
+//! diagnostics under its own source name, replacing whatever it published before.
+
+use crate::BufferPosition;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Severity {
+    Error,
+    Warning,
+    Information,
+    Hint,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Diagnostic {
+    pub start: BufferPosition,
+    pub end: BufferPosition,
+    pub severity: Severity,
+    pub message: String,
+    /// Producer that published the diagnostic, e.g. "lsp"
+    pub source: String,
+    /// Suggested texts to replace the range with, offered by QuickFix
+    pub replacements: Vec<String>,
+}
+
+impl Severity {
+    pub fn label(&self) -> &'static str {
+        match self {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+            Severity::Information => "info",
+            Severity::Hint => "hint",
+        }
+    }
+}
+
+impl Diagnostic {
+    pub fn contains(&self, pos: BufferPosition) -> bool {
+        self.start <= pos && pos <= self.end
+    }
+
+    /// Byte range of the diagnostic on `row`, or None if it does not touch the row.
+    /// Multi-line diagnostics cover the rest of their first line and the start of their last.
+    pub fn columns_on_row(&self, row: usize, line_len: usize) -> Option<(usize, usize)> {
+        if row < self.start.row || row > self.end.row {
+            return None;
+        }
+        let start = if row == self.start.row {
+            self.start.column.min(line_len)
+        } else {
+            0
+        };
+        let end = if row == self.end.row {
+            self.end.column.min(line_len)
+        } else {
+            line_len
+        };
+        Some((start, end.max(start)))
+    }
+}
+
+/// The single-line diagnostic at `pos` that suggests replacements, such as
+/// a misspelled word
+pub fn fixable_at(diagnostics: &[Diagnostic], pos: BufferPosition) -> Option<&Diagnostic> {
+    diagnostics
+        .iter()
+        .find(|d| d.contains(pos) && d.start.row == d.end.row && !d.replacements.is_empty())
+}
+
+/// First diagnostic starting after `pos`, wrapping to the first one
+pub fn next_after(diagnostics: &[Diagnostic], pos: BufferPosition) -> Option<&Diagnostic> {
+    diagnostics
+        .iter()
+        .find(|d| d.start > pos)
+        .or_else(|| diagnostics.first())
+}
+
+/// Last diagnostic starting before `pos`, wrapping to the last one
+pub fn prev_before(diagnostics: &[Diagnostic], pos: BufferPosition) -> Option<&Diagnostic> {
+    diagnostics
+        .iter()
+        .rev()
+        .find(|d| d.start < pos)
+        .or_else(|| diagnostics.last())
+}
+
+/// Most severe diagnostic touching `row`
+pub fn worst_on_row(diagnostics: &[Diagnostic], row: usize) -> Option<Severity> {
+    diagnostics
+        .iter()
+        .filter(|d| d.start.row <= row && row <= d.end.row)
+        .map(|d| d.severity)
+        .min()
+}
@@ -0,0 +1,161 @@
+//! Dictation: speech typed at the cursor as it's recognized
+//!
+//! An external command does the listening, by default whisper.cpp's
+//! `whisper-stream`, which records from the microphone and prints each
+//! phrase it recognizes. Any command that prints recognized text a line at
+//! a time works. Lines are read as they come; the terminal control codes
+//! and timestamps `whisper-stream` prints are left out, as are versions of
+//! a line redrawn while its phrase was still being recognized and markers
+//! such as `[BLANK_AUDIO]` or `(music)` for what wasn't speech.
+//!
+//! [`EditorEngine::poll_dictation`](crate::EditorEngine::poll_dictation)
+//! types what has been recognized; frontends call it from their event loop
+//! while [`EditorEngine::dictating`](crate::EditorEngine::dictating).
+
+use crate::shell;
+use std::io::{self, BufRead, BufReader};
+use std::process::{Child, Stdio};
+use std::sync::mpsc::{self, Receiver};
+use std::thread;
+use std::time::Duration;
+
+/// How long to wait for the last of a command's output once it has exited
+const FLUSH_WAIT: Duration = Duration::from_millis(100);
+
+/// A dictation command running, with the phrases it has recognized
+pub struct Dictation {
+    command: String,
+    child: Child,
+    phrases: Receiver<String>,
+    /// The last line the command printed to standard error, once it has
+    /// closed it
+    errors: Receiver<String>,
+}
+
+impl Dictation {
+    /// Start listening with `command`, run by the system's shell
+    pub fn start(command: &str) -> io::Result<Self> {
+        let mut child = shell::process(command)
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()?;
+
+        let (phrase_sender, phrases) = mpsc::channel();
+        if let Some(pipe) = child.stdout.take() {
+            thread::spawn(move || {
+                let mut reader = BufReader::new(pipe);
+                let mut line = Vec::new();
+                while reader.read_until(b'\n', &mut line).is_ok_and(|len| len > 0) {
+                    let text = phrase(&String::from_utf8_lossy(&line));
+                    if text.is_some_and(|text| phrase_sender.send(text).is_err()) {
+                        break;
+                    }
+                    line.clear();
+                }
+            });
+        }
+        // Recognizers log as they go, so only the last line is kept
+        let (error_sender, errors) = mpsc::channel();
+        if let Some(pipe) = child.stderr.take() {
+            thread::spawn(move || {
+                let last = BufReader::new(pipe)
+                    .lines()
+                    .map_while(Result::ok)
+                    .filter(|line| !line.trim().is_empty())
+                    .last();
+                let _ = error_sender.send(last.unwrap_or_default());
+            });
+        }
+
+        Ok(Self {
+            command: command.to_string(),
+            child,
+            phrases,
+            errors,
+        })
+    }
+
+    pub fn command(&self) -> &str {
+        &self.command
+    }
+
+    /// The phrases recognized since the last poll, and how the command
+    /// ended once it has: an error with the last line it printed to
+    /// standard error if it failed
+    pub fn poll(&mut self) -> (Vec<String>, Option<io::Result<()>>) {
+        let phrases: Vec<String> = self.phrases.try_iter().collect();
+        let status = match self.child.try_wait() {
+            Ok(Some(status)) => status,
+            Ok(None) => return (phrases, None),
+            Err(e) => return (phrases, Some(Err(e))),
+        };
+        // Whatever it printed before exiting may still be on its way
+        let mut phrases = phrases;
+        while let Ok(phrase) = self.phrases.recv_timeout(FLUSH_WAIT) {
+            phrases.push(phrase);
+        }
+        if status.success() {
+            return (phrases, Some(Ok(())));
+        }
+        let reason = self
+            .errors
+            .recv_timeout(FLUSH_WAIT)
+            .ok()
+            .filter(|line| !line.is_empty())
+            .unwrap_or_else(|| status.to_string());
+        let error = io::Error::other(format!("{} failed: {}", self.command, reason.trim()));
+        (phrases, Some(Err(error)))
+    }
+
+    /// Stop listening
+    pub fn stop(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+impl Drop for Dictation {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+/// The text recognized in a line a dictation command printed, if it's
+/// speech
+pub fn phrase(line: &str) -> Option<String> {
+    let line = without_escapes(line);
+    // A line redrawn in place ends with its last version
+    let line = line.trim_end_matches(['\r', '\n']);
+    let line = line.rsplit('\r').next().unwrap_or_default().trim();
+    if line.starts_with("###") {
+        return None;
+    }
+    let text = match line.strip_prefix('[').and_then(|rest| rest.split_once(']')) {
+        Some((stamp, text)) if stamp.contains("-->") => text.trim(),
+        _ => line,
+    };
+    let marker = [('[', ']'), ('(', ')'), ('*', '*')]
+        .iter()
+        .any(|&(open, close)| text.len() > 1 && text.starts_with(open) && text.ends_with(close));
+    (!text.is_empty() && !marker).then(|| text.to_string())
+}
+
+/// `line` without the terminal control sequences in it
+fn without_escapes(line: &str) -> String {
+    let mut text = String::with_capacity(line.len());
+    let mut chars = line.chars();
+    while let Some(c) = chars.next() {
+        if c != '\x1b' {
+            text.push(c);
+        } else if chars.next() == Some('[') {
+            // Parameters, then a final byte from @ to ~
+            for c in chars.by_ref() {
+                if ('@'..='~').contains(&c) {
+                    break;
+                }
+            }
+        }
+    }
+    text
+}
@@ -0,0 +1,220 @@
+//! Line diffs between two versions of a text, as runs of changed lines
+//!
+//! Uses Myers' algorithm on the lines left after trimming the common prefix
+//! and suffix. Past [`MAX_EDIT_DISTANCE`] the middle is reported as one hunk
+//! rather than spending time and memory on a minimal diff.
+
+/// Most inserted plus deleted lines searched for a minimal diff
+pub const MAX_EDIT_DISTANCE: usize = 1000;
+
+/// Byte range that differs between `old` and `new`: the end of their common
+/// prefix, and where the common suffix starts in each
+pub(crate) fn changed_span(old: &str, new: &str) -> (usize, usize, usize) {
+    let prefix: usize = old
+        .chars()
+        .zip(new.chars())
+        .take_while(|(a, b)| a == b)
+        .map(|(c, _)| c.len_utf8())
+        .sum();
+    let suffix: usize = old[prefix..]
+        .chars()
+        .rev()
+        .zip(new[prefix..].chars().rev())
+        .take_while(|(a, b)| a == b)
+        .map(|(c, _)| c.len_utf8())
+        .sum();
+    (prefix, old.len() - suffix, new.len() - suffix)
+}
+
+/// Rows an edit changed, told apart from `old_lengths`, the lengths of the
+/// rows before it, as `(row, old_rows, new_rows)`: rows `row..row +
+/// old_rows` became `row..row + new_rows`, last first. Byte offsets only
+/// depend on row lengths, so a row edited to the same length counts as
+/// unchanged. With the row count unchanged each changed row is its own
+/// entry; otherwise one entry spans from the first row that differs to the
+/// last, and takes at least one row on each side.
+pub(crate) fn changed_rows(old_lengths: &[usize], new: &[String]) -> Vec<(usize, usize, usize)> {
+    if old_lengths.len() == new.len() {
+        return (0..new.len())
+            .rev()
+            .filter(|&row| old_lengths[row] != new[row].len())
+            .map(|row| (row, 1, 1))
+            .collect();
+    }
+    let shorter = old_lengths.len().min(new.len());
+    let mut prefix = old_lengths
+        .iter()
+        .zip(new)
+        .take_while(|(length, line)| **length == line.len())
+        .count();
+    let mut suffix = old_lengths[prefix..]
+        .iter()
+        .rev()
+        .zip(new[prefix..].iter().rev())
+        .take_while(|(length, line)| **length == line.len())
+        .count();
+    // Whole rows inserted or removed take a neighbour along, so the rows
+    // on both sides join into text with the same newlines around it
+    if prefix + suffix == shorter {
+        if prefix > 0 {
+            prefix -= 1;
+        } else {
+            suffix -= 1;
+        }
+    }
+    vec![(
+        prefix,
+        old_lengths.len() - prefix - suffix,
+        new.len() - prefix - suffix,
+    )]
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChangeKind {
+    Added,
+    Modified,
+    Removed,
+}
+
+/// Lines `old_start..old_start + old_len` of the old text replaced by lines
+/// `new_start..new_start + new_len` of the new one (0-based)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Hunk {
+    pub old_start: usize,
+    pub old_len: usize,
+    pub new_start: usize,
+    pub new_len: usize,
+}
+
+impl Hunk {
+    pub fn kind(&self) -> ChangeKind {
+        if self.old_len == 0 {
+            ChangeKind::Added
+        } else if self.new_len == 0 {
+            ChangeKind::Removed
+        } else {
+            ChangeKind::Modified
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Op {
+    Equal,
+    Delete,
+    Insert,
+}
+
+/// Hunks turning `old` into `new`, in order
+pub fn diff_lines<T: PartialEq>(old: &[T], new: &[T]) -> Vec<Hunk> {
+    let prefix = old.iter().zip(new).take_while(|(a, b)| a == b).count();
+    let suffix = old[prefix..]
+        .iter()
+        .rev()
+        .zip(new[prefix..].iter().rev())
+        .take_while(|(a, b)| a == b)
+        .count();
+    let a = &old[prefix..old.len() - suffix];
+    let b = &new[prefix..new.len() - suffix];
+    if a.is_empty() && b.is_empty() {
+        return Vec::new();
+    }
+
+    let Some(ops) = shortest_edit(a, b) else {
+        return vec![Hunk {
+            old_start: prefix,
+            old_len: a.len(),
+            new_start: prefix,
+            new_len: b.len(),
+        }];
+    };
+
+    let mut hunks = Vec::new();
+    let mut current: Option<Hunk> = None;
+    let (mut old_row, mut new_row) = (prefix, prefix);
+    for op in ops {
+        if op == Op::Equal {
+            hunks.extend(current.take());
+            old_row += 1;
+            new_row += 1;
+            continue;
+        }
+        let hunk = current.get_or_insert(Hunk {
+            old_start: old_row,
+            old_len: 0,
+            new_start: new_row,
+            new_len: 0,
+        });
+        if op == Op::Delete {
+            hunk.old_len += 1;
+            old_row += 1;
+        } else {
+            hunk.new_len += 1;
+            new_row += 1;
+        }
+    }
+    hunks.extend(current);
+    hunks
+}
+
+/// Myers' greedy search for the shortest edit script, keeping the frontier
+/// of each step to walk the path back; None past [`MAX_EDIT_DISTANCE`]
+fn shortest_edit<T: PartialEq>(a: &[T], b: &[T]) -> Option<Vec<Op>> {
+    let (n, m) = (a.len() as isize, b.len() as isize);
+    let limit = (a.len() + b.len()).min(MAX_EDIT_DISTANCE) as isize;
+    let offset = limit + 1;
+    let index = |k: isize| (k + offset) as usize;
+    // Furthest x reached on each diagonal k = x - y
+    let mut v = vec![0isize; 2 * limit as usize + 3];
+    // Frontier after each step d, covering diagonals -d..=d
+    let mut trace: Vec<Vec<isize>> = Vec::new();
+
+    for d in 0..=limit {
+        for k in (-d..=d).step_by(2) {
+            let mut x = if k == -d || (k != d && v[index(k - 1)] < v[index(k + 1)]) {
+                v[index(k + 1)]
+            } else {
+                v[index(k - 1)] + 1
+            };
+            let mut y = x - k;
+            while x < n && y < m && a[x as usize] == b[y as usize] {
+                x += 1;
+                y += 1;
+            }
+            v[index(k)] = x;
+            if x >= n && y >= m {
+                trace.push(v[index(-d)..=index(d)].to_vec());
+                return Some(backtrack(&trace, n, m));
+            }
+        }
+        trace.push(v[index(-d)..=index(d)].to_vec());
+    }
+    None
+}
+
+fn backtrack(trace: &[Vec<isize>], n: isize, m: isize) -> Vec<Op> {
+    let frontier = |d: isize, k: isize| trace[d as usize][(k + d) as usize];
+    let mut ops = Vec::new();
+    let (mut x, mut y) = (n, m);
+    for d in (1..trace.len() as isize).rev() {
+        let k = x - y;
+        let prev_k = if k == -d || (k != d && frontier(d - 1, k - 1) < frontier(d - 1, k + 1)) {
+            k + 1
+        } else {
+            k - 1
+        };
+        let prev_x = frontier(d - 1, prev_k);
+        let prev_y = prev_x - prev_k;
+        while x > prev_x && y > prev_y {
+            ops.push(Op::Equal);
+            x -= 1;
+            y -= 1;
+        }
+        ops.push(if x == prev_x { Op::Insert } else { Op::Delete });
+        x = prev_x;
+        y = prev_y;
+    }
+    ops.extend((0..x).map(|_| Op::Equal));
+    ops.reverse();
+    ops
+}
@@ -0,0 +1,251 @@
+//! Comparing the buffer ("mine") with another version of it ("theirs"): the
+//! saved file, another file or a git revision
+//!
+//! The view walks the hunks between the two and resolves them one at a time:
+//! accepting theirs copies their lines into the buffer, accepting mine keeps
+//! the buffer's lines. Either way the hunk drops out of the view.
+
+use crate::diff::{self, Hunk};
+use crate::git;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// Unchanged lines kept around each hunk; longer runs are folded
+pub const CONTEXT_LINES: usize = 3;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DiffSource {
+    /// The buffer's file as last saved
+    Saved,
+    File(PathBuf),
+    /// A git revision of the buffer's file, e.g. `HEAD`
+    Revision(String),
+}
+
+impl DiffSource {
+    /// A file if `input` names one that exists, otherwise a git revision;
+    /// empty input means the saved file
+    pub fn parse(input: &str, base_dir: &Path) -> Self {
+        let input = input.trim();
+        if input.is_empty() {
+            return DiffSource::Saved;
+        }
+        let path = base_dir.join(input);
+        if path.is_file() {
+            DiffSource::File(path)
+        } else {
+            DiffSource::Revision(input.to_string())
+        }
+    }
+}
+
+/// One row of the view; row numbers are 0-based lines of either side
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DiffRow {
+    Same {
+        theirs: usize,
+        mine: usize,
+    },
+    /// A line of hunk `hunk`. In a unified diff one side is always None;
+    /// side by side, lines of both sides share a row.
+    Changed {
+        hunk: usize,
+        theirs: Option<usize>,
+        mine: Option<usize>,
+    },
+    /// This many unchanged lines left out
+    Fold(usize),
+}
+
+#[derive(Debug, Clone)]
+pub struct DiffView {
+    /// What the buffer is compared with, e.g. "HEAD" or a file name
+    pub title: String,
+    theirs: Vec<String>,
+    hunks: Vec<Hunk>,
+    /// Index of the current hunk
+    pub selected: usize,
+}
+
+impl DiffView {
+    /// Compare the buffer of `path` with `source`
+    pub fn open(source: &DiffSource, path: &Path, mine: &[String]) -> io::Result<Self> {
+        let (title, text) = match source {
+            DiffSource::Saved => ("Saved file".to_string(), fs::read_to_string(path)?),
+            DiffSource::File(other) => (other.display().to_string(), fs::read_to_string(other)?),
+            DiffSource::Revision(revision) => {
+                let text = git::show(path, revision)?.ok_or_else(|| {
+                    io::Error::new(
+                        io::ErrorKind::NotFound,
+                        format!("{} is not in git at {}", path.display(), revision),
+                    )
+                })?;
+                (revision.clone(), text)
+            }
+        };
+        Ok(Self::new(
+            title,
+            text.lines().map(String::from).collect(),
+            mine,
+        ))
+    }
+
+    pub fn new(title: String, theirs: Vec<String>, mine: &[String]) -> Self {
+        let mut view = Self {
+            title,
+            theirs,
+            hunks: Vec::new(),
+            selected: 0,
+        };
+        view.refresh(mine);
+        view
+    }
+
+    /// Recompute the hunks after the buffer changed
+    pub fn refresh(&mut self, mine: &[String]) {
+        self.hunks = diff::diff_lines(&self.theirs, mine);
+        self.selected = self.selected.min(self.hunks.len().saturating_sub(1));
+    }
+
+    pub fn hunks(&self) -> &[Hunk] {
+        &self.hunks
+    }
+
+    pub fn theirs(&self) -> &[String] {
+        &self.theirs
+    }
+
+    pub fn selected_hunk(&self) -> Option<&Hunk> {
+        self.hunks.get(self.selected)
+    }
+
+    /// Move to the next hunk, wrapping around
+    pub fn next_hunk(&mut self) {
+        if !self.hunks.is_empty() {
+            self.selected = (self.selected + 1) % self.hunks.len();
+        }
+    }
+
+    pub fn prev_hunk(&mut self) {
+        if !self.hunks.is_empty() {
+            self.selected = (self.selected + self.hunks.len() - 1) % self.hunks.len();
+        }
+    }
+
+    /// The buffer with the current hunk replaced by their lines, and the row
+    /// where the change starts; None when there are no differences
+    pub fn accept_theirs(&self, mine: &[String]) -> Option<(Vec<String>, usize)> {
+        let hunk = self.selected_hunk()?;
+        let mut lines = mine.to_vec();
+        lines.splice(
+            hunk.new_start..hunk.new_start + hunk.new_len,
+            self.theirs[hunk.old_start..hunk.old_start + hunk.old_len]
+                .iter()
+                .cloned(),
+        );
+        Some((lines, hunk.new_start))
+    }
+
+    /// Keep the buffer's lines for the current hunk, dropping it from the view
+    pub fn accept_mine(&mut self, mine: &[String]) {
+        let Some(hunk) = self.selected_hunk().copied() else {
+            return;
+        };
+        self.theirs.splice(
+            hunk.old_start..hunk.old_start + hunk.old_len,
+            mine[hunk.new_start..hunk.new_start + hunk.new_len]
+                .iter()
+                .cloned(),
+        );
+        self.refresh(mine);
+    }
+
+    /// Rows of a unified diff: each hunk's lines from theirs, then from mine
+    pub fn unified_rows(&self, mine_len: usize) -> Vec<DiffRow> {
+        self.rows(mine_len, |index, hunk, rows| {
+            rows.extend((0..hunk.old_len).map(|i| DiffRow::Changed {
+                hunk: index,
+                theirs: Some(hunk.old_start + i),
+                mine: None,
+            }));
+            rows.extend((0..hunk.new_len).map(|i| DiffRow::Changed {
+                hunk: index,
+                theirs: None,
+                mine: Some(hunk.new_start + i),
+            }));
+        })
+    }
+
+    /// Rows of a side-by-side diff, pairing each hunk's lines
+    pub fn side_by_side_rows(&self, mine_len: usize) -> Vec<DiffRow> {
+        self.rows(mine_len, |index, hunk, rows| {
+            rows.extend(
+                (0..hunk.old_len.max(hunk.new_len)).map(|i| DiffRow::Changed {
+                    hunk: index,
+                    theirs: (i < hunk.old_len).then_some(hunk.old_start + i),
+                    mine: (i < hunk.new_len).then_some(hunk.new_start + i),
+                }),
+            );
+        })
+    }
+
+    /// Index of the first row of the current hunk
+    pub fn selected_row(rows: &[DiffRow], selected: usize) -> usize {
+        rows.iter()
+            .position(|row| matches!(row, DiffRow::Changed { hunk, .. } if *hunk == selected))
+            .unwrap_or(0)
+    }
+
+    /// Unchanged runs around and between hunks, folded beyond
+    /// [`CONTEXT_LINES`] from a hunk, with each hunk's rows in between
+    fn rows(
+        &self,
+        mine_len: usize,
+        mut push_hunk: impl FnMut(usize, &Hunk, &mut Vec<DiffRow>),
+    ) -> Vec<DiffRow> {
+        let mut rows = Vec::new();
+        let (mut theirs, mut mine) = (0, 0);
+        for (index, hunk) in self.hunks.iter().enumerate() {
+            let before = index > 0;
+            push_same(&mut rows, theirs, mine, hunk.new_start - mine, before, true);
+            push_hunk(index, hunk, &mut rows);
+            theirs = hunk.old_start + hunk.old_len;
+            mine = hunk.new_start + hunk.new_len;
+        }
+        let after = !self.hunks.is_empty();
+        push_same(&mut rows, theirs, mine, mine_len - mine, after, false);
+        rows
+    }
+}
+
+/// `count` unchanged lines starting at the given rows, keeping the context
+/// after a previous hunk and before a following one
+fn push_same(
+    rows: &mut Vec<DiffRow>,
+    theirs: usize,
+    mine: usize,
+    count: usize,
+    after_hunk: bool,
+    before_hunk: bool,
+) {
+    let head = if after_hunk {
+        count.min(CONTEXT_LINES)
+    } else {
+        0
+    };
+    let tail = if before_hunk {
+        (count - head).min(CONTEXT_LINES)
+    } else {
+        0
+    };
+    let same = |i: usize| DiffRow::Same {
+        theirs: theirs + i,
+        mine: mine + i,
+    };
+    rows.extend((0..head).map(same));
+    if count > head + tail {
+        rows.push(DiffRow::Fold(count - head - tail));
+    }
+    rows.extend((count - tail..count).map(same));
+}
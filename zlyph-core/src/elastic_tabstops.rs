@@ -0,0 +1,89 @@
+//! Elastic tabstops: tab-separated cells on adjacent lines line up, each
+//! column as wide as its widest cell
+//!
+//! A cell is the text of a line up to and including a tab; the text after
+//! a line's last tab isn't one. Column `k` of a run of adjacent lines that
+//! all have a `k`th cell is a block, and every cell in the block is padded
+//! to the widest one's width plus [`GAP`]. Widths are in whatever unit the
+//! frontend measures them in.
+
+use std::ops::Range;
+
+/// Padding past the widest cell of a column, so cells never touch
+pub const GAP: usize = 1;
+
+/// Padding shown after a tab so the cell after it starts in line with the
+/// lines around it
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TabPadding {
+    /// Column just after the tab
+    pub column: usize,
+    pub width: usize,
+}
+
+/// Byte ranges of the cells of `line`, each ending with its tab
+pub fn cells(line: &str) -> Vec<Range<usize>> {
+    let mut start = 0;
+    line.match_indices('\t')
+        .map(|(at, _)| {
+            let cell = start..at + 1;
+            start = at + 1;
+            cell
+        })
+        .collect()
+}
+
+/// The padding after every tab of `lines`, line by line, with `measure`
+/// giving the width of a line's cell from its row and byte range
+pub fn layout(
+    lines: &[String],
+    mut measure: impl FnMut(usize, Range<usize>) -> usize,
+) -> Vec<Vec<TabPadding>> {
+    let cells: Vec<Vec<Range<usize>>> = lines.iter().map(|line| cells(line)).collect();
+    let widths: Vec<Vec<usize>> = cells
+        .iter()
+        .enumerate()
+        .map(|(row, cells)| {
+            cells
+                .iter()
+                .map(|cell| measure(row, cell.clone()))
+                .collect()
+        })
+        .collect();
+
+    let mut padding: Vec<Vec<TabPadding>> = cells
+        .iter()
+        .map(|cells| {
+            cells
+                .iter()
+                .map(|cell| TabPadding {
+                    column: cell.end,
+                    width: 0,
+                })
+                .collect()
+        })
+        .collect();
+    let columns = cells.iter().map(Vec::len).max().unwrap_or(0);
+    for column in 0..columns {
+        let mut row = 0;
+        while row < lines.len() {
+            if cells[row].len() <= column {
+                row += 1;
+                continue;
+            }
+            let block = row..(row..lines.len())
+                .find(|&row| cells[row].len() <= column)
+                .unwrap_or(lines.len());
+            let widest = block
+                .clone()
+                .map(|row| widths[row][column])
+                .max()
+                .unwrap_or(0);
+            for row in block.clone() {
+                padding[row][column].width = widest + GAP - widths[row][column];
+            }
+            row = block.end;
+        }
+    }
+    padding
+}
@@ -0,0 +1,131 @@
+//! Encrypting files at rest with a passphrase
+//!
+//! An encrypted file is text: a header line followed by the base64 of a
+//! random salt, a nonce and the ChaCha20-Poly1305 ciphertext. The key is
+//! derived from the passphrase and salt with Argon2id, once per salt, since
+//! that is slow by design; each save uses a fresh nonce with the same key.
+
+use argon2::Argon2;
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine as _;
+use chacha20poly1305::aead::rand_core::RngCore;
+use chacha20poly1305::aead::{Aead, AeadCore, KeyInit, OsRng};
+use chacha20poly1305::{ChaCha20Poly1305, Key, Nonce};
+use std::io;
+use std::path::Path;
+
+/// First line of every encrypted file
+pub const HEADER: &str = "zlyph-encrypted v1";
+const SALT_LEN: usize = 16;
+const NONCE_LEN: usize = 12;
+/// Service name passphrases are stored under in the OS keychain
+#[cfg(feature = "keychain")]
+const KEYCHAIN_SERVICE: &str = "zlyph";
+
+pub fn is_encrypted(content: &str) -> bool {
+    content.lines().next() == Some(HEADER)
+}
+
+/// A passphrase and the key derived from it for the salt files are
+/// encrypted with
+pub struct Cipher {
+    passphrase: String,
+    salt: [u8; SALT_LEN],
+    cipher: ChaCha20Poly1305,
+}
+
+impl std::fmt::Debug for Cipher {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Cipher").finish_non_exhaustive()
+    }
+}
+
+impl Cipher {
+    /// A cipher with a new random salt, for files not encrypted yet
+    pub fn new(passphrase: &str) -> io::Result<Self> {
+        let mut salt = [0; SALT_LEN];
+        OsRng.fill_bytes(&mut salt);
+        Self::with_salt(passphrase, salt)
+    }
+
+    fn with_salt(passphrase: &str, salt: [u8; SALT_LEN]) -> io::Result<Self> {
+        let mut key = [0; 32];
+        Argon2::default()
+            .hash_password_into(passphrase.as_bytes(), &salt, &mut key)
+            .map_err(|e| io::Error::other(e.to_string()))?;
+        Ok(Self {
+            passphrase: passphrase.to_string(),
+            salt,
+            cipher: ChaCha20Poly1305::new(&Key::from(key)),
+        })
+    }
+
+    pub fn encrypt(&self, text: &str) -> io::Result<String> {
+        let nonce = ChaCha20Poly1305::generate_nonce(&mut OsRng);
+        let ciphertext = self
+            .cipher
+            .encrypt(&nonce, text.as_bytes())
+            .map_err(|_| io::Error::other("encryption failed"))?;
+        let mut payload = self.salt.to_vec();
+        payload.extend_from_slice(&nonce);
+        payload.extend_from_slice(&ciphertext);
+        Ok(format!("{}\n{}\n", HEADER, STANDARD.encode(payload)))
+    }
+
+    /// The text of an encrypted file. A file with another salt derives its
+    /// key, which later saves then use too.
+    pub fn decrypt(&mut self, content: &str) -> io::Result<String> {
+        let invalid = |message: &str| io::Error::new(io::ErrorKind::InvalidData, message);
+        let encoded = content
+            .strip_prefix(HEADER)
+            .ok_or_else(|| invalid("not an encrypted file"))?;
+        let payload = STANDARD
+            .decode(encoded.trim())
+            .map_err(|_| invalid("damaged encrypted file"))?;
+        if payload.len() < SALT_LEN + NONCE_LEN {
+            return Err(invalid("damaged encrypted file"));
+        }
+        let (salt, rest) = payload.split_at(SALT_LEN);
+        let (nonce, ciphertext) = rest.split_at(NONCE_LEN);
+        let nonce = Nonce::from(<[u8; NONCE_LEN]>::try_from(nonce).unwrap());
+        if salt != self.salt {
+            *self = Self::with_salt(&self.passphrase, salt.try_into().unwrap())?;
+        }
+        let text = self
+            .cipher
+            .decrypt(&nonce, ciphertext)
+            .map_err(|_| invalid("wrong passphrase"))?;
+        String::from_utf8(text).map_err(|_| invalid("damaged encrypted file"))
+    }
+}
+
+/// The passphrase saved in the OS keychain for `path`, if any
+#[cfg(feature = "keychain")]
+pub fn keychain_passphrase(path: &Path) -> Option<String> {
+    keyring::Entry::new(KEYCHAIN_SERVICE, &path.display().to_string())
+        .ok()?
+        .get_password()
+        .ok()
+}
+
+/// Without the `keychain` feature nothing is kept in the keychain
+#[cfg(not(feature = "keychain"))]
+pub fn keychain_passphrase(_path: &Path) -> Option<String> {
+    None
+}
+
+/// Save the passphrase for `path` in the OS keychain
+#[cfg(feature = "keychain")]
+pub fn store_in_keychain(path: &Path, passphrase: &str) -> io::Result<()> {
+    keyring::Entry::new(KEYCHAIN_SERVICE, &path.display().to_string())
+        .and_then(|entry| entry.set_password(passphrase))
+        .map_err(|e| io::Error::other(e.to_string()))
+}
+
+#[cfg(not(feature = "keychain"))]
+pub fn store_in_keychain(_path: &Path, _passphrase: &str) -> io::Result<()> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "built without keychain support",
+    ))
+}
@@ -1,9 +1,53 @@
 //! Core editor engine with platform-agnostic business logic
 
-use crate::{BufferPosition, EditorAction, EditorState};
+use crate::align;
+use crate::bidi;
+use crate::bookmarks;
+use crate::calc;
+use crate::changes::{self, ChangeEvent};
+use crate::char_picker;
+use crate::citations::{self, Reference};
+use crate::completion::{self, Completion};
+use crate::config::{self, Config};
+use crate::decorations::{self, Decoration, LineDecoration, Placement, Tone};
+use crate::diagnostics::{self, Diagnostic};
+use crate::dictation::Dictation;
+use crate::diff;
+use crate::encryption::{self, Cipher};
+use crate::file_io::{Content, Job, Task, Update, Worker};
+use crate::footnotes;
+use crate::frontmatter::{self, Frontmatter};
+use crate::goals::{self, WritingSession};
+use crate::invisibles;
+use crate::jump;
+use crate::large_file;
+use crate::long_lines;
+use crate::markdown;
+use crate::multi_cursor::{self, Caret};
+use crate::notes;
+use crate::numbers;
+use crate::paste;
+use crate::pretty::{self, Format};
+use crate::protected;
+use crate::search;
+use crate::sections;
+use crate::sentences;
+use crate::shell::{ShellCommand, ShellTarget};
+use crate::snippets::{self, ActiveSnippet};
+use crate::structure;
+use crate::tables::{self, CellPosition, Table};
+use crate::text_objects;
+use crate::transform::Transform;
+use crate::typography;
+use crate::vfs::{self, FileSystem, TransferStatus, Vfs};
+use crate::{BufferPosition, BufferRange, EditorAction, EditorState};
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet};
 use std::fs;
 use std::io;
+use std::ops::Range;
 use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Receiver, Sender};
 use std::time::{Duration, Instant};
 
 pub struct EditorEngine {
@@ -11,6 +55,66 @@ pub struct EditorEngine {
     undo_stack: Vec<EditorState>,
     redo_stack: Vec<EditorState>,
     last_edit_time: Option<Instant>,
+    /// Previous (cursor, anchor) pairs, restored by ShrinkSelection
+    selection_history: Vec<(BufferPosition, Option<BufferPosition>)>,
+    config: Config,
+    active_snippet: Option<ActiveSnippet>,
+    completion: Option<Completion>,
+    /// Words loaded from the configured completion dictionary
+    dictionary: Vec<String>,
+    /// References loaded from the configured bibliography
+    references: Vec<Reference>,
+    /// Names of the notes `[[links]]` can point to, set by the frontend
+    note_names: Vec<String>,
+    /// Diagnostics from all sources, ordered by position
+    diagnostics: Vec<Diagnostic>,
+    diagnostics_panel_visible: bool,
+    /// Documentation shown for the symbol under the cursor
+    hover: Option<String>,
+    /// A jump to a character, waiting for the next one typed
+    jump: Option<jump::Pending>,
+    /// Rows the frontend shows, which leaps are confined to
+    visible_rows: Option<Range<usize>>,
+    writing_session: Option<WritingSession>,
+    /// Text being searched for, set by the frontend's search UI
+    search_query: Option<String>,
+    /// Cursors besides the main one
+    extra_carets: Vec<Caret>,
+    /// Bumped by every edit, see [`EditorEngine::revision`]
+    revision: u64,
+    /// Where files are loaded from and saved to
+    files: Vfs,
+    /// Passphrase for encrypted files, set by the frontend
+    cipher: Option<Cipher>,
+    /// Files that were encrypted when loaded, kept encrypted when saved
+    encrypted: HashSet<PathBuf>,
+    /// Whether the file loaded is a note, which gets smart punctuation
+    prose: bool,
+    /// Revision the invisible character diagnostics were found at
+    invisibles_revision: u64,
+    /// Ignore every action that would change the text
+    read_only: bool,
+    /// Byte ranges of the whole text that edits are turned away from
+    protected: Vec<Range<usize>>,
+    /// Virtual text, gutter icons and highlights from all sources, ordered
+    /// by position
+    decorations: Vec<Decoration>,
+    /// Keep the text on one line, as in a form's input field
+    single_line: bool,
+    /// The buffer is too big for undo history and whole-text checks
+    large_file: bool,
+    /// Channels given out by [`EditorEngine::subscribe`], dropped once
+    /// their receivers are
+    subscribers: RefCell<Vec<Sender<ChangeEvent>>>,
+    /// Runs background loads and saves, started by the first
+    file_io: Option<Worker>,
+    /// Background loads and saves not yet finished
+    file_tasks: Vec<Task>,
+    /// A shell command whose output is still to go into the buffer, and
+    /// the revision it started at
+    shell_command: Option<(ShellCommand, ShellTarget, u64)>,
+    /// The dictation command listening, if any
+    dictation: Option<Dictation>,
 }
 
 const UNDO_CHUNK_DURATION: Duration = Duration::from_millis(500);
@@ -22,9 +126,216 @@ impl EditorEngine {
             undo_stack: Vec::new(),
             redo_stack: Vec::new(),
             last_edit_time: None,
+            selection_history: Vec::new(),
+            config: Config::default(),
+            active_snippet: None,
+            completion: None,
+            dictionary: Vec::new(),
+            references: Vec::new(),
+            note_names: Vec::new(),
+            diagnostics: Vec::new(),
+            diagnostics_panel_visible: false,
+            hover: None,
+            jump: None,
+            visible_rows: None,
+            writing_session: None,
+            search_query: None,
+            extra_carets: Vec::new(),
+            revision: 0,
+            files: Vfs::new(Default::default()),
+            cipher: None,
+            encrypted: HashSet::new(),
+            prose: true,
+            invisibles_revision: 0,
+            read_only: false,
+            protected: Vec::new(),
+            decorations: Vec::new(),
+            single_line: false,
+            large_file: false,
+            subscribers: RefCell::new(Vec::new()),
+            file_io: None,
+            file_tasks: Vec::new(),
+            shell_command: None,
+            dictation: None,
         }
     }
 
+    /// Start configuring an engine, as in
+    /// `EditorEngine::builder().text("hello").read_only(true).build()`
+    pub fn builder() -> EditorEngineBuilder {
+        EditorEngineBuilder::default()
+    }
+
+    pub fn config(&self) -> &Config {
+        &self.config
+    }
+
+    pub fn set_config(&mut self, config: Config) {
+        self.dictionary = config
+            .completion
+            .dictionary
+            .as_ref()
+            .and_then(|path| fs::read_to_string(path).ok())
+            .map(|content| {
+                content
+                    .lines()
+                    .map(str::trim)
+                    .filter(|word| !word.is_empty())
+                    .map(String::from)
+                    .collect()
+            })
+            .unwrap_or_default();
+        self.references = config
+            .citations
+            .bibliography
+            .as_deref()
+            .map(citations::load)
+            .unwrap_or_default();
+        self.files.set_config(config.remote.clone());
+        self.config = config;
+    }
+
+    /// Names offered when completing a `[[link]]`; see [`crate::notes`]
+    pub fn set_note_names(&mut self, names: Vec<String>) {
+        self.note_names = names;
+    }
+
+    /// The open completion popup, if any
+    pub fn completion(&self) -> Option<&Completion> {
+        self.completion.as_ref()
+    }
+
+    /// Open the completion popup with items from an external provider,
+    /// replacing the partial word before the cursor when accepted
+    pub fn show_completion(&mut self, items: Vec<String>) {
+        let cursor = self.state.cursor;
+        let line = &self.state.lines[cursor.row];
+        let start = completion::word_start(line, cursor.column);
+        let prefix = line[start..cursor.column].to_string();
+        let candidates = items.iter().map(String::as_str);
+        let mut items = completion::rank(candidates, &prefix, self.config.completion.max_items);
+        if prefix.is_empty() {
+            items.sort();
+        }
+
+        self.completion = (!items.is_empty()).then(|| Completion {
+            start: BufferPosition::new(cursor.row, start),
+            prefix,
+            items,
+            selected: 0,
+        });
+    }
+
+    pub fn diagnostics(&self) -> &[Diagnostic] {
+        &self.diagnostics
+    }
+
+    /// Replace all diagnostics previously published by `source`
+    pub fn set_diagnostics(&mut self, source: &str, diagnostics: Vec<Diagnostic>) {
+        self.diagnostics.retain(|d| d.source != source);
+        self.diagnostics.extend(diagnostics);
+        self.diagnostics
+            .sort_by(|a, b| a.start.cmp(&b.start).then(a.severity.cmp(&b.severity)));
+    }
+
+    pub fn diagnostics_panel_visible(&self) -> bool {
+        self.diagnostics_panel_visible
+    }
+
+    pub fn hover(&self) -> Option<&str> {
+        self.hover.as_deref()
+    }
+
+    /// Show hover documentation until the next action
+    pub fn set_hover(&mut self, text: Option<String>) {
+        self.hover = text.filter(|t| !t.trim().is_empty());
+    }
+
+    pub fn search_query(&self) -> Option<&str> {
+        self.search_query.as_deref()
+    }
+
+    pub fn set_search_query(&mut self, query: Option<String>) {
+        self.search_query = query.filter(|q| !q.is_empty());
+        self.highlight_search();
+    }
+
+    /// How many matches the search has and which the cursor is at, e.g.
+    /// "3 of 17 matches", while a search is active
+    pub fn search_status(&self) -> Option<String> {
+        self.search_query.as_ref()?;
+        let matches = self.search_matches();
+        let current = search::current(&matches, self.selection_range(), self.state.cursor);
+        Some(search::count_label(current, matches.len()))
+    }
+
+    /// Highlight the search's matches, the one at the cursor apart from
+    /// the rest
+    fn highlight_search(&mut self) {
+        if self.search_query.is_none() || self.large_file {
+            if self.decorations.iter().any(|d| d.source == search::SOURCE) {
+                self.set_decorations(search::SOURCE, Vec::new());
+            }
+            return;
+        }
+        let matches = self.search_matches();
+        let current = search::current(&matches, self.selection_range(), self.state.cursor);
+        let highlights = matches
+            .iter()
+            .enumerate()
+            .filter_map(|(i, (start, end))| {
+                let (line_start, _) = self.line_span(start.row)?;
+                let tone = if Some(i) == current {
+                    Tone::Accent
+                } else {
+                    Tone::Warning
+                };
+                let range = line_start + start.column..line_start + end.column;
+                Some(Decoration::highlight(range).with_tone(tone))
+            })
+            .collect();
+        self.set_decorations(search::SOURCE, highlights);
+    }
+
+    /// The cursors besides the main one, for frontends to draw
+    pub fn extra_carets(&self) -> &[Caret] {
+        &self.extra_carets
+    }
+
+    /// Rows the frontend has in view, set as it scrolls
+    pub fn set_visible_rows(&mut self, rows: Range<usize>) {
+        self.visible_rows = Some(rows);
+    }
+
+    /// Occurrences of the search query, or while no search is active, of the
+    /// selected text when the selection lies within one line
+    pub fn search_matches(&self) -> Vec<(BufferPosition, BufferPosition)> {
+        let query = match (&self.search_query, self.selection_range()) {
+            (Some(query), _) => query.as_str(),
+            (None, Some((start, end))) if start.row == end.row => {
+                &self.state.lines[start.row][start.column..end.column]
+            }
+            _ => return Vec::new(),
+        };
+        search::find_all(&self.state.lines, query)
+    }
+
+    pub fn word_count(&self) -> usize {
+        goals::count_words(&self.state.lines)
+    }
+
+    pub fn writing_session(&self) -> Option<&WritingSession> {
+        self.writing_session.as_ref()
+    }
+
+    /// Start (or restart) a writing session counting from the current text
+    pub fn start_writing_session(&mut self) {
+        self.writing_session = Some(WritingSession::new(
+            self.config.goal.clone(),
+            self.word_count(),
+        ));
+    }
+
     pub fn state(&self) -> &EditorState {
         &self.state
     }
@@ -33,6 +344,257 @@ impl EditorEngine {
         &mut self.state
     }
 
+    /// The whole buffer, lines joined with `\n`
+    pub fn text(&self) -> String {
+        self.state.to_string()
+    }
+
+    /// The selected text, if anything is selected
+    pub fn selected_text(&self) -> Option<String> {
+        self.state
+            .selection()
+            .map(|range| self.state.text_in(range))
+    }
+
+    pub fn is_read_only(&self) -> bool {
+        self.read_only
+    }
+
+    /// Show the text without letting it be changed; the cursor can still
+    /// move and select for copying
+    pub fn set_read_only(&mut self, read_only: bool) {
+        self.read_only = read_only;
+    }
+
+    /// Turn away edits to the text in `range`, byte offsets into
+    /// [`text`](Self::text), with a status message; see
+    /// [`crate::protected`]. Large files aren't checked.
+    pub fn protect(&mut self, range: Range<usize>) {
+        let len = self.state.to_string().len();
+        protected::add(
+            &mut self.protected,
+            range.start.min(len)..range.end.min(len),
+        );
+    }
+
+    pub fn clear_protected(&mut self) {
+        self.protected.clear();
+    }
+
+    /// The protected ranges, sorted and not overlapping
+    pub fn protected(&self) -> &[Range<usize>] {
+        &self.protected
+    }
+
+    /// The protected columns of line `row`, for frontends to dim
+    pub fn protected_in_line(&self, row: usize) -> Vec<Range<usize>> {
+        let Some((start, end)) = self.line_span(row).filter(|_| !self.protected.is_empty()) else {
+            return Vec::new();
+        };
+        self.protected
+            .iter()
+            .filter(|region| region.start < end && start < region.end)
+            .map(|region| region.start.max(start) - start..region.end.min(end) - start)
+            .collect()
+    }
+
+    /// Replace all decorations previously published by `source`; see
+    /// [`crate::decorations`]. Large files aren't decorated.
+    pub fn set_decorations(&mut self, source: &str, decorations: Vec<Decoration>) {
+        self.decorations.retain(|d| d.source != source);
+        if self.large_file {
+            return;
+        }
+        self.decorations
+            .extend(decorations.into_iter().map(|decoration| Decoration {
+                source: source.to_string(),
+                ..decoration
+            }));
+        self.decorations.sort_by_key(|d| d.range.start);
+    }
+
+    pub fn decorations(&self) -> &[Decoration] {
+        &self.decorations
+    }
+
+    /// The virtual text and highlights on line `row`, for frontends to draw
+    pub fn line_decorations(&self, row: usize) -> Vec<LineDecoration<'_>> {
+        match self.line_span(row) {
+            Some((start, end)) if !self.decorations.is_empty() => {
+                decorations::on_line(&self.decorations, start, end)
+            }
+            _ => Vec::new(),
+        }
+    }
+
+    /// The gutter icon beside line `row` and its tone
+    pub fn gutter_icon(&self, row: usize) -> Option<(&str, Tone)> {
+        let (start, end) = self
+            .line_span(row)
+            .filter(|_| !self.decorations.is_empty())?;
+        decorations::gutter(&self.decorations, start, end)
+    }
+
+    /// Rows with a bookmark, in order; see [`crate::bookmarks`]
+    pub fn bookmarks(&self) -> Vec<usize> {
+        let mut rows: Vec<usize> = self
+            .decorations
+            .iter()
+            .filter(|d| d.source == bookmarks::SOURCE)
+            .map(|d| self.state.offset_to_position(d.range.start).row)
+            .collect();
+        rows.dedup();
+        rows
+    }
+
+    /// Bookmark `rows`, replacing any bookmarks; rows past the end are
+    /// skipped. Large files aren't bookmarked.
+    pub fn set_bookmarks(&mut self, rows: &[usize]) {
+        let markers = rows
+            .iter()
+            .filter_map(|&row| self.line_span(row))
+            .map(|(start, _)| bookmarks::marker(start))
+            .collect();
+        self.set_decorations(bookmarks::SOURCE, markers);
+    }
+
+    fn toggle_bookmark(&mut self) {
+        if self.large_file {
+            self.hover = Some("Bookmarks are off for large files".to_string());
+            return;
+        }
+        let row = self.state.cursor.row;
+        let mut rows = self.bookmarks();
+        match rows.binary_search(&row) {
+            Ok(index) => {
+                rows.remove(index);
+            }
+            Err(index) => rows.insert(index, row),
+        }
+        self.set_bookmarks(&rows);
+    }
+
+    fn goto_bookmark(&mut self, forward: bool) {
+        match bookmarks::next(&self.bookmarks(), self.state.cursor.row, forward) {
+            Some(row) => self.set_cursor_position(row, 0),
+            None => self.hover = Some("No bookmarks".to_string()),
+        }
+    }
+
+    /// Byte offsets of the start and end of line `row` in the whole text
+    fn line_span(&self, row: usize) -> Option<(usize, usize)> {
+        let line = self.state.lines.get(row)?;
+        let start = self.state.position_to_offset(BufferPosition::new(row, 0));
+        Some((start, start + line.len()))
+    }
+
+    /// The note's frontmatter, if it starts with one
+    pub fn frontmatter(&self) -> Option<Frontmatter> {
+        frontmatter::parse(&self.state.lines)
+    }
+
+    /// Rows a frontend should hide under the frontmatter's first line,
+    /// which shows [`Frontmatter::summary`] instead, when
+    /// `[notes] collapse_frontmatter` is on and the cursor and selection
+    /// are outside the block
+    pub fn collapsed_frontmatter(&self) -> Option<Range<usize>> {
+        if !self.config.notes.collapse_frontmatter || !self.prose || self.large_file {
+            return None;
+        }
+        let rows = frontmatter::block_rows(&self.state.lines)?;
+        let touched = |position: BufferPosition| rows.contains(&position.row);
+        if touched(self.state.cursor) || self.state.selection_anchor.is_some_and(touched) {
+            return None;
+        }
+        Some(rows.start + 1..rows.end)
+    }
+
+    pub fn is_single_line(&self) -> bool {
+        self.single_line
+    }
+
+    /// Keep the text on one line: Enter does nothing, and line breaks in
+    /// pasted or loaded text become spaces. Turning it on joins the lines
+    /// already there.
+    pub fn set_single_line(&mut self, single_line: bool) {
+        self.single_line = single_line;
+        if single_line && self.state.lines.len() > 1 {
+            let text = self.state.lines.join("\n");
+            self.load_text(&text);
+        }
+    }
+
+    /// Whether the buffer is at least `[large_file] threshold_mb` in size,
+    /// so there is no undo and frontends should leave out features that
+    /// read the whole text on every edit; see [`crate::large_file`]
+    pub fn is_large_file(&self) -> bool {
+        self.large_file
+    }
+
+    /// Counter bumped whenever the text may have changed, so frontends can
+    /// notice edits without comparing the whole buffer
+    pub fn revision(&self) -> u64 {
+        self.revision
+    }
+
+    /// A channel that gets a [`ChangeEvent`] for each change from now on,
+    /// for keeping something in step without re-reading the whole state.
+    /// Drop the receiver to stop.
+    pub fn subscribe(&mut self) -> Receiver<ChangeEvent> {
+        let (sender, receiver) = mpsc::channel();
+        self.subscribers.get_mut().push(sender);
+        receiver
+    }
+
+    fn emit(&self, event: ChangeEvent) {
+        self.subscribers
+            .borrow_mut()
+            .retain(|subscriber| subscriber.send(event.clone()).is_ok());
+    }
+
+    /// Run `change`, then tell subscribers what it did to the text, cursor
+    /// and selection; the text is only compared when `edits` says it may
+    /// have changed
+    fn observe(&mut self, edits: bool, change: impl FnOnce(&mut Self)) {
+        if self.subscribers.get_mut().is_empty() {
+            change(self);
+            return;
+        }
+        let revision = self.revision;
+        let old_text = (edits && !self.large_file).then(|| self.state.to_string());
+        let cursor = self.state.cursor;
+        let selection = self.state.selection();
+
+        change(self);
+
+        if self.large_file && edits && self.revision != revision {
+            self.emit(ChangeEvent::Edited);
+        } else if let Some(old) = old_text.filter(|_| self.revision != revision) {
+            let new = self.state.to_string();
+            if old != new {
+                let (start, old_end, new_end) = diff::changed_span(&old, &new);
+                self.emit(ChangeEvent::Replaced {
+                    range: BufferRange::new(
+                        changes::position_in(&old, start),
+                        changes::position_in(&old, old_end),
+                    ),
+                    text: new[start..new_end].to_string(),
+                });
+            }
+        }
+        if self.state.cursor != cursor {
+            self.emit(ChangeEvent::CursorMoved {
+                cursor: self.state.cursor,
+            });
+        }
+        let selection_now = self.state.selection();
+        if selection_now != selection {
+            self.emit(ChangeEvent::SelectionChanged {
+                selection: selection_now,
+            });
+        }
+    }
+
     fn should_push_undo_state(&self) -> bool {
         if let Some(last_time) = self.last_edit_time {
             Instant::now().duration_since(last_time) > UNDO_CHUNK_DURATION
@@ -42,21 +604,249 @@ impl EditorEngine {
     }
 
     fn push_undo_state(&mut self) {
-        if !self.should_push_undo_state() {
+        // Every edit comes through here first
+        self.revision += 1;
+        if self.large_file || !self.should_push_undo_state() {
             return;
         }
         self.undo_stack.push(self.state.clone_for_undo());
         self.redo_stack.clear();
     }
 
+    /// Start a new undo step regardless of typing chunking, for discrete commands
+    fn push_undo_checkpoint(&mut self) {
+        self.last_edit_time = None;
+        self.push_undo_state();
+    }
+
     fn mark_edit_time(&mut self) {
         self.last_edit_time = Some(Instant::now());
     }
 
+    /// Carry out `action`, telling subscribers what changed
     pub fn handle_action(&mut self, action: EditorAction) {
+        let edits = action.edits_text();
+        let history = matches!(action, EditorAction::Undo | EditorAction::Redo);
+        self.observe(edits, |engine| {
+            engine.guard_regions(edits && !history, |engine| engine.apply_action(action))
+        });
+        self.highlight_search();
+    }
+
+    /// Run `change` and move the protected regions and decorations with it,
+    /// first putting everything back as it was if `checked` and it touched a
+    /// protected region
+    fn guard_regions(&mut self, checked: bool, change: impl FnOnce(&mut Self)) {
+        if (self.protected.is_empty() && self.decorations.is_empty()) || self.large_file {
+            change(self);
+            return;
+        }
+        // Without protected text there is nothing to put back
+        if self.protected.is_empty() {
+            self.move_decorations(change);
+            return;
+        }
+        let revision = self.revision;
+        let before = self.state.clone();
+        let undo_depth = self.undo_stack.len();
+        let redo_stack = self.redo_stack.clone();
+        let last_edit_time = self.last_edit_time;
+
+        change(self);
+        if self.revision == revision {
+            return;
+        }
+        let old = before.to_string();
+        let new = self.state.to_string();
+        let spans = protected::changed_spans(&old, &new);
+        let allowed = spans
+            .iter()
+            .find(|(start, old_end, _)| !protected::blocks(&self.protected, *start, *old_end));
+        match allowed {
+            Some(&(start, old_end, new_end)) => {
+                protected::shift(&mut self.protected, start, old_end, new_end);
+                decorations::shift(&mut self.decorations, start, old_end, new_end);
+            }
+            None if !checked => {
+                let (start, old_end, new_end) = spans[0];
+                protected::shift(&mut self.protected, start, old_end, new_end);
+                decorations::shift(&mut self.decorations, start, old_end, new_end);
+            }
+            None => {
+                self.state = before;
+                self.undo_stack.truncate(undo_depth);
+                self.redo_stack = redo_stack;
+                self.last_edit_time = last_edit_time;
+                self.revision = revision;
+                self.hover = Some("That text is protected".to_string());
+            }
+        }
+    }
+
+    /// Run `change` and move the decorations with the rows it changed. The
+    /// rows at and beside the carets are kept beforehand, so an edit there
+    /// is placed to the byte; any other row it changed is taken whole.
+    fn move_decorations(&mut self, change: impl FnOnce(&mut Self)) {
+        let revision = self.revision;
+        let lengths: Vec<usize> = self.state.lines.iter().map(String::len).collect();
+        let kept: HashMap<usize, String> = self
+            .caret_rows()
+            .map(|row| (row, self.state.lines[row].clone()))
+            .collect();
+
+        change(self);
+        if self.revision == revision {
+            return;
+        }
+        for (row, old_rows, new_rows) in diff::changed_rows(&lengths, &self.state.lines) {
+            // Entries come last first, so the rows above haven't moved yet
+            let start: usize = lengths[..row].iter().map(|length| length + 1).sum();
+            let old: Option<Vec<&str>> = (row..row + old_rows)
+                .map(|row| kept.get(&row).map(String::as_str))
+                .collect();
+            let new = self.state.lines[row..row + new_rows].join("\n");
+            let (from, old_end, new_end) = match old {
+                Some(old) => diff::changed_span(&old.join("\n"), &new),
+                None => {
+                    let old_len: usize = lengths[row..row + old_rows].iter().sum();
+                    (0, old_len + old_rows - 1, new.len())
+                }
+            };
+            decorations::shift(
+                &mut self.decorations,
+                start + from,
+                start + old_end,
+                start + new_end,
+            );
+        }
+    }
+
+    /// The rows of every caret and its selection, and one either side, where
+    /// typing, deleting and joining lines at the carets land
+    fn caret_rows(&self) -> impl Iterator<Item = usize> + '_ {
+        let main = Caret {
+            cursor: self.state.cursor,
+            anchor: self.state.selection_anchor,
+        };
+        let last = self.state.lines.len() - 1;
+        self.extra_carets
+            .iter()
+            .copied()
+            .chain([main])
+            .flat_map(move |caret| {
+                let (start, end) = caret
+                    .selection()
+                    .map_or((caret.cursor, caret.cursor), |range| {
+                        (range.start, range.end)
+                    });
+                start.row.saturating_sub(1)..=(end.row + 1).min(last)
+            })
+    }
+
+    fn apply_action(&mut self, action: EditorAction) {
+        // A pending jump takes the next character typed; anything else
+        // abandons it
+        if let Some(pending) = self.jump.take() {
+            self.set_decorations(jump::SOURCE, Vec::new());
+            let typed = match &action {
+                EditorAction::TypeCharacter(c) => Some(*c),
+                EditorAction::TypeString(s) if s.chars().count() == 1 => s.chars().next(),
+                _ => None,
+            };
+            if let Some(c) = typed {
+                self.continue_jump(pending, c);
+                return;
+            }
+            if action == EditorAction::Cancel {
+                self.hover = None;
+                return;
+            }
+        }
+        if self.read_only && action.edits_text() {
+            return;
+        }
+        if !self.extra_carets.is_empty() {
+            if multi_cursor::applies_to_all(&action) {
+                self.hover = None;
+                self.edit_at_carets(&action);
+                return;
+            }
+            self.extra_carets.clear();
+        }
+        let action = if self.single_line {
+            match action {
+                EditorAction::Newline if self.completion.is_none() => return,
+                EditorAction::TypeString(s) => EditorAction::TypeString(one_line(&s)),
+                EditorAction::Paste(s) => EditorAction::Paste(one_line(&s)),
+                EditorAction::PastePlain(s) => EditorAction::PastePlain(one_line(&s)),
+                EditorAction::InsertComposed(s) => EditorAction::InsertComposed(one_line(&s)),
+                action => action,
+            }
+        } else {
+            action
+        };
+
+        if !matches!(
+            action,
+            EditorAction::ExpandSelection
+                | EditorAction::ShrinkSelection
+                | EditorAction::SelectParent
+                | EditorAction::SelectNextSibling
+        ) {
+            self.selection_history.clear();
+        }
+
+        self.hover = None;
+
+        let completion_open = self.completion.is_some();
+        let cursor = self.state.cursor;
+        let in_link =
+            notes::open_link_start(&self.state.lines[cursor.row], cursor.column).is_some();
+        // Some(force) when the completion list should be recomputed after this action
+        let refresh_completion = match &action {
+            EditorAction::TriggerCompletion => Some(true),
+            EditorAction::TypeCharacter(c) if text_objects::is_word_char(*c) => Some(false),
+            // Note names may have spaces and punctuation, and "[[" opens the list
+            EditorAction::TypeCharacter(c) if in_link || *c == '[' => Some(false),
+            EditorAction::TypeString(s)
+                if !s.is_empty() && s.chars().all(text_objects::is_word_char) =>
+            {
+                Some(false)
+            }
+            EditorAction::Backspace if completion_open => Some(false),
+            _ => None,
+        };
+
+        let keeps_completion = matches!(action, EditorAction::QuickFix)
+            || (completion_open && matches!(action, EditorAction::MoveUp | EditorAction::MoveDown));
+
+        let aligns_table = matches!(
+            action,
+            EditorAction::TypeCharacter(_)
+                | EditorAction::TypeString(_)
+                | EditorAction::Backspace
+                | EditorAction::Delete
+        );
+
+        let snippet_len_before = match action {
+            EditorAction::Tab | EditorAction::Outdent if !completion_open => None,
+            EditorAction::Undo | EditorAction::Redo => {
+                self.active_snippet = None;
+                None
+            }
+            _ => self.active_snippet.as_ref().map(|_| self.state.text_len()),
+        };
+
         match action {
+            EditorAction::Tab | EditorAction::Newline if completion_open => {
+                self.accept_completion()
+            }
+            EditorAction::MoveUp if completion_open => self.select_completion(-1),
+            EditorAction::MoveDown if completion_open => self.select_completion(1),
             EditorAction::TypeCharacter(c) => self.type_character(c),
             EditorAction::TypeString(s) => self.type_string(&s),
+            EditorAction::Paste(s) => self.paste(&s, false),
+            EditorAction::PastePlain(s) => self.paste(&s, true),
             EditorAction::Backspace => self.backspace(),
             EditorAction::Delete => self.delete(),
             EditorAction::Newline => self.newline(),
@@ -68,6 +858,11 @@ impl EditorEngine {
             EditorAction::MoveToEndOfLine => self.move_to_line_end(),
             EditorAction::MoveWordLeft => self.move_word_left(),
             EditorAction::MoveWordRight => self.move_word_right(),
+            EditorAction::MoveToNextSentence => self.move_to_sentence(true),
+            EditorAction::MoveToPrevSentence => self.move_to_sentence(false),
+            EditorAction::JumpToChar => self.start_jump(jump::Pending::Char { before: false }),
+            EditorAction::JumpBeforeChar => self.start_jump(jump::Pending::Char { before: true }),
+            EditorAction::Leap => self.start_jump(jump::Pending::Leap),
             EditorAction::Undo => self.undo(),
             EditorAction::Redo => self.redo(),
             EditorAction::DeleteLine => self.delete_line(),
@@ -77,8 +872,34 @@ impl EditorEngine {
             EditorAction::DeleteWordRight => self.delete_word_right(),
             EditorAction::MoveLineUp => self.move_line_up(),
             EditorAction::MoveLineDown => self.move_line_down(),
+            EditorAction::MoveSectionUp => self.move_section(false),
+            EditorAction::MoveSectionDown => self.move_section(true),
+            EditorAction::PromoteSection => self.shift_section(true),
+            EditorAction::DemoteSection => self.shift_section(false),
+            EditorAction::SelectSection => self.select_section(),
+            EditorAction::InsertFootnote => self.insert_footnote(),
+            EditorAction::RenumberFootnotes => self.renumber_footnotes(),
+            EditorAction::GoToFootnote => self.go_to_footnote(),
+            EditorAction::InsertBibliography => self.insert_bibliography(),
             EditorAction::Tab => self.tab(),
             EditorAction::Outdent => self.outdent(),
+            EditorAction::InsertDate => {
+                let format = self.config.datetime.date_format.clone();
+                self.insert_now(&format);
+            }
+            EditorAction::InsertTime => {
+                let format = self.config.datetime.time_format.clone();
+                self.insert_now(&format);
+            }
+            EditorAction::InsertTimestamp => {
+                let format = self.config.datetime.timestamp_format.clone();
+                self.insert_now(&format);
+            }
+            EditorAction::InsertUnicodeCodepoint(hex) => match char_picker::parse_codepoint(&hex) {
+                Some(c) => self.type_character(c),
+                None => self.hover = Some(format!("Not a code point: {}", hex)),
+            },
+            EditorAction::InsertComposed(text) => self.insert_as_step(&text),
             EditorAction::SelectLeft => self.select_left(),
             EditorAction::SelectRight => self.select_right(),
             EditorAction::SelectUp => self.select_up(),
@@ -86,6 +907,106 @@ impl EditorEngine {
             EditorAction::SelectWordLeft => self.select_word_left(),
             EditorAction::SelectWordRight => self.select_word_right(),
             EditorAction::SelectAll => self.select_all(),
+            EditorAction::SelectLine => self.select_line(),
+            EditorAction::SelectSentence => self.select_sentence(),
+            EditorAction::SelectParagraph => self.select_paragraph(),
+            EditorAction::SelectToTop => self.select_to_top(),
+            EditorAction::SelectToBottom => self.select_to_bottom(),
+            EditorAction::SelectToLineStart => self.select_to_line_start(),
+            EditorAction::SelectToLineEnd => self.select_to_line_end(),
+            EditorAction::ExpandSelection => self.expand_selection(),
+            EditorAction::ShrinkSelection => self.shrink_selection(),
+            EditorAction::SelectParent => self.select_parent(),
+            EditorAction::SelectNextSibling => self.select_next_sibling(),
+            EditorAction::TriggerCompletion => {
+                // Completion state is updated below
+            }
+            EditorAction::Cancel => {
+                // Completion state is updated below too
+                self.cancel_shell_command();
+                self.search_query = None;
+            }
+            EditorAction::NextMatch => self.goto_match(false),
+            EditorAction::PrevMatch => self.goto_match(true),
+            EditorAction::SelectAllMatches => self.select_all_matches(),
+            EditorAction::Hover => self.hover_citation(),
+            EditorAction::GoToDefinition => {
+                // Answered asynchronously by a language server
+            }
+            EditorAction::NextDiagnostic => self.goto_diagnostic(true),
+            EditorAction::PrevDiagnostic => self.goto_diagnostic(false),
+            EditorAction::ToggleDiagnosticsPanel => {
+                self.diagnostics_panel_visible = !self.diagnostics_panel_visible;
+            }
+            EditorAction::QuickFix => self.quick_fix(),
+            EditorAction::ApplySuggestion(text) => self.apply_suggestion(&text),
+            EditorAction::StripInvisibles => self.strip_invisibles(),
+            EditorAction::EvaluateSelection => self.evaluate_selection(),
+            EditorAction::AlignOn(delimiter) => self.align_on(&delimiter),
+            EditorAction::FormatJson => {
+                let indent = self.config.format.indent;
+                self.reformat(Format::Json, |text| pretty::json(text, indent));
+            }
+            EditorAction::FormatToml => {
+                let indent = self.config.format.indent;
+                self.reformat(Format::Toml, |text| pretty::toml(text, indent));
+            }
+            EditorAction::FormatYaml => {
+                let indent = self.config.format.indent;
+                self.reformat(Format::Yaml, |text| pretty::yaml(text, indent));
+            }
+            EditorAction::MinifyJson => self.reformat(Format::Json, pretty::minify_json),
+            EditorAction::SortKeys => self.sort_keys(),
+            EditorAction::TransformSelection(transform) => self.transform_selection(transform),
+            EditorAction::IncrementNumber(amount) => self.increment_numbers(amount, false),
+            EditorAction::DecrementNumber(amount) => {
+                self.increment_numbers(amount.saturating_neg(), false)
+            }
+            EditorAction::NumberSequence(step) => self.increment_numbers(step, true),
+            EditorAction::FormatTable => self.edit_table(|_, _| Ok(())),
+            EditorAction::InsertTableRow => self.edit_table(|table, cell| {
+                cell.row += 1;
+                cell.offset = 0;
+                table.insert_row(cell.row);
+                Ok(())
+            }),
+            EditorAction::DeleteTableRow => self.edit_table(|table, cell| {
+                if !table.remove_row(cell.row) {
+                    return Err("The header row can't be deleted");
+                }
+                cell.row = cell.row.min(table.row_count() - 1);
+                Ok(())
+            }),
+            EditorAction::InsertTableColumn => self.edit_table(|table, cell| {
+                cell.column += 1;
+                cell.offset = 0;
+                table.insert_column(cell.column);
+                Ok(())
+            }),
+            EditorAction::DeleteTableColumn => self.edit_table(|table, cell| {
+                if !table.remove_column(cell.column) {
+                    return Err("A table needs at least one column");
+                }
+                cell.column = cell.column.min(table.columns() - 1);
+                Ok(())
+            }),
+            EditorAction::ToggleBookmark => self.toggle_bookmark(),
+            EditorAction::NextBookmark => self.goto_bookmark(true),
+            EditorAction::PrevBookmark => self.goto_bookmark(false),
+            EditorAction::SetFrontmatterField { key, value } => {
+                let lines = frontmatter::set_field(&self.state.lines, &key, &value);
+                self.merge(&lines.join("\n"));
+            }
+            EditorAction::RemoveFrontmatterField(key) => {
+                let lines = frontmatter::remove_field(&self.state.lines, &key);
+                self.merge(&lines.join("\n"));
+            }
+            EditorAction::ToggleWritingSession => {
+                if self.writing_session.take().is_none() {
+                    self.start_writing_session();
+                }
+            }
+            EditorAction::ToggleDictation => self.toggle_dictation(),
             EditorAction::IncreaseFontSize => {
                 self.state.font_size = (self.state.font_size + 2.0).min(72.0);
             }
@@ -95,9 +1016,12 @@ impl EditorEngine {
             EditorAction::ResetFontSize => {
                 self.state.font_size = 48.0;
             }
-            EditorAction::Cut | EditorAction::Copy | EditorAction::Paste(_) => {
+            EditorAction::Cut | EditorAction::Copy => {
                 // Clipboard operations need platform-specific handling
             }
+            EditorAction::OpenLinkUnderCursor => {
+                // Opening browsers and files is up to the frontend
+            }
             EditorAction::Quit => {
                 // Handled by platform-specific code
             }
@@ -106,69 +1030,528 @@ impl EditorEngine {
             }
             EditorAction::StartSelection { row, column } => self.start_selection(row, column),
             EditorAction::ExtendSelection { row, column } => self.extend_selection(row, column),
+            EditorAction::MoveSelectionTo { row, column } => {
+                self.drop_selection(row, column, false)
+            }
+            EditorAction::CopySelectionTo { row, column } => self.drop_selection(row, column, true),
         }
-    }
 
-    fn selection_range(&self) -> Option<(BufferPosition, BufferPosition)> {
-        self.state.selection_anchor.map(|anchor| {
-            if anchor.row < self.state.cursor.row
-                || (anchor.row == self.state.cursor.row && anchor.column < self.state.cursor.column)
-            {
-                (anchor, self.state.cursor)
-            } else {
-                (self.state.cursor, anchor)
-            }
-        })
+        if let Some(len_before) = snippet_len_before {
+            self.track_active_snippet(len_before);
+        }
+        if aligns_table {
+            self.align_table();
+        }
+
+        match refresh_completion {
+            Some(force) => self.update_completion(force),
+            None if !keeps_completion => self.completion = None,
+            None => {}
+        }
+        self.refresh_invisibles();
     }
 
-    fn clear_selection(&mut self) {
-        self.state.selection_anchor = None;
+    /// Publish the invisible characters in the buffer if it has changed
+    /// since they were last found
+    fn refresh_invisibles(&mut self) {
+        if self.invisibles_revision == self.revision {
+            return;
+        }
+        self.invisibles_revision = self.revision;
+        // Scanning every line after each edit would make typing crawl
+        let found = if self.large_file {
+            Vec::new()
+        } else {
+            invisibles::diagnostics(&self.state.lines)
+        };
+        self.set_diagnostics(invisibles::SOURCE, found);
     }
 
-    fn delete_selection(&mut self) {
-        if let Some((start, end)) = self.selection_range() {
-            self.delete_range(start, end);
-            self.state.cursor = start;
-            self.clear_selection();
+    fn strip_invisibles(&mut self) {
+        let cursor = self.state.cursor;
+        let stripped: Vec<(usize, String, usize)> = self
+            .state
+            .lines
+            .iter()
+            .enumerate()
+            .filter_map(|(row, line)| {
+                let column = if row == cursor.row { cursor.column } else { 0 };
+                invisibles::strip(line, column).map(|(line, column)| (row, line, column))
+            })
+            .collect();
+        if stripped.is_empty() {
+            self.hover = Some("No invisible characters".to_string());
+            return;
+        }
+
+        self.push_undo_checkpoint();
+        self.clear_selection();
+        let count = stripped.len();
+        for (row, line, column) in stripped {
+            self.state.lines[row] = line;
+            if row == cursor.row {
+                self.state.cursor.column = column;
+            }
         }
+        self.last_edit_time = None;
+        self.hover = Some(format!(
+            "Removed invisible characters from {} line{}",
+            count,
+            if count == 1 { "" } else { "s" }
+        ));
     }
 
-    fn delete_range(&mut self, start: BufferPosition, end: BufferPosition) {
-        if start.row == end.row {
+    /// Work out the selected expression and put the result in its place, or
+    /// after it if it ends in `=`. With nothing selected the cursor's line is
+    /// the expression, and its result is written or updated after an `=`.
+    fn evaluate_selection(&mut self) {
+        let today = chrono::Local::now().date_naive();
+        let selection = self.selection_range();
+        let (start, end) = match selection {
+            Some((start, end)) if start.row != end.row => {
+                self.hover = Some("Select an expression on one line".to_string());
+                return;
+            }
+            Some(range) => range,
+            None => {
+                let row = self.state.cursor.row;
+                let line = &self.state.lines[row];
+                // Anything after an earlier `=` is the old result
+                let expression = line.split('=').next().unwrap_or_default().trim_end();
+                (
+                    BufferPosition::new(row, 0),
+                    BufferPosition::new(row, expression.len()),
+                )
+            }
+        };
+        let text = &self.state.lines[start.row][start.column..end.column];
+        let appended = text.trim_end().strip_suffix('=');
+        let space = if text.ends_with(' ') { "" } else { " " };
+        let evaluated = calc::evaluate(appended.unwrap_or(text), today);
+        let appended = appended.is_some();
+        let result = match evaluated {
+            Ok(value) => value.to_string(),
+            Err(message) => {
+                self.hover = Some(format!("Can't evaluate: {}", message));
+                return;
+            }
+        };
+
+        self.push_undo_checkpoint();
+        self.clear_selection();
+        if selection.is_none() {
             let line = &mut self.state.lines[start.row];
-            line.replace_range(start.column..end.column, "");
+            line.truncate(end.column);
+            line.push_str(" = ");
+            line.push_str(&result);
+            self.state.cursor = BufferPosition::new(start.row, line.len());
+        } else if appended {
+            self.state.cursor = end;
+            self.insert_text(&format!("{}{}", space, result));
         } else {
-            let first_part = self.state.lines[start.row][..start.column].to_string();
-            let last_part = self.state.lines[end.row][end.column..].to_string();
-            self.state.lines[start.row] = first_part + &last_part;
-            self.state.lines.drain((start.row + 1)..=(end.row));
+            self.delete_range(start, end);
+            self.state.cursor = start;
+            self.insert_text(&result);
         }
+        self.last_edit_time = None;
     }
 
-    fn type_character(&mut self, c: char) {
-        self.push_undo_state();
-        self.mark_edit_time();
-        self.delete_selection();
-
-        if c == '\n' {
-            let line = self.state.lines[self.state.cursor.row].clone();
-            let (before, after) = line.split_at(self.state.cursor.column);
-            self.state.lines[self.state.cursor.row] = before.to_string();
-            self.state
-                .lines
-                .insert(self.state.cursor.row + 1, after.to_string());
-            self.state.cursor = BufferPosition::new(self.state.cursor.row + 1, 0);
-        } else {
-            self.state.lines[self.state.cursor.row].insert(self.state.cursor.column, c);
-            self.state.cursor.column += c.len_utf8();
+    fn align_on(&mut self, delimiter: &str) {
+        let (start, end) = match self.selection_range() {
+            // A selection ending at the start of a line doesn't take it in
+            Some((start, end)) if end.column == 0 && end.row > start.row => {
+                (start.row, end.row - 1)
+            }
+            Some((start, end)) => (start.row, end.row),
+            None => {
+                let (start, end) =
+                    text_objects::paragraph_range(&self.state.lines, self.state.cursor.row);
+                (start.row, end.row)
+            }
+        };
+        let aligned = align::align(&self.state.lines[start..=end], delimiter);
+        if aligned[..] == self.state.lines[start..=end] {
+            let found = self.state.lines[start..=end]
+                .iter()
+                .any(|line| line.contains(delimiter));
+            if delimiter.is_empty() || !found {
+                self.hover = Some(format!("No lines contain '{}'", delimiter));
+            }
+            return;
         }
+
+        self.push_undo_checkpoint();
+        let had_selection = self.state.selection_anchor.is_some();
+        self.state.lines.splice(start..=end, aligned);
+        self.state.selection_anchor = had_selection.then(|| BufferPosition::new(start, 0));
+        self.state.cursor = BufferPosition::new(end, self.state.lines[end].len());
+        self.last_edit_time = None;
     }
 
-    fn type_string(&mut self, s: &str) {
+    /// Bytes of the selection, or of the whole buffer, with where it starts
+    fn structured_range(&self, text: &str) -> (Range<usize>, BufferPosition) {
+        let (start, end) = match self.selection_range() {
+            Some((start, end)) => (start, end),
+            None => (
+                BufferPosition::zero(),
+                self.state.offset_to_position(text.len()),
+            ),
+        };
+        let range = self.state.position_to_offset(start)..self.state.position_to_offset(end);
+        (range, start)
+    }
+
+    /// Replace the selection, or the whole buffer, with what `change` makes
+    /// of it, as one undo step; if it isn't valid `format`, mark where
+    /// parsing failed and say why
+    fn reformat(
+        &mut self,
+        format: Format,
+        change: impl FnOnce(&str) -> Result<String, pretty::ParseError>,
+    ) {
+        let text = self.state.to_string();
+        let (range, start) = self.structured_range(&text);
+        match change(&text[range.clone()]) {
+            Err(e) => {
+                let message = format!("Not valid {}: {}", format.name(), e.message);
+                let at = self.state.offset_to_position(range.start + e.offset);
+                let end = self.state.lines[at.row]
+                    .get(at.column..)
+                    .and_then(|rest| rest.chars().next())
+                    .map_or(at, |c| {
+                        BufferPosition::new(at.row, at.column + c.len_utf8())
+                    });
+                let diagnostic = Diagnostic {
+                    start: at,
+                    end,
+                    severity: diagnostics::Severity::Error,
+                    message: message.clone(),
+                    source: pretty::SOURCE.to_string(),
+                    replacements: Vec::new(),
+                };
+                self.set_diagnostics(pretty::SOURCE, vec![diagnostic]);
+                self.hover = Some(message);
+            }
+            Ok(changed) => {
+                self.set_diagnostics(pretty::SOURCE, Vec::new());
+                if changed != text[range.clone()] {
+                    let text = [&text[..range.start], &changed, &text[range.end..]].concat();
+                    self.replace_lines(text.split('\n').map(String::from).collect(), start);
+                    self.last_edit_time = None;
+                }
+            }
+        }
+    }
+
+    /// Sort the keys in the selection, or the whole buffer, in whichever
+    /// format it is written
+    fn sort_keys(&mut self) {
+        let text = self.state.to_string();
+        let (range, _) = self.structured_range(&text);
+        let format = Format::detect(&text[range]);
+        let indent = self.config.format.indent;
+        self.reformat(format, |text| pretty::sort_keys(text, format, indent));
+    }
+
+    /// Replace the selection with `transform` of it, or put the result on
+    /// the lines after it, as one undo step
+    fn transform_selection(&mut self, transform: Transform) {
+        let Some((start, end)) = self.selection_range() else {
+            self.hover = Some(format!("Select the text for {}", transform.label()));
+            return;
+        };
+        let text = self.state.to_string();
+        let range = self.state.position_to_offset(start)..self.state.position_to_offset(end);
+        let result = match transform.apply(&text[range.clone()]) {
+            Ok(result) => result,
+            Err(message) => {
+                self.hover = Some(message);
+                return;
+            }
+        };
+        let (at, inserted) = if transform.replaces() {
+            (range, result)
+        } else {
+            let line_end = self.state.position_to_offset(BufferPosition::new(
+                end.row,
+                self.state.lines[end.row].len(),
+            ));
+            (line_end..line_end, format!("\n{}", result))
+        };
+        let changed = [&text[..at.start], &inserted, &text[at.end..]].concat();
+        self.replace_lines(changed.split('\n').map(String::from).collect(), start);
+        self.state.cursor = self.state.offset_to_position(at.start + inserted.len());
+        if transform.replaces() {
+            self.state.selection_anchor = Some(start);
+        }
+        self.last_edit_time = None;
+    }
+
+    /// Add `amount` to the number at or after the cursor, or to the first
+    /// number on each selected line, `amount` more on each line after the
+    /// first with `sequence`, as one undo step
+    fn increment_numbers(&mut self, amount: i64, sequence: bool) {
+        let changes = self.number_changes(amount, sequence);
+        if changes.is_empty() {
+            self.hover = Some("No number at the cursor".to_string());
+            return;
+        }
+        self.push_undo_checkpoint();
+        self.change_numbers(changes);
+        self.last_edit_time = None;
+    }
+
+    /// The numbers [`Self::increment_numbers`] changes, as the row and
+    /// bytes of each and what it becomes
+    fn number_changes(&self, amount: i64, sequence: bool) -> Vec<(usize, Range<usize>, String)> {
+        let lines = &self.state.lines;
+        let Some((start, end)) = self.selection_range() else {
+            let cursor = self.state.cursor;
+            let line = &lines[cursor.row];
+            return numbers::at(line, cursor.column)
+                .map(|number| {
+                    (
+                        cursor.row,
+                        number.range.clone(),
+                        number.adjusted(line, amount),
+                    )
+                })
+                .into_iter()
+                .collect();
+        };
+        let mut changes = Vec::new();
+        let mut delta = amount;
+        for (row, line) in lines.iter().enumerate().take(end.row + 1).skip(start.row) {
+            let from = if row == start.row { start.column } else { 0 };
+            let to = if row == end.row {
+                end.column
+            } else {
+                line.len()
+            };
+            if let Some(number) = numbers::within(line, from..to) {
+                changes.push((row, number.range.clone(), number.adjusted(line, delta)));
+                if sequence {
+                    delta = delta.saturating_add(amount);
+                }
+            }
+        }
+        changes
+    }
+
+    /// Write `changes` in; without a selection the cursor goes to the
+    /// number's last character, and a selection stays on the same lines
+    fn change_numbers(&mut self, changes: Vec<(usize, Range<usize>, String)>) {
+        let selection = self.selection_range();
+        let forward = self
+            .state
+            .selection_anchor
+            .is_some_and(|anchor| anchor <= self.state.cursor);
+        let mut end_column = selection.map(|(_, end)| end.column);
+        for (row, range, number) in &changes {
+            // The selection's end moves along with a number before it, and
+            // stays within one it ends inside of
+            if let Some((_, end)) = selection.filter(|(_, end)| end.row == *row) {
+                end_column = Some(if range.end <= end.column {
+                    (end.column + number.len()).saturating_sub(range.len())
+                } else {
+                    end.column.min(range.start + number.len())
+                });
+            }
+            self.state.lines[*row].replace_range(range.clone(), number);
+        }
+        match selection.zip(end_column) {
+            Some(((start, end), column)) => {
+                let end = self.clamp_position(end.row, column);
+                let (anchor, cursor) = if forward { (start, end) } else { (end, start) };
+                self.state.selection_anchor = Some(anchor);
+                self.state.cursor = cursor;
+            }
+            None => {
+                if let Some((row, range, number)) = changes.first() {
+                    let column = range.start + number.len().saturating_sub(1);
+                    self.state.cursor = BufferPosition::new(*row, column);
+                }
+            }
+        }
+    }
+
+    /// Change the table at the cursor and write it back with its columns
+    /// lined up, as one undo step; `change` moves the cursor's cell along
+    /// with it or says why it can't be done
+    fn edit_table(
+        &mut self,
+        change: impl FnOnce(&mut Table, &mut CellPosition) -> Result<(), &'static str>,
+    ) {
+        let cursor = self.state.cursor;
+        let Some(mut table) =
+            tables::table_at(&self.state.lines, cursor.row).filter(|_| !self.large_file)
+        else {
+            self.hover = Some("The cursor isn't in a table".to_string());
+            return;
+        };
+        let mut cell = table.cell_at(&self.state.lines, cursor);
+        if let Err(message) = change(&mut table, &mut cell) {
+            self.hover = Some(message.to_string());
+            return;
+        }
+        self.write_table(&table, cell, true);
+    }
+
+    /// Put `table` in place of its lines, if they differ, and the cursor in
+    /// `cell`
+    fn write_table(&mut self, table: &Table, cell: CellPosition, checkpoint: bool) {
+        let lines = table.render();
+        if self.state.lines[table.rows.clone()] != lines[..] {
+            if checkpoint {
+                self.push_undo_checkpoint();
+                self.last_edit_time = None;
+            }
+            self.state.lines.splice(table.rows.clone(), lines);
+        }
+        self.clear_selection();
+        self.state.cursor = table.position_of(cell);
+    }
+
+    /// Move to the end of the next or previous cell when in a table, adding
+    /// a row after the last one
+    fn move_to_cell(&mut self, forward: bool) -> bool {
+        let cursor = self.state.cursor;
+        if self.large_file {
+            return false;
+        }
+        let Some(mut table) = tables::table_at(&self.state.lines, cursor.row) else {
+            return false;
+        };
+        let mut cell = table.cell_at(&self.state.lines, cursor);
+        cell.offset = usize::MAX;
+        if forward {
+            cell.column += 1;
+            if cell.column == table.columns() {
+                cell.column = 0;
+                cell.row += 1;
+                if cell.row == table.row_count() {
+                    table.insert_row(cell.row);
+                }
+            }
+        } else if cell.column > 0 {
+            cell.column -= 1;
+        } else if cell.row > 0 {
+            cell.row -= 1;
+            cell.column = table.columns() - 1;
+        }
+        self.write_table(&table, cell, true);
+        true
+    }
+
+    /// Line up the columns of the table being typed in again, as part of
+    /// the edit
+    fn align_table(&mut self) {
+        let cursor = self.state.cursor;
+        if self.large_file || self.selection_range().is_some() {
+            return;
+        }
+        let Some(mut table) = tables::table_at(&self.state.lines, cursor.row) else {
+            return;
+        };
+        // The delimiter row is left alone while it is being written
+        if cursor.row == table.rows.start + 1 {
+            return;
+        }
+        let cell = table.cell_at(&self.state.lines, cursor);
+        table.keep_typed_spaces(&self.state.lines, cell);
+        self.write_table(&table, cell, false);
+    }
+
+    fn selection_range(&self) -> Option<(BufferPosition, BufferPosition)> {
+        self.state.selection().map(|range| (range.start, range.end))
+    }
+
+    fn clear_selection(&mut self) {
+        self.state.selection_anchor = None;
+    }
+
+    fn delete_selection(&mut self) {
+        if let Some((start, end)) = self.selection_range() {
+            self.delete_range(start, end);
+            self.state.cursor = start;
+            self.clear_selection();
+        }
+    }
+
+    fn delete_range(&mut self, start: BufferPosition, end: BufferPosition) {
+        if start.row == end.row {
+            let line = &mut self.state.lines[start.row];
+            line.replace_range(start.column..end.column, "");
+        } else {
+            let first_part = self.state.lines[start.row][..start.column].to_string();
+            let last_part = self.state.lines[end.row][end.column..].to_string();
+            self.state.lines[start.row] = first_part + &last_part;
+            self.state.lines.drain((start.row + 1)..=(end.row));
+        }
+    }
+
+    fn type_character(&mut self, c: char) {
+        if c == ' ' && self.selection_range().is_none() {
+            self.expand_abbreviation();
+        }
+        if self.type_typographic(c) {
+            return;
+        }
+        self.push_undo_state();
+        self.mark_edit_time();
+        self.delete_selection();
+
+        if c == '\n' {
+            let line = self.state.lines[self.state.cursor.row].clone();
+            let (before, after) = line.split_at(self.state.cursor.column);
+            self.state.lines[self.state.cursor.row] = before.to_string();
+            self.state
+                .lines
+                .insert(self.state.cursor.row + 1, after.to_string());
+            self.state.cursor = BufferPosition::new(self.state.cursor.row + 1, 0);
+        } else {
+            self.state.lines[self.state.cursor.row].insert(self.state.cursor.column, c);
+            self.state.cursor.column += c.len_utf8();
+        }
+    }
+
+    fn type_string(&mut self, s: &str) {
+        // The GUI types single keys as strings
+        if s == " " && self.selection_range().is_none() {
+            self.expand_abbreviation();
+        }
+        let mut chars = s.chars();
+        if let (Some(c), None) = (chars.next(), chars.next()) {
+            if self.type_typographic(c) {
+                return;
+            }
+        }
         self.push_undo_state();
         self.mark_edit_time();
         self.delete_selection();
+        self.insert_text(s);
+    }
+
+    /// Insert pasted text in place of the selection as one undo step,
+    /// re-indented to fit or as plain text
+    fn paste(&mut self, text: &str, plain: bool) {
+        if text.is_empty() {
+            return;
+        }
+        self.push_undo_checkpoint();
+        self.delete_selection();
+        let cursor = self.state.cursor;
+        let text = if plain {
+            paste::plain(text)
+        } else {
+            paste::reindent(text, &self.state.lines[cursor.row][..cursor.column])
+        };
+        self.insert_text(&text);
+        self.last_edit_time = None;
+    }
 
+    /// Insert text at the cursor without touching undo history or the selection
+    fn insert_text(&mut self, s: &str) {
         for c in s.chars() {
             if c == '\n' {
                 let line = self.state.lines[self.state.cursor.row].clone();
@@ -194,12 +1577,7 @@ impl EditorEngine {
             self.state.cursor = start;
             self.clear_selection();
         } else if self.state.cursor.column > 0 {
-            let line = &self.state.lines[self.state.cursor.row];
-            let before = &line[..self.state.cursor.column];
-            if let Some((last_char_start, _)) = before.char_indices().last() {
-                self.state.lines[self.state.cursor.row].remove(last_char_start);
-                self.state.cursor.column = last_char_start;
-            }
+            self.backspace_char();
         } else if self.state.cursor.row > 0 {
             let current_line = self.state.lines.remove(self.state.cursor.row);
             self.state.cursor.row -= 1;
@@ -208,6 +1586,16 @@ impl EditorEngine {
         }
     }
 
+    /// Delete the character before the cursor on its line
+    fn backspace_char(&mut self) {
+        let line = &self.state.lines[self.state.cursor.row];
+        let before = &line[..self.state.cursor.column];
+        if let Some((last_char_start, _)) = before.char_indices().last() {
+            self.state.lines[self.state.cursor.row].remove(last_char_start);
+            self.state.cursor.column = last_char_start;
+        }
+    }
+
     fn delete(&mut self) {
         self.push_undo_state();
         self.mark_edit_time();
@@ -297,6 +1685,9 @@ impl EditorEngine {
 
     fn move_left(&mut self) {
         self.clear_selection();
+        if self.move_visually(true) {
+            return;
+        }
         if self.state.cursor.column > 0 {
             let line = &self.state.lines[self.state.cursor.row];
             let before = &line[..self.state.cursor.column];
@@ -311,6 +1702,9 @@ impl EditorEngine {
 
     fn move_right(&mut self) {
         self.clear_selection();
+        if self.move_visually(false) {
+            return;
+        }
         let line_len = self.state.lines[self.state.cursor.row].len();
         if self.state.cursor.column < line_len {
             let after = &self.state.lines[self.state.cursor.row][self.state.cursor.column..];
@@ -323,21 +1717,41 @@ impl EditorEngine {
         }
     }
 
+    /// Move through a line with right-to-left text in the order it is shown,
+    /// if that is configured; false when moving in typing order instead
+    fn move_visually(&mut self, left: bool) -> bool {
+        let cursor = self.state.cursor;
+        let line = &self.state.lines[cursor.row];
+        if !self.config.cursor.visual_movement || !bidi::has_rtl(line) {
+            return false;
+        }
+        match bidi::Layout::new(line).move_visually(cursor.column, left) {
+            Some(column) => self.state.cursor.column = column,
+            None if left && cursor.row > 0 => {
+                let row = cursor.row - 1;
+                self.state.cursor = BufferPosition::new(row, self.state.lines[row].len());
+            }
+            None if !left && cursor.row + 1 < self.state.lines.len() => {
+                self.state.cursor = BufferPosition::new(cursor.row + 1, 0);
+            }
+            None => {}
+        }
+        true
+    }
+
     fn move_up(&mut self) {
         self.clear_selection();
         if self.state.cursor.row > 0 {
-            self.state.cursor.row -= 1;
-            let line_len = self.state.lines[self.state.cursor.row].len();
-            self.state.cursor.column = self.state.cursor.column.min(line_len);
+            self.state.cursor =
+                self.clamp_position(self.state.cursor.row - 1, self.state.cursor.column);
         }
     }
 
     fn move_down(&mut self) {
         self.clear_selection();
         if self.state.cursor.row + 1 < self.state.lines.len() {
-            self.state.cursor.row += 1;
-            let line_len = self.state.lines[self.state.cursor.row].len();
-            self.state.cursor.column = self.state.cursor.column.min(line_len);
+            self.state.cursor =
+                self.clamp_position(self.state.cursor.row + 1, self.state.cursor.column);
         }
     }
 
@@ -362,29 +1776,12 @@ impl EditorEngine {
             return;
         }
 
+        // Skip whitespace, then the word before it
         let line = &self.state.lines[self.state.cursor.row];
-        let mut pos = self.state.cursor.column;
-
-        // Skip whitespace
-        while pos > 0
-            && line
-                .chars()
-                .nth(pos - 1)
-                .map_or(false, |c| c.is_whitespace())
-        {
-            pos -= 1;
-        }
-
-        // Skip word characters
-        while pos > 0 {
-            let ch = line.chars().nth(pos - 1);
-            if ch.map_or(false, |c| !c.is_alphanumeric() && c != '_') {
-                break;
-            }
-            pos -= 1;
-        }
-
-        self.state.cursor.column = pos;
+        let before = line[..self.state.cursor.column]
+            .trim_end_matches(char::is_whitespace)
+            .trim_end_matches(text_objects::is_word_char);
+        self.state.cursor.column = before.len();
     }
 
     fn move_word_right(&mut self) {
@@ -400,30 +1797,37 @@ impl EditorEngine {
             return;
         }
 
-        let mut pos = self.state.cursor.column;
-
-        // Skip current word
-        while pos < line.len() {
-            let ch = line.chars().nth(pos);
-            if ch.map_or(false, |c| !c.is_alphanumeric() && c != '_') {
-                break;
-            }
-            pos += 1;
-        }
-
-        // Skip whitespace
-        while pos < line.len() && line.chars().nth(pos).map_or(false, |c| c.is_whitespace()) {
-            pos += 1;
-        }
+        // Skip the current word, then the whitespace after it
+        let after = line[self.state.cursor.column..]
+            .trim_start_matches(text_objects::is_word_char)
+            .trim_start_matches(char::is_whitespace);
+        self.state.cursor.column = line.len() - after.len();
+    }
 
-        self.state.cursor.column = pos;
+    /// Move to where the next or previous sentence starts, or to the end
+    /// or start of the document past the last or first
+    fn move_to_sentence(&mut self, next: bool) {
+        self.clear_selection();
+        let lines = &self.state.lines;
+        let cursor = self.state.cursor;
+        self.state.cursor = if next {
+            sentences::next_start(lines, cursor)
+                .unwrap_or_else(|| text_objects::document_range(lines).1)
+        } else {
+            sentences::prev_start(lines, cursor).unwrap_or_else(BufferPosition::zero)
+        };
     }
 
     fn undo(&mut self) {
+        if self.large_file {
+            self.hover = Some("Undo is off for large files".to_string());
+            return;
+        }
         if let Some(prev_state) = self.undo_stack.pop() {
             self.redo_stack.push(self.state.clone_for_undo());
             self.state = prev_state;
             self.last_edit_time = None;
+            self.revision += 1;
         }
     }
 
@@ -432,6 +1836,7 @@ impl EditorEngine {
             self.undo_stack.push(self.state.clone_for_undo());
             self.state = next_state;
             self.last_edit_time = None;
+            self.revision += 1;
         }
     }
 
@@ -456,6 +1861,7 @@ impl EditorEngine {
     fn delete_to_beginning_of_line(&mut self) {
         self.push_undo_state();
         self.last_edit_time = None;
+        self.clear_selection();
         self.state.lines[self.state.cursor.row].replace_range(..self.state.cursor.column, "");
         self.state.cursor.column = 0;
     }
@@ -463,6 +1869,7 @@ impl EditorEngine {
     fn delete_to_end_of_line(&mut self) {
         self.push_undo_state();
         self.last_edit_time = None;
+        self.clear_selection();
         self.state.lines[self.state.cursor.row].replace_range(self.state.cursor.column.., "");
     }
 
@@ -497,6 +1904,7 @@ impl EditorEngine {
         }
         self.push_undo_state();
         self.last_edit_time = None;
+        self.clear_selection();
         self.state
             .lines
             .swap(self.state.cursor.row, self.state.cursor.row - 1);
@@ -509,13 +1917,141 @@ impl EditorEngine {
         }
         self.push_undo_state();
         self.last_edit_time = None;
+        self.clear_selection();
         self.state
             .lines
             .swap(self.state.cursor.row, self.state.cursor.row + 1);
         self.state.cursor.row += 1;
     }
 
+    fn move_section(&mut self, down: bool) {
+        let cursor = self.state.cursor;
+        match sections::move_section(&self.state.lines, cursor.row, down) {
+            Ok((lines, row)) => self.replace_lines(lines, BufferPosition::new(row, cursor.column)),
+            Err(message) => self.hover = Some(message.to_string()),
+        }
+    }
+
+    fn shift_section(&mut self, promote: bool) {
+        let cursor = self.state.cursor;
+        match sections::shift_levels(&self.state.lines, cursor.row, promote) {
+            Ok(lines) => {
+                // The cursor stays on the same text of a heading it's in
+                let grown =
+                    lines[cursor.row].len() as isize - self.state.lines[cursor.row].len() as isize;
+                let column = cursor.column.saturating_add_signed(grown);
+                self.replace_lines(lines, BufferPosition::new(cursor.row, column));
+            }
+            Err(message) => self.hover = Some(message.to_string()),
+        }
+    }
+
+    fn select_section(&mut self) {
+        let sections = sections::tree(&self.state.lines);
+        let Some(section) = sections::at(&sections, self.state.cursor.row) else {
+            self.hover = Some("Not in a section".to_string());
+            return;
+        };
+        let end = match self.state.lines.get(section.end) {
+            Some(_) => BufferPosition::new(section.end, 0),
+            None => {
+                let row = self.state.lines.len() - 1;
+                BufferPosition::new(row, self.state.lines[row].len())
+            }
+        };
+        self.state.selection_anchor = Some(BufferPosition::new(section.row, 0));
+        self.state.cursor = end;
+    }
+
+    /// Refer to a new footnote after the selection, or at the cursor, and
+    /// put the cursor where its definition is written
+    fn insert_footnote(&mut self) {
+        let at = self
+            .selection_range()
+            .map_or(self.state.cursor, |(_, end)| end);
+        let (lines, cursor) = footnotes::insert(&self.state.lines, at);
+        self.replace_lines(lines, cursor);
+    }
+
+    fn renumber_footnotes(&mut self) {
+        let (lines, _) = footnotes::renumber(&self.state.lines);
+        if lines == self.state.lines {
+            self.hover = Some("Footnotes are already in order".to_string());
+        } else {
+            self.replace_lines(lines, self.state.cursor);
+        }
+    }
+
+    fn go_to_footnote(&mut self) {
+        match footnotes::counterpart(&self.state.lines, self.state.cursor) {
+            Some(position) => {
+                self.clear_selection();
+                self.state.cursor = position;
+            }
+            None => self.hover = Some("No footnote to go to".to_string()),
+        }
+    }
+
+    /// Show the reference cited at the cursor; other hovers come from a
+    /// language server
+    fn hover_citation(&mut self) {
+        let line = &self.state.lines[self.state.cursor.row];
+        let Some(key) = citations::key_at(line, self.state.cursor.column) else {
+            return;
+        };
+        self.hover = Some(
+            match self
+                .references
+                .iter()
+                .find(|reference| reference.key == key)
+            {
+                Some(reference) => reference.summary(),
+                None => format!("@{} is not in the bibliography", key),
+            },
+        );
+    }
+
+    /// List the cited references at the cursor, on its line if that's
+    /// blank and otherwise after it
+    fn insert_bibliography(&mut self) {
+        let (entries, missing) = citations::bibliography(&self.state.lines, &self.references);
+        if !missing.is_empty() {
+            let keys: Vec<String> = missing.iter().map(|key| format!("@{}", key)).collect();
+            self.hover = Some(format!("Not in the bibliography: {}", keys.join(", ")));
+        }
+        if entries.is_empty() {
+            if missing.is_empty() {
+                self.hover = Some("No citations to list".to_string());
+            }
+            return;
+        }
+        let row = self.state.cursor.row;
+        let mut lines = self.state.lines.clone();
+        let blank = lines[row].trim().is_empty();
+        let at = if blank { row } else { row + 1 };
+        let mut inserted = if blank { vec![] } else { vec![String::new()] };
+        inserted.extend(entries);
+        let last = at + inserted.len() - 1;
+        let end = BufferPosition::new(last, inserted[inserted.len() - 1].len());
+        lines.splice(at..at + usize::from(blank), inserted);
+        self.replace_lines(lines, end);
+    }
+
     fn tab(&mut self) {
+        if self.active_snippet.is_some() {
+            self.move_to_tab_stop(1);
+            return;
+        }
+        if self.selection_range().is_none() && self.move_to_cell(true) {
+            return;
+        }
+        if self.selection_range().is_none() && self.expand_snippet() {
+            return;
+        }
+        if self.selection_range().is_none() && self.expand_abbreviation() {
+            return;
+        }
+
         self.push_undo_state();
         self.last_edit_time = None;
 
@@ -532,10 +2068,19 @@ impl EditorEngine {
     }
 
     fn outdent(&mut self) {
+        if self.active_snippet.is_some() {
+            self.move_to_tab_stop(-1);
+            return;
+        }
+        if self.selection_range().is_none() && self.move_to_cell(false) {
+            return;
+        }
+
         self.push_undo_state();
         self.last_edit_time = None;
 
         if let Some((start, end)) = self.selection_range() {
+            let mut removed = vec![0; end.row - start.row + 1];
             for row in start.row..=end.row {
                 let spaces_to_remove = self.state.lines[row]
                     .chars()
@@ -545,9 +2090,10 @@ impl EditorEngine {
                 if spaces_to_remove > 0 {
                     self.state.lines[row].replace_range(..spaces_to_remove, "");
                 }
+                removed[row - start.row] = spaces_to_remove;
             }
-            let new_start_col = start.column.saturating_sub(4);
-            let new_end_col = end.column.saturating_sub(4);
+            let new_start_col = start.column.saturating_sub(removed[0]);
+            let new_end_col = end.column.saturating_sub(removed[end.row - start.row]);
             self.state.selection_anchor = Some(BufferPosition::new(start.row, new_start_col));
             self.state.cursor = BufferPosition::new(end.row, new_end_col);
         } else {
@@ -564,35 +2110,344 @@ impl EditorEngine {
         }
     }
 
-    fn select_left(&mut self) {
-        if self.state.selection_anchor.is_none() {
-            self.state.selection_anchor = Some(self.state.cursor);
-        }
-        if self.state.cursor.column > 0 {
-            let line = &self.state.lines[self.state.cursor.row];
-            let before = &line[..self.state.cursor.column];
-            if let Some(prev_char) = before.chars().last() {
-                self.state.cursor.column -= prev_char.len_utf8();
-            }
-        } else if self.state.cursor.row > 0 {
-            self.state.cursor.row -= 1;
-            self.state.cursor.column = self.state.lines[self.state.cursor.row].len();
-        }
+    /// Replace the abbreviation before the cursor with its expansion, as an
+    /// edit of its own so undo brings the abbreviation back
+    fn expand_abbreviation(&mut self) -> bool {
+        let cursor = self.state.cursor;
+        let line = &self.state.lines[cursor.row];
+        let Some((abbreviation, expansion)) =
+            snippets::find_abbreviation(&self.config.abbreviations, line, cursor.column)
+        else {
+            return false;
+        };
+        let start = BufferPosition::new(cursor.row, cursor.column - abbreviation.len());
+        let expansion = expansion.to_string();
+
+        self.push_undo_checkpoint();
+        self.delete_range(start, cursor);
+        self.state.cursor = start;
+        self.insert_text(&expansion);
+        self.last_edit_time = None;
+        true
     }
 
-    fn select_right(&mut self) {
-        if self.state.selection_anchor.is_none() {
-            self.state.selection_anchor = Some(self.state.cursor);
-        }
-        let line_len = self.state.lines[self.state.cursor.row].len();
-        if self.state.cursor.column < line_len {
-            let after = &self.state.lines[self.state.cursor.row][self.state.cursor.column..];
-            if let Some(next_char) = after.chars().next() {
-                self.state.cursor.column += next_char.len_utf8();
-            }
-        } else if self.state.cursor.row + 1 < self.state.lines.len() {
-            self.state.cursor.row += 1;
-            self.state.cursor.column = 0;
+    /// Type `c` as its typographic form, as one undo step with the text it
+    /// replaces; false when it is typed as it is
+    fn type_typographic(&mut self, c: char) -> bool {
+        let cursor = self.state.cursor;
+        if !self.config.typography.enabled
+            || !self.prose
+            || self.selection_range().is_some()
+            || markdown::in_code_block(&self.state.lines, cursor.row)
+            || frontmatter::block_rows(&self.state.lines)
+                .is_some_and(|rows| rows.contains(&cursor.row))
+        {
+            return false;
+        }
+        let before = &self.state.lines[cursor.row][..cursor.column];
+        let Some((replaced, text)) = typography::substitute(before, c) else {
+            return false;
+        };
+        let start = BufferPosition::new(cursor.row, cursor.column - replaced);
+
+        self.push_undo_checkpoint();
+        self.delete_range(start, cursor);
+        self.state.cursor = start;
+        self.insert_text(&text);
+        self.last_edit_time = None;
+        true
+    }
+
+    /// Type the current date and time in a strftime format
+    fn insert_now(&mut self, format: &str) {
+        let now = chrono::Local::now().naive_local();
+        let Some(text) = crate::templates::format_datetime(now.date(), now.time(), format) else {
+            self.hover = Some(format!("Bad date format {:?}", format));
+            return;
+        };
+        self.insert_as_step(&text);
+    }
+
+    /// Replace the selection with `text` as an undo step of its own, apart
+    /// from any typing around it
+    fn insert_as_step(&mut self, text: &str) {
+        if text.is_empty() {
+            return;
+        }
+        self.push_undo_checkpoint();
+        self.delete_selection();
+        self.insert_text(text);
+        self.last_edit_time = None;
+    }
+
+    /// Replace the trigger word before the cursor with its snippet, if any
+    fn expand_snippet(&mut self) -> bool {
+        let cursor = self.state.cursor;
+        let line = &self.state.lines[cursor.row];
+        let Some(snippet) = snippets::find_trigger(&self.config.snippets, line, cursor.column)
+        else {
+            return false;
+        };
+
+        // Continuation lines inherit the indentation of the trigger line
+        let indent: String = line.chars().take_while(|c| c.is_whitespace()).collect();
+        let body = snippet.body.replace('\n', &format!("\n{}", indent));
+        let parsed = snippets::parse_snippet(&body);
+        let start = BufferPosition::new(cursor.row, cursor.column - snippet.trigger.len());
+
+        self.push_undo_checkpoint();
+        self.delete_range(start, cursor);
+        self.state.cursor = start;
+        self.insert_text(&parsed.text);
+
+        let base_offset = self.state.position_to_offset(start);
+        self.active_snippet = Some(ActiveSnippet::new(base_offset, &parsed));
+        self.select_current_tab_stop();
+        true
+    }
+
+    fn move_to_tab_stop(&mut self, step: isize) {
+        if let Some(snippet) = self.active_snippet.as_mut() {
+            snippet.current = snippet
+                .current
+                .saturating_add_signed(step)
+                .min(snippet.stops.len() - 1);
+        }
+        self.select_current_tab_stop();
+    }
+
+    fn select_current_tab_stop(&mut self) {
+        let Some(snippet) = &self.active_snippet else {
+            return;
+        };
+        let range = snippet.current_range();
+        let is_last = snippet.is_on_last_stop();
+
+        let start = self.state.offset_to_position(range.start);
+        let end = self.state.offset_to_position(range.end);
+        self.state.selection_anchor = (start != end).then_some(start);
+        self.state.cursor = end;
+
+        if is_last {
+            self.active_snippet = None;
+        }
+    }
+
+    /// Keep tab stops in sync with edits made inside the current placeholder,
+    /// leaving snippet mode once the cursor moves outside of it
+    fn track_active_snippet(&mut self, len_before: usize) {
+        let delta = self.state.text_len() as isize - len_before as isize;
+        let cursor_offset = self.state.position_to_offset(self.state.cursor);
+
+        if let Some(snippet) = self.active_snippet.as_mut() {
+            snippet.apply_edit_delta(delta);
+            let range = snippet.current_range();
+            if cursor_offset < range.start || cursor_offset > range.end {
+                self.active_snippet = None;
+            }
+        }
+    }
+
+    /// Recompute completion candidates for the word before the cursor.
+    /// `force` opens the popup even when auto-completion is disabled or the
+    /// prefix is shorter than the configured minimum.
+    fn update_completion(&mut self, force: bool) {
+        self.completion = None;
+        let settings = &self.config.completion;
+        if self.state.selection_anchor.is_some() || !(force || settings.enabled) {
+            return;
+        }
+
+        let cursor = self.state.cursor;
+        let line = &self.state.lines[cursor.row];
+        if let Some(start) = notes::open_link_start(line, cursor.column) {
+            let prefix = &line[start..cursor.column];
+            // Right after "[[" every note is offered, in name order
+            let items = if prefix.is_empty() {
+                self.note_names
+                    .iter()
+                    .take(settings.max_items)
+                    .cloned()
+                    .collect()
+            } else {
+                let candidates = self.note_names.iter().map(String::as_str);
+                completion::rank(candidates, prefix, settings.max_items)
+            };
+            self.completion = (!items.is_empty()).then(|| Completion {
+                start: BufferPosition::new(cursor.row, start),
+                prefix: prefix.to_string(),
+                items,
+                selected: 0,
+            });
+            return;
+        }
+        if let Some(start) = citations::open_citation_start(line, cursor.column)
+            .filter(|_| !self.references.is_empty())
+        {
+            let prefix = &line[start..cursor.column];
+            let keys = self
+                .references
+                .iter()
+                .map(|reference| reference.key.as_str());
+            let items = if prefix.is_empty() {
+                keys.take(settings.max_items).map(String::from).collect()
+            } else {
+                completion::rank(keys, prefix, settings.max_items)
+            };
+            self.completion = (!items.is_empty()).then(|| Completion {
+                start: BufferPosition::new(cursor.row, start),
+                prefix: prefix.to_string(),
+                items,
+                selected: 0,
+            });
+            return;
+        }
+        let start = completion::word_start(line, cursor.column);
+        let prefix = &line[start..cursor.column];
+
+        let min_prefix = if force { 0 } else { settings.min_prefix.max(1) };
+        if prefix.chars().count() < min_prefix {
+            return;
+        }
+
+        let words = completion::buffer_words(&self.state.lines, cursor);
+        let candidates = words
+            .iter()
+            .chain(self.dictionary.iter())
+            .map(String::as_str);
+        let items = completion::rank(candidates, prefix, settings.max_items);
+        if items.is_empty() {
+            return;
+        }
+
+        self.completion = Some(Completion {
+            start: BufferPosition::new(cursor.row, start),
+            prefix: prefix.to_string(),
+            items,
+            selected: 0,
+        });
+    }
+
+    fn select_completion(&mut self, step: isize) {
+        if let Some(completion) = self.completion.as_mut() {
+            completion.select_offset(step);
+        }
+    }
+
+    /// Replace the partial word before the cursor with the selected item
+    fn accept_completion(&mut self) {
+        let Some(completion) = self.completion.take() else {
+            return;
+        };
+        let Some(item) = completion.selected_item() else {
+            return;
+        };
+
+        self.push_undo_checkpoint();
+        self.delete_range(completion.start, self.state.cursor);
+        self.state.cursor = completion.start;
+        self.insert_text(item);
+        // Close a completed note link
+        let line = &self.state.lines[completion.start.row];
+        if line[..completion.start.column].ends_with("[[")
+            && !line[self.state.cursor.column..].starts_with("]]")
+        {
+            self.insert_text("]]");
+        }
+        self.last_edit_time = None;
+    }
+
+    /// Jump to the next or previous diagnostic and show its message
+    fn goto_diagnostic(&mut self, forward: bool) {
+        let cursor = self.state.cursor;
+        let target = if forward {
+            diagnostics::next_after(&self.diagnostics, cursor)
+        } else {
+            diagnostics::prev_before(&self.diagnostics, cursor)
+        };
+        let Some(target) = target else {
+            return;
+        };
+
+        let (start, message) = (target.start, target.message.clone());
+        self.set_cursor_position(start.row, start.column);
+        self.hover = Some(message);
+    }
+
+    /// List the replacements of the diagnostic under the cursor in the
+    /// completion popup; accepting one replaces the diagnostic's range
+    fn quick_fix(&mut self) {
+        let Some(diagnostic) = diagnostics::fixable_at(&self.diagnostics, self.state.cursor) else {
+            return;
+        };
+
+        let line_len = self
+            .state
+            .lines
+            .get(diagnostic.end.row)
+            .map_or(0, |l| l.len());
+        if diagnostic.end.column > line_len {
+            return;
+        }
+        self.state.selection_anchor = None;
+        self.state.cursor = diagnostic.end;
+        self.completion = Some(Completion {
+            start: diagnostic.start,
+            prefix: self.state.lines[diagnostic.start.row]
+                [diagnostic.start.column..diagnostic.end.column]
+                .to_string(),
+            items: diagnostic.replacements.clone(),
+            selected: 0,
+        });
+    }
+
+    /// Replace the range of the diagnostic under the cursor with `text`
+    fn apply_suggestion(&mut self, text: &str) {
+        let Some(diagnostic) = diagnostics::fixable_at(&self.diagnostics, self.state.cursor) else {
+            return;
+        };
+        let (start, end) = (diagnostic.start, diagnostic.end);
+        if end.column > self.state.lines[end.row].len() {
+            return;
+        }
+
+        self.push_undo_checkpoint();
+        self.state.selection_anchor = None;
+        self.delete_range(start, end);
+        self.state.cursor = start;
+        self.insert_text(text);
+        self.last_edit_time = None;
+    }
+
+    fn select_left(&mut self) {
+        if self.state.selection_anchor.is_none() {
+            self.state.selection_anchor = Some(self.state.cursor);
+        }
+        if self.state.cursor.column > 0 {
+            let line = &self.state.lines[self.state.cursor.row];
+            let before = &line[..self.state.cursor.column];
+            if let Some(prev_char) = before.chars().last() {
+                self.state.cursor.column -= prev_char.len_utf8();
+            }
+        } else if self.state.cursor.row > 0 {
+            self.state.cursor.row -= 1;
+            self.state.cursor.column = self.state.lines[self.state.cursor.row].len();
+        }
+    }
+
+    fn select_right(&mut self) {
+        if self.state.selection_anchor.is_none() {
+            self.state.selection_anchor = Some(self.state.cursor);
+        }
+        let line_len = self.state.lines[self.state.cursor.row].len();
+        if self.state.cursor.column < line_len {
+            let after = &self.state.lines[self.state.cursor.row][self.state.cursor.column..];
+            if let Some(next_char) = after.chars().next() {
+                self.state.cursor.column += next_char.len_utf8();
+            }
+        } else if self.state.cursor.row + 1 < self.state.lines.len() {
+            self.state.cursor.row += 1;
+            self.state.cursor.column = 0;
         }
     }
 
@@ -601,9 +2456,8 @@ impl EditorEngine {
             self.state.selection_anchor = Some(self.state.cursor);
         }
         if self.state.cursor.row > 0 {
-            self.state.cursor.row -= 1;
-            let line_len = self.state.lines[self.state.cursor.row].len();
-            self.state.cursor.column = self.state.cursor.column.min(line_len);
+            self.state.cursor =
+                self.clamp_position(self.state.cursor.row - 1, self.state.cursor.column);
         }
     }
 
@@ -612,9 +2466,8 @@ impl EditorEngine {
             self.state.selection_anchor = Some(self.state.cursor);
         }
         if self.state.cursor.row + 1 < self.state.lines.len() {
-            self.state.cursor.row += 1;
-            let line_len = self.state.lines[self.state.cursor.row].len();
-            self.state.cursor.column = self.state.cursor.column.min(line_len);
+            self.state.cursor =
+                self.clamp_position(self.state.cursor.row + 1, self.state.cursor.column);
         }
     }
 
@@ -639,71 +2492,1047 @@ impl EditorEngine {
         self.state.cursor = BufferPosition::new(last_row, last_col);
     }
 
+    /// Select whole lines; repeating extends the selection by one more line
+    fn select_line(&mut self) {
+        let (start, end) = self
+            .selection_range()
+            .unwrap_or((self.state.cursor, self.state.cursor));
+        let last_row = self.state.lines.len() - 1;
+
+        // A selection already ending at column 0 of a later row covers whole lines,
+        // so the row it ends on is the next one to include
+        let end_row = end.row.min(last_row);
+
+        self.state.selection_anchor = Some(BufferPosition::new(start.row, 0));
+        self.state.cursor = if end_row < last_row {
+            BufferPosition::new(end_row + 1, 0)
+        } else {
+            BufferPosition::new(last_row, self.state.lines[last_row].len())
+        };
+    }
+
+    fn select_sentence(&mut self) {
+        let (start, end) = sentences::at(&self.state.lines, self.state.cursor);
+        self.state.selection_anchor = Some(start);
+        self.state.cursor = end;
+    }
+
+    fn select_paragraph(&mut self) {
+        let (start, end) = text_objects::paragraph_range(&self.state.lines, self.state.cursor.row);
+        self.state.selection_anchor = Some(start);
+        self.state.cursor = end;
+    }
+
+    fn select_to_top(&mut self) {
+        if self.state.selection_anchor.is_none() {
+            self.state.selection_anchor = Some(self.state.cursor);
+        }
+        self.state.cursor = BufferPosition::zero();
+    }
+
+    fn select_to_bottom(&mut self) {
+        if self.state.selection_anchor.is_none() {
+            self.state.selection_anchor = Some(self.state.cursor);
+        }
+        let (_, end) = text_objects::document_range(&self.state.lines);
+        self.state.cursor = end;
+    }
+
+    fn select_to_line_start(&mut self) {
+        if self.state.selection_anchor.is_none() {
+            self.state.selection_anchor = Some(self.state.cursor);
+        }
+        self.state.cursor.column = 0;
+    }
+
+    fn select_to_line_end(&mut self) {
+        if self.state.selection_anchor.is_none() {
+            self.state.selection_anchor = Some(self.state.cursor);
+        }
+        self.state.cursor.column = self.state.lines[self.state.cursor.row].len();
+    }
+
+    /// Grow the selection to the next enclosing text object:
+    /// word, line, paragraph, then the whole document
+    fn expand_selection(&mut self) {
+        let (start, end) = self
+            .selection_range()
+            .unwrap_or((self.state.cursor, self.state.cursor));
+        let lines = &self.state.lines;
+
+        let candidates: Vec<_> = [
+            text_objects::word_range(lines, self.state.cursor),
+            Some(text_objects::line_range(lines, start.row)),
+            Some(text_objects::paragraph_range(lines, start.row)),
+            Some(text_objects::document_range(lines)),
+        ]
+        .into_iter()
+        .flatten()
+        .collect();
+
+        // The smallest of these and the syntax nodes around the selection
+        if let Some(node) = structure::enclosing(lines, start, end, &candidates) {
+            self.select_node(node);
+        }
+    }
+
+    /// Select the syntax node around the selection, leaving out lines and
+    /// paragraphs that aren't one
+    fn select_parent(&mut self) {
+        let (start, end) = self
+            .selection_range()
+            .unwrap_or((self.state.cursor, self.state.cursor));
+        match structure::enclosing(&self.state.lines, start, end, &[]) {
+            Some(node) => self.select_node(node),
+            None => self.hover = Some("Nothing encloses the selection".to_string()),
+        }
+    }
+
+    /// Select the item after the selection at the same level
+    fn select_next_sibling(&mut self) {
+        let (start, end) = self
+            .selection_range()
+            .unwrap_or((self.state.cursor, self.state.cursor));
+        match structure::next_sibling(&self.state.lines, start, end) {
+            Some(node) => self.select_node(node),
+            None => self.hover = Some("No next sibling".to_string()),
+        }
+    }
+
+    /// Select `start..end`, remembering the selection before for
+    /// ShrinkSelection
+    fn select_node(&mut self, (start, end): (BufferPosition, BufferPosition)) {
+        self.selection_history
+            .push((self.state.cursor, self.state.selection_anchor));
+        self.state.selection_anchor = Some(start);
+        self.state.cursor = end;
+    }
+
+    /// Undo the most recent ExpandSelection, SelectParent or
+    /// SelectNextSibling step
+    fn shrink_selection(&mut self) {
+        if let Some((cursor, anchor)) = self.selection_history.pop() {
+            self.state.cursor = cursor;
+            self.state.selection_anchor = anchor;
+        } else {
+            self.clear_selection();
+        }
+    }
+
+    /// Select the next match after the cursor, or the one before it
+    fn goto_match(&mut self, backwards: bool) {
+        let matches = self.search_matches();
+        let from = self
+            .selection_range()
+            .map_or(self.state.cursor, |(start, _)| start);
+        match search::next(&matches, from, backwards) {
+            Some(index) => {
+                let (start, end) = matches[index];
+                self.state.selection_anchor = Some(start);
+                self.state.cursor = end;
+            }
+            None => self.hover = Some("No matches".to_string()),
+        }
+    }
+
+    /// Select every match, the one at the cursor, or else the first, with
+    /// the main cursor
+    fn select_all_matches(&mut self) {
+        let matches = self.search_matches();
+        let Some(main) = search::current(&matches, self.selection_range(), self.state.cursor)
+            .or((!matches.is_empty()).then_some(0))
+        else {
+            self.hover = Some("No matches".to_string());
+            return;
+        };
+        let (start, end) = matches[main];
+        self.state.selection_anchor = Some(start);
+        self.state.cursor = end;
+        self.extra_carets = matches
+            .iter()
+            .enumerate()
+            .filter(|&(i, _)| i != main)
+            .map(|(_, &(start, end))| Caret::selecting(start, end))
+            .collect();
+    }
+
+    /// Carry out `action` at the main cursor and every other one, as one
+    /// undo step. Carets are visited from the end of the buffer back, and
+    /// those already visited on the same line are shifted by what changed.
+    fn edit_at_carets(&mut self, action: &EditorAction) {
+        if action.edits_text() {
+            self.push_undo_state();
+            self.mark_edit_time();
+        }
+        let main = Caret {
+            cursor: self.state.cursor,
+            anchor: self.state.selection_anchor,
+        };
+        let mut carets: Vec<(Caret, bool)> = self
+            .extra_carets
+            .drain(..)
+            .map(|caret| (caret, false))
+            .chain([(main, true)])
+            .collect();
+        carets.sort_by_key(|(caret, _)| caret.start());
+
+        let count = carets.len() as i64;
+        let mut done: Vec<(Caret, bool)> = Vec::with_capacity(carets.len());
+        for (index, (caret, is_main)) in (0..count).rev().zip(carets.into_iter().rev()) {
+            self.state.cursor = caret.cursor;
+            self.state.selection_anchor = caret.anchor;
+            let row = caret.start().row;
+            let (len, rows) = (self.state.lines[row].len(), self.state.lines.len());
+            match action {
+                EditorAction::TypeCharacter(c) => {
+                    self.delete_selection();
+                    self.insert_text(c.encode_utf8(&mut [0; 4]));
+                }
+                EditorAction::TypeString(s) => {
+                    self.delete_selection();
+                    self.insert_text(s);
+                }
+                // Lines aren't joined at every cursor
+                EditorAction::Backspace if self.selection_range().is_some() => {
+                    self.delete_selection()
+                }
+                EditorAction::Backspace if caret.cursor.column > 0 => self.backspace_char(),
+                EditorAction::Delete if self.selection_range().is_some() => self.delete_selection(),
+                EditorAction::Delete if caret.cursor.column < len => {
+                    self.state.lines[row].remove(caret.cursor.column);
+                }
+                EditorAction::MoveLeft => self.move_left(),
+                EditorAction::MoveRight => self.move_right(),
+                EditorAction::MoveToBeginningOfLine => self.move_to_line_start(),
+                EditorAction::MoveToEndOfLine => self.move_to_line_end(),
+                EditorAction::IncrementNumber(amount) => {
+                    self.change_numbers(self.number_changes(*amount, false))
+                }
+                EditorAction::DecrementNumber(amount) => {
+                    self.change_numbers(self.number_changes(amount.saturating_neg(), false))
+                }
+                // Each cursor's number goes up a step more than the one above
+                EditorAction::NumberSequence(step) => {
+                    self.change_numbers(self.number_changes(step.saturating_mul(index + 1), false))
+                }
+                _ => {}
+            }
+            let grown = self.state.lines[row].len() as isize - len as isize;
+            let removed_rows = rows - self.state.lines.len();
+            for (later, _) in done.iter_mut() {
+                for position in [Some(&mut later.cursor), later.anchor.as_mut()]
+                    .into_iter()
+                    .flatten()
+                {
+                    if position.row == row {
+                        position.column = position.column.saturating_add_signed(grown);
+                    } else if position.row > row {
+                        position.row -= removed_rows;
+                    }
+                }
+            }
+            let caret = Caret {
+                cursor: self.state.cursor,
+                anchor: self.state.selection_anchor,
+            };
+            done.push((caret, is_main));
+        }
+
+        let main = done
+            .iter()
+            .find(|(_, is_main)| *is_main)
+            .map_or(main, |(caret, _)| *caret);
+        self.state.cursor = main.cursor;
+        self.state.selection_anchor = main.anchor;
+        // Cursors that have run into each other become one
+        let mut carets: Vec<Caret> = Vec::new();
+        for (caret, is_main) in done.into_iter().rev() {
+            if !is_main && caret.cursor != main.cursor && !carets.contains(&caret) {
+                carets.push(caret);
+            }
+        }
+        self.extra_carets = carets;
+    }
+
+    /// Wait for the next character typed to finish a jump, saying so in the
+    /// hover
+    fn start_jump(&mut self, pending: jump::Pending) {
+        self.hover = Some(pending.prompt());
+        self.jump = Some(pending);
+    }
+
+    fn continue_jump(&mut self, pending: jump::Pending, c: char) {
+        let cursor = self.state.cursor;
+        match pending {
+            jump::Pending::Char { before } => {
+                match jump::find_char(&self.state.lines[cursor.row], cursor.column, c, before) {
+                    Some(column) => self.set_cursor_position(cursor.row, column),
+                    None => self.hover = Some(format!("No {} after the cursor on this line", c)),
+                }
+            }
+            jump::Pending::Leap => self.start_jump(jump::Pending::LeapPair(c)),
+            jump::Pending::LeapPair(first) => {
+                let rows = self
+                    .visible_rows
+                    .clone()
+                    .unwrap_or(0..self.state.lines.len());
+                let targets = jump::leap_targets(&self.state.lines, rows, [first, c], cursor);
+                match targets.as_slice() {
+                    [] => self.hover = Some(format!("No {}{} in view", first, c)),
+                    [target] => {
+                        self.set_cursor_position(target.position.row, target.position.column)
+                    }
+                    _ => {
+                        let labels = targets
+                            .iter()
+                            .filter_map(|target| {
+                                let (start, _) = self.line_span(target.position.row)?;
+                                Some(
+                                    Decoration::virtual_text(
+                                        start + target.position.column,
+                                        target.label.to_string(),
+                                        Placement::Inline,
+                                    )
+                                    .with_tone(Tone::Accent),
+                                )
+                            })
+                            .collect();
+                        self.set_decorations(jump::SOURCE, labels);
+                        self.start_jump(jump::Pending::LeapLabel(targets));
+                    }
+                }
+            }
+            jump::Pending::LeapLabel(targets) => {
+                match targets.iter().find(|target| target.label == c) {
+                    Some(target) => {
+                        self.set_cursor_position(target.position.row, target.position.column)
+                    }
+                    None => self.hover = Some(format!("No label {}", c)),
+                }
+            }
+        }
+    }
+
     /// Set cursor to specific position, clamping to valid bounds
     fn set_cursor_position(&mut self, row: usize, column: usize) {
         self.clear_selection();
+        self.state.cursor = self.clamp_position(row, column);
+    }
+
+    /// The nearest position in the buffer to `row` and `column`, backing up
+    /// to the start of a character rather than landing inside one
+    fn clamp_position(&self, row: usize, column: usize) -> BufferPosition {
         let row = row.min(self.state.lines.len().saturating_sub(1));
-        let column = column.min(self.state.lines[row].len());
-        self.state.cursor = BufferPosition::new(row, column);
+        let line = &self.state.lines[row];
+        let mut column = column.min(line.len());
+        while !line.is_char_boundary(column) {
+            column -= 1;
+        }
+        BufferPosition::new(row, column)
     }
 
     /// Start a new selection at position
     fn start_selection(&mut self, row: usize, column: usize) {
-        let row = row.min(self.state.lines.len().saturating_sub(1));
-        let column = column.min(self.state.lines[row].len());
-        self.state.cursor = BufferPosition::new(row, column);
+        self.state.cursor = self.clamp_position(row, column);
         self.state.selection_anchor = Some(self.state.cursor);
     }
 
+    /// Put the selected text at a position and select it there, leaving a
+    /// copy behind when `copy`; moving it into itself does nothing
+    fn drop_selection(&mut self, row: usize, column: usize, copy: bool) {
+        let Some((start, end)) = self.selection_range() else {
+            return;
+        };
+        let mut target = self.clamp_position(row, column);
+        if !copy && start <= target && target <= end {
+            return;
+        }
+        let text = self.state.to_string()
+            [self.state.position_to_offset(start)..self.state.position_to_offset(end)]
+            .to_string();
+
+        self.push_undo_checkpoint();
+        if !copy {
+            self.delete_range(start, end);
+            // Text after the selection moved up into its place
+            if target > end {
+                target = if target.row == end.row {
+                    BufferPosition::new(start.row, start.column + target.column - end.column)
+                } else {
+                    BufferPosition::new(target.row - (end.row - start.row), target.column)
+                };
+            }
+        }
+        self.state.cursor = target;
+        self.insert_text(&text);
+        self.state.selection_anchor = Some(target);
+        self.last_edit_time = None;
+    }
+
     /// Extend selection to position
     fn extend_selection(&mut self, row: usize, column: usize) {
         if self.state.selection_anchor.is_none() {
             self.state.selection_anchor = Some(self.state.cursor);
         }
-        let row = row.min(self.state.lines.len().saturating_sub(1));
-        let column = column.min(self.state.lines[row].len());
-        self.state.cursor = BufferPosition::new(row, column);
+        self.state.cursor = self.clamp_position(row, column);
     }
 
-    /// Load editor state from a file
+    /// Load editor state from a file, cancelling any background load
     pub fn load_from_file<P: AsRef<Path>>(&mut self, path: P) -> io::Result<()> {
-        let content = fs::read_to_string(path)?;
-        self.state.lines = if content.is_empty() {
+        let path = path.as_ref();
+        self.cancel_loads();
+        let content = if large_file::is_large(path, &self.config.large_file) {
+            Content::Lines(large_file::read_lines(path)?)
+        } else {
+            Content::Text(self.files.read(path)?)
+        };
+        self.finish_load(path, content)
+    }
+
+    /// Replace the buffer with a file's content once read
+    fn finish_load(&mut self, path: &Path, content: Content) -> io::Result<()> {
+        let mut content = match content {
+            Content::Lines(lines) if lines[0] != encryption::HEADER => {
+                self.reset_buffer(lines, true);
+                self.prose = false;
+                self.warn_of_long_lines();
+                self.emit(ChangeEvent::Loaded {
+                    path: Some(path.to_path_buf()),
+                });
+                return Ok(());
+            }
+            Content::Lines(lines) => lines.join("\n"),
+            Content::Text(text) => text,
+        };
+        if encryption::is_encrypted(&content) {
+            self.encrypted.insert(path.to_path_buf());
+            let cipher = self.cipher.as_mut().ok_or_else(passphrase_needed)?;
+            content = match cipher.decrypt(&content) {
+                Ok(text) => text,
+                Err(e) => {
+                    // Never save with a key that can't open the file
+                    self.cipher = None;
+                    return Err(e);
+                }
+            };
+        }
+        self.replace_buffer(&content);
+        self.prose = notes::is_note(path);
+        self.warn_of_long_lines();
+        self.emit(ChangeEvent::Loaded {
+            path: Some(path.to_path_buf()),
+        });
+        Ok(())
+    }
+
+    /// Say so when a line just loaded is too long to lay out whole; see
+    /// [`crate::long_lines`]. Frontends say it again once they have put
+    /// the cursor back where the file was left.
+    pub fn warn_of_long_lines(&mut self) {
+        if let Some((row, len)) = long_lines::find(&self.state.lines) {
+            self.hover = Some(long_lines::warning(row, len));
+        }
+    }
+
+    /// Read `path` on a background thread and replace the buffer once it
+    /// has been read, cancelling any load still under way; see
+    /// [`crate::file_io`]. Remote files are loaded at once, as the cache
+    /// keeps them quick to open.
+    pub fn load_in_background<P: AsRef<Path>>(&mut self, path: P) {
+        let path = path.as_ref();
+        if vfs::is_remote(path) {
+            if let Err(e) = self.load_from_file(path) {
+                self.emit(ChangeEvent::Failed {
+                    path: path.to_path_buf(),
+                    message: e.to_string(),
+                });
+            }
+            return;
+        }
+        self.cancel_loads();
+        let large = large_file::is_large(path, &self.config.large_file);
+        let task = self.worker().queue(path, Job::Read { large });
+        self.file_tasks.push(task);
+    }
+
+    /// Write the buffer to `path` on a background thread, replacing any
+    /// save of the same file still under way. Encryption happens first, so
+    /// a missing passphrase is an error here. Remote files are saved as by
+    /// [`EditorEngine::save_to_file`], which already uploads them in the
+    /// background.
+    pub fn save_in_background<P: AsRef<Path>>(&mut self, path: P) -> io::Result<()> {
+        let path = path.as_ref();
+        if vfs::is_remote(path) {
+            return self.save_to_file(path);
+        }
+        let content = if self.large_file && !self.is_encrypted_file(path) {
+            Content::Lines(self.state.lines.clone())
+        } else {
+            let mut text = self.state.lines.join("\n");
+            if self.is_encrypted_file(path) {
+                let cipher = self.cipher.as_ref().ok_or_else(passphrase_needed)?;
+                text = cipher.encrypt(&text)?;
+            }
+            Content::Text(text)
+        };
+        self.file_tasks.retain(|task| {
+            let superseded = task.saving && task.path == path;
+            if superseded {
+                task.cancel();
+            }
+            !superseded
+        });
+        let task = self.worker().queue(path, Job::Write(content));
+        self.file_tasks.push(task);
+        Ok(())
+    }
+
+    /// Stop every background load and save, each reported as
+    /// [`ChangeEvent::Cancelled`]
+    pub fn cancel_file_io(&mut self) {
+        for task in std::mem::take(&mut self.file_tasks) {
+            task.cancel();
+            self.emit(ChangeEvent::Cancelled { path: task.path });
+        }
+    }
+
+    /// Whether a background load or save hasn't finished
+    pub fn file_io_pending(&self) -> bool {
+        !self.file_tasks.is_empty()
+    }
+
+    /// Report the progress of background loads and saves and apply those
+    /// that finished, as [`ChangeEvent`]s; returns whether there was any.
+    /// Frontends call this from their event loop.
+    pub fn poll_file_io(&mut self) -> bool {
+        let mut any = false;
+        while let Some((id, update)) = self.file_io.as_ref().and_then(Worker::try_recv) {
+            any |= self.apply_file_update(id, update);
+        }
+        any
+    }
+
+    /// Wait for every background load and save to finish, e.g. before
+    /// quitting
+    pub fn wait_for_file_io(&mut self) {
+        while !self.file_tasks.is_empty() {
+            match self.file_io.as_ref().and_then(Worker::recv) {
+                Some((id, update)) => {
+                    self.apply_file_update(id, update);
+                }
+                None => self.file_tasks.clear(),
+            }
+        }
+    }
+
+    /// Drop background loads, as a newer load replaces whatever they'd read
+    fn cancel_loads(&mut self) {
+        self.file_tasks.retain(|task| {
+            if !task.saving {
+                task.cancel();
+            }
+            task.saving
+        });
+    }
+
+    fn worker(&mut self) -> &mut Worker {
+        self.file_io.get_or_insert_with(Worker::start)
+    }
+
+    /// Act on an update from the worker; false for one about a task that
+    /// was cancelled or replaced since
+    fn apply_file_update(&mut self, id: u64, update: Update) -> bool {
+        let Some(index) = self.file_tasks.iter().position(|task| task.id == id) else {
+            return false;
+        };
+        let path = self.file_tasks[index].path.clone();
+        let result = match update {
+            Update::Progress { done, total } => {
+                self.emit(if self.file_tasks[index].saving {
+                    ChangeEvent::Saving { path, done, total }
+                } else {
+                    ChangeEvent::Loading { path, done, total }
+                });
+                return true;
+            }
+            Update::Read(result) => {
+                self.file_tasks.remove(index);
+                result.and_then(|content| self.finish_load(&path, content))
+            }
+            Update::Written(result) => {
+                self.file_tasks.remove(index);
+                result.map(|()| {
+                    self.emit(ChangeEvent::Saved { path: path.clone() });
+                })
+            }
+        };
+        match result {
+            Ok(()) => {}
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => {
+                self.emit(ChangeEvent::Cancelled { path });
+            }
+            Err(e) => self.emit(ChangeEvent::Failed {
+                path,
+                message: e.to_string(),
+            }),
+        }
+        true
+    }
+
+    /// Run `command` in the background and put what it prints in the
+    /// buffer once it finishes: in place of the `target` text, which is
+    /// piped to it, or at the cursor; see [`crate::shell`]. A command still
+    /// running is cancelled first.
+    pub fn start_shell_command(
+        &mut self,
+        command: &str,
+        target: ShellTarget,
+        dir: Option<&Path>,
+    ) -> io::Result<()> {
+        self.cancel_shell_command();
+        if self.read_only {
+            return Err(io::Error::new(
+                io::ErrorKind::PermissionDenied,
+                "the text is read-only",
+            ));
+        }
+        let input = match target {
+            ShellTarget::Insert => None,
+            ShellTarget::Replace(range) => {
+                let mut input = self.state.text_in(range);
+                if !input.ends_with('\n') {
+                    input.push('\n');
+                }
+                Some(input)
+            }
+        };
+        let job = ShellCommand::spawn(command, input, dir)?;
+        self.shell_command = Some((job, target, self.revision));
+        self.hover = Some(format!("Running {} (Escape cancels)", command));
+        Ok(())
+    }
+
+    /// The shell command still running, if any
+    pub fn shell_command(&self) -> Option<&str> {
+        self.shell_command.as_ref().map(|(job, _, _)| job.command())
+    }
+
+    /// Put the output of a shell command that finished in the buffer, or
+    /// show why it failed; returns whether one finished. Frontends call
+    /// this from their event loop while [`EditorEngine::shell_command`] is
+    /// set.
+    pub fn poll_shell_command(&mut self) -> bool {
+        let Some(result) = self
+            .shell_command
+            .as_mut()
+            .and_then(|(job, _, _)| job.poll())
+        else {
+            return false;
+        };
+        if let Some((job, target, revision)) = self.shell_command.take() {
+            let finished = self.finish_shell_command(job.command(), target, revision, result);
+            self.hover = finished.err().map(|e| e.to_string());
+        }
+        true
+    }
+
+    /// Wait for the shell command to finish and put its output in the
+    /// buffer, as batch scripts do
+    pub fn wait_for_shell_command(&mut self) -> io::Result<()> {
+        let Some((mut job, target, revision)) = self.shell_command.take() else {
+            return Ok(());
+        };
+        let result = job.wait();
+        self.finish_shell_command(job.command(), target, revision, result)
+    }
+
+    /// Stop the shell command, leaving the buffer as it is; returns
+    /// whether one was running
+    pub fn cancel_shell_command(&mut self) -> bool {
+        let Some((mut job, _, _)) = self.shell_command.take() else {
+            return false;
+        };
+        job.cancel();
+        self.hover = Some(format!("Cancelled {}", job.command()));
+        true
+    }
+
+    fn finish_shell_command(
+        &mut self,
+        command: &str,
+        target: ShellTarget,
+        revision: u64,
+        result: io::Result<String>,
+    ) -> io::Result<()> {
+        let output = result?.replace("\r\n", "\n");
+        let range = match target {
+            // The text piped to the command may have moved since
+            ShellTarget::Replace(_) if self.revision != revision => {
+                return Err(io::Error::other(format!(
+                    "The text changed while {} ran",
+                    command
+                )))
+            }
+            ShellTarget::Replace(range) => range,
+            ShellTarget::Insert => self
+                .state
+                .selection()
+                .unwrap_or(BufferRange::new(self.state.cursor, self.state.cursor)),
+        };
+        if self.read_only {
+            return Ok(());
+        }
+        // Lines piped in come back ending in a newline the text didn't have
+        let replaced = self.state.text_in(range);
+        let output = match output.strip_suffix('\n') {
+            Some(stripped) if !replaced.ends_with('\n') => stripped,
+            _ => output.as_str(),
+        };
+        self.observe(true, |engine| {
+            engine.guard_regions(true, |engine| engine.replace_text(range, output))
+        });
+        Ok(())
+    }
+
+    /// Start the configured dictation command, or stop the one listening
+    fn toggle_dictation(&mut self) {
+        if let Some(mut dictation) = self.dictation.take() {
+            dictation.stop();
+            self.hover = Some("Dictation stopped".to_string());
+            return;
+        }
+        if self.read_only {
+            self.hover = Some("The text is read-only".to_string());
+            return;
+        }
+        let command = &self.config.dictation.command;
+        match Dictation::start(command) {
+            Ok(dictation) => {
+                self.hover = Some(format!("Dictating with {}", dictation.command()));
+                self.dictation = Some(dictation);
+            }
+            Err(e) => self.hover = Some(format!("Couldn't start {}: {}", command, e)),
+        }
+    }
+
+    /// Whether a dictation command is listening
+    pub fn dictating(&self) -> bool {
+        self.dictation.is_some()
+    }
+
+    /// Type what the dictation command has recognized at the cursor, each
+    /// phrase as one undo step, or show why it stopped; returns whether
+    /// anything changed. Frontends call this from their event loop while
+    /// [`EditorEngine::dictating`].
+    pub fn poll_dictation(&mut self) -> bool {
+        let Some(dictation) = self.dictation.as_mut() else {
+            return false;
+        };
+        let (phrases, ended) = dictation.poll();
+        let changed = !phrases.is_empty() || ended.is_some();
+        for phrase in phrases {
+            // Phrases follow the text before them as words do
+            let line = &self.state.lines[self.state.cursor.row];
+            let spaced = line[..self.state.cursor.column]
+                .chars()
+                .next_back()
+                .is_none_or(char::is_whitespace);
+            let text = if spaced {
+                phrase
+            } else {
+                format!(" {}", phrase)
+            };
+            self.handle_action(EditorAction::InsertComposed(text));
+        }
+        if let Some(ended) = ended {
+            self.dictation = None;
+            self.hover = Some(match ended {
+                Ok(()) => "Dictation ended".to_string(),
+                Err(e) => e.to_string(),
+            });
+        }
+        changed
+    }
+
+    /// Put `text` in place of `range` as one undo step, leaving the cursor
+    /// after it
+    fn replace_text(&mut self, range: BufferRange, text: &str) {
+        self.push_undo_checkpoint();
+        let (start, end) = (range.start, range.end);
+        let before = self.state.lines[start.row][..start.column].to_string();
+        let after = self.state.lines[end.row][end.column..].to_string();
+        let mut lines: Vec<String> = text.split('\n').map(String::from).collect();
+        let last = lines.len() - 1;
+        lines[0].insert_str(0, &before);
+        let cursor = BufferPosition::new(start.row + last, lines[last].len());
+        lines[last].push_str(&after);
+        self.state.lines.splice(start.row..=end.row, lines);
+        self.state.cursor = cursor;
+        self.state.selection_anchor = None;
+        self.refresh_invisibles();
+    }
+
+    /// Replace the buffer with `content`, starting a fresh undo history
+    pub fn load_text(&mut self, content: &str) {
+        self.replace_buffer(content);
+        self.emit(ChangeEvent::Loaded { path: None });
+    }
+
+    fn replace_buffer(&mut self, content: &str) {
+        let joined;
+        let content = if self.single_line {
+            joined = one_line(content);
+            joined.as_str()
+        } else {
+            content
+        };
+        let lines = if content.is_empty() {
             vec![String::new()]
         } else {
             content.lines().map(|s| s.to_string()).collect()
         };
+        let large = content.len() as u64 >= self.config.large_file.threshold_bytes();
+        self.reset_buffer(lines, large);
+    }
+
+    /// Start over with `lines`, the cursor at the start and no undo history
+    fn reset_buffer(&mut self, lines: Vec<String>, large: bool) {
+        self.state.lines = lines;
+        self.large_file = large;
         self.state.cursor = BufferPosition::zero();
         self.state.selection_anchor = None;
+        self.protected.clear();
+        self.decorations.clear();
         self.undo_stack.clear();
         self.redo_stack.clear();
         self.last_edit_time = None;
-        Ok(())
+        self.revision += 1;
+        self.refresh_invisibles();
+    }
+
+    /// Replace every line as one undo step, e.g. for a command that edits
+    /// many lines at once; the cursor is kept inside the new text
+    pub fn set_lines(&mut self, lines: Vec<String>, cursor: BufferPosition) {
+        self.observe(true, |engine| {
+            engine.guard_regions(true, |engine| engine.replace_lines(lines, cursor))
+        });
+    }
+
+    fn replace_lines(&mut self, lines: Vec<String>, cursor: BufferPosition) {
+        self.push_undo_checkpoint();
+        self.state.lines = if lines.is_empty() {
+            vec![String::new()]
+        } else {
+            lines
+        };
+        self.state.cursor = self.clamp_position(cursor.row, cursor.column);
+        self.state.selection_anchor = None;
+        self.refresh_invisibles();
+    }
+
+    /// Replace the text with a version merged from elsewhere, e.g. another
+    /// instance's edits, as one undo step. The cursor and selection stay on
+    /// the same text, moving past the change if they were inside it.
+    pub fn merge_text(&mut self, text: &str) {
+        self.observe(true, |engine| {
+            engine.guard_regions(false, |engine| engine.merge(text))
+        });
+    }
+
+    fn merge(&mut self, text: &str) {
+        let old = self.state.to_string();
+        if old == text {
+            return;
+        }
+        let (start, old_end, new_end) = diff::changed_span(&old, text);
+        let map = |offset: usize| {
+            if offset <= start {
+                offset
+            } else if offset >= old_end {
+                offset - old_end + new_end
+            } else {
+                new_end
+            }
+        };
+        let cursor = map(self.state.position_to_offset(self.state.cursor));
+        let anchor = self
+            .state
+            .selection_anchor
+            .map(|anchor| map(self.state.position_to_offset(anchor)));
+
+        self.push_undo_checkpoint();
+        self.state.lines = text.split('\n').map(String::from).collect();
+        self.state.cursor = self.state.offset_to_position(cursor);
+        self.state.selection_anchor = anchor.map(|anchor| self.state.offset_to_position(anchor));
+        self.refresh_invisibles();
+    }
+
+    /// Put back the text, cursor and selection `state` recorded, such as
+    /// a session dumped to disk, as one undo step. Positions past the text
+    /// are pulled back onto it.
+    pub fn restore_state(&mut self, state: EditorState) {
+        self.observe(true, |engine| {
+            engine.guard_regions(false, |engine| engine.restore(state))
+        });
+    }
+
+    fn restore(&mut self, state: EditorState) {
+        self.push_undo_checkpoint();
+        self.state.lines = state
+            .lines
+            .iter()
+            .flat_map(|line| line.split('\n'))
+            .map(String::from)
+            .collect();
+        if self.state.lines.is_empty() {
+            self.state.lines.push(String::new());
+        }
+        self.state.cursor = self.clamp_position(state.cursor.row, state.cursor.column);
+        self.state.selection_anchor = state
+            .selection_anchor
+            .map(|anchor| self.clamp_position(anchor.row, anchor.column));
+        self.state.font_size = state.font_size;
+        self.refresh_invisibles();
+    }
+
+    /// Start an empty, untitled buffer
+    pub fn new_buffer(&mut self) {
+        self.load_text("");
     }
 
     /// Save editor state to a file
     pub fn save_to_file<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
-        let content = self.state.lines.join("\n");
-        if let Some(parent) = path.as_ref().parent() {
-            fs::create_dir_all(parent)?;
+        let encrypted = self.is_encrypted_file(path.as_ref());
+        if self.large_file && !encrypted && !vfs::is_remote(path.as_ref()) {
+            large_file::write_lines(path.as_ref(), &self.state.lines)?;
+        } else {
+            let mut content = self.state.lines.join("\n");
+            if encrypted {
+                let cipher = self.cipher.as_ref().ok_or_else(passphrase_needed)?;
+                content = cipher.encrypt(&content)?;
+            }
+            self.files.write(path.as_ref(), &content)?;
         }
-        fs::write(path, content)
+        self.emit(ChangeEvent::Saved {
+            path: path.as_ref().to_path_buf(),
+        });
+        Ok(())
+    }
+
+    /// Use `passphrase` for loading and saving encrypted files
+    pub fn set_passphrase(&mut self, passphrase: &str) -> io::Result<()> {
+        self.cipher = Some(Cipher::new(passphrase)?);
+        Ok(())
+    }
+
+    /// Whether `path` is saved encrypted: it was encrypted when loaded, or
+    /// is the default notes file with encryption turned on
+    pub fn is_encrypted_file(&self, path: &Path) -> bool {
+        self.encrypted.contains(path)
+            || (self.config.encryption.enabled && path == Self::default_file_path())
+    }
+
+    /// Use the passphrase kept in the OS keychain for `path`, if keeping it
+    /// there is configured; returns whether it opens the file
+    pub fn unlock_from_keychain(&mut self, path: &Path) -> bool {
+        if !self.config.encryption.keychain {
+            return false;
+        }
+        let Some(passphrase) = encryption::keychain_passphrase(path) else {
+            return false;
+        };
+        self.set_passphrase(&passphrase).is_ok()
+            && (!path.exists() || self.load_from_file(path).is_ok())
+    }
+
+    /// Whether opening `path` needs a passphrase that hasn't been given
+    pub fn needs_passphrase(&self, path: &Path) -> bool {
+        self.cipher.is_none()
+            && (self.is_encrypted_file(path)
+                || fs::read_to_string(path).is_ok_and(|content| encryption::is_encrypted(&content)))
     }
 
-    /// Get default config file path
+    /// Upload the buffer to its remote file even though the file changed on
+    /// the host since it was opened
+    pub fn overwrite_remote<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        let content = self.state.lines.join("\n");
+        self.files.ssh().overwrite(path.as_ref(), &content)
+    }
+
+    /// Progress or trouble uploading remote files, for the status bar
+    pub fn transfer_status(&self) -> Option<TransferStatus> {
+        self.files.ssh().status()
+    }
+
+    /// Get default notes file path
     pub fn default_file_path() -> PathBuf {
-        let home = std::env::var("HOME")
-            .or_else(|_| std::env::var("USERPROFILE"))
-            .unwrap_or_else(|_| ".".to_string());
-        PathBuf::from(home)
-            .join(".config")
-            .join("zlyph")
-            .join("default.txt")
+        config::config_dir().join("default.txt")
     }
 }
 
+/// Text with its line breaks made spaces, for a single-line buffer
+fn one_line(text: &str) -> String {
+    text.replace("\r\n", " ").replace(['\n', '\r'], " ")
+}
+
+fn passphrase_needed() -> io::Error {
+    io::Error::new(
+        io::ErrorKind::PermissionDenied,
+        "the file is encrypted; a passphrase is needed",
+    )
+}
+
 impl Default for EditorEngine {
     fn default() -> Self {
         Self::new()
     }
 }
+
+/// Configuration for a new [`EditorEngine`], from [`EditorEngine::builder`]
+#[derive(Default)]
+pub struct EditorEngineBuilder {
+    config: Option<Config>,
+    text: Option<String>,
+    read_only: bool,
+    single_line: bool,
+}
+
+impl EditorEngineBuilder {
+    /// Settings to use instead of the defaults; nothing is read from disk
+    /// unless given here, e.g. from [`Config::load`]
+    pub fn config(mut self, config: Config) -> Self {
+        self.config = Some(config);
+        self
+    }
+
+    /// Text the buffer starts with, the cursor at its start
+    pub fn text(mut self, text: impl Into<String>) -> Self {
+        self.text = Some(text.into());
+        self
+    }
+
+    /// See [`EditorEngine::set_read_only`]
+    pub fn read_only(mut self, read_only: bool) -> Self {
+        self.read_only = read_only;
+        self
+    }
+
+    /// See [`EditorEngine::set_single_line`]
+    pub fn single_line(mut self, single_line: bool) -> Self {
+        self.single_line = single_line;
+        self
+    }
+
+    pub fn build(self) -> EditorEngine {
+        let mut engine = EditorEngine::new();
+        if let Some(config) = self.config {
+            engine.set_config(config);
+        }
+        engine.single_line = self.single_line;
+        if let Some(text) = self.text {
+            engine.load_text(&text);
+        }
+        engine.read_only = self.read_only;
+        engine
+    }
+}
@@ -0,0 +1,512 @@
+//! Ex-style commands shared by batch scripts and command lines, such as
+//! `42`, `3,5d`, `%s/foo/bar/g`, `w`, `wq`, `e notes.md` or
+//! `field tags [reading, rust]`
+//!
+//! A command may start with a line range: `%` for the whole buffer, `*` or
+//! `'<,'>` for the selection, or one or two addresses separated by a comma,
+//! each a 1-based line number, `.` for the cursor's line or `$` for the last
+//! line. Without a range, commands act on the cursor's line.
+//!
+//! `s` takes flags after its replacement: `g` for every match on a line,
+//! `i` to ignore case, `w` for whole words only, `p` to preserve the case of
+//! what is replaced and `n` to count the matches without replacing them;
+//! see [`crate::replace`].
+//!
+//! `field key value` sets a frontmatter field, the value written as in
+//! YAML, and `field key` removes one; see [`crate::frontmatter`].
+//! `align =` lines up the `=` in each line of the range, or without one of
+//! the selection or the paragraph at the cursor; see [`crate::align`].
+//! `r !date` inserts what a shell command prints at the cursor, and
+//! `!sort` pipes the range, or without one the selection or the cursor's
+//! line, through a command and puts its output in their place; see
+//! [`crate::shell`].
+//! `format toml` lays out the JSON, TOML or YAML in the range, or without
+//! one the selection or the whole buffer; see [`crate::pretty`].
+//! `transform md5` encodes, decodes, dumps or hashes the range or the
+//! selection; see [`crate::transform`].
+//! Writing, quitting, opening files and fields take no range. Only `w` with a path
+//! runs on an engine alone; the rest are carried out by the editor window.
+
+use crate::frontmatter::{self, Value};
+use crate::replace;
+use crate::shell::ShellTarget;
+use crate::transform::Transform;
+use crate::{BufferPosition, BufferRange, EditorAction, EditorEngine};
+use regex::{Regex, RegexBuilder};
+use std::io;
+use std::path::{Path, PathBuf};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Address {
+    /// 1-based line number
+    Line(usize),
+    Current,
+    Last,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LineRange {
+    Whole,
+    Lines(Address, Address),
+    /// The selected lines; `s` only replaces within the selection
+    Selection,
+}
+
+#[derive(Debug, Clone)]
+pub enum Command {
+    /// Move the cursor to the first non-blank character of a line
+    Goto(Address),
+    Delete(Option<LineRange>),
+    /// Replace the first match on each line, or every match with `g`
+    Substitute {
+        range: Option<LineRange>,
+        pattern: Regex,
+        replacement: String,
+        global: bool,
+        /// `p`, casing each replacement like the text it replaces
+        preserve_case: bool,
+        /// `n`, reporting how many matches there are instead
+        count_only: bool,
+    },
+    /// `w`, writing to the buffer's own file or, given one, another path
+    Write(Option<PathBuf>),
+    /// `q`
+    Quit,
+    /// `wq`
+    WriteQuit,
+    /// `e path`
+    Edit(PathBuf),
+    /// `align delimiter`
+    Align {
+        range: Option<LineRange>,
+        delimiter: String,
+    },
+    /// An action on the range, selected first, or on the selection:
+    /// `format json`, `format toml`, `format yaml` or `transform name`
+    Selection {
+        range: Option<LineRange>,
+        action: EditorAction,
+    },
+    /// `field key value`, or `field key` to remove the field
+    Field {
+        key: String,
+        value: Option<Value>,
+    },
+    /// `!command` to filter lines through a shell command, or `r !command`
+    /// to insert its output
+    Shell {
+        range: Option<LineRange>,
+        command: String,
+        read: bool,
+    },
+}
+
+fn invalid(message: impl Into<String>) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidInput, message.into())
+}
+
+impl Command {
+    pub fn parse(input: &str) -> io::Result<Self> {
+        let input = input.trim();
+        let input = input.strip_prefix(':').unwrap_or(input).trim_start();
+        let (range, rest) = parse_range(input)?;
+        let rest = rest.trim_start();
+        if let Some(command) = rest.strip_prefix('!') {
+            return Ok(Command::Shell {
+                range,
+                command: shell_command(command)?,
+                read: false,
+            });
+        }
+
+        let (name, argument) = match rest.split_once(char::is_whitespace) {
+            Some((name, argument)) => (name, Some(argument.trim()).filter(|a| !a.is_empty())),
+            None => (rest, None),
+        };
+        let file_command = match (name, argument) {
+            ("w" | "write", path) => Some(Command::Write(path.map(PathBuf::from))),
+            ("q" | "quit", None) => Some(Command::Quit),
+            ("wq", None) => Some(Command::WriteQuit),
+            ("e" | "edit", Some(path)) => Some(Command::Edit(PathBuf::from(path))),
+            ("e" | "edit", None) => return Err(invalid("expected a file to edit")),
+            ("field", Some(argument)) => {
+                let (key, value) = match argument.split_once(char::is_whitespace) {
+                    Some((key, value)) => (key, Some(frontmatter::parse_value(value).0)),
+                    None => (argument, None),
+                };
+                Some(Command::Field {
+                    key: key.trim_end_matches(':').to_string(),
+                    value,
+                })
+            }
+            ("field", None) => return Err(invalid("expected a field to set")),
+            ("r" | "read", argument) => match argument.and_then(|a| a.strip_prefix('!')) {
+                Some(command) => Some(Command::Shell {
+                    range: None,
+                    command: shell_command(command)?,
+                    read: true,
+                }),
+                None => return Err(invalid("expected !command to read from")),
+            },
+            ("q" | "quit" | "wq", Some(_)) => {
+                return Err(invalid(format!("{} takes no file", name)))
+            }
+            _ => None,
+        };
+        if let Some(command) = file_command {
+            if range.is_some() {
+                return Err(invalid(format!("{} takes no line range", name)));
+            }
+            return Ok(command);
+        }
+
+        match (name, argument) {
+            ("align", Some(delimiter)) => {
+                return Ok(Command::Align {
+                    range,
+                    delimiter: delimiter.to_string(),
+                })
+            }
+            ("align", None) => return Err(invalid("expected a delimiter to align on")),
+            ("format", argument) => {
+                let action = match argument {
+                    Some("json") => EditorAction::FormatJson,
+                    Some("toml") => EditorAction::FormatToml,
+                    Some("yaml" | "yml") => EditorAction::FormatYaml,
+                    _ => return Err(invalid("expected json, toml or yaml to format")),
+                };
+                return Ok(Command::Selection { range, action });
+            }
+            ("transform", argument) => {
+                let transform = argument.and_then(Transform::from_name).ok_or_else(|| {
+                    let names: Vec<_> = Transform::ALL.iter().map(|t| t.name()).collect();
+                    invalid(format!("expected one of {}", names.join(", ")))
+                })?;
+                return Ok(Command::Selection {
+                    range,
+                    action: EditorAction::TransformSelection(transform),
+                });
+            }
+            _ => {}
+        }
+
+        match (range, rest.chars().next()) {
+            (Some(LineRange::Lines(start, end)), None) if start == end => Ok(Command::Goto(end)),
+            (_, None) => Err(invalid("empty command")),
+            (range, Some('d')) if rest[1..].trim().is_empty() || rest == "delete" => {
+                Ok(Command::Delete(range))
+            }
+            (range, Some('s')) => parse_substitute(range, &rest[1..]),
+            _ => Err(invalid(format!("unknown command: {}", rest))),
+        }
+    }
+
+    pub fn execute(&self, engine: &mut EditorEngine) -> io::Result<()> {
+        self.execute_in(engine, None)
+    }
+
+    /// Execute, running shell commands in `dir`
+    pub fn execute_in(&self, engine: &mut EditorEngine, dir: Option<&Path>) -> io::Result<()> {
+        let state = engine.state();
+        let last = state.lines.len() - 1;
+        let current = state.cursor.row;
+        let resolve = |address: Address| match address {
+            Address::Line(line) => line.saturating_sub(1).min(last),
+            Address::Current => current,
+            Address::Last => last,
+        };
+        let rows = |range: Option<LineRange>| match range {
+            None => (current, current),
+            Some(LineRange::Whole) => (0, last),
+            Some(LineRange::Lines(start, end)) => {
+                let (start, end) = (resolve(start), resolve(end));
+                (start.min(end), start.max(end))
+            }
+            Some(LineRange::Selection) => state
+                .selection()
+                .map_or((current, current), |range| (range.start.row, range.end.row)),
+        };
+
+        match self {
+            Command::Goto(address) => {
+                let cursor = first_non_blank(&state.lines, resolve(*address));
+                engine.handle_action(EditorAction::SetCursorPosition {
+                    row: cursor.row,
+                    column: cursor.column,
+                });
+            }
+            Command::Delete(range) => {
+                let (start, end) = rows(*range);
+                let mut lines = state.lines.clone();
+                lines.drain(start..=end);
+                let row = start.min(lines.len().saturating_sub(1));
+                let cursor = first_non_blank(&lines, row);
+                engine.set_lines(lines, cursor);
+            }
+            Command::Substitute {
+                range,
+                pattern,
+                replacement,
+                global,
+                preserve_case,
+                count_only,
+            } => {
+                let (start, end) = rows(*range);
+                // With the selection as the range, only matches inside it count
+                let within = match range {
+                    Some(LineRange::Selection) => state.selection(),
+                    _ => None,
+                };
+                let mut lines = state.lines.clone();
+                let (mut count, mut changed_lines, mut last_changed) = (0, 0, None);
+                for (row, line) in lines.iter_mut().enumerate().take(end + 1).skip(start) {
+                    let bounds = within.map_or(0..line.len(), |selection| {
+                        let from = if row == selection.start.row {
+                            selection.start.column
+                        } else {
+                            0
+                        };
+                        let to = if row == selection.end.row {
+                            selection.end.column
+                        } else {
+                            line.len()
+                        };
+                        from..to
+                    });
+                    let limit = if *global { usize::MAX } else { 1 };
+                    let found: Vec<_> = pattern
+                        .captures_iter(line)
+                        .filter_map(|captures| {
+                            let found = captures.get(0)?;
+                            (bounds.start <= found.start() && found.end() <= bounds.end)
+                                .then_some((found, captures))
+                        })
+                        .take(limit)
+                        .map(|(found, captures)| {
+                            let mut with = String::new();
+                            captures.expand(replacement, &mut with);
+                            if *preserve_case {
+                                with = replace::preserve_case(found.as_str(), &with);
+                            }
+                            (found.range(), with)
+                        })
+                        .collect();
+                    if found.is_empty() {
+                        continue;
+                    }
+                    count += found.len();
+                    changed_lines += 1;
+                    last_changed = Some(row);
+                    if !*count_only {
+                        for (range, with) in found.into_iter().rev() {
+                            line.replace_range(range, &with);
+                        }
+                    }
+                }
+                if *count_only {
+                    let report = replace::count_report(count, changed_lines);
+                    engine.set_hover(Some(report));
+                    return Ok(());
+                }
+                let row = last_changed.ok_or_else(|| {
+                    io::Error::new(
+                        io::ErrorKind::NotFound,
+                        format!("pattern not found: {}", pattern),
+                    )
+                })?;
+                let cursor = first_non_blank(&lines, row);
+                engine.set_lines(lines, cursor);
+            }
+            Command::Align { range, delimiter } => {
+                if range.is_some() {
+                    let (start, end) = rows(*range);
+                    let column = state.lines[end].len();
+                    engine.handle_action(EditorAction::StartSelection {
+                        row: start,
+                        column: 0,
+                    });
+                    engine.handle_action(EditorAction::ExtendSelection { row: end, column });
+                }
+                engine.handle_action(EditorAction::AlignOn(delimiter.clone()));
+            }
+            Command::Selection { range, action } => {
+                if range.is_some() {
+                    let (start, end) = rows(*range);
+                    let column = state.lines[end].len();
+                    engine.handle_action(EditorAction::StartSelection {
+                        row: start,
+                        column: 0,
+                    });
+                    engine.handle_action(EditorAction::ExtendSelection { row: end, column });
+                }
+                engine.handle_action(action.clone());
+            }
+            Command::Field { key, value } => engine.handle_action(match value {
+                Some(value) => EditorAction::SetFrontmatterField {
+                    key: key.clone(),
+                    value: value.clone(),
+                },
+                None => EditorAction::RemoveFrontmatterField(key.clone()),
+            }),
+            Command::Shell {
+                range,
+                command,
+                read,
+            } => {
+                let target = match (range, state.selection()) {
+                    _ if *read => ShellTarget::Insert,
+                    (None, Some(selection)) => ShellTarget::Replace(selection),
+                    (range, _) => {
+                        let (start, end) = rows(*range);
+                        ShellTarget::Replace(BufferRange::new(
+                            BufferPosition::new(start, 0),
+                            BufferPosition::new(end, state.lines[end].len()),
+                        ))
+                    }
+                };
+                engine.start_shell_command(command, target, dir)?;
+            }
+            Command::Write(Some(path)) => engine.save_to_file(path)?,
+            Command::Write(None) | Command::Quit | Command::WriteQuit | Command::Edit(_) => {
+                return Err(io::Error::new(
+                    io::ErrorKind::Unsupported,
+                    "only an editor window can do that",
+                ));
+            }
+        }
+        Ok(())
+    }
+}
+
+fn shell_command(command: &str) -> io::Result<String> {
+    let command = command.trim();
+    if command.is_empty() {
+        return Err(invalid("expected a shell command after !"));
+    }
+    Ok(command.to_string())
+}
+
+fn first_non_blank(lines: &[String], row: usize) -> BufferPosition {
+    let line = lines.get(row).map_or("", String::as_str);
+    let column = line.len() - line.trim_start().len();
+    BufferPosition::new(row, column)
+}
+
+fn parse_range(input: &str) -> io::Result<(Option<LineRange>, &str)> {
+    if let Some(rest) = input.strip_prefix('%') {
+        return Ok((Some(LineRange::Whole), rest));
+    }
+    if let Some(rest) = input
+        .strip_prefix("'<,'>")
+        .or_else(|| input.strip_prefix('*'))
+    {
+        return Ok((Some(LineRange::Selection), rest));
+    }
+    let Some((start, rest)) = parse_address(input)? else {
+        return Ok((None, input));
+    };
+    let Some(rest) = rest.strip_prefix(',') else {
+        return Ok((Some(LineRange::Lines(start, start)), rest));
+    };
+    let (end, rest) = parse_address(rest)?.ok_or_else(|| invalid("expected a line after ','"))?;
+    Ok((Some(LineRange::Lines(start, end)), rest))
+}
+
+fn parse_address(input: &str) -> io::Result<Option<(Address, &str)>> {
+    if let Some(rest) = input.strip_prefix('.') {
+        return Ok(Some((Address::Current, rest)));
+    }
+    if let Some(rest) = input.strip_prefix('$') {
+        return Ok(Some((Address::Last, rest)));
+    }
+    let digits = input.len() - input.trim_start_matches(|c: char| c.is_ascii_digit()).len();
+    if digits == 0 {
+        return Ok(None);
+    }
+    let line = input[..digits]
+        .parse()
+        .map_err(|_| invalid(format!("bad line number: {}", &input[..digits])))?;
+    Ok(Some((Address::Line(line), &input[digits..])))
+}
+
+/// `/pattern/replacement/flags`, where any punctuation can stand in for `/`
+fn parse_substitute(range: Option<LineRange>, input: &str) -> io::Result<Command> {
+    let mut chars = input.chars();
+    let delimiter = chars
+        .next()
+        .filter(|c| !c.is_alphanumeric() && !c.is_whitespace() && *c != '\\')
+        .ok_or_else(|| invalid("expected s/pattern/replacement/"))?;
+
+    let mut parts = vec![String::new()];
+    let mut escaped = false;
+    for c in chars {
+        if escaped {
+            // An escaped delimiter is literal; other escapes are kept for the regex
+            if c != delimiter {
+                parts.last_mut().unwrap().push('\\');
+            }
+            parts.last_mut().unwrap().push(c);
+            escaped = false;
+        } else if c == '\\' {
+            escaped = true;
+        } else if c == delimiter && parts.len() < 3 {
+            parts.push(String::new());
+        } else {
+            parts.last_mut().unwrap().push(c);
+        }
+    }
+    if parts.len() < 2 {
+        return Err(invalid("expected s/pattern/replacement/"));
+    }
+
+    let flags = parts.get(2).map_or("", String::as_str);
+    if let Some(flag) = flags
+        .chars()
+        .find(|c| !matches!(c, 'g' | 'i' | 'w' | 'p' | 'n'))
+    {
+        return Err(invalid(format!("unknown flag: {}", flag)));
+    }
+    // Whole words only: nothing word-like just outside the match
+    let source = if flags.contains('w') {
+        format!(r"\b(?:{})\b", parts[0])
+    } else {
+        parts[0].clone()
+    };
+    let pattern = RegexBuilder::new(&source)
+        .case_insensitive(flags.contains('i') || flags.contains('p'))
+        .build()
+        .map_err(|e| invalid(e.to_string()))?;
+
+    Ok(Command::Substitute {
+        range,
+        pattern,
+        replacement: expand_replacement(&parts[1]),
+        global: flags.contains('g'),
+        preserve_case: flags.contains('p'),
+        count_only: flags.contains('n'),
+    })
+}
+
+/// Translate ex replacement syntax, where `&` is the whole match and `\1`
+/// a group, into the regex crate's `${0}` and `${1}`
+fn expand_replacement(replacement: &str) -> String {
+    let mut expanded = String::new();
+    let mut chars = replacement.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '&' => expanded.push_str("${0}"),
+            '$' => expanded.push_str("$$"),
+            '\\' => match chars.next() {
+                Some(digit @ '0'..='9') => {
+                    expanded.push_str(&format!("${{{}}}", digit));
+                }
+                Some('t') => expanded.push('\t'),
+                Some('$') => expanded.push_str("$$"),
+                Some(other) => expanded.push(other),
+                None => expanded.push('\\'),
+            },
+            _ => expanded.push(c),
+        }
+    }
+    expanded
+}
@@ -0,0 +1,409 @@
+//! The buffer as a standalone HTML page, highlighted as the editor shows it
+//!
+//! Styles go in a `<style>` block by class, or with `inline_css` on each
+//! span, for pasting into mail and other places that drop stylesheets.
+
+use crate::config::ExportConfig;
+use crate::highlight::{Language, LineHighlights, Style};
+use crate::BufferRange;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+
+/// CSS colors for the page, as in `#abb2bf`; frontends fill it in from
+/// their theme
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Palette {
+    pub background: String,
+    pub text: String,
+    pub muted: String,
+    /// Headings of level 1, 2 and 3, then the rest
+    pub headings: [String; 4],
+    pub code: String,
+    pub code_background: String,
+    pub link: String,
+    pub quote: String,
+}
+
+impl Default for Palette {
+    /// The GUI's default theme
+    fn default() -> Self {
+        Self {
+            background: "#282c34".to_string(),
+            text: "#abb2bf".to_string(),
+            muted: "#5c6370".to_string(),
+            headings: [
+                "#e06c75".to_string(),
+                "#e5c07b".to_string(),
+                "#98c379".to_string(),
+                "#61afef".to_string(),
+            ],
+            code: "#98c379".to_string(),
+            code_background: "#21252b".to_string(),
+            link: "#61afef".to_string(),
+            quote: "#7f848e".to_string(),
+        }
+    }
+}
+
+impl Palette {
+    /// Dark text on white, for paper
+    pub fn light() -> Self {
+        Self {
+            background: "#ffffff".to_string(),
+            text: "#383a42".to_string(),
+            muted: "#a0a1a7".to_string(),
+            headings: [
+                "#e45649".to_string(),
+                "#c18401".to_string(),
+                "#50a14f".to_string(),
+                "#4078f2".to_string(),
+            ],
+            code: "#50a14f".to_string(),
+            code_background: "#f0f0f1".to_string(),
+            link: "#4078f2".to_string(),
+            quote: "#696c77".to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct HtmlOptions {
+    /// The page's title, usually the file's name
+    pub title: String,
+    pub line_numbers: bool,
+    pub inline_css: bool,
+}
+
+impl HtmlOptions {
+    /// Options for exporting the file at `path` as configured
+    pub fn new(path: Option<&Path>, config: &ExportConfig) -> Self {
+        Self {
+            title: path.and_then(Path::file_name).map_or_else(
+                || "Untitled".to_string(),
+                |name| name.to_string_lossy().into_owned(),
+            ),
+            line_numbers: config.line_numbers,
+            inline_css: config.inline_css,
+        }
+    }
+}
+
+/// Where a file is exported to unless asked otherwise: beside it, as
+/// `notes.html` for `notes.md`
+pub fn html_path(path: &Path) -> PathBuf {
+    path.with_extension("html")
+}
+
+/// The classes of the styles and their rules, in the order later ones win
+fn rules(palette: &Palette) -> Vec<(&'static str, Vec<(&'static str, String)>)> {
+    let color = |value: &String| ("color", value.clone());
+    let bold = ("font-weight", "bold".to_string());
+    vec![
+        ("h1", vec![color(&palette.headings[0]), bold.clone()]),
+        ("h2", vec![color(&palette.headings[1]), bold.clone()]),
+        ("h3", vec![color(&palette.headings[2]), bold.clone()]),
+        ("h4", vec![color(&palette.headings[3]), bold.clone()]),
+        ("b", vec![bold]),
+        ("i", vec![("font-style", "italic".to_string())]),
+        ("q", vec![color(&palette.quote)]),
+        ("s", vec![("text-decoration", "line-through".to_string())]),
+        (
+            "c",
+            vec![
+                color(&palette.code),
+                ("background", palette.code_background.clone()),
+            ],
+        ),
+        (
+            "l",
+            vec![
+                color(&palette.link),
+                ("text-decoration", "underline".to_string()),
+            ],
+        ),
+        (
+            "m",
+            vec![
+                color(&palette.muted),
+                ("text-decoration", "none".to_string()),
+            ],
+        ),
+    ]
+}
+
+/// The classes a style is drawn with
+fn classes(style: Style) -> Vec<&'static str> {
+    let mut classes = Vec::new();
+    if let Some(level) = style.heading {
+        classes.push(match level {
+            1 => "h1",
+            2 => "h2",
+            3 => "h3",
+            _ => "h4",
+        });
+    }
+    let flags = [
+        (style.bold, "b"),
+        (style.italic || style.quote, "i"),
+        (style.quote, "q"),
+        (style.strikethrough, "s"),
+        (style.code, "c"),
+        (style.link, "l"),
+        (style.markup, "m"),
+    ];
+    classes.extend(flags.iter().filter(|(on, _)| *on).map(|(_, class)| *class));
+    classes
+}
+
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// The `<style>` block's body
+fn stylesheet(palette: &Palette) -> String {
+    let mut css = format!(
+        "body {{ margin: 0; background: {}; }}\n\
+         pre.zlyph {{ margin: 0; padding: 1em; color: {}; background: {}; \
+         font-family: ui-monospace, Menlo, Consolas, monospace; }}\n\
+         .ln {{ color: {}; user-select: none; }}\n",
+        palette.background, palette.text, palette.background, palette.muted
+    );
+    for (class, declarations) in rules(palette) {
+        let declarations: Vec<String> = declarations
+            .iter()
+            .map(|(property, value)| format!("{}: {};", property, value))
+            .collect();
+        css.push_str(&format!(".{} {{ {} }}\n", class, declarations.join(" ")));
+    }
+    css
+}
+
+/// An opening tag for text drawn in `style`
+fn open_span(style: Style, palette: &Palette, inline_css: bool) -> String {
+    let classes = classes(style);
+    if !inline_css {
+        return format!("<span class=\"{}\">", classes.join(" "));
+    }
+    let mut declarations: Vec<(&str, String)> = Vec::new();
+    for (class, rule) in rules(palette) {
+        if !classes.contains(&class) {
+            continue;
+        }
+        for (property, value) in rule {
+            declarations.retain(|(existing, _)| *existing != property);
+            declarations.push((property, value));
+        }
+    }
+    let style: Vec<String> = declarations
+        .iter()
+        .map(|(property, value)| format!("{}:{}", property, value))
+        .collect();
+    format!("<span style=\"{}\">", style.join(";"))
+}
+
+/// One line's text, cut to `columns`, with its styled runs wrapped in spans
+fn render_line(
+    line: &str,
+    columns: Range<usize>,
+    highlights: Option<&LineHighlights>,
+    palette: &Palette,
+    inline_css: bool,
+) -> String {
+    let mut html = String::new();
+    let mut at = columns.start;
+    for (range, style) in highlights.map_or(&[][..], |h| &h.spans[..]) {
+        let start = range.start.max(columns.start);
+        let end = range.end.min(columns.end);
+        if start >= end {
+            continue;
+        }
+        html.push_str(&escape(&line[at..start]));
+        html.push_str(&open_span(*style, palette, inline_css));
+        html.push_str(&escape(&line[start..end]));
+        html.push_str("</span>");
+        at = end;
+    }
+    html.push_str(&escape(&line[at..columns.end]));
+    html
+}
+
+/// A page showing `lines`, or only `range` of them, highlighted as the file
+/// at `path` would be
+pub fn html(
+    lines: &[String],
+    path: Option<&Path>,
+    range: Option<BufferRange>,
+    palette: &Palette,
+    options: &HtmlOptions,
+) -> String {
+    let highlights = path
+        .and_then(Language::for_path)
+        .map(|language| language.highlight(lines))
+        .unwrap_or_default();
+    let last = lines.len().saturating_sub(1);
+    let (start, end) = match range {
+        Some(range) => (range.start, range.end),
+        None => (
+            crate::BufferPosition::new(0, 0),
+            crate::BufferPosition::new(last, lines.get(last).map_or(0, String::len)),
+        ),
+    };
+    let end_row = end.row.min(last);
+    let number_width = (end_row + 1).to_string().len();
+
+    let mut body = String::new();
+    for (row, line) in lines.iter().enumerate().take(end_row + 1).skip(start.row) {
+        let from = if row == start.row { start.column } else { 0 };
+        let to = if row == end.row {
+            end.column
+        } else {
+            line.len()
+        };
+        if options.line_numbers {
+            let number = format!("{:>width$} ", row + 1, width = number_width);
+            if options.inline_css {
+                body.push_str(&format!(
+                    "<span style=\"color:{};user-select:none\">{}</span>",
+                    palette.muted, number
+                ));
+            } else {
+                body.push_str(&format!("<span class=\"ln\">{}</span>", number));
+            }
+        }
+        body.push_str(&render_line(
+            line,
+            from.min(line.len())..to.min(line.len()),
+            highlights.get(row),
+            palette,
+            options.inline_css,
+        ));
+        body.push('\n');
+    }
+
+    let head = if options.inline_css {
+        String::new()
+    } else {
+        format!("<style>\n{}</style>\n", stylesheet(palette))
+    };
+    let pre = if options.inline_css {
+        format!(
+            "<pre style=\"margin:0;padding:1em;color:{};background:{};\
+             font-family:ui-monospace,Menlo,Consolas,monospace\">",
+            palette.text, palette.background
+        )
+    } else {
+        "<pre class=\"zlyph\">".to_string()
+    };
+    format!(
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n\
+         <title>{}</title>\n{}</head>\n<body>\n{}{}</pre>\n</body>\n</html>\n",
+        escape(&options.title),
+        head,
+        pre,
+        body
+    )
+}
+
+/// A run of text in one style on a line as drawn, starting at a column
+pub(crate) struct Run {
+    pub column: usize,
+    pub text: String,
+    pub style: Style,
+}
+
+/// The lines of `lines`, or only of `range`, in styled runs for the
+/// fixed-width exports, with tabs expanded and wrapped at `width` characters
+pub(crate) fn layout(
+    lines: &[String],
+    path: Option<&Path>,
+    range: Option<BufferRange>,
+    width: usize,
+    highlight: bool,
+) -> Vec<Vec<Run>> {
+    let highlights = path
+        .filter(|_| highlight)
+        .and_then(Language::for_path)
+        .map(|language| language.highlight(lines))
+        .unwrap_or_default();
+    let last = lines.len().saturating_sub(1);
+    let (start, end) = match range {
+        Some(range) => (range.start, range.end),
+        None => (
+            crate::BufferPosition::new(0, 0),
+            crate::BufferPosition::new(last, lines.get(last).map_or(0, String::len)),
+        ),
+    };
+
+    let mut printed = Vec::new();
+    for (row, line) in lines.iter().enumerate().take(end.row + 1).skip(start.row) {
+        let from = if row == start.row { start.column } else { 0 }.min(line.len());
+        let to = if row == end.row {
+            end.column
+        } else {
+            line.len()
+        }
+        .min(line.len());
+        let spans = highlights.get(row).map_or(&[][..], |h| &h.spans[..]);
+
+        let mut runs: Vec<Run> = Vec::new();
+        let mut column = 0;
+        for (index, c) in line[from..to].char_indices() {
+            let at = from + index;
+            let style = spans
+                .iter()
+                .find(|(range, _)| range.contains(&at))
+                .map(|(_, style)| *style)
+                .unwrap_or_default();
+            let text = if c == '\t' {
+                "    ".to_string()
+            } else {
+                c.to_string()
+            };
+            for c in text.chars() {
+                if column == width {
+                    printed.push(std::mem::take(&mut runs));
+                    column = 0;
+                }
+                match runs.last_mut() {
+                    Some(run) if run.style == style => run.text.push(c),
+                    _ => runs.push(Run {
+                        column,
+                        text: c.to_string(),
+                        style,
+                    }),
+                }
+                column += 1;
+            }
+        }
+        printed.push(runs);
+    }
+    printed
+}
+
+/// The color text in `style` is drawn in by the exports that have no
+/// stylesheet
+pub(crate) fn color(style: Style, palette: &Palette) -> &str {
+    if style.markup {
+        &palette.muted
+    } else if style.link {
+        &palette.link
+    } else if style.code {
+        &palette.code
+    } else if style.quote {
+        &palette.quote
+    } else if let Some(level) = style.heading {
+        &palette.headings[usize::from(level.clamp(1, 4)) - 1]
+    } else {
+        &palette.text
+    }
+}
@@ -0,0 +1,175 @@
+//! Loading and saving on a background thread, so a big file or a slow
+//! network mount doesn't hold up typing
+//!
+//! [`EditorEngine::load_in_background`](crate::EditorEngine::load_in_background)
+//! and [`EditorEngine::save_in_background`](crate::EditorEngine::save_in_background)
+//! queue work for one worker thread, which runs it in order. The frontend
+//! calls [`EditorEngine::poll_file_io`](crate::EditorEngine::poll_file_io)
+//! from its event loop; progress, the finished load or save, failures and
+//! cancellations then arrive as [`ChangeEvent`](crate::ChangeEvent)s. A
+//! cancelled load leaves the buffer alone and a cancelled save leaves the
+//! file as it was, since files are written beside the old one and moved
+//! over it once complete.
+
+use crate::large_file;
+use std::fs::File;
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::Arc;
+use std::thread;
+
+/// Bytes read between progress reports and checks for cancelling
+const CHUNK: usize = 1 << 20;
+
+/// A file's text, whole or as lines for a large file
+#[derive(Debug)]
+pub(crate) enum Content {
+    Text(String),
+    Lines(Vec<String>),
+}
+
+pub(crate) enum Job {
+    /// Read the file, as lines if it is large
+    Read {
+        large: bool,
+    },
+    Write(Content),
+}
+
+pub(crate) enum Update {
+    Progress { done: u64, total: u64 },
+    Read(io::Result<Content>),
+    Written(io::Result<()>),
+}
+
+struct Queued {
+    id: u64,
+    path: PathBuf,
+    job: Job,
+    cancelled: Arc<AtomicBool>,
+}
+
+/// A load or save handed to the worker, for matching its updates and
+/// cancelling it
+pub(crate) struct Task {
+    pub id: u64,
+    pub path: PathBuf,
+    pub saving: bool,
+    cancelled: Arc<AtomicBool>,
+}
+
+impl Task {
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+}
+
+/// The thread loads and saves run on, which finishes what is queued and
+/// stops once dropped
+pub(crate) struct Worker {
+    jobs: Sender<Queued>,
+    updates: Receiver<(u64, Update)>,
+    next_id: u64,
+}
+
+impl Worker {
+    pub fn start() -> Self {
+        let (jobs, queue) = mpsc::channel();
+        let (sender, updates) = mpsc::channel();
+        thread::spawn(move || {
+            for queued in queue {
+                run(queued, &sender);
+            }
+        });
+        Self {
+            jobs,
+            updates,
+            next_id: 0,
+        }
+    }
+
+    pub fn queue(&mut self, path: &Path, job: Job) -> Task {
+        self.next_id += 1;
+        let cancelled = Arc::new(AtomicBool::new(false));
+        let task = Task {
+            id: self.next_id,
+            path: path.to_path_buf(),
+            saving: matches!(job, Job::Write(_)),
+            cancelled: cancelled.clone(),
+        };
+        let _ = self.jobs.send(Queued {
+            id: self.next_id,
+            path: path.to_path_buf(),
+            job,
+            cancelled,
+        });
+        task
+    }
+
+    pub fn try_recv(&self) -> Option<(u64, Update)> {
+        self.updates.try_recv().ok()
+    }
+
+    /// Wait for the next update; None if the thread has gone
+    pub fn recv(&self) -> Option<(u64, Update)> {
+        self.updates.recv().ok()
+    }
+}
+
+fn run(queued: Queued, updates: &Sender<(u64, Update)>) {
+    let Queued {
+        id,
+        path,
+        job,
+        cancelled,
+    } = queued;
+    let keep_going = |done, total| {
+        let _ = updates.send((id, Update::Progress { done, total }));
+        !cancelled.load(Ordering::Relaxed)
+    };
+    let update = if cancelled.load(Ordering::Relaxed) {
+        match job {
+            Job::Read { .. } => Update::Read(Err(large_file::cancelled())),
+            Job::Write(_) => Update::Written(Err(large_file::cancelled())),
+        }
+    } else {
+        match job {
+            Job::Read { large: true } => {
+                Update::Read(large_file::read_lines_with(&path, keep_going).map(Content::Lines))
+            }
+            Job::Read { large: false } => {
+                Update::Read(read_text(&path, keep_going).map(Content::Text))
+            }
+            Job::Write(Content::Lines(lines)) => {
+                Update::Written(large_file::write_lines_with(&path, &lines, keep_going))
+            }
+            Job::Write(Content::Text(text)) => Update::Written(large_file::write_lines_with(
+                &path,
+                std::slice::from_ref(&text),
+                keep_going,
+            )),
+        }
+    };
+    let _ = updates.send((id, update));
+}
+
+/// The text of the file at `path`, read a chunk at a time
+fn read_text(path: &Path, mut keep_going: impl FnMut(u64, u64) -> bool) -> io::Result<String> {
+    let mut file = File::open(path)?;
+    let total = file.metadata()?.len();
+    let mut bytes = Vec::with_capacity(total as usize);
+    let mut chunk = vec![0; CHUNK];
+    loop {
+        let read = file.read(&mut chunk)?;
+        if read == 0 {
+            break;
+        }
+        bytes.extend_from_slice(&chunk[..read]);
+        if !keep_going(bytes.len() as u64, total) {
+            return Err(large_file::cancelled());
+        }
+    }
+    String::from_utf8(bytes).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+}
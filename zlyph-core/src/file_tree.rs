@@ -0,0 +1,266 @@
+//! The workspace directory as an expandable tree for a file explorer panel,
+//! and the file operations it offers
+//!
+//! Hidden entries are left out. Gitignored entries are listed but marked, so
+//! frontends can dim them.
+
+use crate::gitignore::Gitignore;
+use std::collections::BTreeSet;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TreeEntry {
+    pub path: PathBuf,
+    pub name: String,
+    /// Nesting below the root, which is not itself listed
+    pub depth: usize,
+    pub is_dir: bool,
+    pub expanded: bool,
+    pub ignored: bool,
+}
+
+pub struct FileTree {
+    pub root: PathBuf,
+    pub selected: usize,
+    expanded: BTreeSet<PathBuf>,
+    /// Visible entries in display order
+    entries: Vec<TreeEntry>,
+}
+
+impl FileTree {
+    pub fn new(root: &Path) -> Self {
+        let mut tree = Self {
+            root: root.to_path_buf(),
+            selected: 0,
+            expanded: BTreeSet::new(),
+            entries: Vec::new(),
+        };
+        tree.refresh();
+        tree
+    }
+
+    /// Re-read the directories from disk, keeping expansion and selection
+    pub fn refresh(&mut self) {
+        let selected = self.selected_entry().map(|entry| entry.path.clone());
+        self.entries.clear();
+
+        let root = self.root.clone();
+        self.list(&root, 0, false, &mut Vec::new());
+
+        if let Some(index) =
+            selected.and_then(|path| self.entries.iter().position(|entry| entry.path == path))
+        {
+            self.selected = index;
+        }
+        self.selected = self.selected.min(self.entries.len().saturating_sub(1));
+    }
+
+    fn list(&mut self, dir: &Path, depth: usize, ignored: bool, ignores: &mut Vec<Gitignore>) {
+        let Ok(entries) = fs::read_dir(dir) else {
+            return;
+        };
+        let pushed = match Gitignore::load(dir) {
+            Some(gitignore) => {
+                ignores.push(gitignore);
+                true
+            }
+            None => false,
+        };
+
+        let mut entries: Vec<(String, PathBuf, bool)> = entries
+            .flatten()
+            .filter_map(|entry| {
+                let name = entry.file_name().to_string_lossy().into_owned();
+                let is_dir = entry.file_type().ok()?.is_dir();
+                (!name.starts_with('.')).then(|| (name, entry.path(), is_dir))
+            })
+            .collect();
+        // Directories first, then by name ignoring case
+        entries.sort_by_key(|(name, _, is_dir)| (!is_dir, name.to_lowercase()));
+
+        for (name, path, is_dir) in entries {
+            let ignored = ignored
+                || ignores
+                    .iter()
+                    .rev()
+                    .find_map(|gitignore| gitignore.matched(&path, is_dir))
+                    .unwrap_or(false);
+            let expanded = is_dir && self.expanded.contains(&path);
+            self.entries.push(TreeEntry {
+                path: path.clone(),
+                name,
+                depth,
+                is_dir,
+                expanded,
+                ignored,
+            });
+            if expanded {
+                self.list(&path, depth + 1, ignored, ignores);
+            }
+        }
+
+        if pushed {
+            ignores.pop();
+        }
+    }
+
+    pub fn entries(&self) -> &[TreeEntry] {
+        &self.entries
+    }
+
+    pub fn selected_entry(&self) -> Option<&TreeEntry> {
+        self.entries.get(self.selected)
+    }
+
+    pub fn select_previous(&mut self) {
+        self.selected = self.selected.saturating_sub(1);
+    }
+
+    pub fn select_next(&mut self) {
+        self.selected = (self.selected + 1).min(self.entries.len().saturating_sub(1));
+    }
+
+    /// Select `path`, expanding the directories above it
+    pub fn select_path(&mut self, path: &Path) {
+        for dir in path.ancestors().skip(1) {
+            if !dir.starts_with(&self.root) || dir == self.root {
+                break;
+            }
+            self.expanded.insert(dir.to_path_buf());
+        }
+        self.refresh();
+        if let Some(index) = self.entries.iter().position(|entry| entry.path == path) {
+            self.selected = index;
+        }
+    }
+
+    pub fn set_expanded(&mut self, index: usize, expanded: bool) {
+        let Some(entry) = self.entries.get(index).filter(|entry| entry.is_dir) else {
+            return;
+        };
+        if expanded {
+            self.expanded.insert(entry.path.clone());
+        } else {
+            self.expanded.remove(&entry.path);
+        }
+        self.refresh();
+    }
+
+    pub fn toggle(&mut self, index: usize) {
+        if let Some(entry) = self.entries.get(index) {
+            self.set_expanded(index, !entry.expanded);
+        }
+    }
+
+    /// Expand the selected directory, or go to its first child if it already is
+    pub fn expand_selected(&mut self) {
+        match self.selected_entry() {
+            Some(entry) if entry.is_dir && !entry.expanded => {
+                self.set_expanded(self.selected, true)
+            }
+            Some(entry) if entry.is_dir => self.select_next(),
+            _ => {}
+        }
+    }
+
+    /// Collapse the selected directory, or go to its parent
+    pub fn collapse_selected(&mut self) {
+        let Some(entry) = self.selected_entry() else {
+            return;
+        };
+        if entry.is_dir && entry.expanded {
+            self.set_expanded(self.selected, false);
+        } else if let Some(parent) = entry.path.parent().map(Path::to_path_buf) {
+            if let Some(index) = self.entries.iter().position(|e| e.path == parent) {
+                self.selected = index;
+            }
+        }
+    }
+
+    /// Directory new entries go in: the selected directory, or the one
+    /// containing the selected file
+    pub fn target_dir(&self) -> PathBuf {
+        match self.selected_entry() {
+            Some(entry) if entry.is_dir => entry.path.clone(),
+            Some(entry) => entry
+                .path
+                .parent()
+                .map_or_else(|| self.root.clone(), Path::to_path_buf),
+            None => self.root.clone(),
+        }
+    }
+
+    pub fn create_file(&mut self, path: &Path) -> io::Result<()> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(path)?;
+        self.select_path(path);
+        Ok(())
+    }
+
+    pub fn create_dir(&mut self, path: &Path) -> io::Result<()> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::create_dir(path)?;
+        self.select_path(path);
+        Ok(())
+    }
+
+    /// Rename or move `from` to `to`, never replacing an existing entry
+    pub fn rename(&mut self, from: &Path, to: &Path) -> io::Result<()> {
+        if to.exists() {
+            return Err(io::Error::new(
+                io::ErrorKind::AlreadyExists,
+                format!("{} already exists", to.display()),
+            ));
+        }
+        if let Some(parent) = to.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::rename(from, to)?;
+
+        // Keep moved directories expanded
+        let moved: Vec<PathBuf> = self
+            .expanded
+            .iter()
+            .filter(|dir| dir.starts_with(from))
+            .cloned()
+            .collect();
+        for dir in moved {
+            self.expanded.remove(&dir);
+            if let Ok(rest) = dir.strip_prefix(from) {
+                self.expanded.insert(to.join(rest));
+            }
+        }
+        self.select_path(to);
+        Ok(())
+    }
+
+    /// Move `from` into the directory `dir`, keeping its name
+    pub fn move_into(&mut self, from: &Path, dir: &Path) -> io::Result<PathBuf> {
+        let name = from
+            .file_name()
+            .ok_or_else(|| io::Error::other("cannot move the workspace root"))?;
+        let to = dir.join(name);
+        self.rename(from, &to)?;
+        Ok(to)
+    }
+
+    pub fn delete(&mut self, path: &Path) -> io::Result<()> {
+        if path.is_dir() {
+            fs::remove_dir_all(path)?;
+        } else {
+            fs::remove_file(path)?;
+        }
+        self.expanded.retain(|dir| !dir.starts_with(path));
+        self.refresh();
+        Ok(())
+    }
+}
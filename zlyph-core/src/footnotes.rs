@@ -0,0 +1,226 @@
+//! Markdown footnotes: `[^1]` in the text and `[^1]: ...` defining it on a
+//! line of its own, kept at the end of the document
+//!
+//! Numbered footnotes are renumbered in the order they're first referred
+//! to, so inserting one between others keeps them counting up; named ones
+//! such as `[^source]` keep their names. Markers in fenced code are left
+//! alone.
+
+use crate::markdown;
+use crate::BufferPosition;
+use std::collections::HashMap;
+use std::ops::Range;
+
+/// A footnote marker as written, `[^` and `]` included
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Marker {
+    pub label: String,
+    pub row: usize,
+    pub columns: Range<usize>,
+    /// Whether it starts the footnote's definition rather than referring
+    /// to it
+    pub definition: bool,
+}
+
+impl Marker {
+    fn number(&self) -> Option<usize> {
+        self.label.parse().ok()
+    }
+}
+
+/// Every footnote marker in `lines`, in order
+pub fn markers(lines: &[String]) -> Vec<Marker> {
+    let code = markdown::code_rows(lines);
+    let mut markers = Vec::new();
+    for (row, line) in lines.iter().enumerate().filter(|&(row, _)| !code[row]) {
+        let indent = line.len() - line.trim_start_matches(' ').len();
+        let mut from = 0;
+        while let Some(at) = line[from..].find("[^").map(|at| from + at) {
+            let label_start = at + 2;
+            let label_len = line[label_start..]
+                .find(|c: char| c == ']' || c == '[' || c.is_whitespace())
+                .filter(|&len| len > 0 && line[label_start + len..].starts_with(']'));
+            let Some(len) = label_len else {
+                from = label_start;
+                continue;
+            };
+            let end = label_start + len + 1;
+            markers.push(Marker {
+                label: line[label_start..end - 1].to_string(),
+                row,
+                columns: at..end,
+                definition: at == indent && indent <= 3 && line[end..].starts_with(':'),
+            });
+            from = end;
+        }
+    }
+    markers
+}
+
+/// The number after the highest numbered footnote
+pub fn next_number(lines: &[String]) -> usize {
+    markers(lines)
+        .iter()
+        .filter_map(Marker::number)
+        .max()
+        .map_or(1, |highest| highest + 1)
+}
+
+/// `lines` with their numbered footnotes counting up from 1 in the order
+/// they're first referred to, footnotes never referred to last, and the
+/// definitions at the end of the document in that order; with the new
+/// label of each renumbered footnote
+pub fn renumber(lines: &[String]) -> (Vec<String>, HashMap<String, String>) {
+    let markers = markers(lines);
+    let mut order: Vec<&str> = Vec::new();
+    let references = markers.iter().filter(|marker| !marker.definition);
+    let definitions = markers.iter().filter(|marker| marker.definition);
+    for marker in references.chain(definitions) {
+        if marker.number().is_some() && !order.contains(&marker.label.as_str()) {
+            order.push(&marker.label);
+        }
+    }
+    let labels: HashMap<String, String> = order
+        .iter()
+        .enumerate()
+        .map(|(index, label)| (label.to_string(), (index + 1).to_string()))
+        .collect();
+
+    let mut renumbered = lines.to_vec();
+    // From the end of each line back, so earlier columns stay put
+    for marker in markers.iter().rev() {
+        if let Some(label) = labels.get(&marker.label) {
+            let columns = marker.columns.start + 2..marker.columns.end - 1;
+            renumbered[marker.row].replace_range(columns, label);
+        }
+    }
+    sort_definitions(&mut renumbered);
+    (renumbered, labels)
+}
+
+/// The rows of the definitions at the end of `lines`, each with the
+/// indented and blank lines after it, put in order of their numbers, named
+/// ones after the numbered
+fn sort_definitions(lines: &mut Vec<String>) {
+    let definition_rows: HashMap<usize, Option<usize>> = markers(lines)
+        .into_iter()
+        .filter(|marker| marker.definition)
+        .map(|marker| (marker.row, marker.number()))
+        .collect();
+    let continues = |line: &String| line.trim().is_empty() || line.starts_with([' ', '\t']);
+    let mut start = lines.len();
+    let mut block_start = None;
+    while start > 0 {
+        let row = start - 1;
+        if definition_rows.contains_key(&row) {
+            block_start = Some(row);
+        } else if !continues(&lines[row]) {
+            break;
+        }
+        start = row;
+    }
+    let Some(start) = block_start else {
+        return;
+    };
+
+    // Blank lines stay where they were: between definitions if there were
+    // any, and after the last one
+    let block = lines.split_off(start);
+    let text_end = block
+        .iter()
+        .rposition(|line| !line.trim().is_empty())
+        .map_or(0, |last| last + 1);
+    let mut groups: Vec<Vec<String>> = Vec::new();
+    for (row, line) in block[..text_end].iter().enumerate() {
+        match groups.last_mut() {
+            Some(group) if !definition_rows.contains_key(&(start + row)) => {
+                group.push(line.clone())
+            }
+            _ => groups.push(vec![line.clone()]),
+        }
+    }
+    let spaced = groups
+        .first()
+        .is_some_and(|group| groups.len() > 1 && group.last().is_some_and(|l| l.trim().is_empty()));
+    for group in &mut groups {
+        while group.last().is_some_and(|line| line.trim().is_empty()) {
+            group.pop();
+        }
+    }
+    let key = |group: &Vec<String>| {
+        let label = group[0].trim_start().trim_start_matches("[^");
+        label
+            .split(']')
+            .next()
+            .and_then(|label| label.parse::<usize>().ok())
+            .unwrap_or(usize::MAX)
+    };
+    groups.sort_by_key(key);
+    for (index, group) in groups.into_iter().enumerate() {
+        if index > 0 && spaced {
+            lines.push(String::new());
+        }
+        lines.extend(group);
+    }
+    lines.extend_from_slice(&block[text_end..]);
+}
+
+/// `lines` with a reference to a new footnote at `at` and its definition
+/// added at the end, renumbered, and where the new definition's text goes
+pub fn insert(lines: &[String], at: BufferPosition) -> (Vec<String>, BufferPosition) {
+    let label = next_number(lines).to_string();
+    let mut lines = lines.to_vec();
+    lines[at.row].insert_str(at.column, &format!("[^{}]", label));
+
+    let text_end = lines
+        .iter()
+        .rposition(|line| !line.trim().is_empty())
+        .map_or(0, |last| last + 1);
+    let trailing = lines.split_off(text_end);
+    let after_definition = markers(&lines)
+        .iter()
+        .rev()
+        .find(|marker| marker.definition)
+        .is_some_and(|marker| {
+            lines[marker.row + 1..]
+                .iter()
+                .all(|line| line.starts_with([' ', '\t']))
+        });
+    if !lines.is_empty() && !after_definition {
+        lines.push(String::new());
+    }
+    lines.push(format!("[^{}]: ", label));
+    lines.extend(trailing);
+
+    let (lines, labels) = renumber(&lines);
+    let label = labels.get(&label).cloned().unwrap_or(label);
+    let definition = markers(&lines)
+        .into_iter()
+        .find(|marker| marker.definition && marker.label == label);
+    let cursor = definition.map_or(at, |marker| {
+        BufferPosition::new(marker.row, lines[marker.row].len())
+    });
+    (lines, cursor)
+}
+
+/// Where the footnote marker at `at` leads: from a reference to the text
+/// of its definition, and from a definition to its first reference
+pub fn counterpart(lines: &[String], at: BufferPosition) -> Option<BufferPosition> {
+    let markers = markers(lines);
+    let here = markers.iter().find(|marker| {
+        marker.row == at.row
+            && (marker.columns.contains(&at.column)
+                || marker.definition && at.column >= marker.columns.start)
+    })?;
+    let there = markers
+        .iter()
+        .find(|marker| marker.label == here.label && marker.definition != here.definition)?;
+    let column = if there.definition {
+        let line = &lines[there.row];
+        let text = &line[there.columns.end + 1..];
+        line.len() - text.trim_start().len()
+    } else {
+        there.columns.start
+    };
+    Some(BufferPosition::new(there.row, column))
+}
@@ -0,0 +1,378 @@
+//! YAML frontmatter: the block between `---` lines at the top of a note
+//! holding fields such as its title, tags and date
+//!
+//! Only the YAML notes carry is understood: `key: value` lines whose value
+//! is plain or quoted text, an inline list `[a, b]`, or `- item` lines
+//! below the key. Anything else, such as nested maps or comments, is kept
+//! as written and left out of the fields. Setting a field rewrites only
+//! its own lines, so the rest of the block stays byte for byte as it was.
+
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+use std::ops::Range;
+
+/// A field's value: text, or a list written inline or one item a line
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum Value {
+    Text(String),
+    List(Vec<String>),
+}
+
+/// A `key: value` line of the frontmatter and any list items below it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Field {
+    pub key: String,
+    pub value: Value,
+    /// Rows the field is written on
+    pub rows: Range<usize>,
+    /// Where the value, or each item of a list, is written, quotes left
+    /// out: its row and columns
+    pub spans: Vec<(usize, Range<usize>)>,
+}
+
+/// The parsed frontmatter of a note
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Frontmatter {
+    /// Rows of the block, both fences included
+    pub rows: Range<usize>,
+    pub fields: Vec<Field>,
+}
+
+/// Rows of the frontmatter block at the top of `lines`, fences included;
+/// `None` if the first line isn't `---` or no line closes the block
+pub fn block_rows<S: AsRef<str>>(lines: &[S]) -> Option<Range<usize>> {
+    if lines.first()?.as_ref().trim_end() != "---" {
+        return None;
+    }
+    let close = lines[1..]
+        .iter()
+        .position(|line| matches!(line.as_ref().trim_end(), "---" | "..."))?;
+    Some(0..close + 2)
+}
+
+/// The frontmatter at the top of `lines`
+pub fn parse<S: AsRef<str>>(lines: &[S]) -> Option<Frontmatter> {
+    let rows = block_rows(lines)?;
+    let inside = rows.start + 1..rows.end - 1;
+    let mut fields: Vec<Field> = Vec::new();
+    for row in inside.clone() {
+        let line = lines[row].as_ref();
+        if let Some((key, value_start)) = key_of(line) {
+            let (value, spans) = parse_value(&line[value_start..]);
+            fields.push(Field {
+                key: key.to_string(),
+                value,
+                rows: row..row + 1,
+                spans: spans
+                    .into_iter()
+                    .map(|span| (row, span.start + value_start..span.end + value_start))
+                    .collect(),
+            });
+            continue;
+        }
+        // Lines below a key belong to it while they are indented or list items
+        let Some(field) = fields.last_mut().filter(|field| field.rows.end == row) else {
+            continue;
+        };
+        let item = line
+            .trim_start()
+            .strip_prefix('-')
+            .filter(|rest| rest.is_empty() || rest.starts_with(' ') || rest.starts_with('\t'));
+        if !line.starts_with([' ', '\t']) && item.is_none() {
+            continue;
+        }
+        field.rows.end = row + 1;
+        let Some(rest) = item else {
+            continue;
+        };
+        let start = line.len() - rest.len();
+        let (text, span) = unquote(rest);
+        let empty_text = matches!(&field.value, Value::Text(text) if text.is_empty());
+        if empty_text {
+            field.value = Value::List(Vec::new());
+            field.spans.clear();
+        }
+        if let Value::List(items) = &mut field.value {
+            items.push(text);
+            field
+                .spans
+                .push((row, span.start + start..span.end + start));
+        }
+    }
+    Some(Frontmatter { rows, fields })
+}
+
+/// A field's key and where its value starts, if `line` is a `key:` line
+pub(crate) fn key_of(line: &str) -> Option<(&str, usize)> {
+    if line.starts_with([' ', '\t', '#', '-']) {
+        return None;
+    }
+    let colon = line.find(':')?;
+    let after = &line[colon + 1..];
+    if !(after.is_empty() || after.starts_with([' ', '\t'])) {
+        return None;
+    }
+    let key = line[..colon].trim_end();
+    (!key.is_empty()).then_some((key, colon + 1))
+}
+
+/// A value as written after its key, and where its text or each item is
+pub fn parse_value(written: &str) -> (Value, Vec<Range<usize>>) {
+    let start = written.len() - written.trim_start().len();
+    let trimmed = written.trim();
+    if let Some(inner) = trimmed
+        .strip_prefix('[')
+        .and_then(|rest| rest.strip_suffix(']'))
+    {
+        let mut items = Vec::new();
+        let mut spans = Vec::new();
+        let mut offset = start + 1;
+        for piece in split_items(inner) {
+            let lead = piece.len() - piece.trim_start().len();
+            let (text, span) = unquote(piece.trim_start());
+            if !piece.trim().is_empty() {
+                items.push(text);
+                spans.push(span.start + offset + lead..span.end + offset + lead);
+            }
+            offset += piece.len() + 1;
+        }
+        return (Value::List(items), spans);
+    }
+    let (text, span) = unquote(strip_comment(trimmed));
+    let spans = std::iter::once(span.start + start..span.end + start).collect();
+    (Value::Text(text), spans)
+}
+
+/// The pieces of an inline list between commas outside quotes
+fn split_items(inner: &str) -> Vec<&str> {
+    let mut pieces = Vec::new();
+    let mut quote = None;
+    let mut from = 0;
+    for (i, c) in inner.char_indices() {
+        match (quote, c) {
+            (None, '"' | '\'') => quote = Some(c),
+            (Some(open), _) if c == open => quote = None,
+            (None, ',') => {
+                pieces.push(&inner[from..i]);
+                from = i + 1;
+            }
+            _ => {}
+        }
+    }
+    pieces.push(&inner[from..]);
+    pieces
+}
+
+/// `text` without a trailing ` # comment`, unless it is quoted
+fn strip_comment(text: &str) -> &str {
+    if text.starts_with(['"', '\'']) {
+        return text;
+    }
+    match text.find(" #") {
+        Some(at) => text[..at].trim_end(),
+        None => text,
+    }
+}
+
+/// The text of a plain or quoted scalar, and where it is written without
+/// its quotes
+fn unquote(written: &str) -> (String, Range<usize>) {
+    let trimmed = written.trim_end();
+    if trimmed.len() >= 2 {
+        if let Some(inner) = trimmed
+            .strip_prefix('"')
+            .and_then(|rest| rest.strip_suffix('"'))
+        {
+            let mut text = String::with_capacity(inner.len());
+            let mut chars = inner.chars();
+            while let Some(c) = chars.next() {
+                if c != '\\' {
+                    text.push(c);
+                    continue;
+                }
+                match chars.next() {
+                    Some('n') => text.push('\n'),
+                    Some('t') => text.push('\t'),
+                    Some(other) => text.push(other),
+                    None => text.push('\\'),
+                }
+            }
+            return (text, 1..trimmed.len() - 1);
+        }
+        if let Some(inner) = trimmed
+            .strip_prefix('\'')
+            .and_then(|rest| rest.strip_suffix('\''))
+        {
+            return (inner.replace("''", "'"), 1..trimmed.len() - 1);
+        }
+    }
+    (trimmed.to_string(), 0..trimmed.len())
+}
+
+impl Frontmatter {
+    pub fn field(&self, key: &str) -> Option<&Field> {
+        self.fields.iter().find(|field| field.key == key)
+    }
+
+    pub fn get(&self, key: &str) -> Option<&Value> {
+        self.field(key).map(|field| &field.value)
+    }
+
+    pub fn title(&self) -> Option<&str> {
+        match self.get("title")? {
+            Value::Text(title) if !title.is_empty() => Some(title),
+            _ => None,
+        }
+    }
+
+    /// The `tags` field's tags without any `#`, whether a list or text
+    /// separated by commas
+    pub fn tags(&self) -> Vec<&str> {
+        let tags: Vec<&str> = match self.get("tags") {
+            Some(Value::List(items)) => items.iter().map(String::as_str).collect(),
+            Some(Value::Text(text)) => text.split(',').collect(),
+            None => Vec::new(),
+        };
+        tags.into_iter()
+            .map(|tag| tag.trim().trim_start_matches('#'))
+            .filter(|tag| !tag.is_empty())
+            .collect()
+    }
+
+    /// The `date` field, written as `YYYY-MM-DD` with perhaps a time after
+    pub fn date(&self) -> Option<NaiveDate> {
+        match self.get("date")? {
+            Value::Text(text) => NaiveDate::parse_from_str(text.get(..10)?, "%Y-%m-%d").ok(),
+            Value::List(_) => None,
+        }
+    }
+
+    /// One line standing for the block when it is collapsed: the title,
+    /// tags and date, or else how many fields there are
+    pub fn summary(&self) -> String {
+        let mut parts = Vec::new();
+        if let Some(title) = self.title() {
+            parts.push(title.to_string());
+        }
+        let tags = self.tags();
+        if !tags.is_empty() {
+            let tags: Vec<String> = tags.iter().map(|tag| format!("#{tag}")).collect();
+            parts.push(tags.join(" "));
+        }
+        if let Some(date) = self.date() {
+            parts.push(date.format("%Y-%m-%d").to_string());
+        }
+        if parts.is_empty() {
+            parts.push(match self.fields.len() {
+                1 => "1 field".to_string(),
+                count => format!("{count} fields"),
+            });
+        }
+        format!("▸ {}", parts.join(" · "))
+    }
+}
+
+/// `lines` with field `key` set to `value`: its own lines rewritten if it
+/// is there, else added at the end of the block, which is started if the
+/// note has none. A list written one item a line stays that way.
+pub fn set_field(lines: &[String], key: &str, value: &Value) -> Vec<String> {
+    let mut lines = lines.to_vec();
+    let Some(frontmatter) = parse(&lines) else {
+        let mut block = vec!["---".to_string()];
+        block.extend(field_lines(key, value, None));
+        block.push("---".to_string());
+        lines.splice(0..0, block);
+        return lines;
+    };
+    match frontmatter.field(key) {
+        Some(field) => {
+            let item_indent = match field.value {
+                Value::List(_) if field.rows.len() > 1 => {
+                    let line = &lines[field.rows.start + 1];
+                    Some(line[..line.len() - line.trim_start().len()].to_string())
+                }
+                _ => None,
+            };
+            let rows = field.rows.clone();
+            lines.splice(rows, field_lines(key, value, item_indent));
+        }
+        None => {
+            let close = frontmatter.rows.end - 1;
+            lines.splice(close..close, field_lines(key, value, None));
+        }
+    }
+    lines
+}
+
+/// `lines` without field `key`, and without the block if that leaves it
+/// empty
+pub fn remove_field(lines: &[String], key: &str) -> Vec<String> {
+    let mut lines = lines.to_vec();
+    let Some(frontmatter) = parse(&lines) else {
+        return lines;
+    };
+    let Some(field) = frontmatter.field(key) else {
+        return lines;
+    };
+    if frontmatter.rows.len() - 2 == field.rows.len() {
+        lines.drain(frontmatter.rows);
+        if lines.is_empty() {
+            lines.push(String::new());
+        }
+    } else {
+        lines.drain(field.rows.clone());
+    }
+    lines
+}
+
+/// A field as written: on one line, or with a list's items one a line
+/// under it when `item_indent` is given
+fn field_lines(key: &str, value: &Value, item_indent: Option<String>) -> Vec<String> {
+    match (value, item_indent) {
+        (Value::Text(text), _) => vec![format!("{key}: {}", scalar(text, false))],
+        (Value::List(items), Some(indent)) => std::iter::once(format!("{key}:"))
+            .chain(
+                items
+                    .iter()
+                    .map(|item| format!("{indent}- {}", scalar(item, false))),
+            )
+            .collect(),
+        (Value::List(items), None) => {
+            let items: Vec<String> = items.iter().map(|item| scalar(item, true)).collect();
+            vec![format!("{key}: [{}]", items.join(", "))]
+        }
+    }
+}
+
+/// `text` as a YAML scalar, quoted when written plainly it would read as
+/// something else
+fn scalar(text: &str, in_list: bool) -> String {
+    let special = text.is_empty()
+        || text.trim() != text
+        || text.starts_with([
+            '-', '?', ':', ',', '[', ']', '{', '}', '#', '&', '*', '!', '|', '>', '\'', '"', '%',
+            '@', '`',
+        ])
+        || text.ends_with(':')
+        || text.contains(": ")
+        || text.contains(" #")
+        || text.contains(['\n', '\t'])
+        || (in_list && text.contains([',', '[', ']']));
+    if !special {
+        return text.to_string();
+    }
+    let mut quoted = String::with_capacity(text.len() + 2);
+    quoted.push('"');
+    for c in text.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            '\t' => quoted.push_str("\\t"),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
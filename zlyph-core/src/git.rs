@@ -0,0 +1,233 @@
+//! Git integration by running the `git` command: the committed version of a
+//! file, the gutter markers showing how the buffer differs from it, and
+//! previewing, reverting and staging each changed hunk
+
+use crate::diff::{self, ChangeKind, Hunk};
+use crate::BufferPosition;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::time::{Duration, Instant};
+
+/// How often the committed version is read again, to pick up new commits
+const BASE_REFRESH_INTERVAL: Duration = Duration::from_secs(5);
+
+/// `path` as committed at HEAD, or None if it is not in a git repository or
+/// not committed yet
+pub fn head_text(path: &Path) -> io::Result<Option<String>> {
+    show(path, "HEAD")
+}
+
+/// `path` as of `revision`, e.g. `HEAD~2` or a branch name; None if it is
+/// not in a git repository or did not exist then
+pub fn show(path: &Path, revision: &str) -> io::Result<Option<String>> {
+    let (Some(dir), Some(name)) = (path.parent(), path.file_name()) else {
+        return Ok(None);
+    };
+    if !dir.is_dir() {
+        return Ok(None);
+    }
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(dir)
+        .arg("show")
+        .arg(format!("{}:./{}", revision, name.to_string_lossy()))
+        .stdin(Stdio::null())
+        .stderr(Stdio::null())
+        .output()?;
+    if !output.status.success() {
+        return Ok(None);
+    }
+    Ok(Some(String::from_utf8_lossy(&output.stdout).into_owned()))
+}
+
+/// Run git in `dir`, failing with its error message if it does
+fn run_git(dir: &Path, args: &[&str], input: Option<&str>) -> io::Result<String> {
+    let mut child = Command::new("git")
+        .arg("-C")
+        .arg(dir)
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()?;
+    if let (Some(input), Some(mut stdin)) = (input, child.stdin.take()) {
+        stdin.write_all(input.as_bytes())?;
+    }
+    let output = child.wait_with_output()?;
+    if !output.status.success() {
+        let message = String::from_utf8_lossy(&output.stderr);
+        return Err(io::Error::other(message.trim().to_string()));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+/// `@@ -a,b +c,d @@` for a hunk without context lines, where an empty side
+/// gives the line before it
+fn hunk_header(hunk: &Hunk) -> String {
+    let start = |start: usize, len: usize| if len == 0 { start } else { start + 1 };
+    format!(
+        "@@ -{},{} +{},{} @@",
+        start(hunk.old_start, hunk.old_len),
+        hunk.old_len,
+        start(hunk.new_start, hunk.new_len),
+        hunk.new_len
+    )
+}
+
+/// How the buffer differs from the file's last commit, kept up to date as
+/// it is edited
+#[derive(Debug, Default)]
+pub struct GitGutter {
+    path: Option<PathBuf>,
+    /// Lines of the committed version; None outside a repository
+    base: Option<Vec<String>>,
+    base_loaded: Option<Instant>,
+    hunks: Vec<Hunk>,
+    /// Engine revision the hunks were computed for
+    revision: Option<u64>,
+}
+
+impl GitGutter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Recompute the hunks if the buffer or its path changed since the last
+    /// call, and reread the committed version now and then; returns whether
+    /// the markers changed
+    pub fn update(&mut self, path: Option<&Path>, lines: &[String], revision: u64) -> bool {
+        if self.path.as_deref() != path {
+            self.path = path.map(Path::to_path_buf);
+            self.base_loaded = None;
+            self.revision = None;
+        }
+        let refresh = self
+            .base_loaded
+            .is_none_or(|loaded| loaded.elapsed() >= BASE_REFRESH_INTERVAL);
+        if refresh {
+            self.base_loaded = Some(Instant::now());
+            let base = self
+                .path
+                .as_deref()
+                .and_then(|path| head_text(path).ok().flatten())
+                .map(|text| text.lines().map(String::from).collect::<Vec<_>>());
+            if base != self.base {
+                self.base = base;
+                self.revision = None;
+            }
+        }
+        if self.revision == Some(revision) {
+            return false;
+        }
+        self.revision = Some(revision);
+
+        let hunks = match &self.base {
+            Some(base) => diff::diff_lines(base, lines),
+            None => Vec::new(),
+        };
+        let changed = hunks != self.hunks;
+        self.hunks = hunks;
+        changed
+    }
+
+    pub fn hunks(&self) -> &[Hunk] {
+        &self.hunks
+    }
+
+    /// The hunk marked beside `row`
+    pub fn hunk_at(&self, row: usize, line_count: usize) -> Option<Hunk> {
+        self.hunks.iter().copied().find(|hunk| {
+            if hunk.new_len == 0 {
+                row == hunk.new_start.min(line_count.saturating_sub(1))
+            } else {
+                (hunk.new_start..hunk.new_start + hunk.new_len).contains(&row)
+            }
+        })
+    }
+
+    /// The hunk as a unified diff without context, for showing in a popup
+    pub fn preview(&self, hunk: &Hunk, lines: &[String]) -> String {
+        let mut text = hunk_header(hunk);
+        for line in self.old_lines(hunk) {
+            text.push_str("\n-");
+            text.push_str(line);
+        }
+        for line in &lines[hunk.new_start..hunk.new_start + hunk.new_len] {
+            text.push_str("\n+");
+            text.push_str(line);
+        }
+        text
+    }
+
+    /// `lines` with the hunk put back as it was committed
+    pub fn revert(&self, hunk: &Hunk, lines: &[String]) -> Vec<String> {
+        let mut reverted = lines.to_vec();
+        reverted.splice(
+            hunk.new_start..hunk.new_start + hunk.new_len,
+            self.old_lines(hunk).iter().cloned(),
+        );
+        reverted
+    }
+
+    /// Add the hunk's new lines to the git index, leaving other changes to
+    /// the file unstaged
+    pub fn stage(&self, hunk: &Hunk, lines: &[String]) -> io::Result<()> {
+        let path = self
+            .path
+            .as_deref()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "buffer has no file"))?;
+        let dir = path.parent().unwrap_or(Path::new("."));
+        let root = run_git(dir, &["rev-parse", "--show-toplevel"], None)?;
+        let root = PathBuf::from(root.trim());
+        let relative = path
+            .canonicalize()?
+            .strip_prefix(root.canonicalize()?)
+            .map_err(|_| io::Error::other("file is outside the repository"))?
+            .to_string_lossy()
+            .replace('\\', "/");
+
+        let mut patch = format!("diff --git a/{0} b/{0}\n--- a/{0}\n+++ b/{0}\n", relative);
+        patch.push_str(&self.preview(hunk, lines));
+        patch.push('\n');
+        run_git(
+            &root,
+            &["apply", "--cached", "--unidiff-zero", "-"],
+            Some(&patch),
+        )?;
+        Ok(())
+    }
+
+    fn old_lines(&self, hunk: &Hunk) -> &[String] {
+        self.base.as_deref().map_or(&[], |base| {
+            &base[hunk.old_start..hunk.old_start + hunk.old_len]
+        })
+    }
+
+    /// Marker to draw beside `row`. Removed lines are marked on the line
+    /// that followed them, or the last line if they ended the file.
+    pub fn marker(&self, row: usize, line_count: usize) -> Option<ChangeKind> {
+        self.hunk_at(row, line_count).map(|hunk| hunk.kind())
+    }
+
+    /// Start of the first change below `row`, wrapping to the first change
+    pub fn next_change(&self, row: usize, line_count: usize) -> Option<BufferPosition> {
+        self.hunks
+            .iter()
+            .map(|hunk| hunk.new_start)
+            .find(|&start| start > row)
+            .or_else(|| self.hunks.first().map(|hunk| hunk.new_start))
+            .map(|start| BufferPosition::new(start.min(line_count.saturating_sub(1)), 0))
+    }
+
+    /// Start of the last change above `row`, wrapping to the last change
+    pub fn prev_change(&self, row: usize, line_count: usize) -> Option<BufferPosition> {
+        self.hunks
+            .iter()
+            .rev()
+            .map(|hunk| hunk.new_start)
+            .find(|&start| start < row)
+            .or_else(|| self.hunks.last().map(|hunk| hunk.new_start))
+            .map(|start| BufferPosition::new(start.min(line_count.saturating_sub(1)), 0))
+    }
+}
@@ -0,0 +1,164 @@
+//! Matching paths against `.gitignore` rules
+//!
+//! Supports the commonly used parts of the format: `*`, `?`, `**`, character
+//! classes, `!` negation, trailing `/` for directories and patterns anchored
+//! to the directory of the `.gitignore` file.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+#[derive(Debug, Clone)]
+struct Rule {
+    pattern: Vec<char>,
+    negated: bool,
+    dir_only: bool,
+    /// Matched against the whole relative path rather than the file name
+    anchored: bool,
+}
+
+/// Rules from one `.gitignore` file, applying to paths below `base`
+#[derive(Debug, Clone)]
+pub struct Gitignore {
+    base: PathBuf,
+    rules: Vec<Rule>,
+}
+
+impl Gitignore {
+    pub fn parse(base: &Path, content: &str) -> Self {
+        let rules = content.lines().filter_map(parse_rule).collect();
+        Self {
+            base: base.to_path_buf(),
+            rules,
+        }
+    }
+
+    /// Rules from `dir/.gitignore`, if the file exists
+    pub fn load(dir: &Path) -> Option<Self> {
+        let content = fs::read_to_string(dir.join(".gitignore")).ok()?;
+        Some(Self::parse(dir, &content))
+    }
+
+    /// Whether these rules decide `path`: Some(true) if it is ignored,
+    /// Some(false) if a negated rule re-includes it, None if no rule matches.
+    /// The last matching rule wins, as in git.
+    pub fn matched(&self, path: &Path, is_dir: bool) -> Option<bool> {
+        let relative = path.strip_prefix(&self.base).ok()?;
+        let relative: Vec<char> = relative
+            .components()
+            .map(|c| c.as_os_str().to_string_lossy())
+            .collect::<Vec<_>>()
+            .join("/")
+            .chars()
+            .collect();
+        let name_start = relative
+            .iter()
+            .rposition(|&c| c == '/')
+            .map_or(0, |i| i + 1);
+
+        self.rules
+            .iter()
+            .rev()
+            .find(|rule| {
+                (is_dir || !rule.dir_only)
+                    && if rule.anchored {
+                        glob_match(&rule.pattern, &relative)
+                    } else {
+                        glob_match(&rule.pattern, &relative[name_start..])
+                    }
+            })
+            .map(|rule| !rule.negated)
+    }
+}
+
+fn parse_rule(line: &str) -> Option<Rule> {
+    let line = line.trim_end();
+    if line.is_empty() || line.starts_with('#') {
+        return None;
+    }
+
+    let (negated, line) = match line.strip_prefix('!') {
+        Some(rest) => (true, rest),
+        None => (false, line.strip_prefix('\\').unwrap_or(line)),
+    };
+    let (dir_only, line) = match line.strip_suffix('/') {
+        Some(rest) => (true, rest),
+        None => (false, line),
+    };
+    let anchored = line.contains('/');
+    let line = line.strip_prefix('/').unwrap_or(line);
+    if line.is_empty() {
+        return None;
+    }
+
+    Some(Rule {
+        pattern: line.chars().collect(),
+        negated,
+        dir_only,
+        anchored,
+    })
+}
+
+/// Match `text` against a gitignore glob: `*` and `?` stay within one path
+/// component, `**` spans any number of them
+pub fn glob_match(pattern: &[char], text: &[char]) -> bool {
+    match pattern.split_first() {
+        None => text.is_empty(),
+        Some(('*', rest)) if rest.first() == Some(&'*') => {
+            let rest = &rest[1..];
+            match rest.split_first() {
+                // "**/" also matches no directories at all
+                Some(('/', after)) => {
+                    glob_match(after, text)
+                        || (0..text.len())
+                            .any(|i| text[i] == '/' && glob_match(after, &text[i + 1..]))
+                }
+                _ => (0..=text.len()).any(|i| glob_match(rest, &text[i..])),
+            }
+        }
+        Some(('*', rest)) => (0..=text.len())
+            .take_while(|&i| i == 0 || text[i - 1] != '/')
+            .any(|i| glob_match(rest, &text[i..])),
+        Some(('?', rest)) => {
+            text.first().is_some_and(|&c| c != '/') && glob_match(rest, &text[1..])
+        }
+        Some(('[', rest)) => match match_class(rest, text.first().copied()) {
+            Some((matched, after)) => matched && glob_match(after, &text[1..]),
+            // An unclosed bracket is an ordinary character
+            None => text.first() == Some(&'[') && glob_match(rest, &text[1..]),
+        },
+        Some(('\\', rest)) if !rest.is_empty() => {
+            text.first() == Some(&rest[0]) && glob_match(&rest[1..], &text[1..])
+        }
+        Some((&c, rest)) => text.first() == Some(&c) && glob_match(rest, &text[1..]),
+    }
+}
+
+/// Match one character against a class like `[a-z]` or `[!0-9]`, given the
+/// pattern after the `[`. Returns whether it matched and the pattern after the
+/// closing `]`, or None if the class is never closed.
+fn match_class(pattern: &[char], c: Option<char>) -> Option<(bool, &[char])> {
+    let (negated, body) = match pattern.first() {
+        Some('!' | '^') => (true, &pattern[1..]),
+        _ => (false, pattern),
+    };
+    // A `]` right after the opening bracket is part of the class
+    let close = body.iter().skip(1).position(|&c| c == ']')? + 1;
+    let class = &body[..close];
+    let after = &body[close + 1..];
+
+    let Some(c) = c.filter(|&c| c != '/') else {
+        return Some((false, after));
+    };
+    let mut matched = false;
+    let mut i = 0;
+    while i < class.len() {
+        if i + 2 < class.len() && class[i + 1] == '-' {
+            matched |= (class[i]..=class[i + 2]).contains(&c);
+            i += 3;
+        } else {
+            matched |= class[i] == c;
+            i += 1;
+        }
+    }
+    Some((matched != negated, after))
+}
@@ -0,0 +1,76 @@
+//! Writing sessions: words written since a session started, measured against
+//! an optional word-count target or time limit
+
+use crate::config::GoalConfig;
+use std::time::{Duration, Instant};
+
+/// Number of whitespace-separated words containing at least one letter or digit
+pub fn count_words(lines: &[String]) -> usize {
+    lines
+        .iter()
+        .flat_map(|line| line.split_whitespace())
+        .filter(|word| word.chars().any(char::is_alphanumeric))
+        .count()
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct WritingSession {
+    pub goal: GoalConfig,
+    pub started: Instant,
+    /// Word count of the buffer when the session started
+    pub start_words: usize,
+}
+
+impl WritingSession {
+    pub fn new(goal: GoalConfig, current_words: usize) -> Self {
+        Self {
+            goal,
+            started: Instant::now(),
+            start_words: current_words,
+        }
+    }
+
+    /// Net words added since the session started; deleting text counts against it
+    pub fn words_written(&self, current_words: usize) -> usize {
+        current_words.saturating_sub(self.start_words)
+    }
+
+    fn duration(&self) -> Option<Duration> {
+        self.goal.minutes.map(|m| Duration::from_secs(m * 60))
+    }
+
+    /// Fraction of the goal reached, from 0.0 to 1.0: the word target when one
+    /// is set, otherwise the time limit. None for an open-ended session.
+    pub fn progress(&self, current_words: usize) -> Option<f32> {
+        let progress = if let Some(target) = self.goal.words.filter(|&w| w > 0) {
+            self.words_written(current_words) as f32 / target as f32
+        } else {
+            let duration = self.duration()?;
+            self.started.elapsed().as_secs_f32() / duration.as_secs_f32().max(1.0)
+        };
+        Some(progress.min(1.0))
+    }
+
+    pub fn is_complete(&self, current_words: usize) -> bool {
+        self.progress(current_words) == Some(1.0)
+    }
+
+    /// Short description for a status bar, e.g. "120 / 500 words · 12:30 left"
+    pub fn status(&self, current_words: usize) -> String {
+        let written = self.words_written(current_words);
+        let mut status = match self.goal.words {
+            Some(target) => format!("{} / {} words", written, target),
+            None => format!("{} words", written),
+        };
+
+        if let Some(duration) = self.duration() {
+            let left = duration.saturating_sub(self.started.elapsed()).as_secs();
+            if left == 0 {
+                status.push_str(" · time's up");
+            } else {
+                status.push_str(&format!(" · {}:{:02} left", left / 60, left % 60));
+            }
+        }
+        status
+    }
+}
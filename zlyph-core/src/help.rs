@@ -0,0 +1,121 @@
+//! The help overlay: every action a frontend binds, with its keys, narrowed
+//! by a search and scrolled a screen at a time
+//!
+//! Frontends build it from their keymap rather than a written list, so the
+//! help always shows the keys that actually work.
+
+use crate::EditorAction;
+
+/// An action and every key bound to it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HelpEntry {
+    /// Group the action is listed under, such as "Editing" or "Notes"
+    pub section: String,
+    pub action: String,
+    /// Key names such as "Ctrl+Shift+Z", in keymap order
+    pub keys: Vec<String>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct HelpOverlay {
+    entries: Vec<HelpEntry>,
+    pub query: String,
+    /// Index of the first match shown
+    pub scroll: usize,
+}
+
+impl HelpOverlay {
+    /// Collect `(section, action, keys)` bindings into entries, putting the
+    /// keys of an action bound more than once on one entry
+    pub fn new<I, S, A, K>(bindings: I) -> Self
+    where
+        I: IntoIterator<Item = (S, A, K)>,
+        S: Into<String>,
+        A: Into<String>,
+        K: Into<String>,
+    {
+        let mut entries: Vec<HelpEntry> = Vec::new();
+        for (section, action, keys) in bindings {
+            let (section, action, keys) = (section.into(), action.into(), keys.into());
+            match entries
+                .iter_mut()
+                .find(|entry| entry.section == section && entry.action == action)
+            {
+                Some(entry) if !entry.keys.contains(&keys) => entry.keys.push(keys),
+                Some(_) => {}
+                None => entries.push(HelpEntry {
+                    section,
+                    action,
+                    keys: vec![keys],
+                }),
+            }
+        }
+        Self {
+            entries,
+            ..Self::default()
+        }
+    }
+
+    pub fn entries(&self) -> &[HelpEntry] {
+        &self.entries
+    }
+
+    /// Entries with every word of the query in their section, action or
+    /// keys, ignoring case
+    pub fn matches(&self) -> Vec<&HelpEntry> {
+        let query = self.query.to_lowercase();
+        let words: Vec<&str> = query.split_whitespace().collect();
+        self.entries
+            .iter()
+            .filter(|entry| {
+                let text = format!(
+                    "{} {} {}",
+                    entry.section,
+                    entry.action,
+                    entry.keys.join(" ")
+                )
+                .to_lowercase();
+                words.iter().all(|word| text.contains(word))
+            })
+            .collect()
+    }
+
+    pub fn type_text(&mut self, text: &str) {
+        self.query.push_str(text);
+        self.scroll = 0;
+    }
+
+    pub fn backspace(&mut self) {
+        self.query.pop();
+        self.scroll = 0;
+    }
+
+    /// Scroll by `lines`, keeping a screen of `height` lines filled where
+    /// there are enough matches
+    pub fn scroll_by(&mut self, lines: isize, height: usize) {
+        let last = self.matches().len().saturating_sub(height);
+        self.scroll = self.scroll.saturating_add_signed(lines).min(last);
+    }
+}
+
+/// An action's name as a sentence, e.g. "Go to definition" for
+/// `GoToDefinition`
+pub fn action_label(action: &EditorAction) -> String {
+    let debug = format!("{:?}", action);
+    let name = debug
+        .split(|c: char| !c.is_alphanumeric())
+        .next()
+        .unwrap_or_default();
+    let mut label = String::new();
+    for (i, c) in name.chars().enumerate() {
+        if i == 0 {
+            label.push(c);
+        } else if c.is_uppercase() {
+            label.push(' ');
+            label.extend(c.to_lowercase());
+        } else {
+            label.push(c);
+        }
+    }
+    label
+}
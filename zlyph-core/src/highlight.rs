@@ -0,0 +1,143 @@
+//! Styling the buffer's text for display: a token pass chosen by the file's
+//! type marks each line with [`Style`]s, which both frontends map onto their
+//! own colors and fonts. Markdown is the only language so far; rainbow
+//! brackets, when on, are laid over any file's highlights.
+
+use crate::{brackets, markdown};
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+
+/// How a run of text is shown; markup is the syntax itself, drawn dimmed
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Style {
+    /// Heading level, 1 to 6
+    pub heading: Option<u8>,
+    pub bold: bool,
+    pub italic: bool,
+    pub strikethrough: bool,
+    pub code: bool,
+    pub link: bool,
+    pub quote: bool,
+    pub markup: bool,
+    /// Nesting depth of a matched bracket, from 0 at the outermost pair
+    pub bracket: Option<u8>,
+    /// How many bracket pairs the text is inside, 0 outside them all
+    pub scope: u8,
+}
+
+/// The styled runs of one line, in order and not overlapping, leaving out
+/// unstyled text; byte ranges on character boundaries
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct LineHighlights {
+    pub spans: Vec<(Range<usize>, Style)>,
+    /// Where each link is written, markup included, and its target
+    pub links: Vec<(Range<usize>, String)>,
+}
+
+impl LineHighlights {
+    /// The target of the link at `column`
+    pub fn link_at(&self, column: usize) -> Option<&str> {
+        self.links
+            .iter()
+            .find(|(range, _)| range.contains(&column))
+            .map(|(_, target)| target.as_str())
+    }
+}
+
+/// Where following a link goes
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LinkTarget {
+    /// Opened in the system's browser or mail client
+    Url(String),
+    /// Opened in the editor
+    File(PathBuf),
+}
+
+impl LinkTarget {
+    /// A link written in `document`: anything with a scheme is a URL, the
+    /// rest are paths relative to the document's folder
+    pub fn resolve(target: &str, document: &Path) -> Self {
+        let scheme = target.split_once(':').is_some_and(|(scheme, _)| {
+            scheme.len() > 1
+                && scheme
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || c == '+')
+        });
+        if scheme {
+            return LinkTarget::Url(target.to_string());
+        }
+        // A heading anchor is dropped; the file opens at the top
+        let path = target.split('#').next().unwrap_or_default();
+        let folder = document.parent().unwrap_or(Path::new(""));
+        LinkTarget::File(folder.join(path))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Language {
+    Markdown,
+}
+
+impl Language {
+    pub fn for_path(path: &Path) -> Option<Self> {
+        let extension = path.extension()?.to_str()?.to_ascii_lowercase();
+        match extension.as_str() {
+            "md" | "markdown" | "mdown" | "mkd" => Some(Language::Markdown),
+            _ => None,
+        }
+    }
+
+    pub fn highlight(self, lines: &[String]) -> Vec<LineHighlights> {
+        match self {
+            Language::Markdown => markdown::highlight(lines),
+        }
+    }
+}
+
+/// The highlights of the open buffer, recomputed when it changes
+#[derive(Debug, Default)]
+pub struct Highlighter {
+    path: Option<PathBuf>,
+    /// Engine revision the highlights were computed for
+    revision: Option<u64>,
+    /// Whether bracket pairs are colored by depth, see [`brackets`]
+    brackets: bool,
+    lines: Vec<LineHighlights>,
+}
+
+impl Highlighter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn update(&mut self, path: Option<&Path>, lines: &[String], revision: u64) {
+        if self.path.as_deref() == path && self.revision == Some(revision) {
+            return;
+        }
+        self.path = path.map(Path::to_path_buf);
+        self.revision = Some(revision);
+        self.lines = path
+            .and_then(Language::for_path)
+            .map(|language| language.highlight(lines))
+            .unwrap_or_default();
+        if self.brackets {
+            brackets::apply(lines, &mut self.lines);
+        }
+    }
+
+    /// Turn rainbow brackets on or off, taking effect at the next update
+    pub fn set_brackets(&mut self, on: bool) {
+        if self.brackets != on {
+            self.brackets = on;
+            self.revision = None;
+        }
+    }
+
+    pub fn line(&self, row: usize) -> Option<&LineHighlights> {
+        self.lines.get(row)
+    }
+
+    pub fn link_at(&self, row: usize, column: usize) -> Option<&str> {
+        self.line(row)?.link_at(column)
+    }
+}
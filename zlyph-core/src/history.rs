@@ -0,0 +1,232 @@
+//! Local history: timestamped copies of each edited file, kept under
+//! `~/.config/zlyph/history` independently of git
+//!
+//! Each file gets a directory named by a hash of its absolute path, holding
+//! a `path` file that names it and one snapshot per version, named by when it
+//! was taken in milliseconds since the Unix epoch. Snapshots older than
+//! `max_age_days` are removed, as are the oldest ones once a file's snapshots
+//! take more than `max_size_kb`; the newest is always kept.
+
+use crate::config::{self, HistoryConfig};
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+/// One saved version of a file
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Snapshot {
+    /// Where the snapshot is stored
+    pub file: PathBuf,
+    pub time: SystemTime,
+    pub size: u64,
+}
+
+impl Snapshot {
+    pub fn read(&self) -> io::Result<String> {
+        fs::read_to_string(&self.file)
+    }
+
+    /// How long before `now` it was taken, e.g. "5 minutes ago"
+    pub fn age_label(&self, now: SystemTime) -> String {
+        let secs = now.duration_since(self.time).unwrap_or_default().as_secs();
+        let (count, unit) = match secs {
+            0..60 => return "just now".to_string(),
+            60..3600 => (secs / 60, "minute"),
+            3600..86400 => (secs / 3600, "hour"),
+            _ => (secs / 86400, "day"),
+        };
+        let plural = if count == 1 { "" } else { "s" };
+        format!("{} {}{} ago", count, unit, plural)
+    }
+
+    /// Size for listing, e.g. "512 B" or "1.2 KB"
+    pub fn size_label(&self) -> String {
+        match self.size {
+            0..1024 => format!("{} B", self.size),
+            1024..1048576 => format!("{:.1} KB", self.size as f64 / 1024.0),
+            _ => format!("{:.1} MB", self.size as f64 / 1048576.0),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct LocalHistory {
+    dir: PathBuf,
+    config: HistoryConfig,
+    /// When each file was last snapshotted
+    recorded: HashMap<PathBuf, Instant>,
+}
+
+impl LocalHistory {
+    pub fn new(config: HistoryConfig) -> Self {
+        Self::with_dir(Self::default_dir(), config)
+    }
+
+    pub fn with_dir(dir: PathBuf, config: HistoryConfig) -> Self {
+        Self {
+            dir,
+            config,
+            recorded: HashMap::new(),
+        }
+    }
+
+    pub fn default_dir() -> PathBuf {
+        config::config_dir().join("history")
+    }
+
+    /// Snapshot `text` as the content of `path` after an edit, unless the
+    /// file was snapshotted less than `interval_secs` ago
+    pub fn record(&mut self, path: &Path, text: &str) -> io::Result<()> {
+        let interval = Duration::from_secs(self.config.interval_secs);
+        if self
+            .recorded
+            .get(path)
+            .is_some_and(|last| last.elapsed() < interval)
+        {
+            return Ok(());
+        }
+        self.snapshot(path, text).map(|_| ())
+    }
+
+    /// Snapshot `text` as the content of `path` now, e.g. when the file is
+    /// opened or left; returns false if it matches the newest snapshot or
+    /// history is turned off
+    pub fn snapshot(&mut self, path: &Path, text: &str) -> io::Result<bool> {
+        if !self.config.enabled {
+            return Ok(false);
+        }
+        self.recorded.insert(path.to_path_buf(), Instant::now());
+        let snapshots = self.snapshots(path)?;
+        if let Some(newest) = snapshots.first() {
+            if newest.read().ok().as_deref() == Some(text) {
+                return Ok(false);
+            }
+        }
+
+        let dir = self.file_dir(path);
+        fs::create_dir_all(&dir)?;
+        fs::write(dir.join("path"), key(path).to_string_lossy().as_bytes())?;
+        let mut millis = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_millis();
+        // Keep names increasing even if the clock went back or two
+        // snapshots fall in the same millisecond
+        if let Some(newest) = snapshots.first() {
+            millis = millis.max(millis_of(newest) + 1);
+        }
+        fs::write(dir.join(format!("{}.txt", millis)), text)?;
+        self.prune(path)?;
+        Ok(true)
+    }
+
+    /// Snapshots of `path`, newest first
+    pub fn snapshots(&self, path: &Path) -> io::Result<Vec<Snapshot>> {
+        let entries = match fs::read_dir(self.file_dir(path)) {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(e),
+        };
+        let mut snapshots = Vec::new();
+        for entry in entries {
+            let entry = entry?;
+            let name = entry.file_name();
+            let Some(millis) = name
+                .to_str()
+                .and_then(|name| name.strip_suffix(".txt"))
+                .and_then(|stem| stem.parse::<u64>().ok())
+            else {
+                continue;
+            };
+            snapshots.push(Snapshot {
+                file: entry.path(),
+                time: UNIX_EPOCH + Duration::from_millis(millis),
+                size: entry.metadata()?.len(),
+            });
+        }
+        snapshots.sort_by_key(|snapshot| std::cmp::Reverse(snapshot.time));
+        Ok(snapshots)
+    }
+
+    /// Remove snapshots past the age and size limits, keeping the newest
+    fn prune(&self, path: &Path) -> io::Result<()> {
+        let max_age = Duration::from_secs(self.config.max_age_days * 86400);
+        let max_size = self.config.max_size_kb * 1024;
+        let now = SystemTime::now();
+        let mut total = 0;
+        for (index, snapshot) in self.snapshots(path)?.iter().enumerate() {
+            total += snapshot.size;
+            let too_old = now.duration_since(snapshot.time).unwrap_or_default() > max_age;
+            if index > 0 && (too_old || total > max_size) {
+                fs::remove_file(&snapshot.file)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn file_dir(&self, path: &Path) -> PathBuf {
+        self.dir.join(path_key(path))
+    }
+}
+
+/// A file name standing for `path`: a hash of its absolute path, the same
+/// across runs and builds
+pub(crate) fn path_key(path: &Path) -> String {
+    format!("{:016x}", fnv1a(key(path).to_string_lossy().as_bytes()))
+}
+
+/// The absolute path history is kept under
+fn key(path: &Path) -> PathBuf {
+    path.canonicalize().unwrap_or_else(|_| path.to_path_buf())
+}
+
+fn millis_of(snapshot: &Snapshot) -> u128 {
+    snapshot
+        .time
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis()
+}
+
+/// A hash that stays the same across builds, unlike std's `DefaultHasher`
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf29ce484222325, |hash, &byte| {
+        (hash ^ byte as u64).wrapping_mul(0x100000001b3)
+    })
+}
+
+/// The snapshot list both frontends show for picking a version to compare
+/// with or restore
+#[derive(Debug, Clone, Default)]
+pub struct HistoryBrowser {
+    /// Index into [`HistoryBrowser::snapshots`]
+    pub selected: usize,
+    snapshots: Vec<Snapshot>,
+}
+
+impl HistoryBrowser {
+    pub fn new(snapshots: Vec<Snapshot>) -> Self {
+        Self {
+            selected: 0,
+            snapshots,
+        }
+    }
+
+    pub fn snapshots(&self) -> &[Snapshot] {
+        &self.snapshots
+    }
+
+    pub fn select_previous(&mut self) {
+        self.selected = self.selected.saturating_sub(1);
+    }
+
+    pub fn select_next(&mut self) {
+        self.selected = (self.selected + 1).min(self.snapshots.len().saturating_sub(1));
+    }
+
+    pub fn selected_snapshot(&self) -> Option<&Snapshot> {
+        self.snapshots.get(self.selected)
+    }
+}
@@ -0,0 +1,215 @@
+//! User automation: commands run when a file is opened, saved, changed or
+//! left idle, or when the editor loses focus
+//!
+//! Hooks come from `[[hooks]]` entries in the config, or are registered in
+//! code. `{file}` in a hook's arguments is replaced by the file's path; the
+//! command also gets `ZLYPH_FILE` and `ZLYPH_EVENT` in its environment and
+//! runs in the file's directory with no terminal attached.
+//!
+//! Both frontends save on every edit, so `save` hooks wait until edits pause
+//! (or the file is left) rather than running on each keystroke.
+
+use crate::gitignore::glob_match;
+use serde::Deserialize;
+use std::collections::BTreeSet;
+use std::io;
+use std::path::Path;
+use std::process::{Child, Command, Stdio};
+use std::time::{Duration, Instant};
+
+/// Quiet time after the last edit before `save` hooks run
+pub const SAVE_SETTLE: Duration = Duration::from_millis(500);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum HookEvent {
+    Open,
+    Save,
+    Change,
+    Idle,
+    FocusLost,
+}
+
+impl HookEvent {
+    pub fn name(self) -> &'static str {
+        match self {
+            HookEvent::Open => "open",
+            HookEvent::Save => "save",
+            HookEvent::Change => "change",
+            HookEvent::Idle => "idle",
+            HookEvent::FocusLost => "focus_lost",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct HookDefinition {
+    pub on: HookEvent,
+    pub command: String,
+    #[serde(default)]
+    pub args: Vec<String>,
+    /// Glob matched against the file name, e.g. "*.md"; unset matches every file
+    #[serde(default)]
+    pub files: Option<String>,
+    /// For `idle` hooks: seconds without edits before the hook runs
+    #[serde(default = "default_idle_secs")]
+    pub idle_secs: u64,
+}
+
+fn default_idle_secs() -> u64 {
+    60
+}
+
+impl HookDefinition {
+    fn applies_to(&self, path: &Path) -> bool {
+        let Some(pattern) = &self.files else {
+            return true;
+        };
+        let name = path
+            .file_name()
+            .map_or_else(String::new, |name| name.to_string_lossy().into_owned());
+        let pattern: Vec<char> = pattern.chars().collect();
+        let name: Vec<char> = name.chars().collect();
+        glob_match(&pattern, &name)
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct HookRegistry {
+    hooks: Vec<HookDefinition>,
+    /// Hooks still running, by index; a hook is not started again until its
+    /// previous run has finished
+    running: Vec<(usize, Child)>,
+    last_edit: Option<Instant>,
+    save_pending: bool,
+    /// Idle hooks already run since the last edit
+    idle_done: BTreeSet<usize>,
+}
+
+impl HookRegistry {
+    pub fn new(hooks: Vec<HookDefinition>) -> Self {
+        Self {
+            hooks,
+            ..Self::default()
+        }
+    }
+
+    pub fn register(&mut self, hook: HookDefinition) {
+        self.hooks.push(hook);
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.hooks.is_empty()
+    }
+
+    /// Start the hooks for `event` on `path`. Returns the first command that
+    /// failed to start; the others still run.
+    pub fn fire(&mut self, event: HookEvent, path: &Path) -> io::Result<()> {
+        let indices: Vec<usize> = (0..self.hooks.len())
+            .filter(|&i| self.hooks[i].on == event)
+            .collect();
+        self.run(&indices, path)
+    }
+
+    /// Note an edit to `path`: runs `change` hooks and schedules `save` hooks
+    pub fn edited(&mut self, path: &Path) -> io::Result<()> {
+        self.last_edit = Some(Instant::now());
+        self.save_pending = true;
+        self.idle_done.clear();
+        self.fire(HookEvent::Change, path)
+    }
+
+    /// Run `save` hooks once edits have settled and `idle` hooks once their
+    /// idle time has passed; call regularly
+    pub fn poll(&mut self, path: &Path) -> io::Result<()> {
+        self.reap();
+        let Some(idle) = self.last_edit.map(|last| last.elapsed()) else {
+            return Ok(());
+        };
+        let mut result = Ok(());
+        if self.save_pending && idle >= SAVE_SETTLE {
+            result = self.flush(path);
+        }
+
+        let due: Vec<usize> = (0..self.hooks.len())
+            .filter(|&i| {
+                let hook = &self.hooks[i];
+                hook.on == HookEvent::Idle
+                    && !self.idle_done.contains(&i)
+                    && idle >= Duration::from_secs(hook.idle_secs)
+            })
+            .collect();
+        self.idle_done.extend(&due);
+        result.and(self.run(&due, path))
+    }
+
+    /// How long until `poll` has a `save` or `idle` hook to run, so the
+    /// frontend can sleep until then; `None` when nothing is waiting
+    pub fn next_due(&self) -> Option<Duration> {
+        let idle = self.last_edit?.elapsed();
+        let save = self.save_pending.then_some(SAVE_SETTLE);
+        let idle_hooks = (0..self.hooks.len())
+            .filter(|&i| self.hooks[i].on == HookEvent::Idle && !self.idle_done.contains(&i))
+            .map(|i| Duration::from_secs(self.hooks[i].idle_secs));
+        save.into_iter()
+            .chain(idle_hooks)
+            .min()
+            .map(|due| due.saturating_sub(idle))
+    }
+
+    /// Run pending `save` hooks now, e.g. before switching files or quitting
+    pub fn flush(&mut self, path: &Path) -> io::Result<()> {
+        if !std::mem::take(&mut self.save_pending) {
+            return Ok(());
+        }
+        self.fire(HookEvent::Save, path)
+    }
+
+    /// Wait for running hooks to finish, so a formatter run on save is done
+    /// before the editor exits
+    pub fn wait(&mut self) {
+        for (_, mut child) in self.running.drain(..) {
+            let _ = child.wait();
+        }
+    }
+
+    fn reap(&mut self) {
+        self.running
+            .retain_mut(|(_, child)| matches!(child.try_wait(), Ok(None)));
+    }
+
+    fn run(&mut self, indices: &[usize], path: &Path) -> io::Result<()> {
+        self.reap();
+        let mut result = Ok(());
+        for &i in indices {
+            let hook = &self.hooks[i];
+            if !hook.applies_to(path) || self.running.iter().any(|(running, _)| *running == i) {
+                continue;
+            }
+            let file = path.to_string_lossy();
+            let mut command = Command::new(&hook.command);
+            command
+                .args(hook.args.iter().map(|arg| arg.replace("{file}", &file)))
+                .env("ZLYPH_FILE", path)
+                .env("ZLYPH_EVENT", hook.on.name())
+                .stdin(Stdio::null())
+                .stdout(Stdio::null())
+                .stderr(Stdio::null());
+            if let Some(dir) = path.parent().filter(|dir| dir.is_dir()) {
+                command.current_dir(dir);
+            }
+            match command.spawn() {
+                Ok(child) => self.running.push((i, child)),
+                Err(e) => {
+                    if result.is_ok() {
+                        result = Err(io::Error::new(
+                            e.kind(),
+                            format!("{} hook `{}`: {}", hook.on.name(), hook.command, e),
+                        ));
+                    }
+                }
+            }
+        }
+        result
+    }
+}
@@ -0,0 +1,134 @@
+//! Indentation guides: the vertical lines frontends draw at each level of a
+//! line's indent, and through blank lines between indented ones
+//!
+//! Indents are measured in columns, a tab reaching the next multiple of
+//! [`TAB_WIDTH`]. How wide a level is comes from the buffer itself: the
+//! step by which lines are most often indented further than the one before.
+
+use std::ops::Range;
+
+/// Columns a tab stands for, and the width of a level when the buffer
+/// doesn't show one
+pub const TAB_WIDTH: usize = 4;
+
+/// Width of the indent of `line` in columns; None for a blank line
+pub fn indent(line: &str) -> Option<usize> {
+    let mut width = 0;
+    for c in line.chars() {
+        match c {
+            ' ' => width += 1,
+            '\t' => width += TAB_WIDTH - width % TAB_WIDTH,
+            _ => return Some(width),
+        }
+    }
+    None
+}
+
+/// The width of one indent level in `lines`
+pub fn unit(lines: &[String]) -> usize {
+    let mut steps = [0usize; 9];
+    let mut previous = 0;
+    for width in lines.iter().filter_map(|line| indent(line)) {
+        if let Some(step) = width
+            .checked_sub(previous)
+            .filter(|step| (2..=8).contains(step))
+        {
+            steps[step] += 1;
+        }
+        previous = width;
+    }
+    // Ties go to the narrower step
+    match (2..=8).rev().max_by_key(|&step| steps[step]) {
+        Some(step) if steps[step] > 0 => step,
+        _ => TAB_WIDTH,
+    }
+}
+
+/// The guide the cursor is inside: the innermost block around its line, or
+/// the block its line opens
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ActiveGuide {
+    pub column: usize,
+    pub rows: Range<usize>,
+}
+
+/// Indent levels of a buffer's lines, worked out again only when it changes
+#[derive(Debug, Default)]
+pub struct IndentGuides {
+    revision: Option<u64>,
+    unit: usize,
+    levels: Vec<usize>,
+}
+
+impl IndentGuides {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn update(&mut self, lines: &[String], revision: u64) {
+        if self.revision == Some(revision) {
+            return;
+        }
+        self.revision = Some(revision);
+        self.unit = unit(lines);
+
+        let indents: Vec<Option<usize>> = lines.iter().map(|line| indent(line)).collect();
+        // A blank line takes the smaller indent of the lines around it, so
+        // guides run through gaps inside a block but not after its end
+        let mut below = vec![0; indents.len()];
+        let mut next = 0;
+        for (row, width) in indents.iter().enumerate().rev() {
+            next = width.unwrap_or(next);
+            below[row] = next;
+        }
+        let mut above = 0;
+        self.levels = indents
+            .iter()
+            .zip(below)
+            .map(|(width, below)| {
+                let width = match width {
+                    Some(width) => {
+                        above = *width;
+                        *width
+                    }
+                    None => above.min(below),
+                };
+                width.div_ceil(self.unit)
+            })
+            .collect();
+    }
+
+    /// Columns a level takes up
+    pub fn unit(&self) -> usize {
+        self.unit
+    }
+
+    /// Columns of the guides drawn on line `row`, from the left
+    pub fn columns(&self, row: usize) -> impl Iterator<Item = usize> + '_ {
+        let levels = self.levels.get(row).copied().unwrap_or(0);
+        (0..levels).map(move |level| level * self.unit)
+    }
+
+    /// The guide to highlight with the cursor on line `row`
+    pub fn active(&self, row: usize) -> Option<ActiveGuide> {
+        let levels = *self.levels.get(row)?;
+        let opens_block = self.levels.get(row + 1).is_some_and(|&next| next > levels);
+        let (level, start) = if opens_block {
+            (levels, row + 1)
+        } else {
+            (levels.checked_sub(1)?, row)
+        };
+        let start = (0..start)
+            .rev()
+            .take_while(|&row| self.levels[row] > level)
+            .last()
+            .unwrap_or(start);
+        let end = (start..self.levels.len())
+            .find(|&row| self.levels[row] <= level)
+            .unwrap_or(self.levels.len());
+        Some(ActiveGuide {
+            column: level * self.unit,
+            rows: start..end,
+        })
+    }
+}
@@ -0,0 +1,154 @@
+//! Keeping a single running instance: the first instance listens on a local
+//! socket, and later launches hand it the file to open and exit
+//!
+//! Each request is one line holding an absolute path; the listener answers
+//! `ok` once it has queued the path. Only Unix domain sockets are supported,
+//! so elsewhere every launch gets its own instance.
+
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::Receiver;
+
+/// Socket the running GUI listens on
+pub fn default_socket_path() -> PathBuf {
+    crate::config::config_dir().join("gui.sock")
+}
+
+/// Ask the instance listening on `socket` to open `file`. Returns false if no
+/// instance is running, in which case the caller should start one.
+#[cfg(unix)]
+pub fn send_to_existing(socket: &Path, file: &Path) -> io::Result<bool> {
+    use std::io::{BufRead, BufReader, Write};
+    use std::os::unix::net::UnixStream;
+    use std::time::Duration;
+
+    let mut stream = match UnixStream::connect(socket) {
+        Ok(stream) => stream,
+        Err(e)
+            if matches!(
+                e.kind(),
+                io::ErrorKind::NotFound | io::ErrorKind::ConnectionRefused
+            ) =>
+        {
+            return Ok(false);
+        }
+        Err(e) => return Err(e),
+    };
+    stream.set_read_timeout(Some(Duration::from_secs(2)))?;
+    writeln!(stream, "{}", file.display())?;
+
+    let mut reply = String::new();
+    BufReader::new(stream).read_line(&mut reply)?;
+    Ok(reply.trim() == "ok")
+}
+
+#[cfg(not(unix))]
+pub fn send_to_existing(_socket: &Path, _file: &Path) -> io::Result<bool> {
+    Ok(false)
+}
+
+/// The local path a `file://` URL names, as the system sends when a file is
+/// opened with the app from the Finder
+pub fn path_from_url(url: &str) -> Option<PathBuf> {
+    let path = url.strip_prefix("file://")?;
+    // A host, if any, comes before the path; only this machine's files open
+    let path = match path.find('/') {
+        Some(0) => path,
+        Some(slash) if &path[..slash] == "localhost" => &path[slash..],
+        _ => return None,
+    };
+
+    let mut bytes = Vec::with_capacity(path.len());
+    let mut rest = path.as_bytes();
+    while let Some((&byte, tail)) = rest.split_first() {
+        let escaped = (byte == b'%')
+            .then(|| tail.get(..2))
+            .flatten()
+            .and_then(|hex| u8::from_str_radix(std::str::from_utf8(hex).ok()?, 16).ok());
+        match escaped {
+            Some(decoded) => {
+                bytes.push(decoded);
+                rest = &tail[2..];
+            }
+            None => {
+                bytes.push(byte);
+                rest = tail;
+            }
+        }
+    }
+    String::from_utf8(bytes).ok().map(PathBuf::from)
+}
+
+/// Paths sent by later launches, collected on a background thread
+pub struct InstanceListener {
+    socket: PathBuf,
+    receiver: Receiver<PathBuf>,
+}
+
+impl InstanceListener {
+    /// Listen on `socket`, replacing a stale socket file left by an instance
+    /// that did not shut down cleanly
+    #[cfg(unix)]
+    pub fn bind(socket: &Path) -> io::Result<Self> {
+        use std::io::{BufRead, BufReader, Write};
+        use std::os::unix::net::{UnixListener, UnixStream};
+        use std::sync::mpsc;
+        use std::thread;
+
+        if socket.exists() {
+            if UnixStream::connect(socket).is_ok() {
+                return Err(io::Error::new(
+                    io::ErrorKind::AddrInUse,
+                    "another instance is listening",
+                ));
+            }
+            std::fs::remove_file(socket)?;
+        }
+        if let Some(parent) = socket.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let listener = UnixListener::bind(socket)?;
+
+        let (sender, receiver) = mpsc::channel();
+        thread::spawn(move || {
+            for mut stream in listener.incoming().flatten() {
+                let mut line = String::new();
+                if BufReader::new(&stream).read_line(&mut line).is_err() {
+                    continue;
+                }
+                let path = line.trim_end_matches(['\r', '\n']);
+                if path.is_empty() {
+                    continue;
+                }
+                if sender.send(PathBuf::from(path)).is_err() {
+                    break;
+                }
+                let _ = stream.write_all(b"ok\n");
+            }
+        });
+
+        Ok(Self {
+            socket: socket.to_path_buf(),
+            receiver,
+        })
+    }
+
+    #[cfg(not(unix))]
+    pub fn bind(_socket: &Path) -> io::Result<Self> {
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "single-instance mode needs Unix domain sockets",
+        ))
+    }
+
+    /// Paths received since the last call
+    pub fn poll(&self) -> Vec<PathBuf> {
+        self.receiver.try_iter().collect()
+    }
+}
+
+impl Drop for InstanceListener {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.socket);
+    }
+}
@@ -0,0 +1,231 @@
+//! Characters that are hard or impossible to see: zero-width characters,
+//! bidirectional controls, unusual spaces and letters from other scripts
+//! that look like Latin ones, published as diagnostics
+//!
+//! Zero-width joiners and non-joiners are only flagged between ASCII
+//! characters, since emoji sequences and some scripts need them, and
+//! lookalike letters only in words that also have ASCII letters, so Greek
+//! and Cyrillic text is left alone.
+
+use crate::diagnostics::{Diagnostic, Severity};
+use crate::BufferPosition;
+use std::ops::Range;
+
+/// Source name of the diagnostics published for these characters
+pub const SOURCE: &str = "invisibles";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Kind {
+    /// Takes no space: zero-width characters, bidi controls, soft hyphens
+    Invisible,
+    /// A space other than the ordinary one, e.g. a no-break space
+    Space,
+    /// A letter from another script that looks like this Latin one
+    Lookalike(char),
+}
+
+/// A suspicious character in a line
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Suspicious {
+    pub range: Range<usize>,
+    pub c: char,
+    pub kind: Kind,
+}
+
+fn is_invisible(c: char) -> bool {
+    matches!(
+        c,
+        '\u{00AD}'
+            | '\u{061C}'
+            | '\u{180E}'
+            | '\u{200B}'
+            | '\u{200E}'
+            | '\u{200F}'
+            | '\u{202A}'..='\u{202E}'
+            | '\u{2060}'..='\u{2064}'
+            | '\u{2066}'..='\u{2069}'
+            | '\u{FEFF}'
+    )
+}
+
+fn is_joiner(c: char) -> bool {
+    matches!(c, '\u{200C}' | '\u{200D}')
+}
+
+fn is_unusual_space(c: char) -> bool {
+    matches!(
+        c,
+        '\u{00A0}' | '\u{2000}'..='\u{200A}' | '\u{202F}' | '\u{205F}'
+    )
+}
+
+/// The Latin letter `c` is mistaken for, if it is a Cyrillic or Greek one
+/// that looks the same
+fn latin_lookalike(c: char) -> Option<char> {
+    let latin = match c {
+        'а' => 'a',
+        'е' => 'e',
+        'һ' => 'h',
+        'і' => 'i',
+        'ј' => 'j',
+        'о' => 'o',
+        'р' => 'p',
+        'с' => 'c',
+        'ѕ' => 's',
+        'у' => 'y',
+        'х' => 'x',
+        'ԁ' => 'd',
+        'ԛ' => 'q',
+        'ԝ' => 'w',
+        'А' => 'A',
+        'В' => 'B',
+        'Е' => 'E',
+        'І' => 'I',
+        'Ј' => 'J',
+        'К' => 'K',
+        'М' => 'M',
+        'Н' => 'H',
+        'О' => 'O',
+        'Р' => 'P',
+        'С' => 'C',
+        'Ѕ' => 'S',
+        'Т' => 'T',
+        'Х' => 'X',
+        'Υ' => 'Y',
+        'ο' => 'o',
+        'ν' => 'v',
+        'Α' => 'A',
+        'Β' => 'B',
+        'Ε' => 'E',
+        'Ζ' => 'Z',
+        'Η' => 'H',
+        'Ι' => 'I',
+        'Κ' => 'K',
+        'Μ' => 'M',
+        'Ν' => 'N',
+        'Ο' => 'O',
+        'Ρ' => 'P',
+        'Τ' => 'T',
+        'Χ' => 'X',
+        _ => return None,
+    };
+    Some(latin)
+}
+
+/// Every suspicious character in `line`, in order
+pub fn find_in_line(line: &str) -> Vec<Suspicious> {
+    let mut found = Vec::new();
+    let chars: Vec<(usize, char)> = line.char_indices().collect();
+    let mut word_start = 0;
+    for (i, &(start, c)) in chars.iter().enumerate() {
+        let range = start..start + c.len_utf8();
+        let kind = if is_invisible(c) {
+            Some(Kind::Invisible)
+        } else if is_joiner(c) {
+            let before = i.checked_sub(1).map(|i| chars[i].1);
+            let after = chars.get(i + 1).map(|&(_, c)| c);
+            [before, after]
+                .iter()
+                .all(|c| c.is_none_or(|c| c.is_ascii()))
+                .then_some(Kind::Invisible)
+        } else if is_unusual_space(c) {
+            Some(Kind::Space)
+        } else {
+            None
+        };
+        if let Some(kind) = kind {
+            found.push(Suspicious { range, c, kind });
+        }
+
+        // Lookalikes are judged a word at a time, at the end of each word
+        let end_of_word = chars
+            .get(i + 1)
+            .is_none_or(|&(_, next)| !next.is_alphanumeric());
+        if !c.is_alphanumeric() {
+            word_start = i + 1;
+        } else if end_of_word {
+            let word = &chars[word_start..=i];
+            if word.iter().any(|&(_, c)| c.is_ascii_alphabetic()) {
+                found.extend(word.iter().filter_map(|&(start, c)| {
+                    latin_lookalike(c).map(|latin| Suspicious {
+                        range: start..start + c.len_utf8(),
+                        c,
+                        kind: Kind::Lookalike(latin),
+                    })
+                }));
+            }
+            word_start = i + 1;
+        }
+    }
+    found.sort_by_key(|suspicious| suspicious.range.start);
+    found
+}
+
+/// How a character is described, e.g. "zero width space (U+200B)"
+pub fn describe(c: char) -> String {
+    let name = unicode_names2::name(c)
+        .map(|name| name.to_string().to_lowercase())
+        .unwrap_or_else(|| "unnamed character".to_string());
+    format!("{} (U+{:04X})", name, c as u32)
+}
+
+/// Diagnostics for the suspicious characters in `lines`. Lookalike letters
+/// and unusual spaces offer their plain form as a quick fix.
+pub fn diagnostics(lines: &[String]) -> Vec<Diagnostic> {
+    let mut diagnostics = Vec::new();
+    for (row, line) in lines.iter().enumerate() {
+        if line.is_ascii() {
+            continue;
+        }
+        for suspicious in find_in_line(line) {
+            let (severity, message, replacements) = match suspicious.kind {
+                Kind::Invisible => (
+                    Severity::Warning,
+                    format!("Invisible {}", describe(suspicious.c)),
+                    Vec::new(),
+                ),
+                Kind::Space => (
+                    Severity::Information,
+                    format!("Unusual space: {}", describe(suspicious.c)),
+                    vec![" ".to_string()],
+                ),
+                Kind::Lookalike(latin) => (
+                    Severity::Warning,
+                    format!("{} looks like Latin \"{}\"", describe(suspicious.c), latin),
+                    vec![latin.to_string()],
+                ),
+            };
+            diagnostics.push(Diagnostic {
+                start: BufferPosition::new(row, suspicious.range.start),
+                end: BufferPosition::new(row, suspicious.range.end),
+                severity,
+                message,
+                source: SOURCE.to_string(),
+                replacements,
+            });
+        }
+    }
+    diagnostics
+}
+
+/// `line` with its invisible characters removed and unusual spaces made
+/// ordinary ones, and where `column` ends up; None if nothing changes.
+/// Lookalike letters are kept, since the quick fix handles them one by one.
+pub fn strip(line: &str, column: usize) -> Option<(String, usize)> {
+    let mut stripped = line.to_string();
+    let mut column = column;
+    let mut changed = false;
+    for suspicious in find_in_line(line).into_iter().rev() {
+        let replacement = match suspicious.kind {
+            Kind::Invisible => "",
+            Kind::Space => " ",
+            Kind::Lookalike(_) => continue,
+        };
+        stripped.replace_range(suspicious.range.clone(), replacement);
+        if suspicious.range.end <= column {
+            column = column - suspicious.range.len() + replacement.len();
+        }
+        changed = true;
+    }
+    changed.then_some((stripped, column))
+}
@@ -0,0 +1,140 @@
+//! Daily notes: one file per day named `YYYY-MM-DD.md` in the journal
+//! directory, created from a template or with a date heading when first
+//! opened, and stepped through day by day
+//!
+//! The journal directory is `[journal] directory`, else the notes directory
+//! if one is configured, else `~/.config/zlyph/journal`. The template is
+//! `[journal] template`, a path or the name of one in the templates
+//! directory, else the template called `daily` if there is one. It is filled
+//! in as in [`crate::templates`], with the note's day as `{{date}}`, which on
+//! its own becomes the heading date.
+
+use crate::config::{self, Config};
+use crate::templates::{self, Variables};
+use chrono::{Local, NaiveDate};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// How daily note files are named
+const FILE_DATE_FORMAT: &str = "%Y-%m-%d";
+
+#[derive(Debug, Clone)]
+pub struct Journal {
+    dir: PathBuf,
+    template: Option<PathBuf>,
+    /// strftime format of the date at the top of a new note
+    heading_format: String,
+}
+
+impl Journal {
+    pub fn new(config: &Config) -> Self {
+        let dir = config
+            .journal
+            .directory
+            .clone()
+            .or_else(|| config.notes.directory.clone())
+            .unwrap_or_else(|| config::config_dir().join("journal"));
+        Self::with_dir(dir, config)
+    }
+
+    pub fn with_dir(dir: PathBuf, config: &Config) -> Self {
+        let templates_dir = templates::default_dir();
+        let template = match &config.journal.template {
+            Some(template) => {
+                let name = template.to_string_lossy();
+                Some(templates::find(&templates_dir, &name).unwrap_or_else(|| template.clone()))
+            }
+            None => templates::find(&templates_dir, "daily"),
+        };
+        Self {
+            dir,
+            template,
+            heading_format: config.journal.heading_format.clone(),
+        }
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    pub fn today() -> NaiveDate {
+        Local::now().date_naive()
+    }
+
+    pub fn path_for(&self, date: NaiveDate) -> PathBuf {
+        self.dir
+            .join(format!("{}.md", date.format(FILE_DATE_FORMAT)))
+    }
+
+    /// The day `path` is the note for, if it is one of this journal's
+    pub fn date_of(&self, path: &Path) -> Option<NaiveDate> {
+        if path.parent()? != self.dir || path.extension()? != "md" {
+            return None;
+        }
+        let stem = path.file_stem()?.to_str()?;
+        NaiveDate::parse_from_str(stem, FILE_DATE_FORMAT).ok()
+    }
+
+    /// Days with a note, oldest first
+    pub fn dates(&self) -> Vec<NaiveDate> {
+        let Ok(entries) = fs::read_dir(&self.dir) else {
+            return Vec::new();
+        };
+        let mut dates: Vec<NaiveDate> = entries
+            .flatten()
+            .filter_map(|entry| self.date_of(&entry.path()))
+            .collect();
+        dates.sort();
+        dates
+    }
+
+    /// The closest day before or after the note at `current` that has a
+    /// note, counting from today when `current` is not a daily note
+    pub fn adjacent(&self, current: Option<&Path>, forward: bool) -> Option<NaiveDate> {
+        let from = current
+            .and_then(|path| self.date_of(path))
+            .unwrap_or_else(Self::today);
+        let dates = self.dates();
+        if forward {
+            dates.into_iter().find(|date| *date > from)
+        } else {
+            dates.into_iter().rev().find(|date| *date < from)
+        }
+    }
+
+    /// What a new note for `date` starts with: the template filled in, or a
+    /// heading with the date
+    pub fn initial_text(&self, date: NaiveDate, clipboard: String) -> io::Result<String> {
+        let vars = Variables {
+            date,
+            time: Local::now().time(),
+            title: templates::title_of(&self.path_for(date)),
+            clipboard,
+        };
+        let heading = templates::format_datetime(date, vars.time, &self.heading_format)
+            .ok_or_else(|| {
+                io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!("bad heading_format {:?}", self.heading_format),
+                )
+            })?;
+        match &self.template {
+            Some(template) => {
+                let text = fs::read_to_string(template)?.replace("{{date}}", &heading);
+                Ok(templates::render(&text, &vars))
+            }
+            None => Ok(format!("# {}\n\n", heading)),
+        }
+    }
+
+    /// The note for `date`, created if it does not exist yet
+    pub fn open(&self, date: NaiveDate, clipboard: String) -> io::Result<PathBuf> {
+        let path = self.path_for(date);
+        if !path.exists() {
+            fs::create_dir_all(&self.dir)?;
+            fs::write(&path, self.initial_text(date, clipboard)?)?;
+        }
+        Ok(path)
+    }
+}
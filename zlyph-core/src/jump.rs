@@ -0,0 +1,97 @@
+//! Jumping to a typed character: to the next occurrence of one on the line,
+//! as Vim's `f` and `t` do, or by leaping to a labeled pair anywhere in view
+//!
+//! A leap takes two characters and labels each place in view they occur
+//! together, nearest the cursor first; typing a label jumps there. The
+//! labels are published as inline virtual text under [`SOURCE`].
+
+use crate::BufferPosition;
+use std::ops::Range;
+
+/// Source name of the decorations labeling leap targets
+pub const SOURCE: &str = "leap";
+
+/// Keys used as labels, the easiest to reach first
+pub const LABELS: &str = "sfnjklhodweimbuyvrgtaqpcxz";
+
+/// A jump waiting for the next character typed
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Pending {
+    /// Onto the character, with `before` just ahead of it
+    Char { before: bool },
+    /// The first character of the pair to leap to
+    Leap,
+    /// The second, after `first`
+    LeapPair(char),
+    /// The label of one of `targets`
+    LeapLabel(Vec<LeapTarget>),
+}
+
+impl Pending {
+    /// What the hover says while the jump waits
+    pub fn prompt(&self) -> String {
+        match self {
+            Pending::Char { before: false } => "Jump to character…".to_string(),
+            Pending::Char { before: true } => "Jump before character…".to_string(),
+            Pending::Leap => "Leap to…".to_string(),
+            Pending::LeapPair(first) => format!("Leap to {}…", first),
+            Pending::LeapLabel(_) => "Type a label to leap there".to_string(),
+        }
+    }
+}
+
+/// A place a leap can land, and the label that picks it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LeapTarget {
+    pub position: BufferPosition,
+    pub label: char,
+}
+
+/// Column of the next `c` after `column` in `line`, or with `before` of the
+/// character just ahead of it; a `c` right after the cursor is passed over
+/// with `before`, so repeating the jump moves on
+pub fn find_char(line: &str, column: usize, c: char, before: bool) -> Option<usize> {
+    let column = column.min(line.len());
+    let start = column + line[column..].chars().next().map_or(0, char::len_utf8);
+    let mut found = line[start..]
+        .match_indices(c)
+        .map(|(at, _)| start + at)
+        .filter(|&at| !before || at > start);
+    let at = found.next()?;
+    if before {
+        line[..at].char_indices().next_back().map(|(at, _)| at)
+    } else {
+        Some(at)
+    }
+}
+
+/// Where `pair` occurs in `rows` of `lines`, each with a label, nearest
+/// `cursor` first; the cursor's own place isn't one, and places beyond the
+/// labels left go unlabeled
+pub fn leap_targets(
+    lines: &[String],
+    rows: Range<usize>,
+    pair: [char; 2],
+    cursor: BufferPosition,
+) -> Vec<LeapTarget> {
+    let pattern: String = pair.iter().collect();
+    let mut places: Vec<BufferPosition> = rows
+        .filter_map(|row| Some((row, lines.get(row)?)))
+        .flat_map(|(row, line)| {
+            line.match_indices(&pattern)
+                .map(move |(column, _)| BufferPosition::new(row, column))
+        })
+        .filter(|&place| place != cursor)
+        .collect();
+    places.sort_by_key(|place| {
+        (
+            place.row.abs_diff(cursor.row),
+            place.column.abs_diff(cursor.column),
+        )
+    });
+    places
+        .into_iter()
+        .zip(LABELS.chars())
+        .map(|(position, label)| LeapTarget { position, label })
+        .collect()
+}
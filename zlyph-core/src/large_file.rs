@@ -0,0 +1,142 @@
+//! Opening and saving files too big to treat like notes
+//!
+//! A local file at least `[large_file] threshold_mb` in size is read a
+//! chunk at a time straight into lines, without first holding the whole
+//! text as one string, and saved by streaming its lines to a temporary file
+//! that then replaces it. While such a file is open the engine keeps no
+//! undo history and skips the invisible character check, and frontends turn
+//! off highlighting, grammar checking, language servers, git markers, local
+//! history and saving on every keystroke; see
+//! [`EditorEngine::is_large_file`](crate::EditorEngine::is_large_file).
+
+use crate::config::LargeFileConfig;
+use crate::vfs;
+use std::fs::{self, File};
+use std::io::{self, BufRead, BufReader, BufWriter, Write};
+use std::path::Path;
+
+/// Bytes read or written at a time
+const CHUNK: usize = 1 << 20;
+
+/// Whether `path` is a local file at least the configured size
+pub fn is_large(path: &Path, config: &LargeFileConfig) -> bool {
+    !vfs::is_remote(path)
+        && fs::metadata(path).is_ok_and(|metadata| metadata.len() >= config.threshold_bytes())
+}
+
+/// The lines of the file at `path`, split as [`str::lines`] would split its
+/// text
+pub fn read_lines(path: &Path) -> io::Result<Vec<String>> {
+    read_lines_with(path, |_, _| true)
+}
+
+/// [`read_lines`], calling `keep_going` with the bytes read so far and the
+/// file's size after each chunk; it stops with [`io::ErrorKind::Interrupted`]
+/// once that returns false
+pub(crate) fn read_lines_with(
+    path: &Path,
+    mut keep_going: impl FnMut(u64, u64) -> bool,
+) -> io::Result<Vec<String>> {
+    let file = File::open(path)?;
+    let total = file.metadata()?.len();
+    let mut reader = BufReader::with_capacity(CHUNK, file);
+    let mut lines = Vec::new();
+    let mut line = String::new();
+    let (mut done, mut reported) = (0, 0);
+    loop {
+        let read = reader.read_line(&mut line)?;
+        if read == 0 {
+            break;
+        }
+        done += read as u64;
+        if line.ends_with('\n') {
+            line.pop();
+            if line.ends_with('\r') {
+                line.pop();
+            }
+        }
+        lines.push(std::mem::take(&mut line));
+        if done - reported >= CHUNK as u64 {
+            reported = done;
+            if !keep_going(done, total) {
+                return Err(cancelled());
+            }
+        }
+    }
+    if lines.is_empty() {
+        lines.push(String::new());
+    }
+    Ok(lines)
+}
+
+/// Write `lines` joined by `\n` to `path`, through a temporary file beside
+/// it so a failed save leaves the old file whole
+pub fn write_lines(path: &Path, lines: &[String]) -> io::Result<()> {
+    write_lines_with(path, lines, |_, _| true)
+}
+
+/// [`write_lines`], calling `keep_going` with the bytes written so far and
+/// the total after each chunk; once that returns false it stops with
+/// [`io::ErrorKind::Interrupted`], leaving the file as it was
+pub(crate) fn write_lines_with(
+    path: &Path,
+    lines: &[String],
+    mut keep_going: impl FnMut(u64, u64) -> bool,
+) -> io::Result<()> {
+    // Replace the file a link points to rather than the link
+    let path = &fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf());
+    let name = path
+        .file_name()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "not a file path"))?;
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let temp = path.with_file_name(format!(".{}.zlyph-save", name.to_string_lossy()));
+    let total = lines
+        .iter()
+        .map(|line| line.len() as u64 + 1)
+        .sum::<u64>()
+        .saturating_sub(1);
+    let result = (|| {
+        let mut writer = BufWriter::with_capacity(CHUNK, File::create(&temp)?);
+        let (mut done, mut reported) = (0, 0);
+        for (i, line) in lines.iter().enumerate() {
+            if i > 0 {
+                writer.write_all(b"\n")?;
+                done += 1;
+            }
+            // Long lines, such as a whole file given as one, go a chunk at a
+            // time so progress keeps coming
+            for chunk in line.as_bytes().chunks(CHUNK) {
+                writer.write_all(chunk)?;
+                done += chunk.len() as u64;
+                if done - reported >= CHUNK as u64 {
+                    reported = done;
+                    if !keep_going(done, total) {
+                        return Err(cancelled());
+                    }
+                }
+            }
+        }
+        writer
+            .into_inner()
+            .map_err(io::IntoInnerError::into_error)?
+            .sync_all()?;
+        if !keep_going(done, total) {
+            return Err(cancelled());
+        }
+        if let Ok(metadata) = fs::metadata(path) {
+            fs::set_permissions(&temp, metadata.permissions())?;
+        }
+        fs::rename(&temp, path)
+    })();
+    if result.is_err() {
+        let _ = fs::remove_file(&temp);
+    }
+    result
+}
+
+/// The error a read or write stopped part way reports
+pub(crate) fn cancelled() -> io::Error {
+    io::Error::new(io::ErrorKind::Interrupted, "cancelled")
+}
@@ -1,7 +1,139 @@
+//! Zlyph's editing engine, with no UI of its own.
+//!
+//! An [`EditorEngine`] holds the text, cursor, selection and undo history.
+//! A frontend turns keys and clicks into [`EditorAction`]s, passes them to
+//! [`EditorEngine::handle_action`] and draws [`EditorEngine::state`]. The
+//! terminal and GPUI frontends are built this way, and so can others be.
+//!
+//! ```
+//! use zlyph_core::{BufferPosition, EditorAction, EditorEngine};
+//!
+//! let mut engine = EditorEngine::builder().text("hello world").build();
+//! engine.handle_action(EditorAction::MoveToEndOfLine);
+//! engine.handle_action(EditorAction::TypeCharacter('!'));
+//! engine.handle_action(EditorAction::SelectToLineStart);
+//!
+//! assert_eq!(engine.text(), "hello world!");
+//! assert_eq!(engine.selected_text().as_deref(), Some("hello world!"));
+//! assert_eq!(engine.state().cursor, BufferPosition::new(0, 0));
+//! ```
+//!
+//! Positions are byte offsets within a line: [`BufferPosition`] is a row
+//! and column, and [`BufferRange`] runs from one position up to another.
+//!
+//! # Stability
+//!
+//! The items re-exported here at the crate root are the stable API and
+//! follow semver: [`EditorEngine`] and its builder, [`EditorAction`],
+//! [`ChangeEvent`], [`EditorState`], [`BufferPosition`], [`BufferRange`]
+//! and [`Config`]. [`EditorAction`] and [`ChangeEvent`] are
+//! `#[non_exhaustive]` so variants can be added in minor releases. The
+//! other public modules serve zlyph's own frontends and may change between
+//! minor versions before 1.0.
+//!
+//! See `examples/` for headless use.
+//!
+//! # Features
+//!
+//! The engine itself needs nothing from the platform. Parts of the frontends
+//! that do are behind cargo features, all off by default:
+//!
+//! - `terminal`: the [`terminal`] module, a shell in a pseudo-terminal
+//! - `live-sync`: the [`live_sync`] module, merging edits between instances
+//! - `keychain`: keeping passphrases of encrypted files in the OS keychain
+//! - `code-image`: the [`code_image`] module, code rendered to SVG and PNG
+//! - `system-fonts`: embedding an installed font in exported PDFs
+
+pub mod accessibility;
 pub mod actions;
+pub mod align;
+pub mod batch;
+pub mod bidi;
+pub mod bookmarks;
+pub mod brackets;
+pub mod calc;
+pub mod changes;
+pub mod char_picker;
+pub mod citations;
+#[cfg(feature = "code-image")]
+pub mod code_image;
+pub mod completion;
+pub mod composition;
+pub mod config;
+pub mod context_menu;
+pub mod cursor;
+pub mod decorations;
+pub mod definitions;
+pub mod diagnostics;
+pub mod dictation;
+pub mod diff;
+pub mod diff_view;
+pub mod elastic_tabstops;
+pub mod encryption;
 pub mod engine;
+pub mod ex;
+pub mod export;
+pub mod file_io;
+pub mod file_tree;
+pub mod footnotes;
+pub mod frontmatter;
+pub mod git;
+pub mod gitignore;
+pub mod goals;
+pub mod help;
+pub mod highlight;
+pub mod history;
+pub mod hooks;
+pub mod indent_guides;
+pub mod instance;
+pub mod invisibles;
+pub mod journal;
+pub mod jump;
+pub mod large_file;
+pub mod links;
+#[cfg(feature = "live-sync")]
+pub mod live_sync;
+pub mod long_lines;
+pub mod markdown;
+pub mod multi_cursor;
+pub mod note_index;
+pub mod notes;
+pub mod numbers;
+pub mod paste;
+pub mod pdf;
+pub mod pretty;
+pub mod project;
+pub mod project_replace;
+pub mod project_search;
+pub mod protected;
+pub mod quick_switch;
+pub mod replace;
+pub mod rpc;
+pub mod search;
+pub mod search_panel;
+pub mod sections;
+pub mod sentences;
+pub mod shell;
+pub mod snippets;
 pub mod state;
+pub mod structure;
+pub mod symbols;
+pub mod tables;
+pub mod tags;
+pub mod task_runner;
+pub mod templates;
+#[cfg(feature = "terminal")]
+pub mod terminal;
+pub mod text_objects;
+pub mod todos;
+pub mod transform;
+pub mod typography;
+pub mod vfs;
+pub mod workspace;
+pub mod yaml;
 
 pub use actions::EditorAction;
-pub use engine::EditorEngine;
-pub use state::{BufferPosition, EditorState};
+pub use changes::ChangeEvent;
+pub use config::Config;
+pub use engine::{EditorEngine, EditorEngineBuilder};
+pub use state::{BufferPosition, BufferRange, EditorState};
@@ -0,0 +1,162 @@
+//! URLs and file paths written as plain text in any buffer, found so they
+//! can be followed like Markdown links
+//!
+//! A path may end in `:42` or `:42:7` to open at that line and column, as
+//! compilers and grep print them. Relative paths need a file extension, so
+//! that words like "and/or" or dates like 1/2/2024 are not taken for paths.
+
+use crate::highlight::LinkTarget;
+use regex::Regex;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Link {
+    /// Where the link is written in its line, line suffix included
+    pub range: Range<usize>,
+    /// The URL, or the path without its line suffix
+    pub target: String,
+    /// 1-based line and column from a `:42:7` suffix
+    pub line: Option<usize>,
+    pub column: Option<usize>,
+}
+
+impl Link {
+    pub fn is_url(&self) -> bool {
+        url_pattern().is_match(&self.target)
+    }
+
+    /// Where following the link goes: URLs to the browser, `~` to the home
+    /// directory, and relative paths from the folder of `document`, or the
+    /// working directory for an untitled buffer
+    pub fn resolve(&self, document: Option<&Path>) -> LinkTarget {
+        if self.is_url() {
+            return LinkTarget::Url(if self.target.starts_with("www.") {
+                format!("https://{}", self.target)
+            } else {
+                self.target.clone()
+            });
+        }
+        if let Some(rest) = self.target.strip_prefix("~/") {
+            let home = std::env::var("HOME")
+                .or_else(|_| std::env::var("USERPROFILE"))
+                .unwrap_or_default();
+            return LinkTarget::File(PathBuf::from(home).join(rest));
+        }
+        let folder = document
+            .and_then(Path::parent)
+            .map(Path::to_path_buf)
+            .or_else(|| std::env::current_dir().ok())
+            .unwrap_or_default();
+        LinkTarget::File(folder.join(&self.target))
+    }
+}
+
+fn url_pattern() -> &'static Regex {
+    static PATTERN: OnceLock<Regex> = OnceLock::new();
+    PATTERN.get_or_init(|| {
+        Regex::new(r#"(?:(?:https?|ftp|file)://|mailto:|www\.)[^\s<>"'`]+"#).unwrap()
+    })
+}
+
+fn path_pattern() -> &'static Regex {
+    static PATTERN: OnceLock<Regex> = OnceLock::new();
+    PATTERN.get_or_init(|| {
+        Regex::new(
+            r"(?P<path>(?:~|\.{1,2})?/[\w.\-~/]*[\w\-~]|[\w.\-]+(?:/[\w.\-]+)+)(?::(?P<line>\d+)(?::(?P<column>\d+))?)?",
+        )
+        .unwrap()
+    })
+}
+
+/// The links written in `line`, in order
+pub fn find_all(line: &str) -> Vec<Link> {
+    let mut links: Vec<Link> = url_pattern()
+        .find_iter(line)
+        .filter(|found| starts_word(line, found.start()))
+        .map(|found| {
+            let text = trim_trailing_punctuation(found.as_str());
+            Link {
+                range: found.start()..found.start() + text.len(),
+                target: text.to_string(),
+                line: None,
+                column: None,
+            }
+        })
+        .collect();
+
+    for captures in path_pattern().captures_iter(line) {
+        let (whole, path) = (&captures[0], captures.name("path").unwrap());
+        let start = path.start();
+        if !starts_word(line, start)
+            || links.iter().any(|url| url.range.contains(&start))
+            || !looks_like_path(path.as_str())
+        {
+            continue;
+        }
+        let number = |name| captures.name(name).and_then(|m| m.as_str().parse().ok());
+        let (target, end) = match captures.name("line") {
+            Some(_) => (path.as_str(), start + whole.len()),
+            None => {
+                let text = trim_trailing_punctuation(path.as_str());
+                (text, start + text.len())
+            }
+        };
+        links.push(Link {
+            range: start..end,
+            target: target.to_string(),
+            line: number("line"),
+            column: number("column"),
+        });
+    }
+    links.sort_by_key(|link| link.range.start);
+    links
+}
+
+/// The link written at `column` of `line`
+pub fn link_at(line: &str, column: usize) -> Option<Link> {
+    find_all(line)
+        .into_iter()
+        .find(|link| link.range.contains(&column))
+}
+
+/// Whether a match at `start` begins a word, not the middle of one
+fn starts_word(line: &str, start: usize) -> bool {
+    line[..start]
+        .chars()
+        .next_back()
+        .is_none_or(|c| !(c.is_alphanumeric() || c == '_' || c == '/'))
+}
+
+/// Absolute, home and dot paths are taken as written; other relative paths
+/// need an extension starting with a letter on their last part
+fn looks_like_path(path: &str) -> bool {
+    if path.starts_with(['/', '~', '.']) {
+        return path.len() > 1;
+    }
+    let name = path.rsplit('/').next().unwrap_or_default();
+    name.rsplit_once('.').is_some_and(|(stem, extension)| {
+        !stem.is_empty() && extension.starts_with(|c: char| c.is_ascii_alphabetic())
+    })
+}
+
+/// Leave off punctuation that ends the sentence around a link, keeping a
+/// closing parenthesis that one in the link opened
+fn trim_trailing_punctuation(text: &str) -> &str {
+    let mut text = text;
+    loop {
+        let Some(last) = text.chars().next_back() else {
+            return text;
+        };
+        let trim = match last {
+            '.' | ',' | ';' | ':' | '!' | '?' | '\'' | '"' | ']' | '}' => true,
+            ')' => text.matches('(').count() < text.matches(')').count(),
+            _ => false,
+        };
+        if !trim {
+            return text;
+        }
+        text = &text[..text.len() - 1];
+    }
+}
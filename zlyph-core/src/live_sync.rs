@@ -0,0 +1,413 @@
+//! Live sync between instances editing the same file: each keeps the buffer
+//! in an Automerge text document and they exchange changes over a local
+//! socket, so simultaneous edits merge character by character instead of
+//! one instance reloading the file over the other's keystrokes
+//!
+//! The first instance to open a file hosts it on a socket under
+//! `~/.config/zlyph/sync`. Later ones connect and receive the host's
+//! document, then run Automerge's sync protocol with the host, which relays
+//! changes between its clients; if the host goes away, a client takes over.
+//! Messages are framed by a 4-byte big-endian length. Only Unix domain
+//! sockets are supported, so elsewhere every instance edits on its own.
+
+use crate::diff::changed_span;
+use crate::history::path_key;
+use automerge::sync::{self, SyncDoc};
+use automerge::transaction::Transactable;
+use automerge::{AutoCommit, ObjId, ObjType, ReadDoc, TextEncoding, Value, ROOT};
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Receiver, Sender};
+
+/// Largest message accepted from a peer
+const MAX_FRAME: usize = 64 << 20;
+
+/// Socket the instances editing `file` meet on
+pub fn socket_path(file: &Path) -> PathBuf {
+    crate::config::config_dir()
+        .join("sync")
+        .join(format!("{}.sock", path_key(file)))
+}
+
+fn automerge_error(e: automerge::AutomergeError) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, e.to_string())
+}
+
+enum Event {
+    #[cfg(unix)]
+    Connected(std::os::unix::net::UnixStream),
+    Message(usize, Vec<u8>),
+    Closed(usize),
+}
+
+#[cfg(unix)]
+struct Peer {
+    id: usize,
+    stream: std::os::unix::net::UnixStream,
+    state: sync::State,
+    /// Whether the host's whole document is still to come
+    awaiting_document: bool,
+}
+
+pub struct LiveSync {
+    doc: AutoCommit,
+    text: ObjId,
+    socket: PathBuf,
+    host: bool,
+    #[cfg(unix)]
+    peers: Vec<Peer>,
+    next_peer: usize,
+    sender: Sender<Event>,
+    events: Receiver<Event>,
+    /// The document's text as of the last local edit or merge
+    last_text: String,
+    /// The text this instance joined with, until the host's document arrives
+    joining: Option<String>,
+}
+
+impl LiveSync {
+    /// Join the instances editing `file`, or start hosting it with `text`
+    pub fn join(file: &Path, text: &str) -> io::Result<Self> {
+        Self::join_at(&socket_path(file), text)
+    }
+
+    /// Join the instances meeting on `socket`. A joining instance keeps
+    /// `text` until the host's document arrives, then takes the host's text
+    /// from [`LiveSync::poll`], keeping its own edits if the host had not
+    /// changed `text` meanwhile.
+    #[cfg(unix)]
+    pub fn join_at(socket: &Path, text: &str) -> io::Result<Self> {
+        use std::os::unix::net::UnixStream;
+
+        let (sender, events) = mpsc::channel();
+        let mut live = Self {
+            doc: AutoCommit::new_with_encoding(TextEncoding::UnicodeCodePoint),
+            text: ROOT,
+            socket: socket.to_path_buf(),
+            host: false,
+            peers: Vec::new(),
+            next_peer: 0,
+            sender,
+            events,
+            last_text: String::new(),
+            joining: None,
+        };
+        match UnixStream::connect(socket) {
+            Ok(stream) => {
+                live.last_text = text.to_string();
+                live.joining = Some(text.to_string());
+                live.add_host(stream)?;
+            }
+            Err(_) => {
+                live.text = live
+                    .doc
+                    .put_object(ROOT, "text", ObjType::Text)
+                    .map_err(automerge_error)?;
+                live.doc
+                    .splice_text(&live.text, 0, 0, text)
+                    .map_err(automerge_error)?;
+                live.last_text = text.to_string();
+                live.listen()?;
+            }
+        }
+        Ok(live)
+    }
+
+    #[cfg(not(unix))]
+    pub fn join_at(_socket: &Path, _text: &str) -> io::Result<Self> {
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "live sync needs Unix domain sockets",
+        ))
+    }
+
+    /// The merged text
+    pub fn text(&self) -> &str {
+        &self.last_text
+    }
+
+    pub fn is_host(&self) -> bool {
+        self.host
+    }
+
+    /// Whether the host's document has arrived, or this instance is the host
+    pub fn is_joined(&self) -> bool {
+        self.joining.is_none()
+    }
+
+    /// Other instances this one exchanges changes with directly
+    pub fn peer_count(&self) -> usize {
+        #[cfg(unix)]
+        {
+            self.peers.len()
+        }
+        #[cfg(not(unix))]
+        {
+            0
+        }
+    }
+
+    /// The file changed on disk: returns its text to merge into the buffer,
+    /// after passing it on like a local edit. Ignored while other instances
+    /// are connected, since their saves would look like outside changes.
+    pub fn file_changed(&mut self, content: &str) -> io::Result<Option<String>> {
+        if self.peer_count() > 0 {
+            return Ok(None);
+        }
+        let text = content.lines().collect::<Vec<_>>().join("\n");
+        if text == self.last_text {
+            return Ok(None);
+        }
+        self.local_edit(&text)?;
+        Ok(Some(text))
+    }
+
+    /// Record the buffer's new text after a local edit and send the change
+    pub fn local_edit(&mut self, text: &str) -> io::Result<()> {
+        if text == self.last_text {
+            return Ok(());
+        }
+        if self.joining.is_some() {
+            self.last_text = text.to_string();
+            return Ok(());
+        }
+        let (start, old_end, new_end) = changed_span(&self.last_text, text);
+        let position = self.last_text[..start].chars().count();
+        let deleted = self.last_text[start..old_end].chars().count();
+        self.doc
+            .splice_text(
+                &self.text,
+                position,
+                deleted as isize,
+                &text[start..new_end],
+            )
+            .map_err(automerge_error)?;
+        self.last_text = text.to_string();
+        self.send_changes();
+        Ok(())
+    }
+
+    /// Handle messages from other instances; returns the merged text if
+    /// their changes altered it
+    pub fn poll(&mut self) -> io::Result<Option<String>> {
+        let mut received = false;
+        while let Ok(event) = self.events.try_recv() {
+            match event {
+                #[cfg(unix)]
+                Event::Connected(stream) => self.add_client(stream)?,
+                Event::Message(id, bytes) => {
+                    self.receive(id, &bytes)?;
+                    received = true;
+                }
+                Event::Closed(id) => {
+                    self.remove_peer(id)?;
+                    received = true;
+                }
+            }
+        }
+        if !received || self.joining.is_some() {
+            return Ok(None);
+        }
+        self.send_changes();
+        let text = self.doc.text(&self.text).map_err(automerge_error)?;
+        if text == self.last_text {
+            return Ok(None);
+        }
+        self.last_text = text.clone();
+        Ok(Some(text))
+    }
+
+    #[cfg(unix)]
+    fn receive(&mut self, id: usize, bytes: &[u8]) -> io::Result<()> {
+        let Some(peer) = self.peers.iter_mut().find(|peer| peer.id == id) else {
+            return Ok(());
+        };
+        if !peer.awaiting_document {
+            let message = sync::Message::decode(bytes)
+                .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e.to_string()))?;
+            return self
+                .doc
+                .sync()
+                .receive_sync_message(&mut peer.state, message)
+                .map_err(automerge_error);
+        }
+
+        // After taking over from a host that went away, the two documents
+        // share their history and simply merge
+        peer.awaiting_document = false;
+        self.doc.load_incremental(bytes).map_err(automerge_error)?;
+        self.text = match self.doc.get(ROOT, "text").map_err(automerge_error)? {
+            Some((Value::Object(ObjType::Text), id)) => id,
+            _ => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    "host sent a document without text",
+                ))
+            }
+        };
+        if let Some(joined) = self.joining.take() {
+            let host_text = self.doc.text(&self.text).map_err(automerge_error)?;
+            if host_text == joined && self.last_text != joined {
+                let edited = std::mem::replace(&mut self.last_text, joined);
+                self.local_edit(&edited)?;
+            }
+        }
+        Ok(())
+    }
+
+    #[cfg(not(unix))]
+    fn receive(&mut self, _id: usize, _bytes: &[u8]) -> io::Result<()> {
+        Ok(())
+    }
+
+    /// Send each peer what it has not seen yet, dropping peers that fail
+    #[cfg(unix)]
+    fn send_changes(&mut self) {
+        let sync = self.doc.sync();
+        self.peers.retain_mut(|peer| {
+            if peer.awaiting_document {
+                return true;
+            }
+            match sync.generate_sync_message(&mut peer.state) {
+                Some(message) => write_frame(&mut peer.stream, &message.encode()).is_ok(),
+                None => true,
+            }
+        });
+    }
+
+    #[cfg(not(unix))]
+    fn send_changes(&mut self) {}
+
+    #[cfg(unix)]
+    fn remove_peer(&mut self, id: usize) -> io::Result<()> {
+        self.peers.retain(|peer| peer.id != id);
+        if self.host || !self.peers.is_empty() {
+            return Ok(());
+        }
+        // The host is gone: join whoever took over, or take over
+        if let Ok(stream) = std::os::unix::net::UnixStream::connect(&self.socket) {
+            return self.add_host(stream);
+        }
+        if self.joining.take().is_some() {
+            // Never got the host's document, so start one from this text
+            self.text = self
+                .doc
+                .put_object(ROOT, "text", ObjType::Text)
+                .map_err(automerge_error)?;
+            self.doc
+                .splice_text(&self.text, 0, 0, &self.last_text)
+                .map_err(automerge_error)?;
+        }
+        self.listen()
+    }
+
+    #[cfg(not(unix))]
+    fn remove_peer(&mut self, _id: usize) -> io::Result<()> {
+        Ok(())
+    }
+
+    /// Host on the socket, replacing a stale socket file left by an instance
+    /// that did not shut down cleanly
+    #[cfg(unix)]
+    fn listen(&mut self) -> io::Result<()> {
+        use std::os::unix::net::UnixListener;
+
+        if self.socket.exists() {
+            std::fs::remove_file(&self.socket)?;
+        }
+        if let Some(parent) = self.socket.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let listener = UnixListener::bind(&self.socket)?;
+        let sender = self.sender.clone();
+        std::thread::spawn(move || {
+            for stream in listener.incoming().flatten() {
+                if sender.send(Event::Connected(stream)).is_err() {
+                    break;
+                }
+            }
+        });
+        self.host = true;
+        Ok(())
+    }
+
+    /// Start syncing with the host, whose first message is its whole document
+    #[cfg(unix)]
+    fn add_host(&mut self, stream: std::os::unix::net::UnixStream) -> io::Result<()> {
+        self.host = false;
+        self.add_peer(stream, true)
+    }
+
+    /// Start syncing with a new client, which first gets the whole document
+    #[cfg(unix)]
+    fn add_client(&mut self, mut stream: std::os::unix::net::UnixStream) -> io::Result<()> {
+        if write_frame(&mut stream, &self.doc.save()).is_ok() {
+            self.add_peer(stream, false)?;
+        }
+        Ok(())
+    }
+
+    /// Read the peer's messages on a background thread
+    #[cfg(unix)]
+    fn add_peer(
+        &mut self,
+        stream: std::os::unix::net::UnixStream,
+        awaiting_document: bool,
+    ) -> io::Result<()> {
+        use std::time::Duration;
+
+        let id = self.next_peer;
+        self.next_peer += 1;
+        let mut reader = stream.try_clone()?;
+        stream.set_write_timeout(Some(Duration::from_secs(1)))?;
+        let sender = self.sender.clone();
+        std::thread::spawn(move || {
+            while let Ok(bytes) = read_frame(&mut reader) {
+                if sender.send(Event::Message(id, bytes)).is_err() {
+                    return;
+                }
+            }
+            let _ = sender.send(Event::Closed(id));
+        });
+        self.peers.push(Peer {
+            id,
+            stream,
+            state: sync::State::new(),
+            awaiting_document,
+        });
+        Ok(())
+    }
+}
+
+impl Drop for LiveSync {
+    fn drop(&mut self) {
+        #[cfg(unix)]
+        for peer in &self.peers {
+            let _ = peer.stream.shutdown(std::net::Shutdown::Both);
+        }
+        if self.host {
+            let _ = std::fs::remove_file(&self.socket);
+        }
+    }
+}
+
+fn read_frame(reader: &mut impl io::Read) -> io::Result<Vec<u8>> {
+    let mut length = [0u8; 4];
+    reader.read_exact(&mut length)?;
+    let length = u32::from_be_bytes(length) as usize;
+    if length > MAX_FRAME {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "message too large",
+        ));
+    }
+    let mut bytes = vec![0u8; length];
+    reader.read_exact(&mut bytes)?;
+    Ok(bytes)
+}
+
+fn write_frame(writer: &mut impl io::Write, bytes: &[u8]) -> io::Result<()> {
+    writer.write_all(&(bytes.len() as u32).to_be_bytes())?;
+    writer.write_all(bytes)?;
+    writer.flush()
+}
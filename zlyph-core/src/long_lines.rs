@@ -0,0 +1,86 @@
+//! Lines too long to lay out whole, as in minified JavaScript or JSON
+//!
+//! Opening a file with a line at least [`LIMIT`] bytes long shows a warning.
+//! The TUI then draws such a line a window at a time around the cursor,
+//! scrolling sideways half a window at a time, and the GUI wraps it in
+//! pieces of a fixed number of characters rather than measuring it word by
+//! word. Columns stay byte offsets into the whole line, so moving and
+//! editing work as on any other line.
+
+use std::ops::Range;
+
+/// Bytes from which a line is shown in pieces
+pub const LIMIT: usize = 10 * 1024;
+
+pub fn is_long(line: &str) -> bool {
+    line.len() >= LIMIT
+}
+
+/// The first line at least [`LIMIT`] long, as its row and length
+pub fn find(lines: &[String]) -> Option<(usize, usize)> {
+    lines
+        .iter()
+        .position(|line| is_long(line))
+        .map(|row| (row, lines[row].len()))
+}
+
+/// What to show on opening a file with the long line at `row`
+pub fn warning(row: usize, len: usize) -> String {
+    format!(
+        "Line {} is {} long and is shown in pieces; Format JSON can break it up",
+        row + 1,
+        size(len)
+    )
+}
+
+/// A byte count in the largest unit it makes at least one of, e.g. "2.4 MB"
+fn size(bytes: usize) -> String {
+    const KB: f64 = 1024.0;
+    let bytes = bytes as f64;
+    if bytes >= KB * KB {
+        format!("{:.1} MB", bytes / (KB * KB))
+    } else {
+        format!("{:.0} KB", bytes / KB)
+    }
+}
+
+/// The bytes of `line` shown `width` characters at a time with `column` in
+/// view; the window moves on half its width once the column passes its end
+pub fn window(line: &str, column: usize, width: usize) -> Range<usize> {
+    let width = width.max(1);
+    let step = (width / 2).max(1);
+    let mut column = column.min(line.len());
+    while !line.is_char_boundary(column) {
+        column -= 1;
+    }
+    let before = line[..column].chars().count();
+    let first = if before < width {
+        0
+    } else {
+        (before - width) / step * step + step
+    };
+    let start = line
+        .char_indices()
+        .nth(first)
+        .map_or(line.len(), |(at, _)| at);
+    let end = line[start..]
+        .char_indices()
+        .nth(width)
+        .map_or(line.len(), |(at, _)| start + at);
+    start..end
+}
+
+/// `line` split into pieces of `chars` characters, the last one shorter
+pub fn pieces(line: &str, chars: usize) -> Vec<Range<usize>> {
+    let chars = chars.max(1);
+    let mut pieces = Vec::new();
+    let mut start = 0;
+    for (count, (at, _)) in line.char_indices().enumerate() {
+        if count > 0 && count % chars == 0 {
+            pieces.push(start..at);
+            start = at;
+        }
+    }
+    pieces.push(start..line.len());
+    pieces
+}
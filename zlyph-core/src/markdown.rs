@@ -0,0 +1,474 @@
+//! The Markdown token pass: headings, emphasis, code, links, quotes and list
+//! markers, with the markup characters marked so they can be dimmed
+//!
+//! This is a line-by-line approximation of CommonMark, good enough for
+//! styling notes as they are typed; only fenced code blocks and the
+//! frontmatter span lines. Frontmatter is YAML, so only its fences and
+//! keys are styled, as markup.
+
+use crate::citations;
+use crate::frontmatter;
+use crate::highlight::{LineHighlights, Style};
+use std::ops::Range;
+
+pub fn highlight(lines: &[String]) -> Vec<LineHighlights> {
+    // The open fence's character and length, while inside a code block
+    let mut fence: Option<(u8, usize)> = None;
+    let frontmatter = frontmatter::block_rows(lines).unwrap_or_default();
+    lines
+        .iter()
+        .enumerate()
+        .map(|(row, line)| {
+            let mut pass = Pass {
+                text: line,
+                styles: vec![Style::default(); line.len()],
+                links: Vec::new(),
+            };
+            let all = 0..line.len();
+            if frontmatter.contains(&row) {
+                let key_end = if row == frontmatter.start || row + 1 == frontmatter.end {
+                    line.len()
+                } else {
+                    frontmatter::key_of(line).map_or(0, |(_, value_start)| value_start)
+                };
+                pass.set(0..key_end, markup(Style::default()));
+                return pass.finish();
+            }
+            match fence {
+                Some((ch, len)) if is_fence_close(line, ch, len) => {
+                    fence = None;
+                    pass.set(all, markup(Style::default()));
+                }
+                Some(_) => pass.set(
+                    all,
+                    Style {
+                        code: true,
+                        ..Style::default()
+                    },
+                ),
+                None => match fence_open(line) {
+                    Some(open) => {
+                        fence = Some(open);
+                        pass.set(all, markup(Style::default()));
+                    }
+                    None => pass.block(),
+                },
+            }
+            pass.finish()
+        })
+        .collect()
+}
+
+/// Whether line `row` is in a fenced code block, its fences included
+pub fn in_code_block(lines: &[String], row: usize) -> bool {
+    let mut fence: Option<(u8, usize)> = None;
+    for line in lines.iter().take(row) {
+        fence = match fence {
+            Some((ch, len)) if is_fence_close(line, ch, len) => None,
+            Some(open) => Some(open),
+            None => fence_open(line),
+        };
+    }
+    fence.is_some()
+        || lines
+            .get(row)
+            .is_some_and(|line| fence_open(line).is_some())
+}
+
+/// Whether each line of `lines` is in a fenced code block, fences included
+pub fn code_rows(lines: &[String]) -> Vec<bool> {
+    let mut fence: Option<(u8, usize)> = None;
+    lines
+        .iter()
+        .map(|line| match fence {
+            Some((ch, len)) => {
+                if is_fence_close(line, ch, len) {
+                    fence = None;
+                }
+                true
+            }
+            None => {
+                fence = fence_open(line);
+                fence.is_some()
+            }
+        })
+        .collect()
+}
+
+/// The fence character and length opening a code block, e.g. "```rust"
+fn fence_open(line: &str) -> Option<(u8, usize)> {
+    let trimmed = line.trim_start_matches(' ');
+    if line.len() - trimmed.len() > 3 {
+        return None;
+    }
+    let ch = *trimmed.as_bytes().first()?;
+    if ch != b'`' && ch != b'~' {
+        return None;
+    }
+    let len = trimmed.bytes().take_while(|&b| b == ch).count();
+    if len < 3 || (ch == b'`' && trimmed[len..].contains('`')) {
+        return None;
+    }
+    Some((ch, len))
+}
+
+fn is_fence_close(line: &str, ch: u8, len: usize) -> bool {
+    let trimmed = line.trim();
+    trimmed.len() >= len && trimmed.bytes().all(|b| b == ch)
+}
+
+fn markup(style: Style) -> Style {
+    Style {
+        markup: true,
+        ..style
+    }
+}
+
+/// Length of the run of `ch` starting at `start`, stopping at `end`
+fn run_len(bytes: &[u8], start: usize, end: usize, ch: u8) -> usize {
+    bytes[start..end].iter().take_while(|&&b| b == ch).count()
+}
+
+/// Styles being worked out for one line
+struct Pass<'a> {
+    text: &'a str,
+    styles: Vec<Style>,
+    links: Vec<(Range<usize>, String)>,
+}
+
+impl Pass<'_> {
+    fn set(&mut self, range: Range<usize>, style: Style) {
+        for slot in &mut self.styles[range] {
+            *slot = style;
+        }
+    }
+
+    /// Block structure at the start of the line, then its inline content
+    fn block(&mut self) {
+        let bytes = self.text.as_bytes();
+        let indent = bytes.iter().take_while(|&&b| b == b' ').count();
+        let rest = &self.text[indent..];
+
+        if is_rule(rest) {
+            self.set(0..self.text.len(), markup(Style::default()));
+            return;
+        }
+        if indent <= 3 {
+            let hashes = run_len(bytes, indent, bytes.len(), b'#');
+            let after = indent + hashes;
+            if (1..=6).contains(&hashes) && (after == bytes.len() || bytes[after] == b' ') {
+                let style = Style {
+                    heading: Some(hashes as u8),
+                    ..Style::default()
+                };
+                let text_start = (after + 1).min(bytes.len());
+                self.set(0..text_start, markup(style));
+                self.inline(text_start..bytes.len(), style);
+                return;
+            }
+        }
+
+        let mut base = Style::default();
+        let mut pos = indent;
+        // Quote markers, possibly nested: "> > text"
+        while pos < bytes.len() && bytes[pos] == b'>' {
+            base.quote = true;
+            let end = if bytes.get(pos + 1) == Some(&b' ') {
+                pos + 2
+            } else {
+                pos + 1
+            };
+            self.set(pos..end, markup(base));
+            pos = end + run_len(bytes, end, bytes.len(), b' ');
+        }
+        if let Some(end) = list_marker(bytes, pos) {
+            self.set(pos..end, markup(base));
+            pos = end;
+            // Task list box: "- [ ] " or "- [x] "
+            let task = &bytes[pos..];
+            if task.len() >= 3
+                && task[0] == b'['
+                && matches!(task[1], b' ' | b'x' | b'X')
+                && task[2] == b']'
+                && task.get(3).is_none_or(|&b| b == b' ')
+            {
+                let end = (pos + 4).min(bytes.len());
+                self.set(pos..end, markup(base));
+                pos = end;
+            }
+        }
+        self.inline(pos..bytes.len(), base);
+    }
+
+    /// Spans, emphasis, links and escapes within `range`, over `base`
+    fn inline(&mut self, range: Range<usize>, base: Style) {
+        self.set(range.clone(), base);
+        let text = self.text;
+        let bytes = text.as_bytes();
+        let end = range.end;
+        let mut i = range.start;
+        while i < end {
+            match bytes[i] {
+                b'\\' if i + 1 < end && bytes[i + 1].is_ascii_punctuation() => {
+                    self.set(i..i + 1, markup(base));
+                    i += 2;
+                }
+                b'`' => {
+                    let n = run_len(bytes, i, end, b'`');
+                    match find_run(bytes, i + n, end, b'`', n) {
+                        Some(close) => {
+                            let code = Style { code: true, ..base };
+                            self.set(i..i + n, markup(code));
+                            self.set(i + n..close, code);
+                            self.set(close..close + n, markup(code));
+                            i = close + n;
+                        }
+                        None => i += n,
+                    }
+                }
+                b'[' if bytes.get(i + 1) == Some(&b'[') => {
+                    match text[i + 2..end].find("]]").map(|close| i + 2 + close) {
+                        // A wiki link; see crate::notes for following it
+                        Some(close) if close > i + 2 => {
+                            let link = Style { link: true, ..base };
+                            self.set(i..i + 2, markup(link));
+                            self.set(i + 2..close, link);
+                            self.set(close..close + 2, markup(link));
+                            i = close + 2;
+                        }
+                        _ => i += 2,
+                    }
+                }
+                b'!' | b'[' => {
+                    let open = if bytes[i] == b'!' { i + 1 } else { i };
+                    match parse_link(bytes, open, end) {
+                        Some((text_end, link_end, target)) => {
+                            let link = Style { link: true, ..base };
+                            self.set(i..open + 1, markup(link));
+                            self.inline(open + 1..text_end, link);
+                            self.set(text_end..link_end, markup(link));
+                            self.links.push((i..link_end, target));
+                            i = link_end;
+                        }
+                        None => i += 1,
+                    }
+                }
+                b'<' => match autolink(text, i, end) {
+                    Some(close) => {
+                        let link = Style { link: true, ..base };
+                        self.set(i..i + 1, markup(link));
+                        self.set(i + 1..close, link);
+                        self.set(close..close + 1, markup(link));
+                        self.links
+                            .push((i..close + 1, text[i + 1..close].to_string()));
+                        i = close + 1;
+                    }
+                    None => i += 1,
+                },
+                b'h' if (i == 0 || !bytes[i - 1].is_ascii_alphanumeric())
+                    && (text[i..end].starts_with("https://")
+                        || text[i..end].starts_with("http://")) =>
+                {
+                    let url_end = bare_url_end(text, i, end);
+                    self.set(i..url_end, Style { link: true, ..base });
+                    self.links.push((i..url_end, text[i..url_end].to_string()));
+                    i = url_end;
+                }
+                // A citation; see crate::citations
+                b'@' if citations::starts_citation(text, i)
+                    && citations::key_len(&text[i + 1..end]) > 0 =>
+                {
+                    let key_end = i + 1 + citations::key_len(&text[i + 1..end]);
+                    let link = Style { link: true, ..base };
+                    self.set(i..i + 1, markup(link));
+                    self.set(i + 1..key_end, link);
+                    i = key_end;
+                }
+                ch @ (b'*' | b'_' | b'~') => {
+                    let n = run_len(bytes, i, end, ch);
+                    match self.emphasis(ch, n, i, end, base) {
+                        Some(next) => i = next,
+                        None => i += n,
+                    }
+                }
+                _ => i += 1,
+            }
+        }
+    }
+
+    /// Style the emphasis opened by the run of `n` `ch` at `start`, if it is
+    /// closed on the line; returns where the text after it starts
+    fn emphasis(
+        &mut self,
+        ch: u8,
+        n: usize,
+        start: usize,
+        end: usize,
+        base: Style,
+    ) -> Option<usize> {
+        let bytes = self.text.as_bytes();
+        if ch == b'~' && n != 2 || n > 3 {
+            return None;
+        }
+        let opens = start + n < end
+            && !bytes[start + n].is_ascii_whitespace()
+            && (ch != b'_' || start == 0 || !bytes[start - 1].is_ascii_alphanumeric());
+        if !opens {
+            return None;
+        }
+        let mut close = start + n;
+        loop {
+            close = find_run(bytes, close, end, ch, n)?;
+            let after = close + n;
+            let closes = !bytes[close - 1].is_ascii_whitespace()
+                && (ch != b'_' || after == end || !bytes[after].is_ascii_alphanumeric());
+            if closes && close > start + n {
+                break;
+            }
+            close += 1;
+        }
+
+        let mut style = base;
+        match (ch, n) {
+            (b'~', _) => style.strikethrough = true,
+            (_, 1) => style.italic = true,
+            (_, 2) => style.bold = true,
+            _ => {
+                style.bold = true;
+                style.italic = true;
+            }
+        }
+        self.set(start..start + n, markup(style));
+        self.inline(start + n..close, style);
+        self.set(close..close + n, markup(style));
+        Some(close + n)
+    }
+
+    fn finish(self) -> LineHighlights {
+        let mut spans: Vec<(Range<usize>, Style)> = Vec::new();
+        for (index, style) in self.styles.into_iter().enumerate() {
+            match spans.last_mut() {
+                Some((range, last)) if *last == style && range.end == index => range.end += 1,
+                _ => spans.push((index..index + 1, style)),
+            }
+        }
+        spans.retain(|(_, style)| *style != Style::default());
+        LineHighlights {
+            spans,
+            links: self.links,
+        }
+    }
+}
+
+/// "---", "***" or "___", possibly spaced out
+fn is_rule(text: &str) -> bool {
+    let text = text.trim_end();
+    let Some(ch) = text
+        .chars()
+        .next()
+        .filter(|ch| matches!(ch, '-' | '*' | '_'))
+    else {
+        return false;
+    };
+    text.chars().all(|c| c == ch || c == ' ') && text.chars().filter(|&c| c == ch).count() >= 3
+}
+
+/// End of a list marker and the space after it: "- ", "* ", "+ ", "1. " or
+/// "1) "
+fn list_marker(bytes: &[u8], start: usize) -> Option<usize> {
+    let digits = bytes[start..]
+        .iter()
+        .take_while(|b| b.is_ascii_digit())
+        .count();
+    let marker_end = match bytes.get(start + digits)? {
+        b'-' | b'*' | b'+' if digits == 0 => start + 1,
+        b'.' | b')' if (1..=9).contains(&digits) => start + digits + 1,
+        _ => return None,
+    };
+    match bytes.get(marker_end) {
+        Some(b' ') => Some(marker_end + 1),
+        None => Some(marker_end),
+        _ => None,
+    }
+}
+
+/// Start of the next run of exactly `n` `ch` in `start..end`
+fn find_run(bytes: &[u8], start: usize, end: usize, ch: u8, n: usize) -> Option<usize> {
+    let mut i = start;
+    while i < end {
+        if bytes[i] == ch {
+            let len = run_len(bytes, i, end, ch);
+            if len == n {
+                return Some(i);
+            }
+            i += len;
+        } else {
+            i += 1;
+        }
+    }
+    None
+}
+
+/// `[text](target)` with `open` at the bracket: the closing bracket, the
+/// end of the link and its target
+fn parse_link(bytes: &[u8], open: usize, end: usize) -> Option<(usize, usize, String)> {
+    if bytes.get(open) != Some(&b'[') {
+        return None;
+    }
+    let text_end = matching(bytes, open, end, b'[', b']')?;
+    if bytes.get(text_end + 1) != Some(&b'(') {
+        return None;
+    }
+    let target_end = matching(bytes, text_end + 1, end, b'(', b')')?;
+    let inner = std::str::from_utf8(&bytes[text_end + 2..target_end]).ok()?;
+    // Leave out a title: [text](url "title")
+    let target = inner.split_whitespace().next().unwrap_or_default();
+    let target = target
+        .strip_prefix('<')
+        .and_then(|t| t.strip_suffix('>'))
+        .unwrap_or(target);
+    Some((text_end, target_end + 1, target.to_string()))
+}
+
+/// The bracket closing the one at `open`, skipping nested pairs and escapes
+fn matching(bytes: &[u8], open: usize, end: usize, left: u8, right: u8) -> Option<usize> {
+    let mut depth = 0;
+    let mut i = open;
+    while i < end {
+        match bytes[i] {
+            b'\\' => i += 1,
+            b if b == left => depth += 1,
+            b if b == right => {
+                depth -= 1;
+                if depth == 0 {
+                    return Some(i);
+                }
+            }
+            _ => {}
+        }
+        i += 1;
+    }
+    None
+}
+
+/// The closing `>` of `<https://...>` or `<mailto:...>` starting at `start`
+fn autolink(text: &str, start: usize, end: usize) -> Option<usize> {
+    let rest = &text[start + 1..end];
+    let close = start + 1 + rest.find('>')?;
+    let inner = &text[start + 1..close];
+    let scheme = ["https://", "http://", "mailto:"]
+        .iter()
+        .any(|scheme| inner.starts_with(scheme));
+    (scheme && !inner.contains(char::is_whitespace)).then_some(close)
+}
+
+/// Where a URL written out in the text ends, leaving off punctuation that
+/// more likely ends the sentence
+fn bare_url_end(text: &str, start: usize, end: usize) -> usize {
+    let rest = &text[start..end];
+    let len = rest
+        .find(|c: char| c.is_whitespace() || c == '<')
+        .unwrap_or(rest.len());
+    let url = rest[..len].trim_end_matches(['.', ',', ';', ':', '!', '?', ')', '\'', '"']);
+    start + url.len()
+}
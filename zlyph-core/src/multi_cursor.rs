@@ -0,0 +1,83 @@
+//! Cursors besides the main one, as made by selecting every match of a
+//! search
+//!
+//! Typing, deleting and moving along a line happen at every cursor at once,
+//! as one undo step; anything else leaves only the main cursor.
+
+use crate::{BufferPosition, BufferRange, EditorAction};
+use std::ops::Range;
+
+/// A cursor with the selection it extends
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Caret {
+    pub cursor: BufferPosition,
+    pub anchor: Option<BufferPosition>,
+}
+
+impl Caret {
+    /// A caret selecting from `start` to `end`, with the cursor at `end`
+    pub fn selecting(start: BufferPosition, end: BufferPosition) -> Self {
+        Self {
+            cursor: end,
+            anchor: Some(start),
+        }
+    }
+
+    pub fn selection(&self) -> Option<BufferRange> {
+        self.anchor
+            .map(|anchor| BufferRange::new(anchor, self.cursor))
+    }
+
+    /// Where the caret's selection starts, or the cursor without one
+    pub fn start(&self) -> BufferPosition {
+        self.selection().map_or(self.cursor, |range| range.start)
+    }
+}
+
+/// Whether `action` is carried out at every cursor rather than ending the
+/// others
+pub fn applies_to_all(action: &EditorAction) -> bool {
+    match action {
+        EditorAction::TypeString(s) => !s.contains('\n'),
+        EditorAction::TypeCharacter(c) => *c != '\n',
+        EditorAction::Backspace
+        | EditorAction::Delete
+        | EditorAction::MoveLeft
+        | EditorAction::MoveRight
+        | EditorAction::MoveToBeginningOfLine
+        | EditorAction::MoveToEndOfLine
+        | EditorAction::IncrementNumber(_)
+        | EditorAction::DecrementNumber(_)
+        | EditorAction::NumberSequence(_) => true,
+        _ => false,
+    }
+}
+
+/// The byte ranges `carets` select on line `row`, `len` bytes long, and
+/// the columns of their cursors there
+pub fn on_line(carets: &[Caret], row: usize, len: usize) -> (Vec<Range<usize>>, Vec<usize>) {
+    let selected = carets
+        .iter()
+        .filter_map(Caret::selection)
+        .filter(|range| (range.start.row..=range.end.row).contains(&row))
+        .map(|range| {
+            let from = if range.start.row == row {
+                range.start.column
+            } else {
+                0
+            };
+            let to = if range.end.row == row {
+                range.end.column
+            } else {
+                len
+            };
+            from..to
+        })
+        .collect();
+    let cursors = carets
+        .iter()
+        .filter(|caret| caret.cursor.row == row)
+        .map(|caret| caret.cursor.column)
+        .collect();
+    (selected, cursors)
+}
@@ -0,0 +1,516 @@
+//! Full-text search over the notes directory, from an inverted index kept
+//! in `~/.config/zlyph/index` so that opening it does not re-read every note
+//!
+//! The index holds each note's words, and its size and modification time.
+//! Loading it re-reads only the notes changed since, and edits update it as
+//! they are saved. Results are ranked with BM25, need every word of the
+//! query, and take the last word as a prefix while it is being typed.
+//! Encrypted notes are left out.
+
+use crate::config;
+use crate::encryption;
+use crate::history;
+use crate::notes;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Receiver, TryRecvError};
+use std::thread;
+use std::time::{Duration, Instant, UNIX_EPOCH};
+
+/// Changes to the stored format make older indexes be rebuilt
+const FORMAT_VERSION: u32 = 1;
+/// How often edits are written to the stored index
+const SAVE_INTERVAL: Duration = Duration::from_secs(10);
+/// How many results a search returns
+const MAX_HITS: usize = 50;
+/// How much of the matching line is shown
+const SNIPPET_CHARS: usize = 120;
+/// BM25 parameters
+const K1: f64 = 1.2;
+const B: f64 = 0.75;
+
+/// The words of `text`, lowercased, in order
+pub fn words(text: &str) -> impl Iterator<Item = String> + '_ {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(str::to_lowercase)
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Document {
+    path: PathBuf,
+    /// Modification time in milliseconds since the Unix epoch, and size,
+    /// telling whether the note changed since it was indexed
+    stamp: (u64, u64),
+    length: u32,
+    terms: BTreeMap<String, u32>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct Stored {
+    version: u32,
+    documents: Vec<Document>,
+}
+
+fn stamp(path: &Path) -> (u64, u64) {
+    let Ok(metadata) = fs::metadata(path) else {
+        return (0, 0);
+    };
+    let millis = metadata
+        .modified()
+        .ok()
+        .and_then(|modified| modified.duration_since(UNIX_EPOCH).ok())
+        .map_or(0, |since| since.as_millis() as u64);
+    (millis, metadata.len())
+}
+
+/// A note matching a search, with the first line showing why
+#[derive(Debug, Clone, PartialEq)]
+pub struct NoteHit {
+    pub path: PathBuf,
+    pub score: f64,
+    pub row: usize,
+    pub snippet: String,
+}
+
+#[derive(Debug)]
+pub struct NoteIndex {
+    dir: PathBuf,
+    /// Where the index is stored
+    file: PathBuf,
+    /// Removed notes leave an empty slot so the postings' ids stay valid,
+    /// reused by the next note indexed
+    documents: Vec<Option<Document>>,
+    free: Vec<usize>,
+    ids: HashMap<PathBuf, usize>,
+    /// For each word, the notes using it and how often
+    postings: BTreeMap<String, Vec<(usize, u32)>>,
+    total_length: u64,
+    dirty: bool,
+    saved_at: Instant,
+}
+
+impl NoteIndex {
+    pub fn default_dir() -> PathBuf {
+        config::config_dir().join("index")
+    }
+
+    /// The stored index of the notes below `dir`, or an empty one
+    pub fn load(dir: &Path) -> Self {
+        let file = Self::default_dir().join(format!("{}.json", history::path_key(dir)));
+        Self::load_from(dir, file)
+    }
+
+    pub fn load_from(dir: &Path, file: PathBuf) -> Self {
+        let stored: Stored = fs::read_to_string(&file)
+            .ok()
+            .and_then(|content| serde_json::from_str(&content).ok())
+            .filter(|stored: &Stored| stored.version == FORMAT_VERSION)
+            .unwrap_or_default();
+        let mut index = Self {
+            dir: dir.to_path_buf(),
+            file,
+            documents: Vec::new(),
+            free: Vec::new(),
+            ids: HashMap::new(),
+            postings: BTreeMap::new(),
+            total_length: 0,
+            dirty: false,
+            saved_at: Instant::now(),
+        };
+        for document in stored.documents {
+            index.insert(document);
+        }
+        index
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// How many notes are indexed
+    pub fn len(&self) -> usize {
+        self.ids.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.ids.is_empty()
+    }
+
+    /// Index notes added or changed since the index was stored, and forget
+    /// removed ones; returns whether anything changed
+    pub fn refresh(&mut self) -> bool {
+        let files = notes::note_files(&self.dir);
+        let present: HashSet<&PathBuf> = files.iter().collect();
+        let removed: Vec<PathBuf> = self
+            .ids
+            .keys()
+            .filter(|path| !present.contains(path))
+            .cloned()
+            .collect();
+        let mut changed = !removed.is_empty();
+        for path in removed {
+            self.remove(&path);
+        }
+        for path in files {
+            let stamp = stamp(&path);
+            let current = self
+                .ids
+                .get(&path)
+                .and_then(|&id| self.documents[id].as_ref());
+            if current.is_some_and(|document| document.stamp == stamp) {
+                continue;
+            }
+            match fs::read_to_string(&path) {
+                Ok(text) => self.index_text(path, &text, stamp),
+                Err(_) => self.remove(&path),
+            }
+            changed = true;
+        }
+        changed
+    }
+
+    /// Index a note's text as just saved
+    pub fn update(&mut self, path: &Path, text: &str) {
+        self.index_text(path.to_path_buf(), text, stamp(path));
+    }
+
+    fn index_text(&mut self, path: PathBuf, text: &str, stamp: (u64, u64)) {
+        self.remove(&path);
+        if encryption::is_encrypted(text) {
+            return;
+        }
+        let mut terms = BTreeMap::new();
+        let mut length = 0;
+        for word in words(text) {
+            *terms.entry(word).or_insert(0) += 1;
+            length += 1;
+        }
+        self.insert(Document {
+            path,
+            stamp,
+            length,
+            terms,
+        });
+        self.dirty = true;
+    }
+
+    fn insert(&mut self, document: Document) {
+        let id = self.free.pop().unwrap_or(self.documents.len());
+        if id == self.documents.len() {
+            self.documents.push(None);
+        }
+        for (term, &count) in &document.terms {
+            self.postings
+                .entry(term.clone())
+                .or_default()
+                .push((id, count));
+        }
+        self.total_length += u64::from(document.length);
+        self.ids.insert(document.path.clone(), id);
+        self.documents[id] = Some(document);
+    }
+
+    pub fn remove(&mut self, path: &Path) {
+        let Some(id) = self.ids.remove(path) else {
+            return;
+        };
+        let Some(document) = self.documents[id].take() else {
+            return;
+        };
+        for term in document.terms.keys() {
+            if let Some(postings) = self.postings.get_mut(term) {
+                postings.retain(|&(posted, _)| posted != id);
+                if postings.is_empty() {
+                    self.postings.remove(term);
+                }
+            }
+        }
+        self.total_length -= u64::from(document.length);
+        self.free.push(id);
+        self.dirty = true;
+    }
+
+    /// Write the index if it changed since it was loaded or last saved
+    pub fn save(&mut self) -> io::Result<()> {
+        if !self.dirty {
+            return Ok(());
+        }
+        let stored = Stored {
+            version: FORMAT_VERSION,
+            documents: self.documents.iter().flatten().cloned().collect(),
+        };
+        let content = serde_json::to_string(&stored).map_err(io::Error::other)?;
+        if let Some(parent) = self.file.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(&self.file, content)?;
+        self.dirty = false;
+        self.saved_at = Instant::now();
+        Ok(())
+    }
+
+    /// Save unless the index was saved recently
+    fn save_if_stale(&mut self) {
+        if self.saved_at.elapsed() >= SAVE_INTERVAL {
+            let _ = self.save();
+        }
+    }
+
+    /// Indexed words matching a query word: the word itself, or while it is
+    /// being typed every word it starts
+    fn matching_terms(&self, word: &str, prefix: bool) -> Vec<&str> {
+        if !prefix {
+            return self
+                .postings
+                .get_key_value(word)
+                .map(|(term, _)| term.as_str())
+                .into_iter()
+                .collect();
+        }
+        self.postings
+            .range(word.to_string()..)
+            .map(|(term, _)| term.as_str())
+            .take_while(|term| term.starts_with(word))
+            .collect()
+    }
+
+    /// Notes with every word of `query`, best first
+    pub fn search(&self, query: &str) -> Vec<NoteHit> {
+        let query_words: Vec<String> = words(query).collect();
+        let typing = !query.ends_with(char::is_whitespace);
+        let count = self.len() as f64;
+        let average_length = self.total_length as f64 / count.max(1.0);
+
+        let mut scores: Option<HashMap<usize, f64>> = None;
+        let mut matched: HashSet<&str> = HashSet::new();
+        for (i, word) in query_words.iter().enumerate() {
+            let mut word_scores: HashMap<usize, f64> = HashMap::new();
+            for term in self.matching_terms(word, typing && i + 1 == query_words.len()) {
+                matched.insert(term);
+                let postings = &self.postings[term];
+                let frequency = postings.len() as f64;
+                let idf = ((count - frequency + 0.5) / (frequency + 0.5) + 1.0).ln();
+                for &(id, term_count) in postings {
+                    let Some(document) = self.documents[id].as_ref() else {
+                        continue;
+                    };
+                    let tf = f64::from(term_count);
+                    let norm = 1.0 - B + B * f64::from(document.length) / average_length;
+                    *word_scores.entry(id).or_insert(0.0) +=
+                        idf * tf * (K1 + 1.0) / (tf + K1 * norm);
+                }
+            }
+            scores = Some(match scores {
+                None => word_scores,
+                Some(scores) => scores
+                    .into_iter()
+                    .filter_map(|(id, score)| word_scores.get(&id).map(|more| (id, score + more)))
+                    .collect(),
+            });
+        }
+
+        let mut ranked: Vec<(usize, f64)> = scores.unwrap_or_default().into_iter().collect();
+        ranked.sort_by(|(a_id, a), (b_id, b)| {
+            b.total_cmp(a).then_with(|| {
+                let path = |id: &usize| self.documents[*id].as_ref().map(|d| &d.path);
+                path(a_id).cmp(&path(b_id))
+            })
+        });
+        ranked
+            .into_iter()
+            .take(MAX_HITS)
+            .filter_map(|(id, score)| {
+                let path = self.documents[id].as_ref()?.path.clone();
+                let (row, snippet) = snippet(&path, &matched);
+                Some(NoteHit {
+                    path,
+                    score,
+                    row,
+                    snippet,
+                })
+            })
+            .collect()
+    }
+}
+
+/// The first line of the note at `path` with one of the `matched` words,
+/// trimmed to fit a results list
+fn snippet(path: &Path, matched: &HashSet<&str>) -> (usize, String) {
+    let Ok(text) = fs::read_to_string(path) else {
+        return (0, String::new());
+    };
+    let (row, line) = text
+        .lines()
+        .enumerate()
+        .find(|(_, line)| words(line).any(|word| matched.contains(word.as_str())))
+        .unwrap_or((0, text.lines().next().unwrap_or_default()));
+    let line = line.trim();
+    let mut snippet: String = line.chars().take(SNIPPET_CHARS).collect();
+    if snippet.len() < line.len() {
+        snippet.push('…');
+    }
+    (row, snippet)
+}
+
+/// The note search panel both frontends show: the index, loaded and brought
+/// up to date on a background thread, the query and its results
+pub struct NoteSearch {
+    pub query: String,
+    /// Index into [`NoteSearch::hits`]
+    pub selected: usize,
+    dir: PathBuf,
+    index: Option<NoteIndex>,
+    loading: Option<Receiver<NoteIndex>>,
+    /// Notes saved while the index was loading, indexed once it arrives
+    pending: Vec<(PathBuf, String)>,
+    hits: Vec<NoteHit>,
+}
+
+impl NoteSearch {
+    /// Load the index of the notes below `dir`
+    pub fn start(dir: &Path) -> Self {
+        Self::spawn(dir, NoteIndex::load)
+    }
+
+    /// Load the index of the notes below `dir` from `file`
+    pub fn start_from(dir: &Path, file: PathBuf) -> Self {
+        Self::spawn(dir, move |dir| NoteIndex::load_from(dir, file))
+    }
+
+    fn spawn(dir: &Path, load: impl FnOnce(&Path) -> NoteIndex + Send + 'static) -> Self {
+        let (sender, receiver) = mpsc::channel();
+        let load_dir = dir.to_path_buf();
+        thread::spawn(move || {
+            let mut index = load(&load_dir);
+            index.refresh();
+            let _ = index.save();
+            let _ = sender.send(index);
+        });
+        Self {
+            query: String::new(),
+            selected: 0,
+            dir: dir.to_path_buf(),
+            index: None,
+            loading: Some(receiver),
+            pending: Vec::new(),
+            hits: Vec::new(),
+        }
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Take the index once it is loaded; returns whether it arrived
+    pub fn poll(&mut self) -> bool {
+        let Some(receiver) = self.loading.as_ref() else {
+            return false;
+        };
+        match receiver.try_recv() {
+            Ok(index) => self.loaded(index),
+            Err(TryRecvError::Empty) => return false,
+            Err(TryRecvError::Disconnected) => self.loading = None,
+        }
+        true
+    }
+
+    /// Block until the index is loaded
+    pub fn wait(&mut self) {
+        if let Some(index) = self.loading.as_ref().and_then(|r| r.recv().ok()) {
+            self.loaded(index);
+        }
+        self.loading = None;
+    }
+
+    fn loaded(&mut self, mut index: NoteIndex) {
+        for (path, text) in self.pending.drain(..) {
+            index.update(&path, &text);
+        }
+        self.index = Some(index);
+        self.loading = None;
+        self.run();
+    }
+
+    pub fn is_ready(&self) -> bool {
+        self.index.is_some()
+    }
+
+    pub fn type_text(&mut self, text: &str) {
+        self.query.push_str(text);
+        self.run();
+    }
+
+    pub fn backspace(&mut self) {
+        self.query.pop();
+        self.run();
+    }
+
+    fn run(&mut self) {
+        self.hits = match &self.index {
+            Some(index) => index.search(&self.query),
+            None => Vec::new(),
+        };
+        self.selected = 0;
+    }
+
+    pub fn hits(&self) -> &[NoteHit] {
+        &self.hits
+    }
+
+    pub fn select_previous(&mut self) {
+        self.selected = self.selected.saturating_sub(1);
+    }
+
+    pub fn select_next(&mut self) {
+        self.selected = (self.selected + 1).min(self.hits.len().saturating_sub(1));
+    }
+
+    pub fn selected_hit(&self) -> Option<&NoteHit> {
+        self.hits.get(self.selected)
+    }
+
+    /// Index the text just saved to `path`, if it is a note below the
+    /// directory
+    pub fn update(&mut self, path: &Path, text: &str) {
+        if !path.starts_with(&self.dir) || !notes::is_note(path) {
+            return;
+        }
+        match self.index.as_mut() {
+            Some(index) => {
+                index.update(path, text);
+                index.save_if_stale();
+            }
+            None => self.pending.push((path.to_path_buf(), text.to_string())),
+        }
+    }
+
+    /// Write edits not yet saved to the stored index
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.index.as_mut().map_or(Ok(()), NoteIndex::save)
+    }
+
+    /// Summary for the panel's title, e.g. "12 notes"
+    pub fn status(&self) -> String {
+        match &self.index {
+            None => "Indexing notes…".to_string(),
+            Some(index) if self.query.trim().is_empty() => {
+                format!("{} notes indexed", index.len())
+            }
+            Some(_) => match self.hits.len() {
+                1 => "1 note".to_string(),
+                count if count >= MAX_HITS => format!("Top {} notes", count),
+                count => format!("{} notes", count),
+            },
+        }
+    }
+}
+
+impl Drop for NoteSearch {
+    fn drop(&mut self) {
+        let _ = self.flush();
+    }
+}
@@ -0,0 +1,216 @@
+//! Notes linked with `[[wiki links]]`: finding the link under the cursor,
+//! the note it names, and the notes linking back to the current one
+//!
+//! A link names a note by file name without its extension, matched without
+//! regard to case anywhere below the notes directory: `[[Project Plan]]`
+//! finds `plans/project plan.md`. `[[name|shown text]]` and
+//! `[[name#heading]]` link to `name`. A link to a note that does not exist
+//! yet creates `name.md` at the top of the notes directory.
+
+use crate::config::{self, NotesConfig};
+use crate::project_search;
+use std::fs;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+
+/// Extensions of files treated as notes
+const NOTE_EXTENSIONS: &[&str] = &["md", "markdown", "mdown", "mkd", "txt"];
+
+/// A `[[wiki link]]` in a line
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WikiLink {
+    /// Where the link is written, brackets included
+    pub range: Range<usize>,
+    /// The note's name, without any heading or shown text
+    pub name: String,
+}
+
+/// Every wiki link in `line`, in order
+pub fn links(line: &str) -> Vec<WikiLink> {
+    let mut links = Vec::new();
+    let mut from = 0;
+    while let Some(open) = line[from..].find("[[").map(|i| from + i) {
+        let Some(close) = line[open + 2..].find("]]").map(|i| open + 2 + i) else {
+            break;
+        };
+        let inner = &line[open + 2..close];
+        let name = inner.split(['|', '#']).next().unwrap_or_default().trim();
+        // "[[" inside a link starts over from the inner one
+        if let Some(nested) = inner.rfind("[[") {
+            from = open + 2 + nested;
+            continue;
+        }
+        if !name.is_empty() {
+            links.push(WikiLink {
+                range: open..close + 2,
+                name: name.to_string(),
+            });
+        }
+        from = close + 2;
+    }
+    links
+}
+
+/// The wiki link written at `column`, brackets included
+pub fn link_at(line: &str, column: usize) -> Option<WikiLink> {
+    links(line)
+        .into_iter()
+        .find(|link| link.range.contains(&column))
+}
+
+/// Start of the note name being typed when `column` follows an unclosed
+/// `[[` on the line
+pub fn open_link_start(line: &str, column: usize) -> Option<usize> {
+    let before = line.get(..column)?;
+    let open = before.rfind("[[")?;
+    let typed = &before[open + 2..];
+    (!typed.contains("]]") && !typed.contains(['|', '#'])).then_some(open + 2)
+}
+
+/// The directory notes are looked up in and created in: the configured
+/// one, otherwise the folder of the file being edited
+pub fn notes_dir(config: &NotesConfig, current: &Path) -> PathBuf {
+    match &config.directory {
+        Some(directory) => directory.clone(),
+        None => current
+            .parent()
+            .filter(|dir| !dir.as_os_str().is_empty())
+            .unwrap_or(Path::new("."))
+            .to_path_buf(),
+    }
+}
+
+pub fn is_note(path: &Path) -> bool {
+    path.extension()
+        .and_then(|extension| extension.to_str())
+        .is_some_and(|extension| {
+            NOTE_EXTENSIONS
+                .iter()
+                .any(|known| known.eq_ignore_ascii_case(extension))
+        })
+}
+
+/// Every note below `dir`, skipping hidden and gitignored entries, and the
+/// snapshots and caches zlyph keeps next to the default notes file
+pub fn note_files(dir: &Path) -> Vec<PathBuf> {
+    let data = ["history", "remote"].map(|name| config::config_dir().join(name));
+    project_search::files(dir)
+        .into_iter()
+        .filter(|path| is_note(path) && !data.iter().any(|data| path.starts_with(data)))
+        .collect()
+}
+
+/// The name a link uses for the note at `path`
+pub fn note_name(path: &Path) -> String {
+    path.file_stem()
+        .map(|stem| stem.to_string_lossy().into_owned())
+        .unwrap_or_default()
+}
+
+/// Names of the notes below `dir`, for completing links
+pub fn note_names(dir: &Path) -> Vec<String> {
+    let mut names: Vec<String> = note_files(dir).iter().map(|path| note_name(path)).collect();
+    names.sort_by_key(|name| name.to_lowercase());
+    names.dedup();
+    names
+}
+
+/// Whether a link naming `name` leads to a note called `note`. A name may
+/// carry a folder or extension, as in `[[plans/Project Plan.md]]`.
+fn names_note(name: &str, note: &str) -> bool {
+    let name = name.rsplit('/').next().unwrap_or(name);
+    let name = Path::new(name)
+        .extension()
+        .filter(|_| is_note(Path::new(name)))
+        .map_or(name, |extension| &name[..name.len() - extension.len() - 1]);
+    name.to_lowercase() == note.to_lowercase()
+}
+
+/// The file a link naming `name` opens: an existing note of that name below
+/// `dir`, otherwise a new Markdown file in `dir`
+pub fn note_path(dir: &Path, name: &str) -> PathBuf {
+    if let Some(existing) = note_files(dir)
+        .into_iter()
+        .find(|path| names_note(name, &note_name(path)))
+    {
+        return existing;
+    }
+    let path = dir.join(name);
+    if is_note(&path) {
+        path
+    } else {
+        dir.join(format!("{}.md", name))
+    }
+}
+
+/// A line in another note that links to the current one
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Backlink {
+    pub path: PathBuf,
+    pub row: usize,
+    /// The linking line, trimmed
+    pub text: String,
+}
+
+/// Every line below `dir` linking to the note at `note`, other than in the
+/// note itself
+pub fn backlinks(dir: &Path, note: &Path) -> Vec<Backlink> {
+    let name = note_name(note);
+    let note = note.canonicalize().unwrap_or_else(|_| note.to_path_buf());
+    let mut found = Vec::new();
+    for path in note_files(dir) {
+        if path.canonicalize().is_ok_and(|path| path == note) {
+            continue;
+        }
+        let Ok(content) = fs::read_to_string(&path) else {
+            continue;
+        };
+        for (row, line) in content.lines().enumerate() {
+            if links(line).iter().any(|link| names_note(&link.name, &name)) {
+                found.push(Backlink {
+                    path: path.clone(),
+                    row,
+                    text: line.trim().to_string(),
+                });
+            }
+        }
+    }
+    found
+}
+
+/// The backlinks list both frontends show for the current note
+#[derive(Debug, Clone, Default)]
+pub struct BacklinksPanel {
+    /// Index into [`BacklinksPanel::entries`]
+    pub selected: usize,
+    /// Name of the note the entries link to
+    pub note: String,
+    entries: Vec<Backlink>,
+}
+
+impl BacklinksPanel {
+    /// Find the notes below `dir` linking to `note`
+    pub fn load(dir: &Path, note: &Path) -> Self {
+        Self {
+            selected: 0,
+            note: note_name(note),
+            entries: backlinks(dir, note),
+        }
+    }
+
+    pub fn entries(&self) -> &[Backlink] {
+        &self.entries
+    }
+
+    pub fn select_previous(&mut self) {
+        self.selected = self.selected.saturating_sub(1);
+    }
+
+    pub fn select_next(&mut self) {
+        self.selected = (self.selected + 1).min(self.entries.len().saturating_sub(1));
+    }
+
+    pub fn selected_entry(&self) -> Option<&Backlink> {
+        self.entries.get(self.selected)
+    }
+}
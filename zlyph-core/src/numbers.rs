@@ -0,0 +1,118 @@
+//! Numbers in a line, found and changed as `Ctrl+A` and `Ctrl+X` do in Vim
+//!
+//! A number is decimal with an optional minus sign, hexadecimal after `0x`
+//! or binary after `0b`. Changing one keeps how it is written: hexadecimal
+//! keeps the case of its letters, and any number written with leading
+//! zeros keeps its width. Hexadecimal and binary numbers are unsigned and
+//! wrap around within 64 bits.
+
+use std::ops::Range;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Number {
+    /// Bytes of the line the number is written in, sign and prefix included
+    pub range: Range<usize>,
+    pub value: i128,
+    radix: u32,
+    /// Digits to pad to, or 0 to write the number as short as it goes
+    width: usize,
+    upper: bool,
+}
+
+impl Number {
+    /// The number `delta` more, written as this one is; `line` is the line
+    /// it was found in
+    pub fn adjusted(&self, line: &str, delta: i64) -> String {
+        let prefix = &line[self.range.start..self.range.start + self.prefix_len()];
+        let width = self.width;
+        match self.radix {
+            16 => {
+                let value = (self.value as u64).wrapping_add(delta as u64);
+                if self.upper {
+                    format!("{}{:0width$X}", prefix, value)
+                } else {
+                    format!("{}{:0width$x}", prefix, value)
+                }
+            }
+            2 => {
+                let value = (self.value as u64).wrapping_add(delta as u64);
+                format!("{}{:0width$b}", prefix, value)
+            }
+            _ => {
+                let value = self.value.saturating_add(delta as i128);
+                let sign = if value < 0 { "-" } else { "" };
+                format!("{}{:0width$}", sign, value.unsigned_abs())
+            }
+        }
+    }
+
+    fn prefix_len(&self) -> usize {
+        match self.radix {
+            10 => 0,
+            _ => 2,
+        }
+    }
+}
+
+/// The number the cursor at `column` is on, or else the first one after it
+pub fn at(line: &str, column: usize) -> Option<Number> {
+    numbers(line).find(|number| number.range.end > column)
+}
+
+/// The first number starting inside `range`
+pub fn within(line: &str, range: Range<usize>) -> Option<Number> {
+    numbers(line).find(|number| range.contains(&number.range.start))
+}
+
+/// Every number in `line`, left to right
+fn numbers(line: &str) -> impl Iterator<Item = Number> + '_ {
+    let bytes = line.as_bytes();
+    let mut at = 0;
+    std::iter::from_fn(move || {
+        while at < bytes.len() {
+            let start = at;
+            let after_word = start > 0 && bytes[start - 1].is_ascii_alphanumeric();
+            let prefixed = |letter: u8, digit: fn(&u8) -> bool| {
+                !after_word
+                    && bytes[start] == b'0'
+                    && bytes.get(start + 1).map(u8::to_ascii_lowercase) == Some(letter)
+                    && bytes.get(start + 2).is_some_and(digit)
+            };
+            let radix = if prefixed(b'x', u8::is_ascii_hexdigit) {
+                16
+            } else if prefixed(b'b', |b| matches!(b, b'0' | b'1')) {
+                2
+            } else if bytes[start].is_ascii_digit() {
+                10
+            } else {
+                at += 1;
+                continue;
+            };
+
+            let digits_start = if radix == 10 { start } else { start + 2 };
+            let digits_end = bytes[digits_start..]
+                .iter()
+                .position(|b| !(*b as char).is_digit(radix))
+                .map_or(bytes.len(), |len| digits_start + len);
+            at = digits_end;
+            let digits = &line[digits_start..digits_end];
+            let Ok(magnitude) = i128::from_str_radix(digits, radix) else {
+                continue;
+            };
+            // A minus sign counts unless it joins two words, as in `a-1`
+            let negative = radix == 10
+                && start > 0
+                && bytes[start - 1] == b'-'
+                && (start < 2 || !bytes[start - 2].is_ascii_alphanumeric());
+            let padded = radix != 10 || digits.len() > 1 && digits.starts_with('0');
+            return Some(Number {
+                range: if negative { start - 1 } else { start }..digits_end,
+                value: if negative { -magnitude } else { magnitude },
+                radix,
+                width: if padded { digits.len() } else { 0 },
+                upper: digits.bytes().any(|b| b.is_ascii_uppercase()),
+            });
+        }
+        None
+    })
+}
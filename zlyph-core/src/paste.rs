@@ -0,0 +1,91 @@
+//! Pasted text fitted to where it lands
+//!
+//! A paste of several lines is re-indented: the block keeps its own nesting
+//! but starts at the indent of the line it goes into. Pasting as plain text
+//! instead drops the block's common indent, invisible characters and
+//! unusual spaces, leaving the text as bare as it can be.
+
+use crate::indent_guides::{self, TAB_WIDTH};
+use crate::invisibles;
+
+/// `line` without up to `width` columns of its indent
+fn dedent(line: &str, width: usize) -> &str {
+    let mut removed = 0;
+    for (at, c) in line.char_indices() {
+        if removed >= width {
+            return &line[at..];
+        }
+        match c {
+            ' ' => removed += 1,
+            '\t' => removed += TAB_WIDTH - removed % TAB_WIDTH,
+            _ => return &line[at..],
+        }
+    }
+    ""
+}
+
+fn is_blank(line: &str) -> bool {
+    line.trim().is_empty()
+}
+
+/// The indent the block of `lines` was written at: the least of its lines'
+/// indents, leaving out the first unless it has one of its own, as text
+/// copied from partway along a line starts without
+fn block_indent(lines: &[&str]) -> usize {
+    let first = lines
+        .first()
+        .filter(|line| !is_blank(line) && line.starts_with([' ', '\t']));
+    first
+        .into_iter()
+        .chain(lines.iter().skip(1).filter(|line| !is_blank(line)))
+        .map(|line| indent_guides::indent(line).unwrap_or(0))
+        .min()
+        .unwrap_or(0)
+}
+
+/// `text` pasted after `before`, the part of its line ahead of the cursor:
+/// a block of several lines is moved to the indent of that line, keeping its
+/// own nesting; a single line is left alone
+pub fn reindent(text: &str, before: &str) -> String {
+    let text = text.replace("\r\n", "\n");
+    let lines: Vec<&str> = text.split('\n').collect();
+    if lines.len() < 2 {
+        return text;
+    }
+    let base = block_indent(&lines);
+    let indent = &before[..before.len() - before.trim_start().len()];
+    // Pasted at the indent, the first line's own indent gives way to it
+    let at_indent = is_blank(before);
+
+    let mut pasted = Vec::with_capacity(lines.len());
+    for (i, line) in lines.iter().enumerate() {
+        pasted.push(match i {
+            0 if at_indent => dedent(line, base).to_string(),
+            0 => line.to_string(),
+            _ if is_blank(line) => String::new(),
+            _ => format!("{}{}", indent, dedent(line, base)),
+        });
+    }
+    pasted.join("\n")
+}
+
+/// `text` as plain as it gets: without the indent all its lines share, and
+/// with invisible characters dropped and unusual spaces made ordinary ones
+pub fn plain(text: &str) -> String {
+    let text = text.replace("\r\n", "\n");
+    let lines: Vec<&str> = text.split('\n').collect();
+    let base = lines
+        .iter()
+        .filter(|line| !is_blank(line))
+        .map(|line| indent_guides::indent(line).unwrap_or(0))
+        .min()
+        .unwrap_or(0);
+    lines
+        .iter()
+        .map(|line| {
+            let line = dedent(line, base);
+            invisibles::strip(line, 0).map_or_else(|| line.to_string(), |(line, _)| line)
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
@@ -0,0 +1,438 @@
+//! The buffer typeset as a PDF for printing: monospaced in the configured
+//! font, wrapped to the page, with the file's name and page numbers in a
+//! header
+//!
+//! The font is embedded when a TrueType face of the configured family or
+//! one of its fallbacks is installed; otherwise the page uses the PDF's own
+//! Courier, which has no glyphs outside Latin-1. Bold text is drawn with
+//! its outline stroked and italic slanted, so one face serves for all.
+
+use crate::config::{Config, FontConfig, Paper, PrintConfig};
+use crate::export::{self, Palette};
+use crate::BufferRange;
+use flate2::write::ZlibEncoder;
+use flate2::Compression;
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+use std::io::{self, Write as _};
+use std::path::{Path, PathBuf};
+
+/// Points per millimetre
+const MM: f32 = 72.0 / 25.4;
+
+/// A face to embed, as the bytes of a TrueType file
+pub struct Font {
+    data: Vec<u8>,
+}
+
+impl Font {
+    /// The face in `data`, if it is one PDF viewers can embed: TrueType
+    /// outlines, alone in its file
+    pub fn from_data(data: Vec<u8>) -> Option<Self> {
+        let face = ttf_parser::Face::parse(&data, 0).ok()?;
+        if face.tables().glyf.is_none() || ttf_parser::fonts_in_collection(&data).is_some() {
+            return None;
+        }
+        Some(Self { data })
+    }
+
+    /// The first of the configured families installed as an embeddable
+    /// face, in the configured weight or the nearest one
+    #[cfg(feature = "system-fonts")]
+    pub fn find(config: &FontConfig) -> Option<Self> {
+        let mut db = fontdb::Database::new();
+        db.load_system_fonts();
+        std::iter::once(&config.family)
+            .chain(&config.fallbacks)
+            .find_map(|family| {
+                let id = db.query(&fontdb::Query {
+                    families: &[fontdb::Family::Name(family)],
+                    weight: fontdb::Weight(config.weight.round() as u16),
+                    ..Default::default()
+                })?;
+                let data = db.with_face_data(id, |data, index| (index == 0).then(|| data.to_vec()));
+                Self::from_data(data.flatten()?)
+            })
+    }
+
+    /// Without the `system-fonts` feature installed fonts aren't looked
+    /// for, so pages use Courier
+    #[cfg(not(feature = "system-fonts"))]
+    pub fn find(_config: &FontConfig) -> Option<Self> {
+        None
+    }
+}
+
+/// What to print besides the text
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PdfOptions {
+    /// Shown in the header, usually the file's name
+    pub title: String,
+    pub highlight: bool,
+}
+
+impl PdfOptions {
+    /// Options for printing the file at `path` as configured
+    pub fn new(path: Option<&Path>, config: &PrintConfig) -> Self {
+        Self {
+            title: path.and_then(Path::file_name).map_or_else(
+                || "Untitled".to_string(),
+                |name| name.to_string_lossy().into_owned(),
+            ),
+            highlight: config.highlight,
+        }
+    }
+}
+
+/// Where a file is exported to unless asked otherwise: beside it, as
+/// `notes.pdf` for `notes.md`
+pub fn pdf_path(path: &Path) -> PathBuf {
+    path.with_extension("pdf")
+}
+
+/// Where a file is written to be printed: a PDF of its name in the
+/// temporary directory
+pub fn print_path(path: Option<&Path>) -> PathBuf {
+    let name = path.map_or_else(|| Path::new("Untitled").to_path_buf(), pdf_path);
+    std::env::temp_dir().join(name.file_name().unwrap_or_default())
+}
+
+/// Print `lines`, or only `range` of them, as configured to a PDF at
+/// `target`, in the configured font if it is installed and in the light
+/// palette
+pub fn write(
+    target: &Path,
+    lines: &[String],
+    path: Option<&Path>,
+    range: Option<BufferRange>,
+    config: &Config,
+) -> io::Result<()> {
+    let font = Font::find(&config.font);
+    let options = PdfOptions::new(path, &config.print);
+    let file = pdf(
+        lines,
+        path,
+        range,
+        font.as_ref(),
+        &Palette::light(),
+        &config.print,
+        &options,
+    );
+    std::fs::write(target, file)
+}
+
+/// `#rrggbb` as a PDF color operand
+fn rgb(color: &str) -> String {
+    let channel = |at: usize| {
+        u8::from_str_radix(color.get(at..at + 2).unwrap_or("00"), 16).unwrap_or(0) as f32 / 255.0
+    };
+    format!("{:.3} {:.3} {:.3}", channel(1), channel(3), channel(5))
+}
+
+/// How strings are written for the page's font
+enum Encoding<'a> {
+    /// Glyph ids of an embedded face, two bytes each
+    Glyphs {
+        face: Box<ttf_parser::Face<'a>>,
+        used: BTreeMap<u16, char>,
+    },
+    /// Latin-1 in the built-in Courier
+    Latin1,
+}
+
+impl Encoding<'_> {
+    fn string(&mut self, text: &str) -> String {
+        match self {
+            Encoding::Glyphs { face, used } => {
+                let mut hex = String::from("<");
+                for c in text.chars() {
+                    let glyph = face.glyph_index(c).map_or(0, |glyph| glyph.0);
+                    used.entry(glyph).or_insert(c);
+                    let _ = write!(hex, "{:04X}", glyph);
+                }
+                hex.push('>');
+                hex
+            }
+            Encoding::Latin1 => {
+                let mut escaped = String::from("(");
+                for c in text.chars() {
+                    match c {
+                        '(' | ')' | '\\' => {
+                            escaped.push('\\');
+                            escaped.push(c);
+                        }
+                        ' '..='~' => escaped.push(c),
+                        c if u32::from(c) <= 0xff => {
+                            let _ = write!(escaped, "\\{:03o}", u32::from(c));
+                        }
+                        _ => escaped.push('?'),
+                    }
+                }
+                escaped.push(')');
+                escaped
+            }
+        }
+    }
+}
+
+/// The objects of a PDF file, written out with their cross-reference table
+struct Writer {
+    objects: Vec<Vec<u8>>,
+}
+
+impl Writer {
+    /// Reserve an object's number to fill in later
+    fn reserve(&mut self) -> usize {
+        self.objects.push(Vec::new());
+        self.objects.len()
+    }
+
+    fn set(&mut self, id: usize, body: impl Into<Vec<u8>>) {
+        self.objects[id - 1] = body.into();
+    }
+
+    fn add(&mut self, body: impl Into<Vec<u8>>) -> usize {
+        let id = self.reserve();
+        self.set(id, body);
+        id
+    }
+
+    /// A compressed stream, with `dictionary`'s entries besides its length
+    fn add_stream(&mut self, dictionary: &str, data: &[u8]) -> usize {
+        let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+        let _ = encoder.write_all(data);
+        let compressed = encoder.finish().unwrap_or_default();
+        let mut body = format!(
+            "<< {} /Filter /FlateDecode /Length {} >>\nstream\n",
+            dictionary,
+            compressed.len()
+        )
+        .into_bytes();
+        body.extend_from_slice(&compressed);
+        body.extend_from_slice(b"\nendstream");
+        self.add(body)
+    }
+
+    fn finish(self, root: usize) -> Vec<u8> {
+        let mut file = b"%PDF-1.4\n%\xe2\xe3\xcf\xd3\n".to_vec();
+        let mut offsets = Vec::new();
+        for (index, body) in self.objects.iter().enumerate() {
+            offsets.push(file.len());
+            file.extend_from_slice(format!("{} 0 obj\n", index + 1).as_bytes());
+            file.extend_from_slice(body);
+            file.extend_from_slice(b"\nendobj\n");
+        }
+        let xref = file.len();
+        let mut table = format!("xref\n0 {}\n0000000000 65535 f \n", offsets.len() + 1);
+        for offset in offsets {
+            let _ = writeln!(table, "{:010} 00000 n ", offset);
+        }
+        let _ = write!(
+            table,
+            "trailer\n<< /Size {} /Root {} 0 R >>\nstartxref\n{}\n%%EOF\n",
+            self.objects.len() + 1,
+            root,
+            xref
+        );
+        file.extend_from_slice(table.as_bytes());
+        file
+    }
+}
+
+/// The font's dictionary, embedding `face` with the glyphs `used` mapped
+/// back to their characters for copying text
+fn embed_font(
+    writer: &mut Writer,
+    data: &[u8],
+    face: &ttf_parser::Face,
+    used: &BTreeMap<u16, char>,
+    advance: f32,
+) -> usize {
+    let scale = 1000.0 / f32::from(face.units_per_em());
+    let bbox = face.global_bounding_box();
+    let file = writer.add_stream(&format!("/Length1 {}", data.len()), data);
+    let descriptor = writer.add(format!(
+        "<< /Type /FontDescriptor /FontName /ZlyphMono /Flags 33 \
+         /FontBBox [{} {} {} {}] /ItalicAngle 0 /Ascent {} /Descent {} \
+         /CapHeight {} /StemV 80 /FontFile2 {} 0 R >>",
+        (f32::from(bbox.x_min) * scale).round(),
+        (f32::from(bbox.y_min) * scale).round(),
+        (f32::from(bbox.x_max) * scale).round(),
+        (f32::from(bbox.y_max) * scale).round(),
+        (f32::from(face.ascender()) * scale).round(),
+        (f32::from(face.descender()) * scale).round(),
+        (f32::from(face.capital_height().unwrap_or(face.ascender())) * scale).round(),
+        file
+    ));
+    let descendant = writer.add(format!(
+        "<< /Type /Font /Subtype /CIDFontType2 /BaseFont /ZlyphMono \
+         /CIDSystemInfo << /Registry (Adobe) /Ordering (Identity) /Supplement 0 >> \
+         /FontDescriptor {} 0 R /DW {} /CIDToGIDMap /Identity >>",
+        descriptor,
+        advance.round()
+    ));
+
+    let mut cmap = String::from(
+        "/CIDInit /ProcSet findresource begin\n12 dict begin\nbegincmap\n\
+         /CMapName /Zlyph-UCS def\n/CMapType 2 def\n\
+         1 begincodespacerange\n<0000> <FFFF>\nendcodespacerange\n",
+    );
+    let used: Vec<_> = used.iter().collect();
+    for chunk in used.chunks(100) {
+        let _ = writeln!(cmap, "{} beginbfchar", chunk.len());
+        for (glyph, c) in chunk {
+            let mut units = [0; 2];
+            let hex: String = c
+                .encode_utf16(&mut units)
+                .iter()
+                .map(|unit| format!("{:04X}", unit))
+                .collect();
+            let _ = writeln!(cmap, "<{:04X}> <{}>", glyph, hex);
+        }
+        cmap.push_str("endbfchar\n");
+    }
+    cmap.push_str("endcmap\nCMapName currentdict /CMap defineresource pop\nend\nend\n");
+    let to_unicode = writer.add_stream("", cmap.as_bytes());
+
+    writer.add(format!(
+        "<< /Type /Font /Subtype /Type0 /BaseFont /ZlyphMono /Encoding /Identity-H \
+         /DescendantFonts [{} 0 R] /ToUnicode {} 0 R >>",
+        descendant, to_unicode
+    ))
+}
+
+/// `lines`, or only `range` of them, typeset on pages as configured;
+/// without a `font` in Courier
+pub fn pdf(
+    lines: &[String],
+    path: Option<&Path>,
+    range: Option<BufferRange>,
+    font: Option<&Font>,
+    palette: &Palette,
+    config: &PrintConfig,
+    options: &PdfOptions,
+) -> Vec<u8> {
+    let face = font.and_then(|font| ttf_parser::Face::parse(&font.data, 0).ok());
+    // Width of a character as a share of the font size
+    let advance = face
+        .as_ref()
+        .and_then(|face| {
+            let glyph = face.glyph_index('M')?;
+            let width = face.glyph_hor_advance(glyph)?;
+            Some(f32::from(width) / f32::from(face.units_per_em()))
+        })
+        .unwrap_or(0.6);
+    let mut encoding = match face {
+        Some(face) => Encoding::Glyphs {
+            face: Box::new(face),
+            used: BTreeMap::new(),
+        },
+        None => Encoding::Latin1,
+    };
+
+    let (page_width, page_height) = match config.paper {
+        Paper::A4 => (595.0, 842.0),
+        Paper::Letter => (612.0, 792.0),
+    };
+    let size = config.font_size.max(4.0);
+    let margin = config.margin_mm.max(0.0) * MM;
+    let leading = size * 1.3;
+    let header = if config.header { leading * 2.0 } else { 0.0 };
+    let columns = (((page_width - 2.0 * margin) / (size * advance)).floor() as usize).max(1);
+    let rows = (((page_height - 2.0 * margin - header) / leading).floor() as usize).max(1);
+
+    let printed = export::layout(lines, path, range, columns, options.highlight);
+    let pages: Vec<&[Vec<export::Run>]> = if printed.is_empty() {
+        vec![&[]]
+    } else {
+        printed.chunks(rows).collect()
+    };
+
+    let mut contents = Vec::new();
+    for (number, page) in pages.iter().enumerate() {
+        let mut content = String::new();
+        let top = page_height - margin;
+        if config.header {
+            let label = format!("Page {} of {}", number + 1, pages.len());
+            let label_x = page_width - margin - label.chars().count() as f32 * size * advance;
+            let _ = writeln!(
+                content,
+                "BT /F1 {size} Tf {} rg 1 0 0 1 {margin:.2} {:.2} Tm {} Tj \
+                 1 0 0 1 {label_x:.2} {:.2} Tm {} Tj ET",
+                rgb(&palette.muted),
+                top - size,
+                encoding.string(&options.title),
+                top - size,
+                encoding.string(&label),
+            );
+            let rule = top - leading * 1.5;
+            let _ = writeln!(
+                content,
+                "{} RG 0.5 w {margin:.2} {rule:.2} m {:.2} {rule:.2} l S",
+                rgb(&palette.muted),
+                page_width - margin
+            );
+        }
+        let _ = writeln!(content, "BT /F1 {size} Tf");
+        for (index, runs) in page.iter().enumerate() {
+            let y = top - header - size - index as f32 * leading;
+            for run in runs {
+                let x = margin + run.column as f32 * size * advance;
+                let bold = run.style.bold || run.style.heading.is_some();
+                let slant = if run.style.italic || run.style.quote {
+                    0.2
+                } else {
+                    0.0
+                };
+                let _ = writeln!(
+                    content,
+                    "{} rg {} RG {} Tr {:.2} w 1 0 {slant} 1 {x:.2} {y:.2} Tm {} Tj",
+                    rgb(export::color(run.style, palette)),
+                    rgb(export::color(run.style, palette)),
+                    if bold { 2 } else { 0 },
+                    size * 0.04,
+                    encoding.string(&run.text),
+                );
+            }
+        }
+        content.push_str("ET\n");
+        contents.push(content);
+    }
+
+    let mut writer = Writer {
+        objects: Vec::new(),
+    };
+    let catalog = writer.reserve();
+    let page_tree = writer.reserve();
+    let font = match &encoding {
+        Encoding::Glyphs { face, used } => {
+            let data = font.map_or(&[][..], |font| &font.data[..]);
+            embed_font(&mut writer, data, face, used, advance * 1000.0)
+        }
+        Encoding::Latin1 => writer
+            .add("<< /Type /Font /Subtype /Type1 /BaseFont /Courier /Encoding /WinAnsiEncoding >>"),
+    };
+    let mut kids = Vec::new();
+    for content in contents {
+        let stream = writer.add_stream("", content.as_bytes());
+        kids.push(writer.add(format!(
+            "<< /Type /Page /Parent {} 0 R /MediaBox [0 0 {} {}] \
+             /Resources << /Font << /F1 {} 0 R >> >> /Contents {} 0 R >>",
+            page_tree, page_width, page_height, font, stream
+        )));
+    }
+    let kids: Vec<String> = kids.iter().map(|kid| format!("{} 0 R", kid)).collect();
+    writer.set(
+        page_tree,
+        format!(
+            "<< /Type /Pages /Kids [{}] /Count {} >>",
+            kids.join(" "),
+            kids.len()
+        ),
+    );
+    writer.set(
+        catalog,
+        format!("<< /Type /Catalog /Pages {} 0 R >>", page_tree),
+    );
+    writer.finish(catalog)
+}
@@ -0,0 +1,404 @@
+//! Reformatting structured text: JSON, TOML and YAML laid out one value to
+//! a line, JSON minified, and the keys of each object or table sorted
+//!
+//! Members keep their order unless sorted, and numbers and strings are
+//! copied as written, so formatting changes nothing but the whitespace
+//! between tokens. TOML keeps its comments; YAML is laid out as far as
+//! [`crate::yaml`] understands it. Text that doesn't parse is left alone
+//! and the error is published as a diagnostic under [`SOURCE`].
+
+use crate::yaml;
+use std::fmt::Write as _;
+use toml_edit::{Array, DocumentMut, Item, RawString, Table, Value};
+
+/// Source name of the diagnostic published for text that doesn't parse
+pub const SOURCE: &str = "format";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    Json,
+    Toml,
+    Yaml,
+}
+
+/// Why text couldn't be parsed, and where
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseError {
+    /// Byte offset into the text
+    pub offset: usize,
+    pub message: String,
+}
+
+impl Format {
+    pub fn name(self) -> &'static str {
+        match self {
+            Format::Json => "JSON",
+            Format::Toml => "TOML",
+            Format::Yaml => "YAML",
+        }
+    }
+
+    /// The format `text` is written in: the first that parses it, or when
+    /// none does, JSON if it opens with a bracket, TOML if a line sets a
+    /// key with `=`, and YAML otherwise
+    pub fn detect(text: &str) -> Format {
+        if parse_json(text).is_ok() {
+            Format::Json
+        } else if parse_toml(text).is_ok() {
+            Format::Toml
+        } else if yaml::format(text, 2, false).is_ok() {
+            Format::Yaml
+        } else if text.trim_start().starts_with(['{', '[']) {
+            Format::Json
+        } else if text.lines().any(|line| line.contains(" = ")) {
+            Format::Toml
+        } else {
+            Format::Yaml
+        }
+    }
+}
+
+/// `text` laid out with each member and element on its own line, indented
+/// `indent` spaces a level
+pub fn json(text: &str, indent: usize) -> Result<String, ParseError> {
+    let value = parse_json(text)?;
+    let mut out = String::with_capacity(text.len() * 2);
+    write_json(&mut out, &value, Some(indent), 0);
+    Ok(out)
+}
+
+/// `text` with every space and line break between tokens taken out
+pub fn minify_json(text: &str) -> Result<String, ParseError> {
+    let value = parse_json(text)?;
+    let mut out = String::with_capacity(text.len());
+    write_json(&mut out, &value, None, 0);
+    Ok(out)
+}
+
+/// `text` with each key set as `key = value`, a blank line before each
+/// table and arrays of tables or arrays one element to a line
+pub fn toml(text: &str, indent: usize) -> Result<String, ParseError> {
+    let mut document = parse_toml(text)?;
+    tidy_table(document.as_table_mut(), indent);
+    let out = document.to_string();
+    Ok(out.trim_start_matches('\n').to_string())
+}
+
+/// `text` with each level of nesting indented `indent` spaces
+pub fn yaml(text: &str, indent: usize) -> Result<String, ParseError> {
+    yaml::format(text, indent, false).map_err(|e| yaml_error(text, e))
+}
+
+/// `text` in `format` with the keys of each object, table or mapping in
+/// order; JSON and YAML are laid out as when formatted, TOML is kept as
+/// written but for the order of its keys
+pub fn sort_keys(text: &str, format: Format, indent: usize) -> Result<String, ParseError> {
+    match format {
+        Format::Json => {
+            let mut value = parse_json(text)?;
+            sort_json(&mut value);
+            let mut out = String::with_capacity(text.len() * 2);
+            write_json(&mut out, &value, Some(indent), 0);
+            Ok(out)
+        }
+        Format::Toml => {
+            let mut document = parse_toml(text)?;
+            sort_table(document.as_table_mut());
+            Ok(document.to_string())
+        }
+        Format::Yaml => yaml::format(text, indent, true).map_err(|e| yaml_error(text, e)),
+    }
+}
+
+/// Byte offset of `column` on `row` of `text`
+fn offset(text: &str, row: usize, column: usize) -> usize {
+    let start: usize = text.split('\n').take(row).map(|line| line.len() + 1).sum();
+    (start + column).min(text.len())
+}
+
+fn yaml_error(text: &str, e: yaml::Error) -> ParseError {
+    ParseError {
+        offset: offset(text, e.row, e.column),
+        message: e.message,
+    }
+}
+
+/// A JSON value with its strings and numbers as written
+#[derive(Debug)]
+enum Json<'a> {
+    Scalar(&'a str),
+    Array(Vec<Json<'a>>),
+    Object(Vec<(&'a str, Json<'a>)>),
+}
+
+fn parse_json(text: &str) -> Result<Json<'_>, ParseError> {
+    serde_json::from_str::<serde::de::IgnoredAny>(text).map_err(|e| {
+        let message = e.to_string();
+        // The position is given by the offset, relative to the whole buffer
+        let message = message
+            .rsplit_once(" at line ")
+            .map_or(message.as_str(), |(message, _)| message);
+        ParseError {
+            offset: offset(
+                text,
+                e.line().saturating_sub(1),
+                e.column().saturating_sub(1),
+            ),
+            message: message.to_string(),
+        }
+    })?;
+    let mut rest = text;
+    Ok(json_value(&mut rest))
+}
+
+/// The value `rest` starts with, moving past it; `rest` is valid JSON
+fn json_value<'a>(rest: &mut &'a str) -> Json<'a> {
+    *rest = rest.trim_start();
+    let text = *rest;
+    match text.as_bytes().first() {
+        Some(b'{') => {
+            *rest = &text[1..];
+            let mut members = Vec::new();
+            while !json_close(rest, '}') {
+                *rest = rest.trim_start();
+                let key = json_string(rest);
+                *rest = rest.trim_start()[1..].trim_start();
+                members.push((key, json_value(rest)));
+            }
+            Json::Object(members)
+        }
+        Some(b'[') => {
+            *rest = &text[1..];
+            let mut elements = Vec::new();
+            while !json_close(rest, ']') {
+                elements.push(json_value(rest));
+            }
+            Json::Array(elements)
+        }
+        Some(b'"') => Json::Scalar(json_string(rest)),
+        _ => {
+            let end = text
+                .find(|c: char| matches!(c, ',' | ']' | '}') || c.is_whitespace())
+                .unwrap_or(text.len());
+            *rest = &text[end..];
+            Json::Scalar(&text[..end])
+        }
+    }
+}
+
+/// Move past a separating comma, or past `close` and say so
+fn json_close(rest: &mut &str, close: char) -> bool {
+    *rest = rest.trim_start();
+    if let Some(after) = rest.strip_prefix(close) {
+        *rest = after;
+        return true;
+    }
+    if let Some(after) = rest.strip_prefix(',') {
+        *rest = after;
+    }
+    false
+}
+
+/// The string `rest` starts with, quotes included, moving past it
+fn json_string<'a>(rest: &mut &'a str) -> &'a str {
+    let text = *rest;
+    let mut escaped = false;
+    for (at, c) in text.char_indices().skip(1) {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' => escaped = true,
+            '"' => {
+                *rest = &text[at + 1..];
+                return &text[..at + 1];
+            }
+            _ => {}
+        }
+    }
+    *rest = "";
+    text
+}
+
+fn sort_json(value: &mut Json) {
+    match value {
+        Json::Scalar(_) => {}
+        Json::Array(elements) => elements.iter_mut().for_each(sort_json),
+        Json::Object(members) => {
+            members.sort_by_cached_key(|(key, _)| {
+                serde_json::from_str::<String>(key).unwrap_or_default()
+            });
+            members.iter_mut().for_each(|(_, value)| sort_json(value));
+        }
+    }
+}
+
+/// Write `value` out, one member or element to a line indented `indent`
+/// spaces a level, or all on one line without spaces
+fn write_json(out: &mut String, value: &Json, indent: Option<usize>, depth: usize) {
+    let new_line = |out: &mut String, depth: usize| {
+        if let Some(indent) = indent {
+            let _ = write!(out, "\n{:1$}", "", indent * depth);
+        }
+    };
+    match value {
+        Json::Scalar(text) => out.push_str(text),
+        // Empty objects and arrays stay on one line
+        Json::Array(elements) if elements.is_empty() => out.push_str("[]"),
+        Json::Object(members) if members.is_empty() => out.push_str("{}"),
+        Json::Array(elements) => {
+            out.push('[');
+            for (i, element) in elements.iter().enumerate() {
+                if i > 0 {
+                    out.push(',');
+                }
+                new_line(out, depth + 1);
+                write_json(out, element, indent, depth + 1);
+            }
+            new_line(out, depth);
+            out.push(']');
+        }
+        Json::Object(members) => {
+            out.push('{');
+            for (i, (key, value)) in members.iter().enumerate() {
+                if i > 0 {
+                    out.push(',');
+                }
+                new_line(out, depth + 1);
+                out.push_str(key);
+                out.push_str(if indent.is_some() { ": " } else { ":" });
+                write_json(out, value, indent, depth + 1);
+            }
+            new_line(out, depth);
+            out.push('}');
+        }
+    }
+}
+
+fn parse_toml(text: &str) -> Result<DocumentMut, ParseError> {
+    text.parse::<DocumentMut>().map_err(|e| ParseError {
+        offset: e.span().map_or(0, |span| span.start),
+        message: e.message().trim().to_string(),
+    })
+}
+
+fn raw(decor: Option<&RawString>) -> &str {
+    decor.and_then(RawString::as_str).unwrap_or("")
+}
+
+/// The comments among the whitespace before a key or header, each on its
+/// own line, and one blank line where there were any
+fn leading(raw: &str) -> String {
+    let mut out = String::new();
+    let mut blank = false;
+    let lines: Vec<&str> = raw.split('\n').collect();
+    for line in &lines[..lines.len() - 1] {
+        let line = line.trim();
+        if !line.is_empty() {
+            out.push_str(line);
+            out.push('\n');
+        } else if !blank {
+            out.push('\n');
+        }
+        blank = line.is_empty();
+    }
+    out
+}
+
+/// A comment after a value on its line, set off by one space
+fn trailing(raw: &str) -> String {
+    match raw.trim() {
+        comment if comment.starts_with('#') => format!(" {}", comment),
+        _ => String::new(),
+    }
+}
+
+fn tidy_table(table: &mut Table, indent: usize) {
+    for (mut key, item) in table.iter_mut() {
+        match item {
+            Item::Value(value) => {
+                let decor = key.leaf_decor_mut();
+                let prefix = leading(raw(decor.prefix()));
+                decor.set_prefix(prefix);
+                decor.set_suffix(" ");
+                tidy_value(value, indent, 0);
+                let suffix = trailing(raw(value.decor().suffix()));
+                value.decor_mut().set_prefix(" ");
+                value.decor_mut().set_suffix(suffix);
+            }
+            Item::Table(table) => tidy_header(table, indent),
+            Item::ArrayOfTables(tables) => {
+                for table in tables.iter_mut() {
+                    tidy_header(table, indent);
+                }
+            }
+            Item::None => {}
+        }
+    }
+}
+
+fn tidy_header(table: &mut Table, indent: usize) {
+    if !table.is_dotted() {
+        let prefix = leading(raw(table.decor().prefix()));
+        let prefix = format!("\n{}", prefix.trim_start_matches('\n'));
+        let suffix = trailing(raw(table.decor().suffix()));
+        table.decor_mut().set_prefix(prefix);
+        table.decor_mut().set_suffix(suffix);
+    }
+    tidy_table(table, indent);
+}
+
+fn tidy_value(value: &mut Value, indent: usize, depth: usize) {
+    match value {
+        Value::Array(array) => tidy_array(array, indent, depth),
+        Value::InlineTable(table) => table.fmt(),
+        _ => {}
+    }
+}
+
+/// Lay out arrays holding arrays or tables, or already written over
+/// several lines, one element to a line; others on one line
+fn tidy_array(array: &mut Array, indent: usize, depth: usize) {
+    let nested = array
+        .iter()
+        .any(|value| matches!(value, Value::Array(_) | Value::InlineTable(_)));
+    let multiline = array.iter().any(|value| {
+        raw(value.decor().prefix()).contains('\n') || raw(value.decor().suffix()).contains('\n')
+    }) || raw(Some(array.trailing())).contains('\n');
+    if !nested && !multiline {
+        array.fmt();
+        return;
+    }
+
+    let pad = " ".repeat(indent * (depth + 1));
+    for value in array.iter_mut() {
+        tidy_value(value, indent, depth + 1);
+        let comments: String = leading(raw(value.decor().prefix()))
+            .lines()
+            .filter(|line| !line.is_empty())
+            .map(|comment| format!("\n{}{}", pad, comment))
+            .collect();
+        let suffix = trailing(raw(value.decor().suffix()));
+        value
+            .decor_mut()
+            .set_prefix(format!("{}\n{}", comments, pad));
+        value.decor_mut().set_suffix(suffix);
+    }
+    let comments: String = leading(raw(Some(array.trailing())))
+        .lines()
+        .filter(|line| !line.is_empty())
+        .map(|comment| format!("\n{}{}", pad, comment))
+        .collect();
+    array.set_trailing(format!("{}\n{:2$}", comments, "", indent * depth));
+    array.set_trailing_comma(true);
+}
+
+fn sort_table(table: &mut Table) {
+    table.sort_values();
+    for (_, item) in table.iter_mut() {
+        match item {
+            Item::Table(table) => sort_table(table),
+            Item::ArrayOfTables(tables) => tables.iter_mut().for_each(sort_table),
+            Item::Value(Value::InlineTable(table)) => table.sort_values(),
+            _ => {}
+        }
+    }
+}
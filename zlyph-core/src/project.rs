@@ -0,0 +1,91 @@
+//! Projects: the directory a file belongs to, which scopes finding in files,
+//! the file tree, the recent files listed first and settings
+//!
+//! A project is rooted at the nearest directory above a file holding a
+//! `.zlyph/` folder or a `.git` repository; outside one, a file's own
+//! directory stands in. Settings in `.zlyph/config.toml` override the user's
+//! `config.toml` for files in the project, table by table, so a project can
+//! turn on `[typography]` or list its own `[[tasks]]` and keep every other
+//! setting.
+
+use crate::config::Config;
+use crate::workspace::WorkspaceState;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// Folder marking a project root and holding its settings
+pub const PROJECT_DIR: &str = ".zlyph";
+
+/// The root of the project `file` is in
+pub fn root(file: &Path) -> PathBuf {
+    let dir = file.parent().unwrap_or(Path::new("."));
+    dir.ancestors()
+        .find(|ancestor| ancestor.join(PROJECT_DIR).is_dir() || ancestor.join(".git").exists())
+        .unwrap_or(dir)
+        .to_path_buf()
+}
+
+/// The project's own settings file
+pub fn settings_path(root: &Path) -> PathBuf {
+    root.join(PROJECT_DIR).join("config.toml")
+}
+
+/// The configuration for `file`: the user's, with its project's settings
+/// on top
+pub fn config(file: &Path) -> io::Result<Config> {
+    config_from(&Config::default_path(), &settings_path(&root(file)))
+}
+
+/// The configuration in `user` with the settings in `project` on top;
+/// either file may be missing
+pub fn config_from(user: &Path, project: &Path) -> io::Result<Config> {
+    let mut merged = read_table(user)?;
+    merge(&mut merged, read_table(project)?);
+    toml::Value::Table(merged)
+        .try_into()
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+}
+
+fn read_table(path: &Path) -> io::Result<toml::Table> {
+    match fs::read_to_string(path) {
+        Ok(content) => content
+            .parse()
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e)),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(toml::Table::new()),
+        Err(e) => Err(e),
+    }
+}
+
+/// Put `overrides` into `base`, merging tables present in both
+fn merge(base: &mut toml::Table, overrides: toml::Table) {
+    for (key, value) in overrides {
+        match (base.get_mut(&key), value) {
+            (Some(toml::Value::Table(base)), toml::Value::Table(value)) => merge(base, value),
+            (_, value) => {
+                base.insert(key, value);
+            }
+        }
+    }
+}
+
+/// The file last opened in the project at `root`, to switch to it
+pub fn last_file(root: &Path) -> Option<PathBuf> {
+    WorkspaceState::load()
+        .unwrap_or_default()
+        .last_file_in(root)
+}
+
+/// Known projects from the workspace state, most recently used first,
+/// leaving out the one `current` is in
+pub fn others(current: Option<&Path>) -> Vec<PathBuf> {
+    let current = current
+        .and_then(|path| path.canonicalize().ok())
+        .map(|path| root(&path));
+    WorkspaceState::load()
+        .unwrap_or_default()
+        .projects()
+        .into_iter()
+        .filter(|project| Some(project) != current.as_ref())
+        .collect()
+}
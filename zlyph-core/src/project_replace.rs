@@ -0,0 +1,257 @@
+//! Replacing project search matches across files
+//!
+//! A [`ReplacePreview`] turns search results into one hunk per match, each
+//! showing its line before and after and each individually switchable.
+//! Applying writes every affected file or none of them, and refuses files
+//! that changed since the search ran. The originals are kept so the change
+//! can be undone file by file. Preserving case gives each match its own
+//! casing of the replacement; see [`crate::replace`].
+
+use crate::project_search::{content_hash, FileMatches};
+use crate::{replace, BufferPosition};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReplaceHunk {
+    pub start: BufferPosition,
+    pub end: BufferPosition,
+    /// The line as found, and with just this match replaced
+    pub before: String,
+    pub after: String,
+    /// What just this match is replaced with
+    pub replacement: String,
+    pub included: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FileReplacement {
+    pub path: PathBuf,
+    pub hunks: Vec<ReplaceHunk>,
+    modified: Option<SystemTime>,
+    content_hash: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReplacePreview {
+    pub replacement: String,
+    pub files: Vec<FileReplacement>,
+}
+
+/// A file as it was before and after a replacement was applied
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AppliedFile {
+    pub path: PathBuf,
+    original: String,
+    replaced: String,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct AppliedReplace {
+    pub files: Vec<AppliedFile>,
+}
+
+impl ReplacePreview {
+    /// Every match of a finished search, all included
+    pub fn new(results: &[FileMatches], replacement: &str) -> Self {
+        Self::build(results, replacement, false)
+    }
+
+    /// Like [`new`](Self::new), casing the replacement like each match
+    pub fn preserving_case(results: &[FileMatches], replacement: &str) -> Self {
+        Self::build(results, replacement, true)
+    }
+
+    fn build(results: &[FileMatches], replacement: &str, preserve_case: bool) -> Self {
+        let files = results
+            .iter()
+            .map(|file| FileReplacement {
+                path: file.path.clone(),
+                hunks: file
+                    .matches
+                    .iter()
+                    .map(|found| {
+                        let range = found.start.column..found.end.column;
+                        let replacement = if preserve_case {
+                            replace::preserve_case(&found.line[range.clone()], replacement)
+                        } else {
+                            replacement.to_string()
+                        };
+                        let mut after = found.line.clone();
+                        after.replace_range(range, &replacement);
+                        ReplaceHunk {
+                            start: found.start,
+                            end: found.end,
+                            before: found.line.clone(),
+                            after,
+                            replacement,
+                            included: true,
+                        }
+                    })
+                    .collect(),
+                modified: file.modified,
+                content_hash: file.content_hash,
+            })
+            .collect();
+        Self {
+            replacement: replacement.to_string(),
+            files,
+        }
+    }
+
+    pub fn hunk_count(&self) -> usize {
+        self.files.iter().map(|file| file.hunks.len()).sum()
+    }
+
+    pub fn included_count(&self) -> usize {
+        self.hunks().filter(|(_, hunk)| hunk.included).count()
+    }
+
+    /// Every hunk with the file it is in, in display order
+    pub fn hunks(&self) -> impl Iterator<Item = (&Path, &ReplaceHunk)> {
+        self.files.iter().flat_map(|file| {
+            file.hunks
+                .iter()
+                .map(move |hunk| (file.path.as_path(), hunk))
+        })
+    }
+
+    /// Include or exclude the `index`th hunk
+    pub fn toggle(&mut self, index: usize) {
+        if let Some(hunk) = self
+            .files
+            .iter_mut()
+            .flat_map(|file| file.hunks.iter_mut())
+            .nth(index)
+        {
+            hunk.included = !hunk.included;
+        }
+    }
+
+    /// Write the included hunks to disk. Nothing is written unless every
+    /// affected file is unchanged since the search and can be written.
+    pub fn apply(&self) -> io::Result<AppliedReplace> {
+        let mut applied = AppliedReplace::default();
+        for file in &self.files {
+            let hunks: Vec<&ReplaceHunk> = file.hunks.iter().filter(|h| h.included).collect();
+            if hunks.is_empty() {
+                continue;
+            }
+
+            let original = fs::read_to_string(&file.path)?;
+            let modified = fs::metadata(&file.path)?.modified().ok();
+            if modified != file.modified && content_hash(&original) != file.content_hash {
+                return Err(io::Error::other(format!(
+                    "{} changed since the search ran",
+                    file.path.display()
+                )));
+            }
+
+            let replaced = replace_hunks(&original, &hunks);
+            applied.files.push(AppliedFile {
+                path: file.path.clone(),
+                original,
+                replaced,
+            });
+        }
+
+        write_all(applied.files.iter().map(|file| {
+            (
+                file.path.as_path(),
+                file.replaced.as_str(),
+                file.original.as_str(),
+            )
+        }))?;
+        Ok(applied)
+    }
+}
+
+impl AppliedReplace {
+    pub fn file(&self, path: &Path) -> Option<&AppliedFile> {
+        self.files.iter().find(|file| file.path == path)
+    }
+
+    /// Restore one file to how it was before the replacement, unless it has
+    /// been edited since
+    pub fn undo_file(&mut self, path: &Path) -> io::Result<()> {
+        let index = self
+            .files
+            .iter()
+            .position(|file| file.path == path)
+            .ok_or_else(|| io::Error::other(format!("{} was not replaced", path.display())))?;
+        let file = &self.files[index];
+        if fs::read_to_string(&file.path)? != file.replaced {
+            return Err(io::Error::other(format!(
+                "{} changed since the replacement",
+                file.path.display()
+            )));
+        }
+        fs::write(&file.path, &file.original)?;
+        self.files.remove(index);
+        Ok(())
+    }
+}
+
+/// `content` with the hunks' ranges replaced, keeping its line endings
+fn replace_hunks(content: &str, hunks: &[&ReplaceHunk]) -> String {
+    let line_ending = if content.contains("\r\n") {
+        "\r\n"
+    } else {
+        "\n"
+    };
+    let mut lines: Vec<String> = content.lines().map(String::from).collect();
+
+    // Back to front, so earlier columns on the same line stay valid
+    let mut hunks = hunks.to_vec();
+    hunks.sort_by_key(|hunk| hunk.start);
+    for hunk in hunks.iter().rev() {
+        if let Some(line) = lines.get_mut(hunk.start.row) {
+            line.replace_range(hunk.start.column..hunk.end.column, &hunk.replacement);
+        }
+    }
+
+    let mut replaced = lines.join(line_ending);
+    if content.ends_with('\n') {
+        replaced.push_str(line_ending);
+    }
+    replaced
+}
+
+/// Write `(path, content, original)` files through temporary files renamed
+/// into place; if any step fails, files already replaced get their original
+/// content back
+fn write_all<'a>(files: impl Iterator<Item = (&'a Path, &'a str, &'a str)>) -> io::Result<()> {
+    let files: Vec<_> = files.collect();
+    let temp_path = |path: &Path| {
+        let name = path.file_name().unwrap_or_default().to_string_lossy();
+        path.with_file_name(format!(".{}.zlyph-replace", name))
+    };
+
+    for (i, (path, content, _)) in files.iter().enumerate() {
+        let written = fs::write(temp_path(path), content).and_then(|_| {
+            // Keep the file's permissions once the copy is renamed over it
+            fs::set_permissions(temp_path(path), fs::metadata(path)?.permissions())
+        });
+        if let Err(err) = written {
+            for (path, _, _) in &files[..=i] {
+                let _ = fs::remove_file(temp_path(path));
+            }
+            return Err(err);
+        }
+    }
+
+    for (i, (path, _, _)) in files.iter().enumerate() {
+        if let Err(err) = fs::rename(temp_path(path), path) {
+            for (path, _, original) in &files[..i] {
+                let _ = fs::write(path, original);
+            }
+            for (path, _, _) in &files[i..] {
+                let _ = fs::remove_file(temp_path(path));
+            }
+            return Err(err);
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,355 @@
+//! Finding a query in every file of a workspace directory
+//!
+//! Files are listed the way `git` sees them: hidden entries and anything
+//! matched by a `.gitignore` are skipped. They are searched on a pool of
+//! background threads, and [`ProjectSearch`] collects the results as they
+//! arrive so a frontend can show them while the search is still running.
+//! A tag search looks for a `#tag` in the notes below the directory instead,
+//! and definition and reference searches look for a name in code; see
+//! [`crate::definitions`].
+
+use crate::gitignore::Gitignore;
+use crate::search::{self, find_all};
+use crate::BufferPosition;
+use crate::{definitions, notes, project, replace, tags};
+use std::collections::hash_map::DefaultHasher;
+use std::fs;
+use std::hash::{Hash, Hasher};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::mpsc::{self, Receiver, TryRecvError};
+use std::sync::Arc;
+use std::thread;
+use std::time::SystemTime;
+
+/// Files larger than this are assumed not to be text worth searching
+const MAX_FILE_SIZE: u64 = 4 * 1024 * 1024;
+
+/// Where a query occurs in the lines of the file at a path
+type Finder = fn(&Path, &[String], &str) -> Vec<(BufferPosition, BufferPosition)>;
+
+/// One occurrence of the query, with the line it is on for display
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProjectMatch {
+    pub start: BufferPosition,
+    pub end: BufferPosition,
+    pub line: String,
+}
+
+/// Every match in one file, in buffer order, and the version of the file
+/// they were found in
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FileMatches {
+    pub path: PathBuf,
+    pub matches: Vec<ProjectMatch>,
+    pub modified: Option<SystemTime>,
+    pub content_hash: u64,
+}
+
+pub fn content_hash(content: &str) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    content.hash(&mut hasher);
+    hasher.finish()
+}
+
+/// The directory to search when editing `file`: the root of its project;
+/// see [`project::root`]
+pub fn workspace_root(file: &Path) -> PathBuf {
+    project::root(file)
+}
+
+/// Every searchable file below `root`, skipping hidden and gitignored entries
+pub fn files(root: &Path) -> Vec<PathBuf> {
+    let mut files = Vec::new();
+    let mut ignores = Vec::new();
+    walk(root, &mut ignores, &mut files);
+    files
+}
+
+fn walk(dir: &Path, ignores: &mut Vec<Gitignore>, files: &mut Vec<PathBuf>) {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return;
+    };
+    let pushed = match Gitignore::load(dir) {
+        Some(gitignore) => {
+            ignores.push(gitignore);
+            true
+        }
+        None => false,
+    };
+
+    let mut entries: Vec<_> = entries.flatten().collect();
+    entries.sort_by_key(|entry| entry.file_name());
+    for entry in entries {
+        if entry.file_name().to_string_lossy().starts_with('.') {
+            continue;
+        }
+        let Ok(file_type) = entry.file_type() else {
+            continue;
+        };
+        let path = entry.path();
+        let is_dir = file_type.is_dir();
+        let ignored = ignores
+            .iter()
+            .rev()
+            .find_map(|gitignore| gitignore.matched(&path, is_dir))
+            .unwrap_or(false);
+        if ignored {
+            continue;
+        }
+
+        if is_dir {
+            walk(&path, ignores, files);
+        } else if file_type.is_file() {
+            files.push(path);
+        }
+    }
+
+    if pushed {
+        ignores.pop();
+    }
+}
+
+/// Matches of `query` in the file at `path`, or None if there are none or
+/// the file is binary, too large or unreadable
+pub fn search_file(path: &Path, query: &str) -> Option<FileMatches> {
+    search_file_with(path, query, |_, lines, query| find_all(lines, query))
+}
+
+fn search_file_with(path: &Path, query: &str, find: Finder) -> Option<FileMatches> {
+    let metadata = fs::metadata(path).ok()?;
+    if metadata.len() > MAX_FILE_SIZE {
+        return None;
+    }
+    let bytes = fs::read(path).ok()?;
+    if bytes.contains(&0) {
+        return None;
+    }
+    let content = String::from_utf8(bytes).ok()?;
+    let lines: Vec<String> = content.lines().map(String::from).collect();
+
+    let matches: Vec<ProjectMatch> = find(path, &lines, query)
+        .into_iter()
+        .map(|(start, end)| ProjectMatch {
+            start,
+            end,
+            line: lines[start.row].clone(),
+        })
+        .collect();
+    if matches.is_empty() {
+        return None;
+    }
+    Some(FileMatches {
+        path: path.to_path_buf(),
+        matches,
+        modified: metadata.modified().ok(),
+        content_hash: content_hash(&content),
+    })
+}
+
+/// A search running in the background; results are collected by [`poll`]
+///
+/// [`poll`]: ProjectSearch::poll
+pub struct ProjectSearch {
+    pub root: PathBuf,
+    pub query: String,
+    results: Vec<FileMatches>,
+    receiver: Receiver<FileMatches>,
+    done: bool,
+    /// Tells the workers to stop early once the search is dropped
+    cancelled: Arc<AtomicBool>,
+}
+
+impl ProjectSearch {
+    pub fn start(root: &Path, query: &str) -> Self {
+        Self::spawn(
+            root,
+            query.to_string(),
+            query.to_string(),
+            files,
+            |_, lines, query| find_all(lines, query),
+        )
+    }
+
+    /// Like [`start`](Self::start), finding `query` only as a whole word
+    /// or regardless of case when asked
+    pub fn start_matching(root: &Path, query: &str, whole_word: bool, ignore_case: bool) -> Self {
+        if !whole_word && !ignore_case {
+            return Self::start(root, query);
+        }
+        let pattern = if query.is_empty() {
+            String::new()
+        } else {
+            replace::literal_pattern(query, whole_word, ignore_case)
+        };
+        Self::spawn(
+            root,
+            query.to_string(),
+            pattern,
+            files,
+            |_, lines, pattern| search::find_pattern(lines, pattern),
+        )
+    }
+
+    /// Find the uses of `tag` in the notes below `root`
+    pub fn start_tag(root: &Path, tag: &str) -> Self {
+        let tag = tag.trim_start_matches('#');
+        Self::spawn(
+            root,
+            format!("#{}", tag),
+            tag.to_string(),
+            notes::note_files,
+            |_, lines, tag| tags::find_tag(lines, tag),
+        )
+    }
+
+    /// Find where `name` is defined below `root`: in its ctags file if it
+    /// has one, or else in the outline of each file
+    pub fn start_definitions(root: &Path, name: &str) -> Self {
+        let Some(tags) = definitions::tags_file(root) else {
+            let name = name.to_string();
+            return Self::spawn(
+                root,
+                name.clone(),
+                name,
+                files,
+                definitions::find_definitions,
+            );
+        };
+        let (sender, receiver) = mpsc::channel();
+        let worker_name = name.to_string();
+        thread::spawn(move || {
+            for matches in definitions::tag_definitions(&tags, &worker_name) {
+                if sender.send(matches).is_err() {
+                    break;
+                }
+            }
+        });
+        Self {
+            root: root.to_path_buf(),
+            query: name.to_string(),
+            results: Vec::new(),
+            receiver,
+            done: false,
+            cancelled: Arc::new(AtomicBool::new(false)),
+        }
+    }
+
+    /// Find the uses of `name` as a whole word below `root`
+    pub fn start_references(root: &Path, name: &str) -> Self {
+        let name = name.to_string();
+        Self::spawn(
+            root,
+            name.clone(),
+            name,
+            files,
+            definitions::find_references,
+        )
+    }
+
+    /// Search the files `list` finds below `root`, shown as `query` and
+    /// looked for as `pattern`
+    fn spawn(
+        root: &Path,
+        query: String,
+        pattern: String,
+        list: fn(&Path) -> Vec<PathBuf>,
+        find: Finder,
+    ) -> Self {
+        let (sender, receiver) = mpsc::channel();
+        let cancelled = Arc::new(AtomicBool::new(false));
+
+        let (walk_root, worker_query, worker_cancelled) =
+            (root.to_path_buf(), pattern, cancelled.clone());
+        thread::spawn(move || {
+            if worker_query.is_empty() {
+                return;
+            }
+            let files = list(&walk_root);
+            let next = AtomicUsize::new(0);
+            let workers = thread::available_parallelism().map_or(4, |n| n.get());
+            thread::scope(|scope| {
+                for _ in 0..workers {
+                    let sender = sender.clone();
+                    let (files, next, query, cancelled) =
+                        (&files, &next, &worker_query, &worker_cancelled);
+                    scope.spawn(move || {
+                        while !cancelled.load(Ordering::Relaxed) {
+                            let Some(path) = files.get(next.fetch_add(1, Ordering::Relaxed)) else {
+                                break;
+                            };
+                            if let Some(matches) = search_file_with(path, query, find) {
+                                if sender.send(matches).is_err() {
+                                    break;
+                                }
+                            }
+                        }
+                    });
+                }
+            });
+        });
+
+        Self {
+            root: root.to_path_buf(),
+            query,
+            results: Vec::new(),
+            receiver,
+            done: false,
+            cancelled,
+        }
+    }
+
+    /// Collect results that arrived since the last call; returns whether
+    /// anything changed
+    pub fn poll(&mut self) -> bool {
+        let mut changed = false;
+        loop {
+            match self.receiver.try_recv() {
+                Ok(matches) => {
+                    self.results.push(matches);
+                    changed = true;
+                }
+                Err(TryRecvError::Empty) => break,
+                Err(TryRecvError::Disconnected) => {
+                    changed |= !self.done;
+                    self.done = true;
+                    break;
+                }
+            }
+        }
+        changed
+    }
+
+    /// Block until every file has been searched
+    pub fn wait(&mut self) {
+        self.results.extend(self.receiver.iter());
+        self.done = true;
+    }
+
+    pub fn is_done(&self) -> bool {
+        self.done
+    }
+
+    /// Files with matches, in the order they were found
+    pub fn results(&self) -> &[FileMatches] {
+        &self.results
+    }
+
+    pub fn match_count(&self) -> usize {
+        self.results.iter().map(|file| file.matches.len()).sum()
+    }
+
+    /// The `index`th match across all files, in display order
+    pub fn get(&self, index: usize) -> Option<(&Path, &ProjectMatch)> {
+        self.results
+            .iter()
+            .flat_map(|file| file.matches.iter().map(|m| (file.path.as_path(), m)))
+            .nth(index)
+    }
+}
+
+impl Drop for ProjectSearch {
+    fn drop(&mut self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+}
@@ -0,0 +1,72 @@
+//! Protected regions: byte ranges of the text that edits may not touch,
+//! such as a template's header or frontmatter another tool put in
+//!
+//! [`EditorEngine::protect`](crate::EditorEngine::protect) marks a range.
+//! An action that would change text inside one is undone before anyone
+//! sees it, and the engine says why in its status message. Typing at
+//! either edge of a region is allowed and stays outside it. Regions move
+//! with the edits around them, and loading a file clears them.
+
+use std::ops::Range;
+
+/// Add `range` to `regions`, kept sorted with overlapping ranges joined
+pub(crate) fn add(regions: &mut Vec<Range<usize>>, range: Range<usize>) {
+    if range.is_empty() {
+        return;
+    }
+    regions.push(range);
+    regions.sort_by_key(|region| region.start);
+    let mut joined: Vec<Range<usize>> = Vec::with_capacity(regions.len());
+    for region in regions.drain(..) {
+        match joined.last_mut() {
+            Some(last) if region.start <= last.end => last.end = last.end.max(region.end),
+            _ => joined.push(region),
+        }
+    }
+    *regions = joined;
+}
+
+/// Whether replacing `start..old_end` changes text inside a region. An
+/// insertion only counts when it lands strictly inside one.
+pub(crate) fn blocks(regions: &[Range<usize>], start: usize, old_end: usize) -> bool {
+    regions.iter().any(|region| {
+        if start == old_end {
+            region.start < start && start < region.end
+        } else {
+            region.start < old_end && start < region.end
+        }
+    })
+}
+
+/// Move `regions` to where they are after `start..old_end` was replaced by
+/// text ending at `new_end`; text inserted at a region's edge stays outside
+/// it, and regions left empty are dropped
+pub(crate) fn shift(regions: &mut Vec<Range<usize>>, start: usize, old_end: usize, new_end: usize) {
+    let map = |offset: usize, inclusive: bool| {
+        if offset < start || (inclusive && offset == start) {
+            offset
+        } else if offset >= old_end {
+            offset - old_end + new_end
+        } else {
+            new_end
+        }
+    };
+    for region in regions.iter_mut() {
+        *region = map(region.start, false)..map(region.end, true);
+    }
+    regions.retain(|region| !region.is_empty());
+}
+
+/// The span changed between `old` and `new`, as
+/// [`changed_span`](crate::diff::changed_span) finds it, and also
+/// with the change pushed as far left as it goes. Typing a letter beside
+/// the same letter could have happened at either place; the two spans
+/// give both.
+pub(crate) fn changed_spans(old: &str, new: &str) -> [(usize, usize, usize); 2] {
+    let first = crate::diff::changed_span(old, new);
+    let reversed = |text: &str| text.chars().rev().collect::<String>();
+    // Offsets into the reversed text count back from the end
+    let (suffix, old_rest, _) = crate::diff::changed_span(&reversed(old), &reversed(new));
+    let leftmost = (old.len() - old_rest, old.len() - suffix, new.len() - suffix);
+    [first, leftmost]
+}
@@ -0,0 +1,109 @@
+//! The recent-files list both frontends show for switching files, most
+//! recently opened first and narrowed by typing, and the same list of known
+//! projects for switching projects
+
+use crate::project;
+use crate::workspace::WorkspaceState;
+use std::path::{Path, PathBuf};
+
+#[derive(Debug, Clone, Default)]
+pub struct QuickSwitch {
+    pub query: String,
+    /// Index into [`QuickSwitch::matches`]
+    pub selected: usize,
+    /// Whether the list holds project roots rather than files
+    pub projects: bool,
+    files: Vec<PathBuf>,
+}
+
+impl QuickSwitch {
+    pub fn new(files: Vec<PathBuf>) -> Self {
+        Self {
+            query: String::new(),
+            selected: 0,
+            projects: false,
+            files,
+        }
+    }
+
+    /// The recent files from the workspace state, those in `current`'s
+    /// project first, leaving out `current` so the first entry is the file
+    /// opened before it
+    pub fn load(current: Option<&Path>) -> Self {
+        let current = current.and_then(|path| path.canonicalize().ok());
+        let root = current.as_deref().map(project::root);
+        let mut files: Vec<PathBuf> = WorkspaceState::load()
+            .unwrap_or_default()
+            .recent_files()
+            .into_iter()
+            .filter(|path| Some(path) != current.as_ref())
+            .collect();
+        if let Some(root) = root {
+            // Stable, so each group stays most recent first
+            files.sort_by_key(|path| !path.starts_with(&root));
+        }
+        Self::new(files)
+    }
+
+    /// The known projects other than `current`'s, most recently used first
+    pub fn load_projects(current: Option<&Path>) -> Self {
+        Self {
+            projects: true,
+            ..Self::new(project::others(current))
+        }
+    }
+
+    /// Files whose path contains every word of the query, ignoring case
+    pub fn matches(&self) -> Vec<&Path> {
+        let query = self.query.to_lowercase();
+        let words: Vec<&str> = query.split_whitespace().collect();
+        self.files
+            .iter()
+            .filter(|path| {
+                let path = path.to_string_lossy().to_lowercase();
+                words.iter().all(|word| path.contains(word))
+            })
+            .map(PathBuf::as_path)
+            .collect()
+    }
+
+    pub fn type_text(&mut self, text: &str) {
+        self.query.push_str(text);
+        self.selected = 0;
+    }
+
+    pub fn backspace(&mut self) {
+        self.query.pop();
+        self.selected = 0;
+    }
+
+    pub fn select_previous(&mut self) {
+        self.selected = self.selected.saturating_sub(1);
+    }
+
+    pub fn select_next(&mut self) {
+        self.selected = (self.selected + 1).min(self.matches().len().saturating_sub(1));
+    }
+
+    pub fn selected_path(&self) -> Option<PathBuf> {
+        self.matches()
+            .get(self.selected)
+            .map(|path| path.to_path_buf())
+    }
+}
+
+/// File name and directory for listing a path, with the home directory
+/// shortened to `~`
+pub fn display_name(path: &Path) -> (String, String) {
+    let name = path
+        .file_name()
+        .map_or_else(String::new, |name| name.to_string_lossy().into_owned());
+    let dir = path.parent().unwrap_or(Path::new(""));
+    let dir = match std::env::var_os("HOME") {
+        Some(home) if dir.starts_with(&home) => {
+            Path::new("~").join(dir.strip_prefix(&home).unwrap_or(dir))
+        }
+        _ => dir.to_path_buf(),
+    };
+    (name, dir.display().to_string())
+}
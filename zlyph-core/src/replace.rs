@@ -0,0 +1,59 @@
+//! Options shared by the ways text is replaced: the `:s` command and
+//! replacing across files
+//!
+//! Preserving case lets one lowercase pattern and replacement stand for
+//! every casing of a word: replacing `foo` with `bar` turns `Foo` into `Bar`
+//! and `FOO` into `BAR`.
+
+/// `replacement` cased the way `matched` is: all capitals, capitalized or
+/// all lowercase; text mixing cases otherwise gets the replacement as typed
+pub fn preserve_case(matched: &str, replacement: &str) -> String {
+    let mut letters = matched.chars().filter(|c| c.is_alphabetic());
+    let Some(first) = letters.next() else {
+        return replacement.to_string();
+    };
+    let rest: Vec<char> = letters.collect();
+    if first.is_uppercase() && rest.iter().all(|c| c.is_uppercase()) && !rest.is_empty() {
+        replacement.to_uppercase()
+    } else if first.is_uppercase() && rest.iter().all(|c| c.is_lowercase()) {
+        let mut chars = replacement.chars();
+        match chars.next() {
+            Some(c) => c.to_uppercase().chain(chars).collect(),
+            None => String::new(),
+        }
+    } else if first.is_lowercase() && rest.iter().all(|c| c.is_lowercase()) {
+        replacement.to_lowercase()
+    } else {
+        replacement.to_string()
+    }
+}
+
+/// A regex source matching `query` literally, as a whole word only and
+/// ignoring case when asked
+pub fn literal_pattern(query: &str, whole_word: bool, ignore_case: bool) -> String {
+    let mut source = regex::escape(query);
+    if whole_word {
+        source = format!(r"\b(?:{})\b", source);
+    }
+    if ignore_case {
+        source.insert_str(0, "(?i)");
+    }
+    source
+}
+
+/// What a replacement that only counts found, e.g. "5 matches on 3 lines"
+/// or "No matches"
+pub fn count_report(matches: usize, lines: usize) -> String {
+    let plural = |n: usize, word: &str| {
+        if n == 1 {
+            format!("{} {}", n, word)
+        } else {
+            format!("{} {}s", n, word)
+        }
+    };
+    match matches {
+        0 => "No matches".to_string(),
+        1 => format!("1 match on {}", plural(lines, "line")),
+        _ => format!("{} matches on {}", matches, plural(lines, "line")),
+    }
+}
@@ -0,0 +1,314 @@
+//! Headless editing over JSON-RPC 2.0, for scripts, tests and other
+//! frontends
+//!
+//! Messages are newline-delimited JSON objects. Each open buffer is an
+//! [`EditorEngine`] addressed by a numeric id:
+//!
+//! - `open` `{path?, text?}` → `{buffer}`: load a file (empty if it does not
+//!   exist yet) or start from `text`
+//! - `close` `{buffer}`
+//! - `save` `{buffer, path?}`: write to `path`, or where it was opened from
+//! - `action` `{buffer, action}` → buffer state: apply an [`EditorAction`],
+//!   e.g. `"MoveDown"` or `{"TypeString": "hi"}`
+//! - `state` `{buffer}` → `{buffer, path, version, text, cursor, selection}`
+//! - `dump` `{buffer}` → the buffer's [`EditorState`]
+//! - `restore` `{buffer, state}` → buffer state: put back a dumped state
+//! - `decorate` `{buffer, source, decorations}`: replace the
+//!   [`Decoration`]s published under `source`
+//! - `buffers` → the open buffer ids
+//! - `subscribe` / `unsubscribe` `{buffer}`: after every change to the
+//!   buffer, a `changed` notification carrying its state follows the response
+
+use crate::decorations::Decoration;
+use crate::{BufferPosition, EditorAction, EditorEngine, EditorState};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::collections::{BTreeMap, BTreeSet};
+use std::io::{self, BufRead, Write};
+use std::path::PathBuf;
+
+const PARSE_ERROR: i64 = -32700;
+const INVALID_REQUEST: i64 = -32600;
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+/// File errors and unknown buffer ids
+const SERVER_ERROR: i64 = -32000;
+
+struct Buffer {
+    engine: EditorEngine,
+    path: Option<PathBuf>,
+    /// Bumped whenever the text, cursor or selection changes
+    version: u64,
+}
+
+struct RpcError {
+    code: i64,
+    message: String,
+}
+
+impl RpcError {
+    fn new(code: i64, message: impl Into<String>) -> Self {
+        Self {
+            code,
+            message: message.into(),
+        }
+    }
+}
+
+impl From<io::Error> for RpcError {
+    fn from(e: io::Error) -> Self {
+        Self::new(SERVER_ERROR, e.to_string())
+    }
+}
+
+#[derive(Deserialize)]
+struct OpenParams {
+    path: Option<PathBuf>,
+    text: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct BufferParams {
+    buffer: u64,
+}
+
+#[derive(Deserialize)]
+struct SaveParams {
+    buffer: u64,
+    path: Option<PathBuf>,
+}
+
+#[derive(Deserialize)]
+struct ActionParams {
+    buffer: u64,
+    action: EditorAction,
+}
+
+#[derive(Deserialize)]
+struct RestoreParams {
+    buffer: u64,
+    state: EditorState,
+}
+
+#[derive(Deserialize)]
+struct DecorateParams {
+    buffer: u64,
+    source: String,
+    decorations: Vec<Decoration>,
+}
+
+#[derive(Default)]
+pub struct RpcServer {
+    buffers: BTreeMap<u64, Buffer>,
+    next_id: u64,
+    subscriptions: BTreeSet<u64>,
+}
+
+impl RpcServer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Answer requests read line by line from `reader` until it closes
+    pub fn serve(&mut self, reader: impl BufRead, mut writer: impl Write) -> io::Result<()> {
+        for line in reader.lines() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            for message in self.handle(&line) {
+                writeln!(writer, "{}", message)?;
+            }
+            writer.flush()?;
+        }
+        Ok(())
+    }
+
+    /// Handle one message; returns the response (none for a notification)
+    /// followed by any `changed` notifications it caused
+    pub fn handle(&mut self, message: &str) -> Vec<String> {
+        let request: Value = match serde_json::from_str(message) {
+            Ok(request) => request,
+            Err(e) => return vec![error_response(Value::Null, PARSE_ERROR, &e.to_string())],
+        };
+        let id = request.get("id").cloned();
+        let Some(method) = request.get("method").and_then(Value::as_str) else {
+            return vec![error_response(
+                id.unwrap_or(Value::Null),
+                INVALID_REQUEST,
+                "missing method",
+            )];
+        };
+        let params = request.get("params").cloned().unwrap_or(Value::Null);
+
+        let mut changed = Vec::new();
+        let result = self.call(method, params, &mut changed);
+
+        let mut messages = Vec::new();
+        if let Some(id) = id {
+            messages.push(match result {
+                Ok(result) => json!({"jsonrpc": "2.0", "id": id, "result": result}).to_string(),
+                Err(e) => error_response(id, e.code, &e.message),
+            });
+        }
+        for buffer in changed {
+            if self.subscriptions.contains(&buffer) {
+                if let Some(state) = self.buffer_state(buffer) {
+                    messages.push(
+                        json!({"jsonrpc": "2.0", "method": "changed", "params": state}).to_string(),
+                    );
+                }
+            }
+        }
+        messages
+    }
+
+    fn call(
+        &mut self,
+        method: &str,
+        params: Value,
+        changed: &mut Vec<u64>,
+    ) -> Result<Value, RpcError> {
+        match method {
+            "open" => {
+                let params: OpenParams = parse_params(params)?;
+                let mut engine = EditorEngine::new();
+                match (&params.text, &params.path) {
+                    (Some(text), _) => engine.load_text(text),
+                    (None, Some(path)) if path.exists() => engine.load_from_file(path)?,
+                    _ => {}
+                }
+                self.next_id += 1;
+                let buffer = self.next_id;
+                self.buffers.insert(
+                    buffer,
+                    Buffer {
+                        engine,
+                        path: params.path,
+                        version: 0,
+                    },
+                );
+                Ok(json!({ "buffer": buffer }))
+            }
+            "close" => {
+                let params: BufferParams = parse_params(params)?;
+                self.buffer(params.buffer)?;
+                self.buffers.remove(&params.buffer);
+                self.subscriptions.remove(&params.buffer);
+                Ok(Value::Null)
+            }
+            "save" => {
+                let params: SaveParams = parse_params(params)?;
+                let buffer = self.buffer(params.buffer)?;
+                let path = params
+                    .path
+                    .or_else(|| buffer.path.clone())
+                    .ok_or_else(|| RpcError::new(INVALID_PARAMS, "buffer has no path"))?;
+                buffer.engine.save_to_file(&path)?;
+                buffer.path = Some(path);
+                Ok(Value::Null)
+            }
+            "action" => {
+                let params: ActionParams = parse_params(params)?;
+                let buffer = self.buffer(params.buffer)?;
+                let before = snapshot(&buffer.engine);
+                buffer.engine.handle_action(params.action);
+                if snapshot(&buffer.engine) != before {
+                    buffer.version += 1;
+                    changed.push(params.buffer);
+                }
+                self.state(params.buffer)
+            }
+            "state" => {
+                let params: BufferParams = parse_params(params)?;
+                self.state(params.buffer)
+            }
+            "dump" => {
+                let params: BufferParams = parse_params(params)?;
+                let buffer = self.buffer(params.buffer)?;
+                Ok(json!(buffer.engine.state()))
+            }
+            "restore" => {
+                let params: RestoreParams = parse_params(params)?;
+                let buffer = self.buffer(params.buffer)?;
+                let before = snapshot(&buffer.engine);
+                buffer.engine.restore_state(params.state);
+                if snapshot(&buffer.engine) != before {
+                    buffer.version += 1;
+                    changed.push(params.buffer);
+                }
+                self.state(params.buffer)
+            }
+            "decorate" => {
+                let params: DecorateParams = parse_params(params)?;
+                let buffer = self.buffer(params.buffer)?;
+                buffer
+                    .engine
+                    .set_decorations(&params.source, params.decorations);
+                Ok(Value::Null)
+            }
+            "buffers" => Ok(json!(self.buffers.keys().collect::<Vec<_>>())),
+            "subscribe" | "unsubscribe" => {
+                let params: BufferParams = parse_params(params)?;
+                self.buffer(params.buffer)?;
+                if method == "subscribe" {
+                    self.subscriptions.insert(params.buffer);
+                } else {
+                    self.subscriptions.remove(&params.buffer);
+                }
+                Ok(Value::Null)
+            }
+            _ => Err(RpcError::new(
+                METHOD_NOT_FOUND,
+                format!("unknown method {}", method),
+            )),
+        }
+    }
+
+    fn buffer(&mut self, id: u64) -> Result<&mut Buffer, RpcError> {
+        self.buffers
+            .get_mut(&id)
+            .ok_or_else(|| RpcError::new(SERVER_ERROR, format!("no buffer {}", id)))
+    }
+
+    fn state(&self, id: u64) -> Result<Value, RpcError> {
+        self.buffer_state(id)
+            .ok_or_else(|| RpcError::new(SERVER_ERROR, format!("no buffer {}", id)))
+    }
+
+    fn buffer_state(&self, id: u64) -> Option<Value> {
+        let buffer = self.buffers.get(&id)?;
+        let state = buffer.engine.state();
+        Some(json!({
+            "buffer": id,
+            "path": buffer.path,
+            "version": buffer.version,
+            "text": state.lines.join("\n"),
+            "cursor": state.cursor,
+            "selection": state.selection_anchor.map(|anchor| json!({
+                "anchor": anchor,
+                "head": state.cursor,
+            })),
+        }))
+    }
+}
+
+type Snapshot = (Vec<String>, BufferPosition, Option<BufferPosition>);
+
+fn snapshot(engine: &EditorEngine) -> Snapshot {
+    let state = engine.state();
+    (state.lines.clone(), state.cursor, state.selection_anchor)
+}
+
+fn parse_params<T: for<'de> Deserialize<'de>>(params: Value) -> Result<T, RpcError> {
+    serde_json::from_value(params).map_err(|e| RpcError::new(INVALID_PARAMS, e.to_string()))
+}
+
+fn error_response(id: Value, code: i64, message: &str) -> String {
+    json!({
+        "jsonrpc": "2.0",
+        "id": id,
+        "error": {"code": code, "message": message},
+    })
+    .to_string()
+}
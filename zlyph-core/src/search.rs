@@ -0,0 +1,97 @@
+//! Finding every occurrence of a query in the buffer, and which of them the
+//! cursor is at
+
+use crate::BufferPosition;
+
+/// Source name of the decorations highlighting matches
+pub const SOURCE: &str = "search";
+
+/// Start and end of every non-overlapping occurrence of `query`, in buffer
+/// order. Queries spanning lines never match.
+pub fn find_all(lines: &[String], query: &str) -> Vec<(BufferPosition, BufferPosition)> {
+    if query.is_empty() || query.contains('\n') {
+        return Vec::new();
+    }
+
+    lines
+        .iter()
+        .enumerate()
+        .flat_map(|(row, line)| {
+            line.match_indices(query).map(move |(column, _)| {
+                (
+                    BufferPosition::new(row, column),
+                    BufferPosition::new(row, column + query.len()),
+                )
+            })
+        })
+        .collect()
+}
+
+/// Every non-empty match of the regex `pattern`, or none if it is invalid
+pub fn find_pattern(lines: &[String], pattern: &str) -> Vec<(BufferPosition, BufferPosition)> {
+    let Ok(regex) = regex::Regex::new(pattern) else {
+        return Vec::new();
+    };
+    lines
+        .iter()
+        .enumerate()
+        .flat_map(|(row, line)| {
+            regex
+                .find_iter(line)
+                .filter(|found| !found.is_empty())
+                .map(move |found| {
+                    (
+                        BufferPosition::new(row, found.start()),
+                        BufferPosition::new(row, found.end()),
+                    )
+                })
+                .collect::<Vec<_>>()
+        })
+        .collect()
+}
+
+/// Index of the match that is `selection`, or failing that the one the
+/// cursor is in or at either end of
+pub fn current(
+    matches: &[(BufferPosition, BufferPosition)],
+    selection: Option<(BufferPosition, BufferPosition)>,
+    cursor: BufferPosition,
+) -> Option<usize> {
+    selection
+        .and_then(|selection| matches.iter().position(|found| *found == selection))
+        .or_else(|| {
+            matches
+                .iter()
+                .position(|(start, end)| *start <= cursor && cursor <= *end)
+        })
+}
+
+/// Index of the first match starting after `from`, or with `backwards` the
+/// last starting before it, wrapping around the buffer
+pub fn next(
+    matches: &[(BufferPosition, BufferPosition)],
+    from: BufferPosition,
+    backwards: bool,
+) -> Option<usize> {
+    if matches.is_empty() {
+        return None;
+    }
+    if backwards {
+        let before = matches.iter().rposition(|(start, _)| *start < from);
+        Some(before.unwrap_or(matches.len() - 1))
+    } else {
+        let after = matches.iter().position(|(start, _)| *start > from);
+        Some(after.unwrap_or(0))
+    }
+}
+
+/// How many matches there are, and which the cursor is at, e.g. "3 of 17
+/// matches"
+pub fn count_label(current: Option<usize>, total: usize) -> String {
+    let noun = if total == 1 { "match" } else { "matches" };
+    match (current, total) {
+        (_, 0) => "No matches".to_string(),
+        (Some(index), _) => format!("{} of {} {}", index + 1, total, noun),
+        (None, _) => format!("{} {}", total, noun),
+    }
+}
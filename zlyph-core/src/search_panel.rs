@@ -0,0 +1,397 @@
+//! State of the find-in-files panel shared by both frontends: the query and
+//! replacement being typed, the running search, the replace preview and the
+//! highlighted entry. Frontends route keys here and draw [`SearchPanel::rows`].
+
+use crate::project_replace::{AppliedReplace, ReplacePreview};
+use crate::project_search::ProjectSearch;
+use crate::workspace::{WorkspaceState, MAX_SEARCHES};
+use crate::BufferPosition;
+use std::path::{Path, PathBuf};
+
+/// One line of the results list
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SearchRow {
+    /// Heading for the matches below it, relative to the search root
+    File(PathBuf),
+    Match {
+        index: usize,
+        row: usize,
+        text: String,
+    },
+    /// A match in the replace preview, with its line before and after
+    Change {
+        index: usize,
+        row: usize,
+        before: String,
+        after: String,
+        included: bool,
+    },
+}
+
+#[derive(Default)]
+pub struct SearchPanel {
+    pub query: String,
+    /// Some while replacing
+    pub replacement: Option<String>,
+    /// Whether typing goes to the replacement rather than the query
+    pub editing_replacement: bool,
+    /// Only find the query as a whole word
+    pub whole_word: bool,
+    /// Find the query in any case and case each replacement like its match
+    pub preserve_case: bool,
+    /// Index of the highlighted match across all files
+    pub selected: usize,
+    /// None while the query is being typed
+    search: Option<ProjectSearch>,
+    preview: Option<ReplacePreview>,
+    applied: Option<AppliedReplace>,
+    /// Outcome of the last apply or undo
+    message: Option<String>,
+    /// Past queries, most recent first
+    history: Vec<String>,
+    /// The past query shown in place of the one being typed, which is kept
+    /// to come back to
+    history_index: Option<usize>,
+    draft: String,
+}
+
+impl SearchPanel {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// A panel whose prompt can bring back the queries in `history`, most
+    /// recent first
+    pub fn with_history(history: Vec<String>) -> Self {
+        Self {
+            history,
+            ..Self::default()
+        }
+    }
+
+    /// A panel with the search history kept in the workspace state
+    pub fn load() -> Self {
+        Self::with_history(WorkspaceState::load().unwrap_or_default().searches)
+    }
+
+    pub fn set_replacing(&mut self, replacing: bool) {
+        if replacing {
+            self.replacement.get_or_insert_with(String::new);
+        } else {
+            self.replacement = None;
+            self.editing_replacement = false;
+            self.preview = None;
+        }
+        self.update_preview();
+    }
+
+    pub fn is_replacing(&self) -> bool {
+        self.replacement.is_some()
+    }
+
+    /// The search that was run, once the query has been submitted
+    pub fn search(&self) -> Option<&ProjectSearch> {
+        self.search.as_ref()
+    }
+
+    pub fn type_text(&mut self, text: &str) {
+        match self.replacement.as_mut() {
+            Some(replacement) if self.editing_replacement => {
+                replacement.push_str(text);
+                self.preview = None;
+                self.update_preview();
+            }
+            _ => {
+                self.query.push_str(text);
+                self.history_index = None;
+                self.clear_results();
+            }
+        }
+    }
+
+    pub fn backspace(&mut self) {
+        match self.replacement.as_mut() {
+            Some(replacement) if self.editing_replacement => {
+                replacement.pop();
+                self.preview = None;
+                self.update_preview();
+            }
+            _ => {
+                self.query.pop();
+                self.history_index = None;
+                self.clear_results();
+            }
+        }
+    }
+
+    /// Show the query searched for before the one shown
+    pub fn history_previous(&mut self) {
+        let index = self.history_index.map_or(0, |index| index + 1);
+        let Some(query) = self.history.get(index) else {
+            return;
+        };
+        if self.history_index.is_none() {
+            self.draft = std::mem::replace(&mut self.query, query.clone());
+        } else {
+            self.query = query.clone();
+        }
+        self.history_index = Some(index);
+        self.clear_results();
+    }
+
+    /// Show the query searched for after the one shown, or the one being
+    /// typed after the most recent
+    pub fn history_next(&mut self) {
+        match self.history_index {
+            None => return,
+            Some(0) => {
+                self.query = std::mem::take(&mut self.draft);
+                self.history_index = None;
+            }
+            Some(index) => {
+                self.query = self.history[index - 1].clone();
+                self.history_index = Some(index - 1);
+            }
+        }
+        self.clear_results();
+    }
+
+    /// Find the query only as a whole word, or anywhere again
+    pub fn toggle_whole_word(&mut self) {
+        self.whole_word = !self.whole_word;
+        self.clear_results();
+    }
+
+    /// Turn preserving case on or off; the query has to be searched again
+    /// as it matches differently
+    pub fn toggle_preserve_case(&mut self) {
+        self.preserve_case = !self.preserve_case;
+        self.clear_results();
+    }
+
+    /// Move typing between the query and the replacement
+    pub fn switch_field(&mut self) {
+        self.editing_replacement = self.is_replacing() && !self.editing_replacement;
+    }
+
+    /// Search for the query in every file below `root`, adding it to the
+    /// history
+    pub fn start(&mut self, root: &Path) {
+        self.history.retain(|query| *query != self.query);
+        self.history.insert(0, self.query.clone());
+        self.history.truncate(MAX_SEARCHES);
+        self.history_index = None;
+        self.search = Some(ProjectSearch::start_matching(
+            root,
+            &self.query,
+            self.whole_word,
+            self.preserve_case,
+        ));
+        self.preview = None;
+        self.message = None;
+        self.selected = 0;
+    }
+
+    /// List every use of `tag` in the notes below `root`
+    pub fn find_tag(&mut self, root: &Path, tag: &str) {
+        self.show_search(ProjectSearch::start_tag(root, tag));
+    }
+
+    /// List where `name` is defined below `root`
+    pub fn find_definitions(&mut self, root: &Path, name: &str) {
+        self.show_search(ProjectSearch::start_definitions(root, name));
+    }
+
+    /// List the uses of `name` below `root`
+    pub fn find_references(&mut self, root: &Path, name: &str) {
+        self.show_search(ProjectSearch::start_references(root, name));
+    }
+
+    fn show_search(&mut self, search: ProjectSearch) {
+        self.set_replacing(false);
+        self.query = search.query.clone();
+        self.search = Some(search);
+        self.preview = None;
+        self.message = None;
+        self.selected = 0;
+    }
+
+    fn clear_results(&mut self) {
+        self.search = None;
+        self.preview = None;
+        self.selected = 0;
+    }
+
+    /// The replace preview is built once the search has finished, so the
+    /// set of matches it covers cannot change under it
+    fn update_preview(&mut self) {
+        if self.preview.is_some() {
+            return;
+        }
+        if let (Some(search), Some(replacement)) = (self.search.as_ref(), &self.replacement) {
+            if search.is_done() {
+                self.preview = Some(if self.preserve_case {
+                    ReplacePreview::preserving_case(search.results(), replacement)
+                } else {
+                    ReplacePreview::new(search.results(), replacement)
+                });
+            }
+        }
+    }
+
+    /// Collect search results; returns whether anything changed
+    pub fn poll(&mut self) -> bool {
+        let changed = self.search.as_mut().is_some_and(ProjectSearch::poll);
+        self.update_preview();
+        changed
+    }
+
+    pub fn select_previous(&mut self) {
+        self.selected = self.selected.saturating_sub(1);
+    }
+
+    pub fn select_next(&mut self) {
+        let count = self.search.as_ref().map_or(0, ProjectSearch::match_count);
+        self.selected = (self.selected + 1).min(count.saturating_sub(1));
+    }
+
+    /// File and range of the highlighted match
+    pub fn selected_match(&self) -> Option<(PathBuf, BufferPosition, BufferPosition)> {
+        let (path, found) = self.search.as_ref()?.get(self.selected)?;
+        Some((path.to_path_buf(), found.start, found.end))
+    }
+
+    /// Include or exclude the highlighted change from the replacement
+    pub fn toggle_selected(&mut self) {
+        if let Some(preview) = self.preview.as_mut() {
+            preview.toggle(self.selected);
+            self.message = None;
+        }
+    }
+
+    /// Write the included changes; returns whether any file was changed
+    pub fn apply(&mut self) -> bool {
+        let Some(preview) = self.preview.as_ref() else {
+            return false;
+        };
+        match preview.apply() {
+            Ok(applied) => {
+                self.message = Some(format!(
+                    "Replaced {} matches in {} files",
+                    preview.included_count(),
+                    applied.files.len()
+                ));
+                let changed = !applied.files.is_empty();
+                self.applied = Some(applied);
+                changed
+            }
+            Err(err) => {
+                self.message = Some(format!("Nothing replaced: {}", err));
+                false
+            }
+        }
+    }
+
+    /// Undo the last replacement in the file of the highlighted change;
+    /// returns whether the file was restored
+    pub fn undo_selected_file(&mut self) -> bool {
+        let Some((path, _, _)) = self.selected_match() else {
+            return false;
+        };
+        let Some(applied) = self.applied.as_mut() else {
+            return false;
+        };
+        let name = path.file_name().unwrap_or_default().to_string_lossy();
+        match applied.undo_file(&path) {
+            Ok(()) => {
+                self.message = Some(format!("Restored {}", name));
+                true
+            }
+            Err(err) => {
+                self.message = Some(format!("Not restored: {}", err));
+                false
+            }
+        }
+    }
+
+    /// Summary for the panel's title, e.g. "12 matches in 3 files", with
+    /// the options that are on
+    pub fn status(&self) -> String {
+        if let Some(message) = &self.message {
+            return message.clone();
+        }
+        let mut status = self.summary();
+        let options: Vec<&str> = [
+            (self.whole_word, "whole word"),
+            (self.preserve_case, "preserve case"),
+        ]
+        .into_iter()
+        .filter_map(|(on, name)| on.then_some(name))
+        .collect();
+        if !options.is_empty() {
+            status.push_str(&format!(" ({})", options.join(", ")));
+        }
+        status
+    }
+
+    fn summary(&self) -> String {
+        match (self.search.as_ref(), self.preview.as_ref()) {
+            (None, _) => "Enter to search".to_string(),
+            (Some(_), Some(preview)) => format!(
+                "{} of {} matches selected",
+                preview.included_count(),
+                preview.hunk_count()
+            ),
+            (Some(search), None) => format!(
+                "{} matches in {} files{}",
+                search.match_count(),
+                search.results().len(),
+                if search.is_done() { "" } else { "…" }
+            ),
+        }
+    }
+
+    /// Results grouped by file: plain matches when finding, changes with
+    /// their replacement when replacing
+    pub fn rows(&self) -> Vec<SearchRow> {
+        let Some(search) = self.search.as_ref() else {
+            return Vec::new();
+        };
+        let relative = |path: &Path| {
+            path.strip_prefix(&search.root)
+                .unwrap_or(path)
+                .to_path_buf()
+        };
+
+        let mut rows = Vec::new();
+        let mut index = 0;
+        if let Some(preview) = self.preview.as_ref() {
+            for file in &preview.files {
+                rows.push(SearchRow::File(relative(&file.path)));
+                for hunk in &file.hunks {
+                    rows.push(SearchRow::Change {
+                        index,
+                        row: hunk.start.row,
+                        before: hunk.before.clone(),
+                        after: hunk.after.clone(),
+                        included: hunk.included,
+                    });
+                    index += 1;
+                }
+            }
+        } else {
+            for file in search.results() {
+                rows.push(SearchRow::File(relative(&file.path)));
+                for found in &file.matches {
+                    rows.push(SearchRow::Match {
+                        index,
+                        row: found.start.row,
+                        text: found.line.clone(),
+                    });
+                    index += 1;
+                }
+            }
+        }
+        rows
+    }
+}
@@ -0,0 +1,161 @@
+//! Markdown sections: a heading and everything under it, up to the next
+//! heading of the same or a higher level, so a section holds its
+//! subsections. Moving, promoting, demoting and selecting a section act on
+//! all of it.
+//!
+//! Headings are found as the outline finds them, so `#` lines in the
+//! frontmatter or in fenced code don't start sections.
+
+use crate::symbols;
+use std::ops::Range;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Section {
+    /// Row of the heading
+    pub row: usize,
+    /// Heading level, 1 to 6
+    pub level: usize,
+    /// Row after the section's last line, its subsections included
+    pub end: usize,
+    pub children: Vec<Section>,
+}
+
+impl Section {
+    pub fn rows(&self) -> Range<usize> {
+        self.row..self.end
+    }
+}
+
+/// The sections of `lines`, each holding those nested in it
+pub fn tree(lines: &[String]) -> Vec<Section> {
+    let headings: Vec<_> = symbols::headings(lines)
+        .into_iter()
+        .map(|heading| (heading.row, heading.depth + 1))
+        .collect();
+    build(&headings, &mut 0, 0, lines.len())
+}
+
+/// The sections from the heading at `next` on that are deeper than
+/// `above`, with their subsections, up to `last` at the most
+fn build(headings: &[(usize, usize)], next: &mut usize, above: usize, last: usize) -> Vec<Section> {
+    let mut sections = Vec::new();
+    while let Some(&(row, level)) = headings.get(*next).filter(|(_, level)| *level > above) {
+        *next += 1;
+        let children = build(headings, next, level, last);
+        // Whatever heading comes next is at this level or higher
+        let end = headings.get(*next).map_or(last, |&(row, _)| row);
+        sections.push(Section {
+            row,
+            level,
+            end,
+            children,
+        });
+    }
+    sections
+}
+
+/// The innermost section `row` is in
+pub fn at(sections: &[Section], row: usize) -> Option<&Section> {
+    family(sections, row).map(|(siblings, index)| &siblings[index])
+}
+
+/// The sections at the level of the innermost one `row` is in, and where
+/// it is among them
+fn family(sections: &[Section], row: usize) -> Option<(&[Section], usize)> {
+    let index = sections
+        .iter()
+        .position(|section| section.rows().contains(&row))?;
+    family(&sections[index].children, row).or(Some((sections, index)))
+}
+
+/// `lines` with the section `row` is in swapped with the one above or
+/// below it at the same level, and the row `row` moves to
+pub fn move_section(
+    lines: &[String],
+    row: usize,
+    down: bool,
+) -> Result<(Vec<String>, usize), &'static str> {
+    let sections = tree(lines);
+    let (siblings, index) = family(&sections, row).ok_or("Not in a section")?;
+    let (upper, lower) = if down {
+        let below = siblings
+            .get(index + 1)
+            .ok_or("No section below at this level")?;
+        (&siblings[index], below)
+    } else {
+        let above = index
+            .checked_sub(1)
+            .ok_or("No section above at this level")?;
+        (&siblings[above], &siblings[index])
+    };
+
+    // Blank lines stay where they were, so the gap between the two
+    // sections and after them is kept
+    let (upper_text, upper_gap) = without_blanks(&lines[upper.rows()]);
+    let (lower_text, lower_gap) = without_blanks(&lines[lower.rows()]);
+    let mut moved = lines[..upper.row].to_vec();
+    moved.extend_from_slice(lower_text);
+    moved.extend_from_slice(upper_gap);
+    moved.extend_from_slice(upper_text);
+    moved.extend_from_slice(lower_gap);
+    moved.extend_from_slice(&lines[lower.end..]);
+
+    let (from, text_rows, to) = if down {
+        (
+            upper.row,
+            upper_text.len(),
+            upper.row + lower_text.len() + upper_gap.len(),
+        )
+    } else {
+        (lower.row, lower_text.len(), upper.row)
+    };
+    let offset = (row - from).min(text_rows.saturating_sub(1));
+    Ok((moved, to + offset))
+}
+
+/// A section's lines before and after the blank ones at its end
+fn without_blanks(lines: &[String]) -> (&[String], &[String]) {
+    let text = lines
+        .iter()
+        .rposition(|line| !line.trim().is_empty())
+        .map_or(0, |last| last + 1);
+    lines.split_at(text)
+}
+
+/// `lines` with the heading of the section `row` is in, and those of its
+/// subsections, a level higher (`promote`) or lower
+pub fn shift_levels(
+    lines: &[String],
+    row: usize,
+    promote: bool,
+) -> Result<Vec<String>, &'static str> {
+    let sections = tree(lines);
+    let section = at(&sections, row).ok_or("Not in a section")?;
+    let mut headings = Vec::new();
+    collect(section, &mut headings);
+    if promote && section.level == 1 {
+        return Err("Already a top-level heading");
+    }
+    if !promote && headings.iter().any(|&(_, level)| level == 6) {
+        return Err("Headings go no deeper than ######");
+    }
+
+    let mut shifted = lines.to_vec();
+    for (row, _) in headings {
+        let line = &mut shifted[row];
+        let marks = line.len() - line.trim_start().len();
+        if promote {
+            line.remove(marks);
+        } else {
+            line.insert(marks, '#');
+        }
+    }
+    Ok(shifted)
+}
+
+fn collect(section: &Section, headings: &mut Vec<(usize, usize)>) {
+    headings.push((section.row, section.level));
+    for child in &section.children {
+        collect(child, headings);
+    }
+}
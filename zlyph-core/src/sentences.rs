@@ -0,0 +1,116 @@
+//! Sentences in prose, for moving and selecting by sentence and for focus
+//! mode's sentence scope
+//!
+//! A sentence ends at `.`, `!` or `?`, with any closing quotes, brackets or
+//! emphasis after it, where a space or the end of a line follows; a
+//! paragraph's end ends one too. A period after an initial such as `J.` or
+//! a common abbreviation such as `e.g.` or `Dr.` doesn't. Sentences don't
+//! run across blank lines, so each paragraph is looked at on its own.
+
+use crate::text_objects;
+use crate::BufferPosition;
+
+/// Characters that can follow a sentence's last punctuation and still be
+/// part of the sentence
+const CLOSERS: &[char] = &['"', '\'', ')', ']', '*', '_', '’', '”'];
+
+/// Abbreviations whose period doesn't end a sentence, lowercased and
+/// without their last period
+const ABBREVIATIONS: &[&str] = &[
+    "e.g", "i.e", "cf", "vs", "al", "mr", "mrs", "ms", "dr", "prof", "fig",
+];
+
+/// The sentences of the paragraph `row` is in, each from its first
+/// character up to the end of its last
+pub fn in_paragraph(lines: &[String], row: usize) -> Vec<(BufferPosition, BufferPosition)> {
+    let (first, last) = text_objects::paragraph_range(lines, row);
+    let mut sentences = Vec::new();
+    let mut start = None;
+    let mut end = first;
+    for (row, line) in (first.row..).zip(&lines[first.row..=last.row]) {
+        let mut chars = line.char_indices().peekable();
+        while let Some((column, c)) = chars.next() {
+            if c.is_whitespace() {
+                continue;
+            }
+            let sentence_start = *start.get_or_insert(BufferPosition::new(row, column));
+            let mut after = column + c.len_utf8();
+            if matches!(c, '.' | '!' | '?') {
+                while let Some(&(at, closer)) = chars.peek() {
+                    if !CLOSERS.contains(&closer) {
+                        break;
+                    }
+                    after = at + closer.len_utf8();
+                    chars.next();
+                }
+                let spaced = line[after..].chars().next().is_none_or(char::is_whitespace);
+                if spaced && !(c == '.' && abbreviation(&line[..column])) {
+                    sentences.push((sentence_start, BufferPosition::new(row, after)));
+                    start = None;
+                }
+            }
+            end = BufferPosition::new(row, after);
+        }
+    }
+    if let Some(start) = start {
+        sentences.push((start, end));
+    }
+    sentences
+}
+
+/// Whether the word `before` ends with is an initial or an abbreviation,
+/// so a period after it doesn't end the sentence
+fn abbreviation(before: &str) -> bool {
+    let word = before
+        .rsplit(char::is_whitespace)
+        .next()
+        .unwrap_or_default()
+        .trim_start_matches(['(', '[', '"', '\'', '*', '_']);
+    let mut chars = word.chars();
+    let initial = chars.next().is_some_and(char::is_uppercase) && chars.next().is_none();
+    initial || ABBREVIATIONS.contains(&word.to_lowercase().as_str())
+}
+
+/// The sentence at `at`, or the space after it; a blank line is a
+/// sentence of its own
+pub fn at(lines: &[String], at: BufferPosition) -> (BufferPosition, BufferPosition) {
+    let sentences = in_paragraph(lines, at.row);
+    sentences
+        .iter()
+        .rev()
+        .find(|(start, _)| *start <= at)
+        .or(sentences.first())
+        .copied()
+        .unwrap_or_else(|| text_objects::line_range(lines, at.row))
+}
+
+/// Where the first sentence after `at` starts, in this paragraph or a
+/// later one
+pub fn next_start(lines: &[String], at: BufferPosition) -> Option<BufferPosition> {
+    let mut row = at.row;
+    while row < lines.len() {
+        let sentences = in_paragraph(lines, row);
+        if let Some(&(start, _)) = sentences.iter().find(|(start, _)| *start > at) {
+            return Some(start);
+        }
+        row = text_objects::paragraph_range(lines, row).1.row + 1;
+    }
+    None
+}
+
+/// Where the sentence `at` is in starts if `at` is past its start, and
+/// otherwise where the one before it starts, in this paragraph or an
+/// earlier one
+pub fn prev_start(lines: &[String], at: BufferPosition) -> Option<BufferPosition> {
+    let mut row = at.row.min(lines.len().saturating_sub(1));
+    loop {
+        let sentences = in_paragraph(lines, row);
+        if let Some(&(start, _)) = sentences.iter().rev().find(|(start, _)| *start < at) {
+            return Some(start);
+        }
+        row = text_objects::paragraph_range(lines, row)
+            .0
+            .row
+            .checked_sub(1)?;
+    }
+}
@@ -0,0 +1,171 @@
+//! Shell commands whose output goes into the buffer: `r !date` inserts
+//! what a command prints at the cursor, and `!sort` pipes lines through a
+//! command and puts its output in their place, as in vi
+//!
+//! Commands run through `sh -c` (`cmd /C` on Windows) with no terminal
+//! attached, on background threads so a slow one doesn't hold up typing.
+//! [`EditorEngine::start_shell_command`](crate::EditorEngine::start_shell_command)
+//! starts one; the frontend calls
+//! [`EditorEngine::poll_shell_command`](crate::EditorEngine::poll_shell_command)
+//! from its event loop until it has finished, and Escape cancels it.
+
+use crate::BufferRange;
+use std::io::{self, Read, Write};
+use std::path::Path;
+use std::process::{Child, Command, ExitStatus, Stdio};
+use std::sync::mpsc::{self, Receiver};
+use std::thread;
+
+/// `command` run by the system's shell, `sh -c` or `cmd /C`
+pub fn process(command: &str) -> Command {
+    let mut process = if cfg!(windows) {
+        let mut process = Command::new("cmd");
+        process.arg("/C");
+        process
+    } else {
+        let mut process = Command::new("sh");
+        process.arg("-c");
+        process
+    };
+    process.arg(command);
+    process
+}
+
+/// Where a command's output goes once it has finished
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ShellTarget {
+    /// At the cursor, replacing the selection if there is one
+    Insert,
+    /// In place of this text, which was the command's input
+    Replace(BufferRange),
+}
+
+/// A command running in the background, with what it prints collected
+pub struct ShellCommand {
+    command: String,
+    child: Child,
+    stdout: Receiver<io::Result<Vec<u8>>>,
+    stderr: Receiver<Vec<u8>>,
+    output: Option<io::Result<Vec<u8>>>,
+    errors: Option<Vec<u8>>,
+    status: Option<ExitStatus>,
+}
+
+impl ShellCommand {
+    /// Start `command` in `dir`, with `input` on its standard input
+    pub fn spawn(command: &str, input: Option<String>, dir: Option<&Path>) -> io::Result<Self> {
+        let mut process = process(command);
+        process
+            .stdin(if input.is_some() {
+                Stdio::piped()
+            } else {
+                Stdio::null()
+            })
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped());
+        if let Some(dir) = dir.filter(|dir| dir.is_dir()) {
+            process.current_dir(dir);
+        }
+        let mut child = process.spawn()?;
+
+        // Writing and reading on threads of their own, so a command that
+        // prints before reading all its input can't stall on a full pipe
+        if let (Some(input), Some(mut stdin)) = (input, child.stdin.take()) {
+            thread::spawn(move || {
+                let _ = stdin.write_all(input.as_bytes());
+            });
+        }
+        let (stdout_sender, stdout) = mpsc::channel();
+        if let Some(mut pipe) = child.stdout.take() {
+            thread::spawn(move || {
+                let mut bytes = Vec::new();
+                let result = pipe.read_to_end(&mut bytes).map(|_| bytes);
+                let _ = stdout_sender.send(result);
+            });
+        }
+        let (stderr_sender, stderr) = mpsc::channel();
+        if let Some(mut pipe) = child.stderr.take() {
+            thread::spawn(move || {
+                let mut bytes = Vec::new();
+                let _ = pipe.read_to_end(&mut bytes);
+                let _ = stderr_sender.send(bytes);
+            });
+        }
+
+        Ok(Self {
+            command: command.to_string(),
+            child,
+            stdout,
+            stderr,
+            output: None,
+            errors: None,
+            status: None,
+        })
+    }
+
+    pub fn command(&self) -> &str {
+        &self.command
+    }
+
+    /// What the command printed once it has finished and closed its
+    /// output, or an error with the first line it printed to standard
+    /// error if it failed; None while it is still running
+    pub fn poll(&mut self) -> Option<io::Result<String>> {
+        if self.output.is_none() {
+            self.output = self.stdout.try_recv().ok();
+        }
+        if self.errors.is_none() {
+            self.errors = self.stderr.try_recv().ok();
+        }
+        if self.status.is_none() {
+            match self.child.try_wait() {
+                Ok(status) => self.status = status,
+                Err(e) => return Some(Err(e)),
+            }
+        }
+        if self.output.is_none() || self.errors.is_none() {
+            return None;
+        }
+        let status = self.status?;
+        let output = self.output.take()?;
+        let errors = self.errors.take().unwrap_or_default();
+        Some(output.and_then(|output| {
+            if status.success() {
+                return Ok(String::from_utf8_lossy(&output).into_owned());
+            }
+            let errors = String::from_utf8_lossy(&errors);
+            let reason = errors
+                .lines()
+                .find(|line| !line.trim().is_empty())
+                .map_or_else(|| status.to_string(), |line| line.trim().to_string());
+            Err(io::Error::other(format!(
+                "{} failed: {}",
+                self.command, reason
+            )))
+        }))
+    }
+
+    /// Wait for the command to finish; see [`ShellCommand::poll`]
+    pub fn wait(&mut self) -> io::Result<String> {
+        loop {
+            if let Some(result) = self.poll() {
+                return result;
+            }
+            thread::sleep(std::time::Duration::from_millis(5));
+        }
+    }
+
+    /// Stop the command
+    pub fn cancel(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+impl Drop for ShellCommand {
+    fn drop(&mut self) {
+        if self.status.is_none() {
+            self.cancel();
+        }
+    }
+}
@@ -0,0 +1,194 @@
+//! Snippet parsing and tab-stop tracking
+//!
+//! Snippet bodies use the familiar `$1`, `${1:placeholder}` and `$0` syntax.
+//! `$0` marks the final cursor position; without it the cursor ends up after
+//! the snippet. A literal dollar sign is written as `\$`.
+
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::ops::Range;
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct SnippetDefinition {
+    /// Word that expands into the snippet when followed by Tab
+    pub trigger: String,
+    pub body: String,
+    #[serde(default)]
+    pub description: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TabStop {
+    pub index: usize,
+    /// Byte range of the placeholder within the expanded text
+    pub range: Range<usize>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParsedSnippet {
+    pub text: String,
+    /// Tab stops in navigation order ($1, $2, ..., then $0)
+    pub tab_stops: Vec<TabStop>,
+}
+
+pub fn parse_snippet(body: &str) -> ParsedSnippet {
+    let mut text = String::new();
+    let mut tab_stops = Vec::new();
+    let mut chars = body.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' if chars.peek() == Some(&'$') => {
+                text.push('$');
+                chars.next();
+            }
+            '$' if chars.peek().is_some_and(|c| c.is_ascii_digit()) => {
+                let mut digits = String::new();
+                while let Some(d) = chars.peek().filter(|c| c.is_ascii_digit()) {
+                    digits.push(*d);
+                    chars.next();
+                }
+                let start = text.len();
+                tab_stops.push(TabStop {
+                    index: digits.parse().unwrap_or(0),
+                    range: start..start,
+                });
+            }
+            '$' if chars.peek() == Some(&'{') => {
+                chars.next();
+                let mut digits = String::new();
+                while let Some(d) = chars.peek().filter(|c| c.is_ascii_digit()) {
+                    digits.push(*d);
+                    chars.next();
+                }
+                let mut placeholder = String::new();
+                if chars.peek() == Some(&':') {
+                    chars.next();
+                }
+                for p in chars.by_ref() {
+                    if p == '}' {
+                        break;
+                    }
+                    placeholder.push(p);
+                }
+                let start = text.len();
+                text.push_str(&placeholder);
+                tab_stops.push(TabStop {
+                    index: digits.parse().unwrap_or(0),
+                    range: start..text.len(),
+                });
+            }
+            _ => text.push(c),
+        }
+    }
+
+    if !tab_stops.iter().any(|stop| stop.index == 0) {
+        tab_stops.push(TabStop {
+            index: 0,
+            range: text.len()..text.len(),
+        });
+    }
+
+    // Keep only the first occurrence of each index, ordered $1..$n then $0
+    let mut ordered: Vec<TabStop> = Vec::new();
+    for stop in tab_stops {
+        if !ordered.iter().any(|s| s.index == stop.index) {
+            ordered.push(stop);
+        }
+    }
+    ordered.sort_by_key(|stop| {
+        if stop.index == 0 {
+            usize::MAX
+        } else {
+            stop.index
+        }
+    });
+
+    ParsedSnippet {
+        text,
+        tab_stops: ordered,
+    }
+}
+
+/// A snippet that has been inserted and whose tab stops are still being visited
+#[derive(Debug, Clone)]
+pub struct ActiveSnippet {
+    /// Document byte ranges of the remaining tab stops
+    pub stops: Vec<Range<usize>>,
+    pub current: usize,
+}
+
+impl ActiveSnippet {
+    pub fn new(base_offset: usize, parsed: &ParsedSnippet) -> Self {
+        Self {
+            stops: parsed
+                .tab_stops
+                .iter()
+                .map(|stop| base_offset + stop.range.start..base_offset + stop.range.end)
+                .collect(),
+            current: 0,
+        }
+    }
+
+    pub fn current_range(&self) -> Range<usize> {
+        self.stops[self.current].clone()
+    }
+
+    pub fn is_on_last_stop(&self) -> bool {
+        self.current + 1 >= self.stops.len()
+    }
+
+    /// Adjust stop ranges after the document grew or shrank by `delta` bytes
+    /// inside the current stop
+    pub fn apply_edit_delta(&mut self, delta: isize) {
+        let shift = |offset: usize| offset.saturating_add_signed(delta);
+        let current = self.current;
+        let current_start = self.stops[current].start;
+        for (i, stop) in self.stops.iter_mut().enumerate() {
+            if i == current {
+                stop.end = shift(stop.end).max(stop.start);
+            } else if stop.start > current_start || (stop.start == current_start && i > current) {
+                *stop = shift(stop.start)..shift(stop.end);
+            }
+        }
+    }
+}
+
+/// The abbreviation ending at `column`, the longest if several do, and
+/// what it expands to
+pub fn find_abbreviation<'a>(
+    abbreviations: &'a BTreeMap<String, String>,
+    line: &str,
+    column: usize,
+) -> Option<(&'a str, &'a str)> {
+    let before = &line[..column];
+    abbreviations
+        .iter()
+        .filter(|(abbreviation, _)| {
+            !abbreviation.is_empty()
+                && before.ends_with(abbreviation.as_str())
+                && before[..before.len() - abbreviation.len()]
+                    .chars()
+                    .next_back()
+                    .is_none_or(|c| !crate::text_objects::is_word_char(c))
+        })
+        .max_by_key(|(abbreviation, _)| abbreviation.len())
+        .map(|(abbreviation, expansion)| (abbreviation.as_str(), expansion.as_str()))
+}
+
+/// Find the snippet whose trigger is the word ending right before `column`
+pub fn find_trigger<'a>(
+    snippets: &'a [SnippetDefinition],
+    line: &str,
+    column: usize,
+) -> Option<&'a SnippetDefinition> {
+    let before = &line[..column];
+    snippets.iter().find(|snippet| {
+        !snippet.trigger.is_empty()
+            && before.ends_with(&snippet.trigger)
+            && before[..before.len() - snippet.trigger.len()]
+                .chars()
+                .next_back()
+                .is_none_or(|c| c.is_whitespace() || !crate::text_objects::is_word_char(c))
+    })
+}
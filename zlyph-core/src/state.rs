@@ -1,6 +1,9 @@
 //! Platform-agnostic editor state
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
 pub struct BufferPosition {
     pub row: usize,
     pub column: usize,
@@ -16,7 +19,37 @@ impl BufferPosition {
     }
 }
 
-#[derive(Clone)]
+/// A span of the buffer from `start` up to but not including `end`, with
+/// `start` never after `end`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct BufferRange {
+    pub start: BufferPosition,
+    pub end: BufferPosition,
+}
+
+impl BufferRange {
+    /// The range between two positions given in either order
+    pub fn new(a: BufferPosition, b: BufferPosition) -> Self {
+        Self {
+            start: a.min(b),
+            end: a.max(b),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.start == self.end
+    }
+
+    pub fn contains(&self, pos: BufferPosition) -> bool {
+        self.start <= pos && pos < self.end
+    }
+}
+
+/// The text, cursor and selection, which serialize to JSON for dumping a
+/// session to disk, attaching to a bug report or sending to another process;
+/// [`EditorEngine::restore_state`](crate::EditorEngine::restore_state) puts
+/// one back
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct EditorState {
     /// Lines of text in the buffer
     pub lines: Vec<String>,
@@ -47,11 +80,6 @@ impl EditorState {
         }
     }
 
-    /// Get the content as a single string
-    pub fn to_string(&self) -> String {
-        self.lines.join("\n")
-    }
-
     /// Create from a string
     pub fn from_string(content: String) -> Self {
         let lines: Vec<String> = if content.is_empty() {
@@ -79,4 +107,55 @@ impl EditorState {
     pub fn line_len(&self, row: usize) -> usize {
         self.lines.get(row).map(|s| s.len()).unwrap_or(0)
     }
+
+    /// Length in bytes of the whole buffer, counting line breaks
+    pub fn text_len(&self) -> usize {
+        self.lines.iter().map(|l| l.len() + 1).sum::<usize>() - 1
+    }
+
+    /// Byte offset of a position within the joined buffer text
+    pub fn position_to_offset(&self, pos: BufferPosition) -> usize {
+        let row = pos.row.min(self.lines.len() - 1);
+        let before: usize = self.lines[..row].iter().map(|l| l.len() + 1).sum();
+        before + pos.column.min(self.lines[row].len())
+    }
+
+    /// Position of a byte offset within the joined buffer text, clamped to the end
+    pub fn offset_to_position(&self, offset: usize) -> BufferPosition {
+        let mut remaining = offset;
+        for (row, line) in self.lines.iter().enumerate() {
+            if remaining <= line.len() {
+                return BufferPosition::new(row, remaining);
+            }
+            remaining -= line.len() + 1;
+        }
+        let last_row = self.lines.len() - 1;
+        BufferPosition::new(last_row, self.lines[last_row].len())
+    }
+
+    /// The selected span, if anything is selected
+    pub fn selection(&self) -> Option<BufferRange> {
+        self.selection_anchor
+            .map(|anchor| BufferRange::new(anchor, self.cursor))
+    }
+
+    /// Text within `range`, clamped to the buffer
+    pub fn text_in(&self, range: BufferRange) -> String {
+        let start = self.position_to_offset(range.start);
+        let end = self.position_to_offset(range.end);
+        self.to_string()[start..end].to_string()
+    }
+}
+
+impl Default for EditorState {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl fmt::Display for EditorState {
+    /// The content as a single string, lines joined with newlines
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.lines.join("\n"))
+    }
 }
@@ -0,0 +1,177 @@
+//! The text's structure as a tree of nodes, found from its brackets and
+//! separators rather than by parsing a language, for selecting by syntax
+//!
+//! A group is the text between a matching pair of brackets, or the whole
+//! document. Its items are separated by commas, semicolons and line breaks
+//! inside brackets, and by line breaks alone outside them all, so prose
+//! splits into lines rather than clauses; brackets nested in an item are
+//! part of it. Going out from an identifier, the nodes are the item it's
+//! in (an expression or argument), the item of the block around that (a
+//! statement), the group's contents, the group with its brackets, and a
+//! block with the line that opens it, such as a function.
+
+use crate::brackets;
+use crate::BufferPosition;
+use std::collections::HashMap;
+
+/// A node's text, from its start up to but not including its end
+pub type Node = (BufferPosition, BufferPosition);
+
+/// The smallest node bigger than `start..end` that holds it, of those
+/// around it and the extra `candidates`, which don't have to hold it
+pub fn enclosing(
+    lines: &[String],
+    start: BufferPosition,
+    end: BufferPosition,
+    candidates: &[Node],
+) -> Option<Node> {
+    nodes(lines, start, end)
+        .into_iter()
+        .chain(candidates.iter().copied())
+        .filter(|&(s, e)| s <= start && e >= end && (s, e) != (start, end))
+        .min_by_key(|&node| size(lines, node))
+}
+
+/// The item after the one `start..end` is, or is in, at the same level
+pub fn next_sibling(lines: &[String], start: BufferPosition, end: BufferPosition) -> Option<Node> {
+    let pairs = brackets::pairs(lines);
+    let closers: HashMap<_, _> = pairs.iter().copied().collect();
+    let group = pairs
+        .iter()
+        .map(|&(open, close)| (after(open), close))
+        .filter(|&(from, to)| from <= start && to >= end)
+        .max_by_key(|&(from, _)| from);
+    let items = match group {
+        Some((from, to)) => items(lines, &closers, from, to, true),
+        None => items(
+            lines,
+            &closers,
+            BufferPosition::zero(),
+            document_end(lines),
+            false,
+        ),
+    };
+    match items.iter().position(|&(s, e)| s <= start && e >= end) {
+        Some(current) => items.get(current + 1).copied(),
+        None => items.into_iter().find(|&(s, _)| s >= end),
+    }
+}
+
+/// Every node holding `start..end`: the items, contents and brackets of the
+/// groups around it, and the blocks those brackets open
+fn nodes(lines: &[String], start: BufferPosition, end: BufferPosition) -> Vec<Node> {
+    let pairs = brackets::pairs(lines);
+    let closers: HashMap<_, _> = pairs.iter().copied().collect();
+    let mut nodes = Vec::new();
+    let mut groups = vec![(BufferPosition::zero(), document_end(lines), false)];
+    for &(open, close) in &pairs {
+        let whole = (open, after(close));
+        if whole.0 > start || whole.1 < end {
+            continue;
+        }
+        nodes.push(whole);
+        // A block opened at the end of a line goes with that line
+        let line = &lines[open.row];
+        if close.row > open.row && line[open.column + 1..].trim().is_empty() {
+            let indent = line.len() - line.trim_start().len();
+            nodes.push((BufferPosition::new(open.row, indent), whole.1));
+        }
+        if after(open) <= start && close >= end {
+            nodes.push((after(open), close));
+            groups.push((after(open), close, true));
+        }
+    }
+    for (from, to, bracketed) in groups {
+        nodes.extend(
+            items(lines, &closers, from, to, bracketed)
+                .into_iter()
+                .filter(|&(s, e)| s <= start && e >= end),
+        );
+    }
+    nodes
+}
+
+/// The items of the group `from..to`, trimmed of space, skipping over the
+/// groups nested in it
+fn items(
+    lines: &[String],
+    closers: &HashMap<BufferPosition, BufferPosition>,
+    from: BufferPosition,
+    to: BufferPosition,
+    bracketed: bool,
+) -> Vec<Node> {
+    let mut items = Vec::new();
+    let mut item_start = from;
+    let mut skip_to = from;
+    for row in from.row..=to.row.min(lines.len().saturating_sub(1)) {
+        let line = &lines[row];
+        for (column, c) in brackets::outside_strings(line) {
+            let at = BufferPosition::new(row, column);
+            if at < skip_to || at < from {
+                continue;
+            }
+            if at >= to {
+                break;
+            }
+            if let Some(&close) = closers.get(&at) {
+                skip_to = after(close);
+            } else if bracketed && matches!(c, b',' | b';') {
+                items.extend(trim(lines, item_start, at));
+                item_start = after(at);
+            }
+        }
+        let line_end = BufferPosition::new(row, line.len());
+        if row < to.row && line_end >= skip_to {
+            items.extend(trim(lines, item_start, line_end));
+            item_start = BufferPosition::new(row + 1, 0);
+        }
+    }
+    items.extend(trim(lines, item_start, to));
+    items
+}
+
+/// `start..end` without the space and line breaks at either end, unless
+/// that's all it is
+fn trim(lines: &[String], mut start: BufferPosition, mut end: BufferPosition) -> Option<Node> {
+    while start < end {
+        let rest = &lines[start.row][start.column..];
+        match rest.chars().next() {
+            None => start = BufferPosition::new(start.row + 1, 0),
+            Some(c) if c.is_whitespace() => start.column += c.len_utf8(),
+            Some(_) => break,
+        }
+    }
+    while end > start {
+        if end.column == 0 {
+            end = BufferPosition::new(end.row - 1, lines[end.row - 1].len());
+            continue;
+        }
+        match lines[end.row][..end.column].chars().next_back() {
+            Some(c) if c.is_whitespace() => end.column -= c.len_utf8(),
+            _ => break,
+        }
+    }
+    (start < end).then_some((start, end))
+}
+
+/// The position after the bracket or separator at `at`
+fn after(at: BufferPosition) -> BufferPosition {
+    BufferPosition::new(at.row, at.column + 1)
+}
+
+fn document_end(lines: &[String]) -> BufferPosition {
+    let row = lines.len().saturating_sub(1);
+    BufferPosition::new(row, lines.get(row).map_or(0, String::len))
+}
+
+/// How many bytes `node` spans, counting a byte for each line break
+fn size(lines: &[String], (start, end): Node) -> usize {
+    if start.row == end.row {
+        return end.column.saturating_sub(start.column);
+    }
+    let between: usize = lines[start.row + 1..end.row]
+        .iter()
+        .map(|line| line.len() + 1)
+        .sum();
+    lines[start.row].len() - start.column + 1 + between + end.column
+}
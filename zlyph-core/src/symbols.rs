@@ -0,0 +1,299 @@
+//! The outline of a buffer: its functions, types and headings, for the
+//! symbols sidebar, the go-to-symbol palette and the breadcrumb trail
+//!
+//! A language server's `textDocument/documentSymbol` answer is used when one
+//! is running for the file. Otherwise [`outline`] finds definitions line by
+//! line: `fn`, `struct`, `impl` and the like in Rust, `def` and `class` in
+//! Python, `function`, `class`, `interface` and `type` in JavaScript and
+//! TypeScript, `func` and `type` in Go, and headings in anything else.
+
+use crate::completion;
+use crate::frontmatter;
+use regex::Regex;
+use std::path::Path;
+use std::sync::OnceLock;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SymbolKind {
+    Heading,
+    Module,
+    Type,
+    Function,
+    Constant,
+    Other,
+}
+
+impl SymbolKind {
+    /// A short tag shown beside the name
+    pub fn label(self) -> &'static str {
+        match self {
+            SymbolKind::Heading => "#",
+            SymbolKind::Module => "mod",
+            SymbolKind::Type => "type",
+            SymbolKind::Function => "fn",
+            SymbolKind::Constant => "const",
+            SymbolKind::Other => "·",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Symbol {
+    pub name: String,
+    pub kind: SymbolKind,
+    pub row: usize,
+    /// Byte column of the name
+    pub column: usize,
+    /// How deeply it is nested, 0 at the top level
+    pub depth: usize,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Syntax {
+    Rust,
+    Python,
+    Script,
+    Go,
+    Headings,
+}
+
+impl Syntax {
+    fn for_path(path: Option<&Path>) -> Self {
+        let extension = path
+            .and_then(Path::extension)
+            .and_then(|extension| extension.to_str())
+            .map(str::to_ascii_lowercase);
+        match extension.as_deref() {
+            Some("rs") => Syntax::Rust,
+            Some("py" | "pyw") => Syntax::Python,
+            Some("js" | "jsx" | "mjs" | "cjs" | "ts" | "tsx" | "mts" | "cts") => Syntax::Script,
+            Some("go") => Syntax::Go,
+            _ => Syntax::Headings,
+        }
+    }
+
+    /// Definitions as indent, keyword and name
+    fn pattern(self) -> &'static Regex {
+        static PATTERNS: OnceLock<[Regex; 4]> = OnceLock::new();
+        let patterns = PATTERNS.get_or_init(|| {
+            [
+                r#"^(\s*)(?:pub(?:\([^)]*\))?\s+)?(?:(?:const|async|unsafe|extern\s+"[^"]*")\s+)*(fn|struct|enum|union|trait|type|mod|const|static|macro_rules!)\s*([A-Za-z_][A-Za-z0-9_]*)"#,
+                r"^(\s*)(?:async\s+)?(def|class)\s+([A-Za-z_][A-Za-z0-9_]*)",
+                r"^(\s*)(?:export\s+)?(?:default\s+)?(?:declare\s+)?(?:abstract\s+)?(?:async\s+)?(function\*?|class|interface|type|enum)\s+([A-Za-z_$][A-Za-z0-9_$]*)",
+                r"^()(func|type)\s+(?:\([^)]*\)\s*)?([A-Za-z_][A-Za-z0-9_]*)",
+            ]
+            .map(|pattern| Regex::new(pattern).unwrap())
+        });
+        match self {
+            Syntax::Rust => &patterns[0],
+            Syntax::Python => &patterns[1],
+            Syntax::Script => &patterns[2],
+            Syntax::Go | Syntax::Headings => &patterns[3],
+        }
+    }
+}
+
+fn rust_impl() -> &'static Regex {
+    static PATTERN: OnceLock<Regex> = OnceLock::new();
+    PATTERN.get_or_init(|| {
+        Regex::new(r"^(\s*)(?:unsafe\s+)?impl(?:<[^>]*>)?\s+([^{]*?)\s*(?:where\b.*)?\{?\s*$")
+            .unwrap()
+    })
+}
+
+fn keyword_kind(keyword: &str) -> SymbolKind {
+    match keyword {
+        "fn" | "def" | "func" | "macro_rules!" => SymbolKind::Function,
+        "mod" => SymbolKind::Module,
+        "const" | "static" => SymbolKind::Constant,
+        keyword if keyword.starts_with("function") => SymbolKind::Function,
+        _ => SymbolKind::Type,
+    }
+}
+
+/// Nesting level of an indent, counting a tab as four spaces
+fn depth(indent: &str) -> usize {
+    indent
+        .chars()
+        .map(|c| if c == '\t' { 4 } else { 1 })
+        .sum::<usize>()
+        / 4
+}
+
+/// The definitions or headings of `lines`, in order, read as the file at
+/// `path` is written
+pub fn outline(lines: &[String], path: Option<&Path>) -> Vec<Symbol> {
+    let syntax = Syntax::for_path(path);
+    if syntax == Syntax::Headings {
+        return headings(lines);
+    }
+    let pattern = syntax.pattern();
+    let mut symbols = Vec::new();
+    for (row, line) in lines.iter().enumerate() {
+        if let Some(captures) = pattern.captures(line) {
+            let name = captures.get(3).expect("name group");
+            symbols.push(Symbol {
+                name: name.as_str().to_string(),
+                kind: keyword_kind(&captures[2]),
+                row,
+                column: name.start(),
+                depth: depth(&captures[1]),
+            });
+        } else if let Some(captures) = (syntax == Syntax::Rust)
+            .then(|| rust_impl().captures(line))
+            .flatten()
+        {
+            let name = captures.get(2).expect("name group");
+            if name.as_str().is_empty() {
+                continue;
+            }
+            symbols.push(Symbol {
+                name: format!("impl {}", name.as_str()),
+                kind: SymbolKind::Type,
+                row,
+                column: name.start(),
+                depth: depth(&captures[1]),
+            });
+        }
+    }
+    symbols
+}
+
+/// `#` headings outside the frontmatter and fenced code, nested by level
+pub(crate) fn headings(lines: &[String]) -> Vec<Symbol> {
+    let body = frontmatter::block_rows(lines).map_or(0, |rows| rows.end);
+    let mut symbols = Vec::new();
+    let mut fence: Option<&str> = None;
+    for (row, line) in lines.iter().enumerate().skip(body) {
+        let trimmed = line.trim_start();
+        if let Some(marker) = ["```", "~~~"].into_iter().find(|m| trimmed.starts_with(m)) {
+            match fence {
+                None => fence = Some(marker),
+                Some(open) if open == marker => fence = None,
+                Some(_) => {}
+            }
+            continue;
+        }
+        if fence.is_some() || line.len() - trimmed.len() > 3 {
+            continue;
+        }
+        let level = trimmed.bytes().take_while(|&b| b == b'#').count();
+        let rest = &trimmed[level..];
+        if !(1..=6).contains(&level) || !(rest.is_empty() || rest.starts_with([' ', '\t'])) {
+            continue;
+        }
+        let name = rest.trim().trim_end_matches('#').trim_end();
+        if name.is_empty() {
+            continue;
+        }
+        symbols.push(Symbol {
+            name: name.to_string(),
+            kind: SymbolKind::Heading,
+            row,
+            column: line.len() - rest.trim_start().len(),
+            depth: level - 1,
+        });
+    }
+    symbols
+}
+
+/// The symbols `row` is in, outermost first: the last one starting at or
+/// before it and each less nested one above that, as a breadcrumb trail
+pub fn breadcrumbs(symbols: &[Symbol], row: usize) -> Vec<&Symbol> {
+    let mut trail: Vec<&Symbol> = Vec::new();
+    for symbol in symbols.iter().take_while(|symbol| symbol.row <= row) {
+        while trail.last().is_some_and(|last| last.depth >= symbol.depth) {
+            trail.pop();
+        }
+        trail.push(symbol);
+    }
+    trail
+}
+
+/// A buffer's symbols as both frontends list them, narrowed by a fuzzy
+/// query
+#[derive(Debug, Clone, Default)]
+pub struct SymbolPanel {
+    pub query: String,
+    /// Index into [`SymbolPanel::matches`]
+    pub selected: usize,
+    symbols: Vec<Symbol>,
+}
+
+impl SymbolPanel {
+    pub fn new(symbols: Vec<Symbol>) -> Self {
+        Self {
+            query: String::new(),
+            selected: 0,
+            symbols,
+        }
+    }
+
+    /// Replace the symbols, as when the buffer changed, keeping the query
+    pub fn set_symbols(&mut self, symbols: Vec<Symbol>) {
+        self.symbols = symbols;
+        self.selected = self.selected.min(self.matches().len().saturating_sub(1));
+    }
+
+    /// Every symbol in order without a query; with one, those whose name
+    /// has its characters in order, best match first
+    pub fn matches(&self) -> Vec<&Symbol> {
+        let query = self.query.trim();
+        if query.is_empty() {
+            return self.symbols.iter().collect();
+        }
+        let mut scored: Vec<(i64, &Symbol)> = self
+            .symbols
+            .iter()
+            .filter_map(|symbol| {
+                // An exact name isn't offered as a completion, but is the
+                // best symbol to jump to
+                let score = if symbol.name.eq_ignore_ascii_case(query) {
+                    Some(i64::MAX)
+                } else {
+                    completion::fuzzy_score(&symbol.name, query)
+                };
+                score.map(|score| (score, symbol))
+            })
+            .collect();
+        scored.sort_by(|a, b| b.0.cmp(&a.0).then_with(|| a.1.row.cmp(&b.1.row)));
+        scored.into_iter().map(|(_, symbol)| symbol).collect()
+    }
+
+    /// Index into the unfiltered list of the symbol `row` is in: the last
+    /// one starting at or before it
+    pub fn containing(&self, row: usize) -> Option<usize> {
+        self.symbols.iter().rposition(|symbol| symbol.row <= row)
+    }
+
+    pub fn type_text(&mut self, text: &str) {
+        self.query.push_str(text);
+        self.selected = 0;
+    }
+
+    pub fn backspace(&mut self) {
+        self.query.pop();
+        self.selected = 0;
+    }
+
+    pub fn select_previous(&mut self) {
+        self.selected = self.selected.saturating_sub(1);
+    }
+
+    pub fn select_next(&mut self) {
+        self.selected = (self.selected + 1).min(self.matches().len().saturating_sub(1));
+    }
+
+    pub fn selected_symbol(&self) -> Option<&Symbol> {
+        self.matches().get(self.selected).copied()
+    }
+
+    /// A title for the list, such as "3 of 12 symbols"
+    pub fn status(&self) -> String {
+        match (self.matches().len(), self.symbols.len()) {
+            (_, 0) => "No symbols in this file".to_string(),
+            (shown, total) if shown == total => format!("{} symbols", total),
+            (shown, total) => format!("{} of {} symbols", shown, total),
+        }
+    }
+}
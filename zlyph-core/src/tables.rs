@@ -0,0 +1,330 @@
+//! Markdown tables: finding the one around a line, and laying it out again
+//! with its pipes lined up
+//!
+//! A table is a run of lines with pipes whose second line is the delimiter
+//! row, `| --- | :-: |`, as in GitHub's Markdown. Widths are counted in
+//! terminal columns, so wide characters such as CJK and emoji take two.
+
+use crate::markdown;
+use crate::BufferPosition;
+use std::ops::Range;
+use unicode_width::UnicodeWidthStr;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Alignment {
+    None,
+    Left,
+    Center,
+    Right,
+}
+
+/// A cell of a table and a byte offset into its text
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CellPosition {
+    /// 0 for the header, then the body rows
+    pub row: usize,
+    pub column: usize,
+    pub offset: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Table {
+    /// Buffer rows of the header, delimiter and body
+    pub rows: Range<usize>,
+    /// Whitespace before the header, kept on every line
+    pub indent: String,
+    pub alignments: Vec<Alignment>,
+    /// The trimmed text of each cell, header first; the delimiter row has
+    /// no cells
+    pub cells: Vec<Vec<String>>,
+}
+
+/// Byte ranges of the cells of a table line, between its pipes; `\|` is a
+/// pipe inside a cell
+pub fn cell_ranges(line: &str) -> Vec<Range<usize>> {
+    let mut pipes = Vec::new();
+    let mut escaped = false;
+    for (i, b) in line.bytes().enumerate() {
+        if escaped {
+            escaped = false;
+        } else if b == b'\\' {
+            escaped = true;
+        } else if b == b'|' {
+            pipes.push(i);
+        }
+    }
+    let start = line.len() - line.trim_start().len();
+    let end = line.trim_end().len();
+    let leading = pipes.first() == Some(&start);
+    let trailing = pipes.len() > usize::from(leading) && pipes.last() == Some(&(end - 1));
+
+    let mut bounds = vec![if leading { start + 1 } else { start }];
+    let inner = &pipes[usize::from(leading)..pipes.len() - usize::from(trailing)];
+    for &pipe in inner {
+        bounds.push(pipe);
+        bounds.push(pipe + 1);
+    }
+    bounds.push(if trailing {
+        end - 1
+    } else {
+        end.max(bounds[0])
+    });
+    bounds.chunks(2).map(|pair| pair[0]..pair[1]).collect()
+}
+
+fn cells(line: &str) -> Vec<String> {
+    cell_ranges(line)
+        .into_iter()
+        .map(|range| line[range].trim().to_string())
+        .collect()
+}
+
+/// The alignments of a delimiter row such as `|:---|--:|`
+fn delimiter(line: &str) -> Option<Vec<Alignment>> {
+    if !line.contains('|') {
+        return None;
+    }
+    cells(line)
+        .iter()
+        .map(|cell| {
+            let dashes = cell.trim_start_matches(':').trim_end_matches(':');
+            if dashes.is_empty() || !dashes.bytes().all(|b| b == b'-') {
+                return None;
+            }
+            Some(match (cell.starts_with(':'), cell.ends_with(':')) {
+                (true, true) => Alignment::Center,
+                (true, false) => Alignment::Left,
+                (false, true) => Alignment::Right,
+                (false, false) => Alignment::None,
+            })
+        })
+        .collect()
+}
+
+fn is_table_line(line: &str) -> bool {
+    !line.trim().is_empty() && line.contains('|')
+}
+
+/// The table `row` is part of, if any
+pub fn table_at(lines: &[String], row: usize) -> Option<Table> {
+    if !lines.get(row).is_some_and(|line| is_table_line(line)) {
+        return None;
+    }
+    let mut start = row;
+    while start > 0 && is_table_line(&lines[start - 1]) {
+        start -= 1;
+    }
+    let mut end = row + 1;
+    while end < lines.len() && is_table_line(&lines[end]) {
+        end += 1;
+    }
+    // Lines with pipes just above the header aren't part of the table
+    let header = (start..end.saturating_sub(1))
+        .take_while(|&header| header <= row)
+        .find(|&header| delimiter(&lines[header + 1]).is_some())?;
+    let alignments = delimiter(&lines[header + 1])?;
+    if markdown::in_code_block(lines, row) {
+        return None;
+    }
+
+    let header_line = &lines[header];
+    let mut table = Table {
+        rows: header..end,
+        indent: header_line[..header_line.len() - header_line.trim_start().len()].to_string(),
+        alignments,
+        cells: std::iter::once(header)
+            .chain(header + 2..end)
+            .map(|row| cells(&lines[row]))
+            .collect(),
+    };
+    let columns = table.columns();
+    table.alignments.resize(columns, Alignment::None);
+    for row in &mut table.cells {
+        row.resize(columns, String::new());
+    }
+    Some(table)
+}
+
+impl Table {
+    pub fn columns(&self) -> usize {
+        self.cells
+            .iter()
+            .map(Vec::len)
+            .chain([self.alignments.len()])
+            .max()
+            .unwrap_or(0)
+    }
+
+    /// The header and body rows
+    pub fn row_count(&self) -> usize {
+        self.cells.len()
+    }
+
+    fn buffer_row(&self, row: usize) -> usize {
+        self.rows.start + if row == 0 { 0 } else { row + 1 }
+    }
+
+    /// The cell at a buffer position, found in `lines` as they were when
+    /// the table was read; the delimiter row counts as the header
+    pub fn cell_at(&self, lines: &[String], position: BufferPosition) -> CellPosition {
+        let row = position
+            .row
+            .saturating_sub(self.rows.start)
+            .saturating_sub(1);
+        let line = &lines[position.row];
+        let ranges = cell_ranges(line);
+        let column = ranges
+            .iter()
+            .position(|range| position.column <= range.end)
+            .unwrap_or(ranges.len().saturating_sub(1));
+        let offset = ranges.get(column).map_or(0, |range| {
+            let text = &line[range.clone()];
+            let start = range.start + text.len() - text.trim_start().len();
+            position.column.saturating_sub(start)
+        });
+        let offset = if position.row == self.rows.start + 1 {
+            0
+        } else {
+            offset
+        };
+        CellPosition {
+            row,
+            column,
+            offset,
+        }
+    }
+
+    /// Keep spaces typed at the end of `cell`'s text, before the cursor,
+    /// which reading the table trimmed
+    pub fn keep_typed_spaces(&mut self, lines: &[String], cell: CellPosition) {
+        let line = &lines[self.buffer_row(cell.row)];
+        let Some(range) = cell_ranges(line).get(cell.column).cloned() else {
+            return;
+        };
+        let text = line[range].trim_start();
+        if cell.offset > text.trim_end().len() {
+            self.cells[cell.row][cell.column] = text[..cell.offset.min(text.len())].to_string();
+        }
+    }
+
+    /// Each column's width: its widest cell, and at least three
+    fn widths(&self) -> Vec<usize> {
+        (0..self.columns())
+            .map(|column| {
+                self.cells
+                    .iter()
+                    .map(|row| row[column].width())
+                    .max()
+                    .unwrap_or(0)
+                    .max(3)
+            })
+            .collect()
+    }
+
+    /// The table's lines with every column padded to its widest cell
+    pub fn render(&self) -> Vec<String> {
+        let widths = self.widths();
+        let line = |cells: Vec<String>| {
+            let mut line = format!("{}|", self.indent);
+            for cell in cells {
+                line.push(' ');
+                line.push_str(&cell);
+                line.push_str(" |");
+            }
+            line
+        };
+        let row = |cells: &Vec<String>| {
+            line(
+                cells
+                    .iter()
+                    .zip(&widths)
+                    .zip(&self.alignments)
+                    .map(|((cell, &width), &alignment)| pad(cell, width, alignment))
+                    .collect(),
+            )
+        };
+        let delimiter = line(
+            widths
+                .iter()
+                .zip(&self.alignments)
+                .map(|(&width, &alignment)| {
+                    let (left, right) = match alignment {
+                        Alignment::None => ("-", "-"),
+                        Alignment::Left => (":", "-"),
+                        Alignment::Center => (":", ":"),
+                        Alignment::Right => ("-", ":"),
+                    };
+                    format!("{}{}{}", left, "-".repeat(width - 2), right)
+                })
+                .collect(),
+        );
+        std::iter::once(row(&self.cells[0]))
+            .chain([delimiter])
+            .chain(self.cells[1..].iter().map(row))
+            .collect()
+    }
+
+    /// Where `cell` is once the table is rendered, with its offset kept
+    /// inside the cell's text
+    pub fn position_of(&self, cell: CellPosition) -> BufferPosition {
+        let row = cell.row.min(self.row_count() - 1);
+        let column = cell.column.min(self.columns() - 1);
+        let line = &self.render()[if row == 0 { 0 } else { row + 1 }];
+        let range = cell_ranges(line)[column].clone();
+        let text = &line[range.clone()];
+        // An empty cell's text starts after the space following its pipe
+        let start = if text.trim().is_empty() {
+            range.start + 1
+        } else {
+            range.start + text.len() - text.trim_start().len()
+        };
+        let offset = cell.offset.min(self.cells[row][column].len());
+        BufferPosition::new(self.buffer_row(row), start + offset)
+    }
+
+    /// Add an empty body row before `at`, which is at least 1
+    pub fn insert_row(&mut self, at: usize) {
+        let at = at.clamp(1, self.row_count());
+        self.cells.insert(at, vec![String::new(); self.columns()]);
+    }
+
+    /// Remove a body row; the header stays
+    pub fn remove_row(&mut self, at: usize) -> bool {
+        if at == 0 || at >= self.row_count() {
+            return false;
+        }
+        self.cells.remove(at);
+        true
+    }
+
+    /// Add an empty, unaligned column before `at`
+    pub fn insert_column(&mut self, at: usize) {
+        let at = at.min(self.columns());
+        self.alignments.insert(at, Alignment::None);
+        for row in &mut self.cells {
+            row.insert(at, String::new());
+        }
+    }
+
+    /// Remove a column, unless it is the only one
+    pub fn remove_column(&mut self, at: usize) -> bool {
+        if self.columns() < 2 || at >= self.columns() {
+            return false;
+        }
+        self.alignments.remove(at);
+        for row in &mut self.cells {
+            row.remove(at);
+        }
+        true
+    }
+}
+
+fn pad(cell: &str, width: usize, alignment: Alignment) -> String {
+    let space = width.saturating_sub(cell.width());
+    let left = match alignment {
+        Alignment::Right => space,
+        Alignment::Center => space / 2,
+        Alignment::None | Alignment::Left => 0,
+    };
+    format!("{}{}{}", " ".repeat(left), cell, " ".repeat(space - left))
+}
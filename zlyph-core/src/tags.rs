@@ -0,0 +1,248 @@
+//! `#tags` in notes: finding them in a line, counting them across the notes
+//! directory in the background, and finding every place one is used
+//!
+//! A tag is `#` followed by letters, digits, `_`, `-` or `/`, with at least
+//! one letter, at the start of a line or after a space or `(`: `#reading`
+//! and `#project/zlyph` are tags, `# Heading`, `#12`, `a#b` and URL
+//! fragments are not. Tags in code spans and fenced code are ignored, and
+//! tags differing only in case are the same tag. The items of a note's
+//! frontmatter `tags` field are tags too, written with or without `#`.
+
+use crate::frontmatter;
+use crate::notes;
+use crate::BufferPosition;
+use std::collections::HashMap;
+use std::fs;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Receiver, TryRecvError};
+use std::thread;
+
+/// A `#tag` in a line
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Tag {
+    /// Where the tag is written, `#` included
+    pub range: Range<usize>,
+    /// The tag without its `#`
+    pub name: String,
+}
+
+fn is_tag_char(c: char) -> bool {
+    c.is_alphanumeric() || matches!(c, '_' | '-' | '/')
+}
+
+/// Every tag in `line`, in order
+pub fn tags(line: &str) -> Vec<Tag> {
+    let mut tags = Vec::new();
+    let mut in_code = false;
+    let mut previous = None;
+    for (start, c) in line.char_indices() {
+        let follows_space = previous.is_none_or(|p: char| p.is_whitespace() || p == '(');
+        previous = Some(c);
+        if c == '`' {
+            in_code = !in_code;
+        }
+        if c != '#' || in_code || !follows_space {
+            continue;
+        }
+        let rest = &line[start + 1..];
+        let len = rest.find(|c| !is_tag_char(c)).unwrap_or(rest.len());
+        let name = rest[..len].trim_end_matches(['-', '/']);
+        if name.chars().any(char::is_alphabetic) {
+            tags.push(Tag {
+                range: start..start + 1 + name.len(),
+                name: name.to_string(),
+            });
+        }
+    }
+    tags
+}
+
+/// Each line of a note with the tags on it, skipping fenced code. In the
+/// frontmatter only the items of its `tags` field are tags.
+fn tagged_lines<S: AsRef<str>>(lines: &[S]) -> Vec<(usize, Vec<Tag>)> {
+    let mut tagged: Vec<(usize, Vec<Tag>)> = Vec::new();
+    let mut body = 0;
+    if let Some(frontmatter) = frontmatter::parse(lines) {
+        body = frontmatter.rows.end;
+        for (row, span) in frontmatter
+            .field("tags")
+            .map(|field| field.spans.clone())
+            .unwrap_or_default()
+        {
+            let written = &lines[row].as_ref()[span.clone()];
+            // Text rather than a list holds its tags separated by commas
+            let mut at = 0;
+            for piece in written.split(',') {
+                let name = piece.trim().trim_start_matches('#');
+                let start = span.start + at + piece.find(name).unwrap_or(0);
+                at += piece.len() + 1;
+                if name.is_empty() {
+                    continue;
+                }
+                let tag = Tag {
+                    range: start..start + name.len(),
+                    name: name.to_string(),
+                };
+                match tagged.last_mut() {
+                    Some((last, tags)) if *last == row => tags.push(tag),
+                    _ => tagged.push((row, vec![tag])),
+                }
+            }
+        }
+    }
+    let mut fence: Option<&str> = None;
+    for (row, line) in lines.iter().enumerate().skip(body) {
+        let trimmed = line.as_ref().trim_start();
+        let marker = ["```", "~~~"]
+            .into_iter()
+            .find(|marker| trimmed.starts_with(marker));
+        match (fence, marker) {
+            (None, Some(marker)) => {
+                fence = Some(marker);
+                continue;
+            }
+            (Some(open), Some(marker)) if open == marker => {
+                fence = None;
+                continue;
+            }
+            (Some(_), _) => continue,
+            (None, None) => {}
+        }
+        let tags = tags(line.as_ref());
+        if !tags.is_empty() {
+            tagged.push((row, tags));
+        }
+    }
+    tagged
+}
+
+/// Where `tag` is used in `lines`, as ranges of the buffer
+pub fn find_tag(lines: &[String], tag: &str) -> Vec<(BufferPosition, BufferPosition)> {
+    let tag = tag.trim_start_matches('#').to_lowercase();
+    tagged_lines(lines)
+        .into_iter()
+        .flat_map(|(row, tags)| {
+            tags.into_iter()
+                .filter(|found| found.name.to_lowercase() == tag)
+                .map(move |found| {
+                    (
+                        BufferPosition::new(row, found.range.start),
+                        BufferPosition::new(row, found.range.end),
+                    )
+                })
+        })
+        .collect()
+}
+
+/// How often a tag is used across the notes
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TagCount {
+    /// The tag as first written
+    pub name: String,
+    pub count: usize,
+    /// How many notes use it
+    pub files: usize,
+}
+
+/// Every tag used in `files`, most used first
+pub fn count_tags(files: &[PathBuf]) -> Vec<TagCount> {
+    let mut counts: HashMap<String, TagCount> = HashMap::new();
+    for path in files {
+        let Ok(content) = fs::read_to_string(path) else {
+            continue;
+        };
+        let mut seen = Vec::new();
+        let lines: Vec<&str> = content.lines().collect();
+        for (_, tags) in tagged_lines(&lines) {
+            for tag in tags {
+                let key = tag.name.to_lowercase();
+                let entry = counts.entry(key.clone()).or_insert_with(|| TagCount {
+                    name: tag.name,
+                    count: 0,
+                    files: 0,
+                });
+                entry.count += 1;
+                if !seen.contains(&key) {
+                    entry.files += 1;
+                    seen.push(key);
+                }
+            }
+        }
+    }
+    let mut counts: Vec<TagCount> = counts.into_values().collect();
+    counts.sort_by(|a, b| {
+        b.count
+            .cmp(&a.count)
+            .then_with(|| a.name.to_lowercase().cmp(&b.name.to_lowercase()))
+    });
+    counts
+}
+
+/// The tags of a notes directory, counted on a background thread, and the
+/// list both frontends show of them
+pub struct TagIndex {
+    pub dir: PathBuf,
+    /// Index into [`TagIndex::tags`]
+    pub selected: usize,
+    tags: Vec<TagCount>,
+    receiver: Option<Receiver<Vec<TagCount>>>,
+}
+
+impl TagIndex {
+    /// Start counting the tags in the notes below `dir`
+    pub fn start(dir: &Path) -> Self {
+        let (sender, receiver) = mpsc::channel();
+        let walk_dir = dir.to_path_buf();
+        thread::spawn(move || {
+            let _ = sender.send(count_tags(&notes::note_files(&walk_dir)));
+        });
+        Self {
+            dir: dir.to_path_buf(),
+            selected: 0,
+            tags: Vec::new(),
+            receiver: Some(receiver),
+        }
+    }
+
+    /// Collect the counts once they are ready; returns whether they arrived
+    pub fn poll(&mut self) -> bool {
+        let Some(receiver) = self.receiver.as_ref() else {
+            return false;
+        };
+        match receiver.try_recv() {
+            Ok(tags) => self.tags = tags,
+            Err(TryRecvError::Empty) => return false,
+            Err(TryRecvError::Disconnected) => {}
+        }
+        self.receiver = None;
+        true
+    }
+
+    /// Block until the tags are counted
+    pub fn wait(&mut self) {
+        if let Some(receiver) = self.receiver.take() {
+            self.tags = receiver.recv().unwrap_or_default();
+        }
+    }
+
+    pub fn is_done(&self) -> bool {
+        self.receiver.is_none()
+    }
+
+    pub fn tags(&self) -> &[TagCount] {
+        &self.tags
+    }
+
+    pub fn select_previous(&mut self) {
+        self.selected = self.selected.saturating_sub(1);
+    }
+
+    pub fn select_next(&mut self) {
+        self.selected = (self.selected + 1).min(self.tags.len().saturating_sub(1));
+    }
+
+    pub fn selected_tag(&self) -> Option<&TagCount> {
+        self.tags.get(self.selected)
+    }
+}
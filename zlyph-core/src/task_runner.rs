@@ -0,0 +1,455 @@
+//! Build and lint tasks such as `cargo check` or `npm test`, run in the
+//! workspace with the errors they print gathered into a list to jump through
+//!
+//! Tasks come from `[[tasks]]` entries in the config; without any, they are
+//! guessed from the project's files, e.g. `cargo check` beside a
+//! `Cargo.toml`. Output is read as compilers print it: rustc's `error:`
+//! line followed by `--> file:line:column`, `file:line:column: message` as
+//! gcc, clang, Go and most linters write it, and `file(line,column): error`
+//! as tsc does. Problems in the open file are also shown in the buffer, as
+//! diagnostics from the `build` source.
+
+use crate::diagnostics::{Diagnostic, Severity};
+use crate::shell;
+use crate::BufferPosition;
+use regex::Regex;
+use serde::Deserialize;
+use std::io::{self, BufRead, BufReader, Read};
+use std::path::{Path, PathBuf};
+use std::process::{Child, ExitStatus, Stdio};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::OnceLock;
+use std::thread;
+
+/// Source name of the diagnostics published for problems in the open file
+pub const DIAGNOSTIC_SOURCE: &str = "build";
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct TaskDefinition {
+    pub name: String,
+    /// Run by the system's shell in the project root; see [`project_root`]
+    pub command: String,
+}
+
+impl TaskDefinition {
+    pub fn new(name: &str, command: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            command: command.to_string(),
+        }
+    }
+}
+
+/// Files that mark the root of a project tasks can be guessed for
+const PROJECT_FILES: [&str; 4] = ["Cargo.toml", "package.json", "go.mod", "Makefile"];
+
+/// Where tasks for `file` run: the outermost directory in its repository
+/// holding a project file such as `Cargo.toml`, so a Cargo workspace's
+/// paths resolve, or the repository itself; outside a repository, the
+/// nearest directory holding one, or the file's own
+pub fn project_root(file: &Path) -> PathBuf {
+    let dir = file.parent().unwrap_or(Path::new("."));
+    let marked = |dir: &&Path| PROJECT_FILES.iter().any(|name| dir.join(name).is_file());
+    let root = match dir
+        .ancestors()
+        .find(|ancestor| ancestor.join(".git").exists())
+    {
+        Some(repository) => dir
+            .ancestors()
+            .take_while(|ancestor| ancestor.starts_with(repository))
+            .filter(marked)
+            .last()
+            .unwrap_or(repository),
+        None => dir.ancestors().find(marked).unwrap_or(dir),
+    };
+    root.to_path_buf()
+}
+
+/// The configured tasks, or ones guessed from the files in `root`
+pub fn tasks_for(configured: &[TaskDefinition], root: &Path) -> Vec<TaskDefinition> {
+    if !configured.is_empty() {
+        return configured.to_vec();
+    }
+    let mut tasks = Vec::new();
+    if root.join("Cargo.toml").is_file() {
+        tasks.push(TaskDefinition::new("check", "cargo check --all-targets"));
+        tasks.push(TaskDefinition::new(
+            "clippy",
+            "cargo clippy --all-targets -- -D warnings",
+        ));
+        tasks.push(TaskDefinition::new("test", "cargo test"));
+    }
+    if root.join("package.json").is_file() {
+        tasks.push(TaskDefinition::new("test", "npm test"));
+        tasks.push(TaskDefinition::new("lint", "npm run lint"));
+    }
+    if root.join("go.mod").is_file() {
+        tasks.push(TaskDefinition::new("vet", "go vet ./..."));
+        tasks.push(TaskDefinition::new("test", "go test ./..."));
+    }
+    if root.join("Makefile").is_file() {
+        tasks.push(TaskDefinition::new("make", "make"));
+    }
+    tasks
+}
+
+/// An error or warning a task printed
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Problem {
+    pub path: PathBuf,
+    pub row: usize,
+    /// Character column, counted from zero
+    pub column: usize,
+    pub severity: Severity,
+    pub message: String,
+}
+
+impl Problem {
+    /// Byte column of the problem on `line`
+    pub fn byte_column(&self, line: &str) -> usize {
+        line.char_indices()
+            .nth(self.column)
+            .map_or(line.len(), |(index, _)| index)
+    }
+}
+
+fn patterns() -> &'static [Regex; 4] {
+    static PATTERNS: OnceLock<[Regex; 4]> = OnceLock::new();
+    PATTERNS.get_or_init(|| {
+        [
+            // rustc's heading, whose location follows on an arrow line
+            Regex::new(r"^(error|warning)(?:\[\w+\])?: (.+)$").unwrap(),
+            Regex::new(r"^\s*--> (.+?):(\d+):(\d+)$").unwrap(),
+            // gcc, clang, Go, eslint's unix format, flake8, mypy
+            Regex::new(
+                r"^([^\s:(][^:(]*):(\d+)(?::(\d+))?:\s*(?:(fatal error|error|warning|note|info|hint)\s*:?\s*)?(.+)$",
+            )
+            .unwrap(),
+            // tsc
+            Regex::new(r"^([^\s(][^(]*)\((\d+),(\d+)\): (error|warning) (.+)$").unwrap(),
+        ]
+    })
+}
+
+fn severity(label: Option<&str>) -> Severity {
+    match label {
+        Some("warning") => Severity::Warning,
+        Some("note" | "info") => Severity::Information,
+        Some("hint") => Severity::Hint,
+        _ => Severity::Error,
+    }
+}
+
+/// The problems in a task's `output`, with paths taken from `root`. Lines
+/// naming a file that doesn't exist, such as timestamps, are skipped.
+pub fn parse(output: &str, root: &Path) -> Vec<Problem> {
+    let [heading, arrow, unix, tsc] = patterns();
+    let mut problems = Vec::new();
+    let mut pending: Option<(Severity, String)> = None;
+    let mut push = |path: &str, row: &str, column: Option<&str>, severity, message: &str| {
+        let path = root.join(path);
+        if !path.is_file() {
+            return;
+        }
+        let number = |text: Option<&str>| {
+            text.and_then(|text| text.parse::<usize>().ok())
+                .unwrap_or(1)
+                .saturating_sub(1)
+        };
+        problems.push(Problem {
+            path: path.canonicalize().unwrap_or(path),
+            row: number(Some(row)),
+            column: number(column),
+            severity,
+            message: message.trim().to_string(),
+        });
+    };
+    for line in output.lines() {
+        let line = strip_escapes(line);
+        let line = line.trim_end();
+        if let Some(captures) = heading.captures(line) {
+            pending = Some((severity(Some(&captures[1])), captures[2].to_string()));
+        } else if let Some(captures) = arrow.captures(line) {
+            if let Some((severity, message)) = pending.take() {
+                push(
+                    &captures[1],
+                    &captures[2],
+                    Some(&captures[3]),
+                    severity,
+                    &message,
+                );
+            }
+        } else if let Some(captures) = tsc.captures(line) {
+            let severity = severity(Some(&captures[4]));
+            push(
+                &captures[1],
+                &captures[2],
+                Some(&captures[3]),
+                severity,
+                &captures[5],
+            );
+        } else if let Some(captures) = unix.captures(line) {
+            let column = captures.get(3).map(|m| m.as_str());
+            let severity = severity(captures.get(4).map(|m| m.as_str()));
+            push(&captures[1], &captures[2], column, severity, &captures[5]);
+        }
+    }
+    problems
+}
+
+/// `line` without the color codes tools print even to a pipe
+fn strip_escapes(line: &str) -> String {
+    let mut text = String::with_capacity(line.len());
+    let mut chars = line.chars();
+    while let Some(c) = chars.next() {
+        if c != '\x1b' {
+            text.push(c);
+            continue;
+        }
+        if chars.next() == Some('[') {
+            for c in chars.by_ref() {
+                if c.is_ascii_alphabetic() {
+                    break;
+                }
+            }
+        }
+    }
+    text
+}
+
+/// Diagnostics for the problems in `path`, whose text is `lines`, each
+/// covering the word it points at
+pub fn diagnostics<S: AsRef<str>>(
+    problems: &[Problem],
+    path: &Path,
+    lines: &[S],
+) -> Vec<Diagnostic> {
+    let path = path.canonicalize().unwrap_or_else(|_| path.to_path_buf());
+    problems
+        .iter()
+        .filter(|problem| problem.path == path)
+        .filter_map(|problem| {
+            let line = lines.get(problem.row)?.as_ref();
+            let start = problem.byte_column(line);
+            let word = line[start..]
+                .find(|c: char| !(c.is_alphanumeric() || c == '_'))
+                .unwrap_or(line.len() - start);
+            let end = if word > 0 {
+                start + word
+            } else {
+                line[start..]
+                    .chars()
+                    .next()
+                    .map_or(start, |c| start + c.len_utf8())
+            };
+            Some(Diagnostic {
+                start: BufferPosition::new(problem.row, start),
+                end: BufferPosition::new(problem.row, end),
+                severity: problem.severity,
+                message: problem.message.clone(),
+                source: DIAGNOSTIC_SOURCE.to_string(),
+                replacements: Vec::new(),
+            })
+        })
+        .collect()
+}
+
+/// A task running in the background, or finished, and the problems it has
+/// printed so far, with one selected
+pub struct TaskRun {
+    pub task: TaskDefinition,
+    /// Index into [`TaskRun::problems`]
+    pub selected: usize,
+    /// Whether the selected problem has been jumped to, so stepping moves on
+    visited: bool,
+    root: PathBuf,
+    child: Child,
+    lines: Receiver<String>,
+    output: String,
+    problems: Vec<Problem>,
+    status: Option<ExitStatus>,
+}
+
+impl TaskRun {
+    /// Start `task` in `root`
+    pub fn start(task: &TaskDefinition, root: &Path) -> io::Result<Self> {
+        let mut process = shell::process(&task.command);
+        process
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped());
+        if root.is_dir() {
+            process.current_dir(root);
+        }
+        let mut child = process.spawn()?;
+        let (sender, lines) = mpsc::channel();
+        if let Some(pipe) = child.stdout.take() {
+            read_lines(pipe, sender.clone());
+        }
+        if let Some(pipe) = child.stderr.take() {
+            read_lines(pipe, sender);
+        }
+        Ok(Self {
+            task: task.clone(),
+            selected: 0,
+            visited: false,
+            root: root.to_path_buf(),
+            child,
+            lines,
+            output: String::new(),
+            problems: Vec::new(),
+            status: None,
+        })
+    }
+
+    /// Gather what the task has printed since the last call; returns
+    /// whether there was anything, or it has just finished
+    pub fn poll(&mut self) -> bool {
+        let mut changed = false;
+        for line in self.lines.try_iter() {
+            self.output.push_str(&line);
+            self.output.push('\n');
+            changed = true;
+        }
+        if changed {
+            self.problems = parse(&self.output, &self.root);
+        }
+        if self.status.is_none() {
+            if let Ok(Some(status)) = self.child.try_wait() {
+                self.status = Some(status);
+                changed = true;
+            }
+        }
+        changed
+    }
+
+    /// Block until the task has finished and everything it printed is read
+    pub fn wait(&mut self) {
+        if let Ok(status) = self.child.wait() {
+            self.status = Some(status);
+        }
+        for line in self.lines.iter() {
+            self.output.push_str(&line);
+            self.output.push('\n');
+        }
+        self.problems = parse(&self.output, &self.root);
+    }
+
+    pub fn is_done(&self) -> bool {
+        self.status.is_some()
+    }
+
+    /// Where the task runs and its paths start
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// Everything the task has printed
+    pub fn output(&self) -> &str {
+        &self.output
+    }
+
+    pub fn problems(&self) -> &[Problem] {
+        &self.problems
+    }
+
+    pub fn select_previous(&mut self) {
+        self.selected = self.selected.saturating_sub(1);
+    }
+
+    pub fn select_next(&mut self) {
+        self.selected = (self.selected + 1).min(self.problems.len().saturating_sub(1));
+    }
+
+    pub fn selected_problem(&self) -> Option<&Problem> {
+        self.problems.get(self.selected)
+    }
+
+    /// The selected problem, to jump to
+    pub fn open_selected(&mut self) -> Option<Problem> {
+        self.visited = true;
+        self.selected_problem().cloned()
+    }
+
+    /// Select and return the problem after the one last jumped to,
+    /// wrapping to the first, or with `back` the one before; the first
+    /// step goes to the first problem, or with `back` the last
+    pub fn step(&mut self, back: bool) -> Option<Problem> {
+        let count = self.problems.len();
+        if count == 0 {
+            return None;
+        }
+        self.selected = match (self.visited, back) {
+            (false, false) => 0,
+            (false, true) => count - 1,
+            (true, false) => (self.selected + 1) % count,
+            (true, true) => (self.selected + count - 1) % count,
+        };
+        self.open_selected()
+    }
+
+    /// A title for the list, such as "check: 2 errors, 1 warning"
+    pub fn status(&self) -> String {
+        let name = &self.task.name;
+        let count = |severity| {
+            self.problems
+                .iter()
+                .filter(|problem| problem.severity == severity)
+                .count()
+        };
+        let plural = |count: usize, word: &str| match count {
+            1 => format!("1 {}", word),
+            count => format!("{} {}s", count, word),
+        };
+        let (errors, warnings) = (count(Severity::Error), count(Severity::Warning));
+        let found = match (errors, warnings) {
+            (0, 0) => None,
+            (errors, 0) => Some(plural(errors, "error")),
+            (0, warnings) => Some(plural(warnings, "warning")),
+            (errors, warnings) => Some(format!(
+                "{}, {}",
+                plural(errors, "error"),
+                plural(warnings, "warning")
+            )),
+        };
+        match (self.status, found) {
+            (None, None) => format!("Running {}…", name),
+            (None, Some(found)) => format!("Running {}… {}", name, found),
+            (Some(_), Some(found)) => format!("{}: {}", name, found),
+            (Some(status), None) if status.success() => format!("{} passed", name),
+            (Some(status), None) => {
+                let last = self
+                    .output
+                    .lines()
+                    .rev()
+                    .find(|line| !line.trim().is_empty());
+                match last {
+                    Some(line) => format!("{} failed: {}", name, strip_escapes(line).trim()),
+                    None => format!("{} failed ({})", name, status),
+                }
+            }
+        }
+    }
+}
+
+impl Drop for TaskRun {
+    fn drop(&mut self) {
+        if self.status.is_none() {
+            let _ = self.child.kill();
+            let _ = self.child.wait();
+        }
+    }
+}
+
+fn read_lines(pipe: impl Read + Send + 'static, sender: Sender<String>) {
+    thread::spawn(move || {
+        for line in BufReader::new(pipe).lines() {
+            let Ok(line) = line else {
+                break;
+            };
+            if sender.send(line).is_err() {
+                break;
+            }
+        }
+    });
+}
@@ -0,0 +1,156 @@
+//! Templates for new notes: files in `~/.config/zlyph/templates` whose
+//! `{{variables}}` are filled in when a note is created from one
+//!
+//! `{{date}}` and `{{time}}` take an optional strftime format after a colon,
+//! as in `{{date:%d %B}}`; `{{title}}` is the new file's name without its
+//! extension and `{{clipboard}}` the clipboard's text. Anything else in
+//! braces is left as written.
+
+use crate::config;
+use chrono::format::{Item, StrftimeItems};
+use chrono::{Local, NaiveDate, NaiveDateTime, NaiveTime};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// What a template's variables are filled in with
+#[derive(Debug, Clone)]
+pub struct Variables {
+    pub date: NaiveDate,
+    pub time: NaiveTime,
+    pub title: String,
+    pub clipboard: String,
+}
+
+impl Variables {
+    /// The current date and time, and the title of the note at `path`
+    pub fn for_note(path: &Path, clipboard: String) -> Self {
+        let now = Local::now().naive_local();
+        Self {
+            date: now.date(),
+            time: now.time(),
+            title: title_of(path),
+            clipboard,
+        }
+    }
+}
+
+/// The name `{{title}}` gives a note at `path`
+pub fn title_of(path: &Path) -> String {
+    path.file_stem()
+        .map(|stem| stem.to_string_lossy().into_owned())
+        .unwrap_or_default()
+}
+
+/// `date` and `time` in a strftime format, or None if the format is bad,
+/// which chrono would panic on
+pub fn format_datetime(date: NaiveDate, time: NaiveTime, format: &str) -> Option<String> {
+    if StrftimeItems::new(format).any(|item| item == Item::Error) {
+        return None;
+    }
+    Some(NaiveDateTime::new(date, time).format(format).to_string())
+}
+
+/// `template` with its variables filled in
+pub fn render(template: &str, vars: &Variables) -> String {
+    let mut out = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(open) = rest.find("{{") {
+        let Some(close) = rest[open + 2..].find("}}").map(|i| open + 2 + i) else {
+            break;
+        };
+        out.push_str(&rest[..open]);
+        let inner = &rest[open + 2..close];
+        let (name, format) = match inner.split_once(':') {
+            Some((name, format)) => (name.trim(), Some(format)),
+            None => (inner.trim(), None),
+        };
+        let value = match (name, format) {
+            ("date", format) => format_datetime(vars.date, vars.time, format.unwrap_or("%Y-%m-%d")),
+            ("time", format) => format_datetime(vars.date, vars.time, format.unwrap_or("%H:%M")),
+            ("title", None) => Some(vars.title.clone()),
+            ("clipboard", None) => Some(vars.clipboard.clone()),
+            _ => None,
+        };
+        match value {
+            Some(value) => out.push_str(&value),
+            None => out.push_str(&rest[open..close + 2]),
+        }
+        rest = &rest[close + 2..];
+    }
+    out.push_str(rest);
+    out
+}
+
+pub fn default_dir() -> PathBuf {
+    config::config_dir().join("templates")
+}
+
+/// The templates in `dir`, by name
+pub fn list(dir: &Path) -> Vec<PathBuf> {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    let mut templates: Vec<PathBuf> = entries
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| path.is_file() && !title_of(path).starts_with('.'))
+        .collect();
+    templates.sort_by_key(|path| title_of(path).to_lowercase());
+    templates
+}
+
+/// The template in `dir` called `name`, with or without its extension
+pub fn find(dir: &Path, name: &str) -> Option<PathBuf> {
+    list(dir)
+        .into_iter()
+        .find(|path| path.file_name().is_some_and(|file| file == name) || title_of(path) == name)
+}
+
+/// Create the note at `path` from `template`, unless it already exists
+pub fn create(path: &Path, template: &Path, clipboard: String) -> io::Result<()> {
+    if path.exists() {
+        return Ok(());
+    }
+    let text = render(
+        &fs::read_to_string(template)?,
+        &Variables::for_note(path, clipboard),
+    );
+    if let Some(parent) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(path, text)
+}
+
+/// The list of templates both frontends show when creating a note
+#[derive(Debug, Clone, Default)]
+pub struct TemplatePicker {
+    /// Index into [`TemplatePicker::templates`]
+    pub selected: usize,
+    templates: Vec<PathBuf>,
+}
+
+impl TemplatePicker {
+    pub fn new(templates: Vec<PathBuf>) -> Self {
+        Self {
+            selected: 0,
+            templates,
+        }
+    }
+
+    pub fn templates(&self) -> &[PathBuf] {
+        &self.templates
+    }
+
+    pub fn select_previous(&mut self) {
+        self.selected = self.selected.saturating_sub(1);
+    }
+
+    pub fn select_next(&mut self) {
+        self.selected = (self.selected + 1).min(self.templates.len().saturating_sub(1));
+    }
+
+    pub fn selected_template(&self) -> Option<&Path> {
+        self.templates.get(self.selected).map(PathBuf::as_path)
+    }
+}
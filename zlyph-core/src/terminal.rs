@@ -0,0 +1,303 @@
+//! A shell in a pseudo-terminal, for the terminal panel below the editor
+//!
+//! The shell's output is read on a background thread and fed to a VT100
+//! screen when the frontend calls [`Terminal::poll`] from its event loop.
+//! Frontends draw [`Terminal::lines`] with the cursor at
+//! [`Terminal::cursor`], turn keys into bytes with [`Terminal::send_key`]
+//! and keep the screen the size of the panel with [`Terminal::resize`].
+//! Text is drawn without the program's colors.
+
+use crate::config::TerminalConfig;
+use portable_pty::{native_pty_system, Child, CommandBuilder, MasterPty, PtySize};
+use std::io::{self, Read, Write};
+use std::path::Path;
+use std::sync::mpsc::{self, Receiver};
+use std::thread;
+
+/// A key typed while the panel has focus
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TerminalKey {
+    Char(char),
+    Enter,
+    Backspace,
+    Tab,
+    BackTab,
+    Escape,
+    Up,
+    Down,
+    Left,
+    Right,
+    Home,
+    End,
+    PageUp,
+    PageDown,
+    Insert,
+    Delete,
+    /// F1 to F12
+    F(u8),
+}
+
+/// The bytes a terminal sends for `key`, with Control and Alt as held.
+/// Arrows are sent in the form programs ask for with `application_cursor`.
+pub fn key_bytes(key: TerminalKey, ctrl: bool, alt: bool, application_cursor: bool) -> Vec<u8> {
+    let arrow = |letter: &str| {
+        if application_cursor {
+            format!("\x1bO{letter}")
+        } else {
+            format!("\x1b[{letter}")
+        }
+    };
+    let sequence = match key {
+        TerminalKey::Char(c) if ctrl => match c.to_ascii_lowercase() {
+            letter @ 'a'..='z' => char::from(letter as u8 & 0x1f).to_string(),
+            '@' | ' ' | '2' => "\0".to_string(),
+            '[' | '3' => "\x1b".to_string(),
+            '\\' | '4' => "\x1c".to_string(),
+            ']' | '5' => "\x1d".to_string(),
+            '^' | '6' => "\x1e".to_string(),
+            '_' | '-' | '7' => "\x1f".to_string(),
+            '?' | '8' => "\x7f".to_string(),
+            other => other.to_string(),
+        },
+        TerminalKey::Char(c) => c.to_string(),
+        TerminalKey::Enter => "\r".to_string(),
+        TerminalKey::Backspace => "\x7f".to_string(),
+        TerminalKey::Tab => "\t".to_string(),
+        TerminalKey::BackTab => "\x1b[Z".to_string(),
+        TerminalKey::Escape => "\x1b".to_string(),
+        TerminalKey::Up => arrow("A"),
+        TerminalKey::Down => arrow("B"),
+        TerminalKey::Right => arrow("C"),
+        TerminalKey::Left => arrow("D"),
+        TerminalKey::Home => arrow("H"),
+        TerminalKey::End => arrow("F"),
+        TerminalKey::PageUp => "\x1b[5~".to_string(),
+        TerminalKey::PageDown => "\x1b[6~".to_string(),
+        TerminalKey::Insert => "\x1b[2~".to_string(),
+        TerminalKey::Delete => "\x1b[3~".to_string(),
+        TerminalKey::F(n) => match n {
+            1 => "\x1bOP".to_string(),
+            2 => "\x1bOQ".to_string(),
+            3 => "\x1bOR".to_string(),
+            4 => "\x1bOS".to_string(),
+            5 => "\x1b[15~".to_string(),
+            6..=10 => format!("\x1b[{}~", n + 11),
+            11 | 12 => format!("\x1b[{}~", n + 12),
+            _ => String::new(),
+        },
+    };
+    let mut bytes = Vec::with_capacity(sequence.len() + 1);
+    if alt && !sequence.is_empty() {
+        bytes.push(0x1b);
+    }
+    bytes.extend_from_slice(sequence.as_bytes());
+    bytes
+}
+
+/// A shell running in a pseudo-terminal, and the screen it draws on
+pub struct Terminal {
+    screen: vt100::Parser,
+    master: Box<dyn MasterPty + Send>,
+    writer: Box<dyn Write + Send>,
+    child: Box<dyn Child + Send + Sync>,
+    output: Receiver<Vec<u8>>,
+    /// The shell's exit code once it has finished
+    exit_code: Option<u32>,
+}
+
+impl Terminal {
+    /// Start the configured shell in `cwd` on a screen `rows` by `cols`
+    pub fn spawn(
+        config: &TerminalConfig,
+        cwd: Option<&Path>,
+        rows: u16,
+        cols: u16,
+    ) -> io::Result<Self> {
+        let (rows, cols) = (rows.max(1), cols.max(1));
+        let pair = native_pty_system()
+            .openpty(size(rows, cols))
+            .map_err(pty_error)?;
+        let mut command = match &config.shell {
+            Some(shell) => {
+                let mut command = CommandBuilder::new(shell);
+                command.args(&config.args);
+                command
+            }
+            None => CommandBuilder::new_default_prog(),
+        };
+        command.env("TERM", "xterm-256color");
+        if let Some(cwd) = cwd.filter(|cwd| cwd.is_dir()) {
+            command.cwd(cwd);
+        }
+        let child = pair.slave.spawn_command(command).map_err(pty_error)?;
+        // The shell holds its own end; keeping ours would leave the reader
+        // waiting after the shell exits
+        drop(pair.slave);
+
+        let mut reader = pair.master.try_clone_reader().map_err(pty_error)?;
+        let writer = pair.master.take_writer().map_err(pty_error)?;
+        let (sender, output) = mpsc::channel();
+        thread::spawn(move || {
+            let mut buffer = [0; 8192];
+            loop {
+                match reader.read(&mut buffer) {
+                    Ok(0) | Err(_) => break,
+                    Ok(read) => {
+                        if sender.send(buffer[..read].to_vec()).is_err() {
+                            break;
+                        }
+                    }
+                }
+            }
+        });
+
+        Ok(Self {
+            screen: vt100::Parser::new(rows, cols, config.scrollback),
+            master: pair.master,
+            writer,
+            child,
+            output,
+            exit_code: None,
+        })
+    }
+
+    /// Draw what the shell has written since the last call; true if the
+    /// screen changed or the shell has just exited
+    pub fn poll(&mut self) -> bool {
+        let mut changed = false;
+        for bytes in self.output.try_iter() {
+            self.screen.process(&bytes);
+            changed = true;
+        }
+        if self.exit_code.is_none() {
+            if let Ok(Some(status)) = self.child.try_wait() {
+                self.exit_code = Some(status.exit_code());
+                changed = true;
+            }
+        }
+        changed
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.exit_code.is_none()
+    }
+
+    /// The shell's exit code once it has finished
+    pub fn exit_code(&self) -> Option<u32> {
+        self.exit_code
+    }
+
+    pub fn write(&mut self, bytes: &[u8]) -> io::Result<()> {
+        self.writer.write_all(bytes)?;
+        self.writer.flush()
+    }
+
+    pub fn send_key(&mut self, key: TerminalKey, ctrl: bool, alt: bool) -> io::Result<()> {
+        let application_cursor = self.screen.screen().application_cursor();
+        self.write(&key_bytes(key, ctrl, alt, application_cursor))
+    }
+
+    /// Type `text` as pasted, marked as a paste if the program asked for
+    /// that so it isn't run line by line
+    pub fn paste(&mut self, text: &str) -> io::Result<()> {
+        let text = text.replace("\r\n", "\r").replace('\n', "\r");
+        if self.screen.screen().bracketed_paste() {
+            self.write(format!("\x1b[200~{text}\x1b[201~").as_bytes())
+        } else {
+            self.write(text.as_bytes())
+        }
+    }
+
+    /// Run each line of `text` as a command, as if typed and followed by
+    /// Enter
+    pub fn run(&mut self, text: &str) -> io::Result<()> {
+        let mut input = String::new();
+        for line in text.lines() {
+            input.push_str(line);
+            input.push('\r');
+        }
+        self.write(input.as_bytes())
+    }
+
+    /// Rows and columns of the screen
+    pub fn size(&self) -> (u16, u16) {
+        self.screen.screen().size()
+    }
+
+    /// Fit the screen to a panel `rows` by `cols`, telling the shell
+    pub fn resize(&mut self, rows: u16, cols: u16) {
+        let (rows, cols) = (rows.max(1), cols.max(1));
+        if self.size() == (rows, cols) {
+            return;
+        }
+        self.screen.screen_mut().set_size(rows, cols);
+        let _ = self.master.resize(size(rows, cols));
+    }
+
+    /// The rows of the screen as text
+    pub fn lines(&self) -> Vec<String> {
+        let (_, cols) = self.size();
+        self.screen.screen().rows(0, cols).collect()
+    }
+
+    /// Row and column of the cursor on the screen, None while the program
+    /// hides it
+    pub fn cursor(&self) -> Option<(u16, u16)> {
+        let screen = self.screen.screen();
+        (!screen.hide_cursor()).then(|| screen.cursor_position())
+    }
+
+    /// Everything the shell has written that is still kept: the lines
+    /// scrolled off the top followed by the screen, without the blank rows
+    /// below the last output
+    pub fn capture(&mut self) -> String {
+        let (rows, cols) = self.size();
+        let screen = self.screen.screen_mut();
+        screen.set_scrollback(usize::MAX);
+        let mut offset = screen.scrollback();
+        let mut lines = Vec::new();
+        // The view shows `rows` lines starting `offset` lines above the
+        // screen; page down through the scrollback a screenful at a time
+        while offset > 0 {
+            screen.set_scrollback(offset);
+            let taken = offset.min(rows as usize);
+            lines.extend(screen.rows(0, cols).take(taken));
+            offset -= taken;
+        }
+        screen.set_scrollback(0);
+        lines.extend(screen.rows(0, cols));
+        while lines.last().is_some_and(|line| line.trim().is_empty()) {
+            lines.pop();
+        }
+        let mut text = lines
+            .iter()
+            .map(|line| line.trim_end())
+            .collect::<Vec<_>>()
+            .join("\n");
+        if !text.is_empty() {
+            text.push('\n');
+        }
+        text
+    }
+}
+
+impl Drop for Terminal {
+    fn drop(&mut self) {
+        if self.exit_code.is_none() {
+            let _ = self.child.kill();
+        }
+    }
+}
+
+fn size(rows: u16, cols: u16) -> PtySize {
+    PtySize {
+        rows,
+        cols,
+        pixel_width: 0,
+        pixel_height: 0,
+    }
+}
+
+fn pty_error(error: impl std::fmt::Display) -> io::Error {
+    io::Error::other(error.to_string())
+}
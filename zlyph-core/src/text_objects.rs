@@ -0,0 +1,79 @@
+//! Text object boundaries (word, line, paragraph, document) used by selection commands
+
+use crate::BufferPosition;
+
+pub fn is_word_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+/// Range of the word touching `pos`, preferring the word to the right of the cursor
+pub fn word_range(
+    lines: &[String],
+    pos: BufferPosition,
+) -> Option<(BufferPosition, BufferPosition)> {
+    let line = lines.get(pos.row)?;
+    let column = pos.column.min(line.len());
+
+    let touches_word = line[column..].chars().next().is_some_and(is_word_char)
+        || line[..column].chars().next_back().is_some_and(is_word_char);
+    if !touches_word {
+        return None;
+    }
+
+    let start = line[..column]
+        .char_indices()
+        .rev()
+        .take_while(|(_, c)| is_word_char(*c))
+        .last()
+        .map_or(column, |(i, _)| i);
+    let end = line[column..]
+        .char_indices()
+        .find(|(_, c)| !is_word_char(*c))
+        .map_or(line.len(), |(i, _)| column + i);
+
+    Some((
+        BufferPosition::new(pos.row, start),
+        BufferPosition::new(pos.row, end),
+    ))
+}
+
+/// Range covering the text of a single line (without its line break)
+pub fn line_range(lines: &[String], row: usize) -> (BufferPosition, BufferPosition) {
+    let row = row.min(lines.len().saturating_sub(1));
+    let len = lines.get(row).map_or(0, |l| l.len());
+    (BufferPosition::new(row, 0), BufferPosition::new(row, len))
+}
+
+/// Range of the block of non-blank lines containing `row`
+pub fn paragraph_range(lines: &[String], row: usize) -> (BufferPosition, BufferPosition) {
+    let row = row.min(lines.len().saturating_sub(1));
+    let is_blank = |r: usize| lines[r].trim().is_empty();
+
+    if is_blank(row) {
+        return line_range(lines, row);
+    }
+
+    let mut start = row;
+    while start > 0 && !is_blank(start - 1) {
+        start -= 1;
+    }
+    let mut end = row;
+    while end + 1 < lines.len() && !is_blank(end + 1) {
+        end += 1;
+    }
+
+    (
+        BufferPosition::new(start, 0),
+        BufferPosition::new(end, lines[end].len()),
+    )
+}
+
+/// Range covering the whole buffer
+pub fn document_range(lines: &[String]) -> (BufferPosition, BufferPosition) {
+    let last_row = lines.len().saturating_sub(1);
+    let last_col = lines.get(last_row).map_or(0, |l| l.len());
+    (
+        BufferPosition::zero(),
+        BufferPosition::new(last_row, last_col),
+    )
+}
@@ -0,0 +1,267 @@
+//! TODO, FIXME and NOTE markers and unchecked Markdown tasks, gathered from
+//! the buffer or every note into a list both frontends show and filter
+//!
+//! A marker is the word written in capitals, on its own rather than inside
+//! another word, followed by a colon, a space, `(` or the end of the line:
+//! `// TODO: tidy`, `FIXME(ana) leaks` and `NOTE` are markers, `TODOs` and
+//! `todo:` are not. A task is a list item starting `[ ]`, as in `- [ ] call`.
+
+use crate::notes;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Receiver, TryRecvError};
+use std::thread;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Kind {
+    Todo,
+    Fixme,
+    Note,
+    /// An unchecked `- [ ]` task
+    Task,
+}
+
+impl Kind {
+    pub fn label(self) -> &'static str {
+        match self {
+            Kind::Todo => "TODO",
+            Kind::Fixme => "FIXME",
+            Kind::Note => "NOTE",
+            Kind::Task => "TASK",
+        }
+    }
+}
+
+const MARKERS: [(&str, Kind); 3] = [
+    ("TODO", Kind::Todo),
+    ("FIXME", Kind::Fixme),
+    ("NOTE", Kind::Note),
+];
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Todo {
+    /// The note it was found in, or `None` for the buffer
+    pub path: Option<PathBuf>,
+    pub row: usize,
+    /// Byte column of the marker, or of the task's `[ ]`
+    pub column: usize,
+    pub kind: Kind,
+    /// What follows the marker, or the whole line when nothing does
+    pub text: String,
+}
+
+/// The task or marker on `line`, if any: its kind, column and text. A line
+/// holding both is listed as its task.
+pub fn find(line: &str) -> Option<(Kind, usize, String)> {
+    task(line).or_else(|| marker(line))
+}
+
+fn task(line: &str) -> Option<(Kind, usize, String)> {
+    let trimmed = line.trim_start();
+    let bullet = if trimmed.starts_with(['-', '*', '+']) {
+        1
+    } else {
+        let digits = trimmed.bytes().take_while(u8::is_ascii_digit).count();
+        let after = trimmed.as_bytes().get(digits);
+        if digits == 0 || !matches!(after, Some(b'.' | b')')) {
+            return None;
+        }
+        digits + 1
+    };
+    let rest = trimmed[bullet..].strip_prefix(' ')?;
+    let text = rest.strip_prefix("[ ]")?;
+    if !(text.is_empty() || text.starts_with(' ')) {
+        return None;
+    }
+    let column = line.len() - rest.len();
+    Some((Kind::Task, column, text.trim().to_string()))
+}
+
+fn marker(line: &str) -> Option<(Kind, usize, String)> {
+    let is_word = |c: char| c.is_alphanumeric() || c == '_';
+    let mut found: Option<(usize, &str, Kind)> = None;
+    for (word, kind) in MARKERS {
+        for (column, _) in line.match_indices(word) {
+            let before = line[..column].chars().next_back();
+            let after = line[column + word.len()..].chars().next();
+            if before.is_some_and(is_word) || !matches!(after, None | Some(':' | ' ' | '(')) {
+                continue;
+            }
+            if found.is_none_or(|(first, _, _)| column < first) {
+                found = Some((column, word, kind));
+            }
+            break;
+        }
+    }
+    let (column, word, kind) = found?;
+    let mut rest = &line[column + word.len()..];
+    // An owner in brackets, as in `TODO(ana):`, isn't part of the text
+    if let Some(owned) = rest.strip_prefix('(') {
+        rest = owned.split_once(')').map_or(rest, |(_, after)| after);
+    }
+    let text = rest.trim_start_matches(':').trim();
+    let text = if text.is_empty() { line.trim() } else { text };
+    Some((kind, column, text.to_string()))
+}
+
+/// Every marker and task in `lines`, in order, as found in `path`
+pub fn scan<S: AsRef<str>>(lines: &[S], path: Option<&Path>) -> Vec<Todo> {
+    lines
+        .iter()
+        .enumerate()
+        .filter_map(|(row, line)| {
+            let (kind, column, text) = find(line.as_ref())?;
+            Some(Todo {
+                path: path.map(Path::to_path_buf),
+                row,
+                column,
+                kind,
+                text,
+            })
+        })
+        .collect()
+}
+
+/// Every marker and task in `files`, file by file
+pub fn scan_files(files: &[PathBuf]) -> Vec<Todo> {
+    let mut found = Vec::new();
+    for path in files {
+        let Ok(content) = fs::read_to_string(path) else {
+            continue;
+        };
+        let lines: Vec<&str> = content.lines().collect();
+        found.extend(scan(&lines, Some(path)));
+    }
+    found
+}
+
+/// The markers and tasks of the buffer or of a notes directory, the latter
+/// gathered on a background thread, narrowed by typing
+#[derive(Debug)]
+pub struct TodoPanel {
+    pub query: String,
+    /// Index into [`TodoPanel::matches`]
+    pub selected: usize,
+    dir: Option<PathBuf>,
+    items: Vec<Todo>,
+    receiver: Option<Receiver<Vec<Todo>>>,
+}
+
+impl TodoPanel {
+    /// List the markers and tasks in the buffer's `lines`
+    pub fn for_buffer<S: AsRef<str>>(lines: &[S]) -> Self {
+        Self {
+            query: String::new(),
+            selected: 0,
+            dir: None,
+            items: scan(lines, None),
+            receiver: None,
+        }
+    }
+
+    /// Start gathering the markers and tasks in the notes below `dir`
+    pub fn start(dir: &Path) -> Self {
+        let (sender, receiver) = mpsc::channel();
+        let walk_dir = dir.to_path_buf();
+        thread::spawn(move || {
+            let _ = sender.send(scan_files(&notes::note_files(&walk_dir)));
+        });
+        Self {
+            query: String::new(),
+            selected: 0,
+            dir: Some(dir.to_path_buf()),
+            items: Vec::new(),
+            receiver: Some(receiver),
+        }
+    }
+
+    /// The notes directory listed, or `None` for the buffer
+    pub fn dir(&self) -> Option<&Path> {
+        self.dir.as_deref()
+    }
+
+    /// Collect the notes' markers once gathered; returns whether they arrived
+    pub fn poll(&mut self) -> bool {
+        let Some(receiver) = self.receiver.as_ref() else {
+            return false;
+        };
+        match receiver.try_recv() {
+            Ok(items) => self.items = items,
+            Err(TryRecvError::Empty) => return false,
+            Err(TryRecvError::Disconnected) => {}
+        }
+        self.receiver = None;
+        true
+    }
+
+    /// Block until the notes' markers are gathered
+    pub fn wait(&mut self) {
+        if let Some(receiver) = self.receiver.take() {
+            self.items = receiver.recv().unwrap_or_default();
+        }
+    }
+
+    pub fn is_done(&self) -> bool {
+        self.receiver.is_none()
+    }
+
+    /// Items whose kind, text or note name contains every word of the
+    /// query, ignoring case
+    pub fn matches(&self) -> Vec<&Todo> {
+        let query = self.query.to_lowercase();
+        let words: Vec<&str> = query.split_whitespace().collect();
+        self.items
+            .iter()
+            .filter(|item| {
+                let note = item.path.as_deref().map(notes::note_name);
+                let haystack = format!(
+                    "{} {} {}",
+                    item.kind.label(),
+                    item.text,
+                    note.unwrap_or_default()
+                )
+                .to_lowercase();
+                words.iter().all(|word| haystack.contains(word))
+            })
+            .collect()
+    }
+
+    pub fn type_text(&mut self, text: &str) {
+        self.query.push_str(text);
+        self.selected = 0;
+    }
+
+    pub fn backspace(&mut self) {
+        self.query.pop();
+        self.selected = 0;
+    }
+
+    pub fn select_previous(&mut self) {
+        self.selected = self.selected.saturating_sub(1);
+    }
+
+    pub fn select_next(&mut self) {
+        self.selected = (self.selected + 1).min(self.matches().len().saturating_sub(1));
+    }
+
+    pub fn selected_item(&self) -> Option<&Todo> {
+        self.matches().get(self.selected).copied()
+    }
+
+    /// A title for the list, such as "3 of 12 in the notes"
+    pub fn status(&self) -> String {
+        if !self.is_done() {
+            return "Gathering TODOs…".to_string();
+        }
+        let place = if self.dir.is_some() {
+            "in the notes"
+        } else {
+            "in this file"
+        };
+        match (self.matches().len(), self.items.len()) {
+            (_, 0) => format!("No TODOs {}", place),
+            (shown, total) if shown == total => format!("{} {}", total, place),
+            (shown, total) => format!("{} of {} {}", shown, total, place),
+        }
+    }
+}
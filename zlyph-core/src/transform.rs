@@ -0,0 +1,217 @@
+//! Transforms of the selected text: Base64, URL and HTML encoding and
+//! decoding, a hex dump and hashes
+//!
+//! Encoding and decoding replace the selection with the result. A hex dump
+//! or a hash is put on the lines after the selection, leaving the text it
+//! was made from in place. Either way it is one undo step.
+
+use base64::Engine as _;
+use md5::Md5;
+use percent_encoding::{AsciiSet, NON_ALPHANUMERIC};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fmt::Write as _;
+
+/// Characters left as they are in URL encoding, besides letters and digits
+const URL_UNRESERVED: &AsciiSet = &NON_ALPHANUMERIC
+    .remove(b'-')
+    .remove(b'_')
+    .remove(b'.')
+    .remove(b'~');
+
+/// Bytes shown on each line of a hex dump
+const DUMP_WIDTH: usize = 16;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Transform {
+    Base64Encode,
+    Base64Decode,
+    UrlEncode,
+    UrlDecode,
+    HtmlEscape,
+    HtmlUnescape,
+    HexDump,
+    Md5,
+    Sha256,
+}
+
+impl Transform {
+    pub const ALL: [Transform; 9] = [
+        Transform::Base64Encode,
+        Transform::Base64Decode,
+        Transform::UrlEncode,
+        Transform::UrlDecode,
+        Transform::HtmlEscape,
+        Transform::HtmlUnescape,
+        Transform::HexDump,
+        Transform::Md5,
+        Transform::Sha256,
+    ];
+
+    /// How the transform is shown in menus
+    pub fn label(self) -> &'static str {
+        match self {
+            Transform::Base64Encode => "Base64 Encode",
+            Transform::Base64Decode => "Base64 Decode",
+            Transform::UrlEncode => "URL Encode",
+            Transform::UrlDecode => "URL Decode",
+            Transform::HtmlEscape => "Escape HTML",
+            Transform::HtmlUnescape => "Unescape HTML",
+            Transform::HexDump => "Hex Dump",
+            Transform::Md5 => "MD5 Hash",
+            Transform::Sha256 => "SHA-256 Hash",
+        }
+    }
+
+    /// How the transform is named on the command line, as in
+    /// `transform base64-encode`
+    pub fn name(self) -> &'static str {
+        match self {
+            Transform::Base64Encode => "base64-encode",
+            Transform::Base64Decode => "base64-decode",
+            Transform::UrlEncode => "url-encode",
+            Transform::UrlDecode => "url-decode",
+            Transform::HtmlEscape => "html-escape",
+            Transform::HtmlUnescape => "html-unescape",
+            Transform::HexDump => "hexdump",
+            Transform::Md5 => "md5",
+            Transform::Sha256 => "sha256",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Transform> {
+        Transform::ALL
+            .into_iter()
+            .find(|transform| transform.name() == name)
+    }
+
+    /// Whether the result takes the place of the text, rather than going
+    /// after it
+    pub fn replaces(self) -> bool {
+        !matches!(
+            self,
+            Transform::HexDump | Transform::Md5 | Transform::Sha256
+        )
+    }
+
+    /// `text` transformed, or why it can't be
+    pub fn apply(self, text: &str) -> Result<String, String> {
+        match self {
+            Transform::Base64Encode => Ok(base64::engine::general_purpose::STANDARD.encode(text)),
+            Transform::Base64Decode => {
+                let encoded: String = text.split_whitespace().collect();
+                let bytes = base64::engine::general_purpose::STANDARD
+                    .decode(encoded)
+                    .map_err(|e| format!("Not valid Base64: {}", e))?;
+                String::from_utf8(bytes)
+                    .map_err(|_| "The decoded bytes aren't UTF-8 text".to_string())
+            }
+            Transform::UrlEncode => {
+                Ok(percent_encoding::utf8_percent_encode(text, URL_UNRESERVED).to_string())
+            }
+            Transform::UrlDecode => percent_encoding::percent_decode_str(text)
+                .decode_utf8()
+                .map(String::from)
+                .map_err(|_| "The decoded bytes aren't UTF-8 text".to_string()),
+            Transform::HtmlEscape => Ok(escape_html(text)),
+            Transform::HtmlUnescape => Ok(unescape_html(text)),
+            Transform::HexDump => Ok(hex_dump(text.as_bytes())),
+            Transform::Md5 => Ok(format!("{:x}", Md5::digest(text))),
+            Transform::Sha256 => Ok(format!("{:x}", Sha256::digest(text))),
+        }
+    }
+}
+
+fn escape_html(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&#39;"),
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+/// `text` with named entities for markup characters and spaces, and
+/// numeric ones, made characters again; others are left as written
+fn unescape_html(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(at) = rest.find('&') {
+        out.push_str(&rest[..at]);
+        rest = &rest[at..];
+        let entity = rest[1..]
+            .find(';')
+            .filter(|&end| end <= 10)
+            .map(|end| &rest[1..end + 1]);
+        match entity.and_then(entity_char) {
+            Some(c) => {
+                out.push(c);
+                rest = &rest[entity.map_or(0, str::len) + 2..];
+            }
+            None => {
+                out.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+fn entity_char(entity: &str) -> Option<char> {
+    let number = match entity.strip_prefix('#') {
+        Some(hex) if hex.starts_with(['x', 'X']) => u32::from_str_radix(&hex[1..], 16).ok(),
+        Some(decimal) => decimal.parse().ok(),
+        None => {
+            return match entity {
+                "amp" => Some('&'),
+                "lt" => Some('<'),
+                "gt" => Some('>'),
+                "quot" => Some('"'),
+                "apos" => Some('\''),
+                "nbsp" => Some('\u{a0}'),
+                _ => None,
+            }
+        }
+    };
+    number.and_then(char::from_u32)
+}
+
+/// `bytes` as lines of an offset, sixteen bytes in hex and the same bytes
+/// as ASCII, a dot for any that isn't printable, as `hexdump -C` shows them
+fn hex_dump(bytes: &[u8]) -> String {
+    let mut out = String::new();
+    for (line, chunk) in bytes.chunks(DUMP_WIDTH).enumerate() {
+        if line > 0 {
+            out.push('\n');
+        }
+        let _ = write!(out, "{:08x} ", line * DUMP_WIDTH);
+        for i in 0..DUMP_WIDTH {
+            if i % 8 == 0 {
+                out.push(' ');
+            }
+            match chunk.get(i) {
+                Some(byte) => {
+                    let _ = write!(out, "{:02x} ", byte);
+                }
+                None => out.push_str("   "),
+            }
+        }
+        out.push_str(" |");
+        out.extend(chunk.iter().map(|&byte| {
+            if byte.is_ascii_graphic() || byte == b' ' {
+                byte as char
+            } else {
+                '.'
+            }
+        }));
+        out.push('|');
+    }
+    out
+}
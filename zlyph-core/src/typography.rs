@@ -0,0 +1,46 @@
+//! Smart punctuation for prose: straight quotes become curly, `--` an en
+//! dash and a third `-` an em dash, and `...` an ellipsis as they are typed
+//!
+//! Only notes (Markdown and plain text files) get these, and not in code
+//! spans or fenced code. Dashes starting a line are left alone so list
+//! markers, rules and front matter fences stay as typed.
+
+/// Characters after which a quote opens rather than closes
+fn opens_quote(previous: Option<char>) -> bool {
+    previous
+        .is_none_or(|c| c.is_whitespace() || matches!(c, '(' | '[' | '{' | '“' | '‘' | '—' | '–'))
+}
+
+/// Whether `before` ends inside a code span
+fn in_code_span(before: &str) -> bool {
+    before.matches('`').count() % 2 == 1
+}
+
+/// What typing `c` after `before`, the start of a line up to the cursor,
+/// becomes: how many bytes before the cursor it replaces, and the text
+/// replacing them and the typed character
+pub fn substitute(before: &str, c: char) -> Option<(usize, String)> {
+    if !matches!(c, '"' | '\'' | '-' | '.') || in_code_span(before) {
+        return None;
+    }
+    let previous = before.chars().next_back();
+    match c {
+        '"' => Some((0, if opens_quote(previous) { "“" } else { "”" }.to_string())),
+        '\'' => Some((0, if opens_quote(previous) { "‘" } else { "’" }.to_string())),
+        '-' => {
+            let (len, dash) = match previous? {
+                '-' => (1, "–"),
+                '–' => ('–'.len_utf8(), "—"),
+                _ => return None,
+            };
+            let rest = &before[..before.len() - len];
+            if rest.trim().is_empty() || rest.ends_with('!') {
+                // A list marker, rule or `<!--` comment
+                return None;
+            }
+            Some((len, dash.to_string()))
+        }
+        '.' if before.ends_with("..") && !before.ends_with("...") => Some((2, "…".to_string())),
+        _ => None,
+    }
+}
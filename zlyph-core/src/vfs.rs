@@ -0,0 +1,569 @@
+//! Where buffers are read from and saved to: files on this machine, or on
+//! another one over SSH for paths like `ssh://me@host:2222/home/me/notes.md`
+//!
+//! Remote files are transferred by running the `ssh` command, so keys,
+//! agents and `~/.ssh/config` apply as usual. Each one is cached under
+//! `~/.config/zlyph/remote` with a checksum of the version last transferred.
+//! Saving writes the cache at once and uploads in the background; the upload
+//! is refused if the file changed on the host since, and retried later if
+//! the host can't be reached. Opening falls back to the cache when the host
+//! is unreachable, keeping edits made offline until they can be uploaded.
+
+use crate::config::{self, RemoteConfig};
+use crate::history::path_key;
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender};
+use std::sync::{Arc, Mutex, PoisonError};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+/// How often uploads that could not reach their host are tried again
+const RETRY_INTERVAL: Duration = Duration::from_secs(30);
+/// Bytes sent between progress updates
+const CHUNK_SIZE: usize = 16 * 1024;
+/// Exit status of the upload script when the file changed on the host
+const CONFLICT_STATUS: i32 = 3;
+/// Exit status of ssh itself when it can't reach the host
+const UNREACHABLE_STATUS: i32 = 255;
+
+pub trait FileSystem {
+    fn read(&self, path: &Path) -> io::Result<String>;
+    fn write(&self, path: &Path, content: &str) -> io::Result<()>;
+}
+
+/// Files on this machine
+#[derive(Debug, Default, Clone, Copy)]
+pub struct LocalFs;
+
+impl FileSystem for LocalFs {
+    fn read(&self, path: &Path) -> io::Result<String> {
+        fs::read_to_string(path)
+    }
+
+    fn write(&self, path: &Path, content: &str) -> io::Result<()> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(path, content)
+    }
+}
+
+/// Whether `path` names a file on another machine
+pub fn is_remote(path: &Path) -> bool {
+    RemotePath::from_path(path).is_some()
+}
+
+/// A file named by `ssh://[user@]host[:port]/path`, or the same with
+/// `sftp://`; a path starting with `/~/` is in the remote home directory
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RemotePath {
+    pub user: Option<String>,
+    pub host: String,
+    pub port: Option<u16>,
+    pub path: String,
+}
+
+impl RemotePath {
+    pub fn parse(url: &str) -> Option<Self> {
+        let rest = url
+            .strip_prefix("ssh://")
+            .or_else(|| url.strip_prefix("sftp://"))?;
+        let (authority, path) = rest.split_at(rest.find('/')?);
+        let (user, host) = match authority.rsplit_once('@') {
+            Some((user, host)) => (Some(user.to_string()), host),
+            None => (None, authority),
+        };
+        let (host, port) = match host.rsplit_once(':') {
+            Some((host, port)) => (host, Some(port.parse().ok()?)),
+            None => (host, None),
+        };
+        if host.is_empty() || path.len() < 2 {
+            return None;
+        }
+        let path = match path.strip_prefix("/~/") {
+            Some(rest) => format!("~/{}", rest),
+            None => path.to_string(),
+        };
+        Some(Self {
+            user,
+            host: host.to_string(),
+            port,
+            path,
+        })
+    }
+
+    pub fn from_path(path: &Path) -> Option<Self> {
+        Self::parse(path.to_str()?)
+    }
+
+    pub fn file_name(&self) -> &str {
+        self.path.rsplit('/').next().unwrap_or(&self.path)
+    }
+
+    /// The path quoted for the remote shell
+    fn shell_path(&self) -> String {
+        match self.path.strip_prefix("~/") {
+            Some(rest) => format!("\"$HOME\"/{}", shell_quote(rest)),
+            None => shell_quote(&self.path),
+        }
+    }
+}
+
+impl fmt::Display for RemotePath {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "ssh://")?;
+        if let Some(user) = &self.user {
+            write!(f, "{}@", user)?;
+        }
+        write!(f, "{}", self.host)?;
+        if let Some(port) = self.port {
+            write!(f, ":{}", port)?;
+        }
+        match self.path.strip_prefix("~/") {
+            Some(rest) => write!(f, "/~/{}", rest),
+            None => write!(f, "{}", self.path),
+        }
+    }
+}
+
+fn shell_quote(text: &str) -> String {
+    format!("'{}'", text.replace('\'', "'\\''"))
+}
+
+/// What POSIX `cksum` prints for `content`: its CRC and length, which the
+/// host can compute without anything beyond a POSIX shell
+pub fn checksum(content: &[u8]) -> String {
+    fn step(crc: u32, byte: u8) -> u32 {
+        (0..8).fold(crc ^ ((byte as u32) << 24), |crc, _| {
+            if crc & 0x8000_0000 != 0 {
+                (crc << 1) ^ 0x04C1_1DB7
+            } else {
+                crc << 1
+            }
+        })
+    }
+    let mut crc = content.iter().fold(0, |crc, &byte| step(crc, byte));
+    let mut len = content.len();
+    while len > 0 {
+        crc = step(crc, len as u8);
+        len >>= 8;
+    }
+    format!("{} {}", !crc, content.len())
+}
+
+/// The latest transfer worth showing in the status bar
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TransferStatus {
+    Uploading {
+        name: String,
+        sent: u64,
+        total: u64,
+    },
+    /// The host can't be reached; the file is saved in the cache and
+    /// uploaded once it can
+    Offline {
+        name: String,
+    },
+    /// The file changed on the host since it was transferred, so it was not
+    /// uploaded; see [`SshFs::overwrite`]
+    Conflict {
+        name: String,
+    },
+    Failed {
+        name: String,
+        message: String,
+    },
+}
+
+impl TransferStatus {
+    pub fn label(&self) -> String {
+        match self {
+            TransferStatus::Uploading { name, sent, total } => {
+                let percent = (sent * 100).checked_div(*total).unwrap_or(100);
+                format!("Uploading {} {}%", name, percent)
+            }
+            TransferStatus::Offline { name } => {
+                format!("{}: host unreachable, saved locally", name)
+            }
+            TransferStatus::Conflict { name } => {
+                format!("{} changed on the host; not uploaded", name)
+            }
+            TransferStatus::Failed { name, message } => format!("{}: {}", name, message),
+        }
+    }
+}
+
+/// Why running a command on the host failed
+enum Failure {
+    Unreachable,
+    Conflict,
+    Other(String),
+}
+
+impl From<Failure> for io::Error {
+    fn from(failure: Failure) -> Self {
+        match failure {
+            Failure::Unreachable => io::Error::new(io::ErrorKind::NotConnected, "host unreachable"),
+            Failure::Conflict => io::Error::other("file changed on the host"),
+            Failure::Other(message) => io::Error::other(message),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Upload {
+    remote: RemotePath,
+    /// The file's cache directory
+    cache: PathBuf,
+    content: String,
+    /// Replace the host's copy even if it changed since it was transferred
+    force: bool,
+    config: RemoteConfig,
+}
+
+/// Files on other machines, cached locally and uploaded in the background
+#[derive(Debug)]
+pub struct SshFs {
+    config: RemoteConfig,
+    cache_dir: PathBuf,
+    status: Arc<Mutex<Option<TransferStatus>>>,
+    /// Started by the first upload
+    uploader: Mutex<Option<(Sender<Upload>, JoinHandle<()>)>>,
+}
+
+impl SshFs {
+    pub fn new(config: RemoteConfig, cache_dir: PathBuf) -> Self {
+        Self {
+            config,
+            cache_dir,
+            status: Arc::new(Mutex::new(None)),
+            uploader: Mutex::new(None),
+        }
+    }
+
+    pub fn default_cache_dir() -> PathBuf {
+        config::config_dir().join("remote")
+    }
+
+    pub fn set_config(&mut self, config: RemoteConfig) {
+        self.config = config;
+    }
+
+    pub fn status(&self) -> Option<TransferStatus> {
+        lock(&self.status).clone()
+    }
+
+    /// Upload `content` as `path` even if the file changed on the host
+    pub fn overwrite(&self, path: &Path, content: &str) -> io::Result<()> {
+        self.save(path, content, true)
+    }
+
+    fn save(&self, path: &Path, content: &str, force: bool) -> io::Result<()> {
+        let remote = parse(path)?;
+        let cache = self.cache_for(&remote);
+        fs::create_dir_all(&cache)?;
+        fs::write(cache.join("content"), content)?;
+        fs::write(cache.join("pending"), "")?;
+        self.upload(Upload {
+            remote,
+            cache,
+            content: content.to_string(),
+            force,
+            config: self.config.clone(),
+        })
+    }
+
+    fn upload(&self, upload: Upload) -> io::Result<()> {
+        let mut uploader = lock(&self.uploader);
+        let (sender, _) = uploader.get_or_insert_with(|| {
+            let (sender, receiver) = mpsc::channel();
+            let status = self.status.clone();
+            let handle = thread::spawn(move || upload_loop(receiver, status));
+            (sender, handle)
+        });
+        sender
+            .send(upload)
+            .map_err(|_| io::Error::other("upload thread stopped"))
+    }
+
+    fn cache_for(&self, remote: &RemotePath) -> PathBuf {
+        self.cache_dir
+            .join(path_key(Path::new(&remote.to_string())))
+    }
+
+    fn set_status(&self, status: Option<TransferStatus>) {
+        *lock(&self.status) = status;
+    }
+}
+
+impl FileSystem for SshFs {
+    /// The host's copy, or the cached one if the host can't be reached or
+    /// edits made while it couldn't are still waiting to be uploaded. A file
+    /// missing on the host reads as empty.
+    fn read(&self, path: &Path) -> io::Result<String> {
+        let remote = parse(path)?;
+        let cache = self.cache_for(&remote);
+        let name = remote.file_name().to_string();
+        let script = format!(
+            "f={}; [ -e \"$f\" ] || exit 0; cksum < \"$f\" && cat -- \"$f\"",
+            remote.shell_path()
+        );
+        let output = match run(&self.config, &remote, &script, None, |_| {}) {
+            Ok(output) => output,
+            Err(Failure::Unreachable) => {
+                let cached = fs::read_to_string(cache.join("content"))?;
+                self.set_status(Some(TransferStatus::Offline { name }));
+                return Ok(cached);
+            }
+            Err(failure) => return Err(failure.into()),
+        };
+        let (base, text) = output
+            .split_once('\n')
+            .map(|(sum, text)| (sum.trim().to_string(), text.to_string()))
+            .unwrap_or_default();
+
+        if cache.join("pending").exists() {
+            if let Ok(mine) = fs::read_to_string(cache.join("content")) {
+                let stored = fs::read_to_string(cache.join("base")).unwrap_or_default();
+                if stored == base {
+                    self.set_status(None);
+                    self.save(path, &mine, false)?;
+                } else {
+                    self.set_status(Some(TransferStatus::Conflict { name }));
+                }
+                return Ok(mine);
+            }
+        }
+        fs::create_dir_all(&cache)?;
+        fs::write(cache.join("content"), &text)?;
+        fs::write(cache.join("base"), &base)?;
+        self.set_status(None);
+        Ok(text)
+    }
+
+    fn write(&self, path: &Path, content: &str) -> io::Result<()> {
+        self.save(path, content, false)
+    }
+}
+
+impl Drop for SshFs {
+    /// Finish the uploads already queued
+    fn drop(&mut self) {
+        if let Some((sender, handle)) = lock(&self.uploader).take() {
+            drop(sender);
+            let _ = handle.join();
+        }
+    }
+}
+
+/// Sends paths like `ssh://host/path` to [`SshFs`] and others to
+/// [`LocalFs`]
+#[derive(Debug)]
+pub struct Vfs {
+    local: LocalFs,
+    ssh: SshFs,
+}
+
+impl Vfs {
+    pub fn new(config: RemoteConfig) -> Self {
+        Self {
+            local: LocalFs,
+            ssh: SshFs::new(config, SshFs::default_cache_dir()),
+        }
+    }
+
+    pub fn ssh(&self) -> &SshFs {
+        &self.ssh
+    }
+
+    pub fn set_config(&mut self, config: RemoteConfig) {
+        self.ssh.set_config(config);
+    }
+}
+
+impl FileSystem for Vfs {
+    fn read(&self, path: &Path) -> io::Result<String> {
+        if is_remote(path) {
+            self.ssh.read(path)
+        } else {
+            self.local.read(path)
+        }
+    }
+
+    fn write(&self, path: &Path, content: &str) -> io::Result<()> {
+        if is_remote(path) {
+            self.ssh.write(path, content)
+        } else {
+            self.local.write(path, content)
+        }
+    }
+}
+
+fn parse(path: &Path) -> io::Result<RemotePath> {
+    RemotePath::from_path(path).ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("{} is not an ssh:// path", path.display()),
+        )
+    })
+}
+
+fn lock<T>(mutex: &Mutex<T>) -> std::sync::MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+/// Upload queued files, only the latest content of each, until the sender
+/// is dropped; uploads that can't reach their host wait for the next one
+fn upload_loop(receiver: Receiver<Upload>, status: Arc<Mutex<Option<TransferStatus>>>) {
+    let mut waiting: HashMap<PathBuf, Upload> = HashMap::new();
+    loop {
+        match receiver.recv_timeout(RETRY_INTERVAL) {
+            Ok(upload) => queue(&mut waiting, upload),
+            Err(RecvTimeoutError::Timeout) => {}
+            Err(RecvTimeoutError::Disconnected) => return,
+        }
+        while let Ok(upload) = receiver.try_recv() {
+            queue(&mut waiting, upload);
+        }
+        for (cache, upload) in std::mem::take(&mut waiting) {
+            let name = upload.remote.file_name().to_string();
+            let result = send(&upload, |sent, total| {
+                *lock(&status) = Some(TransferStatus::Uploading {
+                    name: name.clone(),
+                    sent,
+                    total,
+                });
+            });
+            let next = match result {
+                Ok(()) => None,
+                Err(Failure::Unreachable) => {
+                    waiting.insert(cache, upload);
+                    Some(TransferStatus::Offline { name })
+                }
+                Err(Failure::Conflict) => Some(TransferStatus::Conflict { name }),
+                Err(Failure::Other(message)) => Some(TransferStatus::Failed { name, message }),
+            };
+            *lock(&status) = next;
+        }
+    }
+}
+
+/// Replace any upload of the same file still waiting, keeping it forced if
+/// either was
+fn queue(waiting: &mut HashMap<PathBuf, Upload>, mut upload: Upload) {
+    if let Some(previous) = waiting.remove(&upload.cache) {
+        upload.force |= previous.force;
+    }
+    waiting.insert(upload.cache.clone(), upload);
+}
+
+/// Upload one file, first checking the host's copy is the one last
+/// transferred unless forced. The content is written beside the file and
+/// copied over it once complete, keeping the file's permissions.
+fn send(upload: &Upload, mut progress: impl FnMut(u64, u64)) -> Result<(), Failure> {
+    let check = if upload.force {
+        String::new()
+    } else {
+        let base = fs::read_to_string(upload.cache.join("base")).unwrap_or_default();
+        format!(
+            "s=$([ -e \"$f\" ] && cksum < \"$f\"); [ \"$s\" = {} ] || exit {}; ",
+            shell_quote(&base),
+            CONFLICT_STATUS
+        )
+    };
+    let script = format!(
+        "f={}; t=\"$f.zlyph-upload\"; {}mkdir -p -- \"$(dirname -- \"$f\")\" && \
+         cat > \"$t\" && cat -- \"$t\" > \"$f\" && rm -f -- \"$t\"",
+        upload.remote.shell_path(),
+        check
+    );
+    let total = upload.content.len() as u64;
+    run(
+        &upload.config,
+        &upload.remote,
+        &script,
+        Some(&upload.content),
+        |sent| progress(sent, total),
+    )?;
+
+    let _ = fs::write(
+        upload.cache.join("base"),
+        checksum(upload.content.as_bytes()),
+    );
+    if fs::read_to_string(upload.cache.join("content"))
+        .ok()
+        .as_deref()
+        == Some(&upload.content)
+    {
+        let _ = fs::remove_file(upload.cache.join("pending"));
+    }
+    Ok(())
+}
+
+/// Run `script` with `sh` on the host, writing `input` to it and reporting
+/// how many bytes have been sent
+fn run(
+    config: &RemoteConfig,
+    remote: &RemotePath,
+    script: &str,
+    input: Option<&str>,
+    mut progress: impl FnMut(u64),
+) -> Result<String, Failure> {
+    let mut words = config.ssh_command.split_whitespace();
+    let program = words.next().unwrap_or("ssh");
+    let mut command = Command::new(program);
+    command
+        .args(words)
+        .arg("-o")
+        .arg("BatchMode=yes")
+        .arg("-o")
+        .arg(format!("ConnectTimeout={}", config.connect_timeout_secs));
+    if let Some(port) = remote.port {
+        command.arg("-p").arg(port.to_string());
+    }
+    let destination = match &remote.user {
+        Some(user) => format!("{}@{}", user, remote.host),
+        None => remote.host.clone(),
+    };
+    let mut child = command
+        .arg(destination)
+        .arg(format!("sh -c {}", shell_quote(script)))
+        .stdin(if input.is_some() {
+            Stdio::piped()
+        } else {
+            Stdio::null()
+        })
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| Failure::Other(format!("could not run {}: {}", program, e)))?;
+
+    if let (Some(input), Some(mut stdin)) = (input, child.stdin.take()) {
+        let mut sent = 0;
+        progress(0);
+        for chunk in input.as_bytes().chunks(CHUNK_SIZE) {
+            if stdin.write_all(chunk).is_err() {
+                break;
+            }
+            sent += chunk.len() as u64;
+            progress(sent);
+        }
+    }
+    let output = child
+        .wait_with_output()
+        .map_err(|e| Failure::Other(e.to_string()))?;
+    match output.status.code() {
+        Some(0) => Ok(String::from_utf8_lossy(&output.stdout).into_owned()),
+        Some(UNREACHABLE_STATUS) => Err(Failure::Unreachable),
+        Some(CONFLICT_STATUS) => Err(Failure::Conflict),
+        _ => {
+            let message = String::from_utf8_lossy(&output.stderr);
+            Err(Failure::Other(message.trim().to_string()))
+        }
+    }
+}
@@ -0,0 +1,206 @@
+//! Per-file view state, the recently opened files and their projects and
+//! past searches, shared by both frontends and kept in `~/.config/zlyph/workspace.toml`
+
+use crate::config::{self, ZoomScope};
+use crate::project;
+use crate::BufferPosition;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// How many files the recent-files list remembers
+pub const MAX_RECENT_FILES: usize = 30;
+
+/// How many projects the project list remembers
+pub const MAX_PROJECTS: usize = 30;
+
+/// How many find-in-files queries the search history remembers
+pub const MAX_SEARCHES: usize = 50;
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct FileState {
+    /// First buffer line shown at the top of the view
+    pub scroll_line: usize,
+    /// Where the cursor was left
+    pub cursor_row: usize,
+    pub cursor_column: usize,
+    /// The other end of the selection left in the file, if any
+    pub selection_anchor: Option<BufferPosition>,
+    /// GUI font size the file was last zoomed to, when zoom is kept per file
+    pub font_size: Option<f32>,
+    /// Rows with a bookmark
+    pub bookmarks: Vec<usize>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct WorkspaceState {
+    /// GUI font size last zoomed to, when zoom is shared by all files
+    pub font_size: Option<f32>,
+    /// Absolute paths of opened files, most recent first
+    pub recent: Vec<String>,
+    /// Roots of the projects those files are in, most recent first
+    pub projects: Vec<String>,
+    /// Keyed by absolute file path
+    pub files: BTreeMap<String, FileState>,
+    /// Find-in-files queries, most recent first
+    pub searches: Vec<String>,
+}
+
+fn file_key(path: &Path) -> String {
+    path.canonicalize()
+        .unwrap_or_else(|_| path.to_path_buf())
+        .to_string_lossy()
+        .into_owned()
+}
+
+impl WorkspaceState {
+    pub fn default_path() -> PathBuf {
+        config::config_dir().join("workspace.toml")
+    }
+
+    /// Load state from a file; a missing file yields an empty state
+    pub fn load_from_file<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        match fs::read_to_string(path) {
+            Ok(content) => {
+                toml::from_str(&content).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+            }
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(e) => Err(e),
+        }
+    }
+
+    pub fn load() -> io::Result<Self> {
+        Self::load_from_file(Self::default_path())
+    }
+
+    pub fn save_to_file<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        let path = path.as_ref();
+        let content = toml::to_string(self).map_err(io::Error::other)?;
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(path, content)
+    }
+
+    pub fn file(&self, path: &Path) -> FileState {
+        self.files.get(&file_key(path)).cloned().unwrap_or_default()
+    }
+
+    /// Change one file's entry in the state file at `state_path`, re-reading it
+    /// first so entries written by the other frontend are kept
+    pub fn update_file_in<P: AsRef<Path>>(
+        state_path: P,
+        path: &Path,
+        update: impl FnOnce(&mut FileState),
+    ) -> io::Result<()> {
+        let state_path = state_path.as_ref();
+        let mut state = Self::load_from_file(state_path).unwrap_or_default();
+        update(state.files.entry(file_key(path)).or_default());
+        state.save_to_file(state_path)
+    }
+
+    pub fn update_file(path: &Path, update: impl FnOnce(&mut FileState)) -> io::Result<()> {
+        Self::update_file_in(Self::default_path(), path, update)
+    }
+
+    /// The font size last zoomed to for `path`, or for every file
+    pub fn font_size(&self, path: Option<&Path>, scope: ZoomScope) -> Option<f32> {
+        match scope {
+            ZoomScope::Global => self.font_size,
+            ZoomScope::Buffer => path.and_then(|path| self.file(path).font_size),
+        }
+    }
+
+    /// Remember a zoom to `size` in the state file at `state_path`; an
+    /// untitled buffer's zoom is only remembered when it is shared
+    pub fn store_font_size_in<P: AsRef<Path>>(
+        state_path: P,
+        path: Option<&Path>,
+        scope: ZoomScope,
+        size: f32,
+    ) -> io::Result<()> {
+        let state_path = state_path.as_ref();
+        match (scope, path) {
+            (ZoomScope::Buffer, Some(path)) => {
+                Self::update_file_in(state_path, path, |file| file.font_size = Some(size))
+            }
+            (ZoomScope::Buffer, None) => Ok(()),
+            (ZoomScope::Global, _) => {
+                let mut state = Self::load_from_file(state_path).unwrap_or_default();
+                state.font_size = Some(size);
+                state.save_to_file(state_path)
+            }
+        }
+    }
+
+    pub fn store_font_size(path: Option<&Path>, scope: ZoomScope, size: f32) -> io::Result<()> {
+        Self::store_font_size_in(Self::default_path(), path, scope, size)
+    }
+
+    /// Recently opened files that still exist, most recent first
+    pub fn recent_files(&self) -> Vec<PathBuf> {
+        self.recent
+            .iter()
+            .map(PathBuf::from)
+            .filter(|path| path.is_file())
+            .collect()
+    }
+
+    /// Known projects that still exist, most recently used first
+    pub fn projects(&self) -> Vec<PathBuf> {
+        self.projects
+            .iter()
+            .map(PathBuf::from)
+            .filter(|path| path.is_dir())
+            .collect()
+    }
+
+    /// The file last opened in the project at `root`, to pick up where it
+    /// was left
+    pub fn last_file_in(&self, root: &Path) -> Option<PathBuf> {
+        self.recent_files()
+            .into_iter()
+            .find(|path| project::root(path) == root)
+    }
+
+    /// Move `path` to the front of the recent-files list in the state file at
+    /// `state_path`, and its project to the front of the project list
+    pub fn add_recent_in<P: AsRef<Path>>(state_path: P, path: &Path) -> io::Result<()> {
+        let state_path = state_path.as_ref();
+        let mut state = Self::load_from_file(state_path).unwrap_or_default();
+        let key = file_key(path);
+        let root = project::root(Path::new(&key))
+            .to_string_lossy()
+            .into_owned();
+        state.recent.retain(|recent| *recent != key);
+        state.recent.insert(0, key);
+        state.recent.truncate(MAX_RECENT_FILES);
+        state.projects.retain(|project| *project != root);
+        state.projects.insert(0, root);
+        state.projects.truncate(MAX_PROJECTS);
+        state.save_to_file(state_path)
+    }
+
+    pub fn add_recent(path: &Path) -> io::Result<()> {
+        Self::add_recent_in(Self::default_path(), path)
+    }
+
+    /// Move `query` to the front of the search history in the state file at
+    /// `state_path`
+    pub fn add_search_in<P: AsRef<Path>>(state_path: P, query: &str) -> io::Result<()> {
+        let state_path = state_path.as_ref();
+        let mut state = Self::load_from_file(state_path).unwrap_or_default();
+        state.searches.retain(|search| search != query);
+        state.searches.insert(0, query.to_string());
+        state.searches.truncate(MAX_SEARCHES);
+        state.save_to_file(state_path)
+    }
+
+    pub fn add_search(query: &str) -> io::Result<()> {
+        Self::add_search_in(Self::default_path(), query)
+    }
+}
@@ -0,0 +1,381 @@
+//! Block YAML as a tree of lines, for reindenting it and sorting its keys
+//!
+//! Only the block layout is understood: a line's indentation says what it
+//! belongs to, `- ` starts a sequence item and a value of `|` or `>` opens
+//! a block scalar whose lines keep their indentation relative to each
+//! other. Everything else on a line, such as flow collections, quoted
+//! strings, anchors and comments, is copied as written, so reindenting
+//! changes nothing but the spaces at the start of each line.
+
+use std::cmp::Ordering;
+
+/// Why the text isn't block YAML this module can lay out, and where
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Error {
+    pub row: usize,
+    pub column: usize,
+    pub message: String,
+}
+
+impl Error {
+    fn new(row: usize, column: usize, message: &str) -> Self {
+        Self {
+            row,
+            column,
+            message: message.to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+enum Token {
+    /// `-` opening a sequence item
+    Dash,
+    Text(String),
+    /// The lines of a block scalar, less their common indentation
+    Block(Vec<String>),
+    Blank,
+}
+
+#[derive(Debug, Clone)]
+struct Placed {
+    row: usize,
+    column: usize,
+    token: Token,
+}
+
+#[derive(Debug, Clone)]
+enum Node {
+    /// A sequence item and what it holds, the first node on the dash's line
+    Item(Vec<Node>),
+    /// A line's text and the lines indented below it
+    Line(String, Vec<Node>),
+    Block(Vec<String>),
+    Blank,
+}
+
+/// `text` with each level of nesting indented `indent` spaces, sequences
+/// included, and one blank line where there were several; with `sort`,
+/// the keys of each mapping in order too
+pub fn format(text: &str, indent: usize, sort: bool) -> Result<String, Error> {
+    let tokens = tokenize(text)?;
+    let mut parser = Parser { tokens, next: 0 };
+    let mut nodes = parser.siblings(&|_| true)?;
+    if sort {
+        sort_keys(&mut nodes);
+    }
+    let mut lines = Vec::new();
+    write(&nodes, 0, indent.max(1), &mut lines);
+    while lines.last().is_some_and(String::is_empty) {
+        lines.pop();
+    }
+    let mut out = lines.join("\n");
+    if text.ends_with('\n') {
+        out.push('\n');
+    }
+    Ok(out)
+}
+
+fn tokenize(text: &str) -> Result<Vec<Placed>, Error> {
+    let lines: Vec<&str> = text.lines().collect();
+    let mut tokens = Vec::new();
+    let mut row = 0;
+    while row < lines.len() {
+        let line = lines[row].trim_end();
+        let content = line.trim_start_matches([' ', '\t']);
+        let mut column = line.len() - content.len();
+        if content.is_empty() {
+            tokens.push(Placed {
+                row,
+                column: 0,
+                token: Token::Blank,
+            });
+            row += 1;
+            continue;
+        }
+        if let Some(tab) = line[..column].find('\t') {
+            return Err(Error::new(row, tab, "tabs can't indent YAML"));
+        }
+
+        let base = column;
+        let mut rest = content;
+        while let Some(after) = rest
+            .strip_prefix('-')
+            .filter(|after| after.is_empty() || after.starts_with(' '))
+        {
+            tokens.push(Placed {
+                row,
+                column,
+                token: Token::Dash,
+            });
+            let text = after.trim_start();
+            column += rest.len() - text.len();
+            rest = text;
+        }
+        if !rest.is_empty() {
+            tokens.push(Placed {
+                row,
+                column,
+                token: Token::Text(rest.to_string()),
+            });
+        }
+        row += 1;
+
+        if opens_block(rest) {
+            // The block runs on while lines are blank or indented past the
+            // line opening it; blank lines after it are left out of it
+            let end = (row..lines.len())
+                .take_while(|&r| {
+                    let line = lines[r].trim_end();
+                    line.is_empty() || indentation(line) > base
+                })
+                .filter(|&r| !lines[r].trim().is_empty())
+                .last()
+                .map_or(row, |last| last + 1);
+            let block = &lines[row..end];
+            let common = block
+                .iter()
+                .filter(|line| !line.trim().is_empty())
+                .map(|line| indentation(line))
+                .min()
+                .unwrap_or(0);
+            let kept = block
+                .iter()
+                .map(|line| line.get(common..).unwrap_or("").trim_end().to_string())
+                .collect();
+            if end > row {
+                tokens.push(Placed {
+                    row,
+                    column: common,
+                    token: Token::Block(kept),
+                });
+            }
+            row = end;
+        }
+    }
+
+    // A comment belongs with the line after it, wherever it is indented
+    let mut below = 0;
+    for at in (0..tokens.len()).rev() {
+        let own_line = at == 0 || tokens[at - 1].row != tokens[at].row;
+        match &tokens[at].token {
+            Token::Text(text) if text.starts_with('#') && own_line => tokens[at].column = below,
+            Token::Blank => {}
+            _ => below = tokens[at].column,
+        }
+    }
+    Ok(tokens)
+}
+
+fn indentation(line: &str) -> usize {
+    line.len() - line.trim_start_matches([' ', '\t']).len()
+}
+
+/// Whether `text` ends in `|` or `>`, with any chomping or indentation
+/// indicators, so the lines below it are a block scalar
+fn opens_block(text: &str) -> bool {
+    let text = text.split(" #").next().unwrap_or(text).trim_end();
+    let value = text.rsplit_once(": ").map_or(text, |(_, value)| value);
+    value.starts_with(['|', '>'])
+        && value[1..]
+            .chars()
+            .all(|c| c.is_ascii_digit() || c == '-' || c == '+')
+}
+
+/// The key `text` starts with, unquoted, if it is a `key: value` line
+fn key(text: &str) -> Option<&str> {
+    let quote = text.chars().next()?;
+    if matches!(quote, '"' | '\'') {
+        let close = text[1..].find(quote)? + 1;
+        let after = &text[close + 1..];
+        return (after.starts_with(": ") || after == ":").then(|| &text[1..close]);
+    }
+    if text.starts_with(['#', '[', '{', '&', '*', '!', '|', '>', '%', '@', '`']) {
+        return None;
+    }
+    match text.find(": ") {
+        Some(at) => Some(&text[..at]),
+        None => text.strip_suffix(':'),
+    }
+}
+
+/// Whether a keyed line holds its value on the line itself, so it can't
+/// have a mapping or a sequence below it
+fn holds_value(text: &str) -> bool {
+    let value = match key(text) {
+        Some(_) => text.split_once(": ").map_or("", |(_, value)| value),
+        None => text,
+    };
+    let value = value.trim();
+    // An anchor or tag alone is for the value below, and a flow
+    // collection may go on over the lines below
+    !(value.is_empty()
+        || value.starts_with(['#', '[', '{'])
+        || value.starts_with(['&', '!']) && !value.contains(' '))
+}
+
+struct Parser {
+    tokens: Vec<Placed>,
+    next: usize,
+}
+
+impl Parser {
+    /// The nodes at the level of the first token `within` takes, up to the
+    /// first one it doesn't
+    fn siblings(&mut self, within: &dyn Fn(&Placed) -> bool) -> Result<Vec<Node>, Error> {
+        let mut nodes = Vec::new();
+        let mut level = None;
+        while let Some(placed) = self.tokens.get(self.next) {
+            if matches!(placed.token, Token::Blank) {
+                self.next += 1;
+                nodes.push(Node::Blank);
+                continue;
+            }
+            if !within(placed) {
+                break;
+            }
+            let column = *level.get_or_insert(placed.column);
+            if placed.column != column {
+                let message = "indentation doesn't line up with the lines above it";
+                return Err(Error::new(placed.row, placed.column, message));
+            }
+            nodes.push(self.node()?);
+        }
+        // Blank lines before a dedent belong to the level it returns to
+        while matches!(nodes.last(), Some(Node::Blank)) && self.next < self.tokens.len() {
+            nodes.pop();
+            self.next -= 1;
+        }
+        check_mixed(&nodes, &self.tokens[..self.next])?;
+        Ok(nodes)
+    }
+
+    fn node(&mut self) -> Result<Node, Error> {
+        let placed = self.tokens[self.next].clone();
+        self.next += 1;
+        let column = placed.column;
+        match placed.token {
+            Token::Dash => Ok(Node::Item(self.siblings(&|t| t.column > column)?)),
+            Token::Block(lines) => Ok(Node::Block(lines)),
+            Token::Blank => Ok(Node::Blank),
+            Token::Text(text) if text.starts_with('#') => Ok(Node::Line(text, Vec::new())),
+            Token::Text(text) => {
+                // `key:` may have its sequence's dashes at its own indentation
+                let compact = text.ends_with(':') && key(&text).is_some();
+                let start = self.next;
+                let children = self.siblings(&|t| {
+                    t.column > column
+                        || compact && t.column == column && matches!(t.token, Token::Dash)
+                })?;
+                if holds_value(&text) && !opens_block(&text) {
+                    if let Some(nested) = children.iter().find_map(nested) {
+                        let below = self.tokens[start..self.next]
+                            .iter()
+                            .find(|t| !matches!(t.token, Token::Blank))
+                            .map_or((placed.row, column), |t| (t.row, t.column));
+                        return Err(Error::new(below.0, below.1, nested));
+                    }
+                }
+                Ok(Node::Line(text, children))
+            }
+        }
+    }
+}
+
+/// What a line holding a value can't have below it, if `child` is one
+fn nested(child: &Node) -> Option<&'static str> {
+    match child {
+        Node::Item(_) => Some("a value can't have a sequence below it"),
+        Node::Line(text, _) if key(text).is_some() => Some("a value can't have keys below it"),
+        _ => None,
+    }
+}
+
+/// Sequence items and keys can't share a level
+fn check_mixed(nodes: &[Node], tokens: &[Placed]) -> Result<(), Error> {
+    let items = nodes.iter().any(|node| matches!(node, Node::Item(_)));
+    let keys = nodes
+        .iter()
+        .any(|node| matches!(node, Node::Line(text, _) if key(text).is_some()));
+    if items && keys {
+        let at = tokens.last().map_or((0, 0), |t| (t.row, t.column));
+        let message = "sequence items and keys can't be at the same level";
+        return Err(Error::new(at.0, at.1, message));
+    }
+    Ok(())
+}
+
+/// Each mapping's keys in order, comments going with the key below them
+/// and blank lines between them left out
+fn sort_keys(nodes: &mut Vec<Node>) {
+    for node in nodes.iter_mut() {
+        match node {
+            Node::Item(children) | Node::Line(_, children) => sort_keys(children),
+            Node::Block(_) | Node::Blank => {}
+        }
+    }
+    let is_key = |node: &Node| matches!(node, Node::Line(text, _) if key(text).is_some());
+    if !nodes.iter().any(is_key) {
+        return;
+    }
+
+    let mut entries: Vec<Vec<Node>> = Vec::new();
+    let mut pending = Vec::new();
+    for node in nodes.drain(..) {
+        match node {
+            Node::Blank => {}
+            node if is_key(&node) => {
+                pending.push(node);
+                entries.push(std::mem::take(&mut pending));
+            }
+            node => pending.push(node),
+        }
+    }
+    entries.sort_by(|a, b| match (a.last(), b.last()) {
+        (Some(Node::Line(a, _)), Some(Node::Line(b, _))) => key(a).cmp(&key(b)),
+        _ => Ordering::Equal,
+    });
+    nodes.extend(entries.into_iter().flatten());
+    nodes.extend(pending);
+}
+
+fn write(nodes: &[Node], column: usize, indent: usize, lines: &mut Vec<String>) {
+    let pad = " ".repeat(column);
+    for node in nodes {
+        match node {
+            Node::Blank => {
+                if lines.last().is_some_and(|line| !line.is_empty()) {
+                    lines.push(String::new());
+                }
+            }
+            Node::Line(text, children) => {
+                lines.push(format!("{}{}", pad, text));
+                write(children, column + indent, indent, lines);
+            }
+            Node::Block(block) => lines.extend(block.iter().map(|line| {
+                if line.is_empty() {
+                    String::new()
+                } else {
+                    format!("{}{}", pad, line)
+                }
+            })),
+            Node::Item(children) => {
+                // What the item holds starts on the dash's line unless it
+                // opens with a comment or a blank line
+                let inline = match children.first() {
+                    Some(Node::Line(text, _)) => !text.starts_with('#'),
+                    Some(Node::Item(_)) => true,
+                    _ => false,
+                };
+                let first = lines.len();
+                if !inline {
+                    lines.push(format!("{}-", pad));
+                }
+                write(children, column + 2, indent, lines);
+                if inline {
+                    lines[first].replace_range(..column + 2, &format!("{}- ", pad));
+                }
+            }
+        }
+    }
+}
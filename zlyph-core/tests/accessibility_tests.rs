@@ -0,0 +1,86 @@
+use zlyph_core::accessibility::{self, Announcer};
+use zlyph_core::config::AccessibilityConfig;
+use zlyph_core::{EditorAction, EditorEngine};
+
+/// An announcer that speaks through a command doing nothing
+fn announcer() -> Announcer {
+    Announcer::new(AccessibilityConfig {
+        enabled: true,
+        speak_command: Some("true".to_string()),
+        speak_args: Vec::new(),
+    })
+}
+
+#[test]
+fn test_descriptions() {
+    assert_eq!(accessibility::describe_line(3, "hello"), "Line 4, hello");
+    assert_eq!(accessibility::describe_line(0, "  "), "Line 1, blank");
+    assert_eq!(accessibility::describe_char(' '), "space");
+    assert_eq!(accessibility::describe_char('é'), "é");
+}
+
+#[test]
+fn test_announces_cursor_movement() {
+    let mut engine = EditorEngine::new();
+    engine.load_text("ab\n\nc d");
+    let mut announcer = announcer();
+    // The first update only takes note of where the cursor is
+    assert_eq!(announcer.update(engine.state(), None), None);
+
+    engine.handle_action(EditorAction::MoveRight);
+    assert_eq!(announcer.update(engine.state(), None).as_deref(), Some("b"));
+    engine.handle_action(EditorAction::MoveRight);
+    assert_eq!(
+        announcer.update(engine.state(), None).as_deref(),
+        Some("End of line")
+    );
+    engine.handle_action(EditorAction::MoveDown);
+    assert_eq!(
+        announcer.update(engine.state(), None).as_deref(),
+        Some("Line 2, blank")
+    );
+    assert_eq!(announcer.update(engine.state(), None), None);
+    assert_eq!(announcer.latest(), Some("Line 2, blank"));
+}
+
+#[test]
+fn test_announces_selection_typing_and_status() {
+    let mut engine = EditorEngine::new();
+    engine.load_text("one\ntwo");
+    let mut announcer = announcer();
+    announcer.update(engine.state(), None);
+
+    engine.handle_action(EditorAction::SelectDown);
+    assert_eq!(
+        announcer.update(engine.state(), None).as_deref(),
+        Some("4 characters selected")
+    );
+    engine.handle_action(EditorAction::MoveLeft);
+    assert_eq!(
+        announcer.update(engine.state(), None).as_deref(),
+        Some("Selection cleared")
+    );
+
+    engine.handle_action(EditorAction::TypeString("x".to_string()));
+    assert_eq!(announcer.update(engine.state(), None).as_deref(), Some("x"));
+
+    assert_eq!(
+        announcer
+            .update(engine.state(), Some("Find in files"))
+            .as_deref(),
+        Some("Find in files")
+    );
+    // Closing the panel goes unannounced
+    assert_eq!(announcer.update(engine.state(), None), None);
+}
+
+#[test]
+fn test_disabled_by_default() {
+    let mut engine = EditorEngine::new();
+    engine.load_text("ab");
+    let mut announcer = Announcer::new(AccessibilityConfig::default());
+    announcer.update(engine.state(), None);
+    engine.handle_action(EditorAction::MoveRight);
+    assert_eq!(announcer.update(engine.state(), None), None);
+    assert_eq!(announcer.latest(), None);
+}
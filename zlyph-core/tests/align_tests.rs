@@ -0,0 +1,103 @@
+mod common;
+
+use common::lines;
+use zlyph_core::align;
+use zlyph_core::{BufferPosition, EditorAction, EditorEngine};
+
+#[test]
+fn test_align_assignments() {
+    let aligned = align::align(
+        &lines("  x = 1\n  width=80\n  // no delimiter\n  a == b\n  ok = x>=3 || x!=4"),
+        "=",
+    );
+    assert_eq!(
+        aligned,
+        [
+            "  x     = 1",
+            "  width = 80",
+            "  // no delimiter",
+            "  a == b",
+            "  ok    = x>=3 || x!=4"
+        ]
+    );
+
+    // Compound operators aren't the separator
+    assert_eq!(
+        align::align(
+            &lines(
+                "x += 1
+longer = 2"
+            ),
+            "="
+        ),
+        ["x += 1", "longer = 2"]
+    );
+    assert_eq!(
+        align::align(
+            &lines(
+                "a => 1
+bb = c => 2"
+            ),
+            "="
+        ),
+        ["a => 1", "bb = c => 2"]
+    );
+    assert_eq!(
+        align::align(
+            &lines(
+                "a::b = 1
+key: a::b"
+            ),
+            ":"
+        ),
+        ["a::b = 1", "key: a::b"]
+    );
+}
+
+#[test]
+fn test_align_keys_and_columns() {
+    let keys = align::align(
+        &lines("name: zlyph\nversion: 0.1\nurl: http://example.com"),
+        ":",
+    );
+    assert_eq!(
+        keys,
+        [
+            "name:    zlyph",
+            "version: 0.1",
+            "url:     http://example.com"
+        ]
+    );
+
+    let csv = align::align(&lines("a,bb,c\nlonger,b,cc\nx"), ",");
+    assert_eq!(csv, ["a,      bb, c", "longer, b,  cc", "x"]);
+
+    let pipes = align::align(&lines("| a | bbb |\n| cc | d |"), "|");
+    assert_eq!(pipes, ["| a  | bbb |", "| cc | d   |"]);
+
+    // Wide characters take two columns
+    let wide = align::align(&lines("名前 | x\né | y"), "|");
+    assert_eq!(wide, ["名前 | x", "é    | y"]);
+}
+
+#[test]
+fn test_engine_aligns_selection_or_paragraph() {
+    let mut engine = EditorEngine::new();
+    engine.load_text("a = 1\nbbb = 2\nc = 3\n\nd = 4");
+    engine.handle_action(EditorAction::SetCursorPosition { row: 0, column: 0 });
+    engine.handle_action(EditorAction::ExtendSelection { row: 2, column: 0 });
+    engine.handle_action(EditorAction::AlignOn("=".to_string()));
+    assert_eq!(engine.text(), "a   = 1\nbbb = 2\nc = 3\n\nd = 4");
+    assert_eq!(engine.selected_text().as_deref(), Some("a   = 1\nbbb = 2"));
+
+    engine.handle_action(EditorAction::SetCursorPosition { row: 2, column: 0 });
+    engine.handle_action(EditorAction::AlignOn("=".to_string()));
+    assert_eq!(engine.text(), "a   = 1\nbbb = 2\nc   = 3\n\nd = 4");
+    assert_eq!(engine.state().cursor, BufferPosition::new(2, 7));
+
+    engine.handle_action(EditorAction::Undo);
+    assert_eq!(engine.state().lines[2], "c = 3");
+
+    engine.handle_action(EditorAction::AlignOn(";".to_string()));
+    assert_eq!(engine.hover(), Some("No lines contain ';'"));
+}
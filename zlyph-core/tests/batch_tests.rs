@@ -0,0 +1,215 @@
+mod common;
+
+use common::temp_dir;
+use zlyph_core::batch::{apply_to_text, parse_script, run, Step};
+use zlyph_core::ex::{Address, Command, LineRange};
+use zlyph_core::{BufferPosition, EditorAction, EditorEngine};
+
+fn engine_with(text: &str) -> EditorEngine {
+    let mut engine = EditorEngine::new();
+    engine.load_text(text);
+    engine
+}
+
+#[test]
+fn test_parse_ranges_and_commands() {
+    assert!(matches!(
+        Command::parse(":42").unwrap(),
+        Command::Goto(Address::Line(42))
+    ));
+    assert!(matches!(
+        Command::parse("$").unwrap(),
+        Command::Goto(Address::Last)
+    ));
+    assert!(matches!(
+        Command::parse("2,$d").unwrap(),
+        Command::Delete(Some(LineRange::Lines(Address::Line(2), Address::Last)))
+    ));
+    assert!(matches!(
+        Command::parse("%s/a/b/g").unwrap(),
+        Command::Substitute {
+            range: Some(LineRange::Whole),
+            global: true,
+            ..
+        }
+    ));
+    assert!(Command::parse("").is_err());
+    assert!(Command::parse("x").is_err());
+    assert!(Command::parse("s/a/b/q").is_err());
+    assert!(Command::parse("s/(/b/").is_err());
+}
+
+#[test]
+fn test_substitute() {
+    let mut engine = engine_with("cat cat\ndog cat\n  Cat");
+    Command::parse("s/cat/dog/")
+        .unwrap()
+        .execute(&mut engine)
+        .unwrap();
+    assert_eq!(engine.state().lines[0], "dog cat");
+
+    Command::parse("%s#CAT#(&)#gi")
+        .unwrap()
+        .execute(&mut engine)
+        .unwrap();
+    assert_eq!(engine.state().lines, ["dog (cat)", "dog (cat)", "  (Cat)"]);
+    assert_eq!(engine.state().cursor, BufferPosition::new(2, 2));
+
+    Command::parse(r"1,2s/(\w+) \((\w+)\)/\2 \1 \/ $5/")
+        .unwrap()
+        .execute(&mut engine)
+        .unwrap();
+    assert_eq!(engine.state().lines[..2], ["cat dog / $5", "cat dog / $5"]);
+
+    assert!(Command::parse("s/missing/x/")
+        .unwrap()
+        .execute(&mut engine)
+        .is_err());
+}
+
+#[test]
+fn test_substitute_options() {
+    let mut engine = engine_with("Color colors\nCOLOR color");
+    Command::parse("%s/color/colour/gwp")
+        .unwrap()
+        .execute(&mut engine)
+        .unwrap();
+    assert_eq!(engine.state().lines, ["Colour colors", "COLOUR colour"]);
+
+    // Counting leaves the text alone
+    Command::parse("%s/colou?r/x/gin")
+        .unwrap()
+        .execute(&mut engine)
+        .unwrap();
+    assert_eq!(engine.hover(), Some("4 matches on 2 lines"));
+    assert_eq!(engine.state().lines, ["Colour colors", "COLOUR colour"]);
+
+    // Only the selected part of a line is replaced
+    engine.handle_action(EditorAction::StartSelection { row: 1, column: 3 });
+    engine.handle_action(EditorAction::ExtendSelection { row: 1, column: 13 });
+    Command::parse("'<,'>s/colour/hue/gi")
+        .unwrap()
+        .execute(&mut engine)
+        .unwrap();
+    assert_eq!(engine.state().lines, ["Colour colors", "COLOUR hue"]);
+}
+
+#[test]
+fn test_delete_is_one_undo_step() {
+    let mut engine = engine_with("one\ntwo\nthree\nfour");
+    Command::parse("2,3d")
+        .unwrap()
+        .execute(&mut engine)
+        .unwrap();
+    assert_eq!(engine.state().lines, ["one", "four"]);
+    assert_eq!(engine.state().cursor, BufferPosition::new(1, 0));
+
+    engine.handle_action(EditorAction::Undo);
+    assert_eq!(engine.state().lines, ["one", "two", "three", "four"]);
+
+    Command::parse("%d").unwrap().execute(&mut engine).unwrap();
+    assert_eq!(engine.state().lines, [""]);
+}
+
+#[test]
+fn test_script_mixes_actions_and_ex_commands() {
+    let steps =
+        parse_script(r#"[{"ex": "2"}, "MoveToEndOfLine", {"TypeString": "!"}, {"ex": "1d"}]"#)
+            .unwrap();
+    assert_eq!(steps[1], Step::Action(EditorAction::MoveToEndOfLine));
+
+    let mut engine = engine_with("a\nb\nc");
+    run(&mut engine, &steps).unwrap();
+    assert_eq!(engine.state().lines, ["b!", "c"]);
+
+    let bad = parse_script(r#"[{"ex": "1d"}, {"ex": "nope"}]"#).unwrap();
+    let error = run(&mut engine, &bad).unwrap_err();
+    assert!(error.to_string().starts_with("step 2 (nope)"));
+    assert!(parse_script(r#"["NotAnAction"]"#).is_err());
+}
+
+#[test]
+fn test_apply_to_text_keeps_line_endings() {
+    let steps = vec![Step::Ex {
+        ex: "%s/a/b/g".to_string(),
+    }];
+    assert_eq!(apply_to_text("a\r\na\r\n", &steps).unwrap(), "b\r\nb\r\n");
+    assert_eq!(apply_to_text("a a", &steps).unwrap(), "b b");
+}
+
+#[test]
+fn test_parse_file_commands() {
+    assert!(matches!(
+        Command::parse(":w").unwrap(),
+        Command::Write(None)
+    ));
+    assert!(matches!(
+        Command::parse("w  out.txt ").unwrap(),
+        Command::Write(Some(path)) if path == std::path::Path::new("out.txt")
+    ));
+    assert!(matches!(Command::parse("q").unwrap(), Command::Quit));
+    assert!(matches!(Command::parse(":wq").unwrap(), Command::WriteQuit));
+    assert!(matches!(
+        Command::parse("e ~/notes/today.md").unwrap(),
+        Command::Edit(path) if path == std::path::Path::new("~/notes/today.md")
+    ));
+    assert!(Command::parse("e").is_err());
+    assert!(Command::parse("q now").is_err());
+    assert!(Command::parse("%w").is_err());
+}
+
+#[test]
+fn test_write_needs_a_path_without_a_window() {
+    let path = temp_dir("write").join("out.txt");
+    let mut engine = engine_with("one\ntwo");
+    Command::parse(&format!("w {}", path.display()))
+        .unwrap()
+        .execute(&mut engine)
+        .unwrap();
+    assert_eq!(std::fs::read_to_string(&path).unwrap(), "one\ntwo");
+    let _ = std::fs::remove_file(&path);
+
+    let error = Command::parse("q")
+        .unwrap()
+        .execute(&mut engine)
+        .unwrap_err();
+    assert_eq!(error.kind(), std::io::ErrorKind::Unsupported);
+}
+
+#[test]
+fn test_align_command() {
+    let mut engine = engine_with("a = 1\nlong = 2\n\nx = 3");
+    assert!(Command::parse("align").is_err());
+    Command::parse("align =")
+        .unwrap()
+        .execute(&mut engine)
+        .unwrap();
+    assert_eq!(engine.state().lines, ["a    = 1", "long = 2", "", "x = 3"]);
+
+    Command::parse("%align =")
+        .unwrap()
+        .execute(&mut engine)
+        .unwrap();
+    assert_eq!(engine.state().lines[3], "x    = 3");
+}
+
+#[test]
+fn test_format_command() {
+    let mut engine = engine_with("title: x\nlist:\n    - a: 1\n      b: 2");
+    assert!(Command::parse("format xml").is_err());
+    Command::parse("format yaml")
+        .unwrap()
+        .execute(&mut engine)
+        .unwrap();
+    assert_eq!(
+        engine.state().lines,
+        ["title: x", "list:", "  - a: 1", "    b: 2"]
+    );
+
+    let mut engine = engine_with("a = 1\n{\"b\":2}");
+    Command::parse("2format json")
+        .unwrap()
+        .execute(&mut engine)
+        .unwrap();
+    assert_eq!(engine.state().lines, ["a = 1", "{", "  \"b\": 2", "}"]);
+}
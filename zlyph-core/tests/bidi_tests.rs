@@ -0,0 +1,88 @@
+use zlyph_core::bidi::{self, Layout};
+use zlyph_core::config::CursorConfig;
+use zlyph_core::{Config, EditorAction, EditorEngine};
+
+#[test]
+fn test_has_rtl() {
+    assert!(!bidi::has_rtl("plain text"));
+    assert!(!bidi::has_rtl("café ☕"));
+    assert!(bidi::has_rtl("hello שלום"));
+    assert!(bidi::has_rtl("مرحبا"));
+}
+
+#[test]
+fn test_layout_reverses_rtl_runs() {
+    let layout = Layout::new("ab אבג cd");
+    assert_eq!(layout.text, "ab גבא cd");
+    // Brackets in right-to-left text are mirrored
+    assert_eq!(Layout::new("א(ב)").text, "(ב)א");
+    assert_eq!(Layout::new("plain").text, "plain");
+    assert_eq!(Layout::new("").text, "");
+}
+
+#[test]
+fn test_caret_positions() {
+    let line = "ab אבג";
+    let layout = Layout::new(line);
+    let alef = line.find('א').unwrap();
+    let gimel = line.find('ג').unwrap();
+    // Before alef, the caret is at its right edge, the far right
+    assert_eq!(layout.display_index(alef), layout.text.len());
+    // Before gimel, it is at the right edge of gimel, shown first
+    assert_eq!(layout.display_index(gimel), "ab ג".len());
+    // The end of the line follows the last character typed, gimel
+    assert_eq!(layout.display_index(line.len()), "ab ".len());
+    assert_eq!(layout.display_index(0), 0);
+
+    for column in [0, 1, 2, alef, gimel, line.len()] {
+        assert_eq!(layout.column(layout.display_index(column)), column);
+    }
+}
+
+#[test]
+fn test_selection_crossing_directions() {
+    let line = "ab אבג";
+    let layout = Layout::new(line);
+    let bet = line.find('ב').unwrap();
+    // "b" through alef, which sits at the far right
+    let ranges = layout.display_ranges(1..bet);
+    assert_eq!(ranges, vec![1..3, "ab גב".len()..layout.text.len()]);
+}
+
+#[test]
+fn test_move_visually() {
+    let line = "ab אבג";
+    let layout = Layout::new(line);
+    let alef = line.find('א').unwrap();
+    let bet = line.find('ב').unwrap();
+    // Moving right from before alef reaches the far right edge and stops
+    assert_eq!(layout.move_visually(alef, false), None);
+    // Moving left from before alef goes into the word, before bet
+    assert_eq!(layout.move_visually(alef, true), Some(bet));
+    assert_eq!(layout.move_visually(0, true), None);
+    assert_eq!(layout.move_visually(0, false), Some(1));
+}
+
+#[test]
+fn test_engine_visual_movement() {
+    let mut engine = EditorEngine::new();
+    engine.load_text("אב");
+    engine.handle_action(EditorAction::MoveRight);
+    assert_eq!(engine.state().cursor.column, "א".len());
+
+    engine.set_config(Config {
+        cursor: CursorConfig {
+            visual_movement: true,
+            ..Default::default()
+        },
+        ..Default::default()
+    });
+    engine.handle_action(EditorAction::MoveToBeginningOfLine);
+    // Right to left, the start of the line is at the right edge
+    engine.handle_action(EditorAction::MoveLeft);
+    assert_eq!(engine.state().cursor.column, "א".len());
+    engine.handle_action(EditorAction::MoveLeft);
+    assert_eq!(engine.state().cursor.column, "אב".len());
+    engine.handle_action(EditorAction::MoveRight);
+    assert_eq!(engine.state().cursor.column, "א".len());
+}
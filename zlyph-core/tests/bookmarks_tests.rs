@@ -0,0 +1,111 @@
+use zlyph_core::bookmarks::{self, BookmarksPanel};
+use zlyph_core::decorations::Tone;
+use zlyph_core::{EditorAction, EditorEngine};
+
+const TEXT: &str = "one\ntwo\nsix\nfour";
+
+fn goto(engine: &mut EditorEngine, row: usize, column: usize) {
+    engine.handle_action(EditorAction::SetCursorPosition { row, column });
+}
+
+#[test]
+fn test_toggling_bookmarks_the_cursor_line() {
+    let mut engine = EditorEngine::builder().text(TEXT).build();
+    goto(&mut engine, 2, 3);
+    engine.handle_action(EditorAction::ToggleBookmark);
+    goto(&mut engine, 0, 1);
+    engine.handle_action(EditorAction::ToggleBookmark);
+
+    assert_eq!(engine.bookmarks(), [0, 2]);
+    assert_eq!(
+        engine.gutter_icon(2),
+        Some((bookmarks::SYMBOL, Tone::Accent))
+    );
+    assert_eq!(engine.gutter_icon(1), None);
+    assert_eq!(engine.text(), TEXT);
+
+    engine.handle_action(EditorAction::ToggleBookmark);
+    assert_eq!(engine.bookmarks(), [2]);
+}
+
+#[test]
+fn test_next_and_previous_wrap_around() {
+    let mut engine = EditorEngine::builder().text(TEXT).build();
+    engine.set_bookmarks(&[1, 3]);
+    goto(&mut engine, 1, 2);
+
+    engine.handle_action(EditorAction::NextBookmark);
+    assert_eq!(engine.state().cursor.row, 3);
+    assert_eq!(engine.state().cursor.column, 0);
+    engine.handle_action(EditorAction::NextBookmark);
+    assert_eq!(engine.state().cursor.row, 1);
+    engine.handle_action(EditorAction::PrevBookmark);
+    assert_eq!(engine.state().cursor.row, 3);
+
+    assert_eq!(bookmarks::next(&[1, 3], 0, false), Some(3));
+    assert_eq!(bookmarks::next(&[], 0, true), None);
+}
+
+#[test]
+fn test_jumping_without_bookmarks_says_so() {
+    let mut engine = EditorEngine::builder().text(TEXT).build();
+    goto(&mut engine, 1, 1);
+    engine.handle_action(EditorAction::NextBookmark);
+    assert_eq!(engine.hover(), Some("No bookmarks"));
+    assert_eq!(engine.state().cursor.column, 1);
+}
+
+#[test]
+fn test_bookmarks_follow_their_lines() {
+    let mut engine = EditorEngine::builder().text(TEXT).build();
+    engine.set_bookmarks(&[1, 2, 9]);
+    assert_eq!(engine.bookmarks(), [1, 2]);
+
+    goto(&mut engine, 0, 0);
+    engine.handle_action(EditorAction::Newline);
+    assert_eq!(engine.bookmarks(), [2, 3]);
+
+    // Deleting a line takes its bookmark
+    goto(&mut engine, 2, 0);
+    engine.handle_action(EditorAction::DeleteLine);
+    assert_eq!(engine.text(), "\none\nsix\nfour");
+    assert_eq!(engine.bookmarks(), [2]);
+
+    // Like other decorations, undo moves bookmarks back but doesn't
+    // restore the deleted one
+    engine.handle_action(EditorAction::Undo);
+    assert_eq!(engine.bookmarks(), [3]);
+}
+
+#[test]
+fn test_bookmarks_keep_other_decorations() {
+    let mut engine = EditorEngine::builder().text(TEXT).build();
+    engine.set_decorations(
+        "lint",
+        vec![zlyph_core::decorations::Decoration::gutter(4, "!")],
+    );
+    engine.set_bookmarks(&[0]);
+    engine.set_bookmarks(&[]);
+    assert_eq!(engine.gutter_icon(1), Some(("!", Tone::Muted)));
+    assert!(engine.bookmarks().is_empty());
+}
+
+#[test]
+fn test_panel_lists_bookmarked_lines() {
+    let lines: Vec<String> = ["a", "  indented", "b", "c"]
+        .iter()
+        .map(|line| line.to_string())
+        .collect();
+    let mut panel = BookmarksPanel::new(&lines, &[1, 3], 2);
+
+    assert_eq!(panel.entries()[0].text, "indented");
+    assert_eq!(panel.selected_entry().unwrap().row, 3);
+    panel.select_next();
+    assert_eq!(panel.selected, 1);
+    panel.select_previous();
+    panel.select_previous();
+    assert_eq!(panel.selected_entry().unwrap().row, 1);
+
+    // Past the last bookmark the last one is selected
+    assert_eq!(BookmarksPanel::new(&lines, &[1], 3).selected, 0);
+}
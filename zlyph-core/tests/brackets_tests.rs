@@ -0,0 +1,105 @@
+mod common;
+
+use common::lines;
+use std::path::Path;
+use zlyph_core::brackets;
+use zlyph_core::highlight::{Highlighter, Style};
+
+/// Each run's text with its bracket depth and scope
+fn runs(text: &str) -> Vec<Vec<(String, Option<u8>, u8)>> {
+    let lines = lines(text);
+    brackets::highlight(&lines)
+        .iter()
+        .zip(&lines)
+        .map(|(spans, line)| {
+            spans
+                .iter()
+                .map(|(range, style)| (line[range.clone()].to_string(), style.bracket, style.scope))
+                .collect()
+        })
+        .collect()
+}
+
+fn run(text: &str, bracket: Option<u8>, scope: u8) -> (String, Option<u8>, u8) {
+    (text.to_string(), bracket, scope)
+}
+
+#[test]
+fn test_pairs_colored_by_depth_across_lines() {
+    assert_eq!(
+        runs("f(a[0], {\n  b\n})"),
+        [
+            vec![
+                run("(", Some(0), 0),
+                run("a", None, 1),
+                run("[", Some(1), 1),
+                run("0", None, 2),
+                run("]", Some(1), 1),
+                run(", ", None, 1),
+                run("{", Some(1), 1),
+            ],
+            vec![run("  b", None, 2)],
+            vec![run("}", Some(1), 1), run(")", Some(0), 0)],
+        ]
+    );
+}
+
+#[test]
+fn test_strings_and_strays_are_left_out() {
+    assert_eq!(
+        runs(r#"(a ")" `[` \( ] b)"#),
+        [vec![
+            run("(", Some(0), 0),
+            run(r#"a ")" `[` \( ] b"#, None, 1),
+            run(")", Some(0), 0),
+        ]]
+    );
+    // An unclosed bracket takes no depth from the pair after it
+    assert_eq!(
+        runs("( [x]"),
+        [vec![
+            run("[", Some(0), 0),
+            run("x", None, 1),
+            run("]", Some(0), 0)
+        ]]
+    );
+}
+
+#[test]
+fn test_brackets_laid_over_markdown() {
+    let text = lines("*a (b)*");
+    let mut highlighter = Highlighter::new();
+    highlighter.update(Some(Path::new("a.md")), &text, 1);
+    let before = highlighter.line(0).unwrap().spans.clone();
+    assert!(before.iter().all(|(_, style)| style.bracket.is_none()));
+
+    highlighter.set_brackets(true);
+    highlighter.update(Some(Path::new("a.md")), &text, 1);
+    let spans = &highlighter.line(0).unwrap().spans;
+    let italic = Style {
+        italic: true,
+        ..Style::default()
+    };
+    assert!(spans.contains(&(
+        3..4,
+        Style {
+            bracket: Some(0),
+            ..italic
+        }
+    )));
+    assert!(spans.contains(&(4..5, Style { scope: 1, ..italic })));
+
+    // Plain text files get brackets too
+    highlighter.update(Some(Path::new("a.txt")), &text, 1);
+    let spans = &highlighter.line(0).unwrap().spans;
+    assert_eq!(
+        spans[0],
+        (
+            3..4,
+            Style {
+                bracket: Some(0),
+                ..Style::default()
+            }
+        )
+    );
+}
@@ -0,0 +1,89 @@
+use chrono::NaiveDate;
+use zlyph_core::calc;
+use zlyph_core::{BufferPosition, EditorAction, EditorEngine};
+
+fn eval(expression: &str) -> Result<String, String> {
+    let today = NaiveDate::from_ymd_opt(2026, 10, 17).unwrap();
+    calc::evaluate(expression, today).map(|value| value.to_string())
+}
+
+#[test]
+fn test_arithmetic() {
+    assert_eq!(eval("1 + 2 * 3").as_deref(), Ok("7"));
+    assert_eq!(eval("(1 + 2) * 3").as_deref(), Ok("9"));
+    assert_eq!(eval("-2^2").as_deref(), Ok("-4"));
+    assert_eq!(eval("2^3^2").as_deref(), Ok("512"));
+    assert_eq!(eval("10 / 4").as_deref(), Ok("2.5"));
+    assert_eq!(eval("1/3").as_deref(), Ok("0.3333333333"));
+    assert_eq!(eval("1,200 * 12").as_deref(), Ok("14400"));
+    assert_eq!(eval("80 * 15%").as_deref(), Ok("12"));
+    assert_eq!(eval("6 × 7").as_deref(), Ok("42"));
+    assert!(eval("1 / 0").is_err());
+    assert!(eval("2 +").is_err());
+    assert!(eval("(1 + 2").is_err());
+    assert!(eval("").is_err());
+}
+
+#[test]
+fn test_units() {
+    assert_eq!(eval("3 km + 500 m").as_deref(), Ok("3.5 km"));
+    assert_eq!(eval("90 min in h").as_deref(), Ok("1.5 h"));
+    assert_eq!(eval("1 ft in in").as_deref(), Ok("12 in"));
+    assert_eq!(eval("2 in to cm").as_deref(), Ok("5.08 cm"));
+    assert_eq!(eval("1.5 GiB to MB").as_deref(), Ok("1610.612736 MB"));
+    assert_eq!(eval("10 km / 2").as_deref(), Ok("5 km"));
+    assert_eq!(eval("1 km / 250 m").as_deref(), Ok("4"));
+    assert_eq!(eval("3 days + 1 week").as_deref(), Ok("10 days"));
+    assert!(eval("1 kg + 1 m").is_err());
+    assert!(eval("2 m * 3 m").is_err());
+    assert!(eval("5 parsecs").is_err());
+}
+
+#[test]
+fn test_dates() {
+    assert_eq!(eval("2026-03-01 + 2 weeks").as_deref(), Ok("2026-03-15"));
+    assert_eq!(eval("2026-03-01 - 1 day").as_deref(), Ok("2026-02-28"));
+    assert_eq!(eval("2026-12-25 - today").as_deref(), Ok("69 days"));
+    assert_eq!(eval("today + 30").as_deref(), Ok("2026-11-16"));
+    assert_eq!(eval("2026-10-18 - today").as_deref(), Ok("1 day"));
+    assert!(eval("today + 3 h").is_err());
+    assert!(eval("today + today").is_err());
+}
+
+#[test]
+fn test_engine_evaluates_selection_or_line() {
+    // The selection is replaced by its result
+    let mut engine = EditorEngine::new();
+    engine.load_text("total: 12 * 4 items");
+    engine.handle_action(EditorAction::SetCursorPosition { row: 0, column: 7 });
+    engine.handle_action(EditorAction::ExtendSelection { row: 0, column: 13 });
+    engine.handle_action(EditorAction::EvaluateSelection);
+    assert_eq!(engine.text(), "total: 48 items");
+    assert_eq!(engine.state().cursor, BufferPosition::new(0, 9));
+
+    engine.handle_action(EditorAction::Undo);
+    assert_eq!(engine.text(), "total: 12 * 4 items");
+
+    // A selection ending in `=` keeps the expression
+    engine.load_text("2 + 2 =");
+    engine.handle_action(EditorAction::SelectAll);
+    engine.handle_action(EditorAction::EvaluateSelection);
+    assert_eq!(engine.text(), "2 + 2 = 4");
+
+    // Without a selection the line gets its result, updated when run again
+    engine.load_text("3 ft in cm");
+    engine.handle_action(EditorAction::EvaluateSelection);
+    assert_eq!(engine.text(), "3 ft in cm = 91.44 cm");
+    engine.handle_action(EditorAction::MoveToBeginningOfLine);
+    engine.handle_action(EditorAction::Delete);
+    engine.handle_action(EditorAction::TypeCharacter('2'));
+    engine.handle_action(EditorAction::EvaluateSelection);
+    assert_eq!(engine.text(), "2 ft in cm = 60.96 cm");
+    assert_eq!(engine.state().cursor.column, "2 ft in cm = 60.96 cm".len());
+
+    // Errors leave the text alone
+    engine.load_text("2 + banana");
+    engine.handle_action(EditorAction::EvaluateSelection);
+    assert_eq!(engine.text(), "2 + banana");
+    assert!(engine.hover().is_some_and(|hover| hover.contains("banana")));
+}
@@ -0,0 +1,163 @@
+mod common;
+
+use common::temp_dir;
+use std::sync::mpsc::Receiver;
+use zlyph_core::{BufferPosition, BufferRange, ChangeEvent, EditorAction, EditorEngine};
+
+fn drain(events: &Receiver<ChangeEvent>) -> Vec<ChangeEvent> {
+    events.try_iter().collect()
+}
+
+fn range(start: (usize, usize), end: (usize, usize)) -> BufferRange {
+    BufferRange::new(
+        BufferPosition::new(start.0, start.1),
+        BufferPosition::new(end.0, end.1),
+    )
+}
+
+#[test]
+fn test_typing_reports_insertion_and_cursor() {
+    let mut engine = EditorEngine::builder().text("hello\nworld").build();
+    let events = engine.subscribe();
+
+    engine.handle_action(EditorAction::SetCursorPosition { row: 1, column: 5 });
+    engine.handle_action(EditorAction::TypeCharacter('!'));
+
+    assert_eq!(
+        drain(&events),
+        vec![
+            ChangeEvent::CursorMoved {
+                cursor: BufferPosition::new(1, 5)
+            },
+            ChangeEvent::Replaced {
+                range: range((1, 5), (1, 5)),
+                text: "!".to_string()
+            },
+            ChangeEvent::CursorMoved {
+                cursor: BufferPosition::new(1, 6)
+            },
+        ]
+    );
+}
+
+#[test]
+fn test_deleting_across_lines_reports_old_range() {
+    let mut engine = EditorEngine::builder().text("one\ntwo\nthree").build();
+    engine.handle_action(EditorAction::SetCursorPosition { row: 0, column: 2 });
+    engine.handle_action(EditorAction::ExtendSelection { row: 2, column: 1 });
+    let events = engine.subscribe();
+
+    engine.handle_action(EditorAction::Backspace);
+
+    let events = drain(&events);
+    assert_eq!(
+        events[0],
+        ChangeEvent::Replaced {
+            range: range((0, 2), (2, 1)),
+            text: String::new()
+        }
+    );
+    assert!(events.contains(&ChangeEvent::SelectionChanged { selection: None }));
+    assert_eq!(engine.text(), "onhree");
+}
+
+#[test]
+fn test_selection_changes_are_reported() {
+    let mut engine = EditorEngine::builder().text("abc").build();
+    let events = engine.subscribe();
+
+    engine.handle_action(EditorAction::SelectRight);
+    engine.handle_action(EditorAction::SelectRight);
+
+    let selections: Vec<_> = drain(&events)
+        .into_iter()
+        .filter_map(|event| match event {
+            ChangeEvent::SelectionChanged { selection } => Some(selection),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(
+        selections,
+        vec![Some(range((0, 0), (0, 1))), Some(range((0, 0), (0, 2)))]
+    );
+}
+
+#[test]
+fn test_undo_and_merge_report_replacements() {
+    let mut engine = EditorEngine::builder().text("cat").build();
+    engine.handle_action(EditorAction::MoveToEndOfLine);
+    engine.handle_action(EditorAction::TypeString("s".to_string()));
+    let events = engine.subscribe();
+
+    engine.handle_action(EditorAction::Undo);
+    engine.merge_text("bat");
+
+    let replacements: Vec<_> = drain(&events)
+        .into_iter()
+        .filter(|event| matches!(event, ChangeEvent::Replaced { .. }))
+        .collect();
+    assert_eq!(
+        replacements,
+        vec![
+            ChangeEvent::Replaced {
+                range: range((0, 3), (0, 4)),
+                text: String::new()
+            },
+            ChangeEvent::Replaced {
+                range: range((0, 0), (0, 1)),
+                text: "b".to_string()
+            },
+        ]
+    );
+}
+
+#[test]
+fn test_load_and_save_are_reported() {
+    let dir = temp_dir("load-save");
+    let path = dir.join("note.txt");
+    std::fs::write(&path, "saved text").unwrap();
+
+    let mut engine = EditorEngine::new();
+    let events = engine.subscribe();
+    engine.load_from_file(&path).unwrap();
+    engine.save_to_file(&path).unwrap();
+    engine.load_text("fresh");
+
+    assert_eq!(
+        drain(&events),
+        vec![
+            ChangeEvent::Loaded {
+                path: Some(path.clone())
+            },
+            ChangeEvent::Saved { path: path.clone() },
+            ChangeEvent::Loaded { path: None },
+        ]
+    );
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_dropped_subscriber_is_forgotten() {
+    let mut engine = EditorEngine::new();
+    drop(engine.subscribe());
+    let events = engine.subscribe();
+
+    engine.handle_action(EditorAction::TypeCharacter('x'));
+    engine.handle_action(EditorAction::TypeCharacter('y'));
+
+    assert_eq!(drain(&events).len(), 4);
+}
+
+#[test]
+fn test_events_round_trip_through_json() {
+    let mut engine = EditorEngine::builder().text("ab").build();
+    let events = engine.subscribe();
+    engine.handle_action(EditorAction::SelectAll);
+    engine.handle_action(EditorAction::TypeCharacter('x'));
+    let sent = drain(&events);
+
+    let json = serde_json::to_string(&sent).unwrap();
+    let received: Vec<ChangeEvent> = serde_json::from_str(&json).unwrap();
+
+    assert_eq!(received, sent);
+}
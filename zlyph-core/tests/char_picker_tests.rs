@@ -0,0 +1,68 @@
+use zlyph_core::char_picker::{self, CharPicker};
+use zlyph_core::{EditorAction, EditorEngine};
+
+#[test]
+fn test_parse_codepoint_prefixes() {
+    assert_eq!(char_picker::parse_codepoint("1F680"), Some('🚀'));
+    assert_eq!(char_picker::parse_codepoint("U+2014"), Some('—'));
+    assert_eq!(char_picker::parse_codepoint("u+e9"), Some('é'));
+    assert_eq!(char_picker::parse_codepoint(" 0x41 "), Some('A'));
+    assert_eq!(char_picker::parse_codepoint("U+"), None);
+    assert_eq!(char_picker::parse_codepoint("D800"), None);
+    assert_eq!(char_picker::parse_codepoint("110000"), None);
+    assert_eq!(char_picker::parse_codepoint("rocket"), None);
+}
+
+#[test]
+fn test_search_by_shortcode() {
+    let matches = char_picker::search(":rocket:");
+    assert_eq!(matches[0].text, "🚀");
+    assert_eq!(matches[0].shortcode.as_deref(), Some("rocket"));
+    assert!(char_picker::search(":thumbs").iter().all(|symbol| symbol
+        .shortcode
+        .as_deref()
+        .unwrap()
+        .starts_with("thumbs")));
+}
+
+#[test]
+fn test_search_by_name() {
+    assert_eq!(char_picker::search("greek small letter alpha")[0].text, "α");
+    assert_eq!(char_picker::search("em dash")[0].text, "—");
+    let arrows = char_picker::search("rightwards arrow");
+    assert!(arrows.iter().any(|symbol| symbol.text == "→"));
+    assert!(char_picker::search("no such character zzz").is_empty());
+}
+
+#[test]
+fn test_picker_leads_with_typed_codepoint() {
+    let mut picker = CharPicker::new("U+");
+    assert!(!picker.codepoint_selected());
+    picker.type_text("2014");
+    assert!(picker.codepoint_selected());
+    assert_eq!(picker.selected_symbol().unwrap().text, "—");
+    assert_eq!(picker.selected_symbol().unwrap().name, "em dash");
+    for _ in 0.."U+2014".len() {
+        picker.backspace();
+    }
+    assert!(!picker.codepoint_selected());
+    // With no query the emoji are listed
+    assert!(picker.selected_symbol().unwrap().shortcode.is_some());
+
+    // Hex-looking words are searched by name, not read as code points
+    let picker = CharPicker::new("cafe");
+    assert!(!picker.codepoint_selected());
+}
+
+#[test]
+fn test_insert_unicode_codepoint() {
+    let mut engine = EditorEngine::new();
+    engine.handle_action(EditorAction::TypeString("go ".to_string()));
+    engine.handle_action(EditorAction::InsertUnicodeCodepoint("U+1F680".to_string()));
+    assert_eq!(engine.state().lines[0], "go 🚀");
+    assert_eq!(engine.state().cursor.column, "go 🚀".len());
+
+    engine.handle_action(EditorAction::InsertUnicodeCodepoint("zz".to_string()));
+    assert_eq!(engine.state().lines[0], "go 🚀");
+    assert!(engine.hover().is_some());
+}
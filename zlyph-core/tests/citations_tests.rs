@@ -0,0 +1,104 @@
+mod common;
+
+use common::{buffer_lines, temp_dir};
+use std::fs;
+use zlyph_core::citations::{self, Reference};
+use zlyph_core::config::{CitationsConfig, Config};
+use zlyph_core::{EditorAction, EditorEngine};
+
+const BIBTEX: &str = r#"
+@comment{ see me@example.com }
+@string{ jnl = "Journal of Tests" }
+@Article{smith2020,
+  author = {Smith, Jane and
+            Jones, Ann},
+  title = {On {Testing} Things},
+  journal = jnl,
+  year = 2020,
+}
+@book(lee1999, author = "Bo Lee and Kim Park and {Barnes and Noble}",
+  title = "A Book?", publisher = {Press})
+"#;
+
+fn engine_with_bibliography(text: &str) -> EditorEngine {
+    let path = temp_dir("bibliography").join("references.bib");
+    fs::write(&path, BIBTEX).unwrap();
+    let mut engine = EditorEngine::new();
+    engine.set_config(Config {
+        citations: CitationsConfig {
+            bibliography: Some(path.clone()),
+        },
+        ..Default::default()
+    });
+    fs::remove_file(path).ok();
+    engine.load_text(text);
+    engine
+}
+
+#[test]
+fn test_parse_bibtex_and_csl_json() {
+    let references = citations::parse_bibtex(BIBTEX);
+    assert_eq!(
+        references[0],
+        Reference {
+            key: "smith2020".to_string(),
+            authors: vec!["Smith".to_string(), "Jones".to_string()],
+            title: "On Testing Things".to_string(),
+            year: Some("2020".to_string()),
+            source: Some("jnl".to_string()),
+        }
+    );
+    assert_eq!(references[1].authors, ["Lee", "Park", "Barnes and Noble"]);
+    assert_eq!(references[1].summary(), "Lee et al. A Book? Press.");
+
+    let csl = citations::parse_csl_json(
+        r#"[{"id": "doe2001", "title": "Notes", "container-title": "Letters",
+             "author": [{"family": "Doe", "given": "J."}, {"literal": "WHO"}],
+             "issued": {"date-parts": [[2001, 5]]}}]"#,
+    );
+    assert_eq!(csl[0].summary(), "Doe and WHO (2001). Notes. Letters.");
+}
+
+#[test]
+fn test_citations_found_outside_words_and_code() {
+    assert_eq!(
+        citations::citations("See [@smith2020, p. 4] and @lee1999. Mail me@x.org"),
+        [6..15, 28..35]
+    );
+    assert_eq!(citations::key_at("x @smith2020.", 2), Some("smith2020"));
+    assert_eq!(citations::key_at("x @smith2020.", 0), None);
+    assert_eq!(
+        citations::cited_keys(&buffer_lines("@b then @a\n```\n@c\n```\n@b")),
+        ["b", "a"]
+    );
+}
+
+#[test]
+fn test_complete_hover_and_insert_bibliography() {
+    let mut engine = engine_with_bibliography("As shown by ");
+    engine.handle_action(EditorAction::SetCursorPosition { row: 0, column: 12 });
+    for c in "@smi".chars() {
+        engine.handle_action(EditorAction::TypeCharacter(c));
+    }
+    assert_eq!(engine.completion().unwrap().items, ["smith2020"]);
+    engine.handle_action(EditorAction::Tab);
+    engine.handle_action(EditorAction::TypeString(
+        " and @lee1999 and @nobody.".to_string(),
+    ));
+
+    engine.handle_action(EditorAction::SetCursorPosition { row: 0, column: 15 });
+    engine.handle_action(EditorAction::Hover);
+    assert_eq!(
+        engine.hover(),
+        Some("Smith and Jones (2020). On Testing Things. jnl.")
+    );
+
+    engine.handle_action(EditorAction::InsertBibliography);
+    assert_eq!(
+        engine.state().to_string(),
+        "As shown by @smith2020 and @lee1999 and @nobody.\n\n\
+         - Lee et al. A Book? Press.\n\
+         - Smith and Jones (2020). On Testing Things. jnl."
+    );
+    assert_eq!(engine.hover(), Some("Not in the bibliography: @nobody"));
+}
@@ -0,0 +1,74 @@
+#![cfg(feature = "code-image")]
+
+mod common;
+
+use common::lines;
+use std::path::Path;
+use zlyph_core::code_image::{self, ImageOptions};
+use zlyph_core::config::{CodeImageConfig, FontConfig};
+use zlyph_core::export::Palette;
+use zlyph_core::{BufferPosition, BufferRange};
+
+fn options(window_chrome: bool) -> ImageOptions {
+    let config = CodeImageConfig {
+        window_chrome,
+        ..CodeImageConfig::default()
+    };
+    ImageOptions::new(
+        Some(Path::new("/src/notes.md")),
+        &config,
+        &FontConfig::default(),
+    )
+}
+
+#[test]
+fn test_svg_draws_the_selection_in_a_window() {
+    let text = lines("# Title\nfirst <line>\nsecond\nthird");
+    let range = BufferRange::new(BufferPosition::new(1, 0), BufferPosition::new(2, 6));
+    let svg = code_image::svg(
+        &text,
+        Some(Path::new("notes.md")),
+        Some(range),
+        &Palette::default(),
+        &options(true),
+    );
+    assert!(svg.starts_with("<svg xmlns=\"http://www.w3.org/2000/svg\""));
+    assert!(svg.contains("rx=\"10\" fill=\"#282c34\""));
+    assert_eq!(svg.matches("<circle").count(), 3);
+    assert!(svg.contains(">notes.md</text>"));
+    assert!(svg.contains(">first &lt;line&gt;</text>"));
+    assert!(svg.contains(">second</text>"));
+    assert!(!svg.contains("third"));
+    assert!(!svg.contains("Title"));
+}
+
+#[test]
+fn test_svg_without_chrome() {
+    let svg = code_image::svg(
+        &lines("**bold**"),
+        Some(Path::new("notes.md")),
+        None,
+        &Palette::default(),
+        &options(false),
+    );
+    assert!(!svg.contains("<circle"));
+    assert!(svg.contains("font-weight=\"bold\">bold</text>"));
+    assert!(svg.contains("font-family=\"Monaco, Menlo, DejaVu Sans Mono, monospace\""));
+}
+
+#[test]
+fn test_png() {
+    let svg = code_image::svg(
+        &lines("fn main() {}"),
+        Some(Path::new("main.rs")),
+        None,
+        &Palette::default(),
+        &options(true),
+    );
+    let png = code_image::png(&svg, 1.0).unwrap();
+    assert!(png.starts_with(b"\x89PNG"));
+    assert_eq!(
+        code_image::image_path(Path::new("/src/main.rs")),
+        Path::new("/src/main.png")
+    );
+}
@@ -0,0 +1,29 @@
+//! Helpers shared by the integration tests; each test binary uses some of
+//! them
+#![allow(dead_code)]
+
+use std::fs;
+use std::path::PathBuf;
+
+pub fn lines(text: &str) -> Vec<String> {
+    text.lines().map(String::from).collect()
+}
+
+/// `text` split into lines as a buffer holds them, with an empty last line
+/// after a final newline
+pub fn buffer_lines(text: &str) -> Vec<String> {
+    text.split('\n').map(String::from).collect()
+}
+
+/// An empty directory for one test, named after the test binary and `name`
+pub fn temp_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!(
+        "zlyph-{}-{}-{}",
+        env!("CARGO_CRATE_NAME"),
+        name,
+        std::process::id()
+    ));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    dir.canonicalize().unwrap()
+}
@@ -0,0 +1,136 @@
+mod common;
+
+use common::temp_dir;
+use std::fs;
+use zlyph_core::completion::{fuzzy_score, rank};
+use zlyph_core::config::CompletionConfig;
+use zlyph_core::{Config, EditorAction, EditorEngine};
+
+fn engine_with_text(text: &str) -> EditorEngine {
+    let mut engine = EditorEngine::new();
+    engine.state_mut().lines = text.split('\n').map(String::from).collect();
+    let last_row = engine.state().lines.len() - 1;
+    let last_col = engine.state().lines[last_row].len();
+    engine.handle_action(EditorAction::SetCursorPosition {
+        row: last_row,
+        column: last_col,
+    });
+    engine
+}
+
+fn type_str(engine: &mut EditorEngine, s: &str) {
+    for c in s.chars() {
+        engine.handle_action(EditorAction::TypeCharacter(c));
+    }
+}
+
+#[test]
+fn test_fuzzy_ranking_prefers_prefix_matches() {
+    assert!(fuzzy_score("format", "fo") > fuzzy_score("Foreign", "fo"));
+    assert!(fuzzy_score("Foreign", "fo") > fuzzy_score("info", "fo"));
+    assert!(fuzzy_score("fmt_output", "fo").is_some());
+    assert_eq!(fuzzy_score("bar", "fo"), None);
+    assert_eq!(fuzzy_score("fo", "fo"), None);
+
+    let ranked = rank(["info", "format", "formatter", "Foreign"], "fo", 3);
+    assert_eq!(ranked, vec!["format", "formatter", "Foreign"]);
+}
+
+#[test]
+fn test_popup_opens_after_min_prefix() {
+    let mut engine = engine_with_text("hello helium world\n");
+
+    type_str(&mut engine, "h");
+    assert!(engine.completion().is_none());
+
+    type_str(&mut engine, "e");
+    let completion = engine.completion().unwrap();
+    assert_eq!(completion.prefix, "he");
+    assert_eq!(completion.items, vec!["hello", "helium"]);
+
+    type_str(&mut engine, " ");
+    assert!(engine.completion().is_none());
+}
+
+#[test]
+fn test_accept_completion_with_tab_and_enter() {
+    let mut engine = engine_with_text("alpha beta\n");
+    type_str(&mut engine, "al");
+    engine.handle_action(EditorAction::Tab);
+    assert_eq!(engine.state().lines[1], "alpha");
+    assert!(engine.completion().is_none());
+
+    engine.handle_action(EditorAction::TypeCharacter(' '));
+    type_str(&mut engine, "be");
+    engine.handle_action(EditorAction::Newline);
+    assert_eq!(engine.state().lines.len(), 2);
+    assert_eq!(engine.state().lines[1], "alpha beta");
+    assert_eq!(engine.state().cursor.column, 10);
+}
+
+#[test]
+fn test_navigate_and_cancel_completion() {
+    let mut engine = engine_with_text("cat car cab\n");
+    type_str(&mut engine, "ca");
+    assert_eq!(engine.completion().unwrap().items.len(), 3);
+
+    engine.handle_action(EditorAction::MoveDown);
+    assert_eq!(engine.completion().unwrap().selected, 1);
+    engine.handle_action(EditorAction::MoveUp);
+    engine.handle_action(EditorAction::MoveUp);
+    assert_eq!(engine.completion().unwrap().selected, 2);
+    assert_eq!(engine.state().cursor.row, 1);
+
+    engine.handle_action(EditorAction::Cancel);
+    assert!(engine.completion().is_none());
+    engine.handle_action(EditorAction::Tab);
+    assert_eq!(engine.state().lines[1], "ca    ");
+}
+
+#[test]
+fn test_undo_accepted_completion() {
+    let mut engine = engine_with_text("something\n");
+    type_str(&mut engine, "so");
+    engine.handle_action(EditorAction::Tab);
+    assert_eq!(engine.state().lines[1], "something");
+
+    engine.handle_action(EditorAction::Undo);
+    assert_eq!(engine.state().lines[1], "so");
+}
+
+#[test]
+fn test_trigger_completion_and_dictionary() {
+    let dictionary = temp_dir("dictionary").join("words.txt");
+    fs::write(&dictionary, "zephyr\nzenith\n").unwrap();
+
+    let mut engine = engine_with_text("\n");
+    engine.set_config(Config {
+        completion: CompletionConfig {
+            enabled: false,
+            dictionary: Some(dictionary.clone()),
+            ..Default::default()
+        },
+        ..Default::default()
+    });
+
+    type_str(&mut engine, "ze");
+    assert!(engine.completion().is_none());
+
+    engine.handle_action(EditorAction::TriggerCompletion);
+    assert_eq!(engine.completion().unwrap().items, vec!["zenith", "zephyr"]);
+
+    fs::remove_file(dictionary).ok();
+}
+
+#[test]
+fn test_show_external_completion_items() {
+    let mut engine = engine_with_text("v.");
+    type_str(&mut engine, "pu");
+    engine.show_completion(vec!["len".into(), "push_str".into(), "push".into()]);
+
+    let completion = engine.completion().unwrap();
+    assert_eq!(completion.items, vec!["push", "push_str"]);
+
+    engine.handle_action(EditorAction::Tab);
+    assert_eq!(engine.state().lines[0], "v.push");
+}
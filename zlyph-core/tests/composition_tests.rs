@@ -0,0 +1,42 @@
+use zlyph_core::composition::{self, Composition};
+use zlyph_core::{EditorAction, EditorEngine};
+
+#[test]
+fn test_utf16_offsets() {
+    // "é" is two bytes and one unit, "𝄞" four bytes and two units
+    let text = "aé𝄞b";
+    assert_eq!(composition::to_utf16(text, 0), 0);
+    assert_eq!(composition::to_utf16(text, 3), 2);
+    assert_eq!(composition::to_utf16(text, text.len()), 5);
+
+    assert_eq!(composition::to_byte(text, 2), 3);
+    assert_eq!(composition::to_byte(text, 4), 7);
+    // Halfway through a surrogate pair goes back to the character's start
+    assert_eq!(composition::to_byte(text, 3), 3);
+    assert_eq!(composition::to_byte(text, 99), text.len());
+}
+
+#[test]
+fn test_composition_in_line() {
+    let composition = Composition::new("日本", None);
+    assert_eq!(composition.selected, 6..6);
+    assert_eq!(composition.apply("ab", 1), "a日本b");
+    assert_eq!(composition.apply("ab", 9), "ab日本");
+
+    let composition = Composition::new("にほん", Some(1..2));
+    assert_eq!(composition.selected, 3..6);
+}
+
+#[test]
+fn test_composed_text_is_one_undo_step() {
+    let mut engine = EditorEngine::new();
+    engine.handle_action(EditorAction::TypeString("a".to_string()));
+    engine.handle_action(EditorAction::InsertComposed("日本語".to_string()));
+    engine.handle_action(EditorAction::TypeString("b".to_string()));
+    assert_eq!(engine.state().to_string(), "a日本語b");
+
+    engine.handle_action(EditorAction::Undo);
+    assert_eq!(engine.state().to_string(), "a日本語");
+    engine.handle_action(EditorAction::Undo);
+    assert_eq!(engine.state().to_string(), "a");
+}
@@ -0,0 +1,70 @@
+mod common;
+
+use common::temp_dir;
+use std::fs;
+use zlyph_core::Config;
+
+#[test]
+fn test_store_value_keeps_comments_and_other_settings() {
+    let path = temp_dir("comments").join("config.toml");
+    fs::write(
+        &path,
+        "# my settings\n[completion]\nmin_prefix = 3 # short words too\n",
+    )
+    .unwrap();
+
+    Config::store_value_in_file(&path, "view", "focus_mode", true).unwrap();
+    let content = fs::read_to_string(&path).unwrap();
+    assert!(content.contains("# my settings"));
+    assert!(content.contains("min_prefix = 3 # short words too"));
+
+    let config = Config::load_from_file(&path).unwrap();
+    assert!(config.view.focus_mode);
+    // Settings the table leaves out keep their defaults
+    assert!(config.view.breadcrumbs);
+    assert_eq!(config.completion.min_prefix, 3);
+
+    Config::store_value_in_file(&path, "view", "focus_mode", false).unwrap();
+    assert!(!Config::load_from_file(&path).unwrap().view.focus_mode);
+
+    fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn test_store_value_creates_missing_file() {
+    let dir = temp_dir("missing").join("zlyph");
+    let path = dir.join("config.toml");
+
+    Config::store_value_in_file(&path, "view", "focus_mode", true).unwrap();
+    assert!(Config::load_from_file(&path).unwrap().view.focus_mode);
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_window_defaults_and_overrides() {
+    let config = Config::from_toml("").unwrap();
+    assert_eq!(config.window.opacity, 0.75);
+    assert!(config.window.blur && config.window.titlebar);
+
+    let config = Config::from_toml("[window]\nopacity = 1.0\nblur = false\n").unwrap();
+    assert_eq!(config.window.opacity, 1.0);
+    assert!(!config.window.blur);
+    assert!(config.window.titlebar);
+}
+
+#[test]
+fn test_font_settings_and_fallback() {
+    let config = Config::from_toml(
+        "[font]\nfamily = \"Fira Code\"\nfallbacks = [\"Menlo\"]\nweight = 500\nligatures = false\n",
+    )
+    .unwrap();
+    assert_eq!(config.font.weight, 500.0);
+    assert_eq!(config.font.line_height, 1.5);
+    assert!(!config.font.ligatures);
+
+    let installed = vec!["fira code".to_string(), "Menlo".to_string()];
+    assert_eq!(config.font.pick_family(&installed), Some("Fira Code"));
+    assert_eq!(config.font.pick_family(&installed[1..]), Some("Menlo"));
+    assert_eq!(config.font.pick_family(&[]), None);
+}
@@ -0,0 +1,72 @@
+use zlyph_core::context_menu::{self, ContextMenu, MenuItem};
+use zlyph_core::diagnostics::{Diagnostic, Severity};
+use zlyph_core::{BufferPosition, EditorAction, EditorEngine};
+
+/// "teh cat" with "teh" flagged as misspelled
+fn engine_with_typo() -> EditorEngine {
+    let mut engine = EditorEngine::new();
+    engine.handle_action(EditorAction::TypeString("teh cat".to_string()));
+    engine.set_diagnostics(
+        "spell",
+        vec![Diagnostic {
+            start: BufferPosition::new(0, 0),
+            end: BufferPosition::new(0, 3),
+            severity: Severity::Information,
+            message: "Possible spelling mistake".to_string(),
+            source: "spell".to_string(),
+            replacements: vec!["the".to_string(), "ten".to_string()],
+        }],
+    );
+    engine
+}
+
+#[test]
+fn test_suggestions_over_misspelled_word() {
+    let mut engine = engine_with_typo();
+    engine.handle_action(EditorAction::SetCursorPosition { row: 0, column: 1 });
+    let menu = ContextMenu::new(engine.state(), engine.diagnostics());
+    assert_eq!(menu.suggestions(), 2);
+    assert_eq!(menu.items[0], MenuItem::Suggestion("the".to_string()));
+    assert_eq!(menu.items[2], MenuItem::Cut);
+
+    engine.handle_action(EditorAction::ApplySuggestion("the".to_string()));
+    assert_eq!(engine.state().lines[0], "the cat");
+    assert_eq!(engine.state().cursor, BufferPosition::new(0, 3));
+    engine.handle_action(EditorAction::Undo);
+    assert_eq!(engine.state().lines[0], "teh cat");
+
+    engine.handle_action(EditorAction::SetCursorPosition { row: 0, column: 5 });
+    let menu = ContextMenu::new(engine.state(), engine.diagnostics());
+    assert_eq!(menu.suggestions(), 0);
+}
+
+#[test]
+fn test_selection_enables_items() {
+    let mut engine = engine_with_typo();
+    engine.handle_action(EditorAction::SetCursorPosition { row: 0, column: 1 });
+    let mut menu = ContextMenu::new(engine.state(), engine.diagnostics());
+    assert!(!menu.is_enabled(&MenuItem::Copy));
+    assert!(menu.is_enabled(&MenuItem::Paste));
+
+    // The arrow keys skip what cannot be chosen
+    menu.select_previous();
+    assert_eq!(menu.selected_item(), Some(&MenuItem::SelectAll));
+    menu.select_next();
+    assert_eq!(
+        menu.selected_item(),
+        Some(&MenuItem::Suggestion("the".to_string()))
+    );
+
+    engine.handle_action(EditorAction::SelectAll);
+    let menu = ContextMenu::new(engine.state(), engine.diagnostics());
+    assert!(menu.is_enabled(&MenuItem::Cut));
+    assert!(menu.is_enabled(&MenuItem::SearchWeb));
+}
+
+#[test]
+fn test_search_url() {
+    assert_eq!(
+        context_menu::search_url("https://duckduckgo.com/?q={}", " rust & café "),
+        "https://duckduckgo.com/?q=rust+%26+caf%C3%A9"
+    );
+}
@@ -0,0 +1,57 @@
+use std::time::Duration;
+use zlyph_core::config::{CursorConfig, CursorStyle};
+use zlyph_core::cursor::{is_visible, next_blink};
+use zlyph_core::Config;
+
+#[test]
+fn test_cursor_stays_solid_right_after_input() {
+    let config = CursorConfig::default();
+    assert!(is_visible(&config, Duration::ZERO));
+    assert!(is_visible(&config, Duration::from_millis(529)));
+    assert!(!is_visible(&config, Duration::from_millis(530)));
+    assert!(is_visible(&config, Duration::from_millis(1060)));
+}
+
+#[test]
+fn test_cursor_without_blink_is_always_visible() {
+    let config = CursorConfig {
+        blink: false,
+        ..Default::default()
+    };
+    assert!(is_visible(&config, Duration::from_millis(800)));
+
+    let config = CursorConfig {
+        blink_interval_ms: 0,
+        ..Default::default()
+    };
+    assert!(is_visible(&config, Duration::from_millis(800)));
+}
+
+#[test]
+fn test_cursor_style_from_config() {
+    assert_eq!(Config::from_toml("").unwrap().cursor.style, None);
+
+    let config = Config::from_toml("[cursor]\nstyle = \"underline\"\nsmear = true\n").unwrap();
+    assert_eq!(config.cursor.style, Some(CursorStyle::Underline));
+    assert!(config.cursor.smear);
+    assert!(Config::from_toml("[cursor]\nstyle = \"beam\"\n").is_err());
+}
+
+#[test]
+fn test_next_blink_waits_for_the_phase_to_end() {
+    let config = CursorConfig::default();
+    assert_eq!(
+        next_blink(&config, Duration::from_millis(500)),
+        Some(Duration::from_millis(30))
+    );
+    assert_eq!(
+        next_blink(&config, Duration::from_millis(530)),
+        Some(Duration::from_millis(530))
+    );
+
+    let steady = CursorConfig {
+        blink: false,
+        ..Default::default()
+    };
+    assert_eq!(next_blink(&steady, Duration::ZERO), None);
+}
@@ -0,0 +1,216 @@
+use zlyph_core::decorations::{Decoration, Kind, LineDecoration, Placement, Tone};
+use zlyph_core::{EditorAction, EditorEngine};
+
+const TEXT: &str = "let x = 1;\nlet y = x;";
+
+fn ranges(engine: &EditorEngine) -> Vec<(usize, usize)> {
+    engine
+        .decorations()
+        .iter()
+        .map(|decoration| (decoration.range.start, decoration.range.end))
+        .collect()
+}
+
+fn type_at(engine: &mut EditorEngine, row: usize, column: usize, text: &str) {
+    engine.handle_action(EditorAction::SetCursorPosition { row, column });
+    engine.handle_action(EditorAction::TypeString(text.to_string()));
+}
+
+#[test]
+fn test_decorations_are_listed_by_line() {
+    let mut engine = EditorEngine::builder().text(TEXT).build();
+    engine.set_decorations(
+        "hints",
+        vec![
+            Decoration::virtual_text(16, ": i32", Placement::Inline),
+            Decoration::virtual_text(5, ": i32", Placement::Inline),
+            Decoration::virtual_text(0, "you, today", Placement::EndOfLine),
+            Decoration::highlight(8..14).with_tone(Tone::Accent),
+        ],
+    );
+
+    assert_eq!(
+        engine.line_decorations(0),
+        [
+            LineDecoration::Inline {
+                column: 5,
+                text: ": i32",
+                tone: Tone::Muted
+            },
+            LineDecoration::Highlight {
+                columns: 8..10,
+                tone: Tone::Accent
+            },
+            LineDecoration::EndOfLine {
+                text: "you, today",
+                tone: Tone::Muted
+            },
+        ]
+    );
+    assert_eq!(
+        engine.line_decorations(1),
+        [
+            LineDecoration::Inline {
+                column: 5,
+                text: ": i32",
+                tone: Tone::Muted
+            },
+            LineDecoration::Highlight {
+                columns: 0..3,
+                tone: Tone::Accent
+            },
+        ]
+    );
+    assert!(engine.line_decorations(2).is_empty());
+}
+
+#[test]
+fn test_publishing_replaces_only_the_same_source() {
+    let mut engine = EditorEngine::builder().text(TEXT).build();
+    engine.set_decorations("blame", vec![Decoration::gutter(0, "●")]);
+    engine.set_decorations(
+        "lint",
+        vec![Decoration::gutter(11, "!").with_tone(Tone::Error)],
+    );
+    engine.set_decorations("blame", vec![Decoration::gutter(3, "○")]);
+
+    assert_eq!(engine.gutter_icon(0), Some(("○", Tone::Muted)));
+    assert_eq!(engine.gutter_icon(1), Some(("!", Tone::Error)));
+    assert_eq!(engine.decorations()[1].source, "lint");
+
+    engine.set_decorations("blame", Vec::new());
+    assert_eq!(engine.gutter_icon(0), None);
+}
+
+#[test]
+fn test_decorations_move_with_edits() {
+    let mut engine = EditorEngine::builder().text(TEXT).build();
+    engine.set_decorations(
+        "test",
+        vec![
+            Decoration::virtual_text(5, ": i32", Placement::Inline),
+            Decoration::highlight(8..9),
+            Decoration::gutter(11, "●"),
+        ],
+    );
+
+    // Typing at a point pushes it along; at a range's edge stays outside
+    type_at(&mut engine, 0, 5, "yz");
+    type_at(&mut engine, 0, 10, "0");
+    type_at(&mut engine, 0, 12, "0");
+    assert_eq!(engine.text(), "let xyz = 010;\nlet y = x;");
+    assert_eq!(ranges(&engine), [(7, 7), (11, 12), (15, 15)]);
+
+    engine.handle_action(EditorAction::SetCursorPosition { row: 0, column: 0 });
+    engine.handle_action(EditorAction::Newline);
+    assert_eq!(ranges(&engine), [(8, 8), (12, 13), (16, 16)]);
+    assert!(engine.gutter_icon(2).is_some());
+}
+
+#[test]
+fn test_deleted_text_takes_its_decorations() {
+    let mut engine = EditorEngine::builder().text(TEXT).build();
+    engine.set_decorations(
+        "test",
+        vec![
+            Decoration::virtual_text(5, ": i32", Placement::Inline),
+            Decoration::highlight(8..9),
+            Decoration::virtual_text(21, "end", Placement::EndOfLine),
+        ],
+    );
+
+    engine.handle_action(EditorAction::StartSelection { row: 0, column: 4 });
+    engine.handle_action(EditorAction::ExtendSelection { row: 0, column: 9 });
+    engine.handle_action(EditorAction::Delete);
+    assert_eq!(engine.text(), "let ;\nlet y = x;");
+    assert_eq!(ranges(&engine), [(16, 16)]);
+
+    // What follows the deleted text moves up with it
+    engine.handle_action(EditorAction::SetCursorPosition { row: 0, column: 0 });
+    engine.handle_action(EditorAction::DeleteLine);
+    assert_eq!(engine.text(), "let y = x;");
+    assert_eq!(
+        engine.line_decorations(0),
+        [LineDecoration::EndOfLine {
+            text: "end",
+            tone: Tone::Muted
+        }]
+    );
+}
+
+#[test]
+fn test_decorations_move_with_edits_at_every_cursor() {
+    let mut engine = EditorEngine::builder().text("x = 1\nkeep\nx = 2").build();
+    engine.set_decorations(
+        "test",
+        vec![
+            Decoration::virtual_text(4, ": i32", Placement::Inline),
+            Decoration::highlight(6..10),
+            Decoration::virtual_text(15, ": i32", Placement::Inline),
+        ],
+    );
+    engine.set_search_query(Some("x".to_string()));
+    engine.handle_action(EditorAction::SelectAllMatches);
+    engine.handle_action(EditorAction::TypeString("value".to_string()));
+
+    assert_eq!(engine.text(), "value = 1\nkeep\nvalue = 2");
+    let moved: Vec<_> = engine
+        .decorations()
+        .iter()
+        .filter(|decoration| decoration.source == "test")
+        .map(|decoration| (decoration.range.start, decoration.range.end))
+        .collect();
+    assert_eq!(moved, [(8, 8), (10, 14), (23, 23)]);
+}
+
+#[test]
+fn test_edits_away_from_the_cursor_move_what_follows_them() {
+    let text = "a\u{200b}b\nkeep\nkeep\nc\u{200b}d\ntail";
+    let mut engine = EditorEngine::builder().text(text).build();
+    engine.set_decorations(
+        "test",
+        vec![Decoration::highlight(11..15), Decoration::highlight(22..26)],
+    );
+
+    engine.handle_action(EditorAction::StripInvisibles);
+    assert_eq!(engine.text(), "ab\nkeep\nkeep\ncd\ntail");
+    assert_eq!(ranges(&engine), [(8, 12), (16, 20)]);
+}
+
+#[test]
+fn test_undo_moves_decorations_back() {
+    let mut engine = EditorEngine::builder().text(TEXT).build();
+    engine.set_decorations("test", vec![Decoration::highlight(15..16)]);
+
+    type_at(&mut engine, 0, 0, "// ");
+    assert_eq!(ranges(&engine), [(18, 19)]);
+    engine.handle_action(EditorAction::Undo);
+    assert_eq!(ranges(&engine), [(15, 16)]);
+}
+
+#[test]
+fn test_loading_clears_decorations() {
+    let mut engine = EditorEngine::builder().text(TEXT).build();
+    engine.set_decorations("test", vec![Decoration::gutter(0, "●")]);
+    engine.load_text("fresh");
+    assert!(engine.decorations().is_empty());
+}
+
+#[test]
+fn test_decorations_read_from_json() {
+    let decorations: Vec<Decoration> = serde_json::from_str(
+        r#"[
+            {"range": {"start": 4, "end": 4}, "kind": {"VirtualText": {"text": "hint"}}},
+            {"range": {"start": 0, "end": 3}, "kind": "Highlight", "tone": "Warning"}
+        ]"#,
+    )
+    .unwrap();
+    assert_eq!(
+        decorations[0].kind,
+        Kind::VirtualText {
+            text: "hint".into(),
+            placement: Placement::Inline
+        }
+    );
+    assert_eq!(decorations[1].tone, Tone::Warning);
+}
@@ -0,0 +1,123 @@
+mod common;
+
+use common::{lines, temp_dir};
+use std::fs;
+use std::path::{Path, PathBuf};
+use zlyph_core::definitions::{self, Address};
+use zlyph_core::project_search::ProjectSearch;
+use zlyph_core::BufferPosition;
+
+fn temp_project(name: &str) -> PathBuf {
+    let dir = temp_dir(name);
+    fs::create_dir_all(dir.join("src")).unwrap();
+    fs::write(
+        dir.join("src/lib.rs"),
+        "pub fn parse(text: &str) {}\n\nfn parser() {\n    parse(\"a\");\n}\n",
+    )
+    .unwrap();
+    fs::write(
+        dir.join("src/app.py"),
+        "from lib import parse\n\ndef parse(text):\n    pass\n",
+    )
+    .unwrap();
+    dir
+}
+
+/// Path relative to `root` and row of every match, sorted
+fn found(search: &ProjectSearch, root: &Path) -> Vec<(String, usize)> {
+    let mut found: Vec<_> = search
+        .results()
+        .iter()
+        .flat_map(|file| {
+            let path = file.path.strip_prefix(root).unwrap().display().to_string();
+            file.matches
+                .iter()
+                .map(move |m| (path.clone(), m.start.row))
+        })
+        .collect();
+    found.sort();
+    found
+}
+
+#[test]
+fn test_find_definitions_and_references_in_lines() {
+    let text = lines("fn parse() {}\nfn parser() { parse() }\n// parse_all");
+    let path = Path::new("lib.rs");
+    assert_eq!(
+        definitions::find_definitions(path, &text, "parse"),
+        [(BufferPosition::new(0, 3), BufferPosition::new(0, 8))]
+    );
+    assert_eq!(
+        definitions::find_references(path, &text, "parse"),
+        [
+            (BufferPosition::new(0, 3), BufferPosition::new(0, 8)),
+            (BufferPosition::new(1, 14), BufferPosition::new(1, 19)),
+        ]
+    );
+}
+
+#[test]
+fn test_parse_tags() {
+    let content = "!_TAG_FILE_FORMAT\t2\t/extended format/\n\
+        parse\tsrc/lib.rs\t/^pub fn parse(text: &str) {}$/;\"\tf\n\
+        Config\tsrc/config.rs\t12;\"\ts\n\
+        path\tsrc/a.js\t/^  a\\/b$/;\"\tv\n";
+    let tags = definitions::parse_tags(content, Path::new("/project"));
+    assert_eq!(tags.len(), 3);
+    assert_eq!(tags[0].path, Path::new("/project/src/lib.rs"));
+    assert_eq!(
+        tags[0].address,
+        Address::Pattern {
+            text: "pub fn parse(text: &str) {}".to_string(),
+            exact: true
+        }
+    );
+    assert_eq!(tags[1].address, Address::Line(11));
+    assert_eq!(
+        tags[2].address,
+        Address::Pattern {
+            text: "  a/b".to_string(),
+            exact: true
+        }
+    );
+}
+
+#[test]
+fn test_definitions_from_outline() {
+    let root = temp_project("definitions-outline");
+    let mut search = ProjectSearch::start_definitions(&root, "parse");
+    search.wait();
+    assert_eq!(
+        found(&search, &root),
+        [("src/app.py".to_string(), 2), ("src/lib.rs".to_string(), 0)]
+    );
+
+    let mut search = ProjectSearch::start_references(&root, "parse");
+    search.wait();
+    assert_eq!(
+        found(&search, &root),
+        [
+            ("src/app.py".to_string(), 0),
+            ("src/app.py".to_string(), 2),
+            ("src/lib.rs".to_string(), 0),
+            ("src/lib.rs".to_string(), 3),
+        ]
+    );
+}
+
+#[test]
+fn test_definitions_from_ctags() {
+    let root = temp_project("definitions-ctags");
+    fs::write(
+        root.join("tags"),
+        "parse\tsrc/lib.rs\t/^pub fn parse(text: &str) {}$/;\"\tf\nparse\tsrc/gone.rs\t3;\"\tf\n",
+    )
+    .unwrap();
+    // Only what the tags file lists, so not the Python function
+    let mut search = ProjectSearch::start_definitions(&root, "parse");
+    search.wait();
+    assert_eq!(found(&search, &root), [("src/lib.rs".to_string(), 0)]);
+    let found = &search.results()[0].matches[0];
+    assert_eq!(found.start, BufferPosition::new(0, 7));
+    assert_eq!(found.end, BufferPosition::new(0, 12));
+}
@@ -0,0 +1,106 @@
+use zlyph_core::diagnostics::{worst_on_row, Diagnostic, Severity};
+use zlyph_core::{BufferPosition, EditorAction, EditorEngine};
+
+fn diagnostic(row: usize, column: usize, severity: Severity, message: &str) -> Diagnostic {
+    Diagnostic {
+        start: BufferPosition::new(row, column),
+        end: BufferPosition::new(row, column + 3),
+        severity,
+        message: message.to_string(),
+        source: "test".to_string(),
+        replacements: Vec::new(),
+    }
+}
+
+fn engine_with_diagnostics() -> EditorEngine {
+    let mut engine = EditorEngine::new();
+    engine.handle_action(EditorAction::TypeString(
+        "first line\nsecond line\nthird line".to_string(),
+    ));
+    engine.handle_action(EditorAction::SetCursorPosition { row: 0, column: 0 });
+    engine.set_diagnostics(
+        "test",
+        vec![
+            diagnostic(1, 2, Severity::Warning, "second"),
+            diagnostic(2, 0, Severity::Error, "third"),
+        ],
+    );
+    engine
+}
+
+#[test]
+fn test_next_diagnostic_wraps_and_shows_message() {
+    let mut engine = engine_with_diagnostics();
+
+    engine.handle_action(EditorAction::NextDiagnostic);
+    assert_eq!(engine.state().cursor, BufferPosition::new(1, 2));
+    assert_eq!(engine.hover(), Some("second"));
+
+    engine.handle_action(EditorAction::NextDiagnostic);
+    assert_eq!(engine.state().cursor, BufferPosition::new(2, 0));
+
+    engine.handle_action(EditorAction::NextDiagnostic);
+    assert_eq!(engine.state().cursor, BufferPosition::new(1, 2));
+}
+
+#[test]
+fn test_prev_diagnostic_wraps() {
+    let mut engine = engine_with_diagnostics();
+
+    engine.handle_action(EditorAction::PrevDiagnostic);
+    assert_eq!(engine.state().cursor, BufferPosition::new(2, 0));
+    assert_eq!(engine.hover(), Some("third"));
+
+    engine.handle_action(EditorAction::PrevDiagnostic);
+    assert_eq!(engine.state().cursor, BufferPosition::new(1, 2));
+}
+
+#[test]
+fn test_toggle_diagnostics_panel() {
+    let mut engine = EditorEngine::new();
+    assert!(!engine.diagnostics_panel_visible());
+    engine.handle_action(EditorAction::ToggleDiagnosticsPanel);
+    assert!(engine.diagnostics_panel_visible());
+    engine.handle_action(EditorAction::ToggleDiagnosticsPanel);
+    assert!(!engine.diagnostics_panel_visible());
+}
+
+#[test]
+fn test_diagnostic_ranges_on_rows() {
+    let multi_line = Diagnostic {
+        start: BufferPosition::new(0, 4),
+        end: BufferPosition::new(2, 3),
+        severity: Severity::Hint,
+        message: String::new(),
+        source: "test".to_string(),
+        replacements: Vec::new(),
+    };
+    assert_eq!(multi_line.columns_on_row(0, 10), Some((4, 10)));
+    assert_eq!(multi_line.columns_on_row(1, 6), Some((0, 6)));
+    assert_eq!(multi_line.columns_on_row(2, 10), Some((0, 3)));
+    assert_eq!(multi_line.columns_on_row(3, 10), None);
+
+    let engine = engine_with_diagnostics();
+    assert_eq!(
+        worst_on_row(engine.diagnostics(), 1),
+        Some(Severity::Warning)
+    );
+    assert_eq!(worst_on_row(engine.diagnostics(), 0), None);
+}
+
+#[test]
+fn test_quick_fix_offers_replacements() {
+    let mut engine = EditorEngine::new();
+    engine.handle_action(EditorAction::TypeString("teh cat".to_string()));
+    let mut typo = diagnostic(0, 0, Severity::Error, "typo");
+    typo.replacements = vec!["the".to_string(), "ten".to_string()];
+    engine.set_diagnostics("spell", vec![typo]);
+    engine.handle_action(EditorAction::SetCursorPosition { row: 0, column: 1 });
+
+    engine.handle_action(EditorAction::QuickFix);
+    assert_eq!(engine.completion().unwrap().items, vec!["the", "ten"]);
+
+    engine.handle_action(EditorAction::MoveDown);
+    engine.handle_action(EditorAction::Tab);
+    assert_eq!(engine.state().lines[0], "ten cat");
+}
@@ -0,0 +1,81 @@
+#![cfg(unix)]
+
+use std::time::{Duration, Instant};
+use zlyph_core::config::{Config, DictationConfig};
+use zlyph_core::dictation;
+use zlyph_core::{EditorAction, EditorEngine};
+
+fn dictating_with(text: &str, command: &str) -> EditorEngine {
+    let mut engine = EditorEngine::new();
+    engine.set_config(Config {
+        dictation: DictationConfig {
+            command: command.to_string(),
+        },
+        ..Default::default()
+    });
+    engine.load_text(text);
+    engine.handle_action(EditorAction::MoveToEndOfLine);
+    engine.handle_action(EditorAction::ToggleDictation);
+    engine
+}
+
+/// Poll as the frontends do until the command has ended
+fn poll_until_ended(engine: &mut EditorEngine) {
+    let started = Instant::now();
+    while engine.dictating() {
+        assert!(started.elapsed() < Duration::from_secs(10), "command hung");
+        engine.poll_dictation();
+        std::thread::sleep(Duration::from_millis(10));
+    }
+}
+
+#[test]
+fn test_phrases_leave_out_stamps_redraws_and_markers() {
+    assert_eq!(
+        dictation::phrase("[00:00:00.000 --> 00:00:02.000]   Hello there.\n").as_deref(),
+        Some("Hello there.")
+    );
+    assert_eq!(
+        dictation::phrase("\x1b[2K\r Hell\x1b[2K\r Hello world\r\n").as_deref(),
+        Some("Hello world")
+    );
+    for line in [
+        "[BLANK_AUDIO]\n",
+        " (music)",
+        "### Transcription 1 START",
+        "\n",
+    ] {
+        assert_eq!(dictation::phrase(line), None, "{:?}", line);
+    }
+}
+
+#[test]
+fn test_recognized_phrases_typed_as_undo_steps() {
+    let mut engine = dictating_with(
+        "Notes:",
+        "printf ' First one.\\n[BLANK_AUDIO]\\nSecond.\\n'",
+    );
+    assert!(engine.dictating());
+    poll_until_ended(&mut engine);
+    assert_eq!(engine.text(), "Notes: First one. Second.");
+    assert_eq!(engine.hover(), Some("Dictation ended"));
+
+    engine.handle_action(EditorAction::Undo);
+    assert_eq!(engine.text(), "Notes: First one.");
+}
+
+#[test]
+fn test_failed_or_stopped_command() {
+    let mut engine = dictating_with("keep", "echo 'no microphone' >&2; exit 1");
+    poll_until_ended(&mut engine);
+    assert_eq!(engine.text(), "keep");
+    assert_eq!(
+        engine.hover(),
+        Some("echo 'no microphone' >&2; exit 1 failed: no microphone")
+    );
+
+    let mut engine = dictating_with("keep", "sleep 30");
+    engine.handle_action(EditorAction::ToggleDictation);
+    assert!(!engine.dictating());
+    assert_eq!(engine.hover(), Some("Dictation stopped"));
+}
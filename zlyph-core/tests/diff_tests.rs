@@ -0,0 +1,68 @@
+mod common;
+
+use common::lines;
+use zlyph_core::diff::{diff_lines, ChangeKind, Hunk};
+
+#[test]
+fn test_identical_texts_have_no_hunks() {
+    assert!(diff_lines(&lines("a\nb\nc"), &lines("a\nb\nc")).is_empty());
+}
+
+#[test]
+fn test_added_modified_and_removed_lines() {
+    let old = lines("one\ntwo\nthree\nfour\nfive");
+    let new = lines("one\nTWO\nthree\nfive\nsix\nseven");
+    let hunks = diff_lines(&old, &new);
+    assert_eq!(
+        hunks,
+        vec![
+            Hunk {
+                old_start: 1,
+                old_len: 1,
+                new_start: 1,
+                new_len: 1,
+            },
+            Hunk {
+                old_start: 3,
+                old_len: 1,
+                new_start: 3,
+                new_len: 0,
+            },
+            Hunk {
+                old_start: 5,
+                old_len: 0,
+                new_start: 4,
+                new_len: 2,
+            },
+        ]
+    );
+    let kinds: Vec<ChangeKind> = hunks.iter().map(Hunk::kind).collect();
+    assert_eq!(
+        kinds,
+        vec![ChangeKind::Modified, ChangeKind::Removed, ChangeKind::Added]
+    );
+}
+
+#[test]
+fn test_diff_against_empty_text() {
+    let hunks = diff_lines(&[], &lines("a\nb"));
+    assert_eq!(hunks.len(), 1);
+    assert_eq!(hunks[0].kind(), ChangeKind::Added);
+    assert_eq!(hunks[0].new_len, 2);
+}
+
+#[test]
+fn test_large_rewrites_become_one_hunk() {
+    let old: Vec<String> = (0..2000).map(|i| format!("old {}", i)).collect();
+    let new: Vec<String> = (0..2000).map(|i| format!("new {}", i)).collect();
+    let hunks = diff_lines(&old, &new);
+    assert_eq!(
+        hunks,
+        vec![Hunk {
+            old_start: 0,
+            old_len: 2000,
+            new_start: 0,
+            new_len: 2000,
+        }]
+    );
+}
@@ -0,0 +1,95 @@
+mod common;
+
+use common::{lines, temp_dir};
+use zlyph_core::diff_view::{DiffRow, DiffSource, DiffView};
+
+#[test]
+fn test_source_is_a_file_or_a_revision() {
+    let dir = temp_dir("source");
+    assert_eq!(DiffSource::parse(" ", &dir), DiffSource::Saved);
+    assert_eq!(
+        DiffSource::parse("HEAD~1", &dir),
+        DiffSource::Revision("HEAD~1".to_string())
+    );
+
+    let file = dir.join("source.txt");
+    std::fs::write(&file, "text").unwrap();
+    assert_eq!(
+        DiffSource::parse(file.to_str().unwrap(), &dir),
+        DiffSource::File(file.clone())
+    );
+    std::fs::remove_file(&file).unwrap();
+}
+
+#[test]
+fn test_unified_rows_fold_distant_context() {
+    let theirs = lines("1\n2\n3\n4\n5\n6\n7\n8\n9\n10");
+    let mine = lines("1\n2\n3\n4\n5\n6\n7\n8\nnine\n10");
+    let view = DiffView::new("HEAD".to_string(), theirs, &mine);
+
+    let rows = view.unified_rows(mine.len());
+    assert_eq!(rows[0], DiffRow::Fold(5));
+    assert_eq!(rows[1], DiffRow::Same { theirs: 5, mine: 5 });
+    assert_eq!(
+        &rows[4..],
+        &[
+            DiffRow::Changed {
+                hunk: 0,
+                theirs: Some(8),
+                mine: None
+            },
+            DiffRow::Changed {
+                hunk: 0,
+                theirs: None,
+                mine: Some(8)
+            },
+            DiffRow::Same { theirs: 9, mine: 9 },
+        ]
+    );
+
+    let rows = view.side_by_side_rows(mine.len());
+    assert_eq!(
+        rows[4],
+        DiffRow::Changed {
+            hunk: 0,
+            theirs: Some(8),
+            mine: Some(8)
+        }
+    );
+    assert_eq!(DiffView::selected_row(&rows, 0), 4);
+}
+
+#[test]
+fn test_accepting_hunks_resolves_them() {
+    let theirs = lines("a\nb\nc\nd");
+    let mine = lines("a\nB\nc\nd\ne");
+    let mut view = DiffView::new("other.txt".to_string(), theirs, &mine);
+    assert_eq!(view.hunks().len(), 2);
+
+    let (mine, row) = view.accept_theirs(&mine).unwrap();
+    assert_eq!(mine, lines("a\nb\nc\nd\ne"));
+    assert_eq!(row, 1);
+    view.refresh(&mine);
+    assert_eq!(view.hunks().len(), 1);
+
+    view.accept_mine(&mine);
+    assert!(view.hunks().is_empty());
+    assert_eq!(view.theirs(), &mine[..]);
+    assert_eq!(view.accept_theirs(&mine), None);
+}
+
+#[test]
+fn test_hunk_navigation_wraps() {
+    let mut view = DiffView::new(
+        "HEAD".to_string(),
+        lines("a\nb\nc\nd\ne\nf\ng\nh\ni"),
+        &lines("A\nb\nc\nd\ne\nf\ng\nh\nI"),
+    );
+    assert_eq!(view.selected, 0);
+    view.next_hunk();
+    assert_eq!(view.selected, 1);
+    view.next_hunk();
+    assert_eq!(view.selected, 0);
+    view.prev_hunk();
+    assert_eq!(view.selected, 1);
+}
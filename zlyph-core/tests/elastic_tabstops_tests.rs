@@ -0,0 +1,53 @@
+mod common;
+
+use common::lines;
+use zlyph_core::elastic_tabstops::{self, TabPadding, GAP};
+
+fn widths(text: &str) -> Vec<Vec<usize>> {
+    let lines = lines(text);
+    elastic_tabstops::layout(&lines, |row, cell| lines[row][cell].chars().count())
+        .into_iter()
+        .map(|padding| padding.iter().map(|padding| padding.width).collect())
+        .collect()
+}
+
+#[test]
+fn test_cells_end_with_their_tab() {
+    assert_eq!(elastic_tabstops::cells("a\tbc\td"), vec![0..2, 2..5]);
+    assert!(elastic_tabstops::cells("no tabs").is_empty());
+}
+
+#[test]
+fn test_columns_line_up_on_the_widest_cell() {
+    assert_eq!(
+        widths("name\tage\nbob\t42\nalexandra\t7"),
+        vec![vec![5 + GAP], vec![6 + GAP], vec![GAP]]
+    );
+    let lines = lines("a\tb");
+    assert_eq!(
+        elastic_tabstops::layout(&lines, |_, cell| cell.len()),
+        vec![vec![TabPadding {
+            column: 2,
+            width: GAP
+        }]]
+    );
+}
+
+#[test]
+fn test_lines_without_the_column_end_its_block() {
+    let widths = widths("a\tb\nlonger\tc\n\nx\ty\nshort line\nlonger still\tz");
+    assert_eq!(widths[0], vec![5 + GAP]);
+    assert_eq!(widths[1], vec![GAP]);
+    assert_eq!(widths[3], vec![GAP]);
+    assert!(widths[4].is_empty());
+    assert_eq!(widths[5], vec![GAP]);
+}
+
+#[test]
+fn test_later_columns_are_blocks_of_their_own() {
+    // The second column spans only the lines that have one
+    let widths = widths("a\tbb\tc\naaa\tb\tc\nx\ty");
+    assert_eq!(widths[0], vec![2 + GAP, GAP]);
+    assert_eq!(widths[1], vec![GAP, 1 + GAP]);
+    assert_eq!(widths[2], vec![2 + GAP]);
+}
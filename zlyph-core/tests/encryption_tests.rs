@@ -0,0 +1,63 @@
+mod common;
+
+use common::temp_dir;
+use std::fs;
+use std::io;
+use zlyph_core::encryption::{self, Cipher};
+use zlyph_core::{Config, EditorEngine};
+
+#[test]
+fn test_encrypt_round_trip() {
+    let cipher = Cipher::new("correct horse").unwrap();
+    let sealed = cipher.encrypt("secret\nnotes").unwrap();
+    assert!(encryption::is_encrypted(&sealed));
+    assert!(!sealed.contains("secret"));
+    // Fresh nonce each time
+    assert_ne!(sealed, cipher.encrypt("secret\nnotes").unwrap());
+
+    let mut other = Cipher::new("correct horse").unwrap();
+    assert_eq!(other.decrypt(&sealed).unwrap(), "secret\nnotes");
+
+    let mut wrong = Cipher::new("battery staple").unwrap();
+    let error = wrong.decrypt(&sealed).unwrap_err();
+    assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+    assert!(!encryption::is_encrypted("plain text"));
+}
+
+#[test]
+fn test_engine_keeps_encrypted_files_encrypted() {
+    let path = temp_dir("engine").join("notes.md");
+    let cipher = Cipher::new("pw").unwrap();
+    fs::write(&path, cipher.encrypt("first line").unwrap()).unwrap();
+
+    let mut engine = EditorEngine::new();
+    assert!(engine.needs_passphrase(&path));
+    let error = engine.load_from_file(&path).unwrap_err();
+    assert_eq!(error.kind(), io::ErrorKind::PermissionDenied);
+    // Saving the empty buffer must not replace the encrypted file
+    assert!(engine.save_to_file(&path).is_err());
+
+    engine.set_passphrase("wrong").unwrap();
+    assert!(engine.load_from_file(&path).is_err());
+    assert!(engine.needs_passphrase(&path));
+
+    engine.set_passphrase("pw").unwrap();
+    engine.load_from_file(&path).unwrap();
+    assert_eq!(engine.state().lines, vec!["first line"]);
+    engine.load_text("changed");
+    engine.save_to_file(&path).unwrap();
+
+    let saved = fs::read_to_string(&path).unwrap();
+    assert!(encryption::is_encrypted(&saved));
+    assert_eq!(
+        Cipher::new("pw").unwrap().decrypt(&saved).unwrap(),
+        "changed"
+    );
+}
+
+#[test]
+fn test_encryption_config() {
+    let config = Config::from_toml("[encryption]\nenabled = true\n").unwrap();
+    assert!(config.encryption.enabled);
+    assert!(!config.encryption.keychain);
+}
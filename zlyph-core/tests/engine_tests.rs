@@ -1,4 +1,5 @@
-use zlyph_core::{BufferPosition, EditorAction, EditorEngine};
+use zlyph_core::diagnostics::{Diagnostic, Severity};
+use zlyph_core::{BufferPosition, BufferRange, EditorAction, EditorEngine};
 
 #[test]
 fn test_type_character() {
@@ -317,3 +318,387 @@ fn test_delete_to_end_of_line() {
 
     assert_eq!(engine.state().to_string(), "hello");
 }
+
+#[test]
+fn test_expand_selection_word_line_paragraph_document() {
+    let mut engine = EditorEngine::new();
+    engine.handle_action(EditorAction::TypeString(
+        "hello world\nsecond line\n\nlast".to_string(),
+    ));
+    engine.handle_action(EditorAction::SetCursorPosition { row: 0, column: 7 });
+
+    engine.handle_action(EditorAction::ExpandSelection);
+    assert_eq!(
+        engine.state().selection_anchor,
+        Some(BufferPosition::new(0, 6))
+    );
+    assert_eq!(engine.state().cursor, BufferPosition::new(0, 11));
+
+    engine.handle_action(EditorAction::ExpandSelection);
+    assert_eq!(
+        engine.state().selection_anchor,
+        Some(BufferPosition::new(0, 0))
+    );
+    assert_eq!(engine.state().cursor, BufferPosition::new(0, 11));
+
+    engine.handle_action(EditorAction::ExpandSelection);
+    assert_eq!(
+        engine.state().selection_anchor,
+        Some(BufferPosition::new(0, 0))
+    );
+    assert_eq!(engine.state().cursor, BufferPosition::new(1, 11));
+
+    engine.handle_action(EditorAction::ExpandSelection);
+    assert_eq!(engine.state().cursor, BufferPosition::new(3, 4));
+}
+
+#[test]
+fn test_shrink_selection_restores_previous_steps() {
+    let mut engine = EditorEngine::new();
+    engine.handle_action(EditorAction::TypeString("hello world".to_string()));
+    engine.handle_action(EditorAction::SetCursorPosition { row: 0, column: 2 });
+
+    engine.handle_action(EditorAction::ExpandSelection);
+    engine.handle_action(EditorAction::ExpandSelection);
+    assert_eq!(engine.state().cursor, BufferPosition::new(0, 11));
+
+    engine.handle_action(EditorAction::ShrinkSelection);
+    assert_eq!(
+        engine.state().selection_anchor,
+        Some(BufferPosition::new(0, 0))
+    );
+    assert_eq!(engine.state().cursor, BufferPosition::new(0, 5));
+
+    engine.handle_action(EditorAction::ShrinkSelection);
+    assert_eq!(engine.state().selection_anchor, None);
+    assert_eq!(engine.state().cursor, BufferPosition::new(0, 2));
+}
+
+#[test]
+fn test_select_line_extends_on_repeat() {
+    let mut engine = EditorEngine::new();
+    engine.handle_action(EditorAction::TypeString("one\ntwo\nthree".to_string()));
+    engine.handle_action(EditorAction::SetCursorPosition { row: 0, column: 1 });
+
+    engine.handle_action(EditorAction::SelectLine);
+    assert_eq!(
+        engine.state().selection_anchor,
+        Some(BufferPosition::zero())
+    );
+    assert_eq!(engine.state().cursor, BufferPosition::new(1, 0));
+
+    engine.handle_action(EditorAction::SelectLine);
+    assert_eq!(engine.state().cursor, BufferPosition::new(2, 0));
+
+    engine.handle_action(EditorAction::SelectLine);
+    assert_eq!(engine.state().cursor, BufferPosition::new(2, 5));
+}
+
+#[test]
+fn test_select_paragraph() {
+    let mut engine = EditorEngine::new();
+    engine.handle_action(EditorAction::TypeString("a\nb\n\nc".to_string()));
+    engine.handle_action(EditorAction::SetCursorPosition { row: 1, column: 0 });
+    engine.handle_action(EditorAction::SelectParagraph);
+
+    assert_eq!(
+        engine.state().selection_anchor,
+        Some(BufferPosition::zero())
+    );
+    assert_eq!(engine.state().cursor, BufferPosition::new(1, 1));
+}
+
+#[test]
+fn test_select_to_document_edges() {
+    let mut engine = EditorEngine::new();
+    engine.handle_action(EditorAction::TypeString("one\ntwo\nthree".to_string()));
+    engine.handle_action(EditorAction::SetCursorPosition { row: 1, column: 1 });
+
+    engine.handle_action(EditorAction::SelectToTop);
+    assert_eq!(
+        engine.state().selection_anchor,
+        Some(BufferPosition::new(1, 1))
+    );
+    assert_eq!(engine.state().cursor, BufferPosition::zero());
+
+    engine.handle_action(EditorAction::SelectToBottom);
+    assert_eq!(
+        engine.state().selection_anchor,
+        Some(BufferPosition::new(1, 1))
+    );
+    assert_eq!(engine.state().cursor, BufferPosition::new(2, 5));
+}
+
+#[test]
+fn test_select_to_line_start_end() {
+    let mut engine = EditorEngine::new();
+    engine.handle_action(EditorAction::TypeString("hello world".to_string()));
+    engine.handle_action(EditorAction::SetCursorPosition { row: 0, column: 5 });
+
+    engine.handle_action(EditorAction::SelectToLineEnd);
+    assert_eq!(
+        engine.state().selection_anchor,
+        Some(BufferPosition::new(0, 5))
+    );
+    assert_eq!(engine.state().cursor.column, 11);
+
+    engine.handle_action(EditorAction::SelectToLineStart);
+    assert_eq!(
+        engine.state().selection_anchor,
+        Some(BufferPosition::new(0, 5))
+    );
+    assert_eq!(engine.state().cursor.column, 0);
+}
+
+#[test]
+fn test_diagnostics_are_replaced_per_source() {
+    let mut engine = EditorEngine::new();
+    let diagnostic = |row, source: &str| Diagnostic {
+        start: BufferPosition::new(row, 0),
+        end: BufferPosition::new(row, 1),
+        severity: Severity::Error,
+        message: "problem".to_string(),
+        source: source.to_string(),
+        replacements: Vec::new(),
+    };
+
+    engine.set_diagnostics("lsp", vec![diagnostic(2, "lsp"), diagnostic(0, "lsp")]);
+    engine.set_diagnostics("spell", vec![diagnostic(1, "spell")]);
+    let rows: Vec<usize> = engine.diagnostics().iter().map(|d| d.start.row).collect();
+    assert_eq!(rows, vec![0, 1, 2]);
+
+    engine.set_diagnostics("lsp", Vec::new());
+    assert_eq!(engine.diagnostics(), &[diagnostic(1, "spell")]);
+}
+
+#[test]
+fn test_hover_clears_on_next_action() {
+    let mut engine = EditorEngine::new();
+    engine.set_hover(Some("fn main()".to_string()));
+    assert_eq!(engine.hover(), Some("fn main()"));
+
+    engine.handle_action(EditorAction::MoveRight);
+    assert_eq!(engine.hover(), None);
+}
+
+#[test]
+fn test_new_buffer_resets_text_and_history() {
+    let mut engine = EditorEngine::new();
+    engine.handle_action(EditorAction::TypeString("draft".to_string()));
+
+    engine.new_buffer();
+    assert_eq!(engine.state().lines, vec![String::new()]);
+    assert_eq!(engine.state().cursor, BufferPosition::zero());
+
+    engine.handle_action(EditorAction::Undo);
+    assert_eq!(engine.state().lines, vec![String::new()]);
+}
+
+#[test]
+fn test_revision_changes_with_edits_only() {
+    let mut engine = EditorEngine::new();
+    let start = engine.revision();
+    engine.handle_action(EditorAction::MoveRight);
+    assert_eq!(engine.revision(), start);
+
+    engine.handle_action(EditorAction::TypeCharacter('a'));
+    let typed = engine.revision();
+    assert!(typed > start);
+
+    engine.handle_action(EditorAction::Undo);
+    assert!(engine.revision() > typed);
+}
+
+#[test]
+fn test_merge_text_keeps_cursor_on_its_text() {
+    let mut engine = EditorEngine::new();
+    engine.load_text("hello world");
+    engine.handle_action(EditorAction::SetCursorPosition { row: 0, column: 6 });
+
+    // Inserted before the cursor: it moves along
+    engine.merge_text("oh, hello world");
+    assert_eq!(engine.state().cursor, BufferPosition::new(0, 10));
+
+    // Changed after the cursor: it stays
+    engine.merge_text("oh, hello world!\nbye");
+    assert_eq!(engine.state().lines, vec!["oh, hello world!", "bye"]);
+    assert_eq!(engine.state().cursor, BufferPosition::new(0, 10));
+
+    engine.handle_action(EditorAction::Undo);
+    assert_eq!(engine.state().lines, vec!["oh, hello world"]);
+}
+
+/// An engine holding `text` with `start..end` selected
+fn engine_with_selection(text: &str, start: (usize, usize), end: (usize, usize)) -> EditorEngine {
+    let mut engine = EditorEngine::new();
+    engine.load_text(text);
+    engine.handle_action(EditorAction::StartSelection {
+        row: start.0,
+        column: start.1,
+    });
+    engine.handle_action(EditorAction::ExtendSelection {
+        row: end.0,
+        column: end.1,
+    });
+    engine
+}
+
+#[test]
+fn test_move_selection_is_one_undo_step() {
+    let mut engine = engine_with_selection("one two three", (0, 4), (0, 8));
+    engine.handle_action(EditorAction::MoveSelectionTo { row: 0, column: 13 });
+    assert_eq!(engine.state().to_string(), "one threetwo ");
+    // The moved text stays selected
+    assert_eq!(
+        engine.state().selection_anchor,
+        Some(BufferPosition::new(0, 9))
+    );
+    assert_eq!(engine.state().cursor, BufferPosition::new(0, 13));
+
+    engine.handle_action(EditorAction::Undo);
+    assert_eq!(engine.state().to_string(), "one two three");
+}
+
+#[test]
+fn test_move_across_lines() {
+    let mut engine = engine_with_selection("ab\ncd\nef", (0, 1), (1, 1));
+    engine.handle_action(EditorAction::MoveSelectionTo { row: 2, column: 1 });
+    assert_eq!(engine.state().to_string(), "ad\neb\ncf");
+
+    let mut engine = engine_with_selection("ab\ncd\nef", (1, 1), (2, 1));
+    engine.handle_action(EditorAction::MoveSelectionTo { row: 0, column: 0 });
+    assert_eq!(engine.state().to_string(), "d\neab\ncf");
+}
+
+#[test]
+fn test_copy_and_drop_into_selection() {
+    let mut engine = engine_with_selection("one two", (0, 0), (0, 3));
+    engine.handle_action(EditorAction::MoveSelectionTo { row: 0, column: 2 });
+    assert_eq!(engine.state().to_string(), "one two");
+
+    engine.handle_action(EditorAction::CopySelectionTo { row: 0, column: 7 });
+    assert_eq!(engine.state().to_string(), "one twoone");
+    engine.handle_action(EditorAction::Undo);
+    assert_eq!(engine.state().to_string(), "one two");
+}
+
+#[test]
+fn test_read_only_ignores_edits() {
+    let mut engine = EditorEngine::new();
+    engine.load_text("fixed text");
+    engine.set_read_only(true);
+    let revision = engine.revision();
+
+    engine.handle_action(EditorAction::TypeCharacter('x'));
+    engine.handle_action(EditorAction::TypeString("more".to_string()));
+    engine.handle_action(EditorAction::DeleteLine);
+    assert_eq!(engine.state().to_string(), "fixed text");
+    assert_eq!(engine.revision(), revision);
+
+    // Moving and selecting still work, for copying
+    engine.handle_action(EditorAction::MoveWordRight);
+    engine.handle_action(EditorAction::SelectToLineEnd);
+    assert_eq!(engine.state().cursor, BufferPosition::new(0, 10));
+    assert!(engine.state().selection_anchor.is_some());
+
+    engine.set_read_only(false);
+    engine.handle_action(EditorAction::MoveToEndOfLine);
+    engine.handle_action(EditorAction::TypeCharacter('!'));
+    assert_eq!(engine.state().to_string(), "fixed text!");
+}
+
+#[test]
+fn test_single_line_keeps_one_line() {
+    let mut engine = EditorEngine::new();
+    engine.set_single_line(true);
+    engine.handle_action(EditorAction::TypeString("name".to_string()));
+    engine.handle_action(EditorAction::Newline);
+    engine.handle_action(EditorAction::TypeString(": first\nsecond\r\n".to_string()));
+    assert_eq!(engine.state().lines, vec!["name: first second "]);
+
+    engine.load_text("one\ntwo");
+    assert_eq!(engine.state().lines, vec!["one two"]);
+}
+
+#[test]
+fn test_single_line_joins_existing_lines() {
+    let mut engine = EditorEngine::new();
+    engine.load_text("one\ntwo\nthree");
+    engine.set_single_line(true);
+    assert_eq!(engine.state().lines, vec!["one two three"]);
+}
+
+#[test]
+fn test_builder_configures_engine() {
+    let mut engine = EditorEngine::builder()
+        .text("one\ntwo")
+        .single_line(true)
+        .read_only(true)
+        .build();
+    assert_eq!(engine.text(), "one two");
+    assert!(engine.is_read_only());
+
+    engine.handle_action(EditorAction::TypeCharacter('x'));
+    assert_eq!(engine.text(), "one two");
+}
+
+#[test]
+fn test_selected_text() {
+    let mut engine = EditorEngine::builder().text("alpha\nbeta gamma").build();
+    assert_eq!(engine.selected_text(), None);
+
+    engine.handle_action(EditorAction::SetCursorPosition { row: 0, column: 2 });
+    engine.handle_action(EditorAction::ExtendSelection { row: 1, column: 4 });
+    assert_eq!(engine.selected_text().as_deref(), Some("pha\nbeta"));
+    assert_eq!(
+        engine.state().selection(),
+        Some(BufferRange::new(
+            BufferPosition::new(1, 4),
+            BufferPosition::new(0, 2)
+        ))
+    );
+}
+
+#[test]
+fn test_buffer_range_orders_endpoints() {
+    let range = BufferRange::new(BufferPosition::new(2, 0), BufferPosition::new(1, 5));
+    assert_eq!(range.start, BufferPosition::new(1, 5));
+    assert_eq!(range.end, BufferPosition::new(2, 0));
+    assert!(range.contains(BufferPosition::new(1, 9)));
+    assert!(!range.contains(BufferPosition::new(2, 0)));
+    assert!(!range.is_empty());
+}
+
+#[test]
+fn test_state_restores_from_json() {
+    let mut engine = EditorEngine::builder().text("one\ntwo").build();
+    engine.handle_action(EditorAction::StartSelection { row: 0, column: 1 });
+    engine.handle_action(EditorAction::ExtendSelection { row: 1, column: 2 });
+    let json = serde_json::to_string(engine.state()).unwrap();
+
+    let mut other = EditorEngine::new();
+    other.restore_state(serde_json::from_str(&json).unwrap());
+
+    assert_eq!(other.state(), engine.state());
+    other.handle_action(EditorAction::Undo);
+    assert_eq!(other.text(), "");
+}
+
+#[test]
+fn test_restored_state_is_pulled_onto_the_text() {
+    let mut engine = EditorEngine::new();
+    let state = serde_json::from_str(
+        r#"{"lines": ["中", "a\nb"], "cursor": {"row": 0, "column": 1},
+            "selection_anchor": {"row": 9, "column": 9}, "font_size": 20.0}"#,
+    )
+    .unwrap();
+
+    engine.restore_state(state);
+
+    assert_eq!(engine.text(), "中\na\nb");
+    assert_eq!(engine.state().cursor, BufferPosition::new(0, 0));
+    assert_eq!(
+        engine.state().selection_anchor,
+        Some(BufferPosition::new(2, 1))
+    );
+}
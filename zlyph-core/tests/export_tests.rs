@@ -0,0 +1,83 @@
+mod common;
+
+use common::lines;
+use std::path::Path;
+use zlyph_core::config::ExportConfig;
+use zlyph_core::export::{self, HtmlOptions, Palette};
+use zlyph_core::{BufferPosition, BufferRange};
+
+fn options(line_numbers: bool, inline_css: bool) -> HtmlOptions {
+    HtmlOptions {
+        title: "notes.md".to_string(),
+        line_numbers,
+        inline_css,
+    }
+}
+
+#[test]
+fn test_html_highlights_with_classes() {
+    let page = export::html(
+        &lines("# Title\nSome **bold** & <text>"),
+        Some(Path::new("notes.md")),
+        None,
+        &Palette::default(),
+        &options(false, false),
+    );
+    assert!(page.starts_with("<!DOCTYPE html>"));
+    assert!(page.contains("<title>notes.md</title>"));
+    assert!(page.contains(".h1 { color: #e06c75; font-weight: bold; }"));
+    assert!(page.contains("<span class=\"h1\">"));
+    assert!(page.contains("<span class=\"b\">bold</span>"));
+    assert!(page.contains("&amp; &lt;text&gt;\n</pre>"));
+}
+
+#[test]
+fn test_html_inline_css_and_line_numbers() {
+    let text = lines(
+        &(1..=10)
+            .map(|n| format!("line {}", n))
+            .collect::<Vec<_>>()
+            .join("\n"),
+    );
+    let page = export::html(
+        &text,
+        Some(Path::new("notes.txt")),
+        None,
+        &Palette::default(),
+        &options(true, true),
+    );
+    assert!(!page.contains("<style>"));
+    assert!(!page.contains("class="));
+    assert!(page.contains("<span style=\"color:#5c6370;user-select:none\"> 1 </span>line 1\n"));
+    assert!(page.contains(">10 </span>line 10\n"));
+}
+
+#[test]
+fn test_html_of_a_selection() {
+    let range = BufferRange::new(BufferPosition::new(0, 4), BufferPosition::new(1, 3));
+    let page = export::html(
+        &lines("one two\nthree\nfour"),
+        None,
+        Some(range),
+        &Palette::default(),
+        &options(false, false),
+    );
+    assert!(page.contains("<pre class=\"zlyph\">two\nthr\n</pre>"));
+    assert!(!page.contains("four"));
+}
+
+#[test]
+fn test_options_from_config() {
+    let config = ExportConfig {
+        line_numbers: true,
+        inline_css: false,
+    };
+    let options = HtmlOptions::new(Some(Path::new("/notes/today.md")), &config);
+    assert_eq!(options.title, "today.md");
+    assert!(options.line_numbers);
+    assert_eq!(HtmlOptions::new(None, &config).title, "Untitled");
+    assert_eq!(
+        export::html_path(Path::new("/notes/today.md")),
+        Path::new("/notes/today.html")
+    );
+}
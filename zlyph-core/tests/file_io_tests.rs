@@ -0,0 +1,102 @@
+mod common;
+
+use common::temp_dir;
+use std::fs;
+use std::sync::mpsc::Receiver;
+use zlyph_core::{ChangeEvent, EditorAction, EditorEngine};
+
+fn drain(events: &Receiver<ChangeEvent>) -> Vec<ChangeEvent> {
+    events.try_iter().collect()
+}
+
+#[test]
+fn test_background_load_replaces_buffer_when_done() {
+    let dir = temp_dir("load");
+    let path = dir.join("notes.md");
+    fs::write(&path, "from disk").unwrap();
+    let mut engine = EditorEngine::builder().text("before").build();
+    let events = engine.subscribe();
+
+    engine.load_in_background(&path);
+    assert!(engine.file_io_pending());
+    engine.wait_for_file_io();
+
+    assert!(!engine.file_io_pending());
+    assert_eq!(engine.text(), "from disk");
+    let events = drain(&events);
+    assert!(events.contains(&ChangeEvent::Loading {
+        path: path.clone(),
+        done: 9,
+        total: 9
+    }));
+    assert_eq!(
+        events.last(),
+        Some(&ChangeEvent::Loaded {
+            path: Some(path.clone())
+        })
+    );
+}
+
+#[test]
+fn test_background_save_writes_buffer() {
+    let dir = temp_dir("save");
+    let path = dir.join("notes.md");
+    let mut engine = EditorEngine::builder().text("one\ntwo").build();
+    let events = engine.subscribe();
+
+    engine.save_in_background(&path).unwrap();
+    engine.wait_for_file_io();
+
+    assert_eq!(fs::read_to_string(&path).unwrap(), "one\ntwo");
+    assert!(drain(&events).contains(&ChangeEvent::Saved { path: path.clone() }));
+}
+
+#[test]
+fn test_later_save_replaces_earlier_one() {
+    let dir = temp_dir("supersede");
+    let path = dir.join("notes.md");
+    let mut engine = EditorEngine::builder().text("a").build();
+
+    engine.save_in_background(&path).unwrap();
+    engine.handle_action(EditorAction::MoveToEndOfLine);
+    engine.handle_action(EditorAction::TypeCharacter('b'));
+    engine.save_in_background(&path).unwrap();
+    engine.wait_for_file_io();
+
+    assert_eq!(fs::read_to_string(&path).unwrap(), "ab");
+    assert_eq!(fs::read_dir(&dir).unwrap().count(), 1);
+}
+
+#[test]
+fn test_cancelled_load_leaves_buffer() {
+    let dir = temp_dir("cancel");
+    let path = dir.join("notes.md");
+    fs::write(&path, "from disk").unwrap();
+    let mut engine = EditorEngine::builder().text("before").build();
+    let events = engine.subscribe();
+
+    engine.load_in_background(&path);
+    engine.cancel_file_io();
+    engine.wait_for_file_io();
+    while engine.poll_file_io() {}
+
+    assert!(!engine.file_io_pending());
+    assert_eq!(engine.text(), "before");
+    assert_eq!(drain(&events), vec![ChangeEvent::Cancelled { path }]);
+}
+
+#[test]
+fn test_failed_load_is_reported() {
+    let dir = temp_dir("missing");
+    let path = dir.join("missing.md");
+    let mut engine = EditorEngine::builder().text("before").build();
+    let events = engine.subscribe();
+
+    engine.load_in_background(&path);
+    engine.wait_for_file_io();
+
+    assert_eq!(engine.text(), "before");
+    assert!(drain(&events)
+        .iter()
+        .any(|event| matches!(event, ChangeEvent::Failed { path: failed, .. } if *failed == path)));
+}
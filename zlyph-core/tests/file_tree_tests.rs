@@ -0,0 +1,84 @@
+mod common;
+
+use common::temp_dir;
+use std::fs;
+use std::path::Path;
+use zlyph_core::file_tree::FileTree;
+
+fn write(path: &Path) {
+    fs::create_dir_all(path.parent().unwrap()).unwrap();
+    fs::write(path, "").unwrap();
+}
+
+fn names(tree: &FileTree) -> Vec<String> {
+    tree.entries()
+        .iter()
+        .map(|entry| format!("{}{}", "  ".repeat(entry.depth), entry.name))
+        .collect()
+}
+
+#[test]
+fn test_tree_lists_directories_first_and_expands() {
+    let dir = temp_dir("list");
+    write(&dir.join("b.md"));
+    write(&dir.join("A.md"));
+    write(&dir.join("notes/today.md"));
+    write(&dir.join(".hidden"));
+    write(&dir.join("build/out.txt"));
+    fs::write(dir.join(".gitignore"), "build/\n").unwrap();
+
+    let mut tree = FileTree::new(&dir);
+    assert_eq!(names(&tree), vec!["build", "notes", "A.md", "b.md"]);
+    assert!(tree.entries()[0].ignored);
+    assert!(!tree.entries()[1].ignored);
+
+    tree.selected = 1;
+    tree.expand_selected();
+    assert_eq!(
+        names(&tree),
+        vec!["build", "notes", "  today.md", "A.md", "b.md"]
+    );
+    tree.expand_selected();
+    assert_eq!(tree.selected_entry().unwrap().name, "today.md");
+    tree.collapse_selected();
+    assert_eq!(tree.selected_entry().unwrap().name, "notes");
+    tree.collapse_selected();
+    assert_eq!(names(&tree).len(), 4);
+
+    tree.set_expanded(0, true);
+    assert!(
+        tree.entries()[1].ignored,
+        "children of ignored dirs are ignored"
+    );
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_tree_file_operations() {
+    let dir = temp_dir("ops");
+    let mut tree = FileTree::new(&dir);
+
+    tree.create_file(&dir.join("docs/new.md")).unwrap();
+    assert_eq!(names(&tree), vec!["docs", "  new.md"]);
+    assert_eq!(tree.selected, 1);
+    assert!(tree.create_file(&dir.join("docs/new.md")).is_err());
+
+    tree.rename(&dir.join("docs/new.md"), &dir.join("docs/renamed.md"))
+        .unwrap();
+    assert_eq!(tree.selected_entry().unwrap().name, "renamed.md");
+
+    tree.create_dir(&dir.join("archive")).unwrap();
+    let moved = tree
+        .move_into(&dir.join("docs/renamed.md"), &dir.join("archive"))
+        .unwrap();
+    assert_eq!(moved, dir.join("archive/renamed.md"));
+    assert!(moved.exists());
+    assert_eq!(tree.selected_entry().unwrap().path, moved);
+
+    tree.delete(&dir.join("archive")).unwrap();
+    assert!(!dir.join("archive").exists());
+    assert_eq!(names(&tree), vec!["docs"]);
+
+    fs::remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,69 @@
+mod common;
+
+use common::buffer_lines;
+use zlyph_core::footnotes;
+use zlyph_core::{BufferPosition, EditorAction, EditorEngine};
+
+#[test]
+fn test_markers_skip_code() {
+    let markers = footnotes::markers(&buffer_lines(
+        "a[^1] b[^note]\n```\nx[^2]\n```\n[^1]: one\n[x] [^ 3]",
+    ));
+    let found: Vec<_> = markers
+        .iter()
+        .map(|m| (m.label.as_str(), m.row, m.columns.clone(), m.definition))
+        .collect();
+    assert_eq!(
+        found,
+        [
+            ("1", 0, 1..5, false),
+            ("note", 0, 7..14, false),
+            ("1", 4, 0..4, true),
+        ]
+    );
+    assert_eq!(footnotes::next_number(&buffer_lines("a[^1] b[^7]")), 8);
+}
+
+#[test]
+fn test_insert_between_renumbers() {
+    let mut engine = EditorEngine::new();
+    engine.load_text("First[^1] and second[^2].\n\n[^1]: one\n[^2]: two\n");
+    engine.handle_action(EditorAction::SetCursorPosition { row: 0, column: 9 });
+    engine.handle_action(EditorAction::InsertFootnote);
+    assert_eq!(
+        engine.state().to_string(),
+        "First[^1][^2] and second[^3].\n\n[^1]: one\n[^2]: \n[^3]: two"
+    );
+    assert_eq!(engine.state().cursor, BufferPosition::new(3, 6));
+    engine.handle_action(EditorAction::TypeString("new".to_string()));
+    engine.handle_action(EditorAction::Undo);
+    engine.handle_action(EditorAction::Undo);
+    assert_eq!(
+        engine.state().to_string(),
+        "First[^1] and second[^2].\n\n[^1]: one\n[^2]: two"
+    );
+
+    engine.load_text("Text");
+    engine.handle_action(EditorAction::SetCursorPosition { row: 0, column: 4 });
+    engine.handle_action(EditorAction::InsertFootnote);
+    assert_eq!(engine.state().to_string(), "Text[^1]\n\n[^1]: ");
+}
+
+#[test]
+fn test_renumber_and_go_to_footnote() {
+    let mut engine = EditorEngine::new();
+    engine.load_text("b[^2] a[^1] c[^x]\n\n[^1]: one\n\n[^x]: named\n\n[^2]: two\n  more");
+    engine.handle_action(EditorAction::RenumberFootnotes);
+    assert_eq!(
+        engine.state().to_string(),
+        "b[^1] a[^2] c[^x]\n\n[^1]: two\n  more\n\n[^2]: one\n\n[^x]: named"
+    );
+    engine.handle_action(EditorAction::RenumberFootnotes);
+    assert_eq!(engine.hover(), Some("Footnotes are already in order"));
+
+    engine.handle_action(EditorAction::SetCursorPosition { row: 0, column: 8 });
+    engine.handle_action(EditorAction::GoToFootnote);
+    assert_eq!(engine.state().cursor, BufferPosition::new(5, 6));
+    engine.handle_action(EditorAction::GoToFootnote);
+    assert_eq!(engine.state().cursor, BufferPosition::new(0, 7));
+}
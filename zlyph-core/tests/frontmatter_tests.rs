@@ -0,0 +1,262 @@
+mod common;
+
+use chrono::NaiveDate;
+use common::buffer_lines;
+use zlyph_core::config::{Config, TypographyConfig};
+use zlyph_core::ex::Command;
+use zlyph_core::frontmatter::{self, Value};
+use zlyph_core::{highlight, markdown, tags, EditorAction, EditorEngine};
+
+const NOTE: &str = "---\n\
+title: \"Reading: a list\"\n\
+date: 2026-03-04\n\
+tags:\n  - reading\n  - books\n\
+# picked up later\n\
+related: {kind: list}\n\
+---\n\
+# Books\n\
+Body text";
+
+fn list(items: &[&str]) -> Value {
+    Value::List(items.iter().map(|item| item.to_string()).collect())
+}
+
+#[test]
+fn test_fields_are_parsed() {
+    let front = frontmatter::parse(&buffer_lines(NOTE)).unwrap();
+
+    assert_eq!(front.rows, 0..9);
+    assert_eq!(front.title(), Some("Reading: a list"));
+    assert_eq!(front.date(), NaiveDate::from_ymd_opt(2026, 3, 4));
+    assert_eq!(front.tags(), ["reading", "books"]);
+    assert_eq!(front.field("tags").unwrap().rows, 3..6);
+    assert_eq!(
+        front.get("related"),
+        Some(&Value::Text("{kind: list}".into()))
+    );
+}
+
+#[test]
+fn test_inline_lists_and_quoted_items() {
+    let front = frontmatter::parse(&buffer_lines("---\ntags: [a, \"b, c\", '#d']\n---")).unwrap();
+
+    assert_eq!(front.get("tags"), Some(&list(&["a", "b, c", "#d"])));
+    assert_eq!(front.tags(), ["a", "b, c", "d"]);
+    let field = front.field("tags").unwrap();
+    let spans: Vec<_> = field.spans.iter().map(|(_, span)| span.clone()).collect();
+    assert_eq!(spans, [7..8, 11..15, 19..21]);
+}
+
+#[test]
+fn test_tags_written_as_text() {
+    let front =
+        frontmatter::parse(&buffer_lines("---\ntags: reading, #books # shelved\n---")).unwrap();
+    // As in YAML, a `#` after a space starts a comment
+    assert_eq!(front.tags(), ["reading"]);
+}
+
+#[test]
+fn test_no_frontmatter_without_both_fences() {
+    assert!(frontmatter::parse(&buffer_lines("title: x\n---")).is_none());
+    assert!(frontmatter::parse(&buffer_lines("---\ntitle: x")).is_none());
+    assert!(frontmatter::parse(&buffer_lines("# ---\n---")).is_none());
+}
+
+#[test]
+fn test_setting_a_field_rewrites_only_its_lines() {
+    let before = buffer_lines(NOTE);
+    let after = frontmatter::set_field(&before, "title", &Value::Text("Books".into()));
+
+    assert_eq!(after[1], "title: Books");
+    assert_eq!(after[..1], before[..1]);
+    assert_eq!(after[2..], before[2..]);
+}
+
+#[test]
+fn test_a_list_written_a_line_per_item_stays_that_way() {
+    let after = frontmatter::set_field(&buffer_lines(NOTE), "tags", &list(&["reading", "fiction"]));
+    assert_eq!(after[3..6], ["tags:", "  - reading", "  - fiction"]);
+    assert_eq!(after[6], "# picked up later");
+
+    let inline = frontmatter::set_field(&after, "topics", &list(&["a: b", "c"]));
+    assert_eq!(inline[8], "topics: [\"a: b\", c]");
+    assert_eq!(inline[9], "---");
+}
+
+#[test]
+fn test_text_is_quoted_when_needed() {
+    let set = |text: &str| {
+        frontmatter::set_field(
+            &buffer_lines("---\n---"),
+            "title",
+            &Value::Text(text.into()),
+        )[1]
+        .clone()
+    };
+    assert_eq!(set("Plain words"), "title: Plain words");
+    assert_eq!(set("Note: one"), "title: \"Note: one\"");
+    assert_eq!(set("#1 \"best\""), "title: \"#1 \\\"best\\\"\"");
+    assert_eq!(set(""), "title: \"\"");
+
+    let written = buffer_lines(&format!("---\n{}\n---", set("#1 \"best\"")));
+    let front = frontmatter::parse(&written).unwrap();
+    assert_eq!(front.title(), Some("#1 \"best\""));
+}
+
+#[test]
+fn test_setting_a_field_starts_a_block() {
+    let after = frontmatter::set_field(
+        &buffer_lines("# Heading"),
+        "date",
+        &Value::Text("2026-10-17".into()),
+    );
+    assert_eq!(after, ["---", "date: 2026-10-17", "---", "# Heading"]);
+}
+
+#[test]
+fn test_removing_fields() {
+    let after = frontmatter::remove_field(&buffer_lines(NOTE), "tags");
+    assert_eq!(after[3], "# picked up later");
+    assert_eq!(after.len(), buffer_lines(NOTE).len() - 3);
+
+    let only = buffer_lines("---\ntitle: x\n---\nbody");
+    assert_eq!(frontmatter::remove_field(&only, "title"), ["body"]);
+    assert_eq!(frontmatter::remove_field(&only, "missing"), only);
+}
+
+#[test]
+fn test_actions_edit_fields_as_one_undo_step() {
+    let mut engine = EditorEngine::builder().text(NOTE).build();
+    engine.handle_action(EditorAction::SetCursorPosition { row: 10, column: 4 });
+
+    engine.handle_action(EditorAction::SetFrontmatterField {
+        key: "tags".into(),
+        value: list(&["reading"]),
+    });
+    assert_eq!(engine.frontmatter().unwrap().tags(), ["reading"]);
+    assert_eq!(engine.state().cursor.row, 9);
+    assert_eq!(engine.state().lines[9], "Body text");
+
+    engine.handle_action(EditorAction::RemoveFrontmatterField("date".into()));
+    assert!(engine.frontmatter().unwrap().date().is_none());
+
+    engine.handle_action(EditorAction::Undo);
+    engine.handle_action(EditorAction::Undo);
+    assert_eq!(engine.text(), NOTE);
+}
+
+#[test]
+fn test_field_values_serialize_as_plain_json() {
+    let action: EditorAction =
+        serde_json::from_str(r#"{"SetFrontmatterField": {"key": "tags", "value": ["a", "b"]}}"#)
+            .unwrap();
+    assert_eq!(
+        action,
+        EditorAction::SetFrontmatterField {
+            key: "tags".into(),
+            value: list(&["a", "b"]),
+        }
+    );
+}
+
+#[test]
+fn test_field_ex_command() {
+    let mut engine = EditorEngine::builder().text("body").build();
+
+    Command::parse("field tags [reading, rust]")
+        .unwrap()
+        .execute(&mut engine)
+        .unwrap();
+    Command::parse("field title: Notes on Rust")
+        .unwrap()
+        .execute(&mut engine)
+        .unwrap();
+    assert_eq!(
+        engine.text(),
+        "---\ntags: [reading, rust]\ntitle: Notes on Rust\n---\nbody"
+    );
+
+    Command::parse("field tags")
+        .unwrap()
+        .execute(&mut engine)
+        .unwrap();
+    assert_eq!(engine.text(), "---\ntitle: Notes on Rust\n---\nbody");
+    assert!(Command::parse("3field title x").is_err());
+}
+
+#[test]
+fn test_smart_punctuation_leaves_frontmatter_alone() {
+    let config = Config {
+        typography: TypographyConfig { enabled: true },
+        ..Default::default()
+    };
+    let mut engine = EditorEngine::builder()
+        .config(config)
+        .text("---\ntitle: \n---\nbody")
+        .build();
+    engine.handle_action(EditorAction::SetCursorPosition { row: 1, column: 7 });
+    for c in "\"x\" --".chars() {
+        engine.handle_action(EditorAction::TypeCharacter(c));
+    }
+    assert_eq!(engine.state().lines[1], "title: \"x\" --");
+
+    engine.handle_action(EditorAction::SetCursorPosition { row: 3, column: 0 });
+    engine.handle_action(EditorAction::TypeCharacter('"'));
+    assert_eq!(engine.state().lines[3], "“body");
+}
+
+#[test]
+fn test_frontmatter_is_styled_as_markup_keys() {
+    let lines = buffer_lines(NOTE);
+    let highlights = markdown::highlight(&lines);
+    let markup = highlight::Style {
+        markup: true,
+        ..Default::default()
+    };
+
+    assert_eq!(highlights[0].spans, [(0..3, markup)]);
+    assert_eq!(highlights[1].spans, [(0..6, markup)]);
+    // Comments and list items inside aren't headings or list markers
+    assert!(highlights[6].spans.is_empty());
+    assert!(highlights[4].spans.is_empty());
+    assert_eq!(highlights[9].spans[0].1.heading, Some(1));
+}
+
+#[test]
+fn test_frontmatter_tags_are_found() {
+    let text = "---\ntags: [reading, \"#books\"]\n---\n#reading later";
+    let found = tags::find_tag(&buffer_lines(text), "reading");
+    let rows: Vec<_> = found
+        .iter()
+        .map(|(start, end)| (start.row, start.column, end.column))
+        .collect();
+    assert_eq!(rows, [(1, 7, 14), (3, 0, 8)]);
+
+    let found = tags::find_tag(&buffer_lines(text), "books");
+    assert_eq!(found[0].0.column, 18);
+    assert!(tags::find_tag(&buffer_lines(NOTE), "picked").is_empty());
+}
+
+#[test]
+fn test_frontmatter_collapses_while_the_cursor_is_outside() {
+    let config = Config::from_toml("[notes]\ncollapse_frontmatter = true\n").unwrap();
+    let mut engine = EditorEngine::builder().config(config).text(NOTE).build();
+    assert_eq!(engine.collapsed_frontmatter(), None);
+
+    engine.handle_action(EditorAction::SetCursorPosition { row: 10, column: 0 });
+    assert_eq!(engine.collapsed_frontmatter(), Some(1..9));
+    assert_eq!(
+        engine.frontmatter().unwrap().summary(),
+        "▸ Reading: a list · #reading #books · 2026-03-04"
+    );
+
+    engine.handle_action(EditorAction::SelectToTop);
+    assert_eq!(engine.collapsed_frontmatter(), None);
+}
+
+#[test]
+fn test_frontmatter_stays_expanded_unless_configured() {
+    let mut engine = EditorEngine::builder().text(NOTE).build();
+    engine.handle_action(EditorAction::SetCursorPosition { row: 10, column: 0 });
+    assert_eq!(engine.collapsed_frontmatter(), None);
+}
@@ -0,0 +1,104 @@
+mod common;
+
+use common::temp_dir;
+use std::fs;
+use std::process::Command;
+use zlyph_core::diff::ChangeKind;
+use zlyph_core::git::{self, GitGutter};
+use zlyph_core::BufferPosition;
+
+fn git(dir: &std::path::Path, args: &[&str]) -> bool {
+    Command::new("git")
+        .arg("-C")
+        .arg(dir)
+        .args(["-c", "user.name=test", "-c", "user.email=test@example.com"])
+        .args(args)
+        .output()
+        .is_ok_and(|output| output.status.success())
+}
+
+#[test]
+fn test_gutter_compares_against_head() {
+    let dir = temp_dir("gutter");
+    let file = dir.join("notes.md");
+    fs::write(&file, "one\ntwo\nthree\n").unwrap();
+    if !git(&dir, &["init", "-q"]) {
+        // git is not installed
+        fs::remove_dir_all(&dir).unwrap();
+        return;
+    }
+    assert_eq!(git::head_text(&file).unwrap(), None);
+    assert!(git(&dir, &["add", "notes.md"]));
+    assert!(git(&dir, &["commit", "-qm", "notes"]));
+    assert_eq!(
+        git::head_text(&file).unwrap().as_deref(),
+        Some("one\ntwo\nthree\n")
+    );
+
+    let mut gutter = GitGutter::new();
+    let lines: Vec<String> = ["one", "2", "three", "four"]
+        .iter()
+        .map(|s| s.to_string())
+        .collect();
+    assert!(gutter.update(Some(&file), &lines, 1));
+    assert!(!gutter.update(Some(&file), &lines, 1));
+    assert_eq!(gutter.marker(0, 4), None);
+    assert_eq!(gutter.marker(1, 4), Some(ChangeKind::Modified));
+    assert_eq!(gutter.marker(3, 4), Some(ChangeKind::Added));
+    assert_eq!(gutter.next_change(1, 4), Some(BufferPosition::new(3, 0)));
+    assert_eq!(gutter.next_change(3, 4), Some(BufferPosition::new(1, 0)));
+    assert_eq!(gutter.prev_change(3, 4), Some(BufferPosition::new(1, 0)));
+
+    // An untracked file has no markers
+    assert!(gutter.update(Some(&dir.join("other.md")), &lines, 1));
+    assert!(gutter.hunks().is_empty());
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_preview_revert_and_stage_hunks() {
+    let dir = temp_dir("hunks");
+    let file = dir.join("notes.md");
+    fs::write(&file, "one\ntwo\nthree\nfour\n").unwrap();
+    if !git(&dir, &["init", "-q"]) {
+        fs::remove_dir_all(&dir).unwrap();
+        return;
+    }
+    assert!(git(&dir, &["add", "notes.md"]));
+    assert!(git(&dir, &["commit", "-qm", "notes"]));
+
+    let lines: Vec<String> = ["one", "2", "three", "four", "five"]
+        .iter()
+        .map(|s| s.to_string())
+        .collect();
+    fs::write(&file, lines.join("\n")).unwrap();
+    let mut gutter = GitGutter::new();
+    gutter.update(Some(&file), &lines, 1);
+
+    let modified = gutter.hunk_at(1, lines.len()).unwrap();
+    assert_eq!(
+        gutter.preview(&modified, &lines),
+        "@@ -2,1 +2,1 @@\n-two\n+2"
+    );
+    assert_eq!(
+        gutter.revert(&modified, &lines),
+        vec!["one", "two", "three", "four", "five"]
+    );
+
+    let added = gutter.hunk_at(4, lines.len()).unwrap();
+    assert_eq!(gutter.preview(&added, &lines), "@@ -4,0 +5,1 @@\n+five");
+    gutter.stage(&added, &lines).unwrap();
+    let staged = Command::new("git")
+        .arg("-C")
+        .arg(&dir)
+        .args(["show", ":notes.md"])
+        .output()
+        .unwrap();
+    assert_eq!(
+        String::from_utf8_lossy(&staged.stdout),
+        "one\ntwo\nthree\nfour\nfive\n"
+    );
+
+    fs::remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,87 @@
+mod common;
+
+use common::buffer_lines;
+use std::time::{Duration, Instant};
+use zlyph_core::config::GoalConfig;
+use zlyph_core::goals::{count_words, WritingSession};
+use zlyph_core::{Config, EditorAction, EditorEngine};
+
+fn engine_with_goal(goal: GoalConfig) -> EditorEngine {
+    let mut engine = EditorEngine::new();
+    engine.set_config(Config {
+        goal,
+        ..Default::default()
+    });
+    engine
+}
+
+#[test]
+fn test_count_words_ignores_punctuation_only_tokens() {
+    assert_eq!(count_words(&buffer_lines("")), 0);
+    assert_eq!(
+        count_words(&buffer_lines("hello  world\n\n- one, two -- three")),
+        5
+    );
+}
+
+#[test]
+fn test_session_counts_words_since_start() {
+    let mut engine = engine_with_goal(GoalConfig {
+        words: Some(4),
+        ..Default::default()
+    });
+    engine.handle_action(EditorAction::TypeString("already here ".to_string()));
+
+    engine.handle_action(EditorAction::ToggleWritingSession);
+    engine.handle_action(EditorAction::TypeString("one two".to_string()));
+
+    let session = engine.writing_session().unwrap();
+    assert_eq!(session.words_written(engine.word_count()), 2);
+    assert_eq!(session.progress(engine.word_count()), Some(0.5));
+    assert_eq!(session.status(engine.word_count()), "2 / 4 words");
+
+    engine.handle_action(EditorAction::TypeString(" three four five".to_string()));
+    let session = engine.writing_session().unwrap();
+    assert!(session.is_complete(engine.word_count()));
+
+    engine.handle_action(EditorAction::ToggleWritingSession);
+    assert!(engine.writing_session().is_none());
+}
+
+#[test]
+fn test_deleting_text_never_goes_negative() {
+    let mut engine = engine_with_goal(GoalConfig::default());
+    engine.handle_action(EditorAction::TypeString("some words".to_string()));
+    engine.start_writing_session();
+
+    engine.handle_action(EditorAction::SelectAll);
+    engine.handle_action(EditorAction::Backspace);
+
+    let session = engine.writing_session().unwrap();
+    assert_eq!(session.words_written(engine.word_count()), 0);
+    assert_eq!(session.progress(engine.word_count()), None);
+    assert_eq!(session.status(engine.word_count()), "0 words");
+}
+
+#[test]
+fn test_timed_session_progress() {
+    let session = WritingSession {
+        goal: GoalConfig {
+            minutes: Some(20),
+            ..Default::default()
+        },
+        started: Instant::now() - Duration::from_secs(5 * 60),
+        start_words: 10,
+    };
+
+    let progress = session.progress(30).unwrap();
+    assert!((0.24..0.26).contains(&progress));
+    assert!(session.status(30).starts_with("20 words · 14:5"));
+
+    let finished = WritingSession {
+        started: Instant::now() - Duration::from_secs(21 * 60),
+        ..session
+    };
+    assert!(finished.is_complete(10));
+    assert_eq!(finished.status(10), "0 words · time's up");
+}
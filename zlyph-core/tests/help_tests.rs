@@ -0,0 +1,76 @@
+use zlyph_core::help::{self, HelpOverlay};
+use zlyph_core::EditorAction;
+
+fn overlay() -> HelpOverlay {
+    HelpOverlay::new([
+        ("Editing", "Undo", "Ctrl+Z"),
+        ("Editing", "Redo", "Ctrl+Shift+Z"),
+        ("Movement", "Move to beginning of line", "Home"),
+        ("Movement", "Move to beginning of line", "Ctrl+A"),
+        ("Movement", "Move to beginning of line", "Home"),
+        ("Files", "Open a file", "Ctrl+O"),
+    ])
+}
+
+#[test]
+fn test_bindings_of_one_action_are_merged() {
+    let overlay = overlay();
+    assert_eq!(overlay.entries().len(), 4);
+    assert_eq!(overlay.entries()[2].keys, vec!["Home", "Ctrl+A"]);
+}
+
+#[test]
+fn test_search_matches_every_word_in_any_field() {
+    let mut overlay = overlay();
+    overlay.type_text("ctrl shift");
+    let actions: Vec<&str> = overlay
+        .matches()
+        .iter()
+        .map(|e| e.action.as_str())
+        .collect();
+    assert_eq!(actions, vec!["Redo"]);
+
+    overlay.backspace();
+    overlay.backspace();
+    overlay.backspace();
+    overlay.backspace();
+    overlay.backspace();
+    overlay.backspace();
+    assert_eq!(overlay.query, "ctrl");
+    assert_eq!(overlay.matches().len(), 4);
+
+    overlay.query = "FILES".to_string();
+    assert_eq!(overlay.matches()[0].action, "Open a file");
+}
+
+#[test]
+fn test_scroll_stops_at_the_last_screen() {
+    let mut overlay = overlay();
+    overlay.scroll_by(10, 3);
+    assert_eq!(overlay.scroll, 1);
+    overlay.scroll_by(-5, 3);
+    assert_eq!(overlay.scroll, 0);
+    overlay.scroll_by(1, 10);
+    assert_eq!(overlay.scroll, 0);
+
+    overlay.scroll = 1;
+    overlay.type_text("z");
+    assert_eq!(overlay.scroll, 0);
+}
+
+#[test]
+fn test_action_label() {
+    assert_eq!(
+        help::action_label(&EditorAction::GoToDefinition),
+        "Go to definition"
+    );
+    assert_eq!(help::action_label(&EditorAction::Undo), "Undo");
+    assert_eq!(
+        help::action_label(&EditorAction::SetCursorPosition { row: 1, column: 2 }),
+        "Set cursor position"
+    );
+    assert_eq!(
+        help::action_label(&EditorAction::TypeCharacter('x')),
+        "Type character"
+    );
+}
@@ -0,0 +1,208 @@
+mod common;
+
+use common::lines;
+use std::path::Path;
+use zlyph_core::highlight::{Highlighter, Language, LineHighlights, LinkTarget, Style};
+use zlyph_core::markdown;
+
+fn one(line: &str) -> LineHighlights {
+    markdown::highlight(&lines(line)).remove(0)
+}
+
+/// The style of each span with its text, for readable assertions
+fn styled<'a>(line: &'a str, highlights: &LineHighlights) -> Vec<(&'a str, Style)> {
+    highlights
+        .spans
+        .iter()
+        .map(|(range, style)| (&line[range.clone()], *style))
+        .collect()
+}
+
+const MARKUP: Style = Style {
+    heading: None,
+    bold: false,
+    italic: false,
+    strikethrough: false,
+    code: false,
+    link: false,
+    quote: false,
+    markup: true,
+    bracket: None,
+    scope: 0,
+};
+
+#[test]
+fn test_headings_and_emphasis() {
+    let line = "## Title *here*";
+    let heading = Style {
+        heading: Some(2),
+        ..Style::default()
+    };
+    let italic = Style {
+        italic: true,
+        ..heading
+    };
+    assert_eq!(
+        styled(line, &one(line)),
+        vec![
+            (
+                "## ",
+                Style {
+                    markup: true,
+                    ..heading
+                }
+            ),
+            ("Title ", heading),
+            (
+                "*",
+                Style {
+                    markup: true,
+                    ..italic
+                }
+            ),
+            ("here", italic),
+            (
+                "*",
+                Style {
+                    markup: true,
+                    ..italic
+                }
+            ),
+        ]
+    );
+
+    let line = "a **b** ~~c~~ snake_case_name";
+    let spans = styled(line, &one(line));
+    assert!(spans.contains(&(
+        "b",
+        Style {
+            bold: true,
+            ..Style::default()
+        }
+    )));
+    assert!(spans.contains(&(
+        "c",
+        Style {
+            strikethrough: true,
+            ..Style::default()
+        }
+    )));
+    // Intraword underscores are not emphasis
+    assert!(!spans.iter().any(|(text, _)| text.contains("case")));
+    assert!(one("2 * 3 * 4").spans.is_empty());
+    assert!(one("#hashtag").spans.is_empty());
+}
+
+#[test]
+fn test_code_and_links() {
+    let line = "use `a*b*` or [the docs](https://example.com \"Docs\")";
+    let highlights = one(line);
+    let spans = styled(line, &highlights);
+    assert!(spans.contains(&(
+        "a*b*",
+        Style {
+            code: true,
+            ..Style::default()
+        }
+    )));
+    assert!(spans.contains(&(
+        "the docs",
+        Style {
+            link: true,
+            ..Style::default()
+        }
+    )));
+    assert_eq!(
+        highlights.links,
+        vec![(14..line.len(), "https://example.com".to_string())]
+    );
+    assert_eq!(highlights.link_at(16), Some("https://example.com"));
+    assert_eq!(highlights.link_at(2), None);
+
+    let line = "see https://example.com/a, or <mailto:me@example.com>.";
+    let targets: Vec<_> = one(line).links.into_iter().map(|(_, t)| t).collect();
+    assert_eq!(
+        targets,
+        vec!["https://example.com/a", "mailto:me@example.com"]
+    );
+    assert!(one("\\*not italic\\*").spans.iter().all(|(_, s)| s.markup));
+}
+
+#[test]
+fn test_blocks() {
+    let text = "> quoted\n- [x] done\n1. first\n---\n```rust\nlet *x* = 1;\n```\nafter *it*";
+    let all = markdown::highlight(&lines(text));
+    let source = lines(text);
+
+    let quote = styled(&source[0], &all[0]);
+    assert_eq!(
+        quote[0],
+        (
+            "> ",
+            Style {
+                quote: true,
+                ..MARKUP
+            }
+        )
+    );
+    assert_eq!(
+        quote[1],
+        (
+            "quoted",
+            Style {
+                quote: true,
+                ..Style::default()
+            }
+        )
+    );
+    assert_eq!(styled(&source[1], &all[1]), vec![("- [x] ", MARKUP)]);
+    assert_eq!(styled(&source[2], &all[2]), vec![("1. ", MARKUP)]);
+    assert_eq!(styled(&source[3], &all[3]), vec![("---", MARKUP)]);
+    assert_eq!(styled(&source[4], &all[4]), vec![("```rust", MARKUP)]);
+    // Nothing is parsed inside a fence
+    assert_eq!(
+        styled(&source[5], &all[5]),
+        vec![(
+            "let *x* = 1;",
+            Style {
+                code: true,
+                ..Style::default()
+            }
+        )]
+    );
+    assert_eq!(styled(&source[6], &all[6]), vec![("```", MARKUP)]);
+    assert!(all[7].spans.iter().any(|(_, s)| s.italic));
+}
+
+#[test]
+fn test_language_for_path() {
+    assert_eq!(
+        Language::for_path(Path::new("notes/Today.MD")),
+        Some(Language::Markdown)
+    );
+    assert_eq!(Language::for_path(Path::new("main.rs")), None);
+    assert_eq!(Language::for_path(Path::new("README")), None);
+
+    let mut highlighter = Highlighter::new();
+    highlighter.update(Some(Path::new("a.md")), &lines("[x](y)"), 1);
+    assert_eq!(highlighter.link_at(0, 1), Some("y"));
+    highlighter.update(Some(Path::new("a.txt")), &lines("[x](y)"), 1);
+    assert_eq!(highlighter.link_at(0, 1), None);
+}
+
+#[test]
+fn test_link_targets() {
+    let document = Path::new("/notes/today.md");
+    assert_eq!(
+        LinkTarget::resolve("https://example.com", document),
+        LinkTarget::Url("https://example.com".to_string())
+    );
+    assert_eq!(
+        LinkTarget::resolve("mailto:me@example.com", document),
+        LinkTarget::Url("mailto:me@example.com".to_string())
+    );
+    assert_eq!(
+        LinkTarget::resolve("ideas/plan.md#goals", document),
+        LinkTarget::File("/notes/ideas/plan.md".into())
+    );
+}
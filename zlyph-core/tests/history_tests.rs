@@ -0,0 +1,94 @@
+mod common;
+
+use common::temp_dir;
+use std::fs;
+use std::path::PathBuf;
+use std::time::{Duration, SystemTime};
+use zlyph_core::config::HistoryConfig;
+use zlyph_core::history::{LocalHistory, Snapshot};
+use zlyph_core::Config;
+
+#[test]
+fn test_snapshots_newest_first_skipping_unchanged() {
+    let dir = temp_dir("order");
+    let file = dir.join("notes.md");
+    fs::write(&file, "").unwrap();
+    let mut history = LocalHistory::with_dir(dir.join("history"), HistoryConfig::default());
+
+    assert!(history.snapshot(&file, "one").unwrap());
+    assert!(!history.snapshot(&file, "one").unwrap());
+    assert!(history.snapshot(&file, "two").unwrap());
+
+    let snapshots = history.snapshots(&file).unwrap();
+    assert_eq!(snapshots.len(), 2);
+    assert_eq!(snapshots[0].read().unwrap(), "two");
+    assert_eq!(snapshots[1].read().unwrap(), "one");
+    assert!(history.snapshots(&dir.join("other.md")).unwrap().is_empty());
+}
+
+#[test]
+fn test_record_waits_for_interval() {
+    let dir = temp_dir("interval");
+    let file = dir.join("notes.md");
+    let mut history = LocalHistory::with_dir(dir.join("history"), HistoryConfig::default());
+
+    history.record(&file, "first").unwrap();
+    history.record(&file, "second").unwrap();
+    let snapshots = history.snapshots(&file).unwrap();
+    assert_eq!(snapshots.len(), 1);
+    assert_eq!(snapshots[0].read().unwrap(), "first");
+}
+
+#[test]
+fn test_prune_by_size_and_age_keeps_newest() {
+    let dir = temp_dir("prune");
+    let file = dir.join("notes.md");
+    let config = HistoryConfig {
+        max_size_kb: 1,
+        ..HistoryConfig::default()
+    };
+    let mut history = LocalHistory::with_dir(dir.join("history"), config);
+
+    history.snapshot(&file, &"a".repeat(600)).unwrap();
+    let snapshot_dir = history.snapshots(&file).unwrap()[0]
+        .file
+        .parent()
+        .unwrap()
+        .to_path_buf();
+    fs::write(snapshot_dir.join("1000.txt"), "ancient").unwrap();
+    history.snapshot(&file, &"b".repeat(600)).unwrap();
+
+    let snapshots = history.snapshots(&file).unwrap();
+    assert_eq!(snapshots.len(), 1);
+    assert_eq!(snapshots[0].read().unwrap(), "b".repeat(600));
+
+    history.snapshot(&file, &"c".repeat(2000)).unwrap();
+    let snapshots = history.snapshots(&file).unwrap();
+    assert_eq!(snapshots.len(), 1);
+    assert_eq!(snapshots[0].size, 2000);
+}
+
+#[test]
+fn test_history_config_and_age_label() {
+    let config = Config::from_toml("[history]\nenabled = false\nmax_age_days = 7\n").unwrap();
+    assert!(!config.history.enabled);
+    assert_eq!(config.history.max_age_days, 7);
+    assert_eq!(config.history.interval_secs, 300);
+
+    let now = SystemTime::now();
+    let snapshot = |secs: u64| Snapshot {
+        file: PathBuf::new(),
+        time: now - Duration::from_secs(secs),
+        size: 0,
+    };
+    assert_eq!(snapshot(5).age_label(now), "just now");
+    assert_eq!(snapshot(60).age_label(now), "1 minute ago");
+    assert_eq!(snapshot(7200).age_label(now), "2 hours ago");
+    assert_eq!(snapshot(3 * 86400).age_label(now), "3 days ago");
+    let sized = |size: u64| Snapshot {
+        size,
+        ..snapshot(0)
+    };
+    assert_eq!(sized(512).size_label(), "512 B");
+    assert_eq!(sized(1536).size_label(), "1.5 KB");
+}
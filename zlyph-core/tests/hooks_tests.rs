@@ -0,0 +1,122 @@
+#![cfg(unix)]
+
+mod common;
+
+use common::temp_dir;
+use std::fs;
+use std::time::Duration;
+use zlyph_core::hooks::{HookDefinition, HookEvent, HookRegistry};
+use zlyph_core::Config;
+
+/// A hook appending its event name to `log` in the file's directory
+fn logging_hook(on: HookEvent) -> HookDefinition {
+    HookDefinition {
+        on,
+        command: "sh".to_string(),
+        args: vec![
+            "-c".to_string(),
+            "echo \"$ZLYPH_EVENT $1\" >> log".to_string(),
+            "hook".to_string(),
+            "{file}".to_string(),
+        ],
+        files: None,
+        idle_secs: 0,
+    }
+}
+
+#[test]
+fn test_hooks_from_config() {
+    let config = Config::from_toml(
+        "[[hooks]]\non = \"save\"\ncommand = \"prettier\"\nargs = [\"--write\", \"{file}\"]\nfiles = \"*.md\"\n\n[[hooks]]\non = \"focus_lost\"\ncommand = \"true\"\n",
+    )
+    .unwrap();
+    assert_eq!(config.hooks.len(), 2);
+    assert_eq!(config.hooks[0].on, HookEvent::Save);
+    assert_eq!(config.hooks[0].files.as_deref(), Some("*.md"));
+    assert_eq!(config.hooks[1].on, HookEvent::FocusLost);
+    assert_eq!(config.hooks[1].idle_secs, 60);
+}
+
+#[test]
+fn test_fire_runs_matching_hooks_in_file_directory() {
+    let dir = temp_dir("fire");
+    let file = dir.join("notes.md");
+    let mut hooks = HookRegistry::new(vec![logging_hook(HookEvent::Open)]);
+    hooks.register(HookDefinition {
+        files: Some("*.rs".to_string()),
+        ..logging_hook(HookEvent::Open)
+    });
+
+    hooks.fire(HookEvent::Save, &file).unwrap();
+    hooks.fire(HookEvent::Open, &file).unwrap();
+    hooks.wait();
+
+    let log = fs::read_to_string(dir.join("log")).unwrap();
+    assert_eq!(log, format!("open {}\n", file.display()));
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_save_hooks_wait_for_edits_to_settle() {
+    let dir = temp_dir("save");
+    let file = dir.join("notes.md");
+    let mut hooks = HookRegistry::new(vec![logging_hook(HookEvent::Save)]);
+
+    hooks.edited(&file).unwrap();
+    hooks.poll(&file).unwrap();
+    hooks.wait();
+    assert!(!dir.join("log").exists());
+
+    hooks.flush(&file).unwrap();
+    hooks.wait();
+    hooks.flush(&file).unwrap();
+    hooks.wait();
+    assert_eq!(
+        fs::read_to_string(dir.join("log")).unwrap(),
+        format!("save {}\n", file.display())
+    );
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_idle_hooks_run_once_per_pause() {
+    let dir = temp_dir("idle");
+    let file = dir.join("journal.md");
+    let mut hooks = HookRegistry::new(vec![logging_hook(HookEvent::Idle)]);
+
+    // Nothing to do before the first edit
+    hooks.poll(&file).unwrap();
+    hooks.edited(&file).unwrap();
+    hooks.poll(&file).unwrap();
+    hooks.wait();
+    hooks.poll(&file).unwrap();
+    hooks.wait();
+    hooks.edited(&file).unwrap();
+    hooks.poll(&file).unwrap();
+    hooks.wait();
+
+    let log = fs::read_to_string(dir.join("log")).unwrap();
+    assert_eq!(log.lines().count(), 2);
+    assert!(log.starts_with("idle "));
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_next_due_counts_down_to_the_first_waiting_hook() {
+    let dir = temp_dir("due");
+    let file = dir.join("note.md");
+    let mut idle = logging_hook(HookEvent::Idle);
+    idle.idle_secs = 60;
+    let mut hooks = HookRegistry::new(vec![logging_hook(HookEvent::Save), idle]);
+    assert_eq!(hooks.next_due(), None);
+
+    hooks.edited(&file).unwrap();
+    let due = hooks.next_due().unwrap();
+    assert!(due <= zlyph_core::hooks::SAVE_SETTLE && due > Duration::ZERO);
+
+    hooks.flush(&file).unwrap();
+    let due = hooks.next_due().unwrap();
+    assert!(due > Duration::from_secs(59) && due <= Duration::from_secs(60));
+    hooks.wait();
+    fs::remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,68 @@
+mod common;
+
+use common::lines;
+use zlyph_core::indent_guides::{self, ActiveGuide, IndentGuides};
+
+fn guides(text: &str) -> IndentGuides {
+    let mut guides = IndentGuides::new();
+    guides.update(&lines(text), 0);
+    guides
+}
+
+#[test]
+fn test_indent_counts_tabs_to_the_next_stop() {
+    assert_eq!(indent_guides::indent("    x"), Some(4));
+    assert_eq!(indent_guides::indent("\tx"), Some(4));
+    assert_eq!(indent_guides::indent("  \tx"), Some(4));
+    assert_eq!(indent_guides::indent("   "), None);
+    assert_eq!(indent_guides::indent(""), None);
+}
+
+#[test]
+fn test_unit_follows_the_buffer() {
+    assert_eq!(indent_guides::unit(&lines("a:\n  b:\n    c\n  d")), 2);
+    assert_eq!(indent_guides::unit(&lines("fn a() {\n    b\n}")), 4);
+    assert_eq!(indent_guides::unit(&lines("flat\ntext")), 4);
+}
+
+#[test]
+fn test_guides_run_through_blank_lines_inside_a_block() {
+    let guides =
+        guides("fn a() {\n    if b {\n        c();\n\n        d();\n    }\n}\n\nfn e() {}");
+    let columns = |row| guides.columns(row).collect::<Vec<_>>();
+    assert_eq!(columns(0), Vec::<usize>::new());
+    assert_eq!(columns(2), vec![0, 4]);
+    assert_eq!(columns(3), vec![0, 4]);
+    assert_eq!(columns(5), vec![0]);
+    assert_eq!(columns(7), Vec::<usize>::new());
+}
+
+#[test]
+fn test_active_guide_is_the_block_around_the_cursor() {
+    let guides = guides("fn a() {\n    if b {\n        c();\n        d();\n    }\n    e();\n}");
+    // Inside the if
+    assert_eq!(
+        guides.active(3),
+        Some(ActiveGuide {
+            column: 4,
+            rows: 2..4
+        })
+    );
+    // On the line opening the if, its body
+    assert_eq!(
+        guides.active(1),
+        Some(ActiveGuide {
+            column: 4,
+            rows: 2..4
+        })
+    );
+    // In the function's body
+    assert_eq!(
+        guides.active(5),
+        Some(ActiveGuide {
+            column: 0,
+            rows: 1..6
+        })
+    );
+    assert_eq!(guides.active(6), None);
+}
@@ -0,0 +1,66 @@
+#![cfg(unix)]
+
+mod common;
+
+use common::temp_dir;
+use std::fs;
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+use zlyph_core::instance::{path_from_url, send_to_existing, InstanceListener};
+
+#[test]
+fn test_second_launch_hands_path_to_running_instance() {
+    let dir = temp_dir("handoff");
+    let socket = dir.join("gui.sock");
+    let file = PathBuf::from("/notes/today.md");
+
+    assert!(!send_to_existing(&socket, &file).unwrap());
+
+    let listener = InstanceListener::bind(&socket).unwrap();
+    assert!(InstanceListener::bind(&socket).is_err());
+    assert!(send_to_existing(&socket, &file).unwrap());
+
+    let started = Instant::now();
+    let mut received = Vec::new();
+    while received.is_empty() && started.elapsed() < Duration::from_secs(2) {
+        received = listener.poll();
+    }
+    assert_eq!(received, vec![file.clone()]);
+
+    drop(listener);
+    assert!(!socket.exists());
+    assert!(!send_to_existing(&socket, &file).unwrap());
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_stale_socket_is_replaced() {
+    let dir = temp_dir("stale");
+    let socket = dir.join("gui.sock");
+    drop(std::os::unix::net::UnixListener::bind(&socket).unwrap());
+    assert!(socket.exists());
+
+    let _listener = InstanceListener::bind(&socket).unwrap();
+    assert!(send_to_existing(&socket, &dir.join("a.md")).unwrap());
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_path_from_file_url() {
+    assert_eq!(
+        path_from_url("file:///Users/me/notes%20today/caf%C3%A9.md"),
+        Some(PathBuf::from("/Users/me/notes today/café.md"))
+    );
+    assert_eq!(
+        path_from_url("file://localhost/tmp/a.md"),
+        Some(PathBuf::from("/tmp/a.md"))
+    );
+    assert_eq!(
+        path_from_url("file:///tmp/100%"),
+        Some(PathBuf::from("/tmp/100%"))
+    );
+    assert_eq!(path_from_url("file://server/share/a.md"), None);
+    assert_eq!(path_from_url("https://example.com/a.md"), None);
+}
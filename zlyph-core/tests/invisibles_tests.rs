@@ -0,0 +1,85 @@
+use zlyph_core::diagnostics::Severity;
+use zlyph_core::invisibles::{self, Kind};
+use zlyph_core::{BufferPosition, EditorAction, EditorEngine};
+
+#[test]
+fn test_find_invisible_characters() {
+    let found = invisibles::find_in_line("a\u{200B}b\u{202E}c\u{00A0}d");
+    let kinds: Vec<(char, Kind)> = found.iter().map(|s| (s.c, s.kind)).collect();
+    assert_eq!(
+        kinds,
+        vec![
+            ('\u{200B}', Kind::Invisible),
+            ('\u{202E}', Kind::Invisible),
+            ('\u{00A0}', Kind::Space),
+        ]
+    );
+    assert_eq!(found[0].range, 1..4);
+}
+
+#[test]
+fn test_joiners_in_emoji_are_fine() {
+    // Family emoji are joined with U+200D
+    assert!(invisibles::find_in_line("👩\u{200D}👧").is_empty());
+    assert_eq!(
+        invisibles::find_in_line("ab\u{200D}cd")[0].kind,
+        Kind::Invisible
+    );
+}
+
+#[test]
+fn test_lookalikes_only_in_latin_words() {
+    // Cyrillic "а" in "pаypal"
+    let found = invisibles::find_in_line("log in to pаypal");
+    assert_eq!(found.len(), 1);
+    assert_eq!(found[0].kind, Kind::Lookalike('a'));
+    assert!(invisibles::find_in_line("привет мир").is_empty());
+    assert!(invisibles::find_in_line("καλημέρα").is_empty());
+}
+
+#[test]
+fn test_diagnostics_messages_and_fixes() {
+    let lines = vec!["plain".to_string(), "x\u{00A0}y\u{FEFF}".to_string()];
+    let found = invisibles::diagnostics(&lines);
+    assert_eq!(found.len(), 2);
+    assert_eq!(found[0].start, BufferPosition::new(1, 1));
+    assert_eq!(found[0].severity, Severity::Information);
+    assert_eq!(found[0].message, "Unusual space: no-break space (U+00A0)");
+    assert_eq!(found[0].replacements, vec![" ".to_string()]);
+    assert_eq!(found[1].severity, Severity::Warning);
+    assert!(found[1].message.contains("U+FEFF"));
+    assert_eq!(found[0].source, invisibles::SOURCE);
+}
+
+#[test]
+fn test_strip_keeps_cursor_on_text() {
+    let line = "a\u{200B}b\u{00A0}c";
+    let column = line.find('c').unwrap();
+    assert_eq!(
+        invisibles::strip(line, column),
+        Some(("ab c".to_string(), 3))
+    );
+    assert_eq!(invisibles::strip("plain", 2), None);
+    // Lookalikes are left to the quick fix
+    assert_eq!(invisibles::strip("pаypal", 0), None);
+}
+
+#[test]
+fn test_engine_publishes_and_strips() {
+    let mut engine = EditorEngine::new();
+    engine.load_text("zero\u{200B}width");
+    assert_eq!(engine.diagnostics().len(), 1);
+
+    engine.handle_action(EditorAction::MoveToEndOfLine);
+    engine.handle_action(EditorAction::TypeCharacter('\u{00A0}'));
+    assert_eq!(engine.diagnostics().len(), 2);
+
+    engine.handle_action(EditorAction::StripInvisibles);
+    assert_eq!(engine.state().lines[0], "zerowidth ");
+    assert_eq!(engine.state().cursor.column, "zerowidth ".len());
+    assert!(engine.diagnostics().is_empty());
+
+    engine.handle_action(EditorAction::Undo);
+    assert_eq!(engine.state().lines[0], "zero\u{200B}width\u{00A0}");
+    assert_eq!(engine.diagnostics().len(), 2);
+}
@@ -0,0 +1,80 @@
+mod common;
+
+use chrono::NaiveDate;
+use common::temp_dir;
+use std::fs;
+use zlyph_core::journal::Journal;
+use zlyph_core::Config;
+
+fn date(y: i32, m: u32, d: u32) -> NaiveDate {
+    NaiveDate::from_ymd_opt(y, m, d).unwrap()
+}
+
+#[test]
+fn test_open_creates_note_with_heading() {
+    let dir = temp_dir("heading");
+    let journal = Journal::with_dir(dir.clone(), &Config::default());
+    let path = journal.open(date(2026, 10, 17), String::new()).unwrap();
+    assert_eq!(path, dir.join("2026-10-17.md"));
+    assert_eq!(
+        fs::read_to_string(&path).unwrap(),
+        "# Saturday, October 17, 2026\n\n"
+    );
+
+    // An existing note is left alone
+    fs::write(&path, "kept").unwrap();
+    journal.open(date(2026, 10, 17), String::new()).unwrap();
+    assert_eq!(fs::read_to_string(&path).unwrap(), "kept");
+    assert_eq!(journal.date_of(&path), Some(date(2026, 10, 17)));
+    assert_eq!(journal.date_of(&dir.join("notes.md")), None);
+}
+
+#[test]
+fn test_template_and_format() {
+    let dir = temp_dir("template");
+    let template = dir.join("daily.md");
+    fs::write(&template, "## {{date}}\n- [ ] \n").unwrap();
+    let config = Config::from_toml(&format!(
+        "[journal]\ntemplate = {:?}\nheading_format = \"%d/%m\"\n",
+        template.display().to_string()
+    ))
+    .unwrap();
+    let journal = Journal::with_dir(dir.clone(), &config);
+    let path = journal.open(date(2026, 1, 2), String::new()).unwrap();
+    assert_eq!(fs::read_to_string(path).unwrap(), "## 02/01\n- [ ] \n");
+
+    let config = Config::from_toml("[journal]\nheading_format = \"%Q\"\n").unwrap();
+    let journal = Journal::with_dir(dir, &config);
+    assert!(journal
+        .initial_text(date(2026, 1, 2), String::new())
+        .is_err());
+}
+
+#[test]
+fn test_adjacent_days() {
+    let dir = temp_dir("adjacent");
+    let journal = Journal::with_dir(dir.clone(), &Config::default());
+    for day in [3, 5, 9] {
+        journal.open(date(2020, 1, day), String::new()).unwrap();
+    }
+    fs::write(dir.join("ideas.md"), "").unwrap();
+    assert_eq!(
+        journal.dates(),
+        vec![date(2020, 1, 3), date(2020, 1, 5), date(2020, 1, 9)]
+    );
+
+    let current = journal.path_for(date(2020, 1, 5));
+    assert_eq!(
+        journal.adjacent(Some(&current), false),
+        Some(date(2020, 1, 3))
+    );
+    assert_eq!(
+        journal.adjacent(Some(&current), true),
+        Some(date(2020, 1, 9))
+    );
+    let first = journal.path_for(date(2020, 1, 3));
+    assert_eq!(journal.adjacent(Some(&first), false), None);
+    // From outside the journal, counting from today
+    assert_eq!(journal.adjacent(None, false), Some(date(2020, 1, 9)));
+    assert_eq!(journal.adjacent(None, true), None);
+}
@@ -0,0 +1,82 @@
+mod common;
+
+use common::lines;
+use zlyph_core::jump::{self, LeapTarget};
+use zlyph_core::{BufferPosition, EditorAction, EditorEngine};
+
+#[test]
+fn test_find_char_moves_on_along_the_line() {
+    let line = "let (a, b) = (1, 2);";
+    assert_eq!(jump::find_char(line, 0, '(', false), Some(4));
+    assert_eq!(jump::find_char(line, 4, '(', false), Some(13));
+    assert_eq!(jump::find_char(line, 0, ',', true), Some(5));
+    // Just before a comma already, the next one is found
+    assert_eq!(jump::find_char(line, 5, ',', true), Some(14));
+    assert_eq!(jump::find_char(line, 0, 'z', false), None);
+    assert_eq!(jump::find_char(line, 19, ';', false), None);
+}
+
+#[test]
+fn test_leap_targets_are_labeled_nearest_first() {
+    let lines = lines("ab ab\nxx\nab");
+    let targets = jump::leap_targets(&lines, 0..3, ['a', 'b'], BufferPosition::new(0, 0));
+    assert_eq!(
+        targets,
+        vec![
+            LeapTarget {
+                position: BufferPosition::new(0, 3),
+                label: 's'
+            },
+            LeapTarget {
+                position: BufferPosition::new(2, 0),
+                label: 'f'
+            },
+        ]
+    );
+    // Rows out of view aren't looked at
+    assert_eq!(
+        jump::leap_targets(&lines, 1..2, ['a', 'b'], BufferPosition::new(0, 0)),
+        vec![]
+    );
+}
+
+#[test]
+fn test_engine_jumps_to_the_typed_character() {
+    let mut engine = EditorEngine::new();
+    engine.load_text("call(one, two)");
+    engine.handle_action(EditorAction::JumpToChar);
+    assert!(engine.hover().is_some());
+    engine.handle_action(EditorAction::TypeCharacter(','));
+    assert_eq!(engine.state().cursor, BufferPosition::new(0, 8));
+    assert_eq!(engine.text(), "call(one, two)");
+
+    engine.handle_action(EditorAction::JumpBeforeChar);
+    engine.handle_action(EditorAction::TypeString(")".to_string()));
+    assert_eq!(engine.state().cursor, BufferPosition::new(0, 12));
+
+    // Escape gives up the jump without typing anything
+    engine.handle_action(EditorAction::JumpToChar);
+    engine.handle_action(EditorAction::Cancel);
+    engine.handle_action(EditorAction::TypeCharacter('x'));
+    assert_eq!(engine.text(), "call(one, twxo)");
+}
+
+#[test]
+fn test_engine_leaps_to_a_label() {
+    let mut engine = EditorEngine::new();
+    engine.load_text("fn a() {}\nfn b() {}\nfn c() {}");
+    engine.handle_action(EditorAction::Leap);
+    engine.handle_action(EditorAction::TypeCharacter('f'));
+    engine.handle_action(EditorAction::TypeCharacter('n'));
+    assert_eq!(engine.line_decorations(1).len(), 1);
+    assert_eq!(engine.line_decorations(2).len(), 1);
+    engine.handle_action(EditorAction::TypeCharacter('f'));
+    assert_eq!(engine.state().cursor, BufferPosition::new(2, 0));
+    assert!(engine.line_decorations(1).is_empty());
+
+    // A single place is leapt to straight away
+    engine.handle_action(EditorAction::Leap);
+    engine.handle_action(EditorAction::TypeCharacter('b'));
+    engine.handle_action(EditorAction::TypeCharacter('('));
+    assert_eq!(engine.state().cursor, BufferPosition::new(1, 3));
+}
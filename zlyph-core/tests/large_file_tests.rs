@@ -0,0 +1,118 @@
+mod common;
+
+use common::temp_dir;
+use std::fs;
+use zlyph_core::config::{Config, LargeFileConfig};
+use zlyph_core::large_file;
+use zlyph_core::{ChangeEvent, EditorAction, EditorEngine};
+
+/// Treat every file as large, however small
+fn large_engine() -> EditorEngine {
+    let mut config = Config::default();
+    config.large_file.threshold_mb = 0;
+    EditorEngine::builder().config(config).build()
+}
+
+#[test]
+fn test_read_lines_splits_like_str_lines() {
+    let dir = temp_dir("read");
+    let path = dir.join("log.txt");
+    fs::write(&path, "one\r\ntwo\n\nthree\n").unwrap();
+
+    let lines = large_file::read_lines(&path).unwrap();
+
+    let expected: Vec<String> = "one\r\ntwo\n\nthree\n".lines().map(String::from).collect();
+    assert_eq!(lines, expected);
+
+    fs::write(&path, "").unwrap();
+    assert_eq!(large_file::read_lines(&path).unwrap(), vec![String::new()]);
+}
+
+#[test]
+fn test_write_lines_replaces_file_without_leaving_temp() {
+    let dir = temp_dir("write");
+    let path = dir.join("data.csv");
+    fs::write(&path, "old").unwrap();
+
+    let lines = vec!["a,b".to_string(), "1,2".to_string()];
+    large_file::write_lines(&path, &lines).unwrap();
+
+    assert_eq!(fs::read_to_string(&path).unwrap(), "a,b\n1,2");
+    assert_eq!(fs::read_dir(&dir).unwrap().count(), 1);
+}
+
+#[test]
+fn test_is_large_checks_size_against_threshold() {
+    let dir = temp_dir("size");
+    let path = dir.join("big.txt");
+    fs::write(&path, "x".repeat(1024 * 1024)).unwrap();
+
+    assert!(large_file::is_large(
+        &path,
+        &LargeFileConfig { threshold_mb: 1 }
+    ));
+    assert!(!large_file::is_large(
+        &path,
+        &LargeFileConfig { threshold_mb: 2 }
+    ));
+    assert!(!large_file::is_large(
+        &dir.join("missing.txt"),
+        &LargeFileConfig { threshold_mb: 0 }
+    ));
+}
+
+#[test]
+fn test_large_file_opens_edits_and_saves() {
+    let dir = temp_dir("engine");
+    let path = dir.join("dump.sql");
+    fs::write(&path, "insert 1;\ninsert 2;").unwrap();
+    let mut engine = large_engine();
+
+    engine.load_from_file(&path).unwrap();
+    assert!(engine.is_large_file());
+    assert_eq!(engine.text(), "insert 1;\ninsert 2;");
+
+    engine.handle_action(EditorAction::SetCursorPosition { row: 1, column: 9 });
+    engine.handle_action(EditorAction::TypeString(" -- done".to_string()));
+    engine.save_to_file(&path).unwrap();
+
+    assert_eq!(
+        fs::read_to_string(&path).unwrap(),
+        "insert 1;\ninsert 2; -- done"
+    );
+}
+
+#[test]
+fn test_large_file_keeps_no_undo_history() {
+    let mut engine = large_engine();
+    engine.load_text("abc");
+    assert!(engine.is_large_file());
+
+    engine.handle_action(EditorAction::TypeCharacter('x'));
+    engine.handle_action(EditorAction::Undo);
+
+    assert_eq!(engine.text(), "xabc");
+}
+
+#[test]
+fn test_large_file_edits_are_reported_without_ranges() {
+    let mut engine = large_engine();
+    engine.load_text("abc");
+    let events = engine.subscribe();
+
+    engine.handle_action(EditorAction::TypeCharacter('x'));
+
+    let events: Vec<_> = events.try_iter().collect();
+    assert!(events.contains(&ChangeEvent::Edited));
+    assert!(!events
+        .iter()
+        .any(|event| matches!(event, ChangeEvent::Replaced { .. })));
+}
+
+#[test]
+fn test_small_text_is_not_large() {
+    let mut engine = EditorEngine::new();
+    engine.load_text("abc");
+
+    assert!(!engine.is_large_file());
+}
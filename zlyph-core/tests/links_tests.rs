@@ -0,0 +1,70 @@
+use std::path::{Path, PathBuf};
+use zlyph_core::highlight::LinkTarget;
+use zlyph_core::links::{self, Link};
+
+fn targets(line: &str) -> Vec<String> {
+    links::find_all(line)
+        .into_iter()
+        .map(|link| link.target)
+        .collect()
+}
+
+#[test]
+fn test_finds_urls() {
+    assert_eq!(
+        targets("See https://example.com/a?b=1, or www.rust-lang.org."),
+        vec!["https://example.com/a?b=1", "www.rust-lang.org"]
+    );
+    // A closing parenthesis stays when the URL opened one
+    assert_eq!(
+        targets("(https://en.wikipedia.org/wiki/Rust_(language))"),
+        vec!["https://en.wikipedia.org/wiki/Rust_(language)"]
+    );
+    assert_eq!(
+        targets("mailto:me@example.com"),
+        vec!["mailto:me@example.com"]
+    );
+}
+
+#[test]
+fn test_finds_paths_with_line_numbers() {
+    let found = links::find_all("error at src/main.rs:42:7: oops");
+    assert_eq!(
+        found,
+        vec![Link {
+            range: 9..25,
+            target: "src/main.rs".to_string(),
+            line: Some(42),
+            column: Some(7),
+        }]
+    );
+    assert_eq!(
+        targets("Open ~/notes/today.md, ./build.sh and /etc/hosts."),
+        vec!["~/notes/today.md", "./build.sh", "/etc/hosts"]
+    );
+    // Words and dates with slashes are not paths
+    assert!(targets("and/or on 1/2/2024 at 9/10").is_empty());
+}
+
+#[test]
+fn test_link_at_and_resolve() {
+    let line = "see notes/todo.md or https://example.com";
+    let link = links::link_at(line, 6).unwrap();
+    assert_eq!(
+        link.resolve(Some(Path::new("/home/me/index.md"))),
+        LinkTarget::File(PathBuf::from("/home/me/notes/todo.md"))
+    );
+    let link = links::link_at(line, 25).unwrap();
+    assert!(link.is_url());
+    assert_eq!(
+        link.resolve(None),
+        LinkTarget::Url("https://example.com".to_string())
+    );
+    assert_eq!(links::link_at(line, 1), None);
+
+    let www = links::link_at("www.example.com", 0).unwrap();
+    assert_eq!(
+        www.resolve(None),
+        LinkTarget::Url("https://www.example.com".to_string())
+    );
+}
@@ -0,0 +1,102 @@
+#![cfg(all(unix, feature = "live-sync"))]
+
+mod common;
+
+use common::temp_dir;
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+use zlyph_core::live_sync::LiveSync;
+
+fn socket(name: &str) -> PathBuf {
+    temp_dir(name).join("file.sock")
+}
+
+/// Poll every instance until they all hold `expected`
+fn settle(instances: &mut [&mut LiveSync], expected: &str) {
+    let deadline = Instant::now() + Duration::from_secs(5);
+    while Instant::now() < deadline {
+        for live in instances.iter_mut() {
+            live.poll().unwrap();
+        }
+        if instances
+            .iter()
+            .all(|live| live.is_joined() && live.text() == expected)
+        {
+            return;
+        }
+        std::thread::sleep(Duration::from_millis(10));
+    }
+    let texts: Vec<&str> = instances.iter().map(|live| live.text()).collect();
+    panic!("expected {:?} everywhere, got {:?}", expected, texts);
+}
+
+#[test]
+fn test_joining_takes_the_hosts_text() {
+    let socket = socket("join");
+    let mut host = LiveSync::join_at(&socket, "hello").unwrap();
+    let mut client = LiveSync::join_at(&socket, "stale copy").unwrap();
+    assert!(host.is_host());
+    assert!(!client.is_host());
+    assert_eq!(client.text(), "stale copy");
+    settle(&mut [&mut host, &mut client], "hello");
+}
+
+#[test]
+fn test_edits_made_while_joining_are_kept() {
+    let socket = socket("pending");
+    let mut host = LiveSync::join_at(&socket, "hello").unwrap();
+    let mut client = LiveSync::join_at(&socket, "hello").unwrap();
+    client.local_edit("hello there").unwrap();
+    settle(&mut [&mut host, &mut client], "hello there");
+}
+
+#[test]
+fn test_simultaneous_edits_merge() {
+    let socket = socket("merge");
+    let mut host = LiveSync::join_at(&socket, "hello").unwrap();
+    let mut client = LiveSync::join_at(&socket, "hello").unwrap();
+    let mut other = LiveSync::join_at(&socket, "hello").unwrap();
+    settle(&mut [&mut host, &mut client, &mut other], "hello");
+
+    host.local_edit("hello world").unwrap();
+    client.local_edit("oh hello").unwrap();
+    other.local_edit("hellö").unwrap();
+    settle(&mut [&mut host, &mut client, &mut other], "oh hellö world");
+}
+
+#[test]
+fn test_client_takes_over_from_departed_host() {
+    let socket = socket("takeover");
+    let host = LiveSync::join_at(&socket, "one").unwrap();
+    let mut client = LiveSync::join_at(&socket, "one").unwrap();
+    // Gone before sending its document, so the client starts its own
+    drop(host);
+
+    let deadline = Instant::now() + Duration::from_secs(5);
+    while !client.is_host() && Instant::now() < deadline {
+        client.poll().unwrap();
+        std::thread::sleep(Duration::from_millis(10));
+    }
+    assert!(client.is_host());
+
+    let mut late = LiveSync::join_at(&socket, "one").unwrap();
+    settle(&mut [&mut client, &mut late], "one");
+    late.local_edit("one two").unwrap();
+    settle(&mut [&mut client, &mut late], "one two");
+}
+
+#[test]
+fn test_file_changes_merge_only_when_alone() {
+    let socket = socket("file");
+    let mut host = LiveSync::join_at(&socket, "one").unwrap();
+    assert_eq!(host.file_changed("one").unwrap(), None);
+    assert_eq!(
+        host.file_changed("one\ntwo\n").unwrap().as_deref(),
+        Some("one\ntwo")
+    );
+
+    let mut client = LiveSync::join_at(&socket, "one\ntwo").unwrap();
+    settle(&mut [&mut host, &mut client], "one\ntwo");
+    assert_eq!(host.file_changed("stale").unwrap(), None);
+    assert_eq!(host.text(), "one\ntwo");
+}
@@ -0,0 +1,67 @@
+mod common;
+
+use common::temp_dir;
+use zlyph_core::long_lines;
+use zlyph_core::{pretty, EditorAction, EditorEngine};
+
+#[test]
+fn test_window_keeps_the_column_in_view() {
+    let line = "abcdefghijklmnopqrstuvwxyz";
+    assert_eq!(long_lines::window(line, 3, 10), 0..10);
+    // Past the window's end, it moves on half its width at a time
+    assert_eq!(long_lines::window(line, 10, 10), 5..15);
+    assert_eq!(long_lines::window(line, 14, 10), 5..15);
+    assert_eq!(long_lines::window(line, 15, 10), 10..20);
+    // At the end there is room for the cursor after the last character
+    assert_eq!(long_lines::window(line, 26, 10), 20..26);
+    // Windows count characters, not bytes
+    assert_eq!(long_lines::window("ééééé", 6, 2), 4..8);
+}
+
+#[test]
+fn test_pieces_split_by_characters() {
+    assert_eq!(long_lines::pieces("abcdefg", 3), [0..3, 3..6, 6..7]);
+    assert_eq!(long_lines::pieces("ééé", 2), [0..4, 4..6]);
+    assert_eq!(long_lines::pieces("", 4).first(), Some(&(0..0)));
+}
+
+#[test]
+fn test_loading_a_long_line_warns() {
+    let path = temp_dir("warns").join("items.json");
+    let items: Vec<String> = (0..3000).map(|i| format!("{{\"id\":{}}}", i)).collect();
+    std::fs::write(&path, format!("[{}]", items.join(","))).unwrap();
+
+    let mut engine = EditorEngine::new();
+    engine.load_from_file(&path).unwrap();
+    assert_eq!(
+        engine.hover(),
+        Some("Line 1 is 34 KB long and is shown in pieces; Format JSON can break it up")
+    );
+
+    engine.handle_action(EditorAction::FormatJson);
+    assert_eq!(engine.state().lines.len(), 3000 * 3 + 2);
+    assert_eq!(engine.state().lines[1..4], ["  {", "    \"id\": 0", "  },"]);
+    assert_eq!(long_lines::find(&engine.state().lines), None);
+
+    engine.handle_action(EditorAction::Undo);
+    assert_eq!(engine.state().lines.len(), 1);
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn test_format_json_keeps_order_and_text() {
+    let json = r#"{"z":[1,2.50,{}],"a":"x, y: {\"q\"}","e":[ ]}"#;
+    assert_eq!(
+        pretty::json(json, 2).unwrap(),
+        "{\n  \"z\": [\n    1,\n    2.50,\n    {}\n  ],\n  \"a\": \"x, y: {\\\"q\\\"}\",\n  \"e\": []\n}"
+    );
+    assert!(pretty::json("{\"a\": }", 2).is_err());
+
+    let mut engine = EditorEngine::new();
+    engine.load_text("{\"a\": ");
+    engine.handle_action(EditorAction::FormatJson);
+    assert!(engine
+        .hover()
+        .is_some_and(|hover| hover.starts_with("Not valid JSON")));
+    assert_eq!(engine.state().lines, ["{\"a\": "]);
+}
@@ -0,0 +1,58 @@
+use zlyph_core::multi_cursor::{self, Caret};
+use zlyph_core::{BufferPosition, EditorAction, EditorEngine};
+
+fn select_all(text: &str, query: &str) -> EditorEngine {
+    let mut engine = EditorEngine::new();
+    engine.load_text(text);
+    engine.set_search_query(Some(query.to_string()));
+    engine.handle_action(EditorAction::SelectAllMatches);
+    engine
+}
+
+#[test]
+fn test_select_all_matches_puts_a_cursor_at_each() {
+    let engine = select_all("let x = x + 1;\nx", "x");
+    assert_eq!(engine.selected_text().as_deref(), Some("x"));
+    assert_eq!(
+        engine.extra_carets(),
+        [
+            Caret::selecting(BufferPosition::new(0, 8), BufferPosition::new(0, 9)),
+            Caret::selecting(BufferPosition::new(1, 0), BufferPosition::new(1, 1)),
+        ]
+    );
+    let (selected, cursors) = multi_cursor::on_line(engine.extra_carets(), 0, 14);
+    assert_eq!(selected.first(), Some(&(8..9)));
+    assert_eq!(selected.len(), 1);
+    assert_eq!(cursors, [9]);
+}
+
+#[test]
+fn test_typing_happens_at_every_cursor_as_one_step() {
+    let mut engine = select_all("let x = x + 1;\nx", "x");
+    engine.handle_action(EditorAction::TypeString("value".to_string()));
+    engine.handle_action(EditorAction::TypeCharacter('s'));
+    assert_eq!(engine.text(), "let values = values + 1;\nvalues");
+    engine.handle_action(EditorAction::Backspace);
+    assert_eq!(engine.text(), "let value = value + 1;\nvalue");
+    assert_eq!(engine.state().cursor, BufferPosition::new(0, 9));
+
+    engine.handle_action(EditorAction::Undo);
+    assert_eq!(engine.text(), "let x = x + 1;\nx");
+    assert!(engine.extra_carets().is_empty());
+}
+
+#[test]
+fn test_cursors_move_along_their_lines() {
+    let mut engine = select_all("ab ab", "ab");
+    engine.handle_action(EditorAction::MoveToBeginningOfLine);
+    // Both cursors land on the line's start and become one
+    assert!(engine.extra_carets().is_empty());
+
+    let mut engine = select_all("ab ab", "ab");
+    engine.handle_action(EditorAction::MoveLeft);
+    engine.handle_action(EditorAction::Delete);
+    assert_eq!(engine.text(), "a a");
+    // Anything else leaves only the main cursor
+    engine.handle_action(EditorAction::SelectAll);
+    assert!(engine.extra_carets().is_empty());
+}
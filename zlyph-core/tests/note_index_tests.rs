@@ -0,0 +1,82 @@
+mod common;
+
+use common::temp_dir;
+use std::fs;
+use zlyph_core::note_index::{self, NoteIndex, NoteSearch};
+
+#[test]
+fn test_words() {
+    let words: Vec<String> = note_index::words("Über-Cool notes, v2!").collect();
+    assert_eq!(words, vec!["über", "cool", "notes", "v2"]);
+}
+
+#[test]
+fn test_search_ranks_and_snippets() {
+    let dir = temp_dir("search");
+    let notes = dir.join("notes");
+    fs::create_dir_all(&notes).unwrap();
+    fs::write(
+        notes.join("garden.md"),
+        "# Garden\nTomatoes and basil\nMore tomatoes here\n",
+    )
+    .unwrap();
+    fs::write(
+        notes.join("kitchen.md"),
+        "Recipes\nBasil pesto with tomatoes, and a long tail of other words to make it longer\n",
+    )
+    .unwrap();
+    fs::write(notes.join("code.rs"), "tomatoes").unwrap();
+
+    let mut index = NoteIndex::load_from(&notes, dir.join("index.json"));
+    assert!(index.refresh());
+    assert_eq!(index.len(), 2);
+
+    let hits = index.search("tomatoes basil");
+    let names: Vec<_> = hits
+        .iter()
+        .map(|hit| hit.path.file_name().unwrap().to_owned())
+        .collect();
+    assert_eq!(names, vec!["garden.md", "kitchen.md"]);
+    assert_eq!(
+        (hits[0].row, hits[0].snippet.as_str()),
+        (1, "Tomatoes and basil")
+    );
+    // The word being typed matches as a prefix
+    assert_eq!(index.search("pes").len(), 1);
+    assert!(index.search("pes ").is_empty());
+    assert!(index.search("basil cucumber").is_empty());
+
+    // Stored and reloaded, only changed notes are read again
+    index.save().unwrap();
+    fs::write(notes.join("garden.md"), "Cucumbers\n").unwrap();
+    fs::remove_file(notes.join("kitchen.md")).unwrap();
+    let mut reloaded = NoteIndex::load_from(&notes, dir.join("index.json"));
+    assert_eq!(reloaded.len(), 2);
+    reloaded.refresh();
+    assert_eq!(reloaded.len(), 1);
+    assert!(reloaded.search("basil").is_empty());
+    assert_eq!(reloaded.search("cucumbers").len(), 1);
+}
+
+#[test]
+fn test_note_search_updates() {
+    let dir = temp_dir("panel");
+    let notes = dir.join("notes");
+    fs::create_dir_all(&notes).unwrap();
+    fs::write(notes.join("a.md"), "alpha\n").unwrap();
+
+    let mut search = NoteSearch::start_from(&notes, dir.join("index.json"));
+    // Saved before the index arrives
+    search.update(&notes.join("b.md"), "beta alpha");
+    search.wait();
+    assert!(search.is_ready());
+    search.type_text("alpha");
+    assert_eq!(search.hits().len(), 2);
+    search.update(&notes.join("a.md"), "gamma");
+    search.backspace();
+    search.type_text("a");
+    assert_eq!(search.hits().len(), 1);
+    assert_eq!(search.status(), "1 note");
+    search.flush().unwrap();
+    assert!(dir.join("index.json").exists());
+}
@@ -0,0 +1,108 @@
+mod common;
+
+use common::temp_dir;
+use std::fs;
+use std::path::PathBuf;
+use zlyph_core::config::NotesConfig;
+use zlyph_core::notes::{self, BacklinksPanel, WikiLink};
+use zlyph_core::{Config, EditorAction, EditorEngine};
+
+#[test]
+fn test_parse_wiki_links() {
+    let line = "see [[Project Plan|the plan]] and [[ideas#later]], not [[]]";
+    assert_eq!(
+        notes::links(line),
+        vec![
+            WikiLink {
+                range: 4..29,
+                name: "Project Plan".to_string()
+            },
+            WikiLink {
+                range: 34..49,
+                name: "ideas".to_string()
+            },
+        ]
+    );
+    assert_eq!(notes::link_at(line, 10).unwrap().name, "Project Plan");
+    assert_eq!(notes::link_at(line, 30), None);
+    assert_eq!(notes::links("[[a [[b]]")[0].name, "b");
+
+    assert_eq!(notes::open_link_start("x [[Proj", 8), Some(4));
+    assert_eq!(notes::open_link_start("x [[a]] b", 9), None);
+    assert_eq!(notes::open_link_start("x [[a|sh", 8), None);
+}
+
+#[test]
+fn test_note_paths_and_backlinks() {
+    let dir = temp_dir("backlinks");
+    fs::create_dir_all(dir.join("plans")).unwrap();
+    let plan = dir.join("plans").join("Project Plan.md");
+    fs::write(&plan, "Links to [[Project Plan]] itself\n").unwrap();
+    fs::write(
+        dir.join("today.md"),
+        "# Today\nWork on [[project plan#goals]]\n",
+    )
+    .unwrap();
+    fs::write(
+        dir.join("other.txt"),
+        "[[plans/Project Plan.md|plan]]\n[[today]]\n",
+    )
+    .unwrap();
+    fs::write(dir.join("image.png"), "[[Project Plan]]").unwrap();
+
+    assert_eq!(notes::note_path(&dir, "PROJECT PLAN"), plan);
+    assert_eq!(notes::note_path(&dir, "New Idea"), dir.join("New Idea.md"));
+    assert_eq!(notes::note_path(&dir, "list.txt"), dir.join("list.txt"));
+    assert_eq!(
+        notes::note_names(&dir),
+        vec!["other", "Project Plan", "today"]
+    );
+
+    let mut panel = BacklinksPanel::load(&dir, &plan);
+    assert_eq!(panel.note, "Project Plan");
+    let found: Vec<_> = panel
+        .entries()
+        .iter()
+        .map(|b| (b.path.file_name().unwrap().to_str().unwrap(), b.row))
+        .collect();
+    assert_eq!(found, vec![("other.txt", 0), ("today.md", 1)]);
+    panel.select_next();
+    assert_eq!(
+        panel.selected_entry().unwrap().text,
+        "Work on [[project plan#goals]]"
+    );
+}
+
+#[test]
+fn test_notes_dir() {
+    let current = PathBuf::from("/home/me/notes/today.md");
+    assert_eq!(
+        notes::notes_dir(&NotesConfig::default(), &current),
+        PathBuf::from("/home/me/notes")
+    );
+    let config = Config::from_toml("[notes]\ndirectory = \"/srv/wiki\"\n").unwrap();
+    assert_eq!(
+        notes::notes_dir(&config.notes, &current),
+        PathBuf::from("/srv/wiki")
+    );
+}
+
+#[test]
+fn test_link_completion() {
+    let mut engine = EditorEngine::new();
+    engine.set_note_names(vec!["Project Plan".to_string(), "Reading".to_string()]);
+    for c in "See [[".chars() {
+        engine.handle_action(EditorAction::TypeCharacter(c));
+    }
+    assert_eq!(
+        engine.completion().unwrap().items,
+        vec!["Project Plan", "Reading"]
+    );
+    for c in "pro p".chars() {
+        engine.handle_action(EditorAction::TypeCharacter(c));
+    }
+    assert_eq!(engine.completion().unwrap().items, vec!["Project Plan"]);
+    engine.handle_action(EditorAction::Tab);
+    assert_eq!(engine.state().lines[0], "See [[Project Plan]]");
+    assert!(engine.completion().is_none());
+}
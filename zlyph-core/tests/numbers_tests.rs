@@ -0,0 +1,101 @@
+use zlyph_core::numbers;
+use zlyph_core::{BufferPosition, EditorAction, EditorEngine};
+
+fn adjusted(line: &str, column: usize, delta: i64) -> Option<String> {
+    numbers::at(line, column).map(|number| {
+        let mut line = line.to_string();
+        let written = number.adjusted(&line, delta);
+        line.replace_range(number.range, &written);
+        line
+    })
+}
+
+#[test]
+fn test_numbers_keep_how_they_are_written() {
+    assert_eq!(adjusted("x = 9;", 0, 1).as_deref(), Some("x = 10;"));
+    assert_eq!(adjusted("x = -1", 0, 3).as_deref(), Some("x = 2"));
+    assert_eq!(adjusted("a-1", 0, 1).as_deref(), Some("a-2"));
+    assert_eq!(adjusted("id 007", 0, 1).as_deref(), Some("id 008"));
+    assert_eq!(adjusted("0xFF", 2, 1).as_deref(), Some("0x100"));
+    assert_eq!(
+        adjusted("0x0a", 0, -11).as_deref(),
+        Some("0xffffffffffffffff")
+    );
+    assert_eq!(adjusted("0b0111", 0, 1).as_deref(), Some("0b1000"));
+    assert_eq!(adjusted("1 2 3", 2, 1).as_deref(), Some("1 3 3"));
+    assert_eq!(adjusted("none here", 0, 1), None);
+}
+
+#[test]
+fn test_increment_and_decrement_at_the_cursor() {
+    let mut engine = EditorEngine::new();
+    engine.load_text("width: 98px");
+    engine.handle_action(EditorAction::IncrementNumber(1));
+    engine.handle_action(EditorAction::IncrementNumber(1));
+    assert_eq!(engine.state().lines, ["width: 100px"]);
+    assert_eq!(engine.state().cursor, BufferPosition::new(0, 9));
+    engine.handle_action(EditorAction::DecrementNumber(50));
+    assert_eq!(engine.state().lines, ["width: 50px"]);
+
+    engine.handle_action(EditorAction::Undo);
+    assert_eq!(engine.state().lines, ["width: 100px"]);
+    engine.handle_action(EditorAction::MoveToEndOfLine);
+    engine.handle_action(EditorAction::IncrementNumber(1));
+    assert_eq!(engine.hover(), Some("No number at the cursor"));
+
+    // A selected number that runs past the selection's end
+    engine.load_text("x 1000");
+    engine.handle_action(EditorAction::StartSelection { row: 0, column: 4 });
+    engine.handle_action(EditorAction::ExtendSelection { row: 0, column: 0 });
+    engine.handle_action(EditorAction::DecrementNumber(999));
+    assert_eq!(engine.state().lines, ["x 1"]);
+    assert_eq!(
+        engine.state().selection_anchor,
+        Some(BufferPosition::new(0, 3))
+    );
+    assert_eq!(engine.state().cursor, BufferPosition::new(0, 0));
+
+    // Amounts at the edge of the range stop at it, at one cursor or several
+    engine.load_text("n = 0");
+    engine.handle_action(EditorAction::DecrementNumber(i64::MIN));
+    assert_eq!(engine.state().lines, ["n = 9223372036854775807"]);
+    engine.load_text("n = 0\nn = 0");
+    engine.set_search_query(Some("0".to_string()));
+    engine.handle_action(EditorAction::SelectAllMatches);
+    engine.handle_action(EditorAction::DecrementNumber(i64::MIN));
+    assert_eq!(
+        engine.state().lines,
+        ["n = 9223372036854775807", "n = 9223372036854775807"]
+    );
+}
+
+#[test]
+fn test_number_sequence_down_a_selection_and_cursors() {
+    let mut engine = EditorEngine::new();
+    engine.load_text("item 0\nitem 0\nno number\nitem 0");
+    engine.handle_action(EditorAction::SelectAll);
+    engine.handle_action(EditorAction::NumberSequence(1));
+    assert_eq!(
+        engine.state().lines,
+        ["item 1", "item 2", "no number", "item 3"]
+    );
+
+    engine.load_text("id: 0, 0\nid: 0");
+    engine.set_search_query(Some("0".to_string()));
+    engine.handle_action(EditorAction::SelectAllMatches);
+    engine.handle_action(EditorAction::NumberSequence(10));
+    assert_eq!(engine.state().lines, ["id: 10, 20", "id: 30"]);
+    engine.handle_action(EditorAction::Undo);
+    assert_eq!(engine.state().lines, ["id: 0, 0", "id: 0"]);
+
+    // Steps past the end of the range stop at it
+    engine.handle_action(EditorAction::SelectAllMatches);
+    engine.handle_action(EditorAction::NumberSequence(i64::MAX / 2));
+    assert_eq!(
+        engine.state().lines,
+        [
+            "id: 4611686018427387903, 9223372036854775806",
+            "id: 9223372036854775807"
+        ]
+    );
+}
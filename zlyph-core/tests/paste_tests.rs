@@ -0,0 +1,59 @@
+use zlyph_core::paste;
+use zlyph_core::{BufferPosition, EditorAction, EditorEngine};
+
+#[test]
+fn test_reindent_moves_a_block_to_the_destination_indent() {
+    let block = "if x {\n    y();\n}";
+    assert_eq!(
+        paste::reindent(block, "        "),
+        "if x {\n            y();\n        }"
+    );
+    // Copied from partway along an indented line, without its indent
+    assert_eq!(
+        paste::reindent("a();\n        b();\n", "    "),
+        "a();\n    b();\n"
+    );
+    // Copied with the indent of its first line
+    assert_eq!(
+        paste::reindent("\t\tone\n\t\t\ttwo\n\n\t\tthree", "\t"),
+        "one\n\t\ttwo\n\n\tthree"
+    );
+}
+
+#[test]
+fn test_reindent_after_text_keeps_the_first_line() {
+    assert_eq!(
+        paste::reindent("  x\n      y", "    let v = "),
+        "  x\n        y"
+    );
+    assert_eq!(paste::reindent("  one line", "    "), "  one line");
+}
+
+#[test]
+fn test_plain_drops_indent_and_invisibles() {
+    assert_eq!(
+        paste::plain("    a\u{00A0}b\r\n      c\u{200B}\r\n    d"),
+        "a b\n  c\nd"
+    );
+}
+
+#[test]
+fn test_engine_pastes_as_one_undo_step() {
+    let mut engine = EditorEngine::new();
+    engine.load_text("fn main() {\n    \n}");
+    engine.handle_action(EditorAction::SetCursorPosition { row: 1, column: 4 });
+    engine.handle_action(EditorAction::Paste("let a = 1;\nlet b = 2;".to_string()));
+    assert_eq!(
+        engine.text(),
+        "fn main() {\n    let a = 1;\n    let b = 2;\n}"
+    );
+    assert_eq!(engine.state().cursor, BufferPosition::new(2, 14));
+    engine.handle_action(EditorAction::Undo);
+    assert_eq!(engine.text(), "fn main() {\n    \n}");
+
+    engine.handle_action(EditorAction::SetCursorPosition { row: 1, column: 4 });
+    engine.handle_action(EditorAction::PastePlain("  x\n    y".to_string()));
+    assert_eq!(engine.text(), "fn main() {\n    x\n  y\n}");
+    engine.handle_action(EditorAction::Undo);
+    assert_eq!(engine.text(), "fn main() {\n    \n}");
+}
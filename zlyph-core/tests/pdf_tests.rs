@@ -0,0 +1,78 @@
+mod common;
+
+use common::lines;
+use std::path::Path;
+use zlyph_core::config::{Paper, PrintConfig};
+use zlyph_core::export::Palette;
+use zlyph_core::pdf::{self, Font, PdfOptions};
+use zlyph_core::{BufferPosition, BufferRange};
+
+fn print(lines: &[String], range: Option<BufferRange>, config: &PrintConfig) -> String {
+    let options = PdfOptions::new(Some(Path::new("/notes/today.md")), config);
+    let file = pdf::pdf(
+        lines,
+        Some(Path::new("/notes/today.md")),
+        range,
+        None,
+        &Palette::light(),
+        config,
+        &options,
+    );
+    String::from_utf8_lossy(&file).into_owned()
+}
+
+#[test]
+fn test_pdf_structure() {
+    let file = print(&lines("# Today\nSome text"), None, &PrintConfig::default());
+    assert!(file.starts_with("%PDF-1.4"));
+    assert!(file.ends_with("%%EOF\n"));
+    assert!(file.contains("/Count 1"));
+    assert!(file.contains("/MediaBox [0 0 595 842]"));
+    assert!(file.contains("/BaseFont /Courier"));
+
+    // Every entry of the cross-reference table points at its object
+    let xref = file.rfind("xref\n").unwrap();
+    let entries = file[xref..]
+        .lines()
+        .skip(3)
+        .take_while(|line| line.ends_with(" n "));
+    for (index, entry) in entries.enumerate() {
+        let offset: usize = entry[..10].parse().unwrap();
+        assert!(file[offset..].starts_with(&format!("{} 0 obj", index + 1)));
+    }
+}
+
+#[test]
+fn test_pdf_breaks_pages() {
+    let text: Vec<String> = (0..200).map(|n| format!("line {}", n)).collect();
+    let config = PrintConfig {
+        paper: Paper::Letter,
+        ..PrintConfig::default()
+    };
+    let file = print(&text, None, &config);
+    assert!(file.contains("/MediaBox [0 0 612 792]"));
+    assert!(file.contains("/Count 4"));
+
+    let range = BufferRange::new(BufferPosition::new(0, 0), BufferPosition::new(3, 0));
+    assert!(print(&text, Some(range), &config).contains("/Count 1"));
+}
+
+#[test]
+fn test_font_must_be_truetype() {
+    assert!(Font::from_data(b"not a font".to_vec()).is_none());
+}
+
+#[test]
+fn test_options_from_config() {
+    let config = PrintConfig {
+        highlight: false,
+        ..PrintConfig::default()
+    };
+    let options = PdfOptions::new(Some(Path::new("/notes/today.md")), &config);
+    assert_eq!(options.title, "today.md");
+    assert!(!options.highlight);
+    assert_eq!(
+        pdf::pdf_path(Path::new("/notes/today.md")),
+        Path::new("/notes/today.pdf")
+    );
+}
@@ -0,0 +1,111 @@
+//! Time limits for the core edits on a 1 MB document, well above what they
+//! take today so a busy machine doesn't trip them, but low enough to catch
+//! an edit that starts doing far more work than it should. `cargo bench -p
+//! zlyph-core` has the detailed numbers.
+
+use std::time::{Duration, Instant};
+use zlyph_core::{search, EditorAction, EditorEngine};
+
+const MB: usize = 1024 * 1024;
+
+/// Runs each measurement is the best of, to ride out noise
+const RUNS: usize = 5;
+
+fn document(bytes: usize) -> String {
+    let mut text = String::with_capacity(bytes + 100);
+    let mut line = 0;
+    while text.len() < bytes {
+        if line > 0 {
+            text.push('\n');
+        }
+        if line % 1000 == 0 {
+            text.push_str("a needle in the haystack, ");
+        }
+        text.push_str(&format!(
+            "line {line}: the quick brown fox jumps over the lazy dog {}",
+            line % 97
+        ));
+        line += 1;
+    }
+    text
+}
+
+fn engine(text: &str) -> EditorEngine {
+    let mut engine = EditorEngine::builder().text(text).build();
+    let row = engine.state().lines.len() / 2;
+    engine.handle_action(EditorAction::SetCursorPosition { row, column: 5 });
+    engine
+}
+
+/// The quickest of several runs of `action` on a fresh 1 MB engine, each
+/// set up by `prepare` first
+fn best_time(prepare: impl Fn(&mut EditorEngine), action: impl Fn() -> EditorAction) -> Duration {
+    let text = document(MB);
+    (0..RUNS)
+        .map(|_| {
+            let mut engine = engine(&text);
+            prepare(&mut engine);
+            let start = Instant::now();
+            engine.handle_action(action());
+            start.elapsed()
+        })
+        .min()
+        .unwrap()
+}
+
+fn assert_within(what: &str, took: Duration, budget_ms: u64) {
+    assert!(
+        took <= Duration::from_millis(budget_ms),
+        "{what} took {took:?} on 1 MB, over its {budget_ms} ms budget"
+    );
+}
+
+#[test]
+fn test_insert_at_cursor_budget() {
+    let took = best_time(|_| {}, || EditorAction::TypeCharacter('x'));
+    assert_within("typing a character", took, 100);
+}
+
+#[test]
+fn test_bulk_paste_budget() {
+    let pasted = document(64 * 1024);
+    let took = best_time(|_| {}, || EditorAction::TypeString(pasted.clone()));
+    assert_within("pasting 64 KB", took, 60);
+}
+
+#[test]
+fn test_line_operations_budget() {
+    for action in [
+        EditorAction::Newline,
+        EditorAction::DeleteLine,
+        EditorAction::MoveLineDown,
+    ] {
+        let took = best_time(|_| {}, || action.clone());
+        assert_within(&format!("{action:?}"), took, 10);
+    }
+}
+
+#[test]
+fn test_undo_budget() {
+    let took = best_time(
+        |engine| engine.handle_action(EditorAction::DeleteLine),
+        || EditorAction::Undo,
+    );
+    assert_within("undoing a line delete", took, 15);
+}
+
+#[test]
+fn test_search_budget() {
+    let lines: Vec<String> = document(MB).lines().map(String::from).collect();
+    let took = (0..RUNS)
+        .map(|_| {
+            let start = Instant::now();
+            let matches = search::find_all(&lines, "needle");
+            let took = start.elapsed();
+            assert_eq!(matches.len(), lines.len().div_ceil(1000));
+            took
+        })
+        .min()
+        .unwrap();
+    assert_within("searching", took, 15);
+}
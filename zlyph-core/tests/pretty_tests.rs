@@ -0,0 +1,71 @@
+use zlyph_core::pretty::{self, Format};
+use zlyph_core::{BufferPosition, EditorAction, EditorEngine};
+
+#[test]
+fn test_minify_and_sort_json() {
+    let json = "{\n  \"b\": [1, 2.50],\n  \"a\": {\"y\": \"x y\", \"x\": null}\n}";
+    assert_eq!(
+        pretty::minify_json(json).unwrap(),
+        r#"{"b":[1,2.50],"a":{"y":"x y","x":null}}"#
+    );
+    assert_eq!(
+        pretty::sort_keys(json, Format::Json, 4).unwrap(),
+        "{\n    \"a\": {\n        \"x\": null,\n        \"y\": \"x y\"\n    },\n    \"b\": [\n        1,\n        2.50\n    ]\n}"
+    );
+    assert_eq!(Format::detect(json), Format::Json);
+    assert_eq!(Format::detect("[server]\nport = 80"), Format::Toml);
+    assert_eq!(Format::detect("server:\n  port: 80"), Format::Yaml);
+}
+
+#[test]
+fn test_format_toml_keeps_comments() {
+    let toml = "title=\"x\"   # the title\n[server]\n\n\n# where\nhost   =  \"a\"\nports = [ 80,\n443 ]\n[[tasks]]\nname=\"b\"\n";
+    assert_eq!(
+        pretty::toml(toml, 2).unwrap(),
+        "title = \"x\" # the title\n\n[server]\n\n# where\nhost = \"a\"\nports = [\n  80,\n  443,\n]\n\n[[tasks]]\nname = \"b\"\n"
+    );
+    assert_eq!(
+        pretty::sort_keys("b = 1\n# a\na = 2\n[t]\nz = 1\ny = 2\n", Format::Toml, 2).unwrap(),
+        "# a\na = 2\nb = 1\n[t]\ny = 2\nz = 1\n"
+    );
+    let error = pretty::toml("a = 1\nb = \n", 2).unwrap_err();
+    assert_eq!(error.offset, 10);
+}
+
+#[test]
+fn test_format_yaml_reindents_blocks() {
+    let yaml = "b:\n    - x: 1\n      y: |\n        kept\n          as is\n    -   - 2\n\n\n\na: [1, 2] # flow\n";
+    assert_eq!(
+        pretty::yaml(yaml, 2).unwrap(),
+        "b:\n  - x: 1\n    y: |\n      kept\n        as is\n  - - 2\n\na: [1, 2] # flow\n"
+    );
+    assert_eq!(
+        pretty::sort_keys("z: 1\n# about y\ny:\n  b: 2\n  a: 1\n", Format::Yaml, 2).unwrap(),
+        "# about y\ny:\n  a: 1\n  b: 2\nz: 1\n"
+    );
+    let error = pretty::yaml("a: 1\n  b: 2\n", 2).unwrap_err();
+    assert_eq!(error.offset, 7);
+    assert_eq!(error.message, "a value can't have keys below it");
+    assert!(pretty::yaml("a:\n\t- 1\n", 2).is_err());
+}
+
+#[test]
+fn test_parse_errors_become_diagnostics() {
+    let mut engine = EditorEngine::new();
+    engine.load_text("{\n  \"a\": 1,\n  \"b\" 2\n}");
+    engine.handle_action(EditorAction::FormatJson);
+    let diagnostic = &engine.diagnostics()[0];
+    assert_eq!(diagnostic.source, pretty::SOURCE);
+    assert_eq!(diagnostic.start, BufferPosition::new(2, 6));
+    assert_eq!(engine.hover(), Some("Not valid JSON: expected `:`"));
+
+    engine.load_text("{\"b\": 1, \"a\": 2}");
+    engine.handle_action(EditorAction::SortKeys);
+    assert!(engine.diagnostics().is_empty());
+    assert_eq!(
+        engine.state().lines,
+        ["{", "  \"a\": 2,", "  \"b\": 1", "}"]
+    );
+    engine.handle_action(EditorAction::MinifyJson);
+    assert_eq!(engine.state().lines, [r#"{"a":2,"b":1}"#]);
+}
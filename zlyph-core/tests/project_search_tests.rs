@@ -0,0 +1,277 @@
+mod common;
+
+use common::temp_dir;
+use std::fs;
+use std::path::{Path, PathBuf};
+use zlyph_core::gitignore::{glob_match, Gitignore};
+use zlyph_core::project_replace::ReplacePreview;
+use zlyph_core::project_search::{files, workspace_root, ProjectSearch};
+use zlyph_core::search_panel::{SearchPanel, SearchRow};
+use zlyph_core::BufferPosition;
+
+fn glob(pattern: &str, text: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let text: Vec<char> = text.chars().collect();
+    glob_match(&pattern, &text)
+}
+
+fn write(path: &Path, content: &str) {
+    fs::create_dir_all(path.parent().unwrap()).unwrap();
+    fs::write(path, content).unwrap();
+}
+
+#[test]
+fn test_glob_match() {
+    assert!(glob("*.log", "debug.log"));
+    assert!(!glob("*.log", "logs/debug.log"), "* stays in one component");
+    assert!(glob("**/*.log", "debug.log"));
+    assert!(glob("**/*.log", "logs/deep/debug.log"));
+    assert!(glob("a/**/b", "a/b"));
+    assert!(glob("a/**/b", "a/x/y/b"));
+    assert!(glob("file?.[ch]", "file1.c"));
+    assert!(!glob("file?.[!ch]", "file1.c"));
+    assert!(glob("[0-9]x", "7x"));
+}
+
+#[test]
+fn test_gitignore_rules() {
+    let base = Path::new("/project");
+    let gitignore = Gitignore::parse(
+        base,
+        "# build output\ntarget/\n*.log\n!keep.log\n/root-only.txt\n",
+    );
+
+    assert_eq!(gitignore.matched(&base.join("target"), true), Some(true));
+    assert_eq!(gitignore.matched(&base.join("target"), false), None);
+    assert_eq!(
+        gitignore.matched(&base.join("src/a.log"), false),
+        Some(true)
+    );
+    assert_eq!(
+        gitignore.matched(&base.join("keep.log"), false),
+        Some(false)
+    );
+    assert_eq!(
+        gitignore.matched(&base.join("root-only.txt"), false),
+        Some(true)
+    );
+    assert_eq!(
+        gitignore.matched(&base.join("src/root-only.txt"), false),
+        None
+    );
+}
+
+#[test]
+fn test_files_skip_hidden_and_ignored_entries() {
+    let dir = temp_dir("files");
+    write(&dir.join(".gitignore"), "build/\n*.tmp\n");
+    write(&dir.join("notes.md"), "");
+    write(&dir.join("scratch.tmp"), "");
+    write(&dir.join("build/out.md"), "");
+    write(&dir.join(".hidden/secret.md"), "");
+    write(&dir.join("docs/.gitignore"), "draft.md\n");
+    write(&dir.join("docs/draft.md"), "");
+    write(&dir.join("docs/guide.md"), "");
+
+    let found: Vec<PathBuf> = files(&dir)
+        .into_iter()
+        .map(|path| path.strip_prefix(&dir).unwrap().to_path_buf())
+        .collect();
+    assert_eq!(
+        found,
+        vec![PathBuf::from("docs/guide.md"), PathBuf::from("notes.md")]
+    );
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_search_finds_matches_in_every_file() {
+    let dir = temp_dir("search");
+    write(&dir.join("a.md"), "alpha\nthe needle\n");
+    write(&dir.join("src/b.rs"), "needle needle\n");
+    write(&dir.join("c.md"), "nothing here\n");
+    fs::write(dir.join("binary.bin"), b"needle\0").unwrap();
+
+    let mut search = ProjectSearch::start(&dir, "needle");
+    search.wait();
+    assert!(search.is_done());
+    assert_eq!(search.results().len(), 2);
+    assert_eq!(search.match_count(), 3);
+
+    let mut results = search.results().to_vec();
+    results.sort_by(|a, b| a.path.cmp(&b.path));
+    assert_eq!(results[0].path, dir.join("a.md"));
+    assert_eq!(results[0].matches[0].start, BufferPosition::new(1, 4));
+    assert_eq!(results[0].matches[0].line, "the needle");
+    assert_eq!(results[1].matches[1].end, BufferPosition::new(0, 13));
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_workspace_root_is_enclosing_repository() {
+    let dir = temp_dir("root");
+    fs::create_dir_all(dir.join(".git")).unwrap();
+    write(&dir.join("docs/notes.md"), "");
+    assert_eq!(workspace_root(&dir.join("docs/notes.md")), dir);
+
+    let outside = temp_dir("root-outside");
+    assert_eq!(workspace_root(&outside.join("notes.md")), outside);
+
+    fs::remove_dir_all(&dir).unwrap();
+    fs::remove_dir_all(&outside).unwrap();
+}
+
+#[test]
+fn test_replace_preview_applies_included_hunks() {
+    let dir = temp_dir("replace");
+    write(&dir.join("a.md"), "old and old\r\nkeep\r\n");
+    write(&dir.join("b.md"), "old\n");
+
+    let mut search = ProjectSearch::start(&dir, "old");
+    search.wait();
+    let mut results = search.results().to_vec();
+    results.sort_by(|a, b| a.path.cmp(&b.path));
+
+    let mut preview = ReplacePreview::new(&results, "new");
+    assert_eq!(preview.hunk_count(), 3);
+    let (path, hunk) = preview.hunks().nth(1).unwrap();
+    assert_eq!(path, dir.join("a.md"));
+    assert_eq!(hunk.before, "old and old");
+    assert_eq!(hunk.after, "old and new");
+
+    preview.toggle(0);
+    assert_eq!(preview.included_count(), 2);
+    let mut applied = preview.apply().unwrap();
+    assert_eq!(
+        fs::read_to_string(dir.join("a.md")).unwrap(),
+        "old and new\r\nkeep\r\n"
+    );
+    assert_eq!(fs::read_to_string(dir.join("b.md")).unwrap(), "new\n");
+
+    applied.undo_file(&dir.join("b.md")).unwrap();
+    assert_eq!(fs::read_to_string(dir.join("b.md")).unwrap(), "old\n");
+    assert_eq!(
+        fs::read_to_string(dir.join("a.md")).unwrap(),
+        "old and new\r\nkeep\r\n"
+    );
+    assert!(applied.file(&dir.join("b.md")).is_none());
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_panel_whole_word_and_preserve_case() {
+    let dir = temp_dir("replace-case");
+    write(&dir.join("a.md"), "Old older OLD old\n");
+
+    let mut panel = SearchPanel::new();
+    panel.type_text("old");
+    panel.toggle_whole_word();
+    panel.toggle_preserve_case();
+    panel.set_replacing(true);
+    panel.switch_field();
+    panel.type_text("new");
+    panel.start(&dir);
+    while panel.search().is_some_and(|search| !search.is_done()) {
+        panel.poll();
+    }
+    panel.poll();
+    assert_eq!(
+        panel.status(),
+        "3 of 3 matches selected (whole word, preserve case)"
+    );
+
+    assert!(panel.apply());
+    assert_eq!(
+        fs::read_to_string(dir.join("a.md")).unwrap(),
+        "New older NEW new\n"
+    );
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_replace_refuses_files_changed_since_search() {
+    let dir = temp_dir("replace-stale");
+    write(&dir.join("a.md"), "old\n");
+    write(&dir.join("b.md"), "old\n");
+
+    let mut search = ProjectSearch::start(&dir, "old");
+    search.wait();
+    let preview = ReplacePreview::new(search.results(), "new");
+
+    std::thread::sleep(std::time::Duration::from_millis(20));
+    write(&dir.join("b.md"), "old, edited\n");
+
+    assert!(preview.apply().is_err());
+    assert_eq!(fs::read_to_string(dir.join("a.md")).unwrap(), "old\n");
+    assert_eq!(
+        fs::read_to_string(dir.join("b.md")).unwrap(),
+        "old, edited\n"
+    );
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_search_panel_replace_flow() {
+    let dir = temp_dir("panel");
+    write(&dir.join("a.md"), "cat cat\n");
+
+    let mut panel = SearchPanel::new();
+    panel.set_replacing(true);
+    panel.type_text("cat");
+    panel.switch_field();
+    panel.type_text("dog");
+    panel.start(&dir);
+    while !panel.search().unwrap().is_done() {
+        panel.poll();
+    }
+    panel.poll();
+
+    assert_eq!(panel.status(), "2 of 2 matches selected");
+    panel.select_next();
+    panel.toggle_selected();
+    assert!(matches!(
+        panel.rows()[2],
+        SearchRow::Change {
+            index: 1,
+            included: false,
+            ..
+        }
+    ));
+
+    assert!(panel.apply());
+    assert_eq!(fs::read_to_string(dir.join("a.md")).unwrap(), "dog cat\n");
+    assert!(panel.undo_selected_file());
+    assert_eq!(fs::read_to_string(dir.join("a.md")).unwrap(), "cat cat\n");
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_search_panel_prompt_goes_through_past_queries() {
+    let dir = temp_dir("history");
+    let mut panel = SearchPanel::with_history(vec!["older".to_string()]);
+    panel.type_text("recent");
+    panel.start(&dir);
+
+    panel.type_text("x");
+    assert_eq!(panel.query, "recentx");
+    panel.history_previous();
+    assert_eq!(panel.query, "recent");
+    assert!(panel.search().is_none());
+    panel.history_previous();
+    assert_eq!(panel.query, "older");
+    panel.history_previous();
+    assert_eq!(panel.query, "older");
+    panel.history_next();
+    panel.history_next();
+    assert_eq!(panel.query, "recentx");
+    panel.history_next();
+    assert_eq!(panel.query, "recentx");
+
+    fs::remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,83 @@
+mod common;
+
+use common::temp_dir;
+use std::fs;
+use zlyph_core::project;
+use zlyph_core::workspace::WorkspaceState;
+
+#[test]
+fn test_root_is_the_nearest_marked_folder() {
+    let dir = temp_dir("root");
+    fs::create_dir_all(dir.join("repo/.git")).unwrap();
+    fs::create_dir_all(dir.join("repo/site/.zlyph")).unwrap();
+    fs::create_dir_all(dir.join("repo/site/posts")).unwrap();
+    fs::create_dir_all(dir.join("loose")).unwrap();
+
+    assert_eq!(
+        project::root(&dir.join("repo/site/posts/hello.md")),
+        dir.join("repo/site")
+    );
+    assert_eq!(project::root(&dir.join("repo/notes.md")), dir.join("repo"));
+    assert_eq!(project::root(&dir.join("loose/todo.md")), dir.join("loose"));
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_project_settings_override_the_users() {
+    let dir = temp_dir("config");
+    let (user, settings) = (dir.join("config.toml"), dir.join("project.toml"));
+    fs::write(&user, "[completion]\nmin_prefix = 3\nmax_items = 5\n").unwrap();
+    fs::write(
+        &settings,
+        "[completion]\nmax_items = 20\n\n[typography]\nenabled = true\n",
+    )
+    .unwrap();
+
+    let config = project::config_from(&user, &settings).unwrap();
+    assert_eq!(config.completion.min_prefix, 3);
+    assert_eq!(config.completion.max_items, 20);
+    assert!(config.typography.enabled);
+
+    // Without project settings, the user's stand
+    let config = project::config_from(&user, &dir.join("missing.toml")).unwrap();
+    assert_eq!(config.completion.max_items, 5);
+    assert!(!config.typography.enabled);
+
+    fs::write(&settings, "[completion\n").unwrap();
+    assert!(project::config_from(&user, &settings).is_err());
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_known_projects_and_their_last_file() {
+    let dir = temp_dir("known");
+    let state_path = dir.join("workspace.toml");
+    for project in ["blog", "book"] {
+        fs::create_dir_all(dir.join(project).join(".zlyph")).unwrap();
+    }
+    let files = [
+        dir.join("blog/a.md"),
+        dir.join("book/one.md"),
+        dir.join("blog/b.md"),
+    ];
+    for file in &files {
+        fs::write(file, "").unwrap();
+        WorkspaceState::add_recent_in(&state_path, file).unwrap();
+    }
+
+    let state = WorkspaceState::load_from_file(&state_path).unwrap();
+    assert_eq!(state.projects(), [dir.join("blog"), dir.join("book")]);
+    assert_eq!(
+        state.last_file_in(&dir.join("blog")),
+        Some(files[2].clone())
+    );
+    assert_eq!(
+        state.last_file_in(&dir.join("book")),
+        Some(files[1].clone())
+    );
+    assert_eq!(state.last_file_in(&dir.join("other")), None);
+
+    fs::remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,225 @@
+//! Random action sequences played into the engine, checked after every
+//! step. A failing sequence is shrunk and saved as a JSON trace; copy it
+//! into `tests/traces/` and `test_recorded_traces_keep_invariants` replays
+//! it on every run from then on.
+
+use proptest::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::panic::{self, AssertUnwindSafe};
+use std::path::{Path, PathBuf};
+use zlyph_core::{EditorAction, EditorEngine};
+
+/// Starting text and the actions played into it
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Trace {
+    text: String,
+    actions: Vec<EditorAction>,
+}
+
+fn check(engine: &EditorEngine) -> Result<(), String> {
+    let state = engine.state();
+    if state.lines.is_empty() {
+        return Err("buffer has no lines".into());
+    }
+    if let Some(row) = state.lines.iter().position(|line| line.contains('\n')) {
+        return Err(format!("line {row} holds a line break"));
+    }
+    in_bounds(engine, "cursor", state.cursor)?;
+    if let Some(anchor) = state.selection_anchor {
+        in_bounds(engine, "selection anchor", anchor)?;
+    }
+    if let Some(range) = state.selection() {
+        if range.start > range.end {
+            return Err(format!("selection runs backwards: {range:?}"));
+        }
+    }
+    Ok(())
+}
+
+fn in_bounds(
+    engine: &EditorEngine,
+    what: &str,
+    pos: zlyph_core::BufferPosition,
+) -> Result<(), String> {
+    let Some(line) = engine.state().lines.get(pos.row) else {
+        return Err(format!("{what} {pos:?} is past the last line"));
+    };
+    if !line.is_char_boundary(pos.column) {
+        return Err(format!("{what} {pos:?} is not on a character in {line:?}"));
+    }
+    Ok(())
+}
+
+/// Undo then redo lands back on the same text and cursor
+fn check_round_trip(engine: &mut EditorEngine) -> Result<(), String> {
+    let text = engine.text();
+    let cursor = engine.state().cursor;
+    let revision = engine.revision();
+    engine.handle_action(EditorAction::Undo);
+    if engine.revision() == revision {
+        return Ok(());
+    }
+    check(engine).map_err(|broken| format!("after undo: {broken}"))?;
+    engine.handle_action(EditorAction::Redo);
+    if engine.text() != text || engine.state().cursor != cursor {
+        return Err("undo then redo didn't restore the text and cursor".into());
+    }
+    Ok(())
+}
+
+/// Play `trace`, returning what broke and after which action
+fn replay(trace: &Trace) -> Result<(), String> {
+    let mut engine = EditorEngine::builder().text(trace.text.clone()).build();
+    check(&engine).map_err(|broken| format!("on loading: {broken}"))?;
+    for (step, action) in trace.actions.iter().enumerate() {
+        engine.handle_action(action.clone());
+        check(&engine).map_err(|broken| format!("after step {step} {action:?}: {broken}"))?;
+    }
+    check_round_trip(&mut engine)
+}
+
+/// Replay `trace`, turning a panic into an error too
+fn replay_caught(trace: &Trace) -> Result<(), String> {
+    panic::catch_unwind(AssertUnwindSafe(|| replay(trace)))
+        .unwrap_or_else(|_| Err("the engine panicked".into()))
+}
+
+fn save_failing(trace: &Trace) -> PathBuf {
+    let path = Path::new(env!("CARGO_TARGET_TMPDIR")).join("failing-trace.json");
+    let _ = fs::write(&path, serde_json::to_string_pretty(trace).unwrap());
+    path
+}
+
+fn any_char() -> impl Strategy<Value = char> {
+    prop::sample::select(vec![
+        'a', 'b', 'z', '0', '1', '9', ' ', '\t', '-', '*', '#', '(', ')', '[', ']', '"', '\'', '.',
+        'é', '中', '😀', '\u{200b}',
+    ])
+}
+
+fn any_text() -> impl Strategy<Value = String> {
+    prop::collection::vec(prop_oneof![9 => any_char(), 1 => Just('\n')], 0..40)
+        .prop_map(|chars| chars.into_iter().collect())
+}
+
+fn any_position() -> impl Strategy<Value = (usize, usize)> {
+    (0usize..8, 0usize..24)
+}
+
+fn any_action() -> impl Strategy<Value = EditorAction> {
+    use EditorAction::*;
+    prop_oneof![
+        8 => any_char().prop_map(TypeCharacter),
+        3 => any_text().prop_map(TypeString),
+        2 => any_text().prop_map(InsertComposed),
+        1 => prop::sample::select(vec!["=", ":", ",", "|"])
+            .prop_map(|delimiter| AlignOn(delimiter.to_string())),
+        1 => (-1000i64..1000).prop_map(IncrementNumber),
+        1 => (-1000i64..1000).prop_map(DecrementNumber),
+        1 => (-1000i64..1000).prop_map(NumberSequence),
+        12 => prop::sample::select(vec![
+            Backspace,
+            Delete,
+            Newline,
+            MoveLeft,
+            MoveRight,
+            MoveUp,
+            MoveDown,
+            MoveToBeginningOfLine,
+            MoveToEndOfLine,
+            MoveWordLeft,
+            MoveWordRight,
+            MoveToNextSentence,
+            MoveToPrevSentence,
+            SelectLeft,
+            SelectRight,
+            SelectUp,
+            SelectDown,
+            SelectWordLeft,
+            SelectWordRight,
+            SelectAll,
+            SelectLine,
+            SelectSentence,
+            SelectParagraph,
+            SelectToTop,
+            SelectToBottom,
+            SelectToLineStart,
+            SelectToLineEnd,
+            ExpandSelection,
+            ShrinkSelection,
+            SelectParent,
+            SelectNextSibling,
+            SelectSection,
+            MoveSectionUp,
+            MoveSectionDown,
+            PromoteSection,
+            DemoteSection,
+            InsertFootnote,
+            RenumberFootnotes,
+            GoToFootnote,
+            InsertBibliography,
+            Undo,
+            Redo,
+            DeleteLine,
+            DeleteToBeginningOfLine,
+            DeleteToEndOfLine,
+            DeleteWordLeft,
+            DeleteWordRight,
+            MoveLineUp,
+            MoveLineDown,
+            Tab,
+            Outdent,
+            Cancel,
+            StripInvisibles,
+            EvaluateSelection,
+            FormatTable,
+            InsertTableRow,
+            DeleteTableRow,
+            InsertTableColumn,
+            DeleteTableColumn,
+        ]),
+        1 => any_position().prop_map(|(row, column)| SetCursorPosition { row, column }),
+        1 => any_position().prop_map(|(row, column)| StartSelection { row, column }),
+        1 => any_position().prop_map(|(row, column)| ExtendSelection { row, column }),
+        1 => any_position().prop_map(|(row, column)| MoveSelectionTo { row, column }),
+        1 => any_position().prop_map(|(row, column)| CopySelectionTo { row, column }),
+    ]
+}
+
+fn any_trace() -> impl Strategy<Value = Trace> {
+    (any_text(), prop::collection::vec(any_action(), 0..40))
+        .prop_map(|(text, actions)| Trace { text, actions })
+}
+
+proptest! {
+    // Failures are kept as traces rather than proptest's seed files
+    #![proptest_config(ProptestConfig {
+        cases: 500,
+        failure_persistence: None,
+        ..ProptestConfig::default()
+    })]
+
+    #[test]
+    fn test_random_actions_keep_invariants(trace in any_trace()) {
+        if let Err(broken) = replay_caught(&trace) {
+            let saved = save_failing(&trace);
+            prop_assert!(false, "{}; trace saved to {}", broken, saved.display());
+        }
+    }
+}
+
+#[test]
+fn test_recorded_traces_keep_invariants() {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/traces");
+    let mut replayed = 0;
+    for entry in fs::read_dir(&dir).unwrap() {
+        let path = entry.unwrap().path();
+        let trace: Trace = serde_json::from_str(&fs::read_to_string(&path).unwrap()).unwrap();
+        if let Err(broken) = replay_caught(&trace) {
+            panic!("{}: {broken}", path.display());
+        }
+        replayed += 1;
+    }
+    assert!(replayed > 0);
+}
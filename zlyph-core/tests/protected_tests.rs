@@ -0,0 +1,144 @@
+use zlyph_core::{EditorAction, EditorEngine};
+
+const TEXT: &str = "---\ntitle: Notes\n---\nbody";
+
+/// An engine over TEXT with its frontmatter protected
+fn engine() -> EditorEngine {
+    let mut engine = EditorEngine::builder().text(TEXT).build();
+    engine.protect(0..20);
+    engine
+}
+
+fn regions(engine: &EditorEngine) -> Vec<(usize, usize)> {
+    engine
+        .protected()
+        .iter()
+        .map(|region| (region.start, region.end))
+        .collect()
+}
+
+fn type_at(engine: &mut EditorEngine, row: usize, column: usize, text: &str) {
+    engine.handle_action(EditorAction::SetCursorPosition { row, column });
+    engine.handle_action(EditorAction::TypeString(text.to_string()));
+}
+
+#[test]
+fn test_typing_inside_a_region_is_turned_away() {
+    let mut engine = engine();
+    let revision = engine.revision();
+
+    type_at(&mut engine, 1, 7, "My ");
+
+    assert_eq!(engine.text(), TEXT);
+    assert_eq!(engine.revision(), revision);
+    assert_eq!(engine.hover(), Some("That text is protected"));
+}
+
+#[test]
+fn test_deleting_across_a_region_edge_is_turned_away() {
+    let mut engine = engine();
+    engine.handle_action(EditorAction::StartSelection { row: 2, column: 1 });
+    engine.handle_action(EditorAction::ExtendSelection { row: 3, column: 2 });
+    engine.handle_action(EditorAction::Delete);
+    assert_eq!(engine.text(), TEXT);
+
+    engine.handle_action(EditorAction::SelectAll);
+    engine.handle_action(EditorAction::Delete);
+    assert_eq!(engine.text(), TEXT);
+}
+
+#[test]
+fn test_typing_at_a_region_edge_is_allowed() {
+    let mut engine = engine();
+
+    type_at(&mut engine, 3, 0, "x");
+    type_at(&mut engine, 0, 0, "-");
+
+    assert_eq!(engine.text(), "----\ntitle: Notes\n---\nxbody");
+    assert_eq!(regions(&engine), [(1, 21)]);
+}
+
+#[test]
+fn test_typing_a_letter_beside_the_same_letter_at_an_edge() {
+    let mut engine = EditorEngine::builder().text("aab").build();
+    engine.protect(1..3);
+
+    type_at(&mut engine, 0, 1, "a");
+
+    assert_eq!(engine.text(), "aaab");
+    assert_eq!(regions(&engine), [(2, 4)]);
+}
+
+#[test]
+fn test_regions_move_with_edits_before_them() {
+    let mut engine = EditorEngine::builder()
+        .text("intro\nkeep this\nend")
+        .build();
+    engine.protect(6..15);
+
+    type_at(&mut engine, 0, 0, "An ");
+    assert_eq!(regions(&engine), [(9, 18)]);
+
+    engine.handle_action(EditorAction::SetCursorPosition { row: 0, column: 0 });
+    engine.handle_action(EditorAction::DeleteLine);
+    assert_eq!(engine.text(), "keep this\nend");
+    assert_eq!(regions(&engine), [(0, 9)]);
+}
+
+#[test]
+fn test_undo_still_works_around_regions() {
+    let mut engine = engine();
+    type_at(&mut engine, 3, 4, "!");
+    type_at(&mut engine, 1, 0, "x");
+
+    engine.handle_action(EditorAction::Undo);
+
+    assert_eq!(engine.text(), TEXT);
+    assert_eq!(regions(&engine), [(0, 20)]);
+}
+
+#[test]
+#[allow(clippy::single_range_in_vec_init)]
+fn test_regions_are_listed_by_line_for_drawing() {
+    let mut engine = EditorEngine::builder().text("ab\ncdef\ngh").build();
+    engine.protect(1..5);
+
+    assert_eq!(engine.protected_in_line(0), vec![1..2]);
+    assert_eq!(engine.protected_in_line(1), vec![0..2]);
+    assert!(engine.protected_in_line(2).is_empty());
+}
+
+#[test]
+fn test_overlapping_regions_are_joined() {
+    let mut engine = EditorEngine::builder().text("0123456789").build();
+    engine.protect(6..8);
+    engine.protect(1..3);
+    engine.protect(2..4);
+    engine.protect(4..20);
+
+    assert_eq!(regions(&engine), [(1, 10)]);
+}
+
+#[test]
+fn test_loading_clears_regions() {
+    let mut engine = engine();
+    engine.load_text("fresh");
+    assert!(engine.protected().is_empty());
+
+    type_at(&mut engine, 0, 2, "x");
+    assert_eq!(engine.text(), "frxesh");
+}
+
+#[test]
+fn test_read_only_turns_away_every_edit() {
+    let mut engine = EditorEngine::builder()
+        .text("hello")
+        .read_only(true)
+        .build();
+
+    type_at(&mut engine, 0, 5, "!");
+    engine.handle_action(EditorAction::DeleteLine);
+
+    assert_eq!(engine.text(), "hello");
+    assert_eq!(engine.state().cursor.column, 5);
+}
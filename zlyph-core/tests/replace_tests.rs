@@ -0,0 +1,32 @@
+use zlyph_core::{replace, search, BufferPosition};
+
+#[test]
+fn test_preserve_case_follows_the_match() {
+    assert_eq!(replace::preserve_case("foo", "bar"), "bar");
+    assert_eq!(replace::preserve_case("Foo", "bar"), "Bar");
+    assert_eq!(replace::preserve_case("FOO", "bar"), "BAR");
+    // Mixed case gets the replacement as typed
+    assert_eq!(replace::preserve_case("fOo", "baR"), "baR");
+    // A single capital reads as capitalized, not all caps
+    assert_eq!(replace::preserve_case("A", "the"), "The");
+    assert_eq!(replace::preserve_case("42", "x"), "x");
+}
+
+#[test]
+fn test_literal_pattern_escapes_and_scopes() {
+    let pattern = replace::literal_pattern("a.b", true, true);
+    let lines = ["see A.B here", "axb", "a.bc"].map(String::from);
+    let found = search::find_pattern(&lines, &pattern);
+    assert_eq!(
+        found,
+        [(BufferPosition::new(0, 4), BufferPosition::new(0, 7))]
+    );
+    assert_eq!(replace::literal_pattern("a.b", false, false), r"a\.b");
+}
+
+#[test]
+fn test_count_report() {
+    assert_eq!(replace::count_report(5, 3), "5 matches on 3 lines");
+    assert_eq!(replace::count_report(1, 1), "1 match on 1 line");
+    assert_eq!(replace::count_report(0, 0), "No matches");
+}
@@ -0,0 +1,195 @@
+mod common;
+
+use common::temp_dir;
+use serde_json::{json, Value};
+use std::fs;
+use zlyph_core::rpc::RpcServer;
+
+fn call(server: &mut RpcServer, request: Value) -> Vec<Value> {
+    server
+        .handle(&request.to_string())
+        .iter()
+        .map(|message| serde_json::from_str(message).unwrap())
+        .collect()
+}
+
+#[test]
+fn test_open_apply_actions_and_query_state() {
+    let mut server = RpcServer::new();
+    let opened = call(
+        &mut server,
+        json!({"jsonrpc": "2.0", "id": 1, "method": "open", "params": {"text": "hello\nworld"}}),
+    );
+    assert_eq!(opened[0]["id"], 1);
+    let buffer = opened[0]["result"]["buffer"].clone();
+
+    call(
+        &mut server,
+        json!({"jsonrpc": "2.0", "id": 2, "method": "action",
+               "params": {"buffer": buffer, "action": "MoveDown"}}),
+    );
+    let typed = call(
+        &mut server,
+        json!({"jsonrpc": "2.0", "id": 3, "method": "action",
+               "params": {"buffer": buffer, "action": {"TypeString": "big "}}}),
+    );
+    let state = &typed[0]["result"];
+    assert_eq!(state["text"], "hello\nbig world");
+    assert_eq!(state["cursor"], json!({"row": 1, "column": 4}));
+    assert_eq!(state["version"], 2);
+
+    let buffers = call(
+        &mut server,
+        json!({"jsonrpc": "2.0", "id": 4, "method": "buffers"}),
+    );
+    assert_eq!(buffers[0]["result"], json!([buffer]));
+}
+
+#[test]
+fn test_subscribers_are_notified_of_changes() {
+    let mut server = RpcServer::new();
+    call(
+        &mut server,
+        json!({"jsonrpc": "2.0", "id": 1, "method": "open", "params": {"text": "a"}}),
+    );
+    call(
+        &mut server,
+        json!({"jsonrpc": "2.0", "id": 2, "method": "subscribe", "params": {"buffer": 1}}),
+    );
+
+    // Moving past the end changes nothing, so nothing is sent
+    let unchanged = call(
+        &mut server,
+        json!({"jsonrpc": "2.0", "id": 3, "method": "action",
+               "params": {"buffer": 1, "action": "MoveUp"}}),
+    );
+    assert_eq!(unchanged.len(), 1);
+
+    // A notification gets no response, only the change it caused
+    let changed = call(
+        &mut server,
+        json!({"jsonrpc": "2.0", "method": "action",
+               "params": {"buffer": 1, "action": {"SetCursorPosition": {"row": 0, "column": 1}}}}),
+    );
+    assert_eq!(changed.len(), 1);
+    assert_eq!(changed[0]["method"], "changed");
+    assert_eq!(changed[0]["params"]["cursor"]["column"], 1);
+}
+
+#[test]
+fn test_errors_and_saving() {
+    let mut server = RpcServer::new();
+    let parse = call(&mut server, json!("not an object"));
+    assert_eq!(parse[0]["error"]["code"], -32600);
+    assert!(server.handle("{")[0].contains("-32700"));
+
+    let unknown = call(
+        &mut server,
+        json!({"jsonrpc": "2.0", "id": 1, "method": "nope"}),
+    );
+    assert_eq!(unknown[0]["error"]["code"], -32601);
+    let missing = call(
+        &mut server,
+        json!({"jsonrpc": "2.0", "id": 2, "method": "state", "params": {"buffer": 9}}),
+    );
+    assert_eq!(missing[0]["error"]["code"], -32000);
+
+    let path = temp_dir("open").join("note.md");
+    call(
+        &mut server,
+        json!({"jsonrpc": "2.0", "id": 3, "method": "open", "params": {"path": path}}),
+    );
+    call(
+        &mut server,
+        json!({"jsonrpc": "2.0", "id": 4, "method": "action",
+               "params": {"buffer": 1, "action": {"TypeString": "saved"}}}),
+    );
+    let saved = call(
+        &mut server,
+        json!({"jsonrpc": "2.0", "id": 5, "method": "save", "params": {"buffer": 1}}),
+    );
+    assert_eq!(saved[0]["result"], Value::Null);
+    assert_eq!(fs::read_to_string(&path).unwrap(), "saved");
+    fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn test_serve_reads_line_delimited_requests() {
+    let input = concat!(
+        r#"{"jsonrpc":"2.0","id":1,"method":"open","params":{"text":"x"}}"#,
+        "\n\n",
+        r#"{"jsonrpc":"2.0","id":2,"method":"state","params":{"buffer":1}}"#,
+        "\n"
+    );
+    let mut output = Vec::new();
+    RpcServer::new()
+        .serve(input.as_bytes(), &mut output)
+        .unwrap();
+    let lines: Vec<Value> = String::from_utf8(output)
+        .unwrap()
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect();
+    assert_eq!(lines.len(), 2);
+    assert_eq!(lines[1]["result"]["text"], "x");
+}
+
+#[test]
+fn test_dump_and_restore_state() {
+    let mut server = RpcServer::new();
+    call(
+        &mut server,
+        json!({"jsonrpc": "2.0", "id": 1, "method": "open", "params": {"text": "one\ntwo"}}),
+    );
+    call(
+        &mut server,
+        json!({"jsonrpc": "2.0", "id": 2, "method": "action",
+               "params": {"buffer": 1, "action": {"StartSelection": {"row": 1, "column": 1}}}}),
+    );
+    let dumped = call(
+        &mut server,
+        json!({"jsonrpc": "2.0", "id": 3, "method": "dump", "params": {"buffer": 1}}),
+    );
+    let state = dumped[0]["result"].clone();
+    assert_eq!(state["lines"], json!(["one", "two"]));
+    assert_eq!(state["selection_anchor"], json!({"row": 1, "column": 1}));
+
+    call(
+        &mut server,
+        json!({"jsonrpc": "2.0", "id": 4, "method": "open", "params": {"text": ""}}),
+    );
+    let restored = call(
+        &mut server,
+        json!({"jsonrpc": "2.0", "id": 5, "method": "restore",
+               "params": {"buffer": 2, "state": state}}),
+    );
+    let restored = &restored[0]["result"];
+    assert_eq!(restored["text"], "one\ntwo");
+    assert_eq!(restored["cursor"], json!({"row": 1, "column": 1}));
+    assert_eq!(restored["version"], 1);
+}
+
+#[test]
+fn test_decorate() {
+    let mut server = RpcServer::new();
+    call(
+        &mut server,
+        json!({"jsonrpc": "2.0", "id": 1, "method": "open", "params": {"text": "let x = 1;"}}),
+    );
+    let decorated = call(
+        &mut server,
+        json!({"jsonrpc": "2.0", "id": 2, "method": "decorate",
+        "params": {"buffer": 1, "source": "hints", "decorations": [
+            {"range": {"start": 5, "end": 5},
+             "kind": {"VirtualText": {"text": ": i32"}}}
+        ]}}),
+    );
+    assert_eq!(decorated[0]["result"], Value::Null);
+
+    let bad = call(
+        &mut server,
+        json!({"jsonrpc": "2.0", "id": 3, "method": "decorate",
+               "params": {"buffer": 1, "source": "hints", "decorations": [{"kind": "Glow"}]}}),
+    );
+    assert_eq!(bad[0]["error"]["code"], -32602);
+}
@@ -0,0 +1,92 @@
+mod common;
+
+use common::buffer_lines;
+use zlyph_core::decorations::{LineDecoration, Tone};
+use zlyph_core::search::{self, find_all};
+use zlyph_core::{BufferPosition, BufferRange, EditorAction, EditorEngine};
+
+fn range(row: usize, start: usize, end: usize) -> (BufferPosition, BufferPosition) {
+    (
+        BufferPosition::new(row, start),
+        BufferPosition::new(row, end),
+    )
+}
+
+#[test]
+fn test_find_all_lists_occurrences_in_order() {
+    let lines = buffer_lines("aaa a\n\nbanana");
+    assert_eq!(
+        find_all(&lines, "aa"),
+        vec![range(0, 0, 2)],
+        "matches do not overlap"
+    );
+    assert_eq!(find_all(&lines, "an"), vec![range(2, 1, 3), range(2, 3, 5)]);
+    assert!(find_all(&lines, "").is_empty());
+    assert!(find_all(&lines, "a\nb").is_empty());
+}
+
+#[test]
+fn test_search_matches_follow_query_then_selection() {
+    let mut engine = EditorEngine::new();
+    engine.handle_action(EditorAction::TypeString("cat dog\ncat".to_string()));
+    assert!(engine.search_matches().is_empty());
+
+    engine.handle_action(EditorAction::StartSelection { row: 0, column: 0 });
+    engine.handle_action(EditorAction::ExtendSelection { row: 0, column: 3 });
+    assert_eq!(
+        engine.search_matches(),
+        vec![range(0, 0, 3), range(1, 0, 3)]
+    );
+
+    engine.set_search_query(Some("dog".to_string()));
+    assert_eq!(engine.search_matches(), vec![range(0, 4, 7)]);
+
+    engine.set_search_query(Some(String::new()));
+    assert_eq!(engine.search_query(), None);
+}
+
+#[test]
+fn test_next_match_wraps_and_counts() {
+    let mut engine = EditorEngine::new();
+    engine.load_text("one two\none\ntwo one");
+    assert_eq!(engine.search_status(), None);
+    engine.set_search_query(Some("one".to_string()));
+    assert_eq!(engine.search_status().as_deref(), Some("1 of 3 matches"));
+
+    engine.handle_action(EditorAction::NextMatch);
+    assert_eq!(
+        engine.state().selection(),
+        Some(BufferRange::new(
+            BufferPosition::new(1, 0),
+            BufferPosition::new(1, 3)
+        ))
+    );
+    assert_eq!(engine.search_status().as_deref(), Some("2 of 3 matches"));
+    engine.handle_action(EditorAction::NextMatch);
+    engine.handle_action(EditorAction::NextMatch);
+    assert_eq!(engine.search_status().as_deref(), Some("1 of 3 matches"));
+    engine.handle_action(EditorAction::PrevMatch);
+    assert_eq!(engine.search_status().as_deref(), Some("3 of 3 matches"));
+
+    // The match at the cursor is highlighted apart from the rest
+    let tones: Vec<_> = (0..3)
+        .flat_map(|row| engine.line_decorations(row))
+        .filter_map(|decoration| match decoration {
+            LineDecoration::Highlight { tone, .. } => Some(tone),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(tones, [Tone::Warning, Tone::Warning, Tone::Accent]);
+
+    engine.handle_action(EditorAction::Cancel);
+    assert_eq!(engine.search_status(), None);
+    assert!(engine.line_decorations(0).is_empty());
+}
+
+#[test]
+fn test_count_label() {
+    assert_eq!(search::count_label(None, 0), "No matches");
+    assert_eq!(search::count_label(Some(0), 1), "1 of 1 match");
+    assert_eq!(search::count_label(None, 17), "17 matches");
+    assert_eq!(search::count_label(Some(2), 17), "3 of 17 matches");
+}
@@ -0,0 +1,61 @@
+mod common;
+
+use common::lines;
+use zlyph_core::sections;
+use zlyph_core::{BufferPosition, EditorAction, EditorEngine};
+
+const NOTES: &str = "intro\n# A\na\n## A1\n```\n# not a heading\n```\n\n# B\nb";
+
+#[test]
+fn test_section_tree() {
+    let tree = sections::tree(&lines(NOTES));
+    assert_eq!(tree.len(), 2);
+    assert_eq!((tree[0].row, tree[0].level, tree[0].end), (1, 1, 8));
+    assert_eq!(tree[0].children[0].rows(), 3..8);
+    assert_eq!(tree[1].rows(), 8..10);
+    assert_eq!(sections::at(&tree, 5).map(|s| s.row), Some(3));
+    assert_eq!(sections::at(&tree, 0), None);
+}
+
+#[test]
+fn test_move_section_keeps_blank_lines() {
+    let mut engine = EditorEngine::new();
+    engine.load_text(NOTES);
+    engine.handle_action(EditorAction::SetCursorPosition { row: 9, column: 1 });
+    engine.handle_action(EditorAction::MoveSectionUp);
+    assert_eq!(
+        engine.state().to_string(),
+        "intro\n# B\nb\n\n# A\na\n## A1\n```\n# not a heading\n```"
+    );
+    assert_eq!(engine.state().cursor, BufferPosition::new(2, 1));
+
+    engine.handle_action(EditorAction::MoveSectionUp);
+    assert_eq!(engine.hover(), Some("No section above at this level"));
+    engine.handle_action(EditorAction::MoveSectionDown);
+    assert_eq!(engine.state().to_string(), NOTES);
+    assert_eq!(engine.state().cursor, BufferPosition::new(9, 1));
+}
+
+#[test]
+fn test_promote_and_demote_with_subsections() {
+    let mut engine = EditorEngine::new();
+    engine.load_text(NOTES);
+    engine.handle_action(EditorAction::SetCursorPosition { row: 1, column: 2 });
+    engine.handle_action(EditorAction::DemoteSection);
+    assert_eq!(engine.state().lines[1], "## A");
+    assert_eq!(engine.state().lines[3], "### A1");
+    assert_eq!(engine.state().lines[5], "# not a heading");
+    assert_eq!(engine.state().cursor, BufferPosition::new(1, 3));
+
+    engine.handle_action(EditorAction::PromoteSection);
+    engine.handle_action(EditorAction::PromoteSection);
+    assert_eq!(engine.hover(), Some("Already a top-level heading"));
+    assert_eq!(engine.state().to_string(), NOTES);
+
+    engine.handle_action(EditorAction::SetCursorPosition { row: 4, column: 0 });
+    engine.handle_action(EditorAction::SelectSection);
+    assert_eq!(
+        engine.selected_text().as_deref(),
+        Some("## A1\n```\n# not a heading\n```\n\n")
+    );
+}
@@ -0,0 +1,89 @@
+mod common;
+
+use common::buffer_lines;
+use zlyph_core::sentences;
+use zlyph_core::{BufferPosition, EditorAction, EditorEngine};
+
+/// The text of each sentence of the paragraph `row` is in, line breaks as
+/// spaces
+fn texts(text: &str, row: usize) -> Vec<String> {
+    let lines = buffer_lines(text);
+    sentences::in_paragraph(&lines, row)
+        .into_iter()
+        .map(|(start, end)| {
+            (start.row..=end.row)
+                .map(|row| {
+                    let from = if row == start.row { start.column } else { 0 };
+                    let to = if row == end.row {
+                        end.column
+                    } else {
+                        lines[row].len()
+                    };
+                    &lines[row][from..to]
+                })
+                .collect::<Vec<_>>()
+                .join(" ")
+        })
+        .collect()
+}
+
+#[test]
+fn test_sentences_end_at_punctuation_and_paragraphs() {
+    assert_eq!(
+        texts(
+            "Dr. J. Smith wrote it, e.g. here. \"Really?\" she\nasked. Yes!Fine\n\nNext.",
+            0
+        ),
+        [
+            "Dr. J. Smith wrote it, e.g. here.",
+            "\"Really?\"",
+            "she asked.",
+            "Yes!Fine",
+        ]
+    );
+    assert_eq!(texts("Next.\n\n", 2), Vec::<String>::new());
+}
+
+#[test]
+fn test_sentence_at_position() {
+    let lines = buffer_lines("One. Two three.\n\n");
+    let two = (BufferPosition::new(0, 5), BufferPosition::new(0, 15));
+    assert_eq!(sentences::at(&lines, BufferPosition::new(0, 9)), two);
+    // The space after a sentence goes with it
+    assert_eq!(
+        sentences::at(&lines, BufferPosition::new(0, 4)),
+        (BufferPosition::new(0, 0), BufferPosition::new(0, 4))
+    );
+    assert_eq!(
+        sentences::at(&lines, BufferPosition::new(2, 0)),
+        (BufferPosition::new(2, 0), BufferPosition::new(2, 0))
+    );
+}
+
+#[test]
+fn test_move_and_select_by_sentence() {
+    let mut engine = EditorEngine::new();
+    engine.load_text("One. Two.\n\nThree");
+    let mut next = Vec::new();
+    for _ in 0..3 {
+        engine.handle_action(EditorAction::MoveToNextSentence);
+        next.push(engine.state().cursor);
+    }
+    assert_eq!(
+        next,
+        [
+            BufferPosition::new(0, 5),
+            BufferPosition::new(2, 0),
+            BufferPosition::new(2, 5),
+        ]
+    );
+
+    engine.handle_action(EditorAction::MoveToPrevSentence);
+    assert_eq!(engine.state().cursor, BufferPosition::new(2, 0));
+    engine.handle_action(EditorAction::MoveToPrevSentence);
+    assert_eq!(engine.state().cursor, BufferPosition::new(0, 5));
+
+    engine.handle_action(EditorAction::MoveRight);
+    engine.handle_action(EditorAction::SelectSentence);
+    assert_eq!(engine.selected_text().as_deref(), Some("Two."));
+}
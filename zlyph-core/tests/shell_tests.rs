@@ -0,0 +1,75 @@
+use std::time::{Duration, Instant};
+use zlyph_core::ex::Command;
+use zlyph_core::{BufferPosition, EditorAction, EditorEngine};
+
+fn run(engine: &mut EditorEngine, command: &str) {
+    Command::parse(command).unwrap().execute(engine).unwrap();
+}
+
+#[test]
+fn test_filter_lines_through_command() {
+    let mut engine = EditorEngine::new();
+    engine.load_text("# list\ncherry\napple\nbanana\nend");
+    run(&mut engine, "2,4!sort");
+    assert_eq!(engine.shell_command(), Some("sort"));
+    engine.wait_for_shell_command().unwrap();
+    assert_eq!(engine.text(), "# list\napple\nbanana\ncherry\nend");
+
+    engine.handle_action(EditorAction::Undo);
+    assert_eq!(engine.text(), "# list\ncherry\napple\nbanana\nend");
+}
+
+#[test]
+fn test_filter_selection() {
+    let mut engine = EditorEngine::new();
+    engine.load_text("say hello there");
+    engine.handle_action(EditorAction::SetCursorPosition { row: 0, column: 4 });
+    engine.handle_action(EditorAction::ExtendSelection { row: 0, column: 9 });
+    run(&mut engine, "!tr a-z A-Z");
+    engine.wait_for_shell_command().unwrap();
+    assert_eq!(engine.text(), "say HELLO there");
+    assert_eq!(engine.state().cursor, BufferPosition::new(0, 9));
+}
+
+#[test]
+fn test_read_inserts_output_at_cursor() {
+    let mut engine = EditorEngine::new();
+    engine.load_text("before after");
+    engine.handle_action(EditorAction::SetCursorPosition { row: 0, column: 7 });
+    run(&mut engine, "r !printf 'one\\ntwo '");
+
+    // Polled as the frontends do
+    let started = Instant::now();
+    while !engine.poll_shell_command() {
+        assert!(started.elapsed() < Duration::from_secs(10), "command hung");
+        std::thread::sleep(Duration::from_millis(10));
+    }
+    assert_eq!(engine.text(), "before one\ntwo after");
+    assert_eq!(engine.state().cursor, BufferPosition::new(1, 4));
+    assert_eq!(engine.hover(), None);
+}
+
+#[test]
+fn test_failed_command_leaves_text() {
+    let mut engine = EditorEngine::new();
+    engine.load_text("keep");
+    run(&mut engine, "!echo broken >&2; exit 1");
+    let error = engine.wait_for_shell_command().unwrap_err();
+    assert_eq!(error.to_string(), "echo broken >&2; exit 1 failed: broken");
+    assert_eq!(engine.text(), "keep");
+
+    assert!(Command::parse("r date").is_err());
+    assert!(Command::parse("%!").is_err());
+}
+
+#[test]
+fn test_escape_cancels_command() {
+    let mut engine = EditorEngine::new();
+    engine.load_text("text");
+    run(&mut engine, "r !sleep 10");
+    engine.handle_action(EditorAction::Cancel);
+    assert_eq!(engine.shell_command(), None);
+    assert_eq!(engine.hover(), Some("Cancelled sleep 10"));
+    assert!(!engine.poll_shell_command());
+    assert_eq!(engine.text(), "text");
+}
@@ -0,0 +1,165 @@
+use zlyph_core::snippets::{parse_snippet, SnippetDefinition};
+use zlyph_core::{BufferPosition, Config, EditorAction, EditorEngine};
+
+fn engine_with_snippets(snippets: &[(&str, &str)]) -> EditorEngine {
+    let mut engine = EditorEngine::new();
+    engine.set_config(Config {
+        snippets: snippets
+            .iter()
+            .map(|(trigger, body)| SnippetDefinition {
+                trigger: trigger.to_string(),
+                body: body.to_string(),
+                description: None,
+            })
+            .collect(),
+        ..Default::default()
+    });
+    engine
+}
+
+#[test]
+fn test_parse_snippet_tab_stops() {
+    let parsed = parse_snippet("fn ${1:name}($2) {\n    $0\n}");
+
+    assert_eq!(parsed.text, "fn name() {\n    \n}");
+    let indices: Vec<usize> = parsed.tab_stops.iter().map(|s| s.index).collect();
+    assert_eq!(indices, vec![1, 2, 0]);
+    assert_eq!(parsed.tab_stops[0].range, 3..7);
+    assert_eq!(parsed.tab_stops[1].range, 8..8);
+}
+
+#[test]
+fn test_parse_snippet_escaped_dollar_and_implicit_end() {
+    let parsed = parse_snippet("cost: \\$$1");
+
+    assert_eq!(parsed.text, "cost: $");
+    assert_eq!(parsed.tab_stops.last().unwrap().range, 7..7);
+}
+
+#[test]
+fn test_config_parses_snippets() {
+    let config = Config::from_toml(
+        r#"
+        [[snippets]]
+        trigger = "sig"
+        body = "Best,\n$1"
+        "#,
+    )
+    .unwrap();
+
+    assert_eq!(config.snippets.len(), 1);
+    assert_eq!(config.snippets[0].trigger, "sig");
+}
+
+#[test]
+fn test_tab_expands_trigger_and_selects_placeholder() {
+    let mut engine = engine_with_snippets(&[("todo", "- [ ] ${1:task} ($2)")]);
+    engine.handle_action(EditorAction::TypeString("todo".to_string()));
+    engine.handle_action(EditorAction::Tab);
+
+    assert_eq!(engine.state().to_string(), "- [ ] task ()");
+    assert_eq!(
+        engine.state().selection_anchor,
+        Some(BufferPosition::new(0, 6))
+    );
+    assert_eq!(engine.state().cursor, BufferPosition::new(0, 10));
+}
+
+#[test]
+fn test_tab_stop_navigation_follows_edits() {
+    let mut engine = engine_with_snippets(&[("todo", "- [ ] ${1:task} ($2)$0")]);
+    engine.handle_action(EditorAction::TypeString("todo".to_string()));
+    engine.handle_action(EditorAction::Tab);
+    engine.handle_action(EditorAction::TypeString("write docs".to_string()));
+    engine.handle_action(EditorAction::Tab);
+    engine.handle_action(EditorAction::TypeString("today".to_string()));
+    engine.handle_action(EditorAction::Tab);
+
+    assert_eq!(engine.state().to_string(), "- [ ] write docs (today)");
+    assert_eq!(engine.state().cursor, BufferPosition::new(0, 24));
+
+    // Snippet mode has ended, so Tab indents again
+    engine.handle_action(EditorAction::Tab);
+    assert_eq!(engine.state().to_string(), "- [ ] write docs (today)    ");
+}
+
+#[test]
+fn test_outdent_moves_to_previous_tab_stop() {
+    let mut engine = engine_with_snippets(&[("pair", "$1 and $2")]);
+    engine.handle_action(EditorAction::TypeString("pair".to_string()));
+    engine.handle_action(EditorAction::Tab);
+    engine.handle_action(EditorAction::TypeString("a".to_string()));
+    engine.handle_action(EditorAction::Tab);
+    engine.handle_action(EditorAction::Outdent);
+
+    assert_eq!(engine.state().cursor, BufferPosition::new(0, 1));
+}
+
+#[test]
+fn test_tab_without_trigger_indents() {
+    let mut engine = engine_with_snippets(&[("todo", "- [ ] $1")]);
+    engine.handle_action(EditorAction::TypeString("mytodo".to_string()));
+    engine.handle_action(EditorAction::Tab);
+
+    assert_eq!(engine.state().to_string(), "mytodo    ");
+}
+
+#[test]
+fn test_snippet_expansion_is_one_undo_step() {
+    let mut engine = engine_with_snippets(&[("sig", "Best,\nDoug")]);
+    engine.handle_action(EditorAction::TypeString("sig".to_string()));
+    engine.handle_action(EditorAction::Tab);
+    assert_eq!(engine.state().to_string(), "Best,\nDoug");
+
+    engine.handle_action(EditorAction::Undo);
+    assert_eq!(engine.state().to_string(), "sig");
+}
+
+#[test]
+fn test_abbreviations_expand_on_space_and_tab() {
+    let mut engine = EditorEngine::new();
+    engine.set_config(
+        Config::from_toml(
+            "[abbreviations]\n\";sig\" = \"Best,\\nDoug\"\n\"brb\" = \"be right back\"\n",
+        )
+        .unwrap(),
+    );
+    for c in "ok ;sig ".chars() {
+        engine.handle_action(EditorAction::TypeCharacter(c));
+    }
+    assert_eq!(engine.state().to_string(), "ok Best,\nDoug ");
+    // The space, then the expansion
+    engine.handle_action(EditorAction::Undo);
+    engine.handle_action(EditorAction::Undo);
+    assert_eq!(engine.state().to_string(), "ok ;sig");
+
+    let mut engine = EditorEngine::new();
+    engine.set_config(Config::from_toml("[abbreviations]\nbrb = \"be right back\"\n").unwrap());
+    engine.handle_action(EditorAction::TypeString("xbrb".to_string()));
+    engine.handle_action(EditorAction::TypeString(" ".to_string()));
+    engine.handle_action(EditorAction::TypeString("brb".to_string()));
+    engine.handle_action(EditorAction::Cancel);
+    engine.handle_action(EditorAction::Tab);
+    assert_eq!(engine.state().to_string(), "xbrb be right back");
+}
+
+#[test]
+fn test_insert_date_and_time() {
+    let mut engine = EditorEngine::new();
+    engine.set_config(
+        Config::from_toml("[datetime]\ndate_format = \"%Y\"\ntime_format = \"[%%]\"\n").unwrap(),
+    );
+    engine.handle_action(EditorAction::InsertDate);
+    engine.handle_action(EditorAction::InsertTime);
+    let year = chrono_year();
+    assert_eq!(engine.state().to_string(), format!("{}[%]", year));
+
+    engine.set_config(Config::from_toml("[datetime]\ntimestamp_format = \"%Q\"\n").unwrap());
+    engine.handle_action(EditorAction::InsertTimestamp);
+    assert_eq!(engine.state().to_string(), format!("{}[%]", year));
+    assert!(engine.hover().is_some());
+}
+
+fn chrono_year() -> String {
+    chrono::Local::now().format("%Y").to_string()
+}
@@ -0,0 +1,83 @@
+mod common;
+
+use common::lines;
+use zlyph_core::structure;
+use zlyph_core::{BufferPosition, EditorAction, EditorEngine};
+
+fn selected(engine: &EditorEngine) -> String {
+    engine.selected_text().unwrap_or_default()
+}
+
+const CODE: &str = "fn main() {\n    let x = f(a + b, \"c, d\");\n    g(x);\n}\n\nfn other() {}";
+
+#[test]
+fn test_expand_selection_snaps_to_syntax_nodes() {
+    let mut engine = EditorEngine::new();
+    engine.load_text(CODE);
+    engine.handle_action(EditorAction::SetCursorPosition { row: 1, column: 14 });
+    let mut steps = Vec::new();
+    for _ in 0..6 {
+        engine.handle_action(EditorAction::ExpandSelection);
+        steps.push(selected(&engine));
+    }
+    assert_eq!(
+        steps,
+        [
+            "a",
+            "a + b",
+            "a + b, \"c, d\"",
+            "(a + b, \"c, d\")",
+            "let x = f(a + b, \"c, d\")",
+            "    let x = f(a + b, \"c, d\");",
+        ]
+    );
+    engine.handle_action(EditorAction::ExpandSelection);
+    engine.handle_action(EditorAction::ExpandSelection);
+    engine.handle_action(EditorAction::ExpandSelection);
+    assert_eq!(selected(&engine), &CODE[..CODE.find("\n\n").unwrap()]);
+
+    engine.handle_action(EditorAction::ShrinkSelection);
+    assert_eq!(
+        selected(&engine),
+        "{\n    let x = f(a + b, \"c, d\");\n    g(x);\n}"
+    );
+}
+
+#[test]
+fn test_select_parent_skips_lines() {
+    let mut engine = EditorEngine::new();
+    engine.load_text(CODE);
+    engine.handle_action(EditorAction::SetCursorPosition { row: 2, column: 6 });
+    engine.handle_action(EditorAction::SelectParent);
+    assert_eq!(selected(&engine), "x");
+    engine.handle_action(EditorAction::SelectParent);
+    assert_eq!(selected(&engine), "(x)");
+    engine.handle_action(EditorAction::SelectParent);
+    assert_eq!(selected(&engine), "g(x)");
+    engine.handle_action(EditorAction::SelectParent);
+    assert_eq!(
+        engine.state().selection_anchor,
+        Some(BufferPosition::new(0, 11))
+    );
+}
+
+#[test]
+fn test_next_sibling_at_each_level() {
+    let code = lines(CODE);
+    let at = |row, column| BufferPosition::new(row, column);
+    // Arguments, leaving the comma inside the string alone
+    assert_eq!(
+        structure::next_sibling(&code, at(1, 14), at(1, 19)),
+        Some((at(1, 21), at(1, 27)))
+    );
+    assert_eq!(structure::next_sibling(&code, at(1, 21), at(1, 27)), None);
+    // Statements, then functions
+    assert_eq!(
+        structure::next_sibling(&code, at(1, 6), at(1, 6)),
+        Some((at(2, 4), at(2, 8)))
+    );
+    assert_eq!(
+        structure::next_sibling(&code, at(0, 3), at(0, 3)),
+        Some((at(5, 0), at(5, 13)))
+    );
+}
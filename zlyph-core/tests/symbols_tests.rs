@@ -0,0 +1,149 @@
+mod common;
+
+use common::lines;
+use std::path::Path;
+use zlyph_core::symbols::{self, SymbolKind, SymbolPanel};
+
+fn names(text: &str, path: &str) -> Vec<(String, SymbolKind, usize, usize)> {
+    symbols::outline(&lines(text), Some(Path::new(path)))
+        .into_iter()
+        .map(|symbol| (symbol.name, symbol.kind, symbol.row, symbol.depth))
+        .collect()
+}
+
+#[test]
+fn test_rust_definitions() {
+    let text = "\
+pub(crate) struct Point {
+    x: i32,
+}
+
+impl<T> Display for Point<T> {
+    pub const fn origin() -> Self {}
+}
+
+const LIMIT: usize = 3;
+macro_rules! point {}
+// fn not_a_definition
+let mod_name = 1;";
+    assert_eq!(
+        names(text, "src/point.rs"),
+        [
+            ("Point".into(), SymbolKind::Type, 0, 0),
+            ("impl Display for Point<T>".into(), SymbolKind::Type, 4, 0),
+            ("origin".into(), SymbolKind::Function, 5, 1),
+            ("LIMIT".into(), SymbolKind::Constant, 8, 0),
+            ("point".into(), SymbolKind::Function, 9, 0),
+        ]
+    );
+    let outline = symbols::outline(&lines(text), Some(Path::new("a.rs")));
+    assert_eq!(outline[2].column, 17);
+}
+
+#[test]
+fn test_other_languages() {
+    assert_eq!(
+        names(
+            "class Shape:\n    async def area(self):\n        pass",
+            "shape.py"
+        ),
+        [
+            ("Shape".into(), SymbolKind::Type, 0, 0),
+            ("area".into(), SymbolKind::Function, 1, 1),
+        ]
+    );
+    assert_eq!(
+        names(
+            "export default async function load() {}\ninterface Props {}\nconst x = 1;",
+            "app.tsx"
+        ),
+        [
+            ("load".into(), SymbolKind::Function, 0, 0),
+            ("Props".into(), SymbolKind::Type, 1, 0),
+        ]
+    );
+    assert_eq!(
+        names(
+            "func (s *Server) Serve() {}\ntype Server struct {}",
+            "main.go"
+        ),
+        [
+            ("Serve".into(), SymbolKind::Function, 0, 0),
+            ("Server".into(), SymbolKind::Type, 1, 0),
+        ]
+    );
+}
+
+#[test]
+fn test_headings_skip_frontmatter_and_code() {
+    let text = "---\ntitle: # not a heading\n---\n# Plan ##\n```\n# comment\n```\n  ## Steps\n#hashtag\n####### seven";
+    let outline = symbols::outline(&lines(text), None);
+    let found: Vec<(&str, usize, usize, usize)> = outline
+        .iter()
+        .map(|symbol| {
+            (
+                symbol.name.as_str(),
+                symbol.row,
+                symbol.column,
+                symbol.depth,
+            )
+        })
+        .collect();
+    assert_eq!(found, [("Plan", 3, 2, 0), ("Steps", 7, 5, 1)]);
+    assert_eq!(
+        symbols::outline(&lines(text), Some(Path::new("notes.md"))),
+        outline
+    );
+}
+
+#[test]
+fn test_panel_filters_fuzzily() {
+    let text = "fn parse() {}\nfn parse_args() {}\nfn print() {}\nstruct Parser;";
+    let mut panel = SymbolPanel::new(symbols::outline(&lines(text), Some(Path::new("a.rs"))));
+    assert_eq!(panel.status(), "4 symbols");
+    assert_eq!(panel.containing(2), Some(2));
+
+    panel.type_text("PARSE");
+    let found: Vec<&str> = panel.matches().iter().map(|s| s.name.as_str()).collect();
+    // An exact name comes first, then prefixes, shortest first
+    assert_eq!(found, ["parse", "Parser", "parse_args"]);
+    assert_eq!(panel.status(), "3 of 4 symbols");
+
+    panel.query.clear();
+    panel.type_text("pa");
+    panel.select_next();
+    panel.select_next();
+    assert_eq!(panel.selected_symbol().unwrap().name, "Parser");
+
+    // New symbols keep the query but not a selection past the end
+    panel.set_symbols(symbols::outline(
+        &lines("fn prepare() {}"),
+        Some(Path::new("a.rs")),
+    ));
+    assert_eq!(panel.selected, 0);
+    assert_eq!(panel.query, "pa");
+}
+
+#[test]
+fn test_breadcrumbs_follow_nesting() {
+    let text = "# Plan\n## Steps\n### One\ntext\n## Notes\nmore\n# Done";
+    let outline = symbols::outline(&lines(text), None);
+    let trail = |row| -> Vec<&str> {
+        symbols::breadcrumbs(&outline, row)
+            .iter()
+            .map(|symbol| symbol.name.as_str())
+            .collect()
+    };
+    assert_eq!(trail(3), ["Plan", "Steps", "One"]);
+    assert_eq!(trail(5), ["Plan", "Notes"]);
+    assert_eq!(trail(6), ["Done"]);
+
+    // Levels may be skipped, and nothing encloses text above the first
+    let outline = symbols::outline(&lines("intro\n# A\n### C"), None);
+    assert!(symbols::breadcrumbs(&outline, 0).is_empty());
+    let names: Vec<&str> = symbols::breadcrumbs(&outline, 2)
+        .iter()
+        .map(|symbol| symbol.name.as_str())
+        .collect();
+    assert_eq!(names, ["A", "C"]);
+}
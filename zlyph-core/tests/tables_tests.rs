@@ -0,0 +1,151 @@
+mod common;
+
+use common::lines;
+use zlyph_core::tables::{self, Alignment};
+use zlyph_core::{BufferPosition, EditorAction, EditorEngine};
+
+#[test]
+fn test_cell_ranges() {
+    let line = "| a | b \\| c |";
+    let cells: Vec<&str> = tables::cell_ranges(line)
+        .into_iter()
+        .map(|range| &line[range])
+        .collect();
+    assert_eq!(cells, vec![" a ", " b \\| c "]);
+    assert_eq!(tables::cell_ranges("a|b").len(), 2);
+    assert_eq!(tables::cell_ranges("| a").len(), 1);
+}
+
+#[test]
+fn test_find_and_render_table() {
+    let text = lines("Intro | not a table\n\n|Name|Qty|\n|:--|--:|\n|apple|3|\n|kiwi|12|\n\nAfter");
+    assert!(tables::table_at(&text, 0).is_none());
+    assert!(tables::table_at(&text, 1).is_none());
+
+    let table = tables::table_at(&text, 4).unwrap();
+    assert_eq!(table.rows, 2..6);
+    assert_eq!(table.alignments, vec![Alignment::Left, Alignment::Right]);
+    assert_eq!(table.row_count(), 3);
+    assert_eq!(
+        table.render(),
+        vec![
+            "| Name  | Qty |",
+            "| :---- | --: |",
+            "| apple |   3 |",
+            "| kiwi  |  12 |",
+        ]
+    );
+
+    // Pipes in code blocks aren't tables
+    let code = lines("```\n| a |\n| - |\n```");
+    assert!(tables::table_at(&code, 1).is_none());
+}
+
+#[test]
+fn test_ragged_rows_are_filled() {
+    let text = lines("| a | b | c |\n| - | - |\n| 1 |");
+    let table = tables::table_at(&text, 2).unwrap();
+    assert_eq!(table.columns(), 3);
+    assert_eq!(
+        table.render(),
+        vec![
+            "| a   | b   | c   |",
+            "| --- | --- | --- |",
+            "| 1   |     |     |",
+        ]
+    );
+
+    // Wide characters take two columns
+    let text = lines("| 名前 | x |\n| - | -: |\n| é | 漢字漢 |");
+    assert_eq!(
+        tables::table_at(&text, 0).unwrap().render(),
+        vec![
+            "| 名前 |      x |",
+            "| ---- | -----: |",
+            "| é    | 漢字漢 |",
+        ]
+    );
+}
+
+#[test]
+fn test_tab_moves_between_cells_and_adds_rows() {
+    let mut engine = EditorEngine::new();
+    engine.load_text("| a | b |\n|---|---|\n| 1 | 2 |");
+    engine.handle_action(EditorAction::SetCursorPosition { row: 0, column: 3 });
+
+    engine.handle_action(EditorAction::Tab);
+    assert_eq!(engine.state().cursor, BufferPosition::new(0, 9));
+    assert_eq!(engine.state().lines[1], "| --- | --- |");
+
+    engine.handle_action(EditorAction::Tab);
+    assert_eq!(engine.state().cursor, BufferPosition::new(2, 3));
+    engine.handle_action(EditorAction::Tab);
+    engine.handle_action(EditorAction::Tab);
+    assert_eq!(
+        engine.text(),
+        "| a   | b   |\n| --- | --- |\n| 1   | 2   |\n|     |     |"
+    );
+    assert_eq!(engine.state().cursor, BufferPosition::new(3, 2));
+
+    engine.handle_action(EditorAction::Outdent);
+    assert_eq!(engine.state().cursor, BufferPosition::new(2, 9));
+
+    // Tab outside a table still indents
+    engine.load_text("plain");
+    engine.handle_action(EditorAction::Tab);
+    assert_eq!(engine.text(), "    plain");
+}
+
+#[test]
+fn test_typing_realigns_columns() {
+    let mut engine = EditorEngine::new();
+    engine.load_text("| a   | b   |\n| --- | --- |\n| 1   | 2   |");
+    engine.handle_action(EditorAction::SetCursorPosition { row: 2, column: 3 });
+    engine.handle_action(EditorAction::TypeString("00 ".to_string()));
+    engine.handle_action(EditorAction::TypeCharacter('x'));
+    assert_eq!(
+        engine.text(),
+        "| a     | b   |\n| ----- | --- |\n| 100 x | 2   |"
+    );
+    assert_eq!(engine.state().cursor, BufferPosition::new(2, 7));
+
+    engine.handle_action(EditorAction::Backspace);
+    engine.handle_action(EditorAction::Backspace);
+    engine.handle_action(EditorAction::Backspace);
+    assert_eq!(engine.text(), "| a   | b   |\n| --- | --- |\n| 10  | 2   |");
+    assert_eq!(engine.state().cursor, BufferPosition::new(2, 4));
+}
+
+#[test]
+fn test_row_and_column_actions() {
+    let mut engine = EditorEngine::new();
+    engine.load_text("|a|b|\n|-|-:|\n|1|2|");
+    engine.handle_action(EditorAction::SetCursorPosition { row: 2, column: 1 });
+    engine.handle_action(EditorAction::FormatTable);
+    assert_eq!(engine.text(), "| a   |   b |\n| --- | --: |\n| 1   |   2 |");
+
+    engine.handle_action(EditorAction::InsertTableColumn);
+    assert_eq!(engine.state().lines[0], "| a   |     |   b |");
+    assert_eq!(engine.state().cursor, BufferPosition::new(2, 8));
+    engine.handle_action(EditorAction::DeleteTableColumn);
+    assert_eq!(engine.state().lines[0], "| a   |   b |");
+
+    engine.handle_action(EditorAction::InsertTableRow);
+    assert_eq!(engine.state().lines.len(), 4);
+    assert_eq!(engine.state().cursor.row, 3);
+    engine.handle_action(EditorAction::DeleteTableRow);
+    engine.handle_action(EditorAction::DeleteTableRow);
+    assert_eq!(engine.text(), "| a   |   b |\n| --- | --: |");
+
+    engine.handle_action(EditorAction::DeleteTableRow);
+    assert_eq!(engine.hover(), Some("The header row can't be deleted"));
+
+    // Each change is its own undo step
+    engine.handle_action(EditorAction::Undo);
+    assert_eq!(engine.state().lines.len(), 3);
+
+    engine.load_text("no table");
+    engine.handle_action(EditorAction::InsertTableRow);
+    assert_eq!(engine.text(), "no table");
+    assert_eq!(engine.hover(), Some("The cursor isn't in a table"));
+}
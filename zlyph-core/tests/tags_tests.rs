@@ -0,0 +1,74 @@
+mod common;
+
+use common::temp_dir;
+use std::fs;
+use zlyph_core::project_search::ProjectSearch;
+use zlyph_core::tags::{self, TagCount, TagIndex};
+use zlyph_core::BufferPosition;
+
+fn names(line: &str) -> Vec<String> {
+    tags::tags(line).into_iter().map(|tag| tag.name).collect()
+}
+
+#[test]
+fn test_parse_tags() {
+    assert_eq!(
+        names("#reading list (#project/zlyph) #to-do- and #2024-review"),
+        vec!["reading", "project/zlyph", "to-do", "2024-review"]
+    );
+    assert_eq!(tags::tags("a #b")[0].range, 2..4);
+    // Headings, numbers, words with a # inside, URL fragments and code
+    assert!(names("# Heading").is_empty());
+    assert!(names("issue #12 and C#sharp").is_empty());
+    assert!(names("https://example.com/#top `#code`").is_empty());
+
+    let lines: Vec<String> = "#Rust\n```\n#rust\n```\nmore #rust #rusty"
+        .lines()
+        .map(String::from)
+        .collect();
+    assert_eq!(
+        tags::find_tag(&lines, "#rust"),
+        vec![
+            (BufferPosition::new(0, 0), BufferPosition::new(0, 5)),
+            (BufferPosition::new(4, 5), BufferPosition::new(4, 10)),
+        ]
+    );
+}
+
+#[test]
+fn test_index_and_search_tags() {
+    let dir = temp_dir("index");
+    fs::write(dir.join("a.md"), "#idea #Reading\n#idea again\n").unwrap();
+    fs::write(dir.join("b.txt"), "#reading\n").unwrap();
+    fs::write(dir.join("c.rs"), "#reading\n").unwrap();
+
+    let mut index = TagIndex::start(&dir);
+    index.wait();
+    assert!(index.is_done());
+    assert_eq!(
+        index.tags(),
+        &[
+            TagCount {
+                name: "idea".to_string(),
+                count: 2,
+                files: 1
+            },
+            TagCount {
+                name: "Reading".to_string(),
+                count: 2,
+                files: 2
+            },
+        ]
+    );
+    index.select_next();
+    assert_eq!(index.selected_tag().unwrap().name, "Reading");
+
+    let mut search = ProjectSearch::start_tag(&dir, "reading");
+    search.wait();
+    assert_eq!(search.query, "#reading");
+    assert_eq!(search.match_count(), 2);
+    assert!(search
+        .results()
+        .iter()
+        .all(|file| file.path.extension().unwrap() != "rs"));
+}
@@ -0,0 +1,149 @@
+mod common;
+
+use common::temp_dir;
+use std::fs;
+use std::path::PathBuf;
+use zlyph_core::diagnostics::Severity;
+use zlyph_core::task_runner::{self, TaskDefinition, TaskRun};
+use zlyph_core::BufferPosition;
+
+/// A project with `src/main.rs` and `web/app.ts`
+fn project(name: &str) -> PathBuf {
+    let dir = temp_dir(name);
+    fs::create_dir_all(dir.join("src")).unwrap();
+    fs::create_dir_all(dir.join("web")).unwrap();
+    fs::write(
+        dir.join("src/main.rs"),
+        "fn main() {\n    let x: u8 = \"é\";\n}\n",
+    )
+    .unwrap();
+    fs::write(dir.join("web/app.ts"), "let n: number = 'a';\n").unwrap();
+    dir
+}
+
+#[test]
+fn test_parse_compiler_output() {
+    let dir = project("parse");
+    let output = "\
+   Compiling demo v0.1.0
+\x1b[1m\x1b[31merror[E0308]\x1b[0m: mismatched types
+ --> src/main.rs:2:17
+  |
+warning: unused variable: `x`
+  --> src/main.rs:2:9
+warning: `demo` (bin) generated 1 warning
+error: could not compile `demo`
+src/main.rs:3:1: warning: trailing brace
+web/app.ts(1,5): error TS2322: Type 'string' is not assignable
+missing.c:4:2: error: no such file
+12:30:45: started
+src/main.rs:1: no column
+";
+    let problems = task_runner::parse(output, &dir);
+    let summary: Vec<_> = problems
+        .iter()
+        .map(|p| {
+            let name = p.path.strip_prefix(&dir).unwrap().display().to_string();
+            (name, p.row, p.column, p.severity, p.message.as_str())
+        })
+        .collect();
+    assert_eq!(
+        summary,
+        [
+            (
+                "src/main.rs".to_string(),
+                1,
+                16,
+                Severity::Error,
+                "mismatched types"
+            ),
+            (
+                "src/main.rs".to_string(),
+                1,
+                8,
+                Severity::Warning,
+                "unused variable: `x`"
+            ),
+            (
+                "src/main.rs".to_string(),
+                2,
+                0,
+                Severity::Warning,
+                "trailing brace"
+            ),
+            (
+                "web/app.ts".to_string(),
+                0,
+                4,
+                Severity::Error,
+                "TS2322: Type 'string' is not assignable"
+            ),
+            (
+                "src/main.rs".to_string(),
+                0,
+                0,
+                Severity::Error,
+                "no column"
+            ),
+        ]
+    );
+}
+
+#[test]
+fn test_diagnostics_for_the_open_file() {
+    let dir = project("diagnostics");
+    let problems = task_runner::parse(
+        "src/main.rs:2:9: error: bad\nweb/app.ts:1:1: error: other",
+        &dir,
+    );
+    let lines = ["fn main() {", "    let x: u8 = \"é\";", "}"];
+    let diagnostics = task_runner::diagnostics(&problems, &dir.join("src/main.rs"), &lines);
+    assert_eq!(diagnostics.len(), 1);
+    assert_eq!(diagnostics[0].start, BufferPosition::new(1, 8));
+    assert_eq!(diagnostics[0].end, BufferPosition::new(1, 9));
+    assert_eq!(diagnostics[0].source, task_runner::DIAGNOSTIC_SOURCE);
+
+    // Columns count characters, positions bytes
+    let problems = task_runner::parse("src/main.rs:2:19: error: quote", &dir);
+    assert_eq!(problems[0].byte_column(lines[1]), 19);
+}
+
+#[test]
+fn test_run_task() {
+    let dir = project("run");
+    let task = TaskDefinition::new(
+        "lint",
+        "echo 'src/main.rs:2:5: warning: shadowed' >&2; exit 1",
+    );
+    let mut run = TaskRun::start(&task, &dir).unwrap();
+    run.wait();
+    assert!(run.is_done());
+    assert_eq!(run.problems().len(), 1);
+    assert_eq!(run.status(), "lint: 1 warning");
+    assert_eq!(run.step(false).map(|p| p.row), Some(1));
+    assert_eq!(run.step(false).map(|p| p.row), Some(1));
+
+    let mut run = TaskRun::start(&TaskDefinition::new("test", "echo ok"), &dir).unwrap();
+    run.wait();
+    assert_eq!(run.status(), "test passed");
+    let mut run =
+        TaskRun::start(&TaskDefinition::new("build", "echo broke; exit 2"), &dir).unwrap();
+    run.wait();
+    assert_eq!(run.status(), "build failed: broke");
+}
+
+#[test]
+fn test_tasks_for_project() {
+    let dir = project("detect");
+    assert!(task_runner::tasks_for(&[], &dir).is_empty());
+    fs::write(dir.join("Cargo.toml"), "[package]\n").unwrap();
+    assert_eq!(task_runner::project_root(&dir.join("src/main.rs")), dir);
+    let tasks = task_runner::tasks_for(&[], &dir);
+    assert_eq!(
+        tasks[0],
+        TaskDefinition::new("check", "cargo check --all-targets")
+    );
+
+    let configured = [TaskDefinition::new("lint", "ruff check")];
+    assert_eq!(task_runner::tasks_for(&configured, &dir), configured);
+}
@@ -0,0 +1,70 @@
+mod common;
+
+use chrono::{NaiveDate, NaiveTime};
+use common::temp_dir;
+use std::fs;
+use std::path::Path;
+use zlyph_core::templates::{self, TemplatePicker, Variables};
+
+fn vars() -> Variables {
+    Variables {
+        date: NaiveDate::from_ymd_opt(2026, 3, 4).unwrap(),
+        time: NaiveTime::from_hms_opt(9, 5, 0).unwrap(),
+        title: "Meeting".to_string(),
+        clipboard: "pasted".to_string(),
+    }
+}
+
+#[test]
+fn test_render_variables() {
+    assert_eq!(
+        templates::render("# {{title}}\n{{date}} {{time}}\n{{clipboard}}", &vars()),
+        "# Meeting\n2026-03-04 09:05\npasted"
+    );
+    assert_eq!(
+        templates::render("{{date:%d %B}} at {{ time:%-I%P}}", &vars()),
+        "04 March at 9am"
+    );
+    // Unknown variables, bad formats and stray braces are left as written
+    assert_eq!(
+        templates::render("{{author}} {{date:%Q}} {{title", &vars()),
+        "{{author}} {{date:%Q}} {{title"
+    );
+}
+
+#[test]
+fn test_list_and_create() {
+    let dir = temp_dir("create");
+    fs::write(dir.join("meeting.md"), "# {{title}}\n").unwrap();
+    fs::write(dir.join("Daily.md"), "").unwrap();
+    fs::write(dir.join(".hidden"), "").unwrap();
+
+    let list = templates::list(&dir);
+    assert_eq!(list, vec![dir.join("Daily.md"), dir.join("meeting.md")]);
+    assert_eq!(
+        templates::find(&dir, "meeting"),
+        Some(dir.join("meeting.md"))
+    );
+    assert_eq!(
+        templates::find(&dir, "Daily.md"),
+        Some(dir.join("Daily.md"))
+    );
+    assert_eq!(templates::find(&dir, "other"), None);
+
+    let note = dir.join("notes").join("Standup.md");
+    templates::create(&note, &dir.join("meeting.md"), String::new()).unwrap();
+    assert_eq!(fs::read_to_string(&note).unwrap(), "# Standup\n");
+    // An existing note is not replaced
+    fs::write(&note, "kept").unwrap();
+    templates::create(&note, &dir.join("meeting.md"), String::new()).unwrap();
+    assert_eq!(fs::read_to_string(&note).unwrap(), "kept");
+    assert_eq!(templates::title_of(Path::new("a/b.c.md")), "b.c");
+
+    let mut picker = TemplatePicker::new(list);
+    picker.select_next();
+    picker.select_next();
+    assert_eq!(
+        picker.selected_template(),
+        Some(dir.join("meeting.md").as_path())
+    );
+}
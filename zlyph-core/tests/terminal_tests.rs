@@ -0,0 +1,84 @@
+#![cfg(feature = "terminal")]
+
+use std::time::{Duration, Instant};
+use zlyph_core::config::TerminalConfig;
+use zlyph_core::terminal::{self, Terminal, TerminalKey};
+
+fn shell(script: &str) -> TerminalConfig {
+    TerminalConfig {
+        shell: Some("sh".to_string()),
+        args: vec!["-c".to_string(), script.to_string()],
+        height: 5,
+        scrollback: 100,
+    }
+}
+
+/// Poll until the shell exits, or fail after a few seconds
+fn finish(terminal: &mut Terminal) {
+    let started = Instant::now();
+    while terminal.is_running() {
+        assert!(started.elapsed() < Duration::from_secs(10), "shell hung");
+        terminal.poll();
+        std::thread::sleep(Duration::from_millis(10));
+    }
+    // Output written just before exiting may still be on its way
+    std::thread::sleep(Duration::from_millis(50));
+    terminal.poll();
+}
+
+#[test]
+fn test_runs_the_shell_on_a_screen() {
+    let mut terminal = Terminal::spawn(&shell("printf 'one\\ntwo'; exit 3"), None, 5, 20).unwrap();
+    finish(&mut terminal);
+    assert_eq!(terminal.exit_code(), Some(3));
+    let lines = terminal.lines();
+    assert_eq!(lines.len(), 5);
+    assert_eq!(lines[0], "one");
+    assert_eq!(lines[1], "two");
+    assert_eq!(terminal.cursor(), Some((1, 3)));
+}
+
+#[test]
+fn test_capture_includes_the_scrollback() {
+    let mut terminal = Terminal::spawn(&shell("seq 1 12"), None, 5, 20).unwrap();
+    finish(&mut terminal);
+    let expected: String = (1..=12).map(|n| format!("{}\n", n)).collect();
+    assert_eq!(terminal.capture(), expected);
+    // Capturing leaves the screen showing its bottom
+    assert_eq!(terminal.lines()[0], "9");
+}
+
+#[test]
+fn test_resize() {
+    let mut terminal = Terminal::spawn(&shell("sleep 5"), None, 5, 20).unwrap();
+    terminal.resize(8, 40);
+    assert_eq!(terminal.size(), (8, 40));
+}
+
+#[test]
+fn test_key_bytes() {
+    assert_eq!(
+        terminal::key_bytes(TerminalKey::Char('c'), true, false, false),
+        b"\x03"
+    );
+    assert_eq!(
+        terminal::key_bytes(TerminalKey::Char('b'), false, true, false),
+        b"\x1bb"
+    );
+    assert_eq!(
+        terminal::key_bytes(TerminalKey::Up, false, false, false),
+        b"\x1b[A"
+    );
+    assert_eq!(
+        terminal::key_bytes(TerminalKey::Up, false, false, true),
+        b"\x1bOA"
+    );
+    assert_eq!(
+        terminal::key_bytes(TerminalKey::F(12), false, false, false),
+        b"\x1b[24~"
+    );
+    assert_eq!(
+        terminal::key_bytes(TerminalKey::Enter, false, false, false),
+        b"\r"
+    );
+}
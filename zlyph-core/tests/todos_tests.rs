@@ -0,0 +1,105 @@
+mod common;
+
+use common::temp_dir;
+use std::fs;
+use zlyph_core::todos::{self, Kind, TodoPanel};
+
+fn found(line: &str) -> Option<(Kind, usize, String)> {
+    todos::find(line)
+}
+
+#[test]
+fn test_markers() {
+    assert_eq!(
+        found("    // TODO: tidy up"),
+        Some((Kind::Todo, 7, "tidy up".into()))
+    );
+    assert_eq!(
+        found("FIXME(ana) leaks a handle"),
+        Some((Kind::Fixme, 0, "leaks a handle".into()))
+    );
+    assert_eq!(found("# NOTE"), Some((Kind::Note, 2, "# NOTE".into())));
+    // The first marker on the line wins
+    assert_eq!(found("x NOTE y TODO z").unwrap().0, Kind::Note);
+
+    assert_eq!(found("TODOs for later"), None);
+    assert_eq!(found("MYTODO: x"), None);
+    assert_eq!(found("todo: lowercase"), None);
+    assert_eq!(
+        found("NOTES and a TODO: this").unwrap().2,
+        "this".to_string()
+    );
+}
+
+#[test]
+fn test_unchecked_tasks() {
+    assert_eq!(
+        found("- [ ] call the bank"),
+        Some((Kind::Task, 2, "call the bank".into()))
+    );
+    assert_eq!(
+        found("  12. [ ] TODO: both"),
+        Some((Kind::Task, 6, "TODO: both".into()))
+    );
+    assert_eq!(found("* [ ]").unwrap().0, Kind::Task);
+    assert_eq!(found("- [x] done"), None);
+    assert_eq!(found("-[ ] no space"), None);
+    assert_eq!(found("- [ ]x"), None);
+    assert_eq!(found("[ ] not a list item"), None);
+}
+
+#[test]
+fn test_buffer_panel_filters_by_every_word() {
+    let lines = [
+        "// TODO: parse dates",
+        "fn main() {}",
+        "// FIXME: dates off by one",
+        "- [ ] write the docs",
+    ];
+    let mut panel = TodoPanel::for_buffer(&lines);
+    assert_eq!(panel.matches().len(), 3);
+    assert_eq!(panel.status(), "3 in this file");
+
+    panel.type_text("DATES fix");
+    let rows: Vec<usize> = panel.matches().iter().map(|item| item.row).collect();
+    assert_eq!(rows, [2]);
+    assert_eq!(panel.status(), "1 of 3 in this file");
+
+    panel.backspace();
+    panel.backspace();
+    panel.backspace();
+    panel.select_next();
+    panel.select_next();
+    assert_eq!(panel.selected_item().unwrap().row, 2);
+
+    panel.query = "task".into();
+    assert_eq!(panel.matches()[0].text, "write the docs");
+}
+
+#[test]
+fn test_notes_panel_gathers_every_note() {
+    let dir = temp_dir("notes");
+    fs::write(
+        dir.join("plan.md"),
+        "# Plan\n- [ ] book flights\n- [x] pack",
+    )
+    .unwrap();
+    fs::create_dir_all(dir.join("work")).unwrap();
+    fs::write(dir.join("work/review.md"), "Fine\nTODO: reply to Sam").unwrap();
+    fs::write(dir.join("script.sh"), "# TODO: not a note").unwrap();
+
+    let mut panel = TodoPanel::start(&dir);
+    panel.wait();
+    assert!(panel.is_done());
+    assert_eq!(panel.dir(), Some(dir.as_path()));
+    assert_eq!(panel.status(), "2 in the notes");
+
+    // A note's name filters too
+    panel.type_text("review");
+    let item = panel.selected_item().unwrap();
+    assert_eq!(item.path, Some(dir.join("work/review.md")));
+    assert_eq!((item.row, item.column), (1, 0));
+    assert_eq!(item.text, "reply to Sam");
+
+    fs::remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,71 @@
+use zlyph_core::transform::Transform;
+use zlyph_core::{BufferPosition, EditorAction, EditorEngine};
+
+#[test]
+fn test_encodings_round_trip() {
+    let text = "a <b> & \"c\" é/?=";
+    for (encode, decode) in [
+        (Transform::Base64Encode, Transform::Base64Decode),
+        (Transform::UrlEncode, Transform::UrlDecode),
+        (Transform::HtmlEscape, Transform::HtmlUnescape),
+    ] {
+        let encoded = encode.apply(text).unwrap();
+        assert_eq!(decode.apply(&encoded).unwrap(), text);
+    }
+    assert_eq!(Transform::UrlEncode.apply("a b~c").unwrap(), "a%20b~c");
+    assert_eq!(
+        Transform::HtmlUnescape
+            .apply("&lt;&#x41;&#66;&nbsp;&bogus; &")
+            .unwrap(),
+        "<AB\u{a0}&bogus; &"
+    );
+    assert!(Transform::Base64Decode
+        .apply("not base64!")
+        .is_err_and(|e| e.starts_with("Not valid Base64")));
+    assert_eq!(Transform::from_name("sha256"), Some(Transform::Sha256));
+}
+
+#[test]
+fn test_hashes_and_hex_dump() {
+    assert_eq!(
+        Transform::Md5.apply("hello").unwrap(),
+        "5d41402abc4b2a76b9719d911017c592"
+    );
+    assert_eq!(
+        Transform::Sha256.apply("hello").unwrap(),
+        "2cf24dba5fb0a30e26e83b2ac5b9e29e1b161e5c1fa7425e73043362938b9824"
+    );
+    assert_eq!(
+        Transform::HexDump.apply("hello world, hex!\n").unwrap(),
+        "00000000  68 65 6c 6c 6f 20 77 6f  72 6c 64 2c 20 68 65 78  |hello world, hex|\n\
+         00000010  21 0a                                             |!.|"
+    );
+}
+
+#[test]
+fn test_transform_replaces_or_inserts_as_one_step() {
+    let mut engine = EditorEngine::new();
+    engine.load_text("key: aGk=\nnext");
+    engine.handle_action(EditorAction::StartSelection { row: 0, column: 5 });
+    engine.handle_action(EditorAction::ExtendSelection { row: 0, column: 9 });
+    engine.handle_action(EditorAction::TransformSelection(Transform::Base64Decode));
+    assert_eq!(engine.state().lines, ["key: hi", "next"]);
+    assert_eq!(engine.state().cursor, BufferPosition::new(0, 7));
+
+    engine.handle_action(EditorAction::TransformSelection(Transform::Md5));
+    assert_eq!(
+        engine.state().lines,
+        ["key: hi", "49f68a5c8493ec2c0bf489821c21fc3b", "next"]
+    );
+    engine.handle_action(EditorAction::Undo);
+    assert_eq!(engine.state().lines, ["key: hi", "next"]);
+
+    engine.handle_action(EditorAction::SetCursorPosition { row: 0, column: 0 });
+    engine.handle_action(EditorAction::TransformSelection(Transform::UrlEncode));
+    assert_eq!(engine.hover(), Some("Select the text for URL Encode"));
+    engine.handle_action(EditorAction::SelectAll);
+    engine.handle_action(EditorAction::TransformSelection(Transform::Base64Decode));
+    assert!(engine
+        .hover()
+        .is_some_and(|h| h.starts_with("Not valid Base64")));
+}
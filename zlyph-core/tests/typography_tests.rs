@@ -0,0 +1,123 @@
+mod common;
+
+use common::temp_dir;
+use std::fs;
+use zlyph_core::config::TypographyConfig;
+use zlyph_core::typography;
+use zlyph_core::{BufferPosition, Config, EditorAction, EditorEngine};
+
+fn engine() -> EditorEngine {
+    let mut engine = EditorEngine::new();
+    engine.set_config(Config {
+        typography: TypographyConfig { enabled: true },
+        ..Default::default()
+    });
+    engine
+}
+
+fn type_text(engine: &mut EditorEngine, text: &str) {
+    for c in text.chars() {
+        engine.handle_action(EditorAction::TypeCharacter(c));
+    }
+}
+
+#[test]
+fn test_substitute_quotes() {
+    assert_eq!(typography::substitute("", '"'), Some((0, "“".to_string())));
+    assert_eq!(
+        typography::substitute("say (", '"'),
+        Some((0, "“".to_string()))
+    );
+    assert_eq!(
+        typography::substitute("“hi", '"'),
+        Some((0, "”".to_string()))
+    );
+    assert_eq!(
+        typography::substitute("don", '\''),
+        Some((0, "’".to_string()))
+    );
+    assert_eq!(
+        typography::substitute("a ", '\''),
+        Some((0, "‘".to_string()))
+    );
+    assert_eq!(typography::substitute("run `x", '"'), None);
+}
+
+#[test]
+fn test_substitute_dashes_and_ellipsis() {
+    assert_eq!(
+        typography::substitute("a -", '-'),
+        Some((1, "–".to_string()))
+    );
+    assert_eq!(
+        typography::substitute("a –", '-'),
+        Some((3, "—".to_string()))
+    );
+    assert_eq!(typography::substitute("a-", 'b'), None);
+    assert_eq!(
+        typography::substitute("wait..", '.'),
+        Some((2, "…".to_string()))
+    );
+    // List markers, rules and comments
+    assert_eq!(typography::substitute("-", '-'), None);
+    assert_eq!(typography::substitute("  -", '-'), None);
+    assert_eq!(typography::substitute("<!-", '-'), None);
+}
+
+#[test]
+fn test_typing_prose() {
+    let mut engine = engine();
+    type_text(&mut engine, "\"It's 1--2 words---or so...\"");
+    assert_eq!(engine.state().lines[0], "“It’s 1–2 words—or so…”");
+    assert_eq!(engine.state().cursor.column, engine.state().lines[0].len());
+
+    // GUI keys come as strings
+    engine.handle_action(EditorAction::TypeString(" '".to_string()));
+    assert!(engine.state().lines[0].ends_with(" '"));
+    engine.handle_action(EditorAction::TypeString("'".to_string()));
+    assert!(engine.state().lines[0].ends_with(" '’"));
+}
+
+#[test]
+fn test_substitution_undoes_with_keystroke() {
+    let mut engine = engine();
+    type_text(&mut engine, "a--");
+    assert_eq!(engine.state().lines[0], "a–");
+    engine.handle_action(EditorAction::Undo);
+    assert_eq!(engine.state().lines[0], "a-");
+}
+
+#[test]
+fn test_code_and_disabled_are_left_alone() {
+    let mut engine = engine();
+    type_text(&mut engine, "```\nx = \"a\" -- b...\n```\n\"`'`\"");
+    assert_eq!(
+        engine.state().lines,
+        vec!["```", "x = \"a\" -- b...", "```", "“`'`”"]
+    );
+
+    let mut engine = EditorEngine::new();
+    type_text(&mut engine, "\"a\"");
+    assert_eq!(engine.state().lines[0], "\"a\"");
+}
+
+#[test]
+fn test_only_notes_are_prose() {
+    let dir = temp_dir("prose");
+    let code = dir.join("main.rs");
+    fs::write(&code, "").unwrap();
+    let note = dir.join("note.md");
+    fs::write(&note, "").unwrap();
+
+    let mut engine = engine();
+    engine.load_from_file(&code).unwrap();
+    type_text(&mut engine, "\"a\"");
+    assert_eq!(engine.state().lines[0], "\"a\"");
+
+    engine.load_from_file(&note).unwrap();
+    engine.handle_action(EditorAction::SetCursorPosition { row: 0, column: 0 });
+    type_text(&mut engine, "\"a\"");
+    assert_eq!(engine.state().lines[0], "“a”");
+    assert_eq!(engine.state().cursor, BufferPosition::new(0, "“a”".len()));
+    let _ = fs::remove_dir_all(&dir);
+}
@@ -0,0 +1,136 @@
+mod common;
+
+use common::temp_dir;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+use zlyph_core::config::RemoteConfig;
+use zlyph_core::vfs::{self, FileSystem, RemotePath, SshFs, TransferStatus};
+use zlyph_core::Config;
+
+/// An ssh stand-in running the command on this machine, failing like ssh
+/// does for an unreachable host while `dir/offline` exists
+#[cfg(unix)]
+fn fake_ssh(dir: &Path) -> SshFs {
+    use std::os::unix::fs::PermissionsExt;
+    let script = dir.join("fake-ssh");
+    fs::write(
+        &script,
+        format!(
+            "#!/bin/sh\n[ -e '{}' ] && exit 255\nfor arg; do last=$arg; done\nexec sh -c \"$last\"\n",
+            dir.join("offline").display()
+        ),
+    )
+    .unwrap();
+    fs::set_permissions(&script, fs::Permissions::from_mode(0o755)).unwrap();
+    let config = RemoteConfig {
+        ssh_command: script.display().to_string(),
+        ..RemoteConfig::default()
+    };
+    SshFs::new(config, dir.join("cache"))
+}
+
+fn url(file: &Path) -> PathBuf {
+    PathBuf::from(format!("ssh://me@box{}", file.display()))
+}
+
+fn wait_for(mut done: impl FnMut() -> bool) {
+    let start = Instant::now();
+    while !done() {
+        assert!(start.elapsed() < Duration::from_secs(10), "timed out");
+        std::thread::sleep(Duration::from_millis(20));
+    }
+}
+
+#[test]
+fn test_parse_remote_paths() {
+    let remote = RemotePath::parse("ssh://me@example.com:2222/srv/notes.md").unwrap();
+    assert_eq!(remote.user.as_deref(), Some("me"));
+    assert_eq!(remote.host, "example.com");
+    assert_eq!(remote.port, Some(2222));
+    assert_eq!(remote.path, "/srv/notes.md");
+    assert_eq!(remote.file_name(), "notes.md");
+    assert_eq!(remote.to_string(), "ssh://me@example.com:2222/srv/notes.md");
+
+    let home = RemotePath::parse("sftp://box/~/todo.txt").unwrap();
+    assert_eq!(home.user, None);
+    assert_eq!(home.path, "~/todo.txt");
+    assert_eq!(home.to_string(), "ssh://box/~/todo.txt");
+
+    assert!(RemotePath::parse("ssh://box").is_none());
+    assert!(RemotePath::parse("ssh://box:port/x").is_none());
+    assert!(!vfs::is_remote(Path::new("/home/me/notes.md")));
+    assert!(vfs::is_remote(Path::new("ssh://box/notes.md")));
+}
+
+#[test]
+fn test_checksum_matches_cksum() {
+    assert_eq!(vfs::checksum(b""), "4294967295 0");
+    assert_eq!(vfs::checksum(b"hello"), "3287646509 5");
+}
+
+#[test]
+fn test_remote_config() {
+    let config = Config::from_toml("[remote]\nssh_command = \"ssh -F cfg\"\n").unwrap();
+    assert_eq!(config.remote.ssh_command, "ssh -F cfg");
+    assert_eq!(config.remote.connect_timeout_secs, 10);
+}
+
+#[cfg(unix)]
+#[test]
+fn test_read_and_upload() {
+    let dir = temp_dir("upload");
+    let file = dir.join("host").join("notes.md");
+    let ssh = fake_ssh(&dir);
+    let path = url(&file);
+
+    assert_eq!(ssh.read(&path).unwrap(), "");
+    ssh.write(&path, "first").unwrap();
+    wait_for(|| fs::read_to_string(&file).ok().as_deref() == Some("first"));
+
+    fs::write(&file, "changed on host").unwrap();
+    assert_eq!(ssh.read(&path).unwrap(), "changed on host");
+    ssh.write(&path, "second").unwrap();
+    wait_for(|| fs::read_to_string(&file).unwrap() == "second" && ssh.status().is_none());
+}
+
+#[cfg(unix)]
+#[test]
+fn test_conflict_and_overwrite() {
+    let dir = temp_dir("conflict");
+    let file = dir.join("notes.md");
+    fs::write(&file, "original").unwrap();
+    let ssh = fake_ssh(&dir);
+    let path = url(&file);
+
+    assert_eq!(ssh.read(&path).unwrap(), "original");
+    fs::write(&file, "edited elsewhere").unwrap();
+    ssh.write(&path, "mine").unwrap();
+    wait_for(|| matches!(ssh.status(), Some(TransferStatus::Conflict { .. })));
+    assert_eq!(fs::read_to_string(&file).unwrap(), "edited elsewhere");
+
+    ssh.overwrite(&path, "mine").unwrap();
+    wait_for(|| fs::read_to_string(&file).unwrap() == "mine");
+}
+
+#[cfg(unix)]
+#[test]
+fn test_offline_edits_upload_later() {
+    let dir = temp_dir("offline");
+    let file = dir.join("notes.md");
+    fs::write(&file, "cached").unwrap();
+    let path = url(&file);
+    let ssh = fake_ssh(&dir);
+    assert_eq!(ssh.read(&path).unwrap(), "cached");
+
+    fs::write(dir.join("offline"), "").unwrap();
+    ssh.write(&path, "written offline").unwrap();
+    wait_for(|| matches!(ssh.status(), Some(TransferStatus::Offline { .. })));
+    assert_eq!(ssh.read(&path).unwrap(), "written offline");
+    drop(ssh);
+
+    fs::remove_file(dir.join("offline")).unwrap();
+    let ssh = fake_ssh(&dir);
+    assert_eq!(ssh.read(&path).unwrap(), "written offline");
+    wait_for(|| fs::read_to_string(&file).unwrap() == "written offline");
+}
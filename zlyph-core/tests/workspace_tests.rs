@@ -0,0 +1,183 @@
+mod common;
+
+use common::temp_dir;
+use std::fs;
+use std::path::PathBuf;
+use zlyph_core::config::ZoomScope;
+use zlyph_core::quick_switch::QuickSwitch;
+use zlyph_core::workspace::{FileState, WorkspaceState};
+use zlyph_core::BufferPosition;
+
+#[test]
+fn test_update_file_keeps_other_entries() {
+    let dir = temp_dir("entries");
+    let state_path = dir.join("workspace.toml");
+    let (notes, todo) = (dir.join("notes.md"), dir.join("todo.md"));
+
+    assert_eq!(
+        WorkspaceState::load_from_file(&state_path).unwrap(),
+        WorkspaceState::default()
+    );
+
+    WorkspaceState::update_file_in(&state_path, &notes, |f| f.scroll_line = 12).unwrap();
+    WorkspaceState::update_file_in(&state_path, &todo, |f| f.scroll_line = 3).unwrap();
+    WorkspaceState::update_file_in(&state_path, &notes, |f| f.scroll_line += 1).unwrap();
+
+    let state = WorkspaceState::load_from_file(&state_path).unwrap();
+    assert_eq!(
+        state.file(&notes),
+        FileState {
+            scroll_line: 13,
+            ..FileState::default()
+        }
+    );
+    assert_eq!(
+        state.file(&todo),
+        FileState {
+            scroll_line: 3,
+            ..FileState::default()
+        }
+    );
+    assert_eq!(state.file(&dir.join("other.md")), FileState::default());
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_recent_files_most_recent_first() {
+    let dir = temp_dir("recent");
+    let state_path = dir.join("workspace.toml");
+    let (notes, todo, gone) = (
+        dir.join("notes.md"),
+        dir.join("todo.md"),
+        dir.join("gone.md"),
+    );
+    fs::write(&notes, "").unwrap();
+    fs::write(&todo, "").unwrap();
+
+    WorkspaceState::add_recent_in(&state_path, &notes).unwrap();
+    WorkspaceState::add_recent_in(&state_path, &gone).unwrap();
+    WorkspaceState::add_recent_in(&state_path, &todo).unwrap();
+    WorkspaceState::add_recent_in(&state_path, &notes).unwrap();
+    WorkspaceState::update_file_in(&state_path, &notes, |f| {
+        f.cursor_row = 4;
+        f.cursor_column = 2;
+    })
+    .unwrap();
+
+    let state = WorkspaceState::load_from_file(&state_path).unwrap();
+    assert_eq!(state.recent.len(), 3);
+    let recent: Vec<_> = state
+        .recent_files()
+        .iter()
+        .map(|path| path.file_name().unwrap().to_owned())
+        .collect();
+    assert_eq!(recent, ["notes.md", "todo.md"]);
+    assert_eq!(state.file(&notes).cursor_row, 4);
+    assert_eq!(state.file(&notes).cursor_column, 2);
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_searches_most_recent_first() {
+    let dir = temp_dir("searches");
+    let state_path = dir.join("workspace.toml");
+
+    WorkspaceState::add_search_in(&state_path, "todo").unwrap();
+    WorkspaceState::add_search_in(&state_path, "fixme").unwrap();
+    WorkspaceState::add_search_in(&state_path, "todo").unwrap();
+
+    let state = WorkspaceState::load_from_file(&state_path).unwrap();
+    assert_eq!(state.searches, ["todo", "fixme"]);
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_quick_switch_filters_by_every_word() {
+    let mut switch = QuickSwitch::new(vec![
+        PathBuf::from("/notes/daily/2024-01-02.md"),
+        PathBuf::from("/notes/ideas.md"),
+        PathBuf::from("/code/zlyph/README.md"),
+    ]);
+    assert_eq!(switch.matches().len(), 3);
+
+    switch.type_text("NOTES md");
+    assert_eq!(switch.matches().len(), 2);
+    switch.select_next();
+    switch.select_next();
+    assert_eq!(
+        switch.selected_path(),
+        Some(PathBuf::from("/notes/ideas.md"))
+    );
+
+    switch.type_text(" daily");
+    assert_eq!(switch.selected, 0);
+    assert_eq!(
+        switch.selected_path(),
+        Some(PathBuf::from("/notes/daily/2024-01-02.md"))
+    );
+}
+
+#[test]
+fn test_zoom_per_file_or_shared() {
+    let dir = temp_dir("zoom");
+    let state_path = dir.join("workspace.toml");
+    let (notes, todo) = (dir.join("notes.md"), dir.join("todo.md"));
+
+    WorkspaceState::store_font_size_in(&state_path, Some(&notes), ZoomScope::Buffer, 20.0).unwrap();
+    WorkspaceState::store_font_size_in(&state_path, None, ZoomScope::Global, 30.0).unwrap();
+    WorkspaceState::update_file_in(&state_path, &notes, |f| f.scroll_line = 4).unwrap();
+
+    let state = WorkspaceState::load_from_file(&state_path).unwrap();
+    assert_eq!(state.font_size(Some(&notes), ZoomScope::Buffer), Some(20.0));
+    assert_eq!(state.font_size(Some(&todo), ZoomScope::Buffer), None);
+    assert_eq!(state.font_size(None, ZoomScope::Buffer), None);
+    assert_eq!(state.font_size(Some(&todo), ZoomScope::Global), Some(30.0));
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_selection_is_kept_with_the_cursor() {
+    let dir = temp_dir("selection");
+    let state_path = dir.join("workspace.toml");
+    let notes = dir.join("notes.md");
+
+    WorkspaceState::update_file_in(&state_path, &notes, |f| {
+        f.cursor_row = 4;
+        f.cursor_column = 2;
+        f.selection_anchor = Some(BufferPosition::new(1, 0));
+    })
+    .unwrap();
+    let state = WorkspaceState::load_from_file(&state_path).unwrap();
+    assert_eq!(
+        state.file(&notes).selection_anchor,
+        Some(BufferPosition::new(1, 0))
+    );
+
+    // Leaving the file without a selection forgets it
+    WorkspaceState::update_file_in(&state_path, &notes, |f| f.selection_anchor = None).unwrap();
+    let state = WorkspaceState::load_from_file(&state_path).unwrap();
+    assert_eq!(state.file(&notes).selection_anchor, None);
+    assert_eq!(state.file(&notes).cursor_row, 4);
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_bookmarks_are_kept_per_file() {
+    let dir = temp_dir("bookmarks");
+    let state_path = dir.join("workspace.toml");
+    let (notes, todo) = (dir.join("notes.md"), dir.join("todo.md"));
+
+    WorkspaceState::update_file_in(&state_path, &notes, |f| f.bookmarks = vec![2, 7]).unwrap();
+    WorkspaceState::update_file_in(&state_path, &notes, |f| f.scroll_line = 1).unwrap();
+
+    let state = WorkspaceState::load_from_file(&state_path).unwrap();
+    assert_eq!(state.file(&notes).bookmarks, [2, 7]);
+    assert!(state.file(&todo).bookmarks.is_empty());
+
+    fs::remove_dir_all(&dir).unwrap();
+}
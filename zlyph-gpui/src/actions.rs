@@ -1,4 +1,6 @@
-use gpui::actions;
+use gpui::{actions, Action, KeyBinding};
+use std::path::PathBuf;
+use zlyph_core::transform::Transform;
 
 actions!(
     editor,
@@ -8,6 +10,7 @@ actions!(
         ResetFontSize,
         Copy,
         Paste,
+        PastePlain,
         Cut,
         Undo,
         Redo,
@@ -26,15 +29,230 @@ actions!(
         MoveDown,
         MoveWordLeft,
         MoveWordRight,
+        MoveToNextSentence,
+        MoveToPrevSentence,
+        JumpToChar,
+        JumpBeforeChar,
+        Leap,
         MoveLineUp,
         MoveLineDown,
+        MoveSectionUp,
+        MoveSectionDown,
+        PromoteSection,
+        DemoteSection,
+        SelectSection,
+        InsertFootnote,
+        RenumberFootnotes,
+        GoToFootnote,
+        InsertBibliography,
         SelectLeft,
         SelectRight,
         SelectUp,
         SelectDown,
         SelectWordLeft,
         SelectWordRight,
+        SelectLine,
+        SelectSentence,
+        SelectParagraph,
+        SelectToTop,
+        SelectToBottom,
+        SelectToLineStart,
+        SelectToLineEnd,
+        ExpandSelection,
+        ShrinkSelection,
+        SelectParent,
+        SelectNextSibling,
         Tab,
         Outdent,
+        TriggerCompletion,
+        Cancel,
+        Hover,
+        GoToDefinition,
+        FindReferences,
+        NextMatch,
+        PrevMatch,
+        SelectAllMatches,
+        NextDiagnostic,
+        PrevDiagnostic,
+        NextChange,
+        PrevChange,
+        PreviewHunk,
+        RevertHunk,
+        StageHunk,
+        DiffWithSaved,
+        DiffWithHead,
+        DiffWithFile,
+        ShowLocalHistory,
+        FollowLink,
+        ToggleBacklinks,
+        ToggleBookmark,
+        NextBookmark,
+        PrevBookmark,
+        ShowBookmarks,
+        OpenDailyNote,
+        PreviousDailyNote,
+        NextDailyNote,
+        NewFromTemplate,
+        ShowTags,
+        ShowTodos,
+        ToggleOutline,
+        GoToSymbol,
+        SearchNotes,
+        InsertDate,
+        InsertTime,
+        InsertTimestamp,
+        ShowCharacterPicker,
+        InsertUnicodeCodepoint,
+        ToggleDiagnosticsPanel,
+        QuickFix,
+        StripInvisibles,
+        EvaluateSelection,
+        AlignOn,
+        FormatJson,
+        FormatToml,
+        FormatYaml,
+        MinifyJson,
+        SortKeys,
+        IncrementNumber,
+        DecrementNumber,
+        NumberSequence,
+        RunShellCommand,
+        FilterThroughCommand,
+        FormatTable,
+        InsertTableRow,
+        DeleteTableRow,
+        InsertTableColumn,
+        DeleteTableColumn,
+        ToggleWritingSession,
+        ToggleDictation,
+        ToggleFocusMode,
+        ToggleSentenceFocus,
+        ToggleMinimap,
+        ToggleBreadcrumbs,
+        ToggleElasticTabstops,
+        ToggleRainbowBrackets,
+        ToggleFullscreen,
+        ToggleTitleBar,
+        ToggleBlur,
+        IncreaseOpacity,
+        DecreaseOpacity,
+        OpenFile,
+        SaveAs,
+        ExportHtml,
+        ExportPdf,
+        ExportImage,
+        Print,
+        ToggleTerminal,
+        SendToTerminal,
+        CaptureTerminal,
+        RunTask,
+        ShowErrors,
+        NextError,
+        PreviousError,
+        OverwriteRemote,
+        NewFile,
+        OpenRecent,
+        SwitchProject,
+        ShowCommandLine,
+        FindInFiles,
+        ReplaceInFiles,
+        ToggleReplaceHunk,
+        ApplyReplace,
+        UndoReplaceInFile,
+        ToggleWholeWord,
+        TogglePreserveCase,
+        ToggleFileTree,
+        FocusFileTree,
+        TreeNewFile,
+        TreeNewFolder,
+        TreeRename,
+        TreeMove,
+        TreeDelete,
+        Quit,
+        Hide,
+        HideOthers,
+        ShowAll,
+        OpenHelp,
     ]
 );
+
+/// Open one of the recent files listed in the dock menu
+#[derive(Clone, PartialEq, Debug, Action)]
+#[action(namespace = editor, no_json)]
+pub struct OpenRecentFile {
+    pub path: PathBuf,
+}
+
+/// Encode, decode, dump or hash the selection, as picked from the Edit >
+/// Transform menu
+#[derive(Clone, PartialEq, Debug, Action)]
+#[action(namespace = editor, no_json)]
+pub struct TransformSelection {
+    pub transform: Transform,
+}
+
+/// Keys for moving, selecting and editing text, which an app embedding the
+/// editor binds with `cx.bind_keys(editing_bindings())`. The zlyph app binds
+/// these along with its own.
+pub fn editing_bindings() -> Vec<KeyBinding> {
+    vec![
+        KeyBinding::new("cmd-a", SelectAll, None),
+        KeyBinding::new("cmd-c", Copy, None),
+        KeyBinding::new("cmd-x", Cut, None),
+        KeyBinding::new("cmd-v", Paste, None),
+        KeyBinding::new("alt-cmd-shift-v", PastePlain, None),
+        KeyBinding::new("cmd-z", Undo, None),
+        KeyBinding::new("cmd-shift-z", Redo, None),
+        KeyBinding::new("cmd-shift-k", DeleteLine, None),
+        KeyBinding::new("tab", Tab, None),
+        KeyBinding::new("shift-tab", Outdent, None),
+        KeyBinding::new("escape", Cancel, None),
+        KeyBinding::new("enter", Newline, None),
+        KeyBinding::new("backspace", Backspace, None),
+        KeyBinding::new("delete", Delete, None),
+        KeyBinding::new("cmd-backspace", DeleteToBeginningOfLine, None),
+        KeyBinding::new("cmd-delete", DeleteToEndOfLine, None),
+        KeyBinding::new("cmd-left", MoveToBeginningOfLine, None),
+        KeyBinding::new("cmd-right", MoveToEndOfLine, None),
+        KeyBinding::new("left", MoveLeft, None),
+        KeyBinding::new("right", MoveRight, None),
+        KeyBinding::new("up", MoveUp, None),
+        KeyBinding::new("down", MoveDown, None),
+        KeyBinding::new("shift-left", SelectLeft, None),
+        KeyBinding::new("shift-right", SelectRight, None),
+        KeyBinding::new("shift-up", SelectUp, None),
+        KeyBinding::new("shift-down", SelectDown, None),
+        KeyBinding::new("alt-left", MoveWordLeft, None),
+        KeyBinding::new("alt-right", MoveWordRight, None),
+        KeyBinding::new("ctrl-alt-e", MoveToNextSentence, None),
+        KeyBinding::new("ctrl-alt-a", MoveToPrevSentence, None),
+        KeyBinding::new("ctrl-f", JumpToChar, None),
+        KeyBinding::new("ctrl-t", JumpBeforeChar, None),
+        KeyBinding::new("ctrl-s", Leap, None),
+        KeyBinding::new("alt-shift-left", SelectWordLeft, None),
+        KeyBinding::new("alt-shift-right", SelectWordRight, None),
+        KeyBinding::new("cmd-l", SelectLine, None),
+        KeyBinding::new("cmd-shift-l", SelectParagraph, None),
+        KeyBinding::new("ctrl-alt-s", SelectSentence, None),
+        KeyBinding::new("cmd-shift-up", SelectToTop, None),
+        KeyBinding::new("cmd-shift-down", SelectToBottom, None),
+        KeyBinding::new("cmd-shift-left", SelectToLineStart, None),
+        KeyBinding::new("cmd-shift-right", SelectToLineEnd, None),
+        KeyBinding::new("shift-home", SelectToLineStart, None),
+        KeyBinding::new("shift-end", SelectToLineEnd, None),
+        KeyBinding::new("ctrl-w", ExpandSelection, None),
+        KeyBinding::new("ctrl-shift-w", ShrinkSelection, None),
+        KeyBinding::new("ctrl-alt-up", SelectParent, None),
+        KeyBinding::new("ctrl-alt-right", SelectNextSibling, None),
+        KeyBinding::new("alt-up", MoveLineUp, None),
+        KeyBinding::new("alt-down", MoveLineDown, None),
+        KeyBinding::new("ctrl-cmd-up", MoveSectionUp, None),
+        KeyBinding::new("ctrl-cmd-down", MoveSectionDown, None),
+        KeyBinding::new("ctrl-cmd-left", PromoteSection, None),
+        KeyBinding::new("ctrl-cmd-right", DemoteSection, None),
+        KeyBinding::new("ctrl-cmd-h", SelectSection, None),
+        KeyBinding::new("ctrl-alt-f", InsertFootnote, None),
+        KeyBinding::new("ctrl-alt-g", GoToFootnote, None),
+        KeyBinding::new("ctrl-alt-b", InsertBibliography, None),
+    ]
+}
@@ -1,565 +1,7195 @@
 use crate::actions::*;
-use crate::text_buffer::{BufferPosition, TextBuffer, WrapType};
+use crate::menus;
+use crate::text_buffer::{
+    shape_text, BidiSegment, BufferPosition, TextBuffer, WrapType, DEFAULT_FONT_FAMILY,
+};
 use crate::theme::Theme;
+use crate::window_state::{self, WindowGeometry};
 use gpui::prelude::*;
 use gpui::*;
-use std::time::{Duration, Instant};
-use zlyph_core::{EditorAction, EditorEngine};
+use std::cell::Cell;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use std::sync::mpsc::{self, Receiver};
+use std::sync::Arc;
+use std::time::{Duration, Instant, SystemTime};
+use zlyph_core::accessibility::Announcer;
+use zlyph_core::bookmarks::BookmarksPanel;
+use zlyph_core::char_picker::CharPicker;
+#[cfg(feature = "code-image")]
+use zlyph_core::code_image;
+use zlyph_core::composition::{self, Composition};
+use zlyph_core::config::{
+    CursorConfig, FocusScope, FontConfig, ScrollConfig, WindowConfig, ZoomScope,
+};
+use zlyph_core::context_menu::{self, ContextMenu, MenuItem};
+use zlyph_core::decorations::{Decoration, LineDecoration};
+use zlyph_core::diff::{ChangeKind, Hunk};
+use zlyph_core::diff_view::{DiffRow, DiffSource, DiffView};
+use zlyph_core::elastic_tabstops::{self, TabPadding};
+use zlyph_core::encryption;
+use zlyph_core::ex;
+use zlyph_core::export::{self, HtmlOptions};
+use zlyph_core::file_tree::FileTree;
+use zlyph_core::git::GitGutter;
+use zlyph_core::highlight::{Highlighter, LinkTarget};
+use zlyph_core::history::{HistoryBrowser, LocalHistory};
+use zlyph_core::hooks::{HookEvent, HookRegistry};
+use zlyph_core::indent_guides::IndentGuides;
+use zlyph_core::instance::InstanceListener;
+use zlyph_core::journal::Journal;
+use zlyph_core::links;
+#[cfg(feature = "live-sync")]
+use zlyph_core::live_sync::LiveSync;
+use zlyph_core::multi_cursor;
+use zlyph_core::note_index::NoteSearch;
+use zlyph_core::notes::{self, BacklinksPanel};
+use zlyph_core::pdf;
+use zlyph_core::project;
+use zlyph_core::project_search::{self, ProjectSearch};
+use zlyph_core::quick_switch::{self, QuickSwitch};
+use zlyph_core::search_panel::{SearchPanel, SearchRow};
+use zlyph_core::sentences;
+use zlyph_core::symbols::{self, Symbol, SymbolPanel};
+use zlyph_core::tags::TagIndex;
+use zlyph_core::task_runner::{self, Problem, TaskRun};
+use zlyph_core::templates::{self, TemplatePicker};
+#[cfg(feature = "terminal")]
+use zlyph_core::terminal::{Terminal, TerminalKey};
+use zlyph_core::todos::{Kind, TodoPanel};
+use zlyph_core::vfs::{self, TransferStatus};
+use zlyph_core::workspace::WorkspaceState;
+use zlyph_core::{text_objects, ChangeEvent, Config, EditorAction, EditorEngine};
+use zlyph_languagetool::GrammarChecker;
+use zlyph_lsp::protocol::Location;
+use zlyph_lsp::LspManager;
 
+/// How often language server and grammar checker results are collected
+const BACKGROUND_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// How often the terminal panel is redrawn while its shell is running, so
+/// what is typed echoes promptly
+#[cfg(feature = "terminal")]
+const TERMINAL_POLL_INTERVAL: Duration = Duration::from_millis(16);
+#[cfg(feature = "terminal")]
+const TERMINAL_FONT_SIZE: f32 = 13.0;
+
+/// Pause in editing a large file before it is saved
+const LARGE_FILE_SAVE_DELAY: Duration = Duration::from_secs(2);
+
+/// Space above the first line, clear of the title bar
+const PADDING_TOP: f32 = 40.0;
+
+/// Where the breadcrumb trail starts at the least, clear of the traffic
+/// lights
+const BREADCRUMB_LEFT: f32 = 80.0;
+
+/// Space around the text of an editor embedded in another app, and its
+/// font size until the app sets one
+const EMBEDDED_PADDING: f32 = 4.0;
+const EMBEDDED_FONT_SIZE: f32 = 16.0;
+
+/// Lines laid out above and below the window, so short scrolls don't wait
+/// on shaping
+const OVERSCAN_ROWS: usize = 20;
+
+/// Time constant of the smooth scrolling ease-out
+const SCROLL_EASING: Duration = Duration::from_millis(60);
+
+/// Width of the minimap and the height it gives each buffer line
+const MINIMAP_WIDTH: f32 = 100.0;
+const MINIMAP_LINE_HEIGHT: f32 = 3.0;
+
+/// Trackpad scrolling with Ctrl held that makes one zoom step
+const ZOOM_PIXELS_PER_STEP: f32 = 40.0;
+
+/// How long the trail left by a jumping cursor takes to fade
+const SMEAR_DURATION: Duration = Duration::from_millis(120);
+
+/// Rows of find-in-files results shown at once
+const SEARCH_PANEL_ROWS: usize = 14;
+
+/// Rows of the diff view shown at once
+const DIFF_VIEW_ROWS: usize = 40;
+
+/// Width of the file tree sidebar and the height of each of its rows
+const SIDEBAR_WIDTH: f32 = 220.0;
+const SIDEBAR_ROW_HEIGHT: f32 = 22.0;
+
+const OPACITY_STEP: f32 = 0.05;
+const MIN_OPACITY: f32 = 0.2;
+
+/// The zlyph editor: the app's whole window, or a text input or editor
+/// embedded in another GPUI app
 pub struct TextEditor {
     engine: EditorEngine,
     buffer: TextBuffer,
     focus_handle: FocusHandle,
     theme: Theme,
     is_dragging: bool,
+    /// Where the selection being dragged would be dropped
+    text_drag: Option<BufferPosition>,
+    /// The row and range of the link under the mouse while Cmd/Ctrl is held
+    hovered_link: Option<(usize, Range<usize>)>,
     last_click_time: Option<Instant>,
     last_click_position: Option<BufferPosition>,
-    file_path: std::path::PathBuf,
+    /// None for an untitled buffer that has not been saved yet
+    file_path: Option<PathBuf>,
     last_modified: Option<std::time::SystemTime>,
+    /// Edits not written to disk: an untitled buffer, or a save that failed
+    dirty: bool,
+    /// Title last given to the window
+    window_title: String,
+    /// Scroll position currently drawn, easing toward `scroll_target`
     scroll_offset: f32,
+    scroll_target: f32,
+    /// The line at the top of the view and the visual row it started on
+    /// when last drawn, to keep it in place as lines above rewrap
+    scroll_anchor: Option<(usize, usize)>,
+    last_scroll_frame: Option<Instant>,
+    /// Top line last written to the workspace state
+    saved_scroll_line: usize,
+    /// Cursor position and selection anchor last written to the workspace
+    /// state
+    saved_cursor: BufferPosition,
+    saved_anchor: Option<zlyph_core::BufferPosition>,
+    /// Bookmarked rows last written to the workspace state
+    saved_bookmarks: Vec<usize>,
+    scroll_config: ScrollConfig,
+    lsp: LspManager,
+    grammar: Option<GrammarChecker>,
+    /// Writing session status last drawn, so timed sessions redraw once a second
+    session_status: Option<String>,
+    /// Upload progress or trouble for a remote file
+    transfer_status: Option<TransferStatus>,
+    /// Saves reported by the engine, which writes files in the background
+    file_events: Receiver<ChangeEvent>,
+    /// Progress of a background save, for the status bar
+    file_progress: Option<String>,
+    /// Typewriter scrolling, dimmed surroundings and no chrome
+    focus_mode: bool,
+    /// Whether focus mode dims all but the cursor's paragraph or sentence
+    focus_scope: FocusScope,
+    minimap: bool,
+    /// The file and the sections or definitions the cursor is in, above the
+    /// text
+    breadcrumbs: bool,
+    /// Line up tab-separated columns across adjacent lines
+    elastic_tabstops: bool,
+    /// Color bracket pairs by depth and shade the text inside them
+    rainbow_brackets: bool,
+    /// Padding after each tab while elastic tabstops are on
+    tab_layout: Option<TabLayout>,
+    /// Scrolling by dragging in the minimap rather than selecting text
+    minimap_dragging: bool,
+    /// Whether zoom is remembered for each file or for all of them
+    zoom_scope: ZoomScope,
+    /// Trackpad scrolling with Ctrl held, not yet enough for a zoom step
+    zoom_pixels: f32,
+    /// Height of the window's content area as of the last render
+    viewport_height: f32,
+    window_config: WindowConfig,
+    cursor_config: CursorConfig,
+    /// Cursor position as of the last render and when it last moved, for blinking
+    last_cursor: BufferPosition,
+    last_cursor_move: Instant,
+    cursor_drawn_visible: bool,
+    /// (row, segment start, x) of the cursor as last drawn, and the trail being faded
+    last_cursor_x: Cell<Option<(usize, usize, Pixels)>>,
+    smear: Cell<Option<CursorSmear>>,
+    /// Find in files; kept while hidden so reopening shows the last results
+    search_panel: Option<SearchPanel>,
+    search_visible: bool,
+    /// A name whose definitions are being found without a language server,
+    /// to jump to once they are in if there is only one
+    definition_pending: Option<String>,
+    /// Recent files list; takes all key input while shown
+    quick_switch: Option<QuickSwitch>,
+    /// Files sent by later launches, while this is the single instance
+    instance: Option<InstanceListener>,
+    /// Files the system asks to open, as with Open With in the Finder
+    system_opens: Receiver<PathBuf>,
+    /// File explorer on the left, built when first shown
+    file_tree: Option<FileTree>,
+    tree_visible: bool,
+    /// Whether keys go to the file tree rather than the buffer
+    tree_focused: bool,
+    /// Commands the config attaches to editor events
+    hooks: HookRegistry,
+    /// Engine revision as of the last edit reported to the hooks
+    hooks_revision: u64,
+    /// When a large file's edits are next saved, as it isn't saved on
+    /// every keystroke
+    save_due: Option<Instant>,
+    /// Lines changed since the file's last commit
+    git_gutter: GitGutter,
+    /// Markdown styling of the buffer's text
+    highlighter: Highlighter,
+    /// Indent levels of the buffer's lines, for the guides drawn in them
+    indent_guides: IndentGuides,
+    /// Comparison with another version of the file; takes all key input while shown
+    diff_view: Option<DiffView>,
+    /// Snapshots of edited files
+    history: LocalHistory,
+    /// The current file's snapshots; takes all key input while shown
+    history_browser: Option<HistoryBrowser>,
+    /// Notes linking to the current one; takes all key input while shown
+    backlinks: Option<BacklinksPanel>,
+    /// The current file's bookmarks; takes all key input while shown
+    bookmarks: Option<BookmarksPanel>,
+    /// Tags used in the notes directory; takes all key input while shown
+    tags: Option<TagIndex>,
+    /// TODOs and open tasks in the file or the notes; takes all key input
+    /// while shown
+    todos: Option<TodoPanel>,
+    /// The last build or lint task run, and the errors it printed
+    task_run: Option<TaskRun>,
+    /// Whether the task's errors are listed; the list takes all key input
+    /// while shown
+    errors_visible: bool,
+    /// Functions, types or headings of the buffer down the right edge
+    outline: Option<SymbolPanel>,
+    /// Whether keys go to the outline rather than the buffer
+    outline_focused: bool,
+    /// Symbols to jump to by name; takes all key input while shown
+    symbol_palette: Option<SymbolPanel>,
+    /// Engine revision the outline, palette and breadcrumbs were last read at
+    symbols_revision: u64,
+    /// The buffer's symbols, for the breadcrumb trail
+    symbols: Vec<Symbol>,
+    /// Whether the symbols came from the language server, which the ones
+    /// read from the lines then don't replace
+    server_symbols: bool,
+    /// Full-text search of the notes directory, kept after it is hidden so
+    /// edits keep its index current; takes all key input while shown
+    note_search: Option<NoteSearch>,
+    note_search_visible: bool,
+    /// Templates to create a note from; takes all key input while shown
+    template_picker: Option<TemplatePicker>,
+    /// Unicode characters and emoji to insert; takes all key input while shown
+    char_picker: Option<CharPicker>,
+    /// Edits shared with other instances open on the same file, and the
+    /// engine revision last shared
+    #[cfg(feature = "live-sync")]
+    live_sync: Option<LiveSync>,
+    #[cfg(feature = "live-sync")]
+    sync_revision: u64,
+    /// Asks for an encrypted file's passphrase; takes all key input while shown
+    passphrase_prompt: Option<PassphrasePrompt>,
+    /// Ex command being typed after `:`, such as `w` or `%s/foo/bar/g`
+    command_line: Option<String>,
+    /// Text an input method is still composing, shown at the cursor
+    composition: Option<Composition>,
+    /// Where the cursor was last drawn in the window, for placing the input
+    /// method's candidate window
+    ime_cursor: Cell<Option<Bounds<Pixels>>>,
+    /// Speaks cursor movement and what is shown, for screen reader users
+    announcer: Announcer,
+    /// File last put at the top of the dock menu's recent files
+    dock_file: Option<PathBuf>,
+    /// Right-click menu and where it was opened
+    context_menu: Option<ContextMenu>,
+    context_menu_position: Point<Pixels>,
+    /// Address the context menu searches the selection at
+    search_url: String,
+    /// Font settings, applied to the buffer at the next render once changed
+    font_config: FontConfig,
+    font_applied: bool,
+    /// When the config file was last read, to pick up font changes
+    config_modified: Option<SystemTime>,
+    /// Editing a string inside another app rather than a file in its own
+    /// window: no file, panels, status bar or window chrome
+    embedded: bool,
+    /// Shown while the buffer is empty
+    placeholder: SharedString,
+    /// Where the editor was last laid out in the window, for placing clicks
+    /// when embedded
+    bounds: Rc<Cell<Option<Bounds<Pixels>>>>,
+    /// Engine revision last reported with [`EditorEvent::Changed`]
+    changed_revision: u64,
+    /// The shell in the panel along the bottom, kept running while hidden
+    #[cfg(feature = "terminal")]
+    terminal: Option<Terminal>,
+    #[cfg(feature = "terminal")]
+    terminal_visible: bool,
+    /// Whether keys go to the terminal rather than the buffer
+    #[cfg(feature = "terminal")]
+    terminal_focused: bool,
+    /// Hands keystrokes to the terminal while it has focus, before the key
+    /// bindings see them
+    #[cfg(feature = "terminal")]
+    _terminal_keys: Subscription,
 }
 
-impl TextEditor {
-    pub fn new(file_path: std::path::PathBuf, cx: &mut Context<Self>) -> Self {
-        let mut engine = EditorEngine::new();
-
-        // Ensure parent directory exists
-        if let Some(parent) = file_path.parent() {
-            let _ = std::fs::create_dir_all(parent);
-        }
+/// What a [`TextEditor`] tells the views subscribed to it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EditorEvent {
+    /// The text was edited; [`TextEditor::text`] has the new text
+    Changed,
+    /// Enter was pressed in a single-line editor
+    Submitted,
+}
 
-        // Load existing file if it exists
-        let last_modified = if file_path.exists() {
-            let _ = engine.load_from_file(&file_path);
-            std::fs::metadata(&file_path).ok().and_then(|m| m.modified().ok())
-        } else {
-            None
-        };
+impl EventEmitter<EditorEvent> for TextEditor {}
 
-        let buffer = TextBuffer::from_string(engine.state().to_string());
+/// The passphrase being typed for an encrypted file
+struct PassphrasePrompt {
+    path: PathBuf,
+    input: String,
+    /// The passphrase typed first when choosing one for a new file, to be
+    /// typed again
+    first: Option<String>,
+    error: Option<String>,
+}
 
+impl PassphrasePrompt {
+    fn new(path: PathBuf) -> Self {
         Self {
-            engine,
-            buffer,
-            focus_handle: cx.focus_handle(),
-            theme: Theme::default(),
-            is_dragging: false,
-            last_click_time: None,
-            last_click_position: None,
-            file_path,
-            last_modified,
-            scroll_offset: 0.0,
+            path,
+            input: String::new(),
+            first: None,
+            error: None,
         }
     }
 
-    fn sync_buffer_from_engine(&mut self) {
-        let state = self.engine.state();
-        self.buffer = TextBuffer::from_string(state.to_string());
+    /// A file not created yet gets a new passphrase, typed twice
+    fn is_new(&self) -> bool {
+        !self.path.exists() && !vfs::is_remote(&self.path)
     }
+}
 
-    fn save_to_file(&self) {
-        let _ = self.engine.save_to_file(&self.file_path);
-    }
+/// Keys the file tree or find-in-files panel takes while it has focus
+enum PanelKey {
+    Text(String),
+    Backspace,
+    Enter,
+    Tab,
+    Up,
+    Down,
+    Left,
+    Right,
+    Escape,
+}
 
-    fn sync_and_save(&mut self) {
-        self.sync_buffer_from_engine();
-        self.save_to_file();
-        // Update last modified time after save
-        if let Ok(metadata) = std::fs::metadata(&self.file_path) {
-            if let Ok(modified) = metadata.modified() {
-                self.last_modified = Some(modified);
-            }
-        }
-        self.ensure_cursor_visible();
-    }
+/// How far the cell after each tab is padded with elastic tabstops on, as
+/// worked out for a revision of the text at a font size
+struct TabLayout {
+    revision: u64,
+    font_size: Pixels,
+    space_width: Pixels,
+    padding: Vec<Vec<TabPadding>>,
+}
 
-    fn ensure_cursor_visible(&mut self) {
-        let line_height = self.get_font_size() * 1.5;
-        let cursor_row = self.get_cursor().row as f32;
-        let cursor_y = cursor_row * line_height;
+#[derive(Clone, Copy)]
+struct CursorSmear {
+    row: usize,
+    segment: usize,
+    from_x: Pixels,
+    started: Instant,
+}
 
-        // Assume visible height is roughly 600px minus padding
-        let visible_height = 500.0;
-        let padding = 40.0;
+impl TextEditor {
+    /// The zlyph app's window, editing `file_path`
+    pub fn new(
+        file_path: std::path::PathBuf,
+        instance: Option<InstanceListener>,
+        system_opens: Receiver<PathBuf>,
+        window: &mut Window,
+        cx: &mut Context<Self>,
+    ) -> Self {
+        let mut engine = EditorEngine::new();
+        engine.set_config(project::config(&file_path).unwrap_or_default());
+        let passphrase_prompt = (engine.needs_passphrase(&file_path)
+            && !engine.unlock_from_keychain(&file_path))
+        .then(|| PassphrasePrompt::new(file_path.clone()));
 
-        // Scroll up if cursor is above visible area
-        if cursor_y < self.scroll_offset + padding {
-            self.scroll_offset = (cursor_y - padding).max(0.0);
+        // Ensure parent directory exists
+        if let Some(parent) = file_path.parent().filter(|_| !vfs::is_remote(&file_path)) {
+            let _ = std::fs::create_dir_all(parent);
         }
 
-        // Scroll down if cursor is below visible area
-        if cursor_y > self.scroll_offset + visible_height - padding {
-            self.scroll_offset = cursor_y - visible_height + padding;
+        // Load existing file if it exists
+        let last_modified = if file_path.exists() || vfs::is_remote(&file_path) {
+            let _ = engine.load_from_file(&file_path);
+            std::fs::metadata(&file_path)
+                .ok()
+                .and_then(|m| m.modified().ok())
+        } else {
+            None
+        };
+
+        let mut editor = Self::with_engine(engine, false, cx);
+        editor.lsp.open(&file_path, &editor.engine);
+        let _ = editor.hooks.fire(HookEvent::Open, &file_path);
+        if editor.engine.config().goal.start_on_launch {
+            editor.engine.start_writing_session();
         }
-    }
+        editor.file_path = Some(file_path);
+        editor.last_modified = last_modified;
+        if editor.breadcrumbs_shown() {
+            editor.refresh_symbols();
+        }
+        editor.passphrase_prompt = passphrase_prompt;
+        editor.instance = instance;
+        editor.system_opens = system_opens;
+        editor.placeholder = "Start typing...".into();
 
-    fn check_and_reload(&mut self, cx: &mut Context<Self>) {
-        if let Ok(metadata) = std::fs::metadata(&self.file_path) {
-            if let Ok(modified) = metadata.modified() {
-                if self.last_modified.map_or(true, |last| modified > last) {
-                    if self.engine.load_from_file(&self.file_path).is_ok() {
-                        self.last_modified = Some(modified);
-                        self.sync_buffer_from_engine();
-                        cx.notify();
-                    }
+        cx.observe_window_bounds(window, |_, window, _| {
+            let _ = WindowGeometry::from_window_bounds(window.window_bounds()).save();
+        })
+        .detach();
+
+        cx.observe_window_activation(window, |editor, window, _| {
+            if !window.is_window_active() {
+                if let Some(path) = editor.file_path.as_deref() {
+                    let _ = editor.hooks.fire(HookEvent::FocusLost, path);
                 }
+                editor.snapshot_history();
             }
+        })
+        .detach();
+
+        editor.restore_scroll_position();
+        editor.snapshot_history();
+        #[cfg(feature = "live-sync")]
+        editor.join_live_sync();
+        editor.refresh_note_names();
+        if let Some(path) = editor.file_path.as_deref() {
+            let _ = WorkspaceState::add_recent(path);
         }
+        editor
     }
 
-    fn get_cursor(&self) -> BufferPosition {
-        let core_cursor = self.engine.state().cursor;
-        BufferPosition::new(core_cursor.row, core_cursor.column)
+    /// An editor for a string rather than a file, to embed in another GPUI
+    /// app as a text input or editor. It starts empty with the default
+    /// config, and leaves files, panels and the window to the app, which
+    /// binds [`editing_bindings`](crate::actions::editing_bindings) and
+    /// subscribes to [`EditorEvent`]s.
+    pub fn embedded(cx: &mut Context<Self>) -> Self {
+        let mut editor = Self::with_engine(EditorEngine::new(), true, cx);
+        editor.engine.state_mut().font_size = EMBEDDED_FONT_SIZE;
+        editor
     }
 
-    fn set_cursor(&mut self, pos: BufferPosition) {
-        self.engine.state_mut().cursor = zlyph_core::BufferPosition::new(pos.row, pos.column);
-    }
+    fn with_engine(mut engine: EditorEngine, embedded: bool, cx: &mut Context<Self>) -> Self {
+        let config = engine.config().clone();
+        let file_events = engine.subscribe();
+        let lsp = LspManager::new(config.lsp.clone());
+        let grammar = GrammarChecker::new(&config.languagetool);
 
-    fn get_selection_anchor(&self) -> Option<BufferPosition> {
-        self.engine.state().selection_anchor.map(|pos| BufferPosition::new(pos.row, pos.column))
-    }
+        cx.spawn(async move |this, cx| loop {
+            cx.background_executor()
+                .timer(BACKGROUND_POLL_INTERVAL)
+                .await;
+            let poll = this.update(cx, |editor, cx| {
+                editor.poll_cursor_blink(cx);
+                editor.poll_lsp(cx);
+                editor.poll_grammar(cx);
+                editor.poll_file_io(cx);
+                editor.poll_shell_command(cx);
+                editor.poll_dictation(cx);
+                if editor.embedded {
+                    return;
+                }
+                editor.poll_session_status(cx);
+                editor.poll_transfer_status(cx);
+                editor.poll_project_search(cx);
+                editor.poll_instance(cx);
+                editor.poll_hooks();
+                editor.poll_git_gutter(cx);
+                #[cfg(feature = "live-sync")]
+                editor.poll_live_sync(cx);
+                editor.poll_large_file_save();
+                editor.poll_config(cx);
+                editor.save_scroll_position();
+            });
+            if poll.is_err() {
+                break;
+            }
+        })
+        .detach();
 
-    fn set_selection_anchor(&mut self, pos: Option<BufferPosition>) {
-        self.engine.state_mut().selection_anchor = pos.map(|p| zlyph_core::BufferPosition::new(p.row, p.column));
-    }
+        #[cfg(feature = "terminal")]
+        let editor = cx.entity().downgrade();
+        #[cfg(feature = "terminal")]
+        let terminal_keys = cx.intercept_keystrokes(move |event, window, cx| {
+            let _ = editor.update(cx, |editor, cx| {
+                editor.intercept_terminal_key(&event.keystroke, window, cx)
+            });
+        });
 
-    fn get_font_size(&self) -> f32 {
-        self.engine.state().font_size
+        let engine_revision = engine.revision();
+        Self {
+            buffer: TextBuffer::from_string(engine.state().to_string()),
+            engine,
+            focus_handle: cx.focus_handle(),
+            theme: Theme::with_background_opacity(config.window.opacity),
+            is_dragging: false,
+            text_drag: None,
+            hovered_link: None,
+            last_click_time: None,
+            last_click_position: None,
+            file_path: None,
+            last_modified: None,
+            dirty: false,
+            window_title: String::new(),
+            scroll_offset: 0.0,
+            scroll_target: 0.0,
+            scroll_anchor: None,
+            last_scroll_frame: None,
+            saved_scroll_line: 0,
+            saved_cursor: BufferPosition::new(0, 0),
+            saved_anchor: None,
+            saved_bookmarks: Vec::new(),
+            scroll_config: config.scroll.clone(),
+            lsp,
+            grammar,
+            session_status: None,
+            transfer_status: None,
+            file_events,
+            file_progress: None,
+            focus_mode: config.view.focus_mode && !embedded,
+            focus_scope: config.view.focus_scope,
+            minimap: config.view.minimap,
+            breadcrumbs: config.view.breadcrumbs,
+            elastic_tabstops: config.view.elastic_tabstops,
+            rainbow_brackets: config.view.rainbow_brackets,
+            tab_layout: None,
+            zoom_scope: config.view.zoom,
+            zoom_pixels: 0.0,
+            minimap_dragging: false,
+            viewport_height: 600.0,
+            window_config: config.window.clone(),
+            cursor_config: config.cursor.clone(),
+            last_cursor: BufferPosition::new(0, 0),
+            last_cursor_move: Instant::now(),
+            cursor_drawn_visible: true,
+            last_cursor_x: Cell::new(None),
+            smear: Cell::new(None),
+            search_panel: None,
+            search_visible: false,
+            definition_pending: None,
+            quick_switch: None,
+            instance: None,
+            system_opens: mpsc::channel().1,
+            file_tree: None,
+            tree_visible: false,
+            tree_focused: false,
+            hooks_revision: engine_revision,
+            save_due: None,
+            hooks: HookRegistry::new(config.hooks.clone()),
+            git_gutter: GitGutter::new(),
+            highlighter: Highlighter::new(),
+            indent_guides: IndentGuides::new(),
+            diff_view: None,
+            history: LocalHistory::new(config.history.clone()),
+            history_browser: None,
+            backlinks: None,
+            bookmarks: None,
+            tags: None,
+            todos: None,
+            task_run: None,
+            errors_visible: false,
+            outline: None,
+            outline_focused: false,
+            symbol_palette: None,
+            symbols_revision: 0,
+            symbols: Vec::new(),
+            server_symbols: false,
+            note_search: None,
+            note_search_visible: false,
+            template_picker: None,
+            char_picker: None,
+            #[cfg(feature = "live-sync")]
+            live_sync: None,
+            #[cfg(feature = "live-sync")]
+            sync_revision: 0,
+            passphrase_prompt: None,
+            command_line: None,
+            composition: None,
+            ime_cursor: Cell::new(None),
+            announcer: Announcer::new(config.accessibility.clone()),
+            dock_file: None,
+            context_menu: None,
+            context_menu_position: Point::default(),
+            search_url: config.context_menu.search_url.clone(),
+            font_config: config.font.clone(),
+            font_applied: false,
+            config_modified: config_modified(),
+            embedded,
+            placeholder: SharedString::default(),
+            bounds: Rc::new(Cell::new(None)),
+            changed_revision: engine_revision,
+            #[cfg(feature = "terminal")]
+            terminal: None,
+            #[cfg(feature = "terminal")]
+            terminal_visible: false,
+            #[cfg(feature = "terminal")]
+            terminal_focused: false,
+            #[cfg(feature = "terminal")]
+            _terminal_keys: terminal_keys,
+        }
     }
 
-    fn selection_range(&self) -> Option<(BufferPosition, BufferPosition)> {
-        self.get_selection_anchor().map(|anchor| {
-            let cursor = self.get_cursor();
-            if anchor.row < cursor.row
-                || (anchor.row == cursor.row && anchor.column < cursor.column)
-            {
-                (anchor, cursor)
-            } else {
-                (cursor, anchor)
-            }
-        })
+    pub fn text(&self) -> String {
+        self.engine.state().to_string()
     }
 
-    // All action handlers delegate to engine
-    fn undo(&mut self, _: &Undo, _window: &mut Window, cx: &mut Context<Self>) {
-        self.engine.handle_action(EditorAction::Undo);
-        self.sync_and_save();
+    /// Replace the whole text, with the cursor at the start. Unlike an edit,
+    /// this does not emit [`EditorEvent::Changed`].
+    pub fn set_text(&mut self, text: &str, cx: &mut Context<Self>) {
+        self.engine.load_text(text);
+        self.changed_revision = self.engine.revision();
+        self.sync_buffer_from_engine();
+        self.scroll_target = 0.0;
+        self.scroll_offset = 0.0;
+        self.scroll_anchor = None;
         cx.notify();
     }
 
-    fn redo(&mut self, _: &Redo, _window: &mut Window, cx: &mut Context<Self>) {
-        self.engine.handle_action(EditorAction::Redo);
-        self.sync_and_save();
-        cx.notify();
+    pub fn is_read_only(&self) -> bool {
+        self.engine.is_read_only()
     }
 
-    fn increase_font_size(&mut self, _: &IncreaseFontSize, _window: &mut Window, cx: &mut Context<Self>) {
-        self.engine.handle_action(EditorAction::IncreaseFontSize);
-        self.buffer.invalidate_all_layouts();
+    /// Show the text without letting it be typed over; it can still be
+    /// selected and copied
+    pub fn set_read_only(&mut self, read_only: bool, cx: &mut Context<Self>) {
+        self.engine.set_read_only(read_only);
         cx.notify();
     }
 
-    fn decrease_font_size(&mut self, _: &DecreaseFontSize, _window: &mut Window, cx: &mut Context<Self>) {
-        self.engine.handle_action(EditorAction::DecreaseFontSize);
-        self.buffer.invalidate_all_layouts();
+    /// Turn away edits to the text in `range`, byte offsets into the
+    /// whole text, and draw it muted
+    pub fn protect(&mut self, range: Range<usize>, cx: &mut Context<Self>) {
+        self.engine.protect(range);
         cx.notify();
     }
 
-    fn reset_font_size(&mut self, _: &ResetFontSize, _window: &mut Window, cx: &mut Context<Self>) {
-        self.engine.handle_action(EditorAction::ResetFontSize);
-        self.buffer.invalidate_all_layouts();
+    pub fn clear_protected(&mut self, cx: &mut Context<Self>) {
+        self.engine.clear_protected();
         cx.notify();
     }
 
-    fn handle_newline(&mut self, _: &Newline, _window: &mut Window, cx: &mut Context<Self>) {
-        self.engine.handle_action(EditorAction::Newline);
-        self.sync_and_save();
+    /// Replace the virtual text, gutter icons and highlights published by
+    /// `source`; see [`zlyph_core::decorations`]
+    pub fn set_decorations(
+        &mut self,
+        source: &str,
+        decorations: Vec<Decoration>,
+        cx: &mut Context<Self>,
+    ) {
+        self.engine.set_decorations(source, decorations);
         cx.notify();
     }
 
-    fn handle_backspace(&mut self, _: &Backspace, _window: &mut Window, cx: &mut Context<Self>) {
-        self.engine.handle_action(EditorAction::Backspace);
-        self.sync_and_save();
-        cx.notify();
+    pub fn is_single_line(&self) -> bool {
+        self.engine.is_single_line()
     }
 
-    fn handle_delete(&mut self, _: &Delete, _window: &mut Window, cx: &mut Context<Self>) {
-        self.engine.handle_action(EditorAction::Delete);
-        self.sync_and_save();
+    /// Keep the text on one line, as in a form field: Enter emits
+    /// [`EditorEvent::Submitted`] instead of breaking the line
+    pub fn set_single_line(&mut self, single_line: bool, cx: &mut Context<Self>) {
+        self.engine.set_single_line(single_line);
+        self.changed_revision = self.engine.revision();
+        self.sync_buffer_from_engine();
         cx.notify();
     }
 
-    fn delete_to_beginning_of_line(&mut self, _: &DeleteToBeginningOfLine, _window: &mut Window, cx: &mut Context<Self>) {
-        self.engine.handle_action(EditorAction::DeleteToBeginningOfLine);
-        self.sync_and_save();
+    /// Text shown in place of an empty buffer
+    pub fn set_placeholder(
+        &mut self,
+        placeholder: impl Into<SharedString>,
+        cx: &mut Context<Self>,
+    ) {
+        self.placeholder = placeholder.into();
         cx.notify();
     }
 
-    fn delete_to_end_of_line(&mut self, _: &DeleteToEndOfLine, _window: &mut Window, cx: &mut Context<Self>) {
-        self.engine.handle_action(EditorAction::DeleteToEndOfLine);
-        self.sync_and_save();
+    pub fn set_font_size(&mut self, size: f32, cx: &mut Context<Self>) {
+        self.engine.state_mut().font_size = size;
+        self.buffer.invalidate_all_layouts();
         cx.notify();
     }
 
-    fn move_to_beginning_of_line(&mut self, _: &MoveToBeginningOfLine, _window: &mut Window, cx: &mut Context<Self>) {
-        self.engine.handle_action(EditorAction::MoveToBeginningOfLine);
-        self.ensure_cursor_visible();
-        cx.notify();
+    /// Where the text is drawn in the window: all of it for the app, and
+    /// wherever the app laid the editor out when embedded
+    fn viewport(&self, window: &Window) -> Bounds<Pixels> {
+        let whole_window = Bounds::new(Point::default(), window.viewport_size());
+        if self.embedded {
+            self.bounds.get().unwrap_or(whole_window)
+        } else {
+            whole_window
+        }
     }
 
-    fn move_to_end_of_line(&mut self, _: &MoveToEndOfLine, _window: &mut Window, cx: &mut Context<Self>) {
-        self.engine.handle_action(EditorAction::MoveToEndOfLine);
-        self.ensure_cursor_visible();
-        cx.notify();
+    fn padding_top(&self) -> f32 {
+        if self.embedded {
+            EMBEDDED_PADDING
+        } else {
+            PADDING_TOP
+        }
     }
 
-    fn move_left(&mut self, _: &MoveLeft, _window: &mut Window, cx: &mut Context<Self>) {
-        self.engine.handle_action(EditorAction::MoveLeft);
-        self.ensure_cursor_visible();
-        cx.notify();
+    fn line_height(&self) -> f32 {
+        self.get_font_size() * self.font_config.line_height
     }
 
-    fn move_right(&mut self, _: &MoveRight, _window: &mut Window, cx: &mut Context<Self>) {
-        self.engine.handle_action(EditorAction::MoveRight);
-        self.ensure_cursor_visible();
-        cx.notify();
-    }
+    /// Zoom, scroll and move the cursor to where this file was last left,
+    /// the scrolling and cursor in either frontend
+    fn restore_scroll_position(&mut self) {
+        let workspace = WorkspaceState::load().unwrap_or_default();
+        match workspace.font_size(self.file_path.as_deref(), self.zoom_scope) {
+            Some(size) => self.engine.state_mut().font_size = size.clamp(8.0, 72.0),
+            None => self.engine.handle_action(EditorAction::ResetFontSize),
+        }
+        self.buffer.invalidate_all_layouts();
 
-    fn move_up(&mut self, _: &MoveUp, _window: &mut Window, cx: &mut Context<Self>) {
-        self.engine.handle_action(EditorAction::MoveUp);
-        self.ensure_cursor_visible();
-        cx.notify();
-    }
+        let state = self
+            .file_path
+            .as_deref()
+            .map_or_else(Default::default, |path| workspace.file(path));
+        let scroll_line = state
+            .scroll_line
+            .min(self.buffer.line_count().saturating_sub(1));
+        self.saved_scroll_line = scroll_line;
+        self.engine.set_bookmarks(&state.bookmarks);
+        self.saved_bookmarks = self.engine.bookmarks();
+        self.scroll_target = scroll_line as f32 * self.line_height();
+        self.scroll_offset = self.scroll_target;
+        self.scroll_anchor = None;
 
-    fn move_down(&mut self, _: &MoveDown, _window: &mut Window, cx: &mut Context<Self>) {
-        self.engine.handle_action(EditorAction::MoveDown);
-        self.ensure_cursor_visible();
-        cx.notify();
+        if state.cursor_row > 0 || state.cursor_column > 0 {
+            self.engine.handle_action(EditorAction::SetCursorPosition {
+                row: state.cursor_row,
+                column: state.cursor_column,
+            });
+        } else if scroll_line > 0 {
+            // Put the cursor where it keeps the restored view in place
+            let row = (scroll_line + self.scroll_config.margin)
+                .min(self.buffer.line_count().saturating_sub(1));
+            self.engine
+                .handle_action(EditorAction::SetCursorPosition { row, column: 0 });
+        }
+        if let Some(anchor) = state.selection_anchor {
+            let cursor = self.get_cursor();
+            self.engine.handle_action(EditorAction::StartSelection {
+                row: anchor.row,
+                column: anchor.column,
+            });
+            self.engine.handle_action(EditorAction::ExtendSelection {
+                row: cursor.row,
+                column: cursor.column,
+            });
+        }
+        self.saved_cursor = self.get_cursor();
+        self.saved_anchor = self.engine.state().selection_anchor;
+        self.engine.warn_of_long_lines();
     }
 
-    fn move_word_left(&mut self, _: &MoveWordLeft, _: &mut Window, cx: &mut Context<Self>) {
-        self.engine.handle_action(EditorAction::MoveWordLeft);
-        self.ensure_cursor_visible();
-        cx.notify();
+    fn save_scroll_position(&mut self) {
+        let top = (self.scroll_target.max(0.0) / self.line_height()) as usize;
+        let (scroll_line, _) = self.buffer.row_at_visual(top);
+        let cursor = self.get_cursor();
+        let anchor = self.engine.state().selection_anchor;
+        let bookmarks = self.engine.bookmarks();
+        let Some(path) = self.file_path.as_deref() else {
+            return;
+        };
+        if scroll_line != self.saved_scroll_line
+            || cursor != self.saved_cursor
+            || anchor != self.saved_anchor
+            || bookmarks != self.saved_bookmarks
+        {
+            self.saved_scroll_line = scroll_line;
+            self.saved_cursor = cursor;
+            self.saved_anchor = anchor;
+            self.saved_bookmarks = bookmarks.clone();
+            let _ = WorkspaceState::update_file(path, |file| {
+                file.scroll_line = scroll_line;
+                file.cursor_row = cursor.row;
+                file.cursor_column = cursor.column;
+                file.selection_anchor = anchor;
+                file.bookmarks = bookmarks;
+            });
+        }
     }
 
-    fn move_word_right(&mut self, _: &MoveWordRight, _: &mut Window, cx: &mut Context<Self>) {
-        self.engine.handle_action(EditorAction::MoveWordRight);
-        self.ensure_cursor_visible();
-        cx.notify();
-    }
+    /// Move the drawn scroll position toward the target, easing out; keeps
+    /// requesting frames until it arrives
+    fn step_scroll(&mut self, window: &Window) {
+        let distance = self.scroll_target - self.scroll_offset;
+        if !self.scroll_config.smooth || distance.abs() < 0.5 {
+            self.scroll_offset = self.scroll_target;
+            self.last_scroll_frame = None;
+            return;
+        }
 
-    fn move_line_up(&mut self, _: &MoveLineUp, _: &mut Window, cx: &mut Context<Self>) {
-        self.engine.handle_action(EditorAction::MoveLineUp);
-        self.sync_and_save();
-        cx.notify();
+        let now = Instant::now();
+        let elapsed = self
+            .last_scroll_frame
+            .map_or(Duration::from_millis(16), |last| now - last);
+        self.last_scroll_frame = Some(now);
+
+        let t = 1.0 - (-elapsed.as_secs_f32() / SCROLL_EASING.as_secs_f32()).exp();
+        self.scroll_offset += distance * t;
+        window.request_animation_frame();
     }
 
-    fn move_line_down(&mut self, _: &MoveLineDown, _: &mut Window, cx: &mut Context<Self>) {
-        self.engine.handle_action(EditorAction::MoveLineDown);
-        self.sync_and_save();
-        cx.notify();
+    fn poll_lsp(&mut self, cx: &mut Context<Self>) {
+        let update = self.lsp.process(&mut self.engine);
+        if let Some(location) = update.jump {
+            self.open_location(location);
+        }
+        // A server that found nothing, or doesn't list symbols, leaves the
+        // outline read from the buffer
+        if let Some(symbols) = update.symbols.filter(|symbols| !symbols.is_empty()) {
+            self.server_symbols = true;
+            self.set_symbols(symbols);
+            cx.notify();
+        }
+        if (self.outline.is_some() || self.symbol_palette.is_some() || self.breadcrumbs_shown())
+            && self.symbols_revision != self.engine.revision()
+        {
+            self.refresh_symbols();
+            cx.notify();
+        }
+        if update.changed {
+            self.ensure_cursor_visible();
+            cx.notify();
+        }
     }
 
-    fn delete_line(&mut self, _: &DeleteLine, _: &mut Window, cx: &mut Context<Self>) {
-        self.engine.handle_action(EditorAction::DeleteLine);
-        self.sync_and_save();
-        cx.notify();
+    fn poll_grammar(&mut self, cx: &mut Context<Self>) {
+        if let Some(grammar) = self.grammar.as_mut() {
+            if grammar.poll(&mut self.engine) {
+                cx.notify();
+            }
+        }
     }
 
-    fn handle_tab(&mut self, _: &Tab, _: &mut Window, cx: &mut Context<Self>) {
-        self.engine.handle_action(EditorAction::Tab);
-        self.sync_and_save();
-        cx.notify();
+    fn cursor_visible(&self) -> bool {
+        zlyph_core::cursor::is_visible(&self.cursor_config, self.last_cursor_move.elapsed())
     }
 
-    fn handle_outdent(&mut self, _: &Outdent, _: &mut Window, cx: &mut Context<Self>) {
-        self.engine.handle_action(EditorAction::Outdent);
-        self.sync_and_save();
-        cx.notify();
+    fn poll_cursor_blink(&mut self, cx: &mut Context<Self>) {
+        if self.cursor_visible() != self.cursor_drawn_visible {
+            cx.notify();
+        }
     }
 
-    fn select_left(&mut self, _: &SelectLeft, _: &mut Window, cx: &mut Context<Self>) {
-        self.engine.handle_action(EditorAction::SelectLeft);
-        cx.notify();
+    fn poll_project_search(&mut self, cx: &mut Context<Self>) {
+        if let Some(panel) = self.search_panel.as_mut() {
+            if panel.poll() {
+                cx.notify();
+            }
+        }
+        if self.definition_pending.is_some() {
+            self.resolve_definition(cx);
+        }
+        if self.tags.as_mut().is_some_and(TagIndex::poll) {
+            cx.notify();
+        }
+        if self.todos.as_mut().is_some_and(TodoPanel::poll) {
+            cx.notify();
+        }
+        if self.task_run.as_mut().is_some_and(TaskRun::poll) {
+            self.publish_problems();
+            cx.notify();
+        }
+        if self.note_search.as_mut().is_some_and(NoteSearch::poll) {
+            cx.notify();
+        }
     }
 
-    fn select_right(&mut self, _: &SelectRight, _: &mut Window, cx: &mut Context<Self>) {
-        self.engine.handle_action(EditorAction::SelectRight);
-        cx.notify();
+    /// Open files handed over by later launches or by the system and bring
+    /// the window forward
+    fn poll_instance(&mut self, cx: &mut Context<Self>) {
+        let mut paths = self
+            .instance
+            .as_ref()
+            .map(InstanceListener::poll)
+            .unwrap_or_default();
+        paths.extend(self.system_opens.try_iter());
+        if let Some(path) = paths.into_iter().last() {
+            if self.file_path.as_ref() != Some(&path) {
+                self.open_file(path);
+            }
+            cx.activate(true);
+            cx.notify();
+        }
     }
 
-    fn select_up(&mut self, _: &SelectUp, _: &mut Window, cx: &mut Context<Self>) {
-        self.engine.handle_action(EditorAction::SelectUp);
-        cx.notify();
+    fn poll_git_gutter(&mut self, cx: &mut Context<Self>) {
+        if self.git_gutter.update(
+            self.file_path
+                .as_deref()
+                .filter(|_| !self.engine.is_large_file()),
+            &self.engine.state().lines,
+            self.engine.revision(),
+        ) {
+            cx.notify();
+        }
     }
 
-    fn select_down(&mut self, _: &SelectDown, _: &mut Window, cx: &mut Context<Self>) {
-        self.engine.handle_action(EditorAction::SelectDown);
+    /// Put the output of a finished shell command in the buffer
+    fn poll_shell_command(&mut self, cx: &mut Context<Self>) {
+        let revision = self.engine.revision();
+        if !self.engine.poll_shell_command() {
+            return;
+        }
+        if self.engine.revision() != revision {
+            self.sync_and_save(cx);
+        }
         cx.notify();
     }
 
-    fn select_word_left(&mut self, _: &SelectWordLeft, _: &mut Window, cx: &mut Context<Self>) {
-        self.engine.handle_action(EditorAction::SelectWordLeft);
+    /// Type what dictation has recognized since the last poll
+    fn poll_dictation(&mut self, cx: &mut Context<Self>) {
+        let revision = self.engine.revision();
+        if !self.engine.poll_dictation() {
+            return;
+        }
+        if self.engine.revision() != revision {
+            self.sync_and_save(cx);
+            self.ensure_cursor_visible();
+        }
         cx.notify();
     }
 
-    fn select_word_right(&mut self, _: &SelectWordRight, _: &mut Window, cx: &mut Context<Self>) {
-        self.engine.handle_action(EditorAction::SelectWordRight);
-        cx.notify();
+    /// Send local edits to the other instances and merge in theirs
+    #[cfg(feature = "live-sync")]
+    fn poll_live_sync(&mut self, cx: &mut Context<Self>) {
+        let Some(live) = self.live_sync.as_mut() else {
+            return;
+        };
+        if self.engine.revision() != self.sync_revision {
+            let _ = live.local_edit(&self.engine.state().to_string());
+        }
+        if let Ok(Some(text)) = live.poll() {
+            self.engine.merge_text(&text);
+            self.sync_buffer_from_engine();
+            self.save_to_file();
+            self.lsp.sync(&self.engine);
+            cx.notify();
+        }
+        self.sync_revision = self.engine.revision();
     }
 
-    fn select_all(&mut self, _: &SelectAll, _window: &mut Window, cx: &mut Context<Self>) {
-        self.engine.handle_action(EditorAction::SelectAll);
-        cx.notify();
+    /// Save a large file once edits have paused
+    fn poll_large_file_save(&mut self) {
+        if self.save_due.is_some_and(|due| Instant::now() >= due) {
+            self.save_to_file();
+        }
     }
 
-    fn copy(&mut self, _: &Copy, _: &mut Window, cx: &mut Context<Self>) {
-        if let Some((start, end)) = self.selection_range() {
-            let start_offset = self.buffer.position_to_byte_offset(start);
-            let end_offset = self.buffer.position_to_byte_offset(end);
-            let content = self.buffer.to_string();
-            if end_offset <= content.len() {
-                let selected_text = content[start_offset..end_offset].to_string();
-                cx.write_to_clipboard(selected_text.into());
-            }
+    /// Start sharing edits with other instances open on the current file
+    #[cfg(feature = "live-sync")]
+    fn join_live_sync(&mut self) {
+        if self.engine.is_large_file() {
+            self.live_sync = None;
+            return;
         }
+        let text = self.engine.state().to_string();
+        self.live_sync = self
+            .file_path
+            .as_deref()
+            .and_then(|path| LiveSync::join(path, &text).ok());
+        self.sync_revision = self.engine.revision();
     }
 
-    fn cut(&mut self, _: &Cut, _: &mut Window, cx: &mut Context<Self>) {
-        if let Some((start, end)) = self.selection_range() {
-            let start_offset = self.buffer.position_to_byte_offset(start);
-            let end_offset = self.buffer.position_to_byte_offset(end);
-            let content = self.buffer.to_string();
-            if end_offset <= content.len() {
-                let selected_text = content[start_offset..end_offset].to_string();
-                cx.write_to_clipboard(selected_text.into());
-                self.engine.handle_action(EditorAction::Cut);
-                self.sync_and_save();
+    /// Pick up font changes saved to the config file
+    fn poll_config(&mut self, cx: &mut Context<Self>) {
+        let modified = config_modified();
+        if modified == self.config_modified {
+            return;
+        }
+        self.config_modified = modified;
+        if let Ok(config) = Config::load() {
+            if config.font != self.font_config {
+                self.font_config = config.font;
+                self.font_applied = false;
                 cx.notify();
             }
         }
     }
 
-    fn paste(&mut self, _: &Paste, _: &mut Window, cx: &mut Context<Self>) {
-        if let Some(clipboard_item) = cx.read_from_clipboard() {
-            if let Some(text) = clipboard_item.text() {
-                self.engine.handle_action(EditorAction::Paste(text));
-                self.sync_and_save();
-                cx.notify();
+    /// Shape text in the configured font, or the first of its fallbacks
+    /// that is installed
+    fn apply_font(&mut self, window: &Window) {
+        self.font_applied = true;
+        let installed = window.text_system().all_font_names();
+        let family = match self.font_config.pick_family(&installed) {
+            Some(family) => family.to_string(),
+            None => DEFAULT_FONT_FAMILY.to_string(),
+        };
+        if family != self.font_config.family {
+            self.engine.set_hover(Some(format!(
+                "Font \"{}\" is not installed; using \"{}\"",
+                self.font_config.family, family
+            )));
+        }
+        let features = if self.font_config.ligatures {
+            FontFeatures::default()
+        } else {
+            FontFeatures(Arc::new(vec![("calt".into(), 0), ("liga".into(), 0)]))
+        };
+        let fallbacks = &self.font_config.fallbacks;
+        self.buffer.set_font(Font {
+            family: family.into(),
+            features,
+            weight: FontWeight(self.font_config.weight.clamp(100.0, 900.0)),
+            style: FontStyle::Normal,
+            fallbacks: (!fallbacks.is_empty())
+                .then(|| FontFallbacks::from_fonts(fallbacks.clone())),
+        });
+    }
+
+    fn poll_hooks(&mut self) {
+        if let Some(path) = self.file_path.as_deref() {
+            let _ = self.hooks.poll(path);
+        }
+    }
+
+    fn session_status(&self) -> Option<String> {
+        let session = self.engine.writing_session()?;
+        Some(session.status(self.engine.word_count()))
+    }
+
+    fn poll_session_status(&mut self, cx: &mut Context<Self>) {
+        let status = self.session_status();
+        if status != self.session_status {
+            self.session_status = status;
+            cx.notify();
+        }
+    }
+
+    fn poll_transfer_status(&mut self, cx: &mut Context<Self>) {
+        let status = self.engine.transfer_status();
+        if status != self.transfer_status {
+            self.transfer_status = status;
+            cx.notify();
+        }
+    }
+
+    /// Show the progress of background saves, and note the file's new
+    /// modification time once it is written, so the save isn't mistaken for
+    /// a change made elsewhere
+    fn poll_file_io(&mut self, cx: &mut Context<Self>) {
+        let mut changed = self.engine.poll_file_io();
+        while let Ok(event) = self.file_events.try_recv() {
+            match event {
+                ChangeEvent::Saving { path, done, total } => {
+                    let percent = (done * 100).checked_div(total).unwrap_or(100);
+                    let name = path.file_name().unwrap_or_default().to_string_lossy();
+                    self.file_progress = Some(format!("Saving {} {}%", name, percent));
+                }
+                ChangeEvent::Saved { path } => {
+                    self.file_progress = None;
+                    if self.file_path.as_ref() == Some(&path) {
+                        self.last_modified =
+                            std::fs::metadata(&path).and_then(|m| m.modified()).ok();
+                    }
+                }
+                ChangeEvent::Failed { path, message } => {
+                    self.file_progress = None;
+                    self.dirty |= self.file_path.as_ref() == Some(&path);
+                    let name = path.file_name().unwrap_or_default().to_string_lossy();
+                    self.engine
+                        .set_hover(Some(format!("Could not save {}: {}", name, message)));
+                }
+                ChangeEvent::Cancelled { .. } => self.file_progress = None,
+                _ => continue,
+            }
+            changed = true;
+        }
+        if changed {
+            cx.notify();
+        }
+    }
+
+    /// Upload a remote file even though it changed on its host; local files
+    /// are saved as they are edited anyway
+    fn overwrite_remote(&mut self, _: &OverwriteRemote, _: &mut Window, _cx: &mut Context<Self>) {
+        if let Some(path) = self
+            .file_path
+            .as_deref()
+            .filter(|path| vfs::is_remote(path))
+        {
+            let _ = self.engine.overwrite_remote(path);
+        }
+    }
+
+    /// Switch to the file containing a definition found by the language server
+    fn open_location(&mut self, location: Location) {
+        if !self.open_file(location.path.clone()) {
+            return;
+        }
+
+        let pos = location.position_in(&self.engine.state().lines);
+        self.engine.handle_action(EditorAction::SetCursorPosition {
+            row: pos.row,
+            column: pos.column,
+        });
+        self.sync_buffer_from_engine();
+    }
+
+    /// Switch to `path`, saving the current file first. A path that does not
+    /// exist yet starts as an empty buffer and is created on the first edit.
+    fn open_file(&mut self, path: PathBuf) -> bool {
+        if self.engine.needs_passphrase(&path) {
+            self.passphrase_prompt = Some(PassphrasePrompt::new(path));
+            return false;
+        }
+        self.save_scroll_position();
+        self.save_to_file();
+        if let Some(previous) = self.file_path.as_deref() {
+            let _ = self.hooks.flush(previous);
+        }
+        self.snapshot_history();
+        if self.file_path.as_deref().map(project::root) != Some(project::root(&path)) {
+            self.engine
+                .set_config(project::config(&path).unwrap_or_default());
+        }
+        if path.exists() || vfs::is_remote(&path) {
+            if self.engine.load_from_file(&path).is_err() {
+                return false;
             }
+        } else {
+            self.engine.new_buffer();
+        }
+
+        self.last_modified = std::fs::metadata(&path)
+            .ok()
+            .and_then(|m| m.modified().ok());
+        self.lsp.open(&path, &self.engine);
+        let _ = self.hooks.fire(HookEvent::Open, &path);
+        self.hooks_revision = self.engine.revision();
+        let _ = WorkspaceState::add_recent(&path);
+        self.file_path = Some(path);
+        self.publish_problems();
+        self.dirty = false;
+        self.sync_buffer_from_engine();
+        self.restore_scroll_position();
+        self.snapshot_history();
+        #[cfg(feature = "live-sync")]
+        self.join_live_sync();
+        self.refresh_note_names();
+        self.server_symbols = false;
+        if self.outline.is_some() || self.breadcrumbs_shown() {
+            self.refresh_symbols();
         }
+        true
     }
 
-    fn position_from_mouse(&mut self, mouse_position: Point<Pixels>, window: &mut Window, wrap_width: Pixels) -> BufferPosition {
-        let line_height_px = px(self.get_font_size() * 1.5);
-        let padding_top = px(40.0);
-        let padding_left = px(16.0);
+    /// Keep the buffer in local history, as opened or as left
+    fn snapshot_history(&mut self) {
+        if let Some(path) = self.history_path() {
+            let text = self.engine.state().lines.join("\n");
+            let _ = self.history.snapshot(&path, &text);
+        }
+    }
+
+    /// The file to keep local history for; encrypted files stay out of it,
+    /// since it is plain text, and so do large files
+    fn history_path(&self) -> Option<PathBuf> {
+        self.file_path
+            .clone()
+            .filter(|path| !self.engine.is_encrypted_file(path) && !self.engine.is_large_file())
+    }
 
-        let relative_y = if mouse_position.y > padding_top {
-            mouse_position.y - padding_top
+    /// File name for the window title, marked while there are unsaved edits
+    fn window_title(&self) -> String {
+        let name = self
+            .file_path
+            .as_deref()
+            .and_then(Path::file_name)
+            .map_or("Untitled".to_string(), |name| {
+                name.to_string_lossy().into_owned()
+            });
+        if self.dirty {
+            format!("{} •", name)
         } else {
-            px(0.0)
+            name
+        }
+    }
+
+    /// Run an action that a language server answers asynchronously
+    fn request_from_lsp(&mut self, action: EditorAction) {
+        self.engine.handle_action(action.clone());
+        self.lsp.handle_action(&action, &self.engine);
+    }
+
+    fn sync_buffer_from_engine(&mut self) {
+        self.buffer.set_lines(&self.engine.state().lines);
+    }
+
+    /// Hide the lines of collapsed frontmatter, or show them again once
+    /// the cursor goes into it
+    fn sync_collapsed_frontmatter(&mut self) {
+        self.buffer
+            .set_hidden(self.engine.collapsed_frontmatter().unwrap_or_default());
+    }
+
+    /// Write the buffer to its file in the background; an untitled buffer
+    /// stays dirty until Save As, and a failed save marks it dirty once
+    /// reported to `poll_file_io`
+    fn save_to_file(&mut self) {
+        let Some(path) = self.file_path.as_deref() else {
+            self.dirty = true;
+            return;
         };
+        self.save_due = None;
+        self.dirty = self.engine.save_in_background(path).is_err();
+    }
 
-        let relative_x = if mouse_position.x > padding_left {
-            mouse_position.x - padding_left
+    fn sync_and_save(&mut self, cx: &mut Context<Self>) {
+        self.last_cursor_move = Instant::now();
+        self.sync_buffer_from_engine();
+        if !self.engine.is_large_file() {
+            self.save_to_file();
+        } else if self.engine.revision() != self.changed_revision {
+            self.save_due = Some(Instant::now() + LARGE_FILE_SAVE_DELAY);
+        }
+        self.lsp.sync(&self.engine);
+        self.ensure_cursor_visible();
+        if self.engine.revision() != self.changed_revision {
+            self.changed_revision = self.engine.revision();
+            cx.emit(EditorEvent::Changed);
+        }
+        if self.engine.revision() != self.hooks_revision {
+            self.hooks_revision = self.engine.revision();
+            if let Some(path) = self.file_path.as_deref() {
+                let _ = self.hooks.edited(path);
+            }
+            if let Some(path) = self.history_path() {
+                let text = self.engine.state().lines.join("\n");
+                let _ = self.history.record(&path, &text);
+                if let Some(search) = self.note_search.as_mut() {
+                    search.update(&path, &text);
+                }
+            }
+        }
+    }
+
+    /// Horizontal padding around the text; focus mode narrows the text column
+    fn text_margin(&self, window_width: Pixels) -> Pixels {
+        if self.embedded {
+            px(EMBEDDED_PADDING)
+        } else if self.focus_mode {
+            (window_width * 0.15).max(px(16.0))
+        } else {
+            px(16.0)
+        }
+    }
+
+    fn wrap_width(&self, window_width: Pixels) -> Pixels {
+        window_width
+            - self.text_margin(window_width) * 2.0
+            - self.minimap_width()
+            - self.sidebar_width()
+            - self.outline_width()
+    }
+
+    fn sidebar_width(&self) -> Pixels {
+        if self.tree_visible && !self.focus_mode {
+            px(SIDEBAR_WIDTH)
+        } else {
+            px(0.0)
+        }
+    }
+
+    fn outline_width(&self) -> Pixels {
+        if self.outline.is_some() && !self.focus_mode {
+            px(SIDEBAR_WIDTH)
+        } else {
+            px(0.0)
+        }
+    }
+
+    /// Whether indentation guides are drawn; large files go without, as
+    /// working out their indents on every edit would stall
+    fn indent_guides_shown(&self) -> bool {
+        self.engine.config().view.indent_guides && !self.engine.is_large_file()
+    }
+
+    fn minimap_visible(&self) -> bool {
+        self.minimap && !self.focus_mode && !self.embedded
+    }
+
+    fn minimap_width(&self) -> Pixels {
+        if self.minimap_visible() {
+            px(MINIMAP_WIDTH)
         } else {
             px(0.0)
+        }
+    }
+
+    fn max_scroll(&self) -> f32 {
+        let rows = self.buffer.first_visual_row(self.buffer.line_count());
+        (rows as f32 * self.line_height()).max(0.0)
+    }
+
+    /// Keep the line at the top of the view in place when lines above it
+    /// have been reshaped onto a different number of visual rows
+    fn reanchor_scroll(&mut self) {
+        let Some((row, was)) = self.scroll_anchor else {
+            return;
         };
+        if row >= self.buffer.line_count() {
+            self.scroll_anchor = None;
+            return;
+        }
+        let now = self.buffer.first_visual_row(row);
+        let shift = (now as f32 - was as f32) * self.line_height();
+        self.scroll_offset += shift;
+        self.scroll_target += shift;
+        self.scroll_anchor = Some((row, now));
+    }
 
-        let visual_row = (relative_y / line_height_px) as usize;
-        let text_system = window.text_system();
-        let font_size_px = px(self.get_font_size());
+    /// Shape the lines in view and [`OVERSCAN_ROWS`] either side of them,
+    /// returning the lines to draw. Lines further away are left unshaped
+    /// and counted as one row each, so a long file costs only what shows.
+    fn shape_visible_rows(
+        &mut self,
+        font_size: Pixels,
+        wrap_width: Pixels,
+        text_system: &WindowTextSystem,
+    ) -> Range<usize> {
+        self.sync_collapsed_frontmatter();
+        self.reanchor_scroll();
+        let line_height = self.line_height();
+        let top = (self.scroll_offset.max(0.0) / line_height) as usize;
+        let (top_row, _) = self.buffer.row_at_visual(top);
+        self.scroll_anchor = Some((top_row, self.buffer.first_visual_row(top_row)));
 
-        for buffer_row in 0..self.buffer.line_count() {
-            self.buffer.get_or_shape_line(buffer_row, font_size_px, wrap_width, &text_system);
-        }
-
-        let mut visual_row_counter = 0;
-        for buffer_row in 0..self.buffer.line_count() {
-            if let Some(visual_lines) = self.buffer.get_visual_lines(buffer_row) {
-                let visual_lines_vec: Vec<_> = visual_lines
-                    .iter()
-                    .map(|vl| (vl.byte_range.clone(), vl.wrap_type))
-                    .collect();
-
-                for (_idx, (byte_range, _wrap_type)) in visual_lines_vec.iter().enumerate() {
-                    if visual_row_counter == visual_row {
-                        if let Some(layout) = self.buffer.get_or_shape_line(buffer_row, font_size_px, wrap_width, &text_system) {
-                            let full_line_x = layout.x_for_index(byte_range.start);
-                            let relative_segment_x = relative_x + full_line_x;
-                            let column_in_full_line = layout.closest_index_for_x(relative_segment_x);
-                            let clamped_column = column_in_full_line.clamp(byte_range.start, byte_range.end);
-                            return BufferPosition::new(buffer_row, clamped_column);
-                        }
+        let start = top_row.saturating_sub(OVERSCAN_ROWS);
+        let wanted = (self.viewport_height / line_height).ceil() as usize + OVERSCAN_ROWS;
+        let mut shown = 0;
+        let mut end = start;
+        while end < self.buffer.line_count() && (end < top_row || shown < wanted) {
+            self.buffer
+                .get_or_shape_line(end, font_size, wrap_width, text_system);
+            if end >= top_row {
+                shown += self.buffer.visual_row_count(end);
+            }
+            end += 1;
+        }
+        self.reanchor_scroll();
+        start..end
+    }
+
+    /// How far the minimap is scrolled when the buffer is too long to fit,
+    /// following the editor's scroll position proportionally
+    fn minimap_offset(&self) -> f32 {
+        let overflow = self.buffer.line_count() as f32 * MINIMAP_LINE_HEIGHT - self.viewport_height;
+        if overflow <= 0.0 {
+            return 0.0;
+        }
+        let visible_height = self.viewport_height - PADDING_TOP;
+        let scrollable = (self.max_scroll() - visible_height).max(1.0);
+        overflow * (self.scroll_offset / scrollable).clamp(0.0, 1.0)
+    }
+
+    /// Center the editor on the line under a minimap click or drag
+    fn scroll_to_minimap_y(&mut self, y: Pixels) {
+        let row = ((f32::from(y) + self.minimap_offset()) / MINIMAP_LINE_HEIGHT).max(0.0);
+        let visible_height = self.viewport_height - PADDING_TOP;
+        self.scroll_target =
+            (row * self.line_height() - visible_height / 2.0).clamp(0.0, self.max_scroll());
+    }
+
+    fn ensure_cursor_visible(&mut self) {
+        self.sync_collapsed_frontmatter();
+        self.reanchor_scroll();
+        let line_height = self.line_height();
+        let cursor_row = self.buffer.buffer_to_visual(self.get_cursor()).visual_row as f32;
+        let cursor_y = cursor_row * line_height;
+
+        // Typewriter scrolling: keep the cursor line in the middle of the window.
+        // Negative offsets push the first lines down so they can be centered too.
+        if self.focus_mode {
+            self.scroll_target =
+                cursor_y + self.padding_top() + line_height / 2.0 - self.viewport_height / 2.0;
+            return;
+        }
+
+        // Keep `margin` lines between the cursor and either edge, as far as the
+        // window is tall enough for it
+        let visible_height = (self.viewport_height - self.padding_top()).max(line_height);
+        let margin = (self.scroll_config.margin as f32 * line_height)
+            .min(((visible_height - line_height) / 2.0).max(0.0));
+
+        // Scroll up if cursor is above visible area
+        if cursor_y < self.scroll_target + margin {
+            self.scroll_target = (cursor_y - margin).max(0.0);
+        }
+
+        // Scroll down if cursor is below visible area
+        if cursor_y + line_height > self.scroll_target + visible_height - margin {
+            self.scroll_target = cursor_y + line_height - visible_height + margin;
+        }
+    }
+
+    fn check_and_reload(&mut self, cx: &mut Context<Self>) {
+        // Wait for our own saves to land first
+        if self.engine.file_io_pending() {
+            return;
+        }
+        let Some(path) = self.file_path.as_deref() else {
+            return;
+        };
+        if let Ok(metadata) = std::fs::metadata(path) {
+            if let Ok(modified) = metadata.modified() {
+                if self.last_modified.is_some_and(|last| modified <= last) {
+                    return;
+                }
+                #[cfg(feature = "live-sync")]
+                if let Some(live) = self.live_sync.as_mut() {
+                    self.last_modified = Some(modified);
+                    let Ok(content) = std::fs::read_to_string(path) else {
+                        return;
+                    };
+                    if let Ok(Some(text)) = live.file_changed(&content) {
+                        self.engine.merge_text(&text);
+                        self.sync_revision = self.engine.revision();
+                        self.sync_buffer_from_engine();
+                        cx.notify();
                     }
-                    visual_row_counter += 1;
+                    return;
                 }
+                if self.engine.load_from_file(path).is_ok() {
+                    self.last_modified = Some(modified);
+                    self.sync_buffer_from_engine();
+                    cx.notify();
+                }
+            }
+        }
+    }
+
+    fn get_cursor(&self) -> BufferPosition {
+        let core_cursor = self.engine.state().cursor;
+        BufferPosition::new(core_cursor.row, core_cursor.column)
+    }
+
+    fn set_cursor(&mut self, pos: BufferPosition) {
+        self.engine.state_mut().cursor = zlyph_core::BufferPosition::new(pos.row, pos.column);
+    }
+
+    fn get_selection_anchor(&self) -> Option<BufferPosition> {
+        self.engine
+            .state()
+            .selection_anchor
+            .map(|pos| BufferPosition::new(pos.row, pos.column))
+    }
+
+    fn set_selection_anchor(&mut self, pos: Option<BufferPosition>) {
+        self.engine.state_mut().selection_anchor =
+            pos.map(|p| zlyph_core::BufferPosition::new(p.row, p.column));
+    }
+
+    fn get_font_size(&self) -> f32 {
+        self.engine.state().font_size
+    }
+
+    fn selection_contains(&self, position: BufferPosition) -> bool {
+        self.selection_range().is_some_and(|(start, end)| {
+            (start.row, start.column) <= (position.row, position.column)
+                && (position.row, position.column) <= (end.row, end.column)
+        })
+    }
+
+    fn selection_range(&self) -> Option<(BufferPosition, BufferPosition)> {
+        self.get_selection_anchor().map(|anchor| {
+            let cursor = self.get_cursor();
+            if anchor.row < cursor.row
+                || (anchor.row == cursor.row && anchor.column < cursor.column)
+            {
+                (anchor, cursor)
             } else {
-                if visual_row_counter == visual_row {
-                    if let Some(layout) = self.buffer.get_or_shape_line(buffer_row, font_size_px, wrap_width, &text_system) {
-                        let column = layout.closest_index_for_x(relative_x);
-                        return BufferPosition::new(buffer_row, column);
-                    }
+                (cursor, anchor)
+            }
+        })
+    }
+
+    // All action handlers delegate to engine
+    fn undo(&mut self, _: &Undo, _window: &mut Window, cx: &mut Context<Self>) {
+        self.engine.handle_action(EditorAction::Undo);
+        self.sync_and_save(cx);
+        cx.notify();
+    }
+
+    fn redo(&mut self, _: &Redo, _window: &mut Window, cx: &mut Context<Self>) {
+        self.engine.handle_action(EditorAction::Redo);
+        self.sync_and_save(cx);
+        cx.notify();
+    }
+
+    fn increase_font_size(
+        &mut self,
+        _: &IncreaseFontSize,
+        _window: &mut Window,
+        cx: &mut Context<Self>,
+    ) {
+        self.zoom(EditorAction::IncreaseFontSize, cx);
+    }
+
+    fn decrease_font_size(
+        &mut self,
+        _: &DecreaseFontSize,
+        _window: &mut Window,
+        cx: &mut Context<Self>,
+    ) {
+        self.zoom(EditorAction::DecreaseFontSize, cx);
+    }
+
+    fn reset_font_size(&mut self, _: &ResetFontSize, _window: &mut Window, cx: &mut Context<Self>) {
+        self.zoom(EditorAction::ResetFontSize, cx);
+    }
+
+    /// Change the font size, keeping the same line at the top of the view,
+    /// and remember it for this file or for every file
+    fn zoom(&mut self, action: EditorAction, cx: &mut Context<Self>) {
+        let top_line = self.scroll_target / self.line_height();
+        self.engine.handle_action(action);
+        self.scroll_target = top_line * self.line_height();
+        self.scroll_offset = self.scroll_target;
+        self.buffer.invalidate_all_layouts();
+        if !self.embedded {
+            let _ = WorkspaceState::store_font_size(
+                self.file_path.as_deref(),
+                self.zoom_scope,
+                self.get_font_size(),
+            );
+        }
+        cx.notify();
+    }
+
+    fn handle_newline(&mut self, _: &Newline, _window: &mut Window, cx: &mut Context<Self>) {
+        if self.handle_panel_key(PanelKey::Enter, cx) {
+            return;
+        }
+        if self.engine.is_single_line() && self.engine.completion().is_none() {
+            cx.emit(EditorEvent::Submitted);
+            return;
+        }
+        self.engine.handle_action(EditorAction::Newline);
+        self.sync_and_save(cx);
+        cx.notify();
+    }
+
+    fn handle_backspace(&mut self, _: &Backspace, _window: &mut Window, cx: &mut Context<Self>) {
+        if self.handle_panel_key(PanelKey::Backspace, cx) {
+            return;
+        }
+        self.engine.handle_action(EditorAction::Backspace);
+        self.sync_and_save(cx);
+        cx.notify();
+    }
+
+    fn handle_delete(&mut self, _: &Delete, _window: &mut Window, cx: &mut Context<Self>) {
+        self.engine.handle_action(EditorAction::Delete);
+        self.sync_and_save(cx);
+        cx.notify();
+    }
+
+    fn delete_to_beginning_of_line(
+        &mut self,
+        _: &DeleteToBeginningOfLine,
+        _window: &mut Window,
+        cx: &mut Context<Self>,
+    ) {
+        self.engine
+            .handle_action(EditorAction::DeleteToBeginningOfLine);
+        self.sync_and_save(cx);
+        cx.notify();
+    }
+
+    fn delete_to_end_of_line(
+        &mut self,
+        _: &DeleteToEndOfLine,
+        _window: &mut Window,
+        cx: &mut Context<Self>,
+    ) {
+        self.engine.handle_action(EditorAction::DeleteToEndOfLine);
+        self.sync_and_save(cx);
+        cx.notify();
+    }
+
+    fn move_to_beginning_of_line(
+        &mut self,
+        _: &MoveToBeginningOfLine,
+        _window: &mut Window,
+        cx: &mut Context<Self>,
+    ) {
+        self.engine
+            .handle_action(EditorAction::MoveToBeginningOfLine);
+        self.ensure_cursor_visible();
+        cx.notify();
+    }
+
+    fn move_to_end_of_line(
+        &mut self,
+        _: &MoveToEndOfLine,
+        _window: &mut Window,
+        cx: &mut Context<Self>,
+    ) {
+        self.engine.handle_action(EditorAction::MoveToEndOfLine);
+        self.ensure_cursor_visible();
+        cx.notify();
+    }
+
+    fn move_left(&mut self, _: &MoveLeft, _window: &mut Window, cx: &mut Context<Self>) {
+        if self.handle_panel_key(PanelKey::Left, cx) {
+            return;
+        }
+        self.engine.handle_action(EditorAction::MoveLeft);
+        self.ensure_cursor_visible();
+        cx.notify();
+    }
+
+    fn move_right(&mut self, _: &MoveRight, _window: &mut Window, cx: &mut Context<Self>) {
+        if self.handle_panel_key(PanelKey::Right, cx) {
+            return;
+        }
+        self.engine.handle_action(EditorAction::MoveRight);
+        self.ensure_cursor_visible();
+        cx.notify();
+    }
+
+    fn move_up(&mut self, _: &MoveUp, _window: &mut Window, cx: &mut Context<Self>) {
+        if self.handle_panel_key(PanelKey::Up, cx) {
+            return;
+        }
+        self.engine.handle_action(EditorAction::MoveUp);
+        self.ensure_cursor_visible();
+        cx.notify();
+    }
+
+    fn move_down(&mut self, _: &MoveDown, _window: &mut Window, cx: &mut Context<Self>) {
+        if self.handle_panel_key(PanelKey::Down, cx) {
+            return;
+        }
+        self.engine.handle_action(EditorAction::MoveDown);
+        self.ensure_cursor_visible();
+        cx.notify();
+    }
+
+    fn move_word_left(&mut self, _: &MoveWordLeft, _: &mut Window, cx: &mut Context<Self>) {
+        self.engine.handle_action(EditorAction::MoveWordLeft);
+        self.ensure_cursor_visible();
+        cx.notify();
+    }
+
+    fn move_word_right(&mut self, _: &MoveWordRight, _: &mut Window, cx: &mut Context<Self>) {
+        self.engine.handle_action(EditorAction::MoveWordRight);
+        self.ensure_cursor_visible();
+        cx.notify();
+    }
+
+    fn move_to_next_sentence(
+        &mut self,
+        _: &MoveToNextSentence,
+        _: &mut Window,
+        cx: &mut Context<Self>,
+    ) {
+        self.engine.handle_action(EditorAction::MoveToNextSentence);
+        self.ensure_cursor_visible();
+        cx.notify();
+    }
+
+    fn move_to_prev_sentence(
+        &mut self,
+        _: &MoveToPrevSentence,
+        _: &mut Window,
+        cx: &mut Context<Self>,
+    ) {
+        self.engine.handle_action(EditorAction::MoveToPrevSentence);
+        self.ensure_cursor_visible();
+        cx.notify();
+    }
+
+    fn jump_to_char(&mut self, _: &JumpToChar, _: &mut Window, cx: &mut Context<Self>) {
+        self.engine.handle_action(EditorAction::JumpToChar);
+        cx.notify();
+    }
+
+    fn jump_before_char(&mut self, _: &JumpBeforeChar, _: &mut Window, cx: &mut Context<Self>) {
+        self.engine.handle_action(EditorAction::JumpBeforeChar);
+        cx.notify();
+    }
+
+    fn leap(&mut self, _: &Leap, _: &mut Window, cx: &mut Context<Self>) {
+        self.engine.handle_action(EditorAction::Leap);
+        cx.notify();
+    }
+
+    fn move_line_up(&mut self, _: &MoveLineUp, _: &mut Window, cx: &mut Context<Self>) {
+        self.engine.handle_action(EditorAction::MoveLineUp);
+        self.sync_and_save(cx);
+        cx.notify();
+    }
+
+    fn move_line_down(&mut self, _: &MoveLineDown, _: &mut Window, cx: &mut Context<Self>) {
+        self.engine.handle_action(EditorAction::MoveLineDown);
+        self.sync_and_save(cx);
+        cx.notify();
+    }
+
+    fn move_section_up(&mut self, _: &MoveSectionUp, _: &mut Window, cx: &mut Context<Self>) {
+        self.engine.handle_action(EditorAction::MoveSectionUp);
+        self.sync_and_save(cx);
+        self.ensure_cursor_visible();
+        cx.notify();
+    }
+
+    fn move_section_down(&mut self, _: &MoveSectionDown, _: &mut Window, cx: &mut Context<Self>) {
+        self.engine.handle_action(EditorAction::MoveSectionDown);
+        self.sync_and_save(cx);
+        self.ensure_cursor_visible();
+        cx.notify();
+    }
+
+    fn promote_section(&mut self, _: &PromoteSection, _: &mut Window, cx: &mut Context<Self>) {
+        self.engine.handle_action(EditorAction::PromoteSection);
+        self.sync_and_save(cx);
+        cx.notify();
+    }
+
+    fn demote_section(&mut self, _: &DemoteSection, _: &mut Window, cx: &mut Context<Self>) {
+        self.engine.handle_action(EditorAction::DemoteSection);
+        self.sync_and_save(cx);
+        cx.notify();
+    }
+
+    fn select_section(&mut self, _: &SelectSection, _: &mut Window, cx: &mut Context<Self>) {
+        self.engine.handle_action(EditorAction::SelectSection);
+        self.ensure_cursor_visible();
+        cx.notify();
+    }
+
+    fn insert_footnote(&mut self, _: &InsertFootnote, _: &mut Window, cx: &mut Context<Self>) {
+        self.engine.handle_action(EditorAction::InsertFootnote);
+        self.sync_and_save(cx);
+        self.ensure_cursor_visible();
+        cx.notify();
+    }
+
+    fn renumber_footnotes(
+        &mut self,
+        _: &RenumberFootnotes,
+        _: &mut Window,
+        cx: &mut Context<Self>,
+    ) {
+        self.engine.handle_action(EditorAction::RenumberFootnotes);
+        self.sync_and_save(cx);
+        cx.notify();
+    }
+
+    fn go_to_footnote(&mut self, _: &GoToFootnote, _: &mut Window, cx: &mut Context<Self>) {
+        self.engine.handle_action(EditorAction::GoToFootnote);
+        self.ensure_cursor_visible();
+        cx.notify();
+    }
+
+    fn insert_bibliography(
+        &mut self,
+        _: &InsertBibliography,
+        _: &mut Window,
+        cx: &mut Context<Self>,
+    ) {
+        self.engine.handle_action(EditorAction::InsertBibliography);
+        self.sync_and_save(cx);
+        self.ensure_cursor_visible();
+        cx.notify();
+    }
+
+    fn delete_line(&mut self, _: &DeleteLine, _: &mut Window, cx: &mut Context<Self>) {
+        self.engine.handle_action(EditorAction::DeleteLine);
+        self.sync_and_save(cx);
+        cx.notify();
+    }
+
+    fn insert_date(&mut self, _: &InsertDate, _: &mut Window, cx: &mut Context<Self>) {
+        self.engine.handle_action(EditorAction::InsertDate);
+        self.sync_and_save(cx);
+        cx.notify();
+    }
+
+    fn insert_time(&mut self, _: &InsertTime, _: &mut Window, cx: &mut Context<Self>) {
+        self.engine.handle_action(EditorAction::InsertTime);
+        self.sync_and_save(cx);
+        cx.notify();
+    }
+
+    fn insert_timestamp(&mut self, _: &InsertTimestamp, _: &mut Window, cx: &mut Context<Self>) {
+        self.engine.handle_action(EditorAction::InsertTimestamp);
+        self.sync_and_save(cx);
+        cx.notify();
+    }
+
+    fn show_character_picker(
+        &mut self,
+        _: &ShowCharacterPicker,
+        _: &mut Window,
+        cx: &mut Context<Self>,
+    ) {
+        self.toggle_char_picker("");
+        cx.notify();
+    }
+
+    fn insert_unicode_codepoint(
+        &mut self,
+        _: &InsertUnicodeCodepoint,
+        _: &mut Window,
+        cx: &mut Context<Self>,
+    ) {
+        self.toggle_char_picker("U+");
+        cx.notify();
+    }
+
+    fn toggle_char_picker(&mut self, query: &str) {
+        if self.char_picker.take().is_none() {
+            self.char_picker = Some(CharPicker::new(query));
+        }
+    }
+
+    /// Keys for the character picker: Enter inserts the selected character
+    /// at the cursor
+    fn handle_char_picker_key(&mut self, key: PanelKey, cx: &mut Context<Self>) {
+        let Some(picker) = self.char_picker.as_mut() else {
+            return;
+        };
+        match key {
+            PanelKey::Escape => self.char_picker = None,
+            PanelKey::Enter => self.insert_selected_char(cx),
+            PanelKey::Up => picker.select_previous(),
+            PanelKey::Down => picker.select_next(),
+            PanelKey::Backspace => picker.backspace(),
+            PanelKey::Text(text) => picker.type_text(&text),
+            PanelKey::Tab | PanelKey::Left | PanelKey::Right => {}
+        }
+    }
+
+    fn insert_selected_char(&mut self, cx: &mut Context<Self>) {
+        let Some(picker) = self.char_picker.take() else {
+            return;
+        };
+        let action = match picker.selected_symbol() {
+            _ if picker.codepoint_selected() => EditorAction::InsertUnicodeCodepoint(picker.query),
+            Some(symbol) => EditorAction::TypeString(symbol.text.clone()),
+            None => return,
+        };
+        self.engine.handle_action(action);
+        self.sync_and_save(cx);
+    }
+
+    fn handle_tab(&mut self, _: &Tab, _: &mut Window, cx: &mut Context<Self>) {
+        if self.handle_panel_key(PanelKey::Tab, cx) {
+            return;
+        }
+        self.engine.handle_action(EditorAction::Tab);
+        self.sync_and_save(cx);
+        cx.notify();
+    }
+
+    fn handle_outdent(&mut self, _: &Outdent, _: &mut Window, cx: &mut Context<Self>) {
+        self.engine.handle_action(EditorAction::Outdent);
+        self.sync_and_save(cx);
+        cx.notify();
+    }
+
+    fn trigger_completion(
+        &mut self,
+        _: &TriggerCompletion,
+        _: &mut Window,
+        cx: &mut Context<Self>,
+    ) {
+        self.request_from_lsp(EditorAction::TriggerCompletion);
+        cx.notify();
+    }
+
+    fn hover(&mut self, _: &Hover, _: &mut Window, cx: &mut Context<Self>) {
+        self.request_from_lsp(EditorAction::Hover);
+        cx.notify();
+    }
+
+    fn go_to_definition(&mut self, _: &GoToDefinition, _: &mut Window, cx: &mut Context<Self>) {
+        if self.lsp.is_open() {
+            self.request_from_lsp(EditorAction::GoToDefinition);
+        } else {
+            self.find_symbol(false);
+        }
+        cx.notify();
+    }
+
+    fn find_references(&mut self, _: &FindReferences, _: &mut Window, cx: &mut Context<Self>) {
+        self.find_symbol(true);
+        cx.notify();
+    }
+
+    /// Look up the name at the cursor in the workspace, for files no
+    /// language server answers for: its definitions, jumped to straight
+    /// away when there is one, or with `references` every use of it, listed
+    /// in the find-in-files panel
+    fn find_symbol(&mut self, references: bool) {
+        let state = self.engine.state();
+        let Some((start, end)) = text_objects::word_range(&state.lines, state.cursor) else {
+            let message = "No name at the cursor".to_string();
+            self.engine.set_hover(Some(message));
+            return;
+        };
+        let name = state.lines[start.row][start.column..end.column].to_string();
+        let root = self.workspace_root();
+        let panel = self.search_panel.get_or_insert_with(SearchPanel::load);
+        if references {
+            panel.find_references(&root, &name);
+            self.definition_pending = None;
+            self.show_search_panel(false);
+        } else {
+            panel.find_definitions(&root, &name);
+            let message = format!("Finding {}…", name);
+            self.engine.set_hover(Some(message));
+            self.definition_pending = Some(name);
+        }
+    }
+
+    /// Once the definitions being found are in, jump to the only one or
+    /// list them
+    fn resolve_definition(&mut self, cx: &mut Context<Self>) {
+        let Some(count) = self
+            .search_panel
+            .as_ref()
+            .and_then(SearchPanel::search)
+            .filter(|search| search.is_done())
+            .map(ProjectSearch::match_count)
+        else {
+            return;
+        };
+        let Some(name) = self.definition_pending.take() else {
+            return;
+        };
+        self.engine.set_hover(None);
+        match count {
+            0 => {
+                let message = format!("No definition of {} found", name);
+                self.engine.set_hover(Some(message));
+            }
+            1 => self.jump_to_search_result(),
+            _ => self.show_search_panel(false),
+        }
+        cx.notify();
+    }
+
+    fn cancel(&mut self, _: &Cancel, _: &mut Window, cx: &mut Context<Self>) {
+        if self.handle_panel_key(PanelKey::Escape, cx) {
+            return;
+        }
+        self.engine.handle_action(EditorAction::Cancel);
+        cx.notify();
+    }
+
+    fn next_match(&mut self, _: &NextMatch, _: &mut Window, cx: &mut Context<Self>) {
+        self.engine.handle_action(EditorAction::NextMatch);
+        self.ensure_cursor_visible();
+        cx.notify();
+    }
+
+    fn prev_match(&mut self, _: &PrevMatch, _: &mut Window, cx: &mut Context<Self>) {
+        self.engine.handle_action(EditorAction::PrevMatch);
+        self.ensure_cursor_visible();
+        cx.notify();
+    }
+
+    fn select_all_matches(&mut self, _: &SelectAllMatches, _: &mut Window, cx: &mut Context<Self>) {
+        self.engine.handle_action(EditorAction::SelectAllMatches);
+        cx.notify();
+    }
+
+    fn next_diagnostic(&mut self, _: &NextDiagnostic, _: &mut Window, cx: &mut Context<Self>) {
+        self.engine.handle_action(EditorAction::NextDiagnostic);
+        self.ensure_cursor_visible();
+        cx.notify();
+    }
+
+    fn prev_diagnostic(&mut self, _: &PrevDiagnostic, _: &mut Window, cx: &mut Context<Self>) {
+        self.engine.handle_action(EditorAction::PrevDiagnostic);
+        self.ensure_cursor_visible();
+        cx.notify();
+    }
+
+    fn toggle_bookmark(&mut self, _: &ToggleBookmark, _: &mut Window, cx: &mut Context<Self>) {
+        self.engine.handle_action(EditorAction::ToggleBookmark);
+        cx.notify();
+    }
+
+    fn next_bookmark(&mut self, _: &NextBookmark, _: &mut Window, cx: &mut Context<Self>) {
+        self.engine.handle_action(EditorAction::NextBookmark);
+        self.ensure_cursor_visible();
+        cx.notify();
+    }
+
+    fn prev_bookmark(&mut self, _: &PrevBookmark, _: &mut Window, cx: &mut Context<Self>) {
+        self.engine.handle_action(EditorAction::PrevBookmark);
+        self.ensure_cursor_visible();
+        cx.notify();
+    }
+
+    fn next_change(&mut self, _: &NextChange, _: &mut Window, cx: &mut Context<Self>) {
+        let state = self.engine.state();
+        if let Some(target) = self
+            .git_gutter
+            .next_change(state.cursor.row, state.lines.len())
+        {
+            self.engine.handle_action(EditorAction::SetCursorPosition {
+                row: target.row,
+                column: target.column,
+            });
+            self.ensure_cursor_visible();
+            cx.notify();
+        }
+    }
+
+    fn prev_change(&mut self, _: &PrevChange, _: &mut Window, cx: &mut Context<Self>) {
+        let state = self.engine.state();
+        if let Some(target) = self
+            .git_gutter
+            .prev_change(state.cursor.row, state.lines.len())
+        {
+            self.engine.handle_action(EditorAction::SetCursorPosition {
+                row: target.row,
+                column: target.column,
+            });
+            self.ensure_cursor_visible();
+            cx.notify();
+        }
+    }
+
+    /// The changed hunk at the cursor, or a note saying there is none
+    fn hunk_at_cursor(&mut self) -> Option<Hunk> {
+        let state = self.engine.state();
+        let hunk = self.git_gutter.hunk_at(state.cursor.row, state.lines.len());
+        if hunk.is_none() {
+            self.engine
+                .set_hover(Some("No change at the cursor".to_string()));
+        }
+        hunk
+    }
+
+    fn preview_hunk(&mut self, _: &PreviewHunk, _: &mut Window, cx: &mut Context<Self>) {
+        if let Some(hunk) = self.hunk_at_cursor() {
+            let preview = self.git_gutter.preview(&hunk, &self.engine.state().lines);
+            self.engine.set_hover(Some(preview));
+        }
+        cx.notify();
+    }
+
+    fn revert_hunk(&mut self, _: &RevertHunk, _: &mut Window, cx: &mut Context<Self>) {
+        if let Some(hunk) = self.hunk_at_cursor() {
+            let reverted = self.git_gutter.revert(&hunk, &self.engine.state().lines);
+            self.engine
+                .set_lines(reverted, zlyph_core::BufferPosition::new(hunk.new_start, 0));
+            self.sync_and_save(cx);
+        }
+        cx.notify();
+    }
+
+    fn stage_hunk(&mut self, _: &StageHunk, _: &mut Window, cx: &mut Context<Self>) {
+        if let Some(hunk) = self.hunk_at_cursor() {
+            let message = match self.git_gutter.stage(&hunk, &self.engine.state().lines) {
+                Ok(()) => "Staged".to_string(),
+                Err(e) => format!("Could not stage: {}", e),
+            };
+            self.engine.set_hover(Some(message));
+        }
+        cx.notify();
+    }
+
+    fn diff_with_saved(&mut self, _: &DiffWithSaved, _: &mut Window, cx: &mut Context<Self>) {
+        self.open_diff_view(DiffSource::Saved);
+        cx.notify();
+    }
+
+    fn diff_with_head(&mut self, _: &DiffWithHead, _: &mut Window, cx: &mut Context<Self>) {
+        self.open_diff_view(DiffSource::Revision("HEAD".to_string()));
+        cx.notify();
+    }
+
+    fn diff_with_file(&mut self, _: &DiffWithFile, _: &mut Window, cx: &mut Context<Self>) {
+        let paths = cx.prompt_for_paths(PathPromptOptions {
+            files: true,
+            directories: false,
+            multiple: false,
+            prompt: Some("Compare".into()),
+        });
+        cx.spawn(async move |this, cx| {
+            if let Ok(Ok(Some(paths))) = paths.await {
+                if let Some(path) = paths.into_iter().next() {
+                    let _ = this.update(cx, |editor, cx| {
+                        editor.open_diff_view(DiffSource::File(path));
+                        cx.notify();
+                    });
+                }
+            }
+        })
+        .detach();
+    }
+
+    /// Compare the buffer with `source` in the diff view, or say why not
+    fn open_diff_view(&mut self, source: DiffSource) {
+        let Some(path) = self.file_path.clone() else {
+            self.engine
+                .set_hover(Some("Save the file before comparing it".to_string()));
+            return;
+        };
+        match DiffView::open(&source, &path, &self.engine.state().lines) {
+            Ok(view) => self.diff_view = Some(view),
+            Err(e) => self
+                .engine
+                .set_hover(Some(format!("Cannot compare: {}", e))),
+        }
+    }
+
+    fn show_local_history(&mut self, _: &ShowLocalHistory, _: &mut Window, cx: &mut Context<Self>) {
+        let snapshots = self
+            .file_path
+            .as_deref()
+            .and_then(|path| self.history.snapshots(path).ok())
+            .unwrap_or_default();
+        if snapshots.is_empty() {
+            self.engine
+                .set_hover(Some("No local history for this file".to_string()));
+        } else {
+            self.history_browser = Some(HistoryBrowser::new(snapshots));
+        }
+        cx.notify();
+    }
+
+    /// Where `[[links]]` in the current file are looked up
+    fn notes_dir(&self) -> Option<PathBuf> {
+        let path = self.file_path.as_deref()?;
+        Some(notes::notes_dir(&self.engine.config().notes, path))
+    }
+
+    /// Offer the notes next to the current one when completing `[[links]]`
+    fn refresh_note_names(&mut self) {
+        let names = match (self.file_path.as_deref(), self.notes_dir()) {
+            (Some(path), Some(dir)) if notes::is_note(path) && !vfs::is_remote(path) => {
+                notes::note_names(&dir)
+            }
+            _ => Vec::new(),
+        };
+        self.engine.set_note_names(names);
+    }
+
+    /// Open the `[[note]]`, Markdown link, URL or file path written at a
+    /// position; returns whether there was one
+    fn follow_link(&mut self, row: usize, column: usize, cx: &mut Context<Self>) -> bool {
+        let line = &self.engine.state().lines[row];
+        if let (Some(link), Some(dir)) = (notes::link_at(line, column), self.notes_dir()) {
+            self.open_file(notes::note_path(&dir, &link.name));
+            return true;
+        }
+        if let (Some(target), Some(file)) = (
+            self.highlighter.link_at(row, column),
+            self.file_path.as_deref(),
+        ) {
+            let target = LinkTarget::resolve(target, file);
+            self.open_link_target(target, cx);
+            return true;
+        }
+        let Some(link) = links::link_at(line, column) else {
+            return false;
+        };
+        match link.resolve(self.file_path.as_deref()) {
+            // A bare path is only a guess, so it has to exist
+            LinkTarget::File(path) if !path.is_file() => {
+                self.engine
+                    .set_hover(Some(format!("No file at {}", path.display())));
+            }
+            target => {
+                if self.open_link_target(target, cx) {
+                    if let Some(line) = link.line {
+                        self.engine.handle_action(EditorAction::SetCursorPosition {
+                            row: line.saturating_sub(1),
+                            column: link.column.unwrap_or(1).saturating_sub(1),
+                        });
+                    }
+                }
+            }
+        }
+        true
+    }
+
+    /// Open a URL in the browser or a file in the editor; returns whether a
+    /// file was opened
+    fn open_link_target(&mut self, target: LinkTarget, cx: &mut Context<Self>) -> bool {
+        match target {
+            LinkTarget::Url(url) => {
+                cx.open_url(&url);
+                false
+            }
+            LinkTarget::File(path) => self.open_file(path),
+        }
+    }
+
+    /// The URL or file path under the mouse, for underlining while Cmd/Ctrl
+    /// is held
+    fn link_under_mouse(&self, position: BufferPosition) -> Option<(usize, Range<usize>)> {
+        let line = self.engine.state().lines.get(position.row)?;
+        let link = links::link_at(line, position.column)?;
+        Some((position.row, link.range))
+    }
+
+    fn follow_link_at_cursor(&mut self, _: &FollowLink, _: &mut Window, cx: &mut Context<Self>) {
+        let cursor = self.engine.state().cursor;
+        if !self.follow_link(cursor.row, cursor.column, cx) {
+            self.engine
+                .set_hover(Some("No link at the cursor".to_string()));
+        }
+        cx.notify();
+    }
+
+    fn open_daily_note(&mut self, _: &OpenDailyNote, _: &mut Window, cx: &mut Context<Self>) {
+        self.step_daily_note(None, cx);
+        cx.notify();
+    }
+
+    fn previous_daily_note(
+        &mut self,
+        _: &PreviousDailyNote,
+        _: &mut Window,
+        cx: &mut Context<Self>,
+    ) {
+        self.step_daily_note(Some(false), cx);
+        cx.notify();
+    }
+
+    fn next_daily_note(&mut self, _: &NextDailyNote, _: &mut Window, cx: &mut Context<Self>) {
+        self.step_daily_note(Some(true), cx);
+        cx.notify();
+    }
+
+    /// Open today's note, or with `step` the previous or next day's that
+    /// exists
+    fn step_daily_note(&mut self, step: Option<bool>, cx: &mut Context<Self>) {
+        let journal = Journal::new(self.engine.config());
+        let date = match step {
+            None => Journal::today(),
+            Some(forward) => match journal.adjacent(self.file_path.as_deref(), forward) {
+                Some(date) => date,
+                None => {
+                    let which = if forward { "later" } else { "earlier" };
+                    self.engine
+                        .set_hover(Some(format!("No {} daily note", which)));
+                    return;
+                }
+            },
+        };
+        match journal.open(date, Self::clipboard_text(cx)) {
+            Ok(path) => {
+                self.open_file(path);
+            }
+            Err(e) => self
+                .engine
+                .set_hover(Some(format!("Cannot open daily note: {}", e))),
+        }
+    }
+
+    fn search_notes(&mut self, _: &SearchNotes, _: &mut Window, cx: &mut Context<Self>) {
+        if self.note_search_visible {
+            self.note_search_visible = false;
+        } else if let Some(dir) = self.notes_dir() {
+            if self
+                .note_search
+                .as_ref()
+                .is_none_or(|search| search.dir() != dir)
+            {
+                self.note_search = Some(NoteSearch::start(&dir));
+            }
+            self.note_search_visible = true;
+        }
+        cx.notify();
+    }
+
+    /// Keys for the note search: Enter opens the selected note at the
+    /// matching line
+    fn handle_note_search_key(&mut self, key: PanelKey) {
+        let Some(search) = self.note_search.as_mut() else {
+            return;
+        };
+        match key {
+            PanelKey::Escape => self.note_search_visible = false,
+            PanelKey::Enter => self.open_selected_note_hit(),
+            PanelKey::Up => search.select_previous(),
+            PanelKey::Down => search.select_next(),
+            PanelKey::Backspace => search.backspace(),
+            PanelKey::Text(text) => search.type_text(&text),
+            PanelKey::Tab | PanelKey::Left | PanelKey::Right => {}
+        }
+    }
+
+    fn open_selected_note_hit(&mut self) {
+        let Some(hit) = self
+            .note_search
+            .as_ref()
+            .and_then(|search| search.selected_hit().cloned())
+        else {
+            return;
+        };
+        self.note_search_visible = false;
+        if self.open_file(hit.path) {
+            self.set_cursor(BufferPosition::new(hit.row, 0));
+        }
+    }
+
+    fn show_tags(&mut self, _: &ShowTags, _: &mut Window, cx: &mut Context<Self>) {
+        if self.tags.take().is_none() {
+            self.tags = self.notes_dir().map(|dir| TagIndex::start(&dir));
+        }
+        cx.notify();
+    }
+
+    /// Keys for the tags list: Enter lists the uses of the selected tag in
+    /// the find-in-files panel, Escape or q closes the list
+    fn handle_tags_key(&mut self, key: PanelKey) {
+        let Some(index) = self.tags.as_mut() else {
+            return;
+        };
+        match key {
+            PanelKey::Escape => self.tags = None,
+            PanelKey::Up => index.select_previous(),
+            PanelKey::Down => index.select_next(),
+            PanelKey::Enter => self.find_selected_tag(),
+            PanelKey::Text(text) if text == "q" => self.tags = None,
+            _ => {}
+        }
+    }
+
+    fn find_selected_tag(&mut self) {
+        let Some(index) = self.tags.take() else {
+            return;
+        };
+        let Some(tag) = index.selected_tag() else {
+            return;
+        };
+        self.show_search_panel(false);
+        if let Some(panel) = self.search_panel.as_mut() {
+            panel.find_tag(&index.dir, &tag.name);
+        }
+    }
+
+    fn show_todos(&mut self, _: &ShowTodos, _: &mut Window, cx: &mut Context<Self>) {
+        if self.todos.take().is_none() {
+            self.todos = Some(TodoPanel::for_buffer(&self.engine.state().lines));
+        }
+        cx.notify();
+    }
+
+    /// Keys for the TODO list: typing narrows it, Tab switches between the
+    /// file and every note, Enter jumps to the selected item, Escape closes
+    /// the list
+    fn handle_todos_key(&mut self, key: PanelKey) {
+        let Some(panel) = self.todos.as_mut() else {
+            return;
+        };
+        match key {
+            PanelKey::Escape => self.todos = None,
+            PanelKey::Up => panel.select_previous(),
+            PanelKey::Down => panel.select_next(),
+            PanelKey::Backspace => panel.backspace(),
+            PanelKey::Text(text) => panel.type_text(&text),
+            PanelKey::Enter => self.open_selected_todo(),
+            PanelKey::Tab => {
+                let switched = if panel.dir().is_some() {
+                    Some(TodoPanel::for_buffer(&self.engine.state().lines))
+                } else {
+                    self.notes_dir().map(|dir| TodoPanel::start(&dir))
+                };
+                if let (Some(mut switched), Some(panel)) = (switched, self.todos.as_mut()) {
+                    switched.query = std::mem::take(&mut panel.query);
+                    self.todos = Some(switched);
+                }
+            }
+            PanelKey::Left | PanelKey::Right => {}
+        }
+    }
+
+    fn open_selected_todo(&mut self) {
+        let Some(item) = self
+            .todos
+            .take()
+            .and_then(|panel| panel.selected_item().cloned())
+        else {
+            return;
+        };
+        if item.path.is_none_or(|path| self.open_file(path)) {
+            self.engine.handle_action(EditorAction::SetCursorPosition {
+                row: item.row,
+                column: item.column,
+            });
+            self.ensure_cursor_visible();
+        }
+    }
+
+    /// Run the project's build or lint task again and list its errors
+    fn run_task(&mut self, _: &RunTask, _: &mut Window, cx: &mut Context<Self>) {
+        self.start_task(false);
+        cx.notify();
+    }
+
+    /// Run the task last run again, or with `next` the one after it
+    fn start_task(&mut self, next: bool) {
+        let file = self
+            .file_path
+            .clone()
+            .unwrap_or_else(|| std::env::current_dir().unwrap_or_default().join("untitled"));
+        let root = task_runner::project_root(&file);
+        let tasks = task_runner::tasks_for(&self.engine.config().tasks, &root);
+        let last = self
+            .task_run
+            .as_ref()
+            .and_then(|run| tasks.iter().position(|task| *task == run.task));
+        let index = match last {
+            Some(last) if next => (last + 1) % tasks.len(),
+            Some(last) => last,
+            None => 0,
+        };
+        let Some(task) = tasks.get(index) else {
+            let message = "No tasks for this project; add [[tasks]] to the config".to_string();
+            self.engine.set_hover(Some(message));
+            return;
+        };
+        // Stop the last run before starting another
+        self.task_run = None;
+        match TaskRun::start(task, &root) {
+            Ok(run) => {
+                self.task_run = Some(run);
+                self.errors_visible = true;
+            }
+            Err(e) => {
+                let message = format!("Could not run {}: {}", task.command, e);
+                self.engine.set_hover(Some(message));
+            }
+        }
+        self.publish_problems();
+    }
+
+    fn show_errors(&mut self, _: &ShowErrors, _: &mut Window, cx: &mut Context<Self>) {
+        if self.task_run.is_some() {
+            self.errors_visible = !self.errors_visible;
+        } else {
+            let message = "No task has run; F9 runs one".to_string();
+            self.engine.set_hover(Some(message));
+        }
+        cx.notify();
+    }
+
+    fn next_error(&mut self, _: &NextError, _: &mut Window, cx: &mut Context<Self>) {
+        self.step_error(false);
+        cx.notify();
+    }
+
+    fn previous_error(&mut self, _: &PreviousError, _: &mut Window, cx: &mut Context<Self>) {
+        self.step_error(true);
+        cx.notify();
+    }
+
+    /// Jump to the task's next error, or with `back` the previous one, in
+    /// whichever file it is
+    fn step_error(&mut self, back: bool) {
+        let Some(run) = self.task_run.as_mut() else {
+            let message = "No task has run; F9 runs one".to_string();
+            self.engine.set_hover(Some(message));
+            return;
+        };
+        match run.step(back) {
+            Some(problem) => self.jump_to_problem(problem),
+            None => {
+                let status = run.status();
+                self.engine.set_hover(Some(status));
+            }
+        }
+    }
+
+    /// Keys for the error list: Enter jumps to the selected error, Tab
+    /// runs the next task, Escape closes the list
+    fn handle_errors_key(&mut self, key: PanelKey) {
+        let Some(run) = self.task_run.as_mut() else {
+            return;
+        };
+        match key {
+            PanelKey::Escape => self.errors_visible = false,
+            PanelKey::Up => run.select_previous(),
+            PanelKey::Down => run.select_next(),
+            PanelKey::Enter => self.open_selected_problem(),
+            PanelKey::Tab => self.start_task(true),
+            _ => {}
+        }
+    }
+
+    fn open_selected_problem(&mut self) {
+        if let Some(problem) = self.task_run.as_mut().and_then(TaskRun::open_selected) {
+            self.errors_visible = false;
+            self.jump_to_problem(problem);
+        }
+    }
+
+    /// Show the task's problems in the current file as diagnostics
+    fn publish_problems(&mut self) {
+        let diagnostics = match (self.task_run.as_ref(), self.file_path.as_deref()) {
+            (Some(run), Some(path)) => {
+                task_runner::diagnostics(run.problems(), path, &self.engine.state().lines)
+            }
+            _ => Vec::new(),
+        };
+        self.engine
+            .set_diagnostics(task_runner::DIAGNOSTIC_SOURCE, diagnostics);
+    }
+
+    /// Open the file of `problem` and put the cursor on it
+    fn jump_to_problem(&mut self, problem: Problem) {
+        let current = self
+            .file_path
+            .as_deref()
+            .and_then(|path| path.canonicalize().ok());
+        if current.as_ref() != Some(&problem.path) && !self.open_file(problem.path.clone()) {
+            return;
+        }
+        let column = self
+            .engine
+            .state()
+            .lines
+            .get(problem.row)
+            .map_or(0, |line| problem.byte_column(line));
+        self.engine.handle_action(EditorAction::SetCursorPosition {
+            row: problem.row,
+            column,
+        });
+        self.ensure_cursor_visible();
+        let message = format!("{}: {}", problem.severity.label(), problem.message);
+        self.engine.set_hover(Some(message));
+    }
+
+    /// Show the outline and focus it, or hide it when it has focus
+    fn toggle_outline(&mut self, _: &ToggleOutline, _: &mut Window, cx: &mut Context<Self>) {
+        if self.outline.is_none() {
+            self.outline = Some(SymbolPanel::default());
+            self.refresh_symbols();
+            self.outline_focused = true;
+        } else if self.outline_focused {
+            self.outline = None;
+            self.outline_focused = false;
+        } else {
+            self.outline_focused = true;
+        }
+        cx.notify();
+    }
+
+    /// Keys for the focused outline: typing narrows it, Enter jumps to the
+    /// selected symbol, Escape clears the query and then gives focus back
+    fn handle_outline_key(&mut self, key: PanelKey) {
+        let Some(panel) = self.outline.as_mut() else {
+            return;
+        };
+        match key {
+            PanelKey::Escape if !panel.query.is_empty() => {
+                panel.query.clear();
+                panel.selected = 0;
+            }
+            PanelKey::Escape => self.outline_focused = false,
+            PanelKey::Up => panel.select_previous(),
+            PanelKey::Down => panel.select_next(),
+            PanelKey::Backspace => panel.backspace(),
+            PanelKey::Text(text) => panel.type_text(&text),
+            PanelKey::Enter => self.open_selected_outline_symbol(),
+            PanelKey::Tab | PanelKey::Left | PanelKey::Right => {}
+        }
+    }
+
+    fn open_selected_outline_symbol(&mut self) {
+        let Some(panel) = self.outline.as_mut() else {
+            return;
+        };
+        let symbol = panel.selected_symbol().cloned();
+        panel.query.clear();
+        self.outline_focused = false;
+        if let Some(symbol) = symbol {
+            self.goto_symbol(&symbol);
+        }
+    }
+
+    fn go_to_symbol(&mut self, _: &GoToSymbol, _: &mut Window, cx: &mut Context<Self>) {
+        if self.symbol_palette.take().is_none() {
+            self.symbol_palette = Some(SymbolPanel::default());
+            self.refresh_symbols();
+        }
+        cx.notify();
+    }
+
+    /// Keys for the go-to-symbol palette: typing narrows it, Enter jumps to
+    /// the selected symbol, Escape closes it
+    fn handle_symbol_palette_key(&mut self, key: PanelKey) {
+        let Some(palette) = self.symbol_palette.as_mut() else {
+            return;
+        };
+        match key {
+            PanelKey::Escape => self.symbol_palette = None,
+            PanelKey::Up => palette.select_previous(),
+            PanelKey::Down => palette.select_next(),
+            PanelKey::Backspace => palette.backspace(),
+            PanelKey::Text(text) => palette.type_text(&text),
+            PanelKey::Enter => self.open_selected_palette_symbol(),
+            PanelKey::Tab | PanelKey::Left | PanelKey::Right => {}
+        }
+    }
+
+    fn open_selected_palette_symbol(&mut self) {
+        let Some(symbol) = self
+            .symbol_palette
+            .take()
+            .and_then(|palette| palette.selected_symbol().cloned())
+        else {
+            return;
+        };
+        self.goto_symbol(&symbol);
+    }
+
+    /// Read the buffer's symbols from its lines, and ask the language
+    /// server, whose answer replaces them when it comes
+    fn refresh_symbols(&mut self) {
+        self.symbols_revision = self.engine.revision();
+        if !self.server_symbols {
+            let symbols = symbols::outline(&self.engine.state().lines, self.file_path.as_deref());
+            self.set_symbols(symbols);
+        }
+        self.lsp.request_symbols(&self.engine);
+    }
+
+    fn set_symbols(&mut self, symbols: Vec<Symbol>) {
+        for panel in [self.outline.as_mut(), self.symbol_palette.as_mut()]
+            .into_iter()
+            .flatten()
+        {
+            panel.set_symbols(symbols.clone());
+        }
+        self.symbols = symbols;
+    }
+
+    /// Whether the breadcrumb trail is drawn; large files go without, as
+    /// reading their symbols on every edit would stall
+    fn breadcrumbs_shown(&self) -> bool {
+        self.breadcrumbs && !self.focus_mode && !self.embedded && !self.engine.is_large_file()
+    }
+
+    fn toggle_breadcrumbs(
+        &mut self,
+        _: &ToggleBreadcrumbs,
+        _: &mut Window,
+        cx: &mut Context<Self>,
+    ) {
+        self.breadcrumbs = !self.breadcrumbs;
+        let _ = Config::store_value("view", "breadcrumbs", self.breadcrumbs);
+        if self.breadcrumbs_shown() {
+            self.refresh_symbols();
+        }
+        cx.notify();
+    }
+
+    fn toggle_elastic_tabstops(
+        &mut self,
+        _: &ToggleElasticTabstops,
+        _: &mut Window,
+        cx: &mut Context<Self>,
+    ) {
+        self.elastic_tabstops = !self.elastic_tabstops;
+        let _ = Config::store_value("view", "elastic_tabstops", self.elastic_tabstops);
+        cx.notify();
+    }
+
+    fn toggle_rainbow_brackets(
+        &mut self,
+        _: &ToggleRainbowBrackets,
+        _: &mut Window,
+        cx: &mut Context<Self>,
+    ) {
+        self.rainbow_brackets = !self.rainbow_brackets;
+        let _ = Config::store_value("view", "rainbow_brackets", self.rainbow_brackets);
+        cx.notify();
+    }
+
+    /// Works out again how far the cell after each tab is padded, when
+    /// elastic tabstops are on and the text or its size has changed. Cells
+    /// are measured in spaces, so columns line up exactly in a monospaced
+    /// font; large files go without.
+    fn update_tab_layout(
+        &mut self,
+        font_size: Pixels,
+        wrap_width: Pixels,
+        text_system: &WindowTextSystem,
+    ) {
+        if !self.elastic_tabstops || self.engine.is_large_file() {
+            self.tab_layout = None;
+            return;
+        }
+        let revision = self.engine.revision();
+        if self
+            .tab_layout
+            .as_ref()
+            .is_some_and(|layout| layout.revision == revision && layout.font_size == font_size)
+        {
+            return;
+        }
+        let space_width =
+            shape_text(" ", self.buffer.font(), font_size, text_system).x_for_index(1);
+        let buffer = &mut self.buffer;
+        let padding = elastic_tabstops::layout(&self.engine.state().lines, |row, cell| {
+            buffer
+                .get_or_shape_line(row, font_size, wrap_width, text_system)
+                .map_or(0, |shaped| {
+                    let width = shaped.x_for_index(cell.end) - shaped.x_for_index(cell.start);
+                    (f32::from(width) / f32::from(space_width)).round() as usize
+                })
+        });
+        self.tab_layout = Some(TabLayout {
+            revision,
+            font_size,
+            space_width,
+            padding,
+        });
+    }
+
+    /// The file's name and the symbols the cursor is in, outermost first,
+    /// level with the traffic lights and clear of them; clicking a level
+    /// jumps to it, the file's name to the top
+    fn render_breadcrumbs(&self, left: Pixels, cx: &mut Context<Self>) -> Option<impl IntoElement> {
+        if !self.breadcrumbs_shown() {
+            return None;
+        }
+        let name = self
+            .file_path
+            .as_deref()
+            .and_then(Path::file_name)
+            .map_or_else(
+                || "Untitled".to_string(),
+                |name| name.to_string_lossy().into_owned(),
+            );
+        let row = self.engine.state().cursor.row;
+        let trail: Vec<(String, usize, usize)> = std::iter::once((name, 0, 0))
+            .chain(
+                symbols::breadcrumbs(&self.symbols, row)
+                    .into_iter()
+                    .map(|symbol| (symbol.name.clone(), symbol.row, symbol.column)),
+            )
+            .collect();
+        let last = trail.len() - 1;
+
+        let mut crumbs: Vec<Div> = Vec::new();
+        for (index, (name, row, column)) in trail.into_iter().enumerate() {
+            if index > 0 {
+                crumbs.push(div().text_color(self.theme.text_muted).child("▸"));
+            }
+            let color = if index == last {
+                self.theme.text
+            } else {
+                self.theme.text_muted
+            };
+            crumbs.push(
+                div()
+                    .flex_none()
+                    .text_color(color)
+                    .cursor(CursorStyle::PointingHand)
+                    .child(name)
+                    .on_mouse_down(
+                        MouseButton::Left,
+                        cx.listener(move |editor, _: &MouseDownEvent, _, cx| {
+                            editor
+                                .engine
+                                .handle_action(EditorAction::SetCursorPosition { row, column });
+                            editor.ensure_cursor_visible();
+                            cx.stop_propagation();
+                            cx.notify();
+                        }),
+                    ),
+            );
+        }
+
+        Some(
+            div()
+                .absolute()
+                .top(px(6.0))
+                .left(left.max(px(BREADCRUMB_LEFT)))
+                .right(px(16.0) + self.minimap_width() + self.outline_width())
+                .flex()
+                .items_center()
+                .gap_1()
+                .overflow_hidden()
+                .whitespace_nowrap()
+                .font(self.buffer.font().clone())
+                .text_size(px(12.0))
+                .children(crumbs),
+        )
+    }
+
+    fn goto_symbol(&mut self, symbol: &Symbol) {
+        self.engine.handle_action(EditorAction::SetCursorPosition {
+            row: symbol.row,
+            column: symbol.column,
+        });
+        self.ensure_cursor_visible();
+    }
+
+    fn new_from_template(&mut self, _: &NewFromTemplate, _: &mut Window, cx: &mut Context<Self>) {
+        if self.template_picker.take().is_none() {
+            let dir = templates::default_dir();
+            let list = templates::list(&dir);
+            if list.is_empty() {
+                self.engine
+                    .set_hover(Some(format!("No templates in {}", dir.display())));
+            } else {
+                self.template_picker = Some(TemplatePicker::new(list));
+            }
+        }
+        cx.notify();
+    }
+
+    /// Keys for the template list: Enter asks where to create the note,
+    /// Escape or q closes the list
+    fn handle_template_key(&mut self, key: PanelKey, cx: &mut Context<Self>) {
+        let Some(picker) = self.template_picker.as_mut() else {
+            return;
+        };
+        match key {
+            PanelKey::Escape => self.template_picker = None,
+            PanelKey::Up => picker.select_previous(),
+            PanelKey::Down => picker.select_next(),
+            PanelKey::Enter => self.create_from_selected_template(cx),
+            PanelKey::Text(text) if text == "q" => self.template_picker = None,
+            _ => {}
+        }
+    }
+
+    /// Ask where to create a note from the selected template, then open it
+    fn create_from_selected_template(&mut self, cx: &mut Context<Self>) {
+        let Some(template) = self
+            .template_picker
+            .take()
+            .and_then(|picker| picker.selected_template().map(Path::to_path_buf))
+        else {
+            return;
+        };
+        let directory = self
+            .notes_dir()
+            .or_else(|| std::env::current_dir().ok())
+            .unwrap_or_default();
+        let path = cx.prompt_for_new_path(&directory, None);
+        cx.spawn(async move |this, cx| {
+            if let Ok(Ok(Some(path))) = path.await {
+                let _ = this.update(cx, |editor, cx| {
+                    match templates::create(&path, &template, Self::clipboard_text(cx)) {
+                        Ok(()) => {
+                            editor.open_file(path);
+                        }
+                        Err(e) => editor
+                            .engine
+                            .set_hover(Some(format!("Cannot create note: {}", e))),
+                    }
+                    cx.notify();
+                });
+            }
+        })
+        .detach();
+    }
+
+    fn toggle_backlinks(&mut self, _: &ToggleBacklinks, _: &mut Window, cx: &mut Context<Self>) {
+        if self.backlinks.take().is_none() {
+            if let (Some(path), Some(dir)) = (self.file_path.as_deref(), self.notes_dir()) {
+                let panel = BacklinksPanel::load(&dir, path);
+                if panel.entries().is_empty() {
+                    self.engine
+                        .set_hover(Some(format!("No notes link to {}", panel.note)));
+                } else {
+                    self.backlinks = Some(panel);
+                }
+            }
+        }
+        cx.notify();
+    }
+
+    /// Keys for the backlinks list: Enter opens the selected note at the
+    /// linking line, Escape or q closes the list
+    fn handle_backlinks_key(&mut self, key: PanelKey) {
+        let Some(panel) = self.backlinks.as_mut() else {
+            return;
+        };
+        match key {
+            PanelKey::Escape => self.backlinks = None,
+            PanelKey::Up => panel.select_previous(),
+            PanelKey::Down => panel.select_next(),
+            PanelKey::Enter => self.open_selected_backlink(),
+            PanelKey::Text(text) if text == "q" => self.backlinks = None,
+            _ => {}
+        }
+    }
+
+    fn open_selected_backlink(&mut self) {
+        let Some(entry) = self
+            .backlinks
+            .take()
+            .and_then(|panel| panel.selected_entry().cloned())
+        else {
+            return;
+        };
+        if self.open_file(entry.path) {
+            self.set_cursor(BufferPosition::new(entry.row, 0));
+        }
+    }
+
+    fn show_bookmarks(&mut self, _: &ShowBookmarks, _: &mut Window, cx: &mut Context<Self>) {
+        if self.bookmarks.take().is_none() {
+            let state = self.engine.state();
+            let panel =
+                BookmarksPanel::new(&state.lines, &self.engine.bookmarks(), state.cursor.row);
+            if panel.entries().is_empty() {
+                self.engine.set_hover(Some("No bookmarks".to_string()));
+            } else {
+                self.bookmarks = Some(panel);
+            }
+        }
+        cx.notify();
+    }
+
+    /// Keys for the bookmarks list: Enter jumps to the selected line,
+    /// Escape or q closes the list
+    fn handle_bookmarks_key(&mut self, key: PanelKey) {
+        let Some(panel) = self.bookmarks.as_mut() else {
+            return;
+        };
+        match key {
+            PanelKey::Escape => self.bookmarks = None,
+            PanelKey::Up => panel.select_previous(),
+            PanelKey::Down => panel.select_next(),
+            PanelKey::Enter => self.open_selected_bookmark(),
+            PanelKey::Text(text) if text == "q" => self.bookmarks = None,
+            _ => {}
+        }
+    }
+
+    fn open_selected_bookmark(&mut self) {
+        let Some(row) = self
+            .bookmarks
+            .take()
+            .and_then(|panel| panel.selected_entry().map(|entry| entry.row))
+        else {
+            return;
+        };
+        self.engine
+            .handle_action(EditorAction::SetCursorPosition { row, column: 0 });
+        self.ensure_cursor_visible();
+    }
+
+    /// Keys for the local history list: Enter compares the selected snapshot
+    /// with the buffer, r restores it, Escape or q closes the list
+    fn handle_history_key(&mut self, key: PanelKey, cx: &mut Context<Self>) {
+        let Some(browser) = self.history_browser.as_mut() else {
+            return;
+        };
+        match key {
+            PanelKey::Escape => self.history_browser = None,
+            PanelKey::Up => browser.select_previous(),
+            PanelKey::Down => browser.select_next(),
+            PanelKey::Enter => self.open_selected_snapshot(false, cx),
+            PanelKey::Text(text) => match text.as_str() {
+                "q" => self.history_browser = None,
+                "r" => self.open_selected_snapshot(true, cx),
+                _ => {}
+            },
+            PanelKey::Backspace | PanelKey::Tab | PanelKey::Left | PanelKey::Right => {}
+        }
+    }
+
+    /// Compare the buffer with the selected snapshot, or restore it
+    fn open_selected_snapshot(&mut self, restore: bool, cx: &mut Context<Self>) {
+        let Some(snapshot) = self
+            .history_browser
+            .take()
+            .and_then(|browser| browser.selected_snapshot().cloned())
+        else {
+            return;
+        };
+        let text = match snapshot.read() {
+            Ok(text) => text,
+            Err(e) => {
+                self.engine
+                    .set_hover(Some(format!("Cannot read snapshot: {}", e)));
+                return;
+            }
+        };
+        let theirs: Vec<String> = text.split('\n').map(String::from).collect();
+        if restore {
+            self.snapshot_history();
+            self.engine
+                .set_lines(theirs, zlyph_core::BufferPosition::new(0, 0));
+            self.sync_and_save(cx);
+        } else {
+            let title = snapshot.age_label(std::time::SystemTime::now());
+            self.diff_view = Some(DiffView::new(title, theirs, &self.engine.state().lines));
+        }
+    }
+
+    /// Keys for the diff view: n/p or Down/Up move between hunks, t accepts
+    /// theirs, m keeps mine, Escape or q closes it
+    fn handle_diff_key(&mut self, key: PanelKey, cx: &mut Context<Self>) {
+        let Some(view) = self.diff_view.as_mut() else {
+            return;
+        };
+        match key {
+            PanelKey::Escape => self.diff_view = None,
+            PanelKey::Down => view.next_hunk(),
+            PanelKey::Up => view.prev_hunk(),
+            PanelKey::Text(text) => match text.as_str() {
+                "q" => self.diff_view = None,
+                "n" => view.next_hunk(),
+                "p" => view.prev_hunk(),
+                "m" => view.accept_mine(&self.engine.state().lines),
+                "t" => {
+                    if let Some((lines, row)) = view.accept_theirs(&self.engine.state().lines) {
+                        self.engine
+                            .set_lines(lines, zlyph_core::BufferPosition::new(row, 0));
+                        self.sync_and_save(cx);
+                        if let Some(view) = self.diff_view.as_mut() {
+                            view.refresh(&self.engine.state().lines);
+                        }
+                    }
+                }
+                _ => {}
+            },
+            PanelKey::Enter
+            | PanelKey::Backspace
+            | PanelKey::Tab
+            | PanelKey::Left
+            | PanelKey::Right => {}
+        }
+    }
+
+    fn quick_fix(&mut self, _: &QuickFix, _: &mut Window, cx: &mut Context<Self>) {
+        self.engine.handle_action(EditorAction::QuickFix);
+        cx.notify();
+    }
+
+    fn strip_invisibles(&mut self, _: &StripInvisibles, _: &mut Window, cx: &mut Context<Self>) {
+        self.engine.handle_action(EditorAction::StripInvisibles);
+        self.sync_and_save(cx);
+        cx.notify();
+    }
+
+    fn evaluate_selection(
+        &mut self,
+        _: &EvaluateSelection,
+        _: &mut Window,
+        cx: &mut Context<Self>,
+    ) {
+        self.engine.handle_action(EditorAction::EvaluateSelection);
+        self.sync_and_save(cx);
+        cx.notify();
+    }
+
+    fn format_json(&mut self, _: &FormatJson, _: &mut Window, cx: &mut Context<Self>) {
+        self.engine.handle_action(EditorAction::FormatJson);
+        self.sync_and_save(cx);
+        cx.notify();
+    }
+
+    fn format_toml(&mut self, _: &FormatToml, _: &mut Window, cx: &mut Context<Self>) {
+        self.engine.handle_action(EditorAction::FormatToml);
+        self.sync_and_save(cx);
+        cx.notify();
+    }
+
+    fn format_yaml(&mut self, _: &FormatYaml, _: &mut Window, cx: &mut Context<Self>) {
+        self.engine.handle_action(EditorAction::FormatYaml);
+        self.sync_and_save(cx);
+        cx.notify();
+    }
+
+    fn minify_json(&mut self, _: &MinifyJson, _: &mut Window, cx: &mut Context<Self>) {
+        self.engine.handle_action(EditorAction::MinifyJson);
+        self.sync_and_save(cx);
+        cx.notify();
+    }
+
+    fn sort_keys(&mut self, _: &SortKeys, _: &mut Window, cx: &mut Context<Self>) {
+        self.engine.handle_action(EditorAction::SortKeys);
+        self.sync_and_save(cx);
+        cx.notify();
+    }
+
+    fn increment_number(&mut self, _: &IncrementNumber, _: &mut Window, cx: &mut Context<Self>) {
+        self.engine.handle_action(EditorAction::IncrementNumber(1));
+        self.sync_and_save(cx);
+        cx.notify();
+    }
+
+    fn decrement_number(&mut self, _: &DecrementNumber, _: &mut Window, cx: &mut Context<Self>) {
+        self.engine.handle_action(EditorAction::DecrementNumber(1));
+        self.sync_and_save(cx);
+        cx.notify();
+    }
+
+    fn number_sequence(&mut self, _: &NumberSequence, _: &mut Window, cx: &mut Context<Self>) {
+        self.engine.handle_action(EditorAction::NumberSequence(1));
+        self.sync_and_save(cx);
+        cx.notify();
+    }
+
+    fn transform_selection(
+        &mut self,
+        action: &TransformSelection,
+        _: &mut Window,
+        cx: &mut Context<Self>,
+    ) {
+        self.engine
+            .handle_action(EditorAction::TransformSelection(action.transform));
+        self.sync_and_save(cx);
+        cx.notify();
+    }
+
+    fn format_table(&mut self, _: &FormatTable, _: &mut Window, cx: &mut Context<Self>) {
+        self.engine.handle_action(EditorAction::FormatTable);
+        self.sync_and_save(cx);
+        cx.notify();
+    }
+
+    fn insert_table_row(&mut self, _: &InsertTableRow, _: &mut Window, cx: &mut Context<Self>) {
+        self.engine.handle_action(EditorAction::InsertTableRow);
+        self.sync_and_save(cx);
+        cx.notify();
+    }
+
+    fn delete_table_row(&mut self, _: &DeleteTableRow, _: &mut Window, cx: &mut Context<Self>) {
+        self.engine.handle_action(EditorAction::DeleteTableRow);
+        self.sync_and_save(cx);
+        cx.notify();
+    }
+
+    fn insert_table_column(
+        &mut self,
+        _: &InsertTableColumn,
+        _: &mut Window,
+        cx: &mut Context<Self>,
+    ) {
+        self.engine.handle_action(EditorAction::InsertTableColumn);
+        self.sync_and_save(cx);
+        cx.notify();
+    }
+
+    fn delete_table_column(
+        &mut self,
+        _: &DeleteTableColumn,
+        _: &mut Window,
+        cx: &mut Context<Self>,
+    ) {
+        self.engine.handle_action(EditorAction::DeleteTableColumn);
+        self.sync_and_save(cx);
+        cx.notify();
+    }
+
+    fn toggle_focus_mode(&mut self, _: &ToggleFocusMode, _: &mut Window, cx: &mut Context<Self>) {
+        self.focus_mode = !self.focus_mode;
+        let _ = Config::store_value("view", "focus_mode", self.focus_mode);
+        if !self.focus_mode {
+            self.scroll_target = self.scroll_target.max(0.0);
+        }
+        self.ensure_cursor_visible();
+        cx.notify();
+    }
+
+    /// Switch focus mode between leaving the cursor's paragraph undimmed
+    /// and only its sentence, turning focus mode on if it was off
+    fn toggle_sentence_focus(
+        &mut self,
+        _: &ToggleSentenceFocus,
+        _: &mut Window,
+        cx: &mut Context<Self>,
+    ) {
+        let (scope, name) = match self.focus_scope {
+            FocusScope::Paragraph => (FocusScope::Sentence, "sentence"),
+            FocusScope::Sentence => (FocusScope::Paragraph, "paragraph"),
+        };
+        self.focus_scope = scope;
+        let _ = Config::store_value("view", "focus_scope", name);
+        if !self.focus_mode && scope == FocusScope::Sentence {
+            self.focus_mode = true;
+            let _ = Config::store_value("view", "focus_mode", true);
+            self.ensure_cursor_visible();
+        }
+        cx.notify();
+    }
+
+    /// Leave the file as on switching away from it, then quit; an untitled
+    /// buffer with text asks first
+    fn quit(&mut self, _: &Quit, window: &mut Window, cx: &mut Context<Self>) {
+        self.save_scroll_position();
+        self.save_to_file();
+        self.engine.wait_for_file_io();
+        self.poll_file_io(cx);
+        if let Some(path) = self.file_path.as_deref() {
+            let _ = self.hooks.flush(path);
+        }
+        self.snapshot_history();
+        self.hooks.wait();
+        if !self.dirty {
+            cx.quit();
+            return;
+        }
+        let answer = window.prompt(
+            PromptLevel::Warning,
+            "Quit without saving the untitled buffer?",
+            Some("Its text will be lost."),
+            &["Quit", "Cancel"],
+            cx,
+        );
+        cx.spawn(async move |_, cx| {
+            if let Ok(0) = answer.await {
+                let _ = cx.update(|cx| cx.quit());
+            }
+        })
+        .detach();
+    }
+
+    /// Open a file dropped on the window; of several, the first file
+    fn handle_file_drop(&mut self, paths: &ExternalPaths, _: &mut Window, cx: &mut Context<Self>) {
+        if let Some(path) = paths.paths().iter().find(|path| path.is_file()) {
+            self.open_file(path.clone());
+            cx.activate(true);
+            cx.notify();
+        }
+    }
+
+    fn open_recent_file(
+        &mut self,
+        action: &OpenRecentFile,
+        _: &mut Window,
+        cx: &mut Context<Self>,
+    ) {
+        self.open_file(action.path.clone());
+        cx.notify();
+    }
+
+    /// Put the open file at the top of the dock menu, and of the system's
+    /// recent documents
+    fn update_dock_menu(&mut self, cx: &mut Context<Self>) {
+        if self.dock_file == self.file_path {
+            return;
+        }
+        self.dock_file = self.file_path.clone();
+        if let Some(path) = self
+            .file_path
+            .as_deref()
+            .filter(|path| !vfs::is_remote(path))
+        {
+            cx.add_recent_document(path);
+        }
+        let recent = WorkspaceState::load().unwrap_or_default().recent_files();
+        cx.set_dock_menu(menus::dock_menu(&recent));
+    }
+
+    fn toggle_fullscreen(
+        &mut self,
+        _: &ToggleFullscreen,
+        window: &mut Window,
+        _: &mut Context<Self>,
+    ) {
+        window.toggle_fullscreen();
+    }
+
+    /// Takes effect immediately where the platform supports switching
+    /// decorations (Wayland); elsewhere on the next launch
+    fn toggle_title_bar(
+        &mut self,
+        _: &ToggleTitleBar,
+        window: &mut Window,
+        cx: &mut Context<Self>,
+    ) {
+        self.window_config.titlebar = !self.window_config.titlebar;
+        let _ = Config::store_value("window", "titlebar", self.window_config.titlebar);
+        window.request_decorations(if self.window_config.titlebar {
+            WindowDecorations::Server
+        } else {
+            WindowDecorations::Client
+        });
+        cx.notify();
+    }
+
+    fn toggle_blur(&mut self, _: &ToggleBlur, window: &mut Window, cx: &mut Context<Self>) {
+        self.window_config.blur = !self.window_config.blur;
+        let _ = Config::store_value("window", "blur", self.window_config.blur);
+        window.set_background_appearance(window_state::background_appearance(&self.window_config));
+        cx.notify();
+    }
+
+    fn increase_opacity(
+        &mut self,
+        _: &IncreaseOpacity,
+        window: &mut Window,
+        cx: &mut Context<Self>,
+    ) {
+        self.set_opacity(self.window_config.opacity + OPACITY_STEP, window, cx);
+    }
+
+    fn decrease_opacity(
+        &mut self,
+        _: &DecreaseOpacity,
+        window: &mut Window,
+        cx: &mut Context<Self>,
+    ) {
+        self.set_opacity(self.window_config.opacity - OPACITY_STEP, window, cx);
+    }
+
+    fn set_opacity(&mut self, opacity: f32, window: &mut Window, cx: &mut Context<Self>) {
+        // Round so repeated steps land on (and persist) tidy values like 0.8
+        let opacity = ((opacity * 100.0).round() / 100.0).clamp(MIN_OPACITY, 1.0);
+        self.window_config.opacity = opacity;
+        self.theme.background.a = opacity;
+        window.set_background_appearance(window_state::background_appearance(&self.window_config));
+        let _ = Config::store_value(
+            "window",
+            "opacity",
+            (opacity as f64 * 100.0).round() / 100.0,
+        );
+        cx.notify();
+    }
+
+    fn open_file_dialog(&mut self, _: &OpenFile, _: &mut Window, cx: &mut Context<Self>) {
+        let paths = cx.prompt_for_paths(PathPromptOptions {
+            files: true,
+            directories: false,
+            multiple: false,
+            prompt: None,
+        });
+        cx.spawn(async move |this, cx| {
+            if let Ok(Ok(Some(paths))) = paths.await {
+                if let Some(path) = paths.into_iter().next() {
+                    let _ = this.update(cx, |editor, cx| {
+                        editor.open_file(path);
+                        cx.notify();
+                    });
+                }
+            }
+        })
+        .detach();
+    }
+
+    fn save_as(&mut self, _: &SaveAs, _: &mut Window, cx: &mut Context<Self>) {
+        let directory = self
+            .file_path
+            .as_deref()
+            .and_then(Path::parent)
+            .map(Path::to_path_buf)
+            .or_else(|| std::env::current_dir().ok())
+            .unwrap_or_default();
+        let suggested_name = self
+            .file_path
+            .as_deref()
+            .and_then(Path::file_name)
+            .map(|name| name.to_string_lossy().into_owned());
+        let path = cx.prompt_for_new_path(&directory, suggested_name.as_deref());
+        cx.spawn(async move |this, cx| {
+            if let Ok(Ok(Some(path))) = path.await {
+                let _ = this.update(cx, |editor, cx| {
+                    editor.save_scroll_position();
+                    editor.lsp.open(&path, &editor.engine);
+                    let _ = WorkspaceState::add_recent(&path);
+                    editor.file_path = Some(path);
+                    editor.save_to_file();
+                    #[cfg(feature = "live-sync")]
+                    editor.join_live_sync();
+                    cx.notify();
+                });
+            }
+        })
+        .detach();
+    }
+
+    /// Ask where to write the buffer, or the selection, as an HTML page
+    fn export_html(&mut self, _: &ExportHtml, _: &mut Window, cx: &mut Context<Self>) {
+        self.export_to(export::html_path, cx, |editor, source, path| {
+            let state = editor.engine.state();
+            let options = HtmlOptions::new(source, &editor.engine.config().export);
+            let page = export::html(
+                &state.lines,
+                source,
+                state.selection(),
+                &editor.theme.export_palette(),
+                &options,
+            );
+            std::fs::write(path, page)
+        });
+    }
+
+    /// Ask where to write the buffer, or the selection, as a PDF
+    fn export_pdf(&mut self, _: &ExportPdf, _: &mut Window, cx: &mut Context<Self>) {
+        self.export_to(pdf::pdf_path, cx, |editor, source, path| {
+            let state = editor.engine.state();
+            let config = editor.engine.config();
+            pdf::write(path, &state.lines, source, state.selection(), config)
+        });
+    }
+
+    /// Ask where to write a picture of the selection, or of the buffer
+    #[cfg(feature = "code-image")]
+    fn export_image(&mut self, _: &ExportImage, _: &mut Window, cx: &mut Context<Self>) {
+        self.export_to(code_image::image_path, cx, |editor, source, path| {
+            let state = editor.engine.state();
+            code_image::write(
+                path,
+                &state.lines,
+                source,
+                state.selection(),
+                &editor.theme.export_palette(),
+                editor.engine.config(),
+            )
+        });
+    }
+
+    #[cfg(not(feature = "code-image"))]
+    fn export_image(&mut self, _: &ExportImage, _: &mut Window, cx: &mut Context<Self>) {
+        self.engine
+            .set_hover(Some("This build can't export pictures".to_string()));
+        cx.notify();
+    }
+
+    /// Open the buffer, or the selection, as a PDF in the system's viewer
+    /// to print from there
+    fn print(&mut self, _: &Print, _: &mut Window, cx: &mut Context<Self>) {
+        let source = self.file_path.as_deref();
+        let path = pdf::print_path(source);
+        let state = self.engine.state();
+        match pdf::write(
+            &path,
+            &state.lines,
+            source,
+            state.selection(),
+            self.engine.config(),
+        ) {
+            Ok(()) => cx.open_url(&format!("file://{}", path.display())),
+            Err(e) => self
+                .engine
+                .set_hover(Some(format!("Could not print: {}", e))),
+        }
+        cx.notify();
+    }
+
+    /// Ask for a path beside the file, named by `name`, and export there
+    /// with `write`
+    fn export_to(
+        &mut self,
+        name: fn(&Path) -> PathBuf,
+        cx: &mut Context<Self>,
+        write: impl FnOnce(&mut Self, Option<&Path>, &Path) -> std::io::Result<()> + 'static,
+    ) {
+        let source = self.file_path.clone();
+        let directory = source
+            .as_deref()
+            .and_then(Path::parent)
+            .map(Path::to_path_buf)
+            .or_else(|| std::env::current_dir().ok())
+            .unwrap_or_default();
+        let suggested = name(source.as_deref().unwrap_or(Path::new("Untitled")));
+        let suggested_name = suggested
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned());
+        let path = cx.prompt_for_new_path(&directory, suggested_name.as_deref());
+        cx.spawn(async move |this, cx| {
+            if let Ok(Ok(Some(path))) = path.await {
+                let _ = this.update(cx, |editor, cx| {
+                    let message = match write(editor, source.as_deref(), &path) {
+                        Ok(()) => format!("Exported {}", path.display()),
+                        Err(e) => format!("Could not export: {}", e),
+                    };
+                    editor.engine.set_hover(Some(message));
+                    cx.notify();
+                });
+            }
+        })
+        .detach();
+    }
+
+    fn new_file(&mut self, _: &NewFile, _: &mut Window, cx: &mut Context<Self>) {
+        self.save_scroll_position();
+        self.save_to_file();
+        self.open_untitled();
+        cx.notify();
+    }
+
+    /// Switch to an empty untitled buffer without saving the current one
+    fn open_untitled(&mut self) {
+        self.engine.new_buffer();
+        self.lsp.close();
+        self.file_path = None;
+        #[cfg(feature = "live-sync")]
+        {
+            self.live_sync = None;
+        }
+        self.last_modified = None;
+        self.dirty = false;
+        self.sync_buffer_from_engine();
+        self.restore_scroll_position();
+    }
+
+    fn find_in_files(&mut self, _: &FindInFiles, _: &mut Window, cx: &mut Context<Self>) {
+        self.show_search_panel(false);
+        cx.notify();
+    }
+
+    fn replace_in_files(&mut self, _: &ReplaceInFiles, _: &mut Window, cx: &mut Context<Self>) {
+        self.show_search_panel(true);
+        cx.notify();
+    }
+
+    fn show_search_panel(&mut self, replacing: bool) {
+        self.search_visible = true;
+        self.tree_focused = false;
+        self.search_panel
+            .get_or_insert_with(SearchPanel::load)
+            .set_replacing(replacing);
+    }
+
+    /// Route a key to the recent files list or the file tree while either has
+    /// focus, otherwise to the find-in-files panel if it is shown; returns
+    /// whether any of them took it
+    fn handle_panel_key(&mut self, key: PanelKey, cx: &mut Context<Self>) -> bool {
+        if self.context_menu.is_some() {
+            self.handle_context_menu_key(key, cx);
+            cx.notify();
+            return true;
+        }
+        if self.passphrase_prompt.is_some() {
+            self.handle_passphrase_key(key);
+            cx.notify();
+            return true;
+        }
+        if self.command_line.is_some() {
+            self.handle_command_line_key(key, cx);
+            cx.notify();
+            return true;
+        }
+        if self.diff_view.is_some() {
+            self.handle_diff_key(key, cx);
+            cx.notify();
+            return true;
+        }
+        if self.history_browser.is_some() {
+            self.handle_history_key(key, cx);
+            cx.notify();
+            return true;
+        }
+        if self.backlinks.is_some() {
+            self.handle_backlinks_key(key);
+            cx.notify();
+            return true;
+        }
+        if self.bookmarks.is_some() {
+            self.handle_bookmarks_key(key);
+            cx.notify();
+            return true;
+        }
+        if self.note_search_visible {
+            self.handle_note_search_key(key);
+            cx.notify();
+            return true;
+        }
+        if self.tags.is_some() {
+            self.handle_tags_key(key);
+            cx.notify();
+            return true;
+        }
+        if self.todos.is_some() {
+            self.handle_todos_key(key);
+            cx.notify();
+            return true;
+        }
+        if self.errors_visible && self.task_run.is_some() {
+            self.handle_errors_key(key);
+            cx.notify();
+            return true;
+        }
+        if self.symbol_palette.is_some() {
+            self.handle_symbol_palette_key(key);
+            cx.notify();
+            return true;
+        }
+        if self.template_picker.is_some() {
+            self.handle_template_key(key, cx);
+            cx.notify();
+            return true;
+        }
+        if self.char_picker.is_some() {
+            self.handle_char_picker_key(key, cx);
+            cx.notify();
+            return true;
+        }
+        if self.quick_switch.is_some() {
+            self.handle_switch_key(key);
+            cx.notify();
+            return true;
+        }
+        if self.tree_focused && self.tree_visible {
+            self.handle_tree_key(key);
+            cx.notify();
+            return true;
+        }
+        if self.outline_focused && self.outline.is_some() {
+            self.handle_outline_key(key);
+            cx.notify();
+            return true;
+        }
+        self.handle_search_key(key, cx)
+    }
+
+    /// Keys for the find-in-files panel; Left and Right still move the cursor
+    fn handle_search_key(&mut self, key: PanelKey, cx: &mut Context<Self>) -> bool {
+        if !self.search_visible || matches!(key, PanelKey::Left | PanelKey::Right) {
+            return false;
+        }
+        let Some(panel) = self.search_panel.as_mut() else {
+            return false;
+        };
+
+        match key {
+            PanelKey::Escape => self.search_visible = false,
+            PanelKey::Enter if panel.search().is_some() => self.jump_to_search_result(),
+            PanelKey::Enter => {
+                let root = self
+                    .file_path
+                    .as_deref()
+                    .map(project_search::workspace_root)
+                    .or_else(|| std::env::current_dir().ok())
+                    .unwrap_or_default();
+                let _ = WorkspaceState::add_search(&panel.query);
+                panel.start(&root);
+            }
+            PanelKey::Tab => panel.switch_field(),
+            // In the prompt, Up and Down go through past queries
+            PanelKey::Up if panel.search().is_none() => panel.history_previous(),
+            PanelKey::Down if panel.search().is_none() => panel.history_next(),
+            PanelKey::Up => panel.select_previous(),
+            PanelKey::Down => panel.select_next(),
+            PanelKey::Backspace => panel.backspace(),
+            PanelKey::Text(text) => panel.type_text(&text),
+            PanelKey::Left | PanelKey::Right => {}
+        }
+        cx.notify();
+        true
+    }
+
+    fn open_recent(&mut self, _: &OpenRecent, _: &mut Window, cx: &mut Context<Self>) {
+        self.quick_switch = match self.quick_switch {
+            Some(_) => None,
+            None => Some(QuickSwitch::load(self.file_path.as_deref())),
+        };
+        cx.notify();
+    }
+
+    fn switch_project(&mut self, _: &SwitchProject, _: &mut Window, cx: &mut Context<Self>) {
+        self.quick_switch = match self.quick_switch {
+            Some(_) => None,
+            None => Some(QuickSwitch::load_projects(self.file_path.as_deref())),
+        };
+        cx.notify();
+    }
+
+    fn show_command_line(&mut self, _: &ShowCommandLine, _: &mut Window, cx: &mut Context<Self>) {
+        self.command_line = Some(String::new());
+        cx.notify();
+    }
+
+    /// Open the command line at `align `, to name the delimiter
+    fn align_on(&mut self, _: &AlignOn, _: &mut Window, cx: &mut Context<Self>) {
+        self.command_line = Some("align ".to_string());
+        cx.notify();
+    }
+
+    /// Open the command line at `r !`, to insert a command's output
+    fn run_shell_command(&mut self, _: &RunShellCommand, _: &mut Window, cx: &mut Context<Self>) {
+        self.command_line = Some("r !".to_string());
+        cx.notify();
+    }
+
+    /// Open the command line at `!`, to filter the selection through a
+    /// command
+    fn filter_through_command(
+        &mut self,
+        _: &FilterThroughCommand,
+        _: &mut Window,
+        cx: &mut Context<Self>,
+    ) {
+        self.command_line = Some("!".to_string());
+        cx.notify();
+    }
+
+    fn handle_command_line_key(&mut self, key: PanelKey, cx: &mut Context<Self>) {
+        let Some(input) = self.command_line.as_mut() else {
+            return;
+        };
+        match key {
+            PanelKey::Escape => self.command_line = None,
+            PanelKey::Enter => {
+                let input = self.command_line.take().unwrap_or_default();
+                self.run_ex(&input, cx);
+            }
+            PanelKey::Backspace => {
+                input.pop();
+            }
+            PanelKey::Text(text) => input.push_str(&text),
+            _ => {}
+        }
+    }
+
+    /// Carry out a command typed at the `:` prompt
+    fn run_ex(&mut self, input: &str, cx: &mut Context<Self>) {
+        let command = match ex::Command::parse(input) {
+            Ok(command) => command,
+            Err(e) => {
+                self.engine.set_hover(Some(e.to_string()));
+                return;
+            }
+        };
+        match command {
+            ex::Command::Write(None) if self.file_path.is_none() => cx.dispatch_action(&SaveAs),
+            ex::Command::Write(None) => {
+                self.save_to_file();
+                self.engine.wait_for_file_io();
+                self.poll_file_io(cx);
+                let message = match (&self.file_path, self.dirty) {
+                    (Some(path), false) => format!("Wrote {}", path.display()),
+                    _ => "Could not write the file".to_string(),
+                };
+                self.engine.set_hover(Some(message));
+            }
+            ex::Command::Write(Some(path)) => {
+                let path = self.command_path(&path);
+                let message = match self.engine.save_to_file(&path) {
+                    Ok(()) => format!("Wrote {}", path.display()),
+                    Err(e) => format!("Could not write: {}", e),
+                };
+                self.engine.set_hover(Some(message));
+            }
+            ex::Command::Quit | ex::Command::WriteQuit => cx.dispatch_action(&Quit),
+            ex::Command::Edit(path) => {
+                let path = self.command_path(&path);
+                self.open_file(path);
+            }
+            command => {
+                let revision = self.engine.revision();
+                let dir = self.file_path.as_deref().and_then(Path::parent);
+                if let Err(e) = command.execute_in(&mut self.engine, dir) {
+                    self.engine.set_hover(Some(e.to_string()));
+                } else if self.engine.revision() != revision {
+                    self.sync_and_save(cx);
+                } else {
+                    self.sync_buffer_from_engine();
+                    self.ensure_cursor_visible();
+                }
+            }
+        }
+    }
+
+    /// A path typed at the `:` prompt, with `~` for the home directory and
+    /// relative paths from the current file's folder
+    fn command_path(&self, path: &Path) -> PathBuf {
+        if let Ok(rest) = path.strip_prefix("~") {
+            let home = std::env::var("HOME")
+                .or_else(|_| std::env::var("USERPROFILE"))
+                .unwrap_or_default();
+            return PathBuf::from(home).join(rest);
+        }
+        if path.is_absolute() || vfs::is_remote(path) {
+            return path.to_path_buf();
+        }
+        self.file_path
+            .as_deref()
+            .and_then(Path::parent)
+            .map(Path::to_path_buf)
+            .or_else(|| std::env::current_dir().ok())
+            .unwrap_or_default()
+            .join(path)
+    }
+
+    fn handle_passphrase_key(&mut self, key: PanelKey) {
+        let current = self.file_path.clone();
+        let Some(prompt) = self.passphrase_prompt.as_mut() else {
+            return;
+        };
+        match key {
+            // The file already shown stays locked until its passphrase is given
+            PanelKey::Escape if current.as_ref() != Some(&prompt.path) => {
+                self.passphrase_prompt = None
+            }
+            PanelKey::Enter => self.submit_passphrase(),
+            PanelKey::Backspace => {
+                prompt.input.pop();
+            }
+            PanelKey::Text(text) => prompt.input.push_str(&text),
+            _ => {}
+        }
+    }
+
+    /// Open the prompt's file with the passphrase typed, or ask again
+    fn submit_passphrase(&mut self) {
+        let Some(mut prompt) = self.passphrase_prompt.take() else {
+            return;
+        };
+        let passphrase = std::mem::take(&mut prompt.input);
+        if prompt.is_new() {
+            match prompt.first.take() {
+                None => {
+                    prompt.first = Some(passphrase);
+                    prompt.error = None;
+                    self.passphrase_prompt = Some(prompt);
+                    return;
+                }
+                Some(first) if first != passphrase => {
+                    prompt.error = Some("Passphrases do not match".to_string());
+                    self.passphrase_prompt = Some(prompt);
+                    return;
+                }
+                Some(_) => {}
+            }
+        }
+
+        let opened = self.engine.set_passphrase(&passphrase).and_then(|()| {
+            if self.file_path.as_ref() != Some(&prompt.path) {
+                return if self.open_file(prompt.path.clone()) {
+                    Ok(())
+                } else {
+                    Err(std::io::Error::other("wrong passphrase"))
+                };
+            }
+            if !prompt.is_new() {
+                self.engine.load_from_file(&prompt.path)?;
+                self.last_modified = std::fs::metadata(&prompt.path)
+                    .and_then(|m| m.modified())
+                    .ok();
+                self.hooks_revision = self.engine.revision();
+                self.sync_buffer_from_engine();
+                self.restore_scroll_position();
+                #[cfg(feature = "live-sync")]
+                self.join_live_sync();
+                self.refresh_note_names();
+            }
+            Ok(())
+        });
+        match opened {
+            Ok(()) if self.engine.config().encryption.keychain => {
+                let _ = encryption::store_in_keychain(&prompt.path, &passphrase);
+            }
+            Ok(()) => {}
+            Err(e) => {
+                prompt.error = Some(e.to_string());
+                self.passphrase_prompt = Some(prompt);
+            }
+        }
+    }
+
+    fn handle_switch_key(&mut self, key: PanelKey) {
+        let Some(switch) = self.quick_switch.as_mut() else {
+            return;
+        };
+        match key {
+            PanelKey::Escape => self.quick_switch = None,
+            PanelKey::Enter => self.open_selected_recent(),
+            PanelKey::Up => switch.select_previous(),
+            PanelKey::Down => switch.select_next(),
+            PanelKey::Backspace => switch.backspace(),
+            PanelKey::Text(text) => switch.type_text(&text),
+            PanelKey::Tab | PanelKey::Left | PanelKey::Right => {}
+        }
+    }
+
+    /// Open the highlighted file, or the file last opened in the
+    /// highlighted project
+    fn open_selected_recent(&mut self) {
+        let Some(switch) = self.quick_switch.take() else {
+            return;
+        };
+        let Some(path) = switch.selected_path() else {
+            return;
+        };
+        if !switch.projects {
+            self.open_file(path);
+        } else if let Some(file) = project::last_file(&path) {
+            self.open_file(file);
+        } else {
+            let message = format!("No files left to open in {}", path.display());
+            self.engine.set_hover(Some(message));
+        }
+    }
+
+    /// The directory shown in the file tree: the enclosing repository of the
+    /// open file, or the working directory for an untitled buffer
+    fn workspace_root(&self) -> PathBuf {
+        self.file_path
+            .as_deref()
+            .map(project_search::workspace_root)
+            .or_else(|| std::env::current_dir().ok())
+            .unwrap_or_default()
+    }
+
+    fn toggle_file_tree(&mut self, _: &ToggleFileTree, _: &mut Window, cx: &mut Context<Self>) {
+        if self.tree_visible {
+            self.tree_visible = false;
+            self.tree_focused = false;
+        } else {
+            self.show_file_tree();
+        }
+        cx.notify();
+    }
+
+    fn focus_file_tree(&mut self, _: &FocusFileTree, _: &mut Window, cx: &mut Context<Self>) {
+        self.show_file_tree();
+        self.tree_focused = true;
+        cx.notify();
+    }
+
+    /// Show the tree with the open file selected
+    fn show_file_tree(&mut self) {
+        let root = self.workspace_root();
+        let tree = self.file_tree.get_or_insert_with(|| FileTree::new(&root));
+        match self.file_path.as_deref() {
+            Some(path) => tree.select_path(path),
+            None => tree.refresh(),
+        }
+        self.tree_visible = true;
+    }
+
+    fn handle_tree_key(&mut self, key: PanelKey) {
+        let Some(tree) = self.file_tree.as_mut() else {
+            return;
+        };
+        match key {
+            PanelKey::Up => tree.select_previous(),
+            PanelKey::Down => tree.select_next(),
+            PanelKey::Left => tree.collapse_selected(),
+            PanelKey::Right => tree.expand_selected(),
+            PanelKey::Enter => {
+                let selected = tree.selected;
+                self.open_tree_entry(selected);
+            }
+            PanelKey::Escape => self.tree_focused = false,
+            PanelKey::Text(_) | PanelKey::Backspace | PanelKey::Tab => {}
+        }
+    }
+
+    /// Open a file from the tree and focus the buffer, or expand/collapse a directory
+    fn open_tree_entry(&mut self, index: usize) {
+        let Some(tree) = self.file_tree.as_mut() else {
+            return;
+        };
+        tree.selected = index;
+        let Some(entry) = tree.selected_entry() else {
+            return;
+        };
+        if entry.is_dir {
+            tree.toggle(index);
+        } else {
+            let path = entry.path.clone();
+            if self.file_path.as_ref() != Some(&path) {
+                self.open_file(path);
+            }
+            self.tree_focused = false;
+        }
+    }
+
+    fn selected_tree_path(&self) -> Option<PathBuf> {
+        let tree = self.file_tree.as_ref().filter(|_| self.tree_visible)?;
+        tree.selected_entry().map(|entry| entry.path.clone())
+    }
+
+    fn tree_new_file(&mut self, _: &TreeNewFile, _: &mut Window, cx: &mut Context<Self>) {
+        self.prompt_tree_create(false, cx);
+    }
+
+    fn tree_new_folder(&mut self, _: &TreeNewFolder, _: &mut Window, cx: &mut Context<Self>) {
+        self.prompt_tree_create(true, cx);
+    }
+
+    /// Ask where to create a file or folder, starting in the tree's target
+    /// directory; new files are opened
+    fn prompt_tree_create(&mut self, folder: bool, cx: &mut Context<Self>) {
+        let Some(tree) = self.file_tree.as_ref().filter(|_| self.tree_visible) else {
+            return;
+        };
+        let suggested_name = folder.then_some("New Folder");
+        let path = cx.prompt_for_new_path(&tree.target_dir(), suggested_name);
+        cx.spawn(async move |this, cx| {
+            if let Ok(Ok(Some(path))) = path.await {
+                let _ = this.update(cx, |editor, cx| {
+                    let Some(tree) = editor.file_tree.as_mut() else {
+                        return;
+                    };
+                    if folder {
+                        let _ = tree.create_dir(&path);
+                    } else if tree.create_file(&path).is_ok() {
+                        editor.open_file(path);
+                    }
+                    cx.notify();
+                });
+            }
+        })
+        .detach();
+    }
+
+    fn tree_rename(&mut self, _: &TreeRename, _: &mut Window, cx: &mut Context<Self>) {
+        let Some(from) = self.selected_tree_path() else {
+            return;
+        };
+        let directory = from.parent().map(Path::to_path_buf).unwrap_or_default();
+        let name = from
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned());
+        let path = cx.prompt_for_new_path(&directory, name.as_deref());
+        cx.spawn(async move |this, cx| {
+            if let Ok(Ok(Some(to))) = path.await {
+                let _ = this.update(cx, |editor, cx| {
+                    let renamed = editor
+                        .file_tree
+                        .as_mut()
+                        .is_some_and(|tree| tree.rename(&from, &to).is_ok());
+                    if renamed {
+                        editor.follow_move(&from, &to);
+                    }
+                    cx.notify();
+                });
+            }
+        })
+        .detach();
+    }
+
+    fn tree_move(&mut self, _: &TreeMove, _: &mut Window, cx: &mut Context<Self>) {
+        let Some(from) = self.selected_tree_path() else {
+            return;
+        };
+        let paths = cx.prompt_for_paths(PathPromptOptions {
+            files: false,
+            directories: true,
+            multiple: false,
+            prompt: Some("Move Here".into()),
+        });
+        cx.spawn(async move |this, cx| {
+            if let Ok(Ok(Some(paths))) = paths.await {
+                if let Some(dir) = paths.into_iter().next() {
+                    let _ = this.update(cx, |editor, cx| {
+                        let moved = editor
+                            .file_tree
+                            .as_mut()
+                            .and_then(|tree| tree.move_into(&from, &dir).ok());
+                        if let Some(to) = moved {
+                            editor.follow_move(&from, &to);
+                        }
+                        cx.notify();
+                    });
+                }
+            }
+        })
+        .detach();
+    }
+
+    fn tree_delete(&mut self, _: &TreeDelete, window: &mut Window, cx: &mut Context<Self>) {
+        let Some(path) = self.selected_tree_path() else {
+            return;
+        };
+        let name = path.file_name().unwrap_or_default().to_string_lossy();
+        let answer = window.prompt(
+            PromptLevel::Warning,
+            &format!("Delete {}?", name),
+            Some("This cannot be undone."),
+            &["Delete", "Cancel"],
+            cx,
+        );
+        cx.spawn(async move |this, cx| {
+            if let Ok(0) = answer.await {
+                let _ = this.update(cx, |editor, cx| {
+                    let deleted = editor
+                        .file_tree
+                        .as_mut()
+                        .is_some_and(|tree| tree.delete(&path).is_ok());
+                    // Don't let autosave bring the deleted file back
+                    if deleted
+                        && editor
+                            .file_path
+                            .as_deref()
+                            .is_some_and(|file| file.starts_with(&path))
+                    {
+                        editor.open_untitled();
+                    }
+                    cx.notify();
+                });
+            }
+        })
+        .detach();
+    }
+
+    /// Keep editing the open file after it, or a directory above it, moved
+    fn follow_move(&mut self, from: &Path, to: &Path) {
+        let Some(rest) = self
+            .file_path
+            .as_deref()
+            .and_then(|path| path.strip_prefix(from).ok())
+        else {
+            return;
+        };
+        let path = to.join(rest);
+        self.lsp.open(&path, &self.engine);
+        self.file_path = Some(path);
+    }
+
+    fn toggle_replace_hunk(
+        &mut self,
+        _: &ToggleReplaceHunk,
+        _: &mut Window,
+        cx: &mut Context<Self>,
+    ) {
+        if let Some(panel) = self.search_panel.as_mut().filter(|_| self.search_visible) {
+            panel.toggle_selected();
+            cx.notify();
+        }
+    }
+
+    fn apply_replace(&mut self, _: &ApplyReplace, _: &mut Window, cx: &mut Context<Self>) {
+        if let Some(panel) = self.search_panel.as_mut().filter(|_| self.search_visible) {
+            panel.apply();
+            cx.notify();
+        }
+    }
+
+    fn undo_replace_in_file(
+        &mut self,
+        _: &UndoReplaceInFile,
+        _: &mut Window,
+        cx: &mut Context<Self>,
+    ) {
+        if let Some(panel) = self.search_panel.as_mut().filter(|_| self.search_visible) {
+            panel.undo_selected_file();
+            cx.notify();
+        }
+    }
+
+    fn toggle_whole_word(&mut self, _: &ToggleWholeWord, _: &mut Window, cx: &mut Context<Self>) {
+        if let Some(panel) = self.search_panel.as_mut().filter(|_| self.search_visible) {
+            panel.toggle_whole_word();
+            cx.notify();
+        }
+    }
+
+    fn toggle_preserve_case(
+        &mut self,
+        _: &TogglePreserveCase,
+        _: &mut Window,
+        cx: &mut Context<Self>,
+    ) {
+        if let Some(panel) = self.search_panel.as_mut().filter(|_| self.search_visible) {
+            panel.toggle_preserve_case();
+            cx.notify();
+        }
+    }
+
+    /// Open the file of the highlighted match and select it
+    fn jump_to_search_result(&mut self) {
+        let Some(panel) = self.search_panel.as_ref() else {
+            return;
+        };
+        let Some((path, start, end)) = panel.selected_match() else {
+            return;
+        };
+        let query = panel.query.clone();
+
+        if self.file_path.as_ref() != Some(&path) && !self.open_file(path) {
+            return;
+        }
+        self.search_visible = false;
+        self.engine.set_search_query(Some(query));
+        self.engine.handle_action(EditorAction::StartSelection {
+            row: start.row,
+            column: start.column,
+        });
+        self.engine.handle_action(EditorAction::ExtendSelection {
+            row: end.row,
+            column: end.column,
+        });
+        self.ensure_cursor_visible();
+    }
+
+    fn toggle_minimap(&mut self, _: &ToggleMinimap, _: &mut Window, cx: &mut Context<Self>) {
+        self.minimap = !self.minimap;
+        let _ = Config::store_value("view", "minimap", self.minimap);
+        cx.notify();
+    }
+
+    fn toggle_writing_session(
+        &mut self,
+        _: &ToggleWritingSession,
+        _: &mut Window,
+        cx: &mut Context<Self>,
+    ) {
+        self.engine
+            .handle_action(EditorAction::ToggleWritingSession);
+        cx.notify();
+    }
+
+    fn toggle_dictation(&mut self, _: &ToggleDictation, _: &mut Window, cx: &mut Context<Self>) {
+        self.engine.handle_action(EditorAction::ToggleDictation);
+        cx.notify();
+    }
+
+    /// Show the terminal and give it the keys, or hide it if it has them
+    #[cfg(feature = "terminal")]
+    fn toggle_terminal(&mut self, _: &ToggleTerminal, _: &mut Window, cx: &mut Context<Self>) {
+        if self.terminal_visible && self.terminal_focused {
+            self.terminal_visible = false;
+            self.terminal_focused = false;
+        } else if self.start_terminal(cx).is_some() {
+            self.terminal_visible = true;
+            self.terminal_focused = true;
+        }
+        cx.notify();
+    }
+
+    /// Run the selection, or the cursor's line, in the terminal
+    #[cfg(feature = "terminal")]
+    fn send_to_terminal(&mut self, _: &SendToTerminal, _: &mut Window, cx: &mut Context<Self>) {
+        let text = self.engine.selected_text().unwrap_or_else(|| {
+            let state = self.engine.state();
+            state.lines[state.cursor.row].clone()
+        });
+        if let Some(shell) = self.start_terminal(cx) {
+            let result = shell.run(&text);
+            self.terminal_visible = true;
+            if let Err(e) = result {
+                self.engine
+                    .set_hover(Some(format!("Could not write to the terminal: {}", e)));
+            }
+        }
+        cx.notify();
+    }
+
+    /// Insert everything the terminal has shown at the cursor
+    #[cfg(feature = "terminal")]
+    fn capture_terminal(&mut self, _: &CaptureTerminal, _: &mut Window, cx: &mut Context<Self>) {
+        let Some(shell) = self.terminal.as_mut() else {
+            self.engine
+                .set_hover(Some("The terminal isn't open".to_string()));
+            cx.notify();
+            return;
+        };
+        let output = shell.capture();
+        self.engine.handle_action(EditorAction::Paste(output));
+        self.sync_and_save(cx);
+        cx.notify();
+    }
+
+    /// The terminal's shell, started in the workspace if it isn't running
+    #[cfg(feature = "terminal")]
+    fn start_terminal(&mut self, cx: &mut Context<Self>) -> Option<&mut Terminal> {
+        if self
+            .terminal
+            .as_ref()
+            .is_some_and(|shell| !shell.is_running())
+        {
+            self.terminal = None;
+        }
+        if self.terminal.is_none() {
+            let config = &self.engine.config().terminal;
+            let root = self
+                .file_path
+                .as_deref()
+                .map(project_search::workspace_root);
+            // The panel fits the screen to its width when next drawn
+            match Terminal::spawn(config, root.as_deref(), config.height, 80) {
+                Ok(shell) => {
+                    self.terminal = Some(shell);
+                    self.watch_terminal(cx);
+                }
+                Err(e) => {
+                    self.engine
+                        .set_hover(Some(format!("Could not start the terminal: {}", e)));
+                    return None;
+                }
+            }
+        }
+        self.terminal.as_mut()
+    }
+
+    /// Redraw the terminal as its shell writes, until the shell exits
+    #[cfg(feature = "terminal")]
+    fn watch_terminal(&mut self, cx: &mut Context<Self>) {
+        cx.spawn(async move |this, cx| loop {
+            cx.background_executor().timer(TERMINAL_POLL_INTERVAL).await;
+            let running = this.update(cx, |editor, cx| {
+                let Some(shell) = editor.terminal.as_mut() else {
+                    return false;
+                };
+                if shell.poll() {
+                    cx.notify();
+                }
+                shell.is_running()
+            });
+            if !matches!(running, Ok(true)) {
+                break;
+            }
+        })
+        .detach();
+    }
+
+    #[cfg(feature = "terminal")]
+    fn focused_terminal(&mut self) -> Option<&mut Terminal> {
+        self.terminal
+            .as_mut()
+            .filter(|_| self.terminal_visible && self.terminal_focused)
+    }
+
+    /// Send a keystroke to the terminal while it has focus, ahead of the key
+    /// bindings; Cmd shortcuts and the key that hides the terminal still
+    /// reach them
+    #[cfg(feature = "terminal")]
+    fn intercept_terminal_key(
+        &mut self,
+        keystroke: &Keystroke,
+        window: &mut Window,
+        cx: &mut Context<Self>,
+    ) {
+        if !self.focus_handle.is_focused(window) || keystroke.modifiers.platform {
+            return;
+        }
+        let toggles = window
+            .bindings_for_action(&ToggleTerminal)
+            .iter()
+            .any(|binding| {
+                binding.match_keystrokes(std::slice::from_ref(keystroke)) == Some(false)
+            });
+        if toggles {
+            return;
+        }
+        let Some(shell) = self.focused_terminal() else {
+            return;
+        };
+        if let Some(key) = terminal_key(keystroke) {
+            let modifiers = keystroke.modifiers;
+            if let Err(e) = shell.send_key(key, modifiers.control, modifiers.alt) {
+                self.engine
+                    .set_hover(Some(format!("Could not write to the terminal: {}", e)));
+            }
+        }
+        cx.stop_propagation();
+        cx.notify();
+    }
+
+    #[cfg(not(feature = "terminal"))]
+    fn toggle_terminal(&mut self, _: &ToggleTerminal, _: &mut Window, cx: &mut Context<Self>) {
+        self.terminal_unavailable(cx);
+    }
+
+    #[cfg(not(feature = "terminal"))]
+    fn send_to_terminal(&mut self, _: &SendToTerminal, _: &mut Window, cx: &mut Context<Self>) {
+        self.terminal_unavailable(cx);
+    }
+
+    #[cfg(not(feature = "terminal"))]
+    fn capture_terminal(&mut self, _: &CaptureTerminal, _: &mut Window, cx: &mut Context<Self>) {
+        self.terminal_unavailable(cx);
+    }
+
+    #[cfg(not(feature = "terminal"))]
+    fn terminal_unavailable(&mut self, cx: &mut Context<Self>) {
+        self.engine
+            .set_hover(Some("This build has no terminal".to_string()));
+        cx.notify();
+    }
+
+    fn toggle_diagnostics_panel(
+        &mut self,
+        _: &ToggleDiagnosticsPanel,
+        _: &mut Window,
+        cx: &mut Context<Self>,
+    ) {
+        self.engine
+            .handle_action(EditorAction::ToggleDiagnosticsPanel);
+        cx.notify();
+    }
+
+    fn select_left(&mut self, _: &SelectLeft, _: &mut Window, cx: &mut Context<Self>) {
+        self.engine.handle_action(EditorAction::SelectLeft);
+        cx.notify();
+    }
+
+    fn select_right(&mut self, _: &SelectRight, _: &mut Window, cx: &mut Context<Self>) {
+        self.engine.handle_action(EditorAction::SelectRight);
+        cx.notify();
+    }
+
+    fn select_up(&mut self, _: &SelectUp, _: &mut Window, cx: &mut Context<Self>) {
+        self.engine.handle_action(EditorAction::SelectUp);
+        cx.notify();
+    }
+
+    fn select_down(&mut self, _: &SelectDown, _: &mut Window, cx: &mut Context<Self>) {
+        self.engine.handle_action(EditorAction::SelectDown);
+        cx.notify();
+    }
+
+    fn select_word_left(&mut self, _: &SelectWordLeft, _: &mut Window, cx: &mut Context<Self>) {
+        self.engine.handle_action(EditorAction::SelectWordLeft);
+        cx.notify();
+    }
+
+    fn select_word_right(&mut self, _: &SelectWordRight, _: &mut Window, cx: &mut Context<Self>) {
+        self.engine.handle_action(EditorAction::SelectWordRight);
+        cx.notify();
+    }
+
+    fn select_all(&mut self, _: &SelectAll, _window: &mut Window, cx: &mut Context<Self>) {
+        self.engine.handle_action(EditorAction::SelectAll);
+        cx.notify();
+    }
+
+    fn select_line(&mut self, _: &SelectLine, _: &mut Window, cx: &mut Context<Self>) {
+        self.engine.handle_action(EditorAction::SelectLine);
+        self.ensure_cursor_visible();
+        cx.notify();
+    }
+
+    fn select_sentence(&mut self, _: &SelectSentence, _: &mut Window, cx: &mut Context<Self>) {
+        self.engine.handle_action(EditorAction::SelectSentence);
+        self.ensure_cursor_visible();
+        cx.notify();
+    }
+
+    fn select_paragraph(&mut self, _: &SelectParagraph, _: &mut Window, cx: &mut Context<Self>) {
+        self.engine.handle_action(EditorAction::SelectParagraph);
+        self.ensure_cursor_visible();
+        cx.notify();
+    }
+
+    fn select_to_top(&mut self, _: &SelectToTop, _: &mut Window, cx: &mut Context<Self>) {
+        self.engine.handle_action(EditorAction::SelectToTop);
+        self.ensure_cursor_visible();
+        cx.notify();
+    }
+
+    fn select_to_bottom(&mut self, _: &SelectToBottom, _: &mut Window, cx: &mut Context<Self>) {
+        self.engine.handle_action(EditorAction::SelectToBottom);
+        self.ensure_cursor_visible();
+        cx.notify();
+    }
+
+    fn select_to_line_start(
+        &mut self,
+        _: &SelectToLineStart,
+        _: &mut Window,
+        cx: &mut Context<Self>,
+    ) {
+        self.engine.handle_action(EditorAction::SelectToLineStart);
+        self.ensure_cursor_visible();
+        cx.notify();
+    }
+
+    fn select_to_line_end(&mut self, _: &SelectToLineEnd, _: &mut Window, cx: &mut Context<Self>) {
+        self.engine.handle_action(EditorAction::SelectToLineEnd);
+        self.ensure_cursor_visible();
+        cx.notify();
+    }
+
+    fn expand_selection(&mut self, _: &ExpandSelection, _: &mut Window, cx: &mut Context<Self>) {
+        self.engine.handle_action(EditorAction::ExpandSelection);
+        cx.notify();
+    }
+
+    fn shrink_selection(&mut self, _: &ShrinkSelection, _: &mut Window, cx: &mut Context<Self>) {
+        self.engine.handle_action(EditorAction::ShrinkSelection);
+        cx.notify();
+    }
+
+    fn select_parent(&mut self, _: &SelectParent, _: &mut Window, cx: &mut Context<Self>) {
+        self.engine.handle_action(EditorAction::SelectParent);
+        self.ensure_cursor_visible();
+        cx.notify();
+    }
+
+    fn select_next_sibling(
+        &mut self,
+        _: &SelectNextSibling,
+        _: &mut Window,
+        cx: &mut Context<Self>,
+    ) {
+        self.engine.handle_action(EditorAction::SelectNextSibling);
+        self.ensure_cursor_visible();
+        cx.notify();
+    }
+
+    fn selected_text(&self) -> Option<String> {
+        let (start, end) = self.selection_range()?;
+        let start_offset = self.buffer.position_to_byte_offset(start);
+        let end_offset = self.buffer.position_to_byte_offset(end);
+        let content = self.buffer.to_string();
+        (end_offset <= content.len()).then(|| content[start_offset..end_offset].to_string())
+    }
+
+    fn copy(&mut self, _: &Copy, _: &mut Window, cx: &mut Context<Self>) {
+        self.copy_selection(cx);
+    }
+
+    fn copy_selection(&mut self, cx: &mut Context<Self>) {
+        if let Some(selected_text) = self.selected_text() {
+            cx.write_to_clipboard(selected_text.into());
+        }
+    }
+
+    fn cut(&mut self, _: &Cut, _: &mut Window, cx: &mut Context<Self>) {
+        self.cut_selection(cx);
+    }
+
+    fn cut_selection(&mut self, cx: &mut Context<Self>) {
+        if let Some(selected_text) = self.selected_text() {
+            cx.write_to_clipboard(selected_text.into());
+            self.engine.handle_action(EditorAction::Cut);
+            self.sync_and_save(cx);
+            cx.notify();
+        }
+    }
+
+    /// The clipboard's text, for templates' `{{clipboard}}`
+    fn clipboard_text(cx: &mut Context<Self>) -> String {
+        cx.read_from_clipboard()
+            .and_then(|item| item.text())
+            .unwrap_or_default()
+    }
+
+    fn paste(&mut self, _: &Paste, _: &mut Window, cx: &mut Context<Self>) {
+        self.paste_clipboard(false, cx);
+    }
+
+    fn paste_plain(&mut self, _: &PastePlain, _: &mut Window, cx: &mut Context<Self>) {
+        self.paste_clipboard(true, cx);
+    }
+
+    /// Paste the clipboard's text, re-indented to fit or as plain text
+    fn paste_clipboard(&mut self, plain: bool, cx: &mut Context<Self>) {
+        if let Some(clipboard_item) = cx.read_from_clipboard() {
+            if let Some(text) = clipboard_item.text() {
+                #[cfg(feature = "terminal")]
+                if let Some(shell) = self.focused_terminal() {
+                    if let Err(e) = shell.paste(&text) {
+                        self.engine
+                            .set_hover(Some(format!("Could not write to the terminal: {}", e)));
+                    }
+                    cx.notify();
+                    return;
+                }
+                let action = if plain {
+                    EditorAction::PastePlain(text)
+                } else {
+                    EditorAction::Paste(text)
+                };
+                self.engine.handle_action(action);
+                self.sync_and_save(cx);
+                cx.notify();
+            }
+        }
+    }
+
+    fn position_from_mouse(
+        &mut self,
+        mouse_position: Point<Pixels>,
+        window: &mut Window,
+        wrap_width: Pixels,
+    ) -> BufferPosition {
+        let line_height_px = px(self.line_height());
+        let viewport = self.viewport(window);
+        let padding_top = viewport.origin.y + px(self.padding_top());
+        let padding_left =
+            viewport.origin.x + self.text_margin(viewport.size.width) + self.sidebar_width();
+
+        // Content scrolled out above the window counts toward the row
+        let relative_y = (mouse_position.y - padding_top + px(self.scroll_offset)).max(px(0.0));
+
+        let relative_x = if mouse_position.x > padding_left {
+            mouse_position.x - padding_left
+        } else {
+            px(0.0)
+        };
+
+        let visual_row = (relative_y / line_height_px) as usize;
+        let text_system = window.text_system();
+        let font_size_px = px(self.get_font_size());
+
+        // Below the last line is the end of the text
+        if visual_row >= self.buffer.first_visual_row(self.buffer.line_count()) {
+            let last_row = self.buffer.line_count().saturating_sub(1);
+            return BufferPosition::new(last_row, self.buffer.line_len(last_row));
+        }
+
+        // Only the line under the mouse is shaped; those above count as drawn
+        let (buffer_row, first) = self.buffer.row_at_visual(visual_row);
+        self.buffer
+            .get_or_shape_line(buffer_row, font_size_px, wrap_width, text_system);
+        let Some(byte_range) = self.buffer.get_visual_lines(buffer_row).and_then(|lines| {
+            let index = (visual_row - first).min(lines.len().saturating_sub(1));
+            lines.get(index).map(|line| line.byte_range.clone())
+        }) else {
+            return BufferPosition::new(buffer_row, 0);
+        };
+        let line = self.buffer.line(buffer_row).unwrap_or("");
+        if let Some(segment) = BidiSegment::new(
+            line,
+            byte_range.clone(),
+            self.buffer.font(),
+            font_size_px,
+            text_system,
+        ) {
+            return BufferPosition::new(buffer_row, segment.column(relative_x));
+        }
+        let hints = self.inline_hints(
+            buffer_row,
+            &byte_range,
+            line.len(),
+            font_size_px,
+            text_system,
+        );
+        match self
+            .buffer
+            .get_or_shape_line(buffer_row, font_size_px, wrap_width, text_system)
+        {
+            Some(layout) => {
+                let segment_x = layout.x_for_index(byte_range.start);
+                // Virtual text is stepped over; a click on some lands at its column
+                let mut shift = px(0.0);
+                for hint in &hints {
+                    let x = layout.x_for_index(hint.column) - segment_x + shift;
+                    if relative_x < x {
+                        break;
+                    }
+                    if relative_x < x + hint.width {
+                        return BufferPosition::new(buffer_row, hint.column);
+                    }
+                    shift += hint.width;
+                }
+                let column = layout.closest_index_for_x(relative_x - shift + segment_x);
+                BufferPosition::new(buffer_row, column.clamp(byte_range.start, byte_range.end))
+            }
+            None => BufferPosition::new(buffer_row, 0),
+        }
+    }
+
+    fn find_word_boundaries(
+        &self,
+        pos: BufferPosition,
+    ) -> Option<(BufferPosition, BufferPosition)> {
+        let line = self.buffer.line(pos.row)?;
+        if line.is_empty() || pos.column >= line.len() {
+            return None;
+        }
+
+        let chars: Vec<char> = line.chars().collect();
+        let char_indices: Vec<usize> = line.char_indices().map(|(i, _)| i).collect();
+
+        let mut char_pos = 0;
+        for (idx, &byte_idx) in char_indices.iter().enumerate() {
+            if byte_idx >= pos.column {
+                char_pos = idx;
+                break;
+            }
+        }
+
+        if char_pos >= chars.len() {
+            return None;
+        }
+
+        let current_char = chars[char_pos];
+        if !current_char.is_alphanumeric() && current_char != '_' {
+            return None;
+        }
+
+        let mut start_char = char_pos;
+        while start_char > 0 {
+            let ch = chars[start_char - 1];
+            if !ch.is_alphanumeric() && ch != '_' {
+                break;
+            }
+            start_char -= 1;
+        }
+
+        let mut end_char = char_pos;
+        while end_char < chars.len() {
+            let ch = chars[end_char];
+            if !ch.is_alphanumeric() && ch != '_' {
+                break;
+            }
+            end_char += 1;
+        }
+
+        let start_byte = if start_char < char_indices.len() {
+            char_indices[start_char]
+        } else {
+            line.len()
+        };
+
+        let end_byte = if end_char < char_indices.len() {
+            char_indices[end_char]
+        } else {
+            line.len()
+        };
+
+        Some((
+            BufferPosition::new(pos.row, start_byte),
+            BufferPosition::new(pos.row, end_byte),
+        ))
+    }
+
+    fn handle_mouse_down(
+        &mut self,
+        event: &MouseDownEvent,
+        window: &mut Window,
+        cx: &mut Context<Self>,
+    ) {
+        const DOUBLE_CLICK_DURATION: Duration = Duration::from_millis(500);
+
+        window.focus(&self.focus_handle);
+        self.tree_focused = false;
+        self.outline_focused = false;
+        #[cfg(feature = "terminal")]
+        {
+            self.terminal_focused = false;
+        }
+        self.quick_switch = None;
+        self.context_menu = None;
+
+        let window_size = self.viewport(window).size;
+        let wrap_width = self.wrap_width(window_size.width);
+        let position = self.position_from_mouse(event.position, window, wrap_width);
+
+        if (event.modifiers.platform || event.modifiers.control)
+            && self.follow_link(position.row, position.column, cx)
+        {
+            cx.notify();
+            return;
+        }
+
+        let now = Instant::now();
+        let is_double_click = if let (Some(last_time), Some(last_pos)) =
+            (self.last_click_time, self.last_click_position)
+        {
+            now.duration_since(last_time) < DOUBLE_CLICK_DURATION && last_pos == position
+        } else {
+            false
+        };
+
+        // Pressing inside the selection picks it up to drag rather than
+        // starting a new one
+        if !is_double_click && self.selection_contains(position) {
+            self.text_drag = Some(position);
+            cx.notify();
+            return;
+        }
+
+        if is_double_click {
+            if let Some((start, end)) = self.find_word_boundaries(position) {
+                self.set_selection_anchor(Some(start));
+                self.set_cursor(end);
+                self.is_dragging = false;
+            } else {
+                self.set_cursor(position);
+                self.set_selection_anchor(Some(position));
+                self.is_dragging = true;
+            }
+            self.last_click_time = None;
+            self.last_click_position = None;
+        } else {
+            self.set_cursor(position);
+            self.set_selection_anchor(Some(position));
+            self.is_dragging = true;
+            self.last_click_time = Some(now);
+            self.last_click_position = Some(position);
+        }
+
+        cx.notify();
+    }
+
+    /// Open the context menu at a right click, moving the cursor there
+    /// unless the click is inside the selection
+    fn handle_right_click(
+        &mut self,
+        event: &MouseDownEvent,
+        window: &mut Window,
+        cx: &mut Context<Self>,
+    ) {
+        let wrap_width = self.wrap_width(self.viewport(window).size.width);
+        let position = self.position_from_mouse(event.position, window, wrap_width);
+        if !self.selection_contains(position) {
+            self.set_selection_anchor(None);
+            self.set_cursor(position);
+        }
+        self.context_menu = Some(ContextMenu::new(
+            self.engine.state(),
+            self.engine.diagnostics(),
+        ));
+        self.context_menu_position = event.position;
+        cx.notify();
+    }
+
+    /// Arrow keys move through the context menu and Enter chooses; any
+    /// other key closes it
+    fn handle_context_menu_key(&mut self, key: PanelKey, cx: &mut Context<Self>) {
+        let Some(menu) = self.context_menu.as_mut() else {
+            return;
+        };
+        match key {
+            PanelKey::Up => menu.select_previous(),
+            PanelKey::Down => menu.select_next(),
+            PanelKey::Enter => match menu.selected_item().cloned() {
+                Some(item) => self.run_menu_item(item, cx),
+                None => self.context_menu = None,
+            },
+            _ => self.context_menu = None,
+        }
+    }
+
+    /// Do what a context menu item says, the same way as its shortcut
+    fn run_menu_item(&mut self, item: MenuItem, cx: &mut Context<Self>) {
+        self.context_menu = None;
+        match item {
+            MenuItem::Suggestion(text) => {
+                self.engine
+                    .handle_action(EditorAction::ApplySuggestion(text));
+                self.sync_and_save(cx);
+            }
+            MenuItem::Cut => self.cut_selection(cx),
+            MenuItem::Copy => self.copy_selection(cx),
+            MenuItem::Paste => self.paste_clipboard(false, cx),
+            MenuItem::SelectAll => self.engine.handle_action(EditorAction::SelectAll),
+            MenuItem::SearchWeb => {
+                if let Some(text) = self.selected_text() {
+                    cx.open_url(&context_menu::search_url(&self.search_url, &text));
+                }
+            }
+        }
+        cx.notify();
+    }
+
+    fn handle_mouse_move(
+        &mut self,
+        event: &MouseMoveEvent,
+        window: &mut Window,
+        cx: &mut Context<Self>,
+    ) {
+        if self.minimap_dragging {
+            self.scroll_to_minimap_y(event.position.y);
+            cx.notify();
+        } else if self.text_drag.is_some() {
+            let wrap_width = self.wrap_width(self.viewport(window).size.width);
+            self.text_drag = Some(self.position_from_mouse(event.position, window, wrap_width));
+            cx.notify();
+        } else if self.is_dragging {
+            let window_size = self.viewport(window).size;
+            let wrap_width = self.wrap_width(window_size.width);
+            let position = self.position_from_mouse(event.position, window, wrap_width);
+            self.set_cursor(position);
+            cx.notify();
+        } else {
+            let hovered = if event.modifiers.platform || event.modifiers.control {
+                let wrap_width = self.wrap_width(self.viewport(window).size.width);
+                let position = self.position_from_mouse(event.position, window, wrap_width);
+                self.link_under_mouse(position)
+            } else {
+                None
+            };
+            if hovered != self.hovered_link {
+                self.hovered_link = hovered;
+                cx.notify();
+            }
+        }
+    }
+
+    fn handle_mouse_up(
+        &mut self,
+        event: &MouseUpEvent,
+        _window: &mut Window,
+        cx: &mut Context<Self>,
+    ) {
+        // Dropping dragged text moves it, or with Alt copies it; letting go
+        // inside the selection is a plain click there
+        if let Some(target) = self.text_drag.take() {
+            if self.selection_contains(target) {
+                self.set_selection_anchor(None);
+                self.set_cursor(target);
+            } else {
+                let (row, column) = (target.row, target.column);
+                self.engine.handle_action(if event.modifiers.alt {
+                    EditorAction::CopySelectionTo { row, column }
+                } else {
+                    EditorAction::MoveSelectionTo { row, column }
+                });
+                self.sync_and_save(cx);
+            }
+            cx.notify();
+            return;
+        }
+        self.is_dragging = false;
+        self.minimap_dragging = false;
+        if let Some(anchor) = self.get_selection_anchor() {
+            if anchor == self.get_cursor() {
+                self.set_selection_anchor(None);
+            }
+        }
+        cx.notify();
+    }
+
+    /// Text typed, or finished by an input method when `composed`; panels
+    /// that take key input get it first
+    fn insert_typed_text(&mut self, text: &str, composed: bool, cx: &mut Context<Self>) {
+        if text.is_empty() {
+            cx.notify();
+            return;
+        }
+        if self.handle_panel_key(PanelKey::Text(text.to_string()), cx) {
+            return;
+        }
+        let action = if composed {
+            EditorAction::InsertComposed(text.to_string())
+        } else {
+            EditorAction::TypeString(text.to_string())
+        };
+        self.engine.handle_action(action);
+        self.sync_and_save(cx);
+        cx.notify();
+    }
+
+    /// The cursor's line as the input method sees it, with any composition
+    /// in place, and the cursor's column in it
+    fn ime_line(&self) -> (String, usize) {
+        let cursor = self.engine.state().cursor;
+        let line = &self.engine.state().lines[cursor.row];
+        let text = match &self.composition {
+            Some(composition) => composition.apply(line, cursor.column),
+            None => line.clone(),
+        };
+        (text, cursor.column)
+    }
+
+    /// Select the UTF-16 `range` of the cursor's line, which an input method
+    /// is about to replace
+    fn select_ime_range(&mut self, range: Range<usize>) {
+        let row = self.get_cursor().row;
+        let line = &self.engine.state().lines[row];
+        let start = composition::to_byte(line, range.start);
+        let end = composition::to_byte(line, range.end).max(start);
+        self.set_selection_anchor((start != end).then(|| BufferPosition::new(row, start)));
+        self.set_cursor(BufferPosition::new(row, end));
+    }
+
+    fn handle_scroll(
+        &mut self,
+        event: &ScrollWheelEvent,
+        _window: &mut Window,
+        cx: &mut Context<Self>,
+    ) {
+        // Ctrl with the wheel or trackpad zooms, as in browsers
+        if event.modifiers.control {
+            let steps = match event.delta {
+                ScrollDelta::Lines(lines) => lines.y.signum() as i32,
+                ScrollDelta::Pixels(pixels) => {
+                    self.zoom_pixels += f32::from(pixels.y);
+                    let steps = (self.zoom_pixels / ZOOM_PIXELS_PER_STEP).trunc();
+                    self.zoom_pixels -= steps * ZOOM_PIXELS_PER_STEP;
+                    steps as i32
+                }
+            };
+            for _ in 0..steps.unsigned_abs() {
+                let action = if steps > 0 {
+                    EditorAction::IncreaseFontSize
+                } else {
+                    EditorAction::DecreaseFontSize
+                };
+                self.zoom(action, cx);
+            }
+            return;
+        }
+        self.zoom_pixels = 0.0;
+
+        let line_height = self.line_height();
+        let max_scroll = self.max_scroll();
+
+        match event.delta {
+            // Wheel notches jump a line at a time, so ease toward the new position
+            ScrollDelta::Lines(lines) => {
+                self.scroll_target =
+                    (self.scroll_target - lines.y * line_height).clamp(0.0, max_scroll);
+            }
+            // Trackpads already deliver smooth pixel deltas; follow them directly
+            ScrollDelta::Pixels(pixels) => {
+                self.scroll_target =
+                    (self.scroll_target - f32::from(pixels.y)).clamp(0.0, max_scroll);
+                self.scroll_offset = self.scroll_target;
+            }
+        }
+
+        cx.notify();
+    }
+
+    /// The terminal along the bottom, its screen fitted to the window's
+    /// width; clicking it gives it the keys
+    #[cfg(feature = "terminal")]
+    fn render_terminal_panel(
+        &mut self,
+        window: &mut Window,
+        cx: &mut Context<Self>,
+    ) -> Option<impl IntoElement> {
+        if !self.terminal_visible {
+            return None;
+        }
+        let font = self.buffer.font().clone();
+        let font_size = px(TERMINAL_FONT_SIZE);
+        let text_system = window.text_system();
+        let advance = text_system
+            .advance(text_system.resolve_font(&font), font_size, 'm')
+            .map_or(px(TERMINAL_FONT_SIZE * 0.6), |size| size.width);
+        let line_height = px(TERMINAL_FONT_SIZE * 1.3);
+        let width = window.viewport_size().width - px(32.0);
+        let columns = (width / advance).floor().max(1.0) as u16;
+        let rows = self.engine.config().terminal.height.max(1);
+
+        let focused = self.terminal_focused;
+        let shell = self.terminal.as_mut()?;
+        shell.resize(rows, columns);
+        let title = match shell.exit_code() {
+            Some(code) => format!("Terminal (exited with {})", code),
+            None => "Terminal".to_string(),
+        };
+        let cursor = shell.cursor().filter(|_| focused).map(|(row, column)| {
+            div()
+                .absolute()
+                .top(line_height * row as f32)
+                .left(advance * column as f32)
+                .w(advance)
+                .h(line_height)
+                .bg(self.theme.cursor)
+                .opacity(0.6)
+        });
+        let lines = shell
+            .lines()
+            .into_iter()
+            .map(|line| div().h(line_height).child(SharedString::from(line)));
+
+        Some(
+            div()
+                .absolute()
+                .left_0()
+                .right_0()
+                .bottom_0()
+                .px_4()
+                .py_2()
+                .flex()
+                .flex_col()
+                .font(font)
+                .text_size(font_size)
+                .bg(self.theme.popup_background)
+                .border_t_1()
+                .border_color(if focused {
+                    self.theme.cursor
+                } else {
+                    self.theme.text_muted
+                })
+                .child(div().pb_1().text_color(self.theme.text_muted).child(title))
+                .child(
+                    div()
+                        .relative()
+                        .whitespace_nowrap()
+                        .text_color(self.theme.text)
+                        .children(lines)
+                        .children(cursor),
+                )
+                .on_mouse_down(
+                    MouseButton::Left,
+                    cx.listener(|editor, _: &MouseDownEvent, window, cx| {
+                        window.focus(&editor.focus_handle);
+                        editor.terminal_focused = true;
+                        cx.stop_propagation();
+                        cx.notify();
+                    }),
+                ),
+        )
+    }
+
+    /// Bottom panel listing every diagnostic; clicking one moves the cursor there
+    fn render_diagnostics_panel(&self, cx: &mut Context<Self>) -> impl IntoElement {
+        let cursor = self.engine.state().cursor;
+        let diagnostics = self.engine.diagnostics();
+
+        let rows = diagnostics.iter().map(|d| {
+            let (row, column) = (d.start.row, d.start.column);
+            div()
+                .flex()
+                .gap_3()
+                .px_4()
+                .when(d.contains(cursor), |div| div.bg(self.theme.selection))
+                .child(
+                    div()
+                        .w(px(60.0))
+                        .text_color(self.theme.severity_color(d.severity))
+                        .child(d.severity.label()),
+                )
+                .child(div().text_color(self.theme.text_muted).child(format!(
+                    "{}:{}",
+                    row + 1,
+                    column + 1
+                )))
+                .child(SharedString::from(
+                    d.message.lines().next().unwrap_or_default().to_string(),
+                ))
+                .on_mouse_down(
+                    MouseButton::Left,
+                    cx.listener(move |editor, _: &MouseDownEvent, _, cx| {
+                        editor
+                            .engine
+                            .handle_action(EditorAction::SetCursorPosition { row, column });
+                        editor.ensure_cursor_visible();
+                        cx.stop_propagation();
+                        cx.notify();
+                    }),
+                )
+        });
+
+        div()
+            .absolute()
+            .left_0()
+            .right_0()
+            .bottom_0()
+            .max_h(px(200.0))
+            .py_2()
+            .flex()
+            .flex_col()
+            .overflow_hidden()
+            .font(self.buffer.font().clone())
+            .text_size(px(13.0))
+            .bg(self.theme.popup_background)
+            .when(diagnostics.is_empty(), |panel| {
+                panel.child(
+                    div()
+                        .px_4()
+                        .text_color(self.theme.text_muted)
+                        .child("No problems"),
+                )
+            })
+            .children(rows)
+    }
+
+    /// Directory tree along the left edge, scrolled to keep the selected
+    /// entry in view. Gitignored entries are dimmed and the open file is
+    /// highlighted; clicking a folder expands it and clicking a file opens it.
+    fn render_file_tree(&self, cx: &mut Context<Self>) -> Option<impl IntoElement> {
+        let tree = self
+            .file_tree
+            .as_ref()
+            .filter(|_| self.tree_visible && !self.focus_mode)?;
+
+        let visible_rows =
+            (((self.viewport_height - PADDING_TOP) / SIDEBAR_ROW_HEIGHT) as usize).max(1);
+        let first = tree.selected.saturating_sub(visible_rows - 1);
+        let rows: Vec<Div> = tree
+            .entries()
+            .iter()
+            .enumerate()
+            .skip(first)
+            .take(visible_rows)
+            .map(|(index, entry)| {
+                let icon = match (entry.is_dir, entry.expanded) {
+                    (true, true) => "▾ ",
+                    (true, false) => "▸ ",
+                    (false, _) => "  ",
+                };
+                let color = if self.file_path.as_ref() == Some(&entry.path) {
+                    self.theme.info
+                } else if entry.ignored {
+                    self.theme.text_muted
+                } else {
+                    self.theme.text
+                };
+                div()
+                    .h(px(SIDEBAR_ROW_HEIGHT))
+                    .pl(px(8.0 + entry.depth as f32 * 12.0))
+                    .flex()
+                    .items_center()
+                    .overflow_hidden()
+                    .text_color(color)
+                    .when(index == tree.selected && self.tree_focused, |div| {
+                        div.bg(self.theme.selection)
+                    })
+                    .child(format!("{}{}", icon, entry.name))
+                    .on_mouse_down(
+                        MouseButton::Left,
+                        cx.listener(move |editor, _: &MouseDownEvent, _, cx| {
+                            editor.tree_focused = true;
+                            editor.open_tree_entry(index);
+                            cx.stop_propagation();
+                            cx.notify();
+                        }),
+                    )
+            })
+            .collect();
+
+        Some(
+            div()
+                .absolute()
+                .left_0()
+                .top_0()
+                .bottom_0()
+                .w(px(SIDEBAR_WIDTH))
+                .pt(px(PADDING_TOP))
+                .flex()
+                .flex_col()
+                .overflow_hidden()
+                .font(self.buffer.font().clone())
+                .text_size(px(13.0))
+                .bg(self.theme.popup_background)
+                .cursor(CursorStyle::Arrow)
+                .on_mouse_down(
+                    MouseButton::Left,
+                    cx.listener(|editor, _: &MouseDownEvent, _, cx| {
+                        editor.tree_focused = true;
+                        cx.stop_propagation();
+                        cx.notify();
+                    }),
+                )
+                .children(rows),
+        )
+    }
+
+    /// Recent files or projects under the query being typed, centred near
+    /// the top; clicking one opens it
+    fn render_quick_switch(&self, cx: &mut Context<Self>) -> Option<impl IntoElement> {
+        let switch = self.quick_switch.as_ref()?;
+
+        let rows: Vec<Div> = switch
+            .matches()
+            .into_iter()
+            .enumerate()
+            .take(SEARCH_PANEL_ROWS)
+            .map(|(index, path)| {
+                let (name, dir) = quick_switch::display_name(path);
+                div()
+                    .px_3()
+                    .py_1()
+                    .flex()
+                    .gap_3()
+                    .when(index == switch.selected, |div| div.bg(self.theme.selection))
+                    .child(name)
+                    .child(div().text_color(self.theme.text_muted).child(dir))
+                    .on_mouse_down(
+                        MouseButton::Left,
+                        cx.listener(move |editor, _: &MouseDownEvent, _, cx| {
+                            if let Some(switch) = editor.quick_switch.as_mut() {
+                                switch.selected = index;
+                            }
+                            editor.open_selected_recent();
+                            cx.stop_propagation();
+                            cx.notify();
+                        }),
+                    )
+            })
+            .collect();
+
+        Some(
+            div()
+                .absolute()
+                .top(px(PADDING_TOP))
+                .left_0()
+                .right_0()
+                .flex()
+                .justify_center()
+                .child(
+                    div()
+                        .w(px(520.0))
+                        .py_2()
+                        .flex()
+                        .flex_col()
+                        .overflow_hidden()
+                        .rounded_md()
+                        .font(self.buffer.font().clone())
+                        .text_size(px(13.0))
+                        .bg(self.theme.popup_background)
+                        .cursor(CursorStyle::Arrow)
+                        .child(
+                            div()
+                                .px_3()
+                                .pb_1()
+                                .flex()
+                                .items_center()
+                                .gap_2()
+                                .child(div().text_color(self.theme.info).child(">"))
+                                .child(SharedString::from(switch.query.clone()))
+                                .child(div().w(px(2.0)).h(px(16.0)).bg(self.theme.cursor)),
+                        )
+                        .when(rows.is_empty(), |list| {
+                            list.child(div().px_3().text_color(self.theme.text_muted).child(
+                                if switch.projects {
+                                    "No other projects"
+                                } else {
+                                    "No recent files"
+                                },
+                            ))
+                        })
+                        .children(rows),
+                ),
+        )
+    }
+
+    /// The `:` command line, at the top like the passphrase prompt
+    fn render_command_line(&self) -> Option<impl IntoElement> {
+        let input = self.command_line.as_ref()?;
+        Some(
+            div()
+                .absolute()
+                .top(px(PADDING_TOP))
+                .left_0()
+                .right_0()
+                .flex()
+                .justify_center()
+                .child(
+                    div()
+                        .w(px(420.0))
+                        .p_3()
+                        .flex()
+                        .items_center()
+                        .rounded_md()
+                        .font(self.buffer.font().clone())
+                        .text_size(px(13.0))
+                        .bg(self.theme.popup_background)
+                        .cursor(CursorStyle::Arrow)
+                        .child(div().text_color(self.theme.text_muted).child(":"))
+                        .child(SharedString::from(input.clone()))
+                        .child(div().w(px(2.0)).h(px(16.0)).bg(self.theme.cursor)),
+                ),
+        )
+    }
+
+    /// The passphrase prompt, showing a dot per character typed
+    fn render_passphrase_prompt(&self) -> Option<impl IntoElement> {
+        let prompt = self.passphrase_prompt.as_ref()?;
+        let name = prompt
+            .path
+            .file_name()
+            .map_or_else(String::new, |name| name.to_string_lossy().into_owned());
+        let title = match (prompt.is_new(), &prompt.first) {
+            (true, None) => format!("New passphrase for {}", name),
+            (true, Some(_)) => "Type the passphrase again".to_string(),
+            (false, _) => format!("Passphrase for {}", name),
+        };
+
+        Some(
+            div()
+                .absolute()
+                .top(px(PADDING_TOP))
+                .left_0()
+                .right_0()
+                .flex()
+                .justify_center()
+                .child(
+                    div()
+                        .w(px(420.0))
+                        .p_3()
+                        .flex()
+                        .flex_col()
+                        .gap_2()
+                        .rounded_md()
+                        .font(self.buffer.font().clone())
+                        .text_size(px(13.0))
+                        .bg(self.theme.popup_background)
+                        .cursor(CursorStyle::Arrow)
+                        .child(div().text_color(self.theme.text_muted).child(title))
+                        .child(
+                            div()
+                                .flex()
+                                .items_center()
+                                .child(SharedString::from("•".repeat(prompt.input.chars().count())))
+                                .child(div().w(px(2.0)).h(px(16.0)).bg(self.theme.cursor)),
+                        )
+                        .children(prompt.error.as_ref().map(|error| {
+                            div()
+                                .text_color(self.theme.error)
+                                .child(SharedString::from(error.clone()))
+                        })),
+                ),
+        )
+    }
+
+    /// The current file's local history, newest first; clicking a snapshot
+    /// compares it with the buffer
+    fn render_history_browser(&self, cx: &mut Context<Self>) -> Option<impl IntoElement> {
+        let browser = self.history_browser.as_ref()?;
+        let now = std::time::SystemTime::now();
+
+        let rows: Vec<Div> = browser
+            .snapshots()
+            .iter()
+            .enumerate()
+            .take(SEARCH_PANEL_ROWS)
+            .map(|(index, snapshot)| {
+                div()
+                    .px_3()
+                    .py_1()
+                    .flex()
+                    .gap_3()
+                    .when(index == browser.selected, |div| {
+                        div.bg(self.theme.selection)
+                    })
+                    .child(snapshot.age_label(now))
+                    .child(
+                        div()
+                            .text_color(self.theme.text_muted)
+                            .child(snapshot.size_label()),
+                    )
+                    .on_mouse_down(
+                        MouseButton::Left,
+                        cx.listener(move |editor, _: &MouseDownEvent, _, cx| {
+                            if let Some(browser) = editor.history_browser.as_mut() {
+                                browser.selected = index;
+                            }
+                            editor.open_selected_snapshot(false, cx);
+                            cx.stop_propagation();
+                            cx.notify();
+                        }),
+                    )
+            })
+            .collect();
+
+        Some(
+            div()
+                .absolute()
+                .top(px(PADDING_TOP))
+                .left_0()
+                .right_0()
+                .flex()
+                .justify_center()
+                .child(
+                    div()
+                        .w(px(360.0))
+                        .py_2()
+                        .flex()
+                        .flex_col()
+                        .overflow_hidden()
+                        .rounded_md()
+                        .font(self.buffer.font().clone())
+                        .text_size(px(13.0))
+                        .bg(self.theme.popup_background)
+                        .cursor(CursorStyle::Arrow)
+                        .child(
+                            div()
+                                .px_3()
+                                .pb_1()
+                                .text_color(self.theme.text_muted)
+                                .child("Local history · Enter compare, r restore"),
+                        )
+                        .children(rows),
+                ),
+        )
+    }
+
+    fn render_backlinks(&self, cx: &mut Context<Self>) -> Option<impl IntoElement> {
+        let panel = self.backlinks.as_ref()?;
+
+        let rows: Vec<Div> = panel
+            .entries()
+            .iter()
+            .enumerate()
+            .take(SEARCH_PANEL_ROWS)
+            .map(|(index, entry)| {
+                div()
+                    .px_3()
+                    .py_1()
+                    .flex()
+                    .gap_3()
+                    .overflow_hidden()
+                    .whitespace_nowrap()
+                    .when(index == panel.selected, |div| div.bg(self.theme.selection))
+                    .child(div().flex_none().text_color(self.theme.info).child(format!(
+                        "{}:{}",
+                        notes::note_name(&entry.path),
+                        entry.row + 1
+                    )))
+                    .child(entry.text.clone())
+                    .on_mouse_down(
+                        MouseButton::Left,
+                        cx.listener(move |editor, _: &MouseDownEvent, _, cx| {
+                            if let Some(panel) = editor.backlinks.as_mut() {
+                                panel.selected = index;
+                            }
+                            editor.open_selected_backlink();
+                            cx.stop_propagation();
+                            cx.notify();
+                        }),
+                    )
+            })
+            .collect();
+
+        Some(
+            div()
+                .absolute()
+                .top(px(PADDING_TOP))
+                .left_0()
+                .right_0()
+                .flex()
+                .justify_center()
+                .child(
+                    div()
+                        .w(px(560.0))
+                        .py_2()
+                        .flex()
+                        .flex_col()
+                        .overflow_hidden()
+                        .rounded_md()
+                        .font(self.buffer.font().clone())
+                        .text_size(px(13.0))
+                        .bg(self.theme.popup_background)
+                        .cursor(CursorStyle::Arrow)
+                        .child(
+                            div()
+                                .px_3()
+                                .pb_1()
+                                .text_color(self.theme.text_muted)
+                                .child(format!("Linking to {}", panel.note)),
+                        )
+                        .children(rows),
+                ),
+        )
+    }
+
+    fn render_bookmarks(&self, cx: &mut Context<Self>) -> Option<impl IntoElement> {
+        let panel = self.bookmarks.as_ref()?;
+
+        let rows: Vec<Div> = panel
+            .entries()
+            .iter()
+            .enumerate()
+            .take(SEARCH_PANEL_ROWS)
+            .map(|(index, entry)| {
+                div()
+                    .px_3()
+                    .py_1()
+                    .flex()
+                    .gap_3()
+                    .overflow_hidden()
+                    .whitespace_nowrap()
+                    .when(index == panel.selected, |div| div.bg(self.theme.selection))
+                    .child(
+                        div()
+                            .flex_none()
+                            .text_color(self.theme.info)
+                            .child(format!("{}", entry.row + 1)),
+                    )
+                    .child(entry.text.clone())
+                    .on_mouse_down(
+                        MouseButton::Left,
+                        cx.listener(move |editor, _: &MouseDownEvent, _, cx| {
+                            if let Some(panel) = editor.bookmarks.as_mut() {
+                                panel.selected = index;
+                            }
+                            editor.open_selected_bookmark();
+                            cx.stop_propagation();
+                            cx.notify();
+                        }),
+                    )
+            })
+            .collect();
+
+        Some(
+            div()
+                .absolute()
+                .top(px(PADDING_TOP))
+                .left_0()
+                .right_0()
+                .flex()
+                .justify_center()
+                .child(
+                    div()
+                        .w(px(560.0))
+                        .py_2()
+                        .flex()
+                        .flex_col()
+                        .overflow_hidden()
+                        .rounded_md()
+                        .font(self.buffer.font().clone())
+                        .text_size(px(13.0))
+                        .bg(self.theme.popup_background)
+                        .cursor(CursorStyle::Arrow)
+                        .child(
+                            div()
+                                .px_3()
+                                .pb_1()
+                                .text_color(self.theme.text_muted)
+                                .child("Bookmarks"),
+                        )
+                        .children(rows),
+                ),
+        )
+    }
+
+    /// Notes matching the query, best first, with the line that matched
+    fn render_note_search(&self, cx: &mut Context<Self>) -> Option<impl IntoElement> {
+        let search = self
+            .note_search
+            .as_ref()
+            .filter(|_| self.note_search_visible)?;
+
+        let rows: Vec<Div> = search
+            .hits()
+            .iter()
+            .enumerate()
+            .take(SEARCH_PANEL_ROWS)
+            .map(|(index, hit)| {
+                div()
+                    .px_3()
+                    .py_1()
+                    .flex()
+                    .gap_3()
+                    .overflow_hidden()
+                    .whitespace_nowrap()
+                    .when(index == search.selected, |div| div.bg(self.theme.selection))
+                    .child(
+                        div()
+                            .flex_none()
+                            .text_color(self.theme.info)
+                            .child(notes::note_name(&hit.path)),
+                    )
+                    .child(
+                        div()
+                            .text_color(self.theme.text_muted)
+                            .child(hit.snippet.clone()),
+                    )
+                    .on_mouse_down(
+                        MouseButton::Left,
+                        cx.listener(move |editor, _: &MouseDownEvent, _, cx| {
+                            if let Some(search) = editor.note_search.as_mut() {
+                                search.selected = index;
+                            }
+                            editor.open_selected_note_hit();
+                            cx.stop_propagation();
+                            cx.notify();
+                        }),
+                    )
+            })
+            .collect();
+
+        Some(
+            div()
+                .absolute()
+                .top(px(PADDING_TOP))
+                .left_0()
+                .right_0()
+                .flex()
+                .justify_center()
+                .child(
+                    div()
+                        .w(px(560.0))
+                        .py_2()
+                        .flex()
+                        .flex_col()
+                        .overflow_hidden()
+                        .rounded_md()
+                        .font(self.buffer.font().clone())
+                        .text_size(px(13.0))
+                        .bg(self.theme.popup_background)
+                        .cursor(CursorStyle::Arrow)
+                        .child(
+                            div()
+                                .px_3()
+                                .pb_1()
+                                .flex()
+                                .items_center()
+                                .gap_2()
+                                .child(div().text_color(self.theme.info).child(">"))
+                                .child(SharedString::from(search.query.clone()))
+                                .child(div().w(px(2.0)).h(px(16.0)).bg(self.theme.cursor)),
+                        )
+                        .child(
+                            div()
+                                .px_3()
+                                .pb_1()
+                                .text_color(self.theme.text_muted)
+                                .child(search.status()),
+                        )
+                        .children(rows),
+                ),
+        )
+    }
+
+    fn render_errors(&self, cx: &mut Context<Self>) -> Option<impl IntoElement> {
+        let run = self.task_run.as_ref().filter(|_| self.errors_visible)?;
+
+        let rows: Vec<Div> = if run.problems().is_empty() {
+            // Without errors to list, the end of what the task printed shows
+            // what happened
+            let output: Vec<&str> = run.output().lines().collect();
+            output[output.len().saturating_sub(SEARCH_PANEL_ROWS)..]
+                .iter()
+                .map(|line| {
+                    div()
+                        .px_3()
+                        .overflow_hidden()
+                        .whitespace_nowrap()
+                        .text_color(self.theme.text_muted)
+                        .child(line.to_string())
+                })
+                .collect()
+        } else {
+            run.problems()
+                .iter()
+                .enumerate()
+                .skip((run.selected + 1).saturating_sub(SEARCH_PANEL_ROWS))
+                .take(SEARCH_PANEL_ROWS)
+                .map(|(index, problem)| {
+                    let path = problem
+                        .path
+                        .strip_prefix(run.root())
+                        .unwrap_or(&problem.path);
+                    let place = format!(
+                        "{}:{}:{}",
+                        path.display(),
+                        problem.row + 1,
+                        problem.column + 1
+                    );
+                    div()
+                        .px_3()
+                        .py_1()
+                        .flex()
+                        .gap_3()
+                        .overflow_hidden()
+                        .whitespace_nowrap()
+                        .when(index == run.selected, |div| div.bg(self.theme.selection))
+                        .child(div().flex_none().text_color(self.theme.info).child(place))
+                        .child(
+                            div()
+                                .flex_none()
+                                .text_color(self.theme.severity_color(problem.severity))
+                                .child(problem.severity.label()),
+                        )
+                        .child(problem.message.clone())
+                        .on_mouse_down(
+                            MouseButton::Left,
+                            cx.listener(move |editor, _: &MouseDownEvent, _, cx| {
+                                if let Some(run) = editor.task_run.as_mut() {
+                                    run.selected = index;
+                                }
+                                editor.open_selected_problem();
+                                cx.stop_propagation();
+                                cx.notify();
+                            }),
+                        )
+                })
+                .collect()
+        };
+
+        Some(
+            div()
+                .absolute()
+                .top(px(PADDING_TOP))
+                .left_0()
+                .right_0()
+                .flex()
+                .justify_center()
+                .child(
+                    div()
+                        .w(px(720.0))
+                        .py_2()
+                        .flex()
+                        .flex_col()
+                        .overflow_hidden()
+                        .rounded_md()
+                        .font(self.buffer.font().clone())
+                        .text_size(px(13.0))
+                        .bg(self.theme.popup_background)
+                        .cursor(CursorStyle::Arrow)
+                        .child(
+                            div()
+                                .px_3()
+                                .pb_1()
+                                .text_color(self.theme.text_muted)
+                                .child(format!("{} · Tab runs the next task", run.status())),
+                        )
+                        .children(rows),
+                ),
+        )
+    }
+
+    fn render_todos(&self, cx: &mut Context<Self>) -> Option<impl IntoElement> {
+        let panel = self.todos.as_ref()?;
+
+        let rows: Vec<Div> = panel
+            .matches()
+            .into_iter()
+            .enumerate()
+            .take(SEARCH_PANEL_ROWS)
+            .map(|(index, item)| {
+                let place = match &item.path {
+                    Some(path) => format!("{}:{}", notes::note_name(path), item.row + 1),
+                    None => format!("{}", item.row + 1),
+                };
+                let color = match item.kind {
+                    Kind::Fixme => self.theme.error,
+                    Kind::Todo | Kind::Task => self.theme.warning,
+                    Kind::Note => self.theme.info,
+                };
+                div()
+                    .px_3()
+                    .py_1()
+                    .flex()
+                    .gap_3()
+                    .overflow_hidden()
+                    .whitespace_nowrap()
+                    .when(index == panel.selected, |div| div.bg(self.theme.selection))
+                    .child(div().flex_none().text_color(self.theme.info).child(place))
+                    .child(div().flex_none().text_color(color).child(item.kind.label()))
+                    .child(item.text.clone())
+                    .on_mouse_down(
+                        MouseButton::Left,
+                        cx.listener(move |editor, _: &MouseDownEvent, _, cx| {
+                            if let Some(panel) = editor.todos.as_mut() {
+                                panel.selected = index;
+                            }
+                            editor.open_selected_todo();
+                            cx.stop_propagation();
+                            cx.notify();
+                        }),
+                    )
+            })
+            .collect();
+
+        Some(
+            div()
+                .absolute()
+                .top(px(PADDING_TOP))
+                .left_0()
+                .right_0()
+                .flex()
+                .justify_center()
+                .child(
+                    div()
+                        .w(px(560.0))
+                        .py_2()
+                        .flex()
+                        .flex_col()
+                        .overflow_hidden()
+                        .rounded_md()
+                        .font(self.buffer.font().clone())
+                        .text_size(px(13.0))
+                        .bg(self.theme.popup_background)
+                        .cursor(CursorStyle::Arrow)
+                        .child(
+                            div()
+                                .px_3()
+                                .pb_1()
+                                .flex()
+                                .items_center()
+                                .gap_2()
+                                .child(div().text_color(self.theme.info).child(">"))
+                                .child(SharedString::from(panel.query.clone()))
+                                .child(div().w(px(2.0)).h(px(16.0)).bg(self.theme.cursor)),
+                        )
+                        .child(
+                            div()
+                                .px_3()
+                                .pb_1()
+                                .text_color(self.theme.text_muted)
+                                .child(format!("{} · Tab switches", panel.status())),
+                        )
+                        .children(rows),
+                ),
+        )
+    }
+
+    /// Symbols of the buffer along the right edge, left of the minimap,
+    /// indented by nesting. While unfocused the one the cursor is in is
+    /// highlighted; clicking one jumps to it.
+    fn render_outline(&self, cx: &mut Context<Self>) -> Option<impl IntoElement> {
+        let panel = self.outline.as_ref().filter(|_| !self.focus_mode)?;
+
+        let visible_rows =
+            (((self.viewport_height - PADDING_TOP) / SIDEBAR_ROW_HEIGHT) as usize).max(2) - 1;
+        let selected = if self.outline_focused {
+            Some(panel.selected)
+        } else {
+            panel.containing(self.engine.state().cursor.row)
+        };
+        let first = selected.map_or(0, |selected| selected.saturating_sub(visible_rows - 1));
+        let rows: Vec<Div> = panel
+            .matches()
+            .into_iter()
+            .enumerate()
+            .skip(first)
+            .take(visible_rows)
+            .map(|(index, symbol)| {
+                div()
+                    .h(px(SIDEBAR_ROW_HEIGHT))
+                    .pl(px(8.0 + symbol.depth as f32 * 12.0))
+                    .flex()
+                    .items_center()
+                    .gap_2()
+                    .overflow_hidden()
+                    .whitespace_nowrap()
+                    .when(selected == Some(index), |div| div.bg(self.theme.selection))
+                    .child(
+                        div()
+                            .flex_none()
+                            .text_color(self.theme.text_muted)
+                            .child(symbol.kind.label()),
+                    )
+                    .child(symbol.name.clone())
+                    .on_mouse_down(
+                        MouseButton::Left,
+                        cx.listener(move |editor, _: &MouseDownEvent, _, cx| {
+                            if let Some(panel) = editor.outline.as_mut() {
+                                panel.selected = index;
+                            }
+                            editor.open_selected_outline_symbol();
+                            cx.stop_propagation();
+                            cx.notify();
+                        }),
+                    )
+            })
+            .collect();
+
+        let header = if self.outline_focused {
+            div()
+                .flex()
+                .items_center()
+                .gap_2()
+                .child(div().text_color(self.theme.info).child(">"))
+                .child(SharedString::from(panel.query.clone()))
+                .child(div().w(px(2.0)).h(px(16.0)).bg(self.theme.cursor))
+        } else {
+            div().text_color(self.theme.text_muted).child("Outline")
+        };
+
+        Some(
+            div()
+                .absolute()
+                .right(self.minimap_width())
+                .top_0()
+                .bottom_0()
+                .w(px(SIDEBAR_WIDTH))
+                .pt(px(PADDING_TOP))
+                .flex()
+                .flex_col()
+                .overflow_hidden()
+                .font(self.buffer.font().clone())
+                .text_size(px(13.0))
+                .text_color(self.theme.text)
+                .bg(self.theme.popup_background)
+                .cursor(CursorStyle::Arrow)
+                .on_mouse_down(
+                    MouseButton::Left,
+                    cx.listener(|editor, _: &MouseDownEvent, _, cx| {
+                        editor.outline_focused = true;
+                        cx.stop_propagation();
+                        cx.notify();
+                    }),
+                )
+                .child(div().h(px(SIDEBAR_ROW_HEIGHT)).px_2().child(header))
+                .when(rows.is_empty(), |div| {
+                    div.child(
+                        gpui::div()
+                            .px_2()
+                            .text_color(self.theme.text_muted)
+                            .child("No symbols"),
+                    )
+                })
+                .children(rows),
+        )
+    }
+
+    fn render_symbol_palette(&self, cx: &mut Context<Self>) -> Option<impl IntoElement> {
+        let palette = self.symbol_palette.as_ref()?;
+
+        let rows: Vec<Div> = palette
+            .matches()
+            .into_iter()
+            .enumerate()
+            .take(SEARCH_PANEL_ROWS)
+            .map(|(index, symbol)| {
+                div()
+                    .px_3()
+                    .py_1()
+                    .flex()
+                    .gap_3()
+                    .overflow_hidden()
+                    .whitespace_nowrap()
+                    .when(index == palette.selected, |div| {
+                        div.bg(self.theme.selection)
+                    })
+                    .child(
+                        div()
+                            .flex_none()
+                            .text_color(self.theme.info)
+                            .child(format!("{}", symbol.row + 1)),
+                    )
+                    .child(
+                        div()
+                            .flex_none()
+                            .text_color(self.theme.text_muted)
+                            .child(symbol.kind.label()),
+                    )
+                    .child(symbol.name.clone())
+                    .on_mouse_down(
+                        MouseButton::Left,
+                        cx.listener(move |editor, _: &MouseDownEvent, _, cx| {
+                            if let Some(palette) = editor.symbol_palette.as_mut() {
+                                palette.selected = index;
+                            }
+                            editor.open_selected_palette_symbol();
+                            cx.stop_propagation();
+                            cx.notify();
+                        }),
+                    )
+            })
+            .collect();
+
+        Some(
+            div()
+                .absolute()
+                .top(px(PADDING_TOP))
+                .left_0()
+                .right_0()
+                .flex()
+                .justify_center()
+                .child(
+                    div()
+                        .w(px(560.0))
+                        .py_2()
+                        .flex()
+                        .flex_col()
+                        .overflow_hidden()
+                        .rounded_md()
+                        .font(self.buffer.font().clone())
+                        .text_size(px(13.0))
+                        .bg(self.theme.popup_background)
+                        .cursor(CursorStyle::Arrow)
+                        .child(
+                            div()
+                                .px_3()
+                                .pb_1()
+                                .flex()
+                                .items_center()
+                                .gap_2()
+                                .child(div().text_color(self.theme.info).child(">"))
+                                .child(SharedString::from(palette.query.clone()))
+                                .child(div().w(px(2.0)).h(px(16.0)).bg(self.theme.cursor)),
+                        )
+                        .child(
+                            div()
+                                .px_3()
+                                .pb_1()
+                                .text_color(self.theme.text_muted)
+                                .child(palette.status()),
+                        )
+                        .children(rows),
+                ),
+        )
+    }
+
+    fn render_tags(&self, cx: &mut Context<Self>) -> Option<impl IntoElement> {
+        let index = self.tags.as_ref()?;
+
+        let rows: Vec<Div> = index
+            .tags()
+            .iter()
+            .enumerate()
+            .take(SEARCH_PANEL_ROWS)
+            .map(|(row, tag)| {
+                div()
+                    .px_3()
+                    .py_1()
+                    .flex()
+                    .gap_3()
+                    .overflow_hidden()
+                    .whitespace_nowrap()
+                    .when(row == index.selected, |div| div.bg(self.theme.selection))
+                    .child(format!("#{}", tag.name))
+                    .child(
+                        div()
+                            .text_color(self.theme.text_muted)
+                            .child(format!("{} in {} notes", tag.count, tag.files)),
+                    )
+                    .on_mouse_down(
+                        MouseButton::Left,
+                        cx.listener(move |editor, _: &MouseDownEvent, _, cx| {
+                            if let Some(index) = editor.tags.as_mut() {
+                                index.selected = row;
+                            }
+                            editor.find_selected_tag();
+                            cx.stop_propagation();
+                            cx.notify();
+                        }),
+                    )
+            })
+            .collect();
+        let title = match (index.is_done(), index.tags().len()) {
+            (false, _) => "Tags · indexing…".to_string(),
+            (true, 0) => "No tags".to_string(),
+            (true, count) => format!("{} tags", count),
+        };
+
+        Some(
+            div()
+                .absolute()
+                .top(px(PADDING_TOP))
+                .left_0()
+                .right_0()
+                .flex()
+                .justify_center()
+                .child(
+                    div()
+                        .w(px(360.0))
+                        .py_2()
+                        .flex()
+                        .flex_col()
+                        .overflow_hidden()
+                        .rounded_md()
+                        .font(self.buffer.font().clone())
+                        .text_size(px(13.0))
+                        .bg(self.theme.popup_background)
+                        .cursor(CursorStyle::Arrow)
+                        .child(
+                            div()
+                                .px_3()
+                                .pb_1()
+                                .text_color(self.theme.text_muted)
+                                .child(title),
+                        )
+                        .children(rows),
+                ),
+        )
+    }
+
+    fn render_template_picker(&self, cx: &mut Context<Self>) -> Option<impl IntoElement> {
+        let picker = self.template_picker.as_ref()?;
+
+        let rows: Vec<Div> = picker
+            .templates()
+            .iter()
+            .enumerate()
+            .take(SEARCH_PANEL_ROWS)
+            .map(|(index, path)| {
+                div()
+                    .px_3()
+                    .py_1()
+                    .overflow_hidden()
+                    .whitespace_nowrap()
+                    .when(index == picker.selected, |div| div.bg(self.theme.selection))
+                    .child(templates::title_of(path))
+                    .on_mouse_down(
+                        MouseButton::Left,
+                        cx.listener(move |editor, _: &MouseDownEvent, _, cx| {
+                            if let Some(picker) = editor.template_picker.as_mut() {
+                                picker.selected = index;
+                            }
+                            editor.create_from_selected_template(cx);
+                            cx.stop_propagation();
+                            cx.notify();
+                        }),
+                    )
+            })
+            .collect();
+
+        Some(
+            div()
+                .absolute()
+                .top(px(PADDING_TOP))
+                .left_0()
+                .right_0()
+                .flex()
+                .justify_center()
+                .child(
+                    div()
+                        .w(px(360.0))
+                        .py_2()
+                        .flex()
+                        .flex_col()
+                        .overflow_hidden()
+                        .rounded_md()
+                        .font(self.buffer.font().clone())
+                        .text_size(px(13.0))
+                        .bg(self.theme.popup_background)
+                        .cursor(CursorStyle::Arrow)
+                        .child(
+                            div()
+                                .px_3()
+                                .pb_1()
+                                .text_color(self.theme.text_muted)
+                                .child("New note from template"),
+                        )
+                        .children(rows),
+                ),
+        )
+    }
+
+    /// Right-click menu drawn where the click was, over everything else;
+    /// items that cannot be chosen are dimmed
+    fn render_context_menu(&self, cx: &mut Context<Self>) -> Option<impl IntoElement> {
+        let menu = self.context_menu.as_ref()?;
+        let suggestions = menu.suggestions();
+
+        let rows: Vec<Div> = menu
+            .items
+            .iter()
+            .enumerate()
+            .map(|(index, item)| {
+                let enabled = menu.is_enabled(item);
+                let row = div()
+                    .px_3()
+                    .py_1()
+                    .when(menu.selected == Some(index), |div| {
+                        div.bg(self.theme.selection)
+                    })
+                    .when(index < suggestions, |div| div.font_weight(FontWeight::BOLD))
+                    .when(!enabled, |div| div.text_color(self.theme.text_muted))
+                    .child(SharedString::from(item.label()));
+                let item = item.clone();
+                row.when(enabled, |row| {
+                    row.on_mouse_down(
+                        MouseButton::Left,
+                        cx.listener(move |editor, _: &MouseDownEvent, _, cx| {
+                            editor.run_menu_item(item.clone(), cx);
+                            cx.stop_propagation();
+                        }),
+                    )
+                })
+            })
+            .collect();
+        let separator = (suggestions > 0).then(|| {
+            div()
+                .my_1()
+                .h(px(1.0))
+                .bg(self.theme.text_muted.opacity(0.3))
+        });
+        let mut rows = rows.into_iter();
+
+        Some(
+            deferred(
+                anchored()
+                    .position(self.context_menu_position)
+                    .snap_to_window()
+                    .child(
+                        div()
+                            .min_w(px(200.0))
+                            .py_1()
+                            .flex()
+                            .flex_col()
+                            .rounded_md()
+                            .shadow_md()
+                            .font(self.buffer.font().clone())
+                            .text_size(px(13.0))
+                            .bg(self.theme.popup_background)
+                            .cursor(CursorStyle::Arrow)
+                            // Clicks on dimmed items keep the menu open
+                            .on_mouse_down(MouseButton::Left, |_, _, cx| cx.stop_propagation())
+                            .children(rows.by_ref().take(suggestions))
+                            .children(separator)
+                            .children(rows),
+                    ),
+            )
+            .with_priority(2),
+        )
+    }
+
+    fn render_char_picker(&self, cx: &mut Context<Self>) -> Option<impl IntoElement> {
+        let picker = self.char_picker.as_ref()?;
+
+        let rows: Vec<Div> = picker
+            .matches()
+            .iter()
+            .enumerate()
+            .take(SEARCH_PANEL_ROWS)
+            .map(|(index, symbol)| {
+                div()
+                    .px_3()
+                    .py_1()
+                    .flex()
+                    .gap_3()
+                    .overflow_hidden()
+                    .whitespace_nowrap()
+                    .when(index == picker.selected, |div| div.bg(self.theme.selection))
+                    .child(div().flex_none().w(px(24.0)).child(symbol.text.clone()))
+                    .child(div().flex_none().child(symbol.name.clone()))
+                    .children(symbol.shortcode.as_ref().map(|shortcode| {
+                        div()
+                            .text_color(self.theme.text_muted)
+                            .child(format!(":{}:", shortcode))
+                    }))
+                    .on_mouse_down(
+                        MouseButton::Left,
+                        cx.listener(move |editor, _: &MouseDownEvent, _, cx| {
+                            if let Some(picker) = editor.char_picker.as_mut() {
+                                picker.selected = index;
+                            }
+                            editor.insert_selected_char(cx);
+                            cx.stop_propagation();
+                            cx.notify();
+                        }),
+                    )
+            })
+            .collect();
+
+        Some(
+            div()
+                .absolute()
+                .top(px(PADDING_TOP))
+                .left_0()
+                .right_0()
+                .flex()
+                .justify_center()
+                .child(
+                    div()
+                        .w(px(560.0))
+                        .py_2()
+                        .flex()
+                        .flex_col()
+                        .overflow_hidden()
+                        .rounded_md()
+                        .font(self.buffer.font().clone())
+                        .text_size(px(13.0))
+                        .bg(self.theme.popup_background)
+                        .cursor(CursorStyle::Arrow)
+                        .child(
+                            div()
+                                .px_3()
+                                .pb_1()
+                                .flex()
+                                .items_center()
+                                .gap_2()
+                                .child(div().text_color(self.theme.info).child(">"))
+                                .child(SharedString::from(picker.query.clone()))
+                                .child(div().w(px(2.0)).h(px(16.0)).bg(self.theme.cursor)),
+                        )
+                        .children(rows),
+                ),
+        )
+    }
+
+    /// The buffer beside the version it is compared with, theirs on the
+    /// left and mine on the right, starting a few rows above the current hunk
+    fn render_diff_view(&self) -> Option<impl IntoElement> {
+        let view = self.diff_view.as_ref()?;
+        let mine = &self.engine.state().lines;
+        let rows = view.side_by_side_rows(mine.len());
+        let first = DiffView::selected_row(&rows, view.selected).saturating_sub(3);
+
+        let cell = |text: Option<&String>, row: Option<usize>, color: Option<Hsla>| {
+            let number = row.map_or(String::new(), |row| (row + 1).to_string());
+            div()
+                .flex_1()
+                .flex()
+                .gap_2()
+                .overflow_hidden()
+                .when_some(color, |div, color| div.bg(color.opacity(0.15)))
+                .child(
+                    div()
+                        .w(px(40.0))
+                        .flex_none()
+                        .text_color(self.theme.text_muted)
+                        .child(number),
+                )
+                .child(SharedString::from(text.cloned().unwrap_or_default()))
+        };
+        let lines: Vec<Div> = rows
+            .iter()
+            .skip(first)
+            .take(DIFF_VIEW_ROWS)
+            .map(|row| match *row {
+                DiffRow::Same { theirs, mine: row } => div()
+                    .flex()
+                    .gap_4()
+                    .child(cell(view.theirs().get(theirs), Some(theirs), None))
+                    .child(cell(mine.get(row), Some(row), None)),
+                DiffRow::Changed {
+                    hunk,
+                    theirs,
+                    mine: row,
+                } => div()
+                    .flex()
+                    .gap_4()
+                    .when(hunk == view.selected, |div| div.bg(self.theme.selection))
+                    .child(cell(
+                        theirs.and_then(|theirs| view.theirs().get(theirs)),
+                        theirs,
+                        Some(self.theme.removed),
+                    ))
+                    .child(cell(
+                        row.and_then(|row| mine.get(row)),
+                        row,
+                        Some(self.theme.added),
+                    )),
+                DiffRow::Fold(count) => div()
+                    .pl(px(48.0))
+                    .text_color(self.theme.text_muted)
+                    .child(format!("⋯ {} unchanged lines", count)),
+            })
+            .collect();
+
+        let status = if view.hunks().is_empty() {
+            "no differences".to_string()
+        } else {
+            format!("hunk {}/{}", view.selected + 1, view.hunks().len())
+        };
+        Some(
+            div()
+                .absolute()
+                .top(px(PADDING_TOP))
+                .left_4()
+                .right_4()
+                .py_2()
+                .flex()
+                .flex_col()
+                .overflow_hidden()
+                .rounded_md()
+                .font(self.buffer.font().clone())
+                .text_size(px(13.0))
+                .bg(self.theme.popup_background)
+                .cursor(CursorStyle::Arrow)
+                .child(
+                    div()
+                        .px_3()
+                        .pb_1()
+                        .flex()
+                        .gap_3()
+                        .child(format!("{} ↔ buffer", view.title))
+                        .child(div().text_color(self.theme.text_muted).child(format!(
+                            "{} · n/p move, t take theirs, m keep mine, Esc close",
+                            status
+                        ))),
+                )
+                .child(div().px_3().flex().flex_col().children(lines)),
+        )
+    }
+
+    /// Find-in-files query and results grouped by file; clicking a match
+    /// jumps to it. When replacing, each match shows its line before and
+    /// after, and clicking its checkbox includes or excludes it.
+    fn render_search_panel(&self, cx: &mut Context<Self>) -> Option<impl IntoElement> {
+        let panel = self.search_panel.as_ref().filter(|_| self.search_visible)?;
+
+        let rows = panel.rows();
+        let selected_row = rows
+            .iter()
+            .position(|row| match row {
+                SearchRow::Match { index, .. } | SearchRow::Change { index, .. } => {
+                    *index == panel.selected
+                }
+                SearchRow::File(_) => false,
+            })
+            .unwrap_or(0);
+        let first = selected_row.saturating_sub(SEARCH_PANEL_ROWS / 2);
+
+        let select = |index: usize| {
+            cx.listener(move |editor: &mut Self, _: &MouseDownEvent, _, cx| {
+                if let Some(panel) = editor.search_panel.as_mut() {
+                    panel.selected = index;
+                }
+                editor.jump_to_search_result();
+                cx.stop_propagation();
+                cx.notify();
+            })
+        };
+        let rows: Vec<Div> = rows
+            .into_iter()
+            .skip(first)
+            .take(SEARCH_PANEL_ROWS)
+            .map(|row| match row {
+                SearchRow::File(path) => div()
+                    .px_4()
+                    .pt_1()
+                    .text_color(self.theme.warning)
+                    .child(path.display().to_string()),
+                SearchRow::Match { index, row, text } => div()
+                    .px_4()
+                    .when(index == panel.selected, |div| div.bg(self.theme.selection))
+                    .child(format!("{:>4}: {}", row + 1, text.trim()))
+                    .on_mouse_down(MouseButton::Left, select(index)),
+                SearchRow::Change {
+                    index,
+                    row,
+                    before,
+                    after,
+                    included,
+                } => {
+                    let (removed, added) = if included {
+                        (self.theme.removed, self.theme.added)
+                    } else {
+                        (self.theme.text_muted, self.theme.text_muted)
+                    };
+                    div()
+                        .px_4()
+                        .flex()
+                        .gap_2()
+                        .when(index == panel.selected, |div| div.bg(self.theme.selection))
+                        .child(
+                            div()
+                                .child(if included { "[x]" } else { "[ ]" })
+                                .on_mouse_down(
+                                    MouseButton::Left,
+                                    cx.listener(move |editor, _: &MouseDownEvent, _, cx| {
+                                        if let Some(panel) = editor.search_panel.as_mut() {
+                                            panel.selected = index;
+                                            panel.toggle_selected();
+                                        }
+                                        cx.stop_propagation();
+                                        cx.notify();
+                                    }),
+                                ),
+                        )
+                        .child(format!("{:>4}:", row + 1))
+                        .child(
+                            div()
+                                .flex()
+                                .flex_col()
+                                .child(
+                                    div()
+                                        .text_color(removed)
+                                        .child(format!("- {}", before.trim())),
+                                )
+                                .child(div().text_color(added).child(format!("+ {}", after.trim())))
+                                .on_mouse_down(MouseButton::Left, select(index)),
+                        )
+                }
+            })
+            .collect();
+
+        let field = |label: &'static str, text: &str, active: bool| {
+            div()
+                .px_4()
+                .flex()
+                .gap_3()
+                .child(div().text_color(self.theme.info).child(label))
+                .child(
+                    div()
+                        .flex()
+                        .items_center()
+                        .child(SharedString::from(text.to_string()))
+                        .when(active, |text| {
+                            text.child(div().w(px(2.0)).h(px(16.0)).bg(self.theme.cursor))
+                        }),
+                )
+        };
+        let typing = panel.search().is_none() || panel.editing_replacement;
+
+        Some(
+            div()
+                .absolute()
+                .left_0()
+                .right_0()
+                .bottom_0()
+                .py_2()
+                .flex()
+                .flex_col()
+                .overflow_hidden()
+                .font(self.buffer.font().clone())
+                .text_size(px(13.0))
+                .bg(self.theme.popup_background)
+                .child(
+                    field("Find:", &panel.query, typing && !panel.editing_replacement).child(
+                        div()
+                            .text_color(self.theme.text_muted)
+                            .child(panel.status()),
+                    ),
+                )
+                .children(
+                    panel
+                        .replacement
+                        .as_deref()
+                        .map(|text| field("Replace:", text, panel.editing_replacement)),
+                )
+                .children(rows),
+        )
+    }
+
+    /// Miniature of the buffer along the right edge: one bar per line, the
+    /// visible region, and marks for search matches and diagnostics.
+    /// Clicking or dragging scrolls the editor there.
+    fn render_minimap(&self, cx: &mut Context<Self>) -> impl IntoElement {
+        let offset = self.minimap_offset();
+        let first = (offset / MINIMAP_LINE_HEIGHT) as usize;
+        let count = (self.viewport_height / MINIMAP_LINE_HEIGHT) as usize + 1;
+        let rows = first..(first + count).min(self.buffer.line_count());
+        let y_of = |row: usize| px(row as f32 * MINIMAP_LINE_HEIGHT - offset);
+        // Characters are drawn a pixel wide, leaving room for the marks on the right
+        let max_chars = MINIMAP_WIDTH - 12.0;
+
+        let lines = rows.clone().filter_map(|row| {
+            let line = self.buffer.line(row)?;
+            let indent = line.chars().take_while(|c| c.is_whitespace()).count() as f32;
+            let len = line.trim_end().chars().count() as f32 - indent;
+            (len > 0.0).then(|| {
+                div()
+                    .absolute()
+                    .top(y_of(row))
+                    .left(px(4.0 + indent.min(max_chars)))
+                    .w(px(len.min(max_chars - indent).max(1.0)))
+                    .h(px(MINIMAP_LINE_HEIGHT - 1.0))
+                    .bg(self.theme.text_muted)
+            })
+        });
+
+        let matches = self
+            .engine
+            .search_matches()
+            .into_iter()
+            .filter(|(start, _)| rows.contains(&start.row))
+            .map(|(start, end)| {
+                let line = self.buffer.line(start.row).unwrap_or_default();
+                let column = line[..start.column].chars().count() as f32;
+                let len = line[start.column..end.column].chars().count() as f32;
+                div()
+                    .absolute()
+                    .top(y_of(start.row) - px(1.0))
+                    .left(px(4.0 + column.min(max_chars)))
+                    .w(px(len.max(2.0)))
+                    .h(px(MINIMAP_LINE_HEIGHT + 1.0))
+                    .bg(self.theme.warning)
+            });
+
+        let diagnostic_marks = self
+            .engine
+            .diagnostics()
+            .iter()
+            .filter(|d| rows.contains(&d.start.row))
+            .map(|d| {
+                div()
+                    .absolute()
+                    .top(y_of(d.start.row))
+                    .right(px(2.0))
+                    .w(px(4.0))
+                    .h(px(MINIMAP_LINE_HEIGHT))
+                    .bg(self.theme.severity_color(d.severity))
+            });
+
+        let scale = MINIMAP_LINE_HEIGHT / self.line_height();
+        let viewport = div()
+            .absolute()
+            .left_0()
+            .right_0()
+            .top(px(self.scroll_offset * scale - offset))
+            .h(px((self.viewport_height - PADDING_TOP) * scale))
+            .bg(self.theme.selection.opacity(0.5));
+
+        div()
+            .absolute()
+            .top_0()
+            .right_0()
+            .bottom_0()
+            .w(px(MINIMAP_WIDTH))
+            .overflow_hidden()
+            .bg(self.theme.popup_background.opacity(0.5))
+            .cursor(CursorStyle::Arrow)
+            .child(viewport)
+            .children(lines)
+            .children(matches)
+            .children(diagnostic_marks)
+            .on_mouse_down(
+                MouseButton::Left,
+                cx.listener(|editor, event: &MouseDownEvent, _, cx| {
+                    editor.minimap_dragging = true;
+                    editor.scroll_to_minimap_y(event.position.y);
+                    cx.stop_propagation();
+                    cx.notify();
+                }),
+            )
+    }
+
+    /// The inline virtual text in the part `byte_range` of line `row`, by
+    /// column, with its color and the width it is shown at; the padding of
+    /// elastic tabstops is blank virtual text
+    fn inline_hints(
+        &self,
+        row: usize,
+        byte_range: &Range<usize>,
+        line_len: usize,
+        font_size: Pixels,
+        text_system: &WindowTextSystem,
+    ) -> Vec<InlineHint> {
+        let in_range = |column: usize| {
+            byte_range.contains(&column) || (column == byte_range.end && column == line_len)
+        };
+        let mut hints: Vec<InlineHint> = self
+            .engine
+            .line_decorations(row)
+            .into_iter()
+            .filter_map(|decoration| match decoration {
+                LineDecoration::Inline { column, text, tone } if in_range(column) => {
+                    let shaped = shape_text(text, self.buffer.font(), font_size, text_system);
+                    Some(InlineHint {
+                        column,
+                        text: text.to_string(),
+                        color: self.theme.tone_color(tone),
+                        width: shaped.x_for_index(text.len()),
+                    })
                 }
-                visual_row_counter += 1;
-            }
+                _ => None,
+            })
+            .collect();
+        // A layout from before the last edit could point anywhere
+        let layout = self
+            .tab_layout
+            .as_ref()
+            .filter(|layout| layout.revision == self.engine.revision());
+        if let Some(layout) = layout {
+            let padding = layout.padding.get(row).into_iter().flatten();
+            hints.extend(
+                padding
+                    .filter(|padding| padding.width > 0 && in_range(padding.column))
+                    .map(|padding| InlineHint {
+                        column: padding.column,
+                        text: " ".repeat(padding.width),
+                        color: self.theme.text,
+                        width: layout.space_width * padding.width as f32,
+                    }),
+            );
+            hints.sort_by_key(|hint| hint.column);
         }
-
-        let last_row = self.buffer.line_count().saturating_sub(1);
-        let last_col = self.buffer.line_len(last_row);
-        BufferPosition::new(last_row, last_col)
+        hints
     }
 
-    fn find_word_boundaries(&self, pos: BufferPosition) -> Option<(BufferPosition, BufferPosition)> {
-        let line = self.buffer.line(pos.row)?;
-        if line.is_empty() || pos.column >= line.len() {
-            return None;
+    /// Bar in the left margin beside a line changed since the last commit;
+    /// removed lines show as a tick above the line that followed them
+    fn render_change_marker(&self, kind: ChangeKind, text_margin: Pixels) -> Div {
+        let marker = div().absolute().left(px(4.0) - text_margin);
+        match kind {
+            ChangeKind::Added => marker.top_0().bottom_0().w(px(3.0)).bg(self.theme.added),
+            ChangeKind::Modified => marker.top_0().bottom_0().w(px(3.0)).bg(self.theme.modified),
+            ChangeKind::Removed => marker.top_0().h(px(2.0)).w(px(8.0)).bg(self.theme.removed),
         }
+    }
 
-        let chars: Vec<char> = line.chars().collect();
-        let char_indices: Vec<usize> = line.char_indices().map(|(i, _)| i).collect();
-
-        let mut char_pos = 0;
-        for (idx, &byte_idx) in char_indices.iter().enumerate() {
-            if byte_idx >= pos.column {
-                char_pos = idx;
-                break;
-            }
-        }
+    /// The cursor itself; `char_width` is the width of the character under it
+    fn render_cursor(&self, x: Pixels, char_width: Pixels) -> Div {
+        // Not gpui's CursorStyle, which is the mouse pointer
+        use zlyph_core::config::CursorStyle;
 
-        if char_pos >= chars.len() {
-            return None;
+        let cursor = div().absolute().left(x);
+        match self.cursor_config.style.unwrap_or(CursorStyle::Bar) {
+            CursorStyle::Bar => cursor
+                .top(px(0.0))
+                .bottom(px(0.0))
+                .w(px(2.0))
+                .bg(self.theme.cursor),
+            CursorStyle::Block => cursor
+                .top(px(0.0))
+                .bottom(px(0.0))
+                .w(char_width)
+                .bg(self.theme.cursor.opacity(0.5)),
+            CursorStyle::Underline => cursor
+                .bottom(px(0.0))
+                .h(px(2.0))
+                .w(char_width)
+                .bg(self.theme.cursor),
         }
+    }
 
-        let current_char = chars[char_pos];
-        if !current_char.is_alphanumeric() && current_char != '_' {
+    /// Fading trail between the cursor's previous and current position on the
+    /// same visual line, when smearing is enabled
+    fn render_cursor_smear(
+        &self,
+        row: usize,
+        segment: usize,
+        cursor_x: Pixels,
+        window: &Window,
+    ) -> Option<Div> {
+        if !self.cursor_config.smear {
             return None;
         }
 
-        let mut start_char = char_pos;
-        while start_char > 0 {
-            let ch = chars[start_char - 1];
-            if !ch.is_alphanumeric() && ch != '_' {
-                break;
+        let previous = self.last_cursor_x.replace(Some((row, segment, cursor_x)));
+        if let Some((prev_row, prev_segment, from_x)) = previous {
+            if (prev_row, prev_segment) == (row, segment) && from_x != cursor_x {
+                self.smear.set(Some(CursorSmear {
+                    row,
+                    segment,
+                    from_x,
+                    started: Instant::now(),
+                }));
             }
-            start_char -= 1;
         }
 
-        let mut end_char = char_pos;
-        while end_char < chars.len() {
-            let ch = chars[end_char];
-            if !ch.is_alphanumeric() && ch != '_' {
-                break;
-            }
-            end_char += 1;
+        let smear = self
+            .smear
+            .get()
+            .filter(|s| (s.row, s.segment) == (row, segment))?;
+        let t = smear.started.elapsed().as_secs_f32() / SMEAR_DURATION.as_secs_f32();
+        if t >= 1.0 {
+            self.smear.set(None);
+            return None;
         }
+        window.request_animation_frame();
 
-        let start_byte = if start_char < char_indices.len() {
-            char_indices[start_char]
+        // The tail catches up with the cursor as it fades
+        let tail = smear.from_x + (cursor_x - smear.from_x) * t;
+        let (left, right) = if tail < cursor_x {
+            (tail, cursor_x)
         } else {
-            line.len()
+            (cursor_x, tail)
         };
+        Some(
+            div()
+                .absolute()
+                .left(left)
+                .w(right - left)
+                .top(px(4.0))
+                .bottom(px(4.0))
+                .bg(self.theme.cursor.opacity(0.4 * (1.0 - t))),
+        )
+    }
 
-        let end_byte = if end_char < char_indices.len() {
-            char_indices[end_char]
+    /// What the editor is showing besides the buffer, announced when it changes
+    fn accessibility_status(&self) -> Option<String> {
+        if let Some(hover) = self.engine.hover() {
+            return hover.lines().next().map(str::to_string);
+        }
+        let panel = if self.passphrase_prompt.is_some() {
+            "Passphrase"
+        } else if self.diff_view.is_some() {
+            "Diff view"
+        } else if self.history_browser.is_some() {
+            "Local history"
+        } else if self.backlinks.is_some() {
+            "Backlinks"
+        } else if self.bookmarks.is_some() {
+            "Bookmarks"
+        } else if self.note_search_visible {
+            "Search notes"
+        } else if self.tags.is_some() {
+            "Tags"
+        } else if self.todos.is_some() {
+            "TODOs"
+        } else if self.errors_visible && self.task_run.is_some() {
+            "Errors"
+        } else if self.symbol_palette.is_some() {
+            "Go to symbol"
+        } else if self.template_picker.is_some() {
+            "New note from template"
+        } else if self.char_picker.is_some() {
+            "Insert character"
+        } else if self.quick_switch.is_some() {
+            "Recent files"
+        } else if self.tree_focused && self.tree_visible {
+            "File tree"
+        } else if self.outline_focused && self.outline.is_some() {
+            "Outline"
+        } else if self.search_visible {
+            "Find in files"
         } else {
-            line.len()
+            return None;
         };
-
-        Some((BufferPosition::new(pos.row, start_byte), BufferPosition::new(pos.row, end_byte)))
+        Some(panel.to_string())
     }
 
-    fn handle_mouse_down(&mut self, event: &MouseDownEvent, window: &mut Window, cx: &mut Context<Self>) {
-        const DOUBLE_CLICK_DURATION: Duration = Duration::from_millis(500);
-
-        let window_size = window.viewport_size();
-        let wrap_width = window_size.width - px(32.0);
-        let position = self.position_from_mouse(event.position, window, wrap_width);
-
-        let now = Instant::now();
-        let is_double_click = if let (Some(last_time), Some(last_pos)) =
-            (self.last_click_time, self.last_click_position)
+    /// Remote file transfers and writing session progress in the top-right
+    /// corner, level with the traffic lights
+    fn render_status_bar(&self) -> Option<impl IntoElement> {
+        let transfer = self
+            .file_progress
+            .clone()
+            .or_else(|| Some(self.transfer_status.as_ref()?.label()))
+            .map(|label| {
+                div()
+                    .text_color(self.theme.warning)
+                    .child(SharedString::from(label))
+            });
+        let announcement = self
+            .announcer
+            .latest()
+            .map(|text| SharedString::from(text.to_string()));
+        let search = self
+            .engine
+            .search_status()
+            .map(|status| div().child(SharedString::from(status)));
+        let recording = self.engine.dictating().then(|| {
+            div()
+                .text_color(self.theme.error)
+                .child(SharedString::from("● Dictating"))
+        });
+        let session = self.engine.writing_session();
+        if transfer.is_none()
+            && session.is_none()
+            && announcement.is_none()
+            && search.is_none()
+            && recording.is_none()
         {
-            now.duration_since(last_time) < DOUBLE_CLICK_DURATION && last_pos == position
+            return None;
+        }
+        let words = self.engine.word_count();
+        let color = if session.is_some_and(|session| session.is_complete(words)) {
+            self.theme.info
         } else {
-            false
+            self.theme.text_muted
         };
+        let progress_bar = session
+            .and_then(|session| session.progress(words))
+            .map(|progress| {
+                div()
+                    .w(px(80.0))
+                    .h(px(4.0))
+                    .rounded_sm()
+                    .bg(self.theme.selection)
+                    .child(div().h_full().w(px(80.0 * progress)).rounded_sm().bg(color))
+            });
 
-        if is_double_click {
-            if let Some((start, end)) = self.find_word_boundaries(position) {
-                self.set_selection_anchor(Some(start));
-                self.set_cursor(end);
-                self.is_dragging = false;
-            } else {
-                self.set_cursor(position);
-                self.set_selection_anchor(Some(position));
-                self.is_dragging = true;
-            }
-            self.last_click_time = None;
-            self.last_click_position = None;
-        } else {
-            self.set_cursor(position);
-            self.set_selection_anchor(Some(position));
-            self.is_dragging = true;
-            self.last_click_time = Some(now);
-            self.last_click_position = Some(position);
-        }
+        Some(
+            div()
+                .absolute()
+                .top(px(6.0))
+                .right(px(16.0) + self.minimap_width() + self.outline_width())
+                .flex()
+                .items_center()
+                .gap_2()
+                .font(self.buffer.font().clone())
+                .text_size(px(12.0))
+                .text_color(color)
+                .children(recording)
+                .children(announcement)
+                .children(search)
+                .children(transfer)
+                .children(progress_bar)
+                .children(session.map(|session| SharedString::from(session.status(words)))),
+        )
+    }
 
-        cx.notify();
+    /// Hover documentation drawn below the cursor, above later lines
+    fn render_hover(&self, text: &str, x: Pixels, line_height: Pixels) -> impl IntoElement {
+        deferred(
+            div()
+                .absolute()
+                .left(x)
+                .top(line_height)
+                .max_w(px(600.0))
+                .p_2()
+                .rounded_md()
+                .shadow_md()
+                .bg(self.theme.popup_background)
+                .text_size(line_height / self.font_config.line_height * 0.5)
+                .line_height(relative(1.3))
+                .whitespace_normal()
+                .children(
+                    text.lines()
+                        .map(|line| div().child(SharedString::from(line.to_string()))),
+                ),
+        )
+        .with_priority(1)
     }
 
-    fn handle_mouse_move(&mut self, event: &MouseMoveEvent, window: &mut Window, cx: &mut Context<Self>) {
-        if self.is_dragging {
-            let window_size = window.viewport_size();
-            let wrap_width = window_size.width - px(32.0);
-            let position = self.position_from_mouse(event.position, window, wrap_width);
-            self.set_cursor(position);
-            cx.notify();
-        }
+    /// Completion list drawn below the word being completed, above later lines
+    fn render_completion(
+        &self,
+        completion: &zlyph_core::completion::Completion,
+        x: Pixels,
+        line_height: Pixels,
+    ) -> impl IntoElement {
+        let items = completion.items.iter().enumerate().map(|(i, item)| {
+            div()
+                .px_2()
+                .when(i == completion.selected, |d| d.bg(self.theme.selection))
+                .child(SharedString::from(item.clone()))
+        });
+
+        deferred(
+            div()
+                .absolute()
+                .left(x)
+                .top(line_height)
+                .flex()
+                .flex_col()
+                .py_1()
+                .rounded_md()
+                .shadow_md()
+                .bg(self.theme.popup_background)
+                .text_size(line_height / self.font_config.line_height * 0.6)
+                .children(items),
+        )
+        .with_priority(1)
     }
+}
 
-    fn handle_mouse_up(&mut self, _event: &MouseUpEvent, _window: &mut Window, cx: &mut Context<Self>) {
-        self.is_dragging = false;
-        if let Some(anchor) = self.get_selection_anchor() {
-            if anchor == self.get_cursor() {
-                self.set_selection_anchor(None);
+/// `highlights` split where the `overlay` ranges start and end, with each
+/// overlay's style laid over the text's own inside it
+fn overlay_highlights(
+    highlights: Vec<(Range<usize>, HighlightStyle)>,
+    overlay: &[(Range<usize>, HighlightStyle)],
+) -> Vec<(Range<usize>, HighlightStyle)> {
+    if overlay.is_empty() {
+        return highlights;
+    }
+    let end = highlights
+        .iter()
+        .chain(overlay)
+        .map(|(range, _)| range.end)
+        .max()
+        .unwrap_or(0);
+    let mut bounds: Vec<usize> = highlights
+        .iter()
+        .chain(overlay)
+        .flat_map(|(range, _)| [range.start, range.end])
+        .chain([0, end])
+        .collect();
+    bounds.sort_unstable();
+    bounds.dedup();
+    bounds
+        .windows(2)
+        .filter_map(|piece| {
+            let piece = piece[0]..piece[1];
+            let covers =
+                |range: &Range<usize>| range.start <= piece.start && piece.end <= range.end;
+            let style = highlights
+                .iter()
+                .find(|(range, _)| covers(range))
+                .map(|(_, style)| *style);
+            let over = overlay
+                .iter()
+                .filter(|(range, _)| covers(range))
+                .map(|(_, style)| *style)
+                .reduce(lay_over);
+            match (style, over) {
+                (style, Some(over)) => Some((piece, lay_over(style.unwrap_or_default(), over))),
+                (Some(style), None) => Some((piece, style)),
+                (None, None) => None,
             }
-        }
-        cx.notify();
+        })
+        .collect()
+}
+
+/// `under` with the color, background and font style that `over` sets
+/// put in place of its own
+fn lay_over(under: HighlightStyle, over: HighlightStyle) -> HighlightStyle {
+    HighlightStyle {
+        color: over.color.or(under.color),
+        background_color: over.background_color.or(under.background_color),
+        font_style: over.font_style.or(under.font_style),
+        ..under
     }
+}
 
-    fn handle_key_down(&mut self, event: &KeyDownEvent, _window: &mut Window, cx: &mut Context<Self>) {
-        if let Some(key_char) = &event.keystroke.key_char {
-            if !event.keystroke.modifiers.platform
-                && !event.keystroke.modifiers.control
-                && !event.keystroke.modifiers.alt
-            {
-                self.engine.handle_action(EditorAction::TypeString(key_char.clone()));
-                self.sync_and_save();
-                cx.notify();
+/// Virtual text shown inside a line, before the character at `column`
+struct InlineHint {
+    column: usize,
+    text: String,
+    color: Hsla,
+    width: Pixels,
+}
+
+/// Put `hints` into `text`, the part of a line from byte `offset`, and move
+/// `highlights` to match, splitting those a hint lands inside, with the
+/// hints' own styles among them
+fn insert_hints(
+    text: &mut String,
+    highlights: Vec<(Range<usize>, HighlightStyle)>,
+    hints: &[InlineHint],
+    offset: usize,
+) -> Vec<(Range<usize>, HighlightStyle)> {
+    if hints.is_empty() {
+        return highlights;
+    }
+    for hint in hints.iter().rev() {
+        text.insert_str(hint.column - offset, &hint.text);
+    }
+    // Bytes of hint text shown before `at`, and with `inclusive` at it
+    let shift = |at: usize, inclusive: bool| -> usize {
+        hints
+            .iter()
+            .take_while(|hint| {
+                hint.column - offset < at || (inclusive && hint.column - offset == at)
+            })
+            .map(|hint| hint.text.len())
+            .sum()
+    };
+    let mut shown = Vec::with_capacity(highlights.len() + hints.len() * 2);
+    for (range, style) in highlights {
+        let mut start = range.start;
+        for hint in hints {
+            let at = hint.column - offset;
+            if start < at && at < range.end {
+                shown.push((start + shift(start, true)..at + shift(at, false), style));
+                start = at;
             }
         }
+        shown.push((
+            start + shift(start, true)..range.end + shift(range.end, false),
+            style,
+        ));
     }
-
-    fn handle_scroll(&mut self, event: &ScrollWheelEvent, _window: &mut Window, cx: &mut Context<Self>) {
-        let line_height = self.get_font_size() * 1.5;
-        let delta: f32 = match event.delta {
-            ScrollDelta::Lines(lines) => lines.y * line_height,
-            ScrollDelta::Pixels(pixels) => f32::from(pixels.y),
+    let mut before = 0;
+    for hint in hints {
+        let at = hint.column - offset + before;
+        let style = HighlightStyle {
+            color: Some(hint.color),
+            font_style: Some(FontStyle::Italic),
+            ..Default::default()
         };
+        shown.push((at..at + hint.text.len(), style));
+        before += hint.text.len();
+    }
+    shown.sort_by_key(|(range, _)| range.start);
+    shown
+}
 
-        self.scroll_offset -= delta;
+/// Where `column` of a line is shown, pushed along by the width of the
+/// inline virtual text before it, and with `inclusive` also that at it
+fn hint_shift(hints: &[InlineHint], column: usize, inclusive: bool) -> Pixels {
+    hints
+        .iter()
+        .take_while(|hint| hint.column < column || (inclusive && hint.column == column))
+        .fold(px(0.0), |shift, hint| shift + hint.width)
+}
 
-        // Clamp scroll offset
-        let total_lines = self.buffer.line_count() as f32;
-        let max_scroll = (total_lines * line_height).max(0.0);
-        self.scroll_offset = self.scroll_offset.clamp(0.0, max_scroll);
+/// The terminal panel's name for a keystroke, or None for keys it ignores
+#[cfg(feature = "terminal")]
+fn terminal_key(keystroke: &Keystroke) -> Option<TerminalKey> {
+    let modifiers = keystroke.modifiers;
+    Some(match keystroke.key.as_str() {
+        "enter" => TerminalKey::Enter,
+        "backspace" => TerminalKey::Backspace,
+        "tab" if modifiers.shift => TerminalKey::BackTab,
+        "tab" => TerminalKey::Tab,
+        "escape" => TerminalKey::Escape,
+        "up" => TerminalKey::Up,
+        "down" => TerminalKey::Down,
+        "left" => TerminalKey::Left,
+        "right" => TerminalKey::Right,
+        "home" => TerminalKey::Home,
+        "end" => TerminalKey::End,
+        "pageup" => TerminalKey::PageUp,
+        "pagedown" => TerminalKey::PageDown,
+        "insert" => TerminalKey::Insert,
+        "delete" => TerminalKey::Delete,
+        "space" => TerminalKey::Char(' '),
+        key => {
+            if let Some(n) = key.strip_prefix('f').and_then(|n| n.parse().ok()) {
+                return Some(TerminalKey::F(n));
+            }
+            // With Control or Alt held the key itself is sent, not the
+            // character the keyboard layout makes of it
+            let text = match &keystroke.key_char {
+                Some(text) if !modifiers.control && !modifiers.alt => text.as_str(),
+                _ => key,
+            };
+            let mut chars = text.chars();
+            let c = chars.next()?;
+            if chars.next().is_some() {
+                return None;
+            }
+            TerminalKey::Char(c)
+        }
+    })
+}
 
-        cx.notify();
-    }
+/// When the config file was last changed, if it exists
+fn config_modified() -> Option<SystemTime> {
+    std::fs::metadata(Config::default_path())
+        .and_then(|metadata| metadata.modified())
+        .ok()
 }
 
 impl Focusable for TextEditor {
@@ -568,22 +7198,240 @@ impl Focusable for TextEditor {
     }
 }
 
+/// Typing goes through the platform's input method, which may compose text
+/// over several keys before committing it. Its view of the document is the
+/// cursor's line, in UTF-16 offsets.
+impl EntityInputHandler for TextEditor {
+    fn text_for_range(
+        &mut self,
+        range: Range<usize>,
+        adjusted_range: &mut Option<Range<usize>>,
+        _: &mut Window,
+        _: &mut Context<Self>,
+    ) -> Option<String> {
+        let (line, _) = self.ime_line();
+        let start = composition::to_byte(&line, range.start);
+        let end = composition::to_byte(&line, range.end).max(start);
+        adjusted_range
+            .replace(composition::to_utf16(&line, start)..composition::to_utf16(&line, end));
+        Some(line[start..end].to_string())
+    }
+
+    fn selected_text_range(
+        &mut self,
+        _ignore_disabled_input: bool,
+        _: &mut Window,
+        _: &mut Context<Self>,
+    ) -> Option<UTF16Selection> {
+        let (line, column) = self.ime_line();
+        let cursor = self.get_cursor();
+        let (start, end, reversed) = match &self.composition {
+            Some(composition) => (
+                column + composition.selected.start,
+                column + composition.selected.end,
+                false,
+            ),
+            None => match self.get_selection_anchor() {
+                Some(anchor) if anchor.row == cursor.row => (
+                    anchor.column.min(column),
+                    anchor.column.max(column),
+                    column < anchor.column,
+                ),
+                _ => (column, column, false),
+            },
+        };
+        Some(UTF16Selection {
+            range: composition::to_utf16(&line, start)..composition::to_utf16(&line, end),
+            reversed,
+        })
+    }
+
+    fn marked_text_range(&self, _: &mut Window, _: &mut Context<Self>) -> Option<Range<usize>> {
+        let composition = self.composition.as_ref()?;
+        let (line, column) = self.ime_line();
+        Some(
+            composition::to_utf16(&line, column)
+                ..composition::to_utf16(&line, column + composition.text.len()),
+        )
+    }
+
+    fn unmark_text(&mut self, _: &mut Window, cx: &mut Context<Self>) {
+        if let Some(composition) = self.composition.take() {
+            self.insert_typed_text(&composition.text, true, cx);
+        }
+    }
+
+    fn replace_text_in_range(
+        &mut self,
+        range: Option<Range<usize>>,
+        text: &str,
+        _: &mut Window,
+        cx: &mut Context<Self>,
+    ) {
+        // The composition is replaced by what it turned into
+        if self.composition.take().is_some() {
+            self.insert_typed_text(text, true, cx);
+        } else if let Some(range) = range {
+            self.select_ime_range(range);
+            self.insert_typed_text(text, true, cx);
+        } else {
+            self.insert_typed_text(text, false, cx);
+        }
+    }
+
+    fn replace_and_mark_text_in_range(
+        &mut self,
+        range: Option<Range<usize>>,
+        new_text: &str,
+        new_selected_range: Option<Range<usize>>,
+        _: &mut Window,
+        cx: &mut Context<Self>,
+    ) {
+        // Recomposing text already typed
+        if let (None, Some(range)) = (&self.composition, range) {
+            self.select_ime_range(range);
+        }
+        self.composition =
+            (!new_text.is_empty()).then(|| Composition::new(new_text, new_selected_range));
+        cx.notify();
+    }
+
+    fn bounds_for_range(
+        &mut self,
+        range_utf16: Range<usize>,
+        _element_bounds: Bounds<Pixels>,
+        window: &mut Window,
+        _: &mut Context<Self>,
+    ) -> Option<Bounds<Pixels>> {
+        let mut bounds = self.ime_cursor.get()?;
+        if let Some(composition) = &self.composition {
+            let (line, column) = self.ime_line();
+            let offset = composition::to_byte(&line, range_utf16.start)
+                .saturating_sub(column)
+                .min(composition.text.len());
+            let shaped = shape_text(
+                &composition.text,
+                self.buffer.font(),
+                px(self.get_font_size()),
+                window.text_system(),
+            );
+            bounds.origin.x += shaped.x_for_index(offset);
+        }
+        Some(bounds)
+    }
+
+    fn character_index_for_point(
+        &mut self,
+        point: Point<Pixels>,
+        window: &mut Window,
+        _: &mut Context<Self>,
+    ) -> Option<usize> {
+        let wrap_width = self.wrap_width(self.viewport(window).size.width);
+        let position = self.position_from_mouse(point, window, wrap_width);
+        let (line, column) = self.ime_line();
+        if position.row != self.get_cursor().row {
+            return None;
+        }
+        let composed = self.composition.as_ref().map_or(0, |c| c.text.len());
+        let index = if position.column > column {
+            position.column + composed
+        } else {
+            position.column
+        };
+        Some(composition::to_utf16(&line, index))
+    }
+}
+
 impl Render for TextEditor {
     fn render(&mut self, _window: &mut Window, _cx: &mut Context<Self>) -> impl IntoElement {
         // Check for file changes on every render
         self.check_and_reload(_cx);
 
+        let title = self.window_title();
+        if title != self.window_title && !self.embedded {
+            _window.set_window_title(&title);
+            _window.set_window_edited(self.dirty);
+            self.window_title = title;
+        }
+
+        if !self.font_applied {
+            self.apply_font(_window);
+        }
+        if !self.embedded {
+            self.update_dock_menu(_cx);
+        }
+
         let font_size_px = px(self.get_font_size());
         let cursor = self.get_cursor();
         let is_empty = self.buffer.line_count() == 1 && self.buffer.line_len(0) == 0;
-        let window_size = _window.viewport_size();
-        let wrap_width = window_size.width - px(32.0);
+        let viewport = self.viewport(_window);
+        let window_size = viewport.size;
+        let wrap_width = self.wrap_width(window_size.width);
+        let text_margin = self.text_margin(window_size.width);
+        self.viewport_height = f32::from(window_size.height);
+        self.step_scroll(_window);
+        // Shaping may shift the scroll position, so it comes before the text
+        // is placed
+        let rows = self.shape_visible_rows(font_size_px, wrap_width, _window.text_system());
+        self.engine.set_visible_rows(rows.clone());
+
+        // Moving the cursor restarts the blink cycle so it stays solid while typing
+        if cursor != self.last_cursor {
+            self.last_cursor = cursor;
+            self.last_cursor_move = Instant::now();
+        }
+        let cursor_visible = self.cursor_visible();
+        self.cursor_drawn_visible = cursor_visible;
+        // An embedded editor shows its cursor only while it has focus
+        let cursor_visible =
+            cursor_visible && (!self.embedded || self.focus_handle.is_focused(_window));
+
+        let status = self.accessibility_status();
+        self.announcer
+            .update(self.engine.state(), status.as_deref());
+
+        // Focus mode hides everything but the text and dims all but the current paragraph
+        let diagnostics_panel = (self.engine.diagnostics_panel_visible() && !self.focus_mode)
+            .then(|| self.render_diagnostics_panel(_cx));
+        #[cfg(feature = "terminal")]
+        let terminal_panel = self
+            .render_terminal_panel(_window, _cx)
+            .filter(|_| !self.focus_mode);
+        #[cfg(not(feature = "terminal"))]
+        let terminal_panel: Option<Div> = None;
+        let status_bar = self
+            .render_status_bar()
+            .filter(|_| !self.focus_mode && !self.embedded);
+        let breadcrumbs = self.render_breadcrumbs(text_margin + self.sidebar_width(), _cx);
+        let search_panel = self.render_search_panel(_cx);
+        let file_tree = self.render_file_tree(_cx);
+        let quick_switch = self.render_quick_switch(_cx);
+        let diff_view = self.render_diff_view();
+        let history_browser = self.render_history_browser(_cx);
+        let backlinks = self.render_backlinks(_cx);
+        let bookmarks = self.render_bookmarks(_cx);
+        let tags = self.render_tags(_cx);
+        let todos = self.render_todos(_cx);
+        let errors = self.render_errors(_cx);
+        let outline = self.render_outline(_cx);
+        let symbol_palette = self.render_symbol_palette(_cx);
+        let note_search = self.render_note_search(_cx);
+        let template_picker = self.render_template_picker(_cx);
+        let char_picker = self.render_char_picker(_cx);
+        let context_menu = self.render_context_menu(_cx);
+        let passphrase_prompt = self.render_passphrase_prompt();
+        let command_line = self.render_command_line();
+        let minimap = self.minimap_visible().then(|| self.render_minimap(_cx));
+        let focused = self.focus_mode.then(|| {
+            let state = self.engine.state();
+            match self.focus_scope {
+                FocusScope::Paragraph => text_objects::paragraph_range(&state.lines, cursor.row),
+                FocusScope::Sentence => sentences::at(&state.lines, state.cursor),
+            }
+        });
 
         div()
             .track_focus(&self.focus_handle)
-            .on_action(_cx.listener(Self::increase_font_size))
-            .on_action(_cx.listener(Self::decrease_font_size))
-            .on_action(_cx.listener(Self::reset_font_size))
             .on_action(_cx.listener(Self::handle_newline))
             .on_action(_cx.listener(Self::handle_backspace))
             .on_action(_cx.listener(Self::handle_delete))
@@ -597,8 +7445,22 @@ impl Render for TextEditor {
             .on_action(_cx.listener(Self::move_down))
             .on_action(_cx.listener(Self::move_word_left))
             .on_action(_cx.listener(Self::move_word_right))
+            .on_action(_cx.listener(Self::move_to_next_sentence))
+            .on_action(_cx.listener(Self::move_to_prev_sentence))
+            .on_action(_cx.listener(Self::jump_to_char))
+            .on_action(_cx.listener(Self::jump_before_char))
+            .on_action(_cx.listener(Self::leap))
             .on_action(_cx.listener(Self::move_line_up))
             .on_action(_cx.listener(Self::move_line_down))
+            .on_action(_cx.listener(Self::move_section_up))
+            .on_action(_cx.listener(Self::move_section_down))
+            .on_action(_cx.listener(Self::promote_section))
+            .on_action(_cx.listener(Self::demote_section))
+            .on_action(_cx.listener(Self::select_section))
+            .on_action(_cx.listener(Self::insert_footnote))
+            .on_action(_cx.listener(Self::renumber_footnotes))
+            .on_action(_cx.listener(Self::go_to_footnote))
+            .on_action(_cx.listener(Self::insert_bibliography))
             .on_action(_cx.listener(Self::select_left))
             .on_action(_cx.listener(Self::select_right))
             .on_action(_cx.listener(Self::select_up))
@@ -606,18 +7468,146 @@ impl Render for TextEditor {
             .on_action(_cx.listener(Self::select_word_left))
             .on_action(_cx.listener(Self::select_word_right))
             .on_action(_cx.listener(Self::select_all))
+            .on_action(_cx.listener(Self::select_line))
+            .on_action(_cx.listener(Self::select_sentence))
+            .on_action(_cx.listener(Self::select_paragraph))
+            .on_action(_cx.listener(Self::select_to_top))
+            .on_action(_cx.listener(Self::select_to_bottom))
+            .on_action(_cx.listener(Self::select_to_line_start))
+            .on_action(_cx.listener(Self::select_to_line_end))
+            .on_action(_cx.listener(Self::expand_selection))
+            .on_action(_cx.listener(Self::shrink_selection))
+            .on_action(_cx.listener(Self::select_parent))
+            .on_action(_cx.listener(Self::select_next_sibling))
             .on_action(_cx.listener(Self::copy))
             .on_action(_cx.listener(Self::cut))
             .on_action(_cx.listener(Self::paste))
+            .on_action(_cx.listener(Self::paste_plain))
             .on_action(_cx.listener(Self::undo))
             .on_action(_cx.listener(Self::redo))
             .on_action(_cx.listener(Self::delete_line))
             .on_action(_cx.listener(Self::handle_tab))
             .on_action(_cx.listener(Self::handle_outdent))
-            .on_key_down(_cx.listener(Self::handle_key_down))
+            .on_action(_cx.listener(Self::cancel))
             .on_mouse_down(MouseButton::Left, _cx.listener(Self::handle_mouse_down))
             .on_mouse_move(_cx.listener(Self::handle_mouse_move))
             .on_mouse_up(MouseButton::Left, _cx.listener(Self::handle_mouse_up))
+            // The app's own commands, which an embedding app has no use for
+            .when(!self.embedded, |editor| {
+                editor
+                    .on_action(_cx.listener(Self::increase_font_size))
+                    .on_action(_cx.listener(Self::decrease_font_size))
+                    .on_action(_cx.listener(Self::reset_font_size))
+                    .on_action(_cx.listener(Self::trigger_completion))
+                    .on_action(_cx.listener(Self::hover))
+                    .on_action(_cx.listener(Self::go_to_definition))
+                    .on_action(_cx.listener(Self::next_match))
+                    .on_action(_cx.listener(Self::prev_match))
+                    .on_action(_cx.listener(Self::select_all_matches))
+                    .on_action(_cx.listener(Self::next_diagnostic))
+                    .on_action(_cx.listener(Self::prev_diagnostic))
+                    .on_action(_cx.listener(Self::next_change))
+                    .on_action(_cx.listener(Self::prev_change))
+                    .on_action(_cx.listener(Self::preview_hunk))
+                    .on_action(_cx.listener(Self::revert_hunk))
+                    .on_action(_cx.listener(Self::stage_hunk))
+                    .on_action(_cx.listener(Self::diff_with_saved))
+                    .on_action(_cx.listener(Self::diff_with_head))
+                    .on_action(_cx.listener(Self::diff_with_file))
+                    .on_action(_cx.listener(Self::show_local_history))
+                    .on_action(_cx.listener(Self::follow_link_at_cursor))
+                    .on_action(_cx.listener(Self::toggle_backlinks))
+                    .on_action(_cx.listener(Self::toggle_bookmark))
+                    .on_action(_cx.listener(Self::next_bookmark))
+                    .on_action(_cx.listener(Self::prev_bookmark))
+                    .on_action(_cx.listener(Self::show_bookmarks))
+                    .on_action(_cx.listener(Self::new_from_template))
+                    .on_action(_cx.listener(Self::show_tags))
+                    .on_action(_cx.listener(Self::show_todos))
+                    .on_action(_cx.listener(Self::find_references))
+                    .on_action(_cx.listener(Self::run_task))
+                    .on_action(_cx.listener(Self::show_errors))
+                    .on_action(_cx.listener(Self::next_error))
+                    .on_action(_cx.listener(Self::previous_error))
+                    .on_action(_cx.listener(Self::toggle_outline))
+                    .on_action(_cx.listener(Self::go_to_symbol))
+                    .on_action(_cx.listener(Self::search_notes))
+                    .on_action(_cx.listener(Self::insert_date))
+                    .on_action(_cx.listener(Self::insert_time))
+                    .on_action(_cx.listener(Self::insert_timestamp))
+                    .on_action(_cx.listener(Self::show_character_picker))
+                    .on_action(_cx.listener(Self::insert_unicode_codepoint))
+                    .on_action(_cx.listener(Self::open_daily_note))
+                    .on_action(_cx.listener(Self::previous_daily_note))
+                    .on_action(_cx.listener(Self::next_daily_note))
+                    .on_action(_cx.listener(Self::toggle_diagnostics_panel))
+                    .on_action(_cx.listener(Self::toggle_terminal))
+                    .on_action(_cx.listener(Self::send_to_terminal))
+                    .on_action(_cx.listener(Self::capture_terminal))
+                    .on_action(_cx.listener(Self::quick_fix))
+                    .on_action(_cx.listener(Self::strip_invisibles))
+                    .on_action(_cx.listener(Self::evaluate_selection))
+                    .on_action(_cx.listener(Self::align_on))
+                    .on_action(_cx.listener(Self::run_shell_command))
+                    .on_action(_cx.listener(Self::filter_through_command))
+                    .on_action(_cx.listener(Self::format_json))
+                    .on_action(_cx.listener(Self::format_toml))
+                    .on_action(_cx.listener(Self::format_yaml))
+                    .on_action(_cx.listener(Self::minify_json))
+                    .on_action(_cx.listener(Self::sort_keys))
+                    .on_action(_cx.listener(Self::transform_selection))
+                    .on_action(_cx.listener(Self::increment_number))
+                    .on_action(_cx.listener(Self::decrement_number))
+                    .on_action(_cx.listener(Self::number_sequence))
+                    .on_action(_cx.listener(Self::format_table))
+                    .on_action(_cx.listener(Self::insert_table_row))
+                    .on_action(_cx.listener(Self::delete_table_row))
+                    .on_action(_cx.listener(Self::insert_table_column))
+                    .on_action(_cx.listener(Self::delete_table_column))
+                    .on_action(_cx.listener(Self::toggle_writing_session))
+                    .on_action(_cx.listener(Self::toggle_dictation))
+                    .on_action(_cx.listener(Self::toggle_focus_mode))
+                    .on_action(_cx.listener(Self::toggle_sentence_focus))
+                    .on_action(_cx.listener(Self::toggle_minimap))
+                    .on_action(_cx.listener(Self::toggle_breadcrumbs))
+                    .on_action(_cx.listener(Self::toggle_elastic_tabstops))
+                    .on_action(_cx.listener(Self::toggle_rainbow_brackets))
+                    .on_action(_cx.listener(Self::find_in_files))
+                    .on_action(_cx.listener(Self::replace_in_files))
+                    .on_action(_cx.listener(Self::toggle_replace_hunk))
+                    .on_action(_cx.listener(Self::apply_replace))
+                    .on_action(_cx.listener(Self::toggle_whole_word))
+                    .on_action(_cx.listener(Self::toggle_preserve_case))
+                    .on_action(_cx.listener(Self::undo_replace_in_file))
+                    .on_action(_cx.listener(Self::open_file_dialog))
+                    .on_action(_cx.listener(Self::save_as))
+                    .on_action(_cx.listener(Self::export_html))
+                    .on_action(_cx.listener(Self::export_pdf))
+                    .on_action(_cx.listener(Self::export_image))
+                    .on_action(_cx.listener(Self::print))
+                    .on_action(_cx.listener(Self::overwrite_remote))
+                    .on_action(_cx.listener(Self::new_file))
+                    .on_action(_cx.listener(Self::open_recent))
+                    .on_action(_cx.listener(Self::switch_project))
+                    .on_action(_cx.listener(Self::show_command_line))
+                    .on_action(_cx.listener(Self::toggle_file_tree))
+                    .on_action(_cx.listener(Self::focus_file_tree))
+                    .on_action(_cx.listener(Self::tree_new_file))
+                    .on_action(_cx.listener(Self::tree_new_folder))
+                    .on_action(_cx.listener(Self::tree_rename))
+                    .on_action(_cx.listener(Self::tree_move))
+                    .on_action(_cx.listener(Self::tree_delete))
+                    .on_action(_cx.listener(Self::toggle_fullscreen))
+                    .on_action(_cx.listener(Self::quit))
+                    .on_action(_cx.listener(Self::open_recent_file))
+                    .on_action(_cx.listener(Self::toggle_title_bar))
+                    .on_action(_cx.listener(Self::toggle_blur))
+                    .on_action(_cx.listener(Self::increase_opacity))
+                    .on_action(_cx.listener(Self::decrease_opacity))
+                    .on_mouse_down(MouseButton::Right, _cx.listener(Self::handle_right_click))
+                    .on_drop(_cx.listener(Self::handle_file_drop))
+                    .drag_over::<ExternalPaths>(|style, _, _, _| style.opacity(0.8))
+            })
             .on_scroll_wheel(_cx.listener(Self::handle_scroll))
             .size_full()
             .bg(self.theme.background)
@@ -626,13 +7616,14 @@ impl Render for TextEditor {
             .overflow_hidden()
             .child(
                 div()
-                    .font_family("Monaco")
+                    .font(self.buffer.font().clone())
                     .text_size(font_size_px)
-                    .line_height(relative(1.5))
+                    .line_height(relative(self.font_config.line_height))
                     .flex()
                     .flex_col()
-                    .pt_10()
-                    .px_4()
+                    .pt(px(self.padding_top()))
+                    .pl(text_margin + self.sidebar_width())
+                    .pr(text_margin)
                     .top(px(-self.scroll_offset))
                     .when(is_empty, |parent| {
                         parent.child(
@@ -643,33 +7634,57 @@ impl Render for TextEditor {
                                 .child(
                                     div()
                                         .text_color(self.theme.text_muted)
-                                        .child("Start typing..."),
+                                        .child(self.placeholder.clone()),
                                 )
-                                .child(
-                                    div()
-                                        .absolute()
-                                        .left(px(0.0))
-                                        .top(px(0.0))
-                                        .w(px(2.0))
-                                        .h(font_size_px)
-                                        .bg(self.theme.cursor),
-                                ),
+                                .when(cursor_visible, |parent| {
+                                    parent.child(self.render_cursor(px(0.0), font_size_px * 0.6))
+                                }),
                         )
                     })
                     .when(!is_empty, |parent| {
                         let selection_range = self.selection_range();
                         let mut container = parent;
                         let text_system = _window.text_system();
+                        self.highlighter
+                            .set_brackets(self.rainbow_brackets && !self.engine.is_large_file());
+                        self.highlighter.update(
+                            self.file_path
+                                .as_deref()
+                                .filter(|_| !self.engine.is_large_file()),
+                            &self.engine.state().lines,
+                            self.engine.revision(),
+                        );
+                        self.update_tab_layout(font_size_px, wrap_width, text_system);
+                        // Guides are a space's width apart per column of indent
+                        let guides_shown = self.indent_guides_shown();
+                        if guides_shown {
+                            self.indent_guides
+                                .update(&self.engine.state().lines, self.engine.revision());
+                        }
+                        let space_width = guides_shown.then(|| {
+                            shape_text(" ", self.buffer.font(), font_size_px, text_system)
+                                .x_for_index(1)
+                        });
+                        let active_guide =
+                            space_width.and_then(|_| self.indent_guides.active(cursor.row));
 
-                        for row in 0..self.buffer.line_count() {
+                        // A spacer stands in for the lines above those laid out
+                        let mut visual_row = self.buffer.first_visual_row(rows.start);
+                        container = container.child(
+                            div()
+                                .flex_none()
+                                .h(px(visual_row as f32 * self.line_height())),
+                        );
+                        // Collapsed frontmatter shows its summary on its first line
+                        let summary = self.engine.collapsed_frontmatter().and_then(|hidden| {
+                            Some((hidden.start - 1, self.engine.frontmatter()?.summary()))
+                        });
+                        for row in rows {
                             let line_text = self.buffer.line(row).unwrap_or("").to_string();
-
-                            self.buffer.get_or_shape_line(
-                                row,
-                                font_size_px,
-                                wrap_width,
-                                &text_system,
-                            );
+                            let summary = summary
+                                .as_ref()
+                                .filter(|(at, _)| *at == row)
+                                .map(|(_, summary)| summary);
 
                             if let Some(visual_lines) = self.buffer.get_visual_lines(row) {
                                 let visual_lines_vec: Vec<_> = visual_lines
@@ -677,11 +7692,20 @@ impl Render for TextEditor {
                                     .map(|vl| (vl.byte_range.clone(), vl.wrap_type))
                                     .collect();
 
-                                for (_visual_idx, (byte_range, wrap_type)) in
-                                    visual_lines_vec.iter().enumerate()
-                                {
-                                    let segment_text = &line_text[byte_range.clone()];
-                                    let mut display_text = segment_text.to_string();
+                                for (byte_range, wrap_type) in visual_lines_vec.iter() {
+                                    // Right-to-left text is shown in visual order
+                                    let bidi_segment = BidiSegment::new(
+                                        &line_text,
+                                        byte_range.clone(),
+                                        self.buffer.font(),
+                                        font_size_px,
+                                        text_system,
+                                    );
+                                    let mut display_text = match (summary, &bidi_segment) {
+                                        (Some(summary), _) => summary.clone(),
+                                        (None, Some(segment)) => segment.layout.text.clone(),
+                                        (None, None) => line_text[byte_range.clone()].to_string(),
+                                    };
 
                                     if *wrap_type == WrapType::Hyphenated {
                                         display_text.push('-');
@@ -691,14 +7715,239 @@ impl Render for TextEditor {
                                         && cursor.column >= byte_range.start
                                         && cursor.column <= byte_range.end;
 
+                                    // Text being composed is shown in place at the cursor
+                                    let composing = self
+                                        .composition
+                                        .as_ref()
+                                        .filter(|_| is_cursor_on_this_segment);
+                                    let composed_at = composing.map(|composition| {
+                                        let column =
+                                            cursor.column.min(line_text.len()) - byte_range.start;
+                                        let at = match &bidi_segment {
+                                            Some(segment) => segment.layout.display_index(column),
+                                            None => column,
+                                        };
+                                        display_text.insert_str(at, &composition.text);
+                                        (at, composition.text.len())
+                                    });
+
+                                    let is_dimmed = summary.is_some()
+                                        || focused.is_some_and(|(start, end)| {
+                                            !(start.row..=end.row).contains(&row)
+                                        });
+
+                                    // Markdown styles within this segment, which dimmed
+                                    // lines go without
+                                    let highlights: Vec<_> = self
+                                        .highlighter
+                                        .line(row)
+                                        .filter(|_| !is_dimmed)
+                                        .map(|line| {
+                                            line.spans
+                                                .iter()
+                                                .filter(|(range, _)| {
+                                                    range.start < byte_range.end
+                                                        && range.end > byte_range.start
+                                                })
+                                                .map(|(range, style)| {
+                                                    let start = range.start.max(byte_range.start)
+                                                        - byte_range.start;
+                                                    let end = range.end.min(byte_range.end)
+                                                        - byte_range.start;
+                                                    (start..end, self.theme.highlight_style(*style))
+                                                })
+                                                .collect()
+                                        })
+                                        .unwrap_or_default();
+                                    // Protected text is muted and decorated text tinted,
+                                    // over their markdown styles
+                                    let in_segment = |range: Range<usize>| {
+                                        (range.start < byte_range.end
+                                            && range.end > byte_range.start)
+                                            .then(|| {
+                                                range.start.max(byte_range.start) - byte_range.start
+                                                    ..range.end.min(byte_range.end)
+                                                        - byte_range.start
+                                            })
+                                    };
+                                    let muted = HighlightStyle {
+                                        color: Some(self.theme.text_muted),
+                                        ..Default::default()
+                                    };
+                                    let mut overlay: Vec<_> = self
+                                        .engine
+                                        .protected_in_line(row)
+                                        .into_iter()
+                                        .filter_map(in_segment)
+                                        .map(|range| (range, muted))
+                                        .collect();
+                                    // A focused sentence leaves the rest of its
+                                    // lines dimmed
+                                    if let Some((start, end)) = focused {
+                                        let before = (row == start.row).then_some(0..start.column);
+                                        let after =
+                                            (row == end.row).then_some(end.column..line_text.len());
+                                        overlay.extend(
+                                            before
+                                                .into_iter()
+                                                .chain(after)
+                                                .filter(|range| !range.is_empty())
+                                                .filter_map(in_segment)
+                                                .map(|range| (range, muted)),
+                                        );
+                                    }
+                                    let mut end_of_line = Vec::new();
+                                    let decorations = match summary {
+                                        Some(_) => Vec::new(),
+                                        None => self.engine.line_decorations(row),
+                                    };
+                                    for decoration in decorations {
+                                        match decoration {
+                                            LineDecoration::Highlight { columns, tone } => {
+                                                let tint = HighlightStyle {
+                                                    background_color: Some(
+                                                        self.theme.tone_color(tone).opacity(0.25),
+                                                    ),
+                                                    ..Default::default()
+                                                };
+                                                overlay.extend(
+                                                    in_segment(columns).map(|range| (range, tint)),
+                                                );
+                                            }
+                                            LineDecoration::EndOfLine { text, tone }
+                                                if byte_range.end == line_text.len() =>
+                                            {
+                                                end_of_line.push((
+                                                    SharedString::from(text.to_string()),
+                                                    self.theme.tone_color(tone),
+                                                ));
+                                            }
+                                            _ => {}
+                                        }
+                                    }
+                                    let highlights = overlay_highlights(highlights, &overlay);
+                                    let highlights: Vec<_> = match &bidi_segment {
+                                        Some(segment) => highlights
+                                            .into_iter()
+                                            .flat_map(|(range, style)| {
+                                                segment
+                                                    .layout
+                                                    .display_ranges(range)
+                                                    .into_iter()
+                                                    .map(move |shown| (shown, style))
+                                            })
+                                            .collect(),
+                                        None => highlights,
+                                    };
+                                    let highlights: Vec<_> = match composed_at {
+                                        Some((at, len)) => highlights
+                                            .into_iter()
+                                            .map(|(range, style)| {
+                                                let start = range.start
+                                                    + if range.start >= at { len } else { 0 };
+                                                let end = range.end
+                                                    + if range.end > at { len } else { 0 };
+                                                (start..end, style)
+                                            })
+                                            .collect(),
+                                        None => highlights,
+                                    };
+
+                                    // Inline virtual text goes into the text shown, pushing
+                                    // the rest along; right-to-left text and text being
+                                    // composed go without
+                                    let hints = match (summary, &bidi_segment, composing) {
+                                        (None, None, None) => self.inline_hints(
+                                            row,
+                                            byte_range,
+                                            line_text.len(),
+                                            font_size_px,
+                                            text_system,
+                                        ),
+                                        _ => Vec::new(),
+                                    };
+                                    let highlights = insert_hints(
+                                        &mut display_text,
+                                        highlights,
+                                        &hints,
+                                        byte_range.start,
+                                    );
+
                                     let mut line_div = div()
                                         .relative()
                                         .flex()
                                         .items_center()
                                         .whitespace_nowrap()
-                                        .child(StyledText::new(SharedString::from(
-                                            display_text.clone(),
-                                        )));
+                                        .when(is_dimmed, |d| d.text_color(self.theme.text_muted))
+                                        .child(
+                                            StyledText::new(SharedString::from(
+                                                display_text.clone(),
+                                            ))
+                                            .with_highlights(highlights),
+                                        )
+                                        .children(end_of_line.into_iter().map(|(text, color)| {
+                                            div()
+                                                .ml(font_size_px)
+                                                .italic()
+                                                .text_color(color)
+                                                .child(text)
+                                        }));
+
+                                    // Guides go on a line's first segment only, as the
+                                    // rest start at the margin
+                                    let guide_width = space_width.filter(|_| {
+                                        byte_range.start == 0
+                                            && summary.is_none()
+                                            && bidi_segment.is_none()
+                                    });
+                                    if let Some(space) = guide_width {
+                                        for column in self.indent_guides.columns(row) {
+                                            let lit = active_guide.as_ref().is_some_and(|active| {
+                                                active.column == column
+                                                    && active.rows.contains(&row)
+                                            });
+                                            line_div = line_div.child(
+                                                div()
+                                                    .absolute()
+                                                    .left(space * column as f32)
+                                                    .top(px(0.0))
+                                                    .bottom(px(0.0))
+                                                    .w(px(1.0))
+                                                    .bg(if lit {
+                                                        self.theme.text_muted
+                                                    } else {
+                                                        self.theme.text_muted.opacity(0.35)
+                                                    }),
+                                            );
+                                        }
+                                    }
+
+                                    let icon = self
+                                        .engine
+                                        .gutter_icon(row)
+                                        .filter(|_| byte_range.start == 0 && summary.is_none());
+                                    if let Some((symbol, tone)) = icon {
+                                        line_div = line_div.child(
+                                            div()
+                                                .absolute()
+                                                .left(px(8.0) - text_margin)
+                                                .text_color(self.theme.tone_color(tone))
+                                                .child(symbol.to_string()),
+                                        );
+                                    }
+
+                                    let change = self
+                                        .git_gutter
+                                        .marker(row, self.buffer.line_count())
+                                        .filter(|kind| {
+                                            !self.focus_mode
+                                                && (byte_range.start == 0
+                                                    || *kind != ChangeKind::Removed)
+                                        });
+                                    if let Some(kind) = change {
+                                        line_div = line_div
+                                            .child(self.render_change_marker(kind, text_margin));
+                                    }
 
                                     if let Some((sel_start, sel_end)) = selection_range {
                                         if sel_start.row <= row && row <= sel_end.row {
@@ -719,21 +7968,43 @@ impl Render for TextEditor {
                                             let sel_start_in_seg = line_start_col.max(seg_start);
                                             let sel_end_in_seg = line_end_col.min(seg_end);
 
-                                            if sel_start_in_seg < sel_end_in_seg {
+                                            if let (true, Some(segment)) =
+                                                (sel_start_in_seg < sel_end_in_seg, &bidi_segment)
+                                            {
+                                                for (sel_x, sel_width) in
+                                                    segment.spans(sel_start_in_seg..sel_end_in_seg)
+                                                {
+                                                    line_div = line_div.child(
+                                                        div()
+                                                            .absolute()
+                                                            .left(sel_x)
+                                                            .top(px(0.0))
+                                                            .bottom(px(0.0))
+                                                            .w(sel_width)
+                                                            .bg(self.theme.selection),
+                                                    );
+                                                }
+                                            } else if sel_start_in_seg < sel_end_in_seg {
                                                 if let Some(shaped) = self.buffer.get_or_shape_line(
                                                     row,
                                                     font_size_px,
                                                     wrap_width,
-                                                    &text_system,
+                                                    text_system,
                                                 ) {
                                                     let seg_x_offset =
                                                         shaped.x_for_index(seg_start);
                                                     let sel_x = shaped
                                                         .x_for_index(sel_start_in_seg)
-                                                        - seg_x_offset;
+                                                        - seg_x_offset
+                                                        + hint_shift(
+                                                            &hints,
+                                                            sel_start_in_seg,
+                                                            true,
+                                                        );
                                                     let sel_end_x = shaped
                                                         .x_for_index(sel_end_in_seg)
-                                                        - seg_x_offset;
+                                                        - seg_x_offset
+                                                        + hint_shift(&hints, sel_end_in_seg, false);
                                                     let sel_width = sel_end_x - sel_x;
 
                                                     line_div = line_div.child(
@@ -750,36 +8021,397 @@ impl Render for TextEditor {
                                         }
                                     }
 
-                                    if is_cursor_on_this_segment {
+                                    // The other cursors, and what they select
+                                    let (extra_selected, extra_cursors) = multi_cursor::on_line(
+                                        self.engine.extra_carets(),
+                                        row,
+                                        line_text.len(),
+                                    );
+                                    let extra_selected: Vec<_> = extra_selected
+                                        .into_iter()
+                                        .map(|range| {
+                                            range.start.max(byte_range.start)
+                                                ..range.end.min(byte_range.end)
+                                        })
+                                        .filter(|range| range.start < range.end)
+                                        .collect();
+                                    let extra_cursors: Vec<_> = extra_cursors
+                                        .into_iter()
+                                        .filter(|column| {
+                                            cursor_visible
+                                                && (byte_range.start..=byte_range.end)
+                                                    .contains(column)
+                                        })
+                                        .collect();
+                                    if !extra_selected.is_empty() || !extra_cursors.is_empty() {
+                                        let mut spans = Vec::new();
+                                        let mut carets = Vec::new();
+                                        match &bidi_segment {
+                                            Some(segment) => {
+                                                for range in extra_selected {
+                                                    spans.extend(segment.spans(range));
+                                                }
+                                                carets.extend(
+                                                    extra_cursors
+                                                        .into_iter()
+                                                        .map(|column| segment.x(column)),
+                                                );
+                                            }
+                                            None => {
+                                                if let Some(shaped) = self.buffer.get_or_shape_line(
+                                                    row,
+                                                    font_size_px,
+                                                    wrap_width,
+                                                    text_system,
+                                                ) {
+                                                    let offset =
+                                                        shaped.x_for_index(byte_range.start);
+                                                    let x = |column: usize, leading: bool| {
+                                                        shaped.x_for_index(column) - offset
+                                                            + hint_shift(&hints, column, leading)
+                                                    };
+                                                    for range in extra_selected {
+                                                        let from = x(range.start, true);
+                                                        spans.push((
+                                                            from,
+                                                            x(range.end, false) - from,
+                                                        ));
+                                                    }
+                                                    carets.extend(
+                                                        extra_cursors
+                                                            .into_iter()
+                                                            .map(|column| x(column, true)),
+                                                    );
+                                                }
+                                            }
+                                        }
+                                        for (x, width) in spans {
+                                            line_div = line_div.child(
+                                                div()
+                                                    .absolute()
+                                                    .left(x)
+                                                    .top(px(0.0))
+                                                    .bottom(px(0.0))
+                                                    .w(width)
+                                                    .bg(self.theme.selection),
+                                            );
+                                        }
+                                        for x in carets {
+                                            line_div = line_div
+                                                .child(self.render_cursor(x, font_size_px * 0.6));
+                                        }
+                                    }
+
+                                    let underlines: Vec<_> = self
+                                        .engine
+                                        .diagnostics()
+                                        .iter()
+                                        .filter_map(|d| {
+                                            let (start, end) =
+                                                d.columns_on_row(row, line_text.len())?;
+                                            let start = start.max(byte_range.start);
+                                            let end = end.min(byte_range.end);
+                                            (start <= end).then_some((start, end, d.severity))
+                                        })
+                                        .collect();
+                                    if let (false, Some(segment)) =
+                                        (underlines.is_empty(), &bidi_segment)
+                                    {
+                                        for (start, end, severity) in underlines {
+                                            for (x, width) in segment.spans(start..end) {
+                                                line_div = line_div.child(
+                                                    div()
+                                                        .absolute()
+                                                        .left(x)
+                                                        .bottom(px(2.0))
+                                                        .w(width.max(px(6.0)))
+                                                        .h(px(2.0))
+                                                        .bg(self.theme.severity_color(severity)),
+                                                );
+                                            }
+                                        }
+                                    } else if !underlines.is_empty() {
                                         if let Some(shaped) = self.buffer.get_or_shape_line(
                                             row,
                                             font_size_px,
                                             wrap_width,
-                                            &text_system,
+                                            text_system,
                                         ) {
                                             let seg_x_offset = shaped.x_for_index(byte_range.start);
-                                            let cursor_x = shaped.x_for_index(
-                                                cursor.column.min(line_text.len()),
-                                            ) - seg_x_offset;
+                                            for (start, end, severity) in underlines {
+                                                let x = shaped.x_for_index(start) - seg_x_offset
+                                                    + hint_shift(&hints, start, true);
+                                                let width = (shaped.x_for_index(end)
+                                                    - seg_x_offset
+                                                    + hint_shift(&hints, end, false)
+                                                    - x)
+                                                    .max(px(6.0));
+                                                line_div = line_div.child(
+                                                    div()
+                                                        .absolute()
+                                                        .left(x)
+                                                        .bottom(px(2.0))
+                                                        .w(width)
+                                                        .h(px(2.0))
+                                                        .bg(self.theme.severity_color(severity)),
+                                                );
+                                            }
+                                        }
+                                    }
 
+                                    // The link Cmd/Ctrl+click would follow
+                                    let link = self.hovered_link.as_ref().and_then(|(r, range)| {
+                                        let start = range.start.max(byte_range.start);
+                                        let end = range.end.min(byte_range.end);
+                                        (*r == row && start < end).then_some(start..end)
+                                    });
+                                    if let (Some(link), Some(shaped)) = (
+                                        link,
+                                        self.buffer.get_or_shape_line(
+                                            row,
+                                            font_size_px,
+                                            wrap_width,
+                                            text_system,
+                                        ),
+                                    ) {
+                                        let spans = match &bidi_segment {
+                                            Some(segment) => segment.spans(link),
+                                            None => {
+                                                let offset = shaped.x_for_index(byte_range.start);
+                                                let x = shaped.x_for_index(link.start) - offset
+                                                    + hint_shift(&hints, link.start, true);
+                                                let end = shaped.x_for_index(link.end) - offset
+                                                    + hint_shift(&hints, link.end, false);
+                                                vec![(x, end - x)]
+                                            }
+                                        };
+                                        for (x, width) in spans {
                                             line_div = line_div.child(
                                                 div()
                                                     .absolute()
-                                                    .left(cursor_x)
-                                                    .top(px(0.0))
-                                                    .bottom(px(0.0))
-                                                    .w(px(2.0))
-                                                    .bg(self.theme.cursor),
+                                                    .left(x)
+                                                    .bottom(px(1.0))
+                                                    .w(width)
+                                                    .h(px(1.0))
+                                                    .bg(self.theme.text),
                                             );
                                         }
                                     }
 
+                                    // Where the dragged selection would land
+                                    let drop = self.text_drag.filter(|drop| {
+                                        drop.row == row
+                                            && drop.column >= byte_range.start
+                                            && drop.column <= byte_range.end
+                                    });
+                                    if let (Some(drop), Some(shaped)) = (
+                                        drop,
+                                        self.buffer.get_or_shape_line(
+                                            row,
+                                            font_size_px,
+                                            wrap_width,
+                                            text_system,
+                                        ),
+                                    ) {
+                                        let column = drop.column.min(line_text.len());
+                                        let x = match &bidi_segment {
+                                            Some(segment) => segment.x(column),
+                                            None => {
+                                                shaped.x_for_index(column)
+                                                    - shaped.x_for_index(byte_range.start)
+                                                    + hint_shift(&hints, column, true)
+                                            }
+                                        };
+                                        line_div = line_div.child(
+                                            div()
+                                                .absolute()
+                                                .left(x)
+                                                .top(px(0.0))
+                                                .bottom(px(0.0))
+                                                .w(px(2.0))
+                                                .bg(self.theme.cursor.opacity(0.5)),
+                                        );
+                                    }
+
+                                    if is_cursor_on_this_segment {
+                                        if let Some(shaped) = self.buffer.get_or_shape_line(
+                                            row,
+                                            font_size_px,
+                                            wrap_width,
+                                            text_system,
+                                        ) {
+                                            let seg_x_offset = shaped.x_for_index(byte_range.start);
+                                            let cursor_column = cursor.column.min(line_text.len());
+                                            let next_char =
+                                                line_text[cursor_column..].chars().next();
+                                            let (cursor_x, char_width) = match &bidi_segment {
+                                                Some(segment) => (
+                                                    segment.x(cursor_column),
+                                                    next_char
+                                                        .and_then(|c| {
+                                                            segment
+                                                                .spans(
+                                                                    cursor_column
+                                                                        ..cursor_column
+                                                                            + c.len_utf8(),
+                                                                )
+                                                                .first()
+                                                                .map(|&(_, width)| width)
+                                                        })
+                                                        .unwrap_or(font_size_px * 0.6),
+                                                ),
+                                                None => {
+                                                    let cursor_x = shaped
+                                                        .x_for_index(cursor_column)
+                                                        - seg_x_offset
+                                                        + hint_shift(&hints, cursor_column, true);
+                                                    let char_width = match next_char {
+                                                        Some(c) => {
+                                                            shaped.x_for_index(
+                                                                cursor_column + c.len_utf8(),
+                                                            ) - seg_x_offset
+                                                                - cursor_x
+                                                        }
+                                                        None => font_size_px * 0.6,
+                                                    };
+                                                    (cursor_x, char_width)
+                                                }
+                                            };
+                                            let popup_x = self.engine.completion().map(|c| {
+                                                let column = c.start.column.max(byte_range.start);
+                                                match &bidi_segment {
+                                                    Some(segment) => segment.x(column),
+                                                    None => {
+                                                        shaped.x_for_index(column) - seg_x_offset
+                                                            + hint_shift(&hints, column, true)
+                                                    }
+                                                }
+                                            });
+
+                                            self.ime_cursor.set(Some(Bounds::new(
+                                                point(
+                                                    viewport.origin.x
+                                                        + text_margin
+                                                        + self.sidebar_width()
+                                                        + cursor_x,
+                                                    viewport.origin.y
+                                                        + px(self.padding_top()
+                                                            + visual_row as f32
+                                                                * self.line_height()
+                                                            - self.scroll_offset),
+                                                ),
+                                                size(char_width, px(self.line_height())),
+                                            )));
+
+                                            // The composition is underlined, with the
+                                            // cursor where the input method puts it
+                                            let mut caret_x = cursor_x;
+                                            if let Some(composition) = composing {
+                                                let shaped = shape_text(
+                                                    &composition.text,
+                                                    self.buffer.font(),
+                                                    font_size_px,
+                                                    text_system,
+                                                );
+                                                caret_x +=
+                                                    shaped.x_for_index(composition.selected.end);
+                                                line_div = line_div.child(
+                                                    div()
+                                                        .absolute()
+                                                        .left(cursor_x)
+                                                        .bottom(px(2.0))
+                                                        .w(shaped
+                                                            .x_for_index(composition.text.len()))
+                                                        .h(px(1.0))
+                                                        .bg(self.theme.text),
+                                                );
+                                            }
+
+                                            if let Some(trail) = self.render_cursor_smear(
+                                                row,
+                                                byte_range.start,
+                                                caret_x,
+                                                _window,
+                                            ) {
+                                                line_div = line_div.child(trail);
+                                            }
+                                            if cursor_visible {
+                                                line_div = line_div
+                                                    .child(self.render_cursor(caret_x, char_width));
+                                            }
+
+                                            if let (Some(completion), Some(popup_x)) =
+                                                (self.engine.completion(), popup_x)
+                                            {
+                                                line_div = line_div.child(self.render_completion(
+                                                    completion,
+                                                    popup_x,
+                                                    px(self.line_height()),
+                                                ));
+                                            } else if let Some(hover) = self.engine.hover() {
+                                                line_div = line_div.child(self.render_hover(
+                                                    hover,
+                                                    cursor_x,
+                                                    px(self.line_height()),
+                                                ));
+                                            }
+                                        }
+                                    }
+
                                     container = container.child(line_div);
+                                    visual_row += 1;
                                 }
                             }
                         }
                         container
                     }),
             )
+            // Typing reaches the editor through the platform's input method
+            .child({
+                let editor = _cx.entity();
+                let focus_handle = self.focus_handle.clone();
+                let laid_out = self.bounds.clone();
+                let embedded = self.embedded;
+                canvas(
+                    |_, _, _| {},
+                    move |bounds, _, window, cx| {
+                        // Draw again where the app moved or resized the editor
+                        if laid_out.replace(Some(bounds)) != Some(bounds) && embedded {
+                            let id = editor.entity_id();
+                            window.on_next_frame(move |_, cx| cx.notify(id));
+                        }
+                        window.handle_input(
+                            &focus_handle,
+                            ElementInputHandler::new(bounds, editor),
+                            cx,
+                        );
+                    },
+                )
+                .absolute()
+                .size_full()
+            })
+            .children(minimap)
+            .children(file_tree)
+            .children(outline)
+            .children(breadcrumbs)
+            .children(status_bar)
+            .children(diagnostics_panel)
+            .children(terminal_panel)
+            .children(search_panel)
+            .children(quick_switch)
+            .children(history_browser)
+            .children(backlinks)
+            .children(bookmarks)
+            .children(tags)
+            .children(todos)
+            .children(errors)
+            .children(symbol_palette)
+            .children(note_search)
+            .children(template_picker)
+            .children(char_picker)
+            .children(diff_view)
+            .children(passphrase_prompt)
+            .children(command_line)
+            .children(context_menu)
     }
 }
@@ -0,0 +1,48 @@
+//! Zlyph's editor as a GPUI view
+//!
+//! [`TextEditor`] is the whole zlyph window when built with
+//! [`TextEditor::new`], and a text input or editor for other GPUI apps when
+//! built with [`TextEditor::embedded`]:
+//!
+//! ```no_run
+//! use gpui::{App, AppContext};
+//! use zlyph_gpui::{actions, EditorEvent, TextEditor};
+//!
+//! fn build(cx: &mut App) {
+//!     cx.bind_keys(actions::editing_bindings());
+//!     let editor = cx.new(|cx| {
+//!         let mut editor = TextEditor::embedded(cx);
+//!         editor.set_single_line(true, cx);
+//!         editor.set_placeholder("Search", cx);
+//!         editor
+//!     });
+//!     cx.subscribe(&editor, |editor, event, cx| {
+//!         if let EditorEvent::Changed = event {
+//!             println!("{}", editor.read(cx).text());
+//!         }
+//!     })
+//!     .detach();
+//! }
+//! ```
+//!
+//! # Features
+//!
+//! Panels that need more than a window are behind cargo features, all off
+//! by default; each turns on the zlyph-core feature of the same name:
+//!
+//! - `terminal`: the terminal panel along the bottom
+//! - `live-sync`: merging edits with other instances open on the same file
+//! - `keychain`: keeping passphrases of encrypted files in the OS keychain
+//! - `code-image`: exporting the buffer as a picture
+//! - `system-fonts`: embedding an installed font in exported PDFs
+//!
+//! Without them their actions only say they are unavailable.
+
+pub mod actions;
+mod editor;
+pub mod menus;
+mod text_buffer;
+mod theme;
+pub mod window_state;
+
+pub use editor::{EditorEvent, TextEditor};
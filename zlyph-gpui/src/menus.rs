@@ -0,0 +1,169 @@
+//! The application menu bar and the dock menu
+
+use crate::actions::*;
+use gpui::{Menu, MenuItem, OsAction, SystemMenuType};
+use std::path::PathBuf;
+use zlyph_core::transform::Transform;
+
+/// Recent files listed in the dock menu
+const DOCK_RECENT_FILES: usize = 10;
+
+pub fn app_menus() -> Vec<Menu> {
+    vec![
+        Menu {
+            name: "Zlyph".into(),
+            items: vec![
+                MenuItem::os_submenu("Services", SystemMenuType::Services),
+                MenuItem::separator(),
+                MenuItem::action("Hide Zlyph", Hide),
+                MenuItem::action("Hide Others", HideOthers),
+                MenuItem::action("Show All", ShowAll),
+                MenuItem::separator(),
+                MenuItem::action("Quit Zlyph", Quit),
+            ],
+        },
+        Menu {
+            name: "File".into(),
+            items: vec![
+                MenuItem::action("New", NewFile),
+                MenuItem::action("New from Template…", NewFromTemplate),
+                MenuItem::action("Open…", OpenFile),
+                MenuItem::action("Open Recent…", OpenRecent),
+                MenuItem::action("Switch Project…", SwitchProject),
+                MenuItem::action("Open Daily Note", OpenDailyNote),
+                MenuItem::separator(),
+                MenuItem::action("Save As…", SaveAs),
+                MenuItem::action("Export as HTML…", ExportHtml),
+                MenuItem::action("Export as PDF…", ExportPdf),
+                MenuItem::action("Export as Picture…", ExportImage),
+                MenuItem::action("Print…", Print),
+                MenuItem::action("Local History…", ShowLocalHistory),
+            ],
+        },
+        Menu {
+            name: "Edit".into(),
+            items: vec![
+                MenuItem::os_action("Undo", Undo, OsAction::Undo),
+                MenuItem::os_action("Redo", Redo, OsAction::Redo),
+                MenuItem::separator(),
+                MenuItem::os_action("Cut", Cut, OsAction::Cut),
+                MenuItem::os_action("Copy", Copy, OsAction::Copy),
+                MenuItem::os_action("Paste", Paste, OsAction::Paste),
+                MenuItem::action("Paste as Plain Text", PastePlain),
+                MenuItem::os_action("Select All", SelectAll, OsAction::SelectAll),
+                MenuItem::separator(),
+                MenuItem::action("Find in Files", FindInFiles),
+                MenuItem::action("Replace in Files", ReplaceInFiles),
+                MenuItem::action("Next Match", NextMatch),
+                MenuItem::action("Previous Match", PrevMatch),
+                MenuItem::action("Select All Matches", SelectAllMatches),
+                MenuItem::action("Command Line…", ShowCommandLine),
+                MenuItem::separator(),
+                MenuItem::action("Insert Date", InsertDate),
+                MenuItem::action("Characters and Emoji…", ShowCharacterPicker),
+                MenuItem::action("Start or Stop Dictation", ToggleDictation),
+                MenuItem::action("Strip Invisible Characters", StripInvisibles),
+                MenuItem::action("Evaluate Expression", EvaluateSelection),
+                MenuItem::action("Increment Number", IncrementNumber),
+                MenuItem::action("Decrement Number", DecrementNumber),
+                MenuItem::action("Number Sequence", NumberSequence),
+                MenuItem::action("Align On…", AlignOn),
+                MenuItem::action("Insert Command Output…", RunShellCommand),
+                MenuItem::action("Filter Through Command…", FilterThroughCommand),
+                MenuItem::separator(),
+                MenuItem::action("Format JSON", FormatJson),
+                MenuItem::action("Format TOML", FormatToml),
+                MenuItem::action("Format YAML", FormatYaml),
+                MenuItem::action("Minify JSON", MinifyJson),
+                MenuItem::action("Sort Keys", SortKeys),
+                MenuItem::submenu(Menu {
+                    name: "Transform".into(),
+                    items: Transform::ALL
+                        .into_iter()
+                        .map(|transform| {
+                            MenuItem::action(transform.label(), TransformSelection { transform })
+                        })
+                        .collect(),
+                }),
+                MenuItem::submenu(Menu {
+                    name: "Section".into(),
+                    items: vec![
+                        MenuItem::action("Move Up", MoveSectionUp),
+                        MenuItem::action("Move Down", MoveSectionDown),
+                        MenuItem::action("Promote", PromoteSection),
+                        MenuItem::action("Demote", DemoteSection),
+                        MenuItem::action("Select", SelectSection),
+                    ],
+                }),
+                MenuItem::submenu(Menu {
+                    name: "Footnotes".into(),
+                    items: vec![
+                        MenuItem::action("Insert Footnote", InsertFootnote),
+                        MenuItem::action("Go to Reference or Definition", GoToFootnote),
+                        MenuItem::action("Renumber", RenumberFootnotes),
+                    ],
+                }),
+                MenuItem::action("Insert Bibliography", InsertBibliography),
+                MenuItem::separator(),
+                MenuItem::action("Format Table", FormatTable),
+                MenuItem::action("Insert Table Row", InsertTableRow),
+                MenuItem::action("Delete Table Row", DeleteTableRow),
+                MenuItem::action("Insert Table Column", InsertTableColumn),
+                MenuItem::action("Delete Table Column", DeleteTableColumn),
+                MenuItem::separator(),
+                MenuItem::action("Toggle Bookmark", ToggleBookmark),
+            ],
+        },
+        Menu {
+            name: "View".into(),
+            items: vec![
+                MenuItem::action("Zoom In", IncreaseFontSize),
+                MenuItem::action("Zoom Out", DecreaseFontSize),
+                MenuItem::action("Actual Size", ResetFontSize),
+                MenuItem::separator(),
+                MenuItem::action("File Tree", ToggleFileTree),
+                MenuItem::action("Minimap", ToggleMinimap),
+                MenuItem::action("Breadcrumbs", ToggleBreadcrumbs),
+                MenuItem::action("Elastic Tabstops", ToggleElasticTabstops),
+                MenuItem::action("Rainbow Brackets", ToggleRainbowBrackets),
+                MenuItem::action("Problems", ToggleDiagnosticsPanel),
+                MenuItem::action("Terminal", ToggleTerminal),
+                MenuItem::action("Run Selection in Terminal", SendToTerminal),
+                MenuItem::action("Insert Terminal Output", CaptureTerminal),
+                MenuItem::action("Run Task", RunTask),
+                MenuItem::action("Task Errors", ShowErrors),
+                MenuItem::action("Next Error", NextError),
+                MenuItem::action("Previous Error", PreviousError),
+                MenuItem::action("Backlinks", ToggleBacklinks),
+                MenuItem::action("Bookmarks", ShowBookmarks),
+                MenuItem::action("TODOs", ShowTodos),
+                MenuItem::action("Outline", ToggleOutline),
+                MenuItem::action("Go to Symbol", GoToSymbol),
+                MenuItem::action("Go to Definition", GoToDefinition),
+                MenuItem::action("Find References", FindReferences),
+                MenuItem::separator(),
+                MenuItem::action("Focus Mode", ToggleFocusMode),
+                MenuItem::action("Focus on Sentence", ToggleSentenceFocus),
+                MenuItem::action("Enter Full Screen", ToggleFullscreen),
+            ],
+        },
+        Menu {
+            name: "Help".into(),
+            items: vec![MenuItem::action("Zlyph Help", OpenHelp)],
+        },
+    ]
+}
+
+/// The dock icon's menu: the most recent files, newest first
+pub fn dock_menu(recent_files: &[PathBuf]) -> Vec<MenuItem> {
+    recent_files
+        .iter()
+        .take(DOCK_RECENT_FILES)
+        .map(|path| {
+            let name = path
+                .file_name()
+                .map_or_else(|| path.to_string_lossy(), |name| name.to_string_lossy());
+            MenuItem::action(name.into_owned(), OpenRecentFile { path: path.clone() })
+        })
+        .collect()
+}
@@ -1,5 +1,11 @@
+// Editing and visual-navigation helpers are kept for wrapped-line cursor movement,
+// which is not wired up to the engine yet.
+#![allow(dead_code)]
+
 use gpui::*;
+use std::fmt;
 use std::ops::Range;
+use zlyph_core::{bidi, long_lines};
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct BufferPosition {
@@ -45,8 +51,15 @@ pub struct VisualLine {
 pub struct TextBuffer {
     lines: Vec<String>,
     line_layouts: Vec<Option<CachedLineLayout>>,
+    /// The font lines are shaped in
+    font: Font,
+    /// Lines taking up no rows, such as collapsed frontmatter
+    hidden: Range<usize>,
 }
 
+/// The visual lines of a hidden line
+static NO_VISUAL_LINES: Vec<VisualLine> = Vec::new();
+
 pub struct CachedLineLayout {
     pub shaped_line: ShapedLine,
     pub font_size: Pixels,
@@ -59,6 +72,8 @@ impl TextBuffer {
         Self {
             lines: vec![String::new()],
             line_layouts: vec![None],
+            font: font(DEFAULT_FONT_FAMILY),
+            hidden: 0..0,
         }
     }
 
@@ -72,11 +87,55 @@ impl TextBuffer {
         Self {
             lines,
             line_layouts: (0..line_count).map(|_| None).collect(),
+            font: font(DEFAULT_FONT_FAMILY),
+            hidden: 0..0,
         }
     }
 
-    pub fn to_string(&self) -> String {
-        self.lines.join("\n")
+    /// Replace the text with `lines`, keeping the layouts of the lines at
+    /// the start and end that didn't change, so an edit only reshapes the
+    /// lines it touched
+    pub fn set_lines(&mut self, lines: &[String]) {
+        if lines.is_empty() {
+            return self.set_lines(&[String::new()]);
+        }
+        let prefix = self
+            .lines
+            .iter()
+            .zip(lines)
+            .take_while(|(old, new)| old == new)
+            .count();
+        let suffix = self
+            .lines
+            .iter()
+            .rev()
+            .zip(lines.iter().rev())
+            .take(self.lines.len().min(lines.len()) - prefix)
+            .take_while(|(old, new)| old == new)
+            .count();
+        let changed = prefix..self.lines.len() - suffix;
+        let replacement = &lines[prefix..lines.len() - suffix];
+        self.lines
+            .splice(changed.clone(), replacement.iter().cloned());
+        self.line_layouts
+            .splice(changed, replacement.iter().map(|_| None));
+    }
+
+    /// The buffer in `font` rather than the default
+    pub fn with_font(mut self, font: Font) -> Self {
+        self.font = font;
+        self
+    }
+
+    pub fn font(&self) -> &Font {
+        &self.font
+    }
+
+    pub fn set_font(&mut self, font: Font) {
+        if font != self.font {
+            self.font = font;
+            self.invalidate_all_layouts();
+        }
     }
 
     pub fn line_count(&self) -> usize {
@@ -255,30 +314,13 @@ impl TextBuffer {
             return None;
         }
 
-        let needs_reshaping = self.line_layouts[row].as_ref().map_or(true, |cached| {
-            cached.font_size != font_size || cached.wrap_width != wrap_width
-        });
+        let needs_reshaping = self.line_layouts[row]
+            .as_ref()
+            .is_none_or(|cached| cached.font_size != font_size || cached.wrap_width != wrap_width);
 
         if needs_reshaping {
             let line = &self.lines[row];
-            let text = SharedString::from(line.clone());
-
-            let run = TextRun {
-                len: line.len(),
-                font: Font {
-                    family: "Monaco".into(),
-                    features: Default::default(),
-                    weight: FontWeight::NORMAL,
-                    style: FontStyle::Normal,
-                    fallbacks: None,
-                },
-                color: Hsla::default(),
-                background_color: None,
-                underline: None,
-                strikethrough: None,
-            };
-
-            let shaped = text_system.shape_line(text, font_size, &[run], None);
+            let shaped = shape_text(line, &self.font, font_size, text_system);
             let visual_lines = self.compute_visual_lines(line, &shaped, wrap_width);
 
             self.line_layouts[row] = Some(CachedLineLayout {
@@ -306,6 +348,21 @@ impl TextBuffer {
                 wrap_type: WrapType::SoftWrap,
             }];
         }
+        if long_lines::is_long(line) {
+            // Pieces as many characters wide as the start of the line
+            // averages, rather than measuring every character
+            let sample = line.char_indices().nth(64).map_or(line.len(), |(at, _)| at);
+            let average =
+                f32::from(shaped.x_for_index(sample)) / line[..sample].chars().count() as f32;
+            let chars = (f32::from(wrap_width) / average.max(1.0)) as usize;
+            return long_lines::pieces(line, chars)
+                .into_iter()
+                .map(|byte_range| VisualLine {
+                    byte_range,
+                    wrap_type: WrapType::HardWrap,
+                })
+                .collect();
+        }
 
         let mut visual_lines = Vec::new();
         let mut current_start = 0;
@@ -395,18 +452,54 @@ impl TextBuffer {
         None
     }
 
+    /// Give the lines in `rows` no visual rows, or show them all again
+    /// for an empty range
+    pub fn set_hidden(&mut self, rows: Range<usize>) {
+        self.hidden = rows;
+    }
+
     pub fn get_visual_lines(&self, row: usize) -> Option<&Vec<VisualLine>> {
+        if self.hidden.contains(&row) {
+            return Some(&NO_VISUAL_LINES);
+        }
         self.line_layouts
             .get(row)?
             .as_ref()
             .map(|layout| &layout.visual_lines)
     }
 
+    /// Visual rows line `row` takes up, counting one for a line not shaped
+    /// yet, such as one that hasn't been scrolled into view
+    pub fn visual_row_count(&self, row: usize) -> usize {
+        self.get_visual_lines(row).map_or(1, Vec::len)
+    }
+
+    /// The visual row line `row` starts on, or the total for the row just
+    /// past the last line
+    pub fn first_visual_row(&self, row: usize) -> usize {
+        (0..row.min(self.lines.len()))
+            .map(|row| self.visual_row_count(row))
+            .sum()
+    }
+
+    /// The line shown on `visual_row` and the visual row it starts on; the
+    /// last line for rows past the end
+    pub fn row_at_visual(&self, visual_row: usize) -> (usize, usize) {
+        let mut first = 0;
+        for row in 0..self.lines.len() {
+            let count = self.visual_row_count(row);
+            if visual_row < first + count || row + 1 == self.lines.len() {
+                return (row, first);
+            }
+            first += count;
+        }
+        (0, 0)
+    }
+
     pub fn visual_line_count(&self) -> usize {
-        self.line_layouts
-            .iter()
-            .filter_map(|layout| layout.as_ref())
-            .map(|layout| layout.visual_lines.len())
+        (0..self.lines.len())
+            .filter_map(|row| self.get_visual_lines(row))
+            .map(Vec::len)
             .sum()
     }
 
@@ -450,7 +543,7 @@ impl TextBuffer {
 
         for (buffer_row, _line) in self.lines.iter().enumerate() {
             if let Some(visual_lines) = self.get_visual_lines(buffer_row) {
-                for (_visual_line_idx, visual_line) in visual_lines.iter().enumerate() {
+                for visual_line in visual_lines.iter() {
                     if visual_row_counter == visual_pos.visual_row {
                         let buffer_column = visual_line.byte_range.start
                             + visual_pos.column.min(visual_line.byte_range.len());
@@ -528,6 +621,94 @@ impl TextBuffer {
     }
 }
 
+/// Used until the configured font is applied
+pub const DEFAULT_FONT_FAMILY: &str = "Monaco";
+
+/// `text` laid out in `font`, in the order given
+pub fn shape_text(
+    text: &str,
+    font: &Font,
+    font_size: Pixels,
+    text_system: &WindowTextSystem,
+) -> ShapedLine {
+    let run = TextRun {
+        len: text.len(),
+        font: font.clone(),
+        color: Hsla::default(),
+        background_color: None,
+        underline: None,
+        strikethrough: None,
+    };
+    text_system.shape_line(
+        SharedString::from(text.to_string()),
+        font_size,
+        &[run],
+        None,
+    )
+}
+
+/// A wrapped segment of a line with right-to-left text, shaped in the order
+/// it is shown so its columns can be found on screen
+pub struct BidiSegment {
+    /// Column of the line the segment starts at
+    start: usize,
+    pub layout: bidi::Layout,
+    shaped: ShapedLine,
+}
+
+impl BidiSegment {
+    /// The segment of `line` at `range`, if it has right-to-left text
+    pub fn new(
+        line: &str,
+        range: Range<usize>,
+        font: &Font,
+        font_size: Pixels,
+        text_system: &WindowTextSystem,
+    ) -> Option<Self> {
+        let text = &line[range.clone()];
+        if !bidi::has_rtl(text) {
+            return None;
+        }
+        let layout = bidi::Layout::new(text);
+        let shaped = shape_text(&layout.text, font, font_size, text_system);
+        Some(Self {
+            start: range.start,
+            layout,
+            shaped,
+        })
+    }
+
+    /// Where the caret before `column` is drawn, from the segment's left edge
+    pub fn x(&self, column: usize) -> Pixels {
+        let column = column.saturating_sub(self.start);
+        self.shaped.x_for_index(self.layout.display_index(column))
+    }
+
+    /// Where the columns in `range` are shown, as left edges and widths
+    pub fn spans(&self, range: Range<usize>) -> Vec<(Pixels, Pixels)> {
+        let range = range.start.saturating_sub(self.start)..range.end.saturating_sub(self.start);
+        self.layout
+            .display_ranges(range)
+            .into_iter()
+            .map(|shown| {
+                let x = self.shaped.x_for_index(shown.start);
+                (x, self.shaped.x_for_index(shown.end) - x)
+            })
+            .collect()
+    }
+
+    /// The column of the caret nearest to `x`
+    pub fn column(&self, x: Pixels) -> usize {
+        self.start + self.layout.column(self.shaped.closest_index_for_x(x))
+    }
+}
+
+impl fmt::Display for TextBuffer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.lines.join("\n"))
+    }
+}
+
 impl Default for TextBuffer {
     fn default() -> Self {
         Self::new()
@@ -1,4 +1,8 @@
-use gpui::{hsla, rgb, Hsla};
+use gpui::{hsla, rgb, FontStyle, FontWeight, HighlightStyle, Hsla, Rgba, UnderlineStyle};
+use zlyph_core::decorations::Tone;
+use zlyph_core::diagnostics::Severity;
+use zlyph_core::export::Palette;
+use zlyph_core::highlight::Style;
 
 #[derive(Clone)]
 pub struct Theme {
@@ -7,6 +11,17 @@ pub struct Theme {
     pub text_muted: Hsla,
     pub selection: Hsla,
     pub cursor: Hsla,
+    pub popup_background: Hsla,
+    pub error: Hsla,
+    pub warning: Hsla,
+    pub info: Hsla,
+    pub hint: Hsla,
+    /// Inserted, changed and removed text in diffs
+    pub added: Hsla,
+    pub modified: Hsla,
+    pub removed: Hsla,
+    /// Bracket pairs by nesting depth, repeating
+    pub brackets: [Hsla; 4],
 }
 
 impl Default for Theme {
@@ -17,6 +32,129 @@ impl Default for Theme {
             text_muted: hsla(0.61, 0.11, 0.44, 0.6),
             selection: hsla(0.61, 0.13, 0.28, 0.7),
             cursor: rgb(0x528bff).into(),
+            popup_background: rgb(0x21252b).into(),
+            error: rgb(0xe06c75).into(),
+            warning: rgb(0xe5c07b).into(),
+            info: rgb(0x61afef).into(),
+            hint: hsla(0.61, 0.11, 0.44, 0.8),
+            added: rgb(0x98c379).into(),
+            modified: rgb(0x61afef).into(),
+            removed: rgb(0xe06c75).into(),
+            brackets: [
+                rgb(0xe5c07b).into(),
+                rgb(0xc678dd).into(),
+                rgb(0x56b6c2).into(),
+                rgb(0x98c379).into(),
+            ],
+        }
+    }
+}
+
+impl Theme {
+    /// Theme whose background lets the window's backdrop show through by `opacity`
+    pub fn with_background_opacity(opacity: f32) -> Self {
+        let mut theme = Self::default();
+        theme.background.a = opacity.clamp(0.0, 1.0);
+        theme
+    }
+
+    pub fn severity_color(&self, severity: Severity) -> Hsla {
+        match severity {
+            Severity::Error => self.error,
+            Severity::Warning => self.warning,
+            Severity::Information => self.info,
+            Severity::Hint => self.hint,
+        }
+    }
+
+    pub fn tone_color(&self, tone: Tone) -> Hsla {
+        match tone {
+            Tone::Muted => self.text_muted,
+            Tone::Accent => self.cursor,
+            Tone::Info => self.info,
+            Tone::Warning => self.warning,
+            Tone::Error => self.error,
+        }
+    }
+
+    /// How text the highlighter marked is drawn. Headings keep the line
+    /// height of the rest of the text and stand out by weight and color.
+    pub fn highlight_style(&self, style: Style) -> HighlightStyle {
+        let mut result = HighlightStyle::default();
+        if let Some(level) = style.heading {
+            result.color = Some(match level {
+                1 => self.error,
+                2 => self.warning,
+                3 => self.added,
+                _ => self.info,
+            });
+            result.font_weight = Some(FontWeight::BOLD);
+        }
+        if style.bold {
+            result.font_weight = Some(FontWeight::BOLD);
+        }
+        if style.italic || style.quote {
+            result.font_style = Some(FontStyle::Italic);
+        }
+        if style.quote {
+            result.color = Some(self.hint);
+        }
+        if style.strikethrough {
+            result.strikethrough = Some(gpui::StrikethroughStyle {
+                thickness: gpui::px(1.0),
+                color: None,
+            });
+        }
+        if style.scope > 0 {
+            // A faint wash of the text color, deeper per level up to four
+            let mut wash = self.text;
+            wash.a = 0.04 * f32::from(style.scope.min(4));
+            result.background_color = Some(wash);
+        }
+        if let Some(depth) = style.bracket {
+            result.color = Some(self.brackets[usize::from(depth) % self.brackets.len()]);
+        }
+        if style.code {
+            result.color = Some(self.added);
+            result.background_color = Some(self.popup_background);
+        }
+        if style.link {
+            result.color = Some(self.info);
+            result.underline = Some(UnderlineStyle {
+                thickness: gpui::px(1.0),
+                color: None,
+                wavy: false,
+            });
+        }
+        if style.markup {
+            result.color = Some(self.text_muted);
+            result.underline = None;
+        }
+        result
+    }
+
+    /// The theme's colors for an exported page, opaque whatever the
+    /// window's opacity
+    pub fn export_palette(&self) -> Palette {
+        let css = |color: Hsla| {
+            let Rgba { r, g, b, .. } = color.into();
+            let byte = |channel: f32| (channel * 255.0).round() as u8;
+            format!("#{:02x}{:02x}{:02x}", byte(r), byte(g), byte(b))
+        };
+        Palette {
+            background: css(self.background),
+            text: css(self.text),
+            muted: css(self.text_muted),
+            headings: [
+                css(self.error),
+                css(self.warning),
+                css(self.added),
+                css(self.info),
+            ],
+            code: css(self.added),
+            code_background: css(self.popup_background),
+            link: css(self.info),
+            quote: css(self.hint),
         }
     }
 }
@@ -0,0 +1,88 @@
+//! Window appearance and geometry carried across launches
+
+use gpui::{point, px, size, Bounds, WindowBackgroundAppearance, WindowBounds};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+use zlyph_core::config::{self, WindowConfig};
+
+/// How the window appears behind the (possibly translucent) editor background
+pub fn background_appearance(config: &WindowConfig) -> WindowBackgroundAppearance {
+    if config.opacity >= 1.0 {
+        WindowBackgroundAppearance::Opaque
+    } else if config.blur {
+        WindowBackgroundAppearance::Blurred
+    } else {
+        WindowBackgroundAppearance::Transparent
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum WindowMode {
+    #[default]
+    Windowed,
+    Maximized,
+    Fullscreen,
+}
+
+/// Last window position and size; for maximized and fullscreen windows the
+/// bounds are the ones to restore to
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct WindowGeometry {
+    pub x: f32,
+    pub y: f32,
+    pub width: f32,
+    pub height: f32,
+    #[serde(default)]
+    pub mode: WindowMode,
+}
+
+impl WindowGeometry {
+    /// Kept apart from config.toml since it changes every time the window moves
+    fn path() -> PathBuf {
+        config::config_dir().join("window.toml")
+    }
+
+    pub fn load() -> Option<Self> {
+        let content = fs::read_to_string(Self::path()).ok()?;
+        toml::from_str(&content).ok()
+    }
+
+    pub fn save(&self) -> io::Result<()> {
+        let content = toml::to_string(self).map_err(io::Error::other)?;
+        let path = Self::path();
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(path, content)
+    }
+
+    pub fn from_window_bounds(window_bounds: WindowBounds) -> Self {
+        let (bounds, mode) = match window_bounds {
+            WindowBounds::Windowed(bounds) => (bounds, WindowMode::Windowed),
+            WindowBounds::Maximized(bounds) => (bounds, WindowMode::Maximized),
+            WindowBounds::Fullscreen(bounds) => (bounds, WindowMode::Fullscreen),
+        };
+        Self {
+            x: f32::from(bounds.origin.x),
+            y: f32::from(bounds.origin.y),
+            width: f32::from(bounds.size.width),
+            height: f32::from(bounds.size.height),
+            mode,
+        }
+    }
+
+    pub fn to_window_bounds(self) -> WindowBounds {
+        let bounds = Bounds {
+            origin: point(px(self.x), px(self.y)),
+            size: size(px(self.width.max(200.0)), px(self.height.max(150.0))),
+        };
+        match self.mode {
+            WindowMode::Windowed => WindowBounds::Windowed(bounds),
+            WindowMode::Maximized => WindowBounds::Maximized(bounds),
+            WindowMode::Fullscreen => WindowBounds::Fullscreen(bounds),
+        }
+    }
+}
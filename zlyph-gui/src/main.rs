@@ -0,0 +1,206 @@
+use gpui::*;
+use std::path::PathBuf;
+use std::sync::mpsc;
+use zlyph_core::instance::{self, InstanceListener};
+use zlyph_core::vfs;
+use zlyph_core::{Config, EditorEngine};
+use zlyph_gpui::actions::*;
+use zlyph_gpui::window_state::{self, WindowGeometry};
+use zlyph_gpui::{menus, TextEditor};
+
+/// Opens a separate window instead of handing the file to the running one
+const NEW_WINDOW_FLAG: &str = "--new-window";
+
+fn resolve_file_path() -> PathBuf {
+    let args: Vec<String> = std::env::args()
+        .filter(|arg| arg != NEW_WINDOW_FLAG)
+        .collect();
+
+    if args.len() > 1 {
+        // User provided a file path
+        let path = PathBuf::from(&args[1]);
+        if path.is_absolute() || vfs::is_remote(&path) {
+            path
+        } else {
+            std::env::current_dir().unwrap_or_default().join(path)
+        }
+    } else {
+        // Use default global file
+        EditorEngine::default_file_path()
+    }
+}
+
+fn main() {
+    let file_path = resolve_file_path();
+
+    let socket = instance::default_socket_path();
+    let new_window = std::env::args().any(|arg| arg == NEW_WINDOW_FLAG);
+    if !new_window && instance::send_to_existing(&socket, &file_path).unwrap_or(false) {
+        return;
+    }
+    // Fails while another instance is listening, e.g. with --new-window
+    let instance = InstanceListener::bind(&socket).ok();
+
+    // Files opened with the app from the Finder, or dropped on its dock icon
+    let (open_sender, system_opens) = mpsc::channel();
+    let application = Application::new();
+    application.on_open_urls(move |urls| {
+        for path in urls.iter().filter_map(|url| instance::path_from_url(url)) {
+            let _ = open_sender.send(path);
+        }
+    });
+
+    application.run(move |app| {
+        app.bind_keys([
+            KeyBinding::new("cmd-q", Quit, None),
+            KeyBinding::new("cmd-h", Hide, None),
+            KeyBinding::new("alt-cmd-h", HideOthers, None),
+            KeyBinding::new("cmd-o", OpenFile, None),
+            KeyBinding::new("cmd-shift-s", SaveAs, None),
+            KeyBinding::new("alt-cmd-e", ExportHtml, None),
+            KeyBinding::new("alt-cmd-p", ExportPdf, None),
+            KeyBinding::new("alt-cmd-c", ExportImage, None),
+            KeyBinding::new("cmd-p", Print, None),
+            KeyBinding::new("ctrl-`", ToggleTerminal, None),
+            KeyBinding::new("alt-cmd-enter", SendToTerminal, None),
+            KeyBinding::new("alt-cmd-y", CaptureTerminal, None),
+            KeyBinding::new("f9", RunTask, None),
+            KeyBinding::new("shift-f9", ShowErrors, None),
+            KeyBinding::new("f4", NextError, None),
+            KeyBinding::new("shift-f4", PreviousError, None),
+            KeyBinding::new("cmd-s", OverwriteRemote, None),
+            KeyBinding::new("cmd-n", NewFile, None),
+            KeyBinding::new("cmd-r", OpenRecent, None),
+            KeyBinding::new("cmd-shift-r", SwitchProject, None),
+            KeyBinding::new("cmd-;", ShowCommandLine, None),
+            KeyBinding::new("cmd-shift-f", FindInFiles, None),
+            KeyBinding::new("cmd-shift-h", ReplaceInFiles, None),
+            KeyBinding::new("f3", NextMatch, None),
+            KeyBinding::new("shift-f3", PrevMatch, None),
+            KeyBinding::new("ctrl-cmd-g", SelectAllMatches, None),
+            KeyBinding::new("alt-x", ToggleReplaceHunk, None),
+            KeyBinding::new("alt-enter", ApplyReplace, None),
+            KeyBinding::new("alt-u", UndoReplaceInFile, None),
+            KeyBinding::new("alt-w", ToggleWholeWord, None),
+            KeyBinding::new("alt-c", TogglePreserveCase, None),
+            KeyBinding::new("cmd-b", ToggleFileTree, None),
+            KeyBinding::new("cmd-shift-e", FocusFileTree, None),
+            KeyBinding::new("alt-cmd-n", TreeNewFile, None),
+            KeyBinding::new("alt-cmd-shift-n", TreeNewFolder, None),
+            KeyBinding::new("alt-cmd-r", TreeRename, None),
+            KeyBinding::new("alt-cmd-m", TreeMove, None),
+            KeyBinding::new("alt-cmd-backspace", TreeDelete, None),
+            KeyBinding::new("cmd-=", IncreaseFontSize, None),
+            KeyBinding::new("cmd--", DecreaseFontSize, None),
+            KeyBinding::new("cmd-0", ResetFontSize, None),
+            KeyBinding::new("ctrl-space", TriggerCompletion, None),
+            KeyBinding::new("cmd-i", Hover, None),
+            KeyBinding::new("f12", GoToDefinition, None),
+            KeyBinding::new("shift-f12", FindReferences, None),
+            KeyBinding::new("f8", NextDiagnostic, None),
+            KeyBinding::new("shift-f8", PrevDiagnostic, None),
+            KeyBinding::new("f7", NextChange, None),
+            KeyBinding::new("shift-f7", PrevChange, None),
+            KeyBinding::new("cmd-f2", ToggleBookmark, None),
+            KeyBinding::new("f2", NextBookmark, None),
+            KeyBinding::new("shift-f2", PrevBookmark, None),
+            KeyBinding::new("alt-f2", ShowBookmarks, None),
+            KeyBinding::new("alt-f7", PreviewHunk, None),
+            KeyBinding::new("alt-cmd-z", RevertHunk, None),
+            KeyBinding::new("alt-cmd-s", StageHunk, None),
+            KeyBinding::new("alt-cmd-d", DiffWithSaved, None),
+            KeyBinding::new("alt-cmd-g", DiffWithHead, None),
+            KeyBinding::new("alt-cmd-shift-d", DiffWithFile, None),
+            KeyBinding::new("alt-cmd-l", ShowLocalHistory, None),
+            KeyBinding::new("cmd-enter", FollowLink, None),
+            KeyBinding::new("cmd-shift-b", ToggleBacklinks, None),
+            KeyBinding::new("cmd-j", OpenDailyNote, None),
+            KeyBinding::new("alt-cmd-[", PreviousDailyNote, None),
+            KeyBinding::new("alt-cmd-]", NextDailyNote, None),
+            KeyBinding::new("cmd-shift-n", NewFromTemplate, None),
+            KeyBinding::new("cmd-shift-t", ShowTags, None),
+            KeyBinding::new("cmd-shift-o", ShowTodos, None),
+            KeyBinding::new("alt-cmd-o", ToggleOutline, None),
+            KeyBinding::new("cmd-t", GoToSymbol, None),
+            KeyBinding::new("alt-cmd-f", SearchNotes, None),
+            KeyBinding::new("f5", InsertDate, None),
+            KeyBinding::new("shift-f5", InsertTime, None),
+            KeyBinding::new("cmd-f5", InsertTimestamp, None),
+            KeyBinding::new("ctrl-cmd-space", ShowCharacterPicker, None),
+            KeyBinding::new("ctrl-cmd-u", InsertUnicodeCodepoint, None),
+            KeyBinding::new("cmd-shift-m", ToggleDiagnosticsPanel, None),
+            KeyBinding::new("cmd-.", QuickFix, None),
+            KeyBinding::new("alt-cmd-i", StripInvisibles, None),
+            KeyBinding::new("alt-cmd-=", EvaluateSelection, None),
+            KeyBinding::new("alt-cmd-a", AlignOn, None),
+            KeyBinding::new("alt-cmd-j", FormatJson, None),
+            KeyBinding::new("alt-cmd-k", SortKeys, None),
+            KeyBinding::new("ctrl-a", IncrementNumber, None),
+            KeyBinding::new("ctrl-x", DecrementNumber, None),
+            KeyBinding::new("ctrl-shift-a", NumberSequence, None),
+            KeyBinding::new("alt-cmd-x", RunShellCommand, None),
+            KeyBinding::new("alt-cmd-shift-x", FilterThroughCommand, None),
+            KeyBinding::new("alt-cmd-t", FormatTable, None),
+            KeyBinding::new("ctrl-alt-enter", InsertTableRow, None),
+            KeyBinding::new("ctrl-alt-backspace", DeleteTableRow, None),
+            KeyBinding::new("ctrl-alt-shift-enter", InsertTableColumn, None),
+            KeyBinding::new("ctrl-alt-shift-backspace", DeleteTableColumn, None),
+            KeyBinding::new("cmd-shift-g", ToggleWritingSession, None),
+            KeyBinding::new("ctrl-alt-d", ToggleDictation, None),
+            KeyBinding::new("cmd-shift-enter", ToggleFocusMode, None),
+            KeyBinding::new("ctrl-cmd-m", ToggleMinimap, None),
+            KeyBinding::new("ctrl-cmd-f", ToggleFullscreen, None),
+            KeyBinding::new("ctrl-cmd-t", ToggleTitleBar, None),
+            KeyBinding::new("ctrl-cmd-b", ToggleBlur, None),
+            KeyBinding::new("ctrl-cmd-=", IncreaseOpacity, None),
+            KeyBinding::new("ctrl-cmd--", DecreaseOpacity, None),
+        ]);
+        app.bind_keys(editing_bindings());
+
+        // Quit is handled by the editor, which saves first, while it has a window
+        app.on_action(|_: &Quit, cx| cx.quit());
+        app.on_action(|_: &Hide, cx| cx.hide());
+        app.on_action(|_: &HideOthers, cx| cx.hide_other_apps());
+        app.on_action(|_: &ShowAll, cx| cx.unhide_other_apps());
+        app.on_action(|_: &OpenHelp, cx| {
+            cx.open_url(concat!(
+                env!("CARGO_PKG_REPOSITORY"),
+                "/blob/main/FEATURES.md"
+            ))
+        });
+        app.set_menus(menus::app_menus());
+
+        let window_config = Config::load().unwrap_or_default().window;
+        let window_bounds = WindowGeometry::load()
+            .map(WindowGeometry::to_window_bounds)
+            .unwrap_or(WindowBounds::Windowed(Bounds {
+                origin: Point {
+                    x: px(100.0),
+                    y: px(100.0),
+                },
+                size: Size {
+                    width: px(800.0),
+                    height: px(600.0),
+                },
+            }));
+
+        let window_options = WindowOptions {
+            window_bounds: Some(window_bounds),
+            titlebar: window_config.titlebar.then(|| TitlebarOptions {
+                title: Some("Dright Editor".into()),
+                appears_transparent: true,
+                traffic_light_position: Some(point(px(8.0), px(8.0))),
+            }),
+            window_background: window_state::background_appearance(&window_config),
+            ..Default::default()
+        };
+
+        app.open_window(window_options, |window, app| {
+            let path = file_path.clone();
+            app.new(|cx| TextEditor::new(path, instance, system_opens, window, cx))
+        })
+        .unwrap();
+
+        app.activate(true);
+    });
+}
@@ -0,0 +1,114 @@
+//! LanguageTool `/v2/check` responses and their mapping onto the buffer
+
+use serde_json::Value;
+use std::io;
+use zlyph_core::diagnostics::{Diagnostic, Severity};
+use zlyph_core::BufferPosition;
+
+/// A block of consecutive non-blank lines, checked as one unit
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Paragraph {
+    pub start_row: usize,
+    pub text: String,
+}
+
+pub fn paragraphs(lines: &[String]) -> Vec<Paragraph> {
+    let mut paragraphs = Vec::new();
+    let mut current: Option<Paragraph> = None;
+
+    for (row, line) in lines.iter().enumerate() {
+        if line.trim().is_empty() {
+            paragraphs.extend(current.take());
+            continue;
+        }
+        match current.as_mut() {
+            Some(paragraph) => {
+                paragraph.text.push('\n');
+                paragraph.text.push_str(line);
+            }
+            None => {
+                current = Some(Paragraph {
+                    start_row: row,
+                    text: line.clone(),
+                })
+            }
+        }
+    }
+    paragraphs.extend(current);
+    paragraphs
+}
+
+/// One match reported by LanguageTool; offsets count UTF-16 code units
+#[derive(Debug, Clone, PartialEq)]
+pub struct Issue {
+    pub offset: usize,
+    pub length: usize,
+    pub message: String,
+    pub replacements: Vec<String>,
+    pub severity: Severity,
+}
+
+/// Suggestions beyond this many are rarely useful in a popup
+const MAX_REPLACEMENTS: usize = 8;
+
+pub fn parse_response(body: &str) -> io::Result<Vec<Issue>> {
+    let response: Value =
+        serde_json::from_str(body).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+    let Some(matches) = response["matches"].as_array() else {
+        return Ok(Vec::new());
+    };
+
+    Ok(matches
+        .iter()
+        .map(|m| Issue {
+            offset: m["offset"].as_u64().unwrap_or(0) as usize,
+            length: m["length"].as_u64().unwrap_or(0) as usize,
+            message: m["message"].as_str().unwrap_or_default().to_string(),
+            replacements: m["replacements"]
+                .as_array()
+                .map(|items| {
+                    items
+                        .iter()
+                        .filter_map(|r| r["value"].as_str().map(String::from))
+                        .take(MAX_REPLACEMENTS)
+                        .collect()
+                })
+                .unwrap_or_default(),
+            severity: match m["rule"]["issueType"].as_str() {
+                Some("misspelling") => Severity::Error,
+                Some("grammar") => Severity::Warning,
+                _ => Severity::Information,
+            },
+        })
+        .collect())
+}
+
+/// Buffer position of a UTF-16 offset within a paragraph
+fn position_at(paragraph: &Paragraph, offset: usize) -> BufferPosition {
+    let mut units = 0;
+    let (mut row, mut column) = (paragraph.start_row, 0);
+    for c in paragraph.text.chars() {
+        if units >= offset {
+            break;
+        }
+        units += c.len_utf16();
+        if c == '\n' {
+            row += 1;
+            column = 0;
+        } else {
+            column += c.len_utf8();
+        }
+    }
+    BufferPosition::new(row, column)
+}
+
+pub fn to_diagnostic(paragraph: &Paragraph, issue: &Issue, source: &str) -> Diagnostic {
+    Diagnostic {
+        start: position_at(paragraph, issue.offset),
+        end: position_at(paragraph, issue.offset + issue.length),
+        severity: issue.severity,
+        message: issue.message.clone(),
+        source: source.to_string(),
+        replacements: issue.replacements.clone(),
+    }
+}
@@ -0,0 +1,85 @@
+//! Just enough HTTP to POST a form to a local LanguageTool server
+
+use std::io::{self, Read, Write};
+use std::net::TcpStream;
+use std::time::Duration;
+
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Percent-encode `pairs` as an `application/x-www-form-urlencoded` body
+pub fn form_encode(pairs: &[(&str, &str)]) -> String {
+    let encode = |value: &str| {
+        let mut out = String::new();
+        for byte in value.bytes() {
+            match byte {
+                b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => {
+                    out.push(byte as char)
+                }
+                b' ' => out.push('+'),
+                _ => out.push_str(&format!("%{:02X}", byte)),
+            }
+        }
+        out
+    };
+
+    pairs
+        .iter()
+        .map(|(key, value)| format!("{}={}", encode(key), encode(value)))
+        .collect::<Vec<_>>()
+        .join("&")
+}
+
+/// Split `http://host:port/base` into the socket address, Host header and path prefix
+fn parse_url(url: &str) -> io::Result<(String, String, String)> {
+    let rest = url.strip_prefix("http://").ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "only http:// URLs are supported",
+        )
+    })?;
+    let (authority, path) = match rest.find('/') {
+        Some(i) => (&rest[..i], rest[i..].trim_end_matches('/')),
+        None => (rest, ""),
+    };
+    let address = if authority.contains(':') {
+        authority.to_string()
+    } else {
+        format!("{}:80", authority)
+    };
+    Ok((address, authority.to_string(), path.to_string()))
+}
+
+/// POST a form and return the response body of a 2xx response
+pub fn post_form(base_url: &str, endpoint: &str, body: &str) -> io::Result<String> {
+    let (address, host, prefix) = parse_url(base_url)?;
+    let mut stream = TcpStream::connect(&address)?;
+    stream.set_read_timeout(Some(TIMEOUT))?;
+    stream.set_write_timeout(Some(TIMEOUT))?;
+
+    // HTTP/1.0 keeps the response unchunked and closes the connection when done
+    let request = format!(
+        "POST {}{} HTTP/1.0\r\nHost: {}\r\nContent-Type: application/x-www-form-urlencoded\r\nContent-Length: {}\r\nAccept: application/json\r\n\r\n{}",
+        prefix,
+        endpoint,
+        host,
+        body.len(),
+        body
+    );
+    stream.write_all(request.as_bytes())?;
+
+    let mut response = Vec::new();
+    stream.read_to_end(&mut response)?;
+    let response = String::from_utf8_lossy(&response);
+    let (head, body) = response
+        .split_once("\r\n\r\n")
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "malformed HTTP response"))?;
+
+    let status = head.split_whitespace().nth(1).unwrap_or_default();
+    if !status.starts_with('2') {
+        return Err(io::Error::other(format!(
+            "LanguageTool returned HTTP {}",
+            status
+        )));
+    }
+    Ok(body.to_string())
+}
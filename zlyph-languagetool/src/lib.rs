@@ -0,0 +1,139 @@
+//! Grammar and style checking through a LanguageTool server
+//!
+//! [`GrammarChecker`] sends each changed paragraph to the server once typing
+//! pauses and publishes the matches as diagnostics, with LanguageTool's
+//! suggestions available through the QuickFix action.
+
+pub mod api;
+pub mod http;
+
+use api::{Issue, Paragraph};
+use std::collections::{HashMap, HashSet};
+use std::io;
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::thread;
+use std::time::{Duration, Instant};
+use zlyph_core::config::LanguageToolConfig;
+use zlyph_core::EditorEngine;
+
+/// Source name used for diagnostics published by LanguageTool
+pub const DIAGNOSTIC_SOURCE: &str = "languagetool";
+
+/// How long to stop sending requests after the server could not be reached
+const ERROR_BACKOFF: Duration = Duration::from_secs(30);
+
+type CheckResult = (String, io::Result<Vec<Issue>>);
+
+pub struct GrammarChecker {
+    debounce: Duration,
+    requests: Sender<String>,
+    results: Receiver<CheckResult>,
+    /// Issues by paragraph text, so unchanged paragraphs are never re-sent
+    cache: HashMap<String, Vec<Issue>>,
+    pending: HashSet<String>,
+    last_text: String,
+    last_change: Instant,
+    last_error: Option<Instant>,
+}
+
+impl GrammarChecker {
+    /// Start the checker, or None when it is disabled in the config
+    pub fn new(config: &LanguageToolConfig) -> Option<Self> {
+        if !config.enabled {
+            return None;
+        }
+
+        let (requests, incoming) = mpsc::channel::<String>();
+        let (outgoing, results) = mpsc::channel();
+        let (url, language) = (config.url.clone(), config.language.clone());
+        thread::spawn(move || {
+            for text in incoming {
+                let body = http::form_encode(&[("language", &language), ("text", &text)]);
+                let result = http::post_form(&url, "/v2/check", &body)
+                    .and_then(|response| api::parse_response(&response));
+                if outgoing.send((text, result)).is_err() {
+                    break;
+                }
+            }
+        });
+
+        Some(Self {
+            debounce: Duration::from_millis(config.debounce_ms),
+            requests,
+            results,
+            cache: HashMap::new(),
+            pending: HashSet::new(),
+            last_text: String::new(),
+            last_change: Instant::now(),
+            last_error: None,
+        })
+    }
+
+    /// Call regularly: collects finished checks, sends paragraphs that changed
+    /// once typing has paused, and republishes diagnostics. Returns whether the
+    /// diagnostics changed.
+    pub fn poll(&mut self, engine: &mut EditorEngine) -> bool {
+        if engine.is_large_file() {
+            // Comparing and splitting the whole text on every call would
+            // stall the editor
+            let had_issues = !self.last_text.is_empty();
+            self.last_text.clear();
+            if had_issues {
+                engine.set_diagnostics(DIAGNOSTIC_SOURCE, Vec::new());
+            }
+            return had_issues;
+        }
+        let mut changed = false;
+
+        let text = engine.state().to_string();
+        if text != self.last_text {
+            self.last_text = text;
+            self.last_change = Instant::now();
+            changed = true;
+        }
+
+        while let Ok((paragraph, result)) = self.results.try_recv() {
+            self.pending.remove(&paragraph);
+            match result {
+                Ok(issues) => {
+                    self.cache.insert(paragraph, issues);
+                    changed = true;
+                }
+                Err(_) => self.last_error = Some(Instant::now()),
+            }
+        }
+
+        let paragraphs = api::paragraphs(&engine.state().lines);
+        let backing_off = self.last_error.is_some_and(|t| t.elapsed() < ERROR_BACKOFF);
+        if self.last_change.elapsed() >= self.debounce && !backing_off {
+            for paragraph in &paragraphs {
+                if !self.cache.contains_key(&paragraph.text)
+                    && self.pending.insert(paragraph.text.clone())
+                {
+                    let _ = self.requests.send(paragraph.text.clone());
+                }
+            }
+        }
+
+        if changed {
+            self.publish(engine, &paragraphs);
+        }
+        changed
+    }
+
+    fn publish(&mut self, engine: &mut EditorEngine, paragraphs: &[Paragraph]) {
+        let current: HashSet<&str> = paragraphs.iter().map(|p| p.text.as_str()).collect();
+        self.cache.retain(|text, _| current.contains(text.as_str()));
+
+        let diagnostics = paragraphs
+            .iter()
+            .filter_map(|p| self.cache.get(&p.text).map(|issues| (p, issues)))
+            .flat_map(|(p, issues)| {
+                issues
+                    .iter()
+                    .map(move |issue| api::to_diagnostic(p, issue, DIAGNOSTIC_SOURCE))
+            })
+            .collect();
+        engine.set_diagnostics(DIAGNOSTIC_SOURCE, diagnostics);
+    }
+}
@@ -0,0 +1,106 @@
+use std::io::{Read, Write};
+use std::net::TcpListener;
+use std::thread;
+use std::time::{Duration, Instant};
+use zlyph_core::config::LanguageToolConfig;
+use zlyph_core::diagnostics::Severity;
+use zlyph_core::{BufferPosition, EditorAction, EditorEngine};
+use zlyph_languagetool::api::{paragraphs, parse_response, to_diagnostic};
+use zlyph_languagetool::http::form_encode;
+use zlyph_languagetool::GrammarChecker;
+
+const RESPONSE: &str = r#"{"matches":[{
+    "message": "Possible spelling mistake found.",
+    "offset": 8, "length": 4,
+    "replacements": [{"value": "test"}, {"value": "text"}],
+    "rule": {"id": "MORFOLOGIK_RULE_EN_US", "issueType": "misspelling"}
+}]}"#;
+
+#[test]
+fn test_split_into_paragraphs() {
+    let lines: Vec<String> = ["One.", "Two.", "", "  ", "Three."]
+        .iter()
+        .map(|s| s.to_string())
+        .collect();
+    let found = paragraphs(&lines);
+
+    assert_eq!(found.len(), 2);
+    assert_eq!(found[0].start_row, 0);
+    assert_eq!(found[0].text, "One.\nTwo.");
+    assert_eq!(found[1].start_row, 4);
+}
+
+#[test]
+fn test_issue_offsets_map_to_buffer_positions() {
+    let lines: Vec<String> = vec!["😀 ok".into(), "a tset".into()];
+    let paragraph = &paragraphs(&lines)[0];
+    let issues = parse_response(RESPONSE).unwrap();
+
+    assert_eq!(issues[0].replacements, vec!["test", "text"]);
+    let diagnostic = to_diagnostic(paragraph, &issues[0], "languagetool");
+    assert_eq!(diagnostic.start, BufferPosition::new(1, 2));
+    assert_eq!(diagnostic.end, BufferPosition::new(1, 6));
+    assert_eq!(diagnostic.severity, Severity::Error);
+}
+
+#[test]
+fn test_form_encoding() {
+    assert_eq!(
+        form_encode(&[("language", "en-US"), ("text", "a b&c=é")]),
+        "language=en-US&text=a+b%26c%3D%C3%A9"
+    );
+}
+
+#[test]
+fn test_checker_publishes_diagnostics_from_server() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let url = format!("http://{}", listener.local_addr().unwrap());
+    thread::spawn(move || {
+        let (mut stream, _) = listener.accept().unwrap();
+        let mut request = Vec::new();
+        let mut chunk = [0u8; 1024];
+        while !String::from_utf8_lossy(&request).contains("text=") {
+            let n = stream.read(&mut chunk).unwrap();
+            if n == 0 {
+                break;
+            }
+            request.extend_from_slice(&chunk[..n]);
+        }
+        write!(
+            stream,
+            "HTTP/1.0 200 OK\r\nContent-Type: application/json\r\n\r\n{}",
+            RESPONSE
+        )
+        .unwrap();
+    });
+
+    let mut engine = EditorEngine::new();
+    engine.handle_action(EditorAction::TypeString("This is tset.".to_string()));
+    let mut checker = GrammarChecker::new(&LanguageToolConfig {
+        enabled: true,
+        url,
+        debounce_ms: 0,
+        ..Default::default()
+    })
+    .unwrap();
+
+    let deadline = Instant::now() + Duration::from_secs(5);
+    while engine.diagnostics().is_empty() && Instant::now() < deadline {
+        checker.poll(&mut engine);
+        thread::sleep(Duration::from_millis(10));
+    }
+
+    let diagnostic = &engine.diagnostics()[0];
+    assert_eq!(diagnostic.start, BufferPosition::new(0, 8));
+    assert_eq!(diagnostic.source, "languagetool");
+
+    engine.handle_action(EditorAction::SetCursorPosition { row: 0, column: 9 });
+    engine.handle_action(EditorAction::QuickFix);
+    engine.handle_action(EditorAction::Newline);
+    assert_eq!(engine.state().lines[0], "This is test.");
+}
+
+#[test]
+fn test_checker_disabled_by_default() {
+    assert!(GrammarChecker::new(&LanguageToolConfig::default()).is_none());
+}
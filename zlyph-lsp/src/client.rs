@@ -0,0 +1,173 @@
+//! A single language server process spoken to over stdio
+
+use crate::protocol::{self, path_to_uri};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::io::{self, BufReader};
+use std::path::Path;
+use std::process::{Child, ChildStdin, Command, Stdio};
+use std::sync::mpsc::{self, Receiver};
+use std::thread;
+use zlyph_core::config::LspServerConfig;
+
+/// Requests the editor sends, used to route their responses
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RequestKind {
+    Initialize,
+    Hover,
+    Definition,
+    Completion,
+    DocumentSymbols,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ServerMessage {
+    Response { kind: RequestKind, result: Value },
+    Notification { method: String, params: Value },
+}
+
+pub struct LspClient {
+    child: Child,
+    stdin: ChildStdin,
+    incoming: Receiver<Value>,
+    next_id: u64,
+    pending: HashMap<u64, RequestKind>,
+    initialized: bool,
+    /// Messages held back until the server answers `initialize`
+    queued: Vec<Value>,
+}
+
+impl LspClient {
+    /// Spawn the server and start the initialize handshake
+    pub fn spawn(config: &LspServerConfig, root: &Path) -> io::Result<Self> {
+        let mut child = Command::new(&config.command)
+            .args(&config.args)
+            .current_dir(root)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()?;
+
+        let stdin = child.stdin.take().expect("stdin is piped");
+        let stdout = child.stdout.take().expect("stdout is piped");
+        let (sender, incoming) = mpsc::channel();
+        thread::spawn(move || {
+            let mut reader = BufReader::new(stdout);
+            while let Ok(Some(message)) = protocol::read_message(&mut reader) {
+                if sender.send(message).is_err() {
+                    break;
+                }
+            }
+        });
+
+        let mut client = Self {
+            child,
+            stdin,
+            incoming,
+            next_id: 1,
+            pending: HashMap::new(),
+            initialized: false,
+            queued: Vec::new(),
+        };
+
+        let params = json!({
+            "processId": std::process::id(),
+            "rootUri": path_to_uri(root),
+            "capabilities": {
+                "textDocument": {
+                    "synchronization": { "didSave": false },
+                    "hover": { "contentFormat": ["plaintext", "markdown"] },
+                    "definition": { "linkSupport": true },
+                    "completion": { "completionItem": { "snippetSupport": false } },
+                    "documentSymbol": { "hierarchicalDocumentSymbolSupport": true },
+                    "publishDiagnostics": {}
+                }
+            }
+        });
+        let id = client.allocate_id(RequestKind::Initialize);
+        client.write(
+            json!({ "jsonrpc": "2.0", "id": id, "method": "initialize", "params": params }),
+        )?;
+        Ok(client)
+    }
+
+    fn allocate_id(&mut self, kind: RequestKind) -> u64 {
+        let id = self.next_id;
+        self.next_id += 1;
+        self.pending.insert(id, kind);
+        id
+    }
+
+    fn write(&mut self, message: Value) -> io::Result<()> {
+        protocol::write_message(&mut self.stdin, &message)
+    }
+
+    fn send(&mut self, message: Value) -> io::Result<()> {
+        if self.initialized {
+            self.write(message)
+        } else {
+            self.queued.push(message);
+            Ok(())
+        }
+    }
+
+    pub fn request(&mut self, kind: RequestKind, method: &str, params: Value) -> io::Result<()> {
+        let id = self.allocate_id(kind);
+        self.send(json!({ "jsonrpc": "2.0", "id": id, "method": method, "params": params }))
+    }
+
+    pub fn notify(&mut self, method: &str, params: Value) -> io::Result<()> {
+        self.send(json!({ "jsonrpc": "2.0", "method": method, "params": params }))
+    }
+
+    /// Drain messages received since the last call, finishing the handshake
+    /// when the initialize response arrives
+    pub fn poll(&mut self) -> Vec<ServerMessage> {
+        let mut messages = Vec::new();
+        while let Ok(message) = self.incoming.try_recv() {
+            let id = message.get("id").and_then(Value::as_u64);
+            match (id, message.get("method").and_then(Value::as_str)) {
+                (Some(id), None) => {
+                    let Some(kind) = self.pending.remove(&id) else {
+                        continue;
+                    };
+                    if kind == RequestKind::Initialize {
+                        self.finish_initialize();
+                    } else {
+                        messages.push(ServerMessage::Response {
+                            kind,
+                            result: message.get("result").cloned().unwrap_or(Value::Null),
+                        });
+                    }
+                }
+                (None, Some(method)) => messages.push(ServerMessage::Notification {
+                    method: method.to_string(),
+                    params: message.get("params").cloned().unwrap_or(Value::Null),
+                }),
+                (Some(id), Some(_)) => {
+                    // Server-to-client requests (configuration, progress) get an empty reply
+                    let _ = self.write(json!({ "jsonrpc": "2.0", "id": id, "result": null }));
+                }
+                (None, None) => {}
+            }
+        }
+        messages
+    }
+
+    fn finish_initialize(&mut self) {
+        self.initialized = true;
+        let _ = self.write(json!({ "jsonrpc": "2.0", "method": "initialized", "params": {} }));
+        for message in std::mem::take(&mut self.queued) {
+            let _ = self.write(message);
+        }
+    }
+}
+
+impl Drop for LspClient {
+    fn drop(&mut self) {
+        let _ = self.write(json!({ "jsonrpc": "2.0", "id": 0, "method": "shutdown" }));
+        let _ = self.write(json!({ "jsonrpc": "2.0", "method": "exit" }));
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
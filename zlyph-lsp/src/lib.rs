@@ -0,0 +1,263 @@
+//! Language Server Protocol client for Zlyph
+//!
+//! [`LspManager`] starts one server per configured language, keeps the open
+//! document in sync with the editor buffer and feeds diagnostics, hover text,
+//! definitions, completions and document symbols back into the
+//! [`EditorEngine`] and the frontend.
+
+pub mod client;
+pub mod protocol;
+
+use client::{LspClient, RequestKind, ServerMessage};
+use protocol::{lsp_position, path_to_uri, Location};
+use serde_json::json;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use zlyph_core::config::LspConfig;
+use zlyph_core::symbols::Symbol;
+use zlyph_core::{EditorAction, EditorEngine};
+
+/// Source name used for diagnostics published by language servers
+pub const DIAGNOSTIC_SOURCE: &str = "lsp";
+
+struct OpenDocument {
+    path: PathBuf,
+    uri: String,
+    language_id: String,
+    version: i64,
+    /// Text last sent to the server
+    text: String,
+}
+
+/// Result of applying server messages to the engine
+#[derive(Debug, Default)]
+pub struct LspUpdate {
+    /// Whether any message changed engine state that should be redrawn
+    pub changed: bool,
+    /// A definition located in another file, which the frontend should open
+    pub jump: Option<Location>,
+    /// The document's outline, answering [`LspManager::request_symbols`]
+    pub symbols: Option<Vec<Symbol>>,
+}
+
+pub struct LspManager {
+    config: LspConfig,
+    /// Running servers keyed by language id
+    clients: HashMap<String, LspClient>,
+    document: Option<OpenDocument>,
+}
+
+impl LspManager {
+    pub fn new(config: LspConfig) -> Self {
+        Self {
+            config,
+            clients: HashMap::new(),
+            document: None,
+        }
+    }
+
+    /// Open `path` with the server configured for its extension, starting the
+    /// server if needed. Files without a configured server, and large files,
+    /// are ignored.
+    pub fn open(&mut self, path: &Path, engine: &EditorEngine) {
+        self.close();
+        if engine.is_large_file() {
+            return;
+        }
+
+        let Some(server) = self.config.server_for(path).cloned() else {
+            return;
+        };
+        if !self.clients.contains_key(&server.language_id) {
+            let root = path
+                .parent()
+                .filter(|p| !p.as_os_str().is_empty())
+                .unwrap_or(Path::new("."));
+            match LspClient::spawn(&server, root) {
+                Ok(client) => {
+                    self.clients.insert(server.language_id.clone(), client);
+                }
+                Err(_) => return,
+            }
+        }
+
+        let document = OpenDocument {
+            path: path.to_path_buf(),
+            uri: path_to_uri(path),
+            language_id: server.language_id,
+            version: 1,
+            text: engine.state().to_string(),
+        };
+        let params = json!({
+            "textDocument": {
+                "uri": document.uri,
+                "languageId": document.language_id,
+                "version": document.version,
+                "text": document.text,
+            }
+        });
+        self.document = Some(document);
+        self.notify("textDocument/didOpen", params);
+    }
+
+    /// Whether a language server answers for the open file
+    pub fn is_open(&self) -> bool {
+        self.document.is_some()
+    }
+
+    /// Close the open document, e.g. when switching to an untitled buffer
+    pub fn close(&mut self) {
+        if let Some(document) = self.document.take() {
+            if let Some(client) = self.clients.get_mut(&document.language_id) {
+                let _ = client.notify(
+                    "textDocument/didClose",
+                    json!({ "textDocument": { "uri": document.uri } }),
+                );
+            }
+        }
+    }
+
+    fn client(&mut self) -> Option<&mut LspClient> {
+        let language_id = &self.document.as_ref()?.language_id;
+        self.clients.get_mut(language_id)
+    }
+
+    fn notify(&mut self, method: &str, params: serde_json::Value) {
+        if let Some(client) = self.client() {
+            let _ = client.notify(method, params);
+        }
+    }
+
+    /// Send the full buffer to the server if it changed since the last sync
+    pub fn sync(&mut self, engine: &EditorEngine) {
+        let Some(document) = self.document.as_mut() else {
+            return;
+        };
+        let text = engine.state().to_string();
+        if text == document.text {
+            return;
+        }
+
+        document.version += 1;
+        document.text = text;
+        let params = json!({
+            "textDocument": { "uri": document.uri, "version": document.version },
+            "contentChanges": [{ "text": document.text }],
+        });
+        self.notify("textDocument/didChange", params);
+    }
+
+    /// Forward actions answered by the server (hover, definition, completion)
+    pub fn handle_action(&mut self, action: &EditorAction, engine: &EditorEngine) {
+        let (kind, method) = match action {
+            EditorAction::Hover => (RequestKind::Hover, "textDocument/hover"),
+            EditorAction::GoToDefinition => (RequestKind::Definition, "textDocument/definition"),
+            EditorAction::TriggerCompletion => (RequestKind::Completion, "textDocument/completion"),
+            _ => return,
+        };
+        let Some(uri) = self.document.as_ref().map(|d| d.uri.clone()) else {
+            return;
+        };
+
+        self.sync(engine);
+        let state = engine.state();
+        let params = json!({
+            "textDocument": { "uri": uri },
+            "position": lsp_position(&state.lines, state.cursor),
+        });
+        if let Some(client) = self.client() {
+            let _ = client.request(kind, method, params);
+        }
+    }
+
+    /// Ask the server for the document's outline, which arrives in
+    /// [`LspUpdate::symbols`]
+    pub fn request_symbols(&mut self, engine: &EditorEngine) {
+        let Some(uri) = self.document.as_ref().map(|d| d.uri.clone()) else {
+            return;
+        };
+        self.sync(engine);
+        let params = json!({ "textDocument": { "uri": uri } });
+        if let Some(client) = self.client() {
+            let _ = client.request(
+                RequestKind::DocumentSymbols,
+                "textDocument/documentSymbol",
+                params,
+            );
+        }
+    }
+
+    /// Apply server messages received since the last call to the engine
+    pub fn process(&mut self, engine: &mut EditorEngine) -> LspUpdate {
+        let mut update = LspUpdate::default();
+        let Some(document) = &self.document else {
+            return update;
+        };
+        let Some(client) = self.clients.get_mut(&document.language_id) else {
+            return update;
+        };
+
+        for message in client.poll() {
+            update.changed = true;
+            match message {
+                ServerMessage::Notification { method, params }
+                    if method == "textDocument/publishDiagnostics"
+                        && params["uri"].as_str() == Some(document.uri.as_str()) =>
+                {
+                    let diagnostics = protocol::parse_diagnostics(
+                        &params,
+                        &engine.state().lines,
+                        DIAGNOSTIC_SOURCE,
+                    );
+                    engine.set_diagnostics(DIAGNOSTIC_SOURCE, diagnostics);
+                }
+                ServerMessage::Notification { .. } => {}
+                ServerMessage::Response { kind, result } => match kind {
+                    RequestKind::Hover => engine.set_hover(protocol::parse_hover(&result)),
+                    RequestKind::Completion => {
+                        let items = protocol::parse_completion(&result);
+                        if !items.is_empty() {
+                            engine.show_completion(items);
+                        }
+                    }
+                    RequestKind::Definition => {
+                        let Some(location) = protocol::parse_definition(&result) else {
+                            continue;
+                        };
+                        if same_file(&location.path, &document.path) {
+                            let pos = location.position_in(&engine.state().lines);
+                            engine.handle_action(EditorAction::SetCursorPosition {
+                                row: pos.row,
+                                column: pos.column,
+                            });
+                        } else {
+                            update.jump = Some(location);
+                        }
+                    }
+                    RequestKind::DocumentSymbols => {
+                        update.symbols = Some(protocol::parse_document_symbols(
+                            &result,
+                            &engine.state().lines,
+                        ));
+                    }
+                    RequestKind::Initialize => {}
+                },
+            }
+        }
+
+        update
+    }
+}
+
+fn same_file(a: &Path, b: &Path) -> bool {
+    match (a.canonicalize(), b.canonicalize()) {
+        (Ok(a), Ok(b)) => a == b,
+        _ => a == b,
+    }
+}
+
+impl Drop for LspManager {
+    fn drop(&mut self) {
+        self.close();
+    }
+}
@@ -0,0 +1,260 @@
+//! JSON-RPC framing and conversions between LSP and editor types
+
+use serde_json::Value;
+use std::io::{self, BufRead, Write};
+use std::path::{Path, PathBuf};
+use zlyph_core::diagnostics::{Diagnostic, Severity};
+use zlyph_core::symbols::{Symbol, SymbolKind};
+use zlyph_core::BufferPosition;
+
+/// Write a message with its `Content-Length` header
+pub fn write_message(writer: &mut impl Write, message: &Value) -> io::Result<()> {
+    let body = message.to_string();
+    write!(writer, "Content-Length: {}\r\n\r\n{}", body.len(), body)?;
+    writer.flush()
+}
+
+/// Read the next message; `Ok(None)` means the server closed its output
+pub fn read_message(reader: &mut impl BufRead) -> io::Result<Option<Value>> {
+    let mut content_length = None;
+    loop {
+        let mut header = String::new();
+        if reader.read_line(&mut header)? == 0 {
+            return Ok(None);
+        }
+        let header = header.trim_end();
+        if header.is_empty() {
+            break;
+        }
+        if let Some(value) = header.strip_prefix("Content-Length:") {
+            content_length = value.trim().parse::<usize>().ok();
+        }
+    }
+
+    let length = content_length
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "missing Content-Length"))?;
+    let mut body = vec![0; length];
+    reader.read_exact(&mut body)?;
+    serde_json::from_slice(&body)
+        .map(Some)
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+}
+
+pub fn path_to_uri(path: &Path) -> String {
+    let absolute = path.canonicalize().unwrap_or_else(|_| path.to_path_buf());
+    let mut uri = String::from("file://");
+    for byte in absolute.to_string_lossy().bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'/' | b'-' | b'_' | b'.' | b'~' => {
+                uri.push(byte as char)
+            }
+            _ => uri.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    uri
+}
+
+pub fn uri_to_path(uri: &str) -> Option<PathBuf> {
+    let encoded = uri.strip_prefix("file://")?;
+    let bytes = encoded.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' {
+            let hex = std::str::from_utf8(bytes.get(i + 1..i + 3)?).ok()?;
+            decoded.push(u8::from_str_radix(hex, 16).ok()?);
+            i += 3;
+        } else {
+            decoded.push(bytes[i]);
+            i += 1;
+        }
+    }
+    String::from_utf8(decoded).ok().map(PathBuf::from)
+}
+
+/// Byte column to the UTF-16 offset LSP uses for `character`
+pub fn byte_to_utf16_column(line: &str, column: usize) -> usize {
+    line[..column.min(line.len())].encode_utf16().count()
+}
+
+/// UTF-16 `character` offset back to a byte column, clamped to the line
+pub fn utf16_to_byte_column(line: &str, character: usize) -> usize {
+    let mut units = 0;
+    for (i, c) in line.char_indices() {
+        if units >= character {
+            return i;
+        }
+        units += c.len_utf16();
+    }
+    line.len()
+}
+
+pub fn lsp_position(lines: &[String], pos: BufferPosition) -> Value {
+    let line = lines.get(pos.row).map_or("", |l| l.as_str());
+    serde_json::json!({
+        "line": pos.row,
+        "character": byte_to_utf16_column(line, pos.column),
+    })
+}
+
+pub fn buffer_position(lines: &[String], position: &Value) -> BufferPosition {
+    let row = position["line"].as_u64().unwrap_or(0) as usize;
+    let character = position["character"].as_u64().unwrap_or(0) as usize;
+    match lines.get(row) {
+        Some(line) => BufferPosition::new(row, utf16_to_byte_column(line, character)),
+        None => {
+            let last = lines.len().saturating_sub(1);
+            BufferPosition::new(last, lines.get(last).map_or(0, |l| l.len()))
+        }
+    }
+}
+
+/// Convert `textDocument/publishDiagnostics` params into editor diagnostics
+pub fn parse_diagnostics(params: &Value, lines: &[String], source: &str) -> Vec<Diagnostic> {
+    let Some(items) = params["diagnostics"].as_array() else {
+        return Vec::new();
+    };
+
+    items
+        .iter()
+        .map(|item| Diagnostic {
+            start: buffer_position(lines, &item["range"]["start"]),
+            end: buffer_position(lines, &item["range"]["end"]),
+            severity: match item["severity"].as_u64() {
+                Some(2) => Severity::Warning,
+                Some(3) => Severity::Information,
+                Some(4) => Severity::Hint,
+                _ => Severity::Error,
+            },
+            message: item["message"].as_str().unwrap_or_default().to_string(),
+            source: source.to_string(),
+            replacements: Vec::new(),
+        })
+        .collect()
+}
+
+/// Plain text of a hover result (MarkupContent, MarkedString or an array of them)
+pub fn parse_hover(result: &Value) -> Option<String> {
+    fn marked_text(value: &Value) -> Option<String> {
+        match value {
+            Value::String(s) => Some(s.clone()),
+            Value::Object(o) => o.get("value").and_then(Value::as_str).map(String::from),
+            Value::Array(items) => {
+                let parts: Vec<String> = items.iter().filter_map(marked_text).collect();
+                (!parts.is_empty()).then(|| parts.join("\n\n"))
+            }
+            _ => None,
+        }
+    }
+    marked_text(&result["contents"])
+}
+
+/// A definition target; `character` stays in UTF-16 units until the target
+/// file's text is available
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Location {
+    pub path: PathBuf,
+    pub line: usize,
+    pub character: usize,
+}
+
+impl Location {
+    pub fn position_in(&self, lines: &[String]) -> BufferPosition {
+        buffer_position(
+            lines,
+            &serde_json::json!({ "line": self.line, "character": self.character }),
+        )
+    }
+}
+
+/// First location of a definition result (Location, Location[] or LocationLink[])
+pub fn parse_definition(result: &Value) -> Option<Location> {
+    let location = match result {
+        Value::Array(items) => items.first()?,
+        other => other,
+    };
+    let uri = location
+        .get("uri")
+        .or_else(|| location.get("targetUri"))?
+        .as_str()?;
+    let range = location
+        .get("range")
+        .or_else(|| location.get("targetSelectionRange"))?;
+
+    Some(Location {
+        path: uri_to_path(uri)?,
+        line: range["start"]["line"].as_u64()? as usize,
+        character: range["start"]["character"].as_u64()? as usize,
+    })
+}
+
+/// Insert texts of a completion result (CompletionItem[] or CompletionList)
+pub fn parse_completion(result: &Value) -> Vec<String> {
+    let items = match result {
+        Value::Array(items) => items,
+        Value::Object(list) => match list.get("items").and_then(Value::as_array) {
+            Some(items) => items,
+            None => return Vec::new(),
+        },
+        _ => return Vec::new(),
+    };
+
+    items
+        .iter()
+        .filter_map(|item| {
+            item["insertText"]
+                .as_str()
+                .or_else(|| item["label"].as_str())
+                .map(String::from)
+        })
+        .collect()
+}
+
+fn symbol_kind(kind: Option<u64>) -> SymbolKind {
+    match kind {
+        Some(2..=4) => SymbolKind::Module,
+        Some(5 | 10 | 11 | 23 | 26) => SymbolKind::Type,
+        Some(6 | 9 | 12) => SymbolKind::Function,
+        Some(14 | 22) => SymbolKind::Constant,
+        _ => SymbolKind::Other,
+    }
+}
+
+/// The outline of a `textDocument/documentSymbol` result, nested
+/// DocumentSymbol[] or flat SymbolInformation[], in buffer order
+pub fn parse_document_symbols(result: &Value, lines: &[String]) -> Vec<Symbol> {
+    fn visit(items: &[Value], depth: usize, lines: &[String], symbols: &mut Vec<Symbol>) {
+        for item in items {
+            let Some(name) = item["name"].as_str() else {
+                continue;
+            };
+            let range = item
+                .get("selectionRange")
+                .or_else(|| item.get("range"))
+                .or_else(|| item["location"].get("range"));
+            let Some(range) = range else {
+                continue;
+            };
+            let start = buffer_position(lines, &range["start"]);
+            symbols.push(Symbol {
+                name: name.to_string(),
+                kind: symbol_kind(item["kind"].as_u64()),
+                row: start.row,
+                column: start.column,
+                depth,
+            });
+            if let Some(children) = item["children"].as_array() {
+                visit(children, depth + 1, lines, symbols);
+            }
+        }
+    }
+
+    let mut symbols = Vec::new();
+    if let Some(items) = result.as_array() {
+        visit(items, 0, lines, &mut symbols);
+    }
+    // Servers needn't list symbols in order; children still follow their
+    // parent, which starts before them
+    symbols.sort_by_key(|symbol| (symbol.row, symbol.column));
+    symbols
+}
@@ -0,0 +1,135 @@
+use serde_json::json;
+use std::io::Cursor;
+use std::path::PathBuf;
+use zlyph_core::diagnostics::Severity;
+use zlyph_core::symbols::SymbolKind;
+use zlyph_core::BufferPosition;
+use zlyph_lsp::protocol::*;
+
+fn lines(text: &str) -> Vec<String> {
+    text.split('\n').map(String::from).collect()
+}
+
+#[test]
+fn test_message_framing_round_trip() {
+    let mut buffer = Vec::new();
+    write_message(&mut buffer, &json!({ "id": 1, "result": "é" })).unwrap();
+    write_message(&mut buffer, &json!({ "method": "exit" })).unwrap();
+    assert!(buffer.starts_with(b"Content-Length: 22\r\n\r\n"));
+
+    let mut reader = Cursor::new(buffer);
+    assert_eq!(
+        read_message(&mut reader).unwrap(),
+        Some(json!({ "id": 1, "result": "é" }))
+    );
+    assert_eq!(
+        read_message(&mut reader).unwrap(),
+        Some(json!({ "method": "exit" }))
+    );
+    assert_eq!(read_message(&mut reader).unwrap(), None);
+}
+
+#[test]
+fn test_utf16_columns() {
+    let line = "a😀b";
+    assert_eq!(byte_to_utf16_column(line, 5), 3);
+    assert_eq!(utf16_to_byte_column(line, 3), 5);
+    assert_eq!(utf16_to_byte_column(line, 99), line.len());
+}
+
+#[test]
+fn test_uri_round_trip() {
+    let path = PathBuf::from("/tmp/my notes/ü.md");
+    let uri = path_to_uri(&path);
+    assert_eq!(uri, "file:///tmp/my%20notes/%C3%BC.md");
+    assert_eq!(uri_to_path(&uri), Some(path));
+}
+
+#[test]
+fn test_parse_diagnostics() {
+    let params = json!({
+        "uri": "file:///a.rs",
+        "diagnostics": [{
+            "range": {
+                "start": { "line": 1, "character": 2 },
+                "end": { "line": 1, "character": 5 }
+            },
+            "severity": 2,
+            "message": "unused variable"
+        }]
+    });
+
+    let diagnostics = parse_diagnostics(&params, &lines("fn main() {\n  😀x = 1;\n}"), "lsp");
+    assert_eq!(diagnostics.len(), 1);
+    assert_eq!(diagnostics[0].start, BufferPosition::new(1, 2));
+    assert_eq!(diagnostics[0].end, BufferPosition::new(1, 7));
+    assert_eq!(diagnostics[0].severity, Severity::Warning);
+    assert_eq!(diagnostics[0].message, "unused variable");
+}
+
+#[test]
+fn test_parse_responses() {
+    let hover = json!({ "contents": { "kind": "markdown", "value": "fn main()" } });
+    assert_eq!(parse_hover(&hover), Some("fn main()".to_string()));
+    let hover = json!({ "contents": ["a", { "language": "rust", "value": "b" }] });
+    assert_eq!(parse_hover(&hover), Some("a\n\nb".to_string()));
+
+    let definition = json!([{
+        "targetUri": "file:///src/lib.rs",
+        "targetRange": { "start": { "line": 0, "character": 0 }, "end": { "line": 9, "character": 1 } },
+        "targetSelectionRange": { "start": { "line": 3, "character": 7 }, "end": { "line": 3, "character": 10 } }
+    }]);
+    assert_eq!(
+        parse_definition(&definition),
+        Some(Location {
+            path: PathBuf::from("/src/lib.rs"),
+            line: 3,
+            character: 7,
+        })
+    );
+
+    let completion = json!({
+        "isIncomplete": false,
+        "items": [{ "label": "push" }, { "label": "len()", "insertText": "len" }]
+    });
+    assert_eq!(parse_completion(&completion), vec!["push", "len"]);
+}
+
+#[test]
+fn test_parse_document_symbols() {
+    let lines = lines("struct Ünit;\nimpl Ünit {\n    fn new() {}\n}");
+    let range = |line: u64, character: u64| json!({ "start": { "line": line, "character": character }, "end": { "line": line, "character": character + 3 } });
+    let nested = json!([{
+        "name": "impl Ünit",
+        "kind": 19,
+        "range": range(1, 0),
+        "selectionRange": range(1, 5),
+        "children": [{ "name": "new", "kind": 6, "range": range(2, 4), "selectionRange": range(2, 7) }]
+    }, {
+        "name": "Ünit",
+        "kind": 23,
+        "range": range(0, 0),
+        "selectionRange": range(0, 7)
+    }]);
+    let symbols = parse_document_symbols(&nested, &lines);
+    let found: Vec<(&str, SymbolKind, usize, usize, usize)> = symbols
+        .iter()
+        .map(|s| (s.name.as_str(), s.kind, s.row, s.column, s.depth))
+        .collect();
+    assert_eq!(
+        found,
+        [
+            ("Ünit", SymbolKind::Type, 0, 7, 0),
+            ("impl Ünit", SymbolKind::Other, 1, 5, 0),
+            ("new", SymbolKind::Function, 2, 7, 1),
+        ]
+    );
+
+    let flat = json!([{
+        "name": "new",
+        "kind": 12,
+        "location": { "uri": "file:///a.rs", "range": range(2, 4) }
+    }]);
+    assert_eq!(parse_document_symbols(&flat, &lines)[0].row, 2);
+    assert!(parse_document_symbols(&json!(null), &lines).is_empty());
+}
@@ -0,0 +1,486 @@
+//! Every key the editor answers outside its panels, in one table
+//!
+//! Key handling looks keys up here and the help overlay lists this table,
+//! so the help cannot disagree with what the keys do. Keys inside a panel
+//! (arrows, Enter, Esc) belong to that panel and are not listed.
+
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use zlyph_core::help::{self, HelpOverlay};
+use zlyph_core::EditorAction;
+
+/// What a key does: an edit for the engine, or something the terminal
+/// frontend does itself
+#[derive(Debug, Clone, PartialEq)]
+pub enum Command {
+    Edit(EditorAction),
+    Help,
+    Save,
+    Open,
+    New,
+    SaveAs,
+    ExportHtml,
+    ExportPdf,
+    ExportImage,
+    Print,
+    CompareWith,
+    Ex,
+    DailyNote,
+    PreviousDailyNote,
+    NextDailyNote,
+    RecentFiles,
+    SwitchProject,
+    FileTree,
+    FindInFiles,
+    ReplaceInFiles,
+    LocalHistory,
+    Backlinks,
+    Bookmarks,
+    Tags,
+    Todos,
+    Outline,
+    GoToSymbol,
+    NoteSearch,
+    NoteFromTemplate,
+    InsertCharacter,
+    InsertCodepoint,
+    /// Open the command line at `align `, to name the delimiter
+    AlignOn,
+    /// Open the command line at `transform `, to name the transform
+    TransformSelection,
+    /// Paste the system clipboard, re-indented to fit
+    Paste,
+    /// Paste the system clipboard without its indent or invisible characters
+    PastePlain,
+    /// Open the command line at `r !`, to insert a command's output
+    RunShellCommand,
+    /// Open the command line at `!`, to filter the selection through a
+    /// command
+    FilterThroughCommand,
+    NextChange,
+    PreviousChange,
+    PreviewChange,
+    RevertChange,
+    StageChange,
+    ToggleTerminal,
+    SendToTerminal,
+    CaptureTerminal,
+    FindReferences,
+    RunTask,
+    ShowErrors,
+    NextError,
+    PreviousError,
+}
+
+impl Command {
+    pub fn label(&self) -> String {
+        let label = match self {
+            Command::Edit(EditorAction::Quit) => "Save and quit",
+            Command::Edit(EditorAction::Cancel) => "Close the popup or clear the selection",
+            Command::Edit(EditorAction::Tab) => "Indent or insert a tab",
+            Command::Edit(EditorAction::OpenLinkUnderCursor) => "Follow the link at the cursor",
+            Command::Edit(action) => return help::action_label(action),
+            Command::Help => "Show this help",
+            Command::Save => "Overwrite a remote file that changed on its host",
+            Command::Open => "Open a file",
+            Command::New => "New file",
+            Command::SaveAs => "Save as",
+            Command::ExportHtml => "Export as HTML",
+            Command::ExportPdf => "Export as PDF",
+            Command::ExportImage => "Export the selection as a PNG or SVG picture",
+            Command::Print => "Open as a PDF to print",
+            Command::CompareWith => "Compare with a file or git revision",
+            Command::Ex => "Command line (:w, :q, :wq, :e file, :42, :%s/a/b/g)",
+            Command::DailyNote => "Open today's note",
+            Command::PreviousDailyNote => "Previous daily note",
+            Command::NextDailyNote => "Next daily note",
+            Command::RecentFiles => "Recent files",
+            Command::SwitchProject => "Switch project",
+            Command::FileTree => "Show or focus the file tree",
+            Command::FindInFiles => "Find in files",
+            Command::ReplaceInFiles => "Replace in files",
+            Command::LocalHistory => "Local history",
+            Command::Backlinks => "Notes linking to this one",
+            Command::Bookmarks => "List the bookmarks",
+            Command::Tags => "Tags in the notes",
+            Command::Todos => "TODOs and open tasks in the file or the notes",
+            Command::Outline => "Show or focus the outline of the file",
+            Command::GoToSymbol => "Go to a function, type or heading",
+            Command::NoteSearch => "Search the notes",
+            Command::NoteFromTemplate => "New note from template",
+            Command::InsertCharacter => "Insert a character or emoji by name",
+            Command::InsertCodepoint => "Insert a character by code point",
+            Command::AlignOn => "Align lines on a delimiter",
+            Command::TransformSelection => "Encode, decode or hash the selection",
+            Command::Paste => "Paste, re-indented to fit",
+            Command::PastePlain => "Paste as plain text",
+            Command::RunShellCommand => "Insert the output of a shell command",
+            Command::FilterThroughCommand => "Filter the selection through a shell command",
+            Command::NextChange => "Next change since the last commit",
+            Command::PreviousChange => "Previous change since the last commit",
+            Command::PreviewChange => "Preview the change at the cursor",
+            Command::RevertChange => "Revert the change at the cursor",
+            Command::StageChange => "Stage the change at the cursor",
+            Command::ToggleTerminal => "Show, focus or hide the terminal",
+            Command::SendToTerminal => "Run the selection or line in the terminal",
+            Command::CaptureTerminal => "Insert the terminal's output",
+            Command::FindReferences => "Find where the name at the cursor is used",
+            Command::RunTask => "Run the build or lint task and list its errors",
+            Command::ShowErrors => "Show or hide the task's errors",
+            Command::NextError => "Next error from the task",
+            Command::PreviousError => "Previous error from the task",
+        };
+        label.to_string()
+    }
+}
+
+/// Modifiers a binding needs: exactly these, or any at all
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Mods {
+    Exact(KeyModifiers),
+    Any,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Binding {
+    code: KeyCode,
+    mods: Mods,
+    section: &'static str,
+    pub command: Command,
+}
+
+impl Binding {
+    /// The key as the help shows it, e.g. "Alt+Shift+B"
+    pub fn key_name(&self) -> String {
+        let mut parts = Vec::new();
+        let mods = match self.mods {
+            Mods::Exact(mods) => mods,
+            Mods::Any => KeyModifiers::NONE,
+        };
+        for (modifier, name) in [
+            (KeyModifiers::CONTROL, "Ctrl"),
+            (KeyModifiers::ALT, "Alt"),
+            (KeyModifiers::SUPER, "Cmd"),
+        ] {
+            if mods.contains(modifier) {
+                parts.push(name.to_string());
+            }
+        }
+        let shifted = matches!(self.code, KeyCode::Char(c) if c.is_uppercase());
+        if mods.contains(KeyModifiers::SHIFT) || shifted {
+            parts.push("Shift".to_string());
+        }
+        parts.push(match self.code {
+            KeyCode::Char(' ') => "Space".to_string(),
+            KeyCode::Char(c) => c.to_uppercase().to_string(),
+            KeyCode::F(n) => format!("F{}", n),
+            KeyCode::Esc => "Esc".to_string(),
+            code => format!("{:?}", code),
+        });
+        parts.join("+")
+    }
+
+    fn matches(&self, code: KeyCode, mods: KeyModifiers) -> bool {
+        self.code == code
+            && match self.mods {
+                Mods::Exact(exact) => exact == mods,
+                Mods::Any => true,
+            }
+    }
+}
+
+const NONE: KeyModifiers = KeyModifiers::NONE;
+const SHIFT: KeyModifiers = KeyModifiers::SHIFT;
+const CTRL: KeyModifiers = KeyModifiers::CONTROL;
+const ALT: KeyModifiers = KeyModifiers::ALT;
+const CMD: KeyModifiers = KeyModifiers::SUPER;
+const CTRL_SHIFT: KeyModifiers = CTRL.union(SHIFT);
+const ALT_SHIFT: KeyModifiers = ALT.union(SHIFT);
+const CMD_SHIFT: KeyModifiers = CMD.union(SHIFT);
+
+const fn bind(
+    code: KeyCode,
+    mods: KeyModifiers,
+    section: &'static str,
+    command: Command,
+) -> Binding {
+    Binding {
+        code,
+        mods: Mods::Exact(mods),
+        section,
+        command,
+    }
+}
+
+const fn bind_any(code: KeyCode, section: &'static str, command: Command) -> Binding {
+    Binding {
+        code,
+        mods: Mods::Any,
+        section,
+        command,
+    }
+}
+
+const fn edit(
+    code: KeyCode,
+    mods: KeyModifiers,
+    section: &'static str,
+    action: EditorAction,
+) -> Binding {
+    bind(code, mods, section, Command::Edit(action))
+}
+
+const fn edit_any(code: KeyCode, section: &'static str, action: EditorAction) -> Binding {
+    bind_any(code, section, Command::Edit(action))
+}
+
+use KeyCode::{
+    Backspace, Char, Delete, Down, End, Enter, Esc, Home, Left, PageDown, PageUp, Right, Tab, Up, F,
+};
+
+/// The bindings, searched in order. Terminals report a letter typed with
+/// Shift as the uppercase letter, so Alt+Shift+B is `Char('B')` with Alt.
+pub static BINDINGS: &[Binding] = &[
+    bind(F(1), NONE, "General", Command::Help),
+    bind(Char('h'), CTRL, "General", Command::Help),
+    edit(Char('w'), CTRL, "General", EditorAction::Quit),
+    bind(Char(';'), ALT, "General", Command::Ex),
+    edit_any(Esc, "General", EditorAction::Cancel),
+    edit(Char('='), CTRL, "General", EditorAction::IncreaseFontSize),
+    edit(Char('-'), CTRL, "General", EditorAction::DecreaseFontSize),
+    edit(Char('0'), CTRL, "General", EditorAction::ResetFontSize),
+    // Files
+    bind(Char('o'), CTRL, "Files", Command::Open),
+    bind(Char('n'), CTRL, "Files", Command::New),
+    bind(Char('s'), CTRL, "Files", Command::Save),
+    bind(Char('s'), ALT, "Files", Command::SaveAs),
+    bind(Char('E'), ALT, "Files", Command::ExportHtml),
+    bind(Char('P'), ALT, "Files", Command::ExportPdf),
+    bind(Char('C'), ALT, "Files", Command::ExportImage),
+    bind(Char('p'), CTRL, "Files", Command::Print),
+    bind(Char('r'), CTRL, "Files", Command::RecentFiles),
+    bind(Char('D'), ALT, "Files", Command::SwitchProject),
+    bind(Char('e'), ALT, "Files", Command::FileTree),
+    bind(Char('d'), ALT, "Files", Command::CompareWith),
+    bind(Char('l'), ALT, "Files", Command::LocalHistory),
+    // Notes
+    bind(Char('j'), ALT, "Notes", Command::DailyNote),
+    bind(PageUp, ALT, "Notes", Command::PreviousDailyNote),
+    bind(PageDown, ALT, "Notes", Command::NextDailyNote),
+    bind(Char('n'), ALT, "Notes", Command::NoteFromTemplate),
+    bind(Char('B'), ALT, "Notes", Command::Backlinks),
+    bind(Char('T'), ALT, "Notes", Command::Tags),
+    bind(Char('S'), ALT, "Notes", Command::NoteSearch),
+    edit(Enter, ALT, "Notes", EditorAction::OpenLinkUnderCursor),
+    // Search
+    bind(Char('F'), ALT, "Search", Command::FindInFiles),
+    bind(Char('R'), ALT, "Search", Command::ReplaceInFiles),
+    bind(Char('O'), ALT, "Search", Command::Todos),
+    bind(Char('o'), ALT, "Search", Command::Outline),
+    bind(Char('t'), CTRL, "Search", Command::GoToSymbol),
+    edit(F(3), SHIFT, "Search", EditorAction::PrevMatch),
+    edit_any(F(3), "Search", EditorAction::NextMatch),
+    edit(Char('L'), ALT, "Search", EditorAction::SelectAllMatches),
+    // Git
+    bind(F(7), SHIFT, "Git", Command::PreviousChange),
+    bind_any(F(7), "Git", Command::NextChange),
+    bind(Char('h'), ALT, "Git", Command::PreviewChange),
+    bind(Char('u'), ALT, "Git", Command::RevertChange),
+    bind(Char('a'), ALT, "Git", Command::StageChange),
+    // Terminal
+    bind(Char('`'), ALT, "Terminal", Command::ToggleTerminal),
+    bind(Char('~'), ALT, "Terminal", Command::SendToTerminal),
+    bind(Char('Y'), ALT, "Terminal", Command::CaptureTerminal),
+    // Tasks
+    bind(F(9), SHIFT, "Tasks", Command::ShowErrors),
+    bind_any(F(9), "Tasks", Command::RunTask),
+    bind(F(4), SHIFT, "Tasks", Command::PreviousError),
+    bind_any(F(4), "Tasks", Command::NextError),
+    // Bookmarks
+    edit(F(2), CTRL, "Bookmarks", EditorAction::ToggleBookmark),
+    edit(F(2), SHIFT, "Bookmarks", EditorAction::PrevBookmark),
+    bind(F(2), ALT, "Bookmarks", Command::Bookmarks),
+    edit_any(F(2), "Bookmarks", EditorAction::NextBookmark),
+    // Editing
+    edit(Char('z'), CTRL_SHIFT, "Editing", EditorAction::Redo),
+    edit(Char('z'), CTRL, "Editing", EditorAction::Undo),
+    edit(Char('k'), CTRL_SHIFT, "Editing", EditorAction::DeleteLine),
+    edit(Backspace, CMD, "Editing", EditorAction::DeleteLine),
+    edit(
+        Backspace,
+        CTRL,
+        "Editing",
+        EditorAction::DeleteToBeginningOfLine,
+    ),
+    edit(Backspace, ALT, "Editing", EditorAction::DeleteWordLeft),
+    edit(Delete, CMD, "Editing", EditorAction::DeleteToEndOfLine),
+    edit(Delete, CTRL, "Editing", EditorAction::DeleteToEndOfLine),
+    edit(Delete, ALT, "Editing", EditorAction::DeleteWordRight),
+    // What terminals send for Cmd+Backspace
+    edit(
+        Char('u'),
+        CTRL,
+        "Editing",
+        EditorAction::DeleteToBeginningOfLine,
+    ),
+    edit(Tab, SHIFT, "Editing", EditorAction::Outdent),
+    edit(Tab, NONE, "Editing", EditorAction::Tab),
+    edit(Up, ALT, "Editing", EditorAction::MoveLineUp),
+    edit(Down, ALT, "Editing", EditorAction::MoveLineDown),
+    edit(Up, CTRL, "Editing", EditorAction::MoveSectionUp),
+    edit(Down, CTRL, "Editing", EditorAction::MoveSectionDown),
+    edit(Left, CTRL, "Editing", EditorAction::PromoteSection),
+    edit(Right, CTRL, "Editing", EditorAction::DemoteSection),
+    edit(Char('#'), ALT, "Selection", EditorAction::SelectSection),
+    edit(Char('^'), ALT, "Editing", EditorAction::InsertFootnote),
+    edit(Char('&'), ALT, "Editing", EditorAction::RenumberFootnotes),
+    edit(Char('6'), ALT, "Movement", EditorAction::GoToFootnote),
+    edit(Char('@'), ALT, "Editing", EditorAction::InsertBibliography),
+    edit(F(5), SHIFT, "Editing", EditorAction::InsertTime),
+    edit(F(5), CTRL, "Editing", EditorAction::InsertTimestamp),
+    edit_any(F(5), "Editing", EditorAction::InsertDate),
+    bind(Char(':'), ALT, "Editing", Command::InsertCharacter),
+    bind(Char('U'), ALT, "Editing", Command::InsertCodepoint),
+    edit(Char('I'), ALT, "Editing", EditorAction::StripInvisibles),
+    edit(Char('='), ALT, "Editing", EditorAction::EvaluateSelection),
+    edit(Char('a'), CTRL, "Editing", EditorAction::IncrementNumber(1)),
+    edit(Char('x'), CTRL, "Editing", EditorAction::DecrementNumber(1)),
+    edit(
+        Char('a'),
+        CTRL_SHIFT,
+        "Editing",
+        EditorAction::NumberSequence(1),
+    ),
+    edit(Char('J'), ALT, "Editing", EditorAction::FormatJson),
+    edit(Char('M'), ALT, "Editing", EditorAction::MinifyJson),
+    edit(Char('K'), ALT, "Editing", EditorAction::SortKeys),
+    bind(Char('A'), ALT, "Editing", Command::AlignOn),
+    bind(Char('N'), ALT, "Editing", Command::TransformSelection),
+    bind(Char('v'), CTRL, "Editing", Command::Paste),
+    bind(Char('V'), ALT, "Editing", Command::PastePlain),
+    bind(Char('$'), ALT, "Editing", Command::RunShellCommand),
+    bind(Char('!'), ALT, "Editing", Command::FilterThroughCommand),
+    // Markdown tables
+    edit(Char('|'), ALT, "Tables", EditorAction::FormatTable),
+    edit(Char('r'), ALT, "Tables", EditorAction::InsertTableRow),
+    edit(Char('x'), ALT, "Tables", EditorAction::DeleteTableRow),
+    edit(Char('c'), ALT, "Tables", EditorAction::InsertTableColumn),
+    edit(Char('X'), ALT, "Tables", EditorAction::DeleteTableColumn),
+    edit(
+        Char('g'),
+        ALT,
+        "Editing",
+        EditorAction::ToggleWritingSession,
+    ),
+    edit(Char('v'), ALT, "Editing", EditorAction::ToggleDictation),
+    edit_any(Backspace, "Editing", EditorAction::Backspace),
+    edit_any(Delete, "Editing", EditorAction::Delete),
+    edit_any(Enter, "Editing", EditorAction::Newline),
+    // Language server
+    edit(Char(' '), CTRL, "Language", EditorAction::TriggerCompletion),
+    edit(Char('k'), ALT, "Language", EditorAction::Hover),
+    bind(F(12), SHIFT, "Language", Command::FindReferences),
+    edit_any(F(12), "Language", EditorAction::GoToDefinition),
+    edit(F(8), SHIFT, "Language", EditorAction::PrevDiagnostic),
+    edit_any(F(8), "Language", EditorAction::NextDiagnostic),
+    edit(
+        Char('m'),
+        ALT,
+        "Language",
+        EditorAction::ToggleDiagnosticsPanel,
+    ),
+    edit(Char('.'), ALT, "Language", EditorAction::QuickFix),
+    // Movement; what terminals send for Cmd+Left/Right and Alt+Left/Right
+    // is bound as well
+    edit(
+        Char('a'),
+        CTRL,
+        "Movement",
+        EditorAction::MoveToBeginningOfLine,
+    ),
+    edit(Char('e'), CTRL, "Movement", EditorAction::MoveToEndOfLine),
+    edit(Left, CMD, "Movement", EditorAction::MoveToBeginningOfLine),
+    edit(Right, CMD, "Movement", EditorAction::MoveToEndOfLine),
+    edit(Left, ALT, "Movement", EditorAction::MoveWordLeft),
+    edit(Right, ALT, "Movement", EditorAction::MoveWordRight),
+    edit(Char('b'), ALT, "Movement", EditorAction::MoveWordLeft),
+    edit(Char('f'), ALT, "Movement", EditorAction::MoveWordRight),
+    edit(Char(')'), ALT, "Movement", EditorAction::MoveToNextSentence),
+    edit(Char('('), ALT, "Movement", EditorAction::MoveToPrevSentence),
+    edit(Char('t'), ALT, "Movement", EditorAction::JumpToChar),
+    edit(Char('y'), ALT, "Movement", EditorAction::JumpBeforeChar),
+    edit(Char('/'), ALT, "Movement", EditorAction::Leap),
+    // Selection
+    edit(Left, ALT_SHIFT, "Selection", EditorAction::SelectWordLeft),
+    edit(Right, ALT_SHIFT, "Selection", EditorAction::SelectWordRight),
+    edit(Char('l'), CTRL, "Selection", EditorAction::SelectLine),
+    edit(Char('i'), ALT, "Selection", EditorAction::SelectSentence),
+    edit(Char('p'), ALT, "Selection", EditorAction::SelectParagraph),
+    edit(Home, CTRL_SHIFT, "Selection", EditorAction::SelectToTop),
+    edit(End, CTRL_SHIFT, "Selection", EditorAction::SelectToBottom),
+    edit(Up, CMD_SHIFT, "Selection", EditorAction::SelectToTop),
+    edit(Down, CMD_SHIFT, "Selection", EditorAction::SelectToBottom),
+    edit(
+        Left,
+        CMD_SHIFT,
+        "Selection",
+        EditorAction::SelectToLineStart,
+    ),
+    edit(Right, CMD_SHIFT, "Selection", EditorAction::SelectToLineEnd),
+    edit(Home, SHIFT, "Selection", EditorAction::SelectToLineStart),
+    edit(End, SHIFT, "Selection", EditorAction::SelectToLineEnd),
+    // Alt+W, as Ctrl+W quits
+    edit(Char('w'), ALT, "Selection", EditorAction::ExpandSelection),
+    edit(Char('W'), ALT, "Selection", EditorAction::ShrinkSelection),
+    edit(Up, ALT_SHIFT, "Selection", EditorAction::SelectParent),
+    edit(
+        Down,
+        ALT_SHIFT,
+        "Selection",
+        EditorAction::SelectNextSibling,
+    ),
+    edit(Left, SHIFT, "Selection", EditorAction::SelectLeft),
+    edit(Right, SHIFT, "Selection", EditorAction::SelectRight),
+    edit(Up, SHIFT, "Selection", EditorAction::SelectUp),
+    edit(Down, SHIFT, "Selection", EditorAction::SelectDown),
+    // Plain movement, after the modified versions
+    edit(Left, NONE, "Movement", EditorAction::MoveLeft),
+    edit(Right, NONE, "Movement", EditorAction::MoveRight),
+    edit(Up, NONE, "Movement", EditorAction::MoveUp),
+    edit(Down, NONE, "Movement", EditorAction::MoveDown),
+    edit_any(Home, "Movement", EditorAction::MoveToBeginningOfLine),
+    edit_any(End, "Movement", EditorAction::MoveToEndOfLine),
+];
+
+/// The binding a key press triggers. Shift is left out for characters it
+/// already changed, since terminals disagree on whether to report it.
+pub fn lookup(key: &KeyEvent) -> Option<&'static Binding> {
+    let mut mods = key.modifiers;
+    if matches!(key.code, KeyCode::Char(c) if !c.is_lowercase()) {
+        mods.remove(KeyModifiers::SHIFT);
+    }
+    BINDINGS
+        .iter()
+        .find(|binding| binding.matches(key.code, mods))
+}
+
+/// What a key press does, if it is bound
+pub fn command(key: &KeyEvent) -> Option<Command> {
+    lookup(key).map(|binding| binding.command.clone())
+}
+
+/// The help overlay listing every binding, grouped by section
+pub fn help() -> HelpOverlay {
+    let mut bindings: Vec<&Binding> = BINDINGS.iter().collect();
+    let sections: Vec<&str> = BINDINGS.iter().fold(Vec::new(), |mut sections, binding| {
+        if !sections.contains(&binding.section) {
+            sections.push(binding.section);
+        }
+        sections
+    });
+    bindings.sort_by_key(|binding| sections.iter().position(|s| *s == binding.section));
+    HelpOverlay::new(
+        bindings
+            .into_iter()
+            .map(|binding| (binding.section, binding.command.label(), binding.key_name())),
+    )
+}
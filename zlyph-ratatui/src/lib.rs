@@ -0,0 +1,26 @@
+//! Zlyph's editor as a ratatui widget
+//!
+//! [`EditorWidgetState`] holds an [`EditorEngine`](zlyph_core::EditorEngine)
+//! with the scroll position, and turns crossterm key and mouse events into
+//! edits; [`EditorWidget`] draws it with the selection, cursor and syntax
+//! styles. The `zlyph` terminal editor is built on the two, and any ratatui
+//! application can use them for a multi-line input box or a config editor:
+//!
+//! ```no_run
+//! use zlyph_ratatui::{EditorWidget, EditorWidgetState};
+//! # fn draw(frame: &mut ratatui::Frame, key: crossterm::event::KeyEvent) {
+//! let mut state = EditorWidgetState::from_text("name = \"zlyph\"");
+//! state.handle_key(key);
+//! frame.render_stateful_widget(EditorWidget::default(), frame.area(), &mut state);
+//! # }
+//! ```
+//!
+//! [`keymap`] is the table of keys the editor answers, which the terminal
+//! editor also lists in its help, and [`panels`] draws zlyph-core's popups
+//! and find in files around the editor.
+
+pub mod keymap;
+pub mod panels;
+mod widget;
+
+pub use widget::{highlight_style, tone_color, EditorWidget, EditorWidgetState};
@@ -0,0 +1,635 @@
+//! zlyph-core's panels drawn over a frame: the popups that list recent
+//! files, notes, symbols, bookmarks and the like under the query being
+//! typed, and find in files along the bottom. Each takes the panel it shows
+//! and draws nothing past it, so the application decides when each is open.
+
+use ratatui::{
+    layout::Rect,
+    style::{Color, Style},
+    text::{Line, Span},
+    widgets::{Block, Borders, Clear, List, ListItem, ListState, Paragraph},
+    Frame,
+};
+use zlyph_core::bookmarks::BookmarksPanel;
+use zlyph_core::char_picker::CharPicker;
+use zlyph_core::diagnostics::Severity;
+use zlyph_core::history::HistoryBrowser;
+use zlyph_core::note_index::NoteSearch;
+use zlyph_core::notes::{self, BacklinksPanel};
+use zlyph_core::quick_switch::{self, QuickSwitch};
+use zlyph_core::search_panel::{SearchPanel, SearchRow};
+use zlyph_core::symbols::SymbolPanel;
+use zlyph_core::tags::TagIndex;
+use zlyph_core::task_runner::TaskRun;
+use zlyph_core::templates::{self, TemplatePicker};
+use zlyph_core::todos::{Kind, TodoPanel};
+
+/// A popup at most `max_width` wide, centred near the top of `area`
+fn popup(area: Rect, max_width: u16) -> Rect {
+    let width = area.width.saturating_sub(4).min(max_width);
+    let height = (area.height.saturating_sub(4)).min(16);
+    Rect {
+        x: area.x + (area.width - width) / 2,
+        y: area.y + 2.min(area.height),
+        width,
+        height,
+    }
+}
+
+/// Find-in-files query and results grouped by file, over the lower half.
+/// When replacing, each match shows its line before and after.
+pub fn render_search_panel(frame: &mut Frame, panel: &SearchPanel) {
+    let area = frame.area();
+    let height = (area.height / 2).max(7).min(area.height);
+    let rect = Rect {
+        x: area.x,
+        y: area.bottom() - height,
+        width: area.width,
+        height,
+    };
+
+    let kind = if panel.is_replacing() {
+        "Replace in files"
+    } else {
+        "Find in files"
+    };
+    let block =
+        Block::default()
+            .borders(Borders::ALL)
+            .title(format!(" {} · {} ", kind, panel.status()));
+    let inner = block.inner(rect);
+    frame.render_widget(Clear, rect);
+    frame.render_widget(block, rect);
+
+    let mut fields = vec![("Find: ", panel.query.as_str(), !panel.editing_replacement)];
+    if let Some(replacement) = panel.replacement.as_deref() {
+        fields.push(("Replace: ", replacement, panel.editing_replacement));
+    }
+    for (i, (label, text, active)) in fields.iter().enumerate() {
+        let field_area = Rect {
+            y: inner.y + i as u16,
+            height: 1,
+            ..inner
+        };
+        if field_area.y >= inner.bottom() {
+            return;
+        }
+        frame.render_widget(
+            Paragraph::new(Line::from(vec![
+                Span::styled(*label, Style::default().fg(Color::Cyan)),
+                Span::raw(*text),
+            ])),
+            field_area,
+        );
+        if *active && (panel.search().is_none() || panel.editing_replacement) {
+            let cursor_x = (label.len() + text.chars().count()) as u16;
+            frame.set_cursor_position((
+                field_area.x + cursor_x.min(field_area.width.saturating_sub(1)),
+                field_area.y,
+            ));
+        }
+    }
+
+    let mut selected = None;
+    let items: Vec<ListItem> = panel
+        .rows()
+        .into_iter()
+        .enumerate()
+        .map(|(i, row)| match row {
+            SearchRow::File(path) => ListItem::new(Span::styled(
+                path.display().to_string(),
+                Style::default().fg(Color::Yellow),
+            )),
+            SearchRow::Match { index, row, text } => {
+                if index == panel.selected {
+                    selected = Some(i);
+                }
+                ListItem::new(format!("  {:>4}: {}", row + 1, text.trim()))
+            }
+            SearchRow::Change {
+                index,
+                row,
+                before,
+                after,
+                included,
+            } => {
+                if index == panel.selected {
+                    selected = Some(i);
+                }
+                let mark = if included { "[x]" } else { "[ ]" };
+                let dim = Style::default().fg(Color::DarkGray);
+                let (removed, added) = if included {
+                    (
+                        Style::default().fg(Color::Red),
+                        Style::default().fg(Color::Green),
+                    )
+                } else {
+                    (dim, dim)
+                };
+                ListItem::new(vec![
+                    Line::from(vec![
+                        Span::raw(format!("  {} {:>4}: ", mark, row + 1)),
+                        Span::styled(format!("- {}", before.trim()), removed),
+                    ]),
+                    Line::from(vec![
+                        Span::raw(" ".repeat(12)),
+                        Span::styled(format!("+ {}", after.trim()), added),
+                    ]),
+                ])
+            }
+        })
+        .collect();
+
+    let list = List::new(items)
+        .style(Style::default().fg(Color::White))
+        .highlight_style(Style::default().bg(Color::DarkGray));
+    let mut list_state = ListState::default().with_selected(selected);
+    let fields_height = fields.len() as u16;
+    let list_area = Rect {
+        y: inner.y + fields_height,
+        height: inner.height.saturating_sub(fields_height),
+        ..inner
+    };
+    frame.render_stateful_widget(list, list_area, &mut list_state);
+}
+
+/// Recent files centred near the top, under the query being typed
+pub fn render_quick_switch(frame: &mut Frame, switch: &QuickSwitch) {
+    let rect = popup(frame.area(), 72);
+
+    let title = if switch.projects {
+        " Projects "
+    } else {
+        " Recent files "
+    };
+    let block = Block::default().borders(Borders::ALL).title(title);
+    let inner = block.inner(rect);
+    frame.render_widget(Clear, rect);
+    frame.render_widget(block, rect);
+    if inner.height == 0 {
+        return;
+    }
+
+    let query_area = Rect { height: 1, ..inner };
+    frame.render_widget(
+        Paragraph::new(Line::from(vec![
+            Span::styled("> ", Style::default().fg(Color::Cyan)),
+            Span::raw(switch.query.as_str()),
+        ])),
+        query_area,
+    );
+    let cursor_x = 2 + switch.query.chars().count() as u16;
+    frame.set_cursor_position((
+        query_area.x + cursor_x.min(query_area.width.saturating_sub(1)),
+        query_area.y,
+    ));
+
+    let items: Vec<ListItem> = switch
+        .matches()
+        .into_iter()
+        .map(|path| {
+            let (name, dir) = quick_switch::display_name(path);
+            ListItem::new(Line::from(vec![
+                Span::raw(name),
+                Span::styled(format!("  {}", dir), Style::default().fg(Color::DarkGray)),
+            ]))
+        })
+        .collect();
+    let list_area = Rect {
+        y: inner.y + 1,
+        height: inner.height - 1,
+        ..inner
+    };
+    let list = List::new(items).highlight_style(Style::default().bg(Color::DarkGray));
+    let mut list_state = ListState::default().with_selected(Some(switch.selected));
+    frame.render_stateful_widget(list, list_area, &mut list_state);
+}
+
+/// The current file's local history, newest first
+pub fn render_history_browser(frame: &mut Frame, browser: &HistoryBrowser) {
+    let rect = popup(frame.area(), 48);
+
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .title(" Local history · Enter compare, r restore ");
+    let inner = block.inner(rect);
+    frame.render_widget(Clear, rect);
+    frame.render_widget(block, rect);
+
+    let now = std::time::SystemTime::now();
+    let items: Vec<ListItem> = browser
+        .snapshots()
+        .iter()
+        .map(|snapshot| {
+            ListItem::new(Line::from(vec![
+                Span::raw(snapshot.age_label(now)),
+                Span::styled(
+                    format!("  {}", snapshot.size_label()),
+                    Style::default().fg(Color::DarkGray),
+                ),
+            ]))
+        })
+        .collect();
+    let list = List::new(items).highlight_style(Style::default().bg(Color::DarkGray));
+    let mut list_state = ListState::default().with_selected(Some(browser.selected));
+    frame.render_stateful_widget(list, inner, &mut list_state);
+}
+
+/// Notes matching the query, best first, with the line that matched
+pub fn render_note_search(frame: &mut Frame, search: &NoteSearch) {
+    let rect = popup(frame.area(), 72);
+
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .title(format!(" {} ", search.status()));
+    let inner = block.inner(rect);
+    frame.render_widget(Clear, rect);
+    frame.render_widget(block, rect);
+    if inner.height == 0 {
+        return;
+    }
+
+    let query_area = Rect { height: 1, ..inner };
+    frame.render_widget(
+        Paragraph::new(Line::from(vec![
+            Span::styled("> ", Style::default().fg(Color::Cyan)),
+            Span::raw(search.query.as_str()),
+        ])),
+        query_area,
+    );
+    let cursor_x = 2 + search.query.chars().count() as u16;
+    frame.set_cursor_position((
+        query_area.x + cursor_x.min(query_area.width.saturating_sub(1)),
+        query_area.y,
+    ));
+
+    let items: Vec<ListItem> = search
+        .hits()
+        .iter()
+        .map(|hit| {
+            ListItem::new(Line::from(vec![
+                Span::styled(
+                    format!("{}  ", notes::note_name(&hit.path)),
+                    Style::default().fg(Color::Cyan),
+                ),
+                Span::styled(hit.snippet.clone(), Style::default().fg(Color::DarkGray)),
+            ]))
+        })
+        .collect();
+    let list = List::new(items).highlight_style(Style::default().bg(Color::DarkGray));
+    let mut list_state = ListState::default().with_selected(Some(search.selected));
+    let list_area = Rect {
+        y: inner.y + 1,
+        height: inner.height - 1,
+        ..inner
+    };
+    frame.render_stateful_widget(list, list_area, &mut list_state);
+}
+
+/// Tags used across the notes, with how often and in how many notes
+pub fn render_tags(frame: &mut Frame, index: &TagIndex) {
+    let rect = popup(frame.area(), 48);
+
+    let title = match (index.is_done(), index.tags().len()) {
+        (false, _) => " Tags · indexing… ".to_string(),
+        (true, 0) => " No tags ".to_string(),
+        (true, count) => format!(" {} tags ", count),
+    };
+    let block = Block::default().borders(Borders::ALL).title(title);
+    let inner = block.inner(rect);
+    frame.render_widget(Clear, rect);
+    frame.render_widget(block, rect);
+
+    let items: Vec<ListItem> = index
+        .tags()
+        .iter()
+        .map(|tag| {
+            ListItem::new(Line::from(vec![
+                Span::raw(format!("#{}", tag.name)),
+                Span::styled(
+                    format!("  {} in {} notes", tag.count, tag.files),
+                    Style::default().fg(Color::DarkGray),
+                ),
+            ]))
+        })
+        .collect();
+    let list = List::new(items).highlight_style(Style::default().bg(Color::DarkGray));
+    let mut list_state = ListState::default().with_selected(Some(index.selected));
+    frame.render_stateful_widget(list, inner, &mut list_state);
+}
+
+/// TODO, FIXME and NOTE comments matching the query, in the buffer or
+/// across the notes
+pub fn render_todos(frame: &mut Frame, panel: &TodoPanel) {
+    let rect = popup(frame.area(), 72);
+
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .title(format!(" {} · Tab switches ", panel.status()));
+    let inner = block.inner(rect);
+    frame.render_widget(Clear, rect);
+    frame.render_widget(block, rect);
+    if inner.height == 0 {
+        return;
+    }
+
+    let query_area = Rect { height: 1, ..inner };
+    frame.render_widget(
+        Paragraph::new(Line::from(vec![
+            Span::styled("> ", Style::default().fg(Color::Cyan)),
+            Span::raw(panel.query.as_str()),
+        ])),
+        query_area,
+    );
+    let cursor_x = 2 + panel.query.chars().count() as u16;
+    frame.set_cursor_position((
+        query_area.x + cursor_x.min(query_area.width.saturating_sub(1)),
+        query_area.y,
+    ));
+
+    let items: Vec<ListItem> = panel
+        .matches()
+        .into_iter()
+        .map(|item| {
+            let place = match &item.path {
+                Some(path) => format!("{}:{}  ", notes::note_name(path), item.row + 1),
+                None => format!("{:>4}  ", item.row + 1),
+            };
+            let color = match item.kind {
+                Kind::Fixme => Color::Red,
+                Kind::Todo | Kind::Task => Color::Yellow,
+                Kind::Note => Color::Blue,
+            };
+            ListItem::new(Line::from(vec![
+                Span::styled(place, Style::default().fg(Color::Cyan)),
+                Span::styled(
+                    format!("{:<5} ", item.kind.label()),
+                    Style::default().fg(color),
+                ),
+                Span::raw(item.text.clone()),
+            ]))
+        })
+        .collect();
+    let list = List::new(items).highlight_style(Style::default().bg(Color::DarkGray));
+    let mut list_state = ListState::default().with_selected(Some(panel.selected));
+    let list_area = Rect {
+        y: inner.y + 1,
+        height: inner.height - 1,
+        ..inner
+    };
+    frame.render_stateful_widget(list, list_area, &mut list_state);
+}
+
+/// The last task's errors, or the end of what it printed if it reported
+/// none
+pub fn render_errors(frame: &mut Frame, run: &TaskRun) {
+    let rect = popup(frame.area(), 96);
+
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .title(format!(" {} · Tab runs the next task ", run.status()));
+    let inner = block.inner(rect);
+    frame.render_widget(Clear, rect);
+    frame.render_widget(block, rect);
+    if inner.height == 0 {
+        return;
+    }
+
+    // Without errors to list, the end of what the task printed shows
+    // what happened
+    if run.problems().is_empty() {
+        let output: Vec<Line> = run
+            .output()
+            .lines()
+            .rev()
+            .take(inner.height as usize)
+            .collect::<Vec<_>>()
+            .into_iter()
+            .rev()
+            .map(|line| Line::styled(line.to_string(), Style::default().fg(Color::DarkGray)))
+            .collect();
+        frame.render_widget(Paragraph::new(output), inner);
+        return;
+    }
+
+    let items: Vec<ListItem> = run
+        .problems()
+        .iter()
+        .map(|problem| {
+            let path = problem
+                .path
+                .strip_prefix(run.root())
+                .unwrap_or(&problem.path);
+            let place = format!(
+                "{}:{}:{}  ",
+                path.display(),
+                problem.row + 1,
+                problem.column + 1
+            );
+            let color = match problem.severity {
+                Severity::Error => Color::Red,
+                Severity::Warning => Color::Yellow,
+                Severity::Information | Severity::Hint => Color::Blue,
+            };
+            ListItem::new(Line::from(vec![
+                Span::styled(place, Style::default().fg(Color::Cyan)),
+                Span::styled(
+                    format!("{:<8}", problem.severity.label()),
+                    Style::default().fg(color),
+                ),
+                Span::raw(problem.message.clone()),
+            ]))
+        })
+        .collect();
+    let list = List::new(items).highlight_style(Style::default().bg(Color::DarkGray));
+    let mut list_state = ListState::default().with_selected(Some(run.selected));
+    frame.render_stateful_widget(list, inner, &mut list_state);
+}
+
+/// The buffer's symbols matching the query, in order
+pub fn render_symbol_palette(frame: &mut Frame, palette: &SymbolPanel) {
+    let rect = popup(frame.area(), 72);
+
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .title(format!(" Go to symbol · {} ", palette.status()));
+    let inner = block.inner(rect);
+    frame.render_widget(Clear, rect);
+    frame.render_widget(block, rect);
+    if inner.height == 0 {
+        return;
+    }
+
+    let query_area = Rect { height: 1, ..inner };
+    frame.render_widget(
+        Paragraph::new(Line::from(vec![
+            Span::styled("> ", Style::default().fg(Color::Cyan)),
+            Span::raw(palette.query.as_str()),
+        ])),
+        query_area,
+    );
+    let cursor_x = 2 + palette.query.chars().count() as u16;
+    frame.set_cursor_position((
+        query_area.x + cursor_x.min(query_area.width.saturating_sub(1)),
+        query_area.y,
+    ));
+
+    let items: Vec<ListItem> = palette
+        .matches()
+        .into_iter()
+        .map(|symbol| {
+            ListItem::new(Line::from(vec![
+                Span::styled(
+                    format!("{:>4}  ", symbol.row + 1),
+                    Style::default().fg(Color::Cyan),
+                ),
+                Span::styled(
+                    format!("{:<5} ", symbol.kind.label()),
+                    Style::default().fg(Color::DarkGray),
+                ),
+                Span::raw(symbol.name.clone()),
+            ]))
+        })
+        .collect();
+    let list = List::new(items).highlight_style(Style::default().bg(Color::DarkGray));
+    let mut list_state = ListState::default().with_selected(Some(palette.selected));
+    let list_area = Rect {
+        y: inner.y + 1,
+        height: inner.height - 1,
+        ..inner
+    };
+    frame.render_stateful_widget(list, list_area, &mut list_state);
+}
+
+/// Templates to create a note from
+pub fn render_template_picker(frame: &mut Frame, picker: &TemplatePicker) {
+    let rect = popup(frame.area(), 48);
+
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .title(" New note from template ");
+    let inner = block.inner(rect);
+    frame.render_widget(Clear, rect);
+    frame.render_widget(block, rect);
+
+    let items: Vec<ListItem> = picker
+        .templates()
+        .iter()
+        .map(|path| ListItem::new(templates::title_of(path)))
+        .collect();
+    let list = List::new(items).highlight_style(Style::default().bg(Color::DarkGray));
+    let mut list_state = ListState::default().with_selected(Some(picker.selected));
+    frame.render_stateful_widget(list, inner, &mut list_state);
+}
+
+/// Characters and emoji matching the query, with their names and
+/// shortcodes
+pub fn render_char_picker(frame: &mut Frame, picker: &CharPicker) {
+    let rect = popup(frame.area(), 60);
+
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .title(" Insert character ");
+    let inner = block.inner(rect);
+    frame.render_widget(Clear, rect);
+    frame.render_widget(block, rect);
+    if inner.height == 0 {
+        return;
+    }
+
+    let query_area = Rect { height: 1, ..inner };
+    frame.render_widget(
+        Paragraph::new(Line::from(vec![
+            Span::styled("> ", Style::default().fg(Color::Cyan)),
+            Span::raw(picker.query.as_str()),
+        ])),
+        query_area,
+    );
+    let cursor_x = 2 + picker.query.chars().count() as u16;
+    frame.set_cursor_position((
+        query_area.x + cursor_x.min(query_area.width.saturating_sub(1)),
+        query_area.y,
+    ));
+
+    let items: Vec<ListItem> = picker
+        .matches()
+        .iter()
+        .map(|symbol| {
+            let mut spans = vec![
+                Span::raw(format!("{}  ", symbol.text)),
+                Span::raw(symbol.name.clone()),
+            ];
+            if let Some(shortcode) = &symbol.shortcode {
+                spans.push(Span::styled(
+                    format!("  :{}:", shortcode),
+                    Style::default().fg(Color::DarkGray),
+                ));
+            }
+            ListItem::new(Line::from(spans))
+        })
+        .collect();
+    let list = List::new(items).highlight_style(Style::default().bg(Color::DarkGray));
+    let mut list_state = ListState::default().with_selected(Some(picker.selected));
+    let list_area = Rect {
+        y: inner.y + 1,
+        height: inner.height - 1,
+        ..inner
+    };
+    frame.render_stateful_widget(list, list_area, &mut list_state);
+}
+
+/// Lines in other notes that link to the current one
+pub fn render_backlinks(frame: &mut Frame, panel: &BacklinksPanel) {
+    let rect = popup(frame.area(), 72);
+
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .title(format!(" Linking to {} ", panel.note));
+    let inner = block.inner(rect);
+    frame.render_widget(Clear, rect);
+    frame.render_widget(block, rect);
+
+    let items: Vec<ListItem> = panel
+        .entries()
+        .iter()
+        .map(|entry| {
+            ListItem::new(Line::from(vec![
+                Span::styled(
+                    format!("{}:{}  ", notes::note_name(&entry.path), entry.row + 1),
+                    Style::default().fg(Color::Cyan),
+                ),
+                Span::raw(entry.text.clone()),
+            ]))
+        })
+        .collect();
+    let list = List::new(items).highlight_style(Style::default().bg(Color::DarkGray));
+    let mut list_state = ListState::default().with_selected(Some(panel.selected));
+    frame.render_stateful_widget(list, inner, &mut list_state);
+}
+
+/// The buffer's bookmarked lines
+pub fn render_bookmarks(frame: &mut Frame, panel: &BookmarksPanel) {
+    let rect = popup(frame.area(), 72);
+
+    let block = Block::default().borders(Borders::ALL).title(" Bookmarks ");
+    let inner = block.inner(rect);
+    frame.render_widget(Clear, rect);
+    frame.render_widget(block, rect);
+
+    let items: Vec<ListItem> = panel
+        .entries()
+        .iter()
+        .map(|entry| {
+            ListItem::new(Line::from(vec![
+                Span::styled(
+                    format!("{:>4}  ", entry.row + 1),
+                    Style::default().fg(Color::Cyan),
+                ),
+                Span::raw(entry.text.clone()),
+            ]))
+        })
+        .collect();
+    let list = List::new(items).highlight_style(Style::default().bg(Color::DarkGray));
+    let mut list_state = ListState::default().with_selected(Some(panel.selected));
+    frame.render_stateful_widget(list, inner, &mut list_state);
+}
@@ -0,0 +1,892 @@
+use crate::keymap::{self, Command};
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers, MouseButton, MouseEvent, MouseEventKind};
+use ratatui::{
+    buffer::Buffer,
+    layout::Rect,
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+    widgets::{Block, StatefulWidget, Widget},
+};
+use std::collections::BTreeMap;
+use std::ops::Range;
+use std::sync::mpsc::{Receiver, TryRecvError};
+use std::time::{Duration, Instant};
+use zlyph_core::config::CursorStyle;
+use zlyph_core::decorations::{LineDecoration, Tone};
+use zlyph_core::highlight::{self, Highlighter, LineHighlights};
+use zlyph_core::long_lines;
+use zlyph_core::multi_cursor;
+use zlyph_core::{ChangeEvent, EditorAction, EditorEngine};
+
+/// Lines moved by one step of the mouse wheel
+const SCROLL_LINES: u16 = 3;
+
+/// The buffer being edited and how it is scrolled
+pub struct EditorWidgetState {
+    pub engine: EditorEngine,
+    /// First line shown
+    pub scroll_offset: u16,
+    /// Where the text was last drawn, for placing mouse clicks
+    area: Rect,
+    /// Last key press or click, which holds a blinking cursor solid
+    last_input: Instant,
+    /// The engine's changes since they were last looked at, telling which
+    /// styled lines are stale
+    changes: Receiver<ChangeEvent>,
+    /// The lines in view as last styled, by row, less those edits have made
+    /// stale; only rows in view are kept, however long the text
+    styled: BTreeMap<usize, StyledLine>,
+    /// The engine changed since the last draw
+    changed: bool,
+    /// Scroll offset and cursor look at the last draw
+    drawn: Option<(u16, Style)>,
+}
+
+/// A line styled for drawing, kept until its text or look changes
+struct StyledLine {
+    look: LineLook,
+    highlights: Option<LineHighlights>,
+    line: Line<'static>,
+}
+
+/// Everything besides its text and highlights that a line is styled by
+#[derive(Clone, PartialEq)]
+struct LineLook {
+    /// Selected byte range of the line
+    selection: Option<(usize, usize)>,
+    /// Column of the cursor, if it is on the line
+    cursor: Option<usize>,
+    /// Byte ranges selected by other cursors, and the columns of those on
+    /// the line
+    extra_selected: Vec<Range<usize>>,
+    extra_cursors: Vec<usize>,
+    cursor_style: Style,
+    selection_style: Style,
+    /// Protected byte ranges of the line, drawn dimmed
+    protected: Vec<Range<usize>>,
+    /// Virtual text before byte columns of the line, in order
+    inline: Vec<(usize, String, Style)>,
+    /// Byte ranges tinted by decorations
+    tinted: Vec<(Range<usize>, Style)>,
+    /// Virtual text after the line's end
+    end_of_line: Vec<(String, Style)>,
+}
+
+impl Default for EditorWidgetState {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl EditorWidgetState {
+    pub fn new() -> Self {
+        Self::with_engine(EditorEngine::new())
+    }
+
+    pub fn with_engine(mut engine: EditorEngine) -> Self {
+        let changes = engine.subscribe();
+        Self {
+            engine,
+            scroll_offset: 0,
+            area: Rect::default(),
+            last_input: Instant::now(),
+            changes,
+            styled: BTreeMap::new(),
+            changed: true,
+            drawn: None,
+        }
+    }
+
+    pub fn from_text(text: &str) -> Self {
+        let mut state = Self::new();
+        state.set_text(text);
+        state
+    }
+
+    pub fn text(&self) -> String {
+        self.engine.state().lines.join("\n")
+    }
+
+    /// Replace the whole buffer, with the cursor at the start
+    pub fn set_text(&mut self, text: &str) {
+        self.engine.load_text(text);
+        self.scroll_offset = 0;
+    }
+
+    /// Where the text was drawn last, inside any block around it
+    pub fn area(&self) -> Rect {
+        self.area
+    }
+
+    /// Note a key press or click, so a blinking cursor shows while typing
+    pub fn mark_input(&mut self) {
+        self.last_input = Instant::now();
+    }
+
+    /// How long until a blinking cursor next shows or hides, for an app
+    /// that sleeps between events; `None` when the widget doesn't blink it
+    pub fn next_blink(&self) -> Option<Duration> {
+        if self.cursor_style() == CursorStyle::Bar {
+            // The terminal's own cursor does its blinking
+            return None;
+        }
+        zlyph_core::cursor::next_blink(&self.engine.config().cursor, self.last_input.elapsed())
+    }
+
+    /// Whether the text, cursor, selection, scrolling or cursor blink
+    /// changed since the widget was last drawn, so the app can skip
+    /// drawing frames that would look the same
+    pub fn needs_redraw(&mut self) -> bool {
+        self.collect_changes();
+        self.changed || self.drawn != Some((self.scroll_offset, self.cursor_cell_style()))
+    }
+
+    /// Forget the styled lines that the engine's edits since the last call
+    /// touched, shifting the rest to follow inserted or deleted lines
+    fn collect_changes(&mut self) {
+        loop {
+            match self.changes.try_recv() {
+                Ok(ChangeEvent::Replaced { range, text }) => {
+                    let after = self.styled.split_off(&(range.end.row + 1));
+                    self.styled.split_off(&range.start.row);
+                    let added = text.matches('\n').count();
+                    let removed = range.end.row - range.start.row;
+                    self.styled.extend(
+                        after
+                            .into_iter()
+                            .map(|(row, line)| (row + added - removed, line)),
+                    );
+                }
+                Ok(ChangeEvent::Edited | ChangeEvent::Loaded { .. }) => self.styled.clear(),
+                Ok(_) => {}
+                Err(TryRecvError::Empty) => return,
+                Err(TryRecvError::Disconnected) => {
+                    // `engine` was replaced with another one
+                    self.changes = self.engine.subscribe();
+                    self.styled.clear();
+                    self.changed = true;
+                    return;
+                }
+            }
+            self.changed = true;
+        }
+    }
+
+    /// The edit a key press stands for. Keys bound to commands the
+    /// application carries out, such as opening files, give `None`; see
+    /// [`keymap::command`] for those.
+    pub fn translate_key(&self, event: KeyEvent) -> Option<EditorAction> {
+        match keymap::command(&event) {
+            Some(Command::Edit(action)) => Some(action),
+            Some(_) => None,
+            // Regular character input
+            None => match (event.code, event.modifiers) {
+                (KeyCode::Char(c), KeyModifiers::NONE)
+                | (KeyCode::Char(c), KeyModifiers::SHIFT) => Some(EditorAction::TypeCharacter(c)),
+                _ => None,
+            },
+        }
+    }
+
+    /// Apply a key press. Edits go to the engine; what only the application
+    /// can do, such as quitting, zooming, following links or the commands
+    /// of the keymap, is returned instead.
+    pub fn handle_key(&mut self, event: KeyEvent) -> Option<Command> {
+        self.mark_input();
+        let action = match keymap::command(&event) {
+            Some(Command::Edit(action)) => action,
+            Some(command) => return Some(command),
+            None => self.translate_key(event)?,
+        };
+        if matches!(
+            action,
+            EditorAction::Quit
+                | EditorAction::IncreaseFontSize
+                | EditorAction::DecreaseFontSize
+                | EditorAction::ResetFontSize
+                | EditorAction::OpenLinkUnderCursor
+        ) {
+            return Some(Command::Edit(action));
+        }
+        self.engine.handle_action(action);
+        self.ensure_cursor_visible(self.area.height);
+        None
+    }
+
+    /// The edit a click or drag in the text stands for
+    pub fn translate_mouse(&self, event: MouseEvent) -> Option<EditorAction> {
+        let (row, column) = self.screen_to_document(event.column, event.row)?;
+        let (row, column) = self.clamp_to_document(row, column);
+        match event.kind {
+            // SetCursorPosition rather than StartSelection, so the cursor
+            // shows; dragging starts the selection
+            MouseEventKind::Down(MouseButton::Left) => {
+                Some(EditorAction::SetCursorPosition { row, column })
+            }
+            MouseEventKind::Drag(MouseButton::Left) => {
+                Some(EditorAction::ExtendSelection { row, column })
+            }
+            _ => None,
+        }
+    }
+
+    /// Apply a click, drag or wheel turn over the text; returns whether it
+    /// was one
+    pub fn handle_mouse(&mut self, event: MouseEvent) -> bool {
+        let inside = event.column >= self.area.x
+            && event.column < self.area.right()
+            && event.row >= self.area.y
+            && event.row < self.area.bottom();
+        match event.kind {
+            MouseEventKind::ScrollUp if inside => self.scroll(-1),
+            MouseEventKind::ScrollDown if inside => self.scroll(1),
+            _ => {
+                let Some(action) = self.translate_mouse(event) else {
+                    return false;
+                };
+                self.mark_input();
+                self.engine.handle_action(action);
+                self.ensure_cursor_visible(self.area.height);
+            }
+        }
+        true
+    }
+
+    /// Scroll a wheel step up for a negative `direction`, down otherwise
+    pub fn scroll(&mut self, direction: i16) {
+        if direction < 0 {
+            self.scroll_offset = self.scroll_offset.saturating_sub(SCROLL_LINES);
+        } else {
+            let max_scroll = self.engine.state().lines.len().saturating_sub(1) as u16;
+            self.scroll_offset = (self.scroll_offset + SCROLL_LINES).min(max_scroll);
+        }
+    }
+
+    pub fn ensure_cursor_visible(&mut self, visible_height: u16) {
+        let hidden = self.engine.collapsed_frontmatter();
+        let cursor_row = display_row(&hidden, self.engine.state().cursor.row) as u16;
+        let scroll_offset = display_row(&hidden, self.scroll_offset as usize) as u16;
+        // Scroll margin, reduced when the screen is too short to honour it
+        let padding =
+            (self.engine.config().scroll.margin as u16).min(visible_height.saturating_sub(1) / 2);
+
+        // Scroll up if cursor is above visible area
+        let mut top = scroll_offset;
+        if cursor_row < top + padding {
+            top = cursor_row.saturating_sub(padding);
+        }
+
+        // Scroll down if cursor is below visible area
+        if cursor_row >= top + visible_height.saturating_sub(padding) {
+            top = cursor_row.saturating_sub(visible_height.saturating_sub(padding + 1));
+        }
+        if top != scroll_offset {
+            self.scroll_offset = document_row(&hidden, top as usize) as u16;
+        }
+    }
+
+    /// The buffer position under a screen cell, which may lie past the end
+    /// of a line or of the text; `None` outside the area the text was drawn in
+    pub fn screen_to_document(&self, screen_col: u16, screen_row: u16) -> Option<(usize, usize)> {
+        let area = self.area;
+        if screen_col < area.x || screen_col >= area.right() {
+            return None;
+        }
+        if screen_row < area.y || screen_row >= area.bottom() {
+            return None;
+        }
+        let hidden = self.engine.collapsed_frontmatter();
+        let top = display_row(&hidden, self.scroll_offset as usize);
+        let row = document_row(&hidden, (screen_row - area.y) as usize + top);
+        if let Some(window) = self.long_line_window(row) {
+            let shown = &self.engine.state().lines[row][window.clone()];
+            let column = shown
+                .char_indices()
+                .nth((screen_col - area.x) as usize)
+                .map_or(shown.len(), |(at, _)| at);
+            return Some((row, window.start + column));
+        }
+        // Virtual text is stepped over; a click on some lands at its column
+        let mut column = (screen_col - area.x) as usize;
+        let mut shift = 0;
+        for (at, text) in self.inline_text(row) {
+            if column < at + shift {
+                break;
+            }
+            let width = text.chars().count();
+            if column < at + shift + width {
+                return Some((row, at));
+            }
+            shift += width;
+        }
+        column -= shift;
+        Some((row, column))
+    }
+
+    /// The part of line `row` in view, when it is long enough to be shown
+    /// a window at a time; see [`long_lines`]
+    fn long_line_window(&self, row: usize) -> Option<Range<usize>> {
+        let state = self.engine.state();
+        let line = state
+            .lines
+            .get(row)
+            .filter(|line| long_lines::is_long(line))?;
+        let column = if row == state.cursor.row {
+            state.cursor.column
+        } else {
+            0
+        };
+        Some(long_lines::window(line, column, self.area.width as usize))
+    }
+
+    /// The inline virtual text on line `row`, by byte column
+    fn inline_text(&self, row: usize) -> Vec<(usize, &str)> {
+        self.engine
+            .line_decorations(row)
+            .into_iter()
+            .filter_map(|decoration| match decoration {
+                LineDecoration::Inline { column, text, .. } => Some((column, text)),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// Clamp document position to valid bounds
+    pub fn clamp_to_document(&self, row: usize, column: usize) -> (usize, usize) {
+        let state = self.engine.state();
+        let row = row.min(state.lines.len().saturating_sub(1));
+        let column = column.min(state.lines[row].len());
+        (row, column)
+    }
+
+    /// The screen cell of the cursor, if it is in view, for placing the
+    /// terminal's own cursor with `Frame::set_cursor_position`
+    pub fn cursor_screen_position(&self) -> Option<(u16, u16)> {
+        let state = self.engine.state();
+        let hidden = self.engine.collapsed_frontmatter();
+        let top = display_row(&hidden, self.scroll_offset as usize);
+        let screen_row = (display_row(&hidden, state.cursor.row) as u16).checked_sub(top as u16)?;
+        let line = &state.lines[state.cursor.row];
+        let column = state.cursor.column.min(line.len());
+        let screen_col = match self.long_line_window(state.cursor.row) {
+            Some(window) => line[window.start..column].chars().count() as u16,
+            None => {
+                (line[..column].chars().count()
+                    + virtual_width(&self.inline_text(state.cursor.row), column))
+                    as u16
+            }
+        };
+        (screen_row < self.area.height && screen_col < self.area.width)
+            .then_some((self.area.x + screen_col, self.area.y + screen_row))
+    }
+
+    pub fn cursor_style(&self) -> CursorStyle {
+        self.engine
+            .config()
+            .cursor
+            .style
+            .unwrap_or(CursorStyle::Block)
+    }
+
+    /// Style for the cell under the cursor: reversed for a block, underlined
+    /// for an underline, and plain while blinked off or when the terminal's
+    /// own cursor draws a bar
+    fn cursor_cell_style(&self) -> Style {
+        let config = &self.engine.config().cursor;
+        if !zlyph_core::cursor::is_visible(config, self.last_input.elapsed()) {
+            return Style::default();
+        }
+        match self.cursor_style() {
+            CursorStyle::Block => Style::default().add_modifier(Modifier::REVERSED),
+            CursorStyle::Underline => Style::default().add_modifier(Modifier::UNDERLINED),
+            CursorStyle::Bar => Style::default(),
+        }
+    }
+}
+
+/// Draws an [`EditorWidgetState`]'s text with its selection and cursor
+pub struct EditorWidget<'a> {
+    block: Option<Block<'a>>,
+    style: Style,
+    selection_style: Style,
+    highlighter: Option<&'a Highlighter>,
+    focused: bool,
+}
+
+impl Default for EditorWidget<'_> {
+    fn default() -> Self {
+        Self {
+            block: None,
+            style: Style::default().fg(Color::White),
+            selection_style: Style::default().bg(Color::DarkGray),
+            highlighter: None,
+            focused: true,
+        }
+    }
+}
+
+impl<'a> EditorWidget<'a> {
+    pub fn block(mut self, block: Block<'a>) -> Self {
+        self.block = Some(block);
+        self
+    }
+
+    /// Style of the text
+    pub fn style(mut self, style: Style) -> Self {
+        self.style = style;
+        self
+    }
+
+    pub fn selection_style(mut self, style: Style) -> Self {
+        self.selection_style = style;
+        self
+    }
+
+    /// Syntax styles to lay under the text, kept current by the caller
+    pub fn highlighter(mut self, highlighter: &'a Highlighter) -> Self {
+        self.highlighter = Some(highlighter);
+        self
+    }
+
+    /// Whether to draw the cursor; off for an editor among other inputs
+    /// that does not have focus
+    pub fn focused(mut self, focused: bool) -> Self {
+        self.focused = focused;
+        self
+    }
+}
+
+impl StatefulWidget for EditorWidget<'_> {
+    type State = EditorWidgetState;
+
+    fn render(self, area: Rect, buf: &mut Buffer, state: &mut EditorWidgetState) {
+        let area = match self.block {
+            Some(block) => {
+                let inner = block.inner(area);
+                block.render(area, buf);
+                inner
+            }
+            None => area,
+        };
+        state.area = area;
+        state.collect_changes();
+
+        let cursor_style = if self.focused {
+            state.cursor_cell_style()
+        } else {
+            Style::default()
+        };
+        buf.set_style(area, self.style);
+
+        let doc = state.engine.state();
+        let hidden = state.engine.collapsed_frontmatter();
+        let top = display_row(&hidden, state.scroll_offset as usize);
+        let rows = (top..top + area.height as usize)
+            .map(|shown| document_row(&hidden, shown))
+            .take_while(|&row| row < doc.lines.len());
+        // Collapsed frontmatter shows its summary on its first line
+        let summary = hidden
+            .as_ref()
+            .and_then(|hidden| Some((hidden.start - 1, state.engine.frontmatter()?.summary())));
+        let mut styled_rows = std::mem::take(&mut state.styled);
+        for (y, row) in (area.y..).zip(rows) {
+            if let Some((_, summary)) = summary.as_ref().filter(|(at, _)| *at == row) {
+                let dim = Style::default().add_modifier(Modifier::DIM);
+                buf.set_line(area.x, y, &Line::styled(summary.clone(), dim), area.width);
+                continue;
+            }
+            let look = LineLook::of(&state.engine, row, cursor_style, self.selection_style);
+            if let Some(window) = state.long_line_window(row) {
+                let line = styled_line(
+                    &doc.lines[row][window.clone()],
+                    &look.windowed(&window),
+                    None,
+                );
+                buf.set_line(area.x, y, &line, area.width);
+                continue;
+            }
+            let highlights = self.highlighter.and_then(|h| h.line(row));
+            let styled = styled_rows
+                .remove(&row)
+                .filter(|styled| styled.look == look && styled.highlights.as_ref() == highlights)
+                .unwrap_or_else(|| StyledLine {
+                    line: styled_line(&doc.lines[row], &look, highlights),
+                    look,
+                    highlights: highlights.cloned(),
+                });
+            buf.set_line(area.x, y, &styled.line, area.width);
+            state.styled.insert(row, styled);
+        }
+
+        state.changed = false;
+        state.drawn = Some((state.scroll_offset, state.cursor_cell_style()));
+    }
+}
+
+/// Where line `row` comes among the lines shown, leaving out the `hidden`
+/// rows of collapsed frontmatter
+fn display_row(hidden: &Option<Range<usize>>, row: usize) -> usize {
+    match hidden {
+        Some(hidden) if row >= hidden.end => row - hidden.len(),
+        Some(hidden) if row > hidden.start => hidden.start,
+        _ => row,
+    }
+}
+
+/// The line shown `display_row` lines down, the inverse of [`display_row`]
+fn document_row(hidden: &Option<Range<usize>>, display_row: usize) -> usize {
+    match hidden {
+        Some(hidden) if display_row >= hidden.start => display_row + hidden.len(),
+        _ => display_row,
+    }
+}
+
+impl LineLook {
+    fn of(engine: &EditorEngine, row: usize, cursor_style: Style, selection_style: Style) -> Self {
+        let doc = engine.state();
+        let selection = doc
+            .selection()
+            .filter(|range| (range.start.row..=range.end.row).contains(&row))
+            .map(|range| {
+                let from = if row == range.start.row {
+                    range.start.column
+                } else {
+                    0
+                };
+                let to = if row == range.end.row {
+                    range.end.column
+                } else {
+                    doc.line_len(row)
+                };
+                (from, to)
+            });
+        let (extra_selected, extra_cursors) =
+            multi_cursor::on_line(engine.extra_carets(), row, doc.line_len(row));
+        let mut look = Self {
+            selection,
+            cursor: (row == doc.cursor.row).then_some(doc.cursor.column),
+            extra_selected,
+            extra_cursors,
+            cursor_style,
+            selection_style,
+            protected: engine.protected_in_line(row),
+            inline: Vec::new(),
+            tinted: Vec::new(),
+            end_of_line: Vec::new(),
+        };
+        for decoration in engine.line_decorations(row) {
+            match decoration {
+                LineDecoration::Inline { column, text, tone } => {
+                    look.inline
+                        .push((column, text.to_string(), virtual_text_style(tone)))
+                }
+                LineDecoration::Highlight { columns, tone } => {
+                    look.tinted.push((columns, tint_style(tone)))
+                }
+                LineDecoration::EndOfLine { text, tone } => look
+                    .end_of_line
+                    .push((text.to_string(), virtual_text_style(tone))),
+            }
+        }
+        look
+    }
+
+    /// The look of the part `window` of a long line: its selection and
+    /// cursor, leaving out decorations and other cursors
+    fn windowed(self, window: &Range<usize>) -> Self {
+        let shift = |column: usize| column.clamp(window.start, window.end) - window.start;
+        Self {
+            selection: self
+                .selection
+                .map(|(from, to)| (shift(from), shift(to)))
+                .filter(|(from, to)| from < to),
+            cursor: self
+                .cursor
+                .filter(|column| (window.start..=window.end).contains(column))
+                .map(shift),
+            extra_selected: Vec::new(),
+            extra_cursors: Vec::new(),
+            protected: Vec::new(),
+            inline: Vec::new(),
+            tinted: Vec::new(),
+            end_of_line: Vec::new(),
+            ..self
+        }
+    }
+}
+
+/// A line with its selection, cursor, highlights, protected text and
+/// decorations styled
+fn styled_line(line: &str, look: &LineLook, highlights: Option<&LineHighlights>) -> Line<'static> {
+    let mut spans = Vec::new();
+    match look.selection {
+        Some((from, to)) => {
+            let to = to.min(line.len());
+            if from > 0 {
+                spans.push(Span::raw(&line[..from]));
+            }
+            if to > from {
+                spans.push(Span::styled(&line[from..to], look.selection_style));
+            }
+            if to < line.len() {
+                spans.push(Span::raw(&line[to..]));
+            }
+            // Within a selection the cursor only shows past the line's end
+            if look.cursor.is_some_and(|column| column >= line.len()) {
+                spans.push(Span::styled(" ", look.cursor_style));
+            }
+        }
+        None => match look.cursor {
+            Some(column) => cursor_line_spans(line, column, &mut spans, look.cursor_style),
+            None => spans.push(Span::raw(line)),
+        },
+    }
+
+    if let Some(highlights) = highlights {
+        let layer = highlights
+            .spans
+            .iter()
+            .map(|(range, style)| (range.clone(), highlight_style(*style)));
+        spans = layer_styles(spans, layer);
+    }
+    if !look.protected.is_empty() {
+        let dim = Style::default().add_modifier(Modifier::DIM);
+        spans = layer_styles(
+            spans,
+            look.protected.iter().map(|range| (range.clone(), dim)),
+        );
+    }
+    if !look.tinted.is_empty() {
+        spans = layer_styles(spans, look.tinted.iter().cloned());
+    }
+    if !look.extra_selected.is_empty() || !look.extra_cursors.is_empty() {
+        let cursors = look.extra_cursors.iter().filter_map(|&column| {
+            Some(column..column + line.get(column..)?.chars().next()?.len_utf8())
+        });
+        let mut layer: Vec<_> = look
+            .extra_selected
+            .iter()
+            .map(|range| (range.clone(), look.selection_style))
+            .chain(cursors.map(|range| (range, look.cursor_style)))
+            .collect();
+        layer.sort_by_key(|(range, _)| range.start);
+        spans = layer_styles(spans, layer.into_iter());
+    }
+    if !look.inline.is_empty() {
+        spans = insert_spans(spans, &look.inline);
+    }
+    // Other cursors past the line's end, where the cursor isn't already
+    if look.cursor.is_none_or(|column| column < line.len())
+        && look
+            .extra_cursors
+            .iter()
+            .any(|&column| column >= line.len())
+    {
+        spans.push(Span::styled(" ", look.cursor_style));
+    }
+    for (text, style) in &look.end_of_line {
+        spans.push(Span::styled(format!(" {}", text), *style));
+    }
+    Line::from(
+        spans
+            .into_iter()
+            .map(|span| Span::styled(span.content.into_owned(), span.style))
+            .collect::<Vec<_>>(),
+    )
+}
+
+fn cursor_line_spans<'a>(
+    line: &'a str,
+    cursor_col: usize,
+    spans: &mut Vec<Span<'a>>,
+    cursor_style: Style,
+) {
+    if cursor_col == 0 {
+        // Cursor at start
+        if line.is_empty() {
+            spans.push(Span::styled(" ", cursor_style));
+        } else {
+            let cursor_char = line.chars().next().unwrap();
+            spans.push(Span::styled(cursor_char.to_string(), cursor_style));
+            spans.push(Span::raw(&line[cursor_char.len_utf8()..]));
+        }
+    } else if cursor_col >= line.len() {
+        // Cursor at end
+        spans.push(Span::raw(line));
+        spans.push(Span::styled(" ", cursor_style));
+    } else {
+        // Cursor in middle
+        let (before, rest) = line.split_at(cursor_col);
+        let cursor_char = rest.chars().next().unwrap();
+        let after = &rest[cursor_char.len_utf8()..];
+
+        spans.push(Span::raw(before));
+        spans.push(Span::styled(cursor_char.to_string(), cursor_style));
+        spans.push(Span::raw(after));
+    }
+}
+
+/// Ratatui color for a decoration's tone
+pub fn tone_color(tone: Tone) -> Color {
+    match tone {
+        Tone::Muted => Color::DarkGray,
+        Tone::Accent => Color::Cyan,
+        Tone::Info => Color::Blue,
+        Tone::Warning => Color::Yellow,
+        Tone::Error => Color::Red,
+    }
+}
+
+fn virtual_text_style(tone: Tone) -> Style {
+    Style::default()
+        .fg(tone_color(tone))
+        .add_modifier(Modifier::ITALIC)
+}
+
+/// Background for text a decoration highlights; the muted one is lighter
+/// than the selection's
+fn tint_style(tone: Tone) -> Style {
+    match tone {
+        Tone::Muted => Style::default().bg(Color::Indexed(237)),
+        tone => Style::default().bg(tone_color(tone)).fg(Color::Black),
+    }
+}
+
+/// Screen cells that virtual text takes up before byte `column` of a
+/// line, including text placed at `column` itself, which the cursor there
+/// is drawn after
+fn virtual_width(inline: &[(usize, &str)], column: usize) -> usize {
+    inline
+        .iter()
+        .take_while(|(at, _)| *at <= column)
+        .map(|(_, text)| text.chars().count())
+        .sum()
+}
+
+/// Colors of bracket pairs by nesting depth, repeating
+const RAINBOW: [Color; 4] = [Color::Yellow, Color::Magenta, Color::Cyan, Color::Green];
+
+/// Ratatui style for text the highlighter marked
+pub fn highlight_style(style: highlight::Style) -> Style {
+    let mut result = Style::default();
+    if let Some(level) = style.heading {
+        let color = match level {
+            1 => Color::Magenta,
+            2 => Color::Cyan,
+            3 => Color::Green,
+            _ => Color::Yellow,
+        };
+        result = result.fg(color).add_modifier(Modifier::BOLD);
+    }
+    if style.bold {
+        result = result.add_modifier(Modifier::BOLD);
+    }
+    if style.italic {
+        result = result.add_modifier(Modifier::ITALIC);
+    }
+    if style.strikethrough {
+        result = result.add_modifier(Modifier::CROSSED_OUT);
+    }
+    if style.quote {
+        result = result.fg(Color::Gray).add_modifier(Modifier::ITALIC);
+    }
+    if style.scope > 0 {
+        // Greys a step lighter per level, up to four
+        result = result.bg(Color::Indexed(233 + style.scope.min(4)));
+    }
+    if let Some(depth) = style.bracket {
+        result = result.fg(RAINBOW[usize::from(depth) % RAINBOW.len()]);
+    }
+    if style.code {
+        result = result.fg(Color::Yellow).bg(Color::Indexed(236));
+    }
+    if style.link {
+        result = result.fg(Color::Blue).add_modifier(Modifier::UNDERLINED);
+    }
+    if style.markup {
+        result = result
+            .fg(Color::DarkGray)
+            .remove_modifier(Modifier::UNDERLINED);
+    }
+    result
+}
+
+/// Split a line's spans where the sorted ranges of `layer` start and end,
+/// and lay each range's style under the spans' own, so selection and cursor
+/// still show
+fn layer_styles<'a>(
+    spans: Vec<Span<'a>>,
+    layer: impl Iterator<Item = (Range<usize>, Style)>,
+) -> Vec<Span<'a>> {
+    let layer: Vec<_> = layer.collect();
+    let mut result = Vec::with_capacity(spans.len() + layer.len() * 2);
+    let mut offset = 0;
+    for span in spans {
+        let end = offset + span.content.len();
+        let mut start = offset;
+        for (range, style) in &layer {
+            if range.end <= start || range.start >= end {
+                continue;
+            }
+            if range.start > start {
+                result.push(slice_span(
+                    &span,
+                    start - offset..range.start - offset,
+                    None,
+                ));
+            }
+            let to = range.end.min(end);
+            let style = style.patch(span.style);
+            result.push(slice_span(
+                &span,
+                range.start.max(start) - offset..to - offset,
+                Some(style),
+            ));
+            start = to;
+        }
+        if start == offset {
+            result.push(span);
+        } else if start < end {
+            result.push(slice_span(&span, start - offset..end - offset, None));
+        }
+        offset = end;
+    }
+    result
+}
+
+/// Insert the sorted virtual text of `inline` at its byte columns, between
+/// the spans' characters
+fn insert_spans<'a>(spans: Vec<Span<'a>>, inline: &[(usize, String, Style)]) -> Vec<Span<'a>> {
+    let mut result = Vec::with_capacity(spans.len() + inline.len() * 2);
+    let mut inline = inline.iter().peekable();
+    let mut offset = 0;
+    for span in spans {
+        let end = offset + span.content.len();
+        let mut start = offset;
+        while let Some((column, text, style)) = inline.next_if(|(column, ..)| *column < end) {
+            let column = (*column).max(start);
+            if column > start {
+                result.push(slice_span(&span, start - offset..column - offset, None));
+            }
+            result.push(Span::styled(text.clone(), *style));
+            start = column;
+        }
+        if start == offset {
+            result.push(span);
+        } else if start < end {
+            result.push(slice_span(&span, start - offset..end - offset, None));
+        }
+        offset = end;
+    }
+    // Past the line's end
+    result.extend(inline.map(|(_, text, style)| Span::styled(text.clone(), *style)));
+    result
+}
+
+fn slice_span<'a>(span: &Span<'a>, range: Range<usize>, style: Option<Style>) -> Span<'a> {
+    let content = match &span.content {
+        std::borrow::Cow::Borrowed(text) => std::borrow::Cow::Borrowed(&text[range]),
+        std::borrow::Cow::Owned(text) => std::borrow::Cow::Owned(text[range].to_string()),
+    };
+    Span::styled(content, style.unwrap_or(span.style))
+}
@@ -0,0 +1,71 @@
+use ratatui::backend::TestBackend;
+use ratatui::buffer::Buffer;
+use ratatui::style::Color;
+use ratatui::{Frame, Terminal};
+use std::path::PathBuf;
+use zlyph_core::bookmarks::BookmarksPanel;
+use zlyph_core::templates::TemplatePicker;
+use zlyph_ratatui::panels;
+
+fn draw(width: u16, height: u16, render: impl FnOnce(&mut Frame)) -> Buffer {
+    let mut terminal = Terminal::new(TestBackend::new(width, height)).unwrap();
+    terminal.draw(render).unwrap();
+    terminal.backend().buffer().clone()
+}
+
+fn row_text(buffer: &Buffer, y: u16) -> String {
+    (buffer.area.x..buffer.area.right())
+        .map(|x| buffer[(x, y)].symbol())
+        .collect::<String>()
+        .trim_end()
+        .to_string()
+}
+
+#[test]
+fn bookmarks_list_their_lines_with_the_selection_highlighted() {
+    let lines = vec![
+        "fn main() {".to_string(),
+        "    let x = 1;".to_string(),
+        "}".to_string(),
+    ];
+    let panel = BookmarksPanel::new(&lines, &[0, 1], 1);
+    let buffer = draw(40, 10, |frame| panels::render_bookmarks(frame, &panel));
+
+    assert_eq!(
+        row_text(&buffer, 2),
+        "  ┌ Bookmarks ───────────────────────┐"
+    );
+    assert_eq!(
+        row_text(&buffer, 3),
+        "  │   1  fn main() {                 │"
+    );
+    assert_eq!(
+        row_text(&buffer, 4),
+        "  │   2  let x = 1;                  │"
+    );
+    assert_eq!(buffer[(3, 3)].bg, Color::Reset);
+    assert_eq!(buffer[(3, 4)].bg, Color::DarkGray);
+}
+
+#[test]
+fn popups_are_centred_and_no_wider_than_their_limit() {
+    let picker = TemplatePicker::new(vec![PathBuf::from("meeting.md")]);
+    let buffer = draw(80, 10, |frame| {
+        panels::render_template_picker(frame, &picker)
+    });
+
+    let top = row_text(&buffer, 2);
+    assert_eq!(top.find('┌'), Some(16));
+    assert_eq!(top.chars().count() - 16, 48);
+    assert!(row_text(&buffer, 3).contains("│meeting"));
+}
+
+#[test]
+fn popups_fit_terminals_too_small_for_them() {
+    let picker = TemplatePicker::new(vec![PathBuf::from("meeting.md")]);
+    for (width, height) in [(0, 0), (3, 2), (6, 5)] {
+        draw(width, height, |frame| {
+            panels::render_template_picker(frame, &picker)
+        });
+    }
+}
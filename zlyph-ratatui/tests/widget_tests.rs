@@ -0,0 +1,262 @@
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers, MouseButton, MouseEvent, MouseEventKind};
+use ratatui::buffer::Buffer;
+use ratatui::layout::Rect;
+use ratatui::style::Modifier;
+use ratatui::widgets::{Block, Borders, StatefulWidget};
+use zlyph_core::decorations::{Decoration, Placement, Tone};
+use zlyph_core::EditorAction;
+use zlyph_ratatui::keymap::Command;
+use zlyph_ratatui::{EditorWidget, EditorWidgetState};
+
+fn render(widget: EditorWidget, state: &mut EditorWidgetState, area: Rect) -> Buffer {
+    let mut buffer = Buffer::empty(area);
+    widget.render(area, &mut buffer, state);
+    buffer
+}
+
+fn row_text(buffer: &Buffer, y: u16) -> String {
+    (buffer.area.x..buffer.area.right())
+        .map(|x| buffer[(x, y)].symbol())
+        .collect::<String>()
+        .trim_end()
+        .to_string()
+}
+
+fn key(code: KeyCode) -> KeyEvent {
+    KeyEvent::new(code, KeyModifiers::NONE)
+}
+
+fn click(column: u16, row: u16) -> MouseEvent {
+    MouseEvent {
+        kind: MouseEventKind::Down(MouseButton::Left),
+        column,
+        row,
+        modifiers: KeyModifiers::NONE,
+    }
+}
+
+#[test]
+fn renders_text_inside_the_block() {
+    let mut state = EditorWidgetState::from_text("first\nsecond");
+    let widget = EditorWidget::default().block(Block::default().borders(Borders::ALL));
+    let buffer = render(widget, &mut state, Rect::new(0, 0, 12, 4));
+
+    assert_eq!(row_text(&buffer, 1), "│first     │");
+    assert_eq!(row_text(&buffer, 2), "│second    │");
+    assert_eq!(state.area(), Rect::new(1, 1, 10, 2));
+}
+
+#[test]
+fn typed_keys_edit_the_buffer() {
+    let mut state = EditorWidgetState::from_text("ello");
+    assert_eq!(state.handle_key(key(KeyCode::Char('H'))), None);
+    state.handle_key(key(KeyCode::End));
+    state.handle_key(key(KeyCode::Char('!')));
+
+    assert_eq!(state.text(), "Hello!");
+}
+
+#[test]
+fn application_keys_are_returned_rather_than_applied() {
+    let mut state = EditorWidgetState::from_text("text");
+    let quit = KeyEvent::new(KeyCode::Char('w'), KeyModifiers::CONTROL);
+
+    assert_eq!(
+        state.handle_key(quit),
+        Some(Command::Edit(EditorAction::Quit))
+    );
+    assert_eq!(state.text(), "text");
+}
+
+#[test]
+fn f2_keys_bookmark_lines_and_jump_between_them() {
+    let mut state = EditorWidgetState::from_text("one\ntwo\nthree");
+    let toggle = KeyEvent::new(KeyCode::F(2), KeyModifiers::CONTROL);
+
+    state.handle_key(toggle);
+    state.handle_key(key(KeyCode::Down));
+    state.handle_key(key(KeyCode::Down));
+    state.handle_key(toggle);
+    assert_eq!(state.engine.bookmarks(), [0, 2]);
+
+    assert_eq!(state.handle_key(key(KeyCode::F(2))), None);
+    assert_eq!(state.engine.state().cursor.row, 0);
+    state.handle_key(KeyEvent::new(KeyCode::F(2), KeyModifiers::SHIFT));
+    assert_eq!(state.engine.state().cursor.row, 2);
+
+    let list = KeyEvent::new(KeyCode::F(2), KeyModifiers::ALT);
+    assert_eq!(state.handle_key(list), Some(Command::Bookmarks));
+}
+
+#[test]
+fn clicks_place_the_cursor_within_the_text() {
+    let mut state = EditorWidgetState::from_text("one\ntwo words");
+    render(EditorWidget::default(), &mut state, Rect::new(2, 1, 20, 5));
+
+    assert!(state.handle_mouse(click(5, 2)));
+    let cursor = state.engine.state().cursor;
+    assert_eq!((cursor.row, cursor.column), (1, 3));
+
+    // Past the end of a line or the text lands on its end
+    assert!(state.handle_mouse(click(20, 5)));
+    let cursor = state.engine.state().cursor;
+    assert_eq!((cursor.row, cursor.column), (1, 9));
+
+    assert!(!state.handle_mouse(click(0, 0)));
+}
+
+#[test]
+fn scrolled_text_starts_at_the_offset() {
+    let text = (1..=10)
+        .map(|n| format!("line {n}"))
+        .collect::<Vec<_>>()
+        .join("\n");
+    let mut state = EditorWidgetState::from_text(&text);
+    let area = Rect::new(0, 0, 10, 3);
+    render(EditorWidget::default(), &mut state, area);
+
+    let mut wheel = click(0, 0);
+    wheel.kind = MouseEventKind::ScrollDown;
+    assert!(state.handle_mouse(wheel));
+    let buffer = render(EditorWidget::default(), &mut state, area);
+
+    assert_eq!(state.scroll_offset, 3);
+    assert_eq!(row_text(&buffer, 0), "line 4");
+}
+
+#[test]
+fn cursor_position_follows_the_scroll() {
+    let mut state = EditorWidgetState::from_text("a\nb\nc\nd");
+    let area = Rect::new(4, 2, 10, 2);
+    render(EditorWidget::default(), &mut state, area);
+    state
+        .engine
+        .handle_action(EditorAction::SetCursorPosition { row: 3, column: 1 });
+
+    assert_eq!(state.cursor_screen_position(), None);
+    state.ensure_cursor_visible(area.height);
+    assert_eq!(state.cursor_screen_position(), Some((5, 3)));
+}
+
+#[test]
+fn lines_are_restyled_after_edits_that_add_or_remove_lines() {
+    let mut state = EditorWidgetState::from_text("one\ntwo\nthree\nfour");
+    let area = Rect::new(0, 0, 10, 5);
+    render(EditorWidget::default(), &mut state, area);
+
+    state
+        .engine
+        .handle_action(EditorAction::SetCursorPosition { row: 1, column: 1 });
+    state.engine.handle_action(EditorAction::Newline);
+    let buffer = render(EditorWidget::default(), &mut state, area);
+    let rows: Vec<_> = (0..5).map(|y| row_text(&buffer, y)).collect();
+    assert_eq!(rows, ["one", "t", "wo", "three", "four"]);
+
+    state
+        .engine
+        .handle_action(EditorAction::SetCursorPosition { row: 0, column: 3 });
+    state
+        .engine
+        .handle_action(EditorAction::ExtendSelection { row: 3, column: 0 });
+    state.engine.handle_action(EditorAction::Backspace);
+    let buffer = render(EditorWidget::default(), &mut state, area);
+    let rows: Vec<_> = (0..3).map(|y| row_text(&buffer, y)).collect();
+    assert_eq!(rows, ["onethree", "four", ""]);
+}
+
+#[test]
+fn redraw_is_needed_only_after_a_change() {
+    let mut state = EditorWidgetState::from_text("a\nb\nc\nd");
+    let area = Rect::new(0, 0, 10, 2);
+    assert!(state.needs_redraw());
+    render(EditorWidget::default(), &mut state, area);
+    assert!(!state.needs_redraw());
+
+    state.engine.handle_action(EditorAction::MoveDown);
+    assert!(state.needs_redraw());
+    render(EditorWidget::default(), &mut state, area);
+    assert!(!state.needs_redraw());
+
+    state.scroll(1);
+    assert!(state.needs_redraw());
+}
+
+#[test]
+fn protected_text_is_dimmed() {
+    let mut state = EditorWidgetState::from_text("# header\nbody");
+    state.engine.protect(0..8);
+    state
+        .engine
+        .handle_action(EditorAction::SetCursorPosition { row: 1, column: 4 });
+    let buffer = render(EditorWidget::default(), &mut state, Rect::new(0, 0, 10, 2));
+
+    let dimmed = |x, y| buffer[(x, y)].modifier.contains(Modifier::DIM);
+    assert!((0..8).all(|x| dimmed(x, 0)));
+    assert!((0..4).all(|x| !dimmed(x, 1)));
+}
+
+#[test]
+fn collapsed_frontmatter_shows_one_summary_row() {
+    let mut state = EditorWidgetState::from_text("---\ntitle: Plans\ntags: [work]\n---\nbody");
+    let mut config = state.engine.config().clone();
+    config.notes.collapse_frontmatter = true;
+    state.engine.set_config(config);
+    state
+        .engine
+        .handle_action(EditorAction::SetCursorPosition { row: 4, column: 0 });
+    let area = Rect::new(0, 0, 30, 3);
+    let buffer = render(EditorWidget::default(), &mut state, area);
+
+    assert!(row_text(&buffer, 0).starts_with("▸ Plans · #work"));
+    assert!(row_text(&buffer, 1).starts_with("body"));
+
+    state.handle_mouse(click(1, 1));
+    assert_eq!(state.engine.state().cursor.row, 4);
+}
+
+#[test]
+fn virtual_text_is_drawn_and_stepped_over() {
+    let mut state = EditorWidgetState::from_text("let x = 1;");
+    state.engine.set_decorations(
+        "hints",
+        vec![
+            Decoration::virtual_text(5, ": i32", Placement::Inline),
+            Decoration::virtual_text(0, "you", Placement::EndOfLine),
+            Decoration::highlight(8..9).with_tone(Tone::Warning),
+        ],
+    );
+    state
+        .engine
+        .handle_action(EditorAction::SetCursorPosition { row: 0, column: 8 });
+    let buffer = render(EditorWidget::default(), &mut state, Rect::new(0, 0, 30, 1));
+
+    assert_eq!(row_text(&buffer, 0).trim_end(), "let x: i32 = 1; you");
+    assert!(buffer[(5, 0)].modifier.contains(Modifier::ITALIC));
+    assert_eq!(state.cursor_screen_position(), Some((13, 0)));
+
+    // A click on the hint lands where it is, and after it past it
+    state.handle_mouse(click(7, 0));
+    assert_eq!(state.engine.state().cursor.column, 5);
+    state.handle_mouse(click(14, 0));
+    assert_eq!(state.engine.state().cursor.column, 9);
+}
+
+#[test]
+fn long_lines_scroll_sideways_with_the_cursor() {
+    let line: String = (0..20_000)
+        .map(|i| char::from(b'a' + (i % 26) as u8))
+        .collect();
+    let mut state = EditorWidgetState::from_text(&format!("{}\nshort", line));
+    state
+        .engine
+        .handle_action(EditorAction::SetCursorPosition { row: 0, column: 30 });
+    let buffer = render(EditorWidget::default(), &mut state, Rect::new(0, 0, 20, 2));
+
+    // Half a window at a time: columns 20 to 39 are shown
+    assert_eq!(row_text(&buffer, 0), line[20..40]);
+    assert_eq!(row_text(&buffer, 1), "short");
+    assert_eq!(state.cursor_screen_position(), Some((10, 0)));
+
+    state.handle_mouse(click(3, 0));
+    assert_eq!(state.engine.state().cursor.column, 23);
+}
@@ -1,527 +1,3886 @@
 use anyhow::Result;
 use crossterm::{
+    cursor::SetCursorStyle,
     event::{
-        self, poll, DisableMouseCapture, EnableMouseCapture, Event, KeyCode, KeyEvent,
-        KeyModifiers, MouseButton, MouseEvent, MouseEventKind,
+        self, DisableFocusChange, DisableMouseCapture, EnableFocusChange, EnableMouseCapture,
+        Event, KeyCode, KeyEvent, KeyModifiers, MouseButton, MouseEventKind,
     },
     execute,
     terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
 };
+use notify::{RecommendedWatcher, RecursiveMode, Watcher};
 use ratatui::{
     backend::CrosstermBackend,
-    layout::Rect,
+    layout::{Position, Rect},
     style::{Color, Modifier, Style},
     text::{Line, Span},
-    widgets::Paragraph,
+    widgets::{Block, Borders, Clear, List, ListItem, ListState, Paragraph, Wrap},
     Terminal,
 };
-use std::time::Duration;
-use zlyph_core::{EditorAction, EditorEngine};
+use std::io::{IsTerminal, Read, Write};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::time::{Duration, Instant};
+use zlyph_core::accessibility::Announcer;
+use zlyph_core::batch;
+use zlyph_core::bookmarks::BookmarksPanel;
+use zlyph_core::char_picker::CharPicker;
+use zlyph_core::code_image;
+use zlyph_core::config::CursorStyle;
+use zlyph_core::diagnostics::{self, Severity};
+use zlyph_core::diff::ChangeKind;
+use zlyph_core::diff_view::{DiffRow, DiffSource, DiffView};
+use zlyph_core::encryption;
+use zlyph_core::ex;
+use zlyph_core::export::{self, HtmlOptions, Palette};
+use zlyph_core::file_tree::FileTree;
+use zlyph_core::git::GitGutter;
+use zlyph_core::goals::WritingSession;
+use zlyph_core::help::HelpOverlay;
+use zlyph_core::highlight::{Highlighter, LinkTarget};
+use zlyph_core::history::{HistoryBrowser, LocalHistory};
+use zlyph_core::hooks::{HookEvent, HookRegistry};
+use zlyph_core::indent_guides::IndentGuides;
+use zlyph_core::journal::Journal;
+use zlyph_core::large_file;
+use zlyph_core::links;
+use zlyph_core::live_sync::LiveSync;
+use zlyph_core::note_index::NoteSearch;
+use zlyph_core::notes::{self, BacklinksPanel};
+use zlyph_core::pdf;
+use zlyph_core::project;
+use zlyph_core::project_search::{self, ProjectSearch};
+use zlyph_core::quick_switch::QuickSwitch;
+use zlyph_core::rpc::RpcServer;
+use zlyph_core::search_panel::SearchPanel;
+use zlyph_core::symbols::{self, Symbol, SymbolPanel};
+use zlyph_core::tags::TagIndex;
+use zlyph_core::task_runner::{self, Problem, TaskRun};
+use zlyph_core::templates::{self, TemplatePicker};
+use zlyph_core::terminal::{self, TerminalKey};
+use zlyph_core::text_objects;
+use zlyph_core::todos::TodoPanel;
+use zlyph_core::vfs;
+use zlyph_core::workspace::WorkspaceState;
+use zlyph_core::{BufferPosition, ChangeEvent, EditorAction, EditorEngine};
+use zlyph_languagetool::GrammarChecker;
+use zlyph_lsp::protocol::Location;
+use zlyph_lsp::LspManager;
+use zlyph_ratatui::keymap::{self, Command};
+use zlyph_ratatui::panels;
+use zlyph_ratatui::{tone_color, EditorWidget, EditorWidgetState};
 
 struct TuiEditor {
-    engine: EditorEngine,
+    /// The buffer, its scroll position and the area it was drawn in
+    editor: EditorWidgetState,
     file_path: std::path::PathBuf,
     last_modified: Option<std::time::SystemTime>,
-    scroll_offset: u16,
+    /// Top line last written to the workspace state
+    saved_scroll_line: usize,
+    /// Cursor position and selection anchor last written to the workspace
+    /// state
+    saved_cursor: BufferPosition,
+    saved_anchor: Option<BufferPosition>,
+    /// Bookmarked rows last written to the workspace state
+    saved_bookmarks: Vec<usize>,
     terminal_size: Rect,
+    lsp: LspManager,
+    grammar: Option<GrammarChecker>,
+    /// Path being typed for Open, Save As or New; takes all key input while shown
+    prompt: Option<PathPrompt>,
+    /// Encrypted file to open once its passphrase is typed
+    locked_file: Option<std::path::PathBuf>,
+    /// Find in files; kept while hidden so reopening shows the last results
+    search_panel: Option<SearchPanel>,
+    search_visible: bool,
+    /// A name whose definitions are being found without a language server,
+    /// to jump to once they are in if there is only one
+    definition_pending: Option<String>,
+    /// Recent files list; takes all key input while shown
+    quick_switch: Option<QuickSwitch>,
+    /// File explorer on the left, built when first shown
+    file_tree: Option<FileTree>,
+    tree_visible: bool,
+    /// Whether keys go to the file tree rather than the buffer
+    tree_focused: bool,
+    /// Editing piped input in a temp file, which is kept out of the
+    /// workspace state
+    transient: bool,
+    /// Commands the config attaches to editor events
+    hooks: HookRegistry,
+    /// Lines changed since the file's last commit
+    git_gutter: GitGutter,
+    /// Markdown styling of the buffer's text
+    highlighter: Highlighter,
+    /// Indent levels of the buffer's lines, for the guides drawn in them
+    indent_guides: IndentGuides,
+    /// Comparison with another version of the file; takes all key input while shown
+    diff_view: Option<DiffView>,
+    /// Snapshots of edited files, and the engine revision last recorded
+    history: LocalHistory,
+    history_revision: u64,
+    /// The current file's snapshots; takes all key input while shown
+    history_browser: Option<HistoryBrowser>,
+    /// Notes linking to the current one; takes all key input while shown
+    backlinks: Option<BacklinksPanel>,
+    /// The current file's bookmarks; takes all key input while shown
+    bookmarks: Option<BookmarksPanel>,
+    /// Tags used in the notes directory; takes all key input while shown
+    tags: Option<TagIndex>,
+    /// TODOs and open tasks in the file or the notes; takes all key input
+    /// while shown
+    todos: Option<TodoPanel>,
+    /// The last build or lint task run, and the errors it printed
+    task_run: Option<TaskRun>,
+    /// Whether the task's errors are listed; the list takes all key input
+    /// while shown
+    errors_visible: bool,
+    /// Functions, types or headings of the buffer down the right edge
+    outline: Option<SymbolPanel>,
+    /// Whether keys go to the outline rather than the buffer
+    outline_focused: bool,
+    /// Symbols to jump to by name; takes all key input while shown
+    symbol_palette: Option<SymbolPanel>,
+    /// Engine revision the outline, palette and breadcrumbs were last read at
+    symbols_revision: u64,
+    /// The buffer's symbols, for the breadcrumb trail
+    symbols: Vec<Symbol>,
+    /// Whether the symbols came from the language server, which the ones
+    /// read from the lines then don't replace
+    server_symbols: bool,
+    /// Full-text search of the notes directory, kept after it is hidden so
+    /// edits keep its index current; takes all key input while shown
+    note_search: Option<NoteSearch>,
+    note_search_visible: bool,
+    index_revision: u64,
+    /// Templates to create a note from; takes all key input while shown
+    template_picker: Option<TemplatePicker>,
+    /// The template chosen for the note whose path is being prompted for
+    template: Option<std::path::PathBuf>,
+    /// Unicode characters and emoji to insert; takes all key input while shown
+    char_picker: Option<CharPicker>,
+    /// Every key binding, searchable; takes all key input while shown
+    help: Option<HelpOverlay>,
+    /// Set by `:q` and Ctrl+W; the event loop saves and stops
+    quit_requested: bool,
+    /// Edits shared with other instances open on the same file, and the
+    /// engine revision last shared
+    live_sync: Option<LiveSync>,
+    sync_revision: u64,
+    /// Speaks cursor movement and what is shown, for screen reader users
+    announcer: Announcer,
+    /// When a large file's edits are next saved; see `autosave`
+    save_due: Option<Instant>,
+    /// Loads and saves reported by the engine, which runs them in the
+    /// background
+    file_events: Receiver<ChangeEvent>,
+    /// A file being read in the background, shown once it has loaded. While
+    /// this is the current file, as when a large file is opened at startup,
+    /// the buffer doesn't hold it and is never saved.
+    opening: Option<std::path::PathBuf>,
+    /// Progress of a background load or save, for the status bar
+    file_progress: Option<String>,
+    /// The shell in the panel below the text, kept running while hidden
+    terminal_panel: Option<terminal::Terminal>,
+    terminal_visible: bool,
+    /// Whether keys go to the terminal rather than the buffer
+    terminal_focused: bool,
+}
+
+/// Width of the file tree sidebar, including its border
+const SIDEBAR_WIDTH: u16 = 30;
+
+/// Drawn between the levels of the breadcrumb trail
+const BREADCRUMB_SEPARATOR: &str = " ▸ ";
+
+/// How often background work that may have results waiting is checked
+const BACKGROUND_POLL: Duration = Duration::from_millis(100);
+
+/// How often the terminal panel is redrawn while its shell is running, so
+/// what is typed echoes promptly
+const TERMINAL_POLL: Duration = Duration::from_millis(16);
+
+/// How long after input or an edit replies from language servers and the
+/// grammar checker are still expected
+const REPLY_WINDOW: Duration = Duration::from_secs(3);
+
+/// How often the open file is checked for changes where it can't be watched
+const FILE_POLL: Duration = Duration::from_secs(1);
+
+/// Pause in editing a large file before it is saved
+const LARGE_FILE_SAVE_DELAY: Duration = Duration::from_secs(2);
+
+/// How often the status bar is redrawn while a writing session or upload
+/// changes it with nothing else happening
+const STATUS_TICK: Duration = Duration::from_secs(1);
+
+/// What the path typed into the prompt is used for
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum PromptKind {
+    Open,
+    SaveAs,
+    /// Where to write the buffer, or the selection, as an HTML page
+    ExportHtml,
+    ExportPdf,
+    ExportImage,
+    New,
+    /// File tree operations on the selected entry
+    CreateFile,
+    CreateFolder,
+    Rename,
+    Move,
+    Delete,
+    /// What to compare the buffer with in the diff view
+    Diff,
+    /// The passphrase for an encrypted file being opened
+    Passphrase,
+    /// Where to create a note from the chosen template
+    NewFromTemplate,
+    /// An ex command such as `w`, `42` or `%s/foo/bar/g`
+    Command,
+}
+
+impl PromptKind {
+    fn label(self) -> &'static str {
+        match self {
+            PromptKind::Open => "Open",
+            PromptKind::SaveAs => "Save as",
+            PromptKind::ExportHtml => "Export as HTML",
+            PromptKind::ExportPdf => "Export as PDF",
+            PromptKind::ExportImage => "Export as picture (.png or .svg)",
+            PromptKind::New => "New file",
+            PromptKind::CreateFile => "Create file",
+            PromptKind::CreateFolder => "Create folder",
+            PromptKind::Rename => "Rename to",
+            PromptKind::Move => "Move into",
+            PromptKind::Delete => "Delete (Enter to confirm)",
+            PromptKind::Diff => "Compare with file or git revision (empty: saved file)",
+            PromptKind::Passphrase => "Passphrase",
+            PromptKind::NewFromTemplate => "New note",
+            PromptKind::Command => ":",
+        }
+    }
+}
+
+struct PathPrompt {
+    kind: PromptKind,
+    input: String,
+}
+
+/// What wakes the event loop between timers
+enum Wake {
+    Input(std::io::Result<Event>),
+    /// Something in the open file's directory changed that concerns the file
+    FileChanged,
 }
 
 impl TuiEditor {
-    fn new(file_path: std::path::PathBuf) -> Self {
+    fn new(file_path: std::path::PathBuf, transient: bool) -> Self {
+        let config = project::config(&file_path).unwrap_or_default();
         let mut engine = EditorEngine::new();
+        let mut lsp = LspManager::new(config.lsp.clone());
+        let grammar = GrammarChecker::new(&config.languagetool);
+        let mut hooks = HookRegistry::new(config.hooks.clone());
+        let history = LocalHistory::new(config.history.clone());
+        let announcer = Announcer::new(config.accessibility.clone());
+        engine.set_config(config);
+        if !transient && engine.needs_passphrase(&file_path) {
+            if let Err(e) = unlock(&mut engine, &file_path) {
+                eprintln!("zlyph: {}", e);
+                std::process::exit(1);
+            }
+        }
+
+        // Ensure parent directory exists
+        if let Some(parent) = file_path.parent().filter(|_| !vfs::is_remote(&file_path)) {
+            let _ = std::fs::create_dir_all(parent);
+        }
+
+        // Load existing file if it exists; a large one is read in the
+        // background and finished in `opened`
+        let file_events = engine.subscribe();
+        let opening = large_file::is_large(&file_path, &engine.config().large_file)
+            .then(|| file_path.clone());
+        let last_modified = if opening.is_some() {
+            engine.load_in_background(&file_path);
+            None
+        } else if file_path.exists() || vfs::is_remote(&file_path) {
+            let _ = engine.load_from_file(&file_path);
+            std::fs::metadata(&file_path)
+                .ok()
+                .and_then(|m| m.modified().ok())
+        } else {
+            None
+        };
+        if opening.is_none() {
+            lsp.open(&file_path, &engine);
+            let _ = hooks.fire(HookEvent::Open, &file_path);
+        }
+        if engine.config().goal.start_on_launch {
+            engine.start_writing_session();
+        }
+
+        let mut editor = Self {
+            editor: EditorWidgetState::with_engine(engine),
+            file_path,
+            last_modified,
+            saved_scroll_line: 0,
+            saved_cursor: BufferPosition::zero(),
+            saved_anchor: None,
+            saved_bookmarks: Vec::new(),
+            terminal_size: Rect::default(),
+            lsp,
+            grammar,
+            prompt: None,
+            locked_file: None,
+            search_panel: None,
+            definition_pending: None,
+            search_visible: false,
+            quick_switch: None,
+            file_tree: None,
+            tree_visible: false,
+            tree_focused: false,
+            transient,
+            hooks,
+            git_gutter: GitGutter::new(),
+            highlighter: Highlighter::new(),
+            indent_guides: IndentGuides::new(),
+            diff_view: None,
+            history,
+            history_revision: 0,
+            history_browser: None,
+            backlinks: None,
+            bookmarks: None,
+            tags: None,
+            todos: None,
+            task_run: None,
+            errors_visible: false,
+            outline: None,
+            outline_focused: false,
+            symbol_palette: None,
+            symbols_revision: 0,
+            symbols: Vec::new(),
+            server_symbols: false,
+            note_search: None,
+            note_search_visible: false,
+            index_revision: 0,
+            template_picker: None,
+            char_picker: None,
+            help: None,
+            quit_requested: false,
+            template: None,
+            live_sync: None,
+            sync_revision: 0,
+            announcer,
+            save_due: None,
+            file_events,
+            opening,
+            file_progress: None,
+            terminal_panel: None,
+            terminal_visible: false,
+            terminal_focused: false,
+        };
+        editor.refresh_note_names();
+        if editor.opening.is_some() {
+            // The rest waits for the file to load
+            return editor;
+        }
+        if !transient {
+            editor.restore_scroll_position();
+            let _ = WorkspaceState::add_recent(&editor.file_path);
+        }
+        editor.snapshot_history();
+        editor.history_revision = editor.editor.engine.revision();
+        editor.join_live_sync();
+        if editor.breadcrumbs_shown() {
+            editor.refresh_symbols();
+        }
+        editor
+    }
+
+    /// Start sharing edits with other instances open on the current file
+    fn join_live_sync(&mut self) {
+        self.live_sync = None;
+        if !self.transient && !self.editor.engine.is_large_file() {
+            let text = self.editor.engine.state().to_string();
+            self.live_sync = LiveSync::join(&self.file_path, &text).ok();
+        }
+        self.sync_revision = self.editor.engine.revision();
+    }
+
+    /// Send local edits to the other instances and merge in theirs
+    fn poll_live_sync(&mut self) {
+        let Some(live) = self.live_sync.as_mut() else {
+            return;
+        };
+        if self.editor.engine.revision() != self.sync_revision {
+            let _ = live.local_edit(&self.editor.engine.state().to_string());
+        }
+        if let Ok(Some(text)) = live.poll() {
+            self.editor.engine.merge_text(&text);
+            self.lsp.sync(&self.editor.engine);
+            self.save_now();
+        }
+        self.sync_revision = self.editor.engine.revision();
+    }
+
+    /// Scroll and move the cursor to where this file was last left, in
+    /// either frontend, and bring back its bookmarks
+    fn restore_scroll_position(&mut self) {
+        let last_row = self.editor.engine.state().lines.len().saturating_sub(1);
+        let state = WorkspaceState::load()
+            .unwrap_or_default()
+            .file(&self.file_path);
+        let scroll_line = state.scroll_line.min(last_row);
+        self.editor.engine.set_bookmarks(&state.bookmarks);
+        if state.cursor_row > 0 || state.cursor_column > 0 {
+            self.editor
+                .engine
+                .handle_action(EditorAction::SetCursorPosition {
+                    row: state.cursor_row,
+                    column: state.cursor_column,
+                });
+        } else if scroll_line > 0 {
+            // Put the cursor where it keeps the restored view in place
+            let row = (scroll_line + self.editor.engine.config().scroll.margin).min(last_row);
+            self.editor
+                .engine
+                .handle_action(EditorAction::SetCursorPosition { row, column: 0 });
+        }
+        if let Some(anchor) = state.selection_anchor {
+            let cursor = self.editor.engine.state().cursor;
+            let engine = &mut self.editor.engine;
+            engine.handle_action(EditorAction::StartSelection {
+                row: anchor.row,
+                column: anchor.column,
+            });
+            engine.handle_action(EditorAction::ExtendSelection {
+                row: cursor.row,
+                column: cursor.column,
+            });
+        }
+        self.editor.scroll_offset = scroll_line as u16;
+        self.saved_scroll_line = scroll_line;
+        self.saved_cursor = self.editor.engine.state().cursor;
+        self.saved_anchor = self.editor.engine.state().selection_anchor;
+        self.editor.engine.warn_of_long_lines();
+        self.saved_bookmarks = self.editor.engine.bookmarks();
+    }
+
+    /// Switch to `path`, saving the current file first. A path that does not
+    /// exist yet starts as an empty buffer and is created on the first edit.
+    fn open_file(&mut self, path: std::path::PathBuf) -> bool {
+        self.save_scroll_position();
+        self.save_now();
+        self.load_file(path)
+    }
+
+    /// Switch to `path` without saving the current buffer. A large file is
+    /// read in the background, staying on the current one until it has
+    /// loaded.
+    fn load_file(&mut self, path: std::path::PathBuf) -> bool {
+        let _ = self.hooks.flush(&self.file_path);
+        self.snapshot_history();
+        if project::root(&self.file_path) != project::root(&path) {
+            let config = project::config(&path).unwrap_or_default();
+            self.editor.engine.set_config(config);
+        }
+        if large_file::is_large(&path, &self.editor.engine.config().large_file) {
+            self.editor.engine.load_in_background(&path);
+            self.opening = Some(path);
+            return true;
+        }
+        self.opening = None;
+        if path.exists() || vfs::is_remote(&path) {
+            if self.editor.engine.load_from_file(&path).is_err() {
+                return false;
+            }
+        } else {
+            self.editor.engine.new_buffer();
+        }
+        self.opened(path);
+        true
+    }
+
+    /// Make `path`, now in the buffer, the current file
+    fn opened(&mut self, path: std::path::PathBuf) {
+        self.opening = None;
+        self.editor.engine.set_read_only(false);
+        self.file_path = path;
+        self.transient = false;
+        self.last_modified = std::fs::metadata(&self.file_path)
+            .ok()
+            .and_then(|m| m.modified().ok());
+        self.lsp.open(&self.file_path, &self.editor.engine);
+        self.publish_problems();
+        let _ = self.hooks.fire(HookEvent::Open, &self.file_path);
+        self.restore_scroll_position();
+        let _ = WorkspaceState::add_recent(&self.file_path);
+        self.snapshot_history();
+        self.history_revision = self.editor.engine.revision();
+        self.join_live_sync();
+        self.refresh_note_names();
+        self.server_symbols = false;
+        if self.outline.is_some() || self.breadcrumbs_shown() {
+            self.refresh_symbols();
+        }
+    }
+
+    /// Collect the progress of background loads and saves for the status
+    /// bar, and finish opening a file once it has loaded; returns whether
+    /// there was anything to show
+    fn poll_file_io(&mut self) -> bool {
+        let mut changed = self.editor.engine.poll_file_io();
+        while let Ok(event) = self.file_events.try_recv() {
+            match event {
+                ChangeEvent::Loading { path, done, total } => {
+                    self.file_progress = Some(progress_label("Opening", &path, done, total));
+                }
+                ChangeEvent::Saving { path, done, total } => {
+                    self.file_progress = Some(progress_label("Saving", &path, done, total));
+                }
+                ChangeEvent::Loaded { path: Some(path) }
+                    if self.opening.as_ref() == Some(&path) =>
+                {
+                    self.file_progress = None;
+                    self.opened(path);
+                }
+                ChangeEvent::Saved { path } => {
+                    self.file_progress = None;
+                    if path == self.file_path {
+                        // So the save isn't mistaken for a change made elsewhere
+                        self.last_modified = std::fs::metadata(&path)
+                            .ok()
+                            .and_then(|m| m.modified().ok());
+                    }
+                }
+                ChangeEvent::Cancelled { path } if self.opening.as_ref() == Some(&path) => {
+                    let message = format!("Stopped opening {}", display_name(&path));
+                    self.stopped_opening(&path, message);
+                }
+                ChangeEvent::Failed { path, message } if self.opening.as_ref() == Some(&path) => {
+                    let message = format!("Could not open {}: {}", display_name(&path), message);
+                    self.stopped_opening(&path, message);
+                }
+                ChangeEvent::Cancelled { .. } => self.file_progress = None,
+                ChangeEvent::Failed { path, message } => {
+                    self.file_progress = None;
+                    let message = format!("Could not save {}: {}", display_name(&path), message);
+                    self.editor.engine.set_hover(Some(message));
+                }
+                _ => continue,
+            }
+            changed = true;
+        }
+        changed
+    }
+
+    /// Give up on opening `path`, saying why
+    fn stopped_opening(&mut self, path: &std::path::Path, message: String) {
+        self.file_progress = None;
+        self.editor.engine.set_hover(Some(message));
+        if path == self.file_path {
+            // Opened at startup, so there's nothing to go back to and the
+            // empty buffer must not be saved over the file
+            self.editor.engine.set_read_only(true);
+        } else {
+            self.opening = None;
+        }
+    }
+
+    /// Esc stops opening a file that is still loading
+    fn handle_opening_key(&mut self, key: KeyEvent) -> bool {
+        if key.code != KeyCode::Esc
+            || self.opening.is_none()
+            || !self.editor.engine.file_io_pending()
+        {
+            return false;
+        }
+        self.editor.engine.cancel_file_io();
+        true
+    }
+
+    /// Keep the buffer in local history, as opened or as left
+    fn snapshot_history(&mut self) {
+        if self.keeps_history() {
+            let text = self.editor.engine.state().lines.join("\n");
+            let _ = self.history.snapshot(&self.file_path, &text);
+        }
+    }
+
+    /// Encrypted files stay out of local history, which is plain text
+    fn keeps_history(&self) -> bool {
+        !self.transient
+            && !self.editor.engine.is_encrypted_file(&self.file_path)
+            && !self.editor.engine.is_large_file()
+    }
+
+    /// Keep the buffer in local history after edits, at most once per
+    /// configured interval
+    fn record_history(&mut self) {
+        let revision = self.editor.engine.revision();
+        if revision == self.history_revision || !self.keeps_history() {
+            return;
+        }
+        self.history_revision = revision;
+        let text = self.editor.engine.state().lines.join("\n");
+        let _ = self.history.record(&self.file_path, &text);
+    }
+
+    /// Keys for the local history list while it is shown, and Alt+L which
+    /// shows it: Enter compares the selected snapshot with the buffer and r
+    /// restores it
+    fn handle_history_key(&mut self, key: KeyEvent) -> bool {
+        let Some(browser) = self.history_browser.as_mut() else {
+            if keymap::command(&key) == Some(Command::LocalHistory) {
+                match self.history.snapshots(&self.file_path) {
+                    Ok(snapshots) if !snapshots.is_empty() => {
+                        self.history_browser = Some(HistoryBrowser::new(snapshots));
+                    }
+                    _ => self
+                        .editor
+                        .engine
+                        .set_hover(Some("No local history for this file".to_string())),
+                }
+                return true;
+            }
+            return false;
+        };
+        match key.code {
+            KeyCode::Esc | KeyCode::Char('q') => self.history_browser = None,
+            KeyCode::Up => browser.select_previous(),
+            KeyCode::Down => browser.select_next(),
+            KeyCode::Enter | KeyCode::Char('r') => {
+                let Some(snapshot) = browser.selected_snapshot().cloned() else {
+                    return true;
+                };
+                self.history_browser = None;
+                let text = match snapshot.read() {
+                    Ok(text) => text,
+                    Err(e) => {
+                        self.editor
+                            .engine
+                            .set_hover(Some(format!("Cannot read snapshot: {}", e)));
+                        return true;
+                    }
+                };
+                let theirs: Vec<String> = text.split('\n').map(String::from).collect();
+                if key.code == KeyCode::Enter {
+                    let title = snapshot.age_label(std::time::SystemTime::now());
+                    self.diff_view = Some(DiffView::new(
+                        title,
+                        theirs,
+                        &self.editor.engine.state().lines,
+                    ));
+                } else {
+                    self.snapshot_history();
+                    self.replace_lines(theirs, 0);
+                }
+            }
+            _ => {}
+        }
+        true
+    }
+
+    /// Open today's note, or with `step` the previous or next day's that
+    /// exists
+    fn open_daily_note(&mut self, step: Option<bool>) {
+        let journal = Journal::new(self.editor.engine.config());
+        let date = match step {
+            None => Journal::today(),
+            Some(forward) => match journal.adjacent(Some(&self.file_path), forward) {
+                Some(date) => date,
+                None => {
+                    let which = if forward { "later" } else { "earlier" };
+                    self.editor
+                        .engine
+                        .set_hover(Some(format!("No {} daily note", which)));
+                    return;
+                }
+            },
+        };
+        match journal.open(date, system_clipboard()) {
+            Ok(path) => {
+                self.open_file(path);
+            }
+            Err(e) => self
+                .editor
+                .engine
+                .set_hover(Some(format!("Cannot open daily note: {}", e))),
+        }
+    }
+
+    /// Where `[[links]]` in the current file are looked up
+    fn notes_dir(&self) -> std::path::PathBuf {
+        notes::notes_dir(&self.editor.engine.config().notes, &self.file_path)
+    }
+
+    /// Offer the notes next to the current one when completing `[[links]]`
+    fn refresh_note_names(&mut self) {
+        let names = if notes::is_note(&self.file_path) && !vfs::is_remote(&self.file_path) {
+            notes::note_names(&self.notes_dir())
+        } else {
+            Vec::new()
+        };
+        self.editor.engine.set_note_names(names);
+    }
+
+    /// Alt+Shift+B lists the notes linking to this one; Enter opens the
+    /// selected one at the linking line
+    fn handle_backlinks_key(&mut self, key: KeyEvent) -> bool {
+        let Some(panel) = self.backlinks.as_mut() else {
+            if keymap::command(&key) != Some(Command::Backlinks) {
+                return false;
+            }
+            let panel = BacklinksPanel::load(&self.notes_dir(), &self.file_path);
+            if panel.entries().is_empty() {
+                self.editor
+                    .engine
+                    .set_hover(Some(format!("No notes link to {}", panel.note)));
+            } else {
+                self.backlinks = Some(panel);
+            }
+            return true;
+        };
+        match key.code {
+            KeyCode::Esc | KeyCode::Char('q') => self.backlinks = None,
+            KeyCode::Up => panel.select_previous(),
+            KeyCode::Down => panel.select_next(),
+            KeyCode::Enter => {
+                let Some(entry) = panel.selected_entry().cloned() else {
+                    return true;
+                };
+                self.backlinks = None;
+                if self.open_file(entry.path) {
+                    self.editor
+                        .engine
+                        .handle_action(EditorAction::SetCursorPosition {
+                            row: entry.row,
+                            column: 0,
+                        });
+                }
+            }
+            _ => {}
+        }
+        true
+    }
+
+    /// Alt+F2 lists the bookmarks; Enter jumps to the selected one
+    fn handle_bookmarks_key(&mut self, key: KeyEvent) -> bool {
+        let Some(panel) = self.bookmarks.as_mut() else {
+            if keymap::command(&key) != Some(Command::Bookmarks) {
+                return false;
+            }
+            let engine = &mut self.editor.engine;
+            let panel = BookmarksPanel::new(
+                &engine.state().lines,
+                &engine.bookmarks(),
+                engine.state().cursor.row,
+            );
+            if panel.entries().is_empty() {
+                engine.set_hover(Some("No bookmarks".to_string()));
+            } else {
+                self.bookmarks = Some(panel);
+            }
+            return true;
+        };
+        match key.code {
+            KeyCode::Esc | KeyCode::Char('q') => self.bookmarks = None,
+            KeyCode::Up => panel.select_previous(),
+            KeyCode::Down => panel.select_next(),
+            KeyCode::Enter => {
+                if let Some(entry) = panel.selected_entry() {
+                    let row = entry.row;
+                    self.editor
+                        .engine
+                        .handle_action(EditorAction::SetCursorPosition { row, column: 0 });
+                }
+                self.bookmarks = None;
+            }
+            _ => {}
+        }
+        true
+    }
+
+    /// Keep the note search index current with edits to a note
+    fn update_note_index(&mut self) {
+        let revision = self.editor.engine.revision();
+        if revision == self.index_revision
+            || self.editor.engine.is_encrypted_file(&self.file_path)
+            || self.editor.engine.is_large_file()
+        {
+            return;
+        }
+        self.index_revision = revision;
+        if let Some(search) = self.note_search.as_mut() {
+            search.update(
+                &self.file_path,
+                &self.editor.engine.state().lines.join("\n"),
+            );
+        }
+    }
+
+    /// Alt+Shift+S searches the text of every note; Enter opens the
+    /// selected one at the matching line
+    fn handle_note_search_key(&mut self, key: KeyEvent) -> bool {
+        if keymap::command(&key) == Some(Command::NoteSearch) {
+            let dir = self.notes_dir();
+            if self
+                .note_search
+                .as_ref()
+                .is_none_or(|search| search.dir() != dir)
+            {
+                self.note_search = Some(NoteSearch::start(&dir));
+            }
+            self.note_search_visible = true;
+            return true;
+        }
+        if !self.note_search_visible {
+            return false;
+        }
+        let Some(search) = self.note_search.as_mut() else {
+            self.note_search_visible = false;
+            return false;
+        };
+        match key.code {
+            KeyCode::Esc => self.note_search_visible = false,
+            KeyCode::Enter => {
+                let Some(hit) = search.selected_hit().cloned() else {
+                    return true;
+                };
+                self.note_search_visible = false;
+                if self.open_file(hit.path) {
+                    self.editor
+                        .engine
+                        .handle_action(EditorAction::SetCursorPosition {
+                            row: hit.row,
+                            column: 0,
+                        });
+                }
+            }
+            KeyCode::Up => search.select_previous(),
+            KeyCode::Down => search.select_next(),
+            KeyCode::Backspace => search.backspace(),
+            KeyCode::Char(c)
+                if !key
+                    .modifiers
+                    .intersects(KeyModifiers::CONTROL | KeyModifiers::ALT) =>
+            {
+                search.type_text(&c.to_string());
+            }
+            _ => {}
+        }
+        true
+    }
+
+    /// Alt+Shift+T lists the tags in the notes directory with how often
+    /// each is used; Enter lists the uses of the selected one in the
+    /// find-in-files panel
+    fn handle_tags_key(&mut self, key: KeyEvent) -> bool {
+        let Some(index) = self.tags.as_mut() else {
+            let tags = keymap::command(&key) == Some(Command::Tags);
+            if tags {
+                self.tags = Some(TagIndex::start(&self.notes_dir()));
+            }
+            return tags;
+        };
+        match key.code {
+            KeyCode::Esc | KeyCode::Char('q') => self.tags = None,
+            KeyCode::Up => index.select_previous(),
+            KeyCode::Down => index.select_next(),
+            KeyCode::Enter => {
+                let Some(tag) = index.selected_tag() else {
+                    return true;
+                };
+                let (dir, name) = (index.dir.clone(), tag.name.clone());
+                self.tags = None;
+                self.search_visible = true;
+                self.search_panel
+                    .get_or_insert_with(SearchPanel::load)
+                    .find_tag(&dir, &name);
+            }
+            _ => {}
+        }
+        true
+    }
+
+    /// F9 runs the project's build or lint task and lists the errors it
+    /// prints; Shift+F9 shows or hides the list, where Enter jumps to the
+    /// selected error and Tab runs the next task. F4 and Shift+F4 step
+    /// through the errors from anywhere.
+    fn handle_task_key(&mut self, key: KeyEvent) -> bool {
+        match keymap::command(&key) {
+            Some(Command::RunTask) => {
+                self.run_task(false);
+                return true;
+            }
+            Some(Command::ShowErrors) => {
+                if self.task_run.is_some() {
+                    self.errors_visible = !self.errors_visible;
+                } else {
+                    let message = "No task has run; F9 runs one".to_string();
+                    self.editor.engine.set_hover(Some(message));
+                }
+                return true;
+            }
+            Some(command @ (Command::NextError | Command::PreviousError)) => {
+                let Some(run) = self.task_run.as_mut() else {
+                    let message = "No task has run; F9 runs one".to_string();
+                    self.editor.engine.set_hover(Some(message));
+                    return true;
+                };
+                match run.step(command == Command::PreviousError) {
+                    Some(problem) => self.jump_to_problem(problem),
+                    None => {
+                        let status = run.status();
+                        self.editor.engine.set_hover(Some(status));
+                    }
+                }
+                return true;
+            }
+            _ => {}
+        }
+        if !self.errors_visible {
+            return false;
+        }
+        let Some(run) = self.task_run.as_mut() else {
+            self.errors_visible = false;
+            return false;
+        };
+        match key.code {
+            KeyCode::Esc => self.errors_visible = false,
+            KeyCode::Up => run.select_previous(),
+            KeyCode::Down => run.select_next(),
+            KeyCode::Enter => {
+                if let Some(problem) = run.open_selected() {
+                    self.errors_visible = false;
+                    self.jump_to_problem(problem);
+                }
+            }
+            KeyCode::Tab => self.run_task(true),
+            _ => {}
+        }
+        true
+    }
+
+    /// Run the task last run again, or with `next` the one after it, and
+    /// list its errors
+    fn run_task(&mut self, next: bool) {
+        let root = task_runner::project_root(&self.file_path);
+        let tasks = task_runner::tasks_for(&self.editor.engine.config().tasks, &root);
+        let last = self
+            .task_run
+            .as_ref()
+            .and_then(|run| tasks.iter().position(|task| *task == run.task));
+        let index = match last {
+            Some(last) if next => (last + 1) % tasks.len(),
+            Some(last) => last,
+            None => 0,
+        };
+        let Some(task) = tasks.get(index) else {
+            let message = "No tasks for this project; add [[tasks]] to the config".to_string();
+            self.editor.engine.set_hover(Some(message));
+            return;
+        };
+        // Stop the last run before starting another
+        self.task_run = None;
+        match TaskRun::start(task, &root) {
+            Ok(run) => {
+                self.task_run = Some(run);
+                self.errors_visible = true;
+            }
+            Err(e) => {
+                let message = format!("Could not run {}: {}", task.command, e);
+                self.editor.engine.set_hover(Some(message));
+            }
+        }
+        self.publish_problems();
+    }
+
+    /// Show the task's problems in the current file as diagnostics
+    fn publish_problems(&mut self) {
+        let diagnostics = self.task_run.as_ref().map_or_else(Vec::new, |run| {
+            task_runner::diagnostics(
+                run.problems(),
+                &self.file_path,
+                &self.editor.engine.state().lines,
+            )
+        });
+        self.editor
+            .engine
+            .set_diagnostics(task_runner::DIAGNOSTIC_SOURCE, diagnostics);
+    }
+
+    /// Open the file of `problem` and put the cursor on it
+    fn jump_to_problem(&mut self, problem: Problem) {
+        let current = self.file_path.canonicalize().ok();
+        if current.as_ref() != Some(&problem.path) && !self.open_file(problem.path.clone()) {
+            return;
+        }
+        let engine = &mut self.editor.engine;
+        let column = engine
+            .state()
+            .lines
+            .get(problem.row)
+            .map_or(0, |line| problem.byte_column(line));
+        engine.handle_action(EditorAction::SetCursorPosition {
+            row: problem.row,
+            column,
+        });
+        let message = format!("{}: {}", problem.severity.label(), problem.message);
+        engine.set_hover(Some(message));
+    }
+
+    /// Alt+Shift+O lists the TODOs and open tasks in the file, narrowed by
+    /// typing; Tab switches to those in every note and back, and Enter jumps
+    /// to the selected one
+    fn handle_todos_key(&mut self, key: KeyEvent) -> bool {
+        let Some(panel) = self.todos.as_mut() else {
+            let todos = keymap::command(&key) == Some(Command::Todos);
+            if todos {
+                self.todos = Some(TodoPanel::for_buffer(&self.editor.engine.state().lines));
+            }
+            return todos;
+        };
+        match key.code {
+            KeyCode::Esc => self.todos = None,
+            KeyCode::Up => panel.select_previous(),
+            KeyCode::Down => panel.select_next(),
+            KeyCode::Backspace => panel.backspace(),
+            KeyCode::Tab => {
+                let query = std::mem::take(&mut panel.query);
+                let mut switched = if panel.dir().is_some() {
+                    TodoPanel::for_buffer(&self.editor.engine.state().lines)
+                } else {
+                    TodoPanel::start(&self.notes_dir())
+                };
+                switched.query = query;
+                self.todos = Some(switched);
+            }
+            KeyCode::Enter => {
+                let Some(item) = panel.selected_item().cloned() else {
+                    return true;
+                };
+                self.todos = None;
+                if item.path.is_none_or(|path| self.open_file(path)) {
+                    self.editor
+                        .engine
+                        .handle_action(EditorAction::SetCursorPosition {
+                            row: item.row,
+                            column: item.column,
+                        });
+                }
+            }
+            KeyCode::Char(c)
+                if !key
+                    .modifiers
+                    .intersects(KeyModifiers::CONTROL | KeyModifiers::ALT) =>
+            {
+                panel.type_text(&c.to_string());
+            }
+            _ => {}
+        }
+        true
+    }
+
+    /// Alt+O shows the outline and focuses it, or hides it when focused.
+    /// While focused, typing narrows it and Enter jumps to the selected
+    /// symbol.
+    fn handle_outline_key(&mut self, key: KeyEvent) -> bool {
+        if keymap::command(&key) == Some(Command::Outline) {
+            if self.outline.is_none() {
+                self.outline = Some(SymbolPanel::default());
+                self.refresh_symbols();
+                self.outline_focused = true;
+            } else if self.outline_focused {
+                self.outline = None;
+                self.outline_focused = false;
+            } else {
+                self.outline_focused = true;
+            }
+            return true;
+        }
+        let Some(panel) = self.outline.as_mut().filter(|_| self.outline_focused) else {
+            return false;
+        };
+        match key.code {
+            KeyCode::Esc if !panel.query.is_empty() => {
+                panel.query.clear();
+                panel.selected = 0;
+            }
+            KeyCode::Esc => self.outline_focused = false,
+            KeyCode::Up => panel.select_previous(),
+            KeyCode::Down => panel.select_next(),
+            KeyCode::Backspace => panel.backspace(),
+            KeyCode::Enter => {
+                let symbol = panel.selected_symbol().cloned();
+                panel.query.clear();
+                self.outline_focused = false;
+                if let Some(symbol) = symbol {
+                    self.goto_symbol(&symbol);
+                }
+            }
+            KeyCode::Char(c)
+                if !key
+                    .modifiers
+                    .intersects(KeyModifiers::CONTROL | KeyModifiers::ALT) =>
+            {
+                panel.type_text(&c.to_string());
+            }
+            _ => {}
+        }
+        true
+    }
+
+    /// Ctrl+T lists the buffer's symbols, narrowed by typing; Enter jumps to
+    /// the selected one
+    fn handle_symbol_palette_key(&mut self, key: KeyEvent) -> bool {
+        let Some(palette) = self.symbol_palette.as_mut() else {
+            let open = keymap::command(&key) == Some(Command::GoToSymbol);
+            if open {
+                self.symbol_palette = Some(SymbolPanel::default());
+                self.refresh_symbols();
+            }
+            return open;
+        };
+        match key.code {
+            KeyCode::Esc => self.symbol_palette = None,
+            KeyCode::Up => palette.select_previous(),
+            KeyCode::Down => palette.select_next(),
+            KeyCode::Backspace => palette.backspace(),
+            KeyCode::Enter => {
+                let symbol = palette.selected_symbol().cloned();
+                self.symbol_palette = None;
+                if let Some(symbol) = symbol {
+                    self.goto_symbol(&symbol);
+                }
+            }
+            KeyCode::Char(c)
+                if !key
+                    .modifiers
+                    .intersects(KeyModifiers::CONTROL | KeyModifiers::ALT) =>
+            {
+                palette.type_text(&c.to_string());
+            }
+            _ => {}
+        }
+        true
+    }
+
+    /// Read the buffer's symbols from its lines, and ask the language
+    /// server, whose answer replaces them when it comes
+    fn refresh_symbols(&mut self) {
+        self.symbols_revision = self.editor.engine.revision();
+        if !self.server_symbols {
+            let symbols =
+                symbols::outline(&self.editor.engine.state().lines, Some(&self.file_path));
+            self.set_symbols(symbols);
+        }
+        self.lsp.request_symbols(&self.editor.engine);
+    }
+
+    fn set_symbols(&mut self, symbols: Vec<Symbol>) {
+        for panel in [self.outline.as_mut(), self.symbol_palette.as_mut()]
+            .into_iter()
+            .flatten()
+        {
+            panel.set_symbols(symbols.clone());
+        }
+        self.symbols = symbols;
+    }
+
+    /// Whether the breadcrumb trail is drawn; large files go without, as
+    /// reading their symbols on every edit would stall
+    fn breadcrumbs_shown(&self) -> bool {
+        self.editor.engine.config().view.breadcrumbs && !self.editor.engine.is_large_file()
+    }
+
+    /// Whether indentation guides are drawn; large files go without, like
+    /// the breadcrumbs
+    fn indent_guides_shown(&self) -> bool {
+        self.editor.engine.config().view.indent_guides && !self.editor.engine.is_large_file()
+    }
+
+    /// The file's name and the symbols the cursor is in, outermost first,
+    /// each with where clicking it jumps to
+    fn breadcrumbs(&self) -> Vec<(String, BufferPosition)> {
+        if !self.breadcrumbs_shown() {
+            return Vec::new();
+        }
+        let name = self.file_path.file_name().map_or_else(
+            || "untitled".to_string(),
+            |name| name.to_string_lossy().into_owned(),
+        );
+        let row = self.editor.engine.state().cursor.row;
+        std::iter::once((name, BufferPosition::new(0, 0)))
+            .chain(
+                symbols::breadcrumbs(&self.symbols, row)
+                    .into_iter()
+                    .map(|symbol| {
+                        (
+                            symbol.name.clone(),
+                            BufferPosition::new(symbol.row, symbol.column),
+                        )
+                    }),
+            )
+            .collect()
+    }
+
+    /// Jump to the breadcrumb under a click; returns whether there was one
+    fn handle_breadcrumb_click(&mut self, column: u16, row: u16) -> bool {
+        if row + 1 != self.terminal_size.bottom() {
+            return false;
+        }
+        let mut x = self.terminal_size.x + 2 + self.sidebar_width();
+        for (name, position) in self.breadcrumbs() {
+            let width = name.chars().count() as u16;
+            if (x..x + width).contains(&column) {
+                self.editor
+                    .engine
+                    .handle_action(EditorAction::SetCursorPosition {
+                        row: position.row,
+                        column: position.column,
+                    });
+                return true;
+            }
+            x += width + BREADCRUMB_SEPARATOR.chars().count() as u16;
+        }
+        false
+    }
+
+    fn goto_symbol(&mut self, symbol: &Symbol) {
+        self.editor
+            .engine
+            .handle_action(EditorAction::SetCursorPosition {
+                row: symbol.row,
+                column: symbol.column,
+            });
+    }
+
+    /// Alt+N lists the templates to create a note from; Enter asks where to
+    /// create it
+    fn handle_template_key(&mut self, key: KeyEvent) -> bool {
+        let Some(picker) = self.template_picker.as_mut() else {
+            if keymap::command(&key) != Some(Command::NoteFromTemplate) {
+                return false;
+            }
+            let dir = templates::default_dir();
+            let list = templates::list(&dir);
+            if list.is_empty() {
+                self.editor
+                    .engine
+                    .set_hover(Some(format!("No templates in {}", dir.display())));
+            } else {
+                self.template_picker = Some(TemplatePicker::new(list));
+            }
+            return true;
+        };
+        match key.code {
+            KeyCode::Esc | KeyCode::Char('q') => self.template_picker = None,
+            KeyCode::Up => picker.select_previous(),
+            KeyCode::Down => picker.select_next(),
+            KeyCode::Enter => {
+                self.template = picker.selected_template().map(|path| path.to_path_buf());
+                self.template_picker = None;
+                let input = format!(
+                    "{}{}",
+                    self.notes_dir().display(),
+                    std::path::MAIN_SEPARATOR
+                );
+                self.prompt = Some(PathPrompt {
+                    kind: PromptKind::NewFromTemplate,
+                    input,
+                });
+            }
+            _ => {}
+        }
+        true
+    }
+
+    /// Alt+: searches Unicode characters and emoji by name or `:shortcode:`
+    /// and Alt+Shift+U takes a hex code point; Enter inserts the selected
+    /// character at the cursor
+    fn handle_char_picker_key(&mut self, key: KeyEvent) -> bool {
+        let Some(picker) = self.char_picker.as_mut() else {
+            let query = match keymap::command(&key) {
+                Some(Command::InsertCharacter) => "",
+                Some(Command::InsertCodepoint) => "U+",
+                _ => return false,
+            };
+            self.char_picker = Some(CharPicker::new(query));
+            return true;
+        };
+        match key.code {
+            KeyCode::Esc => self.char_picker = None,
+            KeyCode::Up => picker.select_previous(),
+            KeyCode::Down => picker.select_next(),
+            KeyCode::Backspace => picker.backspace(),
+            KeyCode::Enter => {
+                let action = match picker.selected_symbol() {
+                    _ if picker.codepoint_selected() => {
+                        EditorAction::InsertUnicodeCodepoint(picker.query.clone())
+                    }
+                    Some(symbol) => EditorAction::TypeString(symbol.text.clone()),
+                    None => return true,
+                };
+                self.char_picker = None;
+                self.apply_edit(action);
+            }
+            KeyCode::Char(c)
+                if !key
+                    .modifiers
+                    .intersects(KeyModifiers::CONTROL | KeyModifiers::ALT) =>
+            {
+                picker.type_text(&c.to_string());
+            }
+            _ => {}
+        }
+        true
+    }
+
+    /// Write the buffer under a new name and keep editing it there
+    fn save_as(&mut self, path: std::path::PathBuf) {
+        if self.editor.engine.save_to_file(&path).is_err() {
+            return;
+        }
+        self.file_path = path;
+        self.transient = false;
+        self.last_modified = std::fs::metadata(&self.file_path)
+            .ok()
+            .and_then(|m| m.modified().ok());
+        self.lsp.open(&self.file_path, &self.editor.engine);
+        let _ = WorkspaceState::add_recent(&self.file_path);
+        self.join_live_sync();
+    }
+
+    fn export_html(&mut self, path: &std::path::Path) {
+        let engine = &self.editor.engine;
+        let state = engine.state();
+        let options = HtmlOptions::new(Some(&self.file_path), &engine.config().export);
+        let page = export::html(
+            &state.lines,
+            Some(&self.file_path),
+            state.selection(),
+            &Palette::default(),
+            &options,
+        );
+        let message = match std::fs::write(path, page) {
+            Ok(()) => format!("Exported {}", path.display()),
+            Err(e) => format!("Could not export: {}", e),
+        };
+        self.editor.engine.set_hover(Some(message));
+    }
+
+    fn export_pdf(&mut self, path: &std::path::Path) {
+        let engine = &self.editor.engine;
+        let state = engine.state();
+        let result = pdf::write(
+            path,
+            &state.lines,
+            Some(&self.file_path),
+            state.selection(),
+            engine.config(),
+        );
+        let message = match result {
+            Ok(()) => format!("Exported {}", path.display()),
+            Err(e) => format!("Could not export: {}", e),
+        };
+        self.editor.engine.set_hover(Some(message));
+    }
+
+    fn export_image(&mut self, path: &std::path::Path) {
+        let engine = &self.editor.engine;
+        let state = engine.state();
+        let result = code_image::write(
+            path,
+            &state.lines,
+            Some(&self.file_path),
+            state.selection(),
+            &Palette::default(),
+            engine.config(),
+        );
+        let message = match result {
+            Ok(()) => format!("Exported {}", path.display()),
+            Err(e) => format!("Could not export: {}", e),
+        };
+        self.editor.engine.set_hover(Some(message));
+    }
+
+    /// Open the buffer, or the selection, as a PDF in the system's viewer
+    /// to print from there
+    fn print(&mut self) {
+        let engine = &self.editor.engine;
+        let state = engine.state();
+        let path = pdf::print_path(Some(&self.file_path));
+        let result = pdf::write(
+            &path,
+            &state.lines,
+            Some(&self.file_path),
+            state.selection(),
+            engine.config(),
+        )
+        .and_then(|()| open_url(&path.display().to_string()));
+        if let Err(e) = result {
+            self.editor
+                .engine
+                .set_hover(Some(format!("Could not print: {}", e)));
+        }
+    }
+
+    /// Keys for the terminal while it has focus, and the shortcuts that show
+    /// it, run text in it and capture its output
+    fn handle_terminal_key(&mut self, key: KeyEvent) -> bool {
+        match keymap::command(&key) {
+            Some(Command::ToggleTerminal) => {
+                self.toggle_terminal();
+                return true;
+            }
+            Some(Command::SendToTerminal) => {
+                self.send_to_terminal();
+                return true;
+            }
+            Some(Command::CaptureTerminal) => {
+                self.capture_terminal();
+                return true;
+            }
+            _ => {}
+        }
+        if !self.terminal_visible || !self.terminal_focused {
+            return false;
+        }
+        let Some(shell) = self.terminal_panel.as_mut() else {
+            return false;
+        };
+        let Some(terminal_key) = terminal_key(key.code) else {
+            return true;
+        };
+        let result = shell.send_key(
+            terminal_key,
+            key.modifiers.contains(KeyModifiers::CONTROL),
+            key.modifiers.contains(KeyModifiers::ALT),
+        );
+        if let Err(e) = result {
+            self.editor
+                .engine
+                .set_hover(Some(format!("Could not write to the terminal: {}", e)));
+        }
+        true
+    }
+
+    /// Show the terminal and give it the keys, or hide it if it has them
+    fn toggle_terminal(&mut self) {
+        if self.terminal_visible && self.terminal_focused {
+            self.terminal_visible = false;
+            self.terminal_focused = false;
+        } else if self.start_terminal().is_some() {
+            self.terminal_visible = true;
+            self.terminal_focused = true;
+        }
+    }
+
+    /// The terminal's shell, started in the workspace if it isn't running
+    fn start_terminal(&mut self) -> Option<&mut terminal::Terminal> {
+        if self
+            .terminal_panel
+            .as_ref()
+            .is_some_and(|shell| !shell.is_running())
+        {
+            self.terminal_panel = None;
+        }
+        if self.terminal_panel.is_none() {
+            let config = &self.editor.engine.config().terminal;
+            let root = project_search::workspace_root(&self.file_path);
+            let width = self
+                .terminal_size
+                .width
+                .saturating_sub(4 + self.sidebar_width() + self.outline_width());
+            match terminal::Terminal::spawn(config, Some(&root), config.height, width) {
+                Ok(shell) => self.terminal_panel = Some(shell),
+                Err(e) => {
+                    self.editor
+                        .engine
+                        .set_hover(Some(format!("Could not start the terminal: {}", e)));
+                    return None;
+                }
+            }
+        }
+        self.terminal_panel.as_mut()
+    }
+
+    /// Run the selection, or the cursor's line, in the terminal
+    fn send_to_terminal(&mut self) {
+        let engine = &self.editor.engine;
+        let text = engine.selected_text().unwrap_or_else(|| {
+            let state = engine.state();
+            state.lines[state.cursor.row].clone()
+        });
+        let Some(shell) = self.start_terminal() else {
+            return;
+        };
+        let result = shell.run(&text);
+        self.terminal_visible = true;
+        if let Err(e) = result {
+            self.editor
+                .engine
+                .set_hover(Some(format!("Could not write to the terminal: {}", e)));
+        }
+    }
+
+    /// Paste the system clipboard, re-indented to fit or as plain text
+    fn paste(&mut self, plain: bool) {
+        let text = system_clipboard();
+        if text.is_empty() {
+            self.editor
+                .engine
+                .set_hover(Some("The clipboard is empty".to_string()));
+            return;
+        }
+        let action = if plain {
+            EditorAction::PastePlain(text)
+        } else {
+            EditorAction::Paste(text)
+        };
+        let revision = self.editor.engine.revision();
+        self.editor.engine.handle_action(action);
+        self.lsp.sync(&self.editor.engine);
+        self.autosave(revision);
+    }
+
+    /// Insert everything the terminal has shown at the cursor
+    fn capture_terminal(&mut self) {
+        let Some(shell) = self.terminal_panel.as_mut() else {
+            self.editor
+                .engine
+                .set_hover(Some("The terminal isn't open".to_string()));
+            return;
+        };
+        let output = shell.capture();
+        let revision = self.editor.engine.revision();
+        self.editor
+            .engine
+            .handle_action(EditorAction::Paste(output));
+        self.lsp.sync(&self.editor.engine);
+        self.autosave(revision);
+    }
+
+    /// Keys for the recent files or projects list while it is shown, and
+    /// the shortcuts that show them
+    fn handle_switch_key(&mut self, key: KeyEvent) -> bool {
+        let Some(switch) = self.quick_switch.as_mut() else {
+            match keymap::command(&key) {
+                Some(Command::RecentFiles) => {
+                    self.quick_switch = Some(QuickSwitch::load(Some(&self.file_path)));
+                }
+                Some(Command::SwitchProject) => {
+                    self.quick_switch = Some(QuickSwitch::load_projects(Some(&self.file_path)));
+                }
+                _ => return false,
+            }
+            return true;
+        };
+        match key.code {
+            KeyCode::Esc => self.quick_switch = None,
+            KeyCode::Enter => self.open_selected_recent(),
+            KeyCode::Up => switch.select_previous(),
+            KeyCode::Down => switch.select_next(),
+            KeyCode::Backspace => switch.backspace(),
+            KeyCode::Char(c)
+                if !key
+                    .modifiers
+                    .intersects(KeyModifiers::CONTROL | KeyModifiers::ALT) =>
+            {
+                switch.type_text(&c.to_string());
+            }
+            _ => {}
+        }
+        true
+    }
+
+    /// Open the highlighted file, or the file last opened in the
+    /// highlighted project
+    fn open_selected_recent(&mut self) {
+        let Some(switch) = self.quick_switch.take() else {
+            return;
+        };
+        let Some(path) = switch.selected_path() else {
+            return;
+        };
+        if !switch.projects {
+            self.open_file(path);
+        } else if let Some(file) = project::last_file(&path) {
+            self.open_file(file);
+        } else {
+            let message = format!("No files left to open in {}", path.display());
+            self.editor.engine.set_hover(Some(message));
+        }
+    }
+
+    /// Changes since the last commit: F7 and Shift+F7 jump to the next or
+    /// previous one; Alt+H previews, Alt+U reverts and Alt+A stages the one
+    /// at the cursor
+    fn handle_change_key(&mut self, key: KeyEvent) -> bool {
+        let state = self.editor.engine.state();
+        let (row, line_count) = (state.cursor.row, state.lines.len());
+        let command = keymap::command(&key);
+        match command {
+            Some(Command::NextChange | Command::PreviousChange) => {
+                let target = if command == Some(Command::PreviousChange) {
+                    self.git_gutter.prev_change(row, line_count)
+                } else {
+                    self.git_gutter.next_change(row, line_count)
+                };
+                if let Some(target) = target {
+                    self.editor
+                        .engine
+                        .handle_action(EditorAction::SetCursorPosition {
+                            row: target.row,
+                            column: target.column,
+                        });
+                }
+            }
+            Some(Command::PreviewChange | Command::RevertChange | Command::StageChange) => {
+                let Some(hunk) = self.git_gutter.hunk_at(row, line_count) else {
+                    self.editor
+                        .engine
+                        .set_hover(Some("No change at the cursor".to_string()));
+                    return true;
+                };
+                let lines = &self.editor.engine.state().lines;
+                match command {
+                    Some(Command::PreviewChange) => {
+                        let preview = self.git_gutter.preview(&hunk, lines);
+                        self.editor.engine.set_hover(Some(preview));
+                    }
+                    Some(Command::RevertChange) => {
+                        let reverted = self.git_gutter.revert(&hunk, lines);
+                        self.replace_lines(reverted, hunk.new_start);
+                    }
+                    _ => {
+                        let message = match self.git_gutter.stage(&hunk, lines) {
+                            Ok(()) => "Staged".to_string(),
+                            Err(e) => format!("Could not stage: {}", e),
+                        };
+                        self.editor.engine.set_hover(Some(message));
+                    }
+                }
+            }
+            _ => return false,
+        }
+        true
+    }
+
+    /// Replace the buffer's lines as one undoable edit, with the cursor at
+    /// the start of `row`, and save
+    fn replace_lines(&mut self, lines: Vec<String>, row: usize) {
+        self.editor
+            .engine
+            .set_lines(lines, BufferPosition::new(row, 0));
+        self.saved_edit();
+    }
+
+    /// Make an edit chosen from a panel rather than typed, and save
+    fn apply_edit(&mut self, action: EditorAction) {
+        self.editor.engine.handle_action(action);
+        self.saved_edit();
+    }
+
+    /// Save after an action, so every edit is kept at once. A large file is
+    /// saved once edits have paused instead, and only if the action made
+    /// one, since writing it out takes a while.
+    fn autosave(&mut self, revision_before: u64) {
+        if self.editor.engine.is_large_file() {
+            if self.editor.engine.revision() != revision_before {
+                self.save_due = Some(Instant::now() + LARGE_FILE_SAVE_DELAY);
+            }
+            return;
+        }
+        self.save_now();
+    }
+
+    /// Save the buffer in the background; `poll_file_io` notes the file's
+    /// new modification time once it is written
+    fn save_now(&mut self) {
+        self.save_due = None;
+        if self.holds_file() {
+            let _ = self.editor.engine.save_in_background(&self.file_path);
+        }
+    }
+
+    /// Whether the buffer holds the current file, rather than standing in
+    /// for one still loading
+    fn holds_file(&self) -> bool {
+        self.opening.as_ref() != Some(&self.file_path)
+    }
+
+    /// Pass an edit on to the language server and hooks and save it
+    fn saved_edit(&mut self) {
+        self.lsp.sync(&self.editor.engine);
+        self.save_now();
+        let _ = self.hooks.edited(&self.file_path);
+    }
+
+    /// F1 or Ctrl+H shows every key binding; while shown, typing searches
+    /// them and the arrows and Page Up/Down scroll
+    fn handle_help_key(&mut self, key: KeyEvent) -> bool {
+        let page = self.help_page();
+        let Some(help) = self.help.as_mut() else {
+            if keymap::command(&key) != Some(Command::Help) {
+                return false;
+            }
+            self.help = Some(keymap::help());
+            return true;
+        };
+        match key.code {
+            KeyCode::Esc => self.help = None,
+            _ if keymap::command(&key) == Some(Command::Help) => self.help = None,
+            KeyCode::Up => help.scroll_by(-1, page),
+            KeyCode::Down => help.scroll_by(1, page),
+            KeyCode::PageUp => help.scroll_by(-(page as isize), page),
+            KeyCode::PageDown => help.scroll_by(page as isize, page),
+            KeyCode::Home => help.scroll = 0,
+            KeyCode::End => help.scroll_by(isize::MAX, page),
+            KeyCode::Backspace => help.backspace(),
+            KeyCode::Char(c)
+                if !key
+                    .modifiers
+                    .intersects(KeyModifiers::CONTROL | KeyModifiers::ALT) =>
+            {
+                help.type_text(&c.to_string());
+            }
+            _ => {}
+        }
+        true
+    }
+
+    /// Keys for the diff view while it is shown: n/p or Down/Up move between
+    /// hunks, t accepts theirs, m keeps mine, Esc or q closes it
+    fn handle_diff_key(&mut self, key: KeyEvent) -> bool {
+        let Some(view) = self.diff_view.as_mut() else {
+            return false;
+        };
+        match key.code {
+            KeyCode::Esc | KeyCode::Char('q') => self.diff_view = None,
+            KeyCode::Char('n') | KeyCode::Down | KeyCode::F(7) => view.next_hunk(),
+            KeyCode::Char('p') | KeyCode::Up => view.prev_hunk(),
+            KeyCode::Char('m') => view.accept_mine(&self.editor.engine.state().lines),
+            KeyCode::Char('t') => {
+                if let Some((lines, row)) = view.accept_theirs(&self.editor.engine.state().lines) {
+                    self.replace_lines(lines, row);
+                    if let Some(view) = self.diff_view.as_mut() {
+                        view.refresh(&self.editor.engine.state().lines);
+                    }
+                }
+            }
+            _ => {}
+        }
+        true
+    }
+
+    /// Compare the buffer with `source` in the diff view, or say why not
+    fn open_diff_view(&mut self, source: DiffSource) {
+        match DiffView::open(&source, &self.file_path, &self.editor.engine.state().lines) {
+            Ok(view) => self.diff_view = Some(view),
+            Err(e) => self
+                .editor
+                .engine
+                .set_hover(Some(format!("Cannot compare: {}", e))),
+        }
+    }
+
+    /// Keys for the path prompt while it is shown, and the shortcuts that open it
+    fn handle_file_key(&mut self, key: KeyEvent) -> bool {
+        if let Some(prompt) = self.prompt.as_mut() {
+            match key.code {
+                KeyCode::Enter => {
+                    if let Some(prompt) = self.prompt.take() {
+                        self.run_prompt(prompt);
+                    }
+                }
+                KeyCode::Esc => self.prompt = None,
+                // The delete prompt only shows what is about to go
+                _ if matches!(prompt.kind, PromptKind::Delete) => {}
+                KeyCode::Backspace => {
+                    prompt.input.pop();
+                }
+                KeyCode::Char(c)
+                    if !key
+                        .modifiers
+                        .intersects(KeyModifiers::CONTROL | KeyModifiers::ALT) =>
+                {
+                    prompt.input.push(c);
+                }
+                _ => {}
+            }
+            return true;
+        }
+
+        let command = keymap::command(&key);
+        if command == Some(Command::Save) {
+            // Edits are saved as they are made; this only matters for a
+            // remote file that changed on its host
+            if vfs::is_remote(&self.file_path) {
+                let _ = self.editor.engine.overwrite_remote(&self.file_path);
+            }
+            return true;
+        }
+        if command == Some(Command::Print) {
+            self.print();
+            return true;
+        }
+        if matches!(command, Some(Command::Paste | Command::PastePlain)) {
+            self.paste(command == Some(Command::PastePlain));
+            return true;
+        }
+        let daily = match command {
+            Some(Command::DailyNote) => Some(None),
+            Some(Command::PreviousDailyNote) => Some(Some(false)),
+            Some(Command::NextDailyNote) => Some(Some(true)),
+            _ => None,
+        };
+        if let Some(step) = daily {
+            self.open_daily_note(step);
+            return true;
+        }
+        let kind = match command {
+            Some(Command::Open) => PromptKind::Open,
+            Some(Command::New) => PromptKind::New,
+            Some(Command::SaveAs) => PromptKind::SaveAs,
+            Some(Command::ExportHtml) => PromptKind::ExportHtml,
+            Some(Command::ExportPdf) => PromptKind::ExportPdf,
+            Some(Command::ExportImage) => PromptKind::ExportImage,
+            Some(Command::CompareWith) => PromptKind::Diff,
+            Some(
+                Command::Ex
+                | Command::AlignOn
+                | Command::TransformSelection
+                | Command::RunShellCommand
+                | Command::FilterThroughCommand,
+            ) => PromptKind::Command,
+            _ => return false,
+        };
+        let input = match kind {
+            PromptKind::SaveAs => self.file_path.display().to_string(),
+            PromptKind::ExportHtml => export::html_path(&self.file_path).display().to_string(),
+            PromptKind::ExportPdf => pdf::pdf_path(&self.file_path).display().to_string(),
+            PromptKind::ExportImage => code_image::image_path(&self.file_path)
+                .display()
+                .to_string(),
+            PromptKind::Command => match command {
+                Some(Command::AlignOn) => "align ".to_string(),
+                Some(Command::TransformSelection) => "transform ".to_string(),
+                Some(Command::RunShellCommand) => "r !".to_string(),
+                Some(Command::FilterThroughCommand) => "!".to_string(),
+                _ => String::new(),
+            },
+            PromptKind::Diff => String::new(),
+            _ => self
+                .file_path
+                .parent()
+                .map(|dir| format!("{}{}", dir.display(), std::path::MAIN_SEPARATOR))
+                .unwrap_or_default(),
+        };
+        self.prompt = Some(PathPrompt { kind, input });
+        true
+    }
+
+    /// Look up the name at the cursor in the workspace, for files no
+    /// language server answers for: its definitions, jumped to straight
+    /// away when there is one, or with `references` every use of it, listed
+    /// in the find-in-files panel
+    fn find_symbol(&mut self, references: bool) {
+        let state = self.editor.engine.state();
+        let Some((start, end)) = text_objects::word_range(&state.lines, state.cursor) else {
+            let message = "No name at the cursor".to_string();
+            self.editor.engine.set_hover(Some(message));
+            return;
+        };
+        let name = state.lines[start.row][start.column..end.column].to_string();
+        let root = project_search::workspace_root(&self.file_path);
+        let panel = self.search_panel.get_or_insert_with(SearchPanel::load);
+        if references {
+            panel.find_references(&root, &name);
+            self.definition_pending = None;
+            self.search_visible = true;
+        } else {
+            panel.find_definitions(&root, &name);
+            let message = format!("Finding {}…", name);
+            self.editor.engine.set_hover(Some(message));
+            self.definition_pending = Some(name);
+        }
+    }
+
+    /// Once the definitions being found are in, jump to the only one or
+    /// list them; returns whether anything changed
+    fn resolve_definition(&mut self) -> bool {
+        let Some(count) = self
+            .search_panel
+            .as_ref()
+            .and_then(SearchPanel::search)
+            .filter(|search| search.is_done())
+            .map(ProjectSearch::match_count)
+        else {
+            return false;
+        };
+        let Some(name) = self.definition_pending.take() else {
+            return false;
+        };
+        self.editor.engine.set_hover(None);
+        match count {
+            0 => {
+                let message = format!("No definition of {} found", name);
+                self.editor.engine.set_hover(Some(message));
+            }
+            1 => self.jump_to_search_result(),
+            _ => self.search_visible = true,
+        }
+        true
+    }
+
+    /// Keys for the find-in-files panel while it is shown, and the shortcuts
+    /// that show it
+    fn handle_search_key(&mut self, key: KeyEvent) -> bool {
+        let replacing = match keymap::command(&key) {
+            Some(Command::FindInFiles) => Some(false),
+            Some(Command::ReplaceInFiles) => Some(true),
+            Some(Command::FindReferences) => {
+                self.find_symbol(true);
+                return true;
+            }
+            _ => None,
+        };
+        if let Some(replacing) = replacing {
+            self.search_visible = true;
+            self.search_panel
+                .get_or_insert_with(SearchPanel::load)
+                .set_replacing(replacing);
+            return true;
+        }
+        if !self.search_visible {
+            return false;
+        }
+        let Some(panel) = self.search_panel.as_mut() else {
+            self.search_visible = false;
+            return false;
+        };
+
+        let alt = key.modifiers.contains(KeyModifiers::ALT);
+        match key.code {
+            KeyCode::Esc => self.search_visible = false,
+            KeyCode::Enter if alt => {
+                panel.apply();
+            }
+            KeyCode::Char('x') if alt => panel.toggle_selected(),
+            KeyCode::Char('w') if alt => panel.toggle_whole_word(),
+            KeyCode::Char('c') if alt => panel.toggle_preserve_case(),
+            KeyCode::Char('u') if alt => {
+                panel.undo_selected_file();
+            }
+            KeyCode::Enter if panel.search().is_some() => self.jump_to_search_result(),
+            KeyCode::Enter => {
+                let _ = WorkspaceState::add_search(&panel.query);
+                panel.start(&project_search::workspace_root(&self.file_path));
+            }
+            KeyCode::Tab => panel.switch_field(),
+            // In the prompt, Up and Down go through past queries
+            KeyCode::Up if panel.search().is_none() => panel.history_previous(),
+            KeyCode::Down if panel.search().is_none() => panel.history_next(),
+            KeyCode::Up => panel.select_previous(),
+            KeyCode::Down => panel.select_next(),
+            KeyCode::Backspace => panel.backspace(),
+            KeyCode::Char(c)
+                if !key
+                    .modifiers
+                    .intersects(KeyModifiers::CONTROL | KeyModifiers::ALT) =>
+            {
+                panel.type_text(c.encode_utf8(&mut [0; 4]));
+            }
+            _ => {}
+        }
+        true
+    }
+
+    /// Carry out a command typed at the `:` prompt
+    fn run_ex(&mut self, input: &str) {
+        let command = match ex::Command::parse(input) {
+            Ok(command) => command,
+            Err(e) => {
+                self.editor.engine.set_hover(Some(e.to_string()));
+                return;
+            }
+        };
+        match command {
+            // Edits are saved as they are made, so writing only reports it
+            ex::Command::Write(None) if !self.holds_file() => {
+                let message = format!("{} hasn't loaded yet", display_name(&self.file_path));
+                self.editor.engine.set_hover(Some(message));
+            }
+            ex::Command::Write(None) => {
+                self.editor.engine.wait_for_file_io();
+                let message = match self.editor.engine.save_to_file(&self.file_path) {
+                    Ok(()) => format!("Wrote {}", self.file_path.display()),
+                    Err(e) => format!("Could not write: {}", e),
+                };
+                self.editor.engine.set_hover(Some(message));
+            }
+            ex::Command::Write(Some(path)) => {
+                let path = expand_path(&path.to_string_lossy());
+                let message = match self.editor.engine.save_to_file(&path) {
+                    Ok(()) => format!("Wrote {}", path.display()),
+                    Err(e) => format!("Could not write: {}", e),
+                };
+                self.editor.engine.set_hover(Some(message));
+            }
+            ex::Command::Quit | ex::Command::WriteQuit => self.quit_requested = true,
+            ex::Command::Edit(path) => {
+                self.open_file(expand_path(&path.to_string_lossy()));
+            }
+            command => {
+                let revision = self.editor.engine.revision();
+                let dir = self.file_path.parent();
+                if let Err(e) = command.execute_in(&mut self.editor.engine, dir) {
+                    self.editor.engine.set_hover(Some(e.to_string()));
+                } else if self.editor.engine.revision() != revision {
+                    self.saved_edit();
+                }
+            }
+        }
+    }
+
+    /// Open the file of the highlighted match and select it
+    fn jump_to_search_result(&mut self) {
+        let Some(panel) = self.search_panel.as_ref() else {
+            return;
+        };
+        let Some((path, start, end)) = panel.selected_match() else {
+            return;
+        };
+        let query = panel.query.clone();
+
+        if path != self.file_path && !self.open_file(path) {
+            return;
+        }
+        self.search_visible = false;
+        self.editor.engine.set_search_query(Some(query));
+        self.editor
+            .engine
+            .handle_action(EditorAction::StartSelection {
+                row: start.row,
+                column: start.column,
+            });
+        self.editor
+            .engine
+            .handle_action(EditorAction::ExtendSelection {
+                row: end.row,
+                column: end.column,
+            });
+    }
+
+    fn run_prompt(&mut self, prompt: PathPrompt) {
+        let input = prompt.input.trim();
+        if prompt.kind == PromptKind::Command {
+            self.run_ex(input);
+            return;
+        }
+        if prompt.kind == PromptKind::Diff {
+            let dir = self.file_path.parent().unwrap_or(std::path::Path::new("."));
+            self.open_diff_view(DiffSource::parse(input, dir));
+            return;
+        }
+        if prompt.kind == PromptKind::Passphrase {
+            if let Some(path) = self.locked_file.take() {
+                if self.editor.engine.set_passphrase(&prompt.input).is_ok() {
+                    self.open_file(path);
+                }
+            }
+            return;
+        }
+        if input.is_empty() {
+            return;
+        }
+        let path = expand_path(input);
+        match prompt.kind {
+            PromptKind::NewFromTemplate => {
+                let Some(template) = self.template.take() else {
+                    return;
+                };
+                match templates::create(&path, &template, system_clipboard()) {
+                    Ok(()) => {
+                        self.open_file(path);
+                    }
+                    Err(e) => self
+                        .editor
+                        .engine
+                        .set_hover(Some(format!("Cannot create note: {}", e))),
+                }
+            }
+            PromptKind::Open | PromptKind::New if self.editor.engine.needs_passphrase(&path) => {
+                self.locked_file = Some(path);
+                self.prompt = Some(PathPrompt {
+                    kind: PromptKind::Passphrase,
+                    input: String::new(),
+                });
+            }
+            // A new file that already exists is simply opened
+            PromptKind::Open | PromptKind::New => {
+                self.open_file(path);
+            }
+            PromptKind::SaveAs => self.save_as(path),
+            PromptKind::ExportHtml => self.export_html(&path),
+            PromptKind::ExportPdf => self.export_pdf(&path),
+            PromptKind::ExportImage => self.export_image(&path),
+            kind => self.run_tree_operation(kind, path),
+        }
+    }
+
+    /// Keys for the file tree while it has focus, and the shortcut that
+    /// shows and focuses it
+    fn handle_tree_key(&mut self, key: KeyEvent) -> bool {
+        if keymap::command(&key) == Some(Command::FileTree) {
+            if !self.tree_visible {
+                let root = project_search::workspace_root(&self.file_path);
+                let tree = self.file_tree.get_or_insert_with(|| FileTree::new(&root));
+                tree.select_path(&self.file_path);
+                self.tree_visible = true;
+                self.tree_focused = true;
+            } else if self.tree_focused {
+                self.tree_visible = false;
+                self.tree_focused = false;
+            } else {
+                self.tree_focused = true;
+            }
+            return true;
+        }
+        if !self.tree_focused {
+            return false;
+        }
+        let Some(tree) = self.file_tree.as_mut() else {
+            self.tree_focused = false;
+            return false;
+        };
+
+        let prompt = match key.code {
+            KeyCode::Esc => {
+                self.tree_focused = false;
+                None
+            }
+            KeyCode::Up => {
+                tree.select_previous();
+                None
+            }
+            KeyCode::Down => {
+                tree.select_next();
+                None
+            }
+            KeyCode::Left => {
+                tree.collapse_selected();
+                None
+            }
+            KeyCode::Right => {
+                tree.expand_selected();
+                None
+            }
+            KeyCode::Enter => {
+                self.open_tree_entry(self.file_tree.as_ref().map_or(0, |t| t.selected));
+                None
+            }
+            KeyCode::Char('a') => Some(PromptKind::CreateFile),
+            KeyCode::Char('A') => Some(PromptKind::CreateFolder),
+            KeyCode::Char('r') => Some(PromptKind::Rename),
+            KeyCode::Char('m') => Some(PromptKind::Move),
+            KeyCode::Char('d') => Some(PromptKind::Delete),
+            _ => None,
+        };
+
+        if let Some(kind) = prompt {
+            let Some(tree) = self.file_tree.as_ref() else {
+                return true;
+            };
+            let selected = tree.selected_entry().map(|entry| entry.path.clone());
+            let creating = matches!(kind, PromptKind::CreateFile | PromptKind::CreateFolder);
+            if selected.is_none() && !creating {
+                return true;
+            }
+            let with_separator =
+                |dir: &std::path::Path| format!("{}{}", dir.display(), std::path::MAIN_SEPARATOR);
+            let input = match kind {
+                PromptKind::CreateFile | PromptKind::CreateFolder => {
+                    with_separator(&tree.target_dir())
+                }
+                PromptKind::Move => selected
+                    .as_deref()
+                    .and_then(std::path::Path::parent)
+                    .map(with_separator)
+                    .unwrap_or_default(),
+                _ => selected
+                    .map(|path| path.display().to_string())
+                    .unwrap_or_default(),
+            };
+            self.prompt = Some(PathPrompt { kind, input });
+        }
+        true
+    }
+
+    /// Open a file from the tree and focus the buffer, or expand/collapse a directory
+    fn open_tree_entry(&mut self, index: usize) {
+        let Some(tree) = self.file_tree.as_mut() else {
+            return;
+        };
+        tree.selected = index;
+        let Some(entry) = tree.selected_entry() else {
+            return;
+        };
+        if entry.is_dir {
+            tree.toggle(index);
+        } else {
+            let path = entry.path.clone();
+            if path != self.file_path {
+                self.open_file(path);
+            }
+            self.tree_focused = false;
+        }
+    }
+
+    fn run_tree_operation(&mut self, kind: PromptKind, path: std::path::PathBuf) {
+        let Some(tree) = self.file_tree.as_mut() else {
+            return;
+        };
+        let selected = tree.selected_entry().map(|entry| entry.path.clone());
+        let _ = match (kind, selected) {
+            (PromptKind::CreateFile, _) => tree.create_file(&path),
+            (PromptKind::CreateFolder, _) => tree.create_dir(&path),
+            (PromptKind::Rename, Some(from)) => tree
+                .rename(&from, &path)
+                .map(|()| self.follow_move(&from, &path)),
+            (PromptKind::Move, Some(from)) => tree
+                .move_into(&from, &path)
+                .map(|to| self.follow_move(&from, &to)),
+            (PromptKind::Delete, Some(deleted)) => tree.delete(&deleted).map(|()| {
+                // Don't let autosave bring the deleted file back
+                if self.file_path.starts_with(&deleted) {
+                    self.load_file(EditorEngine::default_file_path());
+                }
+            }),
+            _ => Ok(()),
+        };
+    }
+
+    /// Keep editing the current file after it, or a directory above it, moved
+    fn follow_move(&mut self, from: &std::path::Path, to: &std::path::Path) {
+        if let Ok(rest) = self.file_path.strip_prefix(from) {
+            self.file_path = to.join(rest);
+            self.lsp.open(&self.file_path, &self.editor.engine);
+        }
+    }
+
+    /// Switch to the file containing a definition found by the language server
+    fn open_location(&mut self, location: Location) {
+        if !self.open_file(location.path.clone()) {
+            return;
+        }
+
+        let pos = location.position_in(&self.editor.engine.state().lines);
+        self.editor
+            .engine
+            .handle_action(EditorAction::SetCursorPosition {
+                row: pos.row,
+                column: pos.column,
+            });
+    }
+
+    fn save_scroll_position(&mut self) {
+        if self.transient {
+            return;
+        }
+        let scroll_line = self.editor.scroll_offset as usize;
+        let cursor = self.editor.engine.state().cursor;
+        let anchor = self.editor.engine.state().selection_anchor;
+        let bookmarks = self.editor.engine.bookmarks();
+        if scroll_line != self.saved_scroll_line
+            || cursor != self.saved_cursor
+            || anchor != self.saved_anchor
+            || bookmarks != self.saved_bookmarks
+        {
+            self.saved_scroll_line = scroll_line;
+            self.saved_cursor = cursor;
+            self.saved_anchor = anchor;
+            self.saved_bookmarks = bookmarks.clone();
+            let _ = WorkspaceState::update_file(&self.file_path, |file| {
+                file.scroll_line = scroll_line;
+                file.cursor_row = cursor.row;
+                file.cursor_column = cursor.column;
+                file.selection_anchor = anchor;
+                file.bookmarks = bookmarks;
+            });
+        }
+    }
+
+    /// Rows the terminal panel takes below the text, or 0 while it is
+    /// hidden
+    fn terminal_panel_height(&self) -> u16 {
+        if self.terminal_visible {
+            (self.editor.engine.config().terminal.height.max(1) + 1)
+                .min(self.terminal_size.height / 2)
+        } else {
+            0
+        }
+    }
+
+    /// Rows taken by the diagnostics panel at the bottom of the screen
+    fn diagnostics_panel_height(&self) -> u16 {
+        if self.editor.engine.diagnostics_panel_visible() {
+            self.editor.engine.diagnostics().len().clamp(1, 8) as u16 + 1
+        } else {
+            0
+        }
+    }
+
+    /// Width of the file tree on the left, or 0 while it is hidden
+    fn sidebar_width(&self) -> u16 {
+        if self.tree_visible {
+            SIDEBAR_WIDTH.min(self.terminal_size.width / 2)
+        } else {
+            0
+        }
+    }
+
+    /// Width of the outline along the right edge, or 0 while it is hidden
+    fn outline_width(&self) -> u16 {
+        if self.outline.is_some() {
+            SIDEBAR_WIDTH.min(
+                self.terminal_size
+                    .width
+                    .saturating_sub(self.sidebar_width())
+                    / 2,
+            )
+        } else {
+            0
+        }
+    }
+
+    /// First tree entry shown, keeping the selected one in view
+    fn tree_offset(&self, tree: &FileTree) -> usize {
+        let height = self.terminal_size.height.saturating_sub(1).max(1) as usize;
+        tree.selected.saturating_sub(height - 1)
+    }
+
+    /// Select and open the tree entry under a click; returns whether the
+    /// click was in the sidebar
+    fn handle_tree_click(&mut self, column: u16, row: u16) -> bool {
+        if column >= self.sidebar_width() {
+            return false;
+        }
+        let Some(tree) = self.file_tree.as_ref() else {
+            return false;
+        };
+        self.tree_focused = true;
+        if let Some(row) = row.checked_sub(1) {
+            let index = self.tree_offset(tree) + row as usize;
+            if index < tree.entries().len() {
+                self.open_tree_entry(index);
+            }
+        }
+        true
+    }
+
+    /// Rows available for text between the padding and the diagnostics panel
+    fn text_height(&self) -> u16 {
+        self.terminal_size
+            .height
+            .saturating_sub(2 + self.diagnostics_panel_height() + self.terminal_panel_height())
+    }
+
+    fn check_and_reload(&mut self) -> bool {
+        if let Ok(metadata) = std::fs::metadata(&self.file_path) {
+            if let Ok(modified) = metadata.modified() {
+                let changed = self.last_modified.is_none_or(|last| modified > last);
+                if let (true, Some(live)) = (changed, self.live_sync.as_mut()) {
+                    self.last_modified = Some(modified);
+                    let Ok(content) = std::fs::read_to_string(&self.file_path) else {
+                        return false;
+                    };
+                    let Ok(Some(text)) = live.file_changed(&content) else {
+                        return false;
+                    };
+                    self.editor.engine.merge_text(&text);
+                    self.sync_revision = self.editor.engine.revision();
+                    return true;
+                }
+                if changed && self.editor.engine.load_from_file(&self.file_path).is_ok() {
+                    self.last_modified = Some(modified);
+                    return true;
+                }
+            }
+        }
+        false
+    }
+
+    /// Follow the link under a Ctrl+click; returns whether there was one
+    fn follow_link_at(&mut self, column: u16, row: u16) -> bool {
+        match self.editor.screen_to_document(column, row) {
+            Some((row, column)) => self.follow_link(row, column),
+            None => false,
+        }
+    }
+
+    /// Open the `[[note]]`, Markdown link, URL or file path written at a
+    /// position; returns whether there was one
+    fn follow_link(&mut self, row: usize, column: usize) -> bool {
+        let line = &self.editor.engine.state().lines[row];
+        if let Some(link) = notes::link_at(line, column) {
+            let path = notes::note_path(&self.notes_dir(), &link.name);
+            self.open_file(path);
+            return true;
+        }
+        if let Some(target) = self.highlighter.link_at(row, column) {
+            let target = LinkTarget::resolve(target, &self.file_path);
+            self.open_link_target(target);
+            return true;
+        }
+        let Some(link) = links::link_at(line, column) else {
+            return false;
+        };
+        match link.resolve(Some(&self.file_path)) {
+            // A bare path is only a guess, so it has to exist
+            LinkTarget::File(path) if !path.is_file() => {
+                self.editor
+                    .engine
+                    .set_hover(Some(format!("No file at {}", path.display())));
+            }
+            target => {
+                if self.open_link_target(target) {
+                    if let Some(line) = link.line {
+                        self.editor
+                            .engine
+                            .handle_action(EditorAction::SetCursorPosition {
+                                row: line.saturating_sub(1),
+                                column: link.column.unwrap_or(1).saturating_sub(1),
+                            });
+                    }
+                }
+            }
+        }
+        true
+    }
+
+    /// Open a URL in the browser or a file in the editor; returns whether a
+    /// file was opened
+    fn open_link_target(&mut self, target: LinkTarget) -> bool {
+        match target {
+            LinkTarget::Url(url) => {
+                if let Err(e) = open_url(&url) {
+                    self.editor
+                        .engine
+                        .set_hover(Some(format!("Could not open {}: {}", url, e)));
+                }
+                false
+            }
+            LinkTarget::File(path) => self.open_file(path),
+        }
+    }
+
+    fn run(&mut self) -> Result<()> {
+        // Input already comes from the terminal when stdin is piped
+        enable_raw_mode()?;
+        let mut stdout = terminal_output()?;
+        execute!(
+            stdout,
+            EnterAlternateScreen,
+            EnableMouseCapture,
+            EnableFocusChange
+        )?;
+        // The bar style uses the terminal's own cursor, which does its own blinking
+        if self.editor.cursor_style() == CursorStyle::Bar {
+            let shape = if self.editor.engine.config().cursor.blink {
+                SetCursorStyle::BlinkingBar
+            } else {
+                SetCursorStyle::SteadyBar
+            };
+            execute!(stdout, shape)?;
+        }
+
+        let backend = CrosstermBackend::new(stdout);
+        let mut terminal = Terminal::new(backend)?;
+
+        let result = self.run_loop(&mut terminal);
+
+        disable_raw_mode()?;
+        execute!(
+            terminal.backend_mut(),
+            LeaveAlternateScreen,
+            DisableMouseCapture,
+            DisableFocusChange,
+            SetCursorStyle::DefaultUserShape
+        )?;
+
+        result
+    }
+
+    fn run_loop(
+        &mut self,
+        terminal: &mut Terminal<CrosstermBackend<Box<dyn Write>>>,
+    ) -> Result<()> {
+        let (wake, wakes) = mpsc::channel();
+        spawn_input_reader(wake.clone());
+        let mut watched: Option<(std::path::PathBuf, Option<RecommendedWatcher>)> = None;
+        // The open file may have changed on disk since it was last checked
+        let mut file_changed = true;
+        // Something besides the text area changed, such as a panel or the
+        // terminal's size, so the next frame has to be drawn
+        let mut redraw = true;
+        let mut last_draw = Instant::now();
+        // Last terminal input, after which replies from language servers and
+        // the grammar checker to the edits it made may still arrive
+        let mut last_activity = Instant::now();
+        loop {
+            if watched
+                .as_ref()
+                .is_none_or(|(path, _)| *path != self.file_path)
+            {
+                let watcher = watch_file(&self.file_path, wake.clone());
+                watched = Some((self.file_path.clone(), watcher));
+                file_changed = true;
+            }
+            let watching = watched
+                .as_ref()
+                .is_some_and(|(_, watcher)| watcher.is_some());
+
+            // Check for file changes before rendering, once our own saves
+            // have landed
+            redraw |= self.poll_file_io();
+            self.poll_live_sync();
+            let revision = self.editor.engine.revision();
+            if self.editor.engine.poll_shell_command() {
+                if self.editor.engine.revision() != revision {
+                    self.saved_edit();
+                }
+                redraw = true;
+            }
+            let revision = self.editor.engine.revision();
+            if self.editor.engine.poll_dictation() {
+                if self.editor.engine.revision() != revision {
+                    self.saved_edit();
+                }
+                redraw = true;
+            }
+            if (file_changed || !watching) && !self.editor.engine.file_io_pending() {
+                redraw |= self.check_and_reload();
+                file_changed = false;
+            }
+
+            // Apply diagnostics and answers from the language server
+            let lsp = self.lsp.process(&mut self.editor.engine);
+            redraw |= lsp.changed;
+            if let Some(location) = lsp.jump {
+                self.open_location(location);
+                redraw = true;
+            }
+            // A server that found nothing, or doesn't list symbols, leaves
+            // the outline read from the buffer
+            if let Some(symbols) = lsp.symbols.filter(|symbols| !symbols.is_empty()) {
+                self.server_symbols = true;
+                self.set_symbols(symbols);
+            }
+            if (self.outline.is_some() || self.symbol_palette.is_some() || self.breadcrumbs_shown())
+                && self.symbols_revision != self.editor.engine.revision()
+            {
+                self.refresh_symbols();
+                redraw = true;
+            }
+            if let Some(grammar) = self.grammar.as_mut() {
+                redraw |= grammar.poll(&mut self.editor.engine);
+            }
+            if let Some(panel) = self.search_panel.as_mut() {
+                redraw |= panel.poll();
+            }
+            if self.definition_pending.is_some() {
+                redraw |= self.resolve_definition();
+            }
+            if let Some(index) = self.tags.as_mut() {
+                redraw |= index.poll();
+            }
+            if let Some(panel) = self.todos.as_mut() {
+                redraw |= panel.poll();
+            }
+            if self.task_run.as_mut().is_some_and(TaskRun::poll) {
+                self.publish_problems();
+                redraw = true;
+            }
+            if let Some(search) = self.note_search.as_mut() {
+                redraw |= search.poll();
+            }
+            // Diffing or highlighting a large file on every edit would stall
+            let path =
+                Some(self.file_path.as_path()).filter(|_| !self.editor.engine.is_large_file());
+            redraw |= self.git_gutter.update(
+                path,
+                &self.editor.engine.state().lines,
+                self.editor.engine.revision(),
+            );
+            self.highlighter.set_brackets(
+                self.editor.engine.config().view.rainbow_brackets
+                    && !self.editor.engine.is_large_file(),
+            );
+            self.highlighter.update(
+                path,
+                &self.editor.engine.state().lines,
+                self.editor.engine.revision(),
+            );
+            if self.indent_guides_shown() {
+                self.indent_guides.update(
+                    &self.editor.engine.state().lines,
+                    self.editor.engine.revision(),
+                );
+            }
+
+            redraw |= self
+                .terminal_panel
+                .as_mut()
+                .is_some_and(terminal::Terminal::poll);
+
+            // Update terminal size for coordinate translation
+            let size = Rect::from((Position::ORIGIN, terminal.size()?));
+            redraw |= size != self.terminal_size;
+            self.terminal_size = size;
+
+            // Ensure cursor is visible before rendering
+            let visible_height = self.text_height();
+            self.editor.ensure_cursor_visible(visible_height);
+            let top = self.editor.scroll_offset as usize;
+            self.editor
+                .engine
+                .set_visible_rows(top..top + visible_height as usize);
+
+            let status = self.accessibility_status();
+            redraw |= self
+                .announcer
+                .update(self.editor.engine.state(), status.as_deref())
+                .is_some();
+
+            // The writing session's clock and upload progress move on their own
+            let ticking = self.editor.engine.writing_session().is_some()
+                || self.editor.engine.transfer_status().is_some();
+            let tick = ticking && last_draw.elapsed() >= STATUS_TICK;
+            if self.editor.needs_redraw() || redraw || tick {
+                terminal.draw(|frame| self.render(frame))?;
+                redraw = false;
+                last_draw = Instant::now();
+            }
+            if self.save_due.is_some_and(|due| Instant::now() >= due) {
+                self.save_now();
+            }
+            self.save_scroll_position();
+            let _ = self.hooks.poll(&self.file_path);
+            self.record_history();
+            self.update_note_index();
+
+            // Sleep until input, a change to the file, or the next timer
+            let timeout = self.next_timer(last_draw, last_activity, watching);
+            let wake = match timeout {
+                Some(timeout) => wakes.recv_timeout(timeout).ok(),
+                None => wakes.recv().ok(),
+            };
+            let event = match wake {
+                Some(Wake::Input(event)) => event?,
+                Some(Wake::FileChanged) => {
+                    file_changed = true;
+                    continue;
+                }
+                None => continue,
+            };
+            redraw = true;
+            last_activity = Instant::now();
+            if matches!(event, Event::Key(_) | Event::Mouse(_)) {
+                self.editor.mark_input();
+            }
+            match event {
+                Event::Key(key) if self.handle_opening_key(key) => {}
+                Event::Key(key) if self.handle_help_key(key) => {}
+                Event::Key(key) if self.handle_terminal_key(key) => {}
+                Event::Key(key) if self.handle_diff_key(key) => {}
+                Event::Key(key) if self.handle_history_key(key) => {}
+                Event::Key(key) if self.handle_backlinks_key(key) => {}
+                Event::Key(key) if self.handle_bookmarks_key(key) => {}
+                Event::Key(key) if self.handle_tags_key(key) => {}
+                Event::Key(key) if self.handle_task_key(key) => {}
+                Event::Key(key) if self.handle_todos_key(key) => {}
+                Event::Key(key) if self.handle_symbol_palette_key(key) => {}
+                Event::Key(key) if self.handle_note_search_key(key) => {}
+                Event::Key(key) if self.handle_template_key(key) => {}
+                Event::Key(key) if self.handle_char_picker_key(key) => {}
+                Event::Key(key) if self.handle_switch_key(key) => {}
+                Event::Key(key) if self.handle_file_key(key) => {}
+                Event::Key(key) if self.handle_search_key(key) => {}
+                Event::Key(key) if self.handle_tree_key(key) => {}
+                Event::Key(key) if self.handle_outline_key(key) => {}
+                Event::Key(key) if self.handle_change_key(key) => {}
+                Event::Key(key) => {
+                    if let Some(action) = self.editor.translate_key(key) {
+                        if matches!(
+                            action,
+                            EditorAction::IncreaseFontSize
+                                | EditorAction::DecreaseFontSize
+                                | EditorAction::ResetFontSize
+                        ) {
+                            // The terminal draws the text, so it does the zooming
+                            self.editor.engine.set_hover(Some(
+                                "Zoom with the terminal's font size keys".to_string(),
+                            ));
+                            continue;
+                        }
+                        if matches!(action, EditorAction::OpenLinkUnderCursor) {
+                            let cursor = self.editor.engine.state().cursor;
+                            if !self.follow_link(cursor.row, cursor.column) {
+                                self.editor
+                                    .engine
+                                    .set_hover(Some("No link at the cursor".to_string()));
+                            }
+                            continue;
+                        }
+                        if matches!(action, EditorAction::GoToDefinition) && !self.lsp.is_open() {
+                            self.find_symbol(false);
+                            continue;
+                        }
+                        if matches!(action, EditorAction::Quit) {
+                            self.quit_requested = true;
+                            break;
+                        }
+                        let revision = self.editor.engine.revision();
+                        self.editor.engine.handle_action(action.clone());
+                        self.lsp.sync(&self.editor.engine);
+                        self.lsp.handle_action(&action, &self.editor.engine);
+                        if self.editor.engine.revision() != revision {
+                            let _ = self.hooks.edited(&self.file_path);
+                        }
+
+                        self.autosave(revision);
+                    }
+                }
+                Event::Mouse(mouse) => {
+                    match mouse.kind {
+                        MouseEventKind::ScrollUp => {
+                            self.editor.scroll(-1);
+                        }
+                        MouseEventKind::ScrollDown => {
+                            self.editor.scroll(1);
+                        }
+                        MouseEventKind::Down(MouseButton::Left)
+                            if self.handle_tree_click(mouse.column, mouse.row) => {}
+                        MouseEventKind::Down(MouseButton::Left)
+                            if self.handle_breadcrumb_click(mouse.column, mouse.row) => {}
+                        MouseEventKind::Down(MouseButton::Left)
+                            if mouse.modifiers.contains(KeyModifiers::CONTROL)
+                                && self.follow_link_at(mouse.column, mouse.row) => {}
+                        _ => {
+                            if let Some(action) = self.editor.translate_mouse(mouse) {
+                                let revision = self.editor.engine.revision();
+                                self.editor.engine.handle_action(action);
+
+                                // Ensure cursor visibility after mouse action
+                                let visible_height = self.text_height();
+                                self.editor.ensure_cursor_visible(visible_height);
+
+                                self.autosave(revision);
+                            }
+                        }
+                    }
+                }
+                Event::FocusLost => {
+                    let _ = self.hooks.fire(HookEvent::FocusLost, &self.file_path);
+                }
+                _ => {}
+            }
+            if self.quit_requested {
+                break;
+            }
+        }
+        // Save before quitting, after any save still under way
+        self.editor.engine.wait_for_file_io();
+        if self.holds_file() {
+            let _ = self.editor.engine.save_to_file(&self.file_path);
+        }
+        let _ = self.hooks.flush(&self.file_path);
+        self.snapshot_history();
+        self.hooks.wait();
+        Ok(())
+    }
+
+    /// How long the loop may sleep before a timer needs it: the cursor
+    /// blinking, the status bar's clock, a hook coming due, or background
+    /// work whose results are checked by polling. `None` when only input or
+    /// a change to the file can make a difference.
+    fn next_timer(
+        &self,
+        last_draw: Instant,
+        last_activity: Instant,
+        watching: bool,
+    ) -> Option<Duration> {
+        let engine = &self.editor.engine;
+        let ticking = engine.writing_session().is_some() || engine.transfer_status().is_some();
+        let background = last_activity.elapsed() < REPLY_WINDOW
+            || self.live_sync.is_some()
+            || self
+                .search_panel
+                .as_ref()
+                .and_then(SearchPanel::search)
+                .is_some_and(|search| !search.is_done())
+            || engine.shell_command().is_some()
+            || engine.dictating()
+            || self.tags.as_ref().is_some_and(|tags| !tags.is_done())
+            || self.todos.as_ref().is_some_and(|todos| !todos.is_done())
+            || self.task_run.as_ref().is_some_and(|run| !run.is_done())
+            || self
+                .note_search
+                .as_ref()
+                .is_some_and(|search| !search.is_ready());
+
+        [
+            self.editor.next_blink(),
+            ticking.then(|| STATUS_TICK.saturating_sub(last_draw.elapsed())),
+            self.hooks.next_due(),
+            self.save_due
+                .map(|due| due.saturating_duration_since(Instant::now())),
+            (background || engine.file_io_pending()).then_some(BACKGROUND_POLL),
+            (!watching).then_some(FILE_POLL),
+            self.terminal_panel
+                .as_ref()
+                .is_some_and(terminal::Terminal::is_running)
+                .then_some(TERMINAL_POLL),
+        ]
+        .into_iter()
+        .flatten()
+        .min()
+    }
+
+    fn render(&mut self, frame: &mut ratatui::Frame) {
+        // Create a rect with padding on all sides
+        let area = frame.area();
+        let sidebar_width = self.sidebar_width();
+        let padded_area = Rect {
+            x: area.x + 2 + sidebar_width,
+            y: area.y + 1,
+            width: area
+                .width
+                .saturating_sub(4 + sidebar_width + self.outline_width()),
+            height: area
+                .height
+                .saturating_sub(2 + self.diagnostics_panel_height() + self.terminal_panel_height()),
+        };
+
+        frame.render_stateful_widget(
+            EditorWidget::default().highlighter(&self.highlighter),
+            padded_area,
+            &mut self.editor,
+        );
+        if self.editor.cursor_style() == CursorStyle::Bar {
+            if let Some((x, y)) = self.editor.cursor_screen_position() {
+                frame.set_cursor_position((x, y));
+            }
+        }
+        self.render_indent_guides(frame, padded_area);
+        self.render_change_markers(frame, padded_area);
+        self.render_gutter_icons(frame, padded_area);
+        self.render_diagnostic_markers(frame, padded_area);
+        self.render_diagnostics_panel(frame, padded_area);
+        self.render_terminal_panel(frame, padded_area);
+        self.render_breadcrumbs(frame, padded_area);
+        self.render_status(frame);
+        self.render_completion(frame, padded_area);
+        self.render_hover(frame, padded_area);
+        self.render_file_tree(frame);
+        self.render_outline(frame);
+        if let Some(panel) = self.search_panel.as_ref().filter(|_| self.search_visible) {
+            panels::render_search_panel(frame, panel);
+        }
+        if let Some(switch) = &self.quick_switch {
+            panels::render_quick_switch(frame, switch);
+        }
+        if let Some(browser) = &self.history_browser {
+            panels::render_history_browser(frame, browser);
+        }
+        if let Some(panel) = &self.backlinks {
+            panels::render_backlinks(frame, panel);
+        }
+        if let Some(panel) = &self.bookmarks {
+            panels::render_bookmarks(frame, panel);
+        }
+        if let Some(index) = &self.tags {
+            panels::render_tags(frame, index);
+        }
+        if let Some(panel) = &self.todos {
+            panels::render_todos(frame, panel);
+        }
+        if let Some(run) = self.task_run.as_ref().filter(|_| self.errors_visible) {
+            panels::render_errors(frame, run);
+        }
+        if let Some(palette) = &self.symbol_palette {
+            panels::render_symbol_palette(frame, palette);
+        }
+        if let Some(search) = self
+            .note_search
+            .as_ref()
+            .filter(|_| self.note_search_visible)
+        {
+            panels::render_note_search(frame, search);
+        }
+        if let Some(picker) = &self.template_picker {
+            panels::render_template_picker(frame, picker);
+        }
+        if let Some(picker) = &self.char_picker {
+            panels::render_char_picker(frame, picker);
+        }
+        self.render_diff_view(frame);
+        self.render_prompt(frame);
+        self.render_help(frame);
+    }
+
+    /// Where the help overlay is drawn: most of the screen, centered
+    fn help_rect(&self, area: Rect) -> Rect {
+        let width = area.width.saturating_sub(4).min(80);
+        Rect {
+            x: area.x + (area.width - width) / 2,
+            y: area.y + 1.min(area.height),
+            width,
+            height: area.height.saturating_sub(2),
+        }
+    }
 
-        // Ensure parent directory exists
-        if let Some(parent) = file_path.parent() {
-            let _ = std::fs::create_dir_all(parent);
+    /// How many bindings the help overlay shows at once, below its borders
+    /// and search line
+    fn help_page(&self) -> usize {
+        self.help_rect(self.terminal_size).height.saturating_sub(3) as usize
+    }
+
+    fn render_help(&self, frame: &mut ratatui::Frame) {
+        let Some(help) = self.help.as_ref() else {
+            return;
+        };
+        let rect = self.help_rect(frame.area());
+        let block = Block::default()
+            .borders(Borders::ALL)
+            .title(" Keys (Esc to close) ");
+        let inner = block.inner(rect);
+        frame.render_widget(Clear, rect);
+        frame.render_widget(block, rect);
+        if inner.height == 0 {
+            return;
         }
 
-        // Load existing file if it exists
-        let last_modified = if file_path.exists() {
-            let _ = engine.load_from_file(&file_path);
-            std::fs::metadata(&file_path)
-                .ok()
-                .and_then(|m| m.modified().ok())
+        let query_area = Rect { height: 1, ..inner };
+        frame.render_widget(
+            Paragraph::new(Line::from(vec![
+                Span::styled("> ", Style::default().fg(Color::Cyan)),
+                Span::raw(help.query.as_str()),
+            ])),
+            query_area,
+        );
+        let cursor_x = 2 + help.query.chars().count() as u16;
+        frame.set_cursor_position((
+            query_area.x + cursor_x.min(query_area.width.saturating_sub(1)),
+            query_area.y,
+        ));
+
+        let dim = Style::default().fg(Color::DarkGray);
+        let lines: Vec<Line> = help
+            .matches()
+            .into_iter()
+            .skip(help.scroll)
+            .map(|entry| {
+                Line::from(vec![
+                    Span::styled(format!("{:<11}", entry.section), dim),
+                    Span::styled(
+                        format!("{:<24} ", entry.keys.join(", ")),
+                        Style::default().fg(Color::Cyan),
+                    ),
+                    Span::raw(entry.action.clone()),
+                ])
+            })
+            .collect();
+        let list_area = Rect {
+            y: inner.y + 1,
+            height: inner.height - 1,
+            ..inner
+        };
+        frame.render_widget(Paragraph::new(lines), list_area);
+    }
+
+    /// Unified diff of the buffer against the other version, over the
+    /// whole screen with the current hunk near the top
+    fn render_diff_view(&self, frame: &mut ratatui::Frame) {
+        let Some(view) = self.diff_view.as_ref() else {
+            return;
+        };
+        let area = frame.area();
+        let status = if view.hunks().is_empty() {
+            "no differences".to_string()
         } else {
-            None
+            format!("hunk {}/{}", view.selected + 1, view.hunks().len())
         };
+        let block = Block::default().borders(Borders::ALL).title(format!(
+            " Diff with {} · {} · n/p move, t theirs, m mine, Esc close ",
+            view.title, status
+        ));
+        let inner = block.inner(area);
+        frame.render_widget(Clear, area);
+        frame.render_widget(block, area);
+
+        let mine = &self.editor.engine.state().lines;
+        let rows = view.unified_rows(mine.len());
+        let first = DiffView::selected_row(&rows, view.selected).saturating_sub(3);
+        let number =
+            |row: Option<usize>| row.map_or("    ".to_string(), |r| format!("{:>4}", r + 1));
+        let lines: Vec<Line> = rows
+            .iter()
+            .skip(first)
+            .take(inner.height as usize)
+            .map(|row| match *row {
+                DiffRow::Same { theirs, mine: row } => Line::from(format!(
+                    "{} {}   {}",
+                    number(Some(theirs)),
+                    number(Some(row)),
+                    mine[row]
+                )),
+                DiffRow::Changed {
+                    hunk,
+                    theirs,
+                    mine: row,
+                } => {
+                    let (text, sign, color) = match (theirs, row) {
+                        (Some(theirs), _) => (&view.theirs()[theirs], "-", Color::Red),
+                        (None, Some(row)) => (&mine[row], "+", Color::Green),
+                        (None, None) => return Line::default(),
+                    };
+                    let mut style = Style::default().fg(color);
+                    if hunk == view.selected {
+                        style = style.add_modifier(Modifier::BOLD);
+                    }
+                    Line::from(Span::styled(
+                        format!("{} {} {} {}", number(theirs), number(row), sign, text),
+                        style,
+                    ))
+                }
+                DiffRow::Fold(count) => Line::from(Span::styled(
+                    format!("          ⋯ {} unchanged lines", count),
+                    Style::default().fg(Color::DarkGray),
+                )),
+            })
+            .collect();
+        frame.render_widget(Paragraph::new(lines), inner);
+    }
 
-        Self {
-            engine,
-            file_path,
-            last_modified,
-            scroll_offset: 0,
-            terminal_size: Rect::default(),
-        }
+    /// Directory tree along the left edge; gitignored entries are dimmed and
+    /// the open file is highlighted
+    fn render_file_tree(&self, frame: &mut ratatui::Frame) {
+        let Some(tree) = self.file_tree.as_ref().filter(|_| self.tree_visible) else {
+            return;
+        };
+        let area = frame.area();
+        let rect = Rect {
+            width: self.sidebar_width(),
+            ..area
+        };
+
+        let items: Vec<ListItem> = tree
+            .entries()
+            .iter()
+            .map(|entry| {
+                let icon = match (entry.is_dir, entry.expanded) {
+                    (true, true) => "▾ ",
+                    (true, false) => "▸ ",
+                    (false, _) => "  ",
+                };
+                let style = if entry.path == self.file_path {
+                    Style::default()
+                        .fg(Color::Cyan)
+                        .add_modifier(Modifier::BOLD)
+                } else if entry.ignored {
+                    Style::default().fg(Color::DarkGray)
+                } else {
+                    Style::default().fg(Color::White)
+                };
+                ListItem::new(Span::styled(
+                    format!("{}{}{}", "  ".repeat(entry.depth), icon, entry.name),
+                    style,
+                ))
+            })
+            .collect();
+
+        let title = tree.root.file_name().map_or_else(
+            || tree.root.display().to_string(),
+            |name| name.to_string_lossy().into_owned(),
+        );
+        let border_color = if self.tree_focused {
+            Color::Cyan
+        } else {
+            Color::DarkGray
+        };
+        let list = List::new(items)
+            .block(
+                Block::default()
+                    .borders(Borders::RIGHT | Borders::TOP)
+                    .border_style(Style::default().fg(border_color))
+                    .title(format!(" {} ", title)),
+            )
+            .highlight_style(Style::default().bg(Color::DarkGray));
+        let mut list_state = ListState::default()
+            .with_selected(Some(tree.selected))
+            .with_offset(self.tree_offset(tree));
+
+        frame.render_widget(Clear, rect);
+        frame.render_stateful_widget(list, rect, &mut list_state);
     }
 
-    fn ensure_cursor_visible(&mut self, visible_height: u16) {
-        let cursor_row = self.engine.state().cursor.row as u16;
-        let padding = 2u16;
+    /// Symbols of the buffer along the right edge, indented by nesting.
+    /// While unfocused the one the cursor is in is highlighted.
+    fn render_outline(&self, frame: &mut ratatui::Frame) {
+        let Some(panel) = self.outline.as_ref() else {
+            return;
+        };
+        let area = frame.area();
+        let width = self.outline_width();
+        let rect = Rect {
+            x: area.x + area.width - width,
+            width,
+            ..area
+        };
 
-        // Scroll up if cursor is above visible area
-        if cursor_row < self.scroll_offset + padding {
-            self.scroll_offset = cursor_row.saturating_sub(padding);
+        let items: Vec<ListItem> = panel
+            .matches()
+            .into_iter()
+            .map(|symbol| {
+                ListItem::new(Line::from(vec![
+                    Span::raw("  ".repeat(symbol.depth)),
+                    Span::styled(
+                        format!("{} ", symbol.kind.label()),
+                        Style::default().fg(Color::DarkGray),
+                    ),
+                    Span::raw(symbol.name.clone()),
+                ]))
+            })
+            .collect();
+        let empty = items.is_empty();
+
+        let (title, border_color) = if self.outline_focused {
+            (format!(" > {} ", panel.query), Color::Cyan)
+        } else {
+            (" Outline ".to_string(), Color::DarkGray)
+        };
+        let block = Block::default()
+            .borders(Borders::LEFT | Borders::TOP)
+            .border_style(Style::default().fg(border_color))
+            .title(title);
+        let selected = if self.outline_focused {
+            Some(panel.selected)
+        } else {
+            panel.containing(self.editor.engine.state().cursor.row)
+        };
+        frame.render_widget(Clear, rect);
+        if empty {
+            frame.render_widget(
+                Paragraph::new(Span::styled(
+                    "No symbols",
+                    Style::default().fg(Color::DarkGray),
+                ))
+                .block(block),
+                rect,
+            );
+            return;
         }
+        let list = List::new(items)
+            .block(block)
+            .highlight_style(Style::default().bg(Color::DarkGray));
+        let mut list_state = ListState::default().with_selected(selected);
+        frame.render_stateful_widget(list, rect, &mut list_state);
+    }
 
-        // Scroll down if cursor is below visible area
-        if cursor_row >= self.scroll_offset + visible_height.saturating_sub(padding) {
-            self.scroll_offset =
-                cursor_row.saturating_sub(visible_height.saturating_sub(padding + 1));
+    /// Path prompt on the bottom row, with the terminal cursor at its end
+    fn render_prompt(&self, frame: &mut ratatui::Frame) {
+        let Some(prompt) = self.prompt.as_ref() else {
+            return;
+        };
+        let area = frame.area();
+        if area.height == 0 {
+            return;
         }
+        let label = match prompt.kind {
+            PromptKind::Command => prompt.kind.label().to_string(),
+            kind => format!("{}: ", kind.label()),
+        };
+        let input = if prompt.kind == PromptKind::Passphrase {
+            "•".repeat(prompt.input.chars().count())
+        } else {
+            prompt.input.clone()
+        };
+        let prompt_area = Rect {
+            x: area.x,
+            y: area.bottom() - 1,
+            width: area.width,
+            height: 1,
+        };
+        let line = Line::from(vec![
+            Span::styled(label.clone(), Style::default().fg(Color::Cyan)),
+            Span::raw(input),
+        ]);
+        frame.render_widget(Clear, prompt_area);
+        frame.render_widget(
+            Paragraph::new(line).style(Style::default().bg(Color::Black)),
+            prompt_area,
+        );
+
+        let cursor_x = (label.chars().count() + prompt.input.chars().count()) as u16;
+        frame.set_cursor_position((
+            prompt_area.x + cursor_x.min(area.width.saturating_sub(1)),
+            prompt_area.y,
+        ));
     }
 
-    /// Convert screen coordinates to document position
-    /// Returns None if click is outside the text area
-    fn screen_to_document(&self, screen_col: u16, screen_row: u16) -> Option<(usize, usize)> {
-        let area = self.terminal_size;
-
-        // Calculate padded area boundaries (matches render() logic)
-        let text_x_start = area.x + 2;
-        let text_y_start = area.y + 1;
-        let text_x_end = area.x + area.width.saturating_sub(2);
-        let text_y_end = area.y + area.height.saturating_sub(1);
-
-        // Check if click is within text area
-        if screen_col < text_x_start || screen_col >= text_x_end {
-            return None;
+    /// Decorations' gutter icons in the left padding, where a diagnostic's
+    /// dot takes their place
+    fn render_gutter_icons(&self, frame: &mut ratatui::Frame, text_area: Rect) {
+        let engine = &self.editor.engine;
+        if engine.decorations().is_empty() || text_area.x == 0 {
+            return;
         }
-        if screen_row < text_y_start || screen_row >= text_y_end {
-            return None;
+
+        for screen_row in 0..text_area.height {
+            let row = (self.editor.scroll_offset + screen_row) as usize;
+            if let Some((symbol, tone)) = engine.gutter_icon(row) {
+                frame.buffer_mut()[(text_area.x - 1, text_area.y + screen_row)]
+                    .set_symbol(symbol)
+                    .set_style(Style::default().fg(tone_color(tone)));
+            }
         }
+    }
 
-        // Convert to document coordinates
-        let doc_col = (screen_col - text_x_start) as usize;
-        let doc_row = (screen_row - text_y_start) as usize + self.scroll_offset as usize;
+    /// Colored dots in the left padding for lines with diagnostics
+    fn render_diagnostic_markers(&self, frame: &mut ratatui::Frame, text_area: Rect) {
+        let diagnostics = self.editor.engine.diagnostics();
+        if diagnostics.is_empty() || text_area.x == 0 {
+            return;
+        }
 
-        Some((doc_row, doc_col))
+        for screen_row in 0..text_area.height {
+            let row = (self.editor.scroll_offset + screen_row) as usize;
+            if let Some(severity) = diagnostics::worst_on_row(diagnostics, row) {
+                frame.buffer_mut()[(text_area.x - 1, text_area.y + screen_row)]
+                    .set_symbol("●")
+                    .set_style(Style::default().fg(severity_color(severity)));
+            }
+        }
     }
 
-    /// Clamp document position to valid bounds
-    fn clamp_to_document(&self, row: usize, column: usize) -> (usize, usize) {
-        let state = self.engine.state();
-        let row = row.min(state.lines.len().saturating_sub(1));
-        let column = column.min(state.lines[row].len());
-        (row, column)
-    }
+    /// Indentation guides in the blank cells of each line's indent, dim but
+    /// for the one around the cursor
+    fn render_indent_guides(&self, frame: &mut ratatui::Frame, text_area: Rect) {
+        if !self.indent_guides_shown() {
+            return;
+        }
 
-    fn check_and_reload(&mut self) -> bool {
-        if let Ok(metadata) = std::fs::metadata(&self.file_path) {
-            if let Ok(modified) = metadata.modified() {
-                if self.last_modified.map_or(true, |last| modified > last) {
-                    if self.engine.load_from_file(&self.file_path).is_ok() {
-                        self.last_modified = Some(modified);
-                        return true;
-                    }
+        let doc = self.editor.engine.state();
+        let active = self.indent_guides.active(doc.cursor.row);
+        let buffer = frame.buffer_mut();
+        for screen_row in 0..text_area.height {
+            let row = (self.editor.scroll_offset + screen_row) as usize;
+            if row >= doc.lines.len() {
+                break;
+            }
+            for column in self.indent_guides.columns(row) {
+                if column >= text_area.width as usize {
+                    break;
                 }
+                let cell = &mut buffer[(text_area.x + column as u16, text_area.y + screen_row)];
+                // Tabs and the cursor's cell are left as they are
+                if cell.symbol() != " " || (row, column) == (doc.cursor.row, doc.cursor.column) {
+                    continue;
+                }
+                let lit = active
+                    .as_ref()
+                    .is_some_and(|active| active.column == column && active.rows.contains(&row));
+                cell.set_symbol("│")
+                    .set_fg(if lit { Color::Gray } else { Color::DarkGray });
             }
         }
-        false
     }
 
-    fn render_cursor_line<'a>(
-        &self,
-        line: &'a str,
-        cursor_col: usize,
-        spans: &mut Vec<Span<'a>>,
-        cursor_style: Style,
-    ) {
-        if cursor_col == 0 {
-            // Cursor at start
-            if line.is_empty() {
-                spans.push(Span::styled(" ", cursor_style));
-            } else {
-                let cursor_char = line.chars().next().unwrap();
-                spans.push(Span::styled(cursor_char.to_string(), cursor_style));
-                spans.push(Span::raw(&line[cursor_char.len_utf8()..]));
-            }
-        } else if cursor_col >= line.len() {
-            // Cursor at end
-            spans.push(Span::raw(line));
-            spans.push(Span::styled(" ", cursor_style));
-        } else {
-            // Cursor in middle
-            let (before, rest) = line.split_at(cursor_col);
-            let cursor_char = rest.chars().next().unwrap();
-            let after = &rest[cursor_char.len_utf8()..];
+    /// Lines changed since the last commit, in the outer padding column
+    fn render_change_markers(&self, frame: &mut ratatui::Frame, text_area: Rect) {
+        if self.git_gutter.hunks().is_empty() || text_area.x < 2 {
+            return;
+        }
 
-            spans.push(Span::raw(before));
-            spans.push(Span::styled(cursor_char.to_string(), cursor_style));
-            spans.push(Span::raw(after));
+        let line_count = self.editor.engine.state().lines.len();
+        for screen_row in 0..text_area.height {
+            let row = (self.editor.scroll_offset + screen_row) as usize;
+            if row >= line_count {
+                break;
+            }
+            let (symbol, color) = match self.git_gutter.marker(row, line_count) {
+                Some(ChangeKind::Added) => ("▎", Color::Green),
+                Some(ChangeKind::Modified) => ("▎", Color::Yellow),
+                Some(ChangeKind::Removed) => ("▔", Color::Red),
+                None => continue,
+            };
+            frame.buffer_mut()[(text_area.x - 2, text_area.y + screen_row)]
+                .set_symbol(symbol)
+                .set_style(Style::default().fg(color));
         }
     }
 
-    fn run(&mut self) -> Result<()> {
-        enable_raw_mode()?;
-        let mut stdout = std::io::stdout();
-        execute!(stdout, EnterAlternateScreen, EnableMouseCapture)?;
-
-        let backend = CrosstermBackend::new(stdout);
-        let mut terminal = Terminal::new(backend)?;
-
-        let result = self.run_loop(&mut terminal);
-
-        disable_raw_mode()?;
-        execute!(
-            terminal.backend_mut(),
-            LeaveAlternateScreen,
-            DisableMouseCapture
-        )?;
-
-        result
+    /// What the editor is showing besides the buffer, announced when it changes
+    fn accessibility_status(&self) -> Option<String> {
+        if let Some(prompt) = &self.prompt {
+            return Some(prompt.kind.label().to_string());
+        }
+        if let Some(hover) = self.editor.engine.hover() {
+            return hover.lines().next().map(str::to_string);
+        }
+        let panel = if self.diff_view.is_some() {
+            "Diff view"
+        } else if self.history_browser.is_some() {
+            "Local history"
+        } else if self.backlinks.is_some() {
+            "Backlinks"
+        } else if self.bookmarks.is_some() {
+            "Bookmarks"
+        } else if self.note_search_visible {
+            "Search notes"
+        } else if self.tags.is_some() {
+            "Tags"
+        } else if self.todos.is_some() {
+            "TODOs"
+        } else if self.errors_visible {
+            "Errors"
+        } else if self.symbol_palette.is_some() {
+            "Go to symbol"
+        } else if self.template_picker.is_some() {
+            "New note from template"
+        } else if self.char_picker.is_some() {
+            "Insert character"
+        } else if self.quick_switch.is_some() {
+            "Recent files"
+        } else if self.tree_focused && self.tree_visible {
+            "File tree"
+        } else if self.outline_focused && self.outline.is_some() {
+            "Outline"
+        } else if self.search_visible {
+            "Find in files"
+        } else {
+            return None;
+        };
+        Some(panel.to_string())
     }
 
-    fn run_loop(
-        &mut self,
-        terminal: &mut Terminal<CrosstermBackend<std::io::Stdout>>,
-    ) -> Result<()> {
-        loop {
-            // Check for file changes before rendering
-            if self.check_and_reload() {
-                // File was reloaded
-            }
-
-            // Update terminal size for coordinate translation
-            self.terminal_size = terminal.size()?;
-
-            // Ensure cursor is visible before rendering
-            let visible_height = self.terminal_size.height.saturating_sub(2);
-            self.ensure_cursor_visible(visible_height);
-
-            terminal.draw(|frame| self.render(frame))?;
-
-            // Poll for events with timeout to check file changes periodically
-            if poll(Duration::from_millis(100))? {
-                match event::read()? {
-                    Event::Key(key) => {
-                        if let Some(action) = self.translate_key_event(key) {
-                            if matches!(action, EditorAction::Quit) {
-                                // Save before quitting
-                                let _ = self.engine.save_to_file(&self.file_path);
-                                break;
-                            }
-                            self.engine.handle_action(action);
-
-                            // Auto-save after each action
-                            if self.engine.save_to_file(&self.file_path).is_ok() {
-                                // Update last modified time after we save
-                                if let Ok(metadata) = std::fs::metadata(&self.file_path) {
-                                    if let Ok(modified) = metadata.modified() {
-                                        self.last_modified = Some(modified);
-                                    }
-                                }
-                            }
-                        }
-                    }
-                    Event::Mouse(mouse) => {
-                        match mouse.kind {
-                            MouseEventKind::ScrollUp => {
-                                self.handle_scroll(-1);
-                            }
-                            MouseEventKind::ScrollDown => {
-                                self.handle_scroll(1);
-                            }
-                            _ => {
-                                if let Some(action) = self.translate_mouse_event(mouse) {
-                                    self.engine.handle_action(action);
-
-                                    // Ensure cursor visibility after mouse action
-                                    let visible_height =
-                                        self.terminal_size.height.saturating_sub(2);
-                                    self.ensure_cursor_visible(visible_height);
-
-                                    // Auto-save after mouse actions
-                                    if self.engine.save_to_file(&self.file_path).is_ok() {
-                                        if let Ok(metadata) = std::fs::metadata(&self.file_path) {
-                                            if let Ok(modified) = metadata.modified() {
-                                                self.last_modified = Some(modified);
-                                            }
-                                        }
-                                    }
-                                }
-                            }
-                        }
-                    }
-                    _ => {}
-                }
+    /// The file and the symbols the cursor is in, left-aligned in the bottom
+    /// padding row below the text
+    fn render_breadcrumbs(&self, frame: &mut ratatui::Frame, text_area: Rect) {
+        let trail = self.breadcrumbs();
+        let Some(last) = trail.len().checked_sub(1) else {
+            return;
+        };
+        let mut spans = Vec::new();
+        for (index, (name, _)) in trail.into_iter().enumerate() {
+            if index > 0 {
+                spans.push(Span::styled(
+                    BREADCRUMB_SEPARATOR,
+                    Style::default().fg(Color::DarkGray),
+                ));
             }
+            let color = if index == last {
+                Color::Gray
+            } else {
+                Color::DarkGray
+            };
+            spans.push(Span::styled(name, Style::default().fg(color)));
         }
-        Ok(())
+        let area = frame.area();
+        let breadcrumb_area = Rect {
+            x: text_area.x,
+            y: area.bottom().saturating_sub(1),
+            width: text_area.width,
+            height: 1.min(area.height),
+        };
+        frame.render_widget(Paragraph::new(Line::from(spans)), breadcrumb_area);
     }
 
-    fn translate_key_event(&self, event: KeyEvent) -> Option<EditorAction> {
-        // Debug: Uncomment to see what keys terminal sends (redirects to stderr)
-        // eprintln!("Key: {:?}, Mods: {:?}", event.code, event.modifiers);
-
-        let action = match (event.code, event.modifiers) {
-            // Ctrl+W to quit
-            (KeyCode::Char('w'), KeyModifiers::CONTROL) => Some(EditorAction::Quit),
-
-            // Undo/Redo
-            (KeyCode::Char('z'), mods)
-                if mods.contains(KeyModifiers::CONTROL | KeyModifiers::SHIFT) =>
-            {
-                Some(EditorAction::Redo)
-            }
-            (KeyCode::Char('z'), KeyModifiers::CONTROL) => Some(EditorAction::Undo),
+    /// Writing session progress, right-aligned in the bottom padding row
+    fn render_status(&self, frame: &mut ratatui::Frame) {
+        let transfer = self
+            .file_progress
+            .clone()
+            .or_else(|| Some(self.editor.engine.transfer_status()?.label()));
+        let session = self.editor.engine.writing_session();
+        let announcement = self.announcer.latest();
+        let search = self.editor.engine.search_status();
+        let recording = self.editor.engine.dictating();
+        if transfer.is_none()
+            && session.is_none()
+            && announcement.is_none()
+            && search.is_none()
+            && !recording
+        {
+            return;
+        }
 
-            // Line operations
-            (KeyCode::Char('k'), mods)
-                if mods.contains(KeyModifiers::CONTROL | KeyModifiers::SHIFT) =>
-            {
-                Some(EditorAction::DeleteLine)
+        let mut spans = Vec::new();
+        if recording {
+            spans.push(Span::styled("● Dictating", Style::default().fg(Color::Red)));
+        }
+        if let Some(announcement) = announcement {
+            if !spans.is_empty() {
+                spans.push(Span::raw("  "));
             }
-
-            // Delete operations
-            (KeyCode::Backspace, KeyModifiers::SUPER) => Some(EditorAction::DeleteLine),
-            (KeyCode::Backspace, KeyModifiers::CONTROL) => {
-                Some(EditorAction::DeleteToBeginningOfLine)
+            spans.push(Span::raw(announcement.to_string()));
+        }
+        if let Some(search) = search {
+            if !spans.is_empty() {
+                spans.push(Span::raw("  "));
             }
-            (KeyCode::Backspace, KeyModifiers::ALT) => Some(EditorAction::DeleteWordLeft),
-            (KeyCode::Delete, KeyModifiers::SUPER) => Some(EditorAction::DeleteToEndOfLine),
-            (KeyCode::Delete, KeyModifiers::CONTROL) => Some(EditorAction::DeleteToEndOfLine),
-            (KeyCode::Delete, KeyModifiers::ALT) => Some(EditorAction::DeleteWordRight),
-
-            // Terminal-intercepted Cmd+Backspace fallback (terminal sends Ctrl+U)
-            (KeyCode::Char('u'), KeyModifiers::CONTROL) => {
-                Some(EditorAction::DeleteToBeginningOfLine)
+            spans.push(Span::styled(search, Style::default().fg(Color::Gray)));
+        }
+        if let Some(transfer) = transfer {
+            if !spans.is_empty() {
+                spans.push(Span::raw("  "));
             }
-
-            // Font size (will be ignored in TUI but kept for consistency)
-            (KeyCode::Char('='), KeyModifiers::CONTROL) => Some(EditorAction::IncreaseFontSize),
-            (KeyCode::Char('-'), KeyModifiers::CONTROL) => Some(EditorAction::DecreaseFontSize),
-
-            // Terminal-intercepted Cmd+arrow fallbacks (terminal sends Ctrl+A/E for Cmd+Left/Right)
-            (KeyCode::Char('a'), KeyModifiers::CONTROL) => {
-                Some(EditorAction::MoveToBeginningOfLine)
+            spans.push(Span::styled(transfer, Style::default().fg(Color::Yellow)));
+        }
+        if let Some(session) = session {
+            if !spans.is_empty() {
+                spans.push(Span::raw("  "));
             }
-            (KeyCode::Char('e'), KeyModifiers::CONTROL) => Some(EditorAction::MoveToEndOfLine),
+            self.push_session_status(session, &mut spans);
+        }
 
-            // Tab/Outdent
-            (KeyCode::Tab, KeyModifiers::SHIFT) => Some(EditorAction::Outdent),
-            (KeyCode::Tab, KeyModifiers::NONE) => Some(EditorAction::Tab),
+        let area = frame.area();
+        let width = spans
+            .iter()
+            .map(|s| s.content.chars().count())
+            .sum::<usize>() as u16;
+        let status_area = Rect {
+            x: area.right().saturating_sub(width + 2).max(area.x),
+            y: area.bottom().saturating_sub(1),
+            width: width.min(area.width),
+            height: 1.min(area.height),
+        };
+        frame.render_widget(Paragraph::new(Line::from(spans)), status_area);
+    }
 
-            // Cmd+Left/Right for line start/end (Mac)
-            (KeyCode::Left, KeyModifiers::SUPER) => Some(EditorAction::MoveToBeginningOfLine),
-            (KeyCode::Right, KeyModifiers::SUPER) => Some(EditorAction::MoveToEndOfLine),
+    /// The writing session's progress bar and word count
+    fn push_session_status(&self, session: &WritingSession, spans: &mut Vec<Span<'static>>) {
+        let words = self.editor.engine.word_count();
+        if let Some(progress) = session.progress(words) {
+            const BAR_WIDTH: usize = 10;
+            let filled = (progress * BAR_WIDTH as f32).round() as usize;
+            let color = if session.is_complete(words) {
+                Color::Green
+            } else {
+                Color::Cyan
+            };
+            spans.push(Span::styled("█".repeat(filled), Style::default().fg(color)));
+            spans.push(Span::styled(
+                "░".repeat(BAR_WIDTH - filled),
+                Style::default().fg(Color::DarkGray),
+            ));
+            spans.push(Span::raw(" "));
+        }
+        spans.push(Span::styled(
+            session.status(words),
+            Style::default().fg(Color::Gray),
+        ));
+    }
 
-            // Alt+Left/Right for word jumping (check before shift combinations)
-            (KeyCode::Left, mods) if mods == KeyModifiers::ALT => Some(EditorAction::MoveWordLeft),
-            (KeyCode::Right, mods) if mods == KeyModifiers::ALT => {
-                Some(EditorAction::MoveWordRight)
-            }
+    /// Shell screen in the terminal panel below the diagnostics panel, with
+    /// its cursor while the panel has focus
+    fn render_terminal_panel(&mut self, frame: &mut ratatui::Frame, text_area: Rect) {
+        let height = self.terminal_panel_height();
+        if height == 0 {
+            return;
+        }
 
-            // Shift+Alt for word selection
-            (KeyCode::Left, mods)
-                if mods.contains(KeyModifiers::SHIFT) && mods.contains(KeyModifiers::ALT) =>
-            {
-                Some(EditorAction::SelectWordLeft)
-            }
-            (KeyCode::Right, mods)
-                if mods.contains(KeyModifiers::SHIFT) && mods.contains(KeyModifiers::ALT) =>
-            {
-                Some(EditorAction::SelectWordRight)
+        let area = frame.area();
+        let top = text_area.bottom() + self.diagnostics_panel_height();
+        let panel = Rect {
+            x: text_area.x,
+            y: top,
+            width: text_area.width,
+            height: height.min(area.bottom().saturating_sub(top)),
+        };
+        let title = match self.terminal_panel.as_ref().and_then(|t| t.exit_code()) {
+            Some(code) => format!(" Terminal (exited with {}) ", code),
+            None => " Terminal ".to_string(),
+        };
+        let border = if self.terminal_focused {
+            Color::Cyan
+        } else {
+            Color::DarkGray
+        };
+        let block = Block::default()
+            .borders(Borders::TOP)
+            .border_style(Style::default().fg(border))
+            .title(title);
+        let inner = block.inner(panel);
+        frame.render_widget(Clear, panel);
+        frame.render_widget(block, panel);
+
+        let Some(shell) = self.terminal_panel.as_mut() else {
+            return;
+        };
+        shell.resize(inner.height, inner.width);
+        let lines: Vec<Line> = shell.lines().into_iter().map(Line::from).collect();
+        frame.render_widget(
+            Paragraph::new(lines).style(Style::default().fg(Color::White)),
+            inner,
+        );
+        if self.terminal_focused {
+            if let Some((row, column)) = shell.cursor() {
+                frame.set_cursor_position((inner.x + column, inner.y + row));
             }
+        }
+    }
 
-            // Alt+Up/Down for moving lines
-            (KeyCode::Up, mods) if mods == KeyModifiers::ALT => Some(EditorAction::MoveLineUp),
-            (KeyCode::Down, mods) if mods == KeyModifiers::ALT => Some(EditorAction::MoveLineDown),
-
-            // Selection with Shift (before regular movement)
-            (KeyCode::Left, KeyModifiers::SHIFT) => Some(EditorAction::SelectLeft),
-            (KeyCode::Right, KeyModifiers::SHIFT) => Some(EditorAction::SelectRight),
-            (KeyCode::Up, KeyModifiers::SHIFT) => Some(EditorAction::SelectUp),
-            (KeyCode::Down, KeyModifiers::SHIFT) => Some(EditorAction::SelectDown),
+    /// List of all diagnostics below the text, highlighting the one under the cursor
+    fn render_diagnostics_panel(&self, frame: &mut ratatui::Frame, text_area: Rect) {
+        let height = self.diagnostics_panel_height();
+        if height == 0 {
+            return;
+        }
 
-            // Cursor movement (after modifier versions)
-            (KeyCode::Left, KeyModifiers::NONE) => Some(EditorAction::MoveLeft),
-            (KeyCode::Right, KeyModifiers::NONE) => Some(EditorAction::MoveRight),
-            (KeyCode::Up, KeyModifiers::NONE) => Some(EditorAction::MoveUp),
-            (KeyCode::Down, KeyModifiers::NONE) => Some(EditorAction::MoveDown),
-            (KeyCode::Home, _) => Some(EditorAction::MoveToBeginningOfLine),
-            (KeyCode::End, _) => Some(EditorAction::MoveToEndOfLine),
+        let area = frame.area();
+        let panel = Rect {
+            x: text_area.x,
+            y: text_area.bottom(),
+            width: text_area.width,
+            height: height.min(area.bottom().saturating_sub(text_area.bottom())),
+        };
 
-            // Text editing
-            (KeyCode::Backspace, _) => Some(EditorAction::Backspace),
-            (KeyCode::Delete, _) => Some(EditorAction::Delete),
-            (KeyCode::Enter, _) => Some(EditorAction::Newline),
+        let diagnostics = self.editor.engine.diagnostics();
+        let items: Vec<ListItem> = if diagnostics.is_empty() {
+            vec![ListItem::new("No problems")]
+        } else {
+            diagnostics
+                .iter()
+                .map(|d| {
+                    ListItem::new(Line::from(vec![
+                        Span::styled(
+                            format!("{:<8}", d.severity.label()),
+                            Style::default().fg(severity_color(d.severity)),
+                        ),
+                        Span::raw(format!(
+                            "{}:{}  {}",
+                            d.start.row + 1,
+                            d.start.column + 1,
+                            d.message.lines().next().unwrap_or_default()
+                        )),
+                    ]))
+                })
+                .collect()
+        };
 
-            // Terminal-intercepted Alt+arrow fallbacks (when terminal sends Alt+b/f instead of Alt+arrows)
-            (KeyCode::Char('b'), KeyModifiers::ALT) => Some(EditorAction::MoveWordLeft),
-            (KeyCode::Char('f'), KeyModifiers::ALT) => Some(EditorAction::MoveWordRight),
+        let cursor = self.editor.engine.state().cursor;
+        let selected = diagnostics.iter().position(|d| d.contains(cursor));
+        let list = List::new(items)
+            .block(
+                Block::default()
+                    .borders(Borders::TOP)
+                    .title(" Diagnostics "),
+            )
+            .style(Style::default().fg(Color::White))
+            .highlight_style(Style::default().bg(Color::DarkGray));
+        let mut list_state = ListState::default().with_selected(selected);
 
-            // Regular character input
-            (KeyCode::Char(c), KeyModifiers::NONE) | (KeyCode::Char(c), KeyModifiers::SHIFT) => {
-                Some(EditorAction::TypeCharacter(c))
-            }
+        frame.render_stateful_widget(list, panel, &mut list_state);
+    }
 
-            _ => None,
+    /// Draw hover documentation below the cursor line, or above it when there is no room
+    fn render_hover(&self, frame: &mut ratatui::Frame, text_area: Rect) {
+        let Some(text) = self.editor.engine.hover() else {
+            return;
+        };
+        let state = self.editor.engine.state();
+        let Some(screen_row) = (state.cursor.row as u16).checked_sub(self.editor.scroll_offset)
+        else {
+            return;
         };
 
-        // eprintln!("Action: {:?}", action);
-        action
-    }
+        let area = frame.area();
+        let width = text
+            .lines()
+            .map(|line| line.chars().count())
+            .max()
+            .unwrap_or(0)
+            .min(60) as u16
+            + 2;
+        let height = (text.lines().count() as u16 + 2).min(12);
+
+        let cursor_y = text_area.y + screen_row;
+        let y = if cursor_y + 1 + height <= area.bottom() {
+            cursor_y + 1
+        } else {
+            cursor_y.saturating_sub(height)
+        };
+        let popup = Rect {
+            x: text_area.x.min(area.right().saturating_sub(width)),
+            y,
+            width: width.min(area.width),
+            height: height.min(area.height),
+        };
 
-    fn translate_mouse_event(&self, event: MouseEvent) -> Option<EditorAction> {
-        // Debug: Uncomment to see mouse events (redirects to stderr)
-        // eprintln!("Mouse: kind={:?}, col={}, row={}", event.kind, event.column, event.row);
+        let hover = Paragraph::new(text)
+            .block(Block::default().borders(Borders::ALL))
+            .style(Style::default().fg(Color::White).bg(Color::Black))
+            .wrap(Wrap { trim: false });
 
-        match event.kind {
-            MouseEventKind::Down(MouseButton::Left) => {
-                if let Some((row, col)) = self.screen_to_document(event.column, event.row) {
-                    let (row, col) = self.clamp_to_document(row, col);
-                    // Use SetCursorPosition (not StartSelection) so cursor renders properly
-                    // Selection will start on drag via ExtendSelection
-                    Some(EditorAction::SetCursorPosition { row, column: col })
-                } else {
-                    None
-                }
-            }
-            MouseEventKind::Drag(MouseButton::Left) => {
-                if let Some((row, col)) = self.screen_to_document(event.column, event.row) {
-                    let (row, col) = self.clamp_to_document(row, col);
-                    Some(EditorAction::ExtendSelection { row, column: col })
-                } else {
-                    None
-                }
-            }
-            _ => None,
-        }
+        frame.render_widget(Clear, popup);
+        frame.render_widget(hover, popup);
     }
 
-    fn handle_scroll(&mut self, direction: i16) {
-        const SCROLL_LINES: u16 = 3;
-        if direction < 0 {
-            // Scroll up
-            self.scroll_offset = self.scroll_offset.saturating_sub(SCROLL_LINES);
+    /// Draw the completion list below the cursor, or above it when there is no room
+    fn render_completion(&self, frame: &mut ratatui::Frame, text_area: Rect) {
+        let Some(completion) = self.editor.engine.completion() else {
+            return;
+        };
+        let state = self.editor.engine.state();
+        let Some(screen_row) = (completion.start.row as u16).checked_sub(self.editor.scroll_offset)
+        else {
+            return;
+        };
+
+        let start_col = state.lines[completion.start.row][..completion.start.column]
+            .chars()
+            .count() as u16;
+        let width = completion
+            .items
+            .iter()
+            .map(|item| item.chars().count())
+            .max()
+            .unwrap_or(0) as u16
+            + 4;
+        let height = completion.items.len() as u16 + 2;
+
+        let area = frame.area();
+        let cursor_y = text_area.y + screen_row;
+        let y = if cursor_y + 1 + height <= area.bottom() {
+            cursor_y + 1
         } else {
-            // Scroll down
-            let max_scroll = self.engine.state().lines.len().saturating_sub(1) as u16;
-            self.scroll_offset = (self.scroll_offset + SCROLL_LINES).min(max_scroll);
-        }
-    }
+            cursor_y.saturating_sub(height)
+        };
+        let x = (text_area.x + start_col)
+            .saturating_sub(1)
+            .min(area.right().saturating_sub(width));
+        let popup = Rect {
+            x,
+            y,
+            width: width.min(area.width),
+            height: height.min(area.height),
+        };
 
-    fn render(&self, frame: &mut ratatui::Frame) {
-        let state = self.engine.state();
+        let items: Vec<ListItem> = completion
+            .items
+            .iter()
+            .map(|item| ListItem::new(item.as_str()))
+            .collect();
+        let list = List::new(items)
+            .block(Block::default().borders(Borders::ALL))
+            .style(Style::default().fg(Color::White).bg(Color::Black))
+            .highlight_style(Style::default().fg(Color::Black).bg(Color::Cyan));
+        let mut list_state = ListState::default().with_selected(Some(completion.selected));
+
+        frame.render_widget(Clear, popup);
+        frame.render_stateful_widget(list, popup, &mut list_state);
+    }
+}
 
-        // Selection highlighting style
-        let selection_style = Style::default().bg(Color::DarkGray);
-        let cursor_style = Style::default().add_modifier(Modifier::REVERSED);
+fn severity_color(severity: Severity) -> Color {
+    match severity {
+        Severity::Error => Color::Red,
+        Severity::Warning => Color::Yellow,
+        Severity::Information => Color::Blue,
+        Severity::Hint => Color::DarkGray,
+    }
+}
 
-        // Build styled lines with cursor and selection highlighting
-        let mut display_lines = Vec::new();
+/// A file's name for messages, or its whole path if it has none
+fn display_name(path: &std::path::Path) -> String {
+    path.file_name().map_or_else(
+        || path.display().to_string(),
+        |name| name.to_string_lossy().into_owned(),
+    )
+}
 
-        for (row_idx, line) in state.lines.iter().enumerate() {
-            let mut spans = Vec::new();
+/// Status bar text for a background load or save, as in `Saving notes.md 40%`
+fn progress_label(verb: &str, path: &std::path::Path, done: u64, total: u64) -> String {
+    let percent = (done * 100).checked_div(total).unwrap_or(100);
+    format!("{} {} {}%", verb, display_name(path), percent)
+}
 
-            if let Some(anchor) = state.selection_anchor {
-                // Calculate selection range
-                let (sel_start_row, sel_start_col, sel_end_row, sel_end_col) = if anchor.row
-                    < state.cursor.row
-                    || (anchor.row == state.cursor.row && anchor.column < state.cursor.column)
-                {
-                    (
-                        anchor.row,
-                        anchor.column,
-                        state.cursor.row,
-                        state.cursor.column,
-                    )
-                } else {
-                    (
-                        state.cursor.row,
-                        state.cursor.column,
-                        anchor.row,
-                        anchor.column,
-                    )
-                };
+/// Read terminal events on a thread of their own, so the loop can sleep
+/// until one arrives or something else wakes it
+fn spawn_input_reader(wake: Sender<Wake>) {
+    std::thread::spawn(move || loop {
+        let event = event::read();
+        let failed = event.is_err();
+        if wake.send(Wake::Input(event)).is_err() || failed {
+            break;
+        }
+    });
+}
 
-                if row_idx == state.cursor.row && row_idx >= sel_start_row && row_idx <= sel_end_row
-                {
-                    // Line with cursor and possibly selection
-                    let (sel_from, sel_to) = if row_idx == sel_start_row && row_idx == sel_end_row {
-                        (sel_start_col, sel_end_col)
-                    } else if row_idx == sel_start_row {
-                        (sel_start_col, line.len())
-                    } else if row_idx == sel_end_row {
-                        (0, sel_end_col)
-                    } else {
-                        (0, line.len())
-                    };
+/// Watch the directory of `path`, which survives the file being replaced
+/// by a save elsewhere, waking the loop when the file changes; `None` where
+/// it can't be watched, such as on a remote host
+fn watch_file(path: &std::path::Path, wake: Sender<Wake>) -> Option<RecommendedWatcher> {
+    if vfs::is_remote(path) {
+        return None;
+    }
+    let name = path.file_name()?.to_owned();
+    let dir = match path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir,
+        _ => std::path::Path::new("."),
+    };
+    let mut watcher = notify::recommended_watcher(move |event: notify::Result<notify::Event>| {
+        let concerns_file = event.is_ok_and(|event| {
+            event
+                .paths
+                .iter()
+                .any(|path| path.file_name() == Some(name.as_os_str()))
+        });
+        if concerns_file {
+            let _ = wake.send(Wake::FileChanged);
+        }
+    })
+    .ok()?;
+    watcher.watch(dir, RecursiveMode::NonRecursive).ok()?;
+    Some(watcher)
+}
 
-                    // Before selection
-                    if sel_from > 0 {
-                        spans.push(Span::raw(&line[..sel_from]));
-                    }
+/// The terminal panel's name for a key, or None for keys it ignores
+fn terminal_key(code: KeyCode) -> Option<TerminalKey> {
+    Some(match code {
+        KeyCode::Char(c) => TerminalKey::Char(c),
+        KeyCode::Enter => TerminalKey::Enter,
+        KeyCode::Backspace => TerminalKey::Backspace,
+        KeyCode::Tab => TerminalKey::Tab,
+        KeyCode::BackTab => TerminalKey::BackTab,
+        KeyCode::Esc => TerminalKey::Escape,
+        KeyCode::Up => TerminalKey::Up,
+        KeyCode::Down => TerminalKey::Down,
+        KeyCode::Left => TerminalKey::Left,
+        KeyCode::Right => TerminalKey::Right,
+        KeyCode::Home => TerminalKey::Home,
+        KeyCode::End => TerminalKey::End,
+        KeyCode::PageUp => TerminalKey::PageUp,
+        KeyCode::PageDown => TerminalKey::PageDown,
+        KeyCode::Insert => TerminalKey::Insert,
+        KeyCode::Delete => TerminalKey::Delete,
+        KeyCode::F(n) => TerminalKey::F(n),
+        _ => return None,
+    })
+}
 
-                    // Selected text
-                    if sel_to > sel_from {
-                        let sel_text = &line[sel_from..sel_to.min(line.len())];
-                        spans.push(Span::styled(sel_text, selection_style));
-                    }
+/// Open `url` with the system's default handler, without waiting for it
+fn open_url(url: &str) -> std::io::Result<()> {
+    let mut command = if cfg!(target_os = "macos") {
+        std::process::Command::new("open")
+    } else if cfg!(windows) {
+        let mut command = std::process::Command::new("cmd");
+        command.args(["/c", "start", ""]);
+        command
+    } else {
+        std::process::Command::new("xdg-open")
+    };
+    let mut child = command
+        .arg(url)
+        .stdin(std::process::Stdio::null())
+        .stdout(std::process::Stdio::null())
+        .stderr(std::process::Stdio::null())
+        .spawn()?;
+    std::thread::spawn(move || child.wait());
+    Ok(())
+}
 
-                    // After selection
-                    if sel_to < line.len() {
-                        spans.push(Span::raw(&line[sel_to..]));
-                    }
+/// The system clipboard's text, for templates' `{{clipboard}}`, or nothing
+/// if no clipboard tool is available
+fn system_clipboard() -> String {
+    let commands: &[&[&str]] = if cfg!(target_os = "macos") {
+        &[&["pbpaste"]]
+    } else if cfg!(windows) {
+        &[&["powershell", "-NoProfile", "-Command", "Get-Clipboard"]]
+    } else {
+        &[
+            &["wl-paste", "--no-newline"],
+            &["xclip", "-selection", "clipboard", "-o"],
+            &["xsel", "--clipboard", "--output"],
+        ]
+    };
+    commands
+        .iter()
+        .find_map(|command| {
+            let output = std::process::Command::new(command[0])
+                .args(&command[1..])
+                .stdin(std::process::Stdio::null())
+                .stderr(std::process::Stdio::null())
+                .output()
+                .ok()?;
+            output
+                .status
+                .success()
+                .then(|| String::from_utf8_lossy(&output.stdout).into_owned())
+        })
+        .unwrap_or_default()
+}
 
-                    // Cursor
-                    let cursor_col = state.cursor.column;
-                    if cursor_col >= line.len() {
-                        spans.push(Span::styled(" ", cursor_style));
-                    }
-                } else if row_idx >= sel_start_row && row_idx <= sel_end_row {
-                    // Line within selection range but not cursor line
-                    let (sel_from, sel_to) = if row_idx == sel_start_row {
-                        (sel_start_col, line.len())
-                    } else if row_idx == sel_end_row {
-                        (0, sel_end_col)
-                    } else {
-                        (0, line.len())
-                    };
+/// Ask on the terminal for the passphrase of the file about to be opened,
+/// or use the one in the OS keychain if configured; a file not created yet
+/// gets a new passphrase, typed twice
+fn unlock(engine: &mut EditorEngine, path: &std::path::Path) -> std::io::Result<()> {
+    if engine.unlock_from_keychain(path) {
+        return Ok(());
+    }
 
-                    if sel_from > 0 {
-                        spans.push(Span::raw(&line[..sel_from]));
-                    }
-                    if sel_to > sel_from {
-                        spans.push(Span::styled(
-                            &line[sel_from..sel_to.min(line.len())],
-                            selection_style,
-                        ));
-                    }
-                    if sel_to < line.len() {
-                        spans.push(Span::raw(&line[sel_to..]));
-                    }
-                } else if row_idx == state.cursor.row {
-                    // Cursor line without selection
-                    self.render_cursor_line(line, state.cursor.column, &mut spans, cursor_style);
-                } else {
-                    // Regular line
-                    spans.push(Span::raw(line.as_str()));
-                }
-            } else if row_idx == state.cursor.row {
-                // No selection, just cursor
-                self.render_cursor_line(line, state.cursor.column, &mut spans, cursor_style);
-            } else {
-                // Regular line
-                spans.push(Span::raw(line.as_str()));
+    let passphrase = if path.exists() {
+        let mut attempts = 0;
+        loop {
+            let passphrase =
+                rpassword::prompt_password(format!("Passphrase for {}: ", path.display()))?;
+            engine.set_passphrase(&passphrase)?;
+            match engine.load_from_file(path) {
+                Ok(()) => break passphrase,
+                Err(e) if attempts == 2 => return Err(e),
+                Err(e) => eprintln!("{}", e),
             }
-
-            display_lines.push(Line::from(spans));
+            attempts += 1;
         }
+    } else {
+        let passphrase =
+            rpassword::prompt_password(format!("New passphrase for {}: ", path.display()))?;
+        if rpassword::prompt_password("Repeat passphrase: ")? != passphrase {
+            return Err(std::io::Error::other("passphrases do not match"));
+        }
+        engine.set_passphrase(&passphrase)?;
+        passphrase
+    };
+    if engine.config().encryption.keychain {
+        let _ = encryption::store_in_keychain(path, &passphrase);
+    }
+    Ok(())
+}
 
-        let paragraph = Paragraph::new(display_lines)
-            .style(Style::default().fg(Color::White))
-            .scroll((self.scroll_offset, 0));
-
-        // Create a rect with padding on all sides
-        let area = frame.size();
-        let padded_area = Rect {
-            x: area.x + 2,
-            y: area.y + 1,
-            width: area.width.saturating_sub(4),
-            height: area.height.saturating_sub(2),
-        };
-
-        frame.render_widget(paragraph, padded_area);
+/// Path typed at the prompt: `~` is the home directory, relative paths start
+/// from the working directory
+fn expand_path(input: &str) -> std::path::PathBuf {
+    if vfs::is_remote(std::path::Path::new(input)) {
+        return std::path::PathBuf::from(input);
+    }
+    let path = match input.strip_prefix('~') {
+        Some(rest) if rest.is_empty() || rest.starts_with(std::path::is_separator) => {
+            let home = std::env::var("HOME")
+                .or_else(|_| std::env::var("USERPROFILE"))
+                .unwrap_or_default();
+            std::path::PathBuf::from(format!("{}{}", home, rest))
+        }
+        _ => std::path::PathBuf::from(input),
+    };
+    if path.is_absolute() {
+        path
+    } else {
+        std::env::current_dir().unwrap_or_default().join(path)
     }
 }
 
@@ -540,7 +3899,7 @@ fn resolve_file_path() -> std::path::PathBuf {
 
     if let Some(path_str) = file_arg {
         let path = std::path::PathBuf::from(path_str);
-        if path.is_absolute() {
+        if path.is_absolute() || vfs::is_remote(&path) {
             path
         } else {
             std::env::current_dir().unwrap_or_default().join(path)
@@ -551,9 +3910,128 @@ fn resolve_file_path() -> std::path::PathBuf {
     }
 }
 
+/// Where the UI is drawn: stdout, unless it is redirected to capture the
+/// edited text, in which case the terminal itself
+fn terminal_output() -> std::io::Result<Box<dyn Write>> {
+    if std::io::stdout().is_terminal() {
+        return Ok(Box::new(std::io::stdout()));
+    }
+    let tty = std::fs::OpenOptions::new().write(true).open("/dev/tty")?;
+    Ok(Box::new(tty))
+}
+
+/// `zlyph -`: edit text piped to stdin and print the result to stdout on
+/// quit. While the editor runs, the text is autosaved to a temp file.
+fn edit_stdin() -> Result<()> {
+    let mut content = String::new();
+    std::io::stdin().read_to_string(&mut content)?;
+    let path = std::env::temp_dir().join(format!("zlyph-stdin-{}.txt", std::process::id()));
+    std::fs::write(&path, &content)?;
+
+    let mut editor = TuiEditor::new(path.clone(), true);
+    let result = editor.run();
+    // Read back from the file, as the editor may have switched to another
+    let edited = std::fs::read_to_string(&path);
+    let _ = std::fs::remove_file(&path);
+    result?;
+
+    let lines: Vec<String> = edited?.lines().map(String::from).collect();
+    let mut stdout = std::io::stdout();
+    stdout.write_all(batch::join_like(&lines, &content).as_bytes())?;
+    stdout.flush()?;
+    Ok(())
+}
+
+/// `zlyph --serve [--socket PATH]`: edit headlessly over JSON-RPC on stdio,
+/// or on a Unix socket taking one client at a time
+fn serve(args: &[String]) -> Result<()> {
+    let mut server = RpcServer::new();
+    match args {
+        [] => {
+            let stdin = std::io::stdin();
+            server.serve(stdin.lock(), std::io::stdout())?;
+        }
+        #[cfg(unix)]
+        [flag, path] if flag == "--socket" => {
+            let path = std::path::Path::new(path);
+            let _ = std::fs::remove_file(path);
+            let listener = std::os::unix::net::UnixListener::bind(path)?;
+            for stream in listener.incoming() {
+                let stream = stream?;
+                // Buffers stay open for the next client
+                let _ = server.serve(std::io::BufReader::new(&stream), &stream);
+            }
+        }
+        _ => anyhow::bail!("usage: zlyph --serve [--socket PATH]"),
+    }
+    Ok(())
+}
+
+/// `zlyph apply [--script FILE]... [--ex COMMAND]... [--dry-run] FILE...`:
+/// edit files without a UI. Steps run in the order given; `-` reads stdin
+/// and writes stdout, and `--dry-run` prints results instead of writing.
+fn apply(args: &[String]) -> Result<()> {
+    const USAGE: &str =
+        "usage: zlyph apply [--script FILE]... [--ex COMMAND]... [--dry-run] FILE...";
+    let mut steps = Vec::new();
+    let mut files = Vec::new();
+    let mut dry_run = false;
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--script" => {
+                let path = args.next().ok_or_else(|| anyhow::anyhow!(USAGE))?;
+                let script = std::fs::read_to_string(path)
+                    .map_err(|e| anyhow::anyhow!("{}: {}", path, e))?;
+                steps.extend(
+                    batch::parse_script(&script).map_err(|e| anyhow::anyhow!("{}: {}", path, e))?,
+                );
+            }
+            "--ex" => {
+                let command = args.next().ok_or_else(|| anyhow::anyhow!(USAGE))?;
+                steps.push(batch::Step::Ex {
+                    ex: command.clone(),
+                });
+            }
+            "--dry-run" => dry_run = true,
+            _ => files.push(arg.as_str()),
+        }
+    }
+    if files.is_empty() {
+        anyhow::bail!(USAGE);
+    }
+
+    for file in files {
+        if file == "-" {
+            let mut content = String::new();
+            std::io::stdin().read_to_string(&mut content)?;
+            print!("{}", batch::apply_to_text(&content, &steps)?);
+            continue;
+        }
+        let content =
+            std::fs::read_to_string(file).map_err(|e| anyhow::anyhow!("{}: {}", file, e))?;
+        let edited = batch::apply_to_text(&content, &steps)
+            .map_err(|e| anyhow::anyhow!("{}: {}", file, e))?;
+        if dry_run {
+            print!("{}", edited);
+        } else if edited != content {
+            std::fs::write(file, edited)?;
+        }
+    }
+    Ok(())
+}
+
 fn main() -> Result<()> {
     let args: Vec<String> = std::env::args().collect();
 
+    if args.len() > 1 && args[1] == "apply" {
+        return apply(&args[2..]);
+    }
+
+    if args.len() > 1 && args[1] == "--serve" {
+        return serve(&args[2..]);
+    }
+
     // Check for "gui" subcommand
     if args.len() > 1 && args[1] == "gui" {
         // Launch the GUI version with remaining args
@@ -566,13 +4044,17 @@ fn main() -> Result<()> {
             Ok(s) => std::process::exit(s.code().unwrap_or(1)),
             Err(e) => {
                 eprintln!("Failed to launch zlyph-gui: {}", e);
-                eprintln!("Make sure zlyph-gui is installed: cargo install --path zlyph-gpui");
+                eprintln!("Make sure zlyph-gui is installed: cargo install --path zlyph-gui");
                 std::process::exit(1);
             }
         }
     }
 
+    if args.len() > 1 && args[1] == "-" {
+        return edit_stdin();
+    }
+
     let file_path = resolve_file_path();
-    let mut editor = TuiEditor::new(file_path);
+    let mut editor = TuiEditor::new(file_path, false);
     editor.run()
 }